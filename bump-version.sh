@@ -49,6 +49,7 @@ fi
 if [ "$DRY_RUN" = "--dry-run" ]; then
   echo "[dry-run] Would update the following files:"
   echo "  - waypoint-core/Cargo.toml (package version)"
+  echo "  - waypoint-macros/Cargo.toml (package version)"
   echo "  - waypoint-cli/Cargo.toml (package version + waypoint-core dependency)"
   echo "  - README.md (install pin version, library dependency version)"
   echo ""
@@ -63,6 +64,13 @@ fi
 echo "Updating waypoint-core/Cargo.toml..."
 sed -i '' -E "0,/^version = \".*\"/s/^version = \".*\"/version = \"$VERSION\"/" waypoint-core/Cargo.toml
 
+# --- Update waypoint-macros/Cargo.toml ---
+echo "Updating waypoint-macros/Cargo.toml..."
+sed -i '' -E "0,/^version = \".*\"/s/^version = \".*\"/version = \"$VERSION\"/" waypoint-macros/Cargo.toml
+
+# --- Update waypoint-core/Cargo.toml (waypoint-macros dependency) ---
+sed -i '' -E "s/waypoint-macros = \{ version = \"[^\"]*\"/waypoint-macros = { version = \"$VERSION\"/" waypoint-core/Cargo.toml
+
 # --- Update waypoint-cli/Cargo.toml (package version) ---
 echo "Updating waypoint-cli/Cargo.toml..."
 sed -i '' -E "0,/^version = \".*\"/s/^version = \".*\"/version = \"$VERSION\"/" waypoint-cli/Cargo.toml
@@ -95,7 +103,7 @@ grep 'waypoint-core = "' README.md | sed 's/^/  README.md library dep:     /'
 # --- Git commit and tag ---
 echo ""
 echo "Committing and tagging..."
-git add waypoint-core/Cargo.toml waypoint-cli/Cargo.toml Cargo.lock README.md
+git add waypoint-core/Cargo.toml waypoint-cli/Cargo.toml waypoint-macros/Cargo.toml Cargo.lock README.md
 git commit -m "Release v$VERSION"
 git tag -a "v$VERSION" -m "Release v$VERSION"
 
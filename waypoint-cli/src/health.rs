@@ -0,0 +1,49 @@
+//! HTTP health probe for canary rollouts (`migrate --canary --health-url`).
+//!
+//! Built with the `self-update` feature, which brings in the HTTP client.
+
+use std::time::Duration;
+
+use waypoint_core::multi::HealthProbe;
+
+/// Per-request timeout; a probe that hangs counts as failed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Fetches a URL; any 2xx response passes.
+pub struct HttpProbe {
+    url: String,
+}
+
+impl HttpProbe {
+    pub fn new(url: &str) -> Self {
+        HttpProbe {
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl HealthProbe for HttpProbe {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn probe(&self) -> Result<(), String> {
+        let url = self.url.clone();
+        tokio::task::spawn_blocking(move || {
+            let agent = ureq::Agent::new_with_config(
+                ureq::Agent::config_builder()
+                    .timeout_global(Some(PROBE_TIMEOUT))
+                    .build(),
+            );
+            agent
+                .get(&url)
+                .header("User-Agent", "waypoint-canary")
+                .call()
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
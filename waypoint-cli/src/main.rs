@@ -2,18 +2,29 @@
 //! Provides clap-based command routing for 16 subcommands, exit code mapping
 //! based on error type, and multi-database dispatch.
 
+#[cfg(feature = "self-update")]
+mod health;
 mod output;
 #[cfg(feature = "self-update")]
 mod self_update;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "otel")]
+mod telemetry;
+#[cfg(feature = "self-update")]
+mod webhook;
 
+use std::io::{IsTerminal, Write};
 use std::process;
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use colored::Colorize;
 
 use waypoint_core::config::{normalize_location, CliOverrides, WaypointConfig};
 use waypoint_core::error::WaypointError;
-use waypoint_core::migration::MigrationVersion;
+use waypoint_core::migration::{MigrationVersion, VersionScheme};
+use waypoint_core::notify::{LifecycleEvent, Notifier};
+use waypoint_core::redact::RedactField;
 use waypoint_core::{UndoTarget, Waypoint};
 
 /// Print a report as JSON (when `--json` is active) or via a terminal formatter.
@@ -21,20 +32,14 @@ use waypoint_core::{UndoTarget, Waypoint};
 macro_rules! print_report {
     ($report:expr, $json:expr, $printer:path) => {
         if $json {
-            println!(
-                "{}",
-                serde_json::to_string_pretty(&$report).expect("JSON serialization failed")
-            );
+            println!("{}", output::to_json(&$report));
         } else {
             $printer(&$report);
         }
     };
     ($report:expr, $json:expr, $quiet:expr, $printer:path) => {
         if $json {
-            println!(
-                "{}",
-                serde_json::to_string_pretty(&$report).expect("JSON serialization failed")
-            );
+            println!("{}", output::to_json(&$report));
         } else if !$quiet {
             $printer(&$report);
         }
@@ -89,6 +94,26 @@ struct Cli {
     #[arg(long, value_name = "SECS")]
     statement_timeout: Option<u32>,
 
+    /// Per-query timeout for introspection, diff and advisor catalog queries;
+    /// timed-out object categories are reported as incomplete (default: 0 = no limit)
+    #[arg(long, value_name = "SECS", global = true)]
+    introspection_timeout: Option<u32>,
+
+    /// Include objects created by CREATE EXTENSION in drift, diff and clean
+    /// (clean then drops the schema's extensions too)
+    #[arg(long, global = true)]
+    include_extension_objects: bool,
+
+    /// Limit drift, diff, advise, snapshot and clean to objects whose names
+    /// match these globs, comma-separated (e.g. 'app_*')
+    #[arg(long, value_name = "GLOBS", value_delimiter = ',', global = true)]
+    include: Option<Vec<String>>,
+
+    /// Skip objects whose names match these globs in drift, diff, advise,
+    /// snapshot and clean, comma-separated (e.g. 'tmp_*')
+    #[arg(long, value_name = "GLOBS", value_delimiter = ',', global = true)]
+    exclude: Option<Vec<String>>,
+
     /// Allow out-of-order migrations
     #[arg(long, overrides_with = "no_out_of_order")]
     out_of_order: bool,
@@ -125,10 +150,26 @@ struct Cli {
     #[arg(long, value_name = "ENV", global = true)]
     environment: Option<String>,
 
+    /// Placeholder value, repeatable (overrides config, env vars and --placeholders-file)
+    #[arg(long = "placeholder", value_name = "KEY=VALUE", global = true, value_parser = parse_placeholder)]
+    placeholders: Vec<(String, String)>,
+
+    /// Read placeholder values from a .json object or .env file
+    #[arg(long, value_name = "PATH", global = true)]
+    placeholders_file: Option<String>,
+
     /// Enable dependency-based migration ordering
     #[arg(long, global = true)]
     dependency_ordering: bool,
 
+    /// Wait for every other waypoint run on the database, and make them wait
+    #[arg(long, global = true)]
+    global_lock: bool,
+
+    /// Let info, validate and drift run against a read-only standby
+    #[arg(long, global = true)]
+    allow_standby: bool,
+
     /// Skip pre-flight health checks
     #[arg(long, global = true)]
     skip_preflight: bool,
@@ -141,10 +182,24 @@ struct Cli {
     #[arg(long, global = true)]
     fail_fast: bool,
 
+    /// Fast local iteration: no preflight/safety/simulation, clean and
+    /// out-of-order allowed, short timeouts, schema changes shown after migrate
+    #[arg(long, global = true, conflicts_with = "strict")]
+    dev: bool,
+
+    /// Enable all recommended guardrails and forbid --force / out-of-order;
+    /// for `lint`, only exit 1 if any errors are found
+    #[arg(long, global = true)]
+    strict: bool,
+
     /// Override DANGER safety blocks
     #[arg(long, global = true)]
     force: bool,
 
+    /// Run a command that [policy] marks `require_override`
+    #[arg(long, global = true)]
+    override_policy: bool,
+
     /// Run simulation before migrate
     #[arg(long, global = true)]
     simulate: bool,
@@ -161,18 +216,283 @@ struct Cli {
     command: Commands,
 }
 
+/// `metrics` subcommands.
+#[derive(Subcommand)]
+enum MetricsCommand {
+    /// Cadence, durations, failure rate, undo frequency and longest migrations
+    Summary {
+        /// Window to report on, e.g. 30d, 12h, 2w (default: all history)
+        #[arg(long, value_name = "WINDOW")]
+        since: Option<String>,
+        /// Number of longest migrations to list
+        #[arg(long, default_value_t = waypoint_core::commands::metrics::DEFAULT_TOP)]
+        top: usize,
+        /// Output format: plain, markdown, json
+        #[arg(long, default_value = "plain")]
+        format: String,
+    },
+}
+
+/// `report` subcommands.
+#[derive(Subcommand)]
+enum ReportCommand {
+    /// Summarize what changed between two info, advise, safety or drift JSON reports
+    Diff {
+        /// Report from the earlier run
+        before: String,
+        /// Report from the later run
+        after: String,
+    },
+}
+
+/// `snapshot` subcommands.
+#[derive(Subcommand)]
+enum SnapshotCommand {
+    /// Compare two stored snapshots without a database
+    Diff {
+        /// Earlier snapshot ID
+        from: String,
+        /// Later snapshot ID
+        to: String,
+    },
+    /// Write a snapshot as a schema-only SQL dump, pg_dump style (PostgreSQL)
+    Export {
+        /// Snapshot ID
+        id: String,
+        /// Write the dump to this file instead of stdout
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<String>,
+    },
+    /// Store a schema-only SQL dump (pg_dump --schema-only or `snapshot
+    /// export`) as a snapshot (PostgreSQL)
+    Import {
+        /// SQL file to read
+        file: String,
+        /// Snapshot ID (default: the file name without its extension)
+        #[arg(long)]
+        id: Option<String>,
+    },
+}
+
+/// `audit` subcommands.
+#[derive(Subcommand)]
+enum AuditCommand {
+    /// Cross-check every history row against the files and report each
+    /// mismatch by category (exits 3 on errors)
+    History,
+}
+
+/// `checksums` subcommands.
+#[derive(Subcommand)]
+enum ChecksumsCommand {
+    /// Store the current file checksums for the given versions
+    Update {
+        /// Versions and inclusive ranges, e.g. 1..150 or 3,7..9
+        #[arg(long, value_name = "VERSIONS")]
+        versions: String,
+        /// Why the files changed; stored in the audit table
+        #[arg(long)]
+        reason: String,
+    },
+}
+
+/// `history` subcommands.
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Bring the history table to the current layout (with --dry-run, print the statements)
+    Upgrade,
+}
+
+/// `guard` subcommands.
+#[derive(Subcommand)]
+enum GuardCommand {
+    /// Evaluate a guard expression and print the value of each sub-expression
+    Eval {
+        /// Expression, e.g. 'table_exists("users") AND row_count("users") < 1000'
+        expression: String,
+    },
+    /// Evaluate the guards of all pending migrations without applying anything
+    Check,
+}
+
+/// `multi` subcommands.
+#[derive(Subcommand)]
+enum MultiCommand {
+    /// Apply one pending migration to every database in [[databases]], in dependency order
+    #[command(disable_version_flag = true)]
+    Exec {
+        /// Version of the migration to apply, e.g. 42
+        #[arg(long, value_name = "VER")]
+        version: String,
+        /// Only databases with one of these tags (comma-separated)
+        #[arg(long = "tag", value_delimiter = ',', value_name = "TAGS")]
+        tags: Vec<String>,
+    },
+}
+
+/// `assist` subcommands.
+#[derive(Subcommand)]
+enum AssistCommand {
+    /// Generate migrations that rename or remove enum labels safely
+    Enum {
+        /// Enum type (optionally schema-qualified)
+        #[arg(long = "type", value_name = "TYPE")]
+        type_name: String,
+        /// Labels to remove (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "LABELS")]
+        remove: Vec<String>,
+        /// Labels to rename, as old=new (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "OLD=NEW")]
+        rename: Vec<String>,
+        /// Label given to rows holding a removed label (default: NULL)
+        #[arg(long, value_name = "LABEL")]
+        replace_with: Option<String>,
+        /// Rows updated per backfill batch
+        #[arg(long, default_value_t = waypoint_core::commands::assist::DEFAULT_BATCH_SIZE)]
+        batch_size: i64,
+    },
+    /// Split a table-rewriting ADD COLUMN ... DEFAULT into add, backfill and NOT NULL steps
+    Default {
+        /// The ALTER TABLE ... ADD COLUMN ... DEFAULT statement
+        statement: String,
+        /// Rows updated per backfill batch
+        #[arg(long, default_value_t = waypoint_core::commands::assist::DEFAULT_BATCH_SIZE)]
+        batch_size: i64,
+    },
+    /// Split ADD CONSTRAINT ... UNIQUE into a concurrent index build and USING INDEX
+    Unique {
+        /// The ALTER TABLE ... ADD CONSTRAINT ... UNIQUE (...) statement
+        statement: String,
+    },
+    /// Split ADD CONSTRAINT ... FOREIGN KEY into NOT VALID and a later VALIDATE
+    Fk {
+        /// The ALTER TABLE ... ADD CONSTRAINT ... FOREIGN KEY statement
+        statement: String,
+    },
+}
+
 /// All available waypoint subcommands.
 #[derive(Subcommand)]
 enum Commands {
     /// Apply pending migrations
     Migrate {
-        /// Migrate up to this version only
+        /// Migrate up to this version only; also +N (next N pending) or
+        /// latest-N (hold back the newest N)
         #[arg(long, value_name = "VERSION")]
         target: Option<String>,
+
+        /// Continue a failed non-transactional migration from its failed statement
+        /// (requires statement_progress)
+        #[arg(long)]
+        resume: bool,
+
+        /// Idempotent mode for Terraform/Ansible: print only a stable
+        /// {"changed": ...} JSON result on stdout (with --dry-run, a check)
+        #[arg(long)]
+        converge: bool,
+
+        /// Print progress events as NDJSON lines on stdout while migrating
+        #[arg(long, conflicts_with = "converge")]
+        json_stream: bool,
+
+        /// Apply only these pending versions (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "VERSIONS")]
+        versions: Vec<String>,
+
+        /// Leave these pending versions unapplied (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "VERSIONS")]
+        skip_versions: Vec<String>,
+
+        /// Migrate every tenant database this query returns from the configured
+        /// (control) database: first column the URL, optional second column a name
+        #[arg(long, value_name = "SQL", conflicts_with_all = ["json_stream", "resume"])]
+        tenant_query: Option<String>,
+
+        /// How many tenants to migrate at the same time (with --tenant-query)
+        #[arg(
+            long,
+            value_name = "N",
+            requires = "tenant_query",
+            default_value_t = waypoint_core::multi::DEFAULT_TENANT_CONCURRENCY
+        )]
+        tenant_concurrency: usize,
+
+        /// Migrate up to N databases at once in multi-db mode; databases
+        /// wait only for the ones they depend on
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        max_parallel: usize,
+
+        /// Multi-db mode: migrate these databases first (comma-separated),
+        /// check their health for --bake-time, then migrate the rest or stop
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "NAMES",
+            conflicts_with = "tenant_query"
+        )]
+        canary: Vec<String>,
+
+        /// How long the canaries must stay healthy, e.g. 90s, 30m or 1h
+        #[arg(
+            long,
+            value_name = "DURATION",
+            default_value = "5m",
+            requires = "canary"
+        )]
+        bake_time: String,
+
+        /// How often the health checks run while the canaries bake
+        #[arg(
+            long,
+            value_name = "DURATION",
+            default_value = "30s",
+            requires = "canary"
+        )]
+        health_interval: String,
+
+        /// SQL run on each canary while baking; an error, no rows, or a
+        /// false, 0 or NULL first column fails the check
+        #[arg(long, value_name = "SQL", requires = "canary")]
+        health_sql: Option<String>,
+
+        /// URL fetched while baking; an error or non-2xx response fails the
+        /// check (repeatable)
+        #[arg(long, value_name = "URL", requires = "canary")]
+        health_url: Vec<String>,
     },
 
     /// Show migration status
-    Info,
+    Info {
+        /// Show pending versions outside this list as ignored (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "VERSIONS")]
+        versions: Vec<String>,
+
+        /// Show these pending versions as ignored (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "VERSIONS")]
+        skip_versions: Vec<String>,
+
+        /// Show only migrations still to be applied, and exit with code 21
+        /// if there are any
+        #[arg(long)]
+        pending: bool,
+
+        /// Show only applied migrations
+        #[arg(long)]
+        applied: bool,
+
+        /// Show only failed migrations
+        #[arg(long)]
+        failed: bool,
+
+        /// Show only versions above this one
+        #[arg(long, value_name = "VERSION")]
+        since: Option<String>,
+
+        /// Show each version's apply/undo/failure timeline and the undo
+        /// stack, reconstructed from the history table
+        #[arg(long, conflicts_with_all = ["pending", "applied", "failed", "since"])]
+        history: bool,
+    },
 
     /// Validate applied migrations
     Validate,
@@ -180,6 +500,64 @@ enum Commands {
     /// Repair the schema history table
     Repair,
 
+    /// Audit the history table against the migration files
+    Audit {
+        #[command(subcommand)]
+        action: AuditCommand,
+    },
+
+    /// Record pending migrations already applied outside waypoint
+    Reconcile,
+
+    /// Apply new or changed seed files (S__*.sql), tracked outside the
+    /// schema history (PostgreSQL)
+    Seed {
+        /// Run unchanged seeds again too
+        #[arg(long)]
+        reapply: bool,
+    },
+
+    /// Run data-quality checks: SQL assertions in [checks] locations that
+    /// must return no rows or true
+    Check {
+        /// Run only these checks (default: all)
+        names: Vec<String>,
+    },
+
+    /// Copy a Flyway schema history table into waypoint's, checking checksums against local files
+    ImportFlyway {
+        /// Flyway history table in the configured schema
+        #[arg(long, value_name = "TABLE", default_value = waypoint_core::commands::import_flyway::DEFAULT_FLYWAY_TABLE)]
+        from_table: String,
+    },
+
+    /// Print [notify] migrate notifications as they arrive (PostgreSQL only)
+    Listen {
+        /// Channel to listen on (default: [notify] channel)
+        #[arg(long, value_name = "NAME")]
+        channel: Option<String>,
+
+        /// Stop after this many notifications
+        #[arg(long, value_name = "N")]
+        count: Option<usize>,
+
+        /// Stop after this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+    },
+
+    /// Coordinated checksum updates, recorded in an audit table
+    Checksums {
+        #[command(subcommand)]
+        action: ChecksumsCommand,
+    },
+
+    /// History table maintenance
+    History {
+        #[command(subcommand)]
+        action: HistoryCommand,
+    },
+
     /// Baseline an existing database
     Baseline {
         /// Version to baseline at
@@ -194,12 +572,17 @@ enum Commands {
     /// Undo applied migration(s)
     Undo {
         /// Undo all versions above this version (exclusive)
-        #[arg(long, value_name = "VERSION", conflicts_with = "count")]
+        #[arg(long, value_name = "VERSION", conflicts_with_all = ["count", "only"])]
         target: Option<String>,
 
         /// Number of migrations to undo
-        #[arg(long, value_name = "N", conflicts_with = "target")]
+        #[arg(long, value_name = "N", conflicts_with_all = ["target", "only"])]
         count: Option<usize>,
+
+        /// Undo only this version, leaving later ones applied (refused if a
+        /// later migration depends on it)
+        #[arg(long, value_name = "VERSION")]
+        only: Option<String>,
     },
 
     /// Drop all objects in managed schemas
@@ -214,9 +597,37 @@ enum Commands {
         /// Disable specific rules (comma-separated)
         #[arg(long, value_name = "RULES", value_delimiter = ',')]
         disable: Vec<String>,
-        /// Exit code 1 if any errors found
+    },
+
+    /// Create waypoint.toml and db/migrations/ in the current directory
+    /// (existing files are kept unless --force)
+    Init {
+        /// Write a self-contained starter kit (config, example migrations,
+        /// git hook, CI templates) into DIR instead
+        #[arg(long, value_name = "DIR")]
+        offline_kit: Option<String>,
+    },
+
+    /// Scaffold the next migration file from a template
+    #[command(visible_aliases = ["generate", "new-migration"])]
+    New {
+        /// V__Description (next version), V7__Description, R__Description,
+        /// or just a description
+        name: String,
+
+        /// Template: ddl, data, undo-pair, or a vetted one: add-column,
+        /// create-table, add-index-concurrently, backfill
+        #[arg(long, default_value = "ddl")]
+        template: String,
+
+        /// Also create the matching U{version}__ undo file
         #[arg(long)]
-        strict: bool,
+        undo: bool,
+
+        /// Value for a vetted template's parameter, e.g. --set table=users
+        /// (repeatable; missing ones are prompted for on a terminal)
+        #[arg(long = "set", value_name = "NAME=VALUE")]
+        values: Vec<String>,
     },
 
     /// Auto-generate changelog from migration DDL
@@ -230,13 +641,24 @@ enum Commands {
         /// Output format: plain, markdown, json
         #[arg(long, default_value = "plain")]
         format: String,
+        /// Add when and where each migration was applied, from the history table
+        #[arg(long)]
+        deployed: bool,
     },
 
     /// Compare database schema against a target
     Diff {
         /// Compare against another database URL
-        #[arg(long, value_name = "URL")]
+        #[arg(long, value_name = "URL", conflicts_with = "target_file")]
         target_url: Option<String>,
+        /// Compare against a schema-only SQL dump, e.g. pg_dump --schema-only
+        /// or `snapshot export` output (PostgreSQL only)
+        #[arg(long, value_name = "PATH", conflicts_with = "target_migrations")]
+        target_file: Option<String>,
+        /// Compare against the schema the local migration files describe,
+        /// replayed into a throwaway schema
+        #[arg(long, conflicts_with = "target_url")]
+        target_migrations: bool,
         /// Write output SQL to file
         #[arg(long)]
         output: Option<String>,
@@ -245,11 +667,56 @@ enum Commands {
         auto_version: bool,
     },
 
+    /// Find objects whose names match a LIKE pattern (e.g. "users_%")
+    Find {
+        /// Name pattern; % matches any run of characters, _ a single one
+        pattern: String,
+        /// Object types to search, comma-separated (table, view, index,
+        /// sequence, function, type, constraint, trigger; default: all)
+        #[arg(long, value_name = "TYPES")]
+        types: Option<String>,
+        /// Also search another database (repeatable)
+        #[arg(long, value_name = "URL")]
+        target_url: Vec<String>,
+        /// Also search a stored snapshot, by id or path (repeatable)
+        #[arg(long, value_name = "ID")]
+        snapshot: Vec<String>,
+        /// Also search every stored snapshot
+        #[arg(long)]
+        all_snapshots: bool,
+    },
+
     /// Detect manual schema changes that bypassed migrations
-    Drift,
+    Drift {
+        /// Compare against the baseline stored by the last migrate
+        /// ([drift] store_baseline) instead of replaying migrations
+        #[arg(long)]
+        stored: bool,
+        /// Compare against a schema-only SQL dump (pg_dump --schema-only or
+        /// `snapshot export`) instead of replaying migrations (PostgreSQL only)
+        #[arg(long, value_name = "PATH", conflicts_with = "stored")]
+        against: Option<String>,
+        /// Write a corrective migration recording the drift to this file
+        #[arg(long, value_name = "PATH", group = "fix")]
+        fix_file: Option<String>,
+        /// Write the corrective migration as the next versioned migration
+        /// (V{next}__Absorb_drift.sql) in the first location
+        #[arg(long, group = "fix")]
+        generate_fix: bool,
+        /// Write a script reverting the database to the migrations' schema
+        /// instead (to PATH, or drift_revert_<timestamp>.sql with --generate-fix)
+        #[arg(long, requires = "fix")]
+        revert: bool,
+        /// Include DROP statements in the corrective migration or revert script
+        #[arg(long, requires = "fix")]
+        allow_destructive: bool,
+    },
 
     /// Take a schema snapshot
-    Snapshot,
+    Snapshot {
+        #[command(subcommand)]
+        action: Option<SnapshotCommand>,
+    },
 
     /// Restore from a schema snapshot
     Restore {
@@ -261,6 +728,19 @@ enum Commands {
     /// Run pre-flight health checks
     Preflight,
 
+    /// Operations across every database in [[databases]]
+    Multi {
+        #[command(subcommand)]
+        action: MultiCommand,
+    },
+
+    /// Test guard expressions against the database
+    #[command(alias = "guards")]
+    Guard {
+        #[command(subcommand)]
+        action: GuardCommand,
+    },
+
     /// Detect migration conflicts between git branches
     CheckConflicts {
         /// Base branch to compare against
@@ -271,6 +751,13 @@ enum Commands {
         git_hook: bool,
     },
 
+    /// Renumber this branch's migrations that collide with or precede the base branch's
+    Rebase {
+        /// Base branch to renumber after
+        #[arg(long, default_value = "main")]
+        base: String,
+    },
+
     /// Analyze migration safety (lock levels, impact estimation)
     Safety {
         /// Analyze a specific migration file
@@ -288,12 +775,151 @@ enum Commands {
     /// Dry-run migrations in a temporary schema
     Simulate,
 
+    /// Fold old migrations into one generated baseline and archive the originals
+    Squash {
+        /// Highest version to squash (inclusive)
+        #[arg(long, value_name = "VERSION")]
+        through: String,
+        /// Baseline file name; its version must be one of the squashed versions
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: String,
+        /// Where to move the squashed files (default: archive/ in the first location)
+        #[arg(long, value_name = "DIR")]
+        archive_dir: Option<String>,
+    },
+
+    /// Export table rows as an INSERT/COPY script with anonymized columns
+    Export {
+        /// Table to export (optionally schema-qualified)
+        #[arg(long)]
+        table: String,
+        /// Columns to anonymize, e.g. email,name or phone:hash
+        #[arg(long, value_delimiter = ',', value_name = "COLUMNS")]
+        anonymize: Vec<String>,
+        /// Output format: insert, copy
+        #[arg(long, default_value = "insert")]
+        format: String,
+        /// Only export rows matching this SQL condition
+        #[arg(long = "where", value_name = "CONDITION")]
+        where_clause: Option<String>,
+        /// Maximum number of rows to export
+        #[arg(long)]
+        limit: Option<i64>,
+        /// Write the script to a file instead of stdout
+        #[arg(long, short = 'o')]
+        output: Option<String>,
+    },
+
+    /// Schedule a migrate run for a maintenance window (omit --at to list)
+    Schedule {
+        /// Earliest start time, e.g. 2024-07-01T02:00Z
+        #[arg(long, value_name = "TIME", conflicts_with = "cancel")]
+        at: Option<String>,
+        /// Migrate up to this version only (+N and latest-N are resolved
+        /// when the run starts)
+        #[arg(long, value_name = "VERSION", requires = "at")]
+        target: Option<String>,
+        /// Minutes after --at before an unstarted run expires
+        #[arg(long, value_name = "MINUTES", default_value_t = 120)]
+        window: u32,
+        /// Cancel a pending scheduled run by ID
+        #[arg(long, value_name = "ID")]
+        cancel: Option<i64>,
+    },
+
+    /// Run the earliest due scheduled migrate run (for cron / CronJob)
+    RunScheduled,
+
+    /// Verify a signed execution receipt against its signature and the history table
+    VerifyReceipt {
+        /// Receipt file written to [receipts] dir
+        #[arg(required_unless_present = "run_id", conflicts_with = "run_id")]
+        file: Option<String>,
+        /// Load the receipt from the receipts table instead of a file
+        #[arg(long, value_name = "ID")]
+        run_id: Option<String>,
+        /// Check the signature only, without connecting to the database
+        #[arg(long, conflicts_with = "run_id")]
+        offline: bool,
+    },
+
+    /// Work with JSON reports archived from earlier runs
+    Report {
+        #[command(subcommand)]
+        action: ReportCommand,
+    },
+
+    /// Usage reports built from the schema history table
+    Metrics {
+        #[command(subcommand)]
+        action: MetricsCommand,
+    },
+
+    /// Summarize pending migrations and send the summary as a [notify] notification (for cron)
+    PendingReport {
+        /// Channel to notify on (default: [notify] channel)
+        #[arg(long, value_name = "NAME")]
+        channel: Option<String>,
+    },
+
+    /// Fail when a pending migration's file is older than the allowed age (for CI)
+    SlaCheck {
+        /// Longest a migration may stay pending, e.g. 14d, 36h or 2w
+        #[arg(long, value_name = "AGE")]
+        max_pending_age: String,
+    },
+
+    /// Print the schema's canonical fingerprint, optionally failing unless it matches
+    Fingerprint {
+        /// Fingerprint the schema must have (exit code 24 otherwise)
+        #[arg(long, value_name = "SHA256")]
+        expect: Option<String>,
+    },
+
+    /// Generate multi-step migrations for changes that are unsafe in place
+    Assist {
+        #[command(subcommand)]
+        action: AssistCommand,
+    },
+
+    /// Serve migrate/info/validate/drift over an authenticated HTTP+JSON API
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on (default: [server] bind, 127.0.0.1:8080)
+        #[arg(long, value_name = "ADDR")]
+        bind: Option<String>,
+    },
+
+    /// Print version and build details (--json: capability document for tooling)
+    Version,
+
     /// Update waypoint to the latest version
     #[cfg(feature = "self-update")]
+    #[command(disable_version_flag = true)]
     SelfUpdate {
         /// Check for updates without installing
         #[arg(long)]
         check: bool,
+        /// Release channel: stable, or beta to include pre-releases
+        #[arg(long, default_value = "stable")]
+        channel: String,
+        /// Install exactly this version (e.g. 0.5.1), older or newer
+        #[arg(long, value_name = "X.Y.Z")]
+        version: Option<String>,
+        /// HTTP(S) proxy URL (default: HTTPS_PROXY / ALL_PROXY)
+        #[arg(long, value_name = "URL")]
+        proxy: Option<String>,
+        /// Require SHA256SUMS.minisig to verify against this minisign public
+        /// key (or WAYPOINT_UPDATE_MINISIGN_KEY)
+        #[arg(long, value_name = "KEY")]
+        minisign_key: Option<String>,
+        /// Require SHA256SUMS.asc to verify against the local GPG keyring
+        #[arg(long)]
+        gpg: bool,
+        /// Run install.sh when the release tarball can't be downloaded
+        /// (install.sh's download is not checksum-verified)
+        #[arg(long)]
+        install_sh_fallback: bool,
     },
 }
 
@@ -302,7 +928,14 @@ async fn main() {
     let cli = Cli::parse();
 
     // Set up logging (suppress when JSON output is requested)
-    let filter = if cli.json {
+    let json_stream = matches!(
+        cli.command,
+        Commands::Migrate {
+            json_stream: true,
+            ..
+        }
+    );
+    let filter = if cli.json || json_stream {
         "error"
     } else if cli.verbose {
         "debug"
@@ -314,16 +947,110 @@ async fn main() {
 
     env_logger::Builder::new()
         .parse_env(env_logger::Env::default().default_filter_or(filter))
-        .format_target(false)
-        .format_timestamp(None)
+        .format(|buf, record| {
+            let style = buf.default_level_style(record.level());
+            let message = record.args().to_string();
+            let message = match output::redactor() {
+                Some(r) => r.redact_log(&message),
+                None => message,
+            };
+            writeln!(buf, "[{style}{:<5}{style:#}] {}", record.level(), message)
+        })
         .init();
 
-    if let Err(e) = run(cli).await {
+    #[cfg(feature = "otel")]
+    let result = {
+        use tracing::Instrument;
+        let telemetry = telemetry::init();
+        let span = tracing::info_span!("waypoint", command = command_name(&cli.command));
+        let result = run(cli).instrument(span).await;
+        if let Some(telemetry) = telemetry {
+            telemetry.shutdown();
+        }
+        result
+    };
+    #[cfg(not(feature = "otel"))]
+    let result = run(cli).await;
+
+    if let Err(e) = result {
         print_error(&e);
         process::exit(exit_code(&e));
     }
 }
 
+/// Webhook notifier for `[notifications]`, if any webhooks are configured.
+fn notifier(config: &WaypointConfig) -> Option<Notifier> {
+    #[cfg(feature = "self-update")]
+    {
+        Notifier::new(
+            &config.notifications,
+            std::sync::Arc::new(webhook::HttpWebhook),
+        )
+    }
+    #[cfg(not(feature = "self-update"))]
+    {
+        if !config.notifications.webhooks.is_empty() {
+            log::warn!(
+                "[notifications] webhooks need the HTTP client from the self-update feature; not sending"
+            );
+        }
+        None
+    }
+}
+
+/// Send how a migrate ended to the webhooks. Delivery failures after a
+/// successful run are added to the report's warnings.
+async fn notify_migrate(
+    notifier: Option<&Notifier>,
+    database: &str,
+    schema: &str,
+    start_warnings: Vec<waypoint_core::Warning>,
+    result: Result<waypoint_core::MigrateReport, WaypointError>,
+) -> Result<waypoint_core::MigrateReport, WaypointError> {
+    let Some(notifier) = notifier else {
+        return result;
+    };
+    match result {
+        Ok(mut report) => {
+            let event = LifecycleEvent::migrate_success(database, schema, &report);
+            report.warnings.extend(start_warnings);
+            report.warnings.extend(notifier.send(&event).await);
+            Ok(report)
+        }
+        Err(e) => {
+            notifier
+                .send(&LifecycleEvent::migrate_failure(database, schema, &e))
+                .await;
+            Err(e)
+        }
+    }
+}
+
+/// Ask on the terminal for each template parameter not given with `--set`;
+/// an empty answer takes the default, or leaves the parameter unfilled.
+fn prompt_template_values(
+    template: &waypoint_core::templates::Template,
+    values: &mut std::collections::BTreeMap<String, String>,
+) -> Result<(), WaypointError> {
+    for param in template.params {
+        if values.contains_key(param.name) {
+            continue;
+        }
+        match param.default {
+            Some(default) => eprint!("{} [{}]: ", param.prompt, default),
+            None => eprint!("{}: ", param.prompt),
+        }
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        if !answer.is_empty() {
+            values.insert(param.name.to_string(), answer.to_string());
+        }
+    }
+    Ok(())
+}
+
 /// Map error types to differentiated exit codes.
 // ChecksumMismatch and DiffFailed are deprecated reserved variants that no
 // code path actually constructs. Their arms below are dead but kept until
@@ -347,11 +1074,14 @@ fn exit_code(error: &WaypointError) -> i32 {
         #[cfg(feature = "mysql")]
         WaypointError::MysqlError(_) => 4,
         WaypointError::ConnectionLost { .. } => 4,
+        WaypointError::PrerequisiteFailed { .. } => 4,
         WaypointError::MigrationFailed { .. } => 5,
         WaypointError::MigrationParseError(_) => 5,
+        WaypointError::MigrationTooLarge { .. } => 5,
         WaypointError::HookFailed { .. } => 5,
         WaypointError::UndoFailed { .. } => 5,
         WaypointError::UndoMissing { .. } => 5,
+        WaypointError::UndoUnsafe { .. } => 5,
         WaypointError::NonTransactionalStatement { .. } => 5,
         WaypointError::MultiDbError { .. } => 5,
         WaypointError::LockError(_) => 6,
@@ -364,6 +1094,15 @@ fn exit_code(error: &WaypointError) -> i32 {
         WaypointError::GuardFailed { .. } => 13,
         WaypointError::MigrationBlocked { .. } => 14,
         WaypointError::SimulationFailed { .. } => 15,
+        WaypointError::BackupFailed { .. } => 16,
+        WaypointError::PlanDiverged { .. } => 17,
+        WaypointError::PolicyDenied { .. } => 18,
+        WaypointError::ReadOnlyStandby { .. } => 19,
+        WaypointError::ReceiptInvalid { .. } => 20,
+        WaypointError::PendingMigrations { .. } => 21,
+        WaypointError::ChecksFailed { .. } => 22,
+        WaypointError::PendingAgeExceeded { .. } => 23,
+        WaypointError::FingerprintMismatch { .. } => 24,
         WaypointError::DiffFailed { .. } => 1,
         WaypointError::SnapshotError { .. } => 1,
         WaypointError::GitError(_) => 1,
@@ -374,17 +1113,67 @@ fn exit_code(error: &WaypointError) -> i32 {
 
 /// Build configuration, resolve multi-database mode, and dispatch the chosen subcommand.
 async fn run(cli: Cli) -> Result<(), WaypointError> {
-    let json_output = cli.json;
+    // --converge: stdout carries only the converge JSON, so suppress everything else.
+    let converge = matches!(cli.command, Commands::Migrate { converge: true, .. });
+    let json_stream = matches!(
+        cli.command,
+        Commands::Migrate {
+            json_stream: true,
+            ..
+        }
+    );
+    let json_output = cli.json || converge || json_stream;
     let dry_run = cli.dry_run;
-    let quiet = cli.quiet;
+    let quiet = cli.quiet || converge || json_stream;
     let skip_preflight = cli.skip_preflight;
     let force = cli.force;
+    // `lint --strict` keeps its original meaning (fail on lint errors) rather
+    // than turning on the strict bundle, which would reject out_of_order.
+    let lint_strict = cli.strict && matches!(cli.command, Commands::Lint { .. });
     let simulate_flag = cli.simulate;
 
+    if let Commands::Version = &cli.command {
+        print_report!(version_info(), json_output, output::print_version);
+        return Ok(());
+    }
+
+    // Handle init before config load; there is usually no config yet.
+    if let Commands::Init { offline_kit } = &cli.command {
+        let options = waypoint_core::InitOptions {
+            dir: offline_kit.as_deref().unwrap_or(".").into(),
+            offline_kit: offline_kit.is_some(),
+            force,
+            dry_run,
+        };
+        let report = waypoint_core::commands::init::execute(&options)?;
+        print_report!(report, json_output, quiet, output::print_init_report);
+        return Ok(());
+    }
+
     // Handle self-update before config/DB setup (no database needed)
     #[cfg(feature = "self-update")]
-    if let Commands::SelfUpdate { check } = &cli.command {
-        return self_update::self_update(*check, json_output);
+    if let Commands::SelfUpdate {
+        check,
+        channel,
+        version,
+        proxy,
+        minisign_key,
+        gpg,
+        install_sh_fallback,
+    } = &cli.command
+    {
+        let options = self_update::UpdateOptions {
+            check: *check,
+            channel: channel.parse()?,
+            version: version.clone(),
+            proxy: proxy.clone(),
+            minisign_key: minisign_key
+                .clone()
+                .or_else(|| std::env::var("WAYPOINT_UPDATE_MINISIGN_KEY").ok()),
+            gpg: *gpg,
+            install_sh_fallback: *install_sh_fallback,
+        };
+        return self_update::self_update(&options, json_output);
     }
 
     // Build CLI overrides with negation flag support
@@ -402,6 +1191,24 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
         cli.validate_on_migrate
     };
 
+    let resume = matches!(cli.command, Commands::Migrate { resume: true, .. });
+    let (cherry_pick, skip_versions) = match &cli.command {
+        Commands::Migrate {
+            versions,
+            skip_versions,
+            ..
+        }
+        | Commands::Info {
+            versions,
+            skip_versions,
+            ..
+        } => (
+            Some(versions.clone()).filter(|v| !v.is_empty()),
+            Some(skip_versions.clone()).filter(|v| !v.is_empty()),
+        ),
+        _ => (None, None),
+    };
+
     let overrides = CliOverrides {
         url: cli.url,
         schema: cli.schema,
@@ -410,6 +1217,8 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
             .locations
             .map(|l| l.split(',').map(|s| normalize_location(s.trim())).collect()),
         out_of_order,
+        cherry_pick,
+        skip_versions,
         validate_on_migrate,
         baseline_version: match &cli.command {
             Commands::Baseline {
@@ -421,34 +1230,90 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
         ssl_mode: cli.ssl_mode,
         connect_timeout: cli.connect_timeout,
         statement_timeout: cli.statement_timeout,
+        introspection_timeout: cli.introspection_timeout,
+        include_extension_objects: if cli.include_extension_objects {
+            Some(true)
+        } else {
+            None
+        },
+        include_objects: cli.include,
+        exclude_objects: cli.exclude,
         environment: cli.environment,
         dependency_ordering: if cli.dependency_ordering {
             Some(true)
         } else {
             None
         },
+        global_lock: if cli.global_lock { Some(true) } else { None },
+        allow_standby: if cli.allow_standby { Some(true) } else { None },
         keepalive: cli.keepalive,
         batch_transaction: if cli.transaction { Some(true) } else { None },
+        resume: if resume { Some(true) } else { None },
+        strict: if cli.strict && !lint_strict {
+            Some(true)
+        } else {
+            None
+        },
+        dev: if cli.dev { Some(true) } else { None },
+        placeholders_file: cli.placeholders_file.map(std::path::PathBuf::from),
+        placeholders: cli.placeholders,
     };
 
     // Load config
     let mut config = WaypointConfig::load(cli.config.as_deref(), &overrides)?;
 
+    if config.mode.dev && !json_output && !quiet {
+        eprintln!(
+            "{}",
+            "Dev mode: preflight, safety and simulation off; clean and out-of-order allowed."
+                .dimmed()
+        );
+    }
+
+    if config.mode.strict && (skip_preflight || force) {
+        return Err(WaypointError::ConfigError(format!(
+            "{} is not allowed in strict mode",
+            if force { "--force" } else { "--skip-preflight" }
+        )));
+    }
+
     // Override preflight if --skip-preflight
     if skip_preflight {
         config.preflight.enabled = false;
     }
 
+    output::set_redactor(config.output.redactor(command_name(&cli.command)));
+    config.policy.overridden = cli.override_policy;
+    config.policy.check(command_name(&cli.command))?;
+
+    #[cfg(feature = "server")]
+    if let Commands::Serve { bind } = &cli.command {
+        return server::serve(config, bind.clone()).await;
+    }
+
+    // Remote locations (`git+<url>#<ref>:<path>`, `git:<ref>#<path>`, HTTP
+    // archives, `s3://`) are fetched into temporary checkouts, which are
+    // removed when `fetched` drops at the end of the run.
+    let fetched = waypoint_core::location::fetch(
+        &config.migrations.locations,
+        &config.migrations.location_providers,
+    )?;
+    config.migrations.locations = fetched.locations.clone();
+
     // === Commands that don't need a DB connection ===
 
     match &cli.command {
-        Commands::Lint { disable, strict } => {
-            let mut disabled = config.lint.disabled_rules.clone();
-            disabled.extend(disable.iter().cloned());
-            let report =
-                waypoint_core::commands::lint::execute(&config.migrations.locations, &disabled)?;
+        Commands::Lint { disable } => {
+            let mut lint_config = config.lint.clone();
+            lint_config.disabled_rules.extend(disable.iter().cloned());
+            let report = waypoint_core::commands::lint::execute(
+                &config.migrations.locations,
+                &config.migrations.scan_limits(),
+                &lint_config,
+            )?;
             print_report!(report, json_output, output::print_lint_report);
-            if *strict && report.error_count > 0 {
+            // Strict mode turns on `lint.strict` (see `WaypointConfig::apply_strict`).
+            if (lint_strict || config.lint.strict) && report.error_count > 0 {
                 return Err(WaypointError::LintFailed {
                     error_count: report.error_count,
                     details: format!("{} warning(s)", report.warning_count),
@@ -456,17 +1321,24 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
             }
             return Ok(());
         }
-        Commands::Changelog { from, to, format } => {
-            let report = waypoint_core::commands::changelog::execute(
+        Commands::Changelog {
+            from,
+            to,
+            format,
+            deployed,
+        } => {
+            let mut report = waypoint_core::commands::changelog::execute(
                 &config.migrations.locations,
+                &config.migrations.scan_limits(),
                 from.as_deref(),
                 to.as_deref(),
             )?;
+            if *deployed {
+                let infos = Waypoint::new(config.clone()).await?.info().await?;
+                waypoint_core::commands::changelog::attach_deployments(&mut report, &infos);
+            }
             if json_output {
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&report).expect("JSON serialization failed")
-                );
+                println!("{}", output::to_json(&report));
             } else {
                 let fmt = waypoint_core::commands::changelog::ChangelogFormat::parse(format);
                 match fmt {
@@ -477,11 +1349,7 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
                         );
                     }
                     waypoint_core::commands::changelog::ChangelogFormat::Json => {
-                        println!(
-                            "{}",
-                            serde_json::to_string_pretty(&report)
-                                .expect("JSON serialization failed")
-                        );
+                        println!("{}", output::to_json(&report));
                     }
                     waypoint_core::commands::changelog::ChangelogFormat::PlainText => {
                         print!(
@@ -493,16 +1361,68 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
             }
             return Ok(());
         }
+        Commands::New {
+            name,
+            template,
+            undo,
+            values,
+        } => {
+            let template: waypoint_core::MigrationTemplate = template.parse()?;
+            let mut values = values
+                .iter()
+                .map(|v| match v.split_once('=') {
+                    Some((k, v)) if !k.trim().is_empty() => {
+                        Ok((k.trim().to_string(), v.trim().to_string()))
+                    }
+                    _ => Err(WaypointError::ConfigError(format!(
+                        "Invalid --set '{}': expected name=value",
+                        v
+                    ))),
+                })
+                .collect::<Result<std::collections::BTreeMap<_, _>, _>>()?;
+            if let Some(builtin) = template.builtin() {
+                if !json_output && std::io::stdin().is_terminal() {
+                    prompt_template_values(builtin, &mut values)?;
+                }
+            }
+            let options = waypoint_core::NewMigrationOptions {
+                name: name.clone(),
+                template,
+                undo: *undo,
+                values,
+                dry_run,
+            };
+            // Number after the history table too when a database is configured;
+            // fall back to the files alone if it can't be reached.
+            let report = match config.database.url {
+                Some(_) => match Waypoint::new(config.clone()).await {
+                    Ok(wp) => wp.new_migration(&options).await?,
+                    Err(e) => {
+                        if !json_output && !quiet {
+                            eprintln!(
+                                "{}",
+                                format!(
+                                    "Could not read the history table ({}); numbering from migration files only.",
+                                    e
+                                )
+                                .yellow()
+                            );
+                        }
+                        waypoint_core::commands::new_migration::execute(&config, &options, &[])?
+                    }
+                },
+                None => waypoint_core::commands::new_migration::execute(&config, &options, &[])?,
+            };
+            print_report!(report, json_output, quiet, output::print_new_migration);
+            return Ok(());
+        }
         Commands::CheckConflicts { base, git_hook } => {
             let report = waypoint_core::commands::check_conflicts::execute(
                 &config.migrations.locations,
                 base,
             )?;
             if json_output {
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&report).expect("JSON serialization failed")
-                );
+                println!("{}", output::to_json(&report));
             } else if *git_hook {
                 if report.has_conflicts {
                     eprintln!(
@@ -526,26 +1446,330 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
             }
             return Ok(());
         }
-        _ => {}
-    }
-
-    // === Multi-database mode ===
-    if let Some(ref databases) = config.multi_database {
-        let order = waypoint_core::MultiWaypoint::execution_order(databases)?;
-        let clients =
-            waypoint_core::MultiWaypoint::connect(databases, cli.database.as_deref()).await?;
-
-        match &cli.command {
-            Commands::Migrate { target } => {
-                let result = waypoint_core::MultiWaypoint::migrate_with_options(
-                    databases,
-                    &clients,
-                    &order,
-                    target.as_deref(),
-                    cli.fail_fast,
+        Commands::Rebase { base } => {
+            let options = waypoint_core::RebaseOptions {
+                base: base.clone(),
+                dry_run,
+            };
+            let report = Waypoint::rebase(&config, &options)?;
+            print_report!(report, json_output, quiet, output::print_rebase_report);
+            return Ok(());
+        }
+        Commands::Report {
+            action: ReportCommand::Diff { before, after },
+        } => {
+            let diff = Waypoint::report_diff(before.as_ref(), after.as_ref())?;
+            print_report!(diff, json_output, output::print_report_diff);
+            return Ok(());
+        }
+        Commands::Snapshot {
+            action: Some(SnapshotCommand::Diff { from, to }),
+        } => {
+            let diff = Waypoint::snapshot_diff(&config.snapshots, from, to)?;
+            print_report!(diff, json_output, output::print_snapshot_diff);
+            return Ok(());
+        }
+        Commands::Snapshot {
+            action: Some(SnapshotCommand::Export { id, output: path }),
+        } => {
+            let sql = Waypoint::snapshot_export(&config.snapshots, id, &config.migrations.schema)?;
+            match path {
+                Some(path) => {
+                    std::fs::write(path, &sql).map_err(WaypointError::IoError)?;
+                    if !json_output {
+                        println!(
+                            "{}",
+                            format!("Snapshot '{}' exported to {}", id, path).green()
+                        );
+                    }
+                }
+                None => print!("{}", sql),
+            }
+            return Ok(());
+        }
+        Commands::Snapshot {
+            action: Some(SnapshotCommand::Import { file, id }),
+        } => {
+            let report = Waypoint::snapshot_import(
+                &config.snapshots,
+                file.as_ref(),
+                id.as_deref(),
+                &config.migrations.schema,
+            )?;
+            print_report!(report, json_output, output::print_snapshot_report);
+            return Ok(());
+        }
+        Commands::VerifyReceipt {
+            file: Some(file),
+            offline: true,
+            ..
+        } => {
+            let receipt = waypoint_core::receipt::ExecutionReceipt::read_file(file.as_ref())?;
+            let report = waypoint_core::receipt::verify_offline(&config, &receipt)?;
+            return finish_receipt_verification(&report, json_output);
+        }
+        _ => {}
+    }
+
+    // === Tenant mode ===
+    if let Commands::Migrate {
+        tenant_query: Some(query),
+        tenant_concurrency,
+        target,
+        ..
+    } = &cli.command
+    {
+        if config.multi_database.is_some() {
+            return Err(WaypointError::ConfigError(
+                "--tenant-query runs against the top-level [database]; it can't be combined with [[databases]]"
+                    .to_string(),
+            ));
+        }
+        let control = Waypoint::new(config.clone()).await?;
+        let options = waypoint_core::multi::TenantOptions {
+            query: query.clone(),
+            concurrency: *tenant_concurrency,
+            target_version: target.clone(),
+            force,
+            fail_fast: cli.fail_fast,
+            dry_run,
+        };
+        let result =
+            waypoint_core::MultiWaypoint::for_each_tenant(&config, control.client(), &options)
+                .await?;
+        if !converge {
+            print_report!(result, json_output, output::print_multi_result);
+        }
+        if !result.all_succeeded {
+            return Err(WaypointError::MultiDbError {
+                name: "tenants".to_string(),
+                reason: "One or more tenant databases failed".to_string(),
+            });
+        }
+        if converge {
+            print_converge(&waypoint_core::ConvergeReport::from_multi(&result));
+        }
+        return Ok(());
+    }
+
+    if let Commands::Migrate { canary, .. } = &cli.command {
+        if !canary.is_empty() && config.multi_database.is_none() {
+            return Err(WaypointError::ConfigError(
+                "--canary rolls out across [[databases]]; it needs multi-db mode".to_string(),
+            ));
+        }
+    }
+    if matches!(cli.command, Commands::Multi { .. }) && config.multi_database.is_none() {
+        return Err(WaypointError::ConfigError(
+            "multi commands run across [[databases]]; they need multi-db mode".to_string(),
+        ));
+    }
+
+    // === Multi-database mode ===
+    if let Some(ref databases) = config.multi_database {
+        let order = waypoint_core::MultiWaypoint::execution_order(databases)?;
+        let clients =
+            waypoint_core::MultiWaypoint::connect(&config, databases, cli.database.as_deref())
+                .await?;
+
+        match &cli.command {
+            Commands::Migrate {
+                target,
+                max_parallel,
+                canary,
+                bake_time,
+                health_interval,
+                health_sql,
+                health_url,
+                ..
+            } => {
+                if json_stream {
+                    return Err(WaypointError::ConfigError(
+                        "--json-stream needs a single database; select one with --database"
+                            .to_string(),
+                    ));
+                }
+                if dry_run {
+                    if !converge {
+                        return Err(WaypointError::ConfigError(
+                            "--dry-run migrate in multi-db mode needs --converge; \
+                             use info --pending to list pending migrations"
+                                .to_string(),
+                        ));
+                    }
+                    print_converge(
+                        &waypoint_core::MultiWaypoint::converge_check(
+                            &config,
+                            databases,
+                            &clients,
+                            &order,
+                            target.as_deref(),
+                        )
+                        .await?,
+                    );
+                    return Ok(());
+                }
+                let notifier = notifier(&config);
+                let mut warnings = Vec::new();
+                if let Some(n) = &notifier {
+                    warnings =
+                        waypoint_core::MultiWaypoint::notify_start(n, databases, &order).await;
+                }
+                let mut result = if canary.is_empty() {
+                    waypoint_core::MultiWaypoint::migrate_parallel(
+                        &config,
+                        databases,
+                        clients,
+                        &order,
+                        target.as_deref(),
+                        cli.fail_fast,
+                        force,
+                        *max_parallel,
+                    )
+                    .await?
+                } else {
+                    let options = canary_options(
+                        canary,
+                        bake_time,
+                        health_interval,
+                        health_sql.as_deref(),
+                        health_url,
+                    )?;
+                    waypoint_core::MultiWaypoint::migrate_canary(
+                        &config,
+                        databases,
+                        clients,
+                        &order,
+                        &options,
+                        target.as_deref(),
+                        cli.fail_fast,
+                        force,
+                        *max_parallel,
+                    )
+                    .await?
+                };
+                if let Some(n) = &notifier {
+                    warnings.extend(
+                        waypoint_core::MultiWaypoint::notify_result(n, databases, &result).await,
+                    );
+                }
+                result.warnings.extend(warnings);
+                if !converge {
+                    print_report!(result, json_output, output::print_multi_result);
+                }
+                if !result.all_succeeded {
+                    return Err(WaypointError::MultiDbError {
+                        name: "multi".to_string(),
+                        reason: "One or more databases failed".to_string(),
+                    });
+                }
+                if converge {
+                    print_converge(&waypoint_core::ConvergeReport::from_multi(&result));
+                }
+            }
+            Commands::Info {
+                pending,
+                applied,
+                failed,
+                since,
+                history,
+                ..
+            } => {
+                if *history {
+                    return Err(WaypointError::ConfigError(
+                        "info --history needs a single database; select one with --database"
+                            .to_string(),
+                    ));
+                }
+                // Each database's `--since` is parsed under its own version_scheme.
+                let mut all_info: std::collections::HashMap<
+                    String,
+                    Vec<waypoint_core::MigrationInfo>,
+                > = std::collections::HashMap::new();
+                for (name, infos) in
+                    waypoint_core::MultiWaypoint::info(&config, databases, &clients, &order).await?
+                {
+                    let scheme = databases
+                        .iter()
+                        .find(|d| d.name == name)
+                        .map_or_else(VersionScheme::default, |d| d.migrations.version_scheme);
+                    let filter =
+                        info_filter(*pending, *applied, *failed, since.as_deref(), scheme)?;
+                    all_info.insert(name, filter.apply(infos));
+                }
+                print_report!(all_info, json_output, output::print_multi_info);
+                if *pending {
+                    let scripts = all_info.iter().flat_map(|(name, infos)| {
+                        infos
+                            .iter()
+                            .filter(|i| i.state.is_pending())
+                            .map(move |i| format!("{}/{}", name, i.script))
+                    });
+                    check_no_pending(scripts)?;
+                }
+            }
+            Commands::Find {
+                pattern,
+                types,
+                target_url,
+                snapshot,
+                all_snapshots,
+            } => {
+                let query = find_query(pattern, types.as_deref())?;
+                let snapshots = find_snapshots(&config, snapshot, *all_snapshots)?;
+                let mut report = waypoint_core::MultiWaypoint::find(
+                    &config, databases, &clients, &order, &query,
+                )
+                .await?;
+                waypoint_core::commands::find::search_targets(
+                    &mut report,
+                    &config,
+                    &query,
+                    target_url,
+                    &snapshots,
+                )
+                .await?;
+                print_report!(report, json_output, output::print_find_report);
+            }
+            Commands::Metrics {
+                action: MetricsCommand::Summary { since, top, format },
+            } => {
+                let since = since
+                    .as_deref()
+                    .map(waypoint_core::commands::metrics::parse_since)
+                    .transpose()?;
+                let summaries = waypoint_core::MultiWaypoint::metrics(
+                    &config, databases, &clients, &order, since, *top,
+                )
+                .await?;
+                print_metrics(&summaries, format, json_output);
+            }
+            Commands::Multi {
+                action: MultiCommand::Exec { version, tags },
+            } => {
+                let options = waypoint_core::multi::ApplySingleOptions {
+                    version: version.clone(),
+                    tags: tags.clone(),
+                    dry_run,
+                    fail_fast: cli.fail_fast,
                     force,
+                };
+                let notifier = notifier(&config).filter(|_| !dry_run);
+                let mut warnings = Vec::new();
+                if let Some(n) = &notifier {
+                    warnings =
+                        waypoint_core::MultiWaypoint::notify_start(n, databases, &order).await;
+                }
+                let mut result = waypoint_core::MultiWaypoint::apply_single(
+                    &config, databases, &clients, &order, &options,
                 )
                 .await?;
+                if let Some(n) = &notifier {
+                    warnings.extend(
+                        waypoint_core::MultiWaypoint::notify_result(n, databases, &result).await,
+                    );
+                }
+                result.warnings.extend(warnings);
                 print_report!(result, json_output, output::print_multi_result);
                 if !result.all_succeeded {
                     return Err(WaypointError::MultiDbError {
@@ -554,16 +1778,23 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
                     });
                 }
             }
-            Commands::Info => {
-                let all_info =
-                    waypoint_core::MultiWaypoint::info(databases, &clients, &order).await?;
-                print_report!(all_info, json_output, output::print_multi_info);
+            Commands::PendingReport { channel } => {
+                let summaries = waypoint_core::MultiWaypoint::pending_report(
+                    &config,
+                    databases,
+                    &clients,
+                    &order,
+                    !dry_run,
+                    channel.as_deref(),
+                )
+                .await?;
+                print_report!(summaries, json_output, output::print_pending_summaries);
             }
             _ => {
                 // For other commands, run on filtered single DB
                 if let Some(ref db_name) = cli.database {
                     if let Some(db) = databases.iter().find(|d| &d.name == db_name) {
-                        let single_config = db.to_waypoint_config();
+                        let single_config = db.merge_into(&config)?;
                         let wp = Waypoint::new(single_config).await?;
                         return run_single_db_command(
                             &cli.command,
@@ -590,8 +1821,17 @@ async fn run(cli: Cli) -> Result<(), WaypointError> {
 
     // Dry-run mode: show what would be applied using info/explain
     if dry_run {
-        if let Commands::Migrate { .. } = &cli.command {
+        if let Commands::Migrate { target, .. } = &cli.command {
             let wp = Waypoint::new(config).await?;
+            if converge {
+                let infos = wp.info().await?;
+                print_converge(&waypoint_core::ConvergeReport::pending(
+                    &infos,
+                    target.as_deref(),
+                    &wp.config.migrations,
+                )?);
+                return Ok(());
+            }
             let report =
                 waypoint_core::commands::explain::execute_db(wp.client(), &wp.config).await?;
             print_report!(report, json_output, output::print_explain_report);
@@ -641,18 +1881,25 @@ async fn run_single_db_command(
     command: &Commands,
     wp: &Waypoint,
     json_output: bool,
-    _dry_run: bool,
+    dry_run: bool,
     force: bool,
     simulate_before: bool,
     quiet: bool,
 ) -> Result<(), WaypointError> {
     match command {
-        Commands::Migrate { target, .. } => {
+        Commands::Migrate {
+            target,
+            converge,
+            json_stream,
+            ..
+        } => {
             // Optional: simulate before migrate
             if simulate_before || wp.config.simulation.simulate_before_migrate {
                 let sim_report = wp.simulate().await?;
                 if !sim_report.passed {
-                    print_report!(sim_report, json_output, output::print_simulation_report);
+                    if !converge && !json_stream {
+                        print_report!(sim_report, json_output, output::print_simulation_report);
+                    }
                     return Err(WaypointError::SimulationFailed {
                         reason: sim_report
                             .errors
@@ -667,12 +1914,107 @@ async fn run_single_db_command(
                 }
             }
 
-            let report = wp.migrate_with_options(target.as_deref(), force).await?;
-            print_report!(report, json_output, quiet, output::print_migrate_summary);
+            // Dev mode: show what the run changed, as a schema diff.
+            let show_changes = wp.config.mode.dev && !json_output && !quiet;
+            let before = if show_changes {
+                waypoint_core::schema::introspect_db(wp.client(), &wp.config.migrations.schema)
+                    .await
+                    .ok()
+            } else {
+                None
+            };
+
+            let notifier = notifier(&wp.config);
+            let database = waypoint_core::notify::database_label(&wp.config);
+            let schema = wp.config.migrations.schema.clone();
+            let mut start_warnings = Vec::new();
+            if let Some(n) = &notifier {
+                start_warnings = n
+                    .send(&LifecycleEvent::migrate_start(&database, &schema))
+                    .await;
+            }
+
+            if *json_stream {
+                let result = wp
+                    .migrate_with_observer(target.as_deref(), force, |event| {
+                        println!("{}", output::to_json_line(event));
+                    })
+                    .await;
+                let report = notify_migrate(
+                    notifier.as_ref(),
+                    &database,
+                    &schema,
+                    start_warnings,
+                    result,
+                )
+                .await?;
+                if let Some(checks) = &report.checks {
+                    checks_passed(checks)?;
+                }
+                return Ok(());
+            }
+
+            let result = wp.migrate_with_options(target.as_deref(), force).await;
+            let report = notify_migrate(
+                notifier.as_ref(),
+                &database,
+                &schema,
+                start_warnings,
+                result,
+            )
+            .await?;
+            if *converge {
+                print_converge(&waypoint_core::ConvergeReport::from_report(&report));
+            } else {
+                print_report!(report, json_output, quiet, output::print_migrate_summary);
+            }
+            if let Some(checks) = &report.checks {
+                checks_passed(checks)?;
+            }
+
+            if let Some(before) = before {
+                let after =
+                    waypoint_core::schema::introspect_db(wp.client(), &wp.config.migrations.schema)
+                        .await?;
+                let user_objects = |snapshot| {
+                    waypoint_core::drift_baseline::without_waypoint_objects(
+                        snapshot,
+                        &wp.config.migrations.table,
+                    )
+                };
+                let changes =
+                    waypoint_core::schema::diff(&user_objects(&before), &user_objects(&after));
+                output::print_schema_changes(&changes);
+            }
+        }
+        Commands::Info { history: true, .. } => {
+            let timeline = wp.info_history().await?;
+            print_report!(timeline, json_output, quiet, output::print_history_timeline);
         }
-        Commands::Info => {
-            let infos = wp.info().await?;
+        Commands::Info {
+            pending,
+            applied,
+            failed,
+            since,
+            ..
+        } => {
+            let filter = info_filter(
+                *pending,
+                *applied,
+                *failed,
+                since.as_deref(),
+                wp.config.migrations.version_scheme,
+            )?;
+            let infos = wp.info_filtered(&filter).await?;
             print_report!(infos, json_output, quiet, output::print_info_table);
+            if *pending {
+                check_no_pending(
+                    infos
+                        .iter()
+                        .filter(|i| i.state.is_pending())
+                        .map(|i| i.script.clone()),
+                )?;
+            }
         }
         Commands::Validate => {
             let report = wp.validate().await?;
@@ -682,6 +2024,86 @@ async fn run_single_db_command(
             let report = wp.repair().await?;
             print_report!(report, json_output, quiet, output::print_repair_result);
         }
+        Commands::Audit {
+            action: AuditCommand::History,
+        } => {
+            let report = wp.audit_history().await?;
+            print_report!(report, json_output, quiet, output::print_history_audit);
+            if report.error_count > 0 {
+                return Err(WaypointError::ValidationFailed(format!(
+                    "History audit found {} error(s) and {} warning(s)",
+                    report.error_count, report.warning_count
+                )));
+            }
+        }
+        Commands::Checksums {
+            action: ChecksumsCommand::Update { versions, reason },
+        } => {
+            let options = waypoint_core::ChecksumUpdateOptions {
+                versions: waypoint_core::commands::checksums::parse_version_ranges(
+                    versions,
+                    &wp.config.migrations,
+                )?,
+                reason: reason.clone(),
+                force,
+                dry_run,
+            };
+            let report = wp.update_checksums(&options).await?;
+            print_report!(
+                report,
+                json_output,
+                quiet,
+                output::print_checksum_update_report
+            );
+        }
+        Commands::Reconcile => {
+            let report = wp.reconcile(dry_run).await?;
+            print_report!(report, json_output, quiet, output::print_reconcile_report);
+        }
+        Commands::Seed { reapply } => {
+            let report = wp.seed(dry_run, *reapply).await?;
+            print_report!(report, json_output, quiet, output::print_seed_report);
+        }
+        Commands::Check { names } => {
+            let report = wp.check(names).await?;
+            print_report!(report, json_output, quiet, output::print_check_report);
+            checks_passed(&report)?;
+        }
+        Commands::ImportFlyway { from_table } => {
+            let report = wp.import_flyway(from_table, force, dry_run).await?;
+            print_report!(
+                report,
+                json_output,
+                quiet,
+                output::print_import_flyway_report
+            );
+            if report.blocked {
+                return Err(WaypointError::ValidationFailed(format!(
+                    "{} Flyway checksum(s) differ from local files; nothing was imported (use --force to import anyway)",
+                    report.mismatches
+                )));
+            }
+        }
+        Commands::Listen {
+            channel,
+            count,
+            timeout,
+        } => {
+            wp.listen(channel.as_deref(), *count, *timeout, |event| {
+                if json_output {
+                    println!("{}", output::to_json_line(event));
+                } else {
+                    output::print_listen_event(event);
+                }
+            })
+            .await?;
+        }
+        Commands::History {
+            action: HistoryCommand::Upgrade,
+        } => {
+            let report = wp.upgrade_history(dry_run).await?;
+            print_report!(report, json_output, quiet, output::print_history_upgrade);
+        }
         Commands::Baseline {
             baseline_version,
             baseline_description,
@@ -697,16 +2119,27 @@ async fn run_single_db_command(
                 println!("{}", "Successfully baselined schema.".green().bold());
             }
         }
-        Commands::Undo { target, count } => {
-            let undo_target = if let Some(ver) = target {
-                UndoTarget::Version(MigrationVersion::parse(ver)?)
+        Commands::Undo {
+            target,
+            count,
+            only,
+        } => {
+            let undo_target = if let Some(ver) = only {
+                UndoTarget::Exact(wp.config.migrations.parse_version(ver)?)
+            } else if let Some(ver) = target {
+                UndoTarget::Version(wp.config.migrations.parse_version(ver)?)
             } else if let Some(n) = count {
                 UndoTarget::Count(*n)
             } else {
                 UndoTarget::Last
             };
-            let report = wp.undo(undo_target).await?;
-            print_report!(report, json_output, output::print_undo_summary);
+            if dry_run {
+                let plan = wp.undo_plan(undo_target).await?;
+                print_report!(plan, json_output, output::print_undo_plan);
+            } else {
+                let report = wp.undo(undo_target).await?;
+                print_report!(report, json_output, output::print_undo_summary);
+            }
         }
         Commands::Clean { allow_clean } => {
             let dropped = wp.clean(*allow_clean).await?;
@@ -714,14 +2147,20 @@ async fn run_single_db_command(
         }
         Commands::Diff {
             target_url,
+            target_file,
+            target_migrations,
             output: output_file,
             auto_version,
         } => {
-            let target = match target_url {
-                Some(url) => waypoint_core::commands::diff::DiffTarget::Database(url.clone()),
-                None => {
+            use waypoint_core::commands::diff::DiffTarget;
+            let target = match (target_url, target_file) {
+                (Some(url), _) => DiffTarget::Database(url.clone()),
+                (None, Some(file)) => DiffTarget::File(file.into()),
+                (None, None) if *target_migrations => DiffTarget::Migrations,
+                (None, None) => {
                     return Err(WaypointError::ConfigError(
-                        "Diff requires --target-url".to_string(),
+                        "Diff requires --target-url, --target-file or --target-migrations"
+                            .to_string(),
                     ));
                 }
             };
@@ -729,30 +2168,119 @@ async fn run_single_db_command(
             print_report!(report, json_output, output::print_diff_report);
             if report.has_changes {
                 let output_path = if *auto_version {
-                    // Determine next version from existing migrations
-                    let infos = wp.info().await?;
-                    let max_version = infos
-                        .iter()
-                        .filter_map(|i| i.version.as_ref())
-                        .filter_map(|v| v.parse::<u64>().ok())
-                        .max()
-                        .unwrap_or(0);
-                    let next_version = max_version + 1;
-                    let dir = &wp.config.migrations.locations[0];
-                    let filename = format!("V{}__Auto_generated.sql", next_version);
-                    Some(dir.join(filename).display().to_string())
+                    let mut known = known_versions(wp).await?;
+                    let version = next_version(&wp.config, &mut known)?;
+                    let path = first_location(wp)?
+                        .join(format!("V{}__Auto_generated.sql", version))
+                        .display()
+                        .to_string();
+                    write_new_file(path.as_ref(), &report.generated_sql)?;
+                    Some(path)
                 } else {
+                    if let Some(path) = output_file {
+                        std::fs::write(path, &report.generated_sql)
+                            .map_err(WaypointError::IoError)?;
+                    }
                     output_file.clone()
                 };
                 if let Some(path) = output_path {
-                    std::fs::write(&path, &report.generated_sql).map_err(WaypointError::IoError)?;
                     println!("{}", format!("Generated SQL written to {}", path).green());
                 }
             }
         }
-        Commands::Drift => {
-            let report = wp.drift().await?;
+        Commands::Find {
+            pattern,
+            types,
+            target_url,
+            snapshot,
+            all_snapshots,
+        } => {
+            let query = find_query(pattern, types.as_deref())?;
+            let snapshots = find_snapshots(&wp.config, snapshot, *all_snapshots)?;
+            let report = wp.find(&query, target_url, &snapshots).await?;
+            print_report!(report, json_output, output::print_find_report);
+        }
+        Commands::Drift {
+            stored,
+            against,
+            fix_file,
+            generate_fix,
+            revert,
+            allow_destructive,
+        } => {
+            let report = if *stored {
+                wp.drift_stored().await?
+            } else if let Some(path) = against {
+                wp.drift_against(path.as_ref()).await?
+            } else {
+                wp.drift().await?
+            };
+            if report.has_drift {
+                if let Some(n) = notifier(&wp.config) {
+                    let database = waypoint_core::notify::database_label(&wp.config);
+                    n.send(&LifecycleEvent::drift_detected(&database, &report))
+                        .await;
+                }
+            }
             print_report!(report, json_output, output::print_drift_report);
+            if report.has_drift && (fix_file.is_some() || *generate_fix) {
+                let (label, path, contents, destructive) = if *generate_fix && !*revert {
+                    let fix = wp
+                        .drift_fix_migration(&report, *allow_destructive, dry_run)
+                        .await?;
+                    let file = fix.files.into_iter().next().expect("one migration file");
+                    (
+                        "Corrective migration",
+                        file.path,
+                        file.contents,
+                        report.has_destructive(),
+                    )
+                } else {
+                    let path = fix_file.clone().unwrap_or_else(|| {
+                        format!(
+                            "drift_revert_{}.sql",
+                            chrono::Utc::now().format("%Y%m%d%H%M%S")
+                        )
+                    });
+                    let (label, contents, destructive) = if *revert {
+                        (
+                            "Revert script",
+                            report.revert_sql(*allow_destructive),
+                            report.revert_has_destructive(),
+                        )
+                    } else {
+                        (
+                            "Corrective migration",
+                            report.corrective_sql(*allow_destructive),
+                            report.has_destructive(),
+                        )
+                    };
+                    if !dry_run {
+                        std::fs::write(&path, &contents).map_err(WaypointError::IoError)?;
+                    }
+                    (label, path, contents, destructive)
+                };
+                if !json_output {
+                    if dry_run {
+                        println!(
+                            "{}",
+                            format!("Would write {} to {}:", label.to_lowercase(), path)
+                                .yellow()
+                                .bold()
+                        );
+                        print!("{}", contents);
+                    } else {
+                        println!("{}", format!("{} written to {}", label, path).green());
+                    }
+                    if destructive && !*allow_destructive {
+                        println!(
+                            "{}",
+                            "Destructive statements were commented out; rerun with --allow-destructive to include them."
+                                .yellow()
+                        );
+                    }
+                }
+            }
             if report.has_drift {
                 return Err(WaypointError::DriftDetected {
                     count: report.drifts.len(),
@@ -765,7 +2293,7 @@ async fn run_single_db_command(
                 });
             }
         }
-        Commands::Snapshot => {
+        Commands::Snapshot { action: None } => {
             let report = wp.snapshot(&wp.config.snapshots).await?;
             print_report!(report, json_output, output::print_snapshot_report);
         }
@@ -784,6 +2312,34 @@ async fn run_single_db_command(
             let report = wp.preflight().await?;
             print_report!(report, json_output, output::print_preflight_report);
         }
+        Commands::Guard {
+            action: GuardCommand::Eval { expression },
+        } => {
+            let evaluation = wp.eval_guard(expression).await?;
+            print_report!(evaluation, json_output, output::print_guard_evaluation);
+            if !evaluation.result {
+                return Err(WaypointError::GuardFailed {
+                    kind: "eval".to_string(),
+                    script: "command line".to_string(),
+                    expression: evaluation.expression,
+                    message: None,
+                });
+            }
+        }
+        Commands::Guard {
+            action: GuardCommand::Check,
+        } => {
+            let report = wp.check_guards().await?;
+            print_report!(report, json_output, output::print_guard_check_report);
+            if report.failed > 0 {
+                return Err(WaypointError::GuardFailed {
+                    kind: "check".to_string(),
+                    script: "pending migrations".to_string(),
+                    expression: format!("{} require guard(s) did not hold", report.failed),
+                    message: None,
+                });
+            }
+        }
         Commands::Safety { file } => {
             if let Some(path) = file {
                 let report =
@@ -793,10 +2349,7 @@ async fn run_single_db_command(
             } else {
                 let report = wp.safety().await?;
                 if json_output {
-                    println!(
-                        "{}",
-                        serde_json::to_string_pretty(&report).expect("JSON serialization failed")
-                    );
+                    println!("{}", output::to_json(&report));
                 } else {
                     for r in &report.reports {
                         output::print_safety_report(r);
@@ -827,25 +2380,586 @@ async fn run_single_db_command(
                 });
             }
         }
+        Commands::Squash {
+            through,
+            output: output_file,
+            archive_dir,
+        } => {
+            let options = waypoint_core::SquashOptions {
+                through: through.clone(),
+                output: output_file.clone(),
+                archive_dir: archive_dir.as_ref().map(std::path::PathBuf::from),
+                dry_run,
+            };
+            let report = wp.squash(&options).await?;
+            if json_output {
+                print_report!(report, json_output, output::print_squash_report);
+            } else if dry_run {
+                print!("{}", report.sql);
+            } else if !quiet {
+                output::print_squash_report(&report);
+            }
+        }
+        Commands::Export {
+            table,
+            anonymize,
+            format,
+            where_clause,
+            limit,
+            output: output_file,
+        } => {
+            let options = waypoint_core::ExportOptions {
+                table: table.clone(),
+                anonymize: anonymize.clone(),
+                format: format.parse()?,
+                where_clause: where_clause.clone(),
+                limit: *limit,
+            };
+            if let Some(path) = output_file {
+                let file = std::fs::File::create(path).map_err(WaypointError::IoError)?;
+                let report = wp
+                    .export_to(&options, &mut std::io::BufWriter::new(file))
+                    .await?;
+                if json_output {
+                    print_report!(report, json_output, output::print_export_report);
+                } else if !quiet {
+                    output::print_export_report(&report);
+                    println!("{}", format!("Script written to {}", path).green());
+                }
+            } else if json_output {
+                let report = wp.export(&options).await?;
+                print_report!(report, json_output, output::print_export_report);
+            } else {
+                wp.export_to(&options, &mut std::io::BufWriter::new(std::io::stdout()))
+                    .await?;
+            }
+        }
+        Commands::Schedule {
+            at,
+            target,
+            window,
+            cancel,
+        } => {
+            if let Some(id) = cancel {
+                let run = wp.cancel_schedule(*id).await?;
+                print_report!(run, json_output, quiet, output::print_scheduled_run);
+            } else if let Some(at) = at {
+                let run = wp.schedule(at, target.as_deref(), *window).await?;
+                print_report!(run, json_output, quiet, output::print_scheduled_run);
+            } else {
+                let runs = wp.list_schedules().await?;
+                print_report!(runs, json_output, output::print_schedule_list);
+            }
+        }
+        Commands::Metrics {
+            action: MetricsCommand::Summary { since, top, format },
+        } => {
+            let since = since
+                .as_deref()
+                .map(waypoint_core::commands::metrics::parse_since)
+                .transpose()?;
+            let summary = wp.metrics_summary(since, *top).await?;
+            print_metrics(std::slice::from_ref(&summary), format, json_output);
+        }
+        Commands::PendingReport { channel } => {
+            let summary = wp.pending_report(!dry_run, channel.as_deref()).await?;
+            print_report!(summary, json_output, output::print_pending_summary);
+        }
+        Commands::SlaCheck { max_pending_age } => {
+            let report = wp.sla_check(max_pending_age).await?;
+            print_report!(report, json_output, output::print_sla_check);
+            report.check()?;
+        }
+        Commands::Fingerprint { expect } => {
+            let report = wp.fingerprint().await?;
+            print_report!(report, json_output, output::print_fingerprint);
+            if let Some(expected) = expect {
+                report.check(expected)?;
+            }
+        }
+        Commands::Assist {
+            action:
+                AssistCommand::Enum {
+                    type_name,
+                    remove,
+                    rename,
+                    replace_with,
+                    batch_size,
+                },
+        } => {
+            let options = waypoint_core::EnumChangeOptions {
+                type_name: type_name.clone(),
+                remove: remove.clone(),
+                rename: rename
+                    .iter()
+                    .map(|r| waypoint_core::commands::assist::parse_rename(r))
+                    .collect::<Result<_, _>>()?,
+                replace_with: replace_with.clone(),
+                batch_size: *batch_size,
+            };
+            let report = wp.assist_enum(&options).await?;
+            if json_output {
+                print_report!(report, json_output, output::print_enum_assist_report);
+            } else {
+                let paths = write_generated(wp, &report.migrations, dry_run).await?;
+                if !dry_run && !quiet {
+                    output::print_enum_assist_report(&report);
+                    print_written(&paths);
+                }
+            }
+        }
+        Commands::Assist {
+            action:
+                AssistCommand::Default {
+                    statement,
+                    batch_size,
+                },
+        } => {
+            let report = wp.assist_default(statement, *batch_size).await?;
+            if json_output {
+                print_report!(report, json_output, output::print_default_assist_report);
+            } else {
+                let paths = write_generated(wp, &report.migrations, dry_run).await?;
+                if !dry_run && !quiet {
+                    output::print_default_assist_report(&report);
+                    print_written(&paths);
+                }
+            }
+        }
+        Commands::Assist {
+            action: AssistCommand::Unique { statement },
+        } => {
+            let report = wp.assist_unique(statement).await?;
+            if json_output {
+                print_report!(report, json_output, output::print_unique_assist_report);
+            } else {
+                let paths = write_generated(wp, &report.migrations, dry_run).await?;
+                if !dry_run && !quiet {
+                    output::print_unique_assist_report(&report);
+                    print_written(&paths);
+                }
+            }
+        }
+        Commands::Assist {
+            action: AssistCommand::Fk { statement },
+        } => {
+            let report = wp.assist_fk(statement).await?;
+            if json_output {
+                print_report!(report, json_output, output::print_fk_assist_report);
+            } else {
+                let paths = write_generated(wp, &report.migrations, dry_run).await?;
+                if !dry_run && !quiet {
+                    output::print_fk_assist_report(&report);
+                    print_written(&paths);
+                }
+            }
+        }
+        Commands::RunScheduled => {
+            let report = wp.run_scheduled().await?;
+            print_report!(
+                report,
+                json_output,
+                quiet,
+                output::print_run_scheduled_report
+            );
+        }
+        Commands::VerifyReceipt { file, run_id, .. } => {
+            let receipt = match (file, run_id) {
+                (Some(file), _) => {
+                    waypoint_core::receipt::ExecutionReceipt::read_file(file.as_ref())?
+                }
+                (None, Some(run_id)) => wp.load_receipt(run_id).await?,
+                (None, None) => unreachable!("clap requires a file or --run-id"),
+            };
+            let report = wp.verify_receipt(&receipt).await?;
+            finish_receipt_verification(&report, json_output)?;
+        }
         // No-DB commands handled earlier
-        Commands::Lint { .. } | Commands::Changelog { .. } | Commands::CheckConflicts { .. } => {
+        Commands::Lint { .. }
+        | Commands::Rebase { .. }
+        | Commands::Init { .. }
+        | Commands::New { .. }
+        | Commands::Changelog { .. }
+        | Commands::CheckConflicts { .. }
+        | Commands::Report { .. }
+        | Commands::Snapshot { action: Some(_) }
+        | Commands::Version => {
             unreachable!("handled before DB setup")
         }
         #[cfg(feature = "self-update")]
         Commands::SelfUpdate { .. } => {
             unreachable!("handled before DB setup")
         }
+        #[cfg(feature = "server")]
+        Commands::Serve { .. } => {
+            unreachable!("handled before DB setup")
+        }
+        Commands::Multi { .. } => {
+            return Err(WaypointError::ConfigError(
+                "multi commands run across [[databases]]; they need multi-db mode".to_string(),
+            ));
+        }
     }
 
     Ok(())
 }
 
+/// Parse a `--placeholder key=value` argument.
+fn parse_placeholder(arg: &str) -> Result<(String, String), String> {
+    waypoint_core::placeholder::parse_placeholder_arg(arg).map_err(|e| match e {
+        WaypointError::ConfigError(message) => message,
+        other => other.to_string(),
+    })
+}
+
+/// Subcommand name as used for `[policy]` keys.
+fn command_name(command: &Commands) -> &'static str {
+    match command {
+        Commands::Migrate { .. } => "migrate",
+        Commands::Info { .. } => "info",
+        Commands::Validate => "validate",
+        Commands::Repair => "repair",
+        Commands::Audit { .. } => "audit",
+        Commands::Checksums { .. } => "checksums",
+        Commands::Reconcile => "reconcile",
+        Commands::Seed { .. } => "seed",
+        Commands::Check { .. } => "check",
+        Commands::ImportFlyway { .. } => "import-flyway",
+        Commands::Listen { .. } => "listen",
+        Commands::History { .. } => "history",
+        Commands::Baseline { .. } => "baseline",
+        Commands::Undo { .. } => "undo",
+        Commands::Clean { .. } => "clean",
+        Commands::Lint { .. } => "lint",
+        Commands::Init { .. } => "init",
+        Commands::New { .. } => "new",
+        Commands::Changelog { .. } => "changelog",
+        Commands::Diff { .. } => "diff",
+        Commands::Find { .. } => "find",
+        Commands::Drift { .. } => "drift",
+        Commands::Snapshot { .. } => "snapshot",
+        Commands::Restore { .. } => "restore",
+        Commands::Preflight => "preflight",
+        Commands::Multi { .. } => "multi",
+        Commands::Guard { .. } => "guard",
+        Commands::CheckConflicts { .. } => "check-conflicts",
+        Commands::Rebase { .. } => "rebase",
+        Commands::Safety { .. } => "safety",
+        Commands::Advise { .. } => "advise",
+        Commands::Simulate => "simulate",
+        Commands::Squash { .. } => "squash",
+        Commands::Export { .. } => "export",
+        Commands::Schedule { .. } => "schedule",
+        Commands::RunScheduled => "run-scheduled",
+        Commands::VerifyReceipt { .. } => "verify-receipt",
+        Commands::Report { .. } => "report",
+        Commands::Metrics { .. } => "metrics",
+        Commands::PendingReport { .. } => "pending-report",
+        Commands::SlaCheck { .. } => "sla-check",
+        Commands::Fingerprint { .. } => "fingerprint",
+        Commands::Assist { .. } => "assist",
+        #[cfg(feature = "server")]
+        Commands::Serve { .. } => "serve",
+        Commands::Version => "version",
+        #[cfg(feature = "self-update")]
+        Commands::SelfUpdate { .. } => "self-update",
+    }
+}
+
+/// What this binary was built with, for `waypoint version`.
+fn version_info() -> output::VersionInfo {
+    let features = [
+        ("postgres", cfg!(feature = "postgres")),
+        ("mysql", cfg!(feature = "mysql")),
+        ("server", cfg!(feature = "server")),
+        ("self-update", cfg!(feature = "self-update")),
+        ("otel", cfg!(feature = "otel")),
+    ];
+    let dialects = [
+        ("postgres", cfg!(feature = "postgres")),
+        ("cockroach", cfg!(feature = "postgres")),
+        ("mysql", cfg!(feature = "mysql")),
+    ];
+    let enabled = |list: &[(&'static str, bool)]| -> Vec<&'static str> {
+        list.iter()
+            .filter(|(_, on)| *on)
+            .map(|(name, _)| *name)
+            .collect()
+    };
+    output::VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_hash: env!("GIT_HASH"),
+        build_date: env!("BUILD_TIME"),
+        output_schema_version: output::OUTPUT_SCHEMA_VERSION,
+        history_schema_version: waypoint_core::history::HISTORY_SCHEMA_VERSION,
+        features: enabled(&features),
+        dialects: enabled(&dialects),
+        commands: Cli::command()
+            .get_subcommands()
+            .map(|c| c.get_name().to_string())
+            .collect(),
+    }
+}
+
+/// Print a receipt verification and fail when any check did not pass.
+fn finish_receipt_verification(
+    report: &waypoint_core::receipt::ReceiptVerification,
+    json_output: bool,
+) -> Result<(), WaypointError> {
+    if json_output {
+        println!("{}", output::to_json(report));
+    } else {
+        output::print_receipt_verification(report);
+    }
+    if report.valid {
+        Ok(())
+    } else {
+        let mut failed = Vec::new();
+        if !report.signature_valid {
+            failed.push("signature does not match".to_string());
+        }
+        if report.database_matches == Some(false) {
+            failed.push("issued against a different database".to_string());
+        }
+        if !report.history_mismatches.is_empty() {
+            failed.push(format!(
+                "not in history: {}",
+                report.history_mismatches.join(", ")
+            ));
+        }
+        Err(WaypointError::ReceiptInvalid {
+            run_id: report.run_id.clone(),
+            reason: failed.join("; "),
+        })
+    }
+}
+
+/// Print the `--converge` result, the only thing written to stdout in that mode.
+fn print_converge(report: &waypoint_core::ConvergeReport) {
+    println!("{}", output::to_json_line(report));
+}
+
+/// Build the canary rollout options from the `migrate --canary` flags.
+fn canary_options(
+    canaries: &[String],
+    bake_time: &str,
+    health_interval: &str,
+    health_sql: Option<&str>,
+    health_urls: &[String],
+) -> Result<waypoint_core::multi::CanaryOptions, WaypointError> {
+    #[cfg(feature = "self-update")]
+    let probes = health_urls
+        .iter()
+        .map(|url| {
+            Box::new(health::HttpProbe::new(url)) as Box<dyn waypoint_core::multi::HealthProbe>
+        })
+        .collect();
+    #[cfg(not(feature = "self-update"))]
+    let probes = if health_urls.is_empty() {
+        Vec::new()
+    } else {
+        return Err(WaypointError::ConfigError(
+            "--health-url needs a build with the self-update feature, which provides the HTTP client"
+                .to_string(),
+        ));
+    };
+    Ok(waypoint_core::multi::CanaryOptions {
+        databases: canaries.to_vec(),
+        bake_time: waypoint_core::multi::parse_duration(bake_time)?,
+        poll_interval: waypoint_core::multi::parse_duration(health_interval)?,
+        health_sql: health_sql.map(str::to_string),
+        probes,
+    })
+}
+
+/// Write generated migrations into the first migrations location with the
+/// next free versions, or print them with `--dry-run`. Returns the paths.
+async fn write_generated(
+    wp: &Waypoint,
+    migrations: &[waypoint_core::commands::assist::GeneratedMigration],
+    dry_run: bool,
+) -> Result<Vec<String>, WaypointError> {
+    if dry_run {
+        for m in migrations {
+            println!("-- {}\n{}", m.description, m.sql);
+        }
+        return Ok(Vec::new());
+    }
+    let dir = first_location(wp)?;
+    let mut known = known_versions(wp).await?;
+    let mut paths = Vec::new();
+    for m in migrations {
+        let version = next_version(&wp.config, &mut known)?;
+        let path = dir.join(format!("V{}__{}.sql", version, m.description));
+        write_new_file(&path, &m.sql)?;
+        paths.push(path.display().to_string());
+    }
+    Ok(paths)
+}
+
+fn print_written(paths: &[String]) {
+    for path in paths {
+        println!("{}", format!("Wrote {}", path).green());
+    }
+}
+
+/// The first migrations location, where generated files are written.
+fn first_location(wp: &Waypoint) -> Result<&std::path::Path, WaypointError> {
+    wp.config
+        .migrations
+        .locations
+        .first()
+        .map(|l| l.as_path())
+        .ok_or_else(|| WaypointError::ConfigError("No migration locations configured".to_string()))
+}
+
+/// Versions known to `info`: migration files and the history table.
+async fn known_versions(wp: &Waypoint) -> Result<Vec<MigrationVersion>, WaypointError> {
+    Ok(wp
+        .info()
+        .await?
+        .iter()
+        .filter_map(|i| i.version.as_deref())
+        .filter_map(|v| wp.config.migrations.parse_version(v).ok())
+        .collect())
+}
+
+/// The version after every one in `known`, numbered as `new` numbers it, and
+/// add it to `known` so the next call moves on.
+fn next_version(
+    config: &WaypointConfig,
+    known: &mut Vec<MigrationVersion>,
+) -> Result<String, WaypointError> {
+    let version = waypoint_core::commands::new_migration::next_version(known.iter());
+    known.push(config.migrations.parse_version(&version)?);
+    Ok(version)
+}
+
+/// Write a file that must not exist yet, so a version taken since it was
+/// numbered is never overwritten.
+fn write_new_file(path: &std::path::Path, contents: &str) -> Result<(), WaypointError> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| {
+            WaypointError::IoError(std::io::Error::new(
+                e.kind(),
+                format!("{}: {}", path.display(), e),
+            ))
+        })?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Build the `info` row filter from `--pending`, `--applied`, `--failed` and
+/// `--since`.
+fn info_filter(
+    pending: bool,
+    applied: bool,
+    failed: bool,
+    since: Option<&str>,
+    scheme: VersionScheme,
+) -> Result<waypoint_core::InfoFilter, WaypointError> {
+    Ok(waypoint_core::InfoFilter {
+        pending,
+        applied,
+        failed,
+        since: since
+            .map(|v| MigrationVersion::parse_with(v, scheme))
+            .transpose()?,
+    })
+}
+
+/// Fail with `ChecksFailed` (exit code 22) when a check did not hold.
+fn checks_passed(report: &waypoint_core::CheckReport) -> Result<(), WaypointError> {
+    if report.failed == 0 {
+        return Ok(());
+    }
+    Err(WaypointError::ChecksFailed {
+        count: report.failed,
+        checks: report.failed_names().join(", "),
+    })
+}
+
+/// `info --pending`: fail with `PendingMigrations` (exit code 21) when
+/// `scripts` is not empty.
+fn check_no_pending(scripts: impl Iterator<Item = String>) -> Result<(), WaypointError> {
+    let scripts: Vec<String> = scripts.collect();
+    if scripts.is_empty() {
+        return Ok(());
+    }
+    Err(WaypointError::PendingMigrations {
+        count: scripts.len(),
+        scripts: scripts.join(", "),
+    })
+}
+
+/// Build the `find` query from the pattern and `--types`.
+fn find_query(
+    pattern: &str,
+    types: Option<&str>,
+) -> Result<waypoint_core::FindQuery, WaypointError> {
+    let types = types
+        .map(waypoint_core::ObjectType::parse_list)
+        .transpose()?
+        .unwrap_or_default();
+    waypoint_core::FindQuery::new(pattern, types)
+}
+
+/// Snapshots for `find`: the `--snapshot` ones, plus every stored one with
+/// `--all-snapshots`.
+fn find_snapshots(
+    config: &WaypointConfig,
+    snapshot: &[String],
+    all_snapshots: bool,
+) -> Result<Vec<String>, WaypointError> {
+    let mut snapshots = snapshot.to_vec();
+    if all_snapshots {
+        for id in waypoint_core::commands::find::all_snapshot_ids(&config.snapshots)? {
+            if !snapshots.contains(&id) {
+                snapshots.push(id);
+            }
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Print metrics summaries as plain text, Markdown, or JSON (`--json` or `--format json`).
+/// A single database prints as one JSON object, multiple as an array.
+fn print_metrics(summaries: &[waypoint_core::MetricsSummary], format: &str, json_output: bool) {
+    let format = format.to_lowercase();
+    if json_output || format == "json" {
+        let json = match summaries {
+            [single] if single.database.is_none() => output::to_json(single),
+            _ => output::to_json(summaries),
+        };
+        println!("{}", json);
+    } else if format == "markdown" || format == "md" {
+        print!(
+            "{}",
+            waypoint_core::commands::metrics::render_markdown(summaries)
+        );
+    } else {
+        for (i, summary) in summaries.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            output::print_metrics_summary(summary);
+        }
+    }
+}
+
 /// Print a formatted error message with actionable hints to stderr.
 // Same deprecation-suppression as `exit_code` — keeps the match arms for
 // reserved variants until 0.4.0 drops the variants entirely.
 #[allow(deprecated)]
 fn print_error(error: &WaypointError) {
-    eprintln!("{} {}", "ERROR:".red().bold(), error);
+    let message = match output::redactor() {
+        Some(r) if r.redacts(RedactField::Messages) => r.redact_str(&error.to_string()),
+        _ => error.to_string(),
+    };
+    eprintln!("{} {}", "ERROR:".red().bold(), message);
 
     // Provide actionable guidance
     match error {
@@ -879,16 +2993,28 @@ fn print_error(error: &WaypointError) {
         WaypointError::OutOfOrder { .. } => {
             eprintln!(
                 "{}",
-                "Hint: Use --out-of-order flag to allow out-of-order migrations.".dimmed()
+                "Hint: Use --out-of-order, or out_of_order_policy = \"patch-only\" for hotfixes."
+                    .dimmed()
             );
         }
-        WaypointError::UndoMissing { version } => {
+        WaypointError::UndoMissing {
+            version,
+            backup_ref,
+        } => {
+            let hint = match backup_ref {
+                Some(reference) => format!(
+                    "Hint: Restore from backup {reference}, or create a U{version}__<description>.sql file."
+                ),
+                None => format!(
+                    "Hint: Create a U{version}__<description>.sql file, or enable [reversals] for auto-generated undo."
+                ),
+            };
+            eprintln!("{}", hint.dimmed());
+        }
+        WaypointError::UndoUnsafe { .. } => {
             eprintln!(
                 "{}",
-                format!(
-                    "Hint: Create a U{version}__<description>.sql file, or enable [reversals] for auto-generated undo."
-                )
-                .dimmed()
+                "Hint: Undo the dependent migrations first, e.g. with --target.".dimmed()
             );
         }
         WaypointError::MigrationBlocked { .. } => {
@@ -898,13 +3024,27 @@ fn print_error(error: &WaypointError) {
                     .dimmed()
             );
         }
-        WaypointError::GuardFailed { .. } => {
+        WaypointError::GuardFailed { kind, .. } if kind == "check" => {
+            eprintln!(
+                "{}",
+                "Hint: Make the failing require guards hold (or fix their expressions) before running migrate."
+                    .dimmed()
+            );
+        }
+        WaypointError::GuardFailed { kind, .. } if kind != "eval" => {
             eprintln!(
                 "{}",
                 "Hint: Check guard conditions in your migration directives (-- waypoint:require / -- waypoint:ensure)."
                     .dimmed()
             );
         }
+        WaypointError::GuardFailed { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: The expression evaluated to false; the tree above shows which sub-expression caused it."
+                    .dimmed()
+            );
+        }
         WaypointError::DriftDetected { .. } => {
             eprintln!(
                 "{}",
@@ -932,28 +3072,111 @@ fn print_error(error: &WaypointError) {
                 "Hint: Run 'waypoint info' to check the current migration state.".dimmed()
             );
         }
-        WaypointError::PlaceholderNotFound { key, .. } => {
+        WaypointError::PrerequisiteFailed { .. } => {
             eprintln!(
                 "{}",
-                format!("Hint: Define placeholder '{}' in [placeholders] section of waypoint.toml or as an environment variable.", key).dimmed()
+                "Hint: Create the object manually with a privileged role, or grant CREATE to the migration user."
+                    .dimmed()
             );
         }
-        WaypointError::MigrationFailed { script, .. } => {
+        WaypointError::BackupFailed { .. } => {
             eprintln!(
                 "{}",
-                format!(
-                    "Hint: Fix the SQL error in '{}', then run 'waypoint repair' if needed.",
-                    script
-                )
-                .dimmed()
+                "Hint: Check the [backup] command, or set on_failure = \"warn\" to migrate without a backup."
+                    .dimmed()
             );
         }
-        WaypointError::HookFailed { script, .. } => {
+        WaypointError::PolicyDenied { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: The [policy] section of the config controls which commands may run here."
+                    .dimmed()
+            );
+        }
+        WaypointError::ReadOnlyStandby { command, .. } => {
+            let hint = if waypoint_core::db::STANDBY_READ_COMMANDS.contains(&command.as_str()) {
+                "Hint: Connect to the primary, or use --allow-standby to run it read-only."
+            } else {
+                "Hint: Connect to the primary; a standby cannot apply changes."
+            };
+            eprintln!("{}", hint.dimmed());
+        }
+        WaypointError::ReceiptInvalid { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: The receipt, the signing key or the history table changed since the run; investigate before trusting either."
+                    .dimmed()
+            );
+        }
+        WaypointError::PendingMigrations { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: Run 'waypoint migrate' to apply them; drop --pending to list migrations without failing."
+                    .dimmed()
+            );
+        }
+        WaypointError::PendingAgeExceeded { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: Apply or remove the migrations listed above; an unapplied file that should wait belongs on a branch."
+                    .dimmed()
+            );
+        }
+        WaypointError::FingerprintMismatch { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: The schema differs from the one expected; run 'waypoint info' for pending migrations, or 'waypoint drift --stored' to see what changed."
+                    .dimmed()
+            );
+        }
+        WaypointError::ChecksFailed { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: Each failing check's sample rows are listed above; checks run after migrate leave the migrations applied."
+                    .dimmed()
+            );
+        }
+        WaypointError::PlanDiverged { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: Make a new plan, review it, and apply that instead.".dimmed()
+            );
+        }
+        WaypointError::PlaceholderNotFound { key, .. } => {
             eprintln!(
                 "{}",
-                format!("Hint: Check the hook file '{}' for SQL errors.", script).dimmed()
+                format!("Hint: Define placeholder '{}' in [placeholders] section of waypoint.toml or as an environment variable.", key).dimmed()
             );
         }
+        WaypointError::MigrationFailed { script, reason } => {
+            if reason.contains("already exists") {
+                eprintln!(
+                    "{}",
+                    "Hint: If this migration was applied outside waypoint, run 'waypoint reconcile' to record it."
+                        .dimmed()
+                );
+            } else {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Hint: Fix the SQL error in '{}', then run 'waypoint repair' if needed.",
+                        script
+                    )
+                    .dimmed()
+                );
+            }
+        }
+        WaypointError::HookFailed { script, .. } => {
+            let hint = if script.ends_with(".sql") {
+                format!("Hint: Check the hook file '{}' for SQL errors.", script)
+            } else {
+                format!(
+                    "Hint: Hook command '{}' must exit 0 within [hooks] command_timeout.",
+                    script
+                )
+            };
+            eprintln!("{}", hint.dimmed());
+        }
         WaypointError::UndoFailed { script, .. } => {
             eprintln!(
                 "{}",
@@ -1036,6 +3259,13 @@ fn print_error(error: &WaypointError) {
                     .dimmed()
             );
         }
+        WaypointError::MigrationTooLarge { .. } => {
+            eprintln!(
+                "{}",
+                "Hint: Make sure the file isn't a schema or data dump. If the migration really is that large, raise [migrations] max_migration_bytes / max_statements (0 disables the check)."
+                    .dimmed()
+            );
+        }
         WaypointError::MultiDbDependencyCycle { .. } | WaypointError::MultiDbError { .. } => {
             eprintln!(
                 "{}",
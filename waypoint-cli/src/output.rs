@@ -3,6 +3,7 @@
 //! severity-aware terminal styling.
 
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 use colored::Colorize;
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
@@ -10,6 +11,82 @@ use comfy_table::presets::UTF8_FULL;
 use comfy_table::{Cell, ContentArrangement, Table};
 
 use waypoint_core::commands::info::{MigrationInfo, MigrationState};
+use waypoint_core::commands::migrate::MaintenanceAction;
+use waypoint_core::redact::Redactor;
+
+/// Version of the JSON report shapes printed with `--json`. Bumped when a
+/// field is removed or changes meaning; added fields don't bump it.
+pub const OUTPUT_SCHEMA_VERSION: u32 = 1;
+
+/// `[output]` redaction for the running command, set once config is loaded.
+static REDACTOR: OnceLock<Redactor> = OnceLock::new();
+
+/// Install the redactor used by [`to_json`], [`to_json_line`] and the logger.
+pub fn set_redactor(redactor: Redactor) {
+    let _ = REDACTOR.set(redactor);
+}
+
+/// The active redactor, if `[output] redact` applies to this command.
+pub fn redactor() -> Option<&'static Redactor> {
+    REDACTOR.get().filter(|r| r.is_active())
+}
+
+/// Serialize a report as pretty JSON, with `[output] redact` applied.
+pub fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    match redactor() {
+        Some(r) => {
+            let mut value = serde_json::to_value(value).expect("JSON serialization failed");
+            r.redact_value(&mut value);
+            serde_json::to_string_pretty(&value)
+        }
+        None => serde_json::to_string_pretty(value),
+    }
+    .expect("JSON serialization failed")
+}
+
+/// Serialize a value as a single JSON line, with `[output] redact` applied.
+pub fn to_json_line<T: serde::Serialize + ?Sized>(value: &T) -> String {
+    match redactor() {
+        Some(r) => {
+            let mut value = serde_json::to_value(value).expect("JSON serialization failed");
+            r.redact_value(&mut value);
+            serde_json::to_string(&value)
+        }
+        None => serde_json::to_string(value),
+    }
+    .expect("JSON serialization failed")
+}
+
+/// Build details and capabilities printed by `waypoint version`.
+#[derive(serde::Serialize)]
+pub struct VersionInfo {
+    /// Crate version.
+    pub version: &'static str,
+    /// Short git commit the binary was built from.
+    pub git_hash: &'static str,
+    /// Build date (UTC).
+    pub build_date: &'static str,
+    /// See [`OUTPUT_SCHEMA_VERSION`].
+    pub output_schema_version: u32,
+    /// History-table layout version this build writes.
+    pub history_schema_version: i32,
+    /// Cargo features compiled in.
+    pub features: Vec<&'static str>,
+    /// `[database] dialect` values this build can connect to.
+    pub dialects: Vec<&'static str>,
+    /// Subcommand names.
+    pub commands: Vec<String>,
+}
+
+/// Print `waypoint version` output.
+pub fn print_version(info: &VersionInfo) {
+    println!(
+        "waypoint {} ({} {})",
+        info.version, info.git_hash, info.build_date
+    );
+    println!("Features: {}", info.features.join(", "));
+    println!("Dialects: {}", info.dialects.join(", "));
+}
 
 /// Format migration info as a colored table.
 pub fn print_info_table(infos: &[MigrationInfo]) {
@@ -18,19 +95,26 @@ pub fn print_info_table(infos: &[MigrationInfo]) {
         return;
     }
 
+    // A Schema column only when `[migrations] schemas` lists several.
+    let show_schema = infos.iter().any(|i| i.schema.is_some());
+    let mut header = vec![
+        Cell::new("Version"),
+        Cell::new("Description"),
+        Cell::new("Type"),
+        Cell::new("State"),
+        Cell::new("Installed On"),
+        Cell::new("Execution Time"),
+    ];
+    if show_schema {
+        header.insert(0, Cell::new("Schema"));
+    }
+
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
         .apply_modifier(UTF8_ROUND_CORNERS)
         .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec![
-            Cell::new("Version"),
-            Cell::new("Description"),
-            Cell::new("Type"),
-            Cell::new("State"),
-            Cell::new("Installed On"),
-            Cell::new("Execution Time"),
-        ]);
+        .set_header(header);
 
     for info in infos {
         let version = info.version.as_deref().unwrap_or("");
@@ -45,17 +129,93 @@ pub fn print_info_table(infos: &[MigrationInfo]) {
 
         let state_str = format_state(&info.state);
 
-        table.add_row(vec![
+        let mut row = vec![
             Cell::new(version),
             Cell::new(&info.description),
             Cell::new(&info.migration_type),
             Cell::new(&state_str),
             Cell::new(&installed_on),
             Cell::new(&exec_time),
-        ]);
+        ];
+        if show_schema {
+            row.insert(0, Cell::new(info.schema.as_deref().unwrap_or("")));
+        }
+        table.add_row(row);
+    }
+
+    println!("{table}");
+}
+
+/// Print `info --history`: one row per event, grouped by version.
+pub fn print_history_timeline(timeline: &waypoint_core::HistoryTimeline) {
+    if timeline.versions.is_empty() {
+        println!("{}", "No history recorded.".yellow());
+        return;
     }
 
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Version"),
+            Cell::new("When"),
+            Cell::new("Event"),
+            Cell::new("Script"),
+            Cell::new("By"),
+            Cell::new("Detail"),
+        ]);
+
+    for version in &timeline.versions {
+        for (i, event) in version.events.iter().enumerate() {
+            let label = if i > 0 {
+                String::new()
+            } else {
+                version
+                    .version
+                    .clone()
+                    .unwrap_or_else(|| "(repeatable)".to_string())
+            };
+            let detail = event.detail.clone().unwrap_or_else(|| {
+                event
+                    .execution_time_ms
+                    .map(|t| format!("{}ms", t))
+                    .unwrap_or_default()
+            });
+            table.add_row(vec![
+                Cell::new(label),
+                Cell::new(event.at.format("%Y-%m-%d %H:%M:%S").to_string()),
+                Cell::new(format_timeline_event(event.kind)),
+                Cell::new(&event.script),
+                Cell::new(&event.by),
+                Cell::new(detail),
+            ]);
+        }
+    }
     println!("{table}");
+
+    if timeline.undo_stack.is_empty() {
+        println!("{}", "Undo stack: empty".dimmed());
+    } else {
+        println!(
+            "{} {}",
+            "Undo stack (next first):".dimmed(),
+            timeline.undo_stack.join(" → ")
+        );
+    }
+}
+
+fn format_timeline_event(kind: waypoint_core::TimelineEventKind) -> String {
+    use waypoint_core::TimelineEventKind;
+    let label = kind.to_string();
+    match kind {
+        TimelineEventKind::Applied | TimelineEventKind::Reconciled => label.green().to_string(),
+        TimelineEventKind::Failed => label.red().bold().to_string(),
+        TimelineEventKind::Undone => label.magenta().to_string(),
+        TimelineEventKind::Baseline => label.blue().to_string(),
+        TimelineEventKind::ChecksumUpdated => label.cyan().to_string(),
+    }
 }
 
 /// Return a colored string representation of a migration state.
@@ -92,6 +252,10 @@ pub fn print_migrate_summary(report: &waypoint_core::MigrateReport) {
             "{}",
             "Schema is up to date. No migration necessary.".green()
         );
+        if let Some(fp) = &report.schema_fingerprint {
+            println!("{}", format!("Schema fingerprint: {}", fp).dimmed());
+        }
+        print_report_warnings(&report.warnings);
         return;
     }
 
@@ -107,14 +271,72 @@ pub fn print_migrate_summary(report: &waypoint_core::MigrateReport) {
 
     for detail in &report.details {
         let version = detail.version.as_deref().unwrap_or("(repeatable)");
+        let rows = if detail.rows_affected > 0 {
+            format!(", {} rows", detail.rows_affected)
+        } else {
+            String::new()
+        };
+        let schema = detail
+            .schema
+            .as_deref()
+            .map(|s| format!("[{}] ", s))
+            .unwrap_or_default();
         println!(
-            "  {} {} — {} ({}ms)",
+            "  {} {}{} — {} ({}ms{})",
             "→".green(),
+            schema,
             version,
             detail.description,
-            detail.execution_time_ms
+            detail.execution_time_ms,
+            rows
+        );
+    }
+
+    if !report.dependencies_added.is_empty() {
+        println!(
+            "  {} {}",
+            "+".cyan(),
+            format!(
+                "Included dependencies above target: {}",
+                report.dependencies_added.join(", ")
+            )
+            .cyan()
+        );
+    }
+
+    for m in &report.maintenance {
+        let action = match m.action {
+            MaintenanceAction::Analyze => "analyzed",
+            MaintenanceAction::VacuumAnalyze => "vacuumed and analyzed",
+            MaintenanceAction::SkippedSmall => continue,
+        };
+        println!(
+            "{}",
+            format!("  {} {} ({}ms)", action, m.table, m.execution_time_ms).dimmed()
         );
     }
+
+    if let Some(fp) = &report.schema_fingerprint {
+        println!("{}", format!("Schema fingerprint: {}", fp).dimmed());
+    }
+
+    print_report_warnings(&report.warnings);
+}
+
+/// Print the warnings collected on a migrate, undo or multi-database report,
+/// so they aren't lost in the log output above the summary.
+fn print_report_warnings(warnings: &[waypoint_core::Warning]) {
+    if warnings.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "{}",
+        format!("Warnings ({}):", warnings.len()).yellow().bold()
+    );
+    for w in warnings {
+        println!("  {} {}", "!".yellow(), w);
+    }
 }
 
 /// Print a validate report.
@@ -150,12 +372,17 @@ pub fn print_repair_result(report: &waypoint_core::RepairReport) {
     }
 }
 
-/// Print an undo report summary.
-pub fn print_undo_summary(report: &waypoint_core::UndoReport) {
-    if report.migrations_undone == 0 {
+/// Print the result of `audit history`, grouped by finding category.
+pub fn print_history_audit(report: &waypoint_core::HistoryAuditReport) {
+    if report.findings.is_empty() {
         println!(
             "{}",
-            "No migrations to undo. Schema is already at its earliest state.".green()
+            format!(
+                "Checked {} history row(s) against {} file(s). No findings.",
+                report.rows_checked, report.files_checked
+            )
+            .green()
+            .bold()
         );
         return;
     }
@@ -163,116 +390,581 @@ pub fn print_undo_summary(report: &waypoint_core::UndoReport) {
     println!(
         "{}",
         format!(
-            "Successfully undone {} migration(s) (execution time {}ms)",
-            report.migrations_undone, report.total_time_ms
+            "Checked {} history row(s) against {} file(s): {} error(s), {} warning(s)",
+            report.rows_checked, report.files_checked, report.error_count, report.warning_count
         )
-        .green()
         .bold()
     );
+    for (kind, count) in &report.summary {
+        println!();
+        println!("{} ({})", kind.to_string().bold(), count);
+        for finding in report.findings.iter().filter(|f| f.kind == *kind) {
+            let rank = format!("[#{}]", finding.installed_rank);
+            let rank = match finding.severity {
+                waypoint_core::FindingSeverity::Error => rank.red().bold().to_string(),
+                waypoint_core::FindingSeverity::Warning => rank.yellow().bold().to_string(),
+            };
+            println!("  {} {} {}", rank, finding.script, finding.message);
+        }
+    }
+}
 
-    for detail in &report.details {
+/// Print the result of `checksums update`.
+pub fn print_checksum_update_report(report: &waypoint_core::ChecksumUpdateReport) {
+    let verb = if report.dry_run {
+        "Would update"
+    } else {
+        "Updated"
+    };
+    if report.updated.is_empty() {
         println!(
-            "  {} {} — {} ({}ms)",
-            "←".magenta(),
-            detail.version,
-            detail.description,
-            detail.execution_time_ms
+            "{}",
+            format!(
+                "No checksum changes needed ({} already match).",
+                report.unchanged
+            )
+            .green()
+        );
+    } else {
+        println!(
+            "{}",
+            format!(
+                "{} {} checksum(s): {}",
+                verb,
+                report.updated.len(),
+                report.reason
+            )
+            .green()
+            .bold()
+        );
+        for c in &report.updated {
+            println!(
+                "  {} {} {} ({} -> {})",
+                "→".green(),
+                c.version,
+                c.script,
+                c.old_checksum.map_or("none".to_string(), |v| v.to_string()),
+                c.new_checksum
+            );
+        }
+    }
+    if !report.missing.is_empty() {
+        println!(
+            "  {} no file on disk, left as is: {}",
+            "!".yellow(),
+            report.missing.join(", ")
         );
     }
 }
 
-/// Print items dropped by clean.
-pub fn print_clean_result(dropped: &[String]) {
-    if dropped.is_empty() {
-        println!("{}", "Nothing to clean.".green());
+/// Print the result of `history upgrade`.
+pub fn print_history_upgrade(report: &waypoint_core::HistoryUpgrade) {
+    if report.is_current() {
+        println!(
+            "{}",
+            format!(
+                "History table {} is up to date (version {}).",
+                report.table, report.to_version
+            )
+            .green()
+        );
         return;
     }
-
-    println!(
-        "{}",
-        format!("Successfully cleaned. Dropped {} object(s):", dropped.len())
+    let from = report
+        .from_version
+        .map_or("an untracked layout".to_string(), |v| {
+            format!("version {}", v)
+        });
+    if report.applied {
+        println!(
+            "{}",
+            format!(
+                "Upgraded history table {} from {} to version {}.",
+                report.table, from, report.to_version
+            )
             .green()
             .bold()
-    );
-    for item in dropped {
-        println!("  {} {}", "✗".red(), item);
+        );
+        for column in &report.columns_added {
+            println!("  {} added column {}", "→".green(), column);
+        }
+    } else {
+        println!(
+            "{}",
+            format!(
+                "History table {} is at {}; upgrading to version {} would run:",
+                report.table, from, report.to_version
+            )
+            .yellow()
+            .bold()
+        );
+        for sql in &report.statements {
+            println!("{};", sql);
+        }
     }
 }
 
-/// Print lint report with colored severity.
-pub fn print_lint_report(report: &waypoint_core::LintReport) {
-    if report.issues.is_empty() {
+/// Print one notification received by `waypoint listen`.
+pub fn print_listen_event(event: &waypoint_core::ListenEvent) {
+    let time = event.received_at.format("%H:%M:%S").to_string();
+    let Some(n) = &event.notification else {
         println!(
-            "{}",
-            format!("Checked {} file(s). No issues found.", report.files_checked)
-                .green()
-                .bold()
+            "{} {} {}",
+            time.dimmed(),
+            event.channel.cyan(),
+            event.payload
         );
         return;
-    }
-
+    };
     println!(
-        "{}",
-        format!(
-            "Checked {} file(s): {} error(s), {} warning(s), {} info",
-            report.files_checked, report.error_count, report.warning_count, report.info_count
-        )
-        .bold()
+        "{} {} {} migration(s) applied to {}.{}",
+        time.dimmed(),
+        event.channel.cyan(),
+        n.migrations_applied.to_string().bold(),
+        n.database,
+        n.schema
     );
-    println!();
+    for m in &n.migrations {
+        println!(
+            "  {} {} — {}",
+            "→".green(),
+            m.version.as_deref().unwrap_or("(repeatable)"),
+            m.description
+        );
+    }
+    if n.truncated {
+        println!(
+            "  {}",
+            "(list truncated to fit the NOTIFY payload)".dimmed()
+        );
+    }
+}
 
-    for issue in &report.issues {
-        let severity = match issue.severity {
-            waypoint_core::commands::lint::LintSeverity::Error => {
-                format!("[{}]", issue.rule_id).red().bold().to_string()
-            }
-            waypoint_core::commands::lint::LintSeverity::Warning => {
-                format!("[{}]", issue.rule_id).yellow().bold().to_string()
-            }
-            waypoint_core::commands::lint::LintSeverity::Info => {
-                format!("[{}]", issue.rule_id).blue().to_string()
-            }
-        };
+/// Print the rows `import-flyway` read and what happened to each.
+pub fn print_import_flyway_report(report: &waypoint_core::ImportFlywayReport) {
+    use waypoint_core::commands::import_flyway::ImportStatus;
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Rank"),
+            Cell::new("Version"),
+            Cell::new("Script"),
+            Cell::new("Type"),
+            Cell::new("Status"),
+        ]);
 
-        let line_info = issue.line.map(|l| format!(":{}", l)).unwrap_or_default();
+    for entry in &report.entries {
+        let status = match entry.status {
+            ImportStatus::Imported => "Imported".green(),
+            ImportStatus::ChecksumMismatch => "Checksum mismatch".red(),
+            ImportStatus::MissingFile => "No local file".yellow(),
+            ImportStatus::Skipped => "Skipped".dimmed(),
+        };
+        let kind = match &entry.waypoint_type {
+            Some(t) if *t != entry.flyway_type => format!("{} -> {}", entry.flyway_type, t),
+            _ => entry.flyway_type.clone(),
+        };
+        table.add_row(vec![
+            Cell::new(entry.installed_rank),
+            Cell::new(entry.version.as_deref().unwrap_or("")),
+            Cell::new(&entry.script),
+            Cell::new(kind),
+            Cell::new(status.to_string()),
+        ]);
+    }
+    println!("{table}");
 
+    let summary = if report.blocked {
+        format!(
+            "{} checksum mismatch(es); nothing imported into {}.",
+            report.mismatches, report.target
+        )
+        .red()
+        .bold()
+    } else if report.dry_run {
+        format!(
+            "{} row(s) would be imported from {} into {}.",
+            report.imported, report.source, report.target
+        )
+        .green()
+        .bold()
+    } else {
+        format!(
+            "Imported {} row(s) from {} into {}.",
+            report.imported, report.source, report.target
+        )
+        .green()
+        .bold()
+    };
+    println!("{}", summary);
+    if report
+        .entries
+        .iter()
+        .any(|e| e.status == ImportStatus::MissingFile)
+    {
         println!(
-            "  {} {}{} {}",
-            severity, issue.script, line_info, issue.message
+            "{}",
+            "Rows without a local file show as missing in `waypoint info`.".dimmed()
         );
-
-        if let Some(ref suggestion) = issue.suggestion {
-            println!("    {} {}", "→".dimmed(), suggestion.dimmed());
-        }
     }
 }
 
-/// Print diff report.
-pub fn print_diff_report(report: &waypoint_core::DiffReport) {
-    if !report.has_changes {
-        println!("{}", "No schema differences detected.".green().bold());
+/// Print the result of `seed`.
+pub fn print_seed_report(report: &waypoint_core::SeedReport) {
+    use waypoint_core::SeedStatus;
+
+    if report.seeds.is_empty() {
+        println!("{}", "No seed files found.".yellow());
         return;
     }
 
-    println!(
-        "{}",
-        format!("Found {} schema difference(s):", report.diffs.len())
-            .yellow()
-            .bold()
-    );
-    println!();
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Seed"),
+            Cell::new("Status"),
+            Cell::new("Time (ms)"),
+            Cell::new("Reason"),
+        ]);
 
-    for diff in &report.diffs {
-        let line = format!("{}", diff);
-        if line.starts_with('+') {
-            println!("  {}", line.green());
-        } else if line.starts_with('-') {
-            println!("  {}", line.red());
+    for seed in &report.seeds {
+        let status = match seed.status {
+            SeedStatus::Applied if report.dry_run => "Pending".cyan(),
+            SeedStatus::Reapplied if report.dry_run => "Changed".cyan(),
+            SeedStatus::Applied => "Applied".green(),
+            SeedStatus::Reapplied => "Reapplied".green(),
+            SeedStatus::Unchanged => "Unchanged".normal(),
+            SeedStatus::SkippedEnvironment => "Skipped (env)".dimmed(),
+            SeedStatus::SkippedGuard => "Skipped (guard)".dimmed(),
+        };
+        let time = if matches!(seed.status, SeedStatus::Applied | SeedStatus::Reapplied)
+            && !report.dry_run
+        {
+            seed.execution_time_ms.to_string()
         } else {
-            println!("  {}", line.yellow());
+            String::new()
+        };
+        table.add_row(vec![
+            Cell::new(&seed.script),
+            Cell::new(status.to_string()),
+            Cell::new(time),
+            Cell::new(seed.reason.as_deref().unwrap_or("")),
+        ]);
+    }
+    println!("{table}");
+
+    let summary = if report.dry_run {
+        format!("{} seed(s) would be applied.", report.applied)
+    } else {
+        format!("Applied {} seed(s).", report.applied)
+    };
+    println!("{}", summary.green().bold());
+}
+
+/// Print the result of `reconcile`.
+pub fn print_reconcile_report(report: &waypoint_core::ReconcileReport) {
+    use waypoint_core::commands::reconcile::ReconcileStatus;
+
+    if report.migrations.is_empty() {
+        println!("{}", "No pending migrations to reconcile.".green());
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Version"),
+            Cell::new("Description"),
+            Cell::new("Status"),
+            Cell::new("Failed checks"),
+        ]);
+
+    for entry in &report.migrations {
+        let status = match entry.status {
+            ReconcileStatus::Reconciled => "Reconciled".green(),
+            ReconcileStatus::Partial => "Partial".yellow(),
+            ReconcileStatus::NotApplied => "Not applied".normal(),
+            ReconcileStatus::Unverifiable => "Unverifiable".dimmed(),
+            ReconcileStatus::Unverified => "Unverified".yellow(),
+        };
+        let failed: Vec<String> = entry
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.expression.clone())
+            .chain(entry.unverified.iter().map(|s| format!("unchecked: {}", s)))
+            .collect();
+        table.add_row(vec![
+            Cell::new(&entry.version),
+            Cell::new(&entry.description),
+            Cell::new(status.to_string()),
+            Cell::new(failed.join("\n")),
+        ]);
+    }
+    println!("{table}");
+
+    let summary = if report.dry_run {
+        format!(
+            "{} migration(s) would be recorded as reconciled.",
+            report.reconciled
+        )
+    } else {
+        format!("Recorded {} migration(s) as reconciled.", report.reconciled)
+    };
+    println!("{}", summary.green().bold());
+    if report
+        .migrations
+        .iter()
+        .any(|m| m.status == ReconcileStatus::Partial)
+    {
+        println!(
+            "  {} Partially applied migrations were left pending; finish or revert them by hand.",
+            "!".yellow()
+        );
+    }
+    if report
+        .migrations
+        .iter()
+        .any(|m| m.status == ReconcileStatus::Unverified)
+    {
+        println!(
+            "  {} Unverified migrations have statements no check covers; add -- waypoint:ensure directives for them.",
+            "!".yellow()
+        );
+    }
+}
+
+/// Print the files scaffolded by `new`.
+pub fn print_new_migration(report: &waypoint_core::NewMigrationReport) {
+    if report.dry_run {
+        for f in &report.files {
+            println!("{}", format!("Would write {}:", f.path).yellow().bold());
+            print!("{}", f.contents);
+        }
+        return;
+    }
+    for f in &report.files {
+        println!("{}", format!("Created {}", f.path).green());
+    }
+    if !report.unfilled.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "Fill in {} before applying (or pass --set)",
+                report
+                    .unfilled
+                    .iter()
+                    .map(|p| format!("{{{{{}}}}}", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+            .yellow()
+        );
+    }
+}
+
+/// Print the files written by `init`.
+pub fn print_init_report(report: &waypoint_core::InitReport) {
+    let verb = if report.dry_run {
+        "Would create"
+    } else {
+        "Created"
+    };
+    for path in &report.files {
+        println!("{}", format!("{} {}", verb, path).green());
+    }
+    for path in &report.skipped {
+        println!(
+            "{}",
+            format!("Skipped {} (already exists; --force to overwrite)", path).yellow()
+        );
+    }
+}
+
+/// Print an undo report summary.
+pub fn print_undo_summary(report: &waypoint_core::UndoReport) {
+    if report.migrations_undone == 0 {
+        println!(
+            "{}",
+            "No migrations to undo. Schema is already at its earliest state.".green()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Successfully undone {} migration(s) (execution time {}ms)",
+            report.migrations_undone, report.total_time_ms
+        )
+        .green()
+        .bold()
+    );
+
+    for detail in &report.details {
+        println!(
+            "  {} {} — {} ({}ms)",
+            "←".magenta(),
+            detail.version,
+            detail.description,
+            detail.execution_time_ms
+        );
+    }
+
+    print_report_warnings(&report.warnings);
+}
+
+/// Print what `undo --dry-run` would do.
+pub fn print_undo_plan(plan: &waypoint_core::UndoPlan) {
+    if plan.steps.is_empty() {
+        println!(
+            "{}",
+            "No migrations to undo. Schema is already at its earliest state.".green()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        format!("Would undo {} migration(s):", plan.steps.len())
+            .yellow()
+            .bold()
+    );
+    for step in &plan.steps {
+        let source = if step.auto_reversal {
+            "stored auto-reversal".to_string()
+        } else {
+            step.script.clone()
+        };
+        println!();
+        println!(
+            "  {} {} — {} ({})",
+            "←".magenta(),
+            step.version,
+            step.description,
+            source.dimmed()
+        );
+        for line in step.sql.trim_end().lines() {
+            println!("    {}", line);
         }
     }
 
+    print_report_warnings(&plan.warnings);
+}
+
+/// Print items dropped by clean.
+pub fn print_clean_result(dropped: &[String]) {
+    if dropped.is_empty() {
+        println!("{}", "Nothing to clean.".green());
+        return;
+    }
+
+    println!(
+        "{}",
+        format!("Successfully cleaned. Dropped {} object(s):", dropped.len())
+            .green()
+            .bold()
+    );
+    for item in dropped {
+        println!("  {} {}", "✗".red(), item);
+    }
+}
+
+/// Print lint report with colored severity.
+pub fn print_lint_report(report: &waypoint_core::LintReport) {
+    if report.issues.is_empty() {
+        println!(
+            "{}",
+            format!("Checked {} file(s). No issues found.", report.files_checked)
+                .green()
+                .bold()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Checked {} file(s): {} error(s), {} warning(s), {} info",
+            report.files_checked, report.error_count, report.warning_count, report.info_count
+        )
+        .bold()
+    );
+    println!();
+
+    for issue in &report.issues {
+        let severity = match issue.severity {
+            waypoint_core::commands::lint::LintSeverity::Error => {
+                format!("[{}]", issue.rule_id).red().bold().to_string()
+            }
+            waypoint_core::commands::lint::LintSeverity::Warning => {
+                format!("[{}]", issue.rule_id).yellow().bold().to_string()
+            }
+            waypoint_core::commands::lint::LintSeverity::Info => {
+                format!("[{}]", issue.rule_id).blue().to_string()
+            }
+        };
+
+        let line_info = match (issue.line, issue.column) {
+            (Some(l), Some(c)) => format!(":{}:{}", l, c),
+            (Some(l), None) => format!(":{}", l),
+            _ => String::new(),
+        };
+
+        println!(
+            "  {} {}{} {}",
+            severity, issue.script, line_info, issue.message
+        );
+
+        if let Some(ref suggestion) = issue.suggestion {
+            println!("    {} {}", "→".dimmed(), suggestion.dimmed());
+        }
+    }
+}
+
+/// Note that a report is partial because some catalog queries timed out.
+fn print_incomplete(incomplete: &[String]) {
+    if incomplete.is_empty() {
+        return;
+    }
+    println!(
+        "{}",
+        format!(
+            "Partial result: introspection timed out for {} (raise --introspection-timeout)",
+            incomplete.join(", ")
+        )
+        .yellow()
+    );
+}
+
+/// Print diff report.
+pub fn print_diff_report(report: &waypoint_core::DiffReport) {
+    print_incomplete(&report.incomplete);
+    if !report.has_changes {
+        println!("{}", "No schema differences detected.".green().bold());
+        return;
+    }
+
+    println!(
+        "{}",
+        format!("Found {} schema difference(s):", report.diffs.len())
+            .yellow()
+            .bold()
+    );
+    println!();
+
+    print_diff_lines(&report.diffs);
+
     if !report.generated_sql.is_empty() {
         println!();
         println!("{}", "Generated SQL:".bold());
@@ -280,8 +972,130 @@ pub fn print_diff_report(report: &waypoint_core::DiffReport) {
     }
 }
 
+/// Print the changes between two stored snapshots.
+pub fn print_snapshot_diff(report: &waypoint_core::SnapshotDiffReport) {
+    print_incomplete(&report.incomplete);
+    if !report.has_changes {
+        println!(
+            "{}",
+            format!(
+                "No schema differences between {} and {}.",
+                report.from, report.to
+            )
+            .green()
+            .bold()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{} → {}: {} schema difference(s):",
+            report.from,
+            report.to,
+            report.diffs.len()
+        )
+        .yellow()
+        .bold()
+    );
+    println!();
+
+    print_diff_lines(&report.diffs);
+
+    if !report.generated_sql.is_empty() {
+        println!();
+        println!("{}", "Generated SQL:".bold());
+        println!("{}", report.generated_sql.dimmed());
+    }
+}
+
+/// Print where objects matching a `find` pattern exist.
+pub fn print_find_report(report: &waypoint_core::FindReport) {
+    for source in &report.sources {
+        let label = match &source.schema {
+            Some(schema) => format!("{} ({})", source.source, schema),
+            None => format!("{} (snapshot)", source.source),
+        };
+        print_incomplete(&source.incomplete);
+        if source.objects.is_empty() {
+            println!("{} {}", label.bold(), "no matches".dimmed());
+            continue;
+        }
+        println!("{}", label.bold());
+        for object in &source.objects {
+            let on_table = object
+                .table
+                .as_ref()
+                .map(|t| format!(" on {}", t))
+                .unwrap_or_default();
+            println!(
+                "  {:<10} {}{}",
+                object.object_type.to_string().cyan(),
+                object.name,
+                on_table.dimmed()
+            );
+        }
+    }
+    println!();
+    let holders = report
+        .sources
+        .iter()
+        .filter(|s| !s.objects.is_empty())
+        .count();
+    let summary = format!(
+        "{} match(es) for '{}' in {} of {} source(s)",
+        report.total_matches,
+        report.pattern,
+        holders,
+        report.sources.len()
+    );
+    if report.total_matches == 0 {
+        println!("{}", summary.yellow());
+    } else {
+        println!("{}", summary.green().bold());
+    }
+}
+
+/// Print schema diffs one per line, colored by kind (+ added, - removed, ~ changed).
+fn print_diff_lines(diffs: &[waypoint_core::schema::SchemaDiff]) {
+    for diff in diffs {
+        let line = format!("{}", diff);
+        if line.starts_with('+') {
+            println!("  {}", line.green());
+        } else if line.starts_with('-') {
+            println!("  {}", line.red());
+        } else {
+            println!("  {}", line.yellow());
+        }
+    }
+}
+
+/// Print the schema changes made by a migrate run (dev mode).
+pub fn print_schema_changes(diffs: &[waypoint_core::schema::SchemaDiff]) {
+    if diffs.is_empty() {
+        return;
+    }
+    println!();
+    println!("{}", "Schema changes:".bold());
+    print_diff_lines(diffs);
+}
+
 /// Print drift report.
 pub fn print_drift_report(report: &waypoint_core::DriftReport) {
+    print_incomplete(&report.incomplete);
+    if let Some(baseline) = &report.baseline {
+        println!(
+            "{}",
+            format!(
+                "Compared with baseline stored {} (fingerprint {}, live {})",
+                baseline.recorded_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                baseline.expected.get(..12).unwrap_or(&baseline.expected),
+                baseline.actual.get(..12).unwrap_or(&baseline.actual)
+            )
+            .dimmed()
+        );
+    }
     if !report.has_drift {
         println!(
             "{}",
@@ -309,6 +1123,7 @@ pub fn print_drift_report(report: &waypoint_core::DriftReport) {
             waypoint_core::commands::drift::DriftType::ExtraObject => "+".green(),
             waypoint_core::commands::drift::DriftType::MissingObject => "-".red(),
             waypoint_core::commands::drift::DriftType::ModifiedObject => "~".yellow(),
+            waypoint_core::commands::drift::DriftType::RenamedObject => ">".cyan(),
         };
         println!("  {} {} — {}", icon, drift.object, drift.detail.dimmed());
     }
@@ -316,10 +1131,50 @@ pub fn print_drift_report(report: &waypoint_core::DriftReport) {
     println!();
     println!(
         "{}",
-        "Hint: Run 'waypoint diff' to generate a migration that resolves this drift.".dimmed()
+        "Hint: Run 'waypoint drift --fix-file <PATH>' to write a migration that records this drift."
+            .dimmed()
     );
 }
 
+/// Print what changed between two JSON reports.
+pub fn print_report_diff(diff: &waypoint_core::commands::report_diff::ReportDiff) {
+    use waypoint_core::commands::report_diff::ChangeKind;
+
+    if !diff.has_changes() {
+        println!(
+            "{}",
+            format!("No changes between the two {} reports.", diff.kind)
+                .green()
+                .bold()
+        );
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "Changes between {} reports: {} item(s)",
+            diff.kind,
+            diff.changes.len()
+        )
+        .bold()
+    );
+    for total in &diff.totals {
+        println!("  {}: {} → {}", total.name, total.before, total.after);
+    }
+    if !diff.changes.is_empty() {
+        println!();
+    }
+    for change in &diff.changes {
+        let icon = match change.change {
+            ChangeKind::Added => "+".green(),
+            ChangeKind::Removed => "-".red(),
+            ChangeKind::Changed => "~".yellow(),
+        };
+        println!("  {} {} — {}", icon, change.item, change.detail.dimmed());
+    }
+}
+
 /// Print snapshot report.
 pub fn print_snapshot_report(report: &waypoint_core::SnapshotReport) {
     println!(
@@ -334,52 +1189,330 @@ pub fn print_snapshot_report(report: &waypoint_core::SnapshotReport) {
     println!("  {} {}", "→".green(), report.snapshot_path);
 }
 
-/// Print restore report.
-pub fn print_restore_report(report: &waypoint_core::RestoreReport) {
+/// Print restore report.
+pub fn print_restore_report(report: &waypoint_core::RestoreReport) {
+    println!(
+        "{}",
+        format!(
+            "Restored from snapshot '{}' ({} objects restored)",
+            report.snapshot_id, report.objects_restored
+        )
+        .green()
+        .bold()
+    );
+}
+
+/// Print list of available snapshots.
+pub fn print_snapshot_list(snapshots: &[waypoint_core::commands::snapshot::SnapshotInfo]) {
+    if snapshots.is_empty() {
+        println!("{}", "No snapshots found.".yellow());
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("ID"),
+            Cell::new("Created"),
+            Cell::new("Size"),
+        ]);
+
+    for s in snapshots {
+        let size = if s.size_bytes > 1024 * 1024 {
+            format!("{:.1}MB", s.size_bytes as f64 / (1024.0 * 1024.0))
+        } else if s.size_bytes > 1024 {
+            format!("{:.1}KB", s.size_bytes as f64 / 1024.0)
+        } else {
+            format!("{}B", s.size_bytes)
+        };
+        table.add_row(vec![
+            Cell::new(&s.id),
+            Cell::new(&s.created),
+            Cell::new(&size),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+/// Print a summary of an export.
+pub fn print_export_report(report: &waypoint_core::ExportReport) {
+    println!(
+        "{}",
+        format!("Exported {} row(s) from {}", report.rows, report.table)
+            .green()
+            .bold()
+    );
+    for a in &report.anonymized {
+        println!("  {} {} ({})", "~".cyan(), a.column, a.strategy);
+    }
+}
+
+/// Print the result of a squash.
+pub fn print_squash_report(report: &waypoint_core::SquashReport) {
+    println!(
+        "{}",
+        format!(
+            "Squashed {} migration(s) through version {} into {}",
+            report.squashed.len(),
+            report.through,
+            report.output.display()
+        )
+        .green()
+        .bold()
+    );
+    println!(
+        "  {} {} file(s) moved to {}",
+        "~".cyan(),
+        report.archived.len(),
+        report.archive_dir.display()
+    );
+    for script in &report.data_migrations {
+        println!(
+            "  {} {} changes data; its rows are not in the baseline",
+            "!".yellow(),
+            script
+        );
+    }
+    for w in &report.warnings {
+        println!("  {} {}", "!".yellow(), w);
+    }
+    println!();
+    println!("{}", "Reconciling existing databases:".bold());
+    for line in &report.instructions {
+        println!("  - {}", line);
+    }
+}
+
+/// Print the summary of a generated enum change.
+pub fn print_enum_assist_report(report: &waypoint_core::EnumAssistReport) {
+    println!(
+        "{}",
+        format!(
+            "Enum {}: [{}] -> [{}]",
+            report.type_name,
+            report.old_values.join(", "),
+            report.new_values.join(", ")
+        )
+        .green()
+        .bold()
+    );
+    for c in &report.columns {
+        println!("  {} {}.{}.{}", "~".cyan(), c.schema, c.table, c.column);
+    }
+    println!(
+        "  {} migration(s): new type, backfill, swap. Deploy code that stops using the old labels before the swap.",
+        report.migrations.len()
+    );
+}
+
+/// Print the summary of a split `ADD COLUMN ... DEFAULT`.
+pub fn print_default_assist_report(report: &waypoint_core::DefaultAssistReport) {
+    println!(
+        "{}",
+        format!(
+            "{}.{}: {} migration(s) instead of one table rewrite",
+            report.change.table,
+            report.change.column,
+            report.migrations.len()
+        )
+        .green()
+        .bold()
+    );
+    println!("  {} {}", "→".dimmed(), report.change.reason.dimmed());
+}
+
+/// Print the summary of a split `ADD CONSTRAINT ... UNIQUE`.
+pub fn print_unique_assist_report(report: &waypoint_core::UniqueAssistReport) {
+    let c = &report.constraint;
+    println!(
+        "{}",
+        format!("{} UNIQUE ({}) on {}", c.name, c.columns, c.table)
+            .green()
+            .bold()
+    );
+    println!(
+        "  {} {}",
+        "→".dimmed(),
+        "concurrent index build (no transaction), then ADD CONSTRAINT ... USING INDEX".dimmed()
+    );
+}
+
+/// Print the summary of a split `ADD CONSTRAINT ... FOREIGN KEY`.
+pub fn print_fk_assist_report(report: &waypoint_core::ForeignKeyAssistReport) {
+    let fk = &report.foreign_key;
+    println!(
+        "{}",
+        format!(
+            "{} FOREIGN KEY ({}) on {} REFERENCES {}",
+            fk.name, fk.columns, fk.table, fk.references
+        )
+        .green()
+        .bold()
+    );
+    println!(
+        "  {} {}",
+        "→".dimmed(),
+        "ADD ... NOT VALID, then VALIDATE CONSTRAINT in a low-priority migration".dimmed()
+    );
+}
+
+/// Print a single scheduled run after it was created or cancelled.
+pub fn print_scheduled_run(run: &waypoint_core::ScheduledRun) {
+    println!(
+        "{}",
+        format!(
+            "Scheduled run {} is {} (run at {}, expires {})",
+            run.id, run.status, run.run_at, run.expires_at
+        )
+        .green()
+        .bold()
+    );
+}
+
+/// Print scheduled runs as a table.
+pub fn print_schedule_list(runs: &[waypoint_core::ScheduledRun]) {
+    if runs.is_empty() {
+        println!("{}", "No scheduled runs.".yellow());
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("ID"),
+            Cell::new("Run At"),
+            Cell::new("Expires"),
+            Cell::new("Target"),
+            Cell::new("Status"),
+            Cell::new("Message"),
+        ]);
+
+    for r in runs {
+        table.add_row(vec![
+            Cell::new(r.id),
+            Cell::new(&r.run_at),
+            Cell::new(&r.expires_at),
+            Cell::new(r.target_version.as_deref().unwrap_or("latest")),
+            Cell::new(&r.status),
+            Cell::new(r.message.as_deref().unwrap_or("")),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+/// Print the outcome of `run-scheduled`.
+pub fn print_run_scheduled_report(report: &waypoint_core::RunScheduledReport) {
+    if report.reclaimed > 0 {
+        println!(
+            "{}",
+            format!(
+                "Released {} scheduled run(s) whose claim expired",
+                report.reclaimed
+            )
+            .yellow()
+        );
+    }
+    if report.expired > 0 {
+        println!(
+            "{}",
+            format!("Expired {} overdue scheduled run(s)", report.expired).yellow()
+        );
+    }
+    match (&report.run, &report.migrate) {
+        (Some(run), Some(migrate)) => {
+            println!("{}", format!("Ran scheduled run {}", run.id).bold());
+            print_migrate_summary(migrate);
+        }
+        _ => println!("{}", "No scheduled run is due.".dimmed()),
+    }
+}
+
+/// Print the checks `verify-receipt` ran.
+pub fn print_receipt_verification(report: &waypoint_core::receipt::ReceiptVerification) {
+    let mark = |ok: bool| if ok { "ok".green() } else { "FAILED".red() };
+    println!("{}", format!("Receipt {}", report.run_id).bold());
+    if let Some(ref key_id) = report.key_id {
+        println!("  Key:       {}", key_id);
+    }
+    println!("  Signature: {}", mark(report.signature_valid));
+    if let Some(matches) = report.database_matches {
+        println!("  Database:  {}", mark(matches));
+        println!(
+            "  History:   {}",
+            mark(report.history_mismatches.is_empty())
+        );
+        for script in &report.history_mismatches {
+            println!("    {} no longer recorded as applied", script);
+        }
+    }
+    if report.valid {
+        println!("{}", "Receipt verified.".green().bold());
+    } else {
+        println!("{}", "Receipt did not verify.".red().bold());
+    }
+}
+
+/// Print a migration usage summary.
+pub fn print_metrics_summary(summary: &waypoint_core::MetricsSummary) {
+    if let Some(ref name) = summary.database {
+        println!("{}", format!("=== {} ===", name).bold());
+    }
+    let window = match summary.since {
+        Some(since) => format!("since {}", since.format("%Y-%m-%d %H:%M")),
+        None => "all history".to_string(),
+    };
+    println!("{}", format!("Migration metrics ({})", window).bold());
     println!(
-        "{}",
-        format!(
-            "Restored from snapshot '{}' ({} objects restored)",
-            report.snapshot_id, report.objects_restored
-        )
-        .green()
-        .bold()
+        "  Applied:        {} ({:.1}/week)",
+        summary.applied, summary.per_week
     );
-}
+    let failures = format!("{} ({:.1}%)", summary.failed, summary.failure_rate * 100.0);
+    if summary.failed > 0 {
+        println!("  Failed:         {}", failures.red());
+    } else {
+        println!("  Failed:         {}", failures);
+    }
+    println!("  Undone:         {}", summary.undone);
+    println!(
+        "  Avg duration:   {:.0}ms (total {}ms)",
+        summary.avg_duration_ms, summary.total_duration_ms
+    );
+    if let Some(last) = summary.last_run {
+        println!("  Last run:       {}", last.format("%Y-%m-%d %H:%M:%S"));
+    }
 
-/// Print list of available snapshots.
-pub fn print_snapshot_list(snapshots: &[waypoint_core::commands::snapshot::SnapshotInfo]) {
-    if snapshots.is_empty() {
-        println!("{}", "No snapshots found.".yellow());
+    if summary.longest.is_empty() {
         return;
     }
-
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
         .apply_modifier(UTF8_ROUND_CORNERS)
         .set_content_arrangement(ContentArrangement::Dynamic)
         .set_header(vec![
-            Cell::new("ID"),
-            Cell::new("Created"),
-            Cell::new("Size"),
+            Cell::new("Version"),
+            Cell::new("Script"),
+            Cell::new("Duration"),
+            Cell::new("Installed On"),
         ]);
-
-    for s in snapshots {
-        let size = if s.size_bytes > 1024 * 1024 {
-            format!("{:.1}MB", s.size_bytes as f64 / (1024.0 * 1024.0))
-        } else if s.size_bytes > 1024 {
-            format!("{:.1}KB", s.size_bytes as f64 / 1024.0)
-        } else {
-            format!("{}B", s.size_bytes)
-        };
+    for m in &summary.longest {
         table.add_row(vec![
-            Cell::new(&s.id),
-            Cell::new(&s.created),
-            Cell::new(&size),
+            Cell::new(m.version.as_deref().unwrap_or("R")),
+            Cell::new(&m.script),
+            Cell::new(format!("{}ms", m.execution_time_ms)),
+            Cell::new(m.installed_on.format("%Y-%m-%d %H:%M:%S").to_string()),
         ]);
     }
-
+    println!();
+    println!("{}", "Longest migrations:".bold());
     println!("{table}");
 }
 
@@ -393,6 +1526,9 @@ pub fn print_preflight_report(report: &waypoint_core::PreflightReport) {
             "Pre-flight checks FAILED.".red().bold()
         }
     );
+    if let Some(ref v) = report.server_version {
+        println!("{}", format!("Server version: {}", v).dimmed());
+    }
     println!();
 
     for check in &report.checks {
@@ -405,6 +1541,147 @@ pub fn print_preflight_report(report: &waypoint_core::PreflightReport) {
     }
 }
 
+/// Print a `guard eval` result as an indented tree of sub-expression values.
+pub fn print_guard_evaluation(evaluation: &waypoint_core::guard::GuardEvaluation) {
+    println!(
+        "{}",
+        if evaluation.result {
+            "Guard expression is true.".green().bold()
+        } else {
+            "Guard expression is false.".red().bold()
+        }
+    );
+    println!("{}", format!("Schema: {}", evaluation.schema).dimmed());
+    println!();
+
+    for step in &evaluation.steps {
+        let indent = "  ".repeat(step.depth + 1);
+        let value = match &step.value {
+            Some(waypoint_core::guard::GuardValue::Bool(true)) => "true".green(),
+            Some(waypoint_core::guard::GuardValue::Bool(false)) => "false".red(),
+            Some(other) => other.to_string().cyan(),
+            None => "(not evaluated)".dimmed(),
+        };
+        println!("{}{} {} {}", indent, step.expression, "→".dimmed(), value);
+    }
+}
+
+/// Print the result of `waypoint guards check`.
+pub fn print_guard_check_report(report: &waypoint_core::GuardCheckReport) {
+    if report.results.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "No guards to check in {} pending migration(s).",
+                report.migrations_checked
+            )
+            .green()
+        );
+        return;
+    }
+
+    println!("{}", format!("Schema: {}", report.schema).dimmed());
+    println!();
+
+    let mut current_script = None;
+    for result in &report.results {
+        if current_script != Some(&result.script) {
+            println!("  {}", result.script.bold());
+            current_script = Some(&result.script);
+        }
+        let status = match (result.passed, result.kind.as_str()) {
+            (Some(true), _) => "pass".green(),
+            (Some(false), "ensure") => "not yet".yellow(),
+            (Some(false), _) => "FAIL".red().bold(),
+            (None, _) => "ERROR".red().bold(),
+        };
+        println!("    {} {:<7} {}", status, result.kind, result.expression);
+        if let Some(error) = &result.error {
+            println!("        {}", error.red());
+        }
+        if result.is_failure() {
+            if let Some(message) = &result.message {
+                println!("        {}", message.yellow());
+            }
+        }
+    }
+
+    println!();
+    if report.failed == 0 {
+        println!(
+            "{}",
+            format!(
+                "All require guards hold for {} pending migration(s).",
+                report.migrations_checked
+            )
+            .green()
+            .bold()
+        );
+    } else {
+        println!(
+            "{}",
+            format!("{} require guard(s) did not hold.", report.failed)
+                .red()
+                .bold()
+        );
+    }
+}
+
+/// Print the results of `waypoint check`.
+pub fn print_check_report(report: &waypoint_core::CheckReport) {
+    if report.checks.is_empty() {
+        println!("{}", "No checks found.".yellow());
+        return;
+    }
+
+    println!("{}", format!("Schema: {}", report.schema).dimmed());
+    println!();
+    for check in &report.checks {
+        let status = if check.passed {
+            "pass".green()
+        } else if check.error.is_some() {
+            "ERROR".red().bold()
+        } else {
+            "FAIL".red().bold()
+        };
+        println!(
+            "  {} {} {}",
+            status,
+            check.name,
+            format!("({}, {} ms)", check.file, check.execution_time_ms).dimmed()
+        );
+        if let Some(error) = &check.error {
+            println!("        {}", error.red());
+        } else if !check.passed {
+            println!("        {} row(s) returned", check.rows);
+            for row in &check.sample {
+                println!("        {}", row.dimmed());
+            }
+        }
+    }
+
+    println!();
+    if report.failed == 0 {
+        println!(
+            "{}",
+            format!("All {} check(s) passed.", report.passed)
+                .green()
+                .bold()
+        );
+    } else {
+        println!(
+            "{}",
+            format!(
+                "{} of {} check(s) failed.",
+                report.failed,
+                report.checks.len()
+            )
+            .red()
+            .bold()
+        );
+    }
+}
+
 /// Print explain report (enhanced dry-run).
 pub fn print_explain_report(report: &waypoint_core::ExplainReport) {
     if report.migrations.is_empty() {
@@ -505,8 +1782,88 @@ pub fn print_conflict_report(report: &waypoint_core::ConflictReport) {
     }
 }
 
+/// Print the old-to-new version mapping of a rebase.
+pub fn print_rebase_report(report: &waypoint_core::RebaseReport) {
+    let latest = report
+        .base_latest
+        .as_deref()
+        .map(|v| format!(" (newest: V{})", v))
+        .unwrap_or_default();
+    if report.renumbered.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "{} branch migration(s) already follow '{}'{}; nothing to renumber.",
+                report.local.len(),
+                report.base,
+                latest
+            )
+            .green()
+            .bold()
+        );
+        return;
+    }
+
+    let verb = if report.dry_run {
+        "Would renumber"
+    } else {
+        "Renumbered"
+    };
+    println!(
+        "{}",
+        format!(
+            "{} {} migration(s) after '{}'{}",
+            verb,
+            report.renumbered.len(),
+            report.base,
+            latest
+        )
+        .green()
+        .bold()
+    );
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("From"),
+            Cell::new("To"),
+            Cell::new("Script"),
+            Cell::new("Reason"),
+        ]);
+    for r in &report.renumbered {
+        let mut script = r.new_script.clone();
+        if let Some(undo) = &r.undo_script {
+            script.push_str(&format!("\n(undo: {})", undo));
+        }
+        table.add_row(vec![
+            Cell::new(format!("V{}", r.from)),
+            Cell::new(format!("V{}", r.to)),
+            Cell::new(script),
+            Cell::new(&r.reason),
+        ]);
+    }
+    println!("{table}");
+
+    for script in &report.depends_updated {
+        println!("  {} updated depends in {}", "~".cyan(), script);
+    }
+    for script in &report.depends_ambiguous {
+        println!(
+            "  {} depends in {} names a version on both branches; check it by hand",
+            "!".yellow(),
+            script
+        );
+    }
+}
+
 /// Print multi-database result.
 pub fn print_multi_result(result: &waypoint_core::multi::MultiResult) {
+    if let Some(canary) = &result.canary {
+        print_canary_report(canary);
+    }
     for r in &result.results {
         let icon = if r.success {
             "✓".green()
@@ -530,6 +1887,55 @@ pub fn print_multi_result(result: &waypoint_core::multi::MultiResult) {
         let failed = result.results.iter().filter(|r| !r.success).count();
         println!("{}", format!("{} database(s) failed.", failed).red().bold());
     }
+    print_report_warnings(&result.warnings);
+}
+
+/// Print the canary phase of a multi-db rollout.
+fn print_canary_report(canary: &waypoint_core::multi::CanaryReport) {
+    use waypoint_core::multi::CanaryOutcome;
+
+    println!(
+        "{}",
+        format!(
+            "Canary: {} (baked {}s of {}s)",
+            canary.databases.join(", "),
+            canary.baked_secs,
+            canary.bake_time_secs
+        )
+        .bold()
+    );
+    let passed = canary.checks.iter().filter(|c| c.passed).count();
+    if !canary.checks.is_empty() {
+        println!("  {} health check(s) passed", passed);
+    }
+    for check in canary.checks.iter().filter(|c| !c.passed) {
+        let target = match &check.database {
+            Some(db) => format!("[{}] {}", db, check.check),
+            None => check.check.clone(),
+        };
+        println!(
+            "  {} {} at {}s: {}",
+            "✗".red(),
+            target,
+            check.elapsed_secs,
+            check.error.as_deref().unwrap_or("failed")
+        );
+    }
+    match canary.outcome {
+        CanaryOutcome::Promoted => println!(
+            "{}",
+            "Canaries healthy; migrated the remaining databases.".green()
+        ),
+        CanaryOutcome::CanaryFailed => println!(
+            "{}",
+            "A canary failed to migrate; the remaining databases were left alone.".red()
+        ),
+        CanaryOutcome::Unhealthy => println!(
+            "{}",
+            "A health check failed while baking; the remaining databases were left alone.".red()
+        ),
+    }
+    println!();
 }
 
 /// Print multi-database info.
@@ -541,6 +1947,151 @@ pub fn print_multi_info(all_info: &HashMap<String, Vec<MigrationInfo>>) {
     }
 }
 
+/// Print a `pending-report` summary for one database.
+pub fn print_pending_summary(summary: &waypoint_core::PendingSummary) {
+    if let Some(ref name) = summary.database {
+        println!("{}", format!("=== {} ===", name).bold());
+    }
+    if summary.pending.is_empty() {
+        println!("{}", "No pending migrations.".green());
+    } else {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec![
+                Cell::new("Version"),
+                Cell::new("Script"),
+                Cell::new("Verdict"),
+                Cell::new("Age"),
+            ]);
+        for m in &summary.pending {
+            let verdict = match m.verdict {
+                waypoint_core::safety::SafetyVerdict::Safe => "SAFE".green(),
+                waypoint_core::safety::SafetyVerdict::Caution => "CAUTION".yellow(),
+                waypoint_core::safety::SafetyVerdict::Danger => "DANGER".red(),
+            };
+            table.add_row(vec![
+                Cell::new(m.version.as_deref().unwrap_or("R")),
+                Cell::new(&m.script),
+                Cell::new(verdict.to_string()),
+                Cell::new(
+                    m.age_days
+                        .map(|d| format!("{}d", d))
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+            ]);
+        }
+        println!("{table}");
+    }
+    if summary.notified {
+        println!("Summary sent as a notification.");
+    }
+}
+
+/// Print `pending-report` summaries for every database.
+pub fn print_pending_summaries(summaries: &[waypoint_core::PendingSummary]) {
+    for (i, summary) in summaries.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_pending_summary(summary);
+    }
+}
+
+/// Print a `fingerprint` report: the live fingerprint, and how it compares
+/// with the latest stored baseline.
+pub fn print_fingerprint(report: &waypoint_core::FingerprintReport) {
+    println!("{}", report.fingerprint);
+    match (&report.stored, report.matches_stored()) {
+        (Some(b), Some(true)) => println!(
+            "{}",
+            format!(
+                "Matches the baseline recorded {}.",
+                b.recorded_at.format("%Y-%m-%d %H:%M:%S UTC")
+            )
+            .green()
+        ),
+        (Some(b), _) => println!(
+            "{}",
+            format!(
+                "Differs from the baseline recorded {} ({}).",
+                b.recorded_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                b.fingerprint
+            )
+            .yellow()
+        ),
+        (None, _) => {}
+    }
+}
+
+/// Print an `sla-check` report: every pending migration with its age, and
+/// the verdict.
+pub fn print_sla_check(report: &waypoint_core::SlaCheckReport) {
+    if report.pending.is_empty() {
+        println!("{}", "No pending migrations.".green());
+        return;
+    }
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Version"),
+            Cell::new("Script"),
+            Cell::new("Age"),
+            Cell::new("Aged By"),
+        ]);
+    for m in &report.pending {
+        let age = match m.age_hours {
+            Some(h) if h >= 24 => format!("{}d", h / 24),
+            Some(h) => format!("{}h", h),
+            None => "unknown".to_string(),
+        };
+        let age = if m.breached {
+            age.red().to_string()
+        } else {
+            age
+        };
+        let source = match m.source {
+            Some(waypoint_core::commands::sla_check::AgeSource::Git) => "git commit",
+            Some(waypoint_core::commands::sla_check::AgeSource::Mtime) => "file mtime",
+            None => "-",
+        };
+        table.add_row(vec![
+            Cell::new(m.version.as_deref().unwrap_or("R")),
+            Cell::new(&m.script),
+            Cell::new(age),
+            Cell::new(source),
+        ]);
+    }
+    println!("{table}");
+    let breaches = report.breaches().count();
+    if breaches == 0 {
+        println!(
+            "{}",
+            format!(
+                "All {} pending migration(s) are within {}.",
+                report.pending.len(),
+                report.max_pending_age
+            )
+            .green()
+        );
+    } else {
+        println!(
+            "{}",
+            format!(
+                "{} pending migration(s) older than {} or of unknown age.",
+                breaches, report.max_pending_age
+            )
+            .red()
+            .bold()
+        );
+    }
+}
+
 /// Print a safety analysis report for a single migration.
 pub fn print_safety_report(report: &waypoint_core::SafetyReport) {
     let verdict_str = match report.overall_verdict {
@@ -554,6 +2105,14 @@ pub fn print_safety_report(report: &waypoint_core::SafetyReport) {
         verdict_str, report.script, report.overall_verdict
     );
 
+    if report.unanalyzable {
+        println!(
+            "    {} {}",
+            "!".yellow(),
+            "Unanalyzable: Rust code migration, review up() by hand".yellow()
+        );
+    }
+
     for stmt in &report.statements {
         let icon = match stmt.verdict {
             waypoint_core::safety::SafetyVerdict::Safe => "✓".green(),
@@ -589,6 +2148,38 @@ pub fn print_safety_report(report: &waypoint_core::SafetyReport) {
             println!("      {} {}", "→".dimmed(), suggestion.dimmed());
         }
     }
+
+    for change in &report.default_changes {
+        let duration = change
+            .estimated_rewrite_ms
+            .map(|ms| format!(" (~{}ms)", ms))
+            .unwrap_or_default();
+        let (icon, what) = if change.rewrites_table {
+            (
+                "!".yellow(),
+                format!("rewrites the table{}", duration).yellow(),
+            )
+        } else {
+            ("✓".green(), "no rewrite".green())
+        };
+        println!(
+            "    {} {}.{} default {} — {}",
+            icon, change.table, change.column, change.default_expr, what
+        );
+        println!("      {} {}", "→".dimmed(), change.reason.dimmed());
+        if let Some(ref alt) = change.alternative {
+            println!("      {} {}", "→".dimmed(), alt.dimmed());
+        }
+    }
+
+    for stmt in &report.privileged {
+        println!("    {} {}", "!".yellow(), stmt.statement_preview);
+        println!(
+            "      {} {}",
+            "⚠".yellow(),
+            format!("Elevated privileges: {}", stmt.reason).yellow()
+        );
+    }
 }
 
 /// Print the overall safety verdict.
@@ -615,6 +2206,7 @@ pub fn print_safety_overall(verdict: waypoint_core::safety::SafetyVerdict) {
 
 /// Print advisor report.
 pub fn print_advisor_report(report: &waypoint_core::AdvisorReport) {
+    print_incomplete(&report.incomplete);
     if report.advisories.is_empty() {
         println!(
             "{}",
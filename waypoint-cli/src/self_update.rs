@@ -1,25 +1,128 @@
 //! Self-update mechanism via the GitHub Releases API.
 //! Downloads platform-specific binaries and performs atomic
-//! in-place replacement, with a fallback to install.sh.
+//! in-place replacement, with an opt-in fallback to install.sh when the
+//! release can't be downloaded.
+//!
+//! The release is picked from a channel (`stable`, or `beta` to include
+//! pre-releases) or pinned with `--version`. When a release publishes
+//! `SHA256SUMS`, the tarball's hash is checked against it; with
+//! `--minisign-key` or `--gpg` the `SHA256SUMS` file must also carry a valid
+//! signature (`SHA256SUMS.minisig` / `SHA256SUMS.asc`, checked with the
+//! `minisign` / `gpg` binaries). Once a tarball is downloaded, a failed
+//! check or install is an error and never falls back to install.sh.
 
 use std::env;
 use std::fs;
 use std::io::Read;
+use std::path::Path;
 use std::process::Command;
 
 use colored::Colorize;
 use flate2::read::GzDecoder;
 use semver::Version;
+use sha2::{Digest, Sha256};
 use tar::Archive;
 use waypoint_core::error::WaypointError;
 
 const REPO: &str = "tensorbee/waypoint";
 const INSTALL_SH_URL: &str = "https://raw.githubusercontent.com/tensorbee/waypoint/main/install.sh";
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
 
 /// Minimal representation of a GitHub release for version checking.
 #[derive(serde::Deserialize)]
 struct GitHubRelease {
     tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+/// A file attached to a GitHub release.
+#[derive(serde::Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl GitHubRelease {
+    fn asset(&self, name: &str) -> Option<&GitHubAsset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// Which releases `self-update` considers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// The latest full release (default).
+    Stable,
+    /// The highest version, pre-releases included.
+    Beta,
+}
+
+impl std::str::FromStr for Channel {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stable" => Ok(Channel::Stable),
+            "beta" => Ok(Channel::Beta),
+            other => Err(WaypointError::UpdateError(format!(
+                "Unknown channel '{other}' (expected stable or beta)"
+            ))),
+        }
+    }
+}
+
+/// How `self-update` picks, fetches and verifies a release.
+pub struct UpdateOptions {
+    /// Report whether an update is available without installing it.
+    pub check: bool,
+    /// Channel to take the newest release from.
+    pub channel: Channel,
+    /// Install exactly this version (downgrades allowed).
+    pub version: Option<String>,
+    /// HTTP(S) proxy URL; without it `HTTPS_PROXY` / `ALL_PROXY` apply.
+    pub proxy: Option<String>,
+    /// Minisign public key `SHA256SUMS.minisig` must verify against.
+    pub minisign_key: Option<String>,
+    /// Require `SHA256SUMS.asc` to verify against the local GPG keyring.
+    pub gpg: bool,
+    /// Run install.sh for the chosen release when its tarball can't be
+    /// downloaded. install.sh fetches the binary itself, so waypoint can't
+    /// verify it; not allowed together with a required signature.
+    pub install_sh_fallback: bool,
+}
+
+impl UpdateOptions {
+    fn requires_signature(&self) -> bool {
+        self.minisign_key.is_some() || self.gpg
+    }
+}
+
+/// HTTP agent honouring `--proxy`.
+fn agent(proxy: Option<&str>) -> Result<ureq::Agent, WaypointError> {
+    let mut config = ureq::Agent::config_builder();
+    if let Some(url) = proxy {
+        let proxy = ureq::Proxy::new(url)
+            .map_err(|e| WaypointError::UpdateError(format!("Invalid proxy '{url}': {e}")))?;
+        config = config.proxy(Some(proxy));
+    }
+    Ok(ureq::Agent::new_with_config(config.build()))
+}
+
+/// Download `url` into memory.
+fn download(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>, WaypointError> {
+    agent
+        .get(url)
+        .header("User-Agent", "waypoint-self-update")
+        .call()
+        .map_err(|e| WaypointError::UpdateError(format!("Download of {url} failed: {e}")))?
+        .body_mut()
+        .with_config()
+        .limit(256 * 1024 * 1024)
+        .read_to_vec()
+        .map_err(|e| WaypointError::UpdateError(format!("Failed to read {url}: {e}")))
 }
 
 /// Parse the compile-time crate version into a semver Version.
@@ -28,17 +131,44 @@ fn current_version() -> Result<Version, WaypointError> {
         .map_err(|e| WaypointError::UpdateError(format!("Failed to parse current version: {e}")))
 }
 
-/// Fetch the latest release metadata from the GitHub API.
-fn fetch_latest_release() -> Result<GitHubRelease, WaypointError> {
-    let url = format!("https://api.github.com/repos/{REPO}/releases/latest");
-    let resp: GitHubRelease = ureq::get(&url)
+/// Fetch release metadata (one release or a list) from the GitHub API.
+fn fetch_json<T: serde::de::DeserializeOwned>(
+    agent: &ureq::Agent,
+    path: &str,
+) -> Result<T, WaypointError> {
+    let url = format!("https://api.github.com/repos/{REPO}/{path}");
+    agent
+        .get(&url)
         .header("User-Agent", "waypoint-self-update")
         .call()
-        .map_err(|e| WaypointError::UpdateError(format!("Failed to fetch latest release: {e}")))?
+        .map_err(|e| WaypointError::UpdateError(format!("Failed to fetch {path}: {e}")))?
         .body_mut()
         .read_json()
-        .map_err(|e| WaypointError::UpdateError(format!("Failed to parse release JSON: {e}")))?;
-    Ok(resp)
+        .map_err(|e| WaypointError::UpdateError(format!("Failed to parse release JSON: {e}")))
+}
+
+/// The release to update to: the pinned version, or the newest one on the
+/// channel.
+fn fetch_target_release(
+    agent: &ureq::Agent,
+    options: &UpdateOptions,
+) -> Result<GitHubRelease, WaypointError> {
+    if let Some(ref pinned) = options.version {
+        let version = parse_version(pinned)?;
+        return fetch_json(agent, &format!("releases/tags/v{version}"));
+    }
+    match options.channel {
+        Channel::Stable => fetch_json(agent, "releases/latest"),
+        Channel::Beta => {
+            let releases: Vec<GitHubRelease> = fetch_json(agent, "releases?per_page=50")?;
+            releases
+                .into_iter()
+                .filter_map(|r| parse_version(&r.tag_name).ok().map(|v| (v, r)))
+                .max_by(|(a, _), (b, _)| a.cmp(b))
+                .map(|(_, r)| r)
+                .ok_or_else(|| WaypointError::UpdateError("No releases found".into()))
+        }
+    }
 }
 
 /// Parse a GitHub release tag (with optional 'v' prefix) into a semver Version.
@@ -73,31 +203,135 @@ fn platform_target() -> Result<(&'static str, &'static str), WaypointError> {
     Ok((os, arch))
 }
 
-/// Download a release tarball and atomically replace the current binary.
-fn download_and_replace(version: &str) -> Result<(), WaypointError> {
+/// Download the release tarball for this platform.
+fn download_tarball(
+    agent: &ureq::Agent,
+    release: &GitHubRelease,
+) -> Result<(String, Vec<u8>), WaypointError> {
     let (os, arch) = platform_target()?;
-    let tag = if version.starts_with('v') {
-        version.to_string()
-    } else {
-        format!("v{version}")
-    };
+    let tag = &release.tag_name;
     let tarball_name = format!("waypoint-{tag}-{os}-{arch}.tar.gz");
     let url = format!("https://github.com/{REPO}/releases/download/{tag}/{tarball_name}");
 
     eprintln!("Downloading {}...", url);
+    let bytes = download(agent, &url)?;
+    Ok((tarball_name, bytes))
+}
 
-    let mut resp = ureq::get(&url)
-        .header("User-Agent", "waypoint-self-update")
-        .call()
-        .map_err(|e| WaypointError::UpdateError(format!("Download failed: {e}")))?;
+/// Check the tarball against the release's `SHA256SUMS`, and that file's
+/// signature when one is required. Returns whether anything was verified.
+fn verify_tarball(
+    agent: &ureq::Agent,
+    release: &GitHubRelease,
+    tarball_name: &str,
+    tarball: &[u8],
+    options: &UpdateOptions,
+) -> Result<bool, WaypointError> {
+    let Some(sums_asset) = release.asset(CHECKSUMS_ASSET) else {
+        if options.requires_signature() {
+            return Err(WaypointError::UpdateError(format!(
+                "Release {} publishes no {CHECKSUMS_ASSET}; cannot verify its signature",
+                release.tag_name
+            )));
+        }
+        eprintln!(
+            "{} Release {} publishes no {CHECKSUMS_ASSET}; skipping checksum verification.",
+            "!".yellow().bold(),
+            release.tag_name
+        );
+        return Ok(false);
+    };
+    let sums = download(agent, &sums_asset.browser_download_url)?;
+
+    if options.requires_signature() {
+        let dir = tempfile::tempdir()
+            .map_err(|e| WaypointError::UpdateError(format!("Cannot create temp dir: {e}")))?;
+        let sums_path = dir.path().join(CHECKSUMS_ASSET);
+        fs::write(&sums_path, &sums)
+            .map_err(|e| WaypointError::UpdateError(format!("Failed to write checksums: {e}")))?;
+        if let Some(ref key) = options.minisign_key {
+            let sig_path = fetch_signature(agent, release, dir.path(), "minisig")?;
+            run_verifier(
+                Command::new("minisign")
+                    .arg("-Vq")
+                    .arg("-P")
+                    .arg(key)
+                    .arg("-m")
+                    .arg(&sums_path)
+                    .arg("-x")
+                    .arg(&sig_path),
+                "minisign",
+            )?;
+        }
+        if options.gpg {
+            let sig_path = fetch_signature(agent, release, dir.path(), "asc")?;
+            run_verifier(
+                Command::new("gpg")
+                    .arg("--batch")
+                    .arg("--verify")
+                    .arg(&sig_path)
+                    .arg(&sums_path),
+                "gpg",
+            )?;
+        }
+    }
 
-    let bytes = resp
-        .body_mut()
-        .read_to_vec()
-        .map_err(|e| WaypointError::UpdateError(format!("Failed to read response body: {e}")))?;
+    let expected = String::from_utf8_lossy(&sums)
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*') == tarball_name)
+        .map(|(hash, _)| hash.to_lowercase())
+        .ok_or_else(|| {
+            WaypointError::UpdateError(format!("{CHECKSUMS_ASSET} has no entry for {tarball_name}"))
+        })?;
+    let actual: String = Sha256::digest(tarball)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    if actual != expected {
+        return Err(WaypointError::UpdateError(format!(
+            "Checksum mismatch for {tarball_name}: expected {expected}, got {actual}"
+        )));
+    }
+    Ok(true)
+}
+
+/// Download `SHA256SUMS.<ext>` into `dir`.
+fn fetch_signature(
+    agent: &ureq::Agent,
+    release: &GitHubRelease,
+    dir: &Path,
+    ext: &str,
+) -> Result<std::path::PathBuf, WaypointError> {
+    let name = format!("{CHECKSUMS_ASSET}.{ext}");
+    let asset = release.asset(&name).ok_or_else(|| {
+        WaypointError::UpdateError(format!("Release {} publishes no {name}", release.tag_name))
+    })?;
+    let path = dir.join(&name);
+    fs::write(&path, download(agent, &asset.browser_download_url)?)
+        .map_err(|e| WaypointError::UpdateError(format!("Failed to write {name}: {e}")))?;
+    Ok(path)
+}
+
+/// Run a signature verifier and fail unless it succeeds.
+fn run_verifier(command: &mut Command, tool: &str) -> Result<(), WaypointError> {
+    let output = command
+        .output()
+        .map_err(|e| WaypointError::UpdateError(format!("Failed to run {tool}: {e}")))?;
+    if !output.status.success() {
+        return Err(WaypointError::UpdateError(format!(
+            "Signature verification with {tool} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
 
+/// Extract the binary from a release tarball and atomically replace the
+/// current binary.
+fn replace_binary(bytes: &[u8]) -> Result<(), WaypointError> {
     // Extract the binary from the tar.gz
-    let gz = GzDecoder::new(&bytes[..]);
+    let gz = GzDecoder::new(bytes);
     let mut archive = Archive::new(gz);
     let mut binary_data = None;
 
@@ -188,12 +422,21 @@ fn download_and_replace(version: &str) -> Result<(), WaypointError> {
     Ok(())
 }
 
-/// Fall back to the remote install.sh script when direct update fails.
-fn fallback_install_sh() -> Result<(), WaypointError> {
-    eprintln!("{}", "Falling back to install.sh...".yellow());
-    let status = Command::new("sh")
+/// Install `tag` with the remote install.sh script, through `proxy` if set.
+fn fallback_install_sh(tag: &str, proxy: Option<&str>) -> Result<(), WaypointError> {
+    eprintln!(
+        "{}",
+        "Falling back to install.sh (its download is not checksum-verified)...".yellow()
+    );
+    let mut command = Command::new("sh");
+    command
         .arg("-c")
         .arg(format!("curl -sSf {INSTALL_SH_URL} | sh"))
+        .env("WAYPOINT_VERSION", tag);
+    if let Some(url) = proxy {
+        command.env("https_proxy", url).env("HTTPS_PROXY", url);
+    }
+    let status = command
         .status()
         .map_err(|e| WaypointError::UpdateError(format!("Failed to run install.sh: {e}")))?;
 
@@ -205,11 +448,26 @@ fn fallback_install_sh() -> Result<(), WaypointError> {
     Ok(())
 }
 
-/// Check for and optionally install the latest waypoint release.
-pub fn self_update(check_only: bool, json_output: bool) -> Result<(), WaypointError> {
+/// Check for and optionally install the latest (or pinned) waypoint release.
+pub fn self_update(options: &UpdateOptions, json_output: bool) -> Result<(), WaypointError> {
+    let check_only = options.check;
+    if options.install_sh_fallback && options.requires_signature() {
+        return Err(WaypointError::UpdateError(
+            "--install-sh-fallback cannot be combined with --minisign-key or --gpg: \
+             install.sh output can't be verified"
+                .into(),
+        ));
+    }
+    let agent = agent(options.proxy.as_deref())?;
     let current = current_version()?;
-    let release = fetch_latest_release()?;
+    let release = fetch_target_release(&agent, options)?;
     let latest = parse_version(&release.tag_name)?;
+    // A pin installs exactly that version, older or not.
+    let update_available = if options.version.is_some() {
+        latest != current
+    } else {
+        latest > current
+    };
 
     if json_output && check_only {
         println!(
@@ -217,13 +475,14 @@ pub fn self_update(check_only: bool, json_output: bool) -> Result<(), WaypointEr
             serde_json::json!({
                 "current_version": current.to_string(),
                 "latest_version": latest.to_string(),
-                "update_available": latest > current,
+                "prerelease": release.prerelease,
+                "update_available": update_available,
             })
         );
         return Ok(());
     }
 
-    if current >= latest {
+    if !update_available {
         if json_output {
             println!(
                 "{}",
@@ -263,7 +522,26 @@ pub fn self_update(check_only: bool, json_output: bool) -> Result<(), WaypointEr
         latest.to_string().green().bold()
     );
 
-    match download_and_replace(&latest.to_string()) {
+    let (tarball_name, tarball) = match download_tarball(&agent, &release) {
+        Ok(download) => download,
+        Err(e) if options.install_sh_fallback => {
+            eprintln!("{} Direct update failed: {}", "✗".red().bold(), e);
+            return finish_with_install_sh(
+                &current,
+                &latest,
+                &release.tag_name,
+                options.proxy.as_deref(),
+                json_output,
+            );
+        }
+        Err(e) => return Err(e),
+    };
+    // Never fall back to install.sh past this point: a failed check means
+    // the download can't be trusted, and after a passed one install.sh would
+    // replace a verified tarball with an unverified download.
+    let verified = verify_tarball(&agent, &release, &tarball_name, &tarball, options)?;
+
+    match replace_binary(&tarball) {
         Ok(()) => {
             if json_output {
                 println!(
@@ -272,6 +550,7 @@ pub fn self_update(check_only: bool, json_output: bool) -> Result<(), WaypointEr
                         "current_version": current.to_string(),
                         "latest_version": latest.to_string(),
                         "updated": true,
+                        "verified": verified,
                         "message": format!("Successfully updated to {}.", latest),
                     })
                 );
@@ -279,25 +558,34 @@ pub fn self_update(check_only: bool, json_output: bool) -> Result<(), WaypointEr
                 eprintln!("{} Successfully updated to {}.", "✓".green().bold(), latest);
             }
         }
-        Err(e) => {
-            eprintln!("{} Direct update failed: {}", "✗".red().bold(), e);
-            fallback_install_sh()?;
-            if json_output {
-                println!(
-                    "{}",
-                    serde_json::json!({
-                        "current_version": current.to_string(),
-                        "latest_version": latest.to_string(),
-                        "updated": true,
-                        "fallback": true,
-                        "message": format!("Updated to {} via install.sh.", latest),
-                    })
-                );
-            } else {
-                eprintln!("{} Updated via install.sh.", "✓".green().bold());
-            }
-        }
+        Err(e) => return Err(e),
     }
 
     Ok(())
 }
+
+/// Install `tag` with install.sh and report it.
+fn finish_with_install_sh(
+    current: &Version,
+    latest: &Version,
+    tag: &str,
+    proxy: Option<&str>,
+    json_output: bool,
+) -> Result<(), WaypointError> {
+    fallback_install_sh(tag, proxy)?;
+    if json_output {
+        println!(
+            "{}",
+            serde_json::json!({
+                "current_version": current.to_string(),
+                "latest_version": latest.to_string(),
+                "updated": true,
+                "fallback": true,
+                "message": format!("Updated to {} via install.sh.", latest),
+            })
+        );
+    } else {
+        eprintln!("{} Updated via install.sh.", "✓".green().bold());
+    }
+    Ok(())
+}
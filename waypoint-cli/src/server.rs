@@ -0,0 +1,422 @@
+//! `waypoint serve`: an authenticated HTTP+JSON API for running migrations
+//! from one central service.
+//!
+//! Each request names a database from the `[[databases]]` config (or
+//! `default` in single-database mode), so CI pipelines call the service
+//! instead of holding database credentials themselves. Every request
+//! connects fresh; `migrate` takes the usual advisory lock, so concurrent
+//! requests against one database are serialized by the database itself.
+
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use waypoint_core::config::WaypointConfig;
+use waypoint_core::error::WaypointError;
+use waypoint_core::Waypoint;
+
+/// Name of the configured database in single-database mode.
+const DEFAULT_DATABASE: &str = "default";
+
+/// Shared server state.
+struct AppState {
+    config: WaypointConfig,
+    token: String,
+}
+
+/// Optional JSON body of `POST .../migrate`.
+#[derive(Deserialize, Default)]
+struct MigrateRequest {
+    target: Option<String>,
+}
+
+/// JSON error response: `{"error": "...", "exit_code": N}`.
+struct ApiError {
+    status: StatusCode,
+    message: String,
+    exit_code: Option<i32>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            message: message.into(),
+            exit_code: None,
+        }
+    }
+}
+
+impl From<WaypointError> for ApiError {
+    #[allow(deprecated)]
+    fn from(e: WaypointError) -> Self {
+        let status = match &e {
+            WaypointError::DatabaseNotFound { .. } => StatusCode::NOT_FOUND,
+            WaypointError::ConfigError(_)
+            | WaypointError::MigrationParseError(_)
+            | WaypointError::MigrationTooLarge { .. } => StatusCode::BAD_REQUEST,
+            WaypointError::LockError(_) | WaypointError::UndoUnsafe { .. } => StatusCode::CONFLICT,
+            WaypointError::ValidationFailed(_)
+            | WaypointError::ChecksumMismatch { .. }
+            | WaypointError::PreflightFailed { .. }
+            | WaypointError::GuardFailed { .. }
+            | WaypointError::MigrationBlocked { .. }
+            | WaypointError::SimulationFailed { .. }
+            | WaypointError::BackupFailed { .. }
+            | WaypointError::PlanDiverged { .. }
+            | WaypointError::ReceiptInvalid { .. }
+            | WaypointError::ChecksFailed { .. }
+            | WaypointError::PendingAgeExceeded { .. }
+            | WaypointError::FingerprintMismatch { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            WaypointError::PolicyDenied { .. } => StatusCode::FORBIDDEN,
+            WaypointError::ReadOnlyStandby { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        // Internal errors carry raw database and IO messages (hosts, users,
+        // SQL); callers get a generic message and the detail goes to the log.
+        let message = if status == StatusCode::INTERNAL_SERVER_ERROR {
+            log::error!("API request failed; error={}", e);
+            match &e {
+                WaypointError::MigrationFailed { script, .. } => {
+                    format!("Migration failed for {}; see the server log", script)
+                }
+                _ => "Internal error; see the server log".to_string(),
+            }
+        } else {
+            e.to_string()
+        };
+        ApiError {
+            status,
+            message,
+            exit_code: Some(crate::exit_code(&e)),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = json!({ "error": self.message, "exit_code": self.exit_code });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+/// Run the API server until Ctrl-C.
+pub async fn serve(config: WaypointConfig, bind: Option<String>) -> Result<(), WaypointError> {
+    let token = config
+        .server
+        .token
+        .clone()
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| {
+            WaypointError::ConfigError(
+                "waypoint serve requires a token: set [server] token or WAYPOINT_SERVER_TOKEN"
+                    .to_string(),
+            )
+        })?;
+    let bind = bind.unwrap_or_else(|| config.server.bind.clone());
+    if !is_loopback(&bind) && !config.server.allow_plain_http {
+        return Err(WaypointError::ConfigError(format!(
+            "waypoint serve speaks plain HTTP, so the bearer token would cross the network \
+             unencrypted on {}; bind to 127.0.0.1 behind a TLS proxy, or set [server] \
+             allow_plain_http = true if TLS is terminated in front of it",
+            bind
+        )));
+    }
+
+    let listener = tokio::net::TcpListener::bind(&bind).await?;
+    log::info!(
+        "Server listening; bind={}, databases={}",
+        bind,
+        database_names(&config).join(",")
+    );
+
+    let state = Arc::new(AppState { config, token });
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+/// Whether `bind` listens on the loopback interface only.
+fn is_loopback(bind: &str) -> bool {
+    let host = match bind.rsplit_once(':') {
+        Some((host, _)) => host,
+        None => bind,
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/health", get(|| async { Json(json!({ "status": "ok" })) }))
+        .route("/v1/databases", get(list_databases))
+        .route("/v1/databases/{name}/info", get(info))
+        .route("/v1/databases/{name}/validate", post(validate))
+        .route("/v1/databases/{name}/drift", get(drift))
+        .route("/v1/databases/{name}/migrate", post(migrate))
+        .with_state(state)
+}
+
+/// Require `Authorization: Bearer <token>`.
+fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if constant_time_eq(presented.as_bytes(), state.token.as_bytes()) {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid bearer token",
+        ))
+    }
+}
+
+/// Compare without short-circuiting on the first differing byte.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn database_names(config: &WaypointConfig) -> Vec<String> {
+    match &config.multi_database {
+        Some(dbs) => dbs.iter().map(|d| d.name.clone()).collect(),
+        None => vec![DEFAULT_DATABASE.to_string()],
+    }
+}
+
+/// Config for one named database. Top-level settings (safety, guards,
+/// strict mode, ...) apply to every database.
+fn database_config(config: &WaypointConfig, name: &str) -> Result<WaypointConfig, WaypointError> {
+    match &config.multi_database {
+        Some(dbs) => match dbs.iter().find(|d| d.name == name) {
+            Some(db) => db.merge_into(config),
+            None => Err(WaypointError::DatabaseNotFound {
+                name: name.to_string(),
+                available: database_names(config).join(", "),
+            }),
+        },
+        None if name == DEFAULT_DATABASE => Ok(config.clone()),
+        None => Err(WaypointError::DatabaseNotFound {
+            name: name.to_string(),
+            available: DEFAULT_DATABASE.to_string(),
+        }),
+    }
+}
+
+async fn connect(state: &AppState, name: &str, action: &str) -> Result<Waypoint, ApiError> {
+    log::info!("API request; database={}, action={}", name, action);
+    // There is no --override-policy over the API, so `require_override`
+    // commands are refused.
+    state.config.policy.check(action)?;
+    let config = database_config(&state.config, name)?;
+    Ok(Waypoint::new(config).await?)
+}
+
+/// Serialize a response, applying `[output] redact` for `action`.
+fn to_json(state: &AppState, action: &str, value: impl serde::Serialize) -> ApiResult {
+    let mut value = serde_json::to_value(value)
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    state
+        .config
+        .output
+        .redactor(action)
+        .redact_value(&mut value);
+    Ok(Json(value))
+}
+
+async fn list_databases(State(state): State<Arc<AppState>>, headers: HeaderMap) -> ApiResult {
+    authorize(&state, &headers)?;
+    Ok(Json(json!({ "databases": database_names(&state.config) })))
+}
+
+async fn info(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let wp = connect(&state, &name, "info").await?;
+    to_json(&state, "info", wp.info().await?)
+}
+
+async fn validate(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let wp = connect(&state, &name, "validate").await?;
+    to_json(&state, "validate", wp.validate().await?)
+}
+
+async fn drift(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let wp = connect(&state, &name, "drift").await?;
+    to_json(&state, "drift", wp.drift().await?)
+}
+
+async fn migrate(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    body: Bytes,
+) -> ApiResult {
+    authorize(&state, &headers)?;
+    let request: MigrateRequest = if body.is_empty() {
+        MigrateRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("invalid body: {}", e)))?
+    };
+    let wp = connect(&state, &name, "migrate").await?;
+    to_json(
+        &state,
+        "migrate",
+        wp.migrate(request.target.as_deref()).await?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn state() -> Arc<AppState> {
+        Arc::new(AppState {
+            config: WaypointConfig::default(),
+            token: "s3cret".to_string(),
+        })
+    }
+
+    async fn request(uri: &str, token: Option<&str>) -> (StatusCode, Value) {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = router(state())
+            .oneshot(builder.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"s3cret", b"s3creT"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cre"));
+        assert!(!constant_time_eq(b"", b"s3cret"));
+    }
+
+    #[test]
+    fn test_authorize() {
+        let state = state();
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            authorize(&state, &headers).unwrap_err().status,
+            StatusCode::UNAUTHORIZED
+        );
+
+        headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert!(authorize(&state, &headers).is_err());
+
+        // The token alone, without the scheme, is not accepted.
+        headers.insert(header::AUTHORIZATION, "s3cret".parse().unwrap());
+        assert!(authorize(&state, &headers).is_err());
+
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(authorize(&state, &headers).is_ok());
+    }
+
+    #[test]
+    fn test_is_loopback() {
+        assert!(is_loopback("127.0.0.1:8080"));
+        assert!(is_loopback("localhost:8080"));
+        assert!(is_loopback("[::1]:8080"));
+        assert!(!is_loopback("0.0.0.0:8080"));
+        assert!(!is_loopback("[::]:8080"));
+        assert!(!is_loopback("10.0.0.5:8080"));
+        assert!(!is_loopback("waypoint.internal:8080"));
+    }
+
+    #[test]
+    fn test_internal_errors_are_generic() {
+        let e: ApiError = WaypointError::IoError(std::io::Error::other(
+            "connection to db.internal:5432 as admin refused",
+        ))
+        .into();
+        assert_eq!(e.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!e.message.contains("db.internal"));
+
+        let e: ApiError = WaypointError::MigrationFailed {
+            script: "V2__users.sql".to_string(),
+            reason: "relation \"secret_table\" does not exist".to_string(),
+        }
+        .into();
+        assert!(e.message.contains("V2__users.sql"));
+        assert!(!e.message.contains("secret_table"));
+
+        let e: ApiError = WaypointError::ConfigError("bad target".to_string()).into();
+        assert_eq!(e.status, StatusCode::BAD_REQUEST);
+        assert!(e.message.contains("bad target"));
+    }
+
+    #[tokio::test]
+    async fn test_routes() {
+        let (status, body) = request("/health", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "status": "ok" }));
+
+        let (status, body) = request("/v1/databases", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "missing or invalid bearer token");
+
+        let (status, body) = request("/v1/databases", Some("s3cret")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({ "databases": ["default"] }));
+
+        let (status, _) = request("/v1/databases/orders/info", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = request("/v1/databases/orders/info", Some("s3cret")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body["exit_code"].is_number());
+
+        let (status, _) = request("/v1/nothing", Some("s3cret")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // validate and migrate are POST-only.
+        let (status, _) = request("/v1/databases/default/migrate", Some("s3cret")).await;
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
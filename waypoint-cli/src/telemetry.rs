@@ -0,0 +1,68 @@
+//! OpenTelemetry export of the library's `tracing` spans (`otel` feature).
+//!
+//! Export is off unless `OTEL_EXPORTER_OTLP_ENDPOINT` or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set; spans then go to that
+//! collector over OTLP/HTTP (protobuf). The other standard `OTEL_*`
+//! variables (headers, timeout, `OTEL_SERVICE_NAME`,
+//! `OTEL_RESOURCE_ATTRIBUTES`) are honoured by the exporter and resource.
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Installed tracer provider; flush it with [`Telemetry::shutdown`] before exiting.
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+/// Install the OTLP exporter as the global `tracing` subscriber, if an
+/// endpoint is configured. Failures are logged and leave tracing off.
+pub fn init() -> Option<Telemetry> {
+    let configured = [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .iter()
+    .any(|var| std::env::var(var).is_ok_and(|v| !v.is_empty()));
+    if !configured {
+        return None;
+    }
+
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            log::warn!("Could not set up OTLP trace export; error={}", e);
+            return None;
+        }
+    };
+    let mut resource = Resource::builder();
+    if std::env::var("OTEL_SERVICE_NAME").is_err() {
+        resource = resource.with_service_name("waypoint");
+    }
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build();
+
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("waypoint"));
+    let subscriber = tracing_subscriber::registry().with(layer);
+    if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
+        log::warn!("Could not install the tracing subscriber; error={}", e);
+        return None;
+    }
+    Some(Telemetry { provider })
+}
+
+impl Telemetry {
+    /// Export any spans still buffered. The batch exporter blocks on HTTP, so
+    /// this runs off the async worker.
+    pub fn shutdown(self) {
+        if let Err(e) = tokio::task::block_in_place(|| self.provider.shutdown()) {
+            log::warn!("Could not flush traces; error={}", e);
+        }
+    }
+}
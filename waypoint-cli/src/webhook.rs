@@ -0,0 +1,43 @@
+//! HTTP transport for `[notifications]` webhooks.
+//!
+//! Built with the `self-update` feature, which brings in the HTTP client.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use waypoint_core::notify::WebhookTransport;
+
+/// POSTs webhook bodies with ureq; any 2xx response counts as delivered.
+pub struct HttpWebhook;
+
+#[async_trait::async_trait]
+impl WebhookTransport for HttpWebhook {
+    async fn post(
+        &self,
+        url: &str,
+        headers: &HashMap<String, String>,
+        body: &str,
+        timeout: Duration,
+    ) -> Result<(), String> {
+        let url = url.to_string();
+        let headers = headers.clone();
+        let body = body.to_string();
+        tokio::task::spawn_blocking(move || {
+            let agent = ureq::Agent::new_with_config(
+                ureq::Agent::config_builder()
+                    .timeout_global(Some(timeout))
+                    .build(),
+            );
+            let mut request = agent
+                .post(&url)
+                .header("User-Agent", "waypoint-webhook")
+                .header("Content-Type", "application/json");
+            for (name, value) in &headers {
+                request = request.header(name, value);
+            }
+            request.send(body).map(|_| ()).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
@@ -0,0 +1,65 @@
+//! Tests that run the `waypoint` binary; none of them need a database.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn waypoint(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_waypoint"))
+        .args(args)
+        .current_dir(dir)
+        .env_remove("WAYPOINT_STRICT")
+        .env_remove("WAYPOINT_DEV")
+        .output()
+        .expect("failed to run waypoint")
+}
+
+/// A project with `out_of_order = true` and one migration, `sql`.
+fn project(sql: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("waypoint.toml"),
+        "[migrations]\nlocations = [\"db/migrations\"]\nout_of_order = true\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(dir.path().join("db/migrations")).unwrap();
+    std::fs::write(dir.path().join("db/migrations/V1__Change.sql"), sql).unwrap();
+    dir
+}
+
+#[test]
+fn test_lint_strict_allows_out_of_order() {
+    let clean = project("CREATE TABLE users (id INT PRIMARY KEY);\n");
+    let out = waypoint(clean.path(), &["lint", "--strict"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    // Still fails on lint errors (E001: NOT NULL column without a default).
+    let failing = project("ALTER TABLE users ADD COLUMN email TEXT NOT NULL;\n");
+    let out = waypoint(failing.path(), &["lint", "--strict"]);
+    assert_eq!(out.status.code(), Some(9));
+    let out = waypoint(failing.path(), &["lint"]);
+    assert!(out.status.success());
+}
+
+#[test]
+fn test_strict_rejects_out_of_order_outside_lint() {
+    let dir = project("CREATE TABLE users (id INT PRIMARY KEY);\n");
+    let out = waypoint(dir.path(), &["--strict", "validate"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("out_of_order is not allowed"));
+}
+
+#[cfg(feature = "self-update")]
+#[test]
+fn test_install_sh_fallback_refuses_required_signature() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = waypoint(
+        dir.path(),
+        &["self-update", "--install-sh-fallback", "--gpg"],
+    );
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("cannot be combined"));
+}
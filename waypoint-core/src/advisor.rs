@@ -81,6 +81,25 @@ pub struct AdvisorReport {
     pub suggestion_count: usize,
     /// Count of info items.
     pub info_count: usize,
+    /// Server version the rules ran against, if it could be detected.
+    pub server_version: Option<String>,
+    /// Rules skipped because their query hit the introspection timeout.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub incomplete: Vec<String>,
+}
+
+/// Drop advisories whose object is rejected by the installed
+/// [`ObjectFilter`](crate::introspection::ObjectFilter). An advisory is
+/// matched on the first name in its `object` (the table of `table.column`).
+pub(crate) fn filter_advisories(advisories: &mut Vec<Advisory>) {
+    let filter = crate::introspection::object_filter();
+    if filter.is_empty() {
+        return;
+    }
+    advisories.retain(|a| {
+        let name = a.object.split(['.', ',', ':']).next().unwrap_or_default();
+        filter.allows(name.trim())
+    });
 }
 
 /// Run all advisory rules against the database schema (dialect-aware entry).
@@ -141,6 +160,8 @@ mod tests {
             warning_count: 0,
             suggestion_count: 0,
             info_count: 0,
+            server_version: None,
+            incomplete: Vec::new(),
         };
         assert!(generate_fix_sql(&report).is_empty());
     }
@@ -172,6 +193,8 @@ mod tests {
             warning_count: 2,
             suggestion_count: 0,
             info_count: 0,
+            server_version: None,
+            incomplete: Vec::new(),
         };
         let sql = generate_fix_sql(&report);
         assert!(sql.contains("CREATE INDEX"));
@@ -0,0 +1,323 @@
+//! Audit metadata recorded with each applied migration.
+//!
+//! `migrate` stores where a migration was applied from next to its history
+//! row: the git commit being deployed, the CI build that ran waypoint, the
+//! machine's hostname and the waypoint version. The values land in the
+//! history table's `git_commit`, `ci_build_url`, `hostname` and
+//! `waypoint_version` columns and are shown by `info --json` and
+//! `changelog --deployed`.
+//!
+//! The commit and build URL are detected from the environment variables the
+//! common CI systems set (GitHub Actions, GitLab CI, Jenkins, Buildkite,
+//! CircleCI), falling back to `git rev-parse HEAD` for the commit. Each can
+//! be pinned in the `[audit]` section, and `enabled = false` records none of
+//! them.
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+use crate::db::DbClient;
+use crate::error::Result;
+
+/// Configuration for the `[audit]` section.
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    /// Record audit metadata with each applied migration (default true).
+    pub enabled: bool,
+    /// Git commit to record instead of the detected one.
+    pub git_commit: Option<String>,
+    /// CI build URL to record instead of the detected one.
+    pub ci_build_url: Option<String>,
+    /// Hostname to record instead of the machine's.
+    pub hostname: Option<String>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        AuditConfig {
+            enabled: true,
+            git_commit: None,
+            ci_build_url: None,
+            hostname: None,
+        }
+    }
+}
+
+/// Where a migration was applied from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuditMetadata {
+    /// Git commit being deployed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// URL of the CI build or job that ran waypoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ci_build_url: Option<String>,
+    /// Hostname of the machine that ran waypoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Version of waypoint that applied the migration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waypoint_version: Option<String>,
+}
+
+impl AuditMetadata {
+    /// The metadata to record under `config`: configured values first, then
+    /// whatever was detected from the environment. Empty when disabled.
+    pub fn collect(config: &AuditConfig) -> Self {
+        if !config.enabled {
+            return AuditMetadata::default();
+        }
+        static DETECTED: OnceLock<AuditMetadata> = OnceLock::new();
+        let detected = DETECTED.get_or_init(|| {
+            let mut detected = detect(|name| std::env::var(name).ok());
+            if detected.git_commit.is_none() {
+                detected.git_commit = git_head();
+            }
+            if detected.hostname.is_none() {
+                detected.hostname = system_hostname();
+            }
+            detected
+        });
+        AuditMetadata {
+            git_commit: config
+                .git_commit
+                .clone()
+                .or_else(|| detected.git_commit.clone()),
+            ci_build_url: config
+                .ci_build_url
+                .clone()
+                .or_else(|| detected.ci_build_url.clone()),
+            hostname: config
+                .hostname
+                .clone()
+                .or_else(|| detected.hostname.clone()),
+            waypoint_version: detected.waypoint_version.clone(),
+        }
+    }
+
+    /// Whether nothing was recorded (rows applied by older releases, or with
+    /// `[audit] enabled = false`).
+    pub fn is_empty(&self) -> bool {
+        self.git_commit.is_none()
+            && self.ci_build_url.is_none()
+            && self.hostname.is_none()
+            && self.waypoint_version.is_none()
+    }
+}
+
+/// Commit variables, in the order they are checked.
+const COMMIT_VARS: &[&str] = &[
+    "GITHUB_SHA",
+    "CI_COMMIT_SHA",
+    "GIT_COMMIT",
+    "BUILDKITE_COMMIT",
+    "CIRCLE_SHA1",
+];
+
+/// Build URL variables, in the order they are checked (after GitHub
+/// Actions, whose URL is assembled from several variables).
+const BUILD_URL_VARS: &[&str] = &[
+    "CI_JOB_URL",
+    "BUILD_URL",
+    "BUILDKITE_BUILD_URL",
+    "CIRCLE_BUILD_URL",
+];
+
+/// Detect audit metadata from environment variables read through `var`.
+fn detect(var: impl Fn(&str) -> Option<String>) -> AuditMetadata {
+    let var = |name: &str| var(name).filter(|v| !v.trim().is_empty());
+    let github_run = match (
+        var("GITHUB_SERVER_URL"),
+        var("GITHUB_REPOSITORY"),
+        var("GITHUB_RUN_ID"),
+    ) {
+        (Some(server), Some(repo), Some(run)) => Some(format!(
+            "{}/{}/actions/runs/{}",
+            server.trim_end_matches('/'),
+            repo,
+            run
+        )),
+        _ => None,
+    };
+    AuditMetadata {
+        git_commit: COMMIT_VARS.iter().find_map(|name| var(name)),
+        ci_build_url: github_run.or_else(|| BUILD_URL_VARS.iter().find_map(|name| var(name))),
+        hostname: var("HOSTNAME").or_else(|| var("COMPUTERNAME")),
+        waypoint_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+    }
+}
+
+/// `git rev-parse HEAD` in the working directory, if it is a git checkout.
+fn git_head() -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    let sha = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !sha.is_empty()).then_some(sha)
+}
+
+/// The kernel's hostname, for shells that don't export `HOSTNAME`.
+pub(crate) fn system_hostname() -> Option<String> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+}
+
+/// Record audit metadata on the latest successful history row for a script
+/// (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn store_audit(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    script: &str,
+    audit: &AuditMetadata,
+) -> Result<()> {
+    let fq = format!(
+        "{}.{}",
+        crate::db::quote_ident(schema),
+        crate::db::quote_ident(table)
+    );
+    let sql = format!(
+        "UPDATE {fq} SET git_commit = $1, ci_build_url = $2, hostname = $3, \
+         waypoint_version = $4 WHERE installed_rank = \
+         (SELECT MAX(installed_rank) FROM {fq} WHERE script = $5 AND success = TRUE)"
+    );
+    client
+        .execute(
+            &sql,
+            &[
+                &audit.git_commit,
+                &audit.ci_build_url,
+                &audit.hostname,
+                &audit.waypoint_version,
+                &script,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Record audit metadata on the latest successful history row for a script
+/// (dialect-aware).
+pub async fn store_audit_db(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+    script: &str,
+    audit: &AuditMetadata,
+) -> Result<()> {
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => store_audit(c, schema, table, script, audit).await,
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let fq = client.dialect().qualified_table(schema, table);
+            let mut conn = pool.get_conn().await?;
+            // MySQL can't reference the UPDATE target in a subquery; read the
+            // rank first (see `backup::store_backup_ref_db`).
+            let max_rank: Option<Option<i32>> = conn
+                .exec_first(
+                    format!(
+                        "SELECT MAX(installed_rank) FROM {fq} WHERE script = ? AND success = TRUE"
+                    ),
+                    (script,),
+                )
+                .await?;
+            if let Some(rank) = max_rank.flatten() {
+                conn.exec_drop(
+                    format!(
+                        "UPDATE {fq} SET git_commit = ?, ci_build_url = ?, hostname = ?, \
+                         waypoint_version = ? WHERE installed_rank = ?"
+                    ),
+                    (
+                        &audit.git_commit,
+                        &audit.ci_build_url,
+                        &audit.hostname,
+                        &audit.waypoint_version,
+                        rank,
+                    ),
+                )
+                .await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect_from(vars: &[(&str, &str)]) -> AuditMetadata {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        detect(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_detect_github_actions() {
+        let audit = detect_from(&[
+            ("GITHUB_SHA", "abc123"),
+            ("GITHUB_SERVER_URL", "https://github.com/"),
+            ("GITHUB_REPOSITORY", "acme/billing"),
+            ("GITHUB_RUN_ID", "42"),
+            ("BUILD_URL", "https://jenkins/job/1"),
+            ("HOSTNAME", "runner-7"),
+        ]);
+        assert_eq!(audit.git_commit.as_deref(), Some("abc123"));
+        assert_eq!(
+            audit.ci_build_url.as_deref(),
+            Some("https://github.com/acme/billing/actions/runs/42")
+        );
+        assert_eq!(audit.hostname.as_deref(), Some("runner-7"));
+        assert_eq!(
+            audit.waypoint_version.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+    }
+
+    #[test]
+    fn test_detect_other_ci_and_blank_values() {
+        let audit = detect_from(&[
+            ("GITHUB_SHA", " "),
+            ("CI_COMMIT_SHA", "def456"),
+            ("CI_JOB_URL", "https://gitlab/job/9"),
+        ]);
+        assert_eq!(audit.git_commit.as_deref(), Some("def456"));
+        assert_eq!(audit.ci_build_url.as_deref(), Some("https://gitlab/job/9"));
+        assert_eq!(audit.hostname, None);
+        assert!(!audit.is_empty());
+    }
+
+    #[test]
+    fn test_collect_prefers_config_and_honours_enabled() {
+        let config = AuditConfig {
+            git_commit: Some("pinned".into()),
+            hostname: Some("deploy-box".into()),
+            ..AuditConfig::default()
+        };
+        let audit = AuditMetadata::collect(&config);
+        assert_eq!(audit.git_commit.as_deref(), Some("pinned"));
+        assert_eq!(audit.hostname.as_deref(), Some("deploy-box"));
+        assert!(audit.waypoint_version.is_some());
+
+        let disabled = AuditConfig {
+            enabled: false,
+            ..config
+        };
+        assert!(AuditMetadata::collect(&disabled).is_empty());
+    }
+}
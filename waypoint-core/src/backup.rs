@@ -0,0 +1,300 @@
+//! External backup command run before destructive migrations.
+//!
+//! When `[backup] command` is set, `migrate` runs it (via `sh -c`) before
+//! applying any migration that drops data or receives a DANGER safety verdict.
+//! The last non-empty line the command prints to stdout is taken as the
+//! backup reference (e.g. a pgBackRest label or snapshot ID) and stored in the
+//! history table's `backup_ref` column, so `undo` can point at it when a
+//! migration cannot be reversed automatically.
+
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+use crate::db::DbClient;
+use crate::error::{Result, WaypointError};
+use crate::safety::{is_data_loss, SafetyReport, SafetyVerdict};
+
+/// What to do when the backup command fails or times out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackupFailurePolicy {
+    /// Stop before applying the migration (default).
+    #[default]
+    Abort,
+    /// Log a warning and apply the migration anyway.
+    Warn,
+}
+
+impl std::str::FromStr for BackupFailurePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "abort" | "error" => Ok(Self::Abort),
+            "warn" => Ok(Self::Warn),
+            other => Err(format!("unknown backup on_failure value: '{other}'")),
+        }
+    }
+}
+
+/// Configuration for the `[backup]` section.
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    /// Shell command to run, e.g. `pgbackrest --stanza=main backup --type=incr`.
+    /// `None` disables backups.
+    pub command: Option<String>,
+    /// Kill the command and treat it as failed after this many seconds.
+    pub timeout_secs: u64,
+    /// What to do when the command fails or times out.
+    pub on_failure: BackupFailurePolicy,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            timeout_secs: 3600,
+            on_failure: BackupFailurePolicy::Abort,
+        }
+    }
+}
+
+/// Whether a migration warrants a backup: any statement drops data, or the
+/// safety report (when safety analysis ran) has a DANGER verdict.
+pub fn needs_backup(sql: &str, safety: Option<&SafetyReport>) -> bool {
+    if let Some(report) = safety {
+        if report.overall_verdict == SafetyVerdict::Danger
+            || report.statements.iter().any(|s| s.data_loss)
+        {
+            return true;
+        }
+    }
+    crate::sql_parser::extract_ddl_operations(sql)
+        .iter()
+        .any(is_data_loss)
+}
+
+/// Run the backup command for a migration.
+///
+/// The command sees `WAYPOINT_SCRIPT`, `WAYPOINT_VERSION` (empty for
+/// repeatables), and `WAYPOINT_SCHEMA` in its environment. Returns the backup
+/// reference: the last non-empty stdout line, or the completion time if the
+/// command printed nothing. Returns `Ok(None)` when no command is configured,
+/// or when it failed under the `warn` policy.
+pub async fn run_backup(
+    config: &BackupConfig,
+    script: &str,
+    version: Option<&str>,
+    schema: &str,
+) -> Result<Option<String>> {
+    let Some(command) = config.command.clone() else {
+        return Ok(None);
+    };
+    log::info!(
+        "Running backup before destructive migration; migration={}",
+        script
+    );
+
+    let env = [
+        ("WAYPOINT_SCRIPT", script.to_string()),
+        ("WAYPOINT_VERSION", version.unwrap_or("").to_string()),
+        ("WAYPOINT_SCHEMA", schema.to_string()),
+    ];
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let outcome = tokio::task::spawn_blocking(move || run_command(&command, &env, timeout))
+        .await
+        .unwrap_or_else(|e| Err(format!("backup task panicked: {}", e)));
+
+    match outcome {
+        Ok(stdout) => {
+            let reference = stdout
+                .lines()
+                .rev()
+                .map(str::trim)
+                .find(|l| !l.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    format!(
+                        "backup completed at {}",
+                        chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+                    )
+                });
+            log::info!(
+                "Backup complete; migration={}, backup_ref={}",
+                script,
+                reference
+            );
+            Ok(Some(reference))
+        }
+        Err(reason) => match config.on_failure {
+            BackupFailurePolicy::Abort => Err(WaypointError::BackupFailed {
+                script: script.to_string(),
+                reason,
+            }),
+            BackupFailurePolicy::Warn => {
+                log::warn!(
+                    "Backup failed, continuing without it; migration={}, reason={}",
+                    script,
+                    reason
+                );
+                Ok(None)
+            }
+        },
+    }
+}
+
+/// Run `sh -c command` to completion or timeout, returning its stdout.
+/// Also used for `[hooks]` commands.
+pub(crate) fn run_command(
+    command: &str,
+    env: &[(&str, String)],
+    timeout: Duration,
+) -> std::result::Result<String, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to start command: {}", e))?;
+
+    // Drain the pipes on their own threads so a chatty command can't block on
+    // a full pipe while we wait for it.
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let out_reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout.read_to_string(&mut buf);
+        buf
+    });
+    let err_reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr.read_to_string(&mut buf);
+        buf
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {}s", timeout.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(e) => return Err(format!("failed to wait for command: {}", e)),
+        }
+    };
+
+    let out = out_reader.join().unwrap_or_default();
+    let err = err_reader.join().unwrap_or_default();
+    if status.success() {
+        Ok(out)
+    } else {
+        Err(format!("exited with {}: {}", status, err.trim()))
+    }
+}
+
+/// Record the backup reference on the latest history row for a script,
+/// successful or failed (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn store_backup_ref(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    script: &str,
+    reference: &str,
+) -> Result<()> {
+    let fq = format!(
+        "{}.{}",
+        crate::db::quote_ident(schema),
+        crate::db::quote_ident(table)
+    );
+    let sql = format!(
+        "UPDATE {fq} SET backup_ref = $1 WHERE installed_rank = \
+         (SELECT MAX(installed_rank) FROM {fq} WHERE script = $2)"
+    );
+    client.execute(&sql, &[&reference, &script]).await?;
+    Ok(())
+}
+
+/// Record the backup reference on the latest history row for a script,
+/// successful or failed (dialect-aware).
+pub async fn store_backup_ref_db(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+    script: &str,
+    reference: &str,
+) -> Result<()> {
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => store_backup_ref(c, schema, table, script, reference).await,
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let fq = client.dialect().qualified_table(schema, table);
+            let mut conn = pool.get_conn().await?;
+            // MySQL can't reference the UPDATE target in a subquery; read the
+            // rank first (see `reversal::store_reversal_db`).
+            let max_rank: Option<Option<i32>> = conn
+                .exec_first(
+                    format!("SELECT MAX(installed_rank) FROM {fq} WHERE script = ?"),
+                    (script,),
+                )
+                .await?;
+            if let Some(rank) = max_rank.flatten() {
+                conn.exec_drop(
+                    format!("UPDATE {fq} SET backup_ref = ? WHERE installed_rank = ?"),
+                    (reference, rank),
+                )
+                .await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_backup_static() {
+        assert!(needs_backup("DROP TABLE users;", None));
+        assert!(needs_backup("ALTER TABLE users DROP COLUMN email;", None));
+        assert!(!needs_backup("CREATE TABLE users (id int);", None));
+    }
+
+    #[test]
+    fn test_run_command_reference_and_failure() {
+        let timeout = Duration::from_secs(10);
+        let env = [("WAYPOINT_SCRIPT", "V1__x.sql".to_string())];
+        let out = run_command(
+            "echo starting; echo \"label-$WAYPOINT_SCRIPT\"",
+            &env,
+            timeout,
+        )
+        .unwrap();
+        assert_eq!(out.lines().last(), Some("label-V1__x.sql"));
+
+        let err = run_command("echo boom >&2; exit 3", &env, timeout).unwrap_err();
+        assert!(err.contains("boom"));
+
+        let err = run_command("sleep 5", &env, Duration::from_millis(200)).unwrap_err();
+        assert!(err.contains("timed out"));
+    }
+
+    #[test]
+    fn test_failure_policy_from_str() {
+        assert_eq!("warn".parse(), Ok(BackupFailurePolicy::Warn));
+        assert_eq!("ABORT".parse(), Ok(BackupFailurePolicy::Abort));
+        assert!("retry".parse::<BackupFailurePolicy>().is_err());
+    }
+}
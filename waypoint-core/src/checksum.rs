@@ -1,6 +1,66 @@
-//! CRC32 checksum calculation, compatible with Flyway's line-by-line algorithm.
+//! Migration checksums: Flyway-compatible CRC32 and, optionally, SHA-256.
+//!
+//! The `checksum` history column always holds the CRC32, so Flyway and older
+//! waypoint releases can still read the table. With
+//! `[migrations] checksum_algorithm = "sha256"` the SHA-256 is written to the
+//! `checksum_sha256` column alongside it and becomes the value `validate`
+//! trusts. `checksum_transition = true` lets rows recorded before the switch
+//! (CRC32 only) pass on their CRC32 until `repair` records their SHA-256.
 
 use crc32fast::Hasher;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::WaypointError;
+use crate::history::AppliedMigration;
+use crate::migration::ResolvedMigration;
+
+/// Checksum `validate` verifies applied migrations with
+/// (`[migrations] checksum_algorithm`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    /// CRC32 over the file's lines, as Flyway computes it (default).
+    #[default]
+    Crc32,
+    /// SHA-256 over the file's lines, stored in `checksum_sha256`.
+    Sha256,
+}
+
+impl std::fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChecksumAlgorithm::Crc32 => write!(f, "crc32"),
+            ChecksumAlgorithm::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+impl std::str::FromStr for ChecksumAlgorithm {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "").as_str() {
+            "crc32" => Ok(ChecksumAlgorithm::Crc32),
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            _ => Err(WaypointError::ConfigError(format!(
+                "Invalid checksum_algorithm '{}'. Use 'crc32' or 'sha256'.",
+                s
+            ))),
+        }
+    }
+}
+
+/// How a history row's recorded checksum compares with the file on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumVerdict {
+    /// The file is what was applied.
+    Match,
+    /// The file changed after it was applied.
+    Mismatch,
+    /// `sha256` is required but the row only has a CRC32.
+    Missing,
+}
 
 /// Calculate a CRC32 checksum of the given content, line by line.
 ///
@@ -15,6 +75,92 @@ pub fn calculate_checksum(content: &str) -> i32 {
     hasher.finalize() as i32
 }
 
+/// Calculate a SHA-256 checksum of the given content, line by line, as
+/// lowercase hex.
+///
+/// Each line is hashed without its original line ending and followed by
+/// `\n`, so `\r\n` and `\n` files agree (as with [`calculate_checksum`])
+/// while line boundaries still count.
+pub fn calculate_sha256(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    for line in content.lines() {
+        hasher.update(line.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// CRC32 and SHA-256 of a migration's SQL followed by raw data, for
+/// migrations whose `-- waypoint:copy` files count as part of their content.
+/// The SQL is hashed as in [`calculate_checksum`] and [`calculate_sha256`].
+pub(crate) struct ContentHasher {
+    crc32: Hasher,
+    sha256: Sha256,
+}
+
+impl ContentHasher {
+    pub(crate) fn new(sql: &str) -> Self {
+        let mut crc32 = Hasher::new();
+        let mut sha256 = Sha256::new();
+        for line in sql.lines() {
+            crc32.update(line.as_bytes());
+            sha256.update(line.as_bytes());
+            sha256.update(b"\n");
+        }
+        Self { crc32, sha256 }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.crc32.update(data);
+        self.sha256.update(data);
+    }
+
+    /// The CRC32 and the lowercase hex SHA-256.
+    pub(crate) fn finish(self) -> (i32, String) {
+        let sha256 = self
+            .sha256
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        (self.crc32.finalize() as i32, sha256)
+    }
+}
+
+/// Compare an applied row with its file under `algorithm`.
+///
+/// With `crc32` only the CRC32 is compared; rows without one (baselines)
+/// match. With `sha256` a recorded SHA-256 must match; a row without one is
+/// [`ChecksumVerdict::Missing`], unless `transition` is set, in which case its
+/// CRC32 is compared instead.
+pub fn verify(
+    algorithm: ChecksumAlgorithm,
+    transition: bool,
+    applied: &AppliedMigration,
+    resolved: &ResolvedMigration,
+) -> ChecksumVerdict {
+    let crc32 = || match applied.checksum {
+        Some(c) if c != resolved.checksum => ChecksumVerdict::Mismatch,
+        _ => ChecksumVerdict::Match,
+    };
+    match (algorithm, applied.checksum_sha256.as_deref()) {
+        (ChecksumAlgorithm::Crc32, _) => crc32(),
+        (ChecksumAlgorithm::Sha256, Some(sha)) => {
+            if sha.eq_ignore_ascii_case(&resolved.checksum_sha256()) {
+                ChecksumVerdict::Match
+            } else {
+                ChecksumVerdict::Mismatch
+            }
+        }
+        (ChecksumAlgorithm::Sha256, None) if transition => crc32(),
+        (ChecksumAlgorithm::Sha256, None) => ChecksumVerdict::Missing,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,4 +236,69 @@ mod tests {
 
         assert_eq!(checksum, expected);
     }
+
+    #[test]
+    fn test_sha256_line_ending_normalization() {
+        let unix = calculate_sha256("line1\nline2\n");
+        assert_eq!(unix, calculate_sha256("line1\r\nline2\r\n"));
+        assert_eq!(unix.len(), 64);
+        // Unlike the CRC32, joining lines changes the hash.
+        assert_ne!(calculate_sha256("ab"), calculate_sha256("a\nb"));
+        assert_eq!(calculate_checksum("ab"), calculate_checksum("a\nb"));
+    }
+
+    #[test]
+    fn test_sha256_known_value() {
+        // sha256("SELECT 1;\n")
+        assert_eq!(
+            calculate_sha256("SELECT 1;"),
+            "b4e0497804e46e0a0b0b8c31975b062152d551bac49c3c2e80932567b4085dcd"
+        );
+    }
+
+    #[test]
+    fn test_parse_algorithm() {
+        assert_eq!(
+            "SHA-256".parse::<ChecksumAlgorithm>().unwrap(),
+            ChecksumAlgorithm::Sha256
+        );
+        assert_eq!(
+            "crc32".parse::<ChecksumAlgorithm>().unwrap(),
+            ChecksumAlgorithm::Crc32
+        );
+        assert!("md5".parse::<ChecksumAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V1__a.sql"), "SELECT 1;").unwrap();
+        let resolved = crate::migration::scan_migrations(&[dir.path().to_path_buf()])
+            .unwrap()
+            .remove(0);
+        let row = |checksum: i32, sha: Option<String>| AppliedMigration {
+            checksum: Some(checksum),
+            checksum_sha256: sha,
+            ..AppliedMigration::for_test(1, Some("1"), "V1__a.sql")
+        };
+        let sha = resolved.checksum_sha256();
+        let crc = resolved.checksum;
+        use ChecksumAlgorithm::*;
+        use ChecksumVerdict::*;
+
+        assert_eq!(verify(Crc32, false, &row(crc, None), &resolved), Match);
+        assert_eq!(
+            verify(Crc32, false, &row(1, Some(sha.clone())), &resolved),
+            Mismatch
+        );
+        assert_eq!(verify(Sha256, false, &row(crc, None), &resolved), Missing);
+        assert_eq!(verify(Sha256, true, &row(crc, None), &resolved), Match);
+        assert_eq!(verify(Sha256, true, &row(1, None), &resolved), Mismatch);
+        assert_eq!(verify(Sha256, false, &row(1, Some(sha)), &resolved), Match);
+        // A recorded SHA-256 wins over a matching CRC32, even in transition.
+        assert_eq!(
+            verify(Sha256, true, &row(crc, Some("0".repeat(64))), &resolved),
+            Mismatch
+        );
+    }
 }
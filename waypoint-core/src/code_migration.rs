@@ -0,0 +1,205 @@
+//! Migrations implemented in Rust, for library users (PostgreSQL).
+//!
+//! A [`CodeMigration`] is registered in [`CodeMigrations`] on
+//! `config.migrations.code_migrations` and from then on behaves like a
+//! `V{version}__{description}` file: it is listed by `info`, validated,
+//! ordered among the SQL migrations by version and applied by `migrate` in
+//! its own transaction (or the batch transaction), with its history row
+//! written in the same transaction as its changes.
+//!
+//! There is no file to checksum, so the implementation supplies a stable
+//! hash of its own ([`CodeMigration::stable_hash`]); the recorded CRC32 and
+//! SHA-256 are derived from it, and changing it makes `validate` report a
+//! checksum mismatch just as editing an applied SQL file would.
+//!
+//! `diff --target-migrations` and `simulate` run `up()` when they replay a
+//! code migration. Safety analysis, `lint` (via
+//! [`crate::commands::lint::execute_settings`]) and `explain` have no SQL to
+//! look at and report it as unanalyzable.
+
+use std::fmt;
+use std::sync::Arc;
+
+use tokio_postgres::Client;
+
+use crate::checksum::calculate_checksum;
+use crate::directive::MigrationDirectives;
+use crate::error::{Result, WaypointError};
+use crate::migration::{MigrationKind, MigrationVersion, ResolvedMigration, VersionScheme};
+
+/// A versioned migration implemented in Rust.
+#[async_trait::async_trait]
+pub trait CodeMigration: Send + Sync {
+    /// Version, ordered with the `V` files (e.g. `"3"` or `"3.1"`).
+    fn version(&self) -> &str;
+
+    /// Description recorded in the history table.
+    fn description(&self) -> &str;
+
+    /// Value standing in for the file content when checksumming. Keep it
+    /// stable across builds and change it only when `up` changes, e.g. a
+    /// hand-maintained revision string or a hash of the SQL it runs.
+    fn stable_hash(&self) -> &str;
+
+    /// Apply the migration. Runs inside the migration's transaction; an
+    /// `Err` rolls it back and fails the migration.
+    async fn up(&self, client: &Client) -> Result<()>;
+}
+
+/// Registered [`CodeMigration`]s.
+#[derive(Clone, Default)]
+pub struct CodeMigrations {
+    migrations: Vec<Arc<dyn CodeMigration>>,
+}
+
+impl fmt::Debug for CodeMigrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.migrations.iter().map(|m| script_name(m.as_ref())))
+            .finish()
+    }
+}
+
+impl CodeMigrations {
+    /// Add a migration.
+    pub fn register(&mut self, migration: impl CodeMigration + 'static) {
+        self.migrations.push(Arc::new(migration));
+    }
+
+    /// Whether no migrations are registered.
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty()
+    }
+
+    /// The registered migration resolved as `script`, if any.
+    pub fn find(&self, script: &str) -> Option<&dyn CodeMigration> {
+        self.migrations
+            .iter()
+            .map(Arc::as_ref)
+            .find(|m| script_name(*m) == script)
+    }
+
+    /// Resolve every registered migration for merging with the scanned files.
+    pub(crate) fn resolve(&self, scheme: VersionScheme) -> Result<Vec<ResolvedMigration>> {
+        self.migrations
+            .iter()
+            .map(|m| resolve(m.as_ref(), scheme))
+            .collect()
+    }
+}
+
+/// Script name a code migration is recorded under, `V{version}__{description}.rs`.
+pub fn script_name(migration: &dyn CodeMigration) -> String {
+    format!(
+        "V{}__{}.rs",
+        migration.version(),
+        migration.description().replace(' ', "_")
+    )
+}
+
+fn resolve(migration: &dyn CodeMigration, scheme: VersionScheme) -> Result<ResolvedMigration> {
+    let script = script_name(migration);
+    let version = MigrationVersion::parse_with(migration.version(), scheme).map_err(|e| {
+        WaypointError::MigrationParseError(format!("Code migration '{}': {}", script, e))
+    })?;
+    // The body only feeds the checksums; anything replaying migrations
+    // checks `ResolvedMigration::is_code` and runs `up()` instead.
+    let sql = format!(
+        "-- Rust code migration {}\n-- stable hash: {}\n",
+        script,
+        migration.stable_hash()
+    );
+    Ok(ResolvedMigration {
+        kind: MigrationKind::Versioned(version),
+        description: migration.description().to_string(),
+        checksum: calculate_checksum(&sql),
+        script,
+        sql,
+        directives: MigrationDirectives::default(),
+        copy_checksum_sha256: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Backfill(&'static str);
+
+    #[async_trait::async_trait]
+    impl CodeMigration for Backfill {
+        fn version(&self) -> &str {
+            "2"
+        }
+        fn description(&self) -> &str {
+            "Backfill emails"
+        }
+        fn stable_hash(&self) -> &str {
+            self.0
+        }
+        async fn up(&self, _client: &Client) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_code_migrations_interleave_with_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V1__Create_users.sql"), "CREATE TABLE u();").unwrap();
+        std::fs::write(dir.path().join("V3__Add_index.sql"), "SELECT 1;").unwrap();
+
+        let mut settings = crate::config::MigrationSettings {
+            locations: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        };
+        settings.code_migrations.register(Backfill("rev-1"));
+        let resolved = settings.resolve().unwrap();
+        let scripts: Vec<_> = resolved.iter().map(|m| m.script.as_str()).collect();
+        assert_eq!(
+            scripts,
+            [
+                "V1__Create_users.sql",
+                "V2__Backfill_emails.rs",
+                "V3__Add_index.sql"
+            ]
+        );
+        assert!(settings
+            .code_migrations
+            .find("V2__Backfill_emails.rs")
+            .is_some());
+
+        let mut changed = CodeMigrations::default();
+        changed.register(Backfill("rev-2"));
+        let before = &resolved[1];
+        let after = &changed.resolve(VersionScheme::Numeric).unwrap()[0];
+        assert_ne!(before.checksum, after.checksum);
+        assert_ne!(before.checksum_sha256(), after.checksum_sha256());
+
+        settings.code_migrations.register(Backfill("rev-1"));
+        assert!(settings.resolve().is_err());
+    }
+
+    #[test]
+    fn test_code_migrations_reported_unanalyzable_by_lint() {
+        let mut settings = crate::config::MigrationSettings {
+            locations: vec![],
+            ..Default::default()
+        };
+        settings.code_migrations.register(Backfill("rev-1"));
+        let report = crate::commands::lint::execute_settings(
+            &settings,
+            &crate::config::LintConfig::default(),
+        )
+        .unwrap();
+        let rules: Vec<_> = report.issues.iter().map(|i| i.rule_id.as_str()).collect();
+        assert_eq!(rules, ["I002"]);
+        assert_eq!(report.issues[0].script, "V2__Backfill_emails.rs");
+
+        let safety = crate::safety::SafetyReport::code_migration("V2__Backfill_emails.rs");
+        assert!(safety.unanalyzable);
+        assert_eq!(
+            safety.overall_verdict,
+            crate::safety::SafetyVerdict::Caution
+        );
+    }
+}
@@ -0,0 +1,1242 @@
+//! Generators for multi-migration patterns that are easy to get wrong by hand.
+//!
+//! `assist enum` renames or removes enum labels, which PostgreSQL cannot do
+//! safely in place: `ALTER TYPE ... RENAME VALUE` breaks running code that
+//! still writes the old label, and labels cannot be dropped at all. Instead
+//! it reads the type and every column using it, and generates up to three
+//! migrations:
+//!
+//! 1. `ALTER TYPE ... ADD VALUE` the new names of renamed labels, so old and
+//!    new labels both work while code is deployed;
+//! 2. move rows off the renamed and removed labels in batches, committing
+//!    after each batch;
+//! 3. rename the type to `<type>__old`, create the type again with only the
+//!    new labels, convert each column with `ALTER COLUMN ... TYPE ... USING`
+//!    and drop the old type. The columns keep their indexes and constraints.
+//!
+//! `assist default` splits an `ADD COLUMN ... DEFAULT` that would rewrite the
+//! table (a volatile default, or any default before PostgreSQL 11) into adding
+//! the column without a default, a batched backfill, and — for `NOT NULL`
+//! columns — a validated check constraint before `SET NOT NULL`.
+//!
+//! `assist unique` splits `ADD CONSTRAINT ... UNIQUE (...)`, which builds the
+//! index while holding an ACCESS EXCLUSIVE lock, into a non-transactional
+//! `CREATE UNIQUE INDEX CONCURRENTLY` and an `ADD CONSTRAINT ... USING INDEX`
+//! that only takes the lock briefly.
+//!
+//! `assist fk` splits `ADD CONSTRAINT ... FOREIGN KEY`, which scans the table
+//! to check existing rows while blocking writes, into `ADD ... NOT VALID` and
+//! a `-- waypoint:low-priority` migration that runs `VALIDATE CONSTRAINT`.
+
+use std::sync::LazyLock;
+
+use regex_lite::Regex;
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+use crate::config::WaypointConfig;
+use crate::db::quote_ident;
+use crate::db::DbClient;
+#[cfg(feature = "postgres")]
+use crate::dialect::DialectKind;
+use crate::dialect::PgVersion;
+use crate::error::{Result, WaypointError};
+use crate::safety::{analyze_default_change, DefaultChange, DefaultPattern};
+
+/// Default number of rows updated per backfill batch.
+pub const DEFAULT_BATCH_SIZE: i64 = 5000;
+
+/// Requested change to an enum type.
+#[derive(Debug, Clone)]
+pub struct EnumChangeOptions {
+    /// Enum type name, optionally schema-qualified (`schema.type`).
+    pub type_name: String,
+    /// Labels to remove.
+    pub remove: Vec<String>,
+    /// Labels to rename, as `(old, new)`.
+    pub rename: Vec<(String, String)>,
+    /// Label given to rows that hold a removed label; NULL when unset.
+    pub replace_with: Option<String>,
+    /// Rows updated per backfill batch.
+    pub batch_size: i64,
+}
+
+impl Default for EnumChangeOptions {
+    fn default() -> Self {
+        Self {
+            type_name: String::new(),
+            remove: Vec::new(),
+            rename: Vec::new(),
+            replace_with: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+/// A table column of the enum type.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnumColumn {
+    pub schema: String,
+    pub table: String,
+    pub column: String,
+    /// Whether the column is `NOT NULL` (removed labels then need a
+    /// replacement).
+    pub not_null: bool,
+    /// Column default expression, if any (reset after the type change).
+    pub default: Option<String>,
+}
+
+/// One generated migration script.
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedMigration {
+    /// Description for the filename, e.g. `Enum_status_backfill`.
+    pub description: String,
+    /// Full SQL of the migration.
+    pub sql: String,
+}
+
+/// Result of `assist enum`.
+#[derive(Debug, Serialize)]
+pub struct EnumAssistReport {
+    /// Schema-qualified type name.
+    pub type_name: String,
+    /// Labels before the change, in sort order.
+    pub old_values: Vec<String>,
+    /// Labels after the change, in sort order.
+    pub new_values: Vec<String>,
+    /// Columns that use the type.
+    pub columns: Vec<EnumColumn>,
+    /// The migrations to apply, in order.
+    pub migrations: Vec<GeneratedMigration>,
+}
+
+/// Parse a `--rename` argument of the form `old=new`.
+pub fn parse_rename(s: &str) -> Result<(String, String)> {
+    match s.split_once('=') {
+        Some((old, new)) if !old.trim().is_empty() && !new.trim().is_empty() => {
+            Ok((old.trim().to_string(), new.trim().to_string()))
+        }
+        _ => Err(WaypointError::ConfigError(format!(
+            "Invalid --rename '{}': expected old=new",
+            s
+        ))),
+    }
+}
+
+/// Quote a string as a SQL literal.
+fn literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// A `DO` block that runs `UPDATE table SET assignment` over rows matching
+/// `filter`, `batch_size` rows at a time, committing after each batch. Needs
+/// `-- waypoint:no-transaction` (a `COMMIT` is only allowed outside a
+/// transaction block) and PostgreSQL 11+.
+fn batched_update(table: &str, assignment: &str, filter: &str, batch_size: i64) -> String {
+    format!(
+        "DO $$\nDECLARE\n    n integer;\nBEGIN\n    LOOP\n        \
+         UPDATE {table} SET {assignment}\n        \
+         WHERE ctid IN (\n            \
+         SELECT ctid FROM {table}\n            \
+         WHERE {filter}\n            \
+         LIMIT {batch_size}\n        );\n        \
+         GET DIAGNOSTICS n = ROW_COUNT;\n        \
+         EXIT WHEN n = 0;\n        COMMIT;\n    END LOOP;\nEND\n$$;\n"
+    )
+}
+
+/// Expression mapping `expr` (of the old type) to the new type.
+fn map_expr(expr: &str, options: &EnumChangeOptions, new_type: &str) -> String {
+    let mut arms = String::new();
+    for (old, new) in &options.rename {
+        arms.push_str(&format!(" WHEN {} THEN {}", literal(old), literal(new)));
+    }
+    let replacement = options
+        .replace_with
+        .as_deref()
+        .map_or("NULL".to_string(), literal);
+    for removed in &options.remove {
+        arms.push_str(&format!(" WHEN {} THEN {}", literal(removed), replacement));
+    }
+    format!(
+        "(CASE {}::text{} ELSE {}::text END)::{}",
+        expr, arms, expr, new_type
+    )
+}
+
+/// Rewrite a default such as `'old'::status` for the new labels. Returns
+/// `None` when the default is a removed label (it is then dropped).
+fn map_default(default: &str, options: &EnumChangeOptions) -> Option<String> {
+    let label = default
+        .strip_prefix('\'')
+        .and_then(|rest| rest.split_once("'::"))
+        .map(|(label, _)| label.replace("''", "'"));
+    match label {
+        Some(label) if options.remove.contains(&label) => {
+            options.replace_with.as_deref().map(literal)
+        }
+        Some(label) => match options.rename.iter().find(|(old, _)| *old == label) {
+            Some((_, new)) => Some(literal(new)),
+            None => Some(literal(&label)),
+        },
+        None => Some(default.to_string()),
+    }
+}
+
+/// Build the migrations for an enum change from the type's current labels
+/// and the columns that use it.
+pub fn plan_enum_change(
+    schema: &str,
+    type_name: &str,
+    values: &[String],
+    columns: &[EnumColumn],
+    options: &EnumChangeOptions,
+) -> Result<EnumAssistReport> {
+    let qualified = format!("{}.{}", schema, type_name);
+    if values.is_empty() {
+        return Err(WaypointError::ConfigError(format!(
+            "Enum type '{}' not found",
+            qualified
+        )));
+    }
+    if options.remove.is_empty() && options.rename.is_empty() {
+        return Err(WaypointError::ConfigError(
+            "Nothing to change: pass --remove and/or --rename".to_string(),
+        ));
+    }
+    if options.batch_size <= 0 {
+        return Err(WaypointError::ConfigError(
+            "--batch-size must be positive".to_string(),
+        ));
+    }
+    for label in options
+        .remove
+        .iter()
+        .chain(options.rename.iter().map(|(old, _)| old))
+    {
+        if !values.contains(label) {
+            return Err(WaypointError::ConfigError(format!(
+                "'{}' is not a label of {} (labels: {})",
+                label,
+                qualified,
+                values.join(", ")
+            )));
+        }
+    }
+    // Rows are moved label by label, so a rename target must be new: renaming
+    // onto a label that is itself renamed or removed would move rows twice.
+    if let Some((_, new)) = options.rename.iter().find(|(_, new)| values.contains(new)) {
+        return Err(WaypointError::ConfigError(format!(
+            "Cannot rename to '{}': it is already a label of {}",
+            new, qualified
+        )));
+    }
+
+    let new_values: Vec<String> = values
+        .iter()
+        .filter(|v| !options.remove.contains(v))
+        .map(|v| match options.rename.iter().find(|(old, _)| old == v) {
+            Some((_, new)) => new.clone(),
+            None => v.clone(),
+        })
+        .collect();
+    for (i, v) in new_values.iter().enumerate() {
+        if new_values[..i].contains(v) {
+            return Err(WaypointError::ConfigError(format!(
+                "Label '{}' would appear twice in {}",
+                v, qualified
+            )));
+        }
+    }
+    if let Some(ref r) = options.replace_with {
+        if !new_values.contains(r) {
+            return Err(WaypointError::ConfigError(format!(
+                "--replace-with '{}' is not a label of the changed type (labels: {})",
+                r,
+                new_values.join(", ")
+            )));
+        }
+    }
+
+    if options.replace_with.is_none() && !options.remove.is_empty() {
+        if let Some(c) = columns.iter().find(|c| c.not_null) {
+            return Err(WaypointError::ConfigError(format!(
+                "{}.{}.{} is NOT NULL, so rows holding a removed label need --replace-with",
+                c.schema, c.table, c.column
+            )));
+        }
+    }
+
+    let typ = format!("{}.{}", quote_ident(schema), quote_ident(type_name));
+    let old_type = format!(
+        "{}.{}",
+        quote_ident(schema),
+        quote_ident(&format!("{}__old", type_name))
+    );
+    let labels: Vec<String> = new_values.iter().map(|v| literal(v)).collect();
+    let mut migrations = Vec::new();
+
+    // Step 1: add the new labels next to the old ones, so running code can
+    // keep writing either.
+    let added: Vec<&(String, String)> = options
+        .rename
+        .iter()
+        .filter(|(_, new)| !values.contains(new))
+        .collect();
+    if !added.is_empty() {
+        let mut add = format!(
+            "-- waypoint:no-transaction\n\
+             -- Add the new labels of enum {qualified} next to the old ones. Before\n\
+             -- PostgreSQL 12, ADD VALUE cannot run inside a transaction block.\n\n"
+        );
+        for (old, new) in added {
+            add.push_str(&format!(
+                "ALTER TYPE {typ} ADD VALUE IF NOT EXISTS {} AFTER {};\n",
+                literal(new),
+                literal(old)
+            ));
+        }
+        migrations.push(GeneratedMigration {
+            description: format!("Enum_{}_add_labels", type_name),
+            sql: add,
+        });
+    }
+
+    // Step 2: move rows off the renamed and removed labels in batches. The
+    // targets exist in the type by now; removed labels without a replacement
+    // become NULL and so stop matching.
+    let changed: Vec<String> = options
+        .rename
+        .iter()
+        .map(|(old, _)| old)
+        .chain(&options.remove)
+        .map(|v| literal(v))
+        .collect();
+    if !columns.is_empty() {
+        let mut backfill = format!(
+            "-- waypoint:no-transaction\n\
+             -- Move rows of enum {qualified} off the renamed and removed labels in\n\
+             -- batches of {}, committing after each batch.\n",
+            options.batch_size
+        );
+        for c in columns {
+            let table = format!("{}.{}", quote_ident(&c.schema), quote_ident(&c.table));
+            let col = quote_ident(&c.column);
+            backfill.push('\n');
+            backfill.push_str(&batched_update(
+                &table,
+                &format!("{col} = {}", map_expr(&col, options, &typ)),
+                &format!("{col}::text IN ({})", changed.join(", ")),
+                options.batch_size,
+            ));
+        }
+        migrations.push(GeneratedMigration {
+            description: format!("Enum_{}_backfill", type_name),
+            sql: backfill,
+        });
+    }
+
+    // Step 3: recreate the type without the old labels and convert the
+    // columns in place, which keeps their indexes, constraints and NOT NULL.
+    let mut contract = format!(
+        "-- Drop the old labels of enum {qualified}. Deploy code that no longer\n\
+         -- uses them first. Each ALTER COLUMN TYPE rewrites its table under an\n\
+         -- ACCESS EXCLUSIVE lock; rows still holding an old label are mapped.\n\n\
+         ALTER TYPE {typ} RENAME TO {};\n\
+         CREATE TYPE {typ} AS ENUM ({});\n",
+        quote_ident(&format!("{}__old", type_name)),
+        labels.join(", ")
+    );
+    let mut tables: Vec<(String, Vec<&EnumColumn>)> = Vec::new();
+    for c in columns {
+        let table = format!("{}.{}", quote_ident(&c.schema), quote_ident(&c.table));
+        match tables.iter_mut().find(|(t, _)| *t == table) {
+            Some((_, cols)) => cols.push(c),
+            None => tables.push((table, vec![c])),
+        }
+    }
+    for (table, cols) in &tables {
+        // The old default cannot be cast to the new type; reset it afterwards.
+        let mut clauses = Vec::new();
+        let mut defaults = String::new();
+        for c in cols {
+            let col = quote_ident(&c.column);
+            if let Some(default) = &c.default {
+                clauses.push(format!("ALTER COLUMN {col} DROP DEFAULT"));
+                if let Some(mapped) = map_default(default, options) {
+                    defaults.push_str(&format!(
+                        "ALTER TABLE {table} ALTER COLUMN {col} SET DEFAULT {mapped};\n"
+                    ));
+                }
+            }
+            clauses.push(format!(
+                "ALTER COLUMN {col} TYPE {typ} USING {}",
+                map_expr(&col, options, &typ)
+            ));
+        }
+        contract.push_str(&format!(
+            "\nALTER TABLE {table}\n    {};\n{defaults}",
+            clauses.join(",\n    ")
+        ));
+    }
+    contract.push_str(&format!("\nDROP TYPE {old_type};\n"));
+    migrations.push(GeneratedMigration {
+        description: format!("Enum_{}_drop_old_labels", type_name),
+        sql: contract,
+    });
+
+    Ok(EnumAssistReport {
+        type_name: qualified,
+        old_values: values.to_vec(),
+        new_values,
+        columns: columns.to_vec(),
+        migrations,
+    })
+}
+
+/// Result of `assist default`.
+#[derive(Debug, Serialize)]
+pub struct DefaultAssistReport {
+    /// Analysis of the statement being split.
+    pub change: DefaultChange,
+    /// The migrations to apply instead, in order.
+    pub migrations: Vec<GeneratedMigration>,
+}
+
+/// Split a table-rewriting `ADD COLUMN ... DEFAULT` statement into steps
+/// that don't rewrite the table on PostgreSQL `version`.
+pub fn plan_default_backfill(
+    statement: &str,
+    version: Option<PgVersion>,
+    batch_size: i64,
+) -> Result<DefaultAssistReport> {
+    let change = analyze_default_change(statement, version)
+        .filter(|c| c.pattern == DefaultPattern::AddColumnDefault)
+        .ok_or_else(|| {
+            WaypointError::ConfigError(
+                "Expected an ALTER TABLE ... ADD COLUMN ... DEFAULT statement".to_string(),
+            )
+        })?;
+    if !change.rewrites_table {
+        return Err(WaypointError::ConfigError(format!(
+            "{} does not rewrite the table: {}",
+            change.statement_preview, change.reason
+        )));
+    }
+    if batch_size <= 0 {
+        return Err(WaypointError::ConfigError(
+            "--batch-size must be positive".to_string(),
+        ));
+    }
+
+    let table = &change.table;
+    let col = &change.column;
+    let bare_table = table.rsplit('.').next().unwrap_or(table).trim_matches('"');
+    let bare_col = col.trim_matches('"');
+    let data_type = change.data_type.clone().unwrap_or_default();
+
+    // serial columns become a plain integer column fed by a sequence.
+    let serial_type = match data_type.to_lowercase().as_str() {
+        "smallserial" | "serial2" => Some("smallint"),
+        "serial" | "serial4" => Some("integer"),
+        "bigserial" | "serial8" => Some("bigint"),
+        _ => None,
+    };
+    let mut add = format!(
+        "-- Step 1: add {bare_col} without rewriting {bare_table}. New rows get the\n\
+         -- default; existing rows are filled in by the backfill.\n\n"
+    );
+    let default_expr = match serial_type {
+        Some(int_type) => {
+            let seq = quote_ident(&format!("{}_{}_seq", bare_table, bare_col));
+            add.push_str(&format!(
+                "CREATE SEQUENCE {seq};\n\
+                 ALTER TABLE {table} ADD COLUMN {col} {int_type};\n\
+                 ALTER SEQUENCE {seq} OWNED BY {table}.{col};\n"
+            ));
+            format!("nextval('{}')", seq.replace('\'', "''"))
+        }
+        None => {
+            add.push_str(&format!(
+                "ALTER TABLE {table} ADD COLUMN {col} {data_type};\n"
+            ));
+            change.default_expr.clone()
+        }
+    };
+    add.push_str(&format!(
+        "ALTER TABLE {table} ALTER COLUMN {col} SET DEFAULT {default_expr};\n"
+    ));
+
+    let mut backfill = format!(
+        "-- waypoint:no-transaction\n\
+         -- Step 2: backfill {bare_table}.{bare_col} in batches of {batch_size}, committing\n\
+         -- after each batch.\n\n"
+    );
+    backfill.push_str(&batched_update(
+        table,
+        &format!("{col} = {default_expr}"),
+        &format!("{col} IS NULL"),
+        batch_size,
+    ));
+
+    let mut migrations = vec![
+        GeneratedMigration {
+            description: format!("Add_{}_without_default", bare_col),
+            sql: add,
+        },
+        GeneratedMigration {
+            description: format!("Backfill_{}", bare_col),
+            sql: backfill,
+        },
+    ];
+
+    if change.not_null {
+        let sql = if version.is_none_or(|v| v.has_not_null_from_check()) {
+            let check = quote_ident(&format!("{}_{}_not_null", bare_table, bare_col));
+            format!(
+                "-- Step 3: make {bare_col} NOT NULL. VALIDATE scans the table under SHARE\n\
+                 -- UPDATE EXCLUSIVE, so SET NOT NULL can skip its own scan.\n\n\
+                 ALTER TABLE {table} ADD CONSTRAINT {check} CHECK ({col} IS NOT NULL) NOT VALID;\n\
+                 ALTER TABLE {table} VALIDATE CONSTRAINT {check};\n\
+                 ALTER TABLE {table} ALTER COLUMN {col} SET NOT NULL;\n\
+                 ALTER TABLE {table} DROP CONSTRAINT {check};\n"
+            )
+        } else {
+            format!(
+                "-- Step 3: make {bare_col} NOT NULL (scans the table under an ACCESS\n\
+                 -- EXCLUSIVE lock before PostgreSQL 12).\n\n\
+                 ALTER TABLE {table} ALTER COLUMN {col} SET NOT NULL;\n"
+            )
+        };
+        migrations.push(GeneratedMigration {
+            description: format!("Set_{}_not_null", bare_col),
+            sql,
+        });
+    }
+
+    Ok(DefaultAssistReport { change, migrations })
+}
+
+static ADD_UNIQUE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?is)^ALTER\s+TABLE\s+(?:IF\s+EXISTS\s+)?(?:ONLY\s+)?((?:"[^"]+"|\w+)(?:\.(?:"[^"]+"|\w+))?)\s+ADD\s+(?:CONSTRAINT\s+("[^"]+"|\w+)\s+)?UNIQUE\s*(NULLS\s+(?:NOT\s+)?DISTINCT\s*)?\(([^)]*)\)(.*?)\s*;?\s*$"#,
+    )
+    .unwrap()
+});
+
+static DEFERRABLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(?:NOT\s+)?DEFERRABLE\b|\bINITIALLY\b").unwrap());
+
+static INCLUDE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)^INCLUDE\s*\([^)]*\)").unwrap());
+
+static INDEX_TABLESPACE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bUSING\s+INDEX\s+TABLESPACE\b").unwrap());
+
+/// PostgreSQL's name for an unnamed constraint: `<table>_<columns>_<suffix>`,
+/// shortened to 63 bytes the way `makeObjectName` does it: the longer of the
+/// table and column parts loses a byte at a time until the name fits, then
+/// each part is cut back to a character boundary.
+fn default_constraint_name(table: &str, columns: &str, suffix: &str) -> String {
+    let bare_table = table.rsplit('.').next().unwrap_or(table).trim_matches('"');
+    let cols: Vec<&str> = columns
+        .split(',')
+        .map(|c| c.trim().trim_matches('"'))
+        .collect();
+    let cols = cols.join("_");
+    let available = 63 - (suffix.len() + 2);
+    let (mut table_len, mut cols_len) = (bare_table.len(), cols.len());
+    while table_len + cols_len > available {
+        if table_len > cols_len {
+            table_len -= 1;
+        } else {
+            cols_len -= 1;
+        }
+    }
+    let name = format!(
+        "{}_{}_{}",
+        clip_to_char_boundary(bare_table, table_len),
+        clip_to_char_boundary(&cols, cols_len),
+        suffix
+    );
+    quote_ident(&name)
+}
+
+/// The longest prefix of `s` at most `len` bytes long that ends on a
+/// character boundary.
+fn clip_to_char_boundary(s: &str, mut len: usize) -> &str {
+    while !s.is_char_boundary(len) {
+        len -= 1;
+    }
+    &s[..len]
+}
+
+/// An `ALTER TABLE ... ADD [CONSTRAINT name] UNIQUE (...)` statement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UniqueConstraintAdd {
+    /// Table as written, possibly schema-qualified.
+    pub table: String,
+    /// Constraint name as written, or PostgreSQL's default
+    /// `<table>_<columns>_key` when the statement doesn't name it.
+    pub name: String,
+    /// Column list as written, without the parentheses.
+    pub columns: String,
+    /// `NULLS [NOT] DISTINCT`, if given.
+    pub nulls: Option<String>,
+    /// Index options after the column list (`INCLUDE`, `WITH`, tablespace).
+    pub index_options: String,
+    /// `DEFERRABLE` / `INITIALLY ...` clauses, which stay on the constraint.
+    pub constraint_options: String,
+}
+
+/// Parse a `UNIQUE` constraint addition that builds its own index. Returns
+/// `None` for anything else, including `UNIQUE USING INDEX`.
+pub fn parse_unique_constraint(statement: &str) -> Option<UniqueConstraintAdd> {
+    let stmt = crate::wal::strip_leading_comments(statement).trim();
+    let caps = ADD_UNIQUE_RE.captures(stmt)?;
+    let table = caps[1].to_string();
+    let columns = caps[4].trim().to_string();
+    let name = caps
+        .get(2)
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| default_constraint_name(&table, &columns, "key"));
+    let tail = caps[5].trim();
+    let split = DEFERRABLE_RE.find(tail).map_or(tail.len(), |m| m.start());
+    let index_options = INDEX_TABLESPACE_RE
+        .replace(tail[..split].trim(), "TABLESPACE")
+        .to_string();
+    Some(UniqueConstraintAdd {
+        table,
+        name,
+        columns,
+        nulls: caps.get(3).map(|m| m.as_str().trim().to_uppercase()),
+        index_options,
+        constraint_options: tail[split..].trim().to_string(),
+    })
+}
+
+/// Result of `assist unique`.
+#[derive(Debug, Serialize)]
+pub struct UniqueAssistReport {
+    /// The constraint being added.
+    pub constraint: UniqueConstraintAdd,
+    /// The migrations to apply instead, in order.
+    pub migrations: Vec<GeneratedMigration>,
+}
+
+/// Split `ADD CONSTRAINT ... UNIQUE (...)` into a concurrent index build
+/// and a constraint that attaches the finished index.
+pub fn plan_unique_constraint(statement: &str) -> Result<UniqueAssistReport> {
+    let constraint = parse_unique_constraint(statement).ok_or_else(|| {
+        WaypointError::ConfigError(
+            "Expected an ALTER TABLE ... ADD [CONSTRAINT name] UNIQUE (columns) statement"
+                .to_string(),
+        )
+    })?;
+    let UniqueConstraintAdd {
+        table,
+        name,
+        columns,
+        ..
+    } = &constraint;
+    let bare_name = name.trim_matches('"');
+    let bare_table = table.rsplit('.').next().unwrap_or(table).trim_matches('"');
+    let suffix = |s: &str| {
+        if s.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", s)
+        }
+    };
+    let nulls = suffix(constraint.nulls.as_deref().unwrap_or(""));
+    // CREATE INDEX takes NULLS [NOT] DISTINCT after INCLUDE and before WITH
+    // and TABLESPACE.
+    let options = constraint.index_options.as_str();
+    let include_end = INCLUDE_RE.find(options).map_or(0, |m| m.end());
+    let (include, rest) = options.split_at(include_end);
+
+    let index = format!(
+        "-- waypoint:no-transaction\n\
+         -- Step 1: build the unique index without blocking writes to {bare_table}. If the\n\
+         -- build fails (for example on duplicate values) it leaves an INVALID index\n\
+         -- behind: run DROP INDEX CONCURRENTLY {name} before retrying.\n\n\
+         CREATE UNIQUE INDEX CONCURRENTLY {name} ON {table} ({columns}){}{nulls}{};\n",
+        suffix(include.trim()),
+        suffix(rest.trim())
+    );
+    let attach = format!(
+        "-- Step 2: attach the index as constraint {bare_name}. Takes an ACCESS EXCLUSIVE\n\
+         -- lock only briefly; the index is neither rebuilt nor rescanned.\n\n\
+         ALTER TABLE {table} ADD CONSTRAINT {name} UNIQUE USING INDEX {name}{};\n",
+        suffix(&constraint.constraint_options)
+    );
+
+    Ok(UniqueAssistReport {
+        migrations: vec![
+            GeneratedMigration {
+                description: format!("Create_{}_index", bare_name),
+                sql: index,
+            },
+            GeneratedMigration {
+                description: format!("Attach_{}", bare_name),
+                sql: attach,
+            },
+        ],
+        constraint,
+    })
+}
+
+/// Split a `UNIQUE` constraint addition for the connected server
+/// (PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub fn unique_constraint_db(client: &DbClient, statement: &str) -> Result<UniqueAssistReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => plan_unique_constraint(statement),
+        _ => Err(WaypointError::ConfigError(format!(
+            "assist unique is not supported on {:?} (PostgreSQL only)",
+            client.dialect_kind()
+        ))),
+    }
+}
+
+static ADD_FOREIGN_KEY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?is)^ALTER\s+TABLE\s+(?:IF\s+EXISTS\s+)?(?:ONLY\s+)?((?:"[^"]+"|\w+)(?:\.(?:"[^"]+"|\w+))?)\s+ADD\s+(?:CONSTRAINT\s+("[^"]+"|\w+)\s+)?FOREIGN\s+KEY\s*\(([^)]*)\)\s*REFERENCES\s+(.*?)\s*;?\s*$"#,
+    )
+    .unwrap()
+});
+
+static NOT_VALID_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\s+NOT\s+VALID$").unwrap());
+
+/// An `ALTER TABLE ... ADD [CONSTRAINT name] FOREIGN KEY (...)` statement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForeignKeyAdd {
+    /// Table as written, possibly schema-qualified.
+    pub table: String,
+    /// Constraint name as written, or PostgreSQL's default
+    /// `<table>_<columns>_fkey` when the statement doesn't name it.
+    pub name: String,
+    /// Referencing columns as written, without the parentheses.
+    pub columns: String,
+    /// Everything after `REFERENCES` (target table, columns and actions),
+    /// without `NOT VALID`.
+    pub references: String,
+    /// Whether the statement already says `NOT VALID`.
+    pub not_valid: bool,
+}
+
+/// Parse a foreign key addition. Returns `None` for anything else.
+pub fn parse_foreign_key(statement: &str) -> Option<ForeignKeyAdd> {
+    let stmt = crate::wal::strip_leading_comments(statement).trim();
+    let caps = ADD_FOREIGN_KEY_RE.captures(stmt)?;
+    let table = caps[1].to_string();
+    let columns = caps[3].trim().to_string();
+    let name = caps
+        .get(2)
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| default_constraint_name(&table, &columns, "fkey"));
+    let references = caps[4].trim();
+    let not_valid = NOT_VALID_RE.is_match(references);
+    Some(ForeignKeyAdd {
+        table,
+        name,
+        columns,
+        references: NOT_VALID_RE.replace(references, "").to_string(),
+        not_valid,
+    })
+}
+
+/// Result of `assist fk`.
+#[derive(Debug, Serialize)]
+pub struct ForeignKeyAssistReport {
+    /// The foreign key being added.
+    pub foreign_key: ForeignKeyAdd,
+    /// The migrations to apply instead, in order.
+    pub migrations: Vec<GeneratedMigration>,
+}
+
+/// Split `ADD CONSTRAINT ... FOREIGN KEY` into adding the constraint
+/// `NOT VALID` and validating it in a later, low-priority migration.
+pub fn plan_foreign_key(statement: &str) -> Result<ForeignKeyAssistReport> {
+    let foreign_key = parse_foreign_key(statement).ok_or_else(|| {
+        WaypointError::ConfigError(
+            "Expected an ALTER TABLE ... ADD [CONSTRAINT name] FOREIGN KEY (columns) REFERENCES ... statement"
+                .to_string(),
+        )
+    })?;
+    if foreign_key.not_valid {
+        return Err(WaypointError::ConfigError(format!(
+            "{} is already added NOT VALID; only the VALIDATE CONSTRAINT step is left",
+            foreign_key.name
+        )));
+    }
+    let ForeignKeyAdd {
+        table,
+        name,
+        columns,
+        references,
+        ..
+    } = &foreign_key;
+    let bare_name = name.trim_matches('"');
+    let bare_table = table.rsplit('.').next().unwrap_or(table).trim_matches('"');
+
+    let add = format!(
+        "-- Step 1: add {bare_name} without checking existing rows. Takes a brief SHARE\n\
+         -- ROW EXCLUSIVE lock on both tables; new writes are checked from here on.\n\n\
+         ALTER TABLE {table} ADD CONSTRAINT {name} FOREIGN KEY ({columns}) REFERENCES {references} NOT VALID;\n"
+    );
+    let validate = format!(
+        "-- waypoint:low-priority\n\
+         -- Step 2: check the existing rows of {bare_table}. Holds SHARE UPDATE EXCLUSIVE,\n\
+         -- which doesn't block reads or writes, for the length of the scan.\n\n\
+         ALTER TABLE {table} VALIDATE CONSTRAINT {name};\n"
+    );
+
+    Ok(ForeignKeyAssistReport {
+        migrations: vec![
+            GeneratedMigration {
+                description: format!("Add_{}_not_valid", bare_name),
+                sql: add,
+            },
+            GeneratedMigration {
+                description: format!("Validate_{}", bare_name),
+                sql: validate,
+            },
+        ],
+        foreign_key,
+    })
+}
+
+/// Split a foreign key addition for the connected server (PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub fn foreign_key_db(client: &DbClient, statement: &str) -> Result<ForeignKeyAssistReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => plan_foreign_key(statement),
+        _ => Err(WaypointError::ConfigError(format!(
+            "assist fk is not supported on {:?} (PostgreSQL only)",
+            client.dialect_kind()
+        ))),
+    }
+}
+
+/// Split an `ADD COLUMN ... DEFAULT` for the connected server (PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn default_backfill_db(
+    client: &DbClient,
+    statement: &str,
+    batch_size: i64,
+) -> Result<DefaultAssistReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => {
+            let version = crate::db::get_server_version(client.as_postgres()?)
+                .await
+                .ok();
+            plan_default_backfill(statement, version, batch_size)
+        }
+        _ => Err(WaypointError::ConfigError(format!(
+            "assist default is not supported on {:?} (PostgreSQL only)",
+            client.dialect_kind()
+        ))),
+    }
+}
+
+/// Generate the migrations for an enum change (PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn enum_change_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    options: &EnumChangeOptions,
+) -> Result<EnumAssistReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => enum_change(client.as_postgres()?, config, options).await,
+        _ => Err(WaypointError::ConfigError(format!(
+            "assist enum is not supported on {:?} (PostgreSQL only)",
+            client.dialect_kind()
+        ))),
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn enum_change(
+    client: &Client,
+    config: &WaypointConfig,
+    options: &EnumChangeOptions,
+) -> Result<EnumAssistReport> {
+    let (schema, type_name) = match options.type_name.split_once('.') {
+        Some((s, t)) => (s.to_string(), t.to_string()),
+        None => (config.migrations.schema.clone(), options.type_name.clone()),
+    };
+
+    let values: Vec<String> = client
+        .query(
+            "SELECT e.enumlabel FROM pg_enum e \
+             JOIN pg_type t ON t.oid = e.enumtypid \
+             JOIN pg_namespace n ON n.oid = t.typnamespace \
+             WHERE n.nspname = $1 AND t.typname = $2 \
+             ORDER BY e.enumsortorder",
+            &[&schema, &type_name],
+        )
+        .await?
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+
+    let columns: Vec<EnumColumn> = client
+        .query(
+            "SELECT cn.nspname, c.relname, a.attname, a.attnotnull, \
+                    pg_get_expr(d.adbin, d.adrelid) \
+             FROM pg_attribute a \
+             JOIN pg_class c ON c.oid = a.attrelid \
+             JOIN pg_namespace cn ON cn.oid = c.relnamespace \
+             JOIN pg_type t ON t.oid = a.atttypid \
+             JOIN pg_namespace tn ON tn.oid = t.typnamespace \
+             LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+             WHERE tn.nspname = $1 AND t.typname = $2 \
+               AND a.attnum > 0 AND NOT a.attisdropped AND c.relkind IN ('r', 'p') \
+             ORDER BY 1, 2, 3",
+            &[&schema, &type_name],
+        )
+        .await?
+        .iter()
+        .map(|r| EnumColumn {
+            schema: r.get(0),
+            table: r.get(1),
+            column: r.get(2),
+            not_null: r.get(3),
+            default: r.get(4),
+        })
+        .collect();
+
+    plan_enum_change(&schema, &type_name, &values, &columns, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(remove: &[&str], rename: &[(&str, &str)]) -> EnumChangeOptions {
+        EnumChangeOptions {
+            type_name: "status".to_string(),
+            remove: remove.iter().map(|s| s.to_string()).collect(),
+            rename: rename
+                .iter()
+                .map(|(a, b)| (a.to_string(), b.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn labels(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn column() -> EnumColumn {
+        EnumColumn {
+            schema: "public".to_string(),
+            table: "tasks".to_string(),
+            column: "status".to_string(),
+            not_null: true,
+            default: Some("'todo'::status".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_rename() {
+        assert_eq!(
+            parse_rename("todo=open").unwrap(),
+            ("todo".to_string(), "open".to_string())
+        );
+        assert!(parse_rename("todo").is_err());
+        assert!(parse_rename("=open").is_err());
+    }
+
+    #[test]
+    fn test_plan_remove_and_rename() {
+        let err = plan_enum_change(
+            "public",
+            "status",
+            &labels(&["todo", "done", "archived"]),
+            &[column()],
+            &options(&["archived"], &[("todo", "open")]),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("need --replace-with"));
+
+        let mut nullable = column();
+        nullable.not_null = false;
+        let report = plan_enum_change(
+            "public",
+            "status",
+            &labels(&["todo", "done", "archived"]),
+            &[nullable],
+            &options(&["archived"], &[("todo", "open")]),
+        )
+        .unwrap();
+
+        assert_eq!(report.new_values, labels(&["open", "done"]));
+        assert_eq!(report.migrations.len(), 3);
+
+        let add = &report.migrations[0].sql;
+        assert!(add.starts_with("-- waypoint:no-transaction\n"));
+        assert!(add.contains(
+            r#"ALTER TYPE "public"."status" ADD VALUE IF NOT EXISTS 'open' AFTER 'todo';"#
+        ));
+
+        let backfill = &report.migrations[1].sql;
+        assert!(backfill.starts_with("-- waypoint:no-transaction\n"));
+        assert!(backfill.contains(r#""status"::text IN ('todo', 'archived')"#));
+        assert!(backfill.contains("WHEN 'todo' THEN 'open' WHEN 'archived' THEN NULL"));
+        assert!(backfill.contains("LIMIT 5000"));
+
+        let contract = &report.migrations[2].sql;
+        assert!(contract.contains(r#"ALTER TYPE "public"."status" RENAME TO "status__old";"#));
+        assert!(contract.contains(r#"CREATE TYPE "public"."status" AS ENUM ('open', 'done');"#));
+        assert!(contract.contains(r#"ALTER COLUMN "status" DROP DEFAULT,"#));
+        assert!(contract.contains(r#"ALTER COLUMN "status" TYPE "public"."status" USING (CASE"#));
+        assert!(contract.contains(r#"ALTER COLUMN "status" SET DEFAULT 'open';"#));
+        assert!(contract.contains(r#"DROP TYPE "public"."status__old";"#));
+        assert!(!contract.contains("DROP COLUMN"));
+    }
+
+    #[test]
+    fn test_plan_replace_with() {
+        let mut opts = options(&["archived"], &[]);
+        opts.replace_with = Some("done".to_string());
+        let report = plan_enum_change(
+            "public",
+            "status",
+            &labels(&["todo", "done", "archived"]),
+            &[column()],
+            &opts,
+        )
+        .unwrap();
+        // Nothing to add: removing alone starts at the backfill.
+        assert_eq!(report.migrations.len(), 2);
+        assert!(report.migrations[0]
+            .sql
+            .contains("WHEN 'archived' THEN 'done'"));
+        assert!(report.migrations[1]
+            .sql
+            .contains(r#"ALTER COLUMN "status" SET DEFAULT 'todo';"#));
+
+        opts.replace_with = Some("archived".to_string());
+        assert!(plan_enum_change(
+            "public",
+            "status",
+            &labels(&["todo", "archived"]),
+            &[],
+            &opts
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_plan_rejects_bad_requests() {
+        let values = labels(&["todo", "done"]);
+        assert!(plan_enum_change("public", "status", &[], &[], &options(&["x"], &[])).is_err());
+        assert!(plan_enum_change("public", "status", &values, &[], &options(&[], &[])).is_err());
+        assert!(plan_enum_change(
+            "public",
+            "status",
+            &values,
+            &[],
+            &options(&["missing"], &[])
+        )
+        .is_err());
+        assert!(plan_enum_change(
+            "public",
+            "status",
+            &values,
+            &[],
+            &options(&[], &[("todo", "done")])
+        )
+        .is_err());
+        // Renaming onto a label that is removed would move its rows twice.
+        assert!(plan_enum_change(
+            "public",
+            "status",
+            &values,
+            &[],
+            &options(&["done"], &[("todo", "done")])
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("already a label"));
+    }
+
+    #[test]
+    fn test_map_default() {
+        let opts = options(&["archived"], &[("todo", "open")]);
+        assert_eq!(
+            map_default("'todo'::status", &opts).as_deref(),
+            Some("'open'")
+        );
+        assert_eq!(
+            map_default("'done'::status", &opts).as_deref(),
+            Some("'done'")
+        );
+        assert_eq!(map_default("'archived'::status", &opts), None);
+        assert_eq!(
+            map_default("default_status()", &opts).as_deref(),
+            Some("default_status()")
+        );
+    }
+
+    #[test]
+    fn test_default_backfill_volatile_not_null() {
+        let v16 = Some(PgVersion::from_version_num(160002));
+        let report = plan_default_backfill(
+            "ALTER TABLE orders ADD COLUMN token uuid NOT NULL DEFAULT gen_random_uuid();",
+            v16,
+            1000,
+        )
+        .unwrap();
+        assert_eq!(report.migrations.len(), 3);
+        assert!(report.migrations[0]
+            .sql
+            .contains("ALTER TABLE orders ADD COLUMN token uuid;"));
+        assert!(report.migrations[0]
+            .sql
+            .contains("ALTER TABLE orders ALTER COLUMN token SET DEFAULT gen_random_uuid();"));
+        assert!(report.migrations[1]
+            .sql
+            .contains("UPDATE orders SET token = gen_random_uuid()"));
+        assert!(report.migrations[1].sql.contains("WHERE token IS NULL"));
+        assert!(report.migrations[2].sql.contains(
+            r#"ADD CONSTRAINT "orders_token_not_null" CHECK (token IS NOT NULL) NOT VALID"#
+        ));
+    }
+
+    #[test]
+    fn test_default_backfill_serial_and_rejections() {
+        let v16 = Some(PgVersion::from_version_num(160002));
+        let report =
+            plan_default_backfill("ALTER TABLE t ADD COLUMN n bigserial", v16, 500).unwrap();
+        assert_eq!(report.migrations.len(), 2);
+        let add = &report.migrations[0].sql;
+        assert!(add.contains(r#"CREATE SEQUENCE "t_n_seq";"#));
+        assert!(add.contains("ADD COLUMN n bigint;"));
+        assert!(add.contains(r#"SET DEFAULT nextval('"t_n_seq"');"#));
+
+        // A constant default is already metadata-only on PG 11+.
+        let err = plan_default_backfill("ALTER TABLE t ADD COLUMN c int DEFAULT 0", v16, 500)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not rewrite"));
+        assert!(plan_default_backfill("ALTER TABLE t DROP COLUMN c", v16, 500).is_err());
+    }
+
+    #[test]
+    fn test_parse_unique_constraint() {
+        let c = parse_unique_constraint(
+            "ALTER TABLE app.users ADD CONSTRAINT users_email_key UNIQUE (email) INCLUDE (id) DEFERRABLE INITIALLY DEFERRED;",
+        )
+        .unwrap();
+        assert_eq!(c.table, "app.users");
+        assert_eq!(c.name, "users_email_key");
+        assert_eq!(c.columns, "email");
+        assert_eq!(c.index_options, "INCLUDE (id)");
+        assert_eq!(c.constraint_options, "DEFERRABLE INITIALLY DEFERRED");
+
+        // Unnamed constraints get PostgreSQL's default name.
+        let c = parse_unique_constraint(
+            "alter table orders add unique nulls not distinct (tenant_id, \"Ref\")",
+        )
+        .unwrap();
+        assert_eq!(c.name, r#""orders_tenant_id_Ref_key""#);
+        assert_eq!(c.nulls.as_deref(), Some("NULLS NOT DISTINCT"));
+        // Long names are shortened to 63 bytes, the longer part first and
+        // never inside a multibyte character, as PostgreSQL does.
+        // The table is 27 characters but 51 bytes.
+        let c = parse_unique_constraint(
+            r#"ALTER TABLE "заказы_клиентов_по_регионам" ADD UNIQUE (customer_reference_number)"#,
+        )
+        .unwrap();
+        let name = c.name.trim_matches('"');
+        assert_eq!(name, "заказы_клиентов_п_customer_reference_number_key");
+        assert_eq!(name.len(), 62);
+
+        assert!(parse_unique_constraint(
+            "ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE USING INDEX users_email_idx"
+        )
+        .is_none());
+        assert!(parse_unique_constraint("ALTER TABLE users ADD PRIMARY KEY (id)").is_none());
+    }
+
+    #[test]
+    fn test_plan_unique_constraint() {
+        let report = plan_unique_constraint(
+            "ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email) USING INDEX TABLESPACE fast",
+        )
+        .unwrap();
+        assert_eq!(report.migrations.len(), 2);
+        let index = &report.migrations[0];
+        assert_eq!(index.description, "Create_users_email_key_index");
+        assert!(index.sql.starts_with("-- waypoint:no-transaction\n"));
+        assert!(index.sql.contains(
+            "CREATE UNIQUE INDEX CONCURRENTLY users_email_key ON users (email) TABLESPACE fast;"
+        ));
+        assert!(report.migrations[1].sql.contains(
+            "ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE USING INDEX users_email_key;"
+        ));
+
+        let report = plan_unique_constraint(
+            "ALTER TABLE users ADD UNIQUE NULLS NOT DISTINCT (email) INCLUDE (id) WITH (fillfactor = 90)",
+        )
+        .unwrap();
+        assert!(report.migrations[0].sql.contains(
+            r#"ON users (email) INCLUDE (id) NULLS NOT DISTINCT WITH (fillfactor = 90);"#
+        ));
+
+        assert!(plan_unique_constraint("ALTER TABLE users ADD COLUMN email text").is_err());
+    }
+
+    #[test]
+    fn test_plan_foreign_key() {
+        let report = plan_foreign_key(
+            "ALTER TABLE orders ADD FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;",
+        )
+        .unwrap();
+        assert_eq!(report.foreign_key.name, r#""orders_user_id_fkey""#);
+        assert_eq!(
+            report.migrations[0].description,
+            "Add_orders_user_id_fkey_not_valid"
+        );
+        assert!(report.migrations[0].sql.contains(
+            r#"ALTER TABLE orders ADD CONSTRAINT "orders_user_id_fkey" FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE NOT VALID;"#
+        ));
+        let validate = &report.migrations[1].sql;
+        assert!(validate.starts_with("-- waypoint:low-priority\n"));
+        assert!(
+            validate.contains(r#"ALTER TABLE orders VALIDATE CONSTRAINT "orders_user_id_fkey";"#)
+        );
+        assert!(
+            crate::directive::parse_directives(validate)
+                .unwrap()
+                .low_priority
+        );
+    }
+
+    #[test]
+    fn test_plan_foreign_key_rejections() {
+        let fk = parse_foreign_key(
+            "ALTER TABLE orders ADD CONSTRAINT orders_user_fk FOREIGN KEY (user_id) REFERENCES users NOT VALID",
+        )
+        .unwrap();
+        assert!(fk.not_valid);
+        assert_eq!(fk.references, "users");
+        assert!(plan_foreign_key(
+            "ALTER TABLE orders ADD CONSTRAINT orders_user_fk FOREIGN KEY (user_id) REFERENCES users NOT VALID"
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("already added NOT VALID"));
+        assert!(plan_foreign_key("ALTER TABLE orders ADD CHECK (total > 0)").is_err());
+    }
+}
@@ -0,0 +1,478 @@
+//! Reconcile every history row with the migration files (`waypoint audit history`).
+//!
+//! `validate` answers "may migrate run?" and stops at the first kind of
+//! problem it cares about. The audit instead walks the whole history table
+//! and reports every row that doesn't line up with the files on disk, each
+//! as a categorized [`HistoryFinding`]: applied versions whose file is gone,
+//! files edited after they were applied, undo rows without their `U` file,
+//! rows whose type disagrees with their script (or a `BASELINE` row over a
+//! real migration), renamed scripts, and versions whose last attempt failed.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::checksum::{self, ChecksumVerdict};
+use crate::config::{MigrationSettings, WaypointConfig};
+use crate::db::DbClient;
+use crate::error::Result;
+use crate::history::{self, AppliedMigration};
+use crate::migration::{parse_migration_filename, MigrationKind, ResolvedMigration};
+
+/// Category of an audit finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FindingKind {
+    /// An applied migration's file is no longer on disk.
+    MissingFile,
+    /// A file was modified after it was applied.
+    ChecksumMismatch,
+    /// No SHA-256 was recorded although `checksum_algorithm = "sha256"`.
+    ChecksumNotRecorded,
+    /// An undo row whose `U` file is no longer on disk.
+    UndoFileMissing,
+    /// The row's type disagrees with its script, or a `BASELINE` row sits
+    /// on a version that has a migration file.
+    TypeMismatch,
+    /// The file for an applied version has a different name than the row.
+    ScriptRenamed,
+    /// The latest attempt at a migration failed.
+    FailedMigration,
+}
+
+impl std::fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FindingKind::MissingFile => "missing-file",
+            FindingKind::ChecksumMismatch => "checksum-mismatch",
+            FindingKind::ChecksumNotRecorded => "checksum-not-recorded",
+            FindingKind::UndoFileMissing => "undo-file-missing",
+            FindingKind::TypeMismatch => "type-mismatch",
+            FindingKind::ScriptRenamed => "script-renamed",
+            FindingKind::FailedMigration => "failed-migration",
+        })
+    }
+}
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingSeverity {
+    /// The history no longer describes the files (or the database).
+    Error,
+    /// Worth a look, but consistent with normal use.
+    Warning,
+}
+
+/// One history row that doesn't line up with the files.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryFinding {
+    /// Category of the finding.
+    pub kind: FindingKind,
+    /// Error or warning.
+    pub severity: FindingSeverity,
+    /// `installed_rank` of the row.
+    pub installed_rank: i32,
+    /// Version of the row, `None` for repeatables.
+    pub version: Option<String>,
+    /// Script recorded in the row.
+    pub script: String,
+    /// Human-readable description.
+    pub message: String,
+}
+
+/// Result of `waypoint audit history`.
+#[derive(Debug, Serialize)]
+pub struct HistoryAuditReport {
+    /// History rows examined.
+    pub rows_checked: usize,
+    /// Migration files found in the configured locations.
+    pub files_checked: usize,
+    /// Every finding, in `installed_rank` order.
+    pub findings: Vec<HistoryFinding>,
+    /// Number of findings per category.
+    pub summary: BTreeMap<FindingKind, usize>,
+    /// Findings with [`FindingSeverity::Error`].
+    pub error_count: usize,
+    /// Findings with [`FindingSeverity::Warning`].
+    pub warning_count: usize,
+}
+
+/// Execute the history audit (dialect-aware entry).
+pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<HistoryAuditReport> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+    let applied = if history::history_table_exists_db(client, &schema, table).await? {
+        history::get_applied_migrations_db(client, &schema, table).await?
+    } else {
+        Vec::new()
+    };
+    let resolved = config.migrations.resolve()?;
+    Ok(audit(&applied, &resolved, &config.migrations))
+}
+
+/// Cross-check `applied` (in `installed_rank` order) against `resolved`.
+pub fn audit(
+    applied: &[AppliedMigration],
+    resolved: &[ResolvedMigration],
+    settings: &MigrationSettings,
+) -> HistoryAuditReport {
+    let mut versioned: HashMap<&str, &ResolvedMigration> = HashMap::new();
+    let mut by_script: HashMap<&str, &ResolvedMigration> = HashMap::new();
+    for m in resolved {
+        if let MigrationKind::Versioned(v) = &m.kind {
+            versioned.insert(v.raw.as_str(), m);
+        }
+        by_script.insert(m.script.as_str(), m);
+    }
+
+    // The row each version or repeatable script ended on, successful or not,
+    // and the last successful forward row of each version.
+    let mut last_attempt: HashMap<(Option<&str>, &str), &AppliedMigration> = HashMap::new();
+    let mut last_forward: HashMap<&str, &AppliedMigration> = HashMap::new();
+    let mut last_repeatable: HashMap<&str, &AppliedMigration> = HashMap::new();
+    for am in applied {
+        let key = match &am.version {
+            Some(v) => (Some(v.as_str()), ""),
+            None => (None, am.script.as_str()),
+        };
+        last_attempt.insert(key, am);
+        if !am.success {
+            continue;
+        }
+        match (&am.version, am.migration_type.as_str()) {
+            (Some(v), t) if t != "UNDO_SQL" => {
+                last_forward.insert(v.as_str(), am);
+            }
+            (None, _) => {
+                last_repeatable.insert(am.script.as_str(), am);
+            }
+            _ => {}
+        }
+    }
+    let effective = history::effective_applied_versions(applied);
+
+    let mut findings = Vec::new();
+    let mut push = |am: &AppliedMigration, kind, severity, message: String| {
+        findings.push(HistoryFinding {
+            kind,
+            severity,
+            installed_rank: am.installed_rank,
+            version: am.version.clone(),
+            script: am.script.clone(),
+            message,
+        });
+    };
+
+    for am in applied {
+        if let Some(expected) = expected_type(&am.script) {
+            let forward = matches!(am.migration_type.as_str(), "SQL" | "RECONCILED");
+            if !(am.migration_type == expected || forward && expected == "SQL") {
+                push(
+                    am,
+                    FindingKind::TypeMismatch,
+                    FindingSeverity::Warning,
+                    format!(
+                        "Recorded as {} but the script name is a {} migration",
+                        am.migration_type, expected
+                    ),
+                );
+            }
+        }
+
+        if !am.success {
+            let key = match &am.version {
+                Some(v) => (Some(v.as_str()), ""),
+                None => (None, am.script.as_str()),
+            };
+            if last_attempt
+                .get(&key)
+                .is_some_and(|last| last.installed_rank == am.installed_rank)
+            {
+                push(
+                    am,
+                    FindingKind::FailedMigration,
+                    FindingSeverity::Error,
+                    "The latest attempt failed and was never repaired or retried".to_string(),
+                );
+            }
+            continue;
+        }
+
+        match (&am.version, am.migration_type.as_str()) {
+            (Some(_), "UNDO_SQL") => {
+                // Undo through a stored reversal has no file to check.
+                if am.script.starts_with("auto-reversal:") {
+                    continue;
+                }
+                match by_script.get(am.script.as_str()) {
+                    None => push(
+                        am,
+                        FindingKind::UndoFileMissing,
+                        FindingSeverity::Warning,
+                        "Undo file is no longer on disk".to_string(),
+                    ),
+                    Some(file) if am.checksum.is_some_and(|c| c != file.checksum) => push(
+                        am,
+                        FindingKind::ChecksumMismatch,
+                        FindingSeverity::Warning,
+                        "Undo file was modified after it ran".to_string(),
+                    ),
+                    Some(_) => {}
+                }
+            }
+            (Some(version), migration_type) => {
+                // Only the row a version currently stands on is checked.
+                let current = effective.contains(version)
+                    && last_forward
+                        .get(version.as_str())
+                        .is_some_and(|last| last.installed_rank == am.installed_rank);
+                if !current {
+                    continue;
+                }
+                let file = versioned.get(version.as_str());
+                if migration_type == "BASELINE" {
+                    if let Some(file) = file {
+                        push(
+                            am,
+                            FindingKind::TypeMismatch,
+                            FindingSeverity::Warning,
+                            format!(
+                                "Recorded as BASELINE but {} exists; its SQL never ran on this database",
+                                file.script
+                            ),
+                        );
+                    }
+                    continue;
+                }
+                let Some(file) = file else {
+                    push(
+                        am,
+                        FindingKind::MissingFile,
+                        FindingSeverity::Error,
+                        format!("Applied version {} has no migration file on disk", version),
+                    );
+                    continue;
+                };
+                if file.script != am.script {
+                    push(
+                        am,
+                        FindingKind::ScriptRenamed,
+                        FindingSeverity::Warning,
+                        format!("The file for version {} is now {}", version, file.script),
+                    );
+                }
+                match checksum::verify(
+                    settings.checksum_algorithm,
+                    settings.checksum_transition,
+                    am,
+                    file,
+                ) {
+                    ChecksumVerdict::Match => {}
+                    ChecksumVerdict::Mismatch => push(
+                        am,
+                        FindingKind::ChecksumMismatch,
+                        FindingSeverity::Error,
+                        format!("{} was modified after it was applied", file.script),
+                    ),
+                    ChecksumVerdict::Missing => push(
+                        am,
+                        FindingKind::ChecksumNotRecorded,
+                        FindingSeverity::Warning,
+                        "No SHA-256 recorded; run 'waypoint repair' to record it".to_string(),
+                    ),
+                }
+            }
+            (None, _) => {
+                let latest = last_repeatable
+                    .get(am.script.as_str())
+                    .is_some_and(|last| last.installed_rank == am.installed_rank);
+                if latest && !by_script.contains_key(am.script.as_str()) {
+                    push(
+                        am,
+                        FindingKind::MissingFile,
+                        FindingSeverity::Warning,
+                        "Repeatable migration is no longer on disk".to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    let mut summary = BTreeMap::new();
+    for f in &findings {
+        *summary.entry(f.kind).or_insert(0) += 1;
+    }
+    let error_count = findings
+        .iter()
+        .filter(|f| f.severity == FindingSeverity::Error)
+        .count();
+    HistoryAuditReport {
+        rows_checked: applied.len(),
+        files_checked: resolved.len(),
+        warning_count: findings.len() - error_count,
+        error_count,
+        summary,
+        findings,
+    }
+}
+
+/// The history type a script name implies, or `None` if it isn't a
+/// migration filename (baseline markers, auto-reversals, imported rows).
+fn expected_type(script: &str) -> Option<&'static str> {
+    match parse_migration_filename(script).ok()?.0 {
+        MigrationKind::Versioned(_) => Some("SQL"),
+        MigrationKind::Repeatable => Some("SQL_REPEATABLE"),
+        MigrationKind::Undo(_) => Some("UNDO_SQL"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::ChecksumAlgorithm;
+
+    fn row(
+        rank: i32,
+        version: Option<&str>,
+        migration_type: &str,
+        script: &str,
+        checksum: Option<i32>,
+        success: bool,
+    ) -> AppliedMigration {
+        AppliedMigration {
+            migration_type: migration_type.to_string(),
+            checksum,
+            execution_time: 1,
+            success,
+            ..AppliedMigration::for_test(rank, version, script)
+        }
+    }
+
+    fn kinds(report: &HistoryAuditReport) -> Vec<(i32, FindingKind)> {
+        report
+            .findings
+            .iter()
+            .map(|f| (f.installed_rank, f.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_audit_categorizes_history_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, sql) in [
+            ("V1__Init.sql", "SELECT 1;"),
+            ("V2__Users.sql", "SELECT 2;"),
+            ("V3__Renamed_orders.sql", "SELECT 3;"),
+            ("V5__Base.sql", "SELECT 5;"),
+            ("R__View.sql", "SELECT 'v';"),
+        ] {
+            std::fs::write(dir.path().join(name), sql).unwrap();
+        }
+        let resolved = crate::migration::scan_migrations(&[dir.path().to_path_buf()]).unwrap();
+        let sum = |script: &str| {
+            resolved
+                .iter()
+                .find(|m| m.script == script)
+                .map(|m| m.checksum)
+        };
+
+        let applied = vec![
+            row(
+                1,
+                Some("1"),
+                "SQL",
+                "V1__Init.sql",
+                sum("V1__Init.sql"),
+                true,
+            ),
+            // Edited after it was applied.
+            row(2, Some("2"), "SQL", "V2__Users.sql", Some(42), true),
+            // Renamed on disk since.
+            row(
+                3,
+                Some("3"),
+                "SQL",
+                "V3__Orders.sql",
+                sum("V3__Renamed_orders.sql"),
+                true,
+            ),
+            // File deleted.
+            row(4, Some("4"), "SQL", "V4__Gone.sql", Some(4), true),
+            // Baselined over a real migration.
+            row(
+                5,
+                Some("5"),
+                "BASELINE",
+                "<< Flyway Baseline >>",
+                None,
+                true,
+            ),
+            // Undone by a U file that no longer exists, then by a stored reversal.
+            row(6, Some("6"), "SQL", "V6__Tmp.sql", Some(6), true),
+            row(7, Some("6"), "UNDO_SQL", "U6__Tmp.sql", Some(6), true),
+            row(8, Some("7"), "SQL", "V7__Tmp.sql", Some(7), true),
+            row(9, Some("7"), "UNDO_SQL", "auto-reversal:V7", None, true),
+            // Failed and never retried; failed then retried successfully.
+            row(10, Some("8"), "SQL", "V8__Broken.sql", Some(8), false),
+            row(11, None, "SQL_REPEATABLE", "R__View.sql", Some(1), false),
+            row(
+                12,
+                None,
+                "SQL_REPEATABLE",
+                "R__View.sql",
+                sum("R__View.sql"),
+                true,
+            ),
+            // Type disagrees with the script; repeatable removed from disk.
+            row(13, None, "SQL", "R__Old_view.sql", Some(13), true),
+        ];
+        let report = audit(&applied, &resolved, &MigrationSettings::default());
+
+        assert_eq!(
+            kinds(&report),
+            vec![
+                (2, FindingKind::ChecksumMismatch),
+                (3, FindingKind::ScriptRenamed),
+                (4, FindingKind::MissingFile),
+                (5, FindingKind::TypeMismatch),
+                (7, FindingKind::UndoFileMissing),
+                (10, FindingKind::FailedMigration),
+                (13, FindingKind::TypeMismatch),
+                (13, FindingKind::MissingFile),
+            ]
+        );
+        assert_eq!(report.rows_checked, 13);
+        assert_eq!(report.error_count, 3);
+        assert_eq!(report.warning_count, 5);
+        assert_eq!(report.summary[&FindingKind::TypeMismatch], 2);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["findings"][0]["kind"], "checksum-mismatch");
+        assert_eq!(json["summary"]["missing-file"], 2);
+    }
+
+    #[test]
+    fn test_audit_sha256_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V1__Init.sql"), "SELECT 1;").unwrap();
+        let resolved = crate::migration::scan_migrations(&[dir.path().to_path_buf()]).unwrap();
+        let applied = vec![row(
+            1,
+            Some("1"),
+            "SQL",
+            "V1__Init.sql",
+            Some(resolved[0].checksum),
+            true,
+        )];
+        let settings = MigrationSettings {
+            checksum_algorithm: ChecksumAlgorithm::Sha256,
+            ..MigrationSettings::default()
+        };
+        let report = audit(&applied, &resolved, &settings);
+        assert_eq!(kinds(&report), vec![(1, FindingKind::ChecksumNotRecorded)]);
+        assert_eq!(report.error_count, 0);
+
+        let clean = audit(&applied, &resolved, &MigrationSettings::default());
+        assert!(clean.findings.is_empty());
+    }
+}
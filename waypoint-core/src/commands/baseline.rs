@@ -6,7 +6,7 @@ use tokio_postgres::Client;
 use crate::config::WaypointConfig;
 #[cfg(feature = "postgres")]
 use crate::db;
-use crate::db::DbClient;
+use crate::db::{DbClient, LockTarget};
 use crate::error::{Result, WaypointError};
 use crate::history;
 
@@ -22,13 +22,13 @@ pub async fn execute(
     baseline_version: Option<&str>,
     baseline_description: Option<&str>,
 ) -> Result<()> {
-    let table = &config.migrations.table;
+    let lock = LockTarget::new(&config.migrations, &config.migrations.schema);
 
-    db::acquire_advisory_lock(client, table).await?;
+    db::acquire_advisory_lock(client, &lock).await?;
 
     let result = execute_inner_pg(client, config, baseline_version, baseline_description).await;
 
-    if let Err(e) = db::release_advisory_lock(client, table).await {
+    if let Err(e) = db::release_advisory_lock(client, &lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -89,13 +89,16 @@ pub async fn execute_db(
     baseline_version: Option<&str>,
     baseline_description: Option<&str>,
 ) -> Result<()> {
-    let table = &config.migrations.table;
+    let lock = LockTarget::new(
+        &config.migrations,
+        &client.resolve_schema(&config.migrations.schema).await?,
+    );
 
-    client.acquire_lock(table).await?;
+    client.acquire_lock(&lock).await?;
 
     let result = execute_inner_db(client, config, baseline_version, baseline_description).await;
 
-    if let Err(e) = client.release_lock(table).await {
+    if let Err(e) = client.release_lock(&lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
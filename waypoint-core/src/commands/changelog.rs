@@ -2,13 +2,18 @@
 //!
 //! Parses migration files and extracts DDL operations to produce
 //! a structured changelog in markdown, plain text, or JSON format.
+//! [`attach_deployments`] adds when, and from where, each migration was
+//! applied, taken from the history table.
 
 use std::path::PathBuf;
 
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 
+use crate::audit::AuditMetadata;
+use crate::commands::info::{MigrationInfo, MigrationState};
 use crate::error::Result;
-use crate::migration::{scan_migrations, MigrationKind, MigrationVersion};
+use crate::migration::{scan_migrations_with_limits, MigrationKind, MigrationVersion, ScanLimits};
 use crate::sql_parser::{extract_ddl_operations, DdlOperation};
 
 /// Supported output formats for the changelog.
@@ -41,8 +46,52 @@ pub struct VersionChanges {
     pub description: String,
     /// Filename of the migration script.
     pub script: String,
+    /// Application order of a repeatable migration (`R{order}__` or
+    /// `-- waypoint:order`), if it has one.
+    pub order: Option<u32>,
     /// DDL operations extracted from the migration SQL.
     pub changes: Vec<DdlOperation>,
+    /// When and where the migration was applied, once
+    /// [`attach_deployments`] has run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployed: Option<Deployment>,
+}
+
+/// When and where a migration was applied.
+#[derive(Debug, Clone, Serialize)]
+pub struct Deployment {
+    /// Timestamp of the history row.
+    pub installed_on: DateTime<Utc>,
+    /// Audit metadata recorded with the row.
+    #[serde(flatten)]
+    pub audit: AuditMetadata,
+}
+
+impl Deployment {
+    /// `2026-03-01 12:00 UTC, commit abc123, host ci-7, build <url>`.
+    fn label(&self) -> String {
+        let mut parts = vec![self.installed_on.format("%Y-%m-%d %H:%M UTC").to_string()];
+        if let Some(ref commit) = self.audit.git_commit {
+            parts.push(format!("commit {}", commit));
+        }
+        if let Some(ref host) = self.audit.hostname {
+            parts.push(format!("host {}", host));
+        }
+        if let Some(ref url) = self.audit.ci_build_url {
+            parts.push(format!("build {}", url));
+        }
+        parts.join(", ")
+    }
+}
+
+impl VersionChanges {
+    /// `(Repeatable)`, or `(Repeatable, order 10)` when it has an order.
+    fn repeatable_label(&self) -> String {
+        match self.order {
+            Some(order) => format!("(Repeatable, order {})", order),
+            None => "(Repeatable)".to_string(),
+        }
+    }
 }
 
 /// Complete changelog report.
@@ -57,13 +106,15 @@ pub struct ChangelogReport {
 /// Execute the changelog command.
 pub fn execute(
     locations: &[PathBuf],
+    limits: &ScanLimits,
     from: Option<&str>,
     to: Option<&str>,
 ) -> Result<ChangelogReport> {
-    let migrations = scan_migrations(locations)?;
+    let migrations = scan_migrations_with_limits(locations, limits)?;
 
-    let from_version = from.map(MigrationVersion::parse).transpose()?;
-    let to_version = to.map(MigrationVersion::parse).transpose()?;
+    let parse = |v: &str| MigrationVersion::parse_with(v, limits.version_scheme);
+    let from_version = from.map(parse).transpose()?;
+    let to_version = to.map(parse).transpose()?;
 
     let mut versions = Vec::new();
     let mut total_changes = 0;
@@ -102,7 +153,9 @@ pub fn execute(
             version,
             description: migration.description.clone(),
             script: migration.script.clone(),
+            order: migration.repeatable_order(),
             changes,
+            deployed: None,
         });
     }
 
@@ -112,6 +165,25 @@ pub fn execute(
     })
 }
 
+/// Fill in [`VersionChanges::deployed`] from `info` rows: the latest
+/// successful application of each script. Migrations that are pending,
+/// failed or undone stay undeployed.
+pub fn attach_deployments(report: &mut ChangelogReport, infos: &[MigrationInfo]) {
+    for vc in &mut report.versions {
+        vc.deployed = infos
+            .iter()
+            .rev()
+            .filter(|i| i.script == vc.script)
+            .filter(|i| matches!(i.state, MigrationState::Applied | MigrationState::Outdated))
+            .find_map(|i| {
+                Some(Deployment {
+                    installed_on: i.installed_on?,
+                    audit: i.audit.clone().unwrap_or_default(),
+                })
+            });
+    }
+}
+
 /// Render changelog as markdown.
 pub fn render_markdown(report: &ChangelogReport) -> String {
     let mut output = String::from("# Changelog\n\n");
@@ -119,11 +191,15 @@ pub fn render_markdown(report: &ChangelogReport) -> String {
     for vc in &report.versions {
         let header = match &vc.version {
             Some(v) => format!("## V{} — {}", v, vc.description),
-            None => format!("## (Repeatable) — {}", vc.description),
+            None => format!("## {} — {}", vc.repeatable_label(), vc.description),
         };
         output.push_str(&header);
         output.push('\n');
-        output.push_str(&format!("_Source: {}_\n\n", vc.script));
+        output.push_str(&format!("_Source: {}_\n", vc.script));
+        if let Some(ref deployed) = vc.deployed {
+            output.push_str(&format!("_Deployed: {}_\n", deployed.label()));
+        }
+        output.push('\n');
 
         if vc.changes.is_empty() {
             output.push_str("- No DDL changes detected\n");
@@ -151,11 +227,14 @@ pub fn render_plain(report: &ChangelogReport) -> String {
     for vc in &report.versions {
         let header = match &vc.version {
             Some(v) => format!("V{} - {}", v, vc.description),
-            None => format!("(Repeatable) - {}", vc.description),
+            None => format!("{} - {}", vc.repeatable_label(), vc.description),
         };
         output.push_str(&header);
         output.push('\n');
         output.push_str(&format!("  Source: {}\n", vc.script));
+        if let Some(ref deployed) = vc.deployed {
+            output.push_str(&format!("  Deployed: {}\n", deployed.label()));
+        }
 
         if vc.changes.is_empty() {
             output.push_str("  No DDL changes detected\n");
@@ -196,7 +275,13 @@ mod tests {
         )
         .unwrap();
 
-        let report = execute(&[dir.path().to_path_buf()], None, None).unwrap();
+        let report = execute(
+            &[dir.path().to_path_buf()],
+            &ScanLimits::default(),
+            None,
+            None,
+        )
+        .unwrap();
         assert_eq!(report.versions.len(), 2);
         assert!(report.total_changes >= 2);
     }
@@ -220,11 +305,44 @@ mod tests {
         )
         .unwrap();
 
-        let report = execute(&[dir.path().to_path_buf()], Some("2"), Some("2")).unwrap();
+        let report = execute(
+            &[dir.path().to_path_buf()],
+            &ScanLimits::default(),
+            Some("2"),
+            Some("2"),
+        )
+        .unwrap();
         assert_eq!(report.versions.len(), 1);
         assert_eq!(report.versions[0].version.as_deref(), Some("2"));
     }
 
+    #[test]
+    fn test_repeatables_in_order() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("R__Audit.sql"),
+            "CREATE VIEW audit AS SELECT 1;",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("R2__Users_view.sql"),
+            "CREATE VIEW users_v AS SELECT 1;",
+        )
+        .unwrap();
+
+        let report = execute(
+            &[dir.path().to_path_buf()],
+            &ScanLimits::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        let scripts: Vec<&str> = report.versions.iter().map(|v| v.script.as_str()).collect();
+        assert_eq!(scripts, vec!["R2__Users_view.sql", "R__Audit.sql"]);
+        assert!(render_markdown(&report).contains("## (Repeatable, order 2) — Users view"));
+        assert!(render_plain(&report).contains("(Repeatable) - Audit"));
+    }
+
     #[test]
     fn test_render_markdown() {
         let report = ChangelogReport {
@@ -232,10 +350,12 @@ mod tests {
                 version: Some("1".to_string()),
                 description: "Create users".to_string(),
                 script: "V1__Create_users.sql".to_string(),
+                order: None,
                 changes: vec![DdlOperation::CreateTable {
                     table: "users".to_string(),
                     if_not_exists: false,
                 }],
+                deployed: None,
             }],
             total_changes: 1,
         };
@@ -244,4 +364,64 @@ mod tests {
         assert!(md.contains("## V1"));
         assert!(md.contains("CREATE TABLE users"));
     }
+
+    #[test]
+    fn test_attach_deployments() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("V1__First.sql"),
+            "CREATE TABLE t1 (id int);",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("V2__Second.sql"),
+            "CREATE TABLE t2 (id int);",
+        )
+        .unwrap();
+        let mut report = execute(
+            &[dir.path().to_path_buf()],
+            &ScanLimits::default(),
+            None,
+            None,
+        )
+        .unwrap();
+
+        let installed_on = chrono::DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let row =
+            |script: &str, state: MigrationState, audit: Option<AuditMetadata>| MigrationInfo {
+                version: None,
+                description: String::new(),
+                migration_type: "SQL".to_string(),
+                script: script.to_string(),
+                state,
+                installed_on: Some(installed_on),
+                execution_time: Some(1),
+                checksum: None,
+                schema: None,
+                audit,
+            };
+        let audit = AuditMetadata {
+            git_commit: Some("abc123".into()),
+            hostname: Some("ci-7".into()),
+            ..Default::default()
+        };
+        attach_deployments(
+            &mut report,
+            &[
+                row("V1__First.sql", MigrationState::Applied, Some(audit)),
+                row("V2__Second.sql", MigrationState::Failed, None),
+            ],
+        );
+
+        let deployed = report.versions[0].deployed.as_ref().unwrap();
+        assert_eq!(deployed.audit.git_commit.as_deref(), Some("abc123"));
+        assert!(report.versions[1].deployed.is_none());
+        assert!(render_plain(&report)
+            .contains("  Deployed: 2026-03-01 12:00 UTC, commit abc123, host ci-7\n"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["versions"][0]["deployed"]["git_commit"], "abc123");
+        assert!(json["versions"][1].get("deployed").is_none());
+    }
 }
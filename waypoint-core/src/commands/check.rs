@@ -0,0 +1,598 @@
+//! Data-quality checks: standalone SQL assertions run against the database.
+//!
+//! `.sql` files in `[checks] locations` hold the checks. A file is one check
+//! named after the file, or several when it is split into
+//! `-- waypoint:check <name>` blocks. A check is a query that describes bad
+//! data and passes when it returns no rows. A block declared with
+//! `-- waypoint:check <name> expect=true` instead passes only when it returns a
+//! single true value (e.g. `SELECT count(*) = 0 FROM orders WHERE total < 0`).
+//! Checks run in a read-only transaction and are not tied to a migration, so
+//! the same file can be run from CI, cron or after every `migrate`
+//! (`[checks] after_migrate`). Unlike guards, a failing check does not stop
+//! anything by itself; `waypoint check` exits non-zero.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use regex_lite::Regex;
+use serde::Serialize;
+
+use crate::commands::migrate::MigrateReport;
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::dialect::DialectKind;
+use crate::error::{Result, WaypointError};
+use crate::placeholder::{build_placeholders, replace_placeholders};
+use crate::warning::{Warning, WarningCode};
+
+static BLOCK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^--\s*waypoint:check\b\s*(.*)$").unwrap());
+
+/// Rows of a failing check kept in its result.
+const SAMPLE_ROWS: usize = 5;
+
+/// Configuration for the `[checks]` section.
+#[derive(Debug, Clone)]
+pub struct CheckConfig {
+    /// Directories scanned for check files.
+    pub locations: Vec<PathBuf>,
+    /// Run every check after a successful `migrate`; failures become
+    /// warnings on the migrate report.
+    pub after_migrate: bool,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self {
+            locations: vec![PathBuf::from("db/checks")],
+            after_migrate: false,
+        }
+    }
+}
+
+/// What a check's query must return to pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expect {
+    /// No rows (the default): the query selects the bad data.
+    #[default]
+    Empty,
+    /// A single row with a single true value.
+    True,
+}
+
+impl std::str::FromStr for Expect {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "empty" => Ok(Expect::Empty),
+            "true" => Ok(Expect::True),
+            other => Err(format!(
+                "unknown expect '{}'; expected 'empty' or 'true'",
+                other
+            )),
+        }
+    }
+}
+
+/// A check found on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    /// Block name, or the file name without `.sql`.
+    pub name: String,
+    /// File the check was read from.
+    pub file: String,
+    /// The query.
+    pub sql: String,
+    /// What the query must return to pass.
+    pub expect: Expect,
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    /// Check name.
+    pub name: String,
+    /// File the check was read from.
+    pub file: String,
+    /// What the query had to return.
+    pub expect: Expect,
+    /// Whether the assertion held.
+    pub passed: bool,
+    /// Rows the query returned.
+    pub rows: usize,
+    /// The first rows of a failing check, as `column=value` lists.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<String>,
+    /// Why the check could not run; such a check fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Execution time in milliseconds.
+    pub execution_time_ms: i32,
+}
+
+/// Report returned by `check`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    /// Schema the checks ran against.
+    pub schema: String,
+    /// One entry per check, in file order.
+    pub checks: Vec<CheckResult>,
+    /// Checks that held.
+    pub passed: usize,
+    /// Checks that did not hold or could not run.
+    pub failed: usize,
+}
+
+impl CheckReport {
+    /// Names of the failed checks.
+    pub fn failed_names(&self) -> Vec<&str> {
+        self.checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| c.name.as_str())
+            .collect()
+    }
+
+    /// Fold another schema's checks into this report (`[migrations] schemas`).
+    pub(crate) fn merge(&mut self, other: CheckReport) {
+        self.checks.extend(other.checks);
+        self.passed += other.passed;
+        self.failed += other.failed;
+    }
+}
+
+/// Scan `[checks] locations` for check files, in location order and then by
+/// file name. Check names must be unique.
+pub fn scan_checks(config: &WaypointConfig) -> Result<Vec<Check>> {
+    let mut checks: Vec<Check> = Vec::new();
+    for location in &config.checks.locations {
+        if !location.exists() {
+            log::debug!("Check location does not exist: {}", location.display());
+            continue;
+        }
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(location)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.sort();
+        for path in paths {
+            if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some("sql") {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)?;
+            checks.extend(parse_check_file(&path, &content)?);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for check in &checks {
+        if !seen.insert(check.name.as_str()) {
+            return Err(WaypointError::ConfigError(format!(
+                "Check '{}' is defined more than once",
+                check.name
+            )));
+        }
+    }
+    Ok(checks)
+}
+
+/// Split a check file into its checks. SQL before the first
+/// `-- waypoint:check` line (or the whole file, without one) is a check
+/// named after the file, expecting no rows.
+fn parse_check_file(path: &Path, content: &str) -> Result<Vec<Check>> {
+    let file = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = file.strip_suffix(".sql").unwrap_or(&file).to_string();
+
+    let mut checks = Vec::new();
+    let mut name = stem;
+    let mut expect = Expect::Empty;
+    let mut sql = String::new();
+    for (number, line) in content.lines().enumerate() {
+        if let Some(caps) = BLOCK_RE.captures(line.trim()) {
+            push_check(&mut checks, &name, &file, &sql, expect);
+            (name, expect) = parse_header(&caps[1]).map_err(|reason| {
+                WaypointError::ConfigError(format!("{} line {}: {}", file, number + 1, reason))
+            })?;
+            sql.clear();
+            continue;
+        }
+        sql.push_str(line);
+        sql.push('\n');
+    }
+    push_check(&mut checks, &name, &file, &sql, expect);
+    Ok(checks)
+}
+
+/// Parse `<name> [expect=empty|true]` after `-- waypoint:check`.
+fn parse_header(header: &str) -> std::result::Result<(String, Expect), String> {
+    let mut parts = header.split_whitespace();
+    let name = parts
+        .next()
+        .ok_or("expected '-- waypoint:check <name> [expect=empty|true]'")?;
+    let mut expect = Expect::Empty;
+    for part in parts {
+        match part.strip_prefix("expect=") {
+            Some(value) => expect = value.trim_matches('"').parse()?,
+            None => {
+                return Err(format!(
+                    "unexpected '{}'; expected '-- waypoint:check <name> [expect=empty|true]'",
+                    part
+                ))
+            }
+        }
+    }
+    Ok((name.to_string(), expect))
+}
+
+/// Add a check for `sql`, unless it holds nothing but comments.
+fn push_check(checks: &mut Vec<Check>, name: &str, file: &str, sql: &str, expect: Expect) {
+    let has_sql = sql.lines().any(|line| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with("--")
+    });
+    if has_sql {
+        checks.push(Check {
+            name: name.to_string(),
+            file: file.to_string(),
+            sql: sql.trim().to_string(),
+            expect,
+        });
+    }
+}
+
+/// Run the checks named in `names` (all of them when empty).
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    names: &[String],
+) -> Result<CheckReport> {
+    let mut checks = scan_checks(config)?;
+    if !names.is_empty() {
+        if let Some(unknown) = names.iter().find(|n| !checks.iter().any(|c| &c.name == *n)) {
+            return Err(WaypointError::ConfigError(format!(
+                "No check named '{}' in {}",
+                unknown,
+                config
+                    .checks
+                    .locations
+                    .iter()
+                    .map(|l| l.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+        checks.retain(|c| names.contains(&c.name));
+    }
+
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let db_user = client
+        .current_user()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+    let db_name = client
+        .current_database()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+
+    let mut results = Vec::new();
+    for check in &checks {
+        let placeholders = build_placeholders(
+            &config.placeholders,
+            &schema,
+            &db_user,
+            &db_name,
+            &check.file,
+        );
+        let started = Instant::now();
+        let outcome = match replace_placeholders(&check.sql, &placeholders) {
+            Ok(sql) => query_read_only(client, &sql).await,
+            Err(e) => Err(e),
+        };
+        let execution_time_ms = started.elapsed().as_millis() as i32;
+        let result = match outcome {
+            Ok(rows) => {
+                let passed = holds(&rows, check.expect, client.dialect_kind());
+                CheckResult {
+                    name: check.name.clone(),
+                    file: check.file.clone(),
+                    expect: check.expect,
+                    passed,
+                    rows: rows.len(),
+                    sample: if passed {
+                        Vec::new()
+                    } else {
+                        rows.iter().take(SAMPLE_ROWS).map(render_row).collect()
+                    },
+                    error: None,
+                    execution_time_ms,
+                }
+            }
+            Err(e) => CheckResult {
+                name: check.name.clone(),
+                file: check.file.clone(),
+                expect: check.expect,
+                passed: false,
+                rows: 0,
+                sample: Vec::new(),
+                error: Some(e.to_string()),
+                execution_time_ms,
+            },
+        };
+        if !result.passed {
+            log::warn!(
+                "Check failed; check={}, file={}, rows={}",
+                result.name,
+                result.file,
+                result.rows
+            );
+        }
+        results.push(result);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    log::info!(
+        "Checks completed; passed={}, failed={}, schema={}",
+        results.len() - failed,
+        failed,
+        schema
+    );
+    Ok(CheckReport {
+        schema,
+        passed: results.len() - failed,
+        failed,
+        checks: results,
+    })
+}
+
+/// Run the checks after a finished migrate, if `[checks] after_migrate` is
+/// set. Failed checks become warnings and fail `report.checks`; the
+/// migrations are already committed. Checks that cannot be run at all (a bad
+/// check file, say) count as one failed check named `checks`.
+pub async fn after_migrate(client: &DbClient, config: &WaypointConfig, report: &mut MigrateReport) {
+    if !config.checks.after_migrate {
+        return;
+    }
+    match execute_db(client, config, &[]).await {
+        Ok(checks) => {
+            for failed in checks.checks.iter().filter(|c| !c.passed) {
+                let message = match &failed.error {
+                    Some(error) => format!("check could not run: {}", error),
+                    None => format!("check returned {} row(s)", failed.rows),
+                };
+                report.warnings.push(Warning::new(
+                    WarningCode::CheckFailed,
+                    &failed.name,
+                    message,
+                ));
+            }
+            match &mut report.checks {
+                Some(existing) => existing.merge(checks),
+                None => report.checks = Some(checks),
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to run checks after migrate: {}", e);
+            report.warnings.push(Warning::new(
+                WarningCode::CheckFailed,
+                "checks",
+                format!("checks did not run: {}", e),
+            ));
+            let checks = CheckReport {
+                schema: config.migrations.schema.clone(),
+                checks: vec![CheckResult {
+                    name: "checks".to_string(),
+                    file: String::new(),
+                    expect: Expect::Empty,
+                    passed: false,
+                    rows: 0,
+                    sample: Vec::new(),
+                    error: Some(e.to_string()),
+                    execution_time_ms: 0,
+                }],
+                passed: 0,
+                failed: 1,
+            };
+            match &mut report.checks {
+                Some(existing) => existing.merge(checks),
+                None => report.checks = Some(checks),
+            }
+        }
+    }
+}
+
+/// A result row: column names and text values (`None` for NULL).
+type Row = Vec<(String, Option<String>)>;
+
+/// Whether a check's rows mean its assertion holds: no rows for
+/// `expect=empty`, one row with a single true value for `expect=true`.
+fn holds(rows: &[Row], expect: Expect, kind: DialectKind) -> bool {
+    match (expect, rows) {
+        (Expect::Empty, rows) => rows.is_empty(),
+        (Expect::True, [row]) if row.len() == 1 => {
+            matches!(row[0].1.as_deref().and_then(|v| truth(v, kind)), Some(true))
+        }
+        (Expect::True, _) => false,
+    }
+}
+
+/// Read a boolean as the server prints it. MySQL has no boolean type, so
+/// there `1` and `0` count too; on PostgreSQL they stay numbers, and a
+/// `count(*)` of 0 is a failing row rather than "false".
+fn truth(value: &str, kind: DialectKind) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "t" | "true" => Some(true),
+        "f" | "false" => Some(false),
+        "1" if kind == DialectKind::Mysql => Some(true),
+        "0" if kind == DialectKind::Mysql => Some(false),
+        _ => None,
+    }
+}
+
+fn render_row(row: &Row) -> String {
+    row.iter()
+        .map(|(column, value)| format!("{}={}", column, value.as_deref().unwrap_or("NULL")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Run `sql` in a read-only transaction that is rolled back afterwards.
+async fn query_read_only(client: &DbClient, sql: &str) -> Result<Vec<Row>> {
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(pg) => {
+            use tokio_postgres::SimpleQueryMessage;
+            pg.batch_execute("BEGIN READ ONLY").await?;
+            let result = pg.simple_query(sql).await;
+            if let Err(e) = pg.batch_execute("ROLLBACK").await {
+                log::warn!("Failed to roll back check transaction: {}", e);
+            }
+            Ok(result?
+                .iter()
+                .filter_map(|message| match message {
+                    SimpleQueryMessage::Row(row) => Some(
+                        row.columns()
+                            .iter()
+                            .enumerate()
+                            .map(|(i, column)| {
+                                (column.name().to_string(), row.get(i).map(str::to_string))
+                            })
+                            .collect(),
+                    ),
+                    _ => None,
+                })
+                .collect())
+        }
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let mut conn = pool.get_conn().await?;
+            conn.query_drop("START TRANSACTION READ ONLY").await?;
+            let result: std::result::Result<Vec<mysql_async::Row>, _> = conn.query(sql).await;
+            if let Err(e) = conn.query_drop("ROLLBACK").await {
+                log::warn!("Failed to roll back check transaction: {}", e);
+            }
+            Ok(result?
+                .iter()
+                .map(|row| {
+                    row.columns_ref()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, column)| {
+                            (
+                                column.name_str().into_owned(),
+                                row.as_ref(i).and_then(mysql_text),
+                            )
+                        })
+                        .collect()
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(feature = "mysql")]
+fn mysql_text(value: &mysql_async::Value) -> Option<String> {
+    use mysql_async::Value;
+    match value {
+        Value::NULL => None,
+        Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        Value::Int(n) => Some(n.to_string()),
+        Value::UInt(n) => Some(n.to_string()),
+        Value::Float(n) => Some(n.to_string()),
+        Value::Double(n) => Some(n.to_string()),
+        other => Some(other.as_sql(true).trim_matches('\'').to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: &[(&str, Option<&str>)]) -> Row {
+        values
+            .iter()
+            .map(|(c, v)| (c.to_string(), v.map(str::to_string)))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_check_file_blocks() {
+        let content = "-- Orders must be consistent\n\
+                       SELECT id FROM orders WHERE total < 0;\n\
+                       -- waypoint:check orders_have_customers\n\
+                       SELECT o.id FROM orders o LEFT JOIN customers c ON c.id = o.customer_id\n\
+                       WHERE c.id IS NULL;\n\
+                       --waypoint:check no_future_orders expect=true\n\
+                       -- comment only\n\
+                       SELECT count(*) = 0 FROM orders WHERE created_at > now();\n";
+        let checks = parse_check_file(Path::new("db/checks/orders.sql"), content).unwrap();
+        let names: Vec<&str> = checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["orders", "orders_have_customers", "no_future_orders"]
+        );
+        assert!(checks.iter().all(|c| c.file == "orders.sql"));
+        assert!(checks[1].sql.ends_with("WHERE c.id IS NULL;"));
+        assert_eq!(checks[1].expect, Expect::Empty);
+        assert_eq!(checks[2].expect, Expect::True);
+
+        let blocks_only = "-- header\n-- waypoint:check a\nSELECT 1 WHERE false;\n";
+        let checks = parse_check_file(Path::new("x.sql"), blocks_only).unwrap();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].name, "a");
+
+        assert!(parse_check_file(Path::new("x.sql"), "-- waypoint:check\nSELECT 1;").is_err());
+        assert!(parse_check_file(
+            Path::new("x.sql"),
+            "-- waypoint:check a expect=yes\nSELECT 1;"
+        )
+        .is_err());
+        assert!(parse_check_file(Path::new("x.sql"), "-- waypoint:check a b\nSELECT 1;").is_err());
+    }
+
+    #[test]
+    fn test_holds() {
+        let pg = DialectKind::Postgres;
+        let (empty, true_) = (Expect::Empty, Expect::True);
+        assert!(holds(&[], empty, pg));
+        // A bad-data query that happens to select `true` still found a row.
+        assert!(!holds(&[row(&[("ok", Some("t"))])], empty, pg));
+        assert!(!holds(&[row(&[("count", Some("0"))])], empty, pg));
+
+        assert!(holds(&[row(&[("ok", Some("t"))])], true_, pg));
+        assert!(!holds(&[], true_, pg));
+        assert!(!holds(&[row(&[("ok", Some("f"))])], true_, pg));
+        assert!(!holds(&[row(&[("ok", None)])], true_, pg));
+        // A count of zero is not "false".
+        assert!(!holds(&[row(&[("count", Some("0"))])], true_, pg));
+        assert!(holds(
+            &[row(&[("ok", Some("1"))])],
+            true_,
+            DialectKind::Mysql
+        ));
+        assert!(!holds(
+            &[row(&[("id", Some("1")), ("total", Some("-5"))])],
+            true_,
+            pg
+        ));
+        assert!(!holds(
+            &[row(&[("ok", Some("t"))]), row(&[("ok", Some("t"))])],
+            true_,
+            pg
+        ));
+    }
+
+    #[test]
+    fn test_render_row() {
+        assert_eq!(
+            render_row(&row(&[("id", Some("7")), ("email", None)])),
+            "id=7, email=NULL"
+        );
+    }
+}
@@ -0,0 +1,498 @@
+//! Coordinated checksum updates after intentional edits.
+//!
+//! `waypoint checksums update --versions 1..150 --reason "squash"` records the
+//! current file checksums for the listed versions, and writes one row per
+//! change to an audit table (`<history_table>_checksum_audit`) with the old
+//! and new checksum, the reason, and who made the change. Unlike `repair` it
+//! touches only the versions asked for and never removes failed entries.
+//! When `migrations.environment` is `production` it requires `--force`.
+
+#[cfg(feature = "postgres")]
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+#[cfg(feature = "postgres")]
+use crate::checksum::ChecksumAlgorithm;
+use crate::config::{MigrationSettings, WaypointConfig};
+use crate::db::DbClient;
+#[cfg(feature = "postgres")]
+use crate::db::{quote_ident, LockTarget};
+#[cfg(feature = "postgres")]
+use crate::dialect::DialectKind;
+use crate::error::{Result, WaypointError};
+#[cfg(feature = "postgres")]
+use crate::history::AppliedMigration;
+use crate::migration::MigrationVersion;
+#[cfg(feature = "postgres")]
+use crate::migration::{ResolvedMigration, VersionScheme};
+
+/// Name of the checksum audit table for a given history table.
+pub fn audit_table_name(history_table: &str) -> String {
+    format!("{}_checksum_audit", history_table)
+}
+
+/// An inclusive range of versions, as given to `--versions`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionRange {
+    /// Lowest version in the range.
+    pub from: MigrationVersion,
+    /// Highest version in the range.
+    pub to: MigrationVersion,
+}
+
+impl VersionRange {
+    /// Whether `version` falls inside the range.
+    pub fn contains(&self, version: &MigrationVersion) -> bool {
+        *version >= self.from && *version <= self.to
+    }
+}
+
+/// Parse a `--versions` value: comma-separated versions and inclusive
+/// `from..to` ranges, e.g. `1..150` or `3,7..9`, under
+/// `[migrations] version_scheme`.
+pub fn parse_version_ranges(s: &str, migrations: &MigrationSettings) -> Result<Vec<VersionRange>> {
+    let mut ranges = Vec::new();
+    for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (from, to) = match part.split_once("..") {
+            Some((from, to)) => (from.trim(), to.trim()),
+            None => (part, part),
+        };
+        let range = VersionRange {
+            from: migrations.parse_version(from)?,
+            to: migrations.parse_version(to)?,
+        };
+        if range.from > range.to {
+            return Err(WaypointError::ConfigError(format!(
+                "Invalid version range '{}': {} is above {}",
+                part, range.from, range.to
+            )));
+        }
+        ranges.push(range);
+    }
+    if ranges.is_empty() {
+        return Err(WaypointError::ConfigError(
+            "--versions needs at least one version or range".into(),
+        ));
+    }
+    Ok(ranges)
+}
+
+/// Which checksums to update, and why.
+#[derive(Debug, Clone)]
+pub struct ChecksumUpdateOptions {
+    /// Versions to update.
+    pub versions: Vec<VersionRange>,
+    /// Audit note stored with every change.
+    pub reason: String,
+    /// Required when `migrations.environment` is `production`.
+    pub force: bool,
+    /// Report the changes without writing them.
+    pub dry_run: bool,
+}
+
+/// A single stored checksum change.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChecksumChange {
+    /// Migration version.
+    pub version: String,
+    /// Script name on disk.
+    pub script: String,
+    /// Checksum stored in the history table before the update.
+    pub old_checksum: Option<i32>,
+    /// Checksum of the file now.
+    pub new_checksum: i32,
+}
+
+/// Result of `checksums update`.
+#[derive(Debug, Serialize)]
+pub struct ChecksumUpdateReport {
+    /// Checksums changed (or that would change, on a dry run).
+    pub updated: Vec<ChecksumChange>,
+    /// Applied versions in range whose stored checksum already matches.
+    pub unchanged: usize,
+    /// Applied versions in range with no file on disk; left as they are.
+    pub missing: Vec<String>,
+    /// Audit note recorded with the changes.
+    pub reason: String,
+    /// Whether the history table was left untouched.
+    pub dry_run: bool,
+}
+
+/// Execute `checksums update` (dialect-aware entry; PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn update_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    options: &ChecksumUpdateOptions,
+) -> Result<ChecksumUpdateReport> {
+    check_options(config, options)?;
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => update(client.as_postgres()?, config, options).await,
+        _ => Err(WaypointError::ConfigError(
+            "checksums update records an audit trail (PostgreSQL only)".into(),
+        )),
+    }
+}
+
+/// Reject an update without a reason, or without `--force` in production.
+fn check_options(config: &WaypointConfig, options: &ChecksumUpdateOptions) -> Result<()> {
+    if options.reason.trim().is_empty() {
+        return Err(WaypointError::ConfigError(
+            "checksums update needs a --reason for the audit trail".into(),
+        ));
+    }
+    let production = config
+        .migrations
+        .environment
+        .as_deref()
+        .is_some_and(|e| matches!(e.to_lowercase().as_str(), "production" | "prod"));
+    if production && !options.force {
+        return Err(WaypointError::PolicyDenied {
+            command: "checksums".to_string(),
+            reason: "requires --force when environment is production".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Create the audit table if it does not exist (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn create_audit_table(client: &Client, config: &WaypointConfig) -> Result<()> {
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            id BIGSERIAL PRIMARY KEY,
+            version VARCHAR(50) NOT NULL,
+            script VARCHAR(1000) NOT NULL,
+            old_checksum INTEGER,
+            new_checksum INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            updated_by VARCHAR(100) NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )",
+        audit_qualified(config)
+    );
+    client.batch_execute(&sql).await?;
+    Ok(())
+}
+
+#[cfg(feature = "postgres")]
+fn audit_qualified(config: &WaypointConfig) -> String {
+    format!(
+        "{}.{}",
+        quote_ident(&config.migrations.schema),
+        quote_ident(&audit_table_name(&config.migrations.table))
+    )
+}
+
+#[cfg(feature = "postgres")]
+async fn update(
+    client: &Client,
+    config: &WaypointConfig,
+    options: &ChecksumUpdateOptions,
+) -> Result<ChecksumUpdateReport> {
+    let lock = LockTarget::new(&config.migrations, &config.migrations.schema);
+
+    crate::db::acquire_advisory_lock(client, &lock).await?;
+
+    let result = update_inner(client, config, options).await;
+
+    if let Err(e) = crate::db::release_advisory_lock(client, &lock).await {
+        log::error!("Failed to release advisory lock: {}", e);
+    }
+
+    result
+}
+
+#[cfg(feature = "postgres")]
+async fn update_inner(
+    client: &Client,
+    config: &WaypointConfig,
+    options: &ChecksumUpdateOptions,
+) -> Result<ChecksumUpdateReport> {
+    let schema = &config.migrations.schema;
+    let table = &config.migrations.table;
+
+    if !crate::history::history_table_exists(client, schema, table).await? {
+        return Err(WaypointError::ConfigError(format!(
+            "History table {}.{} does not exist; nothing to update",
+            schema, table
+        )));
+    }
+    let applied = crate::history::get_applied_migrations(client, schema, table).await?;
+    let resolved = config.migrations.resolve()?;
+    let mut report = compute_changes(
+        &applied,
+        &resolved,
+        options,
+        config.migrations.version_scheme,
+    );
+
+    if !options.dry_run && !report.updated.is_empty() {
+        create_audit_table(client, config).await?;
+        let installed_by = match &config.migrations.installed_by {
+            Some(user) => user.clone(),
+            None => crate::db::get_current_user(client)
+                .await
+                .unwrap_or_else(|_| "waypoint".to_string()),
+        };
+
+        client.batch_execute("BEGIN").await?;
+        let written = write_changes(client, config, &report, &resolved, &installed_by).await;
+        match written {
+            Ok(()) => client.batch_execute("COMMIT").await?,
+            Err(e) => {
+                if let Err(rb) = client.batch_execute("ROLLBACK").await {
+                    log::error!("Failed to roll back checksum update: {}", rb);
+                }
+                return Err(e);
+            }
+        }
+    }
+    report.dry_run = options.dry_run;
+
+    log::info!(
+        "Checksum update completed; updated={}, unchanged={}, missing={}, dry_run={}",
+        report.updated.len(),
+        report.unchanged,
+        report.missing.len(),
+        options.dry_run
+    );
+    Ok(report)
+}
+
+#[cfg(feature = "postgres")]
+async fn write_changes(
+    client: &Client,
+    config: &WaypointConfig,
+    report: &ChecksumUpdateReport,
+    resolved: &[ResolvedMigration],
+    updated_by: &str,
+) -> Result<()> {
+    let schema = &config.migrations.schema;
+    let table = &config.migrations.table;
+    let audit_sql = format!(
+        "INSERT INTO {} (version, script, old_checksum, new_checksum, reason, updated_by)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+        audit_qualified(config)
+    );
+    for change in &report.updated {
+        crate::history::update_checksum(
+            client,
+            schema,
+            table,
+            &change.version,
+            change.new_checksum,
+        )
+        .await?;
+        let file = resolved.iter().find(|m| m.script == change.script);
+        if let Some(file) =
+            file.filter(|_| config.migrations.checksum_algorithm == ChecksumAlgorithm::Sha256)
+        {
+            crate::history::update_checksum_sha256(
+                client,
+                schema,
+                table,
+                &change.script,
+                &file.checksum_sha256(),
+            )
+            .await?;
+        }
+        client
+            .execute(
+                &audit_sql,
+                &[
+                    &change.version,
+                    &change.script,
+                    &change.old_checksum,
+                    &change.new_checksum,
+                    &report.reason,
+                    &updated_by,
+                ],
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Work out which applied versions in range need a new checksum.
+#[cfg(feature = "postgres")]
+fn compute_changes(
+    applied: &[AppliedMigration],
+    resolved: &[ResolvedMigration],
+    options: &ChecksumUpdateOptions,
+    scheme: VersionScheme,
+) -> ChecksumUpdateReport {
+    let resolved_by_version: HashMap<&str, &ResolvedMigration> = resolved
+        .iter()
+        .filter(|m| m.is_versioned())
+        .filter_map(|m| m.version().map(|v| (v.raw.as_str(), m)))
+        .collect();
+
+    let mut report = ChecksumUpdateReport {
+        updated: Vec::new(),
+        unchanged: 0,
+        missing: Vec::new(),
+        reason: options.reason.trim().to_string(),
+        dry_run: options.dry_run,
+    };
+    // Latest row per version that is still applied (not undone).
+    let effective = crate::history::effective_applied_versions(applied);
+    let mut seen = HashSet::new();
+    let mut latest: Vec<(MigrationVersion, &AppliedMigration)> = Vec::new();
+    for am in applied.iter().rev() {
+        if !am.success || am.migration_type == "BASELINE" || am.migration_type == "UNDO_SQL" {
+            continue;
+        }
+        let Some(raw) = am.version.as_deref() else {
+            continue;
+        };
+        if !effective.contains(raw) || !seen.insert(raw) {
+            continue;
+        }
+        let Ok(version) = MigrationVersion::parse_with(raw, scheme) else {
+            continue;
+        };
+        if options.versions.iter().any(|r| r.contains(&version)) {
+            latest.push((version, am));
+        }
+    }
+    latest.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (version, am) in latest {
+        match resolved_by_version.get(version.raw.as_str()) {
+            Some(m) if am.checksum == Some(m.checksum) => report.unchanged += 1,
+            Some(m) => report.updated.push(ChecksumChange {
+                version: version.raw.clone(),
+                script: m.script.clone(),
+                old_checksum: am.checksum,
+                new_checksum: m.checksum,
+            }),
+            None => report.missing.push(version.raw.clone()),
+        }
+    }
+    report
+}
+
+#[cfg(all(test, feature = "postgres"))]
+mod tests {
+    use super::*;
+
+    fn row(rank: i32, version: &str, kind: &str, checksum: i32) -> AppliedMigration {
+        AppliedMigration {
+            description: format!("m{}", version),
+            migration_type: kind.to_string(),
+            checksum: Some(checksum),
+            ..AppliedMigration::for_test(rank, Some(version), &format!("V{}__m.sql", version))
+        }
+    }
+
+    fn options(versions: &str, reason: &str) -> ChecksumUpdateOptions {
+        ChecksumUpdateOptions {
+            versions: parse_version_ranges(versions, &MigrationSettings::default()).unwrap(),
+            reason: reason.to_string(),
+            force: false,
+            dry_run: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_version_ranges() {
+        let numeric = MigrationSettings::default();
+        let parse_version_ranges = |s: &str| parse_version_ranges(s, &numeric);
+        let ranges = parse_version_ranges("1..150").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert!(ranges[0].contains(&MigrationVersion::parse("1").unwrap()));
+        assert!(ranges[0].contains(&MigrationVersion::parse("99.1").unwrap()));
+        assert!(!ranges[0].contains(&MigrationVersion::parse("151").unwrap()));
+
+        let ranges = parse_version_ranges("3, 7..9").unwrap();
+        assert!(ranges[0].contains(&MigrationVersion::parse("3").unwrap()));
+        assert!(!ranges[0].contains(&MigrationVersion::parse("4").unwrap()));
+        assert!(ranges[1].contains(&MigrationVersion::parse("8").unwrap()));
+
+        assert!(parse_version_ranges("9..7").is_err());
+        assert!(parse_version_ranges("").is_err());
+        assert!(parse_version_ranges("a..b").is_err());
+    }
+
+    #[test]
+    fn test_parse_version_ranges_lexicographic() {
+        let settings = MigrationSettings {
+            version_scheme: VersionScheme::Lexicographic,
+            ..Default::default()
+        };
+        let v = |s: &str| settings.parse_version(s).unwrap();
+
+        // "10" sorts before "9" as text.
+        assert!(parse_version_ranges("9..10", &settings).is_err());
+        let ranges = parse_version_ranges("10..9", &settings).unwrap();
+        assert!(ranges[0].contains(&v("100")));
+        assert!(ranges[0].contains(&v("8")));
+        assert!(!ranges[0].contains(&v("95")));
+        assert!(!ranges[0].contains(&v("0010")));
+    }
+
+    #[test]
+    fn test_compute_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, sql) in [
+            ("V1__a.sql", "SELECT 1;"),
+            ("V2__b.sql", "SELECT 2;"),
+            ("V3__c.sql", "SELECT 3;"),
+            ("V9__d.sql", "SELECT 9;"),
+        ] {
+            std::fs::write(dir.path().join(name), sql).unwrap();
+        }
+        let resolved = crate::migration::scan_migrations(&[dir.path().to_path_buf()]).unwrap();
+        let checksum = |v: &str| {
+            resolved
+                .iter()
+                .find(|m| m.version().is_some_and(|x| x.raw == v))
+                .unwrap()
+                .checksum
+        };
+
+        let applied = vec![
+            row(1, "1", "SQL", 111),
+            row(2, "2", "SQL", checksum("2")),
+            row(3, "3", "SQL", 333),
+            row(4, "3", "UNDO_SQL", 0),
+            row(5, "4", "SQL", 444),
+            row(6, "9", "SQL", 999),
+        ];
+        let report = compute_changes(
+            &applied,
+            &resolved,
+            &options("1..5", " squash "),
+            VersionScheme::Numeric,
+        );
+
+        // 3 was undone, 9 is out of range, 4 has no file.
+        let versions: Vec<&str> = report.updated.iter().map(|c| c.version.as_str()).collect();
+        assert_eq!(versions, vec!["1"]);
+        assert_eq!(report.updated[0].old_checksum, Some(111));
+        assert_eq!(report.updated[0].new_checksum, checksum("1"));
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.missing, vec!["4".to_string()]);
+        assert_eq!(report.reason, "squash");
+    }
+
+    #[test]
+    fn test_check_options() {
+        let mut config = WaypointConfig::default();
+        assert!(check_options(&config, &options("1", "reformat")).is_ok());
+        assert!(check_options(&config, &options("1", "  ")).is_err());
+
+        config.migrations.environment = Some("Production".to_string());
+        let err = check_options(&config, &options("1", "reformat")).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        let mut forced = options("1", "reformat");
+        forced.force = true;
+        assert!(check_options(&config, &forced).is_ok());
+    }
+}
@@ -1,14 +1,24 @@
 //! Drop all objects in managed schemas (destructive).
+//!
+//! Objects created by `CREATE EXTENSION` are left alone: dropping one member
+//! of an extension breaks it. With `[database] include_extension_objects`,
+//! the extensions installed in the schema are dropped as a whole instead.
+//!
+//! `[database] include_objects` / `exclude_objects` narrow what is dropped,
+//! by object name, the same way they narrow snapshots. With a filter, objects
+//! are dropped `RESTRICT` in one transaction, so a clean that would take an
+//! excluded object with it (a view, a column default, a foreign key) fails
+//! and drops nothing.
 
 #[cfg(feature = "postgres")]
-use tokio_postgres::Client;
+use tokio_postgres::{error::SqlState, Client};
 
 use crate::config::WaypointConfig;
 #[cfg(feature = "postgres")]
 use crate::db;
 #[cfg(feature = "postgres")]
 use crate::db::quote_ident;
-use crate::db::DbClient;
+use crate::db::{DbClient, LockTarget};
 use crate::dialect::DialectKind;
 use crate::error::{Result, WaypointError};
 
@@ -26,15 +36,15 @@ pub async fn execute(
         return Err(WaypointError::CleanDisabled);
     }
 
-    let table = &config.migrations.table;
+    let lock = LockTarget::new(&config.migrations, &config.migrations.schema);
 
     // Acquire advisory lock to prevent concurrent operations
-    db::acquire_advisory_lock(client, table).await?;
+    db::acquire_advisory_lock(client, &lock).await?;
 
     let result = execute_inner_pg(client, config).await;
 
     // Always release the lock
-    if let Err(e) = db::release_advisory_lock(client, table).await {
+    if let Err(e) = db::release_advisory_lock(client, &lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -51,8 +61,11 @@ pub async fn execute_db(
         return Err(WaypointError::CleanDisabled);
     }
 
-    let table = &config.migrations.table;
-    client.acquire_lock(table).await?;
+    let lock = LockTarget::new(
+        &config.migrations,
+        &client.resolve_schema(&config.migrations.schema).await?,
+    );
+    client.acquire_lock(&lock).await?;
 
     let result = match client.dialect_kind() {
         #[cfg(feature = "postgres")]
@@ -69,7 +82,7 @@ pub async fn execute_db(
         )),
     };
 
-    if let Err(e) = client.release_lock(table).await {
+    if let Err(e) = client.release_lock(&lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -78,132 +91,148 @@ pub async fn execute_db(
 
 #[cfg(feature = "postgres")]
 async fn execute_inner_pg(client: &Client, config: &WaypointConfig) -> Result<Vec<String>> {
+    let filter = config.database.object_filter();
+    if filter.is_empty() {
+        return drop_pg_objects(client, config, true).await;
+    }
+
+    // With a filter, everything is dropped `RESTRICT` in one transaction so
+    // a dependency on an excluded object rolls the whole clean back.
+    client.batch_execute("BEGIN").await?;
+    match drop_pg_objects(client, config, false).await {
+        Ok(dropped) => {
+            client.batch_execute("COMMIT").await?;
+            Ok(dropped)
+        }
+        Err(e) => {
+            if let Err(rollback) = client.batch_execute("ROLLBACK").await {
+                log::error!("Failed to roll back clean; error={}", rollback);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn drop_pg_objects(
+    client: &Client,
+    config: &WaypointConfig,
+    cascade: bool,
+) -> Result<Vec<String>> {
     let schema = &config.migrations.schema;
     let schema_q = quote_ident(schema);
     let mut dropped = Vec::new();
+    let filter = config.database.object_filter();
+    let qualified = |name: &str| format!("{}.{}", schema_q, quote_ident(name));
 
     log::warn!(
         "Starting clean — this will DROP all objects in the schema; schema={}",
         schema
     );
 
-    // Drop materialized views
-    let rows = client
-        .query(
-            "SELECT matviewname FROM pg_matviews WHERE schemaname = $1",
-            &[&schema],
-        )
-        .await?;
-    for row in rows {
-        let name: String = row.get(0);
-        let sql = format!(
-            "DROP MATERIALIZED VIEW IF EXISTS {}.{} CASCADE",
-            schema_q,
-            quote_ident(&name)
-        );
-        client.batch_execute(&sql).await?;
-        dropped.push(format!("Materialized view: {}.{}", schema, name));
+    if config.database.include_extension_objects {
+        let rows = client
+            .query(
+                "SELECT e.extname FROM pg_extension e \
+                 JOIN pg_namespace n ON e.extnamespace = n.oid \
+                 WHERE n.nspname = $1",
+                &[&schema],
+            )
+            .await?;
+        for row in rows {
+            let name: String = row.get(0);
+            let sql = format!("DROP EXTENSION IF EXISTS {} CASCADE", quote_ident(&name));
+            client.batch_execute(&sql).await?;
+            dropped.push(format!("Extension: {}", name));
+        }
     }
 
-    // Drop views
-    let rows = client
-        .query(
-            "SELECT table_name FROM information_schema.views WHERE table_schema = $1",
-            &[&schema],
-        )
-        .await?;
-    for row in rows {
-        let name: String = row.get(0);
-        let sql = format!(
-            "DROP VIEW IF EXISTS {}.{} CASCADE",
-            schema_q,
-            quote_ident(&name)
+    // Objects to drop, as (keyword, qualified targets) in drop order.
+    let mut groups: Vec<(&str, Vec<String>)> = Vec::new();
+    let relation_kinds = [
+        ("'m'", "MATERIALIZED VIEW", "Materialized view"),
+        ("'v'", "VIEW", "View"),
+        ("'r', 'p'", "TABLE", "Table"),
+        ("'S'", "SEQUENCE", "Sequence"),
+    ];
+    for (relkinds, keyword, label) in relation_kinds {
+        let rows = client.query(&relations_query(relkinds), &[&schema]).await?;
+        let names: Vec<String> = rows
+            .iter()
+            .map(|row| row.get::<_, String>(0))
+            .filter(|name| filter.allows(name))
+            .collect();
+        groups.push((keyword, names.iter().map(|name| qualified(name)).collect()));
+        dropped.extend(
+            names
+                .iter()
+                .map(|name| format!("{}: {}.{}", label, schema, name)),
         );
-        client.batch_execute(&sql).await?;
-        dropped.push(format!("View: {}.{}", schema, name));
-    }
-
-    // Drop tables
-    let rows = client
-        .query(
-            "SELECT tablename FROM pg_tables WHERE schemaname = $1",
-            &[&schema],
-        )
-        .await?;
-    for row in rows {
-        let name: String = row.get(0);
-        let sql = format!(
-            "DROP TABLE IF EXISTS {}.{} CASCADE",
-            schema_q,
-            quote_ident(&name)
-        );
-        client.batch_execute(&sql).await?;
-        dropped.push(format!("Table: {}.{}", schema, name));
-    }
-
-    // Drop sequences
-    let rows = client
-        .query(
-            "SELECT sequence_name FROM information_schema.sequences WHERE sequence_schema = $1",
-            &[&schema],
-        )
-        .await?;
-    for row in rows {
-        let name: String = row.get(0);
-        let sql = format!(
-            "DROP SEQUENCE IF EXISTS {}.{} CASCADE",
-            schema_q,
-            quote_ident(&name)
-        );
-        client.batch_execute(&sql).await?;
-        dropped.push(format!("Sequence: {}.{}", schema, name));
     }
 
     // Drop functions/procedures
     let rows = client
         .query(
-            "SELECT p.proname, pg_get_function_identity_arguments(p.oid) as args \
-             FROM pg_proc p \
-             JOIN pg_namespace n ON p.pronamespace = n.oid \
-             WHERE n.nspname = $1",
+            &format!(
+                "SELECT p.proname, pg_get_function_identity_arguments(p.oid) as args \
+                 FROM pg_proc p \
+                 JOIN pg_namespace n ON p.pronamespace = n.oid \
+                 WHERE n.nspname = $1 AND {}",
+                not_extension_member("pg_proc", "p.oid")
+            ),
             &[&schema],
         )
         .await?;
+    let mut targets = Vec::new();
     for row in rows {
         let name: String = row.get(0);
+        if !filter.allows(&name) {
+            continue;
+        }
         let args: String = row.get(1);
-        let sql = format!(
-            "DROP FUNCTION IF EXISTS {}.{}({}) CASCADE",
-            schema_q,
-            quote_ident(&name),
-            args
-        );
-        client.batch_execute(&sql).await?;
+        targets.push(format!("{}({})", qualified(&name), args));
         dropped.push(format!("Function: {}.{}", schema, name));
     }
+    groups.push(("FUNCTION", targets));
 
     // Drop custom types (enums, composites)
     let rows = client
         .query(
-            "SELECT t.typname \
-             FROM pg_type t \
-             JOIN pg_namespace n ON t.typnamespace = n.oid \
-             WHERE n.nspname = $1 \
-             AND t.typtype IN ('e', 'c') \
-             AND t.typname NOT LIKE '\\_%'",
+            &format!(
+                "SELECT t.typname \
+                 FROM pg_type t \
+                 JOIN pg_namespace n ON t.typnamespace = n.oid \
+                 WHERE n.nspname = $1 \
+                 AND (t.typtype = 'e' OR (t.typtype = 'c' AND \
+                      (SELECT c.relkind FROM pg_class c WHERE c.oid = t.typrelid) = 'c')) \
+                 AND t.typname NOT LIKE '\\_%' \
+                 AND {}",
+                not_extension_member("pg_type", "t.oid")
+            ),
             &[&schema],
         )
         .await?;
+    let mut targets = Vec::new();
     for row in rows {
         let name: String = row.get(0);
-        let sql = format!(
-            "DROP TYPE IF EXISTS {}.{} CASCADE",
-            schema_q,
-            quote_ident(&name)
-        );
-        client.batch_execute(&sql).await?;
+        if !filter.allows(&name) {
+            continue;
+        }
+        targets.push(qualified(&name));
         dropped.push(format!("Type: {}.{}", schema, name));
     }
+    groups.push(("TYPE", targets));
+
+    if cascade {
+        for (keyword, targets) in &groups {
+            for target in targets {
+                let sql = format!("DROP {} IF EXISTS {} CASCADE", keyword, target);
+                client.batch_execute(&sql).await?;
+            }
+        }
+    } else {
+        drop_restrict(client, groups).await?;
+    }
 
     log::warn!(
         "Clean completed; schema={}, objects_dropped={}",
@@ -214,12 +243,89 @@ async fn execute_inner_pg(client: &Client, config: &WaypointConfig) -> Result<Ve
     Ok(dropped)
 }
 
+/// Drop each group with `RESTRICT`, so nothing outside the filter is
+/// dropped along with them.
+///
+/// A group goes in one statement, which allows dependencies within it. A
+/// group still needed by another is retried after the rest, until a round
+/// drops nothing; what is left then depends on an excluded object, and the
+/// clean fails with PostgreSQL's list of dependents.
+#[cfg(feature = "postgres")]
+async fn drop_restrict(client: &Client, mut groups: Vec<(&str, Vec<String>)>) -> Result<()> {
+    groups.retain(|(_, targets)| !targets.is_empty());
+    while !groups.is_empty() {
+        let mut blocked = Vec::new();
+        let mut details = Vec::new();
+        let round = groups.len();
+        for (keyword, targets) in groups {
+            let sql = format!("DROP {} IF EXISTS {} RESTRICT", keyword, targets.join(", "));
+            client.batch_execute("SAVEPOINT waypoint_clean").await?;
+            match client.batch_execute(&sql).await {
+                Ok(()) => {
+                    client
+                        .batch_execute("RELEASE SAVEPOINT waypoint_clean")
+                        .await?
+                }
+                Err(e) => {
+                    let db_err = match e.as_db_error() {
+                        Some(db_err)
+                            if db_err.code() == &SqlState::DEPENDENT_OBJECTS_STILL_EXIST =>
+                        {
+                            db_err
+                        }
+                        _ => return Err(e.into()),
+                    };
+                    details.push(db_err.detail().unwrap_or(db_err.message()).to_string());
+                    client
+                        .batch_execute("ROLLBACK TO SAVEPOINT waypoint_clean")
+                        .await?;
+                    blocked.push((keyword, targets));
+                }
+            }
+        }
+        if blocked.len() == round {
+            return Err(WaypointError::ConfigError(format!(
+                "Clean would drop objects excluded by include_objects/exclude_objects, \
+                 so nothing was dropped. Filter out what they depend on too.\n  {}",
+                details.join("\n  ")
+            )));
+        }
+        groups = blocked;
+    }
+    Ok(())
+}
+
+/// Names of the relations of the given `relkind`s in schema `$1` that do not
+/// belong to an extension.
+#[cfg(feature = "postgres")]
+fn relations_query(relkinds: &str) -> String {
+    format!(
+        "SELECT c.relname::text FROM pg_class c \
+         JOIN pg_namespace n ON c.relnamespace = n.oid \
+         WHERE n.nspname = $1 AND c.relkind IN ({}) AND {}",
+        relkinds,
+        not_extension_member("pg_class", "c.oid")
+    )
+}
+
+/// SQL condition that the object `oid` in `catalog` was not created by
+/// `CREATE EXTENSION`.
+#[cfg(feature = "postgres")]
+fn not_extension_member(catalog: &str, oid: &str) -> String {
+    format!(
+        "NOT EXISTS (SELECT 1 FROM pg_depend d \
+         WHERE d.classid = '{}'::regclass AND d.objid = {} AND d.deptype = 'e')",
+        catalog, oid
+    )
+}
+
 #[cfg(feature = "mysql")]
 async fn execute_inner_mysql(client: &DbClient, config: &WaypointConfig) -> Result<Vec<String>> {
     use mysql_async::prelude::*;
     let pool = client.as_mysql()?;
     let schema = client.resolve_schema(&config.migrations.schema).await?;
     let mut dropped = Vec::new();
+    let filter = config.database.object_filter();
 
     log::warn!(
         "Starting clean — this will DROP all objects in the database; database={}",
@@ -240,6 +346,9 @@ async fn execute_inner_mysql(client: &DbClient, config: &WaypointConfig) -> Resu
         )
         .await?;
     for name in views {
+        if !filter.allows(&name) {
+            continue;
+        }
         let sql = format!("DROP VIEW IF EXISTS `{}`.`{}`", schema, name);
         conn.query_drop(&sql).await?;
         dropped.push(format!("View: {}.{}", schema, name));
@@ -254,6 +363,9 @@ async fn execute_inner_mysql(client: &DbClient, config: &WaypointConfig) -> Resu
         )
         .await?;
     for name in tables {
+        if !filter.allows(&name) {
+            continue;
+        }
         let sql = format!("DROP TABLE IF EXISTS `{}`.`{}`", schema, name);
         conn.query_drop(&sql).await?;
         dropped.push(format!("Table: {}.{}", schema, name));
@@ -268,6 +380,9 @@ async fn execute_inner_mysql(client: &DbClient, config: &WaypointConfig) -> Resu
         )
         .await?;
     for (name, kind) in routines {
+        if !filter.allows(&name) {
+            continue;
+        }
         let kw = if kind.eq_ignore_ascii_case("PROCEDURE") {
             "PROCEDURE"
         } else {
@@ -286,6 +401,9 @@ async fn execute_inner_mysql(client: &DbClient, config: &WaypointConfig) -> Resu
         )
         .await?;
     for name in events {
+        if !filter.allows(&name) {
+            continue;
+        }
         let sql = format!("DROP EVENT IF EXISTS `{}`.`{}`", schema, name);
         conn.query_drop(&sql).await?;
         dropped.push(format!("Event: {}.{}", schema, name));
@@ -1,20 +1,34 @@
 //! Compare live database schema against a target and generate migration SQL.
 
+use std::path::PathBuf;
+
 use serde::Serialize;
 
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
 use crate::config::WaypointConfig;
+#[cfg(feature = "postgres")]
+use crate::db;
 use crate::db::DbClient;
 use crate::dialect::DialectKind;
+use crate::drift_baseline;
 use crate::error::{Result, WaypointError};
-use crate::schema::{self, SchemaDiff};
+use crate::placeholder::{build_placeholders, replace_placeholders};
+use crate::schema::{self, SchemaDiff, SchemaSnapshot};
+use crate::schema_sql;
 
 /// Target to compare the current schema against.
 pub enum DiffTarget {
     /// Compare against another database identified by its connection URL.
     Database(String),
+    /// Compare against a schema-only SQL dump, such as `pg_dump --schema-only`
+    /// or `snapshot export` output (PostgreSQL only).
+    File(PathBuf),
+    /// Compare against the schema the local migration files describe: every
+    /// versioned migration, then the repeatables, replayed into a throwaway
+    /// schema (a throwaway database on MySQL) that is dropped afterwards.
+    Migrations,
 }
 
 /// Report produced by the diff command.
@@ -26,6 +40,11 @@ pub struct DiffReport {
     pub generated_sql: String,
     /// Whether any differences were detected.
     pub has_changes: bool,
+    /// Version of the source server, used to shape `generated_sql`.
+    pub server_version: Option<String>,
+    /// Object categories left out because introspection timed out.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub incomplete: Vec<String>,
 }
 
 /// Execute the diff command (PostgreSQL legacy entry).
@@ -44,16 +63,23 @@ pub async fn execute(
             let target_client = crate::db::connect(url).await?;
             schema::introspect(&target_client, schema_name).await?
         }
+        DiffTarget::File(ref path) => schema_sql::read_file(path, schema_name)?,
+        DiffTarget::Migrations => replay_migrations(client, config, schema_name).await?,
     };
+    let (current, target_snapshot) =
+        without_history_objects(&target, config, current, target_snapshot);
 
     let diffs = schema::diff(&current, &target_snapshot);
-    let generated_sql = schema::generate_ddl(&diffs);
+    let version = crate::db::get_server_version(client).await.ok();
+    let generated_sql = schema::generate_ddl_for_version(&diffs, version);
     let has_changes = !diffs.is_empty();
 
     Ok(DiffReport {
         diffs,
         generated_sql,
         has_changes,
+        server_version: version.map(|v| v.to_string()),
+        incomplete: schema::incomplete_categories(&current, &target_snapshot),
     })
 }
 
@@ -92,20 +118,250 @@ pub async fn execute_db(
             };
             schema::introspect_db(&target_client, &target_schema).await?
         }
+        DiffTarget::File(ref path) => {
+            if client.dialect_kind() == DialectKind::Mysql {
+                return Err(WaypointError::ConfigError(
+                    "Diffing against a schema file is supported on PostgreSQL only".to_string(),
+                ));
+            }
+            schema_sql::read_file(path, &schema_name)?
+        }
+        DiffTarget::Migrations => match client.dialect_kind() {
+            #[cfg(feature = "postgres")]
+            DialectKind::Postgres => {
+                replay_migrations(client.as_postgres()?, config, &schema_name).await?
+            }
+            #[cfg(feature = "mysql")]
+            DialectKind::Mysql => replay_migrations_mysql(client, config, &schema_name).await?,
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(WaypointError::ConfigError(
+                    "Support for this database is not compiled in".to_string(),
+                ));
+            }
+        },
     };
+    let (current, target_snapshot) =
+        without_history_objects(&target, config, current, target_snapshot);
 
     let diffs = schema::diff(&current, &target_snapshot);
-    let generated_sql = schema::generate_ddl(&diffs);
+    let generated_sql = schema::generate_ddl_for_version(&diffs, client.pg_version().await);
     let has_changes = !diffs.is_empty();
 
     Ok(DiffReport {
         diffs,
         generated_sql,
         has_changes,
+        server_version: client.server_version().await.ok(),
+        incomplete: schema::incomplete_categories(&current, &target_snapshot),
+    })
+}
+
+/// The replayed schema has no history table; leave waypoint's own tables out
+/// of both sides so they don't show up as dropped.
+fn without_history_objects(
+    target: &DiffTarget,
+    config: &WaypointConfig,
+    current: SchemaSnapshot,
+    target_snapshot: SchemaSnapshot,
+) -> (SchemaSnapshot, SchemaSnapshot) {
+    if !matches!(target, DiffTarget::Migrations) {
+        return (current, target_snapshot);
+    }
+    let table = &config.migrations.table;
+    (
+        drift_baseline::without_waypoint_objects(&current, table),
+        drift_baseline::without_waypoint_objects(&target_snapshot, table),
+    )
+}
+
+/// Name for the throwaway schema or database migrations are replayed into.
+fn scratch_name() -> String {
+    format!(
+        "waypoint_diff_{}",
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    )
+}
+
+/// Scripts to replay for [`DiffTarget::Migrations`], in apply order
+/// (versioned by version, then repeatables), with placeholders resolved
+/// against the scratch schema. Undo migrations are left out.
+fn replay_scripts(
+    config: &WaypointConfig,
+    scratch: &str,
+    db_user: &str,
+    db_name: &str,
+) -> Result<Vec<(String, String)>> {
+    let resolved = config.migrations.resolve()?;
+    resolved
+        .iter()
+        .filter(|m| !m.is_undo())
+        .map(|m| {
+            let placeholders =
+                build_placeholders(&config.placeholders, scratch, db_user, db_name, &m.script);
+            let sql = replace_placeholders(&m.sql, &placeholders)?;
+            Ok((m.script.clone(), sql))
+        })
+        .collect()
+}
+
+/// Point a snapshot introspected from the scratch schema at `schema_name`,
+/// including schema-qualified references inside view and index definitions,
+/// so it diffs cleanly against the live schema. The scratch name is unique
+/// enough for a plain text replacement.
+fn with_schema_renamed(
+    snapshot: &SchemaSnapshot,
+    scratch: &str,
+    schema_name: &str,
+) -> Result<SchemaSnapshot> {
+    let json = serde_json::to_string(snapshot).map_err(|e| WaypointError::SnapshotError {
+        reason: format!("Failed to serialize snapshot: {}", e),
+    })?;
+    serde_json::from_str(&json.replace(scratch, schema_name)).map_err(|e| {
+        WaypointError::SnapshotError {
+            reason: format!("Failed to rename snapshot schema: {}", e),
+        }
     })
 }
 
-async fn connect_for_url(url: &str) -> Result<DbClient> {
+/// Replay the migration files into a throwaway schema and introspect it.
+#[cfg(feature = "postgres")]
+async fn replay_migrations(
+    client: &Client,
+    config: &WaypointConfig,
+    schema_name: &str,
+) -> Result<SchemaSnapshot> {
+    let scratch = scratch_name();
+    client
+        .batch_execute(&format!("CREATE SCHEMA {}", db::quote_ident(&scratch)))
+        .await?;
+
+    let result = replay_into_schema(client, config, schema_name, &scratch).await;
+
+    // Always clean up the scratch schema
+    let _ = client
+        .batch_execute(&format!(
+            "DROP SCHEMA {} CASCADE",
+            db::quote_ident(&scratch)
+        ))
+        .await;
+
+    result
+}
+
+#[cfg(feature = "postgres")]
+async fn replay_into_schema(
+    client: &Client,
+    config: &WaypointConfig,
+    schema_name: &str,
+    scratch: &str,
+) -> Result<SchemaSnapshot> {
+    let db_user = db::get_current_user(client)
+        .await
+        .unwrap_or_else(|_| "unknown".to_string());
+    let db_name = db::get_current_database(client)
+        .await
+        .unwrap_or_else(|_| "unknown".to_string());
+    let scripts = replay_scripts(config, scratch, &db_user, &db_name)?;
+
+    client
+        .batch_execute(&format!("SET search_path TO {}", db::quote_ident(scratch)))
+        .await?;
+    // Introspect while the scratch schema is on the search path, so view
+    // definitions come out unqualified the way the live ones do.
+    let mut replayed = Ok(());
+    for (script, sql) in scripts {
+        // A code migration's SQL is only a checksum stand-in; run its `up()`.
+        let result = match config.migrations.code_migrations.find(&script) {
+            Some(code) => code.up(client).await.map_err(|e| e.to_string()),
+            None => client.batch_execute(&sql).await.map_err(|e| e.to_string()),
+        };
+        if let Err(e) = result {
+            replayed = Err(WaypointError::MigrationFailed {
+                script,
+                reason: format!("Diff replay: {}", e),
+            });
+            break;
+        }
+    }
+    let snapshot = match replayed {
+        Ok(()) => schema::introspect(client, scratch).await,
+        Err(e) => Err(e),
+    };
+    client
+        .batch_execute(&format!(
+            "SET search_path TO {}",
+            db::quote_ident(schema_name)
+        ))
+        .await?;
+
+    let snapshot = snapshot?;
+    with_schema_renamed(&snapshot, scratch, schema_name)
+}
+
+/// Replay the migration files into a throwaway database and introspect it.
+#[cfg(feature = "mysql")]
+async fn replay_migrations_mysql(
+    client: &DbClient,
+    config: &WaypointConfig,
+    schema_name: &str,
+) -> Result<SchemaSnapshot> {
+    use mysql_async::prelude::*;
+    let pool = client.as_mysql()?;
+    let scratch = scratch_name();
+
+    let db_user = client
+        .current_user()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+    let db_name = client
+        .current_database()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+    let scripts = replay_scripts(config, &scratch, &db_user, &db_name)?;
+    if let Some((script, _)) = scripts
+        .iter()
+        .find(|(script, _)| crate::migration::is_code_script(script))
+    {
+        return Err(WaypointError::MigrationFailed {
+            script: script.clone(),
+            reason: "Diff replay: code migrations are only supported on PostgreSQL".to_string(),
+        });
+    }
+
+    let mut conn = pool.get_conn().await?;
+    conn.query_drop(format!("CREATE DATABASE `{}`", scratch))
+        .await?;
+
+    // One connection for the whole replay so `USE` persists.
+    let result: Result<SchemaSnapshot> = async {
+        conn.query_drop(format!("USE `{}`", scratch)).await?;
+        for (script, sql) in scripts {
+            for stmt in crate::sql_parser::split_mysql_statements(&sql) {
+                conn.query_drop(&stmt)
+                    .await
+                    .map_err(|e| WaypointError::MigrationFailed {
+                        script: script.clone(),
+                        reason: format!("Diff replay: {}", e),
+                    })?;
+            }
+        }
+        schema::introspect_db(client, &scratch).await
+    }
+    .await;
+
+    // Always drop the scratch database.
+    if let Err(e) = conn
+        .query_drop(format!("DROP DATABASE IF EXISTS `{}`", scratch))
+        .await
+    {
+        log::warn!("Failed to drop diff scratch database {}: {}", scratch, e);
+    }
+
+    with_schema_renamed(&result?, &scratch, schema_name)
+}
+
+pub(crate) async fn connect_for_url(url: &str) -> Result<DbClient> {
     let kind = DialectKind::from_url(url).unwrap_or(DialectKind::Postgres);
     match kind {
         #[cfg(feature = "postgres")]
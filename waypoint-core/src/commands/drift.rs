@@ -2,22 +2,31 @@
 //!
 //! Creates a temporary schema (PostgreSQL) or database (MySQL), applies all
 //! migrations to it, then compares it against the live schema to detect drift.
+//! The report carries DDL both ways: a corrective migration that absorbs the
+//! drift into the migration set ([`write_fix_migration`]), and a revert
+//! script that returns the live schema to what the migrations produce.
+
+use std::path::Path;
 
 use serde::Serialize;
 
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
+use crate::commands::new_migration::{
+    self, MigrationTemplate, NewMigrationOptions, NewMigrationReport,
+};
 use crate::config::WaypointConfig;
 #[cfg(feature = "postgres")]
 use crate::db;
 use crate::db::DbClient;
 use crate::dialect::DialectKind;
+use crate::drift_baseline;
 use crate::error::{Result, WaypointError};
-use crate::history;
-use crate::migration::scan_migrations;
+use crate::history::{self, HISTORY_META_TABLE};
 use crate::placeholder::build_placeholders;
-use crate::schema::{self, SchemaDiff};
+use crate::schema::{self, SchemaDiff, SchemaSnapshot};
+use crate::schema_sql;
 
 /// Type of drift detected.
 #[derive(Debug, Clone, Serialize)]
@@ -28,6 +37,8 @@ pub enum DriftType {
     MissingObject,
     /// An object exists in both but its definition has been changed outside migrations.
     ModifiedObject,
+    /// A table was renamed outside migrations (dropped + added with identical columns).
+    RenamedObject,
 }
 
 impl std::fmt::Display for DriftType {
@@ -36,6 +47,7 @@ impl std::fmt::Display for DriftType {
             DriftType::ExtraObject => write!(f, "Extra (not in migrations)"),
             DriftType::MissingObject => write!(f, "Missing (in migrations but not in DB)"),
             DriftType::ModifiedObject => write!(f, "Modified (differs from migrations)"),
+            DriftType::RenamedObject => write!(f, "Renamed (outside migrations)"),
         }
     }
 }
@@ -60,11 +72,378 @@ pub struct DriftReport {
     pub has_drift: bool,
     /// Name of the schema that was checked for drift.
     pub schema: String,
+    /// Statements that record the drift as a migration, turning the
+    /// migrations' schema into the live one.
+    pub corrective: Vec<CorrectiveStatement>,
+    /// Statements that revert the live schema to the one the migrations
+    /// produce, undoing the drift.
+    pub revert: Vec<CorrectiveStatement>,
+    /// Fingerprints compared by a check against the stored baseline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineComparison>,
+    /// Object categories left out because introspection timed out.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub incomplete: Vec<String>,
+}
+
+/// Stored baseline vs live schema, from `waypoint drift --stored`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineComparison {
+    /// When the baseline was recorded.
+    pub recorded_at: chrono::DateTime<chrono::Utc>,
+    /// Fingerprint recorded by the last migrate.
+    pub expected: String,
+    /// Fingerprint of the live schema.
+    pub actual: String,
+}
+
+/// One statement of the corrective migration.
+#[derive(Debug, Clone, Serialize)]
+pub struct CorrectiveStatement {
+    /// The DDL statement.
+    pub sql: String,
+    /// Whether running it drops an object (a table, column or type with its
+    /// data, or an index, constraint, view, function or trigger) on
+    /// databases that still match the migrations.
+    pub destructive: bool,
+}
+
+impl DriftReport {
+    /// The corrective migration as a script. Destructive statements are
+    /// commented out unless `allow_destructive` is set.
+    pub fn corrective_sql(&self, allow_destructive: bool) -> String {
+        render_script(
+            &format!(
+                "-- Corrective migration for drift in schema '{}'\n",
+                self.schema
+            ),
+            &self.corrective,
+            allow_destructive,
+        )
+    }
+
+    /// The revert script, to run by hand against this database. Destructive
+    /// statements are commented out unless `allow_destructive` is set.
+    pub fn revert_sql(&self, allow_destructive: bool) -> String {
+        render_script(
+            &format!(
+                "-- Revert drift in schema '{}' to the state the migrations produce.\n\
+                 -- Run against this database only; this is not a migration.\n",
+                self.schema
+            ),
+            &self.revert,
+            allow_destructive,
+        )
+    }
+
+    /// Whether any corrective statement is destructive.
+    pub fn has_destructive(&self) -> bool {
+        self.corrective.iter().any(|s| s.destructive)
+    }
+
+    /// Whether any revert statement is destructive.
+    pub fn revert_has_destructive(&self) -> bool {
+        self.revert.iter().any(|s| s.destructive)
+    }
+}
+
+fn render_script(
+    header: &str,
+    statements: &[CorrectiveStatement],
+    allow_destructive: bool,
+) -> String {
+    let mut out = header.to_string();
+    for stmt in statements {
+        if stmt.destructive && !allow_destructive {
+            out.push_str("-- DESTRUCTIVE, rerun with --allow-destructive to include:\n");
+            for line in stmt.sql.lines() {
+                out.push_str(&format!("-- {}\n", line));
+            }
+        } else {
+            out.push_str(&stmt.sql);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Write the corrective migration as the next versioned migration,
+/// `V{next}__Absorb_drift.sql`, in the first configured location
+/// (`drift --generate-fix`). It is numbered after every version on disk and
+/// in `applied_versions`, as `waypoint new` would number it.
+pub fn write_fix_migration(
+    report: &DriftReport,
+    config: &WaypointConfig,
+    applied_versions: &[String],
+    allow_destructive: bool,
+    dry_run: bool,
+) -> Result<NewMigrationReport> {
+    let options = NewMigrationOptions {
+        name: "V__Absorb_drift".to_string(),
+        template: MigrationTemplate::Ddl,
+        undo: false,
+        values: Default::default(),
+        dry_run: true,
+    };
+    let mut fix = new_migration::execute(config, &options, applied_versions)?;
+    for file in &mut fix.files {
+        file.contents = report.corrective_sql(allow_destructive);
+        if !dry_run {
+            if let Some(dir) = std::path::Path::new(&file.path).parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&file.path, &file.contents)?;
+        }
+    }
+    fix.dry_run = dry_run;
+    Ok(fix)
+}
+
+/// Build the corrective migration for `diffs` (expected → live), skipping
+/// the history table, `waypoint_meta`, and drift-check temp objects.
+fn corrective_statements(
+    diffs: &[SchemaDiff],
+    history_table: &str,
+    dialect: DialectKind,
+) -> Vec<CorrectiveStatement> {
+    diffs
+        .iter()
+        .filter(|d| {
+            let label = d.to_string();
+            !label.contains(history_table)
+                && !label.contains(HISTORY_META_TABLE)
+                && !label.contains("waypoint_drift_check")
+        })
+        .filter_map(|d| {
+            let one = std::slice::from_ref(d);
+            let sql = match dialect {
+                DialectKind::Mysql => schema::generate_ddl_mysql(one),
+                DialectKind::Postgres => schema::generate_ddl(one),
+            };
+            let sql = sql.trim().to_string();
+            if sql.is_empty() {
+                return None;
+            }
+            Some(CorrectiveStatement {
+                sql,
+                destructive: matches!(
+                    d,
+                    SchemaDiff::TableDropped(_)
+                        | SchemaDiff::ColumnDropped { .. }
+                        | SchemaDiff::SequenceDropped(_)
+                        | SchemaDiff::EnumDropped(_)
+                        | SchemaDiff::IndexDropped { .. }
+                        | SchemaDiff::ConstraintDropped { .. }
+                        | SchemaDiff::ViewDropped(_)
+                        | SchemaDiff::FunctionDropped(_)
+                        | SchemaDiff::TriggerDropped { .. }
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Build the revert script: the statements turning `live` back into
+/// `expected`. `scratch` is the schema or database the migrations were
+/// replayed into; the objects the diffs carry over from it are pointed at
+/// `live_schema` instead (see [`requalify`]).
+fn revert_statements(
+    expected: &SchemaSnapshot,
+    live: &SchemaSnapshot,
+    history_table: &str,
+    dialect: DialectKind,
+    scratch: Option<(&str, &str)>,
+) -> Vec<CorrectiveStatement> {
+    let mut diffs = schema::detect_renames(live, schema::diff(live, expected));
+    if let Some((scratch, live_schema)) = scratch {
+        diffs = diffs
+            .into_iter()
+            .map(|d| requalify(d, scratch, live_schema, dialect))
+            .collect();
+    }
+    corrective_statements(&diffs, history_table, dialect)
+}
+
+/// Move an object of a diff from the `scratch` schema to `live_schema`: its
+/// schema field, and the `scratch.` qualifiers in the SQL it carries (index,
+/// view, function and constraint definitions, column defaults). Only
+/// qualifiers are rewritten, so an identifier that merely ends with the
+/// scratch name is left alone.
+fn requalify(d: SchemaDiff, scratch: &str, live_schema: &str, dialect: DialectKind) -> SchemaDiff {
+    let sql = |s: String| requalify_sql(&s, scratch, live_schema, dialect);
+    let schema = |s: String| {
+        if s == scratch {
+            live_schema.to_string()
+        } else {
+            s
+        }
+    };
+    let column = |mut c: schema::ColumnDef| {
+        c.default = c.default.map(sql);
+        c
+    };
+    match d {
+        SchemaDiff::TableAdded(mut t) => {
+            t.schema = schema(t.schema);
+            t.columns = t.columns.into_iter().map(column).collect();
+            SchemaDiff::TableAdded(t)
+        }
+        SchemaDiff::ColumnAdded { table, column: c } => SchemaDiff::ColumnAdded {
+            table,
+            column: column(c),
+        },
+        SchemaDiff::ColumnAltered {
+            table,
+            column: name,
+            from,
+            to,
+        } => SchemaDiff::ColumnAltered {
+            table,
+            column: name,
+            from,
+            to: column(to),
+        },
+        SchemaDiff::IndexAdded(mut i) => {
+            i.schema = schema(i.schema);
+            i.definition = sql(i.definition);
+            SchemaDiff::IndexAdded(i)
+        }
+        SchemaDiff::ViewAdded(mut v) => {
+            v.schema = schema(v.schema);
+            v.definition = sql(v.definition);
+            SchemaDiff::ViewAdded(v)
+        }
+        SchemaDiff::ViewAltered { name, from, to } => SchemaDiff::ViewAltered {
+            name,
+            from,
+            to: sql(to),
+        },
+        SchemaDiff::SequenceAdded(mut s) => {
+            s.schema = schema(s.schema);
+            SchemaDiff::SequenceAdded(s)
+        }
+        SchemaDiff::FunctionAdded(mut f) => {
+            f.schema = schema(f.schema);
+            f.definition = sql(f.definition);
+            SchemaDiff::FunctionAdded(f)
+        }
+        SchemaDiff::EnumAdded(mut e) => {
+            e.schema = schema(e.schema);
+            SchemaDiff::EnumAdded(e)
+        }
+        SchemaDiff::ConstraintAdded(mut c) => {
+            c.schema = schema(c.schema);
+            c.definition = sql(c.definition);
+            SchemaDiff::ConstraintAdded(c)
+        }
+        SchemaDiff::TriggerAdded(mut t) => {
+            t.schema = schema(t.schema);
+            t.definition = sql(t.definition);
+            SchemaDiff::TriggerAdded(t)
+        }
+        other => other,
+    }
+}
+
+/// Rewrite `scratch.` qualifiers in `sql`, bare or quoted, to `live_schema`.
+/// A match must start at an identifier boundary; quoted qualifiers keep
+/// their quoting, and a bare one is quoted when `live_schema` needs it.
+fn requalify_sql(sql: &str, scratch: &str, live_schema: &str, dialect: DialectKind) -> String {
+    let quote = match dialect {
+        DialectKind::Mysql => '`',
+        DialectKind::Postgres => '"',
+    };
+    let plain = live_schema
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && live_schema
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    let quoted = |name: &str| {
+        let doubled = format!("{0}{0}", quote);
+        format!("{0}{1}{0}", quote, name.replace(quote, &doubled))
+    };
+    let bare_live = if plain {
+        live_schema.to_string()
+    } else {
+        quoted(live_schema)
+    };
+    let quoted_scratch = format!("{}.", quoted(scratch));
+    let quoted_live = format!("{}.", quoted(live_schema));
+    let bare_scratch = format!("{}.", scratch);
+
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$' || c == quote;
+    let mut out = String::with_capacity(sql.len());
+    let mut rest = sql;
+    let mut prev: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+        let at_boundary = !prev.is_some_and(is_ident);
+        if at_boundary && rest.starts_with(&quoted_scratch) {
+            out.push_str(&quoted_live);
+            rest = &rest[quoted_scratch.len()..];
+            prev = Some('.');
+        } else if at_boundary && rest.starts_with(&bare_scratch) {
+            out.push_str(&bare_live);
+            out.push('.');
+            rest = &rest[bare_scratch.len()..];
+            prev = Some('.');
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            prev = Some(c);
+        }
+    }
+    out
 }
 
 /// Execute the drift command (PostgreSQL legacy entry).
 #[cfg(feature = "postgres")]
 pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<DriftReport> {
+    execute_with_scratch(client, client, config).await
+}
+
+/// Execute the drift command against a read-only standby (PostgreSQL only).
+///
+/// A standby can't hold the temporary schema, so migrations are replayed
+/// into `migrations.standby_scratch_url` and compared with the standby's
+/// live schema.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn execute_on_standby(client: &DbClient, config: &WaypointConfig) -> Result<DriftReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => {
+            let Some(url) = &config.migrations.standby_scratch_url else {
+                return Err(WaypointError::ConfigError(
+                    "drift on a standby replays migrations elsewhere; set standby_scratch_url to a writable database".to_string(),
+                ));
+            };
+            let scratch = db::connect_with_full_config(
+                url,
+                &config.database.ssl_mode,
+                config.database.connect_retries,
+                config.database.connect_timeout_secs,
+                config.database.statement_timeout_secs,
+                config.database.keepalive_secs,
+            )
+            .await?;
+            execute_with_scratch(client.as_postgres()?, &scratch, config).await
+        }
+        _ => Err(WaypointError::ConfigError(
+            "drift on a read-only server is not supported for this database (PostgreSQL only)"
+                .to_string(),
+        )),
+    }
+}
+
+/// Replay migrations into a temporary schema on `scratch` and compare it
+/// with the live schema on `live`. Both may be the same connection.
+#[cfg(feature = "postgres")]
+async fn execute_with_scratch(
+    live: &Client,
+    scratch: &Client,
+    config: &WaypointConfig,
+) -> Result<DriftReport> {
     let schema_name = &config.migrations.schema;
     let table = &config.migrations.table;
 
@@ -75,14 +454,14 @@ pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<DriftRe
     );
 
     // Create temp schema
-    client
+    scratch
         .batch_execute(&format!("CREATE SCHEMA {}", db::quote_ident(&temp_schema)))
         .await?;
 
-    let result = run_drift_check(client, config, schema_name, table, &temp_schema).await;
+    let result = run_drift_check(live, scratch, config, schema_name, table, &temp_schema).await;
 
     // Always clean up temp schema
-    let _ = client
+    let _ = scratch
         .batch_execute(&format!(
             "DROP SCHEMA {} CASCADE",
             db::quote_ident(&temp_schema)
@@ -94,32 +473,33 @@ pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<DriftRe
 
 #[cfg(feature = "postgres")]
 async fn run_drift_check(
-    client: &Client,
+    live: &Client,
+    scratch: &Client,
     config: &WaypointConfig,
     schema_name: &str,
     table: &str,
     temp_schema: &str,
 ) -> Result<DriftReport> {
     // Create history table in temp schema
-    history::create_history_table(client, temp_schema, table).await?;
+    history::create_history_table(scratch, temp_schema, table).await?;
 
     // Get applied migrations (successful ones only)
-    let applied = history::get_applied_migrations(client, schema_name, table).await?;
+    let applied = history::get_applied_migrations(live, schema_name, table).await?;
     let effective = history::effective_applied_versions(&applied);
 
     // Scan migration files
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
 
     // Get DB info for placeholders
-    let db_user = db::get_current_user(client)
+    let db_user = db::get_current_user(live)
         .await
         .unwrap_or_else(|_| "unknown".to_string());
-    let db_name = db::get_current_database(client)
+    let db_name = db::get_current_database(live)
         .await
         .unwrap_or_else(|_| "unknown".to_string());
 
     // Set search_path to temp schema and apply migrations
-    client
+    scratch
         .batch_execute(&format!(
             "SET search_path TO {}",
             db::quote_ident(temp_schema)
@@ -141,7 +521,7 @@ async fn run_drift_check(
             &migration.script,
         );
         let sql = crate::placeholder::replace_placeholders(&migration.sql, &placeholders)?;
-        client.batch_execute(&sql).await.map_err(|e| {
+        scratch.batch_execute(&sql).await.map_err(|e| {
             crate::error::WaypointError::MigrationFailed {
                 script: migration.script.clone(),
                 reason: format!("Drift check: {}", e),
@@ -150,7 +530,7 @@ async fn run_drift_check(
     }
 
     // Reset search_path
-    client
+    scratch
         .batch_execute(&format!(
             "SET search_path TO {}",
             db::quote_ident(schema_name)
@@ -158,11 +538,14 @@ async fn run_drift_check(
         .await?;
 
     // Introspect both schemas
-    let live_snapshot = schema::introspect(client, schema_name).await?;
-    let expected_snapshot = schema::introspect(client, temp_schema).await?;
+    let live_snapshot = schema::introspect(live, schema_name).await?;
+    let expected_snapshot = schema::introspect(scratch, temp_schema).await?;
 
     // Diff: expected (from migrations) vs live (actual DB state)
-    let diffs = schema::diff(&expected_snapshot, &live_snapshot);
+    let diffs = schema::detect_renames(
+        &expected_snapshot,
+        schema::diff(&expected_snapshot, &live_snapshot),
+    );
 
     let mut drifts = Vec::new();
     for d in &diffs {
@@ -177,6 +560,11 @@ async fn run_drift_check(
                 format!("TABLE {}", n),
                 "Table exists in migrations but not in DB".to_string(),
             ),
+            SchemaDiff::TableRenamed { from, to } => (
+                DriftType::RenamedObject,
+                format!("TABLE {} -> {}", from, to),
+                "Table appears to have been renamed manually (identical columns)".to_string(),
+            ),
             SchemaDiff::ColumnAdded { table, column } => (
                 DriftType::ExtraObject,
                 format!("COLUMN {}.{}", table, column.name),
@@ -217,7 +605,10 @@ async fn run_drift_check(
         };
 
         // Filter out the history table itself from drift results
-        if object.contains(table) || object.contains("waypoint_drift_check") {
+        if object.contains(table)
+            || object.contains(HISTORY_META_TABLE)
+            || object.contains("waypoint_drift_check")
+        {
             continue;
         }
 
@@ -234,6 +625,16 @@ async fn run_drift_check(
         drifts,
         has_drift,
         schema: schema_name.to_string(),
+        corrective: corrective_statements(&diffs, table, DialectKind::Postgres),
+        revert: revert_statements(
+            &expected_snapshot,
+            &live_snapshot,
+            table,
+            DialectKind::Postgres,
+            Some((temp_schema, schema_name)),
+        ),
+        baseline: None,
+        incomplete: schema::incomplete_categories(&expected_snapshot, &live_snapshot),
     })
 }
 
@@ -255,6 +656,109 @@ pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<Dr
     }
 }
 
+/// Compare the live schema with the baseline the last migrate stored under
+/// `[drift] store_baseline`, without replaying migrations.
+///
+/// Matching fingerprints mean no drift. Otherwise the stored snapshot is
+/// diffed against the live one to say what changed.
+pub async fn execute_stored(client: &DbClient, config: &WaypointConfig) -> Result<DriftReport> {
+    let schema_name = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+    let stored = drift_baseline::load(client, config).await?.ok_or_else(|| {
+        WaypointError::ConfigError(format!(
+            "No drift baseline stored in {}; set [drift] store_baseline = true and run migrate",
+            drift_baseline::baseline_table_name(table)
+        ))
+    })?;
+
+    let live = drift_baseline::without_waypoint_objects(
+        &schema::introspect_db(client, &schema_name).await?,
+        table,
+    );
+    let actual = drift_baseline::fingerprint(&drift_baseline::canonical_json(&live));
+    // A partial snapshot never matches the fingerprint; compare what was read.
+    let partial = !live.incomplete.is_empty();
+
+    let (drifts, corrective, revert) = if actual == stored.fingerprint {
+        (Vec::new(), Vec::new(), Vec::new())
+    } else {
+        let expected: SchemaSnapshot = serde_json::from_str(&stored.snapshot).map_err(|e| {
+            WaypointError::ConfigError(format!("Invalid stored drift baseline: {}", e))
+        })?;
+        let diffs = schema::detect_renames(&expected, schema::diff(&expected, &live));
+        let mut drifts = diffs_to_drift_entries(&diffs, table);
+        if drifts.is_empty() && !partial {
+            // The fingerprint covers more than the structural diff compares.
+            drifts.push(DriftEntry {
+                drift_type: DriftType::ModifiedObject,
+                object: format!("SCHEMA {}", schema_name),
+                detail: "Schema fingerprint differs from the stored baseline".to_string(),
+            });
+        }
+        (
+            drifts,
+            corrective_statements(&diffs, table, client.dialect_kind()),
+            revert_statements(&expected, &live, table, client.dialect_kind(), None),
+        )
+    };
+
+    Ok(DriftReport {
+        has_drift: !drifts.is_empty(),
+        drifts,
+        schema: schema_name,
+        corrective,
+        revert,
+        baseline: Some(BaselineComparison {
+            recorded_at: stored.recorded_at,
+            expected: stored.fingerprint,
+            actual,
+        }),
+        incomplete: live.incomplete,
+    })
+}
+
+/// Compare the live schema with a schema file (`drift --against`), such as
+/// a `pg_dump --schema-only` dump or `snapshot export` output kept in git,
+/// instead of replaying migrations (PostgreSQL only).
+pub async fn execute_against_file(
+    client: &DbClient,
+    config: &WaypointConfig,
+    path: &Path,
+) -> Result<DriftReport> {
+    if client.dialect_kind() == DialectKind::Mysql {
+        return Err(WaypointError::ConfigError(
+            "drift --against reads PostgreSQL schema dumps; it is not supported on MySQL"
+                .to_string(),
+        ));
+    }
+    let schema_name = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+    let dialect = client.dialect_kind();
+
+    // A dump of the live database includes the history table; leave it out
+    // on both sides.
+    let expected = drift_baseline::without_waypoint_objects(
+        &schema_sql::read_file(path, &schema_name)?,
+        table,
+    );
+    let live = drift_baseline::without_waypoint_objects(
+        &schema::introspect_db(client, &schema_name).await?,
+        table,
+    );
+    let diffs = schema::detect_renames(&expected, schema::diff(&expected, &live));
+    let drifts = diffs_to_drift_entries(&diffs, table);
+
+    Ok(DriftReport {
+        has_drift: !drifts.is_empty(),
+        drifts,
+        schema: schema_name,
+        corrective: corrective_statements(&diffs, table, dialect),
+        revert: revert_statements(&expected, &live, table, dialect, None),
+        baseline: None,
+        incomplete: schema::incomplete_categories(&expected, &live),
+    })
+}
+
 /// MySQL drift detection.
 ///
 /// Creates a throwaway database, replays migrations that have been recorded
@@ -314,7 +818,7 @@ async fn run_drift_check_mysql(
     let applied = history::get_applied_migrations_db(client, schema_name, table).await?;
     let effective = history::effective_applied_versions(&applied);
 
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let db_user = client
         .current_user()
         .await
@@ -358,19 +862,29 @@ async fn run_drift_check_mysql(
     let live = schema::introspect_db(client, schema_name).await?;
     let expected = schema::introspect_db(client, temp_db).await?;
 
-    let diffs = schema::diff(&expected, &live);
+    let diffs = schema::detect_renames(&expected, schema::diff(&expected, &live));
     let drifts = diffs_to_drift_entries(&diffs, table);
 
     Ok(DriftReport {
         has_drift: !drifts.is_empty(),
         drifts,
         schema: schema_name.to_string(),
+        corrective: corrective_statements(&diffs, table, DialectKind::Mysql),
+        revert: revert_statements(
+            &expected,
+            &live,
+            table,
+            DialectKind::Mysql,
+            Some((temp_db, schema_name)),
+        ),
+        baseline: None,
+        incomplete: schema::incomplete_categories(&expected, &live),
     })
 }
 
 /// Convert a list of structural [`SchemaDiff`]s to user-facing [`DriftEntry`]s.
-/// Pulled out so the MySQL path can reuse the PG drift-categorization rules.
-#[cfg(feature = "mysql")]
+/// Pulled out so the MySQL and stored-baseline paths can reuse the PG
+/// drift-categorization rules.
 fn diffs_to_drift_entries(diffs: &[SchemaDiff], history_table: &str) -> Vec<DriftEntry> {
     let mut drifts = Vec::new();
     for d in diffs {
@@ -385,6 +899,11 @@ fn diffs_to_drift_entries(diffs: &[SchemaDiff], history_table: &str) -> Vec<Drif
                 format!("TABLE {}", n),
                 "Table exists in migrations but not in DB".to_string(),
             ),
+            SchemaDiff::TableRenamed { from, to } => (
+                DriftType::RenamedObject,
+                format!("TABLE {} -> {}", from, to),
+                "Table appears to have been renamed manually (identical columns)".to_string(),
+            ),
             SchemaDiff::ColumnAdded { table, column } => (
                 DriftType::ExtraObject,
                 format!("COLUMN {}.{}", table, column.name),
@@ -415,8 +934,11 @@ fn diffs_to_drift_entries(diffs: &[SchemaDiff], history_table: &str) -> Vec<Drif
                 (DriftType::ModifiedObject, detail.clone(), detail)
             }
         };
-        // Filter out the history table itself + the drift-check temp DB.
-        if object.contains(history_table) || object.contains("waypoint_drift_check") {
+        // Filter out the history table, waypoint_meta + the drift-check temp DB.
+        if object.contains(history_table)
+            || object.contains(HISTORY_META_TABLE)
+            || object.contains("waypoint_drift_check")
+        {
             continue;
         }
         drifts.push(DriftEntry {
@@ -427,3 +949,88 @@ fn diffs_to_drift_entries(diffs: &[SchemaDiff], history_table: &str) -> Vec<Drif
     }
     drifts
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_fix_migration_numbers_after_files_and_history() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V3__Create.sql"), "SELECT 1;").unwrap();
+        let mut config = WaypointConfig::default();
+        config.migrations.locations = vec![dir.path().to_path_buf()];
+        let report = DriftReport {
+            drifts: Vec::new(),
+            has_drift: true,
+            schema: "public".to_string(),
+            corrective: vec![
+                CorrectiveStatement {
+                    sql: "CREATE INDEX users_email_idx ON public.users (email);".to_string(),
+                    destructive: false,
+                },
+                CorrectiveStatement {
+                    sql: "DROP TABLE IF EXISTS \"legacy\" CASCADE;".to_string(),
+                    destructive: true,
+                },
+            ],
+            revert: Vec::new(),
+            baseline: None,
+            incomplete: Vec::new(),
+        };
+
+        let dry = write_fix_migration(&report, &config, &["4".to_string()], false, true).unwrap();
+        let path = dir.path().join("V5__Absorb_drift.sql");
+        assert_eq!(dry.version.as_deref(), Some("5"));
+        assert_eq!(dry.files[0].path, path.display().to_string());
+        assert!(!path.exists());
+
+        let fix = write_fix_migration(&report, &config, &["4".to_string()], false, false).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, fix.files[0].contents);
+        assert!(written.contains("\nCREATE INDEX users_email_idx"));
+        assert!(written.contains("-- DROP TABLE IF EXISTS \"legacy\" CASCADE;"));
+
+        // The written migration now takes V5.
+        let next = write_fix_migration(&report, &config, &[], true, true).unwrap();
+        assert_eq!(next.version.as_deref(), Some("6"));
+    }
+
+    #[test]
+    fn test_requalify_sql_rewrites_only_qualifiers() {
+        let scratch = "waypoint_drift_check_1";
+        let pg = |sql: &str, live: &str| requalify_sql(sql, scratch, live, DialectKind::Postgres);
+        assert_eq!(
+            pg(
+                "CREATE INDEX i ON waypoint_drift_check_1.notes USING btree (id)",
+                "public"
+            ),
+            "CREATE INDEX i ON public.notes USING btree (id)"
+        );
+        assert_eq!(
+            pg("SELECT * FROM \"waypoint_drift_check_1\".t", "Sales"),
+            "SELECT * FROM \"Sales\".t"
+        );
+        assert_eq!(
+            pg(
+                "nextval('waypoint_drift_check_1.t_id_seq'::regclass)",
+                "Sales"
+            ),
+            "nextval('\"Sales\".t_id_seq'::regclass)"
+        );
+        // Identifiers that only end with the scratch name stay as they are.
+        assert_eq!(
+            pg("SELECT x_waypoint_drift_check_1.a FROM t", "public"),
+            "SELECT x_waypoint_drift_check_1.a FROM t"
+        );
+        assert_eq!(
+            requalify_sql(
+                "REFERENCES `waypoint_drift_check_1`.`users` (`id`)",
+                scratch,
+                "app",
+                DialectKind::Mysql
+            ),
+            "REFERENCES `app`.`users` (`id`)"
+        );
+    }
+}
@@ -54,6 +54,33 @@ pub struct StatementExplain {
     pub is_ddl: bool,
 }
 
+/// Entry for a Rust code migration, which has no SQL to EXPLAIN.
+fn code_migration_explain(info: &info::MigrationInfo) -> MigrationExplain {
+    MigrationExplain {
+        script: info.script.clone(),
+        version: info.version.clone(),
+        statements: vec![StatementExplain {
+            statement_preview: "Rust code migration".to_string(),
+            plan: "Code migration — not explainable".to_string(),
+            estimated_rows: None,
+            estimated_cost: None,
+            warnings: vec!["Not analyzed: review up() by hand".to_string()],
+            is_ddl: false,
+        }],
+    }
+}
+
+/// Add a warning to the last explained statement if it needs elevated privileges.
+fn flag_privileged(statements: &mut [StatementExplain], sql: &str, dialect: DialectKind) {
+    if let (Some(stmt), Some(p)) = (
+        statements.last_mut(),
+        crate::safety::classify_privileged(sql, dialect),
+    ) {
+        stmt.warnings
+            .push(format!("Requires elevated privileges: {}", p.reason));
+    }
+}
+
 /// Execute explain analysis for pending migrations (PostgreSQL legacy entry).
 #[cfg(feature = "postgres")]
 pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<ExplainReport> {
@@ -73,7 +100,7 @@ pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<Explain
         .unwrap_or_else(|_| "unknown".to_string());
 
     // Scan migration files to get SQL content
-    let resolved = crate::migration::scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
 
     let mut migrations = Vec::new();
 
@@ -81,6 +108,10 @@ pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<Explain
         // Find the resolved migration matching this info
         let migration = resolved.iter().find(|m| m.script == info.script);
         let sql = match migration {
+            Some(m) if m.is_code() => {
+                migrations.push(code_migration_explain(info));
+                continue;
+            }
             Some(m) => {
                 let placeholders =
                     build_placeholders(&config.placeholders, schema, &db_user, &db_name, &m.script);
@@ -162,6 +193,7 @@ pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<Explain
                     }
                 }
             }
+            flag_privileged(&mut statements, trimmed, DialectKind::Postgres);
         }
 
         // Rollback the transaction
@@ -224,12 +256,16 @@ async fn execute_mysql(client: &DbClient, config: &WaypointConfig) -> Result<Exp
         .await
         .unwrap_or_else(|_| "unknown".into());
 
-    let resolved = crate::migration::scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let mut migrations = Vec::new();
 
     for info in &pending {
         let migration = resolved.iter().find(|m| m.script == info.script);
         let sql = match migration {
+            Some(m) if m.is_code() => {
+                migrations.push(code_migration_explain(info));
+                continue;
+            }
             Some(m) => {
                 let placeholders = build_placeholders(
                     &config.placeholders,
@@ -307,6 +343,7 @@ async fn execute_mysql(client: &DbClient, config: &WaypointConfig) -> Result<Exp
                     }),
                 }
             }
+            flag_privileged(&mut statements, trimmed, DialectKind::Mysql);
         }
 
         migrations.push(MigrationExplain {
@@ -0,0 +1,606 @@
+//! Export table rows as INSERT or COPY scripts, with anonymization.
+//!
+//! `waypoint export --table users --anonymize email,name` reads the table and
+//! writes a script that recreates its rows, replacing the listed columns with
+//! fake but realistic values. Replacements are derived from an HMAC-SHA256 of
+//! the original value keyed with the configured seed, so the same input always
+//! maps to the same output: an email that appears in two tables stays
+//! consistent, and re-exporting produces the same seed file. Without the seed
+//! the mapping can't be rebuilt by hashing guessed values, so export refuses
+//! to run hashing strategies until one is set.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+#[cfg(feature = "postgres")]
+use futures_util::{pin_mut, TryStreamExt};
+#[cfg(feature = "postgres")]
+use tokio_postgres::types::ToSql;
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+use crate::config::WaypointConfig;
+#[cfg(feature = "postgres")]
+use crate::db::quote_ident;
+use crate::db::DbClient;
+#[cfg(feature = "postgres")]
+use crate::dialect::DialectKind;
+use crate::error::{Result, WaypointError};
+
+/// Output format for exported rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One `INSERT` statement per row; runs anywhere, including as a migration.
+    #[default]
+    Insert,
+    /// A `COPY ... FROM stdin` block; faster to load, but needs `psql`.
+    Copy,
+}
+
+impl FromStr for ExportFormat {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "insert" => Ok(Self::Insert),
+            "copy" => Ok(Self::Copy),
+            other => Err(WaypointError::ConfigError(format!(
+                "Unknown export format '{}'. Valid values: insert, copy",
+                other
+            ))),
+        }
+    }
+}
+
+/// How to replace an anonymized column's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnonymizeStrategy {
+    /// `user_<hash>@example.com`
+    Email,
+    /// `First Last`
+    Name,
+    /// A first name.
+    FirstName,
+    /// A last name.
+    LastName,
+    /// `+1-555-xxx-xxxx`
+    Phone,
+    /// A 16-character hex digest of the value.
+    Hash,
+    /// The literal string `redacted`.
+    Redact,
+    /// `NULL`
+    Null,
+}
+
+impl AnonymizeStrategy {
+    /// Guess a strategy from a column name, falling back to [`Self::Hash`].
+    pub fn infer(column: &str) -> Self {
+        let c = column.to_lowercase();
+        if c.contains("email") {
+            Self::Email
+        } else if c.contains("first_name") || c.contains("firstname") || c == "given_name" {
+            Self::FirstName
+        } else if c.contains("last_name")
+            || c.contains("lastname")
+            || c.contains("surname")
+            || c == "family_name"
+        {
+            Self::LastName
+        } else if c.contains("name") {
+            Self::Name
+        } else if c.contains("phone") || c.contains("mobile") {
+            Self::Phone
+        } else {
+            Self::Hash
+        }
+    }
+
+    /// Whether the replacement is derived from the value, and so needs a
+    /// secret seed to keep the original from being guessed.
+    pub fn is_keyed(self) -> bool {
+        !matches!(self, Self::Redact | Self::Null)
+    }
+
+    /// Replacement for `value`, or `None` for SQL NULL. NULL inputs stay NULL.
+    pub fn apply(self, seed: &str, value: Option<&str>) -> Option<String> {
+        let value = value?;
+        let h = keyed_hash(seed, value);
+        let first = FIRST_NAMES[(h % FIRST_NAMES.len() as u64) as usize];
+        let last = LAST_NAMES[((h >> 16) % LAST_NAMES.len() as u64) as usize];
+        match self {
+            Self::Email => Some(format!("user_{:016x}@example.com", h)),
+            Self::Name => Some(format!("{} {}", first, last)),
+            Self::FirstName => Some(first.to_string()),
+            Self::LastName => Some(last.to_string()),
+            Self::Phone => Some(format!(
+                "+1-555-{:03}-{:04}",
+                (h >> 32) % 1000,
+                (h >> 8) % 10000
+            )),
+            Self::Hash => Some(format!("{:016x}", h)),
+            Self::Redact => Some("redacted".to_string()),
+            Self::Null => None,
+        }
+    }
+}
+
+impl fmt::Display for AnonymizeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Email => "email",
+            Self::Name => "name",
+            Self::FirstName => "first_name",
+            Self::LastName => "last_name",
+            Self::Phone => "phone",
+            Self::Hash => "hash",
+            Self::Redact => "redact",
+            Self::Null => "null",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for AnonymizeStrategy {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "email" => Ok(Self::Email),
+            "name" => Ok(Self::Name),
+            "first_name" => Ok(Self::FirstName),
+            "last_name" => Ok(Self::LastName),
+            "phone" => Ok(Self::Phone),
+            "hash" => Ok(Self::Hash),
+            "redact" => Ok(Self::Redact),
+            "null" => Ok(Self::Null),
+            other => Err(WaypointError::ConfigError(format!(
+                "Unknown anonymization strategy '{}'. Valid values: email, name, first_name, \
+                 last_name, phone, hash, redact, null",
+                other
+            ))),
+        }
+    }
+}
+
+/// Configuration for the `[export]` section.
+#[derive(Debug, Clone, Default)]
+pub struct ExportConfig {
+    /// Secret key for every derived replacement; change it to get different
+    /// fake values. Required by every strategy except `redact` and `null`.
+    pub seed: String,
+    /// Columns that are always anonymized, keyed by `column` (any table) or
+    /// `table.column`.
+    pub anonymize: HashMap<String, AnonymizeStrategy>,
+}
+
+/// What to export.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Table name, optionally schema-qualified (`schema.table`).
+    pub table: String,
+    /// Extra columns to anonymize, as `column` (strategy inferred from the
+    /// name or taken from `[export.anonymize]`) or `column:strategy`.
+    pub anonymize: Vec<String>,
+    /// Output format.
+    pub format: ExportFormat,
+    /// Optional SQL filter, e.g. `created_at > now() - interval '30 days'`.
+    pub where_clause: Option<String>,
+    /// Maximum number of rows to export.
+    pub limit: Option<i64>,
+}
+
+/// An anonymized column and the strategy applied to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct AnonymizedColumn {
+    pub column: String,
+    pub strategy: AnonymizeStrategy,
+}
+
+/// Result of an export.
+#[derive(Debug, Serialize)]
+pub struct ExportReport {
+    /// Schema-qualified table that was exported.
+    pub table: String,
+    /// Output format of `script`.
+    pub format: ExportFormat,
+    /// Number of rows exported.
+    pub rows: usize,
+    /// Exported columns, in table order.
+    pub columns: Vec<String>,
+    /// Columns whose values were replaced.
+    pub anonymized: Vec<AnonymizedColumn>,
+    /// The generated INSERT or COPY script; empty when it was written to a
+    /// caller's writer with [`execute_db_to`].
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub script: String,
+}
+
+/// Export a table's rows into the report's `script` (dialect-aware;
+/// PostgreSQL only).
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    options: &ExportOptions,
+) -> Result<ExportReport> {
+    let mut script = Vec::new();
+    let mut report = execute_db_to(client, config, options, &mut script).await?;
+    report.script = String::from_utf8(script)
+        .map_err(|e| WaypointError::ConfigError(format!("Export produced invalid UTF-8: {}", e)))?;
+    Ok(report)
+}
+
+/// Export a table's rows, writing the script to `out` one row at a time
+/// instead of holding the table in memory (dialect-aware; PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn execute_db_to(
+    client: &DbClient,
+    config: &WaypointConfig,
+    options: &ExportOptions,
+    out: &mut (dyn Write + Send),
+) -> Result<ExportReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => execute(client.as_postgres()?, config, options, out).await,
+        _ => Err(WaypointError::ConfigError(format!(
+            "Export is not supported on {:?} (PostgreSQL only)",
+            client.dialect_kind()
+        ))),
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn execute(
+    client: &Client,
+    config: &WaypointConfig,
+    options: &ExportOptions,
+    out: &mut (dyn Write + Send),
+) -> Result<ExportReport> {
+    let (schema, table) = match options.table.split_once('.') {
+        Some((s, t)) => (s.to_string(), t.to_string()),
+        None => (config.migrations.schema.clone(), options.table.clone()),
+    };
+
+    let columns: Vec<String> = client
+        .query(
+            "SELECT column_name::text FROM information_schema.columns \
+             WHERE table_schema = $1 AND table_name = $2 AND is_generated = 'NEVER' \
+             ORDER BY ordinal_position",
+            &[&schema, &table],
+        )
+        .await?
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+    if columns.is_empty() {
+        return Err(WaypointError::ConfigError(format!(
+            "Table {}.{} not found",
+            schema, table
+        )));
+    }
+
+    let strategies = resolve_strategies(&config.export, &table, &columns, &options.anonymize)?;
+    if config.export.seed.is_empty() {
+        if let Some((column, strategy)) = columns
+            .iter()
+            .zip(&strategies)
+            .find_map(|(c, s)| s.filter(|s| s.is_keyed()).map(|s| (c, s)))
+        {
+            return Err(WaypointError::ConfigError(format!(
+                "Cannot anonymize column '{}' with '{}' without a secret: set [export] seed \
+                 (or WAYPOINT_EXPORT_SEED), or use redact or null",
+                column, strategy
+            )));
+        }
+    }
+
+    let fq = format!("{}.{}", quote_ident(&schema), quote_ident(&table));
+    let select_list = columns
+        .iter()
+        .map(|c| format!("{}::text", quote_ident(c)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!("SELECT {} FROM {}", select_list, fq);
+    if let Some(ref filter) = options.where_clause {
+        sql.push_str(&format!(" WHERE {}", filter));
+    }
+    if let Some(limit) = options.limit {
+        sql.push_str(&format!(" LIMIT {}", limit));
+    }
+
+    let anonymized: Vec<AnonymizedColumn> = columns
+        .iter()
+        .zip(&strategies)
+        .filter_map(|(c, s)| {
+            s.map(|strategy| AnonymizedColumn {
+                column: c.clone(),
+                strategy,
+            })
+        })
+        .collect();
+
+    let mut header = format!("-- Exported from {}.{} by waypoint export", schema, table);
+    if !anonymized.is_empty() {
+        header.push_str(&format!(
+            " (anonymized: {})",
+            anonymized
+                .iter()
+                .map(|a| a.column.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    header.push('\n');
+    let column_list = columns
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if options.format == ExportFormat::Copy {
+        header.push_str(&copy_header(&fq, &column_list));
+    }
+    out.write_all(header.as_bytes())?;
+
+    // Rows stream from the server, so memory stays flat on large tables.
+    let stream = client
+        .query_raw(sql.as_str(), std::iter::empty::<&(dyn ToSql + Sync)>())
+        .await?;
+    pin_mut!(stream);
+    let mut rows = 0;
+    while let Some(row) = stream.try_next().await? {
+        let values: Vec<Option<String>> = (0..columns.len())
+            .map(|i| {
+                let value: Option<String> = row.get(i);
+                match strategies[i] {
+                    Some(strategy) => strategy.apply(&config.export.seed, value.as_deref()),
+                    None => value,
+                }
+            })
+            .collect();
+        let line = match options.format {
+            ExportFormat::Insert => insert_line(&fq, &column_list, &values),
+            ExportFormat::Copy => copy_line(&values),
+        };
+        out.write_all(line.as_bytes())?;
+        rows += 1;
+    }
+    if options.format == ExportFormat::Copy {
+        out.write_all(b"\\.\n")?;
+    }
+    out.flush()?;
+
+    log::info!(
+        "Exported table; table={}.{}, rows={}, anonymized={}",
+        schema,
+        table,
+        rows,
+        anonymized.len()
+    );
+
+    Ok(ExportReport {
+        table: format!("{}.{}", schema, table),
+        format: options.format,
+        rows,
+        columns,
+        anonymized,
+        script: String::new(),
+    })
+}
+
+/// Strategy for each column (in `columns` order), or `None` to copy it as is.
+///
+/// Columns named in `[export.anonymize]` (as `column` or `table.column`) are
+/// always anonymized; `requested` adds more, as `column` or `column:strategy`.
+#[cfg(feature = "postgres")]
+fn resolve_strategies(
+    export: &ExportConfig,
+    table: &str,
+    columns: &[String],
+    requested: &[String],
+) -> Result<Vec<Option<AnonymizeStrategy>>> {
+    let configured = |column: &str| {
+        export
+            .anonymize
+            .get(&format!("{}.{}", table, column))
+            .or_else(|| export.anonymize.get(column))
+            .copied()
+    };
+    let mut strategies: Vec<Option<AnonymizeStrategy>> =
+        columns.iter().map(|c| configured(c)).collect();
+
+    for entry in requested {
+        let (column, strategy) = match entry.split_once(':') {
+            Some((c, s)) => (c.trim(), Some(s.trim().parse()?)),
+            None => (entry.trim(), None),
+        };
+        let Some(idx) = columns.iter().position(|c| c == column) else {
+            return Err(WaypointError::ConfigError(format!(
+                "Column '{}' not found in table {}",
+                column, table
+            )));
+        };
+        strategies[idx] = Some(
+            strategy
+                .or_else(|| configured(column))
+                .unwrap_or_else(|| AnonymizeStrategy::infer(column)),
+        );
+    }
+    Ok(strategies)
+}
+
+/// One `INSERT` statement for `row`.
+#[cfg(feature = "postgres")]
+fn insert_line(fq: &str, column_list: &str, row: &[Option<String>]) -> String {
+    let values = row
+        .iter()
+        .map(|v| match v {
+            Some(v) => format!("'{}'", v.replace('\'', "''")),
+            None => "NULL".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "INSERT INTO {} ({}) VALUES ({});\n",
+        fq, column_list, values
+    )
+}
+
+#[cfg(feature = "postgres")]
+fn copy_header(fq: &str, column_list: &str) -> String {
+    format!("COPY {} ({}) FROM stdin;\n", fq, column_list)
+}
+
+/// One tab-separated `COPY` text line for `row`.
+#[cfg(feature = "postgres")]
+fn copy_line(row: &[Option<String>]) -> String {
+    let mut line = row
+        .iter()
+        .map(|v| match v {
+            Some(v) => v
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r"),
+            None => "\\N".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\t");
+    line.push('\n');
+    line
+}
+
+/// 64-bit HMAC-SHA256 of `value` keyed with `seed`, stable across runs and
+/// platforms.
+fn keyed_hash(seed: &str, value: &str) -> u64 {
+    let mut mac =
+        <Hmac<Sha256>>::new_from_slice(seed.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(value.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+}
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Blake", "Casey", "Dana", "Eli", "Frankie", "Gray", "Harper", "Indy", "Jordan", "Kai",
+    "Logan", "Morgan", "Noel", "Oakley", "Parker", "Quinn", "Riley", "Sage", "Taylor", "Umi",
+    "Val", "Wren", "Yael",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Adams", "Brooks", "Chen", "Diaz", "Evans", "Fischer", "Garcia", "Hughes", "Ito", "Jensen",
+    "Kumar", "Lopez", "Moreau", "Nakamura", "Okafor", "Patel", "Quist", "Rossi", "Silva", "Tanaka",
+    "Usman", "Varga", "Walsh", "Young",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_strategy() {
+        assert_eq!(AnonymizeStrategy::infer("email"), AnonymizeStrategy::Email);
+        assert_eq!(
+            AnonymizeStrategy::infer("billing_email"),
+            AnonymizeStrategy::Email
+        );
+        assert_eq!(
+            AnonymizeStrategy::infer("first_name"),
+            AnonymizeStrategy::FirstName
+        );
+        assert_eq!(
+            AnonymizeStrategy::infer("surname"),
+            AnonymizeStrategy::LastName
+        );
+        assert_eq!(AnonymizeStrategy::infer("name"), AnonymizeStrategy::Name);
+        assert_eq!(AnonymizeStrategy::infer("mobile"), AnonymizeStrategy::Phone);
+        assert_eq!(AnonymizeStrategy::infer("ssn"), AnonymizeStrategy::Hash);
+    }
+
+    #[test]
+    fn test_apply_is_deterministic() {
+        let a = AnonymizeStrategy::Email.apply("s", Some("alice@corp.com"));
+        let b = AnonymizeStrategy::Email.apply("s", Some("alice@corp.com"));
+        let c = AnonymizeStrategy::Email.apply("other", Some("alice@corp.com"));
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.unwrap().ends_with("@example.com"));
+        assert_eq!(AnonymizeStrategy::Name.apply("s", None), None);
+        assert_eq!(AnonymizeStrategy::Null.apply("s", Some("x")), None);
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn test_resolve_strategies() {
+        let mut export = ExportConfig::default();
+        export
+            .anonymize
+            .insert("ssn".to_string(), AnonymizeStrategy::Redact);
+        export
+            .anonymize
+            .insert("users.nick".to_string(), AnonymizeStrategy::Null);
+        let columns: Vec<String> = ["id", "email", "nick", "ssn", "phone"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let s = resolve_strategies(
+            &export,
+            "users",
+            &columns,
+            &["email".to_string(), "phone:hash".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            s,
+            vec![
+                None,
+                Some(AnonymizeStrategy::Email),
+                Some(AnonymizeStrategy::Null),
+                Some(AnonymizeStrategy::Redact),
+                Some(AnonymizeStrategy::Hash),
+            ]
+        );
+
+        assert!(resolve_strategies(&export, "users", &columns, &["nope".to_string()]).is_err());
+        assert!(
+            resolve_strategies(&export, "users", &columns, &["email:bogus".to_string()]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_keyed_hash_depends_on_seed() {
+        assert_eq!(keyed_hash("k", "alice"), keyed_hash("k", "alice"));
+        assert_ne!(keyed_hash("k", "alice"), keyed_hash("k2", "alice"));
+        assert_ne!(keyed_hash("k", "alice"), keyed_hash("k", "bob"));
+        assert!(AnonymizeStrategy::Email.is_keyed());
+        assert!(!AnonymizeStrategy::Redact.is_keyed());
+        assert!(!AnonymizeStrategy::Null.is_keyed());
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn test_copy_line_escapes() {
+        assert_eq!(
+            copy_header("t", "\"a\", \"b\""),
+            "COPY t (\"a\", \"b\") FROM stdin;\n"
+        );
+        assert_eq!(
+            copy_line(&[Some("x\ty\\z".to_string()), None]),
+            "x\\ty\\\\z\t\\N\n"
+        );
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn test_insert_line_quotes() {
+        let out = insert_line("t", "\"a\"", &[Some("O'Neil".to_string())]);
+        assert_eq!(out, "INSERT INTO t (\"a\") VALUES ('O''Neil');\n");
+    }
+}
@@ -0,0 +1,80 @@
+//! `waypoint fingerprint`: the live schema's canonical fingerprint.
+//!
+//! The same SHA-256 that `[drift] store_baseline` records and `[drift]
+//! publish_fingerprint` puts on the migrate report (see
+//! [`crate::drift_baseline`]), so a service can compare the schema it is
+//! connected to with the one it was built against instead of inferring it
+//! from the migration count. `--expect` fails with exit code 24
+//! (`FingerprintMismatch`) when they differ.
+
+use serde::Serialize;
+
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::drift_baseline::{self, StoredBaseline};
+use crate::error::{Result, WaypointError};
+
+/// Result of `fingerprint`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FingerprintReport {
+    /// Schema that was fingerprinted.
+    pub schema: String,
+    /// SHA-256 of the canonical snapshot, hex-encoded.
+    pub fingerprint: String,
+    /// Latest baseline recorded by migrate, if `store_baseline` has recorded one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored: Option<StoredBaseline>,
+}
+
+impl FingerprintReport {
+    /// Whether the live schema still matches the latest stored baseline.
+    pub fn matches_stored(&self) -> Option<bool> {
+        self.stored
+            .as_ref()
+            .map(|b| b.fingerprint == self.fingerprint)
+    }
+
+    /// `Err(FingerprintMismatch)` unless the live fingerprint is `expected`
+    /// (case-insensitive).
+    pub fn check(&self, expected: &str) -> Result<()> {
+        let expected = expected.trim();
+        if self.fingerprint.eq_ignore_ascii_case(expected) {
+            return Ok(());
+        }
+        Err(WaypointError::FingerprintMismatch {
+            expected: expected.to_string(),
+            actual: self.fingerprint.clone(),
+        })
+    }
+}
+
+/// Fingerprint the configured schema, leaving waypoint's own tables out.
+pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<FingerprintReport> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let (_, fingerprint) =
+        drift_baseline::capture(client, &schema, &config.migrations.table).await?;
+    let stored = drift_baseline::load(client, config).await?;
+    Ok(FingerprintReport {
+        schema,
+        fingerprint,
+        stored,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_expected_fingerprint() {
+        let report = FingerprintReport {
+            schema: "public".into(),
+            fingerprint: "ab12".into(),
+            stored: None,
+        };
+        assert!(report.check("AB12\n").is_ok());
+        assert_eq!(report.matches_stored(), None);
+        let err = report.check("cd34").unwrap_err();
+        assert_eq!(err.to_string(), "Schema fingerprint is ab12, expected cd34");
+    }
+}
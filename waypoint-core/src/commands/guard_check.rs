@@ -0,0 +1,118 @@
+//! Evaluate the guards of pending migrations without applying anything.
+//!
+//! `waypoint guards check` runs every `-- waypoint:require` and
+//! `-- waypoint:ensure` expression of the migrations `migrate` would apply
+//! next, against the current database. A require guard that is false (or
+//! cannot be evaluated) fails the check; ensure guards are postconditions, so
+//! before the migration runs their result is reported for information only.
+
+use serde::Serialize;
+
+use crate::commands::migrate::{should_run_in_environment, ConvergeReport};
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::directive::GuardDirective;
+use crate::error::Result;
+use crate::guard;
+
+/// Outcome of one guard.
+#[derive(Debug, Clone, Serialize)]
+pub struct GuardCheckResult {
+    /// Migration filename.
+    pub script: String,
+    /// `require` or `ensure`.
+    pub kind: String,
+    /// Guard expression as written.
+    pub expression: String,
+    /// Failure message from the directive, if any.
+    pub message: Option<String>,
+    /// Whether the guard held; `None` when it could not be evaluated.
+    pub passed: Option<bool>,
+    /// Parse or evaluation error.
+    pub error: Option<String>,
+}
+
+impl GuardCheckResult {
+    /// Whether this result fails the check: a require guard that did not hold.
+    pub fn is_failure(&self) -> bool {
+        self.kind == "require" && self.passed != Some(true)
+    }
+}
+
+/// Report from `waypoint guards check`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GuardCheckReport {
+    /// Schema the guards were evaluated against.
+    pub schema: String,
+    /// Pending migrations that were checked.
+    pub migrations_checked: usize,
+    /// One entry per guard, in migration order.
+    pub results: Vec<GuardCheckResult>,
+    /// Number of require guards that did not hold.
+    pub failed: usize,
+}
+
+/// Execute the guard check against the pending migrations.
+pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<GuardCheckReport> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let infos = crate::commands::info::execute_db(client, config).await?;
+    let pending = ConvergeReport::pending(&infos, None, &config.migrations)?;
+    let current_env = config.migrations.environment.as_deref();
+
+    let resolved = config.migrations.resolve()?;
+
+    let mut migrations_checked = 0;
+    let mut results = Vec::new();
+    for script in &pending.migrations {
+        let Some(migration) = resolved.iter().find(|m| &m.script == script) else {
+            continue;
+        };
+        if !should_run_in_environment(&migration.directives, current_env) {
+            continue;
+        }
+        migrations_checked += 1;
+
+        let guards = [
+            ("require", &migration.directives.require),
+            ("ensure", &migration.directives.ensure),
+        ];
+        for (kind, directives) in guards {
+            for directive in directives {
+                results.push(check_guard(client, &schema, script, kind, directive).await);
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.is_failure()).count();
+    Ok(GuardCheckReport {
+        schema,
+        migrations_checked,
+        results,
+        failed,
+    })
+}
+
+async fn check_guard(
+    client: &DbClient,
+    schema: &str,
+    script: &str,
+    kind: &str,
+    directive: &GuardDirective,
+) -> GuardCheckResult {
+    let outcome = match guard::parse(&directive.expression) {
+        Ok(expr) => guard::evaluate_db(client, schema, &expr).await,
+        Err(e) => Err(e),
+    };
+    let (passed, error) = match outcome {
+        Ok(passed) => (Some(passed), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    GuardCheckResult {
+        script: script.to_string(),
+        kind: kind.to_string(),
+        expression: directive.expression.clone(),
+        message: directive.message.clone(),
+        passed,
+        error,
+    }
+}
@@ -0,0 +1,44 @@
+//! Explicit history-table upgrades.
+//!
+//! `waypoint history upgrade` brings the history table to the layout this
+//! release writes and records the version in `waypoint_meta`. The same
+//! upgrade runs automatically on first contact; the explicit command exists
+//! for locked-down environments, where `--dry-run` prints the statements
+//! for a DBA to review and apply.
+
+use crate::config::WaypointConfig;
+use crate::db::{DbClient, LockTarget};
+use crate::error::{Result, WaypointError};
+use crate::history::{self, HistoryUpgrade};
+
+/// Plan, and unless `dry_run` apply, the history-table upgrade.
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    dry_run: bool,
+) -> Result<HistoryUpgrade> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+
+    if !history::history_table_exists_db(client, &schema, table).await? {
+        return Err(WaypointError::ConfigError(format!(
+            "History table {}.{} does not exist; migrate or baseline creates it at the current version",
+            schema, table
+        )));
+    }
+
+    if dry_run {
+        return history::plan_history_upgrade_db(client, &schema, table).await;
+    }
+
+    let lock = LockTarget::new(&config.migrations, &schema);
+    client.acquire_lock(&lock).await?;
+
+    let result = history::upgrade_history_table_db(client, &schema, table).await;
+
+    if let Err(e) = client.release_lock(&lock).await {
+        log::error!("Failed to release advisory lock: {}", e);
+    }
+
+    result
+}
@@ -0,0 +1,370 @@
+//! Import a Flyway `flyway_schema_history` table.
+//!
+//! Waypoint's history table shares Flyway's columns and checksum algorithm,
+//! so cutting over only needs the rows copied across: ranks, timestamps,
+//! users and timings are kept, and types are mapped to waypoint's (Flyway
+//! records repeatables as `SQL` with no version; `SQL_BASELINE` becomes
+//! `BASELINE`). Flyway's bookkeeping rows (`SCHEMA`, `DELETE`) and Java
+//! migrations are left behind. Before anything is written, each successful
+//! versioned row is checked against the local file with the same name, and
+//! a checksum mismatch stops the import unless forced.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::WaypointConfig;
+use crate::db::{validate_identifier, DbClient, LockTarget};
+use crate::error::{Result, WaypointError};
+use crate::history;
+use crate::migration::ResolvedMigration;
+
+/// Flyway's default history table name.
+pub const DEFAULT_FLYWAY_TABLE: &str = "flyway_schema_history";
+
+/// Flyway row types copied across, and the waypoint type each becomes.
+/// Repeatable migrations are `SQL` rows without a version.
+const TYPE_MAP: &[(&str, &str)] = &[
+    ("SQL", "SQL"),
+    ("BASELINE", "BASELINE"),
+    ("SQL_BASELINE", "BASELINE"),
+    ("UNDO_SQL", "UNDO_SQL"),
+];
+
+/// What happened to one Flyway history row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    /// Copied, and the local file (if checked) matches.
+    Imported,
+    /// The local file's checksum differs from the recorded one.
+    ChecksumMismatch,
+    /// Copied, but no local file has this name.
+    MissingFile,
+    /// Not copied: a Flyway bookkeeping row or a Java migration.
+    Skipped,
+}
+
+/// One row of the Flyway history table.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportEntry {
+    /// Flyway `installed_rank`, kept as is.
+    pub installed_rank: i32,
+    /// Version, or `None` for repeatables and Flyway's schema row.
+    pub version: Option<String>,
+    /// Flyway `script`.
+    pub script: String,
+    /// Flyway `type`.
+    pub flyway_type: String,
+    /// Type recorded in waypoint's history, `None` when skipped.
+    pub waypoint_type: Option<String>,
+    /// Checksum Flyway recorded.
+    pub checksum: Option<i32>,
+    /// Checksum of the local file, when one was found.
+    pub local_checksum: Option<i32>,
+    /// Outcome.
+    pub status: ImportStatus,
+}
+
+/// Result of `waypoint import-flyway`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportFlywayReport {
+    /// Flyway table read, as `schema.table`.
+    pub source: String,
+    /// Waypoint history table written, as `schema.table`.
+    pub target: String,
+    /// Every Flyway row, in `installed_rank` order.
+    pub entries: Vec<ImportEntry>,
+    /// Rows copied (or, for a dry run or a blocked import, that would be).
+    pub imported: usize,
+    /// Versioned rows whose local file has a different checksum.
+    pub mismatches: usize,
+    /// Whether nothing was written because of mismatches.
+    pub blocked: bool,
+    /// Whether the history table was left untouched.
+    pub dry_run: bool,
+}
+
+struct FlywayRow {
+    installed_rank: i32,
+    version: Option<String>,
+    migration_type: String,
+    script: String,
+    checksum: Option<i32>,
+    success: bool,
+}
+
+/// Waypoint history type for a Flyway row, or `None` if it isn't imported.
+pub fn waypoint_type(flyway_type: &str, version: Option<&str>) -> Option<&'static str> {
+    let mapped = TYPE_MAP
+        .iter()
+        .find(|(from, _)| *from == flyway_type)
+        .map(|(_, to)| *to)?;
+    Some(match (mapped, version) {
+        ("SQL", None) => "SQL_REPEATABLE",
+        _ => mapped,
+    })
+}
+
+/// Read `source_table` and, unless `dry_run` or blocked by checksum
+/// mismatches (override with `force`), copy it into the configured history
+/// table. The history table must be empty or not exist yet.
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    source_table: &str,
+    force: bool,
+    dry_run: bool,
+) -> Result<ImportFlywayReport> {
+    validate_identifier(source_table)?;
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+
+    if source_table == table {
+        return Err(WaypointError::ConfigError(format!(
+            "'{}' is already waypoint's history table; run `waypoint history upgrade` to add waypoint's columns instead",
+            table
+        )));
+    }
+    if !history::history_table_exists_db(client, &schema, source_table).await? {
+        return Err(WaypointError::ConfigError(format!(
+            "No Flyway history table {}.{}",
+            schema, source_table
+        )));
+    }
+    if history::history_table_exists_db(client, &schema, table).await?
+        && history::has_entries_db(client, &schema, table).await?
+    {
+        return Err(WaypointError::ConfigError(format!(
+            "History table {}.{} already has entries; import-flyway only fills an empty one",
+            schema, table
+        )));
+    }
+
+    let rows = read_rows(client, &schema, source_table).await?;
+    let resolved = config.migrations.resolve()?;
+    let mut report = ImportFlywayReport {
+        source: format!("{}.{}", schema, source_table),
+        target: format!("{}.{}", schema, table),
+        entries: rows.iter().map(|row| check_row(row, &resolved)).collect(),
+        imported: 0,
+        mismatches: 0,
+        blocked: false,
+        dry_run,
+    };
+    report.imported = report
+        .entries
+        .iter()
+        .filter(|e| e.status != ImportStatus::Skipped)
+        .count();
+    report.mismatches = report
+        .entries
+        .iter()
+        .filter(|e| e.status == ImportStatus::ChecksumMismatch)
+        .count();
+    for entry in report
+        .entries
+        .iter()
+        .filter(|e| e.status == ImportStatus::ChecksumMismatch)
+    {
+        log::warn!(
+            "Flyway checksum differs from local file; script={}, flyway={}, local={}",
+            entry.script,
+            entry.checksum.unwrap_or_default(),
+            entry.local_checksum.unwrap_or_default()
+        );
+    }
+    report.blocked = report.mismatches > 0 && !force;
+    if dry_run || report.blocked {
+        return Ok(report);
+    }
+
+    let lock = LockTarget::new(&config.migrations, &schema);
+    client.acquire_lock(&lock).await?;
+    let result = copy_rows(client, &schema, source_table, table).await;
+    if let Err(e) = client.release_lock(&lock).await {
+        log::error!("Failed to release advisory lock: {}", e);
+    }
+    result?;
+
+    log::info!(
+        "Imported Flyway history; imported={}, skipped={}, source={}, target={}",
+        report.imported,
+        report.entries.len() - report.imported,
+        report.source,
+        report.target
+    );
+    Ok(report)
+}
+
+/// Classify a Flyway row and check it against the local file of the same
+/// name. Only successful versioned rows are checked: a repeatable's older
+/// rows carry old checksums by design, and a changed repeatable simply
+/// re-runs on the next migrate.
+fn check_row(row: &FlywayRow, resolved: &[ResolvedMigration]) -> ImportEntry {
+    let mapped = waypoint_type(&row.migration_type, row.version.as_deref());
+    let file_name = Path::new(&row.script)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(&row.script);
+    let local = resolved.iter().find(|m| m.script == file_name);
+
+    let status = match mapped {
+        None => ImportStatus::Skipped,
+        Some("SQL") | Some("UNDO_SQL") if row.success => match local {
+            None => ImportStatus::MissingFile,
+            Some(m) if Some(m.checksum) != row.checksum => ImportStatus::ChecksumMismatch,
+            Some(_) => ImportStatus::Imported,
+        },
+        Some("SQL_REPEATABLE") if local.is_none() => ImportStatus::MissingFile,
+        Some(_) => ImportStatus::Imported,
+    };
+    ImportEntry {
+        installed_rank: row.installed_rank,
+        version: row.version.clone(),
+        script: row.script.clone(),
+        flyway_type: row.migration_type.clone(),
+        waypoint_type: mapped.map(str::to_string),
+        checksum: row.checksum,
+        local_checksum: local.map(|m| m.checksum),
+        status,
+    }
+}
+
+async fn read_rows(client: &DbClient, schema: &str, source_table: &str) -> Result<Vec<FlywayRow>> {
+    let sql = format!(
+        "SELECT installed_rank, version, type, script, checksum, success FROM {} ORDER BY installed_rank",
+        client.dialect().qualified_table(schema, source_table)
+    );
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => Ok(c
+            .query(&sql, &[])
+            .await?
+            .iter()
+            .map(|row| FlywayRow {
+                installed_rank: row.get(0),
+                version: row.get(1),
+                migration_type: row.get(2),
+                script: row.get(3),
+                checksum: row.get(4),
+                success: row.get(5),
+            })
+            .collect()),
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let mut conn = pool.get_conn().await?;
+            let rows: Vec<mysql_async::Row> = conn.query(&sql).await?;
+            rows.into_iter()
+                .map(|mut row| {
+                    let missing = |col: &str| {
+                        WaypointError::ConfigError(format!("Flyway history row missing {}", col))
+                    };
+                    Ok(FlywayRow {
+                        installed_rank: row.take(0).ok_or_else(|| missing("installed_rank"))?,
+                        version: row.take(1).unwrap_or(None),
+                        migration_type: row.take(2).ok_or_else(|| missing("type"))?,
+                        script: row.take(3).ok_or_else(|| missing("script"))?,
+                        checksum: row.take(4).unwrap_or(None),
+                        success: row.take::<i8, _>(5).ok_or_else(|| missing("success"))? != 0,
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// Copy the imported rows in one `INSERT ... SELECT`, so timestamps keep
+/// their database types and the copy is all-or-nothing.
+async fn copy_rows(client: &DbClient, schema: &str, source_table: &str, table: &str) -> Result<()> {
+    history::create_history_table_db(client, schema, table).await?;
+    let dialect = client.dialect();
+    let types = TYPE_MAP
+        .iter()
+        .map(|(from, _)| format!("'{}'", from))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "INSERT INTO {target} \
+         (installed_rank, version, description, type, script, checksum, installed_by, installed_on, execution_time, success) \
+         SELECT installed_rank, version, description, \
+         CASE WHEN type = 'SQL' AND version IS NULL THEN 'SQL_REPEATABLE' \
+              WHEN type = 'SQL_BASELINE' THEN 'BASELINE' ELSE type END, \
+         script, checksum, installed_by, installed_on, execution_time, success \
+         FROM {source} WHERE type IN ({types}) ORDER BY installed_rank",
+        target = dialect.qualified_table(schema, table),
+        source = dialect.qualified_table(schema, source_table),
+        types = types,
+    );
+    client.execute_raw(&sql).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directive::MigrationDirectives;
+    use crate::migration::{MigrationKind, MigrationVersion};
+
+    fn row(
+        migration_type: &str,
+        version: Option<&str>,
+        script: &str,
+        checksum: Option<i32>,
+    ) -> FlywayRow {
+        FlywayRow {
+            installed_rank: 1,
+            version: version.map(str::to_string),
+            migration_type: migration_type.to_string(),
+            script: script.to_string(),
+            checksum,
+            success: true,
+        }
+    }
+
+    #[test]
+    fn test_waypoint_type_mapping() {
+        assert_eq!(waypoint_type("SQL", Some("1")), Some("SQL"));
+        assert_eq!(waypoint_type("SQL", None), Some("SQL_REPEATABLE"));
+        assert_eq!(waypoint_type("SQL_BASELINE", Some("3")), Some("BASELINE"));
+        assert_eq!(waypoint_type("BASELINE", Some("1")), Some("BASELINE"));
+        assert_eq!(waypoint_type("SCHEMA", Some("0")), None);
+        assert_eq!(waypoint_type("DELETE", Some("2")), None);
+        assert_eq!(waypoint_type("JDBC", Some("4")), None);
+    }
+
+    #[test]
+    fn test_check_row_against_local_files() {
+        let resolved = vec![ResolvedMigration {
+            kind: MigrationKind::Versioned(MigrationVersion::parse("1").unwrap()),
+            description: "Init".to_string(),
+            script: "V1__Init.sql".to_string(),
+            checksum: 42,
+            sql: String::new(),
+            directives: MigrationDirectives::default(),
+            copy_checksum_sha256: None,
+        }];
+
+        // Flyway may record a path below the location; only the name matters.
+        let ok = check_row(
+            &row("SQL", Some("1"), "core/V1__Init.sql", Some(42)),
+            &resolved,
+        );
+        assert_eq!(ok.status, ImportStatus::Imported);
+
+        let changed = check_row(&row("SQL", Some("1"), "V1__Init.sql", Some(7)), &resolved);
+        assert_eq!(changed.status, ImportStatus::ChecksumMismatch);
+        assert_eq!(changed.local_checksum, Some(42));
+
+        let gone = check_row(&row("SQL", Some("2"), "V2__Gone.sql", Some(1)), &resolved);
+        assert_eq!(gone.status, ImportStatus::MissingFile);
+
+        let schema_row = check_row(
+            &row("SCHEMA", Some("0"), "<< Flyway Schema Creation >>", None),
+            &resolved,
+        );
+        assert_eq!(schema_row.status, ImportStatus::Skipped);
+        assert_eq!(schema_row.waypoint_type, None);
+    }
+}
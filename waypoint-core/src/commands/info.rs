@@ -8,11 +8,14 @@ use serde::Serialize;
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
+use crate::audit::AuditMetadata;
 use crate::config::WaypointConfig;
 use crate::db::DbClient;
 use crate::error::Result;
 use crate::history::{self, AppliedMigration};
-use crate::migration::{scan_migrations, MigrationKind, MigrationVersion, ResolvedMigration};
+use crate::migration::{
+    MigrationKind, MigrationVersion, ResolvedMigration, VersionFilter, VersionScheme,
+};
 
 /// The state of a migration.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -31,7 +34,8 @@ pub enum MigrationState {
     OutOfOrder,
     /// Versioned migration with a version at or below the baseline.
     BelowBaseline,
-    /// Migration was skipped (e.g. filtered by environment).
+    /// Pending migration left out by the version filter (`cherry_pick` /
+    /// `skip_versions`).
     Ignored,
     /// A baseline marker entry in the history table.
     Baseline,
@@ -56,6 +60,59 @@ impl std::fmt::Display for MigrationState {
     }
 }
 
+impl MigrationState {
+    /// Whether `migrate` would still apply this row: a pending or
+    /// out-of-order version, or a repeatable whose file changed.
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            MigrationState::Pending | MigrationState::OutOfOrder | MigrationState::Outdated
+        )
+    }
+}
+
+/// Which `info` rows to keep (`--pending`, `--applied`, `--failed`,
+/// `--since`). The state flags combine: `pending` and `failed` together keep
+/// both. With no state flag set, rows in every state are kept.
+#[derive(Debug, Clone, Default)]
+pub struct InfoFilter {
+    /// Keep rows still to be applied (see [`MigrationState::is_pending`]).
+    pub pending: bool,
+    /// Keep applied rows, including the baseline marker.
+    pub applied: bool,
+    /// Keep failed rows.
+    pub failed: bool,
+    /// Keep only versions above this one. Repeatable rows are kept.
+    pub since: Option<MigrationVersion>,
+}
+
+impl InfoFilter {
+    /// Whether `info` passes the filter.
+    pub fn matches(&self, info: &MigrationInfo) -> bool {
+        let any_state = self.pending || self.applied || self.failed;
+        let state_ok = !any_state
+            || (self.pending && info.state.is_pending())
+            || (self.applied
+                && matches!(
+                    info.state,
+                    MigrationState::Applied | MigrationState::Baseline
+                ))
+            || (self.failed && info.state == MigrationState::Failed);
+        let since_ok = match (&self.since, &info.version) {
+            (Some(since), Some(v)) => {
+                MigrationVersion::parse_with(v, since.scheme).is_ok_and(|v| &v > since)
+            }
+            _ => true,
+        };
+        state_ok && since_ok
+    }
+
+    /// The rows of `infos` that pass the filter, in order.
+    pub fn apply(&self, infos: Vec<MigrationInfo>) -> Vec<MigrationInfo> {
+        infos.into_iter().filter(|i| self.matches(i)).collect()
+    }
+}
+
 /// Combined view of a migration (file + history).
 #[derive(Debug, Clone, Serialize)]
 pub struct MigrationInfo {
@@ -75,6 +132,12 @@ pub struct MigrationInfo {
     pub execution_time: Option<i32>,
     /// CRC32 checksum of the migration SQL content.
     pub checksum: Option<i32>,
+    /// Schema the row belongs to, when `[migrations] schemas` lists several.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// Git commit, CI build and hostname recorded when the row was applied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit: Option<AuditMetadata>,
 }
 
 /// Execute the info command (PostgreSQL legacy entry).
@@ -82,14 +145,20 @@ pub struct MigrationInfo {
 pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<Vec<MigrationInfo>> {
     let schema = &config.migrations.schema;
     let table = &config.migrations.table;
+    let filter = config.migrations.version_filter()?;
 
     if !history::history_table_exists(client, schema, table).await? {
-        let resolved = scan_migrations(&config.migrations.locations)?;
-        return Ok(pending_only(resolved));
+        let resolved = config.migrations.resolve()?;
+        return Ok(pending_only(resolved, &filter));
     }
     let applied = history::get_applied_migrations(client, schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
-    Ok(merge(applied, resolved))
+    let resolved = config.migrations.resolve()?;
+    Ok(merge(
+        applied,
+        resolved,
+        &filter,
+        config.migrations.version_scheme,
+    ))
 }
 
 /// Execute the info command (dialect-aware entry).
@@ -97,22 +166,32 @@ pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<Ve
     let schema = client.resolve_schema(&config.migrations.schema).await?;
     let schema = schema.as_str();
     let table = &config.migrations.table;
+    let filter = config.migrations.version_filter()?;
 
     if !history::history_table_exists_db(client, schema, table).await? {
-        let resolved = scan_migrations(&config.migrations.locations)?;
-        return Ok(pending_only(resolved));
+        let resolved = config.migrations.resolve()?;
+        return Ok(pending_only(resolved, &filter));
     }
     let applied = history::get_applied_migrations_db(client, schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
-    Ok(merge(applied, resolved))
+    let resolved = config.migrations.resolve()?;
+    Ok(merge(
+        applied,
+        resolved,
+        &filter,
+        config.migrations.version_scheme,
+    ))
 }
 
 /// Build the "everything is pending" view used when the history table is absent.
-fn pending_only(resolved: Vec<ResolvedMigration>) -> Vec<MigrationInfo> {
+fn pending_only(resolved: Vec<ResolvedMigration>, filter: &VersionFilter) -> Vec<MigrationInfo> {
     resolved
         .into_iter()
         .filter(|m| !m.is_undo())
         .map(|m| {
+            let state = match m.version() {
+                Some(v) if !filter.selects(v) => MigrationState::Ignored,
+                _ => MigrationState::Pending,
+            };
             let version = m.version().map(|v| v.raw.clone());
             let migration_type = m.migration_type().to_string();
             MigrationInfo {
@@ -120,17 +199,24 @@ fn pending_only(resolved: Vec<ResolvedMigration>) -> Vec<MigrationInfo> {
                 description: m.description,
                 migration_type,
                 script: m.script,
-                state: MigrationState::Pending,
+                state,
                 installed_on: None,
                 execution_time: None,
                 checksum: Some(m.checksum),
+                schema: None,
+                audit: None,
             }
         })
         .collect()
 }
 
 /// Merge applied-migration rows with on-disk migrations into a unified status view.
-fn merge(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Vec<MigrationInfo> {
+fn merge(
+    applied: Vec<AppliedMigration>,
+    resolved: Vec<ResolvedMigration>,
+    filter: &VersionFilter,
+    scheme: VersionScheme,
+) -> Vec<MigrationInfo> {
     let effective = history::effective_applied_versions(&applied);
 
     let resolved_by_version: HashMap<String, &ResolvedMigration> = resolved
@@ -149,11 +235,11 @@ fn merge(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Ve
         .iter()
         .find(|a| a.migration_type == "BASELINE")
         .and_then(|a| a.version.as_ref())
-        .and_then(|v| MigrationVersion::parse(v).ok());
+        .and_then(|v| MigrationVersion::parse_with(v, scheme).ok());
 
     let highest_applied = effective
         .iter()
-        .filter_map(|v| MigrationVersion::parse(v).ok())
+        .filter_map(|v| MigrationVersion::parse_with(v, scheme).ok())
         .max();
 
     let mut infos: Vec<MigrationInfo> = Vec::new();
@@ -212,6 +298,8 @@ fn merge(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Ve
             installed_on: Some(am.installed_on),
             execution_time: Some(am.execution_time),
             checksum: am.checksum,
+            schema: None,
+            audit: (!am.audit.is_empty()).then(|| am.audit.clone()),
         });
     }
 
@@ -224,24 +312,12 @@ fn merge(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Ve
                 if seen_versions.contains_key(&version.raw) {
                     continue;
                 }
-                let state = if let Some(ref bv) = baseline_version {
-                    if version <= bv {
-                        MigrationState::BelowBaseline
-                    } else if let Some(ref highest) = highest_applied {
-                        if version < highest {
-                            MigrationState::OutOfOrder
-                        } else {
-                            MigrationState::Pending
-                        }
-                    } else {
-                        MigrationState::Pending
-                    }
-                } else if let Some(ref highest) = highest_applied {
-                    if version < highest {
-                        MigrationState::OutOfOrder
-                    } else {
-                        MigrationState::Pending
-                    }
+                let state = if baseline_version.as_ref().is_some_and(|bv| version <= bv) {
+                    MigrationState::BelowBaseline
+                } else if !filter.selects(version) {
+                    MigrationState::Ignored
+                } else if highest_applied.as_ref().is_some_and(|h| version < h) {
+                    MigrationState::OutOfOrder
                 } else {
                     MigrationState::Pending
                 };
@@ -255,6 +331,8 @@ fn merge(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Ve
                     installed_on: None,
                     execution_time: None,
                     checksum: Some(m.checksum),
+                    schema: None,
+                    audit: None,
                 });
             }
             MigrationKind::Repeatable => {
@@ -270,16 +348,33 @@ fn merge(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Ve
                     installed_on: None,
                     execution_time: None,
                     checksum: Some(m.checksum),
+                    schema: None,
+                    audit: None,
                 });
             }
             MigrationKind::Undo(_) => unreachable!("undo files are skipped above"),
         }
     }
 
+    // Repeatables keep the order they are applied in (`R{order}__` /
+    // `-- waypoint:order`); ones no longer on disk go last.
+    let repeatable_rank: HashMap<&str, usize> = resolved
+        .iter()
+        .filter(|m| matches!(m.kind, MigrationKind::Repeatable))
+        .enumerate()
+        .map(|(rank, m)| (m.script.as_str(), rank))
+        .collect();
+    let rank = |info: &MigrationInfo| {
+        repeatable_rank
+            .get(info.script.as_str())
+            .copied()
+            .unwrap_or(usize::MAX)
+    };
+
     infos.sort_by(|a, b| match (&a.version, &b.version) {
         (Some(av), Some(bv)) => {
-            let pa = MigrationVersion::parse(av);
-            let pb = MigrationVersion::parse(bv);
+            let pa = MigrationVersion::parse_with(av, scheme);
+            let pb = MigrationVersion::parse_with(bv, scheme);
             match (pa, pb) {
                 (Ok(pa), Ok(pb)) => pa.cmp(&pb),
                 _ => av.cmp(bv),
@@ -287,8 +382,82 @@ fn merge(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Ve
         }
         (Some(_), None) => std::cmp::Ordering::Less,
         (None, Some(_)) => std::cmp::Ordering::Greater,
-        (None, None) => a.description.cmp(&b.description),
+        (None, None) => rank(a)
+            .cmp(&rank(b))
+            .then_with(|| a.description.cmp(&b.description)),
     });
 
     infos
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(version: Option<&str>, state: MigrationState) -> MigrationInfo {
+        MigrationInfo {
+            version: version.map(str::to_string),
+            description: String::new(),
+            migration_type: "SQL".to_string(),
+            script: String::new(),
+            state,
+            installed_on: None,
+            execution_time: None,
+            checksum: None,
+            schema: None,
+            audit: None,
+        }
+    }
+
+    #[test]
+    fn test_info_filter() {
+        let infos = vec![
+            info(Some("1"), MigrationState::Baseline),
+            info(Some("2"), MigrationState::Applied),
+            info(Some("3"), MigrationState::Failed),
+            info(Some("4"), MigrationState::Pending),
+            info(None, MigrationState::Outdated),
+            info(Some("1.5"), MigrationState::OutOfOrder),
+        ];
+        let states = |filter: InfoFilter| -> Vec<MigrationState> {
+            filter
+                .apply(infos.clone())
+                .into_iter()
+                .map(|i| i.state)
+                .collect()
+        };
+
+        assert_eq!(states(InfoFilter::default()).len(), infos.len());
+        assert_eq!(
+            states(InfoFilter {
+                pending: true,
+                ..Default::default()
+            }),
+            [
+                MigrationState::Pending,
+                MigrationState::Outdated,
+                MigrationState::OutOfOrder
+            ]
+        );
+        assert_eq!(
+            states(InfoFilter {
+                applied: true,
+                failed: true,
+                ..Default::default()
+            }),
+            [
+                MigrationState::Baseline,
+                MigrationState::Applied,
+                MigrationState::Failed
+            ]
+        );
+        assert_eq!(
+            states(InfoFilter {
+                pending: true,
+                since: Some(MigrationVersion::parse("2").unwrap()),
+                ..Default::default()
+            }),
+            [MigrationState::Pending, MigrationState::Outdated]
+        );
+    }
+}
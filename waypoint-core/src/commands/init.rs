@@ -0,0 +1,218 @@
+//! Scaffold a project (`waypoint init`).
+//!
+//! Writes a starter `waypoint.toml` and an empty `db/migrations/` directory.
+//! With `--offline-kit` it writes a full starter kit instead: the config,
+//! example migrations, a git pre-commit hook, CI pipeline templates and a
+//! README. Every file is embedded in the binary, so the kit can be produced
+//! on machines without network access.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::Result;
+
+/// Directory migrations are scaffolded into, relative to the project root.
+const MIGRATIONS_DIR: &str = "db/migrations";
+
+/// Files written by a plain `init`.
+const PROJECT_FILES: &[(&str, &str)] = &[(
+    "waypoint.toml",
+    include_str!("../../templates/init/waypoint.toml"),
+)];
+
+/// Extra files written by `init --offline-kit`.
+const KIT_FILES: &[(&str, &str)] = &[
+    ("README.md", include_str!("../../templates/init/README.md")),
+    (
+        "db/migrations/V1__Create_example.sql",
+        include_str!("../../templates/init/migrations/V1__Create_example.sql"),
+    ),
+    (
+        "db/migrations/U1__Create_example.sql",
+        include_str!("../../templates/init/migrations/U1__Create_example.sql"),
+    ),
+    (
+        "db/migrations/V2__Add_example_name_index.sql",
+        include_str!("../../templates/init/migrations/V2__Add_example_name_index.sql"),
+    ),
+    (
+        "db/migrations/R__Example_view.sql",
+        include_str!("../../templates/init/migrations/R__Example_view.sql"),
+    ),
+    (
+        "hooks/pre-commit",
+        include_str!("../../templates/init/hooks/pre-commit"),
+    ),
+    (
+        "ci/github-actions.yml",
+        include_str!("../../templates/init/ci/github-actions.yml"),
+    ),
+    (
+        "ci/gitlab-ci.yml",
+        include_str!("../../templates/init/ci/gitlab-ci.yml"),
+    ),
+];
+
+/// Options for `waypoint init`.
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    /// Project root to write into.
+    pub dir: PathBuf,
+    /// Write the full starter kit rather than just the config.
+    pub offline_kit: bool,
+    /// Overwrite files that already exist instead of skipping them.
+    pub force: bool,
+    /// Report the files without writing them.
+    pub dry_run: bool,
+}
+
+/// Result of `waypoint init`.
+#[derive(Debug, Clone, Serialize)]
+pub struct InitReport {
+    /// Project root.
+    pub dir: String,
+    /// Files written (or, for a dry run, that would be written).
+    pub files: Vec<String>,
+    /// Files left alone because they already exist.
+    pub skipped: Vec<String>,
+    /// Whether the files were only reported, not written.
+    pub dry_run: bool,
+}
+
+/// Execute the init command.
+pub fn execute(options: &InitOptions) -> Result<InitReport> {
+    let kit: &[(&str, &str)] = if options.offline_kit { KIT_FILES } else { &[] };
+    let templates = PROJECT_FILES.iter().chain(kit);
+
+    let mut report = InitReport {
+        dir: options.dir.display().to_string(),
+        files: Vec::new(),
+        skipped: Vec::new(),
+        dry_run: options.dry_run,
+    };
+    for (name, contents) in templates {
+        let path = options.dir.join(name);
+        if path.exists() && !options.force {
+            report.skipped.push(path.display().to_string());
+            continue;
+        }
+        if !options.dry_run {
+            write_file(&path, contents)?;
+        }
+        report.files.push(path.display().to_string());
+    }
+    if !options.dry_run {
+        std::fs::create_dir_all(options.dir.join(MIGRATIONS_DIR))?;
+    }
+    Ok(report)
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)?;
+    // Git only runs hooks that are executable, and `WaypointConfig::load`
+    // warns about a config file others can read.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if path.parent().and_then(Path::file_name) == Some("hooks".as_ref()) {
+            Some(0o755)
+        } else if path.file_name() == Some("waypoint.toml".as_ref()) {
+            Some(0o600)
+        } else {
+            None
+        };
+        if let Some(mode) = mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::scan_migrations;
+
+    fn options(dir: &Path, offline_kit: bool) -> InitOptions {
+        InitOptions {
+            dir: dir.to_path_buf(),
+            offline_kit,
+            force: false,
+            dry_run: false,
+        }
+    }
+
+    #[test]
+    fn test_init_writes_config_and_migrations_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = execute(&options(dir.path(), false)).unwrap();
+        assert_eq!(report.files.len(), 1);
+        assert!(dir.path().join("waypoint.toml").is_file());
+        assert!(dir.path().join(MIGRATIONS_DIR).is_dir());
+        assert!(!dir.path().join("hooks").exists());
+
+        // The generated config loads and points at the generated directory.
+        let config = crate::config::WaypointConfig::load(
+            Some(dir.path().join("waypoint.toml").to_str().unwrap()),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            config.migrations.locations,
+            vec![PathBuf::from(MIGRATIONS_DIR)]
+        );
+    }
+
+    #[test]
+    fn test_offline_kit_skips_existing_files_unless_forced() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("waypoint.toml"), "# mine\n").unwrap();
+
+        let report = execute(&options(dir.path(), true)).unwrap();
+        assert_eq!(report.files.len(), KIT_FILES.len());
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("waypoint.toml")).unwrap(),
+            "# mine\n"
+        );
+        let migrations = scan_migrations(&[dir.path().join(MIGRATIONS_DIR)]).unwrap();
+        assert_eq!(migrations.len(), 4);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.path().join("hooks/pre-commit"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+
+        let forced = execute(&InitOptions {
+            force: true,
+            ..options(dir.path(), true)
+        })
+        .unwrap();
+        assert!(forced.skipped.is_empty());
+        assert_ne!(
+            std::fs::read_to_string(dir.path().join("waypoint.toml")).unwrap(),
+            "# mine\n"
+        );
+    }
+
+    #[test]
+    fn test_dry_run_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let kit = dir.path().join("kit");
+        let report = execute(&InitOptions {
+            dry_run: true,
+            ..options(&kit, true)
+        })
+        .unwrap();
+        assert_eq!(report.files.len(), PROJECT_FILES.len() + KIT_FILES.len());
+        assert!(!kit.exists());
+    }
+}
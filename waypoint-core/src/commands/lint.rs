@@ -7,8 +7,10 @@ use std::path::PathBuf;
 
 use serde::Serialize;
 
+use crate::config::{LintConfig, MigrationSettings};
+use crate::directive::{directive_lines, KNOWN_DIRECTIVES};
 use crate::error::Result;
-use crate::migration::scan_migrations;
+use crate::migration::{scan_migrations_with_limits, ResolvedMigration, ScanLimits, VersionScheme};
 use crate::sql_parser::{extract_ddl_operations, split_statements, DdlOperation};
 
 /// Severity level for a lint issue.
@@ -45,6 +47,8 @@ pub struct LintIssue {
     pub script: String,
     /// Approximate line number of the issue, if determinable.
     pub line: Option<usize>,
+    /// Column within `line` (1-based, in characters), where it is exact.
+    pub column: Option<usize>,
     /// Suggested fix or remediation for the issue.
     pub suggestion: Option<String>,
 }
@@ -64,24 +68,84 @@ pub struct LintReport {
     pub info_count: usize,
 }
 
-/// Execute the lint command.
-pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintReport> {
-    let migrations = scan_migrations(locations)?;
+/// Execute the lint command on the files in `locations`.
+///
+/// Files over `limits` fail the scan, as they would for `migrate`; `config`
+/// supplies the disabled rules, allowed environments and W009 thresholds.
+pub fn execute(
+    locations: &[PathBuf],
+    limits: &ScanLimits,
+    config: &LintConfig,
+) -> Result<LintReport> {
+    let migrations = scan_migrations_with_limits(locations, limits)?;
+    Ok(lint_migrations(&migrations, limits.version_scheme, config))
+}
+
+/// Lint every migration `settings` resolves: the files in its locations plus
+/// embedded files and registered code migrations. Code migrations can't be
+/// analyzed and are reported as `I002`.
+pub fn execute_settings(settings: &MigrationSettings, config: &LintConfig) -> Result<LintReport> {
+    let migrations = settings.resolve()?;
+    Ok(lint_migrations(
+        &migrations,
+        settings.version_scheme,
+        config,
+    ))
+}
+
+fn lint_migrations(
+    migrations: &[ResolvedMigration],
+    scheme: VersionScheme,
+    config: &LintConfig,
+) -> LintReport {
     let mut issues = Vec::new();
     let disabled: std::collections::HashSet<&str> =
-        disabled_rules.iter().map(|s| s.as_str()).collect();
+        config.disabled_rules.iter().map(|s| s.as_str()).collect();
+    let allowed_environments = &config.allowed_environments;
 
     let files_checked = migrations.len();
+    let versions: std::collections::HashSet<&str> = migrations
+        .iter()
+        .filter_map(|m| m.version().map(|v| v.raw.as_str()))
+        .collect();
+
+    if !disabled.contains("W010") {
+        lint_version_scheme(migrations, scheme, &mut issues);
+    }
 
-    for migration in &migrations {
+    for migration in migrations {
         // Skip undo migrations for linting
         if migration.is_undo() {
             continue;
         }
 
+        // I002: Rust code migration, nothing to lint
+        if migration.is_code() {
+            if !disabled.contains("I002") {
+                issues.push(LintIssue {
+                    rule_id: "I002".to_string(),
+                    severity: LintSeverity::Info,
+                    message: "Rust code migration; its statements can't be analyzed".to_string(),
+                    script: migration.script.clone(),
+                    line: None,
+                    column: None,
+                    suggestion: Some("Review up() by hand".to_string()),
+                });
+            }
+            continue;
+        }
+
         let sql = &migration.sql;
         let script = &migration.script;
 
+        lint_directives(
+            migration,
+            &versions,
+            allowed_environments,
+            &disabled,
+            &mut issues,
+        );
+
         // I001: File contains only comments or whitespace
         if !disabled.contains("I001") {
             let meaningful = sql.lines().any(|l| {
@@ -95,6 +159,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                     message: "File contains only comments or whitespace".to_string(),
                     script: script.clone(),
                     line: None,
+                    column: None,
                     suggestion: None,
                 });
                 continue;
@@ -120,6 +185,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         message: format!("CREATE TABLE {} without IF NOT EXISTS", table),
                         script: script.clone(),
                         line: find_line(sql, &upper, "CREATE TABLE"),
+                        column: None,
                         suggestion: Some(
                             "Use CREATE TABLE IF NOT EXISTS to make migration re-runnable"
                                 .to_string(),
@@ -142,6 +208,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         ),
                         script: script.clone(),
                         line: find_line(sql, &upper, "CREATE INDEX"),
+                        column: None,
                         suggestion: Some(
                             "Use CREATE INDEX CONCURRENTLY to avoid blocking writes".to_string(),
                         ),
@@ -165,6 +232,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         ),
                         script: script.clone(),
                         line: find_line(sql, &upper, "ADD"),
+                        column: None,
                         suggestion: Some("Add a DEFAULT value or make the column nullable".to_string()),
                     });
                 }
@@ -185,6 +253,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         ),
                         script: script.clone(),
                         line: find_line(sql, &upper, "ALTER COLUMN"),
+                        column: None,
                         suggestion: Some(
                             "Consider a multi-step approach: add new column, backfill, swap"
                                 .to_string(),
@@ -200,6 +269,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         message: format!("DROP TABLE {} is destructive and irreversible", table),
                         script: script.clone(),
                         line: find_line(sql, &upper, "DROP TABLE"),
+                        column: None,
                         suggestion: Some("Ensure you have a backup or undo migration".to_string()),
                     });
                 }
@@ -215,6 +285,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         ),
                         script: script.clone(),
                         line: find_line(sql, &upper, "DROP COLUMN"),
+                        column: None,
                         suggestion: Some("Ensure you have a backup or undo migration".to_string()),
                     });
                 }
@@ -244,6 +315,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         ),
                         script: script.clone(),
                         line: find_line(sql, &upper, "DEFAULT"),
+                        column: None,
                         suggestion: Some("On PostgreSQL < 11, volatile defaults cause a full table rewrite".to_string()),
                     });
                 }
@@ -259,6 +331,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                         ),
                         script: script.clone(),
                         line: find_line(sql, &upper, "TRUNCATE"),
+                        column: None,
                         suggestion: Some(
                             "Ensure this is intentional and the table can be locked exclusively"
                                 .to_string(),
@@ -270,8 +343,76 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
             }
         }
 
-        // E002: Multiple DDL statements without explicit transaction control
-        if !disabled.contains("E002") {
+        // W008: FOREIGN KEY added to an existing table without NOT VALID.
+        // Tables created in the same migration are empty, so the check is free.
+        if !disabled.contains("W008") {
+            let bare = |t: &str| {
+                t.rsplit('.')
+                    .next()
+                    .unwrap_or(t)
+                    .trim_matches('"')
+                    .to_lowercase()
+            };
+            let created: Vec<String> = ops
+                .iter()
+                .filter_map(|op| match op {
+                    DdlOperation::CreateTable { table, .. } => Some(bare(table)),
+                    _ => None,
+                })
+                .collect();
+            for fk in statements
+                .iter()
+                .filter_map(|s| crate::commands::assist::parse_foreign_key(s))
+                .filter(|fk| !fk.not_valid && !created.contains(&bare(&fk.table)))
+            {
+                issues.push(LintIssue {
+                    rule_id: "W008".to_string(),
+                    severity: LintSeverity::Warning,
+                    message: format!(
+                        "FOREIGN KEY {} on {} without NOT VALID (scans the table while blocking writes)",
+                        fk.name, fk.table
+                    ),
+                    script: script.clone(),
+                    line: find_line(sql, &upper, "FOREIGN KEY"),
+                    column: None,
+                    suggestion: Some(
+                        "Add it NOT VALID and run VALIDATE CONSTRAINT in a later migration; `waypoint assist fk` generates both"
+                            .to_string(),
+                    ),
+                });
+            }
+        }
+
+        // W009: Unusually large migration (a schema dump committed by mistake?)
+        if !disabled.contains("W009") {
+            let bytes = sql.len() as u64;
+            let too_big = config.warn_migration_bytes > 0 && bytes > config.warn_migration_bytes;
+            let too_many = config.warn_statements > 0 && statements.len() > config.warn_statements;
+            if too_big || too_many {
+                issues.push(LintIssue {
+                    rule_id: "W009".to_string(),
+                    severity: LintSeverity::Warning,
+                    message: format!(
+                        "Large migration: {} bytes, {} statements (warning thresholds: {} bytes, {} statements)",
+                        bytes,
+                        statements.len(),
+                        config.warn_migration_bytes,
+                        config.warn_statements
+                    ),
+                    script: script.clone(),
+                    line: None,
+                    column: None,
+                    suggestion: Some(
+                        "Check this isn't a schema or data dump; split it up, or raise [lint] warn_migration_bytes / warn_statements"
+                            .to_string(),
+                    ),
+                });
+            }
+        }
+
+        // E002: Multiple DDL statements without explicit transaction control.
+        // A no-transaction migration can't have one, by design.
+        if !disabled.contains("E002") && !migration.directives.no_transaction {
             let ddl_count = ops
                 .iter()
                 .filter(|op| !matches!(op, DdlOperation::Other { .. }))
@@ -292,6 +433,7 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
                     ),
                     script: script.clone(),
                     line: None,
+                    column: None,
                     suggestion: Some("Consider adding explicit BEGIN/COMMIT for clarity, or split into separate migrations".to_string()),
                 });
             }
@@ -311,13 +453,307 @@ pub fn execute(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintR
         .filter(|i| i.severity == LintSeverity::Info)
         .count();
 
-    Ok(LintReport {
+    LintReport {
         issues,
         files_checked,
         error_count,
         warning_count,
         info_count,
-    })
+    }
+}
+
+/// W010: versions whose order depends on how they are read, so the
+/// configured [`VersionScheme`] may not be the one the names were written for.
+///
+/// - distinct versions that compare equal (`1.2` / `1.2.0`, `06` / `6`)
+/// - `numeric`: a segment zero-padded in one version but of another width in
+///   another (`2024.06.12` / `2024.6.13`), since the padding is ignored
+/// - `lexicographic`: neighbours that sort the other way as numbers
+///   (`9` / `10`), i.e. versions that aren't fixed-width
+fn lint_version_scheme(
+    migrations: &[ResolvedMigration],
+    scheme: VersionScheme,
+    issues: &mut Vec<LintIssue>,
+) {
+    let mut versioned: Vec<&ResolvedMigration> =
+        migrations.iter().filter(|m| m.is_versioned()).collect();
+    versioned.sort_by(|a, b| match (a.version(), b.version()) {
+        (Some(x), Some(y)) => scheme.compare(x, y),
+        _ => std::cmp::Ordering::Equal,
+    });
+    let mut issue = |m: &ResolvedMigration, message: String, suggestion: &str| {
+        issues.push(LintIssue {
+            rule_id: "W010".to_string(),
+            severity: LintSeverity::Warning,
+            message,
+            script: m.script.clone(),
+            line: None,
+            column: None,
+            suggestion: Some(suggestion.to_string()),
+        });
+    };
+
+    for pair in versioned.windows(2) {
+        let (Some(a), Some(b)) = (pair[0].version(), pair[1].version()) else {
+            continue;
+        };
+        if scheme.compare(a, b) == std::cmp::Ordering::Equal {
+            issue(
+                pair[1],
+                format!(
+                    "V{} and V{} are the same version under the {} version scheme",
+                    a.raw, b.raw, scheme
+                ),
+                "Rename one of them so every version is distinct",
+            );
+        } else if scheme == VersionScheme::Lexicographic
+            && VersionScheme::Numeric.compare(a, b) == std::cmp::Ordering::Greater
+        {
+            issue(
+                pair[1],
+                format!(
+                    "V{} sorts after V{} as text but before it as a number",
+                    b.raw, a.raw
+                ),
+                "The lexicographic scheme needs fixed-width versions; zero-pad them, or use version_scheme = \"numeric\"",
+            );
+        }
+    }
+
+    if scheme == VersionScheme::Numeric {
+        // Width of each zero-padded segment position, from its first padded use.
+        let mut padded: Vec<Option<(usize, &str)>> = Vec::new();
+        for m in &versioned {
+            let raw = m.version().map_or("", |v| v.raw.as_str());
+            for (i, seg) in raw.split(['.', '_']).enumerate() {
+                if seg.len() > 1 && seg.starts_with('0') {
+                    if padded.len() <= i {
+                        padded.resize(i + 1, None);
+                    }
+                    padded[i].get_or_insert((seg.len(), raw));
+                }
+            }
+        }
+        for m in &versioned {
+            let raw = m.version().map_or("", |v| v.raw.as_str());
+            let mismatch = raw.split(['.', '_']).enumerate().find_map(|(i, seg)| {
+                padded
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .filter(|(width, _)| seg.len() != *width)
+                    .map(|(width, example)| (i, seg.len(), width, example))
+            });
+            if let Some((i, len, width, example)) = mismatch {
+                issue(
+                    m,
+                    format!(
+                        "Segment {} of V{} has {} digit(s), but V{} zero-pads it to {}; the numeric version scheme ignores the padding",
+                        i + 1,
+                        raw,
+                        len,
+                        example,
+                        width
+                    ),
+                    "Pad versions consistently, or set [migrations] version_scheme to match the naming scheme",
+                );
+            }
+        }
+    }
+}
+
+/// Check the file's `-- waypoint:*` header directives, which are otherwise
+/// only interpreted (or silently ignored) at migrate time.
+///
+/// - E003: unknown directive name
+/// - E004: malformed `require` / `ensure` guard expression
+/// - E005: `env` name outside `allowed_environments`
+/// - E006: `depends` on a version that doesn't exist (or on itself)
+/// - E007: missing or invalid directive value
+fn lint_directives(
+    migration: &ResolvedMigration,
+    versions: &std::collections::HashSet<&str>,
+    allowed_environments: &[String],
+    disabled: &std::collections::HashSet<&str>,
+    issues: &mut Vec<LintIssue>,
+) {
+    let script = &migration.script;
+    let mut issue =
+        |rule_id: &str, line: usize, column: usize, message: String, suggestion: Option<String>| {
+            if !disabled.contains(rule_id) {
+                issues.push(LintIssue {
+                    rule_id: rule_id.to_string(),
+                    severity: LintSeverity::Error,
+                    message,
+                    script: script.clone(),
+                    line: Some(line),
+                    column: Some(column),
+                    suggestion,
+                });
+            }
+        };
+
+    for d in directive_lines(&migration.sql) {
+        let name = d.name.as_str();
+        if !KNOWN_DIRECTIVES.contains(&name) {
+            issue(
+                "E003",
+                d.line,
+                d.column,
+                format!("Unknown directive 'waypoint:{}' is ignored", name),
+                Some(format!("Known directives: {}", KNOWN_DIRECTIVES.join(", "))),
+            );
+            continue;
+        }
+
+        match name {
+            "safety-override" | "no-transaction" | "low-priority" => {
+                if !d.value.is_empty() {
+                    issue(
+                        "E007",
+                        d.line,
+                        d.value_column,
+                        format!("'waypoint:{}' takes no value, so the line is ignored", name),
+                        Some(format!("Write it as '-- waypoint:{}'", name)),
+                    );
+                }
+                continue;
+            }
+            _ if d.value.is_empty() => {
+                issue(
+                    "E007",
+                    d.line,
+                    d.value_column,
+                    format!("'waypoint:{}' has no value", name),
+                    None,
+                );
+                continue;
+            }
+            "set" => {
+                if let Err(e) = crate::directive::parse_settings(&d.value) {
+                    issue(
+                        "E007",
+                        d.line,
+                        d.value_column,
+                        format!("{} (the whole line is ignored)", config_reason(&e)),
+                        Some("Write it as '-- waypoint:set name=value, name=value'".to_string()),
+                    );
+                }
+            }
+            // Placeholders are only replaced at migrate time.
+            "copy" if !d.value.contains("${") => {
+                if let Err(e) = crate::directive::CopyDirective::parse(&d.value) {
+                    issue(
+                        "E007",
+                        d.line,
+                        d.value_column,
+                        format!("{} (migrate fails on it)", config_reason(&e)),
+                        Some("Write it as '-- waypoint:copy table=name file=data.csv'".to_string()),
+                    );
+                }
+            }
+            "order" if d.value.parse::<u32>().is_err() => {
+                issue(
+                    "E007",
+                    d.line,
+                    d.value_column,
+                    format!("Invalid order '{}' (ignored)", d.value),
+                    Some("Use a non-negative whole number".to_string()),
+                );
+            }
+            "require" | "ensure" => {
+                let guard = crate::directive::GuardDirective::parse(&d.value);
+                if let Err(e) = crate::guard::parse(&guard.expression)
+                    .and_then(|expr| crate::guard::check(&expr))
+                {
+                    issue(
+                        "E004",
+                        d.line,
+                        d.value_column,
+                        format!(
+                            "Invalid 'waypoint:{}' expression: {}",
+                            name,
+                            config_reason(&e)
+                        ),
+                        Some("Try it with 'waypoint guard eval'".to_string()),
+                    );
+                }
+            }
+            "env" | "depends" => {
+                for (item, column) in list_items(&d.value, d.value_column) {
+                    if name == "env" {
+                        if !allowed_environments.is_empty()
+                            && !allowed_environments
+                                .iter()
+                                .any(|e| e.eq_ignore_ascii_case(item))
+                        {
+                            issue(
+                                "E005",
+                                d.line,
+                                column,
+                                format!(
+                                    "Environment '{}' is not in [lint] allowed_environments, so this migration may never run",
+                                    item
+                                ),
+                                Some(format!("Allowed: {}", allowed_environments.join(", "))),
+                            );
+                        }
+                        continue;
+                    }
+
+                    let version = item.strip_prefix('V').unwrap_or(item);
+                    let own = migration.version().map(|v| v.raw.as_str());
+                    if own == Some(version) {
+                        issue(
+                            "E006",
+                            d.line,
+                            column,
+                            format!("Migration depends on itself ({})", item),
+                            None,
+                        );
+                    } else if !versions.contains(version) {
+                        issue(
+                            "E006",
+                            d.line,
+                            column,
+                            format!("Dependency {} does not match any versioned migration", item),
+                            Some(
+                                "Check the version number against the migration file names"
+                                    .to_string(),
+                            ),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The message inside a `ConfigError`, without its "Configuration error:" and
+/// "Guard expression:" prefixes.
+fn config_reason(e: &crate::error::WaypointError) -> String {
+    let text = e.to_string();
+    let text = text.strip_prefix("Configuration error: ").unwrap_or(&text);
+    text.strip_prefix("Guard expression: ")
+        .unwrap_or(text)
+        .to_string()
+}
+
+/// Split a comma-separated directive value into its non-empty items, each
+/// with its 1-based column (the value itself starts at `start_column`).
+fn list_items(value: &str, start_column: usize) -> Vec<(&str, usize)> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    for part in value.split(',') {
+        let item = part.trim();
+        if !item.is_empty() {
+            let lead = part.len() - part.trim_start().len();
+            items.push((item, start_column + value[..offset + lead].chars().count()));
+        }
+        offset += part.len() + 1;
+    }
+    items
 }
 
 /// Find the approximate line number of a pattern in SQL content.
@@ -339,6 +775,10 @@ mod tests {
         fs::write(dir.join(name), sql).unwrap();
     }
 
+    fn lint(dir: &TempDir, config: &LintConfig) -> LintReport {
+        execute(&[dir.path().to_path_buf()], &ScanLimits::default(), config).unwrap()
+    }
+
     #[test]
     fn test_lint_create_table_without_if_not_exists() {
         let dir = TempDir::new().unwrap();
@@ -348,7 +788,7 @@ mod tests {
             "CREATE TABLE users (id SERIAL PRIMARY KEY);",
         );
 
-        let report = execute(&[dir.path().to_path_buf()], &[]).unwrap();
+        let report = lint(&dir, &LintConfig::default());
         assert!(report.issues.iter().any(|i| i.rule_id == "W001"));
     }
 
@@ -361,7 +801,7 @@ mod tests {
             "CREATE TABLE IF NOT EXISTS users (id SERIAL PRIMARY KEY);",
         );
 
-        let report = execute(&[dir.path().to_path_buf()], &[]).unwrap();
+        let report = lint(&dir, &LintConfig::default());
         assert!(!report.issues.iter().any(|i| i.rule_id == "W001"));
     }
 
@@ -374,7 +814,7 @@ mod tests {
             "ALTER TABLE users ADD COLUMN email VARCHAR(255) NOT NULL;",
         );
 
-        let report = execute(&[dir.path().to_path_buf()], &[]).unwrap();
+        let report = lint(&dir, &LintConfig::default());
         assert!(report.issues.iter().any(|i| i.rule_id == "E001"));
         assert!(report.error_count > 0);
     }
@@ -388,7 +828,7 @@ mod tests {
             "CREATE INDEX idx_users_email ON users (email);",
         );
 
-        let report = execute(&[dir.path().to_path_buf()], &[]).unwrap();
+        let report = lint(&dir, &LintConfig::default());
         assert!(report.issues.iter().any(|i| i.rule_id == "W002"));
     }
 
@@ -401,7 +841,11 @@ mod tests {
             "CREATE TABLE users (id SERIAL PRIMARY KEY);",
         );
 
-        let report = execute(&[dir.path().to_path_buf()], &["W001".to_string()]).unwrap();
+        let config = LintConfig {
+            disabled_rules: vec!["W001".to_string()],
+            ..Default::default()
+        };
+        let report = lint(&dir, &config);
         assert!(!report.issues.iter().any(|i| i.rule_id == "W001"));
     }
 
@@ -410,7 +854,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         setup_migration(dir.path(), "V1__Drop_old.sql", "DROP TABLE old_table;");
 
-        let report = execute(&[dir.path().to_path_buf()], &[]).unwrap();
+        let report = lint(&dir, &LintConfig::default());
         assert!(report.issues.iter().any(|i| i.rule_id == "W004"));
     }
 
@@ -419,7 +863,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         setup_migration(dir.path(), "V1__Empty.sql", "-- Just a comment\n");
 
-        let report = execute(&[dir.path().to_path_buf()], &[]).unwrap();
+        let report = lint(&dir, &LintConfig::default());
         assert!(report.issues.iter().any(|i| i.rule_id == "I001"));
     }
 
@@ -428,7 +872,222 @@ mod tests {
         let dir = TempDir::new().unwrap();
         setup_migration(dir.path(), "V1__Truncate.sql", "TRUNCATE TABLE users;");
 
-        let report = execute(&[dir.path().to_path_buf()], &[]).unwrap();
+        let report = lint(&dir, &LintConfig::default());
         assert!(report.issues.iter().any(|i| i.rule_id == "W007"));
     }
+
+    #[test]
+    fn test_lint_directives() {
+        let dir = TempDir::new().unwrap();
+        setup_migration(dir.path(), "V1__Base.sql", "SELECT 1;");
+        setup_migration(
+            dir.path(),
+            "V2__Directives.sql",
+            "-- waypoint:depends V1, V9\n\
+             -- waypoint:env dev,prdo\n\
+             -- waypoint:require tabel_exists(\"users\")\n\
+             -- waypoint:ensure column_exists(\"users\"\n\
+             -- waypoint:isolaton serializable\n\
+             -- waypoint:no-transaction please\n\
+             -- waypoint:set work_mem\n\
+             -- waypoint:copy table=users\n\
+             -- waypoint:require table_exists(\"users\") :: \"users must exist\"\n\
+             SELECT 1;\n",
+        );
+
+        let config = LintConfig {
+            allowed_environments: vec!["dev".to_string(), "prod".to_string()],
+            ..Default::default()
+        };
+        let report = lint(&dir, &config);
+        let found = |rule: &str| -> Vec<(Option<usize>, Option<usize>)> {
+            report
+                .issues
+                .iter()
+                .filter(|i| i.rule_id == rule)
+                .map(|i| (i.line, i.column))
+                .collect()
+        };
+
+        assert_eq!(found("E006"), vec![(Some(1), Some(25))]);
+        assert_eq!(found("E005"), vec![(Some(2), Some(21))]);
+        assert_eq!(
+            found("E004"),
+            vec![(Some(3), Some(21)), (Some(4), Some(20))]
+        );
+        assert_eq!(found("E003"), vec![(Some(5), Some(4))]);
+        assert_eq!(
+            found("E007"),
+            vec![
+                (Some(6), Some(28)),
+                (Some(7), Some(17)),
+                (Some(8), Some(18))
+            ]
+        );
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| i.message.contains("unknown function 'tabel_exists'")));
+        assert!(report.issues.iter().all(|i| i.script != "V1__Base.sql"));
+
+        // Any env name passes without an allowed list; rules can be disabled.
+        let config = LintConfig {
+            disabled_rules: vec!["E003".to_string()],
+            ..Default::default()
+        };
+        let report = lint(&dir, &config);
+        assert!(!report
+            .issues
+            .iter()
+            .any(|i| i.rule_id == "E005" || i.rule_id == "E003"));
+    }
+
+    #[test]
+    fn test_lint_fails_on_invalid_isolation_or_retries() {
+        let dir = TempDir::new().unwrap();
+        setup_migration(
+            dir.path(),
+            "V1__Retry.sql",
+            "-- waypoint:retries three\nSELECT 1;\n",
+        );
+        let err = execute(
+            &[dir.path().to_path_buf()],
+            &ScanLimits::default(),
+            &LintConfig::default(),
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("V1__Retry.sql: Invalid retries 'three'"));
+    }
+
+    #[test]
+    fn test_lint_depends_on_itself() {
+        let dir = TempDir::new().unwrap();
+        setup_migration(
+            dir.path(),
+            "V1__Self.sql",
+            "-- waypoint:depends 1\nSELECT 1;",
+        );
+        let report = lint(&dir, &LintConfig::default());
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| i.rule_id == "E006" && i.message.contains("itself")));
+    }
+
+    #[test]
+    fn test_lint_large_migration() {
+        let dir = TempDir::new().unwrap();
+        setup_migration(dir.path(), "V1__Small.sql", "SELECT 1;\nSELECT 2;");
+        setup_migration(dir.path(), "V2__Big.sql", &"SELECT 1;\n".repeat(6));
+
+        let config = LintConfig {
+            warn_statements: 5,
+            ..Default::default()
+        };
+        let report = lint(&dir, &config);
+        let w009: Vec<_> = report
+            .issues
+            .iter()
+            .filter(|i| i.rule_id == "W009")
+            .collect();
+        assert_eq!(w009.len(), 1);
+        assert_eq!(w009[0].script, "V2__Big.sql");
+
+        let config = LintConfig {
+            warn_migration_bytes: 20,
+            warn_statements: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            lint(&dir, &config)
+                .issues
+                .iter()
+                .filter(|i| i.rule_id == "W009")
+                .count(),
+            1
+        );
+
+        // The hard limit fails the scan instead.
+        let limits = ScanLimits {
+            max_bytes: 0,
+            max_statements: 5,
+            ..ScanLimits::default()
+        };
+        let err = execute(&[dir.path().to_path_buf()], &limits, &config).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::error::WaypointError::MigrationTooLarge { ref script, .. } if script == "V2__Big.sql"
+        ));
+    }
+
+    #[test]
+    fn test_lint_foreign_key_without_not_valid() {
+        let dir = TempDir::new().unwrap();
+        setup_migration(
+            dir.path(),
+            "V1__Fk.sql",
+            "ALTER TABLE orders ADD CONSTRAINT orders_user_fk FOREIGN KEY (user_id) REFERENCES users (id);",
+        );
+        setup_migration(
+            dir.path(),
+            "V2__Fk_not_valid.sql",
+            "ALTER TABLE items ADD FOREIGN KEY (order_id) REFERENCES orders (id) NOT VALID;",
+        );
+        setup_migration(
+            dir.path(),
+            "V3__New_table.sql",
+            "CREATE TABLE IF NOT EXISTS notes (id int, user_id int);\nALTER TABLE notes ADD FOREIGN KEY (user_id) REFERENCES users (id);",
+        );
+
+        let report = lint(&dir, &LintConfig::default());
+        let w008: Vec<_> = report
+            .issues
+            .iter()
+            .filter(|i| i.rule_id == "W008")
+            .collect();
+        assert_eq!(w008.len(), 1);
+        assert_eq!(w008[0].script, "V1__Fk.sql");
+    }
+
+    #[test]
+    fn test_lint_ambiguous_version_scheme() {
+        let found = |names: &[&str], scheme| {
+            let dir = TempDir::new().unwrap();
+            for name in names {
+                setup_migration(dir.path(), name, "SELECT 1;");
+            }
+            let migrations =
+                scan_migrations_with_limits(&[dir.path().to_path_buf()], &ScanLimits::default())
+                    .unwrap();
+            let mut issues = Vec::new();
+            lint_version_scheme(&migrations, scheme, &mut issues);
+            issues
+        };
+        let scripts = |issues: Vec<LintIssue>| -> Vec<String> {
+            issues.into_iter().map(|i| i.script).collect()
+        };
+
+        let dated = [
+            "V2024.06.12.1__a.sql",
+            "V2024.6.13__b.sql",
+            "V2024.06.14__c.sql",
+        ];
+        assert_eq!(
+            scripts(found(&dated, VersionScheme::Numeric)),
+            vec!["V2024.6.13__b.sql"]
+        );
+
+        let same = found(&["V1.2__a.sql", "V1.2.0__b.sql"], VersionScheme::Numeric);
+        assert_eq!(same.len(), 1);
+        assert!(same[0].message.contains("same version"));
+
+        let unpadded = ["V9__a.sql", "V10__b.sql", "V11__c.sql"];
+        assert!(found(&unpadded, VersionScheme::Numeric).is_empty());
+        assert_eq!(
+            scripts(found(&unpadded, VersionScheme::Lexicographic)),
+            vec!["V9__a.sql"]
+        );
+    }
 }
@@ -0,0 +1,99 @@
+//! `waypoint listen`: print migrate notifications as they arrive.
+//!
+//! Opens a dedicated connection, runs `LISTEN` on the `[notify]` channel (or
+//! the one given), and hands each notification to a callback. Meant for
+//! checking that `[notify]` reaches the fleet. PostgreSQL only.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+#[cfg(feature = "postgres")]
+use crate::dialect::DialectKind;
+use crate::error::{Result, WaypointError};
+use crate::notify::MigrationNotification;
+
+/// A notification received on the channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenEvent {
+    /// Channel it arrived on.
+    pub channel: String,
+    /// Backend PID of the session that sent it.
+    pub process_id: i32,
+    /// Raw payload.
+    pub payload: String,
+    /// The payload parsed as a migrate notification, when it is one.
+    pub notification: Option<MigrationNotification>,
+    /// When it was received.
+    pub received_at: DateTime<Utc>,
+}
+
+/// Listen on `channel` (default: `[notify] channel`), calling `on_event` for
+/// each notification. Stops after `count` notifications or `timeout_secs`
+/// seconds, whichever comes first; without either, runs until the
+/// connection closes. Returns how many notifications arrived.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    channel: Option<&str>,
+    count: Option<usize>,
+    timeout_secs: Option<u64>,
+    on_event: impl FnMut(&ListenEvent),
+) -> Result<usize> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => listen(config, channel, count, timeout_secs, on_event).await,
+        kind => Err(WaypointError::ConfigError(format!(
+            "listen is not supported on {:?} (PostgreSQL only)",
+            kind
+        ))),
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn listen(
+    config: &WaypointConfig,
+    channel: Option<&str>,
+    count: Option<usize>,
+    timeout_secs: Option<u64>,
+    mut on_event: impl FnMut(&ListenEvent),
+) -> Result<usize> {
+    let channel = channel.unwrap_or(&config.notify.channel);
+    let conn_string = config.connection_string()?;
+    let (client, mut notifications) =
+        crate::db::connect_listener(&conn_string, &config.database).await?;
+    client
+        .batch_execute(&format!("LISTEN {}", crate::db::quote_ident(channel)))
+        .await?;
+    log::info!("Listening for notifications; channel={}", channel);
+
+    let deadline =
+        timeout_secs.map(|secs| tokio::time::Instant::now() + std::time::Duration::from_secs(secs));
+    let mut received = 0;
+    while count.is_none_or(|n| received < n) {
+        let next = match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, notifications.recv()).await {
+                Ok(next) => next,
+                Err(_) => break,
+            },
+            None => notifications.recv().await,
+        };
+        let Some(n) = next else {
+            return Err(WaypointError::ConnectionLost {
+                operation: "listen".to_string(),
+                detail: "connection closed".to_string(),
+            });
+        };
+        received += 1;
+        on_event(&ListenEvent {
+            channel: n.channel().to_string(),
+            process_id: n.process_id(),
+            payload: n.payload().to_string(),
+            notification: serde_json::from_str(n.payload()).ok(),
+            received_at: Utc::now(),
+        });
+    }
+    Ok(received)
+}
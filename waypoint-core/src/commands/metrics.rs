@@ -0,0 +1,306 @@
+//! Usage report built from the schema history table.
+//!
+//! Summarizes migration cadence, durations, failure rate and undo frequency
+//! over a time window, for engineering health reviews. Everything is read
+//! from the history table; nothing leaves the database.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::error::{Result, WaypointError};
+use crate::history::{self, AppliedMigration};
+
+/// Default number of longest migrations listed in a summary.
+pub const DEFAULT_TOP: usize = 5;
+
+/// One of the longest-running migrations in the window.
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowMigration {
+    /// Version string, or None for repeatable migrations.
+    pub version: Option<String>,
+    /// Filename of the migration script.
+    pub script: String,
+    /// Execution time in milliseconds.
+    pub execution_time_ms: i32,
+    /// When the migration was applied.
+    pub installed_on: DateTime<Utc>,
+}
+
+/// Migration usage summary for one database.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSummary {
+    /// Database name (multi-db mode), or None for a single database.
+    pub database: Option<String>,
+    /// Start of the window, or None when all history was considered.
+    pub since: Option<DateTime<Utc>>,
+    /// Forward and repeatable runs that succeeded.
+    pub applied: usize,
+    /// Runs recorded with `success = false`.
+    pub failed: usize,
+    /// Undo runs.
+    pub undone: usize,
+    /// `failed / (applied + failed + undone)`, 0.0 when there were no runs.
+    pub failure_rate: f64,
+    /// Successful applies per week over the window.
+    pub per_week: f64,
+    /// Mean execution time of successful applies, in milliseconds.
+    pub avg_duration_ms: f64,
+    /// Total execution time of successful applies, in milliseconds.
+    pub total_duration_ms: i64,
+    /// Most recent run in the window.
+    pub last_run: Option<DateTime<Utc>>,
+    /// Longest successful applies, slowest first.
+    pub longest: Vec<SlowMigration>,
+}
+
+/// Parse a relative window such as `30d`, `12h` or `2w`.
+pub fn parse_since(s: &str) -> Result<Duration> {
+    parse_relative("--since", s)
+}
+
+/// Parse a span such as `30d`, `12h` or `2w` given to `flag`.
+pub fn parse_relative(flag: &str, s: &str) -> Result<Duration> {
+    let span = crate::config::parse_duration_units(
+        flag,
+        s,
+        &[('h', 3600), ('d', 86_400), ('w', 604_800)],
+    )?;
+    match Duration::from_std(span) {
+        Ok(span) if !span.is_zero() => Ok(span),
+        _ => Err(WaypointError::ConfigError(format!(
+            "Invalid {} '{}': expected a positive span within range",
+            flag,
+            s.trim()
+        ))),
+    }
+}
+
+/// Summarize history rows installed at or after `since`.
+pub fn summarize(
+    rows: &[AppliedMigration],
+    since: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    top: usize,
+) -> MetricsSummary {
+    let in_window: Vec<&AppliedMigration> = rows
+        .iter()
+        .filter(|r| r.migration_type != "BASELINE")
+        .filter(|r| since.is_none_or(|s| r.installed_on >= s))
+        .collect();
+
+    let undone = in_window
+        .iter()
+        .filter(|r| r.migration_type == "UNDO_SQL" && r.success)
+        .count();
+    let failed = in_window.iter().filter(|r| !r.success).count();
+    let mut applies: Vec<&AppliedMigration> = in_window
+        .iter()
+        .copied()
+        .filter(|r| r.migration_type != "UNDO_SQL" && r.success)
+        .collect();
+    let applied = applies.len();
+
+    let runs = applied + failed + undone;
+    let failure_rate = if runs == 0 {
+        0.0
+    } else {
+        failed as f64 / runs as f64
+    };
+
+    let total_duration_ms: i64 = applies.iter().map(|r| r.execution_time as i64).sum();
+    let avg_duration_ms = if applied == 0 {
+        0.0
+    } else {
+        total_duration_ms as f64 / applied as f64
+    };
+
+    // Without --since, the window starts at the first recorded run.
+    let start = since.or_else(|| in_window.iter().map(|r| r.installed_on).min());
+    let per_week = match start {
+        Some(start) => {
+            let days = (now - start).num_days().max(1) as f64;
+            applied as f64 * 7.0 / days
+        }
+        None => 0.0,
+    };
+
+    applies.sort_by(|a, b| {
+        b.execution_time
+            .cmp(&a.execution_time)
+            .then(a.installed_rank.cmp(&b.installed_rank))
+    });
+    let longest = applies
+        .iter()
+        .take(top)
+        .map(|r| SlowMigration {
+            version: r.version.clone(),
+            script: r.script.clone(),
+            execution_time_ms: r.execution_time,
+            installed_on: r.installed_on,
+        })
+        .collect();
+
+    MetricsSummary {
+        database: None,
+        since,
+        applied,
+        failed,
+        undone,
+        failure_rate,
+        per_week,
+        avg_duration_ms,
+        total_duration_ms,
+        last_run: in_window.iter().map(|r| r.installed_on).max(),
+        longest,
+    }
+}
+
+/// Execute `metrics summary` against the configured history table.
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    since: Option<Duration>,
+    top: usize,
+) -> Result<MetricsSummary> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+    let rows = if history::history_table_exists_db(client, &schema, table).await? {
+        history::get_applied_migrations_db(client, &schema, table).await?
+    } else {
+        Vec::new()
+    };
+
+    let now = Utc::now();
+    Ok(summarize(&rows, since.map(|d| now - d), now, top))
+}
+
+/// Render summaries as a Markdown document.
+pub fn render_markdown(summaries: &[MetricsSummary]) -> String {
+    let mut out = String::from("# Migration metrics\n");
+    for s in summaries {
+        out.push('\n');
+        if let Some(ref name) = s.database {
+            out.push_str(&format!("## {}\n\n", name));
+        }
+        match s.since {
+            Some(since) => out.push_str(&format!("Since {}\n\n", since.format("%Y-%m-%d"))),
+            None => out.push_str("All history\n\n"),
+        }
+        out.push_str("| Metric | Value |\n|---|---|\n");
+        out.push_str(&format!("| Applied | {} |\n", s.applied));
+        out.push_str(&format!("| Per week | {:.1} |\n", s.per_week));
+        out.push_str(&format!("| Failed | {} |\n", s.failed));
+        out.push_str(&format!(
+            "| Failure rate | {:.1}% |\n",
+            s.failure_rate * 100.0
+        ));
+        out.push_str(&format!("| Undone | {} |\n", s.undone));
+        out.push_str(&format!("| Avg duration | {:.0}ms |\n", s.avg_duration_ms));
+        out.push_str(&format!("| Total duration | {}ms |\n", s.total_duration_ms));
+        if let Some(last) = s.last_run {
+            out.push_str(&format!(
+                "| Last run | {} |\n",
+                last.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        if !s.longest.is_empty() {
+            out.push_str(
+                "\n### Longest migrations\n\n| Version | Script | Duration |\n|---|---|---|\n",
+            );
+            for m in &s.longest {
+                out.push_str(&format!(
+                    "| {} | {} | {}ms |\n",
+                    m.version.as_deref().unwrap_or("R"),
+                    m.script,
+                    m.execution_time_ms
+                ));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn row(rank: i32, kind: &str, days_ago: i64, ms: i32, success: bool) -> AppliedMigration {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap();
+        AppliedMigration {
+            description: format!("m{}", rank),
+            migration_type: kind.to_string(),
+            installed_on: now - Duration::days(days_ago),
+            execution_time: ms,
+            success,
+            ..AppliedMigration::for_test(
+                rank,
+                Some(&rank.to_string()),
+                &format!("V{}__m{}.sql", rank, rank),
+            )
+        }
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_since("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_since("2w").unwrap(), Duration::weeks(2));
+        assert!(parse_since("30").is_err());
+        assert!(parse_since("0d").is_err());
+        assert!(parse_since("d").is_err());
+        assert!(parse_since("").is_err());
+        assert!(parse_since("3é").is_err());
+        assert!(parse_since("é").is_err());
+        assert!(parse_since(&format!("{}w", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_summarize_window() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap();
+        let rows = vec![
+            row(1, "BASELINE", 60, 0, true),
+            row(2, "SQL", 40, 9000, true),
+            row(3, "SQL", 20, 100, true),
+            row(4, "SQL", 10, 300, true),
+            row(5, "SQL", 9, 50, false),
+            row(6, "UNDO_SQL", 5, 20, true),
+        ];
+        let s = summarize(&rows, Some(now - Duration::days(28)), now, 2);
+
+        assert_eq!(s.applied, 2);
+        assert_eq!(s.failed, 1);
+        assert_eq!(s.undone, 1);
+        assert!((s.failure_rate - 0.25).abs() < 1e-9);
+        assert!((s.per_week - 0.5).abs() < 1e-9);
+        assert!((s.avg_duration_ms - 200.0).abs() < 1e-9);
+        assert_eq!(s.total_duration_ms, 400);
+        assert_eq!(s.longest.len(), 2);
+        assert_eq!(s.longest[0].script, "V4__m4.sql");
+        assert_eq!(s.last_run, Some(now - Duration::days(5)));
+    }
+
+    #[test]
+    fn test_summarize_empty() {
+        let now = Utc::now();
+        let s = summarize(&[], None, now, DEFAULT_TOP);
+        assert_eq!(s.applied, 0);
+        assert_eq!(s.failure_rate, 0.0);
+        assert_eq!(s.per_week, 0.0);
+        assert!(s.longest.is_empty());
+        assert!(s.last_run.is_none());
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 30, 0, 0, 0).unwrap();
+        let mut s = summarize(&[row(2, "SQL", 3, 1500, true)], None, now, DEFAULT_TOP);
+        s.database = Some("orders".to_string());
+        let md = render_markdown(&[s]);
+        assert!(md.contains("## orders"));
+        assert!(md.contains("| Applied | 1 |"));
+        assert!(md.contains("| 2 | V2__m2.sql | 1500ms |"));
+    }
+}
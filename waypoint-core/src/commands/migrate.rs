@@ -8,10 +8,18 @@
 //! downstream callers (and the library `Waypoint` façade) can keep using
 //! the historical paths under `crate::commands::migrate::*`.
 
+use std::collections::{HashMap, HashSet};
+
 use serde::Serialize;
 
+use crate::commands::check::CheckReport;
+use crate::commands::info::{MigrationInfo, MigrationState};
+use crate::config::MigrationSettings;
 use crate::directive::MigrationDirectives;
-use crate::error::WaypointError;
+use crate::error::{Result, WaypointError};
+use crate::migration::{MigrationVersion, ResolvedMigration};
+use crate::multi::MultiResult;
+use crate::warning::{Warning, WarningCode};
 
 // ── Re-exports of the engine-specific entry points ──────────────────────────
 //
@@ -28,7 +36,7 @@ pub use crate::engines::postgres::migrate::{execute, execute_with_options};
 // ── Engine-agnostic public types ────────────────────────────────────────────
 
 /// Report returned after a migrate operation.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct MigrateReport {
     /// Number of migrations that were applied in this run.
     pub migrations_applied: usize,
@@ -40,6 +48,68 @@ pub struct MigrateReport {
     pub hooks_executed: usize,
     /// Total execution time of all hooks in milliseconds.
     pub hooks_time_ms: i32,
+    /// Post-migrate `ANALYZE` / `VACUUM` on rewritten or bulk-modified tables
+    /// (`post_migrate_analyze`).
+    pub maintenance: Vec<TableMaintenance>,
+    /// Versions above `--target` applied because a migration up to the
+    /// target depends on them (`dependency_ordering`).
+    pub dependencies_added: Vec<String>,
+    /// Non-fatal problems raised during the run, in the order they occurred.
+    pub warnings: Vec<Warning>,
+    /// Data-quality checks run after the migrations (`[checks] after_migrate`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<CheckReport>,
+    /// Fingerprint of the schema after the run (`[drift] publish_fingerprint`
+    /// or `store_baseline`); see [`crate::drift_baseline`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_fingerprint: Option<String>,
+    /// Every migration file as the run resolved it, for the execution
+    /// receipt.
+    #[serde(skip)]
+    pub manifest: Vec<crate::receipt::ManifestEntry>,
+}
+
+impl MigrateReport {
+    /// Record an applied migration, with a warning if it affected more rows
+    /// than `[migrations] warn_rows_affected`.
+    pub(crate) fn push_detail(&mut self, detail: MigrateDetail, warn_rows: Option<u64>) {
+        if let Some(w) = warn_rows_affected(&detail.script, detail.rows_affected, warn_rows) {
+            self.warnings.push(w);
+        }
+        crate::events::emit(|| crate::events::MigrateEventKind::MigrationCompleted {
+            script: detail.script.clone(),
+            version: detail.version.clone(),
+            execution_time_ms: detail.execution_time_ms,
+            rows_affected: detail.rows_affected,
+        });
+        self.details.push(detail);
+    }
+
+    /// Fold the report of one schema's run into this one (`[migrations]
+    /// schemas`), tagging its migrations with `schema`.
+    pub(crate) fn merge_schema(&mut self, schema: &str, other: MigrateReport) {
+        self.migrations_applied += other.migrations_applied;
+        self.total_time_ms += other.total_time_ms;
+        self.hooks_executed += other.hooks_executed;
+        self.hooks_time_ms += other.hooks_time_ms;
+        self.details
+            .extend(other.details.into_iter().map(|detail| MigrateDetail {
+                schema: Some(schema.to_string()),
+                ..detail
+            }));
+        self.maintenance.extend(other.maintenance);
+        self.dependencies_added.extend(other.dependencies_added);
+        if self.manifest.is_empty() {
+            self.manifest = other.manifest;
+        }
+        self.warnings.extend(other.warnings);
+        if let Some(checks) = other.checks {
+            match &mut self.checks {
+                Some(existing) => existing.merge(checks),
+                None => self.checks = Some(checks),
+            }
+        }
+    }
 }
 
 /// Details of a single applied migration within a migrate run.
@@ -53,10 +123,290 @@ pub struct MigrateDetail {
     pub script: String,
     /// Execution time of this migration in milliseconds.
     pub execution_time_ms: i32,
+    /// Total rows reported as affected by the migration's statements
+    /// (INSERT/UPDATE/DELETE and the like; DDL contributes zero).
+    pub rows_affected: u64,
+    /// Schema the migration was applied to, when `[migrations] schemas`
+    /// lists several.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+}
+
+/// What post-migrate maintenance did for one table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceAction {
+    /// `ANALYZE` was run.
+    Analyze,
+    /// `VACUUM (ANALYZE)` was run.
+    VacuumAnalyze,
+    /// Skipped because the table is below `post_migrate_min_table_mb`.
+    SkippedSmall,
+}
+
+/// Post-migrate maintenance for a single table.
+#[derive(Debug, Serialize)]
+pub struct TableMaintenance {
+    /// Schema-qualified table name.
+    pub table: String,
+    /// What was done.
+    pub action: MaintenanceAction,
+    /// Table size (`pg_table_size`) in bytes when maintenance was considered.
+    pub table_size_bytes: i64,
+    /// Time spent on maintenance in milliseconds (0 when skipped).
+    pub execution_time_ms: i32,
+}
+
+/// Result of `migrate --converge`: a stable, minimal JSON contract for
+/// configuration-management tools (Terraform, Ansible, ...).
+///
+/// Fields are only ever added, never renamed or removed. A run that applies
+/// nothing reports `changed: false` and still exits 0.
+#[derive(Debug, Serialize)]
+pub struct ConvergeReport {
+    /// Whether any migration was applied (or, in check mode, would be).
+    pub changed: bool,
+    /// Scripts applied (or pending in check mode), in execution order.
+    /// In multi-database mode each entry is `<database>/<script>`, and with
+    /// several `[migrations] schemas` it is `<schema>/<script>`.
+    pub migrations: Vec<String>,
+    /// True when this is a `--dry-run` check and nothing was applied.
+    pub check_mode: bool,
+}
+
+impl ConvergeReport {
+    /// Build the converge result of a completed migrate run.
+    pub fn from_report(report: &MigrateReport) -> Self {
+        let migrations: Vec<String> = report
+            .details
+            .iter()
+            .map(|d| qualified_script(d.schema.as_deref(), &d.script))
+            .collect();
+        ConvergeReport {
+            changed: !migrations.is_empty(),
+            migrations,
+            check_mode: false,
+        }
+    }
+
+    /// Build the converge result of a multi-database migrate run.
+    pub fn from_multi(result: &MultiResult) -> Self {
+        let migrations: Vec<String> = result
+            .results
+            .iter()
+            .flat_map(|r| r.applied.iter().map(move |s| format!("{}/{}", r.name, s)))
+            .collect();
+        ConvergeReport {
+            changed: !migrations.is_empty(),
+            migrations,
+            check_mode: false,
+        }
+    }
+
+    /// Build the check-mode result from `info`: what migrate would apply
+    /// up to `target_version`. Rows from several `[migrations] schemas` are
+    /// judged per schema.
+    pub fn pending(
+        infos: &[MigrationInfo],
+        target_version: Option<&str>,
+        settings: &MigrationSettings,
+    ) -> Result<Self> {
+        let out_of_order = settings.out_of_order_policy();
+        let mut applied: HashMap<Option<&str>, Vec<MigrationVersion>> = HashMap::new();
+        for info in infos
+            .iter()
+            .filter(|i| matches!(i.state, MigrationState::Applied | MigrationState::Baseline))
+        {
+            if let Some(v) = info
+                .version
+                .as_deref()
+                .and_then(|v| settings.parse_version(v).ok())
+            {
+                applied.entry(info.schema.as_deref()).or_default().push(v);
+            }
+        }
+        let candidates: Vec<&MigrationInfo> = infos
+            .iter()
+            .filter(|i| match i.state {
+                MigrationState::Pending | MigrationState::Outdated => true,
+                MigrationState::OutOfOrder => i
+                    .version
+                    .as_deref()
+                    .and_then(|v| settings.parse_version(v).ok())
+                    .is_some_and(|v| {
+                        let schema_applied = applied.get(&i.schema.as_deref());
+                        out_of_order.allows(&v, schema_applied.into_iter().flatten())
+                    }),
+                _ => false,
+            })
+            .collect();
+        let version_of = |i: &MigrationInfo| {
+            i.version
+                .as_deref()
+                .and_then(|v| settings.parse_version(v).ok())
+        };
+        let known: Vec<MigrationVersion> = infos
+            .iter()
+            .filter(|i| {
+                !matches!(
+                    i.state,
+                    MigrationState::Missing | MigrationState::Ignored | MigrationState::Baseline
+                )
+            })
+            .filter_map(version_of)
+            .collect();
+        let pending: Vec<MigrationVersion> =
+            candidates.iter().filter_map(|i| version_of(i)).collect();
+        let target = resolve_target(target_version, &known, |v| pending.contains(v))?;
+        let migrations: Vec<String> = candidates
+            .into_iter()
+            .filter(|i| match (&target, version_of(i)) {
+                (Some(t), Some(v)) => &v <= t,
+                _ => true,
+            })
+            .map(|i| qualified_script(i.schema.as_deref(), &i.script))
+            .collect();
+        Ok(ConvergeReport {
+            changed: !migrations.is_empty(),
+            migrations,
+            check_mode: true,
+        })
+    }
+}
+
+/// `<schema>/<script>` for a migration from one of several `[migrations]
+/// schemas`, otherwise just the script.
+fn qualified_script(schema: Option<&str>, script: &str) -> String {
+    match schema {
+        Some(schema) => format!("{}/{}", schema, script),
+        None => script.to_string(),
+    }
 }
 
 // ── Shared helpers used by both engine paths ────────────────────────────────
 
+/// Resolve a `--target` to the version migrate stops at, or `None` without
+/// one. Besides an absolute version this accepts relative targets:
+///
+/// - `+N`: up to the N-th pending migration (all of them if fewer are pending)
+/// - `latest`: up to the newest migration
+/// - `latest-N`: up to N versions below the newest migration
+///
+/// `known` holds every versioned migration migrate could select, applied or
+/// not; `is_pending` picks those still to be applied.
+pub fn resolve_target(
+    target: Option<&str>,
+    known: &[MigrationVersion],
+    is_pending: impl Fn(&MigrationVersion) -> bool,
+) -> Result<Option<MigrationVersion>> {
+    let Some(target) = target.map(str::trim) else {
+        return Ok(None);
+    };
+    let offset = |n: &str| {
+        n.parse::<usize>().map_err(|_| {
+            WaypointError::ConfigError(format!(
+                "Invalid target '{}': expected a version, +N, latest or latest-N",
+                target
+            ))
+        })
+    };
+    let mut known: Vec<&MigrationVersion> = known.iter().collect();
+    known.sort();
+    known.dedup();
+
+    let resolved = if let Some(n) = target.strip_prefix('+') {
+        let n = offset(n)?;
+        if n == 0 {
+            return Err(WaypointError::ConfigError(
+                "Target '+0' would apply nothing; use +1 or more".to_string(),
+            ));
+        }
+        let pending: Vec<&MigrationVersion> =
+            known.iter().copied().filter(|v| is_pending(v)).collect();
+        match pending.get(n.min(pending.len()).saturating_sub(1)) {
+            Some(v) => (*v).clone(),
+            None => return Ok(None),
+        }
+    } else if let Some(rest) = target.strip_prefix("latest") {
+        let n = match rest {
+            "" => 0,
+            _ => offset(rest.strip_prefix('-').unwrap_or("?"))?,
+        };
+        match known.len().checked_sub(n + 1).map(|i| known[i]) {
+            Some(v) => v.clone(),
+            None => {
+                return Err(WaypointError::ConfigError(format!(
+                    "Target '{}' is before the first migration ({} known)",
+                    target,
+                    known.len()
+                )))
+            }
+        }
+    } else {
+        // Parsed under the same scheme as the versions it is compared with.
+        let scheme = known.first().map(|v| v.scheme).unwrap_or_default();
+        return MigrationVersion::parse_with(target, scheme).map(Some);
+    };
+    log::info!(
+        "Resolved relative target; target={}, version={}",
+        target,
+        resolved
+    );
+    Ok(Some(resolved))
+}
+
+/// Versions of the versioned migrations in `resolved` that the version
+/// filter (`cherry_pick` / `skip_versions`) selects, for [`resolve_target`].
+pub(crate) fn selectable_versions(
+    resolved: &[ResolvedMigration],
+    config: &crate::config::WaypointConfig,
+) -> Result<Vec<MigrationVersion>> {
+    let filter = config.migrations.version_filter()?;
+    Ok(resolved
+        .iter()
+        .filter(|m| m.is_versioned())
+        .filter_map(|m| m.version())
+        .filter(|v| filter.selects(v))
+        .cloned()
+        .collect())
+}
+
+/// Versions a `+N` target counts from: the versioned migrations in
+/// `resolved` that this run would apply if there were no target. This is the
+/// apply loop's own predicate: unapplied, above the baseline, selected by the
+/// version filter, run in the current environment, and allowed by the
+/// out-of-order policy when below the highest applied version. Guard skips
+/// are only known while applying, so those migrations still count.
+pub(crate) fn runnable_versions(
+    resolved: &[ResolvedMigration],
+    config: &crate::config::WaypointConfig,
+    effective_versions: &HashSet<String>,
+    baseline_version: Option<&MigrationVersion>,
+) -> Result<Vec<MigrationVersion>> {
+    let filter = config.migrations.version_filter()?;
+    let policy = config.migrations.out_of_order_policy();
+    let current_env = config.migrations.environment.as_deref();
+    let applied: Vec<MigrationVersion> = effective_versions
+        .iter()
+        .filter_map(|v| config.migrations.parse_version(v).ok())
+        .collect();
+    let highest = applied.iter().max();
+    Ok(resolved
+        .iter()
+        .filter(|m| m.is_versioned())
+        .filter(|m| should_run_in_environment(&m.directives, current_env))
+        .filter_map(|m| m.version())
+        .filter(|v| !effective_versions.contains(&v.raw))
+        .filter(|v| !matches!(baseline_version, Some(b) if *v <= b))
+        .filter(|v| filter.selects(v))
+        .filter(|v| match highest {
+            Some(hi) if *v < hi => policy.allows(v, applied.iter()),
+            _ => true,
+        })
+        .cloned()
+        .collect())
+}
+
 /// Result of evaluating require-guard preconditions for a single migration.
 pub(crate) enum GuardAction {
     /// All preconditions passed; proceed with the migration.
@@ -67,6 +417,35 @@ pub(crate) enum GuardAction {
     Error(WaypointError),
 }
 
+/// Report a migration about to be applied to the migrate observer.
+pub(crate) fn migration_started(migration: &ResolvedMigration) {
+    crate::events::emit(|| crate::events::MigrateEventKind::MigrationStarted {
+        script: migration.script.clone(),
+        version: migration.version().map(|v| v.raw.clone()),
+        description: migration.description.clone(),
+    });
+}
+
+/// Report a migration's safety analysis to the migrate observer.
+#[cfg(feature = "postgres")]
+pub(crate) fn safety_analyzed(script: &str, report: &crate::safety::SafetyReport) {
+    crate::events::emit(|| crate::events::MigrateEventKind::SafetyVerdict {
+        script: script.to_string(),
+        verdict: report.overall_verdict,
+        suggestions: report.suggestions.clone(),
+    });
+}
+
+/// Report an evaluated `require`/`ensure` guard to the migrate observer.
+pub(crate) fn guard_evaluated(script: &str, kind: &str, expression: &str, passed: bool) {
+    crate::events::emit(|| crate::events::MigrateEventKind::GuardEvaluated {
+        script: script.to_string(),
+        kind: kind.to_string(),
+        expression: expression.to_string(),
+        passed,
+    });
+}
+
 /// Check if a migration should run in the current environment.
 ///
 /// Returns true if:
@@ -87,9 +466,41 @@ pub(crate) fn should_run_in_environment(
     directives.env.iter().any(|e| e.eq_ignore_ascii_case(env))
 }
 
+/// Warn when a migration touched more rows than `[migrations] warn_rows_affected`.
+pub(crate) fn warn_rows_affected(
+    script: &str,
+    rows: u64,
+    threshold: Option<u64>,
+) -> Option<Warning> {
+    match threshold {
+        Some(limit) if rows > limit => {
+            log::warn!(
+                "Migration affected more rows than expected; script={}, rows_affected={}, threshold={}",
+                script,
+                rows,
+                limit
+            );
+            Some(Warning::new(
+                WarningCode::RowsAffected,
+                script,
+                format!("affected {} rows (threshold {})", rows, limit),
+            ))
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::migration::OutOfOrderPolicy;
+
+    fn settings(out_of_order_policy: OutOfOrderPolicy) -> MigrationSettings {
+        MigrationSettings {
+            out_of_order_policy,
+            ..MigrationSettings::default()
+        }
+    }
 
     #[test]
     fn test_should_run_in_environment_no_directives() {
@@ -121,6 +532,15 @@ mod tests {
         assert!(!should_run_in_environment(&directives, Some("dev")));
     }
 
+    #[test]
+    fn test_warn_rows_affected_threshold() {
+        let w = warn_rows_affected("V2__Backfill.sql", 5_000, Some(1_000)).unwrap();
+        assert_eq!(w.code, WarningCode::RowsAffected);
+        assert_eq!(w.context, "V2__Backfill.sql");
+        assert!(warn_rows_affected("V2__Backfill.sql", 1_000, Some(1_000)).is_none());
+        assert!(warn_rows_affected("V2__Backfill.sql", 5_000, None).is_none());
+    }
+
     #[test]
     fn test_should_run_in_environment_no_env_configured() {
         let directives = MigrationDirectives {
@@ -129,4 +549,128 @@ mod tests {
         };
         assert!(should_run_in_environment(&directives, None));
     }
+
+    fn info(version: Option<&str>, script: &str, state: MigrationState) -> MigrationInfo {
+        MigrationInfo {
+            version: version.map(str::to_string),
+            description: String::new(),
+            migration_type: "SQL".to_string(),
+            script: script.to_string(),
+            state,
+            installed_on: None,
+            execution_time: None,
+            checksum: None,
+            schema: None,
+            audit: None,
+        }
+    }
+
+    #[test]
+    fn test_converge_pending() {
+        let infos = vec![
+            info(Some("1"), "V1__a.sql", MigrationState::Applied),
+            info(Some("2"), "V2__b.sql", MigrationState::OutOfOrder),
+            info(Some("3"), "V3__c.sql", MigrationState::Pending),
+            info(Some("4"), "V4__d.sql", MigrationState::Pending),
+            info(None, "R__views.sql", MigrationState::Outdated),
+        ];
+
+        let never = settings(OutOfOrderPolicy::Never);
+        let all = ConvergeReport::pending(&infos, None, &never).unwrap();
+        assert!(all.changed && all.check_mode);
+        assert_eq!(all.migrations, ["V3__c.sql", "V4__d.sql", "R__views.sql"]);
+
+        let capped =
+            ConvergeReport::pending(&infos, Some("3"), &settings(OutOfOrderPolicy::Always))
+                .unwrap();
+        assert_eq!(
+            capped.migrations,
+            ["V2__b.sql", "V3__c.sql", "R__views.sql"]
+        );
+
+        let none = ConvergeReport::pending(&infos[..1], None, &never).unwrap();
+        assert!(!none.changed);
+        assert!(none.migrations.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_relative_targets() {
+        let known: Vec<MigrationVersion> = ["1", "2", "3", "4", "5"]
+            .iter()
+            .map(|v| MigrationVersion::parse(v).unwrap())
+            .collect();
+        let pending = |v: &MigrationVersion| v.segments[0] >= 3;
+        let resolve = |t: &str| {
+            resolve_target(Some(t), &known, pending)
+                .unwrap()
+                .map(|v| v.raw)
+        };
+
+        assert_eq!(resolve("2.1"), Some("2.1".to_string()));
+        assert_eq!(resolve("+1"), Some("3".to_string()));
+        assert_eq!(resolve("+2"), Some("4".to_string()));
+        assert_eq!(resolve("+9"), Some("5".to_string()));
+        assert_eq!(resolve("latest"), Some("5".to_string()));
+        assert_eq!(resolve("latest-1"), Some("4".to_string()));
+        assert_eq!(resolve("latest-3"), Some("2".to_string()));
+        assert_eq!(resolve_target(None, &known, pending).unwrap(), None);
+        assert_eq!(resolve_target(Some("+1"), &known, |_| false).unwrap(), None);
+        for bad in ["+0", "+x", "latest-", "latest1", "latest-5"] {
+            assert!(
+                matches!(
+                    resolve_target(Some(bad), &known, pending),
+                    Err(WaypointError::ConfigError(_))
+                ),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_converge_pending_relative_target() {
+        let infos = vec![
+            info(Some("1"), "V1__a.sql", MigrationState::Applied),
+            info(Some("2"), "V2__b.sql", MigrationState::Pending),
+            info(Some("3"), "V3__c.sql", MigrationState::Pending),
+            info(Some("4"), "V4__d.sql", MigrationState::Pending),
+        ];
+        let never = settings(OutOfOrderPolicy::Never);
+        let next = ConvergeReport::pending(&infos, Some("+1"), &never).unwrap();
+        assert_eq!(next.migrations, ["V2__b.sql"]);
+        let held = ConvergeReport::pending(&infos, Some("latest-1"), &never).unwrap();
+        assert_eq!(held.migrations, ["V2__b.sql", "V3__c.sql"]);
+    }
+
+    #[test]
+    fn test_converge_from_report() {
+        let mut report = MigrateReport {
+            migrations_applied: 0,
+            total_time_ms: 0,
+            details: vec![],
+            hooks_executed: 0,
+            hooks_time_ms: 0,
+            maintenance: vec![],
+            dependencies_added: vec![],
+            warnings: vec![],
+            checks: None,
+            schema_fingerprint: None,
+            manifest: Vec::new(),
+        };
+        let json = serde_json::to_value(ConvergeReport::from_report(&report)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"changed": false, "migrations": [], "check_mode": false})
+        );
+
+        report.details.push(MigrateDetail {
+            version: Some("1".to_string()),
+            description: "a".to_string(),
+            script: "V1__a.sql".to_string(),
+            execution_time_ms: 3,
+            rows_affected: 0,
+            schema: None,
+        });
+        assert!(ConvergeReport::from_report(&report).changed);
+    }
 }
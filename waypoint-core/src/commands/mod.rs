@@ -1,22 +1,50 @@
 //! Command implementations: migrate, info, validate, repair, baseline, clean,
-//! lint, changelog, diff, drift, snapshot, explain, check-conflicts, safety,
-//! advisor, simulate. The `preflight` command is exposed via
-//! [`crate::preflight::run_preflight_db`] directly (no command-wrapper module).
+//! audit history, lint, changelog, diff, drift, snapshot, explain,
+//! check-conflicts, safety, advisor, simulate, schedule, export, metrics,
+//! plan, assist, history upgrade, init, new, reconcile, listen, find,
+//! import-flyway, guard check, report diff, seed, pending-report, check,
+//! info --history timeline, rebase. The
+//! `preflight` command is exposed via [`crate::preflight::run_preflight_db`]
+//! directly (no command-wrapper module).
 
 pub mod advisor;
+pub mod assist;
+pub mod audit_history;
 pub mod baseline;
 pub mod changelog;
+pub mod check;
 pub mod check_conflicts;
+pub mod checksums;
 pub mod clean;
 pub mod diff;
 pub mod drift;
 pub mod explain;
+pub mod export;
+pub mod find;
+pub mod fingerprint;
+pub mod guard_check;
+pub mod history_upgrade;
+pub mod import_flyway;
 pub mod info;
+pub mod init;
 pub mod lint;
+pub mod listen;
+pub mod metrics;
 pub mod migrate;
+pub mod new_migration;
+pub mod pending_report;
+pub mod plan;
+pub mod rebase;
+pub mod reconcile;
 pub mod repair;
+pub mod report_diff;
 pub mod safety;
+pub mod schedule;
+pub mod seed;
 pub mod simulate;
+pub mod sla_check;
 pub mod snapshot;
+pub mod squash;
+pub mod timeline;
 pub mod undo;
 pub mod validate;
@@ -0,0 +1,461 @@
+//! Scaffold new migration files (`waypoint new`).
+//!
+//! Picks the next version from the files on disk and, when a database is
+//! reachable, the history table, then writes `V{version}__{description}.sql`
+//! into the first configured location from a built-in template. An undo stub
+//! `U{version}__{description}.sql` can be written alongside. The vetted
+//! templates (`add-column`, `create-table`, ...) come from
+//! [`crate::templates`] and bring their own undo body.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::config::{MigrationSettings, WaypointConfig};
+use crate::error::{Result, WaypointError};
+use crate::migration::{scan_migrations_with_limits, MigrationVersion};
+use crate::templates::{self, Template};
+
+/// Built-in template for the new migration's body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MigrationTemplate {
+    /// A schema change.
+    Ddl,
+    /// A batched, re-runnable data change.
+    Data,
+    /// A schema change plus its `U` undo file.
+    UndoPair,
+    /// Vetted: add a nullable column under a lock timeout.
+    AddColumn,
+    /// Vetted: create a table with an identity key.
+    CreateTable,
+    /// Vetted: `CREATE INDEX CONCURRENTLY` outside a transaction.
+    AddIndexConcurrently,
+    /// Vetted: batched, resumable backfill of a column.
+    Backfill,
+}
+
+impl MigrationTemplate {
+    /// The vetted template behind this choice, if it is one.
+    pub fn builtin(self) -> Option<&'static Template> {
+        let name = match self {
+            Self::Ddl | Self::Data | Self::UndoPair => return None,
+            Self::AddColumn => "add-column",
+            Self::CreateTable => "create-table",
+            Self::AddIndexConcurrently => "add-index-concurrently",
+            Self::Backfill => "backfill",
+        };
+        templates::find(name)
+    }
+}
+
+impl FromStr for MigrationTemplate {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "ddl" => Ok(Self::Ddl),
+            "data" => Ok(Self::Data),
+            "undo-pair" | "undo_pair" => Ok(Self::UndoPair),
+            "add-column" => Ok(Self::AddColumn),
+            "create-table" => Ok(Self::CreateTable),
+            "add-index-concurrently" => Ok(Self::AddIndexConcurrently),
+            "backfill" => Ok(Self::Backfill),
+            other => Err(WaypointError::ConfigError(format!(
+                "Unknown migration template '{}'. Valid values: ddl, data, undo-pair, \
+                 add-column, create-table, add-index-concurrently, backfill",
+                other
+            ))),
+        }
+    }
+}
+
+/// Options for scaffolding a migration.
+#[derive(Debug, Clone)]
+pub struct NewMigrationOptions {
+    /// `V__Add_users`, `V7__Add_users`, `R__Refresh_views`, or a bare
+    /// description (versioned, next version).
+    pub name: String,
+    /// Template for the migration body.
+    pub template: MigrationTemplate,
+    /// Also write a `U{version}__` undo stub.
+    pub undo: bool,
+    /// Parameter values for a vetted template (`table`, `column`, ...).
+    pub values: BTreeMap<String, String>,
+    /// Report the files without writing them.
+    pub dry_run: bool,
+}
+
+/// A file written (or, for a dry run, that would be written).
+#[derive(Debug, Clone, Serialize)]
+pub struct NewMigrationFile {
+    /// Path of the file.
+    pub path: String,
+    /// File contents.
+    pub contents: String,
+}
+
+/// Result of scaffolding a migration.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewMigrationReport {
+    /// Version given to the migration, or None for a repeatable one.
+    pub version: Option<String>,
+    /// The migration file, followed by its undo stub if one was asked for.
+    pub files: Vec<NewMigrationFile>,
+    /// Template parameters left as `{{name}}` for lack of a value.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unfilled: Vec<String>,
+    /// Whether the files were only reported, not written.
+    pub dry_run: bool,
+}
+
+/// What the `name` argument asks for.
+#[derive(Debug, PartialEq)]
+enum Requested {
+    /// Versioned, with an explicit version or the next one.
+    Versioned(Option<MigrationVersion>),
+    Repeatable,
+}
+
+/// Split the `name` argument into its kind and a filename-safe description,
+/// parsing a requested version under `[migrations] version_scheme`.
+fn parse_name(name: &str, migrations: &MigrationSettings) -> Result<(Requested, String)> {
+    let name = name.trim();
+    let name = name.strip_suffix(".sql").unwrap_or(name);
+    let (requested, description) = if let Some(rest) = name.strip_prefix("R__") {
+        (Requested::Repeatable, rest)
+    } else if let Some((prefix, rest)) = name.split_once("__") {
+        match prefix.strip_prefix('V') {
+            Some("") => (Requested::Versioned(None), rest),
+            Some(version) => (
+                Requested::Versioned(Some(migrations.parse_version(version)?)),
+                rest,
+            ),
+            None if prefix.starts_with('U') => {
+                return Err(WaypointError::ConfigError(
+                    "Create undo files together with their migration, using --undo".to_string(),
+                ))
+            }
+            None => (Requested::Versioned(None), name),
+        }
+    } else {
+        (Requested::Versioned(None), name)
+    };
+
+    let description: String = description
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() || c == '-' {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if description.is_empty() {
+        return Err(WaypointError::ConfigError(
+            "Migration name needs a description, e.g. V__Add_users_table".to_string(),
+        ));
+    }
+    Ok((requested, description))
+}
+
+/// The version after the highest of `existing`: `1` when there are none,
+/// otherwise the last segment incremented (`7` → `8`, `1.2` → `1.3`),
+/// keeping its separator and zero padding (`007` → `008`).
+pub fn next_version<'a>(existing: impl IntoIterator<Item = &'a MigrationVersion>) -> String {
+    let Some(max) = existing.into_iter().max() else {
+        return "1".to_string();
+    };
+    let split = max.raw.rfind(['.', '_']).map_or(0, |i| i + 1);
+    let (head, last) = max.raw.split_at(split);
+    let next = max.segments.last().copied().unwrap_or(0) + 1;
+    format!("{}{:0width$}", head, next, width = last.len())
+}
+
+/// Scaffold a migration, numbering it after every version found in the
+/// configured locations and in `applied_versions` (from the history table).
+pub fn execute(
+    config: &WaypointConfig,
+    options: &NewMigrationOptions,
+    applied_versions: &[String],
+) -> Result<NewMigrationReport> {
+    let (requested, description) = parse_name(&options.name, &config.migrations)?;
+    let undo = options.undo || options.template == MigrationTemplate::UndoPair;
+    let dir = config.migrations.locations.first().ok_or_else(|| {
+        WaypointError::ConfigError("No migration locations configured".to_string())
+    })?;
+
+    let mut known: Vec<MigrationVersion> = applied_versions
+        .iter()
+        .filter_map(|v| config.migrations.parse_version(v).ok())
+        .collect();
+    let existing_locations: Vec<_> = config
+        .migrations
+        .locations
+        .iter()
+        .filter(|l| l.exists())
+        .cloned()
+        .collect();
+    known.extend(
+        scan_migrations_with_limits(&existing_locations, &config.migrations.scan_limits())?
+            .iter()
+            .filter_map(|m| m.version().cloned()),
+    );
+
+    let version = match requested {
+        Requested::Repeatable => {
+            if undo {
+                return Err(WaypointError::ConfigError(
+                    "Repeatable migrations have no undo file".to_string(),
+                ));
+            }
+            None
+        }
+        Requested::Versioned(Some(v)) => {
+            if known.iter().any(|k| k == &v) {
+                return Err(WaypointError::ConfigError(format!(
+                    "Version {} is already used by a migration file or the history table",
+                    v.raw
+                )));
+            }
+            Some(v.raw)
+        }
+        Requested::Versioned(None) => Some(next_version(&known)),
+    };
+
+    let title = description.replace('_', " ");
+    let rendered = options
+        .template
+        .builtin()
+        .map(|t| t.render(&title, &options.values));
+    let mut files = Vec::new();
+    match (&version, &rendered) {
+        (Some(v), _) => {
+            let script = format!("V{}__{}.sql", v, description);
+            let body = match &rendered {
+                Some(r) => r.migration.clone(),
+                None => template_body(options.template, &title),
+            };
+            files.push(file(dir, &script, body));
+            if undo {
+                let undo_body = match rendered.as_ref().and_then(|r| r.undo.clone()) {
+                    Some(body) => body,
+                    None => format!(
+                        "-- Undo {}\n--\n-- Reverse every change made by {}, newest first.\n\n",
+                        title, script
+                    ),
+                };
+                files.push(file(
+                    dir,
+                    &format!("U{}__{}.sql", v, description),
+                    undo_body,
+                ));
+            }
+        }
+        (None, Some(_)) => {
+            return Err(WaypointError::ConfigError(
+                "Vetted templates are for versioned migrations; use V__ instead of R__".to_string(),
+            ))
+        }
+        (None, None) => files.push(file(
+            dir,
+            &format!("R__{}.sql", description),
+            template_body(options.template, &title),
+        )),
+    }
+
+    for f in &files {
+        if Path::new(&f.path).exists() {
+            return Err(WaypointError::ConfigError(format!(
+                "{} already exists",
+                f.path
+            )));
+        }
+    }
+    if !options.dry_run {
+        std::fs::create_dir_all(dir)?;
+        for f in &files {
+            std::fs::write(&f.path, &f.contents)?;
+        }
+    }
+
+    Ok(NewMigrationReport {
+        version,
+        files,
+        unfilled: rendered.map(|r| r.unfilled).unwrap_or_default(),
+        dry_run: options.dry_run,
+    })
+}
+
+fn file(dir: &Path, name: &str, contents: String) -> NewMigrationFile {
+    NewMigrationFile {
+        path: dir.join(name).display().to_string(),
+        contents,
+    }
+}
+
+fn template_body(template: MigrationTemplate, title: &str) -> String {
+    match template {
+        MigrationTemplate::Ddl
+        | MigrationTemplate::UndoPair
+        | MigrationTemplate::AddColumn
+        | MigrationTemplate::CreateTable
+        | MigrationTemplate::AddIndexConcurrently
+        | MigrationTemplate::Backfill => format!(
+            "-- {}\n--\n-- Schema change. Keep to one logical change; add -- waypoint:require\n\
+             -- guards at the top if it depends on earlier state.\n\n",
+            title
+        ),
+        MigrationTemplate::Data => format!(
+            "-- {}\n--\n-- Data change. On large tables update in batches, and keep it safe to\n\
+             -- re-run after a partial failure, e.g.:\n\
+             --\n\
+             --   UPDATE my_table SET new_col = old_col\n\
+             --   WHERE id IN (SELECT id FROM my_table WHERE new_col IS NULL LIMIT 10000);\n\n",
+            title
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn versions(raw: &[&str]) -> Vec<MigrationVersion> {
+        raw.iter()
+            .map(|v| MigrationVersion::parse(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_next_version() {
+        assert_eq!(next_version(&versions(&[])), "1");
+        assert_eq!(next_version(&versions(&["1", "7", "3"])), "8");
+        assert_eq!(next_version(&versions(&["1.2", "1.1"])), "1.3");
+        assert_eq!(next_version(&versions(&["2_9"])), "2_10");
+        assert_eq!(next_version(&versions(&["007"])), "008");
+    }
+
+    #[test]
+    fn test_parse_name() {
+        let settings = MigrationSettings::default();
+        let parse_name = |name: &str| parse_name(name, &settings);
+        assert_eq!(
+            parse_name("V__Add users-table").unwrap(),
+            (Requested::Versioned(None), "Add_users_table".to_string())
+        );
+        let (requested, _) = parse_name("V5__Add_users.sql").unwrap();
+        assert_eq!(
+            requested,
+            Requested::Versioned(Some(MigrationVersion::parse("5").unwrap()))
+        );
+        assert_eq!(parse_name("R__Views").unwrap().0, Requested::Repeatable);
+        assert_eq!(
+            parse_name("Add_users").unwrap(),
+            (Requested::Versioned(None), "Add_users".to_string())
+        );
+        assert!(parse_name("U3__Add_users").is_err());
+        assert!(parse_name("V__").is_err());
+    }
+
+    #[test]
+    fn test_execute_numbers_after_files_and_history() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V2__First.sql"), "SELECT 1;").unwrap();
+        let mut config = WaypointConfig::default();
+        config.migrations.locations = vec![PathBuf::from(dir.path())];
+
+        let options = NewMigrationOptions {
+            name: "V__Add_users".to_string(),
+            template: MigrationTemplate::UndoPair,
+            undo: false,
+            values: BTreeMap::new(),
+            dry_run: false,
+        };
+        let report = execute(&config, &options, &["4".to_string()]).unwrap();
+        assert_eq!(report.version.as_deref(), Some("5"));
+        assert_eq!(report.files.len(), 2);
+        assert!(dir.path().join("V5__Add_users.sql").exists());
+        assert!(dir.path().join("U5__Add_users.sql").exists());
+
+        // The same explicit version again is refused.
+        let again = NewMigrationOptions {
+            name: "V5__Other".to_string(),
+            template: MigrationTemplate::Ddl,
+            undo: false,
+            values: BTreeMap::new(),
+            dry_run: true,
+        };
+        assert!(execute(&config, &again, &[]).is_err());
+    }
+
+    #[test]
+    fn test_execute_scans_with_configured_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("V1__Big.sql"),
+            "SELECT 1; SELECT 2; SELECT 3;",
+        )
+        .unwrap();
+        let mut config = WaypointConfig::default();
+        config.migrations.locations = vec![PathBuf::from(dir.path())];
+        let options = NewMigrationOptions {
+            name: "V__Next".to_string(),
+            template: MigrationTemplate::Ddl,
+            undo: false,
+            values: BTreeMap::new(),
+            dry_run: true,
+        };
+        // No limits by default.
+        assert_eq!(
+            execute(&config, &options, &[]).unwrap().version.as_deref(),
+            Some("2")
+        );
+
+        config.migrations.max_statements = 2;
+        assert!(matches!(
+            execute(&config, &options, &[]),
+            Err(WaypointError::MigrationTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_execute_vetted_template_with_undo() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = WaypointConfig::default();
+        config.migrations.locations = vec![PathBuf::from(dir.path())];
+
+        let options = NewMigrationOptions {
+            name: "V__Index_orders_by_customer".to_string(),
+            template: "add-index-concurrently".parse().unwrap(),
+            undo: true,
+            values: BTreeMap::from([
+                ("table".to_string(), "orders".to_string()),
+                ("columns".to_string(), "customer_id".to_string()),
+            ]),
+            dry_run: true,
+        };
+        let report = execute(&config, &options, &[]).unwrap();
+        assert_eq!(report.unfilled, vec!["index"]);
+        let migration = &report.files[0].contents;
+        assert!(migration.starts_with("-- Index orders by customer\n"));
+        assert!(migration.contains("-- waypoint:no-transaction"));
+        assert!(migration.contains("ON orders (customer_id)"));
+        assert!(report.files[1]
+            .contents
+            .contains("DROP INDEX IF EXISTS {{index}};"));
+
+        let repeatable = NewMigrationOptions {
+            name: "R__Orders".to_string(),
+            undo: false,
+            ..options
+        };
+        assert!(execute(&config, &repeatable, &[]).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+//! `waypoint pending-report`: a recurring nudge about migration debt.
+//!
+//! Meant for cron. Lists the migrations `migrate` would still apply, with
+//! each one's safety verdict and how long ago its file was added, and sends
+//! the summary as a `pending_report` notification on the `[notify]` channel
+//! (PostgreSQL only). The payload carries a Slack-formatted `text` field, so
+//! a listener can forward it to a chat channel as is.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::info;
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+#[cfg(feature = "postgres")]
+use crate::dialect::DialectKind;
+use crate::error::{Result, WaypointError};
+use crate::notify::MAX_PAYLOAD_BYTES;
+use crate::safety::{self, SafetyVerdict};
+
+/// A migration `migrate` would still apply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingMigration {
+    /// Version string, or None for repeatable migrations.
+    pub version: Option<String>,
+    /// Filename of the migration script.
+    pub script: String,
+    /// Worst safety verdict across the script's statements.
+    pub verdict: SafetyVerdict,
+    /// Days since the file was added (git history, else its modification
+    /// time), or None when neither is available.
+    pub age_days: Option<i64>,
+}
+
+/// Pending work on one database.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingSummary {
+    /// Database name (multi-db mode), or None for a single database.
+    pub database: Option<String>,
+    /// Schema holding the history table.
+    pub schema: String,
+    /// Pending migrations, in the order `migrate` would apply them.
+    pub pending: Vec<PendingMigration>,
+    /// Worst verdict across the pending migrations (`SAFE` when none).
+    pub worst_verdict: SafetyVerdict,
+    /// Age of the oldest pending migration, in days.
+    pub oldest_age_days: Option<i64>,
+    /// Whether the summary was sent as a notification.
+    pub notified: bool,
+}
+
+impl PendingSummary {
+    /// Slack-formatted (mrkdwn) summary: a headline, then one bullet per
+    /// pending migration.
+    pub fn text(&self) -> String {
+        render_text(self.label(), &self.pending, self.pending.len(), self)
+    }
+
+    fn label(&self) -> &str {
+        self.database.as_deref().unwrap_or(&self.schema)
+    }
+}
+
+fn render_text(
+    label: &str,
+    listed: &[PendingMigration],
+    total: usize,
+    summary: &PendingSummary,
+) -> String {
+    if total == 0 {
+        return format!("*{}*: no pending migrations", label);
+    }
+    let mut text = format!(
+        "*{}*: {} pending migration{}",
+        label,
+        total,
+        if total == 1 { "" } else { "s" }
+    );
+    if let Some(days) = summary.oldest_age_days {
+        text.push_str(&format!(", oldest {}", age(days)));
+    }
+    text.push_str(&format!(", worst verdict {}", summary.worst_verdict));
+    for migration in listed {
+        text.push_str(&format!("\n• `{}` {}", migration.script, migration.verdict));
+        if let Some(days) = migration.age_days {
+            text.push_str(&format!(", {}", age(days)));
+        }
+    }
+    if listed.len() < total {
+        text.push_str(&format!("\n…and {} more", total - listed.len()));
+    }
+    text
+}
+
+fn age(days: i64) -> String {
+    if days == 1 {
+        "1 day".to_string()
+    } else {
+        format!("{} days", days)
+    }
+}
+
+/// Payload of the notification sent by `pending-report`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingNotification {
+    /// Always `"pending_report"`.
+    pub event: String,
+    /// Database the summary describes.
+    pub database: String,
+    /// Schema holding the history table.
+    pub schema: String,
+    /// Number of pending migrations.
+    pub pending_count: usize,
+    /// Worst verdict across the pending migrations.
+    pub worst_verdict: SafetyVerdict,
+    /// Age of the oldest pending migration, in days.
+    pub oldest_age_days: Option<i64>,
+    /// The pending migrations, in apply order.
+    pub pending: Vec<PendingMigration>,
+    /// Slack-formatted summary of the listed migrations.
+    pub text: String,
+    /// Whether `pending` was cut short to fit PostgreSQL's payload limit.
+    pub truncated: bool,
+    /// When the notification was sent.
+    pub sent_at: DateTime<Utc>,
+}
+
+impl PendingNotification {
+    /// Describe `summary`; `database` names it when the summary has no name.
+    pub fn from_summary(summary: &PendingSummary, database: &str) -> Self {
+        let database = summary.database.as_deref().unwrap_or(database);
+        Self {
+            event: "pending_report".to_string(),
+            database: database.to_string(),
+            schema: summary.schema.clone(),
+            pending_count: summary.pending.len(),
+            worst_verdict: summary.worst_verdict,
+            oldest_age_days: summary.oldest_age_days,
+            pending: summary.pending.clone(),
+            text: render_text(database, &summary.pending, summary.pending.len(), summary),
+            truncated: false,
+            sent_at: Utc::now(),
+        }
+    }
+
+    /// JSON payload, dropping migrations from the end (and setting
+    /// `truncated`) until it fits in a `NOTIFY`.
+    pub fn to_payload(&self, summary: &PendingSummary) -> String {
+        let mut notification = self.clone();
+        loop {
+            let payload = serde_json::to_string(&notification).unwrap_or_default();
+            if payload.len() <= MAX_PAYLOAD_BYTES || notification.pending.is_empty() {
+                return payload;
+            }
+            notification.pending.pop();
+            notification.truncated = true;
+            notification.text = render_text(
+                &notification.database,
+                &notification.pending,
+                notification.pending_count,
+                summary,
+            );
+        }
+    }
+}
+
+/// Summarize the pending migrations of one database.
+pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<PendingSummary> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let rows = info::execute_db(client, config).await?;
+    let resolved = config.migrations.resolve()?;
+    let now = Utc::now();
+
+    let mut pending = Vec::new();
+    for row in rows.iter().filter(|r| r.state.is_pending()) {
+        let Some(migration) = resolved.iter().find(|m| m.script == row.script) else {
+            continue;
+        };
+        let report = safety::analyze_migration_db(
+            client,
+            &schema,
+            &migration.sql,
+            &migration.script,
+            &config.safety,
+        )
+        .await?;
+        let age_days = locate(&config.migrations.locations, &migration.script)
+            .and_then(|path| added_at(&path))
+            .map(|added| (now - added).num_days().max(0));
+        pending.push(PendingMigration {
+            version: row.version.clone(),
+            script: row.script.clone(),
+            verdict: report.overall_verdict,
+            age_days,
+        });
+    }
+
+    Ok(PendingSummary {
+        database: None,
+        schema,
+        worst_verdict: pending
+            .iter()
+            .map(|m| m.verdict)
+            .max()
+            .unwrap_or(SafetyVerdict::Safe),
+        oldest_age_days: pending.iter().filter_map(|m| m.age_days).max(),
+        pending,
+        notified: false,
+    })
+}
+
+/// Send `summary` as a `pending_report` notification on `channel` (default:
+/// `[notify] channel`) and mark it notified. PostgreSQL only.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn send_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    summary: &mut PendingSummary,
+    channel: Option<&str>,
+) -> Result<()> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => {
+            let channel = channel.unwrap_or(&config.notify.channel);
+            let database = client.current_database().await?;
+            let payload = PendingNotification::from_summary(summary, &database).to_payload(summary);
+            client
+                .as_postgres()?
+                .execute("SELECT pg_notify($1, $2)", &[&channel, &payload])
+                .await?;
+            log::info!(
+                "Sent pending report; channel={}, pending={}",
+                channel,
+                summary.pending.len()
+            );
+            summary.notified = true;
+            Ok(())
+        }
+        kind => Err(WaypointError::ConfigError(format!(
+            "pending-report notifications are not supported on {:?} (PostgreSQL only); \
+             use --dry-run to print the summary",
+            kind
+        ))),
+    }
+}
+
+/// Path of `script` in the first location that has it.
+pub(crate) fn locate(locations: &[PathBuf], script: &str) -> Option<PathBuf> {
+    locations
+        .iter()
+        .map(|location| location.join(script))
+        .find(|path| path.is_file())
+}
+
+/// When `path` was first committed, from `git log`; falls back to the file's
+/// modification time outside a git checkout or for uncommitted files.
+fn added_at(path: &Path) -> Option<DateTime<Utc>> {
+    committed_at(path).or_else(|| modified_at(path))
+}
+
+/// When `path` was first committed, or None outside a git checkout or for
+/// uncommitted files.
+pub(crate) fn committed_at(path: &Path) -> Option<DateTime<Utc>> {
+    let dir = path.parent()?;
+    let name = path.file_name()?.to_str()?;
+    std::process::Command::new("git")
+        .current_dir(dir)
+        .args(["log", "--diff-filter=A", "--format=%cI", "-1", "--", name])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            DateTime::parse_from_rfc3339(String::from_utf8_lossy(&output.stdout).trim()).ok()
+        })
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// The file's modification time.
+pub(crate) fn modified_at(path: &Path) -> Option<DateTime<Utc>> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Utc>::from(modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(count: usize) -> PendingSummary {
+        PendingSummary {
+            database: Some("app".to_string()),
+            schema: "public".to_string(),
+            pending: (1..=count)
+                .map(|i| PendingMigration {
+                    version: Some(i.to_string()),
+                    script: format!("V{}__{}.sql", i, "x".repeat(100)),
+                    verdict: if i == 2 {
+                        SafetyVerdict::Danger
+                    } else {
+                        SafetyVerdict::Safe
+                    },
+                    age_days: Some(i as i64),
+                })
+                .collect(),
+            worst_verdict: if count >= 2 {
+                SafetyVerdict::Danger
+            } else {
+                SafetyVerdict::Safe
+            },
+            oldest_age_days: (count > 0).then_some(count as i64),
+            notified: false,
+        }
+    }
+
+    #[test]
+    fn test_text_lists_pending_migrations() {
+        assert_eq!(summary(0).text(), "*app*: no pending migrations");
+        let text = summary(2).text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "*app*: 2 pending migrations, oldest 2 days, worst verdict DANGER"
+        );
+        assert!(lines[1].starts_with("• `V1__"));
+        assert!(lines[1].ends_with("` SAFE, 1 day"));
+        assert!(lines[2].ends_with("` DANGER, 2 days"));
+    }
+
+    #[test]
+    fn test_payload_fits_notify_limit() {
+        let large = summary(200);
+        let notification = PendingNotification::from_summary(&large, "ignored");
+        assert_eq!(notification.database, "app");
+        let payload = notification.to_payload(&large);
+        assert!(payload.len() <= MAX_PAYLOAD_BYTES);
+        let parsed: PendingNotification = serde_json::from_str(&payload).unwrap();
+        assert!(parsed.truncated);
+        assert_eq!(parsed.pending_count, 200);
+        assert!(parsed
+            .text
+            .ends_with(&format!("…and {} more", 200 - parsed.pending.len())));
+    }
+}
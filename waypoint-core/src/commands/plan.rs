@@ -0,0 +1,443 @@
+//! Two-phase plan/apply for orchestration tools.
+//!
+//! [`execute_db`] records what `migrate` would do right now — pending scripts,
+//! their checksums, safety verdicts and rough duration estimates — as a
+//! serializable [`MigrationPlan`]. [`check_db`] re-plans and reports any
+//! difference from a stored plan, so `Waypoint::apply` only runs an approved
+//! plan exactly as it was approved (the primitive behind a Terraform provider).
+//! `apply` also runs migrate [`with_approved`] plan, so the engine checks it
+//! again under the migrate lock, against the migrations it is about to apply.
+
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::info::{self, MigrationInfo, MigrationState};
+use crate::commands::migrate::{should_run_in_environment, ConvergeReport};
+use crate::config::{MigrationSettings, WaypointConfig};
+use crate::db::DbClient;
+use crate::dependency;
+use crate::error::{Result, WaypointError};
+use crate::migration::ResolvedMigration;
+use crate::safety::{self, SafetyReport, SafetyVerdict, ESTIMATED_ROWS_PER_SEC};
+
+tokio::task_local! {
+    static APPROVED: MigrationPlan;
+}
+
+/// A pending migration as captured in a plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedMigration {
+    /// Version string, or None for repeatable migrations.
+    pub version: Option<String>,
+    /// Filename of the migration script.
+    pub script: String,
+    /// CRC32 checksum of the migration SQL at plan time.
+    pub checksum: i32,
+    /// Worst safety verdict across the script's statements, if safety
+    /// analysis is enabled.
+    pub safety_verdict: Option<SafetyVerdict>,
+    /// Rough duration estimate from table statistics, if any statement
+    /// scans or rewrites a table with known row counts.
+    pub estimated_duration_ms: Option<i64>,
+}
+
+/// Serializable snapshot of what `migrate` would do, produced by `plan()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationPlan {
+    /// Schema the plan was made against.
+    pub schema: String,
+    /// Highest applied version at plan time.
+    pub current_version: Option<String>,
+    /// `--target` the plan was made with.
+    pub target_version: Option<String>,
+    /// Pending migrations in execution order.
+    pub migrations: Vec<PlannedMigration>,
+    /// When the plan was made.
+    pub created_at: DateTime<Utc>,
+}
+
+impl MigrationPlan {
+    /// Whether applying the plan would do nothing.
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty()
+    }
+
+    /// Sum of the per-migration duration estimates, in milliseconds.
+    pub fn estimated_duration_ms(&self) -> i64 {
+        self.migrations
+            .iter()
+            .filter_map(|m| m.estimated_duration_ms)
+            .sum()
+    }
+
+    /// Describe how `current` differs from this plan, or `None` if it matches.
+    ///
+    /// Only the parts that decide what runs are compared: the schema, the
+    /// applied version, and each pending script with its checksum.
+    pub fn divergence(&self, current: &MigrationPlan) -> Option<String> {
+        if self.schema != current.schema {
+            return Some(format!(
+                "schema changed from '{}' to '{}'",
+                self.schema, current.schema
+            ));
+        }
+        if self.current_version != current.current_version {
+            return Some(format!(
+                "applied version is now {} (planned from {})",
+                current.current_version.as_deref().unwrap_or("none"),
+                self.current_version.as_deref().unwrap_or("none")
+            ));
+        }
+        let key = |m: &PlannedMigration| (m.script.clone(), m.checksum);
+        let planned: Vec<_> = self.migrations.iter().map(key).collect();
+        let now: Vec<_> = current.migrations.iter().map(key).collect();
+        if planned == now {
+            return None;
+        }
+        if let Some(m) = self
+            .migrations
+            .iter()
+            .find(|m| !current.migrations.iter().any(|c| c.script == m.script))
+        {
+            return Some(format!("{} is no longer pending", m.script));
+        }
+        if let Some(m) = current
+            .migrations
+            .iter()
+            .find(|c| !self.migrations.iter().any(|m| m.script == c.script))
+        {
+            return Some(format!("{} is pending but not in the plan", m.script));
+        }
+        if let Some((m, _)) = self
+            .migrations
+            .iter()
+            .zip(&current.migrations)
+            .find(|(m, c)| m.script == c.script && m.checksum != c.checksum)
+        {
+            return Some(format!("{} changed since it was planned", m.script));
+        }
+        Some("pending migrations are in a different order".to_string())
+    }
+}
+
+/// Estimate duration from statements that scan or rewrite a table.
+///
+/// SAFE statements are metadata-only and contribute nothing; CAUTION and
+/// DANGER statements cost their table's estimated row count.
+fn estimate_duration_ms(report: &SafetyReport) -> Option<i64> {
+    let rows: Vec<i64> = report
+        .statements
+        .iter()
+        .filter(|s| s.verdict != SafetyVerdict::Safe)
+        .filter_map(|s| s.estimated_rows)
+        .collect();
+    if rows.is_empty() {
+        return None;
+    }
+    Some(rows.iter().sum::<i64>() * 1000 / ESTIMATED_ROWS_PER_SEC)
+}
+
+/// Highest versioned migration recorded as applied.
+fn current_version(infos: &[MigrationInfo], settings: &MigrationSettings) -> Option<String> {
+    infos
+        .iter()
+        .filter(|i| {
+            matches!(
+                i.state,
+                MigrationState::Applied | MigrationState::Baseline | MigrationState::OutOfOrder
+            ) && i.installed_on.is_some()
+        })
+        .filter_map(|i| {
+            let raw = i.version.as_ref()?;
+            settings.parse_version(raw).ok().map(|v| (v, raw.clone()))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, raw)| raw)
+}
+
+/// Reorder pending scripts the way `migrate` does with `dependency_ordering`,
+/// adding unapplied dependencies above the target. Repeatables stay last.
+fn order_by_dependencies(
+    config: &WaypointConfig,
+    infos: &[MigrationInfo],
+    resolved: &[ResolvedMigration],
+    scripts: Vec<String>,
+) -> Result<Vec<String>> {
+    let env = config.migrations.environment.as_deref();
+    let versioned: Vec<&ResolvedMigration> = resolved
+        .iter()
+        .filter(|m| m.is_versioned())
+        .filter(|m| should_run_in_environment(&m.directives, env))
+        .collect();
+    let selected: Vec<&ResolvedMigration> = versioned
+        .iter()
+        .copied()
+        .filter(|m| scripts.contains(&m.script))
+        .collect();
+    let (ordered, _) = dependency::resolve_pending(&versioned, &selected, |v| {
+        infos.iter().any(|i| {
+            i.version.as_deref() == Some(v.raw.as_str())
+                && matches!(
+                    i.state,
+                    MigrationState::Applied
+                        | MigrationState::Baseline
+                        | MigrationState::BelowBaseline
+                )
+        })
+    })?;
+    let mut ordered: Vec<String> = ordered.iter().map(|m| m.script.clone()).collect();
+    ordered.extend(
+        scripts
+            .into_iter()
+            .filter(|s| !versioned.iter().any(|m| &m.script == s)),
+    );
+    Ok(ordered)
+}
+
+/// Build a plan of what `migrate` up to `target_version` would apply now.
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    target_version: Option<&str>,
+) -> Result<MigrationPlan> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let infos = info::execute_db(client, config).await?;
+    let pending = ConvergeReport::pending(&infos, target_version, &config.migrations)?;
+    let resolved = config.migrations.resolve()?;
+    let scripts = if config.migrations.dependency_ordering {
+        order_by_dependencies(config, &infos, &resolved, pending.migrations)?
+    } else {
+        pending.migrations
+    };
+
+    let mut migrations = Vec::new();
+    for script in &scripts {
+        // `info` resolved the files separately; a script it saw that is gone
+        // now means they changed while planning.
+        let m = resolved
+            .iter()
+            .find(|m| &m.script == script)
+            .ok_or_else(|| {
+                WaypointError::ConfigError(format!(
+                    "Pending migration {} disappeared while planning; the migration files changed",
+                    script
+                ))
+            })?;
+        let report = if config.safety.enabled {
+            Some(
+                safety::analyze_migration_db(client, &schema, &m.sql, &m.script, &config.safety)
+                    .await?,
+            )
+        } else {
+            None
+        };
+        migrations.push(PlannedMigration {
+            version: m.version().map(|v| v.raw.clone()),
+            script: m.script.clone(),
+            checksum: m.checksum,
+            safety_verdict: report.as_ref().map(|r| r.overall_verdict),
+            estimated_duration_ms: report.as_ref().and_then(estimate_duration_ms),
+        });
+    }
+
+    Ok(MigrationPlan {
+        schema,
+        current_version: current_version(&infos, &config.migrations),
+        target_version: target_version.map(str::to_string),
+        migrations,
+        created_at: Utc::now(),
+    })
+}
+
+/// Run `fut` (a migrate) with `plan` as the approved plan, which the migrate
+/// engine checks with [`check_approved`] while it holds the migrate lock.
+pub(crate) async fn with_approved<F: Future>(plan: MigrationPlan, fut: F) -> F::Output {
+    APPROVED.scope(plan, fut).await
+}
+
+/// Inside [`with_approved`], fail with `PlanDiverged` unless `pending`, what
+/// the migrate is about to apply from `current_version`, matches the plan.
+/// The schema was compared by [`check_db`] before the lock was taken.
+pub(crate) fn check_approved<'a>(
+    current_version: Option<&str>,
+    pending: impl IntoIterator<Item = &'a ResolvedMigration>,
+) -> Result<()> {
+    let Ok(Some(reason)) = APPROVED.try_with(|plan| {
+        let current = MigrationPlan {
+            schema: plan.schema.clone(),
+            current_version: current_version.map(str::to_string),
+            target_version: plan.target_version.clone(),
+            migrations: pending
+                .into_iter()
+                .map(|m| PlannedMigration {
+                    version: m.version().map(|v| v.raw.clone()),
+                    script: m.script.clone(),
+                    checksum: m.checksum,
+                    safety_verdict: None,
+                    estimated_duration_ms: None,
+                })
+                .collect(),
+            created_at: Utc::now(),
+        };
+        plan.divergence(&current)
+    }) else {
+        return Ok(());
+    };
+    Err(WaypointError::PlanDiverged { reason })
+}
+
+/// Re-plan and return an error if the database or files no longer match `plan`.
+pub async fn check_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    plan: &MigrationPlan,
+) -> Result<()> {
+    let current = execute_db(client, config, plan.target_version.as_deref()).await?;
+    match plan.divergence(&current) {
+        Some(reason) => Err(WaypointError::PlanDiverged { reason }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::{LockLevel, StatementAnalysis};
+
+    fn planned(script: &str, checksum: i32) -> PlannedMigration {
+        PlannedMigration {
+            version: None,
+            script: script.to_string(),
+            checksum,
+            safety_verdict: None,
+            estimated_duration_ms: None,
+        }
+    }
+
+    fn plan(current: Option<&str>, migrations: Vec<PlannedMigration>) -> MigrationPlan {
+        MigrationPlan {
+            schema: "public".to_string(),
+            current_version: current.map(str::to_string),
+            target_version: None,
+            migrations,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_divergence() {
+        let base = plan(
+            Some("1"),
+            vec![planned("V2__a.sql", 7), planned("V3__b.sql", 8)],
+        );
+        assert!(base.divergence(&base.clone()).is_none());
+
+        let applied_elsewhere = plan(Some("2"), vec![planned("V3__b.sql", 8)]);
+        assert!(base
+            .divergence(&applied_elsewhere)
+            .unwrap()
+            .contains("applied version is now 2"));
+
+        let edited = plan(
+            Some("1"),
+            vec![planned("V2__a.sql", 9), planned("V3__b.sql", 8)],
+        );
+        assert_eq!(
+            base.divergence(&edited).unwrap(),
+            "V2__a.sql changed since it was planned"
+        );
+
+        let added = plan(
+            Some("1"),
+            vec![
+                planned("V2__a.sql", 7),
+                planned("V3__b.sql", 8),
+                planned("V4__c.sql", 1),
+            ],
+        );
+        assert_eq!(
+            base.divergence(&added).unwrap(),
+            "V4__c.sql is pending but not in the plan"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_approved_compares_what_migrate_applies() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V2__Add.sql"), "SELECT 2;").unwrap();
+        let resolved = crate::migration::scan_migrations(&[dir.path().to_path_buf()]).unwrap();
+        let approved = plan(
+            Some("1"),
+            vec![planned("V2__Add.sql", resolved[0].checksum)],
+        );
+
+        // Outside `apply` there is nothing to compare with.
+        assert!(check_approved(Some("1"), &resolved).is_ok());
+        with_approved(approved.clone(), async {
+            assert!(check_approved(Some("1"), &resolved).is_ok());
+            assert!(matches!(
+                check_approved(Some("2"), &resolved),
+                Err(WaypointError::PlanDiverged { .. })
+            ));
+        })
+        .await;
+
+        std::fs::write(dir.path().join("V2__Add.sql"), "SELECT 'edited';").unwrap();
+        let edited = crate::migration::scan_migrations(&[dir.path().to_path_buf()]).unwrap();
+        let err = with_approved(approved, async { check_approved(Some("1"), &edited) })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Migration plan is out of date: V2__Add.sql changed since it was planned"
+        );
+    }
+
+    #[test]
+    fn test_plan_round_trips_through_json() {
+        let mut m = planned("V2__a.sql", 7);
+        m.version = Some("2".to_string());
+        m.safety_verdict = Some(SafetyVerdict::Danger);
+        m.estimated_duration_ms = Some(1500);
+        let p = plan(None, vec![m]);
+
+        let json = serde_json::to_string(&p).unwrap();
+        let back: MigrationPlan = serde_json::from_str(&json).unwrap();
+        assert!(p.divergence(&back).is_none());
+        assert_eq!(back.migrations[0], p.migrations[0]);
+        assert_eq!(back.estimated_duration_ms(), 1500);
+    }
+
+    #[test]
+    fn test_estimate_duration_ignores_safe_statements() {
+        let stmt = |verdict, rows| StatementAnalysis {
+            statement_preview: String::new(),
+            lock_level: LockLevel::AccessExclusiveLock,
+            affected_table: Some("t".to_string()),
+            table_size: None,
+            estimated_rows: rows,
+            verdict,
+            suggestions: vec![],
+            data_loss: false,
+        };
+        let mut report = SafetyReport {
+            script: "V1__a.sql".to_string(),
+            overall_verdict: SafetyVerdict::Danger,
+            statements: vec![
+                stmt(SafetyVerdict::Safe, Some(10_000_000)),
+                stmt(SafetyVerdict::Danger, Some(400_000)),
+            ],
+            suggestions: vec![],
+            server_version: None,
+            privileged: vec![],
+            default_changes: vec![],
+            unanalyzable: false,
+        };
+        assert_eq!(estimate_duration_ms(&report), Some(2000));
+
+        report.statements.truncate(1);
+        assert_eq!(estimate_duration_ms(&report), None);
+    }
+}
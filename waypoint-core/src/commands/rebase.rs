@@ -0,0 +1,521 @@
+//! Renumber a branch's migrations after its base branch moved on.
+//!
+//! `waypoint rebase --base main` compares the migration locations with the
+//! same directories on `main` (read with `git ls-tree`, so files merged there
+//! don't need to be checked out). Files that are only on disk are the
+//! branch's own migrations. When one of them collides with or sorts before
+//! the newest version on the base branch, it and the branch migrations after
+//! it are renumbered to the next free versions in their original order,
+//! matching undo files are renamed with them, and `-- waypoint:depends`
+//! directives in the branch's files are rewritten to the new versions.
+//! A reference to a version that collided with the base branch could mean
+//! either migration, so it is left alone and reported. Every rename is
+//! checked before any file is written. Nothing touches a database;
+//! databases that already applied the old versions (a developer's local
+//! one, say) need `clean` or `repair`.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Serialize;
+
+use crate::commands::new_migration::next_version;
+use crate::config::WaypointConfig;
+use crate::directive::directive_lines;
+use crate::error::{Result, WaypointError};
+use crate::git_location::GitLocation;
+use crate::migration::{
+    parse_migration_filename, scan_migrations_with_limits, MigrationKind, MigrationVersion,
+};
+
+/// What to rebase onto.
+#[derive(Debug, Clone)]
+pub struct RebaseOptions {
+    /// Branch (or any git ref) the migrations must come after.
+    pub base: String,
+    /// Report the renumbering without renaming or editing files.
+    pub dry_run: bool,
+}
+
+/// One renumbered migration.
+#[derive(Debug, Clone, Serialize)]
+pub struct Renumbered {
+    /// Version before the rebase.
+    pub from: String,
+    /// Version after the rebase.
+    pub to: String,
+    /// Script before the rebase.
+    pub script: String,
+    /// Script after the rebase.
+    pub new_script: String,
+    /// Matching undo script, renamed along with it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub undo_script: Option<String>,
+    /// Why it moved: a collision, preceding the base, or keeping order.
+    pub reason: String,
+}
+
+/// Result of `waypoint rebase`.
+#[derive(Debug, Serialize)]
+pub struct RebaseReport {
+    /// Base ref compared against.
+    pub base: String,
+    /// Newest version on the base branch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_latest: Option<String>,
+    /// Branch migrations found (versioned, not on the base branch).
+    pub local: Vec<String>,
+    /// Old to new version mapping, in version order.
+    pub renumbered: Vec<Renumbered>,
+    /// Branch scripts whose `depends` directives were rewritten (new names).
+    pub depends_updated: Vec<String>,
+    /// Branch scripts (new names) whose `depends` name a version that exists
+    /// on both branches and was renumbered on this one; left for a person to
+    /// check.
+    pub depends_ambiguous: Vec<String>,
+    /// Whether files were left untouched.
+    pub dry_run: bool,
+}
+
+/// Work out the new versions for the branch's versioned migrations.
+///
+/// `local` must be sorted. Returns `(old, new, reason)` for every version
+/// that changes; nothing changes unless some local version collides with
+/// or precedes the newest base version.
+pub fn plan(
+    base: &[MigrationVersion],
+    local: &[MigrationVersion],
+    base_ref: &str,
+) -> Vec<(MigrationVersion, String, String)> {
+    let Some(base_max) = base.iter().max() else {
+        return Vec::new();
+    };
+    if !local.iter().any(|v| v <= base_max) {
+        return Vec::new();
+    }
+
+    let mut taken: Vec<MigrationVersion> = base.to_vec();
+    let mut moves = Vec::new();
+    for v in local {
+        let new = match taken.iter().max() {
+            Some(top) if v > top => v.clone(),
+            _ => MigrationVersion::parse_with(&next_version(taken.iter()), v.scheme)
+                .unwrap_or_else(|_| v.clone()),
+        };
+        if new.raw != v.raw {
+            let reason = if base.iter().any(|b| b.cmp(v).is_eq()) {
+                format!("collides with V{} on {}", v, base_ref)
+            } else if v <= base_max {
+                format!("precedes V{} on {}", base_max, base_ref)
+            } else {
+                "keeps branch order".to_string()
+            };
+            moves.push((v.clone(), new.raw.clone(), reason));
+        }
+        taken.push(new);
+    }
+    moves
+}
+
+/// Whether `item`, one entry of a `depends` list, names `version` (compared
+/// as `migrate` compares versions, so `V05` names `5` under `numeric`).
+fn names_version(item: &str, version: &MigrationVersion) -> bool {
+    let raw = item.trim();
+    let raw = raw.strip_prefix('V').unwrap_or(raw);
+    MigrationVersion::parse_with(raw, version.scheme).is_ok_and(|v| v.cmp(version).is_eq())
+}
+
+/// Whether a `-- waypoint:depends` directive in `sql` names one of `versions`.
+fn depends_on_any(sql: &str, versions: &[&MigrationVersion]) -> bool {
+    directive_lines(sql)
+        .into_iter()
+        .filter(|d| d.name == "depends")
+        .flat_map(|d| d.value.split(',').map(str::to_string).collect::<Vec<_>>())
+        .any(|item| versions.iter().any(|v| names_version(&item, v)))
+}
+
+/// Rewrite `-- waypoint:depends` versions in `sql` through `mapping` (old
+/// version to new raw version). Returns `None` when nothing changed.
+pub fn rewrite_depends(sql: &str, mapping: &[(MigrationVersion, String)]) -> Option<String> {
+    let lines: HashSet<usize> = directive_lines(sql)
+        .into_iter()
+        .filter(|d| d.name == "depends")
+        .map(|d| d.line)
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let mut changed = false;
+    let mut out = String::with_capacity(sql.len());
+    for (idx, line) in sql.split_inclusive('\n').enumerate() {
+        let Some(at) = lines
+            .contains(&(idx + 1))
+            .then(|| line.find("waypoint:depends"))
+            .flatten()
+        else {
+            out.push_str(line);
+            continue;
+        };
+        let split = at + "waypoint:depends".len();
+        let (head, rest) = line.split_at(split);
+        let body = rest.trim_end_matches(['\n', '\r']);
+        let ending = &rest[body.len()..];
+        let rewritten: Vec<String> = body
+            .split(',')
+            .map(|item| {
+                let trimmed = item.trim();
+                let prefix = if trimmed.starts_with('V') { "V" } else { "" };
+                match mapping
+                    .iter()
+                    .find(|(old, _)| names_version(trimmed, old))
+                    .map(|(_, new)| new)
+                {
+                    Some(new) => {
+                        changed = true;
+                        item.replacen(trimmed, &format!("{}{}", prefix, new), 1)
+                    }
+                    None => item.to_string(),
+                }
+            })
+            .collect();
+        out.push_str(head);
+        out.push_str(&rewritten.join(","));
+        out.push_str(ending);
+    }
+    changed.then_some(out)
+}
+
+/// Script name with its version replaced (`V5__x.sql` → `V8__x.sql`).
+fn renamed(script: &str, old: &str, new: &str) -> String {
+    let prefix = &script[..1];
+    format!("{}{}{}", prefix, new, &script[1 + old.len()..])
+}
+
+/// File names in `location` on `base` (empty when the directory is new).
+fn base_file_names(location: &Path, base: &str) -> Result<HashSet<String>> {
+    let output = Command::new("git")
+        .current_dir(location)
+        .args(["ls-tree", "--name-only", base, "--", "."])
+        .output()
+        .map_err(|e| WaypointError::GitError(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(WaypointError::GitError(format!(
+            "git ls-tree {} failed: {}",
+            base,
+            stderr.trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.rsplit('/').next().unwrap_or(l).to_string())
+        .collect())
+}
+
+/// Execute the rebase command.
+pub fn execute(config: &WaypointConfig, options: &RebaseOptions) -> Result<RebaseReport> {
+    let mut base_versions = Vec::new();
+    // (location, script, version) of the branch's own versioned migrations
+    let mut local: Vec<(PathBuf, String, MigrationVersion)> = Vec::new();
+    // (location, script) of every branch file, for depends rewriting
+    let mut local_files: Vec<(PathBuf, String)> = Vec::new();
+    let mut undo_scripts: HashMap<String, (PathBuf, String)> = HashMap::new();
+
+    for location in &config.migrations.locations {
+        if GitLocation::parse(location)?.is_some() || !location.is_dir() {
+            continue;
+        }
+        let on_base = base_file_names(location, &options.base)?;
+        for name in &on_base {
+            if let Ok((MigrationKind::Versioned(v), _)) = parse_migration_filename(name) {
+                base_versions.push(v.in_scheme(config.migrations.version_scheme)?);
+            }
+        }
+        let scanned = scan_migrations_with_limits(
+            std::slice::from_ref(location),
+            &config.migrations.scan_limits(),
+        )?;
+        for m in scanned {
+            if on_base.contains(&m.script) {
+                continue;
+            }
+            match &m.kind {
+                MigrationKind::Versioned(v) => {
+                    local.push((location.clone(), m.script.clone(), v.clone()))
+                }
+                MigrationKind::Undo(v) => {
+                    undo_scripts.insert(v.raw.clone(), (location.clone(), m.script.clone()));
+                }
+                MigrationKind::Repeatable => {}
+            }
+            local_files.push((location.clone(), m.script));
+        }
+    }
+    local.sort_by(|a, b| a.2.cmp(&b.2));
+
+    let local_versions: Vec<MigrationVersion> = local.iter().map(|l| l.2.clone()).collect();
+    let moves = plan(&base_versions, &local_versions, &options.base);
+    // Only references that can only mean the branch's own migration are
+    // rewritten; a collided version also names the base branch's migration.
+    let (collided, mapping): (Vec<_>, Vec<_>) = moves
+        .iter()
+        .map(|(old, new, _)| (old.clone(), new.clone()))
+        .partition(|(old, _)| base_versions.iter().any(|b| b.cmp(old).is_eq()));
+    let collided: Vec<&MigrationVersion> = collided.iter().map(|(old, _)| old).collect();
+
+    let mut renumbered = Vec::new();
+    for (old, new, reason) in &moves {
+        let (_, script, _) = local
+            .iter()
+            .find(|l| l.2.raw == old.raw)
+            .expect("planned versions come from local");
+        renumbered.push(Renumbered {
+            from: old.raw.clone(),
+            to: new.clone(),
+            new_script: renamed(script, &old.raw, new),
+            script: script.clone(),
+            undo_script: undo_scripts.get(&old.raw).map(|(_, s)| s.clone()),
+            reason: reason.clone(),
+        });
+    }
+
+    // Edit contents first, under the old names, then rename.
+    let new_name = |script: &str| -> String {
+        renumbered
+            .iter()
+            .find_map(|r| {
+                if r.script == script {
+                    Some(r.new_script.clone())
+                } else {
+                    r.undo_script
+                        .as_deref()
+                        .filter(|u| *u == script)
+                        .map(|u| renamed(u, &r.from, &r.to))
+                }
+            })
+            .unwrap_or_else(|| script.to_string())
+    };
+    let mut edits = Vec::new();
+    let mut depends_updated = Vec::new();
+    let mut depends_ambiguous = Vec::new();
+    for (location, script) in &local_files {
+        let path = location.join(script);
+        let sql = std::fs::read_to_string(&path)?;
+        if depends_on_any(&sql, &collided) {
+            depends_ambiguous.push(new_name(script));
+        }
+        if let Some(updated) = rewrite_depends(&sql, &mapping) {
+            edits.push((path, updated));
+            depends_updated.push(new_name(script));
+        }
+    }
+
+    // Highest first: every version moves up, possibly onto the next one.
+    let mut renames = Vec::new();
+    for r in renumbered.iter().rev() {
+        let (location, _, _) = local.iter().find(|l| l.1 == r.script).unwrap();
+        renames.push((location.join(&r.script), location.join(&r.new_script)));
+        if let Some((undo_location, undo)) = undo_scripts.get(&r.from) {
+            renames.push((
+                undo_location.join(undo),
+                undo_location.join(renamed(undo, &r.from, &r.to)),
+            ));
+        }
+    }
+    check_renames(&renames)?;
+
+    if !options.dry_run {
+        for (path, updated) in edits {
+            std::fs::write(&path, updated)?;
+        }
+        for (from, to) in renames {
+            std::fs::rename(&from, &to)?;
+        }
+    }
+
+    Ok(RebaseReport {
+        base: options.base.clone(),
+        base_latest: base_versions.iter().max().map(|v| v.raw.clone()),
+        local: local.into_iter().map(|l| l.1).collect(),
+        renumbered,
+        depends_updated,
+        depends_ambiguous,
+        dry_run: options.dry_run,
+    })
+}
+
+/// Fail, before anything is written, when a rename in `renames` (applied in
+/// order) would overwrite a file or its source is missing.
+fn check_renames(renames: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let mut vacated: HashSet<&PathBuf> = HashSet::new();
+    let mut created: HashSet<&PathBuf> = HashSet::new();
+    let occupied = |path: &PathBuf, vacated: &HashSet<&PathBuf>, created: &HashSet<&PathBuf>| {
+        created.contains(path) || (path.exists() && !vacated.contains(path))
+    };
+    for (from, to) in renames {
+        if !occupied(from, &vacated, &created) {
+            return Err(WaypointError::ConfigError(format!(
+                "Cannot rename {}: the file does not exist",
+                from.display()
+            )));
+        }
+        if occupied(to, &vacated, &created) {
+            return Err(WaypointError::ConfigError(format!(
+                "Cannot rename {} to {}: the file already exists",
+                from.display(),
+                to.display()
+            )));
+        }
+        created.remove(from);
+        vacated.insert(from);
+        created.insert(to);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(raw: &[&str]) -> Vec<MigrationVersion> {
+        raw.iter()
+            .map(|v| MigrationVersion::parse(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_renumbers_after_base() {
+        let moves = |base: &[&str], local: &[&str]| -> Vec<(String, String)> {
+            plan(&versions(base), &versions(local), "main")
+                .into_iter()
+                .map(|(old, new, _)| (old.raw, new))
+                .collect()
+        };
+
+        // Nothing to do when the branch is already ahead.
+        assert!(moves(&["1", "2"], &["3", "4"]).is_empty());
+        assert!(moves(&[], &["1"]).is_empty());
+
+        assert_eq!(
+            moves(&["1", "2", "3", "4"], &["3", "5"]),
+            vec![("3".into(), "5".into()), ("5".into(), "6".into())]
+        );
+        // A later branch version already past the new numbers stays put.
+        assert_eq!(
+            moves(&["1", "2", "3"], &["3", "9"]),
+            vec![("3".into(), "4".into())]
+        );
+        // Padding and separators follow the base branch's newest version.
+        assert_eq!(
+            moves(&["001", "002", "003"], &["002"]),
+            vec![("002".into(), "004".into())]
+        );
+
+        let reasons: Vec<String> =
+            plan(&versions(&["1", "4"]), &versions(&["2", "4", "5"]), "main")
+                .into_iter()
+                .map(|m| m.2)
+                .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "precedes V4 on main",
+                "collides with V4 on main",
+                "keeps branch order"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rewrite_depends() {
+        let mapping = vec![(MigrationVersion::parse("3").unwrap(), "5".to_string())];
+        let sql = "-- waypoint:depends V2, V3\n-- waypoint:env dev\nCREATE TABLE t (id int);\n-- waypoint:depends 3\n";
+        assert_eq!(
+            rewrite_depends(sql, &mapping).unwrap(),
+            "-- waypoint:depends V2, V5\n-- waypoint:env dev\nCREATE TABLE t (id int);\n-- waypoint:depends 3\n"
+        );
+        assert!(rewrite_depends("-- waypoint:depends V2\nSELECT 1;", &mapping).is_none());
+        assert!(rewrite_depends("SELECT 1;", &mapping).is_none());
+    }
+
+    #[test]
+    fn test_execute_renames_branch_migrations() {
+        let repo = tempfile::tempdir().unwrap();
+        let db = repo.path().join("db");
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(repo.path())
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "--quiet", "--initial-branch=main"]);
+        std::fs::create_dir_all(&db).unwrap();
+        std::fs::write(db.join("V1__Init.sql"), "SELECT 1;").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        git(&["branch", "feature"]);
+        std::fs::write(db.join("V2__Main.sql"), "SELECT 2;").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "main"]);
+        git(&["checkout", "--quiet", "feature"]);
+        std::fs::write(db.join("V2__Feature.sql"), "SELECT 2;").unwrap();
+        std::fs::write(db.join("U2__Feature.sql"), "SELECT 2;").unwrap();
+        std::fs::write(db.join("V3__More.sql"), "-- waypoint:depends V2\nSELECT 3;").unwrap();
+        std::fs::write(db.join("V4__Last.sql"), "-- waypoint:depends V3\nSELECT 4;").unwrap();
+
+        let mut config = WaypointConfig::default();
+        config.migrations.locations = vec![db.clone()];
+        let options = |dry_run| RebaseOptions {
+            base: "main".into(),
+            dry_run,
+        };
+
+        // Something already at a target stops the rebase before anything changes.
+        std::fs::create_dir(db.join("V5__Last.sql")).unwrap();
+        assert!(matches!(
+            execute(&config, &options(false)),
+            Err(WaypointError::ConfigError(_))
+        ));
+        assert!(db.join("V2__Feature.sql").is_file());
+        assert!(!db.join("V3__Feature.sql").exists());
+        std::fs::remove_dir(db.join("V5__Last.sql")).unwrap();
+
+        let report = execute(&config, &options(false)).unwrap();
+        let mapping: Vec<(&str, &str)> = report
+            .renumbered
+            .iter()
+            .map(|r| (r.from.as_str(), r.to.as_str()))
+            .collect();
+        assert_eq!(mapping, vec![("2", "3"), ("3", "4"), ("4", "5")]);
+        for name in [
+            "V1__Init.sql",
+            "V3__Feature.sql",
+            "U3__Feature.sql",
+            "V4__More.sql",
+            "V5__Last.sql",
+        ] {
+            assert!(db.join(name).is_file(), "{} missing", name);
+        }
+        assert!(!db.join("V2__Feature.sql").exists());
+        assert!(!db.join("U2__Feature.sql").exists());
+        // V2 also names main's migration, so it is reported, not rewritten.
+        assert_eq!(report.depends_ambiguous, vec!["V4__More.sql"]);
+        assert_eq!(
+            std::fs::read_to_string(db.join("V4__More.sql")).unwrap(),
+            "-- waypoint:depends V2\nSELECT 3;"
+        );
+        assert_eq!(report.depends_updated, vec!["V5__Last.sql"]);
+        assert_eq!(
+            std::fs::read_to_string(db.join("V5__Last.sql")).unwrap(),
+            "-- waypoint:depends V4\nSELECT 4;"
+        );
+    }
+}
@@ -0,0 +1,730 @@
+//! Reconcile the history table with changes applied outside waypoint.
+//!
+//! For each pending versioned migration, `waypoint reconcile` derives
+//! ensure-style guard checks from its DDL (`CREATE TABLE users` →
+//! `table_exists("users")`, `DROP INDEX i` → `NOT index_exists("i")`, …) plus
+//! its own `-- waypoint:ensure` directives, and evaluates them. Migrations
+//! whose checks all pass are recorded as applied with type `RECONCILED`, so
+//! migrate no longer fails on duplicate objects. Migrations only partly
+//! present, with nothing to check, or with statements no check covers
+//! (`INSERT`, `ALTER COLUMN`, ...) are reported and left pending.
+
+use serde::Serialize;
+
+use crate::checksum::ChecksumAlgorithm;
+use crate::commands::migrate::should_run_in_environment;
+use crate::config::WaypointConfig;
+use crate::db::{DbClient, LockTarget};
+use crate::dialect::DialectKind;
+use crate::error::Result;
+use crate::guard;
+use crate::history;
+use crate::migration::ResolvedMigration;
+use crate::placeholder::{build_placeholders, replace_placeholders};
+use crate::sql_parser::split_statements;
+
+/// History `migration_type` of a migration recorded by reconcile.
+pub const RECONCILED_TYPE: &str = "RECONCILED";
+
+/// What reconcile found for a pending migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileStatus {
+    /// Every check passed: the migration's effects are present.
+    Reconciled,
+    /// Some checks passed: applied in part, needs a manual look.
+    Partial,
+    /// No check passed: genuinely pending.
+    NotApplied,
+    /// No checks could be derived (e.g. data-only migrations).
+    Unverifiable,
+    /// Every check passed, but some statements can't be checked, so the
+    /// migration may be only partly applied.
+    Unverified,
+}
+
+/// A single derived check and its outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileCheck {
+    /// Guard expression, e.g. `table_exists("users")`.
+    pub expression: String,
+    /// Whether it held.
+    pub passed: bool,
+}
+
+/// Reconcile result for one pending migration.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileEntry {
+    /// Migration version.
+    pub version: String,
+    /// Migration description.
+    pub description: String,
+    /// Migration filename.
+    pub script: String,
+    /// Outcome.
+    pub status: ReconcileStatus,
+    /// Checks evaluated, in order.
+    pub checks: Vec<ReconcileCheck>,
+    /// Statements no check covers, shortened.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unverified: Vec<String>,
+}
+
+/// Result of a reconcile run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileReport {
+    /// One entry per pending versioned migration, in version order.
+    pub migrations: Vec<ReconcileEntry>,
+    /// Migrations recorded (or, for a dry run, that would be recorded) as applied.
+    pub reconciled: usize,
+    /// Whether the history table was left untouched.
+    pub dry_run: bool,
+}
+
+/// Guard checks derived from a migration, and the statements they don't cover.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DerivedChecks {
+    /// Guard expressions that hold once the migration has been applied.
+    pub checks: Vec<String>,
+    /// Statements whose effect can't be checked (data changes, column
+    /// alterations, unnamed constraints, objects in another schema).
+    pub unverified: Vec<String>,
+}
+
+/// Derive guard expressions whose truth means `sql` has already been applied
+/// to `schema`. `-- waypoint:ensure` directives come first, then one check
+/// per object a statement creates or drops. Statements with no observable
+/// result to check are listed in `unverified`; transaction control and `SET`
+/// are skipped.
+pub fn derive_checks(
+    migration: &ResolvedMigration,
+    sql: &str,
+    dialect: DialectKind,
+    schema: &str,
+) -> DerivedChecks {
+    let mut derived = DerivedChecks {
+        checks: migration
+            .directives
+            .ensure
+            .iter()
+            .map(|guard| guard.expression.clone())
+            .collect(),
+        unverified: Vec::new(),
+    };
+    for stmt in split_statements(sql) {
+        let tokens = tokenize(stmt, dialect);
+        if tokens.is_empty() {
+            continue;
+        }
+        match statement_checks(&tokens, dialect, schema) {
+            Some(checks) => {
+                for check in checks {
+                    if !derived.checks.contains(&check) {
+                        derived.checks.push(check);
+                    }
+                }
+            }
+            None => derived.unverified.push(preview(stmt)),
+        }
+    }
+    derived
+}
+
+fn preview(stmt: &str) -> String {
+    let stmt = stmt
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with("--"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    match stmt.char_indices().nth(60) {
+        Some((idx, _)) => format!("{}...", &stmt[..idx]),
+        None => stmt,
+    }
+}
+
+/// A lexical token of a statement head. Unquoted words keep their spelling;
+/// `Quoted` holds a `"..."` or `` `...` `` identifier without the quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Punct(char),
+}
+
+fn tokenize(stmt: &str, dialect: DialectKind) -> Vec<Token> {
+    let chars: Vec<char> = stmt.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || (c == '`' && dialect == DialectKind::Mysql) || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == c {
+                    // A doubled quote is an escaped one.
+                    if chars.get(i + 1) == Some(&c) {
+                        text.push(c);
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                text.push(chars[i]);
+                i += 1;
+            }
+            i += 1;
+            tokens.push(if c == '\'' {
+                Token::Punct('\'')
+            } else {
+                Token::Quoted(text)
+            });
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// A cursor over a statement's tokens.
+struct Cursor<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    dialect: DialectKind,
+}
+
+impl Cursor<'_> {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Consume `keywords` if they all come next, in order.
+    fn keywords(&mut self, keywords: &[&str]) -> bool {
+        let matched = keywords.iter().enumerate().all(|(i, k)| {
+            matches!(self.tokens.get(self.pos + i), Some(Token::Word(w)) if w.eq_ignore_ascii_case(k))
+        });
+        if matched {
+            self.pos += keywords.len();
+        }
+        matched
+    }
+
+    fn punct(&mut self, c: char) -> bool {
+        let matched = self.tokens.get(self.pos) == Some(&Token::Punct(c));
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    /// An identifier as the server stores it: PostgreSQL folds unquoted
+    /// names to lower case.
+    fn ident(&mut self) -> Option<String> {
+        let name = match self.tokens.get(self.pos)? {
+            Token::Word(w) if self.dialect == DialectKind::Postgres => w.to_lowercase(),
+            Token::Word(w) => w.clone(),
+            Token::Quoted(q) => q.clone(),
+            Token::Punct(_) => return None,
+        };
+        self.pos += 1;
+        Some(name)
+    }
+
+    /// A possibly schema-qualified name.
+    fn qualified(&mut self) -> Option<(Option<String>, String)> {
+        let first = self.ident()?;
+        if self.punct('.') {
+            Some((Some(first), self.ident()?))
+        } else {
+            Some((None, first))
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.tokens[self.pos..]
+            .iter()
+            .all(|t| *t == Token::Punct(';'))
+    }
+}
+
+/// Guard checks proving `tokens` (one statement) took effect in `schema`:
+/// `Some(vec![])` for statements that leave nothing behind, `None` when the
+/// effect can't be checked.
+fn statement_checks(tokens: &[Token], dialect: DialectKind, schema: &str) -> Option<Vec<String>> {
+    let mut c = Cursor {
+        tokens,
+        pos: 0,
+        dialect,
+    };
+    // Names in another schema are invisible to guards, which look at `schema`.
+    let local = |qualifier: &Option<String>| match qualifier {
+        None => true,
+        Some(s) if dialect == DialectKind::Mysql => s.eq_ignore_ascii_case(schema),
+        Some(s) => s == schema,
+    };
+    let quoted = |name: &str| (!name.contains(['"', '\\'])).then(|| format!("\"{}\"", name));
+
+    for keyword in ["BEGIN", "COMMIT", "END", "START", "SET", "RESET"] {
+        if c.peek_keyword(keyword) {
+            return Some(Vec::new());
+        }
+    }
+
+    if c.keywords(&["CREATE"]) {
+        c.keywords(&["OR", "REPLACE"]);
+        if c.keywords(&["TEMP"]) || c.keywords(&["TEMPORARY"]) {
+            return Some(Vec::new());
+        }
+        if c.keywords(&["TABLE"]) || c.keywords(&["UNLOGGED", "TABLE"]) {
+            c.keywords(&["IF", "NOT", "EXISTS"]);
+            let (qualifier, table) = c.qualified()?;
+            return local(&qualifier).then_some(vec![format!("table_exists({})", quoted(&table)?)]);
+        }
+        c.keywords(&["UNIQUE"]);
+        if c.keywords(&["INDEX"]) {
+            c.keywords(&["CONCURRENTLY"]);
+            c.keywords(&["IF", "NOT", "EXISTS"]);
+            // An unnamed index gets a generated name we can't predict.
+            if c.peek_keyword("ON") {
+                return None;
+            }
+            let name = c.ident()?;
+            if !c.keywords(&["ON"]) {
+                return None;
+            }
+            c.keywords(&["ONLY"]);
+            let (qualifier, _) = c.qualified()?;
+            return local(&qualifier).then_some(vec![format!("index_exists({})", quoted(&name)?)]);
+        }
+        if c.keywords(&["VIEW"]) {
+            c.keywords(&["IF", "NOT", "EXISTS"]);
+            let (qualifier, view) = c.qualified()?;
+            // Views are listed in information_schema.tables on both engines.
+            return local(&qualifier).then_some(vec![format!("table_exists({})", quoted(&view)?)]);
+        }
+        if c.keywords(&["FUNCTION"]) {
+            let (qualifier, function) = c.qualified()?;
+            return local(&qualifier)
+                .then_some(vec![format!("function_exists({})", quoted(&function)?)]);
+        }
+        if dialect == DialectKind::Postgres && c.keywords(&["TYPE"]) {
+            let (qualifier, name) = c.qualified()?;
+            if !c.keywords(&["AS", "ENUM"]) {
+                return None;
+            }
+            return local(&qualifier).then_some(vec![format!("enum_exists({})", quoted(&name)?)]);
+        }
+        return None;
+    }
+
+    if c.keywords(&["DROP"]) {
+        let check = if c.keywords(&["TABLE"]) || c.keywords(&["VIEW"]) {
+            "table_exists"
+        } else if c.keywords(&["INDEX"]) {
+            c.keywords(&["CONCURRENTLY"]);
+            "index_exists"
+        } else if c.keywords(&["FUNCTION"]) {
+            "function_exists"
+        } else {
+            return None;
+        };
+        c.keywords(&["IF", "EXISTS"]);
+        let mut checks = Vec::new();
+        loop {
+            let (qualifier, name) = c.qualified()?;
+            if !local(&qualifier) {
+                return None;
+            }
+            checks.push(format!("NOT {}({})", check, quoted(&name)?));
+            // Skip a function signature.
+            if c.punct('(') {
+                let mut depth = 1;
+                while depth > 0 {
+                    match c.tokens.get(c.pos)? {
+                        Token::Punct('(') => depth += 1,
+                        Token::Punct(')') => depth -= 1,
+                        _ => {}
+                    }
+                    c.pos += 1;
+                }
+            }
+            if !c.punct(',') {
+                break;
+            }
+        }
+        c.keywords(&["CASCADE"]);
+        c.keywords(&["RESTRICT"]);
+        return c.at_end().then_some(checks);
+    }
+
+    if c.keywords(&["ALTER", "TABLE"]) {
+        c.keywords(&["IF", "EXISTS"]);
+        c.keywords(&["ONLY"]);
+        let (qualifier, table) = c.qualified()?;
+        if !local(&qualifier) {
+            return None;
+        }
+        let table = quoted(&table)?;
+        let mut checks = Vec::new();
+        for action in split_actions(&tokens[c.pos..]) {
+            let mut a = Cursor {
+                tokens: action,
+                pos: 0,
+                dialect,
+            };
+            let check = if a.keywords(&["ADD", "CONSTRAINT"]) {
+                format!("constraint_exists({}, {})", table, quoted(&a.ident()?)?)
+            } else if a.keywords(&["DROP", "CONSTRAINT"]) {
+                a.keywords(&["IF", "EXISTS"]);
+                format!("NOT constraint_exists({}, {})", table, quoted(&a.ident()?)?)
+            } else if a.keywords(&["ADD"]) {
+                const CONSTRAINTS: [&str; 8] = [
+                    "PRIMARY", "UNIQUE", "FOREIGN", "CHECK", "EXCLUDE", "INDEX", "KEY", "FULLTEXT",
+                ];
+                if CONSTRAINTS.iter().any(|k| a.peek_keyword(k)) {
+                    return None;
+                }
+                a.keywords(&["COLUMN"]);
+                a.keywords(&["IF", "NOT", "EXISTS"]);
+                format!("column_exists({}, {})", table, quoted(&a.ident()?)?)
+            } else if a.keywords(&["DROP"]) {
+                const OTHERS: [&str; 4] = ["PRIMARY", "FOREIGN", "INDEX", "KEY"];
+                if OTHERS.iter().any(|k| a.peek_keyword(k)) {
+                    return None;
+                }
+                a.keywords(&["COLUMN"]);
+                a.keywords(&["IF", "EXISTS"]);
+                format!("NOT column_exists({}, {})", table, quoted(&a.ident()?)?)
+            } else {
+                return None;
+            };
+            checks.push(check);
+        }
+        return (!checks.is_empty()).then_some(checks);
+    }
+
+    None
+}
+
+/// Split an `ALTER TABLE` action list on its top-level commas.
+fn split_actions(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut actions = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Punct('(') => depth += 1,
+            Token::Punct(')') => depth = depth.saturating_sub(1),
+            Token::Punct(',' | ';') if depth == 0 => {
+                if i > start {
+                    actions.push(&tokens[start..i]);
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < tokens.len() {
+        actions.push(&tokens[start..]);
+    }
+    actions
+}
+
+/// Check every pending versioned migration and, unless `dry_run`, record
+/// those already present as applied.
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    dry_run: bool,
+) -> Result<ReconcileReport> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+
+    if dry_run {
+        return run_reconcile(client, config, &schema, dry_run).await;
+    }
+
+    let lock = LockTarget::new(&config.migrations, &schema);
+    client.acquire_lock(&lock).await?;
+
+    let result = run_reconcile(client, config, &schema, dry_run).await;
+
+    if let Err(e) = client.release_lock(&lock).await {
+        log::error!("Failed to release advisory lock: {}", e);
+    }
+
+    if let Ok(report) = &result {
+        log::info!(
+            "Reconcile completed; reconciled={}, pending_checked={}, table={}.{}",
+            report.reconciled,
+            report.migrations.len(),
+            schema,
+            table
+        );
+    }
+    result
+}
+
+async fn run_reconcile(
+    client: &DbClient,
+    config: &WaypointConfig,
+    schema: &str,
+    dry_run: bool,
+) -> Result<ReconcileReport> {
+    let table = &config.migrations.table;
+
+    let applied = if history::history_table_exists_db(client, schema, table).await? {
+        history::get_applied_migrations_db(client, schema, table).await?
+    } else {
+        Vec::new()
+    };
+    let effective = history::effective_applied_versions(&applied);
+    let baseline_version = applied
+        .iter()
+        .find(|a| a.migration_type == "BASELINE")
+        .and_then(|a| a.version.as_ref())
+        .and_then(|v| config.migrations.parse_version(v).ok());
+
+    let resolved = config.migrations.resolve()?;
+    let current_env = config.migrations.environment.as_deref();
+    let mut pending: Vec<&ResolvedMigration> = resolved
+        .iter()
+        .filter(|m| m.is_versioned())
+        .filter(|m| should_run_in_environment(&m.directives, current_env))
+        .filter(|m| {
+            let version = m.version().expect("versioned");
+            !effective.contains(&version.raw)
+                && baseline_version.as_ref().is_none_or(|bv| version > bv)
+        })
+        .collect();
+    pending.sort_by(|a, b| a.version().cmp(&b.version()));
+
+    let db_user = client
+        .current_user()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+    let db_name = client
+        .current_database()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+    let installed_by = config
+        .migrations
+        .installed_by
+        .as_deref()
+        .unwrap_or(&db_user)
+        .to_string();
+
+    let mut report = ReconcileReport {
+        migrations: Vec::new(),
+        reconciled: 0,
+        dry_run,
+    };
+
+    for migration in pending {
+        let placeholders = build_placeholders(
+            &config.placeholders,
+            schema,
+            &db_user,
+            &db_name,
+            &migration.script,
+        );
+        let sql = replace_placeholders(&migration.sql, &placeholders)?;
+
+        let derived = derive_checks(migration, &sql, client.dialect_kind(), schema);
+        let mut checks = Vec::new();
+        for expression in derived.checks {
+            let expr = guard::parse(&expression)?;
+            let passed = guard::evaluate_db(client, schema, &expr).await?;
+            checks.push(ReconcileCheck { expression, passed });
+        }
+
+        let passed = checks.iter().filter(|c| c.passed).count();
+        let status = if checks.is_empty() {
+            ReconcileStatus::Unverifiable
+        } else if passed == checks.len()
+            && !derived.unverified.is_empty()
+            && migration.directives.ensure.is_empty()
+        {
+            // `ensure` directives are the author's word for what no derived
+            // check can see.
+            ReconcileStatus::Unverified
+        } else if passed == checks.len() {
+            ReconcileStatus::Reconciled
+        } else if passed > 0 {
+            ReconcileStatus::Partial
+        } else {
+            ReconcileStatus::NotApplied
+        };
+
+        let version = &migration.version().expect("versioned").raw;
+        if status == ReconcileStatus::Reconciled {
+            if !dry_run {
+                if report.reconciled == 0 {
+                    history::create_history_table_db(client, schema, table).await?;
+                }
+                history::insert_applied_migration_db(
+                    client,
+                    schema,
+                    table,
+                    Some(version),
+                    &migration.description,
+                    RECONCILED_TYPE,
+                    &migration.script,
+                    Some(migration.checksum),
+                    &installed_by,
+                    0,
+                    true,
+                )
+                .await?;
+                if config.migrations.checksum_algorithm == ChecksumAlgorithm::Sha256 {
+                    history::update_checksum_sha256_db(
+                        client,
+                        schema,
+                        table,
+                        &migration.script,
+                        &migration.checksum_sha256(),
+                    )
+                    .await?;
+                }
+                log::info!(
+                    "Recorded migration as reconciled; migration={}, schema={}",
+                    migration.script,
+                    schema
+                );
+            }
+            report.reconciled += 1;
+        }
+
+        report.migrations.push(ReconcileEntry {
+            version: version.clone(),
+            description: migration.description.clone(),
+            script: migration.script.clone(),
+            status,
+            checks,
+            unverified: derived.unverified,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directive::{GuardDirective, MigrationDirectives};
+    use crate::migration::{MigrationKind, MigrationVersion};
+
+    fn migration(sql: &str, ensure: &[&str]) -> ResolvedMigration {
+        ResolvedMigration {
+            kind: MigrationKind::Versioned(MigrationVersion::parse("1").unwrap()),
+            description: "Users".to_string(),
+            script: "V1__Users.sql".to_string(),
+            checksum: 0,
+            sql: sql.to_string(),
+            directives: MigrationDirectives {
+                ensure: ensure.iter().map(|e| GuardDirective::parse(e)).collect(),
+                ..Default::default()
+            },
+            copy_checksum_sha256: None,
+        }
+    }
+
+    #[test]
+    fn test_derive_checks_from_ddl_and_ensure() {
+        let sql = "CREATE TABLE users (id INT);\n\
+                   ALTER TABLE users ADD COLUMN email TEXT;\n\
+                   CREATE INDEX users_email_idx ON users (email);\n\
+                   DROP TABLE legacy_users;\n\
+                   CREATE TYPE mood AS ENUM ('happy');";
+        let m = migration(sql, &["table_exists(\"users\")"]);
+
+        let derived = derive_checks(&m, sql, DialectKind::Postgres, "public");
+        assert_eq!(
+            derived.checks,
+            vec![
+                "table_exists(\"users\")",
+                "column_exists(\"users\", \"email\")",
+                "index_exists(\"users_email_idx\")",
+                "NOT table_exists(\"legacy_users\")",
+                "enum_exists(\"mood\")",
+            ]
+        );
+        assert!(derived.unverified.is_empty());
+        // MySQL has no enum types to look up.
+        let derived = derive_checks(&m, sql, DialectKind::Mysql, "app");
+        assert_eq!(derived.checks.len(), 4);
+        assert_eq!(derived.unverified.len(), 1);
+    }
+
+    #[test]
+    fn test_derive_checks_flags_unverifiable_statements() {
+        let sql = "BEGIN;\n\
+                   INSERT INTO users (id) VALUES (1);\n\
+                   UPDATE users SET email = 'x';\n\
+                   ALTER TABLE users ALTER COLUMN email SET NOT NULL;\n\
+                   ALTER TABLE users ADD PRIMARY KEY (id);\n\
+                   ALTER TABLE users ADD CONSTRAINT users_email_key UNIQUE (email);\n\
+                   COMMIT;";
+        let derived = derive_checks(&migration(sql, &[]), sql, DialectKind::Postgres, "public");
+        assert_eq!(
+            derived.checks,
+            vec!["constraint_exists(\"users\", \"users_email_key\")"]
+        );
+        assert_eq!(derived.unverified.len(), 4);
+        assert!(derived.unverified[0].starts_with("INSERT INTO users"));
+    }
+
+    #[test]
+    fn test_derive_checks_parses_qualified_and_quoted_names() {
+        let sql = r#"CREATE TABLE public."Users" (id INT);
+            CREATE TABLE "public".Orders (id INT);
+            ALTER TABLE ONLY public."Users" ADD COLUMN "Email" TEXT, DROP COLUMN legacy;
+            CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS "Users_Email" ON public."Users" (lower("Email"));
+            DROP TABLE IF EXISTS a, public.b CASCADE;
+            CREATE TABLE audit.events (id INT);"#;
+        let derived = derive_checks(&migration(sql, &[]), sql, DialectKind::Postgres, "public");
+        assert_eq!(
+            derived.checks,
+            vec![
+                "table_exists(\"Users\")",
+                "table_exists(\"orders\")",
+                "column_exists(\"Users\", \"Email\")",
+                "NOT column_exists(\"Users\", \"legacy\")",
+                "index_exists(\"Users_Email\")",
+                "NOT table_exists(\"a\")",
+                "NOT table_exists(\"b\")",
+            ]
+        );
+        // Guards only see the configured schema.
+        assert_eq!(
+            derived.unverified,
+            vec!["CREATE TABLE audit.events (id INT)"]
+        );
+
+        let sql = "ALTER TABLE `app`.`Users` ADD COLUMN `Email` TEXT;";
+        let derived = derive_checks(&migration(sql, &[]), sql, DialectKind::Mysql, "app");
+        assert_eq!(derived.checks, vec!["column_exists(\"Users\", \"Email\")"]);
+    }
+}
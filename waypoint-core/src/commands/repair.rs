@@ -1,19 +1,20 @@
 //! Repair the schema history table (remove failed entries, update checksums).
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use serde::Serialize;
 
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
+use crate::checksum::ChecksumAlgorithm;
 use crate::config::WaypointConfig;
 #[cfg(feature = "postgres")]
 use crate::db;
-use crate::db::DbClient;
+use crate::db::{DbClient, LockTarget};
 use crate::error::Result;
 use crate::history::{self, AppliedMigration};
-use crate::migration::{scan_migrations, ResolvedMigration};
+use crate::migration::ResolvedMigration;
 
 /// Report returned after a repair operation.
 #[derive(Debug, Serialize)]
@@ -31,12 +32,13 @@ pub struct RepairReport {
 pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<RepairReport> {
     let schema = &config.migrations.schema;
     let table = &config.migrations.table;
+    let lock = LockTarget::new(&config.migrations, schema);
 
-    db::acquire_advisory_lock(client, table).await?;
+    db::acquire_advisory_lock(client, &lock).await?;
 
     let result = execute_inner_pg(client, config, schema, table).await;
 
-    if let Err(e) = db::release_advisory_lock(client, table).await {
+    if let Err(e) = db::release_advisory_lock(client, &lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -54,9 +56,10 @@ async fn execute_inner_pg(
 
     let failed_removed = history::delete_failed_migrations(client, schema, table).await?;
     let applied = history::get_applied_migrations(client, schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
 
-    let (mut details, checksums_to_apply) = compute_repair(&applied, &resolved);
+    let (mut details, checksums_to_apply) =
+        compute_repair(&applied, &resolved, config.migrations.checksum_algorithm);
     if failed_removed > 0 {
         details.insert(0, format!("Removed {} failed migration(s)", failed_removed));
     }
@@ -69,6 +72,9 @@ async fn execute_inner_pg(
             RepairChecksum::Repeatable { script, new } => {
                 history::update_repeatable_checksum(client, schema, table, &script, new).await?;
             }
+            RepairChecksum::Sha256 { script, new } => {
+                history::update_checksum_sha256(client, schema, table, &script, &new).await?;
+            }
         }
         checksums_updated += 1;
     }
@@ -90,12 +96,13 @@ async fn execute_inner_pg(
 pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<RepairReport> {
     let schema = client.resolve_schema(&config.migrations.schema).await?;
     let table = &config.migrations.table;
+    let lock = LockTarget::new(&config.migrations, &schema);
 
-    client.acquire_lock(table).await?;
+    client.acquire_lock(&lock).await?;
 
     let result = execute_inner_db(client, config, &schema, table).await;
 
-    if let Err(e) = client.release_lock(table).await {
+    if let Err(e) = client.release_lock(&lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -112,9 +119,10 @@ async fn execute_inner_db(
 
     let failed_removed = history::delete_failed_migrations_db(client, schema, table).await?;
     let applied = history::get_applied_migrations_db(client, schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
 
-    let (mut details, checksums_to_apply) = compute_repair(&applied, &resolved);
+    let (mut details, checksums_to_apply) =
+        compute_repair(&applied, &resolved, config.migrations.checksum_algorithm);
     if failed_removed > 0 {
         details.insert(0, format!("Removed {} failed migration(s)", failed_removed));
     }
@@ -127,6 +135,9 @@ async fn execute_inner_db(
             RepairChecksum::Repeatable { script, new } => {
                 history::update_repeatable_checksum_db(client, schema, table, &script, new).await?;
             }
+            RepairChecksum::Sha256 { script, new } => {
+                history::update_checksum_sha256_db(client, schema, table, &script, &new).await?;
+            }
         }
         checksums_updated += 1;
     }
@@ -147,11 +158,16 @@ async fn execute_inner_db(
 enum RepairChecksum {
     Versioned { version: String, new: i32 },
     Repeatable { script: String, new: i32 },
+    Sha256 { script: String, new: String },
 }
 
+/// Work out the checksum rewrites. With `sha256`, rows whose SHA-256 is
+/// missing or stale also get the file's SHA-256, which is how tables move
+/// over from CRC32.
 fn compute_repair(
     applied: &[AppliedMigration],
     resolved: &[ResolvedMigration],
+    algorithm: ChecksumAlgorithm,
 ) -> (Vec<String>, Vec<RepairChecksum>) {
     let resolved_by_version: HashMap<String, &ResolvedMigration> = resolved
         .iter()
@@ -167,12 +183,34 @@ fn compute_repair(
 
     let mut details = Vec::new();
     let mut updates = Vec::new();
+    let mut sha_scripts = HashSet::new();
 
     for am in applied {
         if !am.success || am.migration_type == "BASELINE" {
             continue;
         }
 
+        let file = match am.version {
+            Some(ref version) => resolved_by_version.get(version),
+            None => resolved_by_script.get(&am.script),
+        };
+        let wants_sha = algorithm == ChecksumAlgorithm::Sha256 && am.migration_type != "UNDO_SQL";
+        if let Some(file) = file.filter(|_| wants_sha) {
+            let sha = file.checksum_sha256();
+            if am.checksum_sha256.as_deref() != Some(sha.as_str())
+                && sha_scripts.insert(am.script.clone())
+            {
+                details.push(match am.checksum_sha256 {
+                    Some(_) => format!("Updated SHA-256 checksum for '{}'", am.script),
+                    None => format!("Recorded SHA-256 checksum for '{}'", am.script),
+                });
+                updates.push(RepairChecksum::Sha256 {
+                    script: am.script.clone(),
+                    new: sha,
+                });
+            }
+        }
+
         if let Some(ref version) = am.version {
             if let Some(resolved) = resolved_by_version.get(version) {
                 if am.checksum != Some(resolved.checksum) {
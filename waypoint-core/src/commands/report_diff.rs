@@ -0,0 +1,411 @@
+//! Compare two JSON reports from different runs.
+//!
+//! `waypoint report diff before.json after.json` reads the `--json` output of
+//! `info`, `advise`, `safety` or `drift` from two runs (for example archived
+//! by CI) and summarizes what changed: items that appeared, disappeared or
+//! changed, and the totals that moved. The report kind is detected from the
+//! JSON's shape; both files must be the same kind.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{Result, WaypointError};
+
+/// Which command produced a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportKind {
+    /// `waypoint info --json`.
+    Info,
+    /// `waypoint advise --json`.
+    Advisor,
+    /// `waypoint safety --json`.
+    Safety,
+    /// `waypoint drift --json`.
+    Drift,
+}
+
+impl std::fmt::Display for ReportKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportKind::Info => write!(f, "info"),
+            ReportKind::Advisor => write!(f, "advisor"),
+            ReportKind::Safety => write!(f, "safety"),
+            ReportKind::Drift => write!(f, "drift"),
+        }
+    }
+}
+
+/// How an item differs between the two reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// Only in the second report.
+    Added,
+    /// Only in the first report.
+    Removed,
+    /// In both, with different content.
+    Changed,
+}
+
+/// One item that differs between the reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportChange {
+    /// Added, removed or changed.
+    pub change: ChangeKind,
+    /// The item: a migration script, advisory, or drifted object.
+    pub item: String,
+    /// What the item is (added/removed) or what changed about it.
+    pub detail: String,
+}
+
+/// A summary figure that moved between the reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportTotal {
+    /// Name of the figure, e.g. `pending` or `warnings`.
+    pub name: String,
+    /// Value in the first report.
+    pub before: String,
+    /// Value in the second report.
+    pub after: String,
+}
+
+/// Result of comparing two reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportDiff {
+    /// Kind of both reports.
+    pub kind: ReportKind,
+    /// Summary figures that differ.
+    pub totals: Vec<ReportTotal>,
+    /// Items that differ, sorted by item.
+    pub changes: Vec<ReportChange>,
+}
+
+impl ReportDiff {
+    /// Whether the reports differ at all.
+    pub fn has_changes(&self) -> bool {
+        !self.totals.is_empty() || !self.changes.is_empty()
+    }
+}
+
+/// Read and compare two JSON report files.
+pub fn execute(before: &Path, after: &Path) -> Result<ReportDiff> {
+    diff(&read_report(before)?, &read_report(after)?)
+}
+
+fn read_report(path: &Path) -> Result<Value> {
+    let text = std::fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|e| {
+        WaypointError::ConfigError(format!("{} is not a JSON report: {}", path.display(), e))
+    })
+}
+
+/// Detect which command produced a JSON report.
+pub fn detect_kind(report: &Value) -> Option<ReportKind> {
+    match report {
+        Value::Array(rows) => rows
+            .iter()
+            .all(|r| r.get("script").is_some() && r.get("state").is_some())
+            .then_some(ReportKind::Info),
+        Value::Object(map) => {
+            if map.contains_key("advisories") {
+                Some(ReportKind::Advisor)
+            } else if map.contains_key("drifts") {
+                Some(ReportKind::Drift)
+            } else if map.contains_key("overall_verdict")
+                && (map.contains_key("reports") || map.contains_key("statements"))
+            {
+                Some(ReportKind::Safety)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Compare two parsed JSON reports of the same kind.
+pub fn diff(before: &Value, after: &Value) -> Result<ReportDiff> {
+    let unknown = || {
+        WaypointError::ConfigError("Not an info, advise, safety or drift JSON report".to_string())
+    };
+    let kind = detect_kind(before).ok_or_else(unknown)?;
+    let after_kind = detect_kind(after).ok_or_else(unknown)?;
+    if kind != after_kind {
+        return Err(WaypointError::ConfigError(format!(
+            "Cannot compare reports of different kinds ({} and {})",
+            kind, after_kind
+        )));
+    }
+
+    let mut totals = Vec::new();
+    let items = |v: &Value,
+                 f: fn(&Value) -> Option<(String, String)>|
+     -> BTreeMap<String, String> { rows(kind, v).iter().filter_map(f).collect() };
+    let (old, new) = match kind {
+        ReportKind::Info => {
+            for state in ["Pending", "Applied", "Failed", "Missing", "Outdated"] {
+                let count = |v: &Value| {
+                    rows(kind, v)
+                        .iter()
+                        .filter(|r| text(r.get("state")) == state)
+                        .count()
+                };
+                total(
+                    &mut totals,
+                    &state.to_lowercase(),
+                    count(before),
+                    count(after),
+                );
+            }
+            (items(before, info_item), items(after, info_item))
+        }
+        ReportKind::Advisor => {
+            for (name, field) in [
+                ("warnings", "warning_count"),
+                ("suggestions", "suggestion_count"),
+                ("info", "info_count"),
+            ] {
+                total(
+                    &mut totals,
+                    name,
+                    text(before.get(field)),
+                    text(after.get(field)),
+                );
+            }
+            (items(before, advisory_item), items(after, advisory_item))
+        }
+        ReportKind::Safety => {
+            total(
+                &mut totals,
+                "overall verdict",
+                text(before.get("overall_verdict")),
+                text(after.get("overall_verdict")),
+            );
+            (items(before, safety_item), items(after, safety_item))
+        }
+        ReportKind::Drift => {
+            let count = |v: &Value| rows(kind, v).len();
+            total(&mut totals, "drifts", count(before), count(after));
+            (items(before, drift_item), items(after, drift_item))
+        }
+    };
+
+    let mut changes = Vec::new();
+    for (item, detail) in &old {
+        match new.get(item) {
+            None => changes.push(ReportChange {
+                change: ChangeKind::Removed,
+                item: item.clone(),
+                detail: detail.clone(),
+            }),
+            Some(now) if now != detail => changes.push(ReportChange {
+                change: ChangeKind::Changed,
+                item: item.clone(),
+                detail: format!("{} → {}", detail, now),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (item, detail) in &new {
+        if !old.contains_key(item) {
+            changes.push(ReportChange {
+                change: ChangeKind::Added,
+                item: item.clone(),
+                detail: detail.clone(),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.item.cmp(&b.item));
+
+    Ok(ReportDiff {
+        kind,
+        totals,
+        changes,
+    })
+}
+
+/// The per-item rows of a report.
+fn rows(kind: ReportKind, report: &Value) -> Vec<Value> {
+    let list = match kind {
+        ReportKind::Info => Some(report),
+        ReportKind::Advisor => report.get("advisories"),
+        ReportKind::Drift => report.get("drifts"),
+        // `safety --file` prints a single script's report.
+        ReportKind::Safety => match report.get("reports") {
+            Some(reports) => Some(reports),
+            None => return vec![report.clone()],
+        },
+    };
+    list.and_then(Value::as_array).cloned().unwrap_or_default()
+}
+
+/// Render a JSON scalar for display; missing and null become `-`.
+fn text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "-".to_string(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn total(totals: &mut Vec<ReportTotal>, name: &str, before: impl ToString, after: impl ToString) {
+    let (before, after) = (before.to_string(), after.to_string());
+    if before != after {
+        totals.push(ReportTotal {
+            name: name.to_string(),
+            before,
+            after,
+        });
+    }
+}
+
+fn info_item(row: &Value) -> Option<(String, String)> {
+    let script = row.get("script")?.as_str()?;
+    let item = match row.get("schema").and_then(Value::as_str) {
+        Some(schema) => format!("{}/{}", schema, script),
+        None => script.to_string(),
+    };
+    Some((
+        item,
+        format!(
+            "{} (checksum {})",
+            text(row.get("state")),
+            text(row.get("checksum"))
+        ),
+    ))
+}
+
+fn advisory_item(row: &Value) -> Option<(String, String)> {
+    Some((
+        format!("{} {}", text(row.get("rule_id")), text(row.get("object"))),
+        format!(
+            "[{}] {}",
+            text(row.get("severity")),
+            text(row.get("explanation"))
+        ),
+    ))
+}
+
+fn safety_item(row: &Value) -> Option<(String, String)> {
+    let statements = row.get("statements").and_then(Value::as_array);
+    let data_loss = statements
+        .map(|s| {
+            s.iter()
+                .filter(|st| st["data_loss"] == Value::Bool(true))
+                .count()
+        })
+        .unwrap_or(0);
+    Some((
+        row.get("script")?.as_str()?.to_string(),
+        format!(
+            "{}, {} statement(s), {} with data loss",
+            text(row.get("overall_verdict")),
+            statements.map_or(0, Vec::len),
+            data_loss
+        ),
+    ))
+}
+
+fn drift_item(row: &Value) -> Option<(String, String)> {
+    Some((
+        row.get("object")?.as_str()?.to_string(),
+        format!(
+            "{}: {}",
+            text(row.get("drift_type")),
+            text(row.get("detail"))
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect_kind() {
+        assert_eq!(
+            detect_kind(&json!([{"script": "V1__a.sql", "state": "Applied"}])),
+            Some(ReportKind::Info)
+        );
+        assert_eq!(detect_kind(&json!([])), Some(ReportKind::Info));
+        assert_eq!(
+            detect_kind(&json!({"advisories": [], "warning_count": 0})),
+            Some(ReportKind::Advisor)
+        );
+        assert_eq!(
+            detect_kind(&json!({"reports": [], "overall_verdict": "Safe"})),
+            Some(ReportKind::Safety)
+        );
+        assert_eq!(
+            detect_kind(&json!({"drifts": [], "has_drift": false})),
+            Some(ReportKind::Drift)
+        );
+        assert_eq!(detect_kind(&json!({"tables": []})), None);
+    }
+
+    #[test]
+    fn test_diff_info() {
+        let before = json!([
+            {"script": "V1__a.sql", "state": "Applied", "checksum": 1},
+            {"script": "V2__b.sql", "state": "Pending", "checksum": 2},
+            {"script": "V3__c.sql", "state": "Pending", "checksum": 3},
+        ]);
+        let after = json!([
+            {"script": "V1__a.sql", "state": "Applied", "checksum": 1},
+            {"script": "V2__b.sql", "state": "Applied", "checksum": 2},
+            {"script": "V4__d.sql", "state": "Pending", "checksum": 4},
+        ]);
+        let d = diff(&before, &after).unwrap();
+        assert_eq!(d.kind, ReportKind::Info);
+        let changes: Vec<(ChangeKind, &str)> = d
+            .changes
+            .iter()
+            .map(|c| (c.change, c.item.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (ChangeKind::Changed, "V2__b.sql"),
+                (ChangeKind::Removed, "V3__c.sql"),
+                (ChangeKind::Added, "V4__d.sql"),
+            ]
+        );
+        assert_eq!(
+            d.changes[0].detail,
+            "Pending (checksum 2) → Applied (checksum 2)"
+        );
+        let pending = d.totals.iter().find(|t| t.name == "pending").unwrap();
+        assert_eq!(
+            (pending.before.as_str(), pending.after.as_str()),
+            ("2", "1")
+        );
+    }
+
+    #[test]
+    fn test_diff_advisor_and_drift() {
+        let before = json!({"advisories": [
+            {"rule_id": "A001", "object": "orders.user_id", "severity": "warning", "explanation": "no index"},
+        ], "warning_count": 1, "suggestion_count": 0, "info_count": 0});
+        let after =
+            json!({"advisories": [], "warning_count": 0, "suggestion_count": 0, "info_count": 0});
+        let d = diff(&before, &after).unwrap();
+        assert_eq!(d.changes.len(), 1);
+        assert_eq!(d.changes[0].change, ChangeKind::Removed);
+        assert_eq!(d.changes[0].item, "A001 orders.user_id");
+        assert_eq!(d.totals[0].name, "warnings");
+
+        let drift = json!({"drifts": [], "has_drift": false, "schema": "public"});
+        assert!(!diff(&drift, &drift).unwrap().has_changes());
+    }
+
+    #[test]
+    fn test_diff_rejects_mismatched_kinds() {
+        let err = diff(&json!([]), &json!({"drifts": []})).unwrap_err();
+        assert!(err.to_string().contains("different kinds (info and drift)"));
+    }
+}
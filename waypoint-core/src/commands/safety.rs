@@ -61,13 +61,12 @@ fn filename_from_path(file_path: &str) -> String {
 #[cfg(feature = "postgres")]
 pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<SafetyCommandReport> {
     use crate::history;
-    use crate::migration::scan_migrations;
 
     let schema = &config.migrations.schema;
     let table = &config.migrations.table;
 
     history::create_history_table(client, schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let applied = history::get_applied_migrations(client, schema, table).await?;
     let effective = history::effective_applied_versions(&applied);
 
@@ -108,13 +107,12 @@ pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<SafetyC
 /// Analyze all pending migration files for safety (dialect-aware entry).
 pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<SafetyCommandReport> {
     use crate::history;
-    use crate::migration::scan_migrations;
 
     let schema = client.resolve_schema(&config.migrations.schema).await?;
     let table = &config.migrations.table;
 
     history::create_history_table_db(client, &schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let applied = history::get_applied_migrations_db(client, &schema, table).await?;
     let effective = history::effective_applied_versions(&applied);
 
@@ -0,0 +1,382 @@
+//! Scheduled migration runs for maintenance windows.
+//!
+//! `waypoint schedule --at <time>` records a pending run in a control table
+//! (`<history_table>_schedule`) next to the schema history table.
+//! `waypoint run-scheduled`, invoked from cron or a Kubernetes CronJob, claims
+//! the earliest due entry and runs `migrate` for it. Claims use
+//! `FOR UPDATE SKIP LOCKED`, so overlapping runners never pick up the same
+//! entry, and entries not started before their window closes are marked
+//! `expired` instead of running late. A claim expires after
+//! `schedule_claim_ttl_secs`, so an entry whose runner crashed goes back to
+//! `pending` rather than staying `running` forever.
+
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::{Client, Row};
+
+use crate::commands::migrate::MigrateReport;
+use crate::config::WaypointConfig;
+#[cfg(feature = "postgres")]
+use crate::db::quote_ident;
+use crate::db::DbClient;
+use crate::dialect::DialectKind;
+use crate::error::{Result, WaypointError};
+
+/// Default for `[migrations] schedule_claim_ttl_secs`.
+pub const DEFAULT_CLAIM_TTL_SECS: u32 = 6 * 3600;
+
+/// Name of the schedule control table for a given history table.
+pub fn schedule_table_name(history_table: &str) -> String {
+    format!("{}_schedule", history_table)
+}
+
+/// A scheduled migration run, as stored in the control table.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledRun {
+    /// Identifier used by `schedule --cancel`.
+    pub id: i64,
+    /// Migrate up to this version only, or all pending migrations.
+    pub target_version: Option<String>,
+    /// Earliest time the run may start.
+    pub run_at: String,
+    /// The run is expired if not started by this time.
+    pub expires_at: String,
+    /// `pending`, `running`, `succeeded`, `failed`, `expired`, or `cancelled`.
+    pub status: String,
+    /// Database user that scheduled the run.
+    pub created_by: String,
+    /// Outcome summary or error message once the run has finished.
+    pub message: Option<String>,
+}
+
+/// Report returned by `run-scheduled`.
+#[derive(Debug, Serialize)]
+pub struct RunScheduledReport {
+    /// Running entries whose claim had expired, put back to `pending`.
+    pub reclaimed: u64,
+    /// Pending entries marked expired during this invocation.
+    pub expired: u64,
+    /// The entry that was run, if any was due.
+    pub run: Option<ScheduledRun>,
+    /// The migrate report for that entry.
+    pub migrate: Option<MigrateReport>,
+}
+
+/// Register a scheduled run. `at` is any timestamp PostgreSQL accepts
+/// (e.g. `2024-07-01T02:00Z`); the entry expires `window_minutes` later.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn schedule_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    at: &str,
+    target_version: Option<&str>,
+    window_minutes: u32,
+) -> Result<ScheduledRun> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => {
+            schedule(
+                client.as_postgres()?,
+                config,
+                at,
+                target_version,
+                window_minutes,
+            )
+            .await
+        }
+        _ => Err(unsupported(client.dialect_kind())),
+    }
+}
+
+/// List scheduled runs, newest first. Read-only: without a control table
+/// there are none.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn list_db(client: &DbClient, config: &WaypointConfig) -> Result<Vec<ScheduledRun>> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => list(client.as_postgres()?, config).await,
+        _ => Err(unsupported(client.dialect_kind())),
+    }
+}
+
+/// Cancel a pending scheduled run. Does not create the control table.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn cancel_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    id: i64,
+) -> Result<ScheduledRun> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => cancel(client.as_postgres()?, config, id).await,
+        _ => Err(unsupported(client.dialect_kind())),
+    }
+}
+
+/// Expire overdue entries, then claim and run the earliest due entry.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn run_scheduled_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+) -> Result<RunScheduledReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => {
+            let started_at = chrono::Utc::now();
+            let mut report = run_scheduled(client.as_postgres()?, config).await?;
+            if let Some(migrate) = &mut report.migrate {
+                crate::receipt::issue(client, config, migrate, started_at).await;
+            }
+            Ok(report)
+        }
+        _ => Err(unsupported(client.dialect_kind())),
+    }
+}
+
+fn unsupported(kind: DialectKind) -> WaypointError {
+    WaypointError::ConfigError(format!(
+        "Scheduled runs are not supported on {:?} (PostgreSQL only)",
+        kind
+    ))
+}
+
+// ── PostgreSQL ──────────────────────────────────────────────────────────────
+
+#[cfg(feature = "postgres")]
+const COLUMNS: &str =
+    "id, target_version, run_at::text, expires_at::text, status, created_by, message";
+
+#[cfg(feature = "postgres")]
+fn qualified(config: &WaypointConfig) -> String {
+    format!(
+        "{}.{}",
+        quote_ident(&config.migrations.schema),
+        quote_ident(&schedule_table_name(&config.migrations.table))
+    )
+}
+
+#[cfg(feature = "postgres")]
+fn from_row(row: &Row) -> ScheduledRun {
+    ScheduledRun {
+        id: row.get(0),
+        target_version: row.get(1),
+        run_at: row.get(2),
+        expires_at: row.get(3),
+        status: row.get(4),
+        created_by: row.get(5),
+        message: row.get(6),
+    }
+}
+
+/// Create the schedule table if it does not exist (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn create_schedule_table(client: &Client, config: &WaypointConfig) -> Result<()> {
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            id BIGSERIAL PRIMARY KEY,
+            target_version VARCHAR(50),
+            run_at TIMESTAMPTZ NOT NULL,
+            expires_at TIMESTAMPTZ NOT NULL,
+            status VARCHAR(20) NOT NULL DEFAULT 'pending',
+            created_by VARCHAR(100) NOT NULL DEFAULT current_user,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            started_at TIMESTAMPTZ,
+            claim_expires_at TIMESTAMPTZ,
+            finished_at TIMESTAMPTZ,
+            message TEXT
+        )",
+        qualified(config)
+    );
+    client.batch_execute(&sql).await?;
+    Ok(())
+}
+
+/// Whether the schedule table exists (PostgreSQL).
+#[cfg(feature = "postgres")]
+async fn schedule_table_exists(client: &Client, config: &WaypointConfig) -> Result<bool> {
+    let row = client
+        .query_one("SELECT to_regclass($1) IS NOT NULL", &[&qualified(config)])
+        .await?;
+    Ok(row.get(0))
+}
+
+#[cfg(feature = "postgres")]
+async fn schedule(
+    client: &Client,
+    config: &WaypointConfig,
+    at: &str,
+    target_version: Option<&str>,
+    window_minutes: u32,
+) -> Result<ScheduledRun> {
+    create_schedule_table(client, config).await?;
+    let window = window_minutes as i32;
+    let sql = format!(
+        "INSERT INTO {} (target_version, run_at, expires_at)
+         VALUES ($1, $2::text::timestamptz, $2::text::timestamptz + make_interval(mins => $3))
+         RETURNING {}",
+        qualified(config),
+        COLUMNS
+    );
+    let row = client
+        .query_one(&sql, &[&target_version, &at, &window])
+        .await?;
+    let run = from_row(&row);
+    log::info!(
+        "Scheduled migration run; id={}, run_at={}, expires_at={}",
+        run.id,
+        run.run_at,
+        run.expires_at
+    );
+    Ok(run)
+}
+
+#[cfg(feature = "postgres")]
+async fn list(client: &Client, config: &WaypointConfig) -> Result<Vec<ScheduledRun>> {
+    if !schedule_table_exists(client, config).await? {
+        return Ok(Vec::new());
+    }
+    let sql = format!(
+        "SELECT {} FROM {} ORDER BY run_at DESC, id DESC",
+        COLUMNS,
+        qualified(config)
+    );
+    let rows = client.query(&sql, &[]).await?;
+    Ok(rows.iter().map(from_row).collect())
+}
+
+#[cfg(feature = "postgres")]
+async fn cancel(client: &Client, config: &WaypointConfig, id: i64) -> Result<ScheduledRun> {
+    let not_found =
+        || WaypointError::ConfigError(format!("No pending scheduled run with id {}", id));
+    if !schedule_table_exists(client, config).await? {
+        return Err(not_found());
+    }
+    let sql = format!(
+        "UPDATE {} SET status = 'cancelled', finished_at = now()
+         WHERE id = $1 AND status = 'pending'
+         RETURNING {}",
+        qualified(config),
+        COLUMNS
+    );
+    match client.query_opt(&sql, &[&id]).await? {
+        Some(row) => Ok(from_row(&row)),
+        None => Err(not_found()),
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn run_scheduled(client: &Client, config: &WaypointConfig) -> Result<RunScheduledReport> {
+    create_schedule_table(client, config).await?;
+    let table = qualified(config);
+
+    let reclaimed = client
+        .execute(
+            &format!(
+                "UPDATE {} SET status = 'pending', started_at = NULL, claim_expires_at = NULL,
+                     message = 'claim expired before the runner reported back'
+                 WHERE status = 'running' AND claim_expires_at < now()",
+                table
+            ),
+            &[],
+        )
+        .await?;
+    if reclaimed > 0 {
+        log::warn!(
+            "Released expired claims of scheduled runs; count={}",
+            reclaimed
+        );
+    }
+
+    let expired = client
+        .execute(
+            &format!(
+                "UPDATE {} SET status = 'expired', finished_at = now()
+                 WHERE status = 'pending' AND expires_at < now()",
+                table
+            ),
+            &[],
+        )
+        .await?;
+    if expired > 0 {
+        log::warn!("Expired scheduled runs; count={}", expired);
+    }
+
+    let claim = format!(
+        "UPDATE {t} SET status = 'running', started_at = now(),
+             claim_expires_at = CASE WHEN $1::INT8 > 0 THEN now() + make_interval(secs => $1::INT8) END
+         WHERE id = (
+             SELECT id FROM {t}
+             WHERE status = 'pending' AND run_at <= now()
+             ORDER BY run_at, id
+             LIMIT 1
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING {c}",
+        t = table,
+        c = COLUMNS
+    );
+    let ttl = config.migrations.schedule_claim_ttl_secs as i64;
+    let Some(row) = client.query_opt(&claim, &[&ttl]).await? else {
+        log::info!("No scheduled run is due");
+        return Ok(RunScheduledReport {
+            reclaimed,
+            expired,
+            run: None,
+            migrate: None,
+        });
+    };
+    let mut run = from_row(&row);
+    log::info!(
+        "Starting scheduled run; id={}, target={}",
+        run.id,
+        run.target_version.as_deref().unwrap_or("latest")
+    );
+
+    let result = crate::commands::migrate::execute_with_options(
+        client,
+        config,
+        run.target_version.as_deref(),
+        false,
+    )
+    .await;
+
+    let (status, message) = match &result {
+        Ok(report) => (
+            "succeeded",
+            format!("applied {} migration(s)", report.migrations_applied),
+        ),
+        Err(e) => ("failed", e.to_string()),
+    };
+    client
+        .execute(
+            &format!(
+                "UPDATE {} SET status = $2, message = $3, finished_at = now() WHERE id = $1",
+                table
+            ),
+            &[&run.id, &status, &message],
+        )
+        .await?;
+    run.status = status.to_string();
+    run.message = Some(message);
+
+    Ok(RunScheduledReport {
+        reclaimed,
+        expired,
+        run: Some(run),
+        migrate: Some(result?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_table_name() {
+        assert_eq!(
+            schedule_table_name("waypoint_schema_history"),
+            "waypoint_schema_history_schedule"
+        );
+    }
+}
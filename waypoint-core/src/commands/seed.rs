@@ -0,0 +1,599 @@
+//! Data seeding, kept out of the schema history.
+//!
+//! Seed files are named `S__{description}.sql`, or `S{order}__{description}.sql`
+//! to fix their order, and live in `[seeds] locations` or next to the
+//! migrations. `waypoint seed` applies them in order and records each one in a
+//! seed history table (`<history_table>_seeds`), so reference data no longer
+//! rides along as repeatable migrations. A seed runs again only when its
+//! checksum changes (or with `--reapply`). `-- waypoint:env` limits a seed to
+//! some environments, and a `-- waypoint:require` guard that evaluates to
+//! false skips it, e.g. `-- waypoint:require row_count("countries") = 0`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use regex_lite::Regex;
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+use crate::checksum::calculate_checksum;
+#[cfg(feature = "postgres")]
+use crate::commands::migrate::should_run_in_environment;
+use crate::config::WaypointConfig;
+#[cfg(feature = "postgres")]
+use crate::db::quote_ident;
+use crate::db::DbClient;
+#[cfg(feature = "postgres")]
+use crate::db::LockTarget;
+use crate::dialect::DialectKind;
+use crate::directive::{self, MigrationDirectives};
+use crate::error::{Result, WaypointError};
+#[cfg(feature = "postgres")]
+use crate::guard;
+#[cfg(feature = "postgres")]
+use crate::placeholder::{build_placeholders, replace_placeholders};
+
+static SEED_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^S(\d*)__(.+)$").unwrap());
+
+/// Configuration for the `[seeds]` section.
+#[derive(Debug, Clone)]
+pub struct SeedConfig {
+    /// Directories scanned for `S__` files, on top of the migration locations.
+    pub locations: Vec<PathBuf>,
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        Self {
+            locations: vec![PathBuf::from("db/seeds")],
+        }
+    }
+}
+
+/// Name of the seed history table for a given history table.
+pub fn seed_table_name(history_table: &str) -> String {
+    format!("{}_seeds", history_table)
+}
+
+/// A seed file discovered on disk.
+#[derive(Debug, Clone)]
+pub struct SeedFile {
+    /// File name, e.g. `S10__Countries.sql`.
+    pub script: String,
+    /// Description from the file name.
+    pub description: String,
+    /// Order from an `S{order}__` prefix; seeds without one run last.
+    pub order: Option<u32>,
+    /// CRC32 checksum of the file content.
+    pub checksum: i32,
+    /// Raw SQL content.
+    pub sql: String,
+    /// Parsed header directives (`env`, `require`, `copy`).
+    pub directives: MigrationDirectives,
+}
+
+/// What `seed` did with one seed file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedStatus {
+    /// Applied for the first time.
+    Applied,
+    /// Applied again: its checksum changed, or `--reapply` was given.
+    Reapplied,
+    /// Already applied with the same checksum.
+    Unchanged,
+    /// Its `-- waypoint:env` list leaves out the current environment.
+    SkippedEnvironment,
+    /// A `-- waypoint:require` guard evaluated to false.
+    SkippedGuard,
+}
+
+/// One seed file's outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct SeedResult {
+    /// File name.
+    pub script: String,
+    /// What happened (or, with `--dry-run`, would happen).
+    pub status: SeedStatus,
+    /// Why the seed was skipped, when it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Execution time in milliseconds, for seeds that ran.
+    pub execution_time_ms: i32,
+}
+
+/// Report returned by `seed`.
+#[derive(Debug, Serialize)]
+pub struct SeedReport {
+    /// Every seed file, in apply order.
+    pub seeds: Vec<SeedResult>,
+    /// Seeds applied or reapplied.
+    pub applied: usize,
+    /// Environment seeds were filtered for.
+    pub environment: Option<String>,
+    /// Nothing was written.
+    pub dry_run: bool,
+}
+
+/// Scan `[seeds] locations` and the migration locations for seed files,
+/// sorted by order (seeds without one last), then file name.
+pub fn scan_seeds(config: &WaypointConfig) -> Result<Vec<SeedFile>> {
+    let mut seeds: Vec<SeedFile> = Vec::new();
+    let mut found_in: HashMap<String, PathBuf> = HashMap::new();
+    for location in config
+        .seeds
+        .locations
+        .iter()
+        .chain(config.migrations.locations.iter())
+    {
+        if !location.exists() {
+            log::debug!("Seed location does not exist: {}", location.display());
+            continue;
+        }
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(location)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.sort();
+        for path in paths {
+            let Some(filename) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let Some(stem) = filename.strip_suffix(".sql") else {
+                continue;
+            };
+            let Some(caps) = SEED_RE.captures(stem) else {
+                continue;
+            };
+            if !path.is_file() {
+                continue;
+            }
+            if let Some(other) = found_in.insert(filename.to_string(), location.clone()) {
+                if other != *location {
+                    return Err(WaypointError::ConfigError(format!(
+                        "Seed '{}' exists in both {} and {}",
+                        filename,
+                        other.display(),
+                        location.display()
+                    )));
+                }
+                continue;
+            }
+            let sql = std::fs::read_to_string(&path)?;
+            seeds.push(SeedFile {
+                script: filename.to_string(),
+                description: caps[2].replace('_', " "),
+                order: caps[1].parse().ok(),
+                checksum: calculate_checksum(&sql),
+                directives: directive::parse_directives(&sql).map_err(|e| match e {
+                    WaypointError::ConfigError(msg) => {
+                        WaypointError::ConfigError(format!("{}: {}", filename, msg))
+                    }
+                    e => e,
+                })?,
+                sql,
+            });
+        }
+    }
+    seeds.sort_by(|a, b| {
+        (a.order.is_none(), a.order, &a.script).cmp(&(b.order.is_none(), b.order, &b.script))
+    });
+    Ok(seeds)
+}
+
+/// What to do with one seed, given the checksum recorded for it (if any),
+/// and whether it should run.
+#[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+fn planned_status(seed: &SeedFile, recorded: Option<i32>, reapply: bool) -> (SeedStatus, bool) {
+    match recorded {
+        None => (SeedStatus::Applied, true),
+        Some(checksum) if checksum != seed.checksum || reapply => (SeedStatus::Reapplied, true),
+        Some(_) => (SeedStatus::Unchanged, false),
+    }
+}
+
+/// Apply pending seed files (PostgreSQL only). With `dry_run`, report what
+/// would run without writing anything; `reapply` runs unchanged seeds too.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    dry_run: bool,
+    reapply: bool,
+) -> Result<SeedReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => {
+            let schema = client.resolve_schema(&config.migrations.schema).await?;
+            if dry_run {
+                return run_seeds(client, config, &schema, dry_run, reapply).await;
+            }
+
+            let lock = LockTarget::new(&config.migrations, &schema);
+            client.acquire_lock(&lock).await?;
+            let result = run_seeds(client, config, &schema, dry_run, reapply).await;
+            if let Err(e) = client.release_lock(&lock).await {
+                log::error!("Failed to release advisory lock: {}", e);
+            }
+
+            if let Ok(report) = &result {
+                log::info!(
+                    "Seed completed; applied={}, seeds={}, schema={}",
+                    report.applied,
+                    report.seeds.len(),
+                    schema
+                );
+            }
+            result
+        }
+        _ => Err(unsupported(client.dialect_kind())),
+    }
+}
+
+fn unsupported(kind: DialectKind) -> WaypointError {
+    WaypointError::ConfigError(format!(
+        "Seeding is not supported on {:?} (PostgreSQL only)",
+        kind
+    ))
+}
+
+// ── PostgreSQL ──────────────────────────────────────────────────────────────
+
+#[cfg(feature = "postgres")]
+fn qualified(config: &WaypointConfig, schema: &str) -> String {
+    format!(
+        "{}.{}",
+        quote_ident(schema),
+        quote_ident(&seed_table_name(&config.migrations.table))
+    )
+}
+
+/// Create the seed history table if it does not exist (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn create_seed_table(
+    client: &Client,
+    config: &WaypointConfig,
+    schema: &str,
+) -> Result<()> {
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            script VARCHAR(1000) PRIMARY KEY,
+            description VARCHAR(200) NOT NULL,
+            checksum INTEGER NOT NULL,
+            environment VARCHAR(100),
+            applied_by VARCHAR(100) NOT NULL,
+            applied_on TIMESTAMPTZ NOT NULL DEFAULT now(),
+            execution_time INTEGER NOT NULL,
+            runs INTEGER NOT NULL DEFAULT 1
+        )",
+        qualified(config, schema)
+    );
+    client.batch_execute(&sql).await?;
+    Ok(())
+}
+
+/// Checksums recorded in the seed history table, by script.
+#[cfg(feature = "postgres")]
+async fn recorded_checksums(
+    client: &Client,
+    config: &WaypointConfig,
+    schema: &str,
+) -> Result<HashMap<String, i32>> {
+    let exists: bool = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM information_schema.tables
+             WHERE table_schema = $1 AND table_name = $2)",
+            &[&schema, &seed_table_name(&config.migrations.table)],
+        )
+        .await?
+        .get(0);
+    if !exists {
+        return Ok(HashMap::new());
+    }
+    let rows = client
+        .query(
+            &format!("SELECT script, checksum FROM {}", qualified(config, schema)),
+            &[],
+        )
+        .await?;
+    Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+}
+
+#[cfg(feature = "postgres")]
+async fn run_seeds(
+    db: &DbClient,
+    config: &WaypointConfig,
+    schema: &str,
+    dry_run: bool,
+    reapply: bool,
+) -> Result<SeedReport> {
+    let client = db.as_postgres()?;
+    let seeds = scan_seeds(config)?;
+    let recorded = recorded_checksums(client, config, schema).await?;
+    let environment = config.migrations.environment.clone();
+
+    let db_user = db.current_user().await.unwrap_or_else(|_| "unknown".into());
+    let db_name = db
+        .current_database()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+    let applied_by = config
+        .migrations
+        .installed_by
+        .clone()
+        .unwrap_or_else(|| db_user.clone());
+    if !dry_run {
+        create_seed_table(client, config, schema).await?;
+    }
+
+    let mut results = Vec::new();
+    for seed in &seeds {
+        let skipped = |status, reason: String| SeedResult {
+            script: seed.script.clone(),
+            status,
+            reason: Some(reason),
+            execution_time_ms: 0,
+        };
+        if !should_run_in_environment(&seed.directives, environment.as_deref()) {
+            results.push(skipped(
+                SeedStatus::SkippedEnvironment,
+                format!("env {}", seed.directives.env.join(",")),
+            ));
+            continue;
+        }
+        let (status, run) = planned_status(seed, recorded.get(&seed.script).copied(), reapply);
+        if !run {
+            results.push(SeedResult {
+                script: seed.script.clone(),
+                status,
+                reason: None,
+                execution_time_ms: 0,
+            });
+            continue;
+        }
+        let mut unmet = None;
+        for require in &seed.directives.require {
+            let expr = guard::parse(&require.expression)?;
+            if !guard::evaluate_db(db, schema, &expr).await? {
+                unmet = Some(
+                    require
+                        .message
+                        .clone()
+                        .unwrap_or_else(|| format!("require {}", require.expression)),
+                );
+                break;
+            }
+        }
+        if let Some(reason) = unmet {
+            results.push(skipped(SeedStatus::SkippedGuard, reason));
+            continue;
+        }
+        if dry_run {
+            results.push(SeedResult {
+                script: seed.script.clone(),
+                status,
+                reason: None,
+                execution_time_ms: 0,
+            });
+            continue;
+        }
+
+        let placeholders = build_placeholders(
+            &config.placeholders,
+            schema,
+            &db_user,
+            &db_name,
+            &seed.script,
+        );
+        let sql = replace_placeholders(&seed.sql, &placeholders)?;
+        log::info!("Applying seed; script={}", seed.script);
+        let execution_time_ms = apply_seed(
+            client,
+            config,
+            schema,
+            seed,
+            &sql,
+            environment.as_deref(),
+            &applied_by,
+        )
+        .await?;
+        results.push(SeedResult {
+            script: seed.script.clone(),
+            status,
+            reason: None,
+            execution_time_ms,
+        });
+    }
+
+    let applied = results
+        .iter()
+        .filter(|r| matches!(r.status, SeedStatus::Applied | SeedStatus::Reapplied))
+        .count();
+    Ok(SeedReport {
+        seeds: results,
+        applied,
+        environment,
+        dry_run,
+    })
+}
+
+/// Run one seed and record it in the same transaction.
+#[cfg(feature = "postgres")]
+async fn apply_seed(
+    client: &Client,
+    config: &WaypointConfig,
+    schema: &str,
+    seed: &SeedFile,
+    sql: &str,
+    environment: Option<&str>,
+    applied_by: &str,
+) -> Result<i32> {
+    let locations: Vec<PathBuf> = config
+        .seeds
+        .locations
+        .iter()
+        .chain(config.migrations.locations.iter())
+        .cloned()
+        .collect();
+    let copies = crate::migration::load_copies(&seed.script, sql, &locations)?;
+    let started = std::time::Instant::now();
+    client.batch_execute("BEGIN").await?;
+    let outcome = async {
+        let failed = |e: tokio_postgres::Error| WaypointError::MigrationFailed {
+            script: seed.script.clone(),
+            reason: crate::error::format_db_error(&e),
+        };
+        client.batch_execute(sql).await.map_err(failed)?;
+        for copy in &copies {
+            crate::db::copy_in(client, &copy.statement, copy.open(&seed.script)?)
+                .await
+                .map_err(|e| match e {
+                    WaypointError::DatabaseError(e) => failed(e),
+                    other => other,
+                })?;
+        }
+        let execution_time = started.elapsed().as_millis() as i32;
+        client
+            .execute(
+                &format!(
+                    "INSERT INTO {0} (script, description, checksum, environment, applied_by, execution_time)
+                     VALUES ($1, $2, $3, $4, $5, $6)
+                     ON CONFLICT (script) DO UPDATE SET
+                         description = EXCLUDED.description,
+                         checksum = EXCLUDED.checksum,
+                         environment = EXCLUDED.environment,
+                         applied_by = EXCLUDED.applied_by,
+                         applied_on = now(),
+                         execution_time = EXCLUDED.execution_time,
+                         runs = {0}.runs + 1",
+                    qualified(config, schema)
+                ),
+                &[
+                    &seed.script,
+                    &seed.description,
+                    &seed.checksum,
+                    &environment,
+                    &applied_by,
+                    &execution_time,
+                ],
+            )
+            .await?;
+        Ok::<i32, WaypointError>(execution_time)
+    }
+    .await;
+    match outcome {
+        Ok(execution_time) => {
+            client.batch_execute("COMMIT").await?;
+            Ok(execution_time)
+        }
+        Err(e) => {
+            if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                log::warn!("Failed to rollback transaction: {}", rollback_err);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &std::path::Path, name: &str, sql: &str) {
+        std::fs::write(dir.join(name), sql).unwrap();
+    }
+
+    #[test]
+    fn test_scan_seeds_orders_and_skips_migrations() {
+        let seeds_dir = tempfile::tempdir().unwrap();
+        let migrations_dir = tempfile::tempdir().unwrap();
+        write(
+            seeds_dir.path(),
+            "S__Users.sql",
+            "INSERT INTO users VALUES (1);",
+        );
+        write(
+            seeds_dir.path(),
+            "S2__Roles.sql",
+            "INSERT INTO roles VALUES (1);",
+        );
+        write(
+            seeds_dir.path(),
+            "S10__Countries.sql",
+            "-- waypoint:env dev\nSELECT 1;",
+        );
+        write(seeds_dir.path(), "notes.txt", "not a seed");
+        write(
+            migrations_dir.path(),
+            "V1__Create.sql",
+            "CREATE TABLE t (id INT);",
+        );
+        write(
+            migrations_dir.path(),
+            "S1__Plans.sql",
+            "INSERT INTO plans VALUES (1);",
+        );
+
+        let mut config = WaypointConfig::default();
+        config.seeds.locations = vec![seeds_dir.path().to_path_buf()];
+        config.migrations.locations = vec![migrations_dir.path().to_path_buf()];
+
+        let seeds = scan_seeds(&config).unwrap();
+        let scripts: Vec<&str> = seeds.iter().map(|s| s.script.as_str()).collect();
+        assert_eq!(
+            scripts,
+            vec![
+                "S1__Plans.sql",
+                "S2__Roles.sql",
+                "S10__Countries.sql",
+                "S__Users.sql"
+            ]
+        );
+        assert_eq!(seeds[2].description, "Countries");
+        assert_eq!(seeds[2].directives.env, vec!["dev"]);
+        assert_eq!(seeds[3].order, None);
+    }
+
+    #[test]
+    fn test_scan_seeds_rejects_duplicates_across_locations() {
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        write(a.path(), "S__Users.sql", "SELECT 1;");
+        write(b.path(), "S__Users.sql", "SELECT 2;");
+
+        let mut config = WaypointConfig::default();
+        config.seeds.locations = vec![a.path().to_path_buf()];
+        config.migrations.locations = vec![b.path().to_path_buf()];
+        assert!(scan_seeds(&config).is_err());
+    }
+
+    #[test]
+    fn test_planned_status() {
+        let seed = SeedFile {
+            script: "S__Users.sql".to_string(),
+            description: "Users".to_string(),
+            order: None,
+            checksum: 42,
+            sql: String::new(),
+            directives: MigrationDirectives::default(),
+        };
+        assert_eq!(
+            planned_status(&seed, None, false),
+            (SeedStatus::Applied, true)
+        );
+        assert_eq!(
+            planned_status(&seed, Some(42), false),
+            (SeedStatus::Unchanged, false)
+        );
+        assert_eq!(
+            planned_status(&seed, Some(7), false),
+            (SeedStatus::Reapplied, true)
+        );
+        assert_eq!(
+            planned_status(&seed, Some(42), true),
+            (SeedStatus::Reapplied, true)
+        );
+    }
+}
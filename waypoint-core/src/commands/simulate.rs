@@ -13,7 +13,6 @@ use crate::db::DbClient;
 use crate::dialect::DialectKind;
 use crate::error::{Result, WaypointError};
 use crate::history;
-use crate::migration::scan_migrations;
 use crate::placeholder::{build_placeholders, replace_placeholders};
 #[cfg(feature = "postgres")]
 use crate::schema;
@@ -136,7 +135,7 @@ async fn run_simulation(
         })?;
 
     // Get pending migrations
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let applied = history::get_applied_migrations(client, schema_name, table).await?;
     let effective = history::effective_applied_versions(&applied);
 
@@ -178,14 +177,22 @@ async fn run_simulation(
             }
         };
 
-        match client.batch_execute(&sql).await {
-            Ok(_) => {
+        // A code migration's SQL is only a checksum stand-in; run its `up()`.
+        let result = match config.migrations.code_migrations.find(&migration.script) {
+            Some(code) => code.up(client).await.map_err(|e| e.to_string()),
+            None => client
+                .batch_execute(&sql)
+                .await
+                .map_err(|e| crate::error::format_db_error(&e)),
+        };
+        match result {
+            Ok(()) => {
                 simulated += 1;
             }
-            Err(e) => {
+            Err(error) => {
                 errors.push(SimulationError {
                     script: migration.script.clone(),
-                    error: crate::error::format_db_error(&e),
+                    error,
                 });
             }
         }
@@ -207,6 +214,12 @@ async fn run_simulation(
 }
 
 /// Execute migration simulation in a throwaway schema (dialect-aware entry).
+#[tracing::instrument(
+    name = "simulate",
+    skip_all,
+    fields(schema = %config.migrations.schema),
+    err(Display)
+)]
 pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<SimulationReport> {
     match client.dialect_kind() {
         #[cfg(feature = "postgres")]
@@ -358,7 +371,7 @@ async fn run_simulation_mysql(
     }
 
     // Get pending migrations.
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let applied =
         history::get_applied_migrations_db(client, source_db, &config.migrations.table).await?;
     let effective = history::effective_applied_versions(&applied);
@@ -403,6 +416,14 @@ async fn run_simulation_mysql(
             }
         };
 
+        if migration.is_code() {
+            errors.push(SimulationError {
+                script: migration.script.clone(),
+                error: "Code migrations are only supported on PostgreSQL".to_string(),
+            });
+            continue;
+        }
+
         // Execute via execute_raw which handles MySQL per-statement protocol.
         // We've USE'd into temp_db so unqualified table refs land there.
         match client.execute_raw(&sql).await {
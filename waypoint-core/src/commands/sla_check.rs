@@ -0,0 +1,205 @@
+//! `waypoint sla-check`: fail when pending migrations have waited too long.
+//!
+//! For CI against staging or production: every migration `migrate` would
+//! still apply is aged from when its file was added (first git commit, else
+//! its modification time, as `pending-report` does), and any older than
+//! `--max-pending-age` is a breach. A migration whose age can't be found
+//! fails the check too, and so does a shallow clone, whose commit dates are
+//! those of the truncated history. The CLI exits with code 23
+//! (`PendingAgeExceeded`) when there is a breach.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::commands::info;
+use crate::commands::pending_report::{committed_at, locate, modified_at};
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::error::{Result, WaypointError};
+
+/// Where a pending migration's age came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeSource {
+    /// The commit that added the file.
+    Git,
+    /// The file's modification time (not in a git checkout, or uncommitted).
+    Mtime,
+}
+
+/// A pending migration and how long it has waited.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingAge {
+    /// Version string, or None for repeatable migrations.
+    pub version: Option<String>,
+    /// Filename of the migration script.
+    pub script: String,
+    /// When the file was added, or None when it could not be found.
+    pub added_at: Option<DateTime<Utc>>,
+    /// Where `added_at` came from.
+    pub source: Option<AgeSource>,
+    /// Hours since `added_at`.
+    pub age_hours: Option<i64>,
+    /// Whether the migration is older than the threshold, or its age is
+    /// unknown.
+    pub breached: bool,
+}
+
+/// Result of `sla-check`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SlaCheckReport {
+    /// Schema holding the history table.
+    pub schema: String,
+    /// The threshold as given, e.g. `14d`.
+    pub max_pending_age: String,
+    /// Every pending migration, in the order `migrate` would apply them.
+    pub pending: Vec<PendingAge>,
+}
+
+impl SlaCheckReport {
+    /// Pending migrations older than the threshold.
+    pub fn breaches(&self) -> impl Iterator<Item = &PendingAge> {
+        self.pending.iter().filter(|m| m.breached)
+    }
+
+    /// `Err(PendingAgeExceeded)` when any migration breached the threshold.
+    pub fn check(&self) -> Result<()> {
+        let scripts: Vec<String> = self
+            .breaches()
+            .map(|m| match m.age_hours {
+                Some(_) => m.script.clone(),
+                None => format!("{} (age unknown)", m.script),
+            })
+            .collect();
+        if scripts.is_empty() {
+            return Ok(());
+        }
+        Err(WaypointError::PendingAgeExceeded {
+            count: scripts.len(),
+            max_age: self.max_pending_age.clone(),
+            scripts: scripts.join(", "),
+        })
+    }
+}
+
+/// Age the pending migrations of one database against `max_pending_age`
+/// (`14d`, `36h`, `2w`).
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    max_pending_age: &str,
+) -> Result<SlaCheckReport> {
+    let max_age = crate::commands::metrics::parse_relative("--max-pending-age", max_pending_age)?;
+    reject_shallow_clone(&config.migrations.locations)?;
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let rows = info::execute_db(client, config).await?;
+    let now = Utc::now();
+
+    let pending = rows
+        .iter()
+        .filter(|r| r.state.is_pending())
+        .map(|row| {
+            let path = locate(&config.migrations.locations, &row.script);
+            let added = path.as_deref().and_then(|path| {
+                committed_at(path)
+                    .map(|at| (at, AgeSource::Git))
+                    .or_else(|| modified_at(path).map(|at| (at, AgeSource::Mtime)))
+            });
+            age(row.version.clone(), row.script.clone(), added, now, max_age)
+        })
+        .collect();
+
+    Ok(SlaCheckReport {
+        schema,
+        max_pending_age: max_pending_age.trim().to_string(),
+        pending,
+    })
+}
+
+/// Fail when a location is in a shallow git clone: `git log` there dates
+/// every file by the oldest fetched commit, so ages would look too young.
+fn reject_shallow_clone(locations: &[PathBuf]) -> Result<()> {
+    for location in locations.iter().filter(|l| l.is_dir()) {
+        let shallow = std::process::Command::new("git")
+            .current_dir(location)
+            .args(["rev-parse", "--is-shallow-repository"])
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .is_some_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "true");
+        if shallow {
+            return Err(WaypointError::GitError(format!(
+                "{} is in a shallow clone, so commit dates are unreliable; \
+                 fetch the full history (git fetch --unshallow) before sla-check",
+                location.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn age(
+    version: Option<String>,
+    script: String,
+    added: Option<(DateTime<Utc>, AgeSource)>,
+    now: DateTime<Utc>,
+    max_age: Duration,
+) -> PendingAge {
+    let waited = added.map(|(at, _)| (now - at).max(Duration::zero()));
+    PendingAge {
+        version,
+        script,
+        added_at: added.map(|(at, _)| at),
+        source: added.map(|(_, source)| source),
+        age_hours: waited.map(|w| w.num_hours()),
+        breached: waited.is_none_or(|w| w > max_age),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaches_past_threshold() {
+        let now = Utc::now();
+        let max_age = Duration::days(14);
+        let fresh = age(
+            Some("2".into()),
+            "V2__b.sql".into(),
+            Some((now - Duration::days(3), AgeSource::Git)),
+            now,
+            max_age,
+        );
+        let stale = age(
+            Some("1".into()),
+            "V1__a.sql".into(),
+            Some((now - Duration::days(15), AgeSource::Mtime)),
+            now,
+            max_age,
+        );
+        let unknown = age(None, "R__v.sql".into(), None, now, max_age);
+        assert!(!fresh.breached);
+        assert_eq!(fresh.age_hours, Some(72));
+        assert!(stale.breached);
+        assert!(unknown.breached);
+        assert_eq!(unknown.age_hours, None);
+
+        let mut report = SlaCheckReport {
+            schema: "public".into(),
+            max_pending_age: "14d".into(),
+            pending: vec![fresh],
+        };
+        assert!(report.check().is_ok());
+        report.pending.push(stale);
+        report.pending.push(unknown);
+        let err = report.check().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "2 pending migration(s) older than 14d: V1__a.sql, R__v.sql (age unknown)"
+        );
+    }
+}
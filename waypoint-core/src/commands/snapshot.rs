@@ -1,9 +1,13 @@
 //! Logical schema snapshots for rollback without undo files.
 //!
 //! Takes a snapshot of the current schema as DDL, stores it as a SQL file,
-//! and can restore from a previous snapshot.
+//! and can restore from a previous snapshot. The metadata file next to it
+//! carries the introspected [`SchemaSnapshot`], which [`execute_diff`]
+//! compares between two snapshots without a database. [`execute_export`]
+//! and [`execute_import`] convert between stored snapshots and schema-only
+//! SQL dumps (see [`crate::schema_sql`]).
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::Serialize;
 
@@ -14,8 +18,8 @@ use crate::config::WaypointConfig;
 use crate::db::DbClient;
 use crate::dialect::DialectKind;
 use crate::error::{Result, WaypointError};
-#[cfg(feature = "postgres")]
-use crate::schema;
+use crate::schema::{self, SchemaDiff, SchemaSnapshot};
+use crate::schema_sql;
 
 /// Configuration for snapshots.
 #[derive(Debug, Clone)]
@@ -79,6 +83,24 @@ pub struct SnapshotInfo {
     pub created: String,
 }
 
+/// Report from comparing two stored snapshots.
+#[derive(Debug, Serialize)]
+pub struct SnapshotDiffReport {
+    /// Identifier of the earlier snapshot.
+    pub from: String,
+    /// Identifier of the later snapshot.
+    pub to: String,
+    /// Object-level changes from `from` to `to`.
+    pub diffs: Vec<SchemaDiff>,
+    /// DDL that turns the `from` schema into the `to` schema.
+    pub generated_sql: String,
+    /// Whether any differences were found.
+    pub has_changes: bool,
+    /// Object categories left out because either snapshot is incomplete.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub incomplete: Vec<String>,
+}
+
 /// Take a snapshot of the current schema (PostgreSQL legacy entry).
 #[cfg(feature = "postgres")]
 pub async fn execute_snapshot(
@@ -104,14 +126,7 @@ pub async fn execute_snapshot(
     let meta_path = dir.join(format!("{}.json", snapshot_id));
 
     // Count objects
-    let objects_captured = snapshot.tables.len()
-        + snapshot.views.len()
-        + snapshot.indexes.len()
-        + snapshot.sequences.len()
-        + snapshot.functions.len()
-        + snapshot.enums.len()
-        + snapshot.constraints.len()
-        + snapshot.triggers.len();
+    let objects_captured = captured_objects(&snapshot);
 
     // Write SQL file
     std::fs::write(&sql_path, &ddl)?;
@@ -128,6 +143,7 @@ pub async fn execute_snapshot(
         "sequences": snapshot.sequences.len(),
         "functions": snapshot.functions.len(),
         "enums": snapshot.enums.len(),
+        "snapshot": snapshot,
     });
     std::fs::write(&meta_path, serde_json::to_string_pretty(&meta).unwrap())?;
 
@@ -251,6 +267,147 @@ pub fn list_snapshots(snapshot_config: &SnapshotConfig) -> Result<Vec<SnapshotIn
     Ok(snapshots)
 }
 
+/// Read the schema stored with a snapshot, and the engine it was taken on.
+pub fn load_snapshot(
+    snapshot_config: &SnapshotConfig,
+    snapshot_id: &str,
+) -> Result<(SchemaSnapshot, DialectKind)> {
+    let meta_path = snapshot_config
+        .directory
+        .join(format!("{}.json", snapshot_id));
+    let content =
+        std::fs::read_to_string(&meta_path).map_err(|_| WaypointError::SnapshotError {
+            reason: format!(
+                "Snapshot '{}' not found at {}",
+                snapshot_id,
+                meta_path.display()
+            ),
+        })?;
+    let mut meta: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| WaypointError::SnapshotError {
+            reason: format!("Invalid snapshot metadata {}: {}", meta_path.display(), e),
+        })?;
+    let dialect = match meta.get("engine").and_then(|e| e.as_str()) {
+        Some("mysql") => DialectKind::Mysql,
+        _ => DialectKind::Postgres,
+    };
+    let snapshot = match meta.get_mut("snapshot").map(serde_json::Value::take) {
+        Some(value) => serde_json::from_value(value).map_err(|e| WaypointError::SnapshotError {
+            reason: format!("Invalid schema in snapshot '{}': {}", snapshot_id, e),
+        })?,
+        None => {
+            return Err(WaypointError::SnapshotError {
+                reason: format!(
+                    "Snapshot '{}' has no stored schema to compare (taken by an older waypoint); take a new snapshot",
+                    snapshot_id
+                ),
+            })
+        }
+    };
+    Ok((snapshot, dialect))
+}
+
+/// Compare two stored snapshots (`snapshot diff`, no DB required).
+pub fn execute_diff(
+    snapshot_config: &SnapshotConfig,
+    from: &str,
+    to: &str,
+) -> Result<SnapshotDiffReport> {
+    let (before, dialect) = load_snapshot(snapshot_config, from)?;
+    let (after, _) = load_snapshot(snapshot_config, to)?;
+    let diffs = schema::diff(&before, &after);
+    Ok(SnapshotDiffReport {
+        from: from.to_string(),
+        to: to.to_string(),
+        generated_sql: schema::generate_sql(&diffs, dialect),
+        has_changes: !diffs.is_empty(),
+        incomplete: schema::incomplete_categories(&before, &after),
+        diffs,
+    })
+}
+
+/// Write a stored snapshot as a schema-only SQL dump of `schema`
+/// (`snapshot export`, no DB required, PostgreSQL snapshots only).
+pub fn execute_export(
+    snapshot_config: &SnapshotConfig,
+    snapshot_id: &str,
+    schema_name: &str,
+) -> Result<String> {
+    let (snapshot, dialect) = load_snapshot(snapshot_config, snapshot_id)?;
+    if dialect == DialectKind::Mysql {
+        return Err(WaypointError::SnapshotError {
+            reason: format!(
+                "Snapshot '{}' was taken on MySQL; export writes PostgreSQL dumps",
+                snapshot_id
+            ),
+        });
+    }
+    Ok(schema_sql::to_sql(&snapshot, schema_name))
+}
+
+/// Store a schema-only SQL dump as a snapshot (`snapshot import`, no DB
+/// required), so `snapshot diff` can compare it with snapshots taken from a
+/// database. The ID defaults to the file name without its extension.
+pub fn execute_import(
+    snapshot_config: &SnapshotConfig,
+    path: &Path,
+    snapshot_id: Option<&str>,
+    schema_name: &str,
+) -> Result<SnapshotReport> {
+    let snapshot = schema_sql::read_file(path, schema_name)?;
+    let snapshot_id = match snapshot_id {
+        Some(id) => id.to_string(),
+        None => path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("imported")
+            .to_string(),
+    };
+    let dir = &snapshot_config.directory;
+    let sql_path = dir.join(format!("{}.sql", snapshot_id));
+    let meta_path = dir.join(format!("{}.json", snapshot_id));
+    if sql_path.exists() || meta_path.exists() {
+        return Err(WaypointError::SnapshotError {
+            reason: format!(
+                "Snapshot '{}' already exists in {}",
+                snapshot_id,
+                dir.display()
+            ),
+        });
+    }
+    std::fs::create_dir_all(dir)?;
+
+    let objects_captured = captured_objects(&snapshot);
+    std::fs::write(&sql_path, schema::to_ddl(&snapshot))?;
+    let meta = serde_json::json!({
+        "snapshot_id": snapshot_id,
+        "schema": schema_name,
+        "objects_captured": objects_captured,
+        "created_at": chrono::Utc::now().to_rfc3339(),
+        "imported_from": path.display().to_string(),
+        "snapshot": snapshot,
+    });
+    std::fs::write(&meta_path, serde_json::to_string_pretty(&meta).unwrap())?;
+
+    Ok(SnapshotReport {
+        snapshot_id,
+        snapshot_path: sql_path.display().to_string(),
+        objects_captured,
+    })
+}
+
+/// Objects counted in [`SnapshotReport::objects_captured`].
+fn captured_objects(snapshot: &SchemaSnapshot) -> usize {
+    snapshot.tables.len()
+        + snapshot.views.len()
+        + snapshot.indexes.len()
+        + snapshot.sequences.len()
+        + snapshot.functions.len()
+        + snapshot.enums.len()
+        + snapshot.constraints.len()
+        + snapshot.triggers.len()
+}
+
 /// Take a snapshot of the current schema (dialect-aware entry).
 pub async fn execute_snapshot_db(
     client: &DbClient,
@@ -382,6 +539,8 @@ async fn execute_snapshot_mysql(
     }
 
     let objects_captured = tables.len() + views.len();
+    drop(conn);
+    let snapshot = schema::introspect_db(client, &schema_name).await?;
     std::fs::write(&sql_path, &ddl)?;
     let meta = serde_json::json!({
         "snapshot_id": snapshot_id,
@@ -391,6 +550,7 @@ async fn execute_snapshot_mysql(
         "created_at": chrono::Utc::now().to_rfc3339(),
         "tables": tables.len(),
         "views": views.len(),
+        "snapshot": snapshot,
     });
     std::fs::write(&meta_path, serde_json::to_string_pretty(&meta).unwrap())?;
     prune_snapshots(dir, snapshot_config.max_snapshots)?;
@@ -568,6 +728,110 @@ fn prune_snapshots(dir: &PathBuf, max: usize) -> Result<()> {
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{ColumnDef, TableDef};
+
+    fn write_snapshot(dir: &std::path::Path, id: &str, columns: &[&str]) {
+        let snapshot = SchemaSnapshot {
+            tables: vec![TableDef {
+                schema: "public".into(),
+                name: "users".into(),
+                columns: columns
+                    .iter()
+                    .zip(1..)
+                    .map(|(name, ordinal_position)| ColumnDef {
+                        name: name.to_string(),
+                        data_type: "text".into(),
+                        is_nullable: true,
+                        default: None,
+                        ordinal_position,
+                    })
+                    .collect(),
+            }],
+            views: vec![],
+            indexes: vec![],
+            sequences: vec![],
+            functions: vec![],
+            enums: vec![],
+            constraints: vec![],
+            triggers: vec![],
+            extensions: vec![],
+            distributed_tables: vec![],
+            incomplete: vec![],
+        };
+        let meta = serde_json::json!({ "snapshot_id": id, "snapshot": snapshot });
+        std::fs::write(dir.join(format!("{}.json", id)), meta.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_diff_stored_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SnapshotConfig {
+            directory: dir.path().to_path_buf(),
+            ..SnapshotConfig::default()
+        };
+        write_snapshot(dir.path(), "20260101_000000", &["id", "name"]);
+        write_snapshot(dir.path(), "20260201_000000", &["id", "email"]);
+
+        let report = execute_diff(&config, "20260101_000000", "20260201_000000").unwrap();
+        assert!(report.has_changes);
+        let labels: Vec<String> = report.diffs.iter().map(|d| d.to_string()).collect();
+        assert_eq!(labels.len(), 2, "{:?}", labels);
+        assert!(report.generated_sql.contains("ADD COLUMN \"email\" text"));
+        assert!(report.generated_sql.contains("DROP COLUMN"));
+
+        let same = execute_diff(&config, "20260101_000000", "20260101_000000").unwrap();
+        assert!(!same.has_changes);
+    }
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SnapshotConfig {
+            directory: dir.path().join("snapshots"),
+            ..SnapshotConfig::default()
+        };
+        std::fs::create_dir_all(&config.directory).unwrap();
+        write_snapshot(&config.directory, "20260101_000000", &["id", "name"]);
+
+        let sql = execute_export(&config, "20260101_000000", "public").unwrap();
+        assert!(sql.contains("CREATE TABLE \"users\" (\n    \"id\" text,\n    \"name\" text\n);"));
+        let file = dir.path().join("schema.sql");
+        std::fs::write(&file, &sql).unwrap();
+
+        let report = execute_import(&config, &file, None, "public").unwrap();
+        assert_eq!(report.snapshot_id, "schema");
+        assert_eq!(report.objects_captured, 1);
+        let diff = execute_diff(&config, "20260101_000000", "schema").unwrap();
+        assert!(!diff.has_changes, "{:?}", diff.diffs);
+
+        let again = execute_import(&config, &file, None, "public");
+        assert!(matches!(again, Err(WaypointError::SnapshotError { .. })));
+    }
+
+    #[test]
+    fn test_diff_reports_missing_and_legacy_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SnapshotConfig {
+            directory: dir.path().to_path_buf(),
+            ..SnapshotConfig::default()
+        };
+        std::fs::write(
+            dir.path().join("20250101_000000.json"),
+            r#"{"snapshot_id": "20250101_000000", "tables": 1}"#,
+        )
+        .unwrap();
+        let legacy = load_snapshot(&config, "20250101_000000").unwrap_err();
+        assert!(legacy.to_string().contains("no stored schema"));
+        assert!(matches!(
+            load_snapshot(&config, "nope"),
+            Err(WaypointError::SnapshotError { .. })
+        ));
+    }
+}
+
 #[cfg(all(test, feature = "mysql"))]
 mod tests_mysql_definer {
     use super::strip_mysql_definer;
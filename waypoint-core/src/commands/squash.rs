@@ -0,0 +1,933 @@
+//! Squash old migrations into a single baseline file.
+//!
+//! `waypoint squash --through 150 --output V1__Squashed_baseline.sql` applies
+//! versions up to 150 in a throwaway schema, reads the resulting structure
+//! back from the catalog, and writes it as one migration. The generated DDL
+//! is replayed into a second throwaway schema and read back again; the squash
+//! only goes ahead when both reads agree. The original files are moved to an
+//! archive directory next to a note describing how to reconcile the history
+//! table of databases that already applied them.
+
+#[cfg(feature = "postgres")]
+use std::collections::BTreeMap;
+#[cfg(feature = "postgres")]
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+#[cfg(feature = "postgres")]
+use crate::config::MigrationSettings;
+use crate::config::WaypointConfig;
+#[cfg(feature = "postgres")]
+use crate::db::quote_ident;
+use crate::db::DbClient;
+#[cfg(feature = "postgres")]
+use crate::dialect::DialectKind;
+use crate::error::{Result, WaypointError};
+#[cfg(feature = "postgres")]
+use crate::migration::{
+    parse_migration_filename, scan_migrations_with_limits, MigrationKind, MigrationVersion,
+    ResolvedMigration,
+};
+#[cfg(feature = "postgres")]
+use crate::schema::SchemaSnapshot;
+
+/// File written to the archive directory with reconciliation instructions.
+pub const SQUASH_NOTES_FILE: &str = "SQUASHED.md";
+
+/// What to squash and where to put the result.
+#[derive(Debug, Clone)]
+pub struct SquashOptions {
+    /// Highest version to fold into the baseline (inclusive).
+    pub through: String,
+    /// Baseline file name, e.g. `V1__Squashed_baseline.sql`. Its version must
+    /// be one of the squashed versions. Written to the first migration
+    /// location unless the name includes a directory.
+    pub output: String,
+    /// Where to move the squashed files. Defaults to `archive/` inside the
+    /// first migration location (locations are not scanned recursively).
+    pub archive_dir: Option<PathBuf>,
+    /// Generate and verify the baseline without writing or moving files.
+    pub dry_run: bool,
+}
+
+/// Result of a squash.
+#[derive(Debug, Serialize)]
+pub struct SquashReport {
+    /// Highest squashed version.
+    pub through: String,
+    /// Path of the generated baseline.
+    pub output: PathBuf,
+    /// Generated baseline SQL.
+    pub sql: String,
+    /// Versioned scripts folded into the baseline.
+    pub squashed: Vec<String>,
+    /// Directory the squashed (and matching undo) files are moved to.
+    pub archive_dir: PathBuf,
+    /// Files moved to the archive directory.
+    pub archived: Vec<String>,
+    /// Squashed scripts that modify data; their rows are not in the baseline.
+    pub data_migrations: Vec<String>,
+    /// Objects the baseline does not reproduce.
+    pub warnings: Vec<String>,
+    /// How to reconcile existing databases' history tables.
+    pub instructions: Vec<String>,
+    /// Whether files were left untouched.
+    pub dry_run: bool,
+}
+
+/// The files a squash would touch, worked out before any database work.
+#[cfg(feature = "postgres")]
+#[derive(Debug)]
+struct SquashPlan<'a> {
+    squashed: Vec<&'a ResolvedMigration>,
+    undo: Vec<&'a ResolvedMigration>,
+    version: MigrationVersion,
+    output: PathBuf,
+    archive_dir: PathBuf,
+}
+
+/// Execute a squash (dialect-aware entry; PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn execute_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    options: &SquashOptions,
+) -> Result<SquashReport> {
+    match client.dialect_kind() {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => execute(client.as_postgres()?, config, options).await,
+        _ => Err(WaypointError::ConfigError(
+            "squash builds the baseline from the PostgreSQL catalog (PostgreSQL only)".into(),
+        )),
+    }
+}
+
+/// Execute a squash (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn execute(
+    client: &Client,
+    config: &WaypointConfig,
+    options: &SquashOptions,
+) -> Result<SquashReport> {
+    let resolved = scan_migrations_with_limits(
+        &config.migrations.locations,
+        &config.migrations.scan_limits(),
+    )?;
+    let plan = plan_files(&resolved, &config.migrations, options)?;
+
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let source = format!("waypoint_squash_{}", stamp);
+    let check = format!("{}_check", source);
+
+    let result = build_baseline(client, config, &plan.squashed, &source, &check).await;
+
+    for schema in [&source, &check] {
+        let drop_sql = format!("DROP SCHEMA IF EXISTS {} CASCADE", quote_ident(schema));
+        if let Err(e) = client.batch_execute(&drop_sql).await {
+            log::error!("Failed to drop squash schema {}: {}", schema, e);
+        }
+    }
+    let restore_path = format!(
+        "SET search_path TO {}",
+        quote_ident(&config.migrations.schema)
+    );
+    if let Err(e) = client.batch_execute(&restore_path).await {
+        log::warn!("Failed to restore search_path: {}", e);
+    }
+
+    let (statements, warnings) = result?;
+    let report = build_report(options, &plan, &statements, warnings);
+
+    if !options.dry_run {
+        write_files(&plan, &report, &config.migrations.locations)?;
+    }
+    log::info!(
+        "Squash completed; through={}, squashed={}, statements={}, dry_run={}",
+        report.through,
+        report.squashed.len(),
+        statements.len(),
+        options.dry_run
+    );
+    Ok(report)
+}
+
+/// Pick the squashed files and check the output and archive paths.
+#[cfg(feature = "postgres")]
+fn plan_files<'a>(
+    resolved: &'a [ResolvedMigration],
+    migrations: &MigrationSettings,
+    options: &SquashOptions,
+) -> Result<SquashPlan<'a>> {
+    let through = migrations.parse_version(&options.through)?;
+    let first_location = migrations.locations.first().ok_or_else(|| {
+        WaypointError::ConfigError("squash needs at least one migration location".into())
+    })?;
+
+    let squashed: Vec<&ResolvedMigration> = resolved
+        .iter()
+        .filter(|m| m.is_versioned() && m.version().is_some_and(|v| *v <= through))
+        .collect();
+    if squashed.is_empty() {
+        return Err(WaypointError::ConfigError(format!(
+            "No versioned migrations at or below version {} to squash",
+            through
+        )));
+    }
+    let undo = resolved
+        .iter()
+        .filter(|m| m.is_undo() && m.version().is_some_and(|v| *v <= through))
+        .collect();
+
+    let output_path = Path::new(&options.output);
+    let file_name = output_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| {
+            WaypointError::ConfigError(format!("Invalid squash output '{}'", options.output))
+        })?;
+    let version = match parse_migration_filename(file_name)? {
+        (MigrationKind::Versioned(v), _) => v.in_scheme(migrations.version_scheme)?,
+        _ => {
+            return Err(WaypointError::ConfigError(format!(
+                "Squash output '{}' must be a versioned migration (V<version>__<description>.sql)",
+                file_name
+            )))
+        }
+    };
+    if !squashed.iter().any(|m| m.version() == Some(&version)) {
+        return Err(WaypointError::ConfigError(format!(
+            "Squash output version {} must be one of the squashed versions, so databases \
+             that already applied it only need a checksum update",
+            version
+        )));
+    }
+    let output = if output_path
+        .parent()
+        .is_some_and(|p| !p.as_os_str().is_empty())
+    {
+        output_path.to_path_buf()
+    } else {
+        first_location.join(file_name)
+    };
+    if output.exists() && !squashed.iter().any(|m| m.script == file_name) {
+        return Err(WaypointError::ConfigError(format!(
+            "{} already exists and is not one of the squashed files",
+            output.display()
+        )));
+    }
+
+    let archive_dir = options
+        .archive_dir
+        .clone()
+        .unwrap_or_else(|| first_location.join("archive"));
+    if migrations.locations.iter().any(|l| l == &archive_dir) {
+        return Err(WaypointError::ConfigError(format!(
+            "Archive directory {} is a migration location; archived files would still run",
+            archive_dir.display()
+        )));
+    }
+
+    Ok(SquashPlan {
+        squashed,
+        undo,
+        version,
+        output,
+        archive_dir,
+    })
+}
+
+/// Apply the squashed migrations to `source`, dump it, replay the dump into
+/// `check` and make sure both dumps agree. Returns the replayable statements
+/// in working order, plus warnings for objects the dump leaves out.
+#[cfg(feature = "postgres")]
+async fn build_baseline(
+    client: &Client,
+    config: &WaypointConfig,
+    squashed: &[&ResolvedMigration],
+    source: &str,
+    check: &str,
+) -> Result<(Vec<String>, Vec<String>)> {
+    use crate::placeholder::{build_placeholders, replace_placeholders};
+
+    let failed = |reason: String| WaypointError::SimulationFailed { reason };
+
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA {0}; SET search_path TO {0}",
+            quote_ident(source)
+        ))
+        .await
+        .map_err(|e| failed(format!("Failed to create squash schema: {}", e)))?;
+
+    let db_user = crate::db::get_current_user(client)
+        .await
+        .unwrap_or_else(|_| "unknown".to_string());
+    let db_name = crate::db::get_current_database(client)
+        .await
+        .unwrap_or_else(|_| "unknown".to_string());
+    for migration in squashed {
+        let placeholders = build_placeholders(
+            &config.placeholders,
+            source,
+            &db_user,
+            &db_name,
+            &migration.script,
+        );
+        let sql = replace_placeholders(&migration.sql, &placeholders)?;
+        client.batch_execute(&sql).await.map_err(|e| {
+            failed(format!(
+                "{}: {}",
+                migration.script,
+                crate::error::format_db_error(&e)
+            ))
+        })?;
+    }
+
+    let expected = dump(client, source).await?;
+    let warnings = unsupported_objects(client, source).await?;
+
+    client
+        .batch_execute(&format!(
+            "CREATE SCHEMA {0}; SET search_path TO {0}",
+            quote_ident(check)
+        ))
+        .await
+        .map_err(|e| failed(format!("Failed to create squash check schema: {}", e)))?;
+    let ordered = replay(client, expected.clone()).await?;
+
+    let actual = dump(client, check).await?;
+    let mismatches = compare(&expected, &actual);
+    if !mismatches.is_empty() {
+        return Err(failed(format!(
+            "the squashed baseline does not reproduce the schema:\n{}",
+            mismatches.join("\n")
+        )));
+    }
+    Ok((ordered, warnings))
+}
+
+/// Run each statement, deferring failures until something else succeeds.
+///
+/// Catalog order is alphabetical, not dependency order (a view over another
+/// view, a function used in a default), so the order that finally works is
+/// the order written to the baseline.
+#[cfg(feature = "postgres")]
+async fn replay(client: &Client, statements: Vec<String>) -> Result<Vec<String>> {
+    let mut ordered = Vec::with_capacity(statements.len());
+    let mut pending = statements;
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut deferred = Vec::new();
+        let mut last_error = String::new();
+        for stmt in pending {
+            match client.batch_execute(&stmt).await {
+                Ok(()) => ordered.push(stmt),
+                Err(e) => {
+                    last_error = format!("{}\n  {}", crate::error::format_db_error(&e), stmt);
+                    deferred.push(stmt);
+                }
+            }
+        }
+        if deferred.len() == before {
+            return Err(WaypointError::SimulationFailed {
+                reason: format!(
+                    "{} statement(s) of the squashed baseline could not be replayed: {}",
+                    deferred.len(),
+                    last_error
+                ),
+            });
+        }
+        pending = deferred;
+    }
+    Ok(ordered)
+}
+
+/// A column as the catalog describes it, with full type modifiers.
+#[cfg(feature = "postgres")]
+#[derive(Debug, Clone, PartialEq)]
+struct Column {
+    name: String,
+    data_type: String,
+    not_null: bool,
+    default: Option<String>,
+    /// `a` (ALWAYS), `d` (BY DEFAULT) or empty.
+    identity: String,
+    generated: bool,
+}
+
+/// A sequence owned by a column: `serial` (`identity == false`) or identity.
+#[cfg(feature = "postgres")]
+#[derive(Debug, Clone, PartialEq)]
+struct OwnedSequence {
+    sequence: String,
+    table: String,
+    column: String,
+    identity: bool,
+}
+
+/// Read `schema` back as replayable statements, unqualified.
+#[cfg(feature = "postgres")]
+async fn dump(client: &Client, schema: &str) -> Result<Vec<String>> {
+    let snapshot = crate::schema::introspect(client, schema).await?;
+
+    let rows = client
+        .query(
+            "SELECT c.relname, a.attname, format_type(a.atttypid, a.atttypmod), a.attnotnull,
+                    pg_get_expr(d.adbin, d.adrelid), a.attidentity::text, a.attgenerated::text
+             FROM pg_attribute a
+             JOIN pg_class c ON c.oid = a.attrelid
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+             WHERE n.nspname = $1 AND c.relkind IN ('r', 'p')
+               AND a.attnum > 0 AND NOT a.attisdropped
+             ORDER BY c.relname, a.attnum",
+            &[&schema],
+        )
+        .await?;
+    let mut columns: BTreeMap<String, Vec<Column>> = BTreeMap::new();
+    for r in &rows {
+        columns.entry(r.get(0)).or_default().push(Column {
+            name: r.get(1),
+            data_type: r.get(2),
+            not_null: r.get(3),
+            default: r.get(4),
+            identity: r.get(5),
+            generated: r.get::<_, String>(6) == "s",
+        });
+    }
+
+    let rows = client
+        .query(
+            "SELECT s.relname, t.relname, a.attname, d.deptype = 'i'
+             FROM pg_depend d
+             JOIN pg_class s ON s.oid = d.objid AND s.relkind = 'S'
+             JOIN pg_namespace n ON n.oid = s.relnamespace
+             JOIN pg_class t ON t.oid = d.refobjid
+             JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = d.refobjsubid
+             WHERE n.nspname = $1
+               AND d.classid = 'pg_class'::regclass
+               AND d.refclassid = 'pg_class'::regclass
+               AND d.deptype IN ('a', 'i')
+             ORDER BY s.relname",
+            &[&schema],
+        )
+        .await?;
+    let owned: Vec<OwnedSequence> = rows
+        .iter()
+        .map(|r| OwnedSequence {
+            sequence: r.get(0),
+            table: r.get(1),
+            column: r.get(2),
+            identity: r.get(3),
+        })
+        .collect();
+
+    let rows = client
+        .query(
+            "SELECT pg_get_triggerdef(t.oid)
+             FROM pg_trigger t
+             JOIN pg_class c ON c.oid = t.tgrelid
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = $1 AND NOT t.tgisinternal
+             ORDER BY c.relname, t.tgname",
+            &[&schema],
+        )
+        .await?;
+    let triggers: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+
+    let prefix = format!("{}.", schema);
+    Ok(render(&snapshot, &columns, &owned, &triggers)
+        .into_iter()
+        .map(|s| s.replace(&prefix, ""))
+        .collect())
+}
+
+/// Objects in `schema` that [`dump`] does not reproduce.
+#[cfg(feature = "postgres")]
+async fn unsupported_objects(client: &Client, schema: &str) -> Result<Vec<String>> {
+    let rows = client
+        .query(
+            "SELECT 'domain ' || t.typname
+             FROM pg_type t JOIN pg_namespace n ON n.oid = t.typnamespace
+             WHERE n.nspname = $1 AND t.typtype = 'd'
+             UNION ALL
+             SELECT CASE c.relkind WHEN 'c' THEN 'composite type ' ELSE 'partitioning of ' END
+                    || c.relname
+             FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = $1 AND c.relkind IN ('c', 'p')
+             ORDER BY 1",
+            &[&schema],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|r| {
+            format!(
+                "{} is not reproduced by the baseline; add it by hand",
+                r.get::<_, String>(0)
+            )
+        })
+        .collect())
+}
+
+/// Build the baseline statements in a dependency-friendly first guess at an
+/// order; [`replay`] fixes up whatever this gets wrong.
+#[cfg(feature = "postgres")]
+fn render(
+    snapshot: &SchemaSnapshot,
+    columns: &BTreeMap<String, Vec<Column>>,
+    owned: &[OwnedSequence],
+    triggers: &[String],
+) -> Vec<String> {
+    let mut statements = Vec::new();
+
+    for ext in &snapshot.extensions {
+        statements.push(format!(
+            "CREATE EXTENSION IF NOT EXISTS {};",
+            quote_ident(ext)
+        ));
+    }
+    for e in &snapshot.enums {
+        let values: Vec<String> = e
+            .values
+            .iter()
+            .map(|v| format!("'{}'", v.replace('\'', "''")))
+            .collect();
+        statements.push(format!(
+            "CREATE TYPE {} AS ENUM ({});",
+            quote_ident(&e.name),
+            values.join(", ")
+        ));
+    }
+    for s in &snapshot.sequences {
+        if owned.iter().any(|o| o.identity && o.sequence == s.name) {
+            continue;
+        }
+        statements.push(format!(
+            "CREATE SEQUENCE {} AS {};",
+            quote_ident(&s.name),
+            s.data_type
+        ));
+    }
+    for func in &snapshot.functions {
+        statements.push(format!("{};", func.definition.trim().trim_end_matches(';')));
+    }
+    for t in &snapshot.tables {
+        let cols: Vec<String> = columns
+            .get(&t.name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|c| {
+                let mut col = format!("    {} {}", quote_ident(&c.name), c.data_type);
+                match (c.generated, c.identity.as_str(), &c.default) {
+                    (true, _, Some(expr)) => {
+                        col.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", expr))
+                    }
+                    (_, "a", _) => col.push_str(" GENERATED ALWAYS AS IDENTITY"),
+                    (_, "d", _) => col.push_str(" GENERATED BY DEFAULT AS IDENTITY"),
+                    (_, _, Some(expr)) => col.push_str(&format!(" DEFAULT {}", expr)),
+                    _ => {}
+                }
+                if c.not_null {
+                    col.push_str(" NOT NULL");
+                }
+                col
+            })
+            .collect();
+        statements.push(format!(
+            "CREATE TABLE {} (\n{}\n);",
+            quote_ident(&t.name),
+            cols.join(",\n")
+        ));
+    }
+    for o in owned.iter().filter(|o| !o.identity) {
+        statements.push(format!(
+            "ALTER SEQUENCE {} OWNED BY {}.{};",
+            quote_ident(&o.sequence),
+            quote_ident(&o.table),
+            quote_ident(&o.column)
+        ));
+    }
+
+    // Keys before foreign keys, which need the referenced unique index.
+    let (foreign, keys): (Vec<_>, Vec<_>) = snapshot
+        .constraints
+        .iter()
+        .partition(|c| c.constraint_type == "FOREIGN KEY");
+    for c in keys.iter().chain(foreign.iter()) {
+        statements.push(format!(
+            "ALTER TABLE {} ADD CONSTRAINT {} {};",
+            quote_ident(&c.table_name),
+            quote_ident(&c.name),
+            c.definition
+        ));
+    }
+    // Primary key, unique and exclusion constraints create their own index.
+    for idx in &snapshot.indexes {
+        if snapshot.constraints.iter().any(|c| c.name == idx.name) {
+            continue;
+        }
+        statements.push(format!("{};", idx.definition));
+    }
+
+    for v in &snapshot.views {
+        let keyword = if v.is_materialized {
+            "MATERIALIZED VIEW"
+        } else {
+            "VIEW"
+        };
+        statements.push(format!(
+            "CREATE {} {} AS {};",
+            keyword,
+            quote_ident(&v.name),
+            v.definition.trim().trim_end_matches(';').trim()
+        ));
+    }
+    for t in triggers {
+        statements.push(format!("{};", t));
+    }
+
+    statements
+}
+
+/// Statements present in one dump but not the other.
+#[cfg(feature = "postgres")]
+fn compare(expected: &[String], actual: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    for s in expected.iter().filter(|s| !actual.contains(s)) {
+        out.push(format!("  missing: {}", s));
+    }
+    for s in actual.iter().filter(|s| !expected.contains(s)) {
+        out.push(format!("  unexpected: {}", s));
+    }
+    out
+}
+
+/// Whether a migration changes rows as well as structure.
+#[cfg(feature = "postgres")]
+fn modifies_data(sql: &str) -> bool {
+    crate::sql_parser::split_statements(sql).iter().any(|stmt| {
+        let head = crate::wal::strip_leading_comments(stmt)
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        matches!(
+            head.as_str(),
+            "INSERT" | "UPDATE" | "DELETE" | "COPY" | "MERGE"
+        )
+    })
+}
+
+#[cfg(feature = "postgres")]
+fn build_report(
+    options: &SquashOptions,
+    plan: &SquashPlan<'_>,
+    statements: &[String],
+    warnings: Vec<String>,
+) -> SquashReport {
+    let first = plan.squashed[0].version().map(|v| v.raw.clone());
+    let last = plan.squashed[plan.squashed.len() - 1]
+        .version()
+        .map(|v| v.raw.clone());
+    let output_name = plan
+        .output
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut sql = format!(
+        "-- Squashed baseline of versions {} through {} ({} migrations).\n\
+         -- Generated by `waypoint squash`; the original files are in {}.\n\n",
+        first.unwrap_or_default(),
+        last.unwrap_or_default(),
+        plan.squashed.len(),
+        plan.archive_dir.display()
+    );
+    sql.push_str(&statements.join("\n\n"));
+    sql.push('\n');
+
+    let instructions = vec![
+        format!(
+            "Databases at or past version {}: record the new checksum of {} with \
+             `waypoint checksums update --versions {} --reason \"squash through {}\"`. \
+             History rows for the archived versions stay; `validate` reports their files as missing, which is a warning only.",
+            options.through, output_name, plan.version, options.through
+        ),
+        format!(
+            "Databases below version {}: bring them up to it with the archived files first: \
+             `waypoint --locations {} migrate --target {}`.",
+            options.through,
+            plan.archive_dir.display(),
+            options.through
+        ),
+        format!(
+            "New databases: `waypoint migrate` applies {} and continues with the migrations after version {}.",
+            output_name, options.through
+        ),
+    ];
+
+    SquashReport {
+        through: options.through.clone(),
+        output: plan.output.clone(),
+        sql,
+        squashed: plan.squashed.iter().map(|m| m.script.clone()).collect(),
+        archive_dir: plan.archive_dir.clone(),
+        archived: plan
+            .squashed
+            .iter()
+            .chain(plan.undo.iter())
+            .map(|m| m.script.clone())
+            .collect(),
+        data_migrations: plan
+            .squashed
+            .iter()
+            .filter(|m| modifies_data(&m.sql))
+            .map(|m| m.script.clone())
+            .collect(),
+        warnings,
+        instructions,
+        dry_run: options.dry_run,
+    }
+}
+
+/// Move the squashed files to the archive, then write the baseline and notes.
+#[cfg(feature = "postgres")]
+fn write_files(plan: &SquashPlan<'_>, report: &SquashReport, locations: &[PathBuf]) -> Result<()> {
+    std::fs::create_dir_all(&plan.archive_dir)?;
+    for script in &report.archived {
+        let Some(from) = locations
+            .iter()
+            .map(|l| l.join(script))
+            .find(|p| p.is_file())
+        else {
+            continue;
+        };
+        std::fs::rename(&from, plan.archive_dir.join(script))?;
+    }
+    std::fs::write(&report.output, &report.sql)?;
+
+    let mut notes = format!(
+        "# Squashed through version {}\n\n`{}` replaces:\n\n",
+        report.through,
+        report.output.display()
+    );
+    for script in &report.squashed {
+        notes.push_str(&format!("- {}\n", script));
+    }
+    notes.push_str("\n## Reconciling existing databases\n\n");
+    for line in &report.instructions {
+        notes.push_str(&format!("- {}\n", line));
+    }
+    if !report.data_migrations.is_empty() {
+        notes.push_str(
+            "\n## Data not carried over\n\nThese migrations changed rows; the baseline only has structure:\n\n",
+        );
+        for script in &report.data_migrations {
+            notes.push_str(&format!("- {}\n", script));
+        }
+    }
+    std::fs::write(plan.archive_dir.join(SQUASH_NOTES_FILE), notes)?;
+    Ok(())
+}
+
+#[cfg(all(test, feature = "postgres"))]
+mod tests {
+    use super::*;
+    use crate::migration::{scan_migrations, VersionScheme};
+    use crate::schema::{ConstraintDef, IndexDef, SequenceDef, TableDef};
+
+    fn options(through: &str, output: &str) -> SquashOptions {
+        SquashOptions {
+            through: through.to_string(),
+            output: output.to_string(),
+            archive_dir: None,
+            dry_run: true,
+        }
+    }
+
+    fn migrations_dir() -> (tempfile::TempDir, Vec<ResolvedMigration>) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, sql) in [
+            ("V1__Init.sql", "CREATE TABLE a (id int);"),
+            ("V2__Seed.sql", "-- seed\nINSERT INTO a VALUES (1);"),
+            ("U2__Seed.sql", "DELETE FROM a;"),
+            ("V3__Later.sql", "CREATE TABLE b (id int);"),
+            ("R__View.sql", "CREATE OR REPLACE VIEW v AS SELECT 1;"),
+        ] {
+            std::fs::write(dir.path().join(name), sql).unwrap();
+        }
+        let resolved = scan_migrations(&[dir.path().to_path_buf()]).unwrap();
+        (dir, resolved)
+    }
+
+    #[test]
+    fn test_plan_files() {
+        let (dir, resolved) = migrations_dir();
+        let settings = MigrationSettings {
+            locations: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        };
+
+        let plan = plan_files(&resolved, &settings, &options("2", "V1__Squashed.sql")).unwrap();
+        let scripts: Vec<&str> = plan.squashed.iter().map(|m| m.script.as_str()).collect();
+        assert_eq!(scripts, vec!["V1__Init.sql", "V2__Seed.sql"]);
+        assert_eq!(plan.undo.len(), 1);
+        assert_eq!(plan.output, dir.path().join("V1__Squashed.sql"));
+        assert_eq!(plan.archive_dir, dir.path().join("archive"));
+        assert!(modifies_data(&plan.squashed[1].sql));
+        assert!(!modifies_data(&plan.squashed[0].sql));
+
+        // Output version must already be applied wherever the squashed files were.
+        assert!(plan_files(&resolved, &settings, &options("2", "V3__Squashed.sql")).is_err());
+        assert!(plan_files(&resolved, &settings, &options("2", "R__Squashed.sql")).is_err());
+        assert!(plan_files(&resolved, &settings, &options("0", "V1__Squashed.sql")).is_err());
+
+        let mut in_location = options("2", "V1__Squashed.sql");
+        in_location.archive_dir = Some(dir.path().to_path_buf());
+        assert!(plan_files(&resolved, &settings, &in_location).is_err());
+    }
+
+    #[test]
+    fn test_plan_files_lexicographic() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["V9__Init.sql", "V10__Seed.sql", "V95__Later.sql"] {
+            std::fs::write(dir.path().join(name), "CREATE TABLE a (id int);").unwrap();
+        }
+        let settings = MigrationSettings {
+            locations: vec![dir.path().to_path_buf()],
+            version_scheme: VersionScheme::Lexicographic,
+            ..Default::default()
+        };
+        let resolved =
+            scan_migrations_with_limits(&settings.locations, &settings.scan_limits()).unwrap();
+
+        // As text, "10" < "9" < "95".
+        let plan = plan_files(&resolved, &settings, &options("9", "V10__Squashed.sql")).unwrap();
+        let scripts: Vec<&str> = plan.squashed.iter().map(|m| m.script.as_str()).collect();
+        assert_eq!(scripts, vec!["V10__Seed.sql", "V9__Init.sql"]);
+        assert!(plan_files(&resolved, &settings, &options("10", "V9__Squashed.sql")).is_err());
+    }
+
+    #[test]
+    fn test_render_order() {
+        let snapshot = SchemaSnapshot {
+            tables: vec![TableDef {
+                schema: "s".into(),
+                name: "orders".into(),
+                columns: Vec::new(),
+            }],
+            views: Vec::new(),
+            indexes: vec![
+                IndexDef {
+                    schema: "s".into(),
+                    name: "orders_pkey".into(),
+                    table_name: "orders".into(),
+                    definition: "CREATE UNIQUE INDEX orders_pkey ON s.orders USING btree (id)"
+                        .into(),
+                    is_unique: true,
+                },
+                IndexDef {
+                    schema: "s".into(),
+                    name: "orders_user_idx".into(),
+                    table_name: "orders".into(),
+                    definition: "CREATE INDEX orders_user_idx ON s.orders USING btree (user_id)"
+                        .into(),
+                    is_unique: false,
+                },
+            ],
+            sequences: vec![
+                SequenceDef {
+                    schema: "s".into(),
+                    name: "orders_id_seq".into(),
+                    data_type: "bigint".into(),
+                },
+                SequenceDef {
+                    schema: "s".into(),
+                    name: "orders_no_seq".into(),
+                    data_type: "integer".into(),
+                },
+            ],
+            functions: Vec::new(),
+            enums: Vec::new(),
+            constraints: vec![
+                ConstraintDef {
+                    schema: "s".into(),
+                    table_name: "orders".into(),
+                    name: "orders_user_fkey".into(),
+                    constraint_type: "FOREIGN KEY".into(),
+                    definition: "FOREIGN KEY (user_id) REFERENCES users(id)".into(),
+                },
+                ConstraintDef {
+                    schema: "s".into(),
+                    table_name: "orders".into(),
+                    name: "orders_pkey".into(),
+                    constraint_type: "PRIMARY KEY".into(),
+                    definition: "PRIMARY KEY (id)".into(),
+                },
+            ],
+            triggers: Vec::new(),
+            extensions: Vec::new(),
+            distributed_tables: Vec::new(),
+            incomplete: Vec::new(),
+        };
+        let mut columns = BTreeMap::new();
+        columns.insert(
+            "orders".to_string(),
+            vec![
+                Column {
+                    name: "id".into(),
+                    data_type: "bigint".into(),
+                    not_null: true,
+                    default: None,
+                    identity: "a".into(),
+                    generated: false,
+                },
+                Column {
+                    name: "no".into(),
+                    data_type: "integer".into(),
+                    not_null: true,
+                    default: Some("nextval('orders_no_seq'::regclass)".into()),
+                    identity: String::new(),
+                    generated: false,
+                },
+            ],
+        );
+        let owned = vec![
+            OwnedSequence {
+                sequence: "orders_id_seq".into(),
+                table: "orders".into(),
+                column: "id".into(),
+                identity: true,
+            },
+            OwnedSequence {
+                sequence: "orders_no_seq".into(),
+                table: "orders".into(),
+                column: "no".into(),
+                identity: false,
+            },
+        ];
+
+        let statements = render(&snapshot, &columns, &owned, &[]);
+        assert_eq!(
+            statements,
+            vec![
+                "CREATE SEQUENCE \"orders_no_seq\" AS integer;".to_string(),
+                "CREATE TABLE \"orders\" (\n    \"id\" bigint GENERATED ALWAYS AS IDENTITY NOT NULL,\n    \"no\" integer DEFAULT nextval('orders_no_seq'::regclass) NOT NULL\n);".to_string(),
+                "ALTER SEQUENCE \"orders_no_seq\" OWNED BY \"orders\".\"no\";".to_string(),
+                "ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_pkey\" PRIMARY KEY (id);".to_string(),
+                "ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_user_fkey\" FOREIGN KEY (user_id) REFERENCES users(id);".to_string(),
+                "CREATE INDEX orders_user_idx ON s.orders USING btree (user_id);".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! `info --history`: how each version reached its current state.
+//!
+//! The history table is append-only apart from `repair`, so its rows already
+//! tell the story of a messy incident: a failed attempt, the fix, an undo, a
+//! re-apply. This module replays them per version in `installed_rank` order,
+//! adds the `checksums update` changes from `<table>_checksum_audit`
+//! (PostgreSQL), and lists the undo stack, i.e. the order `undo` would revert
+//! the applied versions in. `repair` deletes failed rows, so attempts it
+//! cleaned up no longer show.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::commands::reconcile::RECONCILED_TYPE;
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::error::Result;
+use crate::history::{self, AppliedMigration};
+use crate::migration::{MigrationVersion, VersionScheme};
+
+/// What happened to a version at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    /// Applied by `migrate` (or run again, for a repeatable migration).
+    Applied,
+    /// An attempt that failed and was recorded as such.
+    Failed,
+    /// Reverted by `undo`.
+    Undone,
+    /// Marked as the baseline.
+    Baseline,
+    /// Recorded by `reconcile` as already applied outside waypoint.
+    Reconciled,
+    /// Stored checksum changed by `checksums update`.
+    ChecksumUpdated,
+}
+
+impl std::fmt::Display for TimelineEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            TimelineEventKind::Applied => "applied",
+            TimelineEventKind::Failed => "failed",
+            TimelineEventKind::Undone => "undone",
+            TimelineEventKind::Baseline => "baseline",
+            TimelineEventKind::Reconciled => "reconciled",
+            TimelineEventKind::ChecksumUpdated => "checksum updated",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// One event in a version's timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEvent {
+    /// When it happened.
+    pub at: DateTime<Utc>,
+    /// What happened.
+    pub kind: TimelineEventKind,
+    /// Script that ran (the undo script, for an undo).
+    pub script: String,
+    /// Who did it.
+    pub by: String,
+    /// History row rank; absent for checksum updates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub installed_rank: Option<i32>,
+    /// Execution time in milliseconds; absent for checksum updates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_time_ms: Option<i32>,
+    /// Extra detail, e.g. the old and new checksum and the reason.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// The events of one version (or repeatable script), oldest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionTimeline {
+    /// Version string, or None for repeatable migrations.
+    pub version: Option<String>,
+    /// Description of the first history row.
+    pub description: String,
+    /// Events, in chronological order.
+    pub events: Vec<TimelineEvent>,
+    /// The last event that changed the version's state (checksum updates
+    /// don't), i.e. how it stands now.
+    pub current: TimelineEventKind,
+}
+
+/// Report returned by `info --history`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryTimeline {
+    /// Schema holding the history table.
+    pub schema: String,
+    /// Versioned migrations in version order, then repeatables by script.
+    pub versions: Vec<VersionTimeline>,
+    /// Applied versions in the order `undo` would revert them (newest first).
+    pub undo_stack: Vec<String>,
+}
+
+/// A row of `<table>_checksum_audit`.
+#[derive(Debug, Clone)]
+pub struct ChecksumAuditRow {
+    /// Migration version.
+    pub version: String,
+    /// Script name on disk.
+    pub script: String,
+    /// Checksum before the update.
+    pub old_checksum: Option<i32>,
+    /// Checksum after the update.
+    pub new_checksum: i32,
+    /// Audit note.
+    pub reason: String,
+    /// Who made the change.
+    pub updated_by: String,
+    /// When the change was made.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Build the per-version timelines from history rows (in `installed_rank`
+/// order) and checksum audit rows.
+pub fn build(
+    applied: &[AppliedMigration],
+    audit: &[ChecksumAuditRow],
+    scheme: VersionScheme,
+) -> Vec<VersionTimeline> {
+    let mut timelines: Vec<VersionTimeline> = Vec::new();
+    let key = |version: &Option<String>, script: &str| match version {
+        Some(v) => v.clone(),
+        None => format!("R:{}", script),
+    };
+    let mut keys: Vec<String> = Vec::new();
+
+    for am in applied {
+        let kind = if !am.success {
+            TimelineEventKind::Failed
+        } else {
+            match am.migration_type.as_str() {
+                "UNDO_SQL" => TimelineEventKind::Undone,
+                "BASELINE" => TimelineEventKind::Baseline,
+                t if t == RECONCILED_TYPE => TimelineEventKind::Reconciled,
+                _ => TimelineEventKind::Applied,
+            }
+        };
+        let event = TimelineEvent {
+            at: am.installed_on,
+            kind,
+            script: am.script.clone(),
+            by: am.installed_by.clone(),
+            installed_rank: Some(am.installed_rank),
+            execution_time_ms: Some(am.execution_time),
+            detail: None,
+        };
+        let k = key(&am.version, &am.script);
+        match keys.iter().position(|existing| *existing == k) {
+            Some(i) => timelines[i].events.push(event),
+            None => {
+                keys.push(k);
+                timelines.push(VersionTimeline {
+                    version: am.version.clone(),
+                    description: am.description.clone(),
+                    events: vec![event],
+                    current: kind,
+                });
+            }
+        }
+    }
+
+    for row in audit {
+        let event = TimelineEvent {
+            at: row.updated_at,
+            kind: TimelineEventKind::ChecksumUpdated,
+            script: row.script.clone(),
+            by: row.updated_by.clone(),
+            installed_rank: None,
+            execution_time_ms: None,
+            detail: Some(format!(
+                "{} -> {}: {}",
+                row.old_checksum
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| "none".into()),
+                row.new_checksum,
+                row.reason
+            )),
+        };
+        if let Some(i) = keys.iter().position(|k| *k == row.version) {
+            timelines[i].events.push(event);
+        }
+    }
+
+    for timeline in &mut timelines {
+        // Stable: history rows keep their rank order on equal timestamps.
+        timeline.events.sort_by_key(|e| e.at);
+        if let Some(last) = timeline
+            .events
+            .iter()
+            .rev()
+            .find(|e| e.kind != TimelineEventKind::ChecksumUpdated)
+        {
+            timeline.current = last.kind;
+        }
+    }
+    timelines.sort_by(|a, b| match (&a.version, &b.version) {
+        (Some(x), Some(y)) => version_order(x, scheme).cmp(&version_order(y, scheme)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.events[0].script.cmp(&b.events[0].script),
+    });
+    timelines
+}
+
+fn version_order(version: &str, scheme: VersionScheme) -> Option<MigrationVersion> {
+    MigrationVersion::parse_with(version, scheme).ok()
+}
+
+/// Applied versions, newest first: the order `undo` reverts them in.
+pub fn undo_stack(applied: &[AppliedMigration], scheme: VersionScheme) -> Vec<String> {
+    let mut versions: Vec<MigrationVersion> = history::effective_applied_versions(applied)
+        .iter()
+        .filter_map(|v| MigrationVersion::parse_with(v, scheme).ok())
+        .collect();
+    versions.sort();
+    versions.into_iter().rev().map(|v| v.raw).collect()
+}
+
+/// Reconstruct the history timeline of the configured schema.
+pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<HistoryTimeline> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let table = &config.migrations.table;
+    let applied = if history::history_table_exists_db(client, &schema, table).await? {
+        history::get_applied_migrations_db(client, &schema, table).await?
+    } else {
+        Vec::new()
+    };
+    let audit = checksum_audit(client, config, &schema).await?;
+
+    Ok(HistoryTimeline {
+        versions: build(&applied, &audit, config.migrations.version_scheme),
+        undo_stack: undo_stack(&applied, config.migrations.version_scheme),
+        schema,
+    })
+}
+
+/// Rows of the checksum audit table, if there is one (PostgreSQL only).
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+async fn checksum_audit(
+    client: &DbClient,
+    config: &WaypointConfig,
+    schema: &str,
+) -> Result<Vec<ChecksumAuditRow>> {
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(pg) => {
+            use crate::db::quote_ident;
+            let table = crate::commands::checksums::audit_table_name(&config.migrations.table);
+            let qualified = format!("{}.{}", quote_ident(schema), quote_ident(&table));
+            let exists: bool = pg
+                .query_one("SELECT to_regclass($1) IS NOT NULL", &[&qualified])
+                .await?
+                .get(0);
+            if !exists {
+                return Ok(Vec::new());
+            }
+            let rows = pg
+                .query(
+                    &format!(
+                        "SELECT version, script, old_checksum, new_checksum, reason, updated_by, \
+                         updated_at FROM {} ORDER BY id",
+                        qualified
+                    ),
+                    &[],
+                )
+                .await?;
+            Ok(rows
+                .iter()
+                .map(|r| ChecksumAuditRow {
+                    version: r.get(0),
+                    script: r.get(1),
+                    old_checksum: r.get(2),
+                    new_checksum: r.get(3),
+                    reason: r.get(4),
+                    updated_by: r.get(5),
+                    updated_at: r.get(6),
+                })
+                .collect())
+        }
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(_) => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn row(rank: i32, version: Option<&str>, kind: &str, success: bool) -> AppliedMigration {
+        AppliedMigration {
+            description: "x".into(),
+            migration_type: kind.into(),
+            checksum: Some(1),
+            installed_by: "ops".into(),
+            installed_on: Utc.with_ymd_and_hms(2024, 1, 1, 0, rank as u32, 0).unwrap(),
+            execution_time: 5,
+            success,
+            ..AppliedMigration::for_test(
+                rank,
+                version,
+                &format!("{}__x.sql", version.unwrap_or("R")),
+            )
+        }
+    }
+
+    #[test]
+    fn test_build_groups_events_per_version() {
+        let applied = vec![
+            row(1, Some("1"), "SQL", true),
+            row(2, Some("2"), "SQL", false),
+            row(3, Some("2"), "SQL", true),
+            row(4, None, "SQL", true),
+            row(5, Some("10"), "SQL", true),
+            row(6, Some("2"), "UNDO_SQL", true),
+            row(7, None, "SQL", true),
+        ];
+        let audit = vec![ChecksumAuditRow {
+            version: "1".into(),
+            script: "1__x.sql".into(),
+            old_checksum: Some(1),
+            new_checksum: 2,
+            reason: "reformat".into(),
+            updated_by: "ops".into(),
+            updated_at: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+        }];
+        let timelines = build(&applied, &audit, VersionScheme::Numeric);
+
+        let versions: Vec<Option<&str>> = timelines.iter().map(|t| t.version.as_deref()).collect();
+        assert_eq!(versions, vec![Some("1"), Some("2"), Some("10"), None]);
+
+        let kinds = |i: usize| -> Vec<TimelineEventKind> {
+            timelines[i].events.iter().map(|e| e.kind).collect()
+        };
+        use TimelineEventKind::*;
+        assert_eq!(kinds(0), vec![Applied, ChecksumUpdated]);
+        assert_eq!(timelines[0].current, Applied);
+        assert_eq!(
+            timelines[0].events[1].detail.as_deref(),
+            Some("1 -> 2: reformat")
+        );
+        assert_eq!(kinds(1), vec![Failed, Applied, Undone]);
+        assert_eq!(timelines[1].current, Undone);
+        assert_eq!(kinds(3), vec![Applied, Applied]);
+
+        assert_eq!(
+            undo_stack(&applied, VersionScheme::Numeric),
+            vec!["10", "1"]
+        );
+    }
+}
@@ -11,12 +11,16 @@ use tokio_postgres::Client;
 use crate::config::WaypointConfig;
 #[cfg(feature = "postgres")]
 use crate::db;
-use crate::db::DbClient;
+use crate::db::{DbClient, LockTarget};
+use crate::dependency::DependencyGraph;
 use crate::dialect::DialectKind;
 use crate::error::{Result, WaypointError};
 use crate::history;
-use crate::migration::{scan_migrations, MigrationVersion, ResolvedMigration};
+use crate::migration::{MigrationVersion, ResolvedMigration};
 use crate::placeholder::{build_placeholders, replace_placeholders};
+use crate::provenance::ProvenanceIndex;
+use crate::telemetry;
+use crate::warning::{Warning, WarningCode};
 
 /// How many / which versions to undo.
 #[derive(Debug, Clone)]
@@ -27,6 +31,9 @@ pub enum UndoTarget {
     Version(MigrationVersion),
     /// Undo the last N applied migrations in reverse order.
     Count(usize),
+    /// Undo only this version, leaving later versions applied. Refused when a
+    /// later applied migration depends on it.
+    Exact(MigrationVersion),
 }
 
 /// Report returned after an undo operation.
@@ -38,6 +45,9 @@ pub struct UndoReport {
     pub total_time_ms: i32,
     /// Per-migration details for each undone migration.
     pub details: Vec<UndoDetail>,
+    /// Non-fatal problems raised during the run, such as undos that relied on
+    /// auto-generated reversal SQL.
+    pub warnings: Vec<Warning>,
 }
 
 /// Details of a single undone migration.
@@ -55,6 +65,32 @@ pub struct UndoDetail {
     pub auto_reversal: bool,
 }
 
+/// What an undo would run, from [`plan_db`] (`undo --dry-run`).
+#[derive(Debug, Serialize)]
+pub struct UndoPlan {
+    /// Versions that would be undone, newest first.
+    pub steps: Vec<UndoStep>,
+    /// Warnings the undo would raise, such as steps that would rely on
+    /// auto-generated reversal SQL.
+    pub warnings: Vec<Warning>,
+}
+
+/// One version in an [`UndoPlan`].
+#[derive(Debug, Serialize)]
+pub struct UndoStep {
+    /// Version string of the migration that would be undone.
+    pub version: String,
+    /// Human-readable description from the undo migration filename.
+    pub description: String,
+    /// Filename of the undo migration script, or `auto-reversal:V{version}`.
+    pub script: String,
+    /// Whether the stored auto-generated reversal would be used instead of a
+    /// manual `U` file.
+    pub auto_reversal: bool,
+    /// The SQL that would run, with placeholders replaced.
+    pub sql: String,
+}
+
 /// Execute undo SQL within an atomic transaction (BEGIN/execute/history-insert/COMMIT).
 ///
 /// On SQL execution failure, the transaction is rolled back and a best-effort
@@ -150,15 +186,15 @@ pub async fn execute(
     config: &WaypointConfig,
     target: UndoTarget,
 ) -> Result<UndoReport> {
-    let table = &config.migrations.table;
+    let lock = LockTarget::new(&config.migrations, &config.migrations.schema);
 
     // Acquire advisory lock
-    db::acquire_advisory_lock(client, table).await?;
+    db::acquire_advisory_lock(client, &lock).await?;
 
     let result = run_undo(client, config, target).await;
 
     // Always release the advisory lock
-    if let Err(e) = db::release_advisory_lock(client, table).await {
+    if let Err(e) = db::release_advisory_lock(client, &lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -191,7 +227,7 @@ async fn run_undo(
     history::create_history_table(client, schema, table).await?;
 
     // Scan migration files — build map of undo files by version
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let undo_by_version: HashMap<String, &ResolvedMigration> = resolved
         .iter()
         .filter(|m| m.is_undo())
@@ -205,20 +241,13 @@ async fn run_undo(
     // Build list of currently-applied versioned migrations, sorted descending by version
     let mut applied_versions: Vec<MigrationVersion> = effective
         .iter()
-        .filter_map(|v| MigrationVersion::parse(v).ok())
+        .filter_map(|v| config.migrations.parse_version(v).ok())
         .collect();
     applied_versions.sort();
     applied_versions.reverse(); // newest first
 
     // Determine which versions to undo
-    let versions_to_undo: Vec<MigrationVersion> = match target {
-        UndoTarget::Last => applied_versions.into_iter().take(1).collect(),
-        UndoTarget::Count(n) => applied_versions.into_iter().take(n).collect(),
-        UndoTarget::Version(ref target_ver) => applied_versions
-            .into_iter()
-            .filter(|v| v > target_ver)
-            .collect(),
-    };
+    let versions_to_undo = select_versions(&target, applied_versions, &resolved)?;
 
     // Get database user info for placeholders
     let db_user = db::get_current_user(client)
@@ -237,6 +266,7 @@ async fn run_undo(
         migrations_undone: 0,
         total_time_ms: 0,
         details: Vec::new(),
+        warnings: Vec::new(),
     };
 
     // Execute undo for each version (newest first)
@@ -259,16 +289,19 @@ async fn run_undo(
             );
             let sql = replace_placeholders(&undo_migration.sql, &placeholders)?;
 
-            let exec_time = execute_undo_sql(
-                client,
-                schema,
-                table,
-                &version.raw,
-                &undo_migration.description,
-                &undo_migration.script,
-                Some(undo_migration.checksum),
-                installed_by,
-                &sql,
+            let exec_time = telemetry::traced(
+                telemetry::undo_span(&version.raw, &undo_migration.script),
+                execute_undo_sql(
+                    client,
+                    schema,
+                    table,
+                    &version.raw,
+                    &undo_migration.description,
+                    &undo_migration.script,
+                    Some(undo_migration.checksum),
+                    installed_by,
+                    &sql,
+                ),
             )
             .await?;
 
@@ -292,16 +325,19 @@ async fn run_undo(
                         schema
                     );
 
-                    let exec_time = execute_undo_sql(
-                        client,
-                        schema,
-                        table,
-                        &version.raw,
-                        "Auto-generated reversal",
-                        &script,
-                        None,
-                        installed_by,
-                        &reversal_sql,
+                    let exec_time = telemetry::traced(
+                        telemetry::undo_span(&version.raw, &script),
+                        execute_undo_sql(
+                            client,
+                            schema,
+                            table,
+                            &version.raw,
+                            "Auto-generated reversal",
+                            &script,
+                            None,
+                            installed_by,
+                            &reversal_sql,
+                        ),
                     )
                     .await?;
 
@@ -314,23 +350,131 @@ async fn run_undo(
                         execution_time_ms: exec_time,
                         auto_reversal: true,
                     });
+                    report.warnings.push(auto_reversal_warning(&version.raw));
                 }
                 None => {
-                    return Err(WaypointError::UndoMissing {
-                        version: version.raw.clone(),
-                    });
+                    return Err(undo_missing(&applied, &version.raw));
                 }
             }
         } else {
-            return Err(WaypointError::UndoMissing {
-                version: version.raw.clone(),
-            });
+            return Err(undo_missing(&applied, &version.raw));
         }
     }
 
     Ok(report)
 }
 
+/// Versions to undo, newest first, given the applied versions (newest first).
+fn select_versions(
+    target: &UndoTarget,
+    applied_versions: Vec<MigrationVersion>,
+    resolved: &[ResolvedMigration],
+) -> Result<Vec<MigrationVersion>> {
+    Ok(match target {
+        UndoTarget::Last => applied_versions.into_iter().take(1).collect(),
+        UndoTarget::Count(n) => applied_versions.into_iter().take(*n).collect(),
+        UndoTarget::Version(target_ver) => applied_versions
+            .into_iter()
+            .filter(|v| v > target_ver)
+            .collect(),
+        UndoTarget::Exact(version) => {
+            if !applied_versions.contains(version) {
+                return Err(WaypointError::ConfigError(format!(
+                    "Version {} is not applied",
+                    version.raw
+                )));
+            }
+            check_exact_undo(version, &applied_versions, resolved)?;
+            vec![version.clone()]
+        }
+    })
+}
+
+/// Refuse to undo `version` alone when a later applied migration depends on
+/// it, either through `-- waypoint:depends` or by using an object `version`
+/// defined (per the DDL provenance index).
+fn check_exact_undo(
+    version: &MigrationVersion,
+    applied_versions: &[MigrationVersion],
+    resolved: &[ResolvedMigration],
+) -> Result<()> {
+    let versioned: Vec<&ResolvedMigration> = resolved.iter().filter(|m| m.is_versioned()).collect();
+    let later: Vec<&ResolvedMigration> = versioned
+        .iter()
+        .filter(|m| {
+            m.version()
+                .is_some_and(|v| v > version && applied_versions.contains(v))
+        })
+        .copied()
+        .collect();
+    if later.is_empty() {
+        return Ok(());
+    }
+    if !versioned.iter().any(|m| m.version() == Some(version)) {
+        return Err(WaypointError::UndoUnsafe {
+            version: version.raw.clone(),
+            reason: "its migration file is missing, so later dependencies can't be checked"
+                .to_string(),
+        });
+    }
+
+    let mut reasons = Vec::new();
+    let graph = DependencyGraph::build(&versioned, false)?;
+    let dependents = graph.dependents(&version.raw);
+    let provenance = ProvenanceIndex::build(&versioned);
+    for m in &later {
+        let later_version = &m.version().expect("versioned").raw;
+        if dependents.contains(later_version) {
+            reasons.push(format!("V{} depends on it", later_version));
+        }
+        let uses = provenance.uses_of(&version.raw, m);
+        if !uses.is_empty() {
+            let objects: Vec<String> = uses.iter().map(|o| o.to_string()).collect();
+            reasons.push(format!("V{} uses {}", later_version, objects.join(", ")));
+        }
+    }
+
+    if reasons.is_empty() {
+        Ok(())
+    } else {
+        Err(WaypointError::UndoUnsafe {
+            version: version.raw.clone(),
+            reason: reasons.join("; "),
+        })
+    }
+}
+
+/// Warning recorded when a version is undone from auto-generated reversal SQL,
+/// which restores schema but not data the migration removed.
+fn auto_reversal_warning(version: &str) -> Warning {
+    Warning::new(
+        WarningCode::AutoReversal,
+        format!("V{}", version),
+        "undone with auto-generated reversal SQL; data removed by the migration is not restored",
+    )
+}
+
+/// Build the `UndoMissing` error for a version, pointing at the backup taken
+/// before it was applied when there is one.
+fn undo_missing(applied: &[history::AppliedMigration], version: &str) -> WaypointError {
+    let backup_ref = applied
+        .iter()
+        .rev()
+        .filter(|a| a.success && a.version.as_deref() == Some(version))
+        .find_map(|a| a.backup_ref.as_deref());
+    if let Some(reference) = backup_ref {
+        log::warn!(
+            "No undo available; restore from the backup taken before this migration; version={}, backup_ref={}",
+            version,
+            reference
+        );
+    }
+    WaypointError::UndoMissing {
+        version: version.to_string(),
+        backup_ref: backup_ref.map(str::to_string),
+    }
+}
+
 // ── Dialect-aware entry + MySQL path (Phase 1+: manual U-files only) ──────────
 //
 // MySQL undo deliberately supports manual U{version}__*.sql files only. Auto-
@@ -338,6 +482,12 @@ async fn run_undo(
 // `reversal::get_reversal` path is PG-specific).
 
 /// Execute the undo command (dialect-aware entry).
+#[tracing::instrument(
+    name = "undo",
+    skip_all,
+    fields(schema = %config.migrations.schema),
+    err(Display)
+)]
 pub async fn execute_db(
     client: &DbClient,
     config: &WaypointConfig,
@@ -359,19 +509,111 @@ pub async fn execute_db(
     }
 }
 
+/// Work out what [`execute_db`] would undo for `target`, and with which
+/// SQL, without taking the lock or writing anything. Fails the same way the
+/// undo would when a version has neither a `U` file nor a stored reversal.
+pub async fn plan_db(
+    client: &DbClient,
+    config: &WaypointConfig,
+    target: UndoTarget,
+) -> Result<UndoPlan> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let schema = schema.as_str();
+    let table = &config.migrations.table;
+
+    let resolved = config.migrations.resolve()?;
+    let undo_by_version: HashMap<String, &ResolvedMigration> = resolved
+        .iter()
+        .filter(|m| m.is_undo())
+        .filter_map(|m| m.version().map(|v| (v.raw.clone(), m)))
+        .collect();
+
+    let applied = if history::history_table_exists_db(client, schema, table).await? {
+        history::get_applied_migrations_db(client, schema, table).await?
+    } else {
+        Vec::new()
+    };
+    let effective = history::effective_applied_versions(&applied);
+
+    let mut applied_versions: Vec<MigrationVersion> = effective
+        .iter()
+        .filter_map(|v| config.migrations.parse_version(v).ok())
+        .collect();
+    applied_versions.sort();
+    applied_versions.reverse();
+
+    let versions_to_undo = select_versions(&target, applied_versions, &resolved)?;
+
+    let db_user = client
+        .current_user()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+    let db_name = client
+        .current_database()
+        .await
+        .unwrap_or_else(|_| "unknown".into());
+
+    let mut plan = UndoPlan {
+        steps: Vec::new(),
+        warnings: Vec::new(),
+    };
+    for version in &versions_to_undo {
+        let step = match undo_by_version.get(&version.raw) {
+            Some(m) => {
+                let placeholders =
+                    build_placeholders(&config.placeholders, schema, &db_user, &db_name, &m.script);
+                UndoStep {
+                    version: version.raw.clone(),
+                    description: m.description.clone(),
+                    script: m.script.clone(),
+                    auto_reversal: false,
+                    sql: replace_placeholders(&m.sql, &placeholders)?,
+                }
+            }
+            None if config.reversals.enabled => {
+                match crate::reversal::get_reversal_db(client, schema, table, &version.raw).await? {
+                    Some(sql) => {
+                        plan.warnings.push(auto_reversal_warning(&version.raw));
+                        UndoStep {
+                            version: version.raw.clone(),
+                            description: "Auto-generated reversal".to_string(),
+                            script: format!("auto-reversal:V{}", version.raw),
+                            auto_reversal: true,
+                            sql,
+                        }
+                    }
+                    None => return Err(undo_missing(&applied, &version.raw)),
+                }
+            }
+            None => return Err(undo_missing(&applied, &version.raw)),
+        };
+        plan.steps.push(step);
+    }
+
+    log::info!(
+        "Undo planned; migrations={}, schema={}",
+        plan.steps.len(),
+        schema
+    );
+    Ok(plan)
+}
+
 #[cfg(feature = "mysql")]
 async fn execute_mysql(
     client: &DbClient,
     config: &WaypointConfig,
     target: UndoTarget,
 ) -> Result<UndoReport> {
-    let table = &config.migrations.table;
+    let lock = LockTarget::new(
+        &config.migrations,
+        &client.resolve_schema(&config.migrations.schema).await?,
+    );
 
-    client.acquire_lock(table).await?;
+    client.acquire_lock(&lock).await?;
 
     let result = run_undo_mysql(client, config, target).await;
 
-    if let Err(e) = client.release_lock(table).await {
+    if let Err(e) = client.release_lock(&lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -403,7 +645,7 @@ async fn run_undo_mysql(
 
     history::create_history_table_db(client, schema, table).await?;
 
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
     let undo_by_version: HashMap<String, &ResolvedMigration> = resolved
         .iter()
         .filter(|m| m.is_undo())
@@ -415,19 +657,12 @@ async fn run_undo_mysql(
 
     let mut applied_versions: Vec<MigrationVersion> = effective
         .iter()
-        .filter_map(|v| MigrationVersion::parse(v).ok())
+        .filter_map(|v| config.migrations.parse_version(v).ok())
         .collect();
     applied_versions.sort();
     applied_versions.reverse();
 
-    let versions_to_undo: Vec<MigrationVersion> = match target {
-        UndoTarget::Last => applied_versions.into_iter().take(1).collect(),
-        UndoTarget::Count(n) => applied_versions.into_iter().take(n).collect(),
-        UndoTarget::Version(ref target_ver) => applied_versions
-            .into_iter()
-            .filter(|v| v > target_ver)
-            .collect(),
-    };
+    let versions_to_undo = select_versions(&target, applied_versions, &resolved)?;
 
     let db_user = client
         .current_user()
@@ -448,6 +683,7 @@ async fn run_undo_mysql(
         migrations_undone: 0,
         total_time_ms: 0,
         details: Vec::new(),
+        warnings: Vec::new(),
     };
 
     for version in &versions_to_undo {
@@ -491,21 +727,21 @@ async fn run_undo_mysql(
                         )
                     }
                     None => {
-                        return Err(WaypointError::UndoMissing {
-                            version: version.raw.clone(),
-                        });
+                        return Err(undo_missing(&applied, &version.raw));
                     }
                 }
             }
             None => {
-                return Err(WaypointError::UndoMissing {
-                    version: version.raw.clone(),
-                });
+                return Err(undo_missing(&applied, &version.raw));
             }
         };
 
         let start = std::time::Instant::now();
-        let exec_result = client.execute_raw(&sql).await;
+        let exec_result = telemetry::traced(
+            telemetry::undo_span(&version.raw, &script),
+            client.execute_raw(&sql),
+        )
+        .await;
         let exec_time = start.elapsed().as_millis() as i32;
 
         match exec_result {
@@ -534,6 +770,9 @@ async fn run_undo_mysql(
                     execution_time_ms: exec_time,
                     auto_reversal,
                 });
+                if auto_reversal {
+                    report.warnings.push(auto_reversal_warning(&version.raw));
+                }
             }
             Err(e) => {
                 // MySQL DDL auto-commits so the schema may be in a partially-
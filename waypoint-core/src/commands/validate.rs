@@ -7,11 +7,12 @@ use serde::Serialize;
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
-use crate::config::WaypointConfig;
+use crate::checksum::{self, ChecksumAlgorithm, ChecksumVerdict};
+use crate::config::{MigrationSettings, WaypointConfig};
 use crate::db::DbClient;
 use crate::error::{Result, WaypointError};
 use crate::history::{self, AppliedMigration};
-use crate::migration::{scan_migrations, ResolvedMigration};
+use crate::migration::ResolvedMigration;
 
 /// Report returned after a validate operation.
 #[derive(Debug, Serialize)]
@@ -34,8 +35,8 @@ pub async fn execute(client: &Client, config: &WaypointConfig) -> Result<Validat
         return Ok(empty_report());
     }
     let applied = history::get_applied_migrations(client, schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
-    finalise(check(applied, resolved))
+    let resolved = config.migrations.resolve()?;
+    finalise(check(applied, resolved, &config.migrations))
 }
 
 /// Execute the validate command (dialect-aware entry).
@@ -48,8 +49,8 @@ pub async fn execute_db(client: &DbClient, config: &WaypointConfig) -> Result<Va
         return Ok(empty_report());
     }
     let applied = history::get_applied_migrations_db(client, schema, table).await?;
-    let resolved = scan_migrations(&config.migrations.locations)?;
-    finalise(check(applied, resolved))
+    let resolved = config.migrations.resolve()?;
+    finalise(check(applied, resolved, &config.migrations))
 }
 
 fn empty_report() -> ValidateReport {
@@ -73,7 +74,11 @@ fn finalise(report: ValidateReport) -> Result<ValidateReport> {
     Ok(report)
 }
 
-fn check(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> ValidateReport {
+fn check(
+    applied: Vec<AppliedMigration>,
+    resolved: Vec<ResolvedMigration>,
+    settings: &MigrationSettings,
+) -> ValidateReport {
     let resolved_by_version: HashMap<String, &ResolvedMigration> = resolved
         .iter()
         .filter(|m| m.is_versioned())
@@ -100,14 +105,35 @@ fn check(applied: Vec<AppliedMigration>, resolved: Vec<ResolvedMigration>) -> Va
         if am.version.is_some() {
             if let Some(ref version) = am.version {
                 if let Some(resolved) = resolved_by_version.get(version) {
-                    if let Some(expected_checksum) = am.checksum {
-                        if resolved.checksum != expected_checksum {
+                    match checksum::verify(
+                        settings.checksum_algorithm,
+                        settings.checksum_transition,
+                        am,
+                        resolved,
+                    ) {
+                        ChecksumVerdict::Match => {}
+                        ChecksumVerdict::Mismatch => {
+                            let (applied_sum, resolved_sum) =
+                                match (&am.checksum_sha256, settings.checksum_algorithm) {
+                                    (Some(sha), ChecksumAlgorithm::Sha256) => {
+                                        (sha.clone(), resolved.checksum_sha256())
+                                    }
+                                    _ => (
+                                        am.checksum.unwrap_or_default().to_string(),
+                                        resolved.checksum.to_string(),
+                                    ),
+                                };
                             issues.push(format!(
                                 "Checksum mismatch for version {}: applied={}, resolved={}. \
                                  Migration file '{}' has been modified after it was applied.",
-                                version, expected_checksum, resolved.checksum, resolved.script
+                                version, applied_sum, resolved_sum, resolved.script
                             ));
                         }
+                        ChecksumVerdict::Missing => issues.push(format!(
+                            "No SHA-256 checksum recorded for version {} ({}). Run 'waypoint repair' \
+                             to record it, or set checksum_transition = true while switching.",
+                            version, am.script
+                        )),
                     }
                 } else {
                     warnings.push(format!(
@@ -9,7 +9,13 @@ use std::path::PathBuf;
 
 use serde::Deserialize;
 
+use crate::checksum::ChecksumAlgorithm;
+use crate::directive::IsolationLevel;
 use crate::error::{Result, WaypointError};
+use crate::migration::{
+    scan_migrations_with_limits, MigrationVersion, OutOfOrderPolicy, ResolvedMigration, ScanLimits,
+    VersionFilter, VersionScheme,
+};
 
 /// Helper macro to apply an optional owned value directly to a target field.
 ///
@@ -83,6 +89,81 @@ impl std::str::FromStr for SslMode {
     }
 }
 
+/// What the migration lock is keyed on (`[migrations] lock_key`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockKey {
+    /// Schema and history table, so runs against different schemas of the
+    /// same database do not wait for each other.
+    #[default]
+    Schema,
+    /// History table name only, as in earlier releases. Use while older
+    /// waypoint versions may still run against the same database.
+    Table,
+}
+
+impl std::str::FromStr for LockKey {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "schema" => Ok(LockKey::Schema),
+            "table" => Ok(LockKey::Table),
+            _ => Err(WaypointError::ConfigError(format!(
+                "Invalid lock_key '{}'. Use 'schema' or 'table'.",
+                s
+            ))),
+        }
+    }
+}
+
+/// Explicit database dialect (`[database] dialect`).
+///
+/// `Auto` (the default) detects the engine from the URL scheme. `Cockroach`
+/// connects over the PostgreSQL wire protocol and fails fast if the server
+/// turns out not to be CockroachDB.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DialectSetting {
+    /// Detect from the connection URL.
+    #[default]
+    Auto,
+    /// PostgreSQL.
+    Postgres,
+    /// MySQL 8.0+.
+    Mysql,
+    /// CockroachDB (PostgreSQL wire protocol).
+    Cockroach,
+}
+
+impl DialectSetting {
+    /// The wire-protocol dialect this setting implies, if not `Auto`.
+    pub fn kind(&self) -> Option<crate::dialect::DialectKind> {
+        match self {
+            DialectSetting::Auto => None,
+            DialectSetting::Postgres | DialectSetting::Cockroach => {
+                Some(crate::dialect::DialectKind::Postgres)
+            }
+            DialectSetting::Mysql => Some(crate::dialect::DialectKind::Mysql),
+        }
+    }
+}
+
+impl std::str::FromStr for DialectSetting {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(DialectSetting::Auto),
+            "postgres" | "postgresql" => Ok(DialectSetting::Postgres),
+            "mysql" => Ok(DialectSetting::Mysql),
+            "cockroach" | "cockroachdb" => Ok(DialectSetting::Cockroach),
+            _ => Err(WaypointError::ConfigError(format!(
+                "Invalid dialect '{}'. Use 'auto', 'postgres', 'mysql', or 'cockroach'.",
+                s
+            ))),
+        }
+    }
+}
+
 /// Top-level configuration for Waypoint.
 #[derive(Debug, Clone, Default)]
 pub struct WaypointConfig {
@@ -112,6 +193,34 @@ pub struct WaypointConfig {
     pub advisor: crate::advisor::AdvisorConfig,
     /// Migration simulation configuration.
     pub simulation: SimulationConfig,
+    /// Operating mode (strict production posture).
+    pub mode: ModeConfig,
+    /// Schemas and extensions created before the first migration.
+    pub prerequisites: crate::prerequisites::PrerequisitesConfig,
+    /// Backup command run before destructive migrations.
+    pub backup: crate::backup::BackupConfig,
+    /// `NOTIFY` sent after a migrate that applied migrations.
+    pub notify: crate::notify::NotifyConfig,
+    /// Webhook notifications about migration runs.
+    pub notifications: crate::notify::NotificationsConfig,
+    /// Data export and anonymization configuration.
+    pub export: crate::commands::export::ExportConfig,
+    /// `waypoint serve` HTTP API configuration.
+    pub server: ServerConfig,
+    /// Per-command policy (`clean = "never"`, `undo = "require_override"`, ...).
+    pub policy: crate::policy::PolicyConfig,
+    /// Redaction of identifiers and SQL in JSON and log output.
+    pub output: crate::redact::OutputConfig,
+    /// Signed execution receipts issued after migrate.
+    pub receipts: crate::receipt::ReceiptConfig,
+    /// Drift baseline stored in the database after migrate.
+    pub drift: crate::drift_baseline::DriftConfig,
+    /// Git commit, CI build and hostname recorded per applied migration.
+    pub audit: crate::audit::AuditConfig,
+    /// Seed file locations for `waypoint seed`.
+    pub seeds: crate::commands::seed::SeedConfig,
+    /// Data-quality check locations for `waypoint check`.
+    pub checks: crate::commands::check::CheckConfig,
 }
 
 /// Database connection configuration.
@@ -137,8 +246,23 @@ pub struct DatabaseConfig {
     pub connect_timeout_secs: u32,
     /// Statement timeout in seconds (0 means no timeout).
     pub statement_timeout_secs: u32,
+    /// Per-query timeout in seconds for introspection, diff and advisor
+    /// catalog queries (0 means no timeout). Separate from
+    /// `statement_timeout_secs`, which applies to migrations.
+    pub introspection_timeout_secs: u32,
+    /// Keep objects created by `CREATE EXTENSION` in drift, diff and clean
+    /// (default false: they belong to the extension, not to migrations).
+    pub include_extension_objects: bool,
+    /// Glob patterns of object names that drift, diff, advisor, snapshot
+    /// and clean are limited to (empty means all).
+    pub include_objects: Vec<String>,
+    /// Glob patterns of object names that drift, diff, advisor, snapshot
+    /// and clean skip.
+    pub exclude_objects: Vec<String>,
     /// TCP keepalive interval in seconds (0 disables, default 120).
     pub keepalive_secs: u32,
+    /// Explicit dialect; `Auto` detects from the URL scheme.
+    pub dialect: DialectSetting,
 }
 
 impl Default for DatabaseConfig {
@@ -154,7 +278,22 @@ impl Default for DatabaseConfig {
             ssl_mode: SslMode::Prefer,
             connect_timeout_secs: 30,
             statement_timeout_secs: 0,
+            introspection_timeout_secs: 0,
+            include_extension_objects: false,
+            include_objects: Vec::new(),
+            exclude_objects: Vec::new(),
             keepalive_secs: 120,
+            dialect: DialectSetting::Auto,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// The include/exclude patterns as an [`ObjectFilter`](crate::introspection::ObjectFilter).
+    pub fn object_filter(&self) -> crate::introspection::ObjectFilter {
+        crate::introspection::ObjectFilter {
+            include: self.include_objects.clone(),
+            exclude: self.exclude_objects.clone(),
         }
     }
 }
@@ -172,13 +311,21 @@ impl fmt::Debug for DatabaseConfig {
             .field("ssl_mode", &self.ssl_mode)
             .field("connect_timeout_secs", &self.connect_timeout_secs)
             .field("statement_timeout_secs", &self.statement_timeout_secs)
+            .field(
+                "introspection_timeout_secs",
+                &self.introspection_timeout_secs,
+            )
+            .field("include_extension_objects", &self.include_extension_objects)
+            .field("include_objects", &self.include_objects)
+            .field("exclude_objects", &self.exclude_objects)
             .field("keepalive_secs", &self.keepalive_secs)
+            .field("dialect", &self.dialect)
             .finish()
     }
 }
 
-/// Hook configuration for running SQL before/after migrations.
-#[derive(Debug, Clone, Default)]
+/// Hook configuration for running SQL and commands before/after migrations.
+#[derive(Debug, Clone)]
 pub struct HooksConfig {
     /// SQL scripts to run once before the entire migration run.
     pub before_migrate: Vec<PathBuf>,
@@ -188,13 +335,53 @@ pub struct HooksConfig {
     pub before_each_migrate: Vec<PathBuf>,
     /// SQL scripts to run after each individual migration.
     pub after_each_migrate: Vec<PathBuf>,
+    /// Shell commands to run once before the migration run.
+    pub before_migrate_cmd: Vec<String>,
+    /// Shell commands to run once after the migration run.
+    pub after_migrate_cmd: Vec<String>,
+    /// Kill a hook command and fail the run after this many seconds.
+    pub command_timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            before_migrate: Vec::new(),
+            after_migrate: Vec::new(),
+            before_each_migrate: Vec::new(),
+            after_each_migrate: Vec::new(),
+            before_migrate_cmd: Vec::new(),
+            after_migrate_cmd: Vec::new(),
+            command_timeout_secs: 300,
+        }
+    }
 }
 
 /// Lint configuration.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct LintConfig {
     /// List of lint rule names to disable.
     pub disabled_rules: Vec<String>,
+    /// Whether `lint` fails (non-zero exit) when it finds errors.
+    pub strict: bool,
+    /// Environment names `-- waypoint:env` may use; empty accepts any.
+    pub allowed_environments: Vec<String>,
+    /// Warn (W009) about migration files larger than this, in bytes (0 = off).
+    pub warn_migration_bytes: u64,
+    /// Warn (W009) about migration files with more statements than this (0 = off).
+    pub warn_statements: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            disabled_rules: Vec::new(),
+            strict: false,
+            allowed_environments: Vec::new(),
+            warn_migration_bytes: 1024 * 1024,
+            warn_statements: 1_000,
+        }
+    }
 }
 
 /// Migration behavior settings.
@@ -208,8 +395,30 @@ pub struct MigrationSettings {
     pub schema: String,
     /// Whether to allow applying migrations with versions below the highest applied version.
     pub out_of_order: bool,
+    /// Which versions below the highest applied one may be applied when
+    /// `out_of_order` is off (`patch-only` allows hotfixes such as `4.1`
+    /// after `5`).
+    pub out_of_order_policy: OutOfOrderPolicy,
+    /// How versions are parsed and ordered, for legacy naming schemes
+    /// (see [`VersionScheme`]).
+    pub version_scheme: VersionScheme,
+    /// Apply only these pending versions (Flyway's `cherryPick`). Empty
+    /// means every pending version.
+    pub cherry_pick: Vec<String>,
+    /// Leave these pending versions unapplied; `info` shows them as ignored.
+    pub skip_versions: Vec<String>,
+    /// Leave pending repeatable migrations unapplied (`multi exec`, which
+    /// applies a single version).
+    pub skip_repeatables: bool,
     /// Whether to validate already-applied migration checksums before migrating.
     pub validate_on_migrate: bool,
+    /// Checksum `validate` trusts. With `sha256`, migrations also record a
+    /// SHA-256 next to the Flyway-compatible CRC32.
+    pub checksum_algorithm: ChecksumAlgorithm,
+    /// With `checksum_algorithm = "sha256"`, accept rows that only have a
+    /// CRC32 (recorded before the switch) on their CRC32 until `repair`
+    /// records their SHA-256.
+    pub checksum_transition: bool,
     /// Whether the `clean` command is allowed to run.
     pub clean_enabled: bool,
     /// Version to use when running the `baseline` command.
@@ -220,10 +429,122 @@ pub struct MigrationSettings {
     pub environment: Option<String>,
     /// Whether to use `@depends` directives to order migrations topologically.
     pub dependency_ordering: bool,
+    /// PostgreSQL only: with `dependency_ordering`, apply up to this many
+    /// independent migrations at once on separate connections. `1` (the
+    /// default) applies them one at a time.
+    pub parallel_migrations: usize,
+    /// What the migration lock is keyed on.
+    pub lock_key: LockKey,
+    /// Whether to take the global lock exclusively, serializing with every
+    /// other waypoint run on the database.
+    pub global_lock: bool,
+    /// Seconds to wait for the migration lock before failing (0 = wait
+    /// forever).
+    pub lock_wait_timeout_secs: u32,
+    /// CockroachDB only: seconds after which a lock row left by a crashed run
+    /// may be taken over (0 = never).
+    pub lock_ttl_secs: u32,
+    /// Seconds after which a `run-scheduled` claim whose runner never
+    /// reported back is released, so the entry can run again (0 = never).
+    pub schedule_claim_ttl_secs: u32,
+    /// Whether `info`, `validate` and `drift` may run against a read-only
+    /// standby. Commands that write always refuse to.
+    pub allow_standby: bool,
+    /// PostgreSQL only: writable database `drift` replays migrations into
+    /// when connected to a standby.
+    pub standby_scratch_url: Option<String>,
     /// Whether to display a progress indicator during migration.
     pub show_progress: bool,
     /// Whether to wrap all pending migrations in a single transaction (all-or-nothing).
     pub batch_transaction: bool,
+    /// Whether to execute migrations statement by statement and record each
+    /// statement's outcome in the `<table>_statements` sidecar table.
+    pub statement_progress: bool,
+    /// Whether to skip statements already recorded as successful for a
+    /// previously failed non-transactional migration (`migrate --resume`).
+    pub resume: bool,
+    /// Warn when a single migration affects more rows than this (e.g. an
+    /// unexpectedly large backfill). `None` disables the warning.
+    pub warn_rows_affected: Option<u64>,
+    /// PostgreSQL only: run `ANALYZE` on tables that applied migrations
+    /// rewrote or bulk-modified, so the planner sees fresh statistics.
+    pub post_migrate_analyze: bool,
+    /// PostgreSQL only: run `VACUUM (ANALYZE)` instead of `ANALYZE` for those
+    /// tables. Has no effect unless `post_migrate_analyze` is set.
+    pub post_migrate_vacuum: bool,
+    /// Skip post-migrate maintenance on tables smaller than this, in megabytes.
+    pub post_migrate_min_table_mb: i64,
+    /// A migration's `UPDATE`/`DELETE` statements count as bulk modifications
+    /// for post-migrate maintenance only when it affected at least this many
+    /// rows. Rewrites always count.
+    pub post_migrate_min_rows: u64,
+    /// PostgreSQL only: isolation level for migration transactions. A
+    /// `-- waypoint:isolation` directive overrides it per migration.
+    pub isolation_level: Option<IsolationLevel>,
+    /// Largest migration file accepted when scanning, in bytes (0 = no limit).
+    pub max_migration_bytes: u64,
+    /// Most statements accepted in one migration file (0 = no limit).
+    pub max_statements: usize,
+    /// Schemas `migrate` and `info` run against one after another, each with
+    /// its own history table and `${waypoint:schema}`. Empty means just
+    /// `schema`.
+    pub schemas: Vec<String>,
+    /// Migrations implemented in Rust, applied among the files by version.
+    /// Set from code only; see [`crate::code_migration`].
+    #[cfg(feature = "postgres")]
+    pub code_migrations: crate::code_migration::CodeMigrations,
+    /// Migration files compiled into the binary, resolved alongside
+    /// `locations`. Set from code only; see [`crate::embedded`].
+    pub embedded_migrations: crate::embedded::EmbeddedMigrations,
+    /// Providers that fetch remote `locations` (`git:`, `https://`, `s3://`, ...).
+    pub location_providers: crate::location::LocationProviders,
+}
+
+impl MigrationSettings {
+    /// Effective out-of-order policy: `always` when `out_of_order` is set,
+    /// otherwise `out_of_order_policy`.
+    pub fn out_of_order_policy(&self) -> OutOfOrderPolicy {
+        if self.out_of_order {
+            OutOfOrderPolicy::Always
+        } else {
+            self.out_of_order_policy
+        }
+    }
+
+    /// Version filter built from `cherry_pick` and `skip_versions`.
+    pub fn version_filter(&self) -> Result<VersionFilter> {
+        VersionFilter::new(&self.cherry_pick, &self.skip_versions, self.version_scheme)
+    }
+
+    /// Parse `raw` under `version_scheme`.
+    pub fn parse_version(&self, raw: &str) -> Result<MigrationVersion> {
+        MigrationVersion::parse_with(raw, self.version_scheme)
+    }
+
+    /// Per-file limits for scanning `locations`.
+    pub fn scan_limits(&self) -> ScanLimits {
+        ScanLimits {
+            max_bytes: self.max_migration_bytes,
+            max_statements: self.max_statements,
+            version_scheme: self.version_scheme,
+        }
+    }
+
+    /// Every migration: the files in `locations` plus any embedded files
+    /// and registered code migrations, in apply order.
+    pub fn resolve(&self) -> Result<Vec<ResolvedMigration>> {
+        let limits = self.scan_limits();
+        let fetched = crate::location::fetch(&self.locations, &self.location_providers)?;
+        let mut migrations = scan_migrations_with_limits(&fetched.locations, &limits)?;
+        let scanned = migrations.len();
+        migrations.extend(self.embedded_migrations.resolve(&limits)?);
+        #[cfg(feature = "postgres")]
+        migrations.extend(self.code_migrations.resolve(self.version_scheme)?);
+        if migrations.len() > scanned {
+            crate::migration::sort_migrations(&mut migrations)?;
+        }
+        Ok(migrations)
+    }
 }
 
 impl Default for MigrationSettings {
@@ -233,14 +554,44 @@ impl Default for MigrationSettings {
             table: "waypoint_schema_history".to_string(),
             schema: "public".to_string(),
             out_of_order: false,
+            out_of_order_policy: OutOfOrderPolicy::Never,
+            version_scheme: VersionScheme::Numeric,
+            cherry_pick: Vec::new(),
+            skip_repeatables: false,
+            skip_versions: Vec::new(),
             validate_on_migrate: true,
+            checksum_algorithm: ChecksumAlgorithm::Crc32,
+            checksum_transition: false,
             clean_enabled: false,
             baseline_version: "1".to_string(),
             installed_by: None,
             environment: None,
             dependency_ordering: false,
+            parallel_migrations: 1,
+            lock_key: LockKey::Schema,
+            global_lock: false,
+            lock_wait_timeout_secs: 0,
+            lock_ttl_secs: crate::db::DEFAULT_LOCK_TTL_SECS,
+            schedule_claim_ttl_secs: crate::commands::schedule::DEFAULT_CLAIM_TTL_SECS,
+            allow_standby: false,
+            standby_scratch_url: None,
             show_progress: true,
             batch_transaction: false,
+            statement_progress: false,
+            resume: false,
+            warn_rows_affected: None,
+            post_migrate_analyze: false,
+            post_migrate_vacuum: false,
+            post_migrate_min_table_mb: 10,
+            post_migrate_min_rows: 10_000,
+            isolation_level: None,
+            max_migration_bytes: 0,
+            max_statements: 0,
+            schemas: Vec::new(),
+            #[cfg(feature = "postgres")]
+            code_migrations: Default::default(),
+            embedded_migrations: Default::default(),
+            location_providers: Default::default(),
         }
     }
 }
@@ -252,6 +603,48 @@ pub struct SimulationConfig {
     pub simulate_before_migrate: bool,
 }
 
+/// Operating mode configuration.
+#[derive(Debug, Clone, Default)]
+pub struct ModeConfig {
+    /// Enable every recommended guardrail and forbid the escape hatches;
+    /// see [`WaypointConfig::apply_strict`].
+    pub strict: bool,
+    /// Fast local iteration profile; see [`WaypointConfig::apply_dev`].
+    pub dev: bool,
+}
+
+/// `waypoint serve` HTTP API configuration.
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// Address to listen on.
+    pub bind: String,
+    /// Bearer token clients must present. The server refuses to start without one.
+    pub token: Option<String>,
+    /// Listen on a non-loopback address even though the server speaks plain
+    /// HTTP. Only for when TLS is terminated in front of it.
+    pub allow_plain_http: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind: "127.0.0.1:8080".to_string(),
+            token: None,
+            allow_plain_http: false,
+        }
+    }
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("bind", &self.bind)
+            .field("token", &self.token.as_ref().map(|_| "[REDACTED]"))
+            .field("allow_plain_http", &self.allow_plain_http)
+            .finish()
+    }
+}
+
 // ── TOML deserialization structs ──
 
 #[derive(Deserialize, Default)]
@@ -269,6 +662,20 @@ struct TomlConfig {
     safety: Option<TomlSafetyConfig>,
     advisor: Option<TomlAdvisorConfig>,
     simulation: Option<TomlSimulationConfig>,
+    mode: Option<TomlModeConfig>,
+    prerequisites: Option<TomlPrerequisitesConfig>,
+    backup: Option<TomlBackupConfig>,
+    notify: Option<TomlNotifyConfig>,
+    notifications: Option<TomlNotificationsConfig>,
+    export: Option<TomlExportConfig>,
+    server: Option<TomlServerConfig>,
+    policy: Option<HashMap<String, String>>,
+    output: Option<TomlOutputConfig>,
+    receipts: Option<TomlReceiptConfig>,
+    drift: Option<TomlDriftConfig>,
+    audit: Option<TomlAuditConfig>,
+    seeds: Option<TomlSeedsConfig>,
+    checks: Option<TomlChecksConfig>,
 }
 
 #[derive(Deserialize, Default)]
@@ -283,7 +690,12 @@ struct TomlDatabaseConfig {
     ssl_mode: Option<String>,
     connect_timeout: Option<u32>,
     statement_timeout: Option<u32>,
+    introspection_timeout: Option<u32>,
+    include_extension_objects: Option<bool>,
+    include_objects: Option<Vec<String>>,
+    exclude_objects: Option<Vec<String>>,
     keepalive: Option<u32>,
+    dialect: Option<String>,
 }
 
 #[derive(Deserialize, Default)]
@@ -292,19 +704,47 @@ struct TomlMigrationSettings {
     table: Option<String>,
     schema: Option<String>,
     out_of_order: Option<bool>,
+    out_of_order_policy: Option<String>,
+    version_scheme: Option<String>,
+    cherry_pick: Option<Vec<String>>,
+    skip_versions: Option<Vec<String>>,
     validate_on_migrate: Option<bool>,
+    checksum_algorithm: Option<String>,
+    checksum_transition: Option<bool>,
     clean_enabled: Option<bool>,
     baseline_version: Option<String>,
     installed_by: Option<String>,
     environment: Option<String>,
     dependency_ordering: Option<bool>,
+    parallel_migrations: Option<usize>,
+    lock_key: Option<String>,
+    global_lock: Option<bool>,
+    lock_wait_timeout_secs: Option<u32>,
+    lock_ttl_secs: Option<u32>,
+    schedule_claim_ttl_secs: Option<u32>,
+    allow_standby: Option<bool>,
+    standby_scratch_url: Option<String>,
     show_progress: Option<bool>,
     batch_transaction: Option<bool>,
+    statement_progress: Option<bool>,
+    warn_rows_affected: Option<u64>,
+    post_migrate_analyze: Option<bool>,
+    post_migrate_vacuum: Option<bool>,
+    post_migrate_min_table_mb: Option<i64>,
+    post_migrate_min_rows: Option<u64>,
+    isolation_level: Option<String>,
+    max_migration_bytes: Option<u64>,
+    max_statements: Option<usize>,
+    schemas: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Default)]
 struct TomlLintConfig {
     disabled_rules: Option<Vec<String>>,
+    strict: Option<bool>,
+    allowed_environments: Option<Vec<String>>,
+    warn_migration_bytes: Option<u64>,
+    warn_statements: Option<usize>,
 }
 
 #[derive(Deserialize, Default)]
@@ -321,6 +761,99 @@ struct TomlPreflightConfig {
     max_replication_lag_mb: Option<i64>,
     max_replication_lag_secs: Option<i64>,
     long_query_threshold_secs: Option<i64>,
+    replica_urls: Option<Vec<String>>,
+    replica_lag_gate: Option<bool>,
+    replica_lag_timeout_secs: Option<u64>,
+    wal_budget_mb: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlBackupConfig {
+    command: Option<String>,
+    timeout_secs: Option<u64>,
+    on_failure: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlNotifyConfig {
+    enabled: Option<bool>,
+    channel: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlNotificationsConfig {
+    webhooks: Option<Vec<TomlWebhookConfig>>,
+    timeout_secs: Option<u64>,
+    retries: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TomlWebhookConfig {
+    url: String,
+    format: Option<String>,
+    events: Option<Vec<String>>,
+    headers: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlOutputConfig {
+    redact: Option<Vec<String>>,
+    redact_mode: Option<String>,
+    hash_salt: Option<String>,
+    commands: Option<HashMap<String, Vec<String>>>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlReceiptConfig {
+    enabled: Option<bool>,
+    dir: Option<String>,
+    history: Option<bool>,
+    key: Option<String>,
+    key_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlDriftConfig {
+    store_baseline: Option<bool>,
+    publish_fingerprint: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlSeedsConfig {
+    locations: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlChecksConfig {
+    locations: Option<Vec<String>>,
+    after_migrate: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlAuditConfig {
+    enabled: Option<bool>,
+    git_commit: Option<String>,
+    ci_build_url: Option<String>,
+    hostname: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlServerConfig {
+    bind: Option<String>,
+    token: Option<String>,
+    allow_plain_http: Option<bool>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlExportConfig {
+    seed: Option<String>,
+    anonymize: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Default)]
+struct TomlPrerequisitesConfig {
+    schemas: Option<Vec<String>>,
+    extensions: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Default)]
@@ -328,6 +861,7 @@ struct TomlNamedDatabaseConfig {
     name: Option<String>,
     url: Option<String>,
     depends_on: Option<Vec<String>>,
+    tags: Option<Vec<String>>,
     migrations: Option<TomlMigrationSettings>,
     hooks: Option<TomlHooksConfig>,
     placeholders: Option<HashMap<String, String>>,
@@ -339,11 +873,15 @@ struct TomlHooksConfig {
     after_migrate: Option<Vec<String>>,
     before_each_migrate: Option<Vec<String>>,
     after_each_migrate: Option<Vec<String>>,
+    before_migrate_cmd: Option<Vec<String>>,
+    after_migrate_cmd: Option<Vec<String>>,
+    command_timeout: Option<u64>,
 }
 
 #[derive(Deserialize, Default)]
 struct TomlGuardsConfig {
     on_require_fail: Option<String>,
+    targets: Option<HashMap<String, String>>,
 }
 
 #[derive(Deserialize, Default)]
@@ -359,6 +897,10 @@ struct TomlSafetyConfig {
     large_table_threshold: Option<i64>,
     huge_table_threshold: Option<i64>,
     refresh_stats_mysql: Option<bool>,
+    block_privileged: Option<bool>,
+    lock_timeout_ms: Option<u64>,
+    ddl_retry: Option<u32>,
+    ddl_retry_backoff_ms: Option<u64>,
 }
 
 #[derive(Deserialize, Default)]
@@ -372,6 +914,12 @@ struct TomlSimulationConfig {
     simulate_before_migrate: Option<bool>,
 }
 
+#[derive(Deserialize, Default)]
+struct TomlModeConfig {
+    strict: Option<bool>,
+    dev: Option<bool>,
+}
+
 /// CLI overrides that take highest priority.
 #[derive(Debug, Default, Clone)]
 pub struct CliOverrides {
@@ -385,6 +933,10 @@ pub struct CliOverrides {
     pub locations: Option<Vec<PathBuf>>,
     /// Override whether out-of-order migrations are allowed.
     pub out_of_order: Option<bool>,
+    /// Override the versions to cherry-pick (`migrate --versions`).
+    pub cherry_pick: Option<Vec<String>>,
+    /// Override the versions to skip (`migrate --skip-versions`).
+    pub skip_versions: Option<Vec<String>>,
     /// Override whether to validate checksums on migrate.
     pub validate_on_migrate: Option<bool>,
     /// Override the baseline version string.
@@ -397,14 +949,38 @@ pub struct CliOverrides {
     pub connect_timeout: Option<u32>,
     /// Override the statement timeout in seconds.
     pub statement_timeout: Option<u32>,
+    /// Override the introspection query timeout in seconds.
+    pub introspection_timeout: Option<u32>,
+    /// Override whether extension-owned objects are included in drift,
+    /// diff and clean.
+    pub include_extension_objects: Option<bool>,
+    /// Override the object-name include patterns.
+    pub include_objects: Option<Vec<String>>,
+    /// Override the object-name exclude patterns.
+    pub exclude_objects: Option<Vec<String>>,
     /// Override the logical environment name.
     pub environment: Option<String>,
     /// Override whether to use dependency-based migration ordering.
     pub dependency_ordering: Option<bool>,
+    /// Override whether to take the global lock exclusively.
+    pub global_lock: Option<bool>,
+    /// Override whether read-only commands may run against a standby.
+    pub allow_standby: Option<bool>,
     /// Override TCP keepalive interval in seconds.
     pub keepalive: Option<u32>,
     /// Override batch transaction mode (all-or-nothing).
     pub batch_transaction: Option<bool>,
+    /// Resume a failed non-transactional migration from its failed statement.
+    pub resume: Option<bool>,
+    /// Override strict mode.
+    pub strict: Option<bool>,
+    /// Override dev mode.
+    pub dev: Option<bool>,
+    /// File of placeholder values (`.json` object or `.env` lines), layered
+    /// over TOML and env placeholders.
+    pub placeholders_file: Option<PathBuf>,
+    /// `--placeholder key=value` pairs, layered over everything else.
+    pub placeholders: Vec<(String, String)>,
 }
 
 impl WaypointConfig {
@@ -436,7 +1012,7 @@ impl WaypointConfig {
                     toml_path, e
                 ))
             })?;
-            config.apply_toml(toml_config);
+            config.apply_toml(toml_config)?;
         } else if config_path.is_some() {
             // If explicitly specified, error if not found
             return Err(WaypointError::ConfigError(format!(
@@ -446,10 +1022,26 @@ impl WaypointConfig {
         }
 
         // Layer 2: Environment variables
-        config.apply_env();
+        config.apply_env()?;
+
+        // Dev mode sits between env and CLI: it replaces file/env settings,
+        // but explicit CLI flags still win.
+        apply_option!(overrides.strict => config.mode.strict);
+        apply_option!(overrides.dev => config.mode.dev);
+        config.apply_dev()?;
 
         // Layer 1: CLI overrides
         config.apply_cli(overrides);
+        config.apply_cli_placeholders(overrides)?;
+
+        config.apply_strict()?;
+
+        // [guards] applies to every [[databases]] entry.
+        if let Some(databases) = config.multi_database.as_mut() {
+            for db in databases {
+                db.guards = config.guards.clone();
+            }
+        }
 
         // Validate identifiers
         crate::db::validate_identifier(&config.migrations.schema)?;
@@ -464,7 +1056,7 @@ impl WaypointConfig {
         Ok(config)
     }
 
-    fn apply_toml(&mut self, toml: TomlConfig) {
+    fn apply_toml(&mut self, toml: TomlConfig) -> Result<()> {
         if let Some(db) = toml.database {
             apply_option_some!(db.url => self.database.url);
             apply_option_some!(db.host => self.database.host);
@@ -484,7 +1076,20 @@ impl WaypointConfig {
             }
             apply_option!(db.connect_timeout => self.database.connect_timeout_secs);
             apply_option!(db.statement_timeout => self.database.statement_timeout_secs);
+            apply_option!(db.introspection_timeout => self.database.introspection_timeout_secs);
+            apply_option!(db.include_extension_objects => self.database.include_extension_objects);
+            apply_option!(db.include_objects => self.database.include_objects);
+            apply_option!(db.exclude_objects => self.database.exclude_objects);
             apply_option!(db.keepalive => self.database.keepalive_secs);
+            if let Some(v) = db.dialect {
+                match v.parse() {
+                    Ok(d) => self.database.dialect = d,
+                    Err(_) => log::warn!(
+                        "Invalid dialect '{}' in config, using default 'auto'. Valid values: auto, postgres, mysql, cockroach",
+                        v
+                    ),
+                }
+            }
         }
 
         if let Some(m) = toml.migrations {
@@ -494,14 +1099,60 @@ impl WaypointConfig {
             apply_option!(m.table => self.migrations.table);
             apply_option!(m.schema => self.migrations.schema);
             apply_option!(m.out_of_order => self.migrations.out_of_order);
+            if let Some(v) = m.out_of_order_policy {
+                match v.parse() {
+                    Ok(policy) => self.migrations.out_of_order_policy = policy,
+                    Err(e) => log::warn!("{}; ignoring out_of_order_policy", e),
+                }
+            }
+            if let Some(v) = m.version_scheme {
+                self.migrations.version_scheme = v.parse()?;
+            }
+            apply_option!(m.cherry_pick => self.migrations.cherry_pick);
+            apply_option!(m.skip_versions => self.migrations.skip_versions);
             apply_option!(m.validate_on_migrate => self.migrations.validate_on_migrate);
+            if let Some(v) = m.checksum_algorithm {
+                match v.parse() {
+                    Ok(algorithm) => self.migrations.checksum_algorithm = algorithm,
+                    Err(e) => log::warn!("{}; ignoring checksum_algorithm", e),
+                }
+            }
+            apply_option!(m.checksum_transition => self.migrations.checksum_transition);
             apply_option!(m.clean_enabled => self.migrations.clean_enabled);
             apply_option!(m.baseline_version => self.migrations.baseline_version);
             apply_option_some!(m.installed_by => self.migrations.installed_by);
             apply_option_some!(m.environment => self.migrations.environment);
             apply_option!(m.dependency_ordering => self.migrations.dependency_ordering);
+            apply_option!(m.parallel_migrations => self.migrations.parallel_migrations);
+            if let Some(v) = m.lock_key {
+                match v.parse() {
+                    Ok(key) => self.migrations.lock_key = key,
+                    Err(e) => log::warn!("{}; ignoring lock_key", e),
+                }
+            }
+            apply_option!(m.global_lock => self.migrations.global_lock);
+            apply_option!(m.lock_wait_timeout_secs => self.migrations.lock_wait_timeout_secs);
+            apply_option!(m.lock_ttl_secs => self.migrations.lock_ttl_secs);
+            apply_option!(m.schedule_claim_ttl_secs => self.migrations.schedule_claim_ttl_secs);
+            apply_option!(m.allow_standby => self.migrations.allow_standby);
+            apply_option_some!(m.standby_scratch_url => self.migrations.standby_scratch_url);
             apply_option!(m.show_progress => self.migrations.show_progress);
             apply_option!(m.batch_transaction => self.migrations.batch_transaction);
+            apply_option!(m.statement_progress => self.migrations.statement_progress);
+            apply_option_some!(m.warn_rows_affected => self.migrations.warn_rows_affected);
+            apply_option!(m.post_migrate_analyze => self.migrations.post_migrate_analyze);
+            apply_option!(m.post_migrate_vacuum => self.migrations.post_migrate_vacuum);
+            apply_option!(m.post_migrate_min_table_mb => self.migrations.post_migrate_min_table_mb);
+            apply_option!(m.post_migrate_min_rows => self.migrations.post_migrate_min_rows);
+            apply_option!(m.max_migration_bytes => self.migrations.max_migration_bytes);
+            apply_option!(m.max_statements => self.migrations.max_statements);
+            apply_option!(m.schemas => self.migrations.schemas);
+            if let Some(v) = m.isolation_level {
+                match v.parse() {
+                    Ok(level) => self.migrations.isolation_level = Some(level),
+                    Err(e) => log::warn!("{}; ignoring isolation_level", e),
+                }
+            }
         }
 
         if let Some(h) = toml.hooks {
@@ -517,6 +1168,9 @@ impl WaypointConfig {
             if let Some(v) = h.after_each_migrate {
                 self.hooks.after_each_migrate = v.into_iter().map(PathBuf::from).collect();
             }
+            apply_option!(h.before_migrate_cmd => self.hooks.before_migrate_cmd);
+            apply_option!(h.after_migrate_cmd => self.hooks.after_migrate_cmd);
+            apply_option!(h.command_timeout => self.hooks.command_timeout_secs);
         }
 
         if let Some(p) = toml.placeholders {
@@ -525,6 +1179,10 @@ impl WaypointConfig {
 
         if let Some(l) = toml.lint {
             apply_option!(l.disabled_rules => self.lint.disabled_rules);
+            apply_option!(l.strict => self.lint.strict);
+            apply_option!(l.allowed_environments => self.lint.allowed_environments);
+            apply_option!(l.warn_migration_bytes => self.lint.warn_migration_bytes);
+            apply_option!(l.warn_statements => self.lint.warn_statements);
         }
 
         if let Some(s) = toml.snapshots {
@@ -541,6 +1199,10 @@ impl WaypointConfig {
             apply_option!(p.max_replication_lag_mb => self.preflight.max_replication_lag_mb);
             apply_option!(p.max_replication_lag_secs => self.preflight.max_replication_lag_secs);
             apply_option!(p.long_query_threshold_secs => self.preflight.long_query_threshold_secs);
+            apply_option!(p.replica_urls => self.preflight.replica_urls);
+            apply_option!(p.replica_lag_gate => self.preflight.replica_lag_gate);
+            apply_option!(p.replica_lag_timeout_secs => self.preflight.replica_lag_timeout_secs);
+            apply_option!(p.wal_budget_mb => self.preflight.wal_budget_mb);
         }
 
         if let Some(g) = toml.guards {
@@ -553,6 +1215,13 @@ impl WaypointConfig {
                     ),
                 }
             }
+            if let Some(targets) = g.targets {
+                // Lowercased like the WAYPOINT_GUARDS_TARGET_* overrides.
+                self.guards.targets = targets
+                    .into_iter()
+                    .map(|(name, url)| (name.to_lowercase(), url))
+                    .collect();
+            }
         }
 
         if let Some(r) = toml.reversals {
@@ -566,6 +1235,10 @@ impl WaypointConfig {
             apply_option!(s.large_table_threshold => self.safety.large_table_threshold);
             apply_option!(s.huge_table_threshold => self.safety.huge_table_threshold);
             apply_option!(s.refresh_stats_mysql => self.safety.refresh_stats_mysql);
+            apply_option!(s.block_privileged => self.safety.block_privileged);
+            apply_option!(s.lock_timeout_ms => self.safety.lock_timeout_ms);
+            apply_option!(s.ddl_retry => self.safety.ddl_retry);
+            apply_option!(s.ddl_retry_backoff_ms => self.safety.ddl_retry_backoff_ms);
         }
 
         if let Some(a) = toml.advisor {
@@ -577,6 +1250,148 @@ impl WaypointConfig {
             apply_option!(s.simulate_before_migrate => self.simulation.simulate_before_migrate);
         }
 
+        if let Some(m) = toml.mode {
+            apply_option!(m.strict => self.mode.strict);
+            apply_option!(m.dev => self.mode.dev);
+        }
+
+        if let Some(p) = toml.prerequisites {
+            apply_option!(p.schemas => self.prerequisites.schemas);
+            apply_option!(p.extensions => self.prerequisites.extensions);
+        }
+
+        if let Some(sv) = toml.server {
+            apply_option!(sv.bind => self.server.bind);
+            apply_option_some!(sv.token => self.server.token);
+            apply_option!(sv.allow_plain_http => self.server.allow_plain_http);
+        }
+
+        if let Some(b) = toml.backup {
+            apply_option_some!(b.command => self.backup.command);
+            apply_option!(b.timeout_secs => self.backup.timeout_secs);
+            if let Some(v) = b.on_failure {
+                match v.parse() {
+                    Ok(policy) => self.backup.on_failure = policy,
+                    Err(e) => log::warn!("{}; using default 'abort'", e),
+                }
+            }
+        }
+
+        if let Some(n) = toml.notify {
+            apply_option!(n.enabled => self.notify.enabled);
+            apply_option!(n.channel => self.notify.channel);
+        }
+
+        if let Some(n) = toml.notifications {
+            apply_option!(n.timeout_secs => self.notifications.timeout_secs);
+            apply_option!(n.retries => self.notifications.retries);
+            for w in n.webhooks.unwrap_or_default() {
+                // A typo would otherwise silently drop the alerts it was for.
+                let invalid = |e: String| {
+                    WaypointError::ConfigError(format!("[[notifications.webhooks]] {}", e))
+                };
+                let format = match w.format.as_deref() {
+                    None => Default::default(),
+                    Some(format) => format.parse().map_err(invalid)?,
+                };
+                let events = match w.events {
+                    None => crate::notify::LifecycleKind::ALL.to_vec(),
+                    Some(names) => names
+                        .iter()
+                        .map(|name| name.parse().map_err(invalid))
+                        .collect::<Result<_>>()?,
+                };
+                self.notifications
+                    .webhooks
+                    .push(crate::notify::WebhookConfig {
+                        url: w.url,
+                        format,
+                        events,
+                        headers: w.headers.unwrap_or_default(),
+                    });
+            }
+        }
+
+        if let Some(e) = toml.export {
+            apply_option!(e.seed => self.export.seed);
+            // Fail closed: a typo must not export the column in cleartext.
+            for (column, v) in e.anonymize.unwrap_or_default() {
+                let strategy = v.parse().map_err(|e| {
+                    WaypointError::ConfigError(format!("[export.anonymize] {}: {}", column, e))
+                })?;
+                self.export.anonymize.insert(column, strategy);
+            }
+        }
+
+        for (command, v) in toml.policy.unwrap_or_default() {
+            if !crate::policy::COMMANDS.contains(&command.as_str()) {
+                log::warn!("Unknown command in [policy]; command={}", command);
+            }
+            // Fail closed: a typo in a policy value must not allow the command.
+            let policy = v.parse().unwrap_or_else(|e| {
+                log::warn!("{}; treating '{}' as 'never'", e, command);
+                crate::policy::CommandPolicy::Never
+            });
+            self.policy.commands.insert(command, policy);
+        }
+
+        if let Some(o) = toml.output {
+            if let Some(v) = o.redact {
+                self.output.redact = parse_redact_fields(&v);
+            }
+            if let Some(v) = o.redact_mode {
+                match v.parse() {
+                    Ok(mode) => self.output.redact_mode = mode,
+                    Err(e) => log::warn!("{}; using default 'hash'", e),
+                }
+            }
+            apply_option_some!(o.hash_salt => self.output.hash_salt);
+            for (command, v) in o.commands.unwrap_or_default() {
+                if !crate::policy::COMMANDS.contains(&command.as_str()) {
+                    log::warn!("Unknown command in [output.commands]; command={}", command);
+                }
+                self.output
+                    .commands
+                    .insert(command, parse_redact_fields(&v));
+            }
+        }
+
+        if let Some(r) = toml.receipts {
+            apply_option!(r.enabled => self.receipts.enabled);
+            if let Some(dir) = r.dir {
+                // An empty `dir` keeps receipts out of the filesystem.
+                self.receipts.dir = (!dir.is_empty()).then(|| PathBuf::from(dir));
+            }
+            apply_option!(r.history => self.receipts.history);
+            apply_option_some!(r.key => self.receipts.key);
+            apply_option_some!(r.key_id => self.receipts.key_id);
+        }
+
+        if let Some(d) = toml.drift {
+            apply_option!(d.store_baseline => self.drift.store_baseline);
+            apply_option!(d.publish_fingerprint => self.drift.publish_fingerprint);
+        }
+
+        if let Some(s) = toml.seeds {
+            if let Some(v) = s.locations {
+                self.seeds.locations = v.iter().map(|l| normalize_location(l)).collect();
+            }
+        }
+
+        if let Some(c) = toml.checks {
+            if let Some(v) = c.locations {
+                self.checks.locations = v.iter().map(|l| normalize_location(l)).collect();
+            }
+            apply_option!(c.after_migrate => self.checks.after_migrate);
+        }
+
+        if let Some(a) = toml.audit {
+            apply_option!(a.enabled => self.audit.enabled);
+            apply_option_some!(a.git_commit => self.audit.git_commit);
+            apply_option_some!(a.ci_build_url => self.audit.ci_build_url);
+            apply_option_some!(a.hostname => self.audit.hostname);
+        }
+
         if let Some(databases) = toml.databases {
             let mut named_dbs = Vec::new();
             for db in databases {
@@ -598,14 +1413,60 @@ impl WaypointConfig {
                     apply_option!(m.table => mig_settings.table);
                     apply_option!(m.schema => mig_settings.schema);
                     apply_option!(m.out_of_order => mig_settings.out_of_order);
+                    if let Some(v) = m.out_of_order_policy {
+                        match v.parse() {
+                            Ok(policy) => mig_settings.out_of_order_policy = policy,
+                            Err(e) => log::warn!("{}; ignoring out_of_order_policy", e),
+                        }
+                    }
+                    if let Some(v) = m.version_scheme {
+                        mig_settings.version_scheme = v.parse()?;
+                    }
+                    apply_option!(m.cherry_pick => mig_settings.cherry_pick);
+                    apply_option!(m.skip_versions => mig_settings.skip_versions);
                     apply_option!(m.validate_on_migrate => mig_settings.validate_on_migrate);
+                    if let Some(v) = m.checksum_algorithm {
+                        match v.parse() {
+                            Ok(algorithm) => mig_settings.checksum_algorithm = algorithm,
+                            Err(e) => log::warn!("{}; ignoring checksum_algorithm", e),
+                        }
+                    }
+                    apply_option!(m.checksum_transition => mig_settings.checksum_transition);
                     apply_option!(m.clean_enabled => mig_settings.clean_enabled);
                     apply_option!(m.baseline_version => mig_settings.baseline_version);
                     apply_option_some!(m.installed_by => mig_settings.installed_by);
                     apply_option_some!(m.environment => mig_settings.environment);
                     apply_option!(m.dependency_ordering => mig_settings.dependency_ordering);
+                    apply_option!(m.parallel_migrations => mig_settings.parallel_migrations);
+                    if let Some(v) = m.lock_key {
+                        match v.parse() {
+                            Ok(key) => mig_settings.lock_key = key,
+                            Err(e) => log::warn!("{}; ignoring lock_key", e),
+                        }
+                    }
+                    apply_option!(m.global_lock => mig_settings.global_lock);
+                    apply_option!(m.lock_wait_timeout_secs => mig_settings.lock_wait_timeout_secs);
+                    apply_option!(m.lock_ttl_secs => mig_settings.lock_ttl_secs);
+                    apply_option!(m.schedule_claim_ttl_secs => mig_settings.schedule_claim_ttl_secs);
+                    apply_option!(m.allow_standby => mig_settings.allow_standby);
+                    apply_option_some!(m.standby_scratch_url => mig_settings.standby_scratch_url);
                     apply_option!(m.show_progress => mig_settings.show_progress);
                     apply_option!(m.batch_transaction => mig_settings.batch_transaction);
+                    apply_option!(m.statement_progress => mig_settings.statement_progress);
+                    apply_option_some!(m.warn_rows_affected => mig_settings.warn_rows_affected);
+                    apply_option!(m.post_migrate_analyze => mig_settings.post_migrate_analyze);
+                    apply_option!(m.post_migrate_vacuum => mig_settings.post_migrate_vacuum);
+                    apply_option!(m.post_migrate_min_table_mb => mig_settings.post_migrate_min_table_mb);
+                    apply_option!(m.post_migrate_min_rows => mig_settings.post_migrate_min_rows);
+                    apply_option!(m.max_migration_bytes => mig_settings.max_migration_bytes);
+                    apply_option!(m.max_statements => mig_settings.max_statements);
+                    apply_option!(m.schemas => mig_settings.schemas);
+                    if let Some(v) = m.isolation_level {
+                        match v.parse() {
+                            Ok(level) => mig_settings.isolation_level = Some(level),
+                            Err(e) => log::warn!("{}; ignoring isolation_level", e),
+                        }
+                    }
                 }
 
                 let mut hooks_config = HooksConfig::default();
@@ -624,6 +1485,9 @@ impl WaypointConfig {
                         hooks_config.after_each_migrate =
                             v.into_iter().map(PathBuf::from).collect();
                     }
+                    apply_option!(h.before_migrate_cmd => hooks_config.before_migrate_cmd);
+                    apply_option!(h.after_migrate_cmd => hooks_config.after_migrate_cmd);
+                    apply_option!(h.command_timeout => hooks_config.command_timeout_secs);
                 }
 
                 named_dbs.push(crate::multi::NamedDatabaseConfig {
@@ -633,13 +1497,16 @@ impl WaypointConfig {
                     hooks: hooks_config,
                     placeholders: db.placeholders.unwrap_or_default(),
                     depends_on: db.depends_on.unwrap_or_default(),
+                    tags: db.tags.unwrap_or_default(),
+                    guards: Default::default(),
                 });
             }
             self.multi_database = Some(named_dbs);
         }
+        Ok(())
     }
 
-    fn apply_env(&mut self) {
+    fn apply_env(&mut self) -> Result<()> {
         if let Ok(v) = std::env::var("WAYPOINT_DATABASE_URL") {
             self.database.url = Some(v);
         }
@@ -670,6 +1537,11 @@ impl WaypointConfig {
                 self.database.ssl_mode = mode;
             }
         }
+        if let Ok(v) = std::env::var("WAYPOINT_DATABASE_DIALECT") {
+            if let Ok(d) = v.parse() {
+                self.database.dialect = d;
+            }
+        }
         if let Ok(v) = std::env::var("WAYPOINT_CONNECT_TIMEOUT") {
             if let Ok(n) = v.parse::<u32>() {
                 self.database.connect_timeout_secs = n;
@@ -680,6 +1552,45 @@ impl WaypointConfig {
                 self.database.statement_timeout_secs = n;
             }
         }
+        if let Ok(v) = std::env::var("WAYPOINT_INTROSPECTION_TIMEOUT") {
+            if let Ok(n) = v.parse::<u32>() {
+                self.database.introspection_timeout_secs = n;
+            }
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_INCLUDE_EXTENSION_OBJECTS") {
+            self.database.include_extension_objects = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_INCLUDE_OBJECTS") {
+            self.database.include_objects = split_versions(&v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_EXCLUDE_OBJECTS") {
+            self.database.exclude_objects = split_versions(&v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_MAX_MIGRATION_BYTES") {
+            if let Ok(n) = v.parse::<u64>() {
+                self.migrations.max_migration_bytes = n;
+            }
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_MAX_STATEMENTS") {
+            if let Ok(n) = v.parse::<usize>() {
+                self.migrations.max_statements = n;
+            }
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_LOCK_WAIT_TIMEOUT_SECS") {
+            if let Ok(n) = v.parse::<u32>() {
+                self.migrations.lock_wait_timeout_secs = n;
+            }
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_LOCK_TIMEOUT_MS") {
+            if let Ok(n) = v.parse::<u64>() {
+                self.safety.lock_timeout_ms = n;
+            }
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_DDL_RETRY") {
+            if let Ok(n) = v.parse::<u32>() {
+                self.safety.ddl_retry = n;
+            }
+        }
         if let Ok(v) = std::env::var("WAYPOINT_MIGRATIONS_LOCATIONS") {
             self.migrations.locations =
                 v.split(',').map(|s| normalize_location(s.trim())).collect();
@@ -690,34 +1601,162 @@ impl WaypointConfig {
         if let Ok(v) = std::env::var("WAYPOINT_MIGRATIONS_SCHEMA") {
             self.migrations.schema = v;
         }
+        if let Ok(v) = std::env::var("WAYPOINT_MIGRATIONS_SCHEMAS") {
+            self.migrations.schemas = v
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect();
+        }
 
         if let Ok(v) = std::env::var("WAYPOINT_KEEPALIVE") {
             if let Ok(n) = v.parse::<u32>() {
                 self.database.keepalive_secs = n;
             }
         }
+        if let Ok(v) = std::env::var("WAYPOINT_PARALLEL_MIGRATIONS") {
+            if let Ok(n) = v.parse::<usize>() {
+                self.migrations.parallel_migrations = n;
+            }
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_CHERRY_PICK") {
+            self.migrations.cherry_pick = split_versions(&v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_SKIP_VERSIONS") {
+            self.migrations.skip_versions = split_versions(&v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_VERSION_SCHEME") {
+            self.migrations.version_scheme = v.parse()?;
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_CHECKSUM_ALGORITHM") {
+            match v.parse() {
+                Ok(algorithm) => self.migrations.checksum_algorithm = algorithm,
+                Err(e) => log::warn!("{}; ignoring WAYPOINT_CHECKSUM_ALGORITHM", e),
+            }
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_CHECKSUM_TRANSITION") {
+            self.migrations.checksum_transition = v == "1" || v.eq_ignore_ascii_case("true");
+        }
         if let Ok(v) = std::env::var("WAYPOINT_BATCH_TRANSACTION") {
             self.migrations.batch_transaction = v == "1" || v.eq_ignore_ascii_case("true");
         }
-        if let Ok(v) = std::env::var("WAYPOINT_ENVIRONMENT") {
-            self.migrations.environment = Some(v);
+        if let Ok(v) = std::env::var("WAYPOINT_GLOBAL_LOCK") {
+            self.migrations.global_lock = v == "1" || v.eq_ignore_ascii_case("true");
         }
-
-        // Scan for placeholder env vars: WAYPOINT_PLACEHOLDER_{KEY}
-        for (key, value) in std::env::vars() {
-            if let Some(placeholder_key) = key.strip_prefix("WAYPOINT_PLACEHOLDER_") {
-                self.placeholders
-                    .insert(placeholder_key.to_lowercase(), value);
+        if let Ok(v) = std::env::var("WAYPOINT_STATEMENT_PROGRESS") {
+            self.migrations.statement_progress = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_ALLOW_STANDBY") {
+            self.migrations.allow_standby = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_STANDBY_SCRATCH_URL") {
+            self.migrations.standby_scratch_url = Some(v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_POST_MIGRATE_ANALYZE") {
+            self.migrations.post_migrate_analyze = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_BACKUP_COMMAND") {
+            self.backup.command = Some(v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_NOTIFY") {
+            self.notify.enabled = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_NOTIFY_CHANNEL") {
+            self.notify.channel = v;
+        }
+        for (var, format) in [
+            ("WAYPOINT_WEBHOOK_URL", crate::notify::WebhookFormat::Json),
+            (
+                "WAYPOINT_SLACK_WEBHOOK_URL",
+                crate::notify::WebhookFormat::Slack,
+            ),
+        ] {
+            if let Ok(url) = std::env::var(var) {
+                self.notifications
+                    .webhooks
+                    .push(crate::notify::WebhookConfig {
+                        url,
+                        format,
+                        events: crate::notify::LifecycleKind::ALL.to_vec(),
+                        headers: HashMap::new(),
+                    });
             }
         }
-    }
-
-    fn apply_cli(&mut self, overrides: &CliOverrides) {
-        apply_option_some_clone!(overrides.url => self.database.url);
-        apply_option_clone!(overrides.schema => self.migrations.schema);
-        apply_option_clone!(overrides.table => self.migrations.table);
+        if let Ok(v) = std::env::var("WAYPOINT_REDACT") {
+            let fields: Vec<String> = v.split(',').map(|s| s.trim().to_string()).collect();
+            self.output.redact = parse_redact_fields(&fields);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_REDACT_SALT") {
+            self.output.hash_salt = Some(v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_EXPORT_SEED") {
+            self.export.seed = v;
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_RECEIPTS") {
+            self.receipts.enabled = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_RECEIPT_KEY") {
+            self.receipts.key = Some(v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_DRIFT_STORE_BASELINE") {
+            self.drift.store_baseline = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_DRIFT_PUBLISH_FINGERPRINT") {
+            self.drift.publish_fingerprint = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_AUDIT") {
+            self.audit.enabled = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_AUDIT_GIT_COMMIT") {
+            self.audit.git_commit = Some(v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_AUDIT_CI_BUILD_URL") {
+            self.audit.ci_build_url = Some(v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_SERVER_BIND") {
+            self.server.bind = v;
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_SERVER_TOKEN") {
+            self.server.token = Some(v);
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_SERVER_ALLOW_PLAIN_HTTP") {
+            self.server.allow_plain_http = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_STRICT") {
+            self.mode.strict = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_DEV") {
+            self.mode.dev = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Ok(v) = std::env::var("WAYPOINT_ENVIRONMENT") {
+            self.migrations.environment = Some(v);
+        }
+
+        // Scan for placeholder env vars: WAYPOINT_PLACEHOLDER_{KEY}
+        for (key, value) in std::env::vars() {
+            if let Some(placeholder_key) = key.strip_prefix("WAYPOINT_PLACEHOLDER_") {
+                self.placeholders
+                    .insert(placeholder_key.to_lowercase(), value);
+            } else if let Some(target) = key.strip_prefix("WAYPOINT_GUARDS_TARGET_") {
+                self.guards.targets.insert(target.to_lowercase(), value);
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_cli(&mut self, overrides: &CliOverrides) {
+        apply_option_some_clone!(overrides.url => self.database.url);
+        if let Some(ref schema) = overrides.schema {
+            // An explicit --schema runs against that schema alone.
+            self.migrations.schema = schema.clone();
+            self.migrations.schemas.clear();
+        }
+        apply_option_clone!(overrides.table => self.migrations.table);
         apply_option_clone!(overrides.locations => self.migrations.locations);
         apply_option!(overrides.out_of_order => self.migrations.out_of_order);
+        apply_option_clone!(overrides.cherry_pick => self.migrations.cherry_pick);
+        apply_option_clone!(overrides.skip_versions => self.migrations.skip_versions);
         apply_option!(overrides.validate_on_migrate => self.migrations.validate_on_migrate);
         apply_option_clone!(overrides.baseline_version => self.migrations.baseline_version);
         apply_option!(overrides.connect_retries => self.database.connect_retries);
@@ -729,10 +1768,111 @@ impl WaypointConfig {
         }
         apply_option!(overrides.connect_timeout => self.database.connect_timeout_secs);
         apply_option!(overrides.statement_timeout => self.database.statement_timeout_secs);
+        apply_option!(overrides.introspection_timeout => self.database.introspection_timeout_secs);
+        apply_option!(overrides.include_extension_objects => self.database.include_extension_objects);
+        apply_option_clone!(overrides.include_objects => self.database.include_objects);
+        apply_option_clone!(overrides.exclude_objects => self.database.exclude_objects);
         apply_option_some_clone!(overrides.environment => self.migrations.environment);
         apply_option!(overrides.dependency_ordering => self.migrations.dependency_ordering);
+        apply_option!(overrides.global_lock => self.migrations.global_lock);
+        apply_option!(overrides.allow_standby => self.migrations.allow_standby);
         apply_option!(overrides.keepalive => self.database.keepalive_secs);
         apply_option!(overrides.batch_transaction => self.migrations.batch_transaction);
+        apply_option!(overrides.resume => self.migrations.resume);
+        apply_option!(overrides.strict => self.mode.strict);
+        apply_option!(overrides.dev => self.mode.dev);
+    }
+
+    /// Layer `--placeholders-file` and then `--placeholder` values over the
+    /// TOML and env placeholders, for the top-level config and every
+    /// `[[databases]]` entry.
+    fn apply_cli_placeholders(&mut self, overrides: &CliOverrides) -> Result<()> {
+        let mut layered = match &overrides.placeholders_file {
+            Some(path) => crate::placeholder::load_placeholders_file(path)?,
+            None => HashMap::new(),
+        };
+        layered.extend(overrides.placeholders.iter().cloned());
+        if layered.is_empty() {
+            return Ok(());
+        }
+        // Lookup is case-insensitive, so drop any differently-cased key the
+        // override replaces.
+        let replace = |map: &mut HashMap<String, String>| {
+            map.retain(|k, _| !layered.keys().any(|l| l.eq_ignore_ascii_case(k) && l != k));
+            map.extend(layered.clone());
+        };
+        replace(&mut self.placeholders);
+        for db in self.multi_database.iter_mut().flatten() {
+            replace(&mut db.placeholders);
+        }
+        Ok(())
+    }
+
+    /// Apply dev mode, if enabled: skip pre-flight checks, safety analysis
+    /// and simulation, allow `clean` and out-of-order migrations, and fail
+    /// fast on connection problems (5s connect timeout, no retries). Errors
+    /// if strict mode is also enabled.
+    ///
+    /// [`load`](Self::load) applies this before CLI overrides, so explicit
+    /// flags such as `--connect-timeout` still win.
+    pub fn apply_dev(&mut self) -> Result<()> {
+        if !self.mode.dev {
+            return Ok(());
+        }
+        if self.mode.strict {
+            return Err(WaypointError::ConfigError(
+                "Dev mode and strict mode cannot both be enabled".to_string(),
+            ));
+        }
+        self.preflight.enabled = false;
+        self.safety.enabled = false;
+        self.simulation.simulate_before_migrate = false;
+        self.migrations.clean_enabled = true;
+        self.migrations.out_of_order = true;
+        self.database.connect_timeout_secs = 5;
+        self.database.connect_retries = 0;
+        Ok(())
+    }
+
+    /// Apply strict mode, if enabled: turn on validate-on-migrate, pre-flight
+    /// checks, safety analysis with DANGER blocking, simulation before
+    /// migrate, and strict lint. Errors if `out_of_order` is enabled (or
+    /// `out_of_order_policy = "always"`), since strict mode forbids it;
+    /// `patch-only` hotfixes are still allowed.
+    pub fn apply_strict(&mut self) -> Result<()> {
+        if !self.mode.strict {
+            return Ok(());
+        }
+        if self.migrations.out_of_order_policy() == OutOfOrderPolicy::Always {
+            return Err(WaypointError::ConfigError(
+                "out_of_order is not allowed in strict mode".to_string(),
+            ));
+        }
+        self.migrations.validate_on_migrate = true;
+        self.preflight.enabled = true;
+        self.safety.enabled = true;
+        self.safety.block_on_danger = true;
+        self.simulation.simulate_before_migrate = true;
+        self.lint.strict = true;
+        Ok(())
+    }
+
+    /// Schemas `migrate` and `info` run against: `schemas`, or just `schema`
+    /// when it is empty.
+    pub fn target_schemas(&self) -> Vec<String> {
+        if self.migrations.schemas.is_empty() {
+            vec![self.migrations.schema.clone()]
+        } else {
+            self.migrations.schemas.clone()
+        }
+    }
+
+    /// A copy of this config that runs against `schema` alone.
+    pub fn for_schema(&self, schema: &str) -> WaypointConfig {
+        let mut config = self.clone();
+        config.migrations.schema = schema.to_string();
+        config.migrations.schemas.clear();
+        config
     }
 
     /// Build a connection string from the config.
@@ -840,9 +1980,72 @@ pub fn normalize_location(location: &str) -> PathBuf {
     PathBuf::from(stripped)
 }
 
+/// Parse `s`, a number followed by one of `units` (suffix and its length in
+/// seconds), such as `30m` or `2w`. `what` names the value in the error. The
+/// suffix is split off as a character, and a value too large to represent
+/// is an error rather than an overflow.
+pub fn parse_duration_units(
+    what: &str,
+    s: &str,
+    units: &[(char, u64)],
+) -> Result<std::time::Duration> {
+    let s = s.trim();
+    let invalid = || {
+        let names: Vec<String> = units.iter().map(|(u, _)| u.to_string()).collect();
+        let expected = match names.split_last() {
+            Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+            _ => names.concat(),
+        };
+        WaypointError::ConfigError(format!(
+            "Invalid {} '{}': expected a number followed by {}",
+            what, s, expected
+        ))
+    };
+    let mut chars = s.chars();
+    let unit = chars.next_back().ok_or_else(invalid)?;
+    let n: u64 = chars.as_str().parse().map_err(|_| invalid())?;
+    let (_, seconds) = units.iter().find(|(u, _)| *u == unit).ok_or_else(invalid)?;
+    n.checked_mul(*seconds)
+        .map(std::time::Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// Parse `[output] redact` entries. Fails closed: an unknown entry redacts
+/// every field rather than leaking the one that was misspelled.
+fn parse_redact_fields(values: &[String]) -> Vec<crate::redact::RedactField> {
+    use crate::redact::RedactField;
+    let mut fields = Vec::new();
+    for v in values.iter().filter(|v| !v.is_empty()) {
+        match v.parse() {
+            Ok(field) => fields.push(field),
+            Err(e) => {
+                log::warn!("{}; redacting every field", e);
+                return vec![
+                    RedactField::TableNames,
+                    RedactField::ColumnNames,
+                    RedactField::Sql,
+                    RedactField::Scripts,
+                    RedactField::Messages,
+                ];
+            }
+        }
+    }
+    fields
+}
+
+/// Split a comma-separated version list from an environment variable.
+fn split_versions(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::migration::MigrationVersion;
 
     #[test]
     fn test_default_config() {
@@ -893,6 +2096,52 @@ mod tests {
         assert!(config.connection_string().is_err());
     }
 
+    #[test]
+    fn test_cli_placeholder_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("vars.env");
+        std::fs::write(&file, "ENV=from_file\nregion=eu\n").unwrap();
+
+        let mut config = WaypointConfig::default();
+        let toml_str = r#"
+[placeholders]
+env = "from_toml"
+owner = "app"
+
+[[databases]]
+name = "main"
+url = "postgres://localhost/main"
+
+[databases.placeholders]
+env = "from_db_toml"
+"#;
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        config
+            .apply_cli_placeholders(&CliOverrides {
+                placeholders_file: Some(file),
+                placeholders: vec![("region".to_string(), "us".to_string())],
+                ..Default::default()
+            })
+            .unwrap();
+
+        // The file's `ENV` replaces TOML's `env` rather than sitting beside it.
+        assert_eq!(config.placeholders.get("ENV").unwrap(), "from_file");
+        assert!(!config.placeholders.contains_key("env"));
+        assert_eq!(config.placeholders.get("owner").unwrap(), "app");
+        assert_eq!(config.placeholders.get("region").unwrap(), "us");
+        let db = &config.multi_database.as_ref().unwrap()[0];
+        assert_eq!(db.placeholders.get("ENV").unwrap(), "from_file");
+        assert_eq!(db.placeholders.get("region").unwrap(), "us");
+
+        let missing = CliOverrides {
+            placeholders_file: Some(dir.path().join("nope.json")),
+            ..Default::default()
+        };
+        assert!(config.apply_cli_placeholders(&missing).is_err());
+    }
+
     #[test]
     fn test_cli_overrides() {
         let mut config = WaypointConfig::default();
@@ -902,16 +2151,29 @@ mod tests {
             table: Some("custom_table".to_string()),
             locations: Some(vec![PathBuf::from("custom/path")]),
             out_of_order: Some(true),
+            cherry_pick: None,
+            skip_versions: None,
             validate_on_migrate: Some(false),
             baseline_version: Some("5".to_string()),
             connect_retries: None,
             ssl_mode: None,
             connect_timeout: None,
             statement_timeout: None,
+            introspection_timeout: None,
+            include_extension_objects: None,
+            include_objects: None,
+            exclude_objects: None,
             environment: None,
             dependency_ordering: None,
+            global_lock: None,
+            allow_standby: None,
             keepalive: None,
             batch_transaction: None,
+            resume: None,
+            strict: None,
+            dev: None,
+            placeholders_file: None,
+            placeholders: Vec::new(),
         };
 
         config.apply_cli(&overrides);
@@ -950,7 +2212,7 @@ app_name = "myapp"
 
         let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
         let mut config = WaypointConfig::default();
-        config.apply_toml(toml_config);
+        config.apply_toml(toml_config).unwrap();
 
         assert_eq!(
             config.database.url.as_deref(),
@@ -967,6 +2229,884 @@ app_name = "myapp"
         assert_eq!(config.placeholders.get("app_name").unwrap(), "myapp");
     }
 
+    #[test]
+    fn test_toml_prerequisites() {
+        let toml_str = r#"
+[prerequisites]
+schemas = ["app"]
+extensions = ["pgcrypto", "uuid-ossp"]
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(config.prerequisites.schemas, vec!["app"]);
+        assert_eq!(
+            config.prerequisites.extensions,
+            vec!["pgcrypto", "uuid-ossp"]
+        );
+    }
+
+    #[test]
+    fn test_toml_statement_progress_and_rows_threshold() {
+        let toml_str = r#"
+[migrations]
+statement_progress = true
+warn_rows_affected = 50000
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert!(config.migrations.statement_progress);
+        assert_eq!(config.migrations.warn_rows_affected, Some(50000));
+    }
+
+    #[test]
+    fn test_toml_post_migrate_analyze() {
+        let toml_str = r#"
+[migrations]
+post_migrate_analyze = true
+post_migrate_vacuum = true
+post_migrate_min_table_mb = 100
+post_migrate_min_rows = 500
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert!(config.migrations.post_migrate_analyze);
+        assert!(config.migrations.post_migrate_vacuum);
+        assert_eq!(config.migrations.post_migrate_min_table_mb, 100);
+        assert_eq!(config.migrations.post_migrate_min_rows, 500);
+    }
+
+    #[test]
+    fn test_toml_isolation_level() {
+        let toml_str = r#"
+[migrations]
+isolation_level = "repeatable read"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.migrations.isolation_level,
+            Some(IsolationLevel::RepeatableRead)
+        );
+    }
+
+    #[test]
+    fn test_toml_checksum_algorithm() {
+        let toml_str = r#"
+[migrations]
+checksum_algorithm = "sha256"
+checksum_transition = true
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert_eq!(
+            config.migrations.checksum_algorithm,
+            ChecksumAlgorithm::Crc32
+        );
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.migrations.checksum_algorithm,
+            ChecksumAlgorithm::Sha256
+        );
+        assert!(config.migrations.checksum_transition);
+    }
+
+    #[test]
+    fn test_toml_lint_allowed_environments() {
+        let toml_str = r#"
+[lint]
+allowed_environments = ["dev", "staging", "prod"]
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert!(config.lint.allowed_environments.is_empty());
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.lint.allowed_environments,
+            vec!["dev", "staging", "prod"]
+        );
+    }
+
+    #[test]
+    fn test_toml_scan_limits() {
+        let toml_str = r#"
+[migrations]
+max_migration_bytes = 2048
+max_statements = 0
+
+[lint]
+warn_statements = 50
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.migrations.scan_limits(), ScanLimits::default());
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.migrations.scan_limits(),
+            ScanLimits {
+                max_bytes: 2048,
+                max_statements: 0,
+                ..ScanLimits::default()
+            }
+        );
+        assert_eq!(config.lint.warn_statements, 50);
+        assert_eq!(config.lint.warn_migration_bytes, 1024 * 1024);
+    }
+
+    #[test]
+    fn test_toml_schemas() {
+        let toml_str = r#"
+[migrations]
+schema = "app"
+schemas = ["tenant_a", "tenant_b"]
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.target_schemas(), vec!["public"]);
+        config.apply_toml(toml_config).unwrap();
+        assert_eq!(config.target_schemas(), vec!["tenant_a", "tenant_b"]);
+
+        let tenant = config.for_schema("tenant_b");
+        assert_eq!(tenant.migrations.schema, "tenant_b");
+        assert_eq!(tenant.target_schemas(), vec!["tenant_b"]);
+
+        config.apply_cli(&CliOverrides {
+            schema: Some("other".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(config.target_schemas(), vec!["other"]);
+    }
+
+    #[test]
+    fn test_toml_version_scheme() {
+        let toml_str = r#"
+[migrations]
+version_scheme = "lexicographic"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.migrations.version_scheme, VersionScheme::Numeric);
+        config.apply_toml(toml_config).unwrap();
+        assert_eq!(
+            config.migrations.version_scheme,
+            VersionScheme::Lexicographic
+        );
+
+        assert_eq!(
+            config
+                .migrations
+                .parse_version("1_2")
+                .unwrap()
+                .cmp(&config.migrations.parse_version("1.10").unwrap()),
+            std::cmp::Ordering::Greater
+        );
+
+        let toml_config: TomlConfig =
+            toml::from_str("[migrations]\nversion_scheme = \"natural\"\n").unwrap();
+        assert!(matches!(
+            config.apply_toml(toml_config),
+            Err(WaypointError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_toml_out_of_order_policy() {
+        let toml_str = r#"
+[migrations]
+out_of_order_policy = "patch-only"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.migrations.out_of_order_policy(),
+            OutOfOrderPolicy::PatchOnly
+        );
+        config.mode.strict = true;
+        assert!(config.apply_strict().is_ok());
+
+        config.migrations.out_of_order = true;
+        assert_eq!(
+            config.migrations.out_of_order_policy(),
+            OutOfOrderPolicy::Always
+        );
+        assert!(config.apply_strict().is_err());
+    }
+
+    #[test]
+    fn test_version_filter_settings() {
+        let toml_str = r#"
+[migrations]
+cherry_pick = ["4", "7"]
+skip_versions = ["6"]
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+        assert_eq!(config.migrations.cherry_pick, vec!["4", "7"]);
+        assert_eq!(config.migrations.skip_versions, vec!["6"]);
+
+        config.apply_cli(&CliOverrides {
+            cherry_pick: Some(vec!["9".to_string()]),
+            ..Default::default()
+        });
+        let filter = config.migrations.version_filter().unwrap();
+        assert!(filter.selects(&MigrationVersion::parse("9").unwrap()));
+        assert!(!filter.selects(&MigrationVersion::parse("4").unwrap()));
+
+        config.migrations.skip_versions = vec!["six".to_string()];
+        assert!(config.migrations.version_filter().is_err());
+    }
+
+    #[test]
+    fn test_toml_output_redact() {
+        use crate::redact::{RedactField, RedactMode};
+
+        let toml_str = r#"
+[output]
+redact = ["table_names", "sql"]
+redact_mode = "mask"
+
+[output.commands]
+info = []
+drift = ["tables", "bogus"]
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.output.redact,
+            vec![RedactField::TableNames, RedactField::Sql]
+        );
+        assert_eq!(config.output.redact_mode, RedactMode::Mask);
+        assert!(!config.output.redactor("info").is_active());
+        assert!(config.output.redactor("migrate").redacts(RedactField::Sql));
+        // An unknown entry fails closed.
+        assert!(config
+            .output
+            .redactor("drift")
+            .redacts(RedactField::Messages));
+    }
+
+    #[test]
+    fn test_toml_lock_settings() {
+        let toml_str = r#"
+[migrations]
+lock_key = "table"
+global_lock = true
+lock_wait_timeout_secs = 30
+lock_ttl_secs = 0
+schedule_claim_ttl_secs = 600
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.migrations.lock_key, LockKey::Schema);
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(config.migrations.lock_key, LockKey::Table);
+        assert!(config.migrations.global_lock);
+        assert_eq!(config.migrations.lock_wait_timeout_secs, 30);
+        assert_eq!(config.migrations.lock_ttl_secs, 0);
+        assert_eq!(config.migrations.schedule_claim_ttl_secs, 600);
+        assert!("row".parse::<LockKey>().is_err());
+    }
+
+    #[test]
+    fn test_toml_standby_settings() {
+        let toml_str = r#"
+[migrations]
+allow_standby = true
+standby_scratch_url = "postgres://u:p@primary:5432/scratch"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert!(!config.migrations.allow_standby);
+        config.apply_toml(toml_config).unwrap();
+
+        assert!(config.migrations.allow_standby);
+        assert_eq!(
+            config.migrations.standby_scratch_url.as_deref(),
+            Some("postgres://u:p@primary:5432/scratch")
+        );
+    }
+
+    #[test]
+    fn test_toml_preflight_replicas() {
+        let toml_str = r#"
+[preflight]
+replica_urls = ["postgres://u:p@replica1:5432/db"]
+replica_lag_gate = true
+replica_lag_timeout_secs = 60
+wal_budget_mb = 2048
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.preflight.replica_urls,
+            vec!["postgres://u:p@replica1:5432/db"]
+        );
+        assert!(config.preflight.replica_lag_gate);
+        assert_eq!(config.preflight.replica_lag_timeout_secs, 60);
+        assert_eq!(config.preflight.wal_budget_mb, 2048);
+    }
+
+    #[test]
+    fn test_toml_guard_targets() {
+        let toml_str = r#"
+[guards]
+on_require_fail = "warn"
+
+[guards.targets]
+Prod = "postgres://u:p@prod:5432/app"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.guards.on_require_fail,
+            crate::guard::OnRequireFail::Warn
+        );
+        assert_eq!(
+            config.guards.targets.get("prod").map(String::as_str),
+            Some("postgres://u:p@prod:5432/app")
+        );
+    }
+
+    #[test]
+    fn test_toml_backup() {
+        let toml_str = r#"
+[backup]
+command = "pgbackrest --stanza=main backup --type=incr"
+timeout_secs = 600
+on_failure = "warn"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(
+            config.backup.command.as_deref(),
+            Some("pgbackrest --stanza=main backup --type=incr")
+        );
+        assert_eq!(config.backup.timeout_secs, 600);
+        assert_eq!(
+            config.backup.on_failure,
+            crate::backup::BackupFailurePolicy::Warn
+        );
+    }
+
+    #[test]
+    fn test_toml_notify() {
+        let mut config = WaypointConfig::default();
+        assert!(!config.notify.enabled);
+        assert_eq!(config.notify.channel, "waypoint_migrations");
+
+        let toml_config: TomlConfig =
+            toml::from_str("[notify]\nenabled = true\nchannel = \"app_schema\"\n").unwrap();
+        config.apply_toml(toml_config).unwrap();
+        assert!(config.notify.enabled);
+        assert_eq!(config.notify.channel, "app_schema");
+    }
+
+    #[test]
+    fn test_toml_notifications() {
+        let mut config = WaypointConfig::default();
+        assert!(config.notifications.webhooks.is_empty());
+
+        let toml_config: TomlConfig = toml::from_str(
+            r#"
+[notifications]
+timeout_secs = 3
+retries = 5
+
+[[notifications.webhooks]]
+url = "https://hooks.slack.com/services/T0/B0/x"
+format = "slack"
+events = ["migrate_failure", "drift-detected"]
+
+[[notifications.webhooks]]
+url = "https://ops.example.com/hook"
+headers = { Authorization = "Bearer t0ken" }
+"#,
+        )
+        .unwrap();
+        config.apply_toml(toml_config).unwrap();
+        let n = &config.notifications;
+        assert_eq!((n.timeout_secs, n.retries), (3, 5));
+        assert_eq!(n.webhooks[0].format, crate::notify::WebhookFormat::Slack);
+        assert_eq!(
+            n.webhooks[0].events,
+            vec![
+                crate::notify::LifecycleKind::MigrateFailure,
+                crate::notify::LifecycleKind::DriftDetected
+            ]
+        );
+        assert_eq!(n.webhooks[1].format, crate::notify::WebhookFormat::Json);
+        assert_eq!(n.webhooks[1].events.len(), 4);
+        assert!(!format!("{:?}", n).contains("t0ken"));
+
+        for webhook in [
+            r#"url = "https://x"
+events = ["deploy"]"#,
+            r#"url = "https://x"
+format = "teams""#,
+        ] {
+            let toml_config: TomlConfig =
+                toml::from_str(&format!("[[notifications.webhooks]]\n{}\n", webhook)).unwrap();
+            let err = WaypointConfig::default()
+                .apply_toml(toml_config)
+                .unwrap_err();
+            assert!(matches!(err, WaypointError::ConfigError(_)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_toml_receipts() {
+        let mut config = WaypointConfig::default();
+        assert!(!config.receipts.enabled);
+        assert_eq!(config.receipts.dir, Some(PathBuf::from("receipts")));
+
+        let toml_str = r#"
+[receipts]
+enabled = true
+dir = ""
+history = true
+key = "k"
+key_id = "2026-q1"
+"#;
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        assert!(config.receipts.enabled);
+        assert!(config.receipts.history);
+        assert_eq!(config.receipts.dir, None);
+        assert_eq!(config.receipts.key_id.as_deref(), Some("2026-q1"));
+        assert!(!format!("{:?}", config.receipts).contains("\"k\""));
+    }
+
+    #[test]
+    fn test_toml_drift() {
+        let mut config = WaypointConfig::default();
+        assert!(!config.drift.store_baseline);
+
+        let toml_str = r#"
+[drift]
+store_baseline = true
+publish_fingerprint = true
+"#;
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        assert!(config.drift.store_baseline);
+        assert!(config.drift.publish_fingerprint);
+    }
+
+    #[test]
+    fn test_toml_seeds() {
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.seeds.locations, vec![PathBuf::from("db/seeds")]);
+
+        let toml_str = r#"
+[seeds]
+locations = ["filesystem:sql/seeds", "sql/fixtures"]
+"#;
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        assert_eq!(
+            config.seeds.locations,
+            vec![PathBuf::from("sql/seeds"), PathBuf::from("sql/fixtures")]
+        );
+    }
+
+    #[test]
+    fn test_toml_checks() {
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.checks.locations, vec![PathBuf::from("db/checks")]);
+        assert!(!config.checks.after_migrate);
+
+        let toml_str = r#"
+[checks]
+locations = ["filesystem:sql/checks"]
+after_migrate = true
+"#;
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        assert_eq!(config.checks.locations, vec![PathBuf::from("sql/checks")]);
+        assert!(config.checks.after_migrate);
+    }
+
+    #[test]
+    fn test_toml_audit() {
+        let mut config = WaypointConfig::default();
+        assert!(config.audit.enabled);
+        assert_eq!(config.audit.git_commit, None);
+
+        let toml_str = r#"
+[audit]
+enabled = false
+git_commit = "abc123"
+hostname = "deploy-1"
+"#;
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        assert!(!config.audit.enabled);
+        assert_eq!(config.audit.git_commit.as_deref(), Some("abc123"));
+        assert_eq!(config.audit.hostname.as_deref(), Some("deploy-1"));
+        assert_eq!(config.audit.ci_build_url, None);
+    }
+
+    #[test]
+    fn test_toml_introspection_timeout() {
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.database.introspection_timeout_secs, 0);
+
+        let toml_str = r#"
+[database]
+statement_timeout = 600
+introspection_timeout = 20
+"#;
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        assert_eq!(config.database.statement_timeout_secs, 600);
+        assert_eq!(config.database.introspection_timeout_secs, 20);
+    }
+
+    #[test]
+    fn test_include_extension_objects() {
+        let mut config = WaypointConfig::default();
+        assert!(!config.database.include_extension_objects);
+
+        config
+            .apply_toml(
+                toml::from_str(
+                    r#"
+[database]
+include_extension_objects = true
+"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(config.database.include_extension_objects);
+
+        config.apply_cli(&CliOverrides {
+            include_extension_objects: Some(false),
+            ..Default::default()
+        });
+        assert!(!config.database.include_extension_objects);
+    }
+
+    #[test]
+    fn test_toml_hook_commands() {
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.hooks.command_timeout_secs, 300);
+
+        config
+            .apply_toml(
+                toml::from_str(
+                    r#"
+[hooks]
+before_migrate = ["hooks/before.sql"]
+before_migrate_cmd = ["./scripts/notify.sh start"]
+after_migrate_cmd = ["./scripts/flush-cache.sh", "./scripts/notify.sh done"]
+command_timeout = 30
+"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(config.hooks.before_migrate.len(), 1);
+        assert_eq!(
+            config.hooks.before_migrate_cmd,
+            vec!["./scripts/notify.sh start"]
+        );
+        assert_eq!(config.hooks.after_migrate_cmd.len(), 2);
+        assert_eq!(config.hooks.command_timeout_secs, 30);
+    }
+
+    #[test]
+    fn test_object_filter_patterns() {
+        let mut config = WaypointConfig::default();
+        assert!(config.database.object_filter().is_empty());
+
+        config
+            .apply_toml(
+                toml::from_str(
+                    r#"
+[database]
+include_objects = ["app_*"]
+exclude_objects = ["tmp_*", "*_old"]
+"#,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        let filter = config.database.object_filter();
+        assert!(filter.allows("app_users"));
+        assert!(!filter.allows("app_users_old"));
+        assert!(!filter.allows("users"));
+
+        config.apply_cli(&CliOverrides {
+            include_objects: Some(vec![]),
+            exclude_objects: Some(vec!["audit_*".into()]),
+            ..Default::default()
+        });
+        assert!(config.database.include_objects.is_empty());
+        assert_eq!(config.database.exclude_objects, vec!["audit_*"]);
+    }
+
+    #[test]
+    fn test_toml_server() {
+        let toml_str = r#"
+[server]
+bind = "0.0.0.0:9090"
+token = "s3cret"
+allow_plain_http = true
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        assert_eq!(config.server.bind, "127.0.0.1:8080");
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(config.server.bind, "0.0.0.0:9090");
+        assert_eq!(config.server.token.as_deref(), Some("s3cret"));
+        assert!(!format!("{:?}", config.server).contains("s3cret"));
+        assert!(config.server.allow_plain_http);
+    }
+
+    #[test]
+    fn test_toml_policy() {
+        use crate::policy::CommandPolicy;
+        let toml_str = r#"
+[policy]
+clean = "never"
+undo = "require_override"
+migrate = "allowed"
+repair = "nevr"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(config.policy.get("clean"), CommandPolicy::Never);
+        assert_eq!(config.policy.get("undo"), CommandPolicy::RequireOverride);
+        assert_eq!(config.policy.get("migrate"), CommandPolicy::Allowed);
+        assert_eq!(config.policy.get("repair"), CommandPolicy::Never);
+        assert_eq!(config.policy.get("info"), CommandPolicy::Allowed);
+    }
+
+    #[test]
+    fn test_toml_export() {
+        use crate::commands::export::AnonymizeStrategy;
+        let toml_str = r#"
+[export]
+seed = "dev"
+
+[export.anonymize]
+email = "email"
+"users.ssn" = "redact"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(config.export.seed, "dev");
+        assert_eq!(
+            config.export.anonymize.get("email"),
+            Some(&AnonymizeStrategy::Email)
+        );
+        assert_eq!(
+            config.export.anonymize.get("users.ssn"),
+            Some(&AnonymizeStrategy::Redact)
+        );
+
+        let toml_config: TomlConfig =
+            toml::from_str("[export.anonymize]\nnotes = \"bogus\"\n").unwrap();
+        let err = WaypointConfig::default()
+            .apply_toml(toml_config)
+            .unwrap_err();
+        assert!(err.to_string().contains("notes"));
+    }
+
+    #[test]
+    fn test_strict_mode_enables_guardrails() {
+        let toml_str = r#"
+[mode]
+strict = true
+
+[safety]
+block_on_danger = false
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+        config.migrations.validate_on_migrate = false;
+        config.apply_strict().unwrap();
+
+        assert!(config.mode.strict);
+        assert!(config.migrations.validate_on_migrate);
+        assert!(config.preflight.enabled);
+        assert!(config.safety.enabled);
+        assert!(config.safety.block_on_danger);
+        assert!(config.simulation.simulate_before_migrate);
+        assert!(config.lint.strict);
+
+        config.migrations.out_of_order = true;
+        assert!(config.apply_strict().is_err());
+    }
+
+    #[test]
+    fn test_strict_mode_applies_to_named_databases() {
+        let toml_str = r#"
+[mode]
+strict = true
+
+[policy]
+clean = "never"
+
+[[databases]]
+name = "main"
+url = "postgres://localhost/main"
+
+[databases.migrations]
+validate_on_migrate = false
+
+[[databases]]
+name = "loose"
+url = "postgres://localhost/loose"
+
+[databases.migrations]
+out_of_order = true
+"#;
+        let mut config = WaypointConfig::default();
+        config
+            .apply_toml(toml::from_str(toml_str).unwrap())
+            .unwrap();
+        config.apply_strict().unwrap();
+
+        let databases = config.multi_database.clone().unwrap();
+        let main = databases[0].merge_into(&config).unwrap();
+        assert!(main.mode.strict);
+        assert!(main.migrations.validate_on_migrate);
+        assert!(main.safety.block_on_danger);
+        assert!(main.preflight.enabled);
+        assert_eq!(
+            main.policy.get("clean"),
+            crate::policy::CommandPolicy::Never
+        );
+        assert_eq!(
+            main.database.url.as_deref(),
+            Some("postgres://localhost/main")
+        );
+
+        // Strict mode forbids out_of_order on a named database too.
+        assert!(databases[1].merge_into(&config).is_err());
+    }
+
+    #[test]
+    fn test_dev_mode_profile() {
+        let toml_str = r#"
+[mode]
+dev = true
+
+[preflight]
+enabled = true
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+        config.apply_dev().unwrap();
+
+        assert!(!config.preflight.enabled);
+        assert!(!config.safety.enabled);
+        assert!(!config.simulation.simulate_before_migrate);
+        assert!(config.migrations.clean_enabled);
+        assert!(config.migrations.out_of_order);
+        assert_eq!(config.database.connect_timeout_secs, 5);
+        assert_eq!(config.database.connect_retries, 0);
+
+        config.mode.strict = true;
+        assert!(config.apply_dev().is_err());
+    }
+
+    #[test]
+    fn test_toml_dialect_cockroach() {
+        let toml_str = r#"
+[database]
+url = "postgresql://root@localhost:26257/defaultdb"
+dialect = "cockroach"
+"#;
+
+        let toml_config: TomlConfig = toml::from_str(toml_str).unwrap();
+        let mut config = WaypointConfig::default();
+        config.apply_toml(toml_config).unwrap();
+
+        assert_eq!(config.database.dialect, DialectSetting::Cockroach);
+        assert_eq!(
+            config.database.dialect.kind(),
+            Some(crate::dialect::DialectKind::Postgres)
+        );
+    }
+
+    #[test]
+    fn test_dialect_setting_parse() {
+        assert_eq!(
+            "auto".parse::<DialectSetting>().unwrap(),
+            DialectSetting::Auto
+        );
+        assert_eq!(
+            "MySQL".parse::<DialectSetting>().unwrap(),
+            DialectSetting::Mysql
+        );
+        assert_eq!(
+            "cockroachdb".parse::<DialectSetting>().unwrap(),
+            DialectSetting::Cockroach
+        );
+        assert!("sqlite".parse::<DialectSetting>().is_err());
+        assert_eq!(DialectSetting::Auto.kind(), None);
+    }
+
     #[test]
     fn test_normalize_jdbc_url_with_credentials() {
         let url = "jdbc:postgresql://myhost:5432/mydb?user=admin&password=secret";
@@ -1046,4 +3186,20 @@ app_name = "myapp"
         let conn = config.connection_string().unwrap();
         assert!(conn.contains("password='p@ss\\'w ord'"));
     }
+
+    #[test]
+    fn test_parse_duration_units() {
+        use std::time::Duration;
+        let parse = crate::multi::parse_duration;
+        assert_eq!(parse("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse(" 2h ").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse("0m").unwrap(), Duration::ZERO);
+        for bad in ["", "s", "30", "30x", "3é", "é", "-5m"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+        // Overflow is an error, not a panic.
+        assert!(parse(&format!("{}h", u64::MAX)).is_err());
+        let err = parse("30x").unwrap_err().to_string();
+        assert!(err.contains("s, m or h"), "{}", err);
+    }
 }
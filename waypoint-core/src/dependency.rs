@@ -1,12 +1,14 @@
 //! Migration dependency graph with topological sort.
 //!
 //! Supports `-- waypoint:depends V3,V5` directives for non-linear
-//! migration ordering using Kahn's algorithm.
+//! migration ordering using Kahn's algorithm. With `dependency_ordering`,
+//! [`resolve_pending`] orders a migrate run by the graph and pulls in
+//! dependencies above `--target` that the selected migrations need.
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use crate::error::{Result, WaypointError};
-use crate::migration::ResolvedMigration;
+use crate::migration::{MigrationVersion, ResolvedMigration};
 
 /// A directed acyclic graph of migration dependencies.
 pub struct DependencyGraph {
@@ -86,9 +88,20 @@ impl DependencyGraph {
 
     /// Produce a topologically sorted order of versions using Kahn's algorithm.
     ///
-    /// Uses borrowed `&str` references internally to avoid cloning during
-    /// the sort; only clones into owned `String`s for the output.
+    /// Among versions whose dependencies are all sorted, the lowest version
+    /// goes first, so the order only departs from version order where a
+    /// dependency requires it. Uses borrowed `&str` references internally to
+    /// avoid cloning during the sort; only clones into owned `String`s for
+    /// the output.
     pub fn topological_sort(&self) -> Result<Vec<String>> {
+        // Position in version order, used to pick the lowest ready version
+        let position: HashMap<&str, usize> = self
+            .all_versions
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.as_str(), i))
+            .collect();
+
         // Compute in-degree for each node using borrowed keys
         let mut in_degree: HashMap<&str, usize> = HashMap::new();
         for v in &self.all_versions {
@@ -96,16 +109,17 @@ impl DependencyGraph {
         }
 
         // Start with nodes that have no dependencies
-        let mut queue: VecDeque<&str> = VecDeque::new();
-        for v in &self.all_versions {
+        let mut ready: BTreeSet<usize> = BTreeSet::new();
+        for (i, v) in self.all_versions.iter().enumerate() {
             if *in_degree.get(v.as_str()).unwrap_or(&0) == 0 {
-                queue.push_back(v);
+                ready.insert(i);
             }
         }
 
         let mut sorted = Vec::new();
 
-        while let Some(node) = queue.pop_front() {
+        while let Some(i) = ready.pop_first() {
+            let node = self.all_versions[i].as_str();
             sorted.push(node.to_string());
 
             // For each node that depends on this one, decrement in-degree
@@ -114,7 +128,7 @@ impl DependencyGraph {
                     let deg = in_degree.get_mut(dep.as_str()).unwrap();
                     *deg -= 1;
                     if *deg == 0 {
-                        queue.push_back(dep);
+                        ready.insert(position[dep.as_str()]);
                     }
                 }
             }
@@ -133,6 +147,38 @@ impl DependencyGraph {
         Ok(sorted)
     }
 
+    /// Versions `version` depends on directly.
+    pub fn dependencies(&self, version: &str) -> Vec<String> {
+        self.all_versions
+            .iter()
+            .filter(|v| {
+                self.edges
+                    .get(version)
+                    .is_some_and(|deps| deps.contains(*v))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Versions that depend on `version`, directly or transitively, in
+    /// version order.
+    pub fn dependents(&self, version: &str) -> Vec<String> {
+        let mut found: HashSet<&str> = HashSet::new();
+        let mut stack = vec![version];
+        while let Some(v) = stack.pop() {
+            for dependent in self.reverse_edges.get(v).into_iter().flatten() {
+                if found.insert(dependent) {
+                    stack.push(dependent);
+                }
+            }
+        }
+        self.all_versions
+            .iter()
+            .filter(|v| found.contains(v.as_str()))
+            .cloned()
+            .collect()
+    }
+
     /// Trace an actual cycle path for error reporting.
     fn trace_cycle(&self, in_degree: &HashMap<String, usize>) -> String {
         // Start from any node still in the cycle
@@ -188,6 +234,57 @@ impl DependencyGraph {
     }
 }
 
+/// Order the migrations selected for a run by their dependencies.
+///
+/// `versioned` holds every versioned migration that may run here; `pending`
+/// the ones selected (those up to `--target`). Transitive dependencies of the
+/// selection that `is_applied` reports as not yet applied are added, even when
+/// they are above the target. Returns the ordered set and the versions that
+/// were added, in the order they will run.
+pub fn resolve_pending<'a>(
+    versioned: &[&'a ResolvedMigration],
+    pending: &[&'a ResolvedMigration],
+    is_applied: impl Fn(&MigrationVersion) -> bool,
+) -> Result<(Vec<&'a ResolvedMigration>, Vec<String>)> {
+    let graph = DependencyGraph::build(versioned, true)?;
+    let order = graph.topological_sort()?;
+    let by_version: HashMap<&str, &'a ResolvedMigration> = versioned
+        .iter()
+        .filter_map(|m| m.version().map(|v| (v.raw.as_str(), *m)))
+        .collect();
+
+    let mut selected: HashSet<String> = pending
+        .iter()
+        .filter_map(|m| m.version().map(|v| v.raw.clone()))
+        .collect();
+    let mut added: HashSet<String> = HashSet::new();
+    let mut stack: Vec<String> = selected.iter().cloned().collect();
+    while let Some(version) = stack.pop() {
+        for dep in graph.edges.get(&version).into_iter().flatten() {
+            if selected.contains(dep) {
+                continue;
+            }
+            let Some(m) = by_version.get(dep.as_str()) else {
+                continue;
+            };
+            if m.version().is_some_and(&is_applied) {
+                continue;
+            }
+            selected.insert(dep.clone());
+            added.insert(dep.clone());
+            stack.push(dep.clone());
+        }
+    }
+
+    let ordered = order
+        .iter()
+        .filter(|v| selected.contains(*v))
+        .filter_map(|v| by_version.get(v.as_str()).copied())
+        .collect();
+    let added = order.into_iter().filter(|v| added.contains(v)).collect();
+    Ok((ordered, added))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,6 +303,7 @@ mod tests {
                 env: vec![],
                 ..Default::default()
             },
+            copy_checksum_sha256: None,
         }
     }
 
@@ -299,6 +397,10 @@ mod tests {
         // V1 must be first, V4 must be last
         assert_eq!(order[0], "1");
         assert_eq!(order[3], "4");
+
+        assert_eq!(graph.dependents("2"), vec!["4"]);
+        assert_eq!(graph.dependents("1"), vec!["2", "3", "4"]);
+        assert!(graph.dependents("4").is_empty());
     }
 
     #[test]
@@ -309,4 +411,38 @@ mod tests {
         let graph = DependencyGraph::build(&migrations, false).unwrap();
         assert!(graph.topological_sort().is_err());
     }
+
+    #[test]
+    fn test_resolve_pending_pulls_in_dependencies_above_target() {
+        let m1 = make_migration("1", vec![]);
+        let m2 = make_migration("2", vec!["4"]);
+        let m3 = make_migration("3", vec![]);
+        let m4 = make_migration("4", vec!["1"]);
+        let m5 = make_migration("5", vec![]);
+        let versioned: Vec<&ResolvedMigration> = vec![&m1, &m2, &m3, &m4, &m5];
+
+        // --target 2 with V1 applied: V2 needs V4, which is above the target.
+        let applied = |v: &MigrationVersion| v.raw == "1";
+        let (ordered, added) = resolve_pending(&versioned, &[&m2], applied).unwrap();
+        let order: Vec<&str> = ordered
+            .iter()
+            .map(|m| m.version().unwrap().raw.as_str())
+            .collect();
+        assert_eq!(order, vec!["4", "2"]);
+        assert_eq!(added, vec!["4"]);
+
+        // Without explicit dependencies the run stays in version order.
+        let applied_up_to_4 = |v: &MigrationVersion| v.raw != "3" && v.raw != "5";
+        let (ordered, _) = resolve_pending(&versioned, &[&m5, &m3], applied_up_to_4).unwrap();
+        let order: Vec<&str> = ordered
+            .iter()
+            .map(|m| m.version().unwrap().raw.as_str())
+            .collect();
+        assert_eq!(order, vec!["3", "5"]);
+
+        // Nothing is added when every dependency is already selected.
+        let (ordered, added) = resolve_pending(&versioned, &[&m2, &m4], applied).unwrap();
+        assert_eq!(ordered.len(), 2);
+        assert!(added.is_empty());
+    }
 }
@@ -34,14 +34,15 @@ impl DialectKind {
 
     /// Detect dialect from a connection URL scheme.
     ///
-    /// Recognises `postgres://`, `postgresql://`, `mysql://`. Returns `None` for
+    /// Recognises `postgres://`, `postgresql://`, `mysql://` and `mariadb://`
+    /// (MariaDB runs on the MySQL backend). Returns `None` for
     /// key=value style PG strings or unknown schemes — caller may need to fall
     /// back to an explicit `dialect = "..."` config field.
     pub fn from_url(url: &str) -> Option<Self> {
         let lower = url.trim_start().to_lowercase();
         if lower.starts_with("postgres://") || lower.starts_with("postgresql://") {
             Some(DialectKind::Postgres)
-        } else if lower.starts_with("mysql://") {
+        } else if lower.starts_with("mysql://") || lower.starts_with("mariadb://") {
             Some(DialectKind::Mysql)
         } else {
             None
@@ -49,6 +50,66 @@ impl DialectKind {
     }
 }
 
+/// PostgreSQL server version, used to gate version-dependent SQL and checks.
+///
+/// Parsed from `server_version_num`. For 10+ `minor` is the minor release
+/// (`16.2` → `{16, 2}`); for 9.x it is the second component of the major
+/// version (`9.6.24` → `{9, 6}`), so ordering matches PostgreSQL's own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PgVersion {
+    /// Major version (e.g. `16`, or `9` for 9.x).
+    pub major: u32,
+    /// Minor release (10+) or second major component (9.x).
+    pub minor: u32,
+}
+
+impl PgVersion {
+    /// Parse from the integer form of `server_version_num` (e.g. `160002`).
+    pub fn from_version_num(num: i32) -> Self {
+        let num = num.max(0) as u32;
+        if num >= 100_000 {
+            PgVersion {
+                major: num / 10_000,
+                minor: num % 10_000,
+            }
+        } else {
+            PgVersion {
+                major: num / 10_000,
+                minor: (num / 100) % 100,
+            }
+        }
+    }
+
+    /// `ALTER TABLE ... ADD COLUMN ... DEFAULT` without a table rewrite (PG 11+).
+    pub fn has_fast_column_default(&self) -> bool {
+        self.major >= 11
+    }
+
+    /// `SET NOT NULL` skips its table scan when a validated `IS NOT NULL`
+    /// check constraint exists (PG 12+).
+    pub fn has_not_null_from_check(&self) -> bool {
+        self.major >= 12
+    }
+
+    /// WAL-named functions and columns (`pg_wal_lsn_diff`, `replay_lsn`) (PG 10+).
+    /// Older servers use the `xlog`/`location` names.
+    pub fn has_wal_functions(&self) -> bool {
+        self.major >= 10
+    }
+
+    /// Trusted extensions that non-superusers may create, and the `trusted`
+    /// column of `pg_available_extension_versions` (PG 13+).
+    pub fn has_trusted_extensions(&self) -> bool {
+        self.major >= 13
+    }
+}
+
+impl std::fmt::Display for PgVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
 /// Describes how migrations should be split, locked, and tracked on a given engine.
 ///
 /// All methods are pure — they operate on strings or return DDL templates and do
@@ -122,6 +183,38 @@ mod tests {
             DialectKind::from_url("  mysql://h/d"),
             Some(DialectKind::Mysql)
         );
+        assert_eq!(
+            DialectKind::from_url("mariadb://u:p@h/d"),
+            Some(DialectKind::Mysql)
+        );
+    }
+
+    #[test]
+    fn pg_version_parses_modern_and_legacy() {
+        let v16 = PgVersion::from_version_num(160002);
+        assert_eq!(
+            v16,
+            PgVersion {
+                major: 16,
+                minor: 2
+            }
+        );
+        assert_eq!(v16.to_string(), "16.2");
+
+        let v96 = PgVersion::from_version_num(90624);
+        assert_eq!(v96, PgVersion { major: 9, minor: 6 });
+        assert!(v96 < PgVersion::from_version_num(100000));
+    }
+
+    #[test]
+    fn pg_version_capabilities() {
+        let v10 = PgVersion::from_version_num(100023);
+        assert!(v10.has_wal_functions());
+        assert!(!v10.has_fast_column_default());
+        assert!(PgVersion::from_version_num(110000).has_fast_column_default());
+        assert!(!PgVersion::from_version_num(90624).has_wal_functions());
+        assert!(!PgVersion::from_version_num(120015).has_trusted_extensions());
+        assert!(PgVersion::from_version_num(130000).has_trusted_extensions());
     }
 
     #[test]
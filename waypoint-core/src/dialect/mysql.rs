@@ -44,7 +44,13 @@ CREATE TABLE IF NOT EXISTS {fq} (
     installed_on   TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
     execution_time INT NOT NULL,
     success        BOOLEAN NOT NULL,
-    reversal_sql   LONGTEXT
+    reversal_sql   LONGTEXT,
+    backup_ref     TEXT,
+    checksum_sha256 VARCHAR(64),
+    git_commit     VARCHAR(64),
+    ci_build_url   TEXT,
+    hostname       VARCHAR(255),
+    waypoint_version VARCHAR(32)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci;
 
 CREATE INDEX {success_idx} ON {fq} (success);
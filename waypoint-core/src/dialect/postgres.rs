@@ -31,7 +31,13 @@ CREATE TABLE IF NOT EXISTS {fq} (
     installed_on   TIMESTAMPTZ NOT NULL DEFAULT now(),
     execution_time INTEGER NOT NULL,
     success        BOOLEAN NOT NULL,
-    reversal_sql   TEXT
+    reversal_sql   TEXT,
+    backup_ref     TEXT,
+    checksum_sha256 VARCHAR(64),
+    git_commit     VARCHAR(64),
+    ci_build_url   TEXT,
+    hostname       VARCHAR(255),
+    waypoint_version VARCHAR(32)
 );
 
 CREATE INDEX IF NOT EXISTS {success_idx} ON {fq} (success);
@@ -72,6 +78,12 @@ mod tests {
             "execution_time",
             "success",
             "reversal_sql",
+            "backup_ref",
+            "checksum_sha256",
+            "git_commit",
+            "ci_build_url",
+            "hostname",
+            "waypoint_version",
         ] {
             assert!(ddl.contains(col), "DDL missing column {}", col);
         }
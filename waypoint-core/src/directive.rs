@@ -4,9 +4,60 @@
 //! ```sql
 //! -- waypoint:env dev,staging
 //! -- waypoint:depends V3,V5
+//! -- waypoint:set statement_timeout=60s
 //! CREATE TABLE ...
 //! ```
 
+use crate::error::WaypointError;
+
+/// Transaction isolation level for a migration: `-- waypoint:isolation serializable`.
+///
+/// Ordered from weakest to strongest, so the strictest of several levels is
+/// their maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IsolationLevel {
+    /// `READ COMMITTED` (PostgreSQL's default).
+    ReadCommitted,
+    /// `REPEATABLE READ`.
+    RepeatableRead,
+    /// `SERIALIZABLE`.
+    Serializable,
+}
+
+impl IsolationLevel {
+    /// SQL keyword form, as used in `BEGIN ISOLATION LEVEL ...`.
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+impl std::fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_sql())
+    }
+}
+
+impl std::str::FromStr for IsolationLevel {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized = s.trim().to_lowercase().replace(['_', '-'], " ");
+        match normalized.split_whitespace().collect::<Vec<_>>().join(" ").as_str() {
+            "read committed" => Ok(IsolationLevel::ReadCommitted),
+            "repeatable read" => Ok(IsolationLevel::RepeatableRead),
+            "serializable" => Ok(IsolationLevel::Serializable),
+            _ => Err(WaypointError::ConfigError(format!(
+                "Invalid isolation level '{}'. Use 'read committed', 'repeatable read', or 'serializable'.",
+                s
+            ))),
+        }
+    }
+}
+
 /// Parsed directives from a migration file header.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct MigrationDirectives {
@@ -15,11 +66,299 @@ pub struct MigrationDirectives {
     /// Environment tags: `-- waypoint:env dev,staging`
     pub env: Vec<String>,
     /// Preconditions: `-- waypoint:require table_exists("users")`
-    pub require: Vec<String>,
+    pub require: Vec<GuardDirective>,
     /// Postconditions: `-- waypoint:ensure column_exists("users", "email")`
-    pub ensure: Vec<String>,
+    pub ensure: Vec<GuardDirective>,
     /// Safety override: `-- waypoint:safety-override` bypasses DANGER blocks
     pub safety_override: bool,
+    /// No transaction: `-- waypoint:no-transaction` runs each statement on its
+    /// own (PostgreSQL), e.g. for several `CREATE INDEX CONCURRENTLY` in one file
+    pub no_transaction: bool,
+    /// Isolation level: `-- waypoint:isolation serializable` (PostgreSQL;
+    /// ignored with `no-transaction`)
+    pub isolation: Option<IsolationLevel>,
+    /// Retries on serialization failure or deadlock: `-- waypoint:retries 3`
+    pub retries: u32,
+    /// Low priority: `-- waypoint:low-priority` marks follow-up work that
+    /// holds only weak locks (e.g. `VALIDATE CONSTRAINT`); it runs without
+    /// `statement_timeout` (PostgreSQL; ignored with `no-transaction`)
+    pub low_priority: bool,
+    /// Settings: `-- waypoint:set statement_timeout=60s, role=migrator`
+    /// (PostgreSQL), applied with `SET LOCAL` for the migration's transaction
+    pub settings: Vec<(String, String)>,
+    /// Application order of a repeatable migration: `-- waypoint:order 10`
+    /// (overrides an `R10__` filename prefix; ignored on versioned migrations)
+    pub order: Option<u32>,
+    /// CSV loads: `-- waypoint:copy table=users file=users.csv`, streamed
+    /// with `COPY ... FROM STDIN` after the file's SQL (PostgreSQL)
+    pub copy: Vec<CopyDirective>,
+}
+
+/// A `-- waypoint:copy` CSV load:
+/// `-- waypoint:copy table=users file=users.csv columns=id,email header=false`.
+///
+/// `table` (optionally `schema.table`) and `file` are required. `file` is
+/// relative to the directory holding the SQL file. `header` defaults to
+/// `true`, `delimiter` to a comma.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyDirective {
+    /// Target table, `table` or `schema.table`.
+    pub table: String,
+    /// CSV file, relative to the SQL file's directory.
+    pub file: String,
+    /// Columns the CSV fills, in file order; all columns when empty.
+    pub columns: Vec<String>,
+    /// Whether the first line is a header row to skip.
+    pub header: bool,
+    /// Field delimiter.
+    pub delimiter: char,
+}
+
+impl CopyDirective {
+    /// Parse the value of a `-- waypoint:copy` directive: space-separated
+    /// `key=value` pairs.
+    pub fn parse(value: &str) -> Result<CopyDirective, WaypointError> {
+        let invalid = |reason: String| {
+            WaypointError::ConfigError(format!("Invalid copy directive '{}': {}", value, reason))
+        };
+        let is_ident = |name: &str| {
+            name.chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+
+        let (mut table, mut file) = (None, None);
+        let mut directive = CopyDirective {
+            table: String::new(),
+            file: String::new(),
+            columns: Vec::new(),
+            header: true,
+            delimiter: ',',
+        };
+        for item in value.split_whitespace() {
+            let Some((key, setting)) = item.split_once('=') else {
+                return Err(invalid(format!("expected key=value, got '{}'", item)));
+            };
+            match key {
+                "table" => {
+                    if setting.split('.').count() > 2 || !setting.split('.').all(is_ident) {
+                        return Err(invalid(format!("'{}' is not a table name", setting)));
+                    }
+                    table = Some(setting.to_string());
+                }
+                "file" if !setting.is_empty() => file = Some(setting.to_string()),
+                "columns" => {
+                    directive.columns = setting.split(',').map(str::to_string).collect();
+                    if let Some(bad) = directive.columns.iter().find(|c| !is_ident(c)) {
+                        return Err(invalid(format!("'{}' is not a column name", bad)));
+                    }
+                }
+                "header" => {
+                    directive.header = match setting.to_lowercase().as_str() {
+                        "true" | "yes" | "on" => true,
+                        "false" | "no" | "off" => false,
+                        _ => return Err(invalid("header must be true or false".into())),
+                    }
+                }
+                "delimiter" => {
+                    let mut chars = setting.chars();
+                    directive.delimiter = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ if setting == "tab" => '\t',
+                        _ => return Err(invalid("delimiter must be one character".into())),
+                    };
+                }
+                _ => return Err(invalid(format!("unknown key '{}'", key))),
+            }
+        }
+        directive.table = table.ok_or_else(|| invalid("missing table=".into()))?;
+        directive.file = file.ok_or_else(|| invalid("missing file=".into()))?;
+        Ok(directive)
+    }
+}
+
+/// A `require` or `ensure` guard, with an optional failure message:
+/// `-- waypoint:require table_exists("users") :: "users table must exist"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardDirective {
+    /// Guard expression as written, without the message.
+    pub expression: String,
+    /// Message reported when the guard fails.
+    pub message: Option<String>,
+}
+
+impl GuardDirective {
+    /// Split a directive value into expression and message.
+    ///
+    /// The message follows the last `::` outside a string literal and must be
+    /// a double-quoted string (`\"` and `\\` are unescaped). Anything else is
+    /// left in the expression so the guard parser reports it.
+    pub fn parse(value: &str) -> GuardDirective {
+        let whole = || GuardDirective {
+            expression: value.trim().to_string(),
+            message: None,
+        };
+
+        let mut split = None;
+        let mut in_string = false;
+        let mut chars = value.char_indices().peekable();
+        while let Some((i, ch)) = chars.next() {
+            match ch {
+                '\\' if in_string => {
+                    chars.next();
+                }
+                '"' => in_string = !in_string,
+                ':' if !in_string && chars.peek().is_some_and(|&(_, c)| c == ':') => {
+                    split = Some(i);
+                    chars.next();
+                }
+                _ => {}
+            }
+        }
+
+        let Some(at) = split else {
+            return whole();
+        };
+        let expression = value[..at].trim();
+        let Some(quoted) = value[at + 2..]
+            .trim()
+            .strip_prefix('"')
+            .and_then(|m| m.strip_suffix('"'))
+        else {
+            return whole();
+        };
+
+        let mut message = String::with_capacity(quoted.len());
+        let mut chars = quoted.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => message.extend(chars.next()),
+                // An unescaped quote means this was not a single string.
+                '"' => return whole(),
+                _ => message.push(ch),
+            }
+        }
+        if expression.is_empty() {
+            return whole();
+        }
+
+        GuardDirective {
+            expression: expression.to_string(),
+            message: (!message.is_empty()).then_some(message),
+        }
+    }
+}
+
+/// Directive names `parse_directives` understands, without the `waypoint:` prefix.
+pub const KNOWN_DIRECTIVES: &[&str] = &[
+    "depends",
+    "env",
+    "require",
+    "ensure",
+    "safety-override",
+    "no-transaction",
+    "isolation",
+    "retries",
+    "low-priority",
+    "set",
+    "order",
+    "copy",
+];
+
+/// A `-- waypoint:*` comment in a file header, with its position.
+///
+/// Lines and columns are 1-based; columns count characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectiveLine {
+    /// Line of the directive.
+    pub line: usize,
+    /// Column of `waypoint:`.
+    pub column: usize,
+    /// Directive name, e.g. `depends` (may be unknown).
+    pub name: String,
+    /// Everything after the name, trimmed.
+    pub value: String,
+    /// Column where `value` starts (just past the name when it is empty).
+    pub value_column: usize,
+}
+
+/// List the `-- waypoint:*` comments in the header that `parse_directives`
+/// reads, known or not, with their positions.
+pub fn directive_lines(sql: &str) -> Vec<DirectiveLine> {
+    let mut found = Vec::new();
+
+    for (idx, line) in sql.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if !trimmed.starts_with("--") {
+            break;
+        }
+
+        let comment_body = trimmed.strip_prefix("--").unwrap().trim_start();
+        let Some(rest) = comment_body.strip_prefix("waypoint:") else {
+            continue;
+        };
+        let lead = line.len() - line.trim_start().len();
+        let start = lead + trimmed.len() - comment_body.len();
+        let name_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let after_name = &rest[name_len..];
+        let value = after_name.trim();
+        let value_start = if value.is_empty() {
+            start + "waypoint:".len() + name_len
+        } else {
+            lead + trimmed.len() - after_name.trim_start().len()
+        };
+
+        found.push(DirectiveLine {
+            line: idx + 1,
+            column: line[..start].chars().count() + 1,
+            name: rest[..name_len].to_string(),
+            value: value.to_string(),
+            value_column: line[..value_start].chars().count() + 1,
+        });
+    }
+
+    found
+}
+
+/// Parse the value of a `-- waypoint:set` directive: comma-separated
+/// `name=value` pairs. Names are setting names (letters, digits, `_` and `.`);
+/// values may be wrapped in single quotes and cannot contain commas.
+pub fn parse_settings(value: &str) -> Result<Vec<(String, String)>, WaypointError> {
+    let mut settings = Vec::new();
+    for item in value.split(',') {
+        let item = item.trim();
+        let invalid = |reason: &str| {
+            WaypointError::ConfigError(format!("Invalid setting '{}': {}", item, reason))
+        };
+        let Some((name, setting)) = item.split_once('=') else {
+            return Err(invalid("expected name=value"));
+        };
+        let name = name.trim();
+        let valid_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+        if !valid_name {
+            return Err(invalid("not a setting name"));
+        }
+        let setting = setting.trim();
+        let setting = setting
+            .strip_prefix('\'')
+            .and_then(|v| v.strip_suffix('\''))
+            .unwrap_or(setting);
+        if setting.is_empty() {
+            return Err(invalid("missing value"));
+        }
+        settings.push((name.to_lowercase(), setting.to_string()));
+    }
+    Ok(settings)
 }
 
 /// Strip a directive prefix, ensuring the prefix is followed by whitespace or end of string.
@@ -39,8 +378,10 @@ fn strip_directive_prefix<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
 /// Parse `-- waypoint:*` directives from SQL content.
 ///
 /// Only parses comment lines (`--`) at the top of the file.
-/// Stops at the first non-empty, non-comment line.
-pub fn parse_directives(sql: &str) -> MigrationDirectives {
+/// Stops at the first non-empty, non-comment line. An invalid `isolation` or
+/// `retries` value is an error; malformed `set` and `copy` lines are skipped
+/// and left to `waypoint lint`.
+pub fn parse_directives(sql: &str) -> Result<MigrationDirectives, WaypointError> {
     let mut directives = MigrationDirectives::default();
 
     for line in sql.lines() {
@@ -76,18 +417,43 @@ pub fn parse_directives(sql: &str) -> MigrationDirectives {
             }
         } else if let Some(value) = strip_directive_prefix(comment_body, "waypoint:require") {
             if !value.is_empty() {
-                directives.require.push(value.to_string());
+                directives.require.push(GuardDirective::parse(value));
             }
         } else if let Some(value) = strip_directive_prefix(comment_body, "waypoint:ensure") {
             if !value.is_empty() {
-                directives.ensure.push(value.to_string());
+                directives.ensure.push(GuardDirective::parse(value));
             }
         } else if comment_body.trim() == "waypoint:safety-override" {
             directives.safety_override = true;
+        } else if comment_body.trim() == "waypoint:no-transaction" {
+            directives.no_transaction = true;
+        } else if comment_body.trim() == "waypoint:low-priority" {
+            directives.low_priority = true;
+        } else if let Some(value) = strip_directive_prefix(comment_body, "waypoint:isolation") {
+            directives.isolation = Some(value.parse()?);
+        } else if let Some(value) = strip_directive_prefix(comment_body, "waypoint:retries") {
+            directives.retries = value.trim().parse().map_err(|_| {
+                WaypointError::ConfigError(format!(
+                    "Invalid retries '{}'. Use a non-negative whole number.",
+                    value
+                ))
+            })?;
+        } else if let Some(value) = strip_directive_prefix(comment_body, "waypoint:order") {
+            directives.order = value.parse().ok();
+        } else if let Some(value) = strip_directive_prefix(comment_body, "waypoint:set") {
+            // A malformed line is skipped as a whole; `waypoint lint` reports it.
+            if let Ok(settings) = parse_settings(value) {
+                directives.settings.extend(settings);
+            }
+        } else if let Some(value) = strip_directive_prefix(comment_body, "waypoint:copy") {
+            // Likewise: `waypoint lint` reports a malformed copy line.
+            if let Ok(copy) = CopyDirective::parse(value) {
+                directives.copy.push(copy);
+            }
         }
     }
 
-    directives
+    Ok(directives)
 }
 
 #[cfg(test)]
@@ -97,7 +463,7 @@ mod tests {
     #[test]
     fn test_parse_env_directive() {
         let sql = "-- waypoint:env dev,staging\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.env, vec!["dev", "staging"]);
         assert!(d.depends.is_empty());
     }
@@ -105,7 +471,7 @@ mod tests {
     #[test]
     fn test_parse_depends_directive() {
         let sql = "-- waypoint:depends V3,V5\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.depends, vec!["3", "5"]);
         assert!(d.env.is_empty());
     }
@@ -113,14 +479,14 @@ mod tests {
     #[test]
     fn test_parse_depends_without_v_prefix() {
         let sql = "-- waypoint:depends 3,5\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.depends, vec!["3", "5"]);
     }
 
     #[test]
     fn test_parse_multiple_directives() {
         let sql = "-- waypoint:env dev\n-- waypoint:depends V1,V2\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.env, vec!["dev"]);
         assert_eq!(d.depends, vec!["1", "2"]);
     }
@@ -128,13 +494,13 @@ mod tests {
     #[test]
     fn test_stops_at_non_comment_line() {
         let sql = "-- waypoint:env dev\nCREATE TABLE foo();\n-- waypoint:env prod\n";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.env, vec!["dev"]);
     }
 
     #[test]
     fn test_empty_sql() {
-        let d = parse_directives("");
+        let d = parse_directives("").unwrap();
         assert!(d.env.is_empty());
         assert!(d.depends.is_empty());
     }
@@ -142,7 +508,7 @@ mod tests {
     #[test]
     fn test_no_directives() {
         let sql = "-- Regular comment\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert!(d.env.is_empty());
         assert!(d.depends.is_empty());
     }
@@ -150,14 +516,14 @@ mod tests {
     #[test]
     fn test_skips_leading_blank_lines() {
         let sql = "\n\n-- waypoint:env prod\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.env, vec!["prod"]);
     }
 
     #[test]
     fn test_whitespace_in_values() {
         let sql = "-- waypoint:env  dev , staging , prod \nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.env, vec!["dev", "staging", "prod"]);
     }
 
@@ -170,21 +536,25 @@ mod tests {
     #[test]
     fn test_parse_require_directive() {
         let sql = "-- waypoint:require table_exists(\"users\")\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
-        assert_eq!(d.require, vec!["table_exists(\"users\")"]);
+        let d = parse_directives(sql).unwrap();
+        assert_eq!(d.require[0].expression, "table_exists(\"users\")");
+        assert_eq!(d.require[0].message, None);
     }
 
     #[test]
     fn test_parse_ensure_directive() {
         let sql = "-- waypoint:ensure column_exists(\"users\", \"email\")\nALTER TABLE users ADD COLUMN email TEXT;";
-        let d = parse_directives(sql);
-        assert_eq!(d.ensure, vec!["column_exists(\"users\", \"email\")"]);
+        let d = parse_directives(sql).unwrap();
+        assert_eq!(
+            d.ensure[0].expression,
+            "column_exists(\"users\", \"email\")"
+        );
     }
 
     #[test]
     fn test_parse_multiple_guards() {
         let sql = "-- waypoint:require table_exists(\"users\")\n-- waypoint:require NOT column_exists(\"users\", \"email\")\n-- waypoint:ensure column_exists(\"users\", \"email\")\nALTER TABLE users ADD COLUMN email TEXT;";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert_eq!(d.require.len(), 2);
         assert_eq!(d.ensure.len(), 1);
     }
@@ -192,21 +562,124 @@ mod tests {
     #[test]
     fn test_parse_safety_override() {
         let sql = "-- waypoint:safety-override\nALTER TABLE large_table ADD COLUMN foo TEXT;";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert!(d.safety_override);
     }
 
     #[test]
     fn test_safety_override_default_false() {
         let sql = "CREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert!(!d.safety_override);
     }
 
+    #[test]
+    fn test_parse_no_transaction() {
+        let sql = "-- waypoint:no-transaction\nCREATE INDEX CONCURRENTLY idx ON t (c);";
+        let d = parse_directives(sql).unwrap();
+        assert!(d.no_transaction);
+        assert!(
+            !parse_directives("CREATE TABLE foo();")
+                .unwrap()
+                .no_transaction
+        );
+    }
+
+    #[test]
+    fn test_parse_low_priority() {
+        let sql = "-- waypoint:low-priority\nALTER TABLE t VALIDATE CONSTRAINT t_fk;";
+        assert!(parse_directives(sql).unwrap().low_priority);
+        assert!(
+            !parse_directives("-- waypoint:low-priority-ish\nSELECT 1;")
+                .unwrap()
+                .low_priority
+        );
+    }
+
+    #[test]
+    fn test_parse_isolation_and_retries() {
+        let sql =
+            "-- waypoint:isolation repeatable read\n-- waypoint:retries 3\nUPDATE t SET x = 1;";
+        let d = parse_directives(sql).unwrap();
+        assert_eq!(d.isolation, Some(IsolationLevel::RepeatableRead));
+        assert_eq!(d.retries, 3);
+
+        let d = parse_directives("-- waypoint:isolation serializable\nSELECT 1;").unwrap();
+        assert_eq!(d.isolation, Some(IsolationLevel::Serializable));
+        assert_eq!(d.retries, 0);
+
+        assert!(parse_directives("-- waypoint:isolation bogus\nSELECT 1;").is_err());
+        assert!(parse_directives("-- waypoint:retries three\nSELECT 1;").is_err());
+        assert!(parse_directives("-- waypoint:retries -1\nSELECT 1;").is_err());
+    }
+
+    #[test]
+    fn test_parse_copy_directive() {
+        let sql = "-- waypoint:copy table=app.users file=data/users.csv columns=id,email\n\
+                   -- waypoint:copy table=tags file=tags.tsv header=false delimiter=tab\n\
+                   -- waypoint:copy table=users\n\
+                   SELECT 1;";
+        let d = parse_directives(sql).unwrap();
+        assert_eq!(d.copy.len(), 2);
+        assert_eq!(d.copy[0].table, "app.users");
+        assert_eq!(d.copy[0].file, "data/users.csv");
+        assert_eq!(d.copy[0].columns, vec!["id", "email"]);
+        assert!(d.copy[0].header);
+        assert_eq!(d.copy[0].delimiter, ',');
+        assert!(!d.copy[1].header);
+        assert_eq!(d.copy[1].delimiter, '\t');
+
+        assert!(CopyDirective::parse("file=a.csv").is_err());
+        assert!(CopyDirective::parse("table=a;b file=a.csv").is_err());
+        assert!(CopyDirective::parse("table=a file=a.csv format=binary").is_err());
+        assert!(CopyDirective::parse("table=a file=a.csv delimiter=ab").is_err());
+    }
+
+    #[test]
+    fn test_parse_set_directive() {
+        let sql = "-- waypoint:set statement_timeout=60s, lock_timeout = '5s'\n\
+                   -- waypoint:set Role=migrator\n\
+                   -- waypoint:set work_mem\n\
+                   ALTER TABLE t ADD COLUMN c INT;";
+        let d = parse_directives(sql).unwrap();
+        assert_eq!(
+            d.settings,
+            vec![
+                ("statement_timeout".to_string(), "60s".to_string()),
+                ("lock_timeout".to_string(), "5s".to_string()),
+                ("role".to_string(), "migrator".to_string()),
+            ]
+        );
+
+        assert!(parse_settings("work_mem").is_err());
+        assert!(parse_settings("work mem=1MB").is_err());
+        assert!(parse_settings("1x=1").is_err());
+        assert!(parse_settings("work_mem=").is_err());
+        assert!(parse_settings("search_path=a, b").is_err());
+        assert_eq!(
+            parse_settings("myapp.flag=on").unwrap(),
+            vec![("myapp.flag".to_string(), "on".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_isolation_level_from_str() {
+        assert_eq!(
+            "REPEATABLE_READ".parse::<IsolationLevel>().unwrap(),
+            IsolationLevel::RepeatableRead
+        );
+        assert_eq!(
+            "read-committed".parse::<IsolationLevel>().unwrap(),
+            IsolationLevel::ReadCommitted
+        );
+        assert!("snapshot".parse::<IsolationLevel>().is_err());
+        assert!(IsolationLevel::Serializable > IsolationLevel::RepeatableRead);
+    }
+
     #[test]
     fn test_env_prefix_does_not_match_ensure() {
         let sql = "-- waypoint:ensure column_exists(\"users\", \"email\")\nALTER TABLE users ADD COLUMN email TEXT;";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         // Should be parsed as ensure, not env
         assert!(d.env.is_empty());
         assert_eq!(d.ensure.len(), 1);
@@ -216,7 +689,7 @@ mod tests {
     fn test_directive_prefix_boundary() {
         // "waypoint:environment" should NOT match "waypoint:env"
         let sql = "-- waypoint:environment prod\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         // Should NOT be parsed as env directive since "waypoint:environment" != "waypoint:env"
         assert!(d.env.is_empty());
     }
@@ -224,21 +697,91 @@ mod tests {
     #[test]
     fn test_parse_empty_depends() {
         let sql = "-- waypoint:depends\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert!(d.depends.is_empty());
     }
 
     #[test]
     fn test_parse_empty_env() {
         let sql = "-- waypoint:env\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
+        let d = parse_directives(sql).unwrap();
         assert!(d.env.is_empty());
     }
 
+    #[test]
+    fn test_directive_lines_positions() {
+        let sql = "\n-- waypoint:depends  V1, V2  \n  --waypoint:safety-override\n-- plain comment\n-- waypoint:bogus\nSELECT 1;\n-- waypoint:env prod\n";
+        let lines = directive_lines(sql);
+        assert_eq!(lines.len(), 3);
+
+        assert_eq!(lines[0].line, 2);
+        assert_eq!(lines[0].column, 4);
+        assert_eq!(lines[0].name, "depends");
+        assert_eq!(lines[0].value, "V1, V2");
+        assert_eq!(lines[0].value_column, 22);
+
+        assert_eq!(lines[1].line, 3);
+        assert_eq!(lines[1].column, 5);
+        assert_eq!(lines[1].name, "safety-override");
+        assert_eq!(lines[1].value, "");
+        assert_eq!(lines[1].value_column, 29);
+
+        assert_eq!(lines[2].name, "bogus");
+        assert!(!KNOWN_DIRECTIVES.contains(&lines[2].name.as_str()));
+    }
+
     #[test]
     fn test_parse_require_with_special_chars() {
         let sql = "-- waypoint:require table_exists(\"my-table\")\nCREATE TABLE foo();";
-        let d = parse_directives(sql);
-        assert_eq!(d.require, vec!["table_exists(\"my-table\")"]);
+        let d = parse_directives(sql).unwrap();
+        assert_eq!(d.require[0].expression, "table_exists(\"my-table\")");
+    }
+
+    #[test]
+    fn test_parse_guard_message() {
+        let sql = "-- waypoint:require table_exists(\"users\") :: \"users table must exist before adding FK\"\n\
+                   -- waypoint:ensure column_exists(\"users\", \"email\")::\"email column \\\"missing\\\"\"\n\
+                   ALTER TABLE orders ADD FOREIGN KEY (user_id) REFERENCES users(id);";
+        let d = parse_directives(sql).unwrap();
+        assert_eq!(d.require[0].expression, "table_exists(\"users\")");
+        assert_eq!(
+            d.require[0].message.as_deref(),
+            Some("users table must exist before adding FK")
+        );
+        assert_eq!(
+            d.ensure[0].expression,
+            "column_exists(\"users\", \"email\")"
+        );
+        assert_eq!(
+            d.ensure[0].message.as_deref(),
+            Some("email column \"missing\"")
+        );
+    }
+
+    #[test]
+    fn test_parse_guard_message_ignores_separator_in_strings() {
+        let g = GuardDirective::parse("sql(\"SELECT 1::int\") > 0");
+        assert_eq!(g.expression, "sql(\"SELECT 1::int\") > 0");
+        assert_eq!(g.message, None);
+
+        let g = GuardDirective::parse("sql(\"SELECT 1::int\") > 0 :: \"a :: b\"");
+        assert_eq!(g.expression, "sql(\"SELECT 1::int\") > 0");
+        assert_eq!(g.message.as_deref(), Some("a :: b"));
+    }
+
+    #[test]
+    fn test_parse_guard_malformed_message_kept_in_expression() {
+        let g = GuardDirective::parse("table_exists(\"users\") :: no quotes");
+        assert_eq!(g.expression, "table_exists(\"users\") :: no quotes");
+        assert_eq!(g.message, None);
+    }
+
+    #[test]
+    fn test_parse_order_directive() {
+        let d = parse_directives("-- waypoint:order 10\nCREATE VIEW v AS SELECT 1;").unwrap();
+        assert_eq!(d.order, Some(10));
+
+        let d = parse_directives("-- waypoint:order first\nCREATE VIEW v AS SELECT 1;").unwrap();
+        assert_eq!(d.order, None);
     }
 }
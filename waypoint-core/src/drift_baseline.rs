@@ -0,0 +1,362 @@
+//! Drift baseline stored in the database.
+//!
+//! With `[drift] store_baseline = true`, a migrate that applied at least one
+//! migration (or finds no baseline yet) introspects the schema and records
+//! its fingerprint — a SHA-256 over a canonical JSON serialization of the
+//! snapshot — together with the snapshot itself in the
+//! `<history_table>_drift_baseline` sidecar table. `waypoint drift --stored`
+//! then checks drift from any machine by comparing the live schema with the
+//! latest stored baseline, without replaying migrations or shipping snapshot
+//! files around.
+//!
+//! With `[drift] publish_fingerprint = true` (or `store_baseline`), every
+//! migrate also puts the live fingerprint on the report as
+//! `schema_fingerprint`, for dependent services to check against; `waypoint
+//! fingerprint` computes the same value on demand.
+//!
+//! Waypoint's own tables (the history table and its sidecars, and
+//! `waypoint_meta`) are left out of the snapshot.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::commands::migrate::MigrateReport;
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::error::{Result, WaypointError};
+use crate::history::{self, HISTORY_META_TABLE};
+use crate::schema::{self, SchemaSnapshot};
+use crate::warning::{Warning, WarningCode};
+
+/// Configuration for the `[drift]` section.
+#[derive(Debug, Clone, Default)]
+pub struct DriftConfig {
+    /// Record the schema fingerprint in the database after migrate.
+    pub store_baseline: bool,
+    /// Put the schema fingerprint on every migrate report.
+    pub publish_fingerprint: bool,
+}
+
+/// A baseline recorded by migrate.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredBaseline {
+    /// When the baseline was recorded.
+    pub recorded_at: DateTime<Utc>,
+    /// Fingerprint of the schema after that migrate.
+    pub fingerprint: String,
+    /// Canonical snapshot JSON the fingerprint was computed from.
+    #[serde(skip)]
+    pub snapshot: String,
+}
+
+/// Name of the sidecar baseline table for a given history table.
+pub fn baseline_table_name(history_table: &str) -> String {
+    format!("{}_drift_baseline", history_table)
+}
+
+/// Waypoint's own tables for `history_table`: the history table, its
+/// sidecar tables, and the shared meta table.
+fn waypoint_tables(history_table: &str) -> Vec<String> {
+    vec![
+        history_table.to_string(),
+        crate::receipt::receipts_table_name(history_table),
+        crate::db::lock_table_name(history_table),
+        baseline_table_name(history_table),
+        crate::progress::progress_table_name(history_table),
+        crate::commands::seed::seed_table_name(history_table),
+        crate::commands::checksums::audit_table_name(history_table),
+        crate::commands::schedule::schedule_table_name(history_table),
+        HISTORY_META_TABLE.to_string(),
+    ]
+}
+
+/// Whether `name` is one of waypoint's own tables.
+fn is_waypoint_table(name: &str, history_table: &str) -> bool {
+    waypoint_tables(history_table).iter().any(|t| t == name)
+}
+
+/// Whether `name` is a sequence owned by one of waypoint's own tables
+/// (`<table>_<column>_seq`).
+fn is_waypoint_sequence(name: &str, history_table: &str) -> bool {
+    name.ends_with("_seq")
+        && waypoint_tables(history_table).iter().any(|t| {
+            name.strip_prefix(t.as_str())
+                .is_some_and(|r| r.starts_with('_'))
+        })
+}
+
+/// Drop waypoint's own tables, and their indexes, constraints, triggers and
+/// sequences, from a snapshot.
+pub fn without_waypoint_objects(snapshot: &SchemaSnapshot, history_table: &str) -> SchemaSnapshot {
+    let mut s = snapshot.clone();
+    let keep = |name: &str| !is_waypoint_table(name, history_table);
+    s.tables.retain(|t| keep(&t.name));
+    s.indexes.retain(|i| keep(&i.table_name));
+    s.constraints.retain(|c| keep(&c.table_name));
+    s.triggers.retain(|t| keep(&t.table_name));
+    s.sequences
+        .retain(|q| !is_waypoint_sequence(&q.name, history_table));
+    s.distributed_tables.retain(|t| keep(&t.name));
+    s
+}
+
+/// Canonical JSON serialization of a snapshot: object keys sorted, and each
+/// top-level object list sorted so introspection order doesn't matter.
+/// Lists inside an object (columns, enum values) keep their order, which is
+/// significant.
+pub fn canonical_json(snapshot: &SchemaSnapshot) -> String {
+    let mut value = serde_json::to_value(snapshot).expect("snapshot serializes");
+    if let serde_json::Value::Object(map) = &mut value {
+        for list in map.values_mut() {
+            if let serde_json::Value::Array(items) = list {
+                items.sort_by_cached_key(|v| v.to_string());
+            }
+        }
+    }
+    value.to_string()
+}
+
+/// SHA-256 of [`canonical_json`], hex-encoded.
+pub fn fingerprint(canonical: &str) -> String {
+    Sha256::digest(canonical.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Introspect `schema` and return its canonical JSON and fingerprint.
+pub async fn capture(
+    client: &DbClient,
+    schema: &str,
+    history_table: &str,
+) -> Result<(String, String)> {
+    let snapshot = schema::introspect_db(client, schema).await?;
+    let canonical = canonical_json(&without_waypoint_objects(&snapshot, history_table));
+    let fp = fingerprint(&canonical);
+    Ok((canonical, fp))
+}
+
+/// Fingerprint the schema after a finished run and put it on the report as
+/// `schema_fingerprint`, if `[drift] publish_fingerprint` or
+/// `store_baseline` is enabled. With `store_baseline`, also record a
+/// baseline if the run applied something or no baseline exists yet. A run
+/// that applied nothing keeps the existing baseline, so drift that happened
+/// since isn't absorbed. Failures are logged and added to the report's
+/// warnings; the migrations are already committed.
+pub async fn record(client: &DbClient, config: &WaypointConfig, report: &mut MigrateReport) {
+    let store_baseline = config.drift.store_baseline;
+    if !store_baseline && !config.drift.publish_fingerprint {
+        return;
+    }
+    let result = async {
+        let schema = client.resolve_schema(&config.migrations.schema).await?;
+        let (canonical, fp) = capture(client, &schema, &config.migrations.table).await?;
+        let stored = store_baseline
+            && (report.migrations_applied > 0 || load(client, config).await?.is_none());
+        if stored {
+            store(client, config, &schema, &fp, &canonical).await?;
+        }
+        Ok::<_, WaypointError>((fp, stored))
+    }
+    .await;
+
+    match result {
+        Ok((fp, stored)) => {
+            if stored {
+                log::info!("Recorded drift baseline; fingerprint={}", fp);
+            }
+            report.schema_fingerprint = Some(fp);
+        }
+        Err(e) => {
+            log::error!("Failed to fingerprint the schema: {}", e);
+            let message = if store_baseline {
+                format!("drift baseline was not recorded: {}", e)
+            } else {
+                format!("schema fingerprint was not computed: {}", e)
+            };
+            report.warnings.push(Warning::new(
+                WarningCode::DriftBaselineFailed,
+                "drift",
+                message,
+            ));
+        }
+    }
+}
+
+async fn store(
+    client: &DbClient,
+    config: &WaypointConfig,
+    schema: &str,
+    fingerprint: &str,
+    snapshot: &str,
+) -> Result<()> {
+    let table = baseline_table_name(&config.migrations.table);
+    let qualified = client.dialect().qualified_table(schema, &table);
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => {
+            c.batch_execute(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+                    id BIGSERIAL PRIMARY KEY,
+                    recorded_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                    fingerprint VARCHAR(64) NOT NULL,
+                    snapshot TEXT NOT NULL
+                )",
+                qualified
+            ))
+            .await?;
+            c.execute(
+                &format!(
+                    "INSERT INTO {} (fingerprint, snapshot) VALUES ($1, $2)",
+                    qualified
+                ),
+                &[&fingerprint, &snapshot],
+            )
+            .await?;
+        }
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            client
+                .execute_raw(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} (
+                        id BIGINT AUTO_INCREMENT PRIMARY KEY,
+                        recorded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                        fingerprint VARCHAR(64) NOT NULL,
+                        snapshot LONGTEXT NOT NULL
+                    )",
+                    qualified
+                ))
+                .await?;
+            let mut conn = pool.get_conn().await?;
+            conn.exec_drop(
+                format!(
+                    "INSERT INTO {} (fingerprint, snapshot) VALUES (?, ?)",
+                    qualified
+                ),
+                (fingerprint, snapshot),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Load the latest baseline from the `<history_table>_drift_baseline`
+/// table, or `None` when none has been recorded.
+pub async fn load(client: &DbClient, config: &WaypointConfig) -> Result<Option<StoredBaseline>> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let table = baseline_table_name(&config.migrations.table);
+    if !history::history_table_exists_db(client, &schema, &table).await? {
+        return Ok(None);
+    }
+    let qualified = client.dialect().qualified_table(&schema, &table);
+    let sql = format!(
+        "SELECT recorded_at, fingerprint, snapshot FROM {} ORDER BY id DESC LIMIT 1",
+        qualified
+    );
+    let baseline = match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => c.query_opt(&sql, &[]).await?.map(|row| StoredBaseline {
+            recorded_at: row.get(0),
+            fingerprint: row.get(1),
+            snapshot: row.get(2),
+        }),
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let mut conn = pool.get_conn().await?;
+            let row: Option<(chrono::NaiveDateTime, String, String)> =
+                conn.query_first(sql).await?;
+            row.map(|(recorded_at, fingerprint, snapshot)| StoredBaseline {
+                recorded_at: recorded_at.and_utc(),
+                fingerprint,
+                snapshot,
+            })
+        }
+    };
+    Ok(baseline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{ColumnDef, IndexDef, SequenceDef, TableDef};
+
+    fn table(name: &str) -> TableDef {
+        TableDef {
+            schema: "public".into(),
+            name: name.into(),
+            columns: vec![ColumnDef {
+                name: "id".into(),
+                data_type: "integer".into(),
+                is_nullable: false,
+                default: None,
+                ordinal_position: 1,
+            }],
+        }
+    }
+
+    fn snapshot(tables: &[&str]) -> SchemaSnapshot {
+        SchemaSnapshot {
+            tables: tables.iter().map(|t| table(t)).collect(),
+            views: vec![],
+            indexes: vec![],
+            sequences: vec![],
+            functions: vec![],
+            enums: vec![],
+            constraints: vec![],
+            triggers: vec![],
+            extensions: vec![],
+            distributed_tables: vec![],
+            incomplete: vec![],
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_introspection_order() {
+        let a = canonical_json(&snapshot(&["users", "orders"]));
+        let b = canonical_json(&snapshot(&["orders", "users"]));
+        assert_eq!(a, b);
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_eq!(fingerprint(&a).len(), 64);
+
+        let c = canonical_json(&snapshot(&["orders", "users", "items"]));
+        assert_ne!(fingerprint(&a), fingerprint(&c));
+    }
+
+    #[test]
+    fn test_without_waypoint_objects() {
+        let mut s = snapshot(&[
+            "users",
+            "waypoint_schema_history",
+            "waypoint_schema_history_receipts",
+            "waypoint_schema_history_drift_baseline",
+            "waypoint_meta",
+            "waypoint_schema_history_archive",
+        ]);
+        s.indexes.push(IndexDef {
+            schema: "public".into(),
+            name: "waypoint_schema_history_s_idx".into(),
+            table_name: "waypoint_schema_history".into(),
+            definition: String::new(),
+            is_unique: false,
+        });
+        s.sequences.push(SequenceDef {
+            schema: "public".into(),
+            name: "waypoint_schema_history_drift_baseline_id_seq".into(),
+            data_type: "bigint".into(),
+        });
+        let filtered = without_waypoint_objects(&s, "waypoint_schema_history");
+        let names: Vec<&str> = filtered.tables.iter().map(|t| t.name.as_str()).collect();
+        // Only waypoint's own tables go, not user tables sharing the prefix.
+        assert_eq!(names, vec!["users", "waypoint_schema_history_archive"]);
+        assert!(filtered.sequences.is_empty());
+        assert!(filtered.indexes.is_empty());
+        assert_eq!(
+            baseline_table_name("waypoint_schema_history"),
+            "waypoint_schema_history_drift_baseline"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Migrations compiled into the binary.
+//!
+//! An application that runs its migrations at startup can ship them inside
+//! the executable instead of next to it: build an [`EmbeddedMigrations`] with
+//! [`embed_migrations!`], [`EmbeddedMigrations::from_include_dir`] (feature
+//! `include_dir`) or [`EmbeddedMigrations::from_files`] from any other
+//! source, put it on `config.migrations.embedded_migrations` and clear
+//! `locations`. Embedded files are read through the same [`MigrationDir`]
+//! scan as a location on disk, with the same filename parsing, limits and
+//! checksums, so switching between the two leaves the history table valid.
+//!
+//! Only migrations are embedded: hook scripts are still read from
+//! `locations`, and `-- waypoint:copy` loads need the migration on disk.
+
+use crate::error::{Result, WaypointError};
+use crate::migration::{resolve_dir, MigrationDir, ResolvedMigration, ScanLimits};
+
+/// Embed migration files from a directory of the calling crate at compile
+/// time, as an [`EmbeddedMigrations`].
+///
+/// The directory is relative to the crate's `Cargo.toml`. With just the
+/// directory, every `.sql` file directly in it is embedded; a list of names
+/// embeds only those files.
+///
+/// ```ignore
+/// config.migrations.embedded_migrations =
+///     waypoint_core::embedded::embed_migrations!("db/migrations");
+/// config.migrations.locations.clear();
+/// ```
+///
+/// The directory is listed when the calling crate is compiled, and only
+/// changes to the embedded files trigger a rebuild: after adding a file, add
+/// `println!("cargo:rerun-if-changed=db/migrations")` to a build script or
+/// touch a source file.
+pub use waypoint_macros::embed_migrations;
+
+/// One embedded migration file.
+#[derive(Debug, Clone)]
+pub struct EmbeddedFile {
+    /// Filename, e.g. `V1__Create_users.sql`.
+    pub name: String,
+    /// File content.
+    pub sql: String,
+}
+
+/// Migration files embedded in the binary.
+#[derive(Debug, Clone, Default)]
+pub struct EmbeddedMigrations {
+    files: Vec<EmbeddedFile>,
+}
+
+impl EmbeddedMigrations {
+    /// Embedded migrations from `(path, content)` pairs. Only the last path
+    /// component is kept as the filename; like a scanned directory, files
+    /// that are not migrations are skipped when resolving.
+    pub fn from_files<I, N, S>(files: I) -> Self
+    where
+        I: IntoIterator<Item = (N, S)>,
+        N: AsRef<str>,
+        S: Into<String>,
+    {
+        let files = files
+            .into_iter()
+            .map(|(path, sql)| {
+                let path = path.as_ref();
+                let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+                EmbeddedFile {
+                    name: name.to_string(),
+                    sql: sql.into(),
+                }
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Embedded migrations from an [`include_dir::Dir`], including its
+    /// subdirectories. Files that are not UTF-8 are skipped.
+    ///
+    /// ```ignore
+    /// static MIGRATIONS: include_dir::Dir =
+    ///     include_dir::include_dir!("$CARGO_MANIFEST_DIR/db/migrations");
+    /// let embedded = EmbeddedMigrations::from_include_dir(&MIGRATIONS);
+    /// ```
+    #[cfg(feature = "include_dir")]
+    pub fn from_include_dir(dir: &include_dir::Dir<'_>) -> Self {
+        fn collect<'a>(dir: &'a include_dir::Dir<'a>, files: &mut Vec<(&'a str, &'a str)>) {
+            for entry in dir.entries() {
+                match entry {
+                    include_dir::DirEntry::Dir(sub) => collect(sub, files),
+                    include_dir::DirEntry::File(file) => {
+                        if let (Some(path), Some(sql)) =
+                            (file.path().to_str(), file.contents_utf8())
+                        {
+                            files.push((path, sql));
+                        }
+                    }
+                }
+            }
+        }
+        let mut files = Vec::new();
+        collect(dir, &mut files);
+        Self::from_files(files)
+    }
+
+    /// The embedded files, in the order given.
+    pub fn files(&self) -> &[EmbeddedFile] {
+        &self.files
+    }
+
+    /// Whether nothing is embedded.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Resolve the embedded migrations, failing with `MigrationTooLarge` on
+    /// the first one over `limits`. The result is unsorted.
+    pub(crate) fn resolve(&self, limits: &ScanLimits) -> Result<Vec<ResolvedMigration>> {
+        resolve_dir(self, limits)
+    }
+
+    fn file(&self, name: &str) -> Result<&EmbeddedFile> {
+        self.files.iter().find(|f| f.name == name).ok_or_else(|| {
+            WaypointError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No embedded migration named '{}'", name),
+            ))
+        })
+    }
+}
+
+impl MigrationDir for EmbeddedMigrations {
+    fn file_names(&self) -> Result<Vec<String>> {
+        Ok(self.files.iter().map(|f| f.name.clone()).collect())
+    }
+
+    fn file_size(&self, name: &str) -> Result<u64> {
+        Ok(self.file(name)?.sql.len() as u64)
+    }
+
+    fn read_file(&self, name: &str) -> Result<String> {
+        Ok(self.file(name)?.sql.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_merge_with_scanned_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V2__Disk.sql"), "SELECT 2;").unwrap();
+
+        let mut settings = crate::config::MigrationSettings {
+            locations: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        };
+        settings.embedded_migrations = EmbeddedMigrations::from_files([
+            (
+                "migrations/V1__Create_users.sql",
+                "CREATE TABLE users (id int);",
+            ),
+            (
+                "migrations/V3__Add_email.sql",
+                "ALTER TABLE users ADD email text;",
+            ),
+            ("migrations/beforeMigrate.sql", "SELECT 1;"),
+            ("migrations/README.md", "notes"),
+        ]);
+        let resolved = settings.resolve().unwrap();
+        let scripts: Vec<_> = resolved.iter().map(|m| m.script.as_str()).collect();
+        assert_eq!(
+            scripts,
+            ["V1__Create_users.sql", "V2__Disk.sql", "V3__Add_email.sql"]
+        );
+        assert_eq!(
+            resolved[0].checksum,
+            crate::checksum::calculate_checksum("CREATE TABLE users (id int);")
+        );
+
+        settings.embedded_migrations =
+            EmbeddedMigrations::from_files([("V2__Again.sql", "SELECT 2;")]);
+        assert!(settings.resolve().is_err());
+
+        let limits = ScanLimits {
+            max_bytes: 4,
+            ..ScanLimits::default()
+        };
+        assert!(settings.embedded_migrations.resolve(&limits).is_err());
+    }
+
+    #[test]
+    fn test_embed_migrations_macro() {
+        let embedded = embed_migrations!("templates/new", ["backfill.sql"]);
+        assert_eq!(embedded.files()[0].name, "backfill.sql");
+        assert!(embedded.files()[0].sql.contains("{{batch_size}}"));
+        // Not a migration filename, so nothing resolves.
+        assert!(embedded.resolve(&ScanLimits::default()).unwrap().is_empty());
+
+        // Without a list, every .sql file in the directory is embedded.
+        let all = embed_migrations!("templates/new");
+        let names: Vec<_> = all.files().iter().map(|f| f.name.as_str()).collect();
+        let mut on_disk: Vec<_> = std::fs::read_dir("templates/new")
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .filter(|n| n.ends_with(".sql"))
+            .collect();
+        on_disk.sort();
+        assert_eq!(names, on_disk);
+        assert!(names.contains(&"backfill.sql"));
+    }
+
+    #[cfg(feature = "include_dir")]
+    #[test]
+    fn test_from_include_dir() {
+        static DIR: include_dir::Dir = include_dir::include_dir!("$CARGO_MANIFEST_DIR/templates");
+        let embedded = EmbeddedMigrations::from_include_dir(&DIR);
+        assert!(embedded.files().iter().any(|f| f.name == "backfill.sql"));
+        assert!(embedded.files().iter().all(|f| !f.name.contains('/')));
+    }
+}
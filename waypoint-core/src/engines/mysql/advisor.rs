@@ -4,7 +4,7 @@
 
 use mysql_async::prelude::*;
 
-use crate::advisor::{AdvisorConfig, AdvisorReport, Advisory, AdvisorySeverity};
+use crate::advisor::{filter_advisories, AdvisorConfig, AdvisorReport, Advisory, AdvisorySeverity};
 use crate::db::DbClient;
 use crate::error::Result;
 
@@ -38,6 +38,8 @@ pub async fn analyze(
         advisories.extend(check_m005_duplicate_indexes(client, schema).await?);
     }
 
+    filter_advisories(&mut advisories);
+
     let warning_count = advisories
         .iter()
         .filter(|a| a.severity == AdvisorySeverity::Warning)
@@ -57,6 +59,8 @@ pub async fn analyze(
         warning_count,
         suggestion_count,
         info_count,
+        server_version: client.server_version().await.ok(),
+        incomplete: Vec::new(),
     })
 }
 
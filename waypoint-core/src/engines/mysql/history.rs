@@ -7,9 +7,10 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use mysql_async::prelude::*;
 use mysql_async::Pool;
 
+use crate::audit::AuditMetadata;
 use crate::dialect::{mysql::MysqlDialect, DatabaseDialect};
 use crate::error::{Result, WaypointError};
-use crate::history::AppliedMigration;
+use crate::history::{AppliedMigration, HistoryUpgrade, HISTORY_META_TABLE};
 
 /// Build a fully-qualified MySQL-quoted table name (`` `schema`.`table` ``).
 fn fq(schema: &str, table: &str) -> String {
@@ -29,16 +30,72 @@ pub async fn history_table_exists(pool: &Pool, schema: &str, table: &str) -> Res
     Ok(exists.is_some())
 }
 
+/// Read the history-table layout version recorded in `waypoint_meta`.
+pub async fn history_schema_version(pool: &Pool, schema: &str, table: &str) -> Result<Option<i32>> {
+    if !history_table_exists(pool, schema, HISTORY_META_TABLE).await? {
+        return Ok(None);
+    }
+    let sql = format!(
+        "SELECT schema_version FROM {} WHERE history_table = ?",
+        fq(schema, HISTORY_META_TABLE)
+    );
+    let mut conn = pool.get_conn().await?;
+    Ok(conn.exec_first(&sql, (table,)).await?)
+}
+
+/// Plan the upgrade of a history table to the current layout on MySQL.
+pub async fn plan_history_upgrade(
+    pool: &Pool,
+    schema: &str,
+    table: &str,
+) -> Result<HistoryUpgrade> {
+    let version = history_schema_version(pool, schema, table).await?;
+    let mut conn = pool.get_conn().await?;
+    let columns: Vec<String> = conn
+        .exec(
+            "SELECT column_name FROM information_schema.columns \
+             WHERE table_schema = ? AND table_name = ?",
+            (schema, table),
+        )
+        .await?;
+    let upgrade =
+        crate::history::build_upgrade_plan(&MysqlDialect, schema, table, version, &columns);
+    crate::history::warn_if_newer(&upgrade);
+    Ok(upgrade)
+}
+
+/// Apply a planned upgrade on MySQL. DDL commits implicitly there, so the
+/// statements run one at a time; the version row is written last, and a
+/// re-run after a partial failure picks up the columns still missing.
+pub async fn apply_history_upgrade(pool: &Pool, upgrade: &mut HistoryUpgrade) -> Result<()> {
+    if upgrade.is_current() {
+        return Ok(());
+    }
+    log::info!(
+        "Upgrading history table; table={}, from_version={:?}, to_version={}, columns_added={:?}",
+        upgrade.table,
+        upgrade.from_version,
+        upgrade.to_version,
+        upgrade.columns_added
+    );
+    let mut conn = pool.get_conn().await?;
+    for sql in &upgrade.statements {
+        conn.query_drop(sql).await?;
+    }
+    upgrade.applied = true;
+    Ok(())
+}
+
 /// Read all applied migrations ordered by `installed_rank` from MySQL.
+/// Columns added by [`crate::history::HISTORY_UPGRADES`] come back as
+/// `None` when an older table doesn't have them yet.
 pub async fn get_applied_migrations(
     pool: &Pool,
     schema: &str,
     table: &str,
 ) -> Result<Vec<AppliedMigration>> {
     let sql = format!(
-        "SELECT installed_rank, version, description, type, script, checksum, \
-         installed_by, installed_on, execution_time, success, reversal_sql \
-         FROM {} ORDER BY installed_rank",
+        "SELECT * FROM {} ORDER BY installed_rank",
         fq(schema, table)
     );
     let mut conn = pool.get_conn().await?;
@@ -75,6 +132,14 @@ pub async fn get_applied_migrations(
             .ok_or_else(|| WaypointError::ConfigError("missing success".into()))?;
         let success = success_raw != 0;
         let reversal_sql: Option<String> = row.take("reversal_sql").unwrap_or(None);
+        let backup_ref: Option<String> = row.take("backup_ref").unwrap_or(None);
+        let checksum_sha256: Option<String> = row.take("checksum_sha256").unwrap_or(None);
+        let audit = AuditMetadata {
+            git_commit: row.take("git_commit").unwrap_or(None),
+            ci_build_url: row.take("ci_build_url").unwrap_or(None),
+            hostname: row.take("hostname").unwrap_or(None),
+            waypoint_version: row.take("waypoint_version").unwrap_or(None),
+        };
 
         out.push(AppliedMigration {
             installed_rank,
@@ -88,6 +153,9 @@ pub async fn get_applied_migrations(
             execution_time,
             success,
             reversal_sql,
+            backup_ref,
+            checksum_sha256,
+            audit,
         });
     }
     Ok(out)
@@ -172,6 +240,23 @@ pub async fn update_checksum(
     Ok(())
 }
 
+/// Set the SHA-256 checksum on every history row for a script.
+pub async fn update_checksum_sha256(
+    pool: &Pool,
+    schema: &str,
+    table: &str,
+    script: &str,
+    checksum_sha256: &str,
+) -> Result<()> {
+    let sql = format!(
+        "UPDATE {} SET checksum_sha256 = ? WHERE script = ?",
+        fq(schema, table)
+    );
+    let mut conn = pool.get_conn().await?;
+    conn.exec_drop(&sql, (checksum_sha256, script)).await?;
+    Ok(())
+}
+
 /// Update the checksum for a repeatable migration.
 pub async fn update_repeatable_checksum(
     pool: &Pool,
@@ -11,16 +11,22 @@
 
 use std::collections::HashMap;
 
+use crate::audit::AuditMetadata;
+use crate::checksum::ChecksumAlgorithm;
 use crate::commands::migrate::{
+    guard_evaluated, migration_started, resolve_target, runnable_versions, selectable_versions,
     should_run_in_environment, GuardAction, MigrateDetail, MigrateReport,
 };
 use crate::config::WaypointConfig;
-use crate::db::DbClient;
+use crate::db::{DbClient, LockTarget};
+use crate::dialect::DialectKind;
 use crate::error::{Result, WaypointError};
 use crate::history;
 use crate::hooks::{self, HookType, ResolvedHook};
-use crate::migration::{scan_migrations, MigrationVersion, ResolvedMigration};
+use crate::migration::{MigrationVersion, ResolvedMigration};
 use crate::placeholder::{build_placeholders, replace_placeholders};
+use crate::telemetry;
+use crate::warning::{Warning, WarningCode};
 
 /// Dialect-aware `require` guard evaluator. Mirrors the PG version but uses
 /// `guard::evaluate_db` so the underlying SQL is dispatched per engine.
@@ -29,13 +35,23 @@ async fn evaluate_require_guards_db(
     schema: &str,
     migration: &ResolvedMigration,
     config: &WaypointConfig,
+    warnings: &mut Vec<Warning>,
 ) -> Result<GuardAction> {
     if migration.directives.require.is_empty() {
         return Ok(GuardAction::Continue);
     }
-    for expr_str in &migration.directives.require {
+    for guard in &migration.directives.require {
+        let expr_str = &guard.expression;
         match crate::guard::parse(expr_str) {
-            Ok(expr) => match crate::guard::evaluate_db(client, schema, &expr).await {
+            Ok(expr) => match telemetry::guard(
+                "require",
+                &migration.script,
+                expr_str,
+                crate::guard::evaluate_db(client, schema, &expr),
+            )
+            .await
+            .inspect(|&passed| guard_evaluated(&migration.script, "require", expr_str, passed))
+            {
                 Ok(true) => {}
                 Ok(false) => match config.guards.on_require_fail {
                     crate::guard::OnRequireFail::Skip => {
@@ -46,16 +62,32 @@ async fn evaluate_require_guards_db(
                         );
                         return Ok(GuardAction::Skip);
                     }
-                    crate::guard::OnRequireFail::Warn => log::warn!(
-                        "Guard require failed (continuing); script={}, expr={}",
-                        migration.script,
-                        expr_str
-                    ),
+                    crate::guard::OnRequireFail::Warn => {
+                        log::warn!(
+                            "Guard require failed (continuing); script={}, expr={}",
+                            migration.script,
+                            expr_str
+                        );
+                        warnings.push(Warning::new(
+                            WarningCode::GuardRequireFailed,
+                            &migration.script,
+                            format!(
+                                "require guard failed (continuing): {}{}",
+                                expr_str,
+                                guard
+                                    .message
+                                    .as_deref()
+                                    .map(|m| format!(" — {}", m))
+                                    .unwrap_or_default()
+                            ),
+                        ));
+                    }
                     crate::guard::OnRequireFail::Error => {
                         return Ok(GuardAction::Error(WaypointError::GuardFailed {
                             kind: "require".to_string(),
                             script: migration.script.clone(),
                             expression: expr_str.clone(),
+                            message: guard.message.clone(),
                         }));
                     }
                 },
@@ -64,6 +96,7 @@ async fn evaluate_require_guards_db(
                         kind: "require".to_string(),
                         script: migration.script.clone(),
                         expression: format!("{} (evaluation error: {})", expr_str, e),
+                        message: guard.message.clone(),
                     }));
                 }
             },
@@ -72,6 +105,7 @@ async fn evaluate_require_guards_db(
                     kind: "require".to_string(),
                     script: migration.script.clone(),
                     expression: format!("{} (parse error: {})", expr_str, e),
+                    message: guard.message.clone(),
                 }));
             }
         }
@@ -85,15 +119,25 @@ async fn evaluate_ensure_guards_db(
     schema: &str,
     migration: &ResolvedMigration,
 ) -> Result<()> {
-    for expr_str in &migration.directives.ensure {
+    for guard in &migration.directives.ensure {
+        let expr_str = &guard.expression;
         match crate::guard::parse(expr_str) {
-            Ok(expr) => match crate::guard::evaluate_db(client, schema, &expr).await {
+            Ok(expr) => match telemetry::guard(
+                "ensure",
+                &migration.script,
+                expr_str,
+                crate::guard::evaluate_db(client, schema, &expr),
+            )
+            .await
+            .inspect(|&passed| guard_evaluated(&migration.script, "ensure", expr_str, passed))
+            {
                 Ok(true) => {}
                 Ok(false) => {
                     return Err(WaypointError::GuardFailed {
                         kind: "ensure".to_string(),
                         script: migration.script.clone(),
                         expression: expr_str.clone(),
+                        message: guard.message.clone(),
                     });
                 }
                 Err(e) => {
@@ -101,6 +145,7 @@ async fn evaluate_ensure_guards_db(
                         kind: "ensure".to_string(),
                         script: migration.script.clone(),
                         expression: format!("{} (evaluation error: {})", expr_str, e),
+                        message: guard.message.clone(),
                     });
                 }
             },
@@ -109,6 +154,7 @@ async fn evaluate_ensure_guards_db(
                     kind: "ensure".to_string(),
                     script: migration.script.clone(),
                     expression: format!("{} (parse error: {})", expr_str, e),
+                    message: guard.message.clone(),
                 });
             }
         }
@@ -126,6 +172,12 @@ pub async fn execute(
 }
 
 /// Execute the migrate command with options (MySQL).
+#[tracing::instrument(
+    name = "migrate",
+    skip_all,
+    fields(schema = %config.migrations.schema, target = target_version),
+    err(Display)
+)]
 pub async fn execute_with_options(
     client: &DbClient,
     config: &WaypointConfig,
@@ -140,13 +192,16 @@ pub async fn execute_with_options(
         )));
     }
 
-    let table = &config.migrations.table;
+    let lock = LockTarget::new(
+        &config.migrations,
+        &client.resolve_schema(&config.migrations.schema).await?,
+    );
 
-    client.acquire_lock(table).await?;
+    client.acquire_lock(&lock).await?;
 
     let result = run_migrate(client, config, target_version).await;
 
-    if let Err(e) = client.release_lock(table).await {
+    if let Err(e) = client.release_lock(&lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
@@ -171,10 +226,19 @@ async fn run_migrate(
     config: &WaypointConfig,
     target_version: Option<&str>,
 ) -> Result<MigrateReport> {
+    let prereqs =
+        crate::prerequisites::ensure_prerequisites_db(client, &config.prerequisites).await?;
+    if prereqs.created_count() > 0 {
+        log::info!("Prerequisites created; count={}", prereqs.created_count());
+    }
+
     let schema = client.resolve_schema(&config.migrations.schema).await?;
     let table = &config.migrations.table;
 
     history::create_history_table_db(client, &schema, table).await?;
+    if config.migrations.statement_progress {
+        crate::progress::create_progress_table_mysql(client, &schema, table).await?;
+    }
 
     if config.migrations.validate_on_migrate {
         if let Err(e) = crate::commands::validate::execute_db(client, config).await {
@@ -200,7 +264,13 @@ async fn run_migrate(
         }
     }
 
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    #[cfg(feature = "postgres")]
+    if !config.migrations.code_migrations.is_empty() {
+        return Err(WaypointError::ConfigError(
+            "Code migrations are only supported on PostgreSQL".to_string(),
+        ));
+    }
+    let resolved = config.migrations.resolve()?;
     let applied = history::get_applied_migrations_db(client, &schema, table).await?;
 
     let mut all_hooks: Vec<ResolvedHook> = hooks::scan_hooks(&config.migrations.locations)?;
@@ -222,17 +292,16 @@ async fn run_migrate(
         .unwrap_or(&db_user)
         .to_string();
 
-    let target = target_version.map(MigrationVersion::parse).transpose()?;
     let baseline_version = applied
         .iter()
         .find(|a| a.migration_type == "BASELINE")
         .and_then(|a| a.version.as_ref())
-        .map(|v| MigrationVersion::parse(v))
+        .map(|v| config.migrations.parse_version(v))
         .transpose()?;
     let effective_versions = history::effective_applied_versions(&applied);
     let highest_applied = effective_versions
         .iter()
-        .filter_map(|v| MigrationVersion::parse(v).ok())
+        .filter_map(|v| config.migrations.parse_version(v).ok())
         .max();
     let applied_scripts: HashMap<String, Option<i32>> = applied
         .iter()
@@ -240,6 +309,26 @@ async fn run_migrate(
         .map(|a| (a.script.clone(), a.checksum))
         .collect();
     let current_env = config.migrations.environment.as_deref();
+    let out_of_order_policy = config.migrations.out_of_order_policy();
+    let applied_versions: Vec<MigrationVersion> = effective_versions
+        .iter()
+        .filter_map(|v| config.migrations.parse_version(v).ok())
+        .collect();
+    let version_filter = config.migrations.version_filter()?;
+    let all_versioned: Vec<&ResolvedMigration> =
+        resolved.iter().filter(|m| m.is_versioned()).collect();
+    version_filter.check_known(&all_versioned)?;
+    let runnable = runnable_versions(
+        &resolved,
+        config,
+        &effective_versions,
+        baseline_version.as_ref(),
+    )?;
+    let target = resolve_target(
+        target_version,
+        &selectable_versions(&resolved, config)?,
+        |v| runnable.contains(v),
+    )?;
 
     let pending_versioned: Vec<&ResolvedMigration> = resolved
         .iter()
@@ -262,16 +351,17 @@ async fn run_migrate(
                     return false;
                 }
             }
+            if !version_filter.selects(v) {
+                return false;
+            }
             if let Some(ref t) = target {
                 if v > t {
                     return false;
                 }
             }
-            if !config.migrations.out_of_order {
-                if let Some(ref hi) = highest_applied {
-                    if v < hi {
-                        return false;
-                    }
+            if let Some(ref hi) = highest_applied {
+                if v < hi && !out_of_order_policy.allows(v, applied_versions.iter()) {
+                    return false;
                 }
             }
             if !should_run_in_environment(&m.directives, current_env) {
@@ -284,7 +374,7 @@ async fn run_migrate(
     let pending_repeatables: Vec<&ResolvedMigration> = resolved
         .iter()
         .filter(|m| {
-            if m.version().is_some() || m.is_undo() {
+            if m.version().is_some() || m.is_undo() || config.migrations.skip_repeatables {
                 return false;
             }
             if !should_run_in_environment(&m.directives, current_env) {
@@ -303,6 +393,12 @@ async fn run_migrate(
         details: Vec::new(),
         hooks_executed: 0,
         hooks_time_ms: 0,
+        maintenance: Vec::new(),
+        dependencies_added: Vec::new(),
+        warnings: Vec::new(),
+        checks: None,
+        schema_fingerprint: None,
+        manifest: crate::receipt::manifest(&resolved),
     };
 
     // `pending_versioned` isn't used again after this — move it in and sort
@@ -310,7 +406,47 @@ async fn run_migrate(
     let mut sorted_versioned = pending_versioned;
     sorted_versioned.sort_by(|a, b| a.version().unwrap().cmp(b.version().unwrap()));
 
+    // With dependency ordering, run in `-- waypoint:depends` order and add
+    // unapplied dependencies above the target.
+    if config.migrations.dependency_ordering {
+        let versioned: Vec<&ResolvedMigration> = resolved
+            .iter()
+            .filter(|m| m.is_versioned())
+            .filter(|m| should_run_in_environment(&m.directives, current_env))
+            .collect();
+        let (ordered, added) =
+            crate::dependency::resolve_pending(&versioned, &sorted_versioned, |v| {
+                effective_versions.contains(&v.raw)
+                    || baseline_version.as_ref().is_some_and(|bl| v <= bl)
+            })?;
+        version_filter.check_dependencies(&added)?;
+        if !added.is_empty() {
+            log::info!(
+                "Including dependencies above target; versions={}",
+                added.join(",")
+            );
+        }
+        sorted_versioned = ordered;
+        report.dependencies_added = added;
+    }
+
+    crate::commands::plan::check_approved(
+        highest_applied.as_ref().map(|v| v.raw.as_str()),
+        sorted_versioned
+            .iter()
+            .chain(pending_repeatables.iter())
+            .copied(),
+    )?;
+
     let has_pending = !sorted_versioned.is_empty() || !pending_repeatables.is_empty();
+    let hook_env = hooks::CommandHookEnv::new(
+        &db_name,
+        &schema,
+        sorted_versioned
+            .iter()
+            .chain(pending_repeatables.iter())
+            .copied(),
+    );
 
     if has_pending {
         let placeholders = build_placeholders(
@@ -328,13 +464,22 @@ async fn run_migrate(
             &mut report,
         )
         .await?;
+        let (count, ms) =
+            hooks::run_command_hooks(&config.hooks, &HookType::BeforeMigrate, &hook_env, &[])
+                .await?;
+        report.hooks_executed += count;
+        report.hooks_time_ms += ms;
     }
 
     for m in sorted_versioned {
+        if config.preflight.enabled && report.migrations_applied > 0 {
+            crate::preflight::wait_for_replicas(&config.preflight, DialectKind::Mysql).await?;
+        }
+
         let placeholders =
             build_placeholders(&config.placeholders, &schema, &db_user, &db_name, &m.script);
 
-        match evaluate_require_guards_db(client, &schema, m, config).await? {
+        match evaluate_require_guards_db(client, &schema, m, config, &mut report.warnings).await? {
             GuardAction::Continue => {}
             GuardAction::Skip => continue,
             GuardAction::Error(e) => return Err(e),
@@ -349,21 +494,51 @@ async fn run_migrate(
         )
         .await?;
 
+        let backup_ref =
+            if config.backup.command.is_some() && crate::backup::needs_backup(&m.sql, None) {
+                crate::backup::run_backup(
+                    &config.backup,
+                    &m.script,
+                    m.version().map(|v| v.raw.as_str()),
+                    &schema,
+                )
+                .await?
+            } else {
+                None
+            };
+
         let before_snapshot = if config.reversals.enabled && m.is_versioned() {
             Some(crate::reversal::capture_before_db(client, &schema).await?)
         } else {
             None
         };
 
-        let elapsed = apply_one(client, m, &schema, table, &installed_by, &placeholders).await?;
+        let (elapsed, rows_affected) = telemetry::traced(
+            telemetry::migration_span(m),
+            apply_one(
+                client,
+                config,
+                m,
+                &schema,
+                table,
+                &installed_by,
+                &placeholders,
+            ),
+        )
+        .await?;
         report.migrations_applied += 1;
         report.total_time_ms += elapsed;
-        report.details.push(MigrateDetail {
-            version: m.version().map(|v| v.raw.clone()),
-            description: m.description.clone(),
-            script: m.script.clone(),
-            execution_time_ms: elapsed,
-        });
+        report.push_detail(
+            MigrateDetail {
+                version: m.version().map(|v| v.raw.clone()),
+                description: m.description.clone(),
+                script: m.script.clone(),
+                execution_time_ms: elapsed,
+                rows_affected,
+                schema: None,
+            },
+            config.migrations.warn_rows_affected,
+        );
 
         // ensure guards run AFTER the migration. On MySQL DDL has already
         // auto-committed, so an ensure-failure does NOT roll back the
@@ -371,6 +546,24 @@ async fn run_migrate(
         // the post-migration state. This is the documented MySQL caveat.
         evaluate_ensure_guards_db(client, &schema, m).await?;
 
+        if let Some(ref reference) = backup_ref {
+            if let Err(e) =
+                crate::backup::store_backup_ref_db(client, &schema, table, &m.script, reference)
+                    .await
+            {
+                log::warn!(
+                    "Failed to record backup reference; migration={}, error={}",
+                    m.script,
+                    e
+                );
+                report.warnings.push(Warning::new(
+                    WarningCode::BackupRefNotRecorded,
+                    &m.script,
+                    format!("backup reference {} not recorded: {}", reference, e),
+                ));
+            }
+        }
+
         if let (Some(before), Some(ver)) = (before_snapshot.as_ref(), m.version()) {
             match crate::reversal::generate_reversal_db(
                 client,
@@ -396,14 +589,29 @@ async fn run_migrate(
                                 ver.raw,
                                 e
                             );
+                            report.warnings.push(Warning::new(
+                                WarningCode::ReversalFailed,
+                                &m.script,
+                                format!("reversal SQL not stored: {}", e),
+                            ));
                         }
                         for w in &result.warnings {
                             log::warn!("Reversal warning for version {}: {}", ver.raw, w);
+                            report.warnings.push(Warning::new(
+                                WarningCode::Reversal,
+                                &m.script,
+                                w.clone(),
+                            ));
                         }
                     }
                 }
                 Err(e) => {
                     log::warn!("Failed to generate reversal for version {}: {}", ver.raw, e);
+                    report.warnings.push(Warning::new(
+                        WarningCode::ReversalFailed,
+                        &m.script,
+                        format!("reversal not generated: {}", e),
+                    ));
                 }
             }
         }
@@ -422,7 +630,7 @@ async fn run_migrate(
         let placeholders =
             build_placeholders(&config.placeholders, &schema, &db_user, &db_name, &m.script);
 
-        match evaluate_require_guards_db(client, &schema, m, config).await? {
+        match evaluate_require_guards_db(client, &schema, m, config, &mut report.warnings).await? {
             GuardAction::Continue => {}
             GuardAction::Skip => continue,
             GuardAction::Error(e) => return Err(e),
@@ -437,15 +645,32 @@ async fn run_migrate(
         )
         .await?;
 
-        let elapsed = apply_one(client, m, &schema, table, &installed_by, &placeholders).await?;
+        let (elapsed, rows_affected) = telemetry::traced(
+            telemetry::migration_span(m),
+            apply_one(
+                client,
+                config,
+                m,
+                &schema,
+                table,
+                &installed_by,
+                &placeholders,
+            ),
+        )
+        .await?;
         report.migrations_applied += 1;
         report.total_time_ms += elapsed;
-        report.details.push(MigrateDetail {
-            version: None,
-            description: m.description.clone(),
-            script: m.script.clone(),
-            execution_time_ms: elapsed,
-        });
+        report.push_detail(
+            MigrateDetail {
+                version: None,
+                description: m.description.clone(),
+                script: m.script.clone(),
+                execution_time_ms: elapsed,
+                rows_affected,
+                schema: None,
+            },
+            config.migrations.warn_rows_affected,
+        );
 
         evaluate_ensure_guards_db(client, &schema, m).await?;
 
@@ -475,11 +700,138 @@ async fn run_migrate(
             &mut report,
         )
         .await?;
+        let (count, ms) = hooks::run_command_hooks(
+            &config.hooks,
+            &HookType::AfterMigrate,
+            &hook_env,
+            &report.details,
+        )
+        .await?;
+        report.hooks_executed += count;
+        report.hooks_time_ms += ms;
+    }
+
+    if config.preflight.enabled && report.migrations_applied > 0 {
+        crate::preflight::log_replica_lag(&config.preflight, DialectKind::Mysql).await;
     }
 
     Ok(report)
 }
 
+/// Execute a migration statement by statement, recording each outcome in the
+/// progress table. MySQL DDL auto-commits, so `--resume` always applies:
+/// statements recorded as successful by a failed earlier attempt are skipped.
+async fn apply_statements(
+    client: &DbClient,
+    config: &WaypointConfig,
+    m: &ResolvedMigration,
+    schema: &str,
+    table: &str,
+    sql: &str,
+) -> Result<(i32, u64)> {
+    use mysql_async::prelude::*;
+
+    let statements = crate::sql_parser::split_mysql_statements(sql);
+    let total = statements.len();
+    let progress_table = client
+        .dialect()
+        .qualified_table(schema, &crate::progress::progress_table_name(table));
+
+    let mut skip = 0;
+    if config.migrations.resume {
+        let records =
+            crate::progress::get_statement_records_mysql(client, schema, table, &m.script).await?;
+        skip = crate::progress::resume_point(&m.script, m.checksum, &records)?;
+        if skip > 0 {
+            log::info!(
+                "Resuming migration; migration={}, completed_statements={}, total_statements={}",
+                m.script,
+                skip,
+                total
+            );
+        }
+    }
+
+    let mut conn = client.as_mysql()?.get_conn().await?;
+    crate::progress::clear_statements_mysql(&mut conn, &progress_table, &m.script, skip as i32 + 1)
+        .await?;
+
+    let start = std::time::Instant::now();
+    let mut total_rows = 0u64;
+    for (i, stmt) in statements.iter().enumerate().skip(skip) {
+        let index = i as i32 + 1;
+        let stmt_start = std::time::Instant::now();
+        let span = telemetry::statement_span(&m.script, index, total);
+        let result = telemetry::traced(span.clone(), conn.query_drop(stmt)).await;
+        let duration_ms = stmt_start.elapsed().as_millis() as i32;
+        match result {
+            Ok(()) => {
+                let rows = conn.affected_rows();
+                span.record("rows", rows);
+                total_rows += rows;
+                crate::events::emit(|| crate::events::MigrateEventKind::StatementExecuted {
+                    script: m.script.clone(),
+                    statement: i + 1,
+                    total,
+                    rows_affected: rows,
+                    duration_ms,
+                });
+                crate::progress::record_statement_mysql(
+                    &mut conn,
+                    &progress_table,
+                    &m.script,
+                    m.checksum,
+                    index,
+                    duration_ms,
+                    Some(rows as i64),
+                    true,
+                )
+                .await?;
+            }
+            Err(e) => {
+                if let Err(record_err) = crate::progress::record_statement_mysql(
+                    &mut conn,
+                    &progress_table,
+                    &m.script,
+                    m.checksum,
+                    index,
+                    duration_ms,
+                    None,
+                    false,
+                )
+                .await
+                {
+                    log::warn!(
+                        "Failed to record statement failure; script={}, statement={}, error={}",
+                        m.script,
+                        index,
+                        record_err
+                    );
+                }
+                return Err(WaypointError::MigrationFailed {
+                    script: m.script.clone(),
+                    reason: format!("statement {} of {}: {}", index, total, e),
+                });
+            }
+        }
+    }
+    Ok((start.elapsed().as_millis() as i32, total_rows))
+}
+
+/// Run plain (untracked) migration SQL on one connection, summing affected rows.
+async fn execute_counting(client: &DbClient, sql: &str) -> Result<(i32, u64)> {
+    use mysql_async::prelude::*;
+
+    let start = std::time::Instant::now();
+    let mut conn = client.as_mysql()?.get_conn().await?;
+    let mut rows = 0u64;
+    for stmt in crate::sql_parser::split_mysql_statements(sql) {
+        conn.query_drop(&stmt).await?;
+        rows += conn.affected_rows();
+    }
+    Ok((start.elapsed().as_millis() as i32, rows))
+}
+
 /// Run all hooks of `phase` and fold the result into `report`.
 async fn fire_hooks(
     client: &DbClient,
@@ -496,21 +848,38 @@ async fn fire_hooks(
 
 async fn apply_one(
     client: &DbClient,
+    config: &WaypointConfig,
     m: &ResolvedMigration,
     schema: &str,
     table: &str,
     installed_by: &str,
     placeholders: &HashMap<String, String>,
-) -> Result<i32> {
+) -> Result<(i32, u64)> {
     let sql = replace_placeholders(&m.sql, placeholders)?;
-    log::info!("Applying migration; script={}", m.script);
-    let elapsed = client
-        .execute_raw(&sql)
-        .await
-        .map_err(|e| WaypointError::MigrationFailed {
+    if !m.directives.copy.is_empty() {
+        return Err(WaypointError::MigrationFailed {
+            script: m.script.clone(),
+            reason: "-- waypoint:copy is not supported on MySQL (PostgreSQL only)".to_string(),
+        });
+    }
+    if !m.directives.settings.is_empty() {
+        return Err(WaypointError::MigrationFailed {
             script: m.script.clone(),
-            reason: e.to_string(),
-        })?;
+            reason: "-- waypoint:set is not supported on MySQL (PostgreSQL only)".to_string(),
+        });
+    }
+    log::info!("Applying migration; script={}", m.script);
+    migration_started(m);
+    let (elapsed, rows_affected) = if config.migrations.statement_progress {
+        apply_statements(client, config, m, schema, table, &sql).await?
+    } else {
+        execute_counting(client, &sql)
+            .await
+            .map_err(|e| WaypointError::MigrationFailed {
+                script: m.script.clone(),
+                reason: e.to_string(),
+            })?
+    };
 
     let migration_type = if m.version().is_some() {
         "SQL"
@@ -531,6 +900,14 @@ async fn apply_one(
         true,
     )
     .await?;
+    if config.migrations.checksum_algorithm == ChecksumAlgorithm::Sha256 {
+        history::update_checksum_sha256_db(client, schema, table, &m.script, &m.checksum_sha256())
+            .await?;
+    }
+    let audit = AuditMetadata::collect(&config.audit);
+    if !audit.is_empty() {
+        crate::audit::store_audit_db(client, schema, table, &m.script, &audit).await?;
+    }
 
-    Ok(elapsed)
+    Ok((elapsed, rows_affected))
 }
@@ -9,6 +9,7 @@
 use mysql_async::prelude::*;
 
 use crate::db::DbClient;
+use crate::dialect::DialectKind;
 use crate::error::Result;
 use crate::safety::{
     affected_table, classify_row_count, compute_verdict, is_data_loss, LockLevel, SafetyConfig,
@@ -349,6 +350,11 @@ pub async fn analyze_migration(
         overall_verdict: worst_verdict,
         statements,
         suggestions: all_suggestions,
+        server_version: (version != MysqlVersion(0, 0, 0))
+            .then(|| format!("{}.{}.{}", version.0, version.1, version.2)),
+        privileged: crate::safety::privileged_statements(sql, DialectKind::Mysql),
+        default_changes: Vec::new(),
+        unanalyzable: false,
     })
 }
 
@@ -4,9 +4,11 @@
 
 use tokio_postgres::Client;
 
-use crate::advisor::{AdvisorConfig, AdvisorReport, Advisory, AdvisorySeverity};
+use crate::advisor::{filter_advisories, AdvisorConfig, AdvisorReport, Advisory, AdvisorySeverity};
 use crate::db::quote_ident;
+use crate::dialect::PgVersion;
 use crate::error::Result;
+use crate::introspection;
 
 /// Run all PostgreSQL advisory rules against the database schema.
 pub async fn analyze(
@@ -14,38 +16,115 @@ pub async fn analyze(
     schema: &str,
     config: &AdvisorConfig,
 ) -> Result<AdvisorReport> {
-    let mut advisories = Vec::new();
+    let version = crate::db::get_server_version(client).await.ok();
+    // CockroachDB has no pg_stat_* usage counters, so stats-based rules don't apply.
+    let crdb = crate::db::is_cockroach(client).await;
+    // Rules whose query hits the introspection timeout are skipped and listed.
+    let incomplete = std::sync::Mutex::new(Vec::new());
+    let mut advisories = introspection::limited(client, async {
+        let mut advisories = Vec::new();
+
+        if !config.disabled_rules.contains(&"A001".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A001",
+                    check_a001_fk_without_index(client, schema),
+                )
+                .await?,
+            );
+        }
+        if !crdb && !config.disabled_rules.contains(&"A002".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A002",
+                    check_a002_unused_indexes(client, schema),
+                )
+                .await?,
+            );
+        }
+        if !config.disabled_rules.contains(&"A003".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A003",
+                    check_a003_timestamp_without_tz(client, schema),
+                )
+                .await?,
+            );
+        }
+        if !config.disabled_rules.contains(&"A004".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A004",
+                    check_a004_table_without_pk(client, schema),
+                )
+                .await?,
+            );
+        }
+        if !crdb && !config.disabled_rules.contains(&"A005".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A005",
+                    check_a005_nullable_all_nonnull(client, schema, version),
+                )
+                .await?,
+            );
+        }
+        if !config.disabled_rules.contains(&"A006".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A006",
+                    check_a006_varchar_without_limit(client, schema),
+                )
+                .await?,
+            );
+        }
+        if !config.disabled_rules.contains(&"A007".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A007",
+                    check_a007_duplicate_indexes(client, schema),
+                )
+                .await?,
+            );
+        }
+        if !crdb && !config.disabled_rules.contains(&"A008".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A008",
+                    check_a008_seq_scan_large_table(client, schema),
+                )
+                .await?,
+            );
+        }
+        if !config.disabled_rules.contains(&"A009".to_string()) {
+            advisories.extend(
+                introspection::tolerate(&incomplete, "A009", check_a009_large_enum(client, schema))
+                    .await?,
+            );
+        }
+        if !config.disabled_rules.contains(&"A010".to_string()) {
+            advisories.extend(
+                introspection::tolerate(
+                    &incomplete,
+                    "A010",
+                    check_a010_orphaned_sequences(client, schema),
+                )
+                .await?,
+            );
+        }
+        Ok(advisories)
+    })
+    .await?;
 
-    if !config.disabled_rules.contains(&"A001".to_string()) {
-        advisories.extend(check_a001_fk_without_index(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A002".to_string()) {
-        advisories.extend(check_a002_unused_indexes(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A003".to_string()) {
-        advisories.extend(check_a003_timestamp_without_tz(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A004".to_string()) {
-        advisories.extend(check_a004_table_without_pk(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A005".to_string()) {
-        advisories.extend(check_a005_nullable_all_nonnull(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A006".to_string()) {
-        advisories.extend(check_a006_varchar_without_limit(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A007".to_string()) {
-        advisories.extend(check_a007_duplicate_indexes(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A008".to_string()) {
-        advisories.extend(check_a008_seq_scan_large_table(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A009".to_string()) {
-        advisories.extend(check_a009_large_enum(client, schema).await?);
-    }
-    if !config.disabled_rules.contains(&"A010".to_string()) {
-        advisories.extend(check_a010_orphaned_sequences(client, schema).await?);
-    }
+    filter_advisories(&mut advisories);
 
     let warning_count = advisories
         .iter()
@@ -66,6 +145,8 @@ pub async fn analyze(
         warning_count,
         suggestion_count,
         info_count,
+        server_version: version.map(|v| v.to_string()),
+        incomplete: introspection::into_sorted(incomplete),
     })
 }
 
@@ -231,7 +312,11 @@ async fn check_a004_table_without_pk(client: &Client, schema: &str) -> Result<Ve
 
 // ── A005: Nullable column where all values are non-null ──
 
-async fn check_a005_nullable_all_nonnull(client: &Client, schema: &str) -> Result<Vec<Advisory>> {
+async fn check_a005_nullable_all_nonnull(
+    client: &Client,
+    schema: &str,
+    version: Option<PgVersion>,
+) -> Result<Vec<Advisory>> {
     let sql = r#"
         SELECT c.table_name, c.column_name
         FROM information_schema.columns c
@@ -268,11 +353,7 @@ async fn check_a005_nullable_all_nonnull(client: &Client, schema: &str) -> Resul
                         "Column {}.{} is nullable but contains no NULL values. Consider adding NOT NULL constraint",
                         table, column
                     ),
-                    fix_sql: Some(format!(
-                        "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;",
-                        quote_ident(&table),
-                        quote_ident(&column)
-                    )),
+                    fix_sql: Some(set_not_null_fix_sql(&table, &column, version)),
                 });
             }
         }
@@ -281,6 +362,30 @@ async fn check_a005_nullable_all_nonnull(client: &Client, schema: &str) -> Resul
     Ok(advisories)
 }
 
+/// Build the A005 fix. PG 12+ skips the full-table scan of `SET NOT NULL`
+/// when a validated `IS NOT NULL` check constraint already proves it, so the
+/// scan happens under `VALIDATE CONSTRAINT` (SHARE UPDATE EXCLUSIVE) instead.
+fn set_not_null_fix_sql(table: &str, column: &str, version: Option<PgVersion>) -> String {
+    if version.is_some_and(|v| v.has_not_null_from_check()) {
+        let check = quote_ident(&format!("{}_{}_not_null", table, column));
+        format!(
+            "ALTER TABLE {t} ADD CONSTRAINT {k} CHECK ({c} IS NOT NULL) NOT VALID;\n\
+             ALTER TABLE {t} VALIDATE CONSTRAINT {k};\n\
+             ALTER TABLE {t} ALTER COLUMN {c} SET NOT NULL;\n\
+             ALTER TABLE {t} DROP CONSTRAINT {k};",
+            t = quote_ident(table),
+            c = quote_ident(column),
+            k = check
+        )
+    } else {
+        format!(
+            "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;",
+            quote_ident(table),
+            quote_ident(column)
+        )
+    }
+}
+
 // ── A006: VARCHAR without length limit ──
 
 async fn check_a006_varchar_without_limit(client: &Client, schema: &str) -> Result<Vec<Advisory>> {
@@ -463,3 +568,25 @@ async fn check_a010_orphaned_sequences(client: &Client, schema: &str) -> Result<
         })
         .collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_a005_fix_plain_without_version() {
+        let sql = set_not_null_fix_sql("users", "email", None);
+        assert_eq!(
+            sql,
+            "ALTER TABLE \"users\" ALTER COLUMN \"email\" SET NOT NULL;"
+        );
+    }
+
+    #[test]
+    fn test_a005_fix_uses_check_constraint_on_pg12() {
+        let sql = set_not_null_fix_sql("users", "email", Some(PgVersion::from_version_num(120000)));
+        assert!(sql.contains("CHECK (\"email\" IS NOT NULL) NOT VALID"));
+        assert!(sql.contains("VALIDATE CONSTRAINT \"users_email_not_null\""));
+        assert!(sql.ends_with("DROP CONSTRAINT \"users_email_not_null\";"));
+    }
+}
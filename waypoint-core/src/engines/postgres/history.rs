@@ -5,9 +5,11 @@
 
 use tokio_postgres::Client;
 
+use crate::audit::AuditMetadata;
 use crate::db::quote_ident;
+use crate::dialect::postgres::PostgresDialect;
 use crate::error::Result;
-use crate::history::AppliedMigration;
+use crate::history::{AppliedMigration, HistoryUpgrade, HISTORY_META_TABLE};
 
 /// Create the schema history table if it does not exist.
 pub async fn create_history_table(client: &Client, schema: &str, table: &str) -> Result<()> {
@@ -27,7 +29,13 @@ CREATE TABLE IF NOT EXISTS {fq} (
     installed_on   TIMESTAMPTZ NOT NULL DEFAULT now(),
     execution_time INTEGER NOT NULL,
     success        BOOLEAN NOT NULL,
-    reversal_sql   TEXT
+    reversal_sql   TEXT,
+    backup_ref     TEXT,
+    checksum_sha256 VARCHAR(64),
+    git_commit     VARCHAR(64),
+    ci_build_url   TEXT,
+    hostname       VARCHAR(255),
+    waypoint_version VARCHAR(32)
 );
 
 CREATE INDEX IF NOT EXISTS {idx_name} ON {fq} (success);
@@ -39,20 +47,77 @@ CREATE INDEX IF NOT EXISTS {ver_idx_name} ON {fq} (version);
     );
 
     client.batch_execute(&sql).await?;
-    upgrade_history_table(client, schema, table).await?;
+    ensure_schema_version(client, schema, table).await?;
     Ok(())
 }
 
-/// Auto-upgrade the history table to add new columns if they don't exist.
-async fn upgrade_history_table(client: &Client, schema: &str, table: &str) -> Result<()> {
-    let fq = format!("{}.{}", quote_ident(schema), quote_ident(table));
+/// Bring an existing history table up to the current layout on first
+/// contact (see [`crate::history::ensure_schema_version_db`]).
+pub async fn ensure_schema_version(
+    client: &Client,
+    schema: &str,
+    table: &str,
+) -> Result<HistoryUpgrade> {
+    let mut upgrade = plan_history_upgrade(client, schema, table).await?;
+    let result = apply_history_upgrade(client, &mut upgrade).await;
+    crate::history::check_first_contact(&upgrade, result)?;
+    Ok(upgrade)
+}
+
+/// Read the history-table layout version recorded in `waypoint_meta`.
+pub async fn history_schema_version(
+    client: &Client,
+    schema: &str,
+    table: &str,
+) -> Result<Option<i32>> {
+    if !history_table_exists(client, schema, HISTORY_META_TABLE).await? {
+        return Ok(None);
+    }
     let sql = format!(
-        "ALTER TABLE {fq} ADD COLUMN IF NOT EXISTS reversal_sql TEXT",
-        fq = fq,
+        "SELECT schema_version FROM {}.{} WHERE history_table = $1",
+        quote_ident(schema),
+        quote_ident(HISTORY_META_TABLE)
     );
-    if let Err(e) = client.batch_execute(&sql).await {
-        log::debug!("History table upgrade (reversal_sql): {}", e);
+    let row = client.query_opt(&sql, &[&table]).await?;
+    Ok(row.map(|r| r.get(0)))
+}
+
+/// Plan the upgrade of a history table to the current layout.
+pub async fn plan_history_upgrade(
+    client: &Client,
+    schema: &str,
+    table: &str,
+) -> Result<HistoryUpgrade> {
+    let version = history_schema_version(client, schema, table).await?;
+    let rows = client
+        .query(
+            "SELECT column_name::text FROM information_schema.columns
+             WHERE table_schema = $1 AND table_name = $2",
+            &[&schema, &table],
+        )
+        .await?;
+    let columns: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+    let upgrade =
+        crate::history::build_upgrade_plan(&PostgresDialect, schema, table, version, &columns);
+    crate::history::warn_if_newer(&upgrade);
+    Ok(upgrade)
+}
+
+/// Apply a planned upgrade in one transaction, so a failure leaves the
+/// history table and `waypoint_meta` as they were.
+pub async fn apply_history_upgrade(client: &Client, upgrade: &mut HistoryUpgrade) -> Result<()> {
+    if upgrade.is_current() {
+        return Ok(());
     }
+    log::info!(
+        "Upgrading history table; table={}, from_version={:?}, to_version={}, columns_added={:?}",
+        upgrade.table,
+        upgrade.from_version,
+        upgrade.to_version,
+        upgrade.columns_added
+    );
+    crate::db::execute_in_transaction(client, &upgrade.statements.join(";\n")).await?;
+    upgrade.applied = true;
     Ok(())
 }
 
@@ -82,33 +147,45 @@ pub async fn next_installed_rank(client: &Client, schema: &str, table: &str) ->
 }
 
 /// Query all applied migrations from the history table.
+///
+/// Columns added by [`crate::history::HISTORY_UPGRADES`] are read by name
+/// and come back as `None` when an older table doesn't have them yet.
 pub async fn get_applied_migrations(
     client: &Client,
     schema: &str,
     table: &str,
 ) -> Result<Vec<AppliedMigration>> {
     let sql = format!(
-        "SELECT installed_rank, version, description, type, script, checksum, \
-         installed_by, installed_on, execution_time, success, reversal_sql \
-         FROM {}.{} ORDER BY installed_rank",
+        "SELECT * FROM {}.{} ORDER BY installed_rank",
         quote_ident(schema),
         quote_ident(table)
     );
     let rows = client.query(&sql, &[]).await?;
+    let upgraded = |row: &tokio_postgres::Row, name: &str| -> Option<String> {
+        row.try_get::<_, Option<String>>(name).ok().flatten()
+    };
     let mut migrations = Vec::with_capacity(rows.len());
     for row in rows {
         migrations.push(AppliedMigration {
-            installed_rank: row.get(0),
-            version: row.get(1),
-            description: row.get(2),
-            migration_type: row.get(3),
-            script: row.get(4),
-            checksum: row.get(5),
-            installed_by: row.get(6),
-            installed_on: row.get(7),
-            execution_time: row.get(8),
-            success: row.get(9),
-            reversal_sql: row.get(10),
+            installed_rank: row.get("installed_rank"),
+            version: row.get("version"),
+            description: row.get("description"),
+            migration_type: row.get("type"),
+            script: row.get("script"),
+            checksum: row.get("checksum"),
+            installed_by: row.get("installed_by"),
+            installed_on: row.get("installed_on"),
+            execution_time: row.get("execution_time"),
+            success: row.get("success"),
+            reversal_sql: upgraded(&row, "reversal_sql"),
+            backup_ref: upgraded(&row, "backup_ref"),
+            checksum_sha256: upgraded(&row, "checksum_sha256"),
+            audit: AuditMetadata {
+                git_commit: upgraded(&row, "git_commit"),
+                ci_build_url: upgraded(&row, "ci_build_url"),
+                hostname: upgraded(&row, "hostname"),
+                waypoint_version: upgraded(&row, "waypoint_version"),
+            },
         });
     }
     Ok(migrations)
@@ -202,6 +279,23 @@ pub async fn update_repeatable_checksum(
     Ok(())
 }
 
+/// Set the SHA-256 checksum on every history row for a script.
+pub async fn update_checksum_sha256(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    script: &str,
+    checksum_sha256: &str,
+) -> Result<()> {
+    let sql = format!(
+        "UPDATE {}.{} SET checksum_sha256 = $1 WHERE script = $2",
+        quote_ident(schema),
+        quote_ident(table)
+    );
+    client.execute(&sql, &[&checksum_sha256, &script]).await?;
+    Ok(())
+}
+
 /// Check if the history table has any entries.
 pub async fn has_entries(client: &Client, schema: &str, table: &str) -> Result<bool> {
     let sql = format!(
@@ -10,16 +10,24 @@ use std::collections::{HashMap, HashSet};
 
 use tokio_postgres::Client;
 
+use crate::audit::AuditMetadata;
+use crate::checksum::ChecksumAlgorithm;
+use crate::code_migration::CodeMigration;
 use crate::commands::migrate::{
-    should_run_in_environment, GuardAction, MigrateDetail, MigrateReport,
+    guard_evaluated, migration_started, resolve_target, runnable_versions, safety_analyzed,
+    selectable_versions, should_run_in_environment, GuardAction, MaintenanceAction, MigrateDetail,
+    MigrateReport, TableMaintenance,
 };
 use crate::config::WaypointConfig;
-use crate::db;
+use crate::db::{self, LockTarget};
+use crate::dialect::DialectKind;
 use crate::error::{Result, WaypointError};
 use crate::history;
 use crate::hooks::{self, HookType, ResolvedHook};
-use crate::migration::{scan_migrations, MigrationVersion, ResolvedMigration};
+use crate::migration::{load_copies, CopyLoad, MigrationVersion, ResolvedMigration};
 use crate::placeholder::{build_placeholders, replace_placeholders};
+use crate::telemetry;
+use crate::warning::{Warning, WarningCode};
 
 /// Common state prepared by `prepare_migrate()` for both run modes.
 struct MigrateSetup<'a> {
@@ -57,7 +65,15 @@ async fn prepare_migrate<'a>(
     let schema = &config.migrations.schema;
     let table = &config.migrations.table;
 
+    let prereqs = crate::prerequisites::ensure_prerequisites(client, &config.prerequisites).await?;
+    if prereqs.created_count() > 0 {
+        log::info!("Prerequisites created; count={}", prereqs.created_count());
+    }
+
     history::create_history_table(client, schema, table).await?;
+    if config.migrations.statement_progress {
+        crate::progress::create_progress_table(client, schema, table).await?;
+    }
 
     if config.migrations.validate_on_migrate {
         if let Err(e) = crate::commands::validate::execute(client, config).await {
@@ -85,7 +101,7 @@ async fn prepare_migrate<'a>(
         }
     }
 
-    let resolved = scan_migrations(&config.migrations.locations)?;
+    let resolved = config.migrations.resolve()?;
 
     let mut all_hooks: Vec<ResolvedHook> = hooks::scan_hooks(&config.migrations.locations)?;
     let config_hooks = hooks::load_config_hooks(&config.hooks)?;
@@ -106,22 +122,32 @@ async fn prepare_migrate<'a>(
         .unwrap_or(&db_user)
         .to_string();
 
-    let target = target_version.map(MigrationVersion::parse).transpose()?;
-
     let baseline_version = applied
         .iter()
         .find(|a| a.migration_type == "BASELINE")
         .and_then(|a| a.version.as_ref())
-        .map(|v| MigrationVersion::parse(v))
+        .map(|v| config.migrations.parse_version(v))
         .transpose()?;
 
     let effective_versions = history::effective_applied_versions(&applied);
 
     let highest_applied = effective_versions
         .iter()
-        .filter_map(|v| MigrationVersion::parse(v).ok())
+        .filter_map(|v| config.migrations.parse_version(v).ok())
         .max();
 
+    let runnable = runnable_versions(
+        &resolved,
+        config,
+        &effective_versions,
+        baseline_version.as_ref(),
+    )?;
+    let target = resolve_target(
+        target_version,
+        &selectable_versions(&resolved, config)?,
+        |v| runnable.contains(v),
+    )?;
+
     let applied_scripts: HashMap<String, Option<i32>> = applied
         .iter()
         .filter(|a| a.success && a.version.is_none())
@@ -146,12 +172,14 @@ async fn prepare_migrate<'a>(
 }
 
 /// Filter resolved migrations down to pending versioned ones, applying
-/// baseline/target/out-of-order checks.
+/// baseline/version-filter/target/out-of-order checks.
 fn filter_pending_versioned<'a>(
     versioned: &[&'a ResolvedMigration],
     setup: &MigrateSetup<'_>,
     config: &WaypointConfig,
 ) -> Result<Vec<&'a ResolvedMigration>> {
+    let filter = config.migrations.version_filter()?;
+    filter.check_known(versioned)?;
     let mut pending = Vec::new();
     for migration in versioned {
         let version = migration.version().unwrap();
@@ -167,6 +195,11 @@ fn filter_pending_versioned<'a>(
             }
         }
 
+        if !filter.selects(version) {
+            log::debug!("Skipping {} (not selected)", migration.script);
+            continue;
+        }
+
         if let Some(ref tv) = setup.target {
             if version > tv {
                 log::debug!("Skipping {} (above target {})", migration.script, tv);
@@ -174,14 +207,12 @@ fn filter_pending_versioned<'a>(
             }
         }
 
-        if !config.migrations.out_of_order {
-            if let Some(ref highest) = setup.highest_applied {
-                if version < highest {
-                    return Err(WaypointError::OutOfOrder {
-                        version: version.raw.clone(),
-                        highest: highest.raw.clone(),
-                    });
-                }
+        if let Some(ref highest) = setup.highest_applied {
+            if version < highest && !out_of_order_allowed(version, setup, config) {
+                return Err(WaypointError::OutOfOrder {
+                    version: version.raw.clone(),
+                    highest: highest.raw.clone(),
+                });
             }
         }
 
@@ -190,6 +221,52 @@ fn filter_pending_versioned<'a>(
     Ok(pending)
 }
 
+/// Whether `version`, below the highest applied version, may be applied
+/// under the configured out-of-order policy.
+fn out_of_order_allowed(
+    version: &MigrationVersion,
+    setup: &MigrateSetup<'_>,
+    config: &WaypointConfig,
+) -> bool {
+    let applied: Vec<MigrationVersion> = setup
+        .effective_versions
+        .iter()
+        .filter_map(|v| config.migrations.parse_version(v).ok())
+        .collect();
+    config
+        .migrations
+        .out_of_order_policy()
+        .allows(version, applied.iter())
+}
+
+/// With `dependency_ordering`, order the pending set by `-- waypoint:depends`
+/// and add unapplied dependencies above the target. Returns the added versions.
+fn order_by_dependencies<'a>(
+    versioned: &[&'a ResolvedMigration],
+    pending: Vec<&'a ResolvedMigration>,
+    setup: &MigrateSetup<'_>,
+    config: &WaypointConfig,
+) -> Result<(Vec<&'a ResolvedMigration>, Vec<String>)> {
+    if !config.migrations.dependency_ordering {
+        return Ok((pending, Vec::new()));
+    }
+    let (ordered, added) = crate::dependency::resolve_pending(versioned, &pending, |v| {
+        setup.effective_versions.contains(&v.raw)
+            || setup.baseline_version.as_ref().is_some_and(|bv| v <= bv)
+    })?;
+    config
+        .migrations
+        .version_filter()?
+        .check_dependencies(&added)?;
+    if !added.is_empty() {
+        log::info!(
+            "Including dependencies above target; versions={}",
+            added.join(",")
+        );
+    }
+    Ok((ordered, added))
+}
+
 /// Filter resolved migrations down to pending repeatable ones (checksum changed or new).
 fn filter_pending_repeatables<'a>(
     repeatables: &[&'a ResolvedMigration],
@@ -207,20 +284,70 @@ fn filter_pending_repeatables<'a>(
     pending
 }
 
+/// Fail before anything is applied when a pending migration contains a
+/// statement the connected role lacks the privileges for (`[safety]
+/// block_privileged`), rather than partway through the run. Migrations with
+/// `-- waypoint:safety-override` are skipped. Placeholders are replaced
+/// first, so statements are classified as `migrate` will run them.
+async fn guard_privileges(
+    client: &Client,
+    config: &WaypointConfig,
+    setup: &MigrateSetup<'_>,
+    migrations: &[&ResolvedMigration],
+) -> Result<()> {
+    let migrations: Vec<&ResolvedMigration> = migrations
+        .iter()
+        .copied()
+        .filter(|m| !m.directives.safety_override)
+        .collect();
+    let scripts = crate::wal::substituted_scripts(
+        config,
+        &setup.db_user,
+        &setup.db_name,
+        migrations.iter().copied(),
+    )?;
+    for (migration, sql) in migrations.iter().zip(&scripts) {
+        let statements = crate::safety::privileged_statements(sql, DialectKind::Postgres);
+        let unmet = super::safety::unmet_privileges(client, &statements).await?;
+        if !unmet.is_empty() {
+            let reasons: Vec<&str> = unmet.iter().map(|s| s.reason.as_str()).collect();
+            return Err(WaypointError::MigrationBlocked {
+                script: migration.script.clone(),
+                reason: format!(
+                    "role '{}' lacks the privileges it needs: {}",
+                    setup.db_user,
+                    reasons.join("; ")
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
 /// Evaluate all `-- waypoint:require` guard preconditions for a migration.
 async fn evaluate_require_guards(
     client: &Client,
     schema: &str,
     migration: &ResolvedMigration,
     config: &WaypointConfig,
+    warnings: &mut Vec<Warning>,
 ) -> Result<GuardAction> {
     if migration.directives.require.is_empty() {
         return Ok(GuardAction::Continue);
     }
 
-    for expr_str in &migration.directives.require {
+    for guard in &migration.directives.require {
+        let expr_str = &guard.expression;
         match crate::guard::parse(expr_str) {
-            Ok(expr) => match crate::guard::evaluate(client, schema, &expr).await {
+            Ok(expr) => match telemetry::guard(
+                "require",
+                &migration.script,
+                expr_str,
+                crate::guard::evaluate(client, schema, &expr),
+            )
+            .await
+            .inspect(|&passed| guard_evaluated(&migration.script, "require", expr_str, passed))
+            {
                 Ok(true) => {}
                 Ok(false) => match config.guards.on_require_fail {
                     crate::guard::OnRequireFail::Skip => {
@@ -231,16 +358,32 @@ async fn evaluate_require_guards(
                         );
                         return Ok(GuardAction::Skip);
                     }
-                    crate::guard::OnRequireFail::Warn => log::warn!(
-                        "Guard require failed (continuing); script={}, expr={}",
-                        migration.script,
-                        expr_str
-                    ),
+                    crate::guard::OnRequireFail::Warn => {
+                        log::warn!(
+                            "Guard require failed (continuing); script={}, expr={}",
+                            migration.script,
+                            expr_str
+                        );
+                        warnings.push(Warning::new(
+                            WarningCode::GuardRequireFailed,
+                            &migration.script,
+                            format!(
+                                "require guard failed (continuing): {}{}",
+                                expr_str,
+                                guard
+                                    .message
+                                    .as_deref()
+                                    .map(|m| format!(" — {}", m))
+                                    .unwrap_or_default()
+                            ),
+                        ));
+                    }
                     crate::guard::OnRequireFail::Error => {
                         return Ok(GuardAction::Error(WaypointError::GuardFailed {
                             kind: "require".to_string(),
                             script: migration.script.clone(),
                             expression: expr_str.clone(),
+                            message: guard.message.clone(),
                         }));
                     }
                 },
@@ -255,6 +398,7 @@ async fn evaluate_require_guards(
                         kind: "require".to_string(),
                         script: migration.script.clone(),
                         expression: format!("{} (evaluation error: {})", expr_str, e),
+                        message: guard.message.clone(),
                     }));
                 }
             },
@@ -263,6 +407,7 @@ async fn evaluate_require_guards(
                     kind: "require".to_string(),
                     script: migration.script.clone(),
                     expression: format!("{} (parse error: {})", expr_str, e),
+                    message: guard.message.clone(),
                 }));
             }
         }
@@ -276,15 +421,25 @@ async fn evaluate_ensure_guards(
     schema: &str,
     migration: &ResolvedMigration,
 ) -> Result<()> {
-    for expr_str in &migration.directives.ensure {
+    for guard in &migration.directives.ensure {
+        let expr_str = &guard.expression;
         match crate::guard::parse(expr_str) {
-            Ok(expr) => match crate::guard::evaluate(client, schema, &expr).await {
+            Ok(expr) => match telemetry::guard(
+                "ensure",
+                &migration.script,
+                expr_str,
+                crate::guard::evaluate(client, schema, &expr),
+            )
+            .await
+            .inspect(|&passed| guard_evaluated(&migration.script, "ensure", expr_str, passed))
+            {
                 Ok(true) => {}
                 Ok(false) => {
                     return Err(WaypointError::GuardFailed {
                         kind: "ensure".to_string(),
                         script: migration.script.clone(),
                         expression: expr_str.clone(),
+                        message: guard.message.clone(),
                     });
                 }
                 Err(e) => {
@@ -292,6 +447,7 @@ async fn evaluate_ensure_guards(
                         kind: "ensure".to_string(),
                         script: migration.script.clone(),
                         expression: format!("{} (evaluation error: {})", expr_str, e),
+                        message: guard.message.clone(),
                     });
                 }
             },
@@ -300,6 +456,7 @@ async fn evaluate_ensure_guards(
                     kind: "ensure".to_string(),
                     script: migration.script.clone(),
                     expression: format!("{} (parse error: {})", expr_str, e),
+                    message: guard.message.clone(),
                 });
             }
         }
@@ -317,26 +474,38 @@ pub async fn execute(
 }
 
 /// Execute the migrate command with additional options.
+#[tracing::instrument(
+    name = "migrate",
+    skip_all,
+    fields(schema = %config.migrations.schema, target = target_version),
+    err(Display)
+)]
 pub async fn execute_with_options(
     client: &Client,
     config: &WaypointConfig,
     target_version: Option<&str>,
     force: bool,
 ) -> Result<MigrateReport> {
-    let table = &config.migrations.table;
+    let lock = LockTarget::new(&config.migrations, &config.migrations.schema);
 
-    db::acquire_advisory_lock(client, table).await?;
+    db::acquire_advisory_lock(client, &lock).await?;
 
-    let result = if config.migrations.batch_transaction {
+    let mut result = if config.migrations.batch_transaction {
         run_batch_migrate(client, config, target_version, force).await
     } else {
         run_migrate(client, config, target_version, force).await
     };
 
-    if let Err(e) = db::release_advisory_lock(client, table).await {
+    if let Err(e) = db::release_advisory_lock(client, &lock).await {
         log::error!("Failed to release advisory lock: {}", e);
     }
 
+    if let Ok(report) = &mut result {
+        if config.notify.enabled && report.migrations_applied > 0 {
+            crate::notify::send(client, config, report).await;
+        }
+    }
+
     match &result {
         Ok(report) => log::info!(
             "Migrate completed; migrations_applied={}, total_time_ms={}, hooks_executed={}",
@@ -367,8 +536,41 @@ async fn run_migrate(
         details: Vec::new(),
         hooks_executed: 0,
         hooks_time_ms: 0,
+        maintenance: Vec::new(),
+        dependencies_added: Vec::new(),
+        warnings: Vec::new(),
+        checks: None,
+        schema_fingerprint: None,
+        manifest: crate::receipt::manifest(&setup.resolved),
     };
 
+    let versioned: Vec<&ResolvedMigration> = setup
+        .resolved
+        .iter()
+        .filter(|m| m.is_versioned())
+        .filter(|m| should_run_in_environment(&m.directives, setup.current_env))
+        .collect();
+
+    let pending_versioned = filter_pending_versioned(&versioned, &setup, config)?;
+    let (pending_versioned, dependencies_added) =
+        order_by_dependencies(&versioned, pending_versioned, &setup, config)?;
+    report.dependencies_added = dependencies_added;
+
+    let repeatables: Vec<&ResolvedMigration> = setup
+        .resolved
+        .iter()
+        .filter(|m| !m.is_versioned() && !m.is_undo())
+        .filter(|_| !config.migrations.skip_repeatables)
+        .filter(|m| should_run_in_environment(&m.directives, setup.current_env))
+        .collect();
+    crate::commands::plan::check_approved(
+        setup.highest_applied.as_ref().map(|v| v.raw.as_str()),
+        pending_versioned
+            .iter()
+            .copied()
+            .chain(filter_pending_repeatables(&repeatables, &setup)),
+    )?;
+
     let before_placeholders = build_placeholders(
         &config.placeholders,
         schema,
@@ -386,165 +588,56 @@ async fn run_migrate(
     report.hooks_executed += count;
     report.hooks_time_ms += ms;
 
-    let versioned: Vec<&ResolvedMigration> = setup
-        .resolved
-        .iter()
-        .filter(|m| m.is_versioned())
-        .filter(|m| should_run_in_environment(&m.directives, setup.current_env))
-        .collect();
-
-    let pending_versioned = filter_pending_versioned(&versioned, &setup, config)?;
-
-    for migration in &pending_versioned {
-        let version = migration.version().unwrap();
-
-        let each_placeholders = build_placeholders(
-            &config.placeholders,
-            schema,
+    if config.preflight.enabled {
+        let scripts = crate::wal::substituted_scripts(
+            config,
             &setup.db_user,
             &setup.db_name,
-            &migration.script,
-        );
-        let (count, ms) = hooks::run_hooks(
-            client,
-            &setup.all_hooks,
-            &HookType::BeforeEachMigrate,
-            &each_placeholders,
-        )
-        .await?;
-        report.hooks_executed += count;
-        report.hooks_time_ms += ms;
+            pending_versioned.iter().copied(),
+        )?;
+        let scripts: Vec<&str> = scripts.iter().map(String::as_str).collect();
+        crate::wal::guard_wal(client, &config.preflight, schema, &scripts).await?;
+    }
 
-        if config.safety.enabled {
-            let safety_report = crate::safety::analyze_migration(
+    if config.safety.block_privileged && !force_override {
+        let pending: Vec<&ResolvedMigration> = pending_versioned
+            .iter()
+            .copied()
+            .chain(filter_pending_repeatables(&repeatables, &setup))
+            .collect();
+        guard_privileges(client, config, &setup, &pending).await?;
+    }
+
+    let hook_env = hooks::CommandHookEnv::new(
+        &setup.db_name,
+        schema,
+        pending_versioned
+            .iter()
+            .copied()
+            .chain(filter_pending_repeatables(&repeatables, &setup)),
+    );
+    let (count, ms) =
+        hooks::run_command_hooks(&config.hooks, &HookType::BeforeMigrate, &hook_env, &[]).await?;
+    report.hooks_executed += count;
+    report.hooks_time_ms += ms;
+
+    let waves = plan_waves(config, &setup, &versioned, &pending_versioned)?;
+    for wave in &waves {
+        if let [migration] = wave.as_slice() {
+            migrate_versioned(
                 client,
-                schema,
-                &migration.sql,
-                &migration.script,
-                &config.safety,
+                config,
+                &setup,
+                migration,
+                force_override,
+                &mut report,
             )
             .await?;
-            if safety_report.overall_verdict == crate::safety::SafetyVerdict::Danger
-                && config.safety.block_on_danger
-                && !migration.directives.safety_override
-                && !force_override
-            {
-                return Err(WaypointError::MigrationBlocked {
-                    script: migration.script.clone(),
-                    reason: safety_report.suggestions.join("; "),
-                });
-            }
-        }
-
-        match evaluate_require_guards(client, schema, migration, config).await? {
-            GuardAction::Continue => {}
-            GuardAction::Skip => continue,
-            GuardAction::Error(e) => return Err(e),
-        }
-
-        let before_snapshot = if config.reversals.enabled && migration.is_versioned() {
-            Some(crate::reversal::capture_before(client, schema).await?)
         } else {
-            None
-        };
-
-        let has_ensure_guards = !migration.directives.ensure.is_empty();
-        let exec_time = apply_migration(
-            client,
-            config,
-            migration,
-            schema,
-            table,
-            &setup.installed_by,
-            &setup.db_user,
-            &setup.db_name,
-            has_ensure_guards,
-        )
-        .await?;
-
-        if has_ensure_guards {
-            if let Err(guard_err) = evaluate_ensure_guards(client, schema, migration).await {
-                if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
-                    log::error!(
-                        "Failed to rollback after ensure guard failure: {}",
-                        rollback_err
-                    );
-                }
-                return Err(guard_err);
-            }
-            client.batch_execute("COMMIT").await?;
-        }
-
-        if let Some(ref before) = before_snapshot {
-            if let Some(ver) = migration.version() {
-                match crate::reversal::generate_reversal(
-                    client,
-                    schema,
-                    before,
-                    config.reversals.warn_data_loss,
-                )
-                .await
-                {
-                    Ok(result) => {
-                        if let Some(ref reversal_sql) = result.reversal_sql {
-                            if let Err(e) = crate::reversal::store_reversal(
-                                client,
-                                schema,
-                                table,
-                                &ver.raw,
-                                reversal_sql,
-                            )
-                            .await
-                            {
-                                log::warn!(
-                                    "Failed to store reversal SQL; version={}, error={}",
-                                    ver.raw,
-                                    e
-                                );
-                            }
-                        }
-                        for warning in &result.warnings {
-                            log::warn!("Reversal warning for {}: {}", migration.script, warning);
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!(
-                            "Failed to generate reversal; script={}, error={}",
-                            migration.script,
-                            e
-                        );
-                    }
-                }
-            }
+            migrate_wave(client, config, &setup, wave, force_override, &mut report).await?;
         }
-
-        let (count, ms) = hooks::run_hooks(
-            client,
-            &setup.all_hooks,
-            &HookType::AfterEachMigrate,
-            &each_placeholders,
-        )
-        .await?;
-        report.hooks_executed += count;
-        report.hooks_time_ms += ms;
-
-        report.migrations_applied += 1;
-        report.total_time_ms += exec_time;
-        report.details.push(MigrateDetail {
-            version: Some(version.raw.clone()),
-            description: migration.description.clone(),
-            script: migration.script.clone(),
-            execution_time_ms: exec_time,
-        });
     }
 
-    let repeatables: Vec<&ResolvedMigration> = setup
-        .resolved
-        .iter()
-        .filter(|m| !m.is_versioned() && !m.is_undo())
-        .filter(|m| should_run_in_environment(&m.directives, setup.current_env))
-        .collect();
-
     for migration in &repeatables {
         if let Some(&applied_checksum) = setup.applied_scripts.get(&migration.script) {
             if applied_checksum == Some(migration.checksum) {
@@ -573,16 +666,19 @@ async fn run_migrate(
         report.hooks_executed += count;
         report.hooks_time_ms += ms;
 
-        let exec_time = apply_migration(
-            client,
-            config,
-            migration,
-            schema,
-            table,
-            &setup.installed_by,
-            &setup.db_user,
-            &setup.db_name,
-            false,
+        let (exec_time, rows_affected) = telemetry::traced(
+            telemetry::migration_span(migration),
+            apply_migration(
+                client,
+                config,
+                migration,
+                schema,
+                table,
+                &setup.installed_by,
+                &setup.db_user,
+                &setup.db_name,
+                false,
+            ),
         )
         .await?;
 
@@ -598,12 +694,17 @@ async fn run_migrate(
 
         report.migrations_applied += 1;
         report.total_time_ms += exec_time;
-        report.details.push(MigrateDetail {
-            version: None,
-            description: migration.description.clone(),
-            script: migration.script.clone(),
-            execution_time_ms: exec_time,
-        });
+        report.push_detail(
+            MigrateDetail {
+                version: None,
+                description: migration.description.clone(),
+                script: migration.script.clone(),
+                execution_time_ms: exec_time,
+                rows_affected,
+                schema: None,
+            },
+            config.migrations.warn_rows_affected,
+        );
     }
 
     let after_placeholders = build_placeholders(
@@ -622,6 +723,30 @@ async fn run_migrate(
     .await?;
     report.hooks_executed += count;
     report.hooks_time_ms += ms;
+    let (count, ms) = hooks::run_command_hooks(
+        &config.hooks,
+        &HookType::AfterMigrate,
+        &hook_env,
+        &report.details,
+    )
+    .await?;
+    report.hooks_executed += count;
+    report.hooks_time_ms += ms;
+
+    if config.migrations.post_migrate_analyze {
+        report.maintenance = post_migrate_maintenance(
+            client,
+            config,
+            &setup,
+            &report.details,
+            &mut report.warnings,
+        )
+        .await;
+    }
+
+    if config.preflight.enabled && report.migrations_applied > 0 {
+        crate::preflight::log_replica_lag(&config.preflight, DialectKind::Postgres).await;
+    }
 
     Ok(report)
 }
@@ -724,15 +849,25 @@ async fn run_batch_migrate(
         .filter(|m| should_run_in_environment(&m.directives, current_env))
         .collect();
 
-    let mut pending_versioned = filter_pending_versioned(&versioned, &setup, config)?;
+    let pending_versioned = filter_pending_versioned(&versioned, &setup, config)?;
+    let (mut pending_versioned, dependencies_added) =
+        order_by_dependencies(&versioned, pending_versioned, &setup, config)?;
 
     let repeatables: Vec<&ResolvedMigration> = setup
         .resolved
         .iter()
         .filter(|m| !m.is_versioned() && !m.is_undo())
+        .filter(|_| !config.migrations.skip_repeatables)
         .filter(|m| should_run_in_environment(&m.directives, current_env))
         .collect();
     let pending_repeatables = filter_pending_repeatables(&repeatables, &setup);
+    crate::commands::plan::check_approved(
+        setup.highest_applied.as_ref().map(|v| v.raw.as_str()),
+        pending_versioned
+            .iter()
+            .chain(pending_repeatables.iter())
+            .copied(),
+    )?;
 
     let placeholders_map = build_placeholders(
         &config.placeholders,
@@ -742,10 +877,40 @@ async fn run_batch_migrate(
         "batch_validate",
     );
     for migration in pending_versioned.iter().chain(pending_repeatables.iter()) {
+        if migration.directives.no_transaction {
+            return Err(WaypointError::NonTransactionalStatement {
+                script: migration.script.clone(),
+                statement: "-- waypoint:no-transaction".to_string(),
+            });
+        }
         let sql = replace_placeholders(&migration.sql, &placeholders_map)?;
         validate_batch_compatible(&migration.script, &sql)?;
     }
 
+    if config.preflight.enabled {
+        let scripts = crate::wal::substituted_scripts(
+            config,
+            &setup.db_user,
+            &setup.db_name,
+            pending_versioned
+                .iter()
+                .chain(pending_repeatables.iter())
+                .copied(),
+        )?;
+        let scripts: Vec<&str> = scripts.iter().map(String::as_str).collect();
+        crate::wal::guard_wal(client, &config.preflight, schema, &scripts).await?;
+    }
+
+    if config.safety.block_privileged && !force_override {
+        let pending: Vec<&ResolvedMigration> = pending_versioned
+            .iter()
+            .chain(pending_repeatables.iter())
+            .copied()
+            .collect();
+        guard_privileges(client, config, &setup, &pending).await?;
+    }
+
+    let mut dangerous: HashSet<&str> = HashSet::new();
     if config.safety.enabled {
         for migration in &pending_versioned {
             let safety_report = crate::safety::analyze_migration(
@@ -756,6 +921,7 @@ async fn run_batch_migrate(
                 &config.safety,
             )
             .await?;
+            safety_analyzed(&migration.script, &safety_report);
             if safety_report.overall_verdict == crate::safety::SafetyVerdict::Danger
                 && config.safety.block_on_danger
                 && !migration.directives.safety_override
@@ -766,12 +932,18 @@ async fn run_batch_migrate(
                     reason: safety_report.suggestions.join("; "),
                 });
             }
+            if crate::backup::needs_backup(&migration.sql, Some(&safety_report)) {
+                dangerous.insert(&migration.script);
+            }
         }
     }
 
     let mut skipped_scripts: HashSet<&str> = HashSet::new();
+    let mut guard_warnings = Vec::new();
     for migration in &pending_versioned {
-        match evaluate_require_guards(client, schema, migration, config).await? {
+        match evaluate_require_guards(client, schema, migration, config, &mut guard_warnings)
+            .await?
+        {
             GuardAction::Continue => {}
             GuardAction::Skip => {
                 skipped_scripts.insert(&migration.script);
@@ -787,6 +959,12 @@ async fn run_batch_migrate(
         details: Vec::new(),
         hooks_executed: 0,
         hooks_time_ms: 0,
+        maintenance: Vec::new(),
+        dependencies_added,
+        warnings: guard_warnings,
+        checks: None,
+        schema_fingerprint: None,
+        manifest: crate::receipt::manifest(&setup.resolved),
     };
 
     let before_placeholders = build_placeholders(
@@ -805,8 +983,20 @@ async fn run_batch_migrate(
     .await?;
     report.hooks_executed += count;
     report.hooks_time_ms += ms;
-
-    if pending_versioned.is_empty() && pending_repeatables.is_empty() {
+    let hook_env = hooks::CommandHookEnv::new(
+        &setup.db_name,
+        schema,
+        pending_versioned
+            .iter()
+            .chain(pending_repeatables.iter())
+            .copied(),
+    );
+    let (count, ms) =
+        hooks::run_command_hooks(&config.hooks, &HookType::BeforeMigrate, &hook_env, &[]).await?;
+    report.hooks_executed += count;
+    report.hooks_time_ms += ms;
+
+    if pending_versioned.is_empty() && pending_repeatables.is_empty() {
         let after_placeholders = build_placeholders(
             &config.placeholders,
             schema,
@@ -823,9 +1013,46 @@ async fn run_batch_migrate(
         .await?;
         report.hooks_executed += count;
         report.hooks_time_ms += ms;
+        let (count, ms) =
+            hooks::run_command_hooks(&config.hooks, &HookType::AfterMigrate, &hook_env, &[])
+                .await?;
+        report.hooks_executed += count;
+        report.hooks_time_ms += ms;
         return Ok(report);
     }
 
+    // The batch commits once, so there is nothing to wait for between its
+    // migrations; gate on replica lag before the transaction starts instead.
+    if config.preflight.enabled {
+        crate::preflight::wait_for_replicas(&config.preflight, DialectKind::Postgres).await?;
+    }
+
+    // One backup before the batch transaction covers every destructive
+    // migration in it.
+    let backup_scripts: Vec<&ResolvedMigration> = if config.backup.command.is_some() {
+        pending_versioned
+            .iter()
+            .copied()
+            .filter(|m| {
+                dangerous.contains(m.script.as_str()) || crate::backup::needs_backup(&m.sql, None)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let backup_ref = match backup_scripts.first() {
+        Some(first) => {
+            crate::backup::run_backup(
+                &config.backup,
+                &first.script,
+                first.version().map(|v| v.raw.as_str()),
+                schema,
+            )
+            .await?
+        }
+        None => None,
+    };
+
     let before_snapshot = if config.reversals.enabled {
         match crate::reversal::capture_before(client, schema).await {
             Ok(snap) => Some(snap),
@@ -834,6 +1061,14 @@ async fn run_batch_migrate(
                     "Failed to capture before-snapshot for batch reversal: {}",
                     e
                 );
+                report.warnings.push(Warning::new(
+                    WarningCode::ReversalFailed,
+                    "batch",
+                    format!(
+                        "before-snapshot not captured, so no reversals were generated: {}",
+                        e
+                    ),
+                ));
                 None
             }
         }
@@ -841,154 +1076,256 @@ async fn run_batch_migrate(
         None
     };
 
-    let batch_start = std::time::Instant::now();
-    client.batch_execute("BEGIN").await?;
+    // One transaction covers every migration, so use the strictest level any
+    // of them asks for.
+    let isolation = pending_versioned
+        .iter()
+        .chain(pending_repeatables.iter())
+        .filter_map(|m| m.directives.isolation)
+        .chain(config.migrations.isolation_level)
+        .max();
+    let begin = match isolation {
+        Some(level) => format!("BEGIN ISOLATION LEVEL {}", level.as_sql()),
+        None => "BEGIN".to_string(),
+    };
 
+    // One transaction covers every migration, so a serialization failure
+    // retries the whole batch, up to the most retries any of them allows.
+    let retries = pending_versioned
+        .iter()
+        .chain(pending_repeatables.iter())
+        .map(|m| m.directives.retries)
+        .max()
+        .unwrap_or(0);
     let installed_by = &setup.installed_by;
-    let batch_result = async {
-        for migration in &pending_versioned {
-            let version = migration.version().unwrap();
-            let each_placeholders = build_placeholders(
-                &config.placeholders,
-                schema,
-                &setup.db_user,
-                &setup.db_name,
-                &migration.script,
-            );
-
-            let (count, ms) = hooks::run_hooks(
-                client,
-                &setup.all_hooks,
-                &HookType::BeforeEachMigrate,
-                &each_placeholders,
-            )
-            .await?;
-            report.hooks_executed += count;
-            report.hooks_time_ms += ms;
-
-            let sql = replace_placeholders(&migration.sql, &each_placeholders)?;
-            let start = std::time::Instant::now();
-            client
-                .batch_execute(&sql)
-                .await
-                .map_err(|e| WaypointError::MigrationFailed {
-                    script: migration.script.clone(),
-                    reason: crate::error::format_db_error(&e),
-                })?;
-            let exec_time = start.elapsed().as_millis() as i32;
-
-            let version_str = Some(version.raw.as_str());
-            let type_str = migration.migration_type().to_string();
-            history::insert_applied_migration(
-                client,
-                schema,
-                table,
-                version_str,
-                &migration.description,
-                &type_str,
-                &migration.script,
-                Some(migration.checksum),
-                installed_by,
-                exec_time,
-                true,
-            )
-            .await?;
+    let mut attempt = 0;
+    let mut batch_start;
+    let batch_result = loop {
+        batch_start = std::time::Instant::now();
+        client.batch_execute(&begin).await?;
+
+        // What the report held before this attempt, to put back on a retry.
+        let mark = (
+            report.migrations_applied,
+            report.total_time_ms,
+            report.details.len(),
+            report.hooks_executed,
+            report.hooks_time_ms,
+            report.warnings.len(),
+        );
+        let mut retryable = false;
+        let result = async {
+            for migration in &pending_versioned {
+                let version = migration.version().unwrap();
+                let each_placeholders = build_placeholders(
+                    &config.placeholders,
+                    schema,
+                    &setup.db_user,
+                    &setup.db_name,
+                    &migration.script,
+                );
 
-            let (count, ms) = hooks::run_hooks(
-                client,
-                &setup.all_hooks,
-                &HookType::AfterEachMigrate,
-                &each_placeholders,
-            )
-            .await?;
-            report.hooks_executed += count;
-            report.hooks_time_ms += ms;
+                let (count, ms) = hooks::run_hooks(
+                    client,
+                    &setup.all_hooks,
+                    &HookType::BeforeEachMigrate,
+                    &each_placeholders,
+                )
+                .await?;
+                report.hooks_executed += count;
+                report.hooks_time_ms += ms;
+
+                let sql = replace_placeholders(&migration.sql, &each_placeholders)?;
+                let copies = load_copies(&migration.script, &sql, &config.migrations.locations)?;
+                migration_started(migration);
+                let start = std::time::Instant::now();
+                let rows_affected = match config.migrations.code_migrations.find(&migration.script)
+                {
+                    Some(code) => {
+                        telemetry::traced(telemetry::migration_span(migration), code.up(client))
+                            .await
+                            .map_err(|e| WaypointError::MigrationFailed {
+                                script: migration.script.clone(),
+                                reason: e.to_string(),
+                            })?;
+                        0
+                    }
+                    None => telemetry::traced(
+                        telemetry::migration_span(migration),
+                        execute_with_copies(client, &migration.script, &sql, &copies),
+                    )
+                    .await
+                    .map_err(|e| {
+                        retryable = is_retryable(&e);
+                        WaypointError::MigrationFailed {
+                            script: migration.script.clone(),
+                            reason: error_reason(&e),
+                        }
+                    })?,
+                };
+                let exec_time = start.elapsed().as_millis() as i32;
 
-            report.migrations_applied += 1;
-            report.total_time_ms += exec_time;
-            report.details.push(MigrateDetail {
-                version: Some(version.raw.clone()),
-                description: migration.description.clone(),
-                script: migration.script.clone(),
-                execution_time_ms: exec_time,
-            });
-        }
+                insert_success_row(
+                    client,
+                    config,
+                    migration,
+                    schema,
+                    table,
+                    installed_by,
+                    exec_time,
+                )
+                .await?;
 
-        for migration in &pending_repeatables {
-            let each_placeholders = build_placeholders(
-                &config.placeholders,
-                schema,
-                &setup.db_user,
-                &setup.db_name,
-                &migration.script,
-            );
+                let (count, ms) = hooks::run_hooks(
+                    client,
+                    &setup.all_hooks,
+                    &HookType::AfterEachMigrate,
+                    &each_placeholders,
+                )
+                .await?;
+                report.hooks_executed += count;
+                report.hooks_time_ms += ms;
+
+                report.migrations_applied += 1;
+                report.total_time_ms += exec_time;
+                report.push_detail(
+                    MigrateDetail {
+                        version: Some(version.raw.clone()),
+                        description: migration.description.clone(),
+                        script: migration.script.clone(),
+                        execution_time_ms: exec_time,
+                        rows_affected,
+                        schema: None,
+                    },
+                    config.migrations.warn_rows_affected,
+                );
+            }
 
-            let (count, ms) = hooks::run_hooks(
-                client,
-                &setup.all_hooks,
-                &HookType::BeforeEachMigrate,
-                &each_placeholders,
-            )
-            .await?;
-            report.hooks_executed += count;
-            report.hooks_time_ms += ms;
+            for migration in &pending_repeatables {
+                let each_placeholders = build_placeholders(
+                    &config.placeholders,
+                    schema,
+                    &setup.db_user,
+                    &setup.db_name,
+                    &migration.script,
+                );
 
-            let sql = replace_placeholders(&migration.sql, &each_placeholders)?;
-            let start = std::time::Instant::now();
-            client
-                .batch_execute(&sql)
+                let (count, ms) = hooks::run_hooks(
+                    client,
+                    &setup.all_hooks,
+                    &HookType::BeforeEachMigrate,
+                    &each_placeholders,
+                )
+                .await?;
+                report.hooks_executed += count;
+                report.hooks_time_ms += ms;
+
+                let sql = replace_placeholders(&migration.sql, &each_placeholders)?;
+                let copies = load_copies(&migration.script, &sql, &config.migrations.locations)?;
+                migration_started(migration);
+                let start = std::time::Instant::now();
+                let rows_affected = telemetry::traced(
+                    telemetry::migration_span(migration),
+                    execute_with_copies(client, &migration.script, &sql, &copies),
+                )
                 .await
-                .map_err(|e| WaypointError::MigrationFailed {
-                    script: migration.script.clone(),
-                    reason: crate::error::format_db_error(&e),
+                .map_err(|e| {
+                    retryable = is_retryable(&e);
+                    WaypointError::MigrationFailed {
+                        script: migration.script.clone(),
+                        reason: error_reason(&e),
+                    }
                 })?;
-            let exec_time = start.elapsed().as_millis() as i32;
+                let exec_time = start.elapsed().as_millis() as i32;
 
-            let type_str = migration.migration_type().to_string();
-            history::insert_applied_migration(
-                client,
-                schema,
-                table,
-                None,
-                &migration.description,
-                &type_str,
-                &migration.script,
-                Some(migration.checksum),
-                installed_by,
-                exec_time,
-                true,
-            )
-            .await?;
+                insert_success_row(
+                    client,
+                    config,
+                    migration,
+                    schema,
+                    table,
+                    installed_by,
+                    exec_time,
+                )
+                .await?;
 
-            let (count, ms) = hooks::run_hooks(
-                client,
-                &setup.all_hooks,
-                &HookType::AfterEachMigrate,
-                &each_placeholders,
-            )
-            .await?;
-            report.hooks_executed += count;
-            report.hooks_time_ms += ms;
+                let (count, ms) = hooks::run_hooks(
+                    client,
+                    &setup.all_hooks,
+                    &HookType::AfterEachMigrate,
+                    &each_placeholders,
+                )
+                .await?;
+                report.hooks_executed += count;
+                report.hooks_time_ms += ms;
+
+                report.migrations_applied += 1;
+                report.total_time_ms += exec_time;
+                report.push_detail(
+                    MigrateDetail {
+                        version: None,
+                        description: migration.description.clone(),
+                        script: migration.script.clone(),
+                        execution_time_ms: exec_time,
+                        rows_affected,
+                        schema: None,
+                    },
+                    config.migrations.warn_rows_affected,
+                );
+            }
 
-            report.migrations_applied += 1;
-            report.total_time_ms += exec_time;
-            report.details.push(MigrateDetail {
-                version: None,
-                description: migration.description.clone(),
-                script: migration.script.clone(),
-                execution_time_ms: exec_time,
-            });
+            Ok::<(), WaypointError>(())
         }
+        .await;
 
-        Ok::<(), WaypointError>(())
-    }
-    .await;
+        match result {
+            Err(e) if attempt < retries && (retryable || is_retryable(&e)) => {
+                if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                    log::error!("Failed to rollback batch transaction: {}", rollback_err);
+                }
+                report.migrations_applied = mark.0;
+                report.total_time_ms = mark.1;
+                report.details.truncate(mark.2);
+                report.hooks_executed = mark.3;
+                report.hooks_time_ms = mark.4;
+                report.warnings.truncate(mark.5);
+                attempt += 1;
+                wait_for_serialization_retry("batch", attempt, retries).await;
+            }
+            result => break result,
+        }
+    };
 
     match batch_result {
         Ok(()) => {
             client.batch_execute("COMMIT").await?;
             report.total_time_ms = batch_start.elapsed().as_millis() as i32;
 
+            if let Some(ref reference) = backup_ref {
+                for migration in &backup_scripts {
+                    if let Err(e) = crate::backup::store_backup_ref(
+                        client,
+                        schema,
+                        table,
+                        &migration.script,
+                        reference,
+                    )
+                    .await
+                    {
+                        log::warn!(
+                            "Failed to record backup reference; migration={}, error={}",
+                            migration.script,
+                            e
+                        );
+                        report.warnings.push(Warning::new(
+                            WarningCode::BackupRefNotRecorded,
+                            &migration.script,
+                            format!("backup reference {} not recorded: {}", reference, e),
+                        ));
+                    }
+                }
+            }
+
             if let Some(ref before) = before_snapshot {
                 for migration in &pending_versioned {
                     if let Some(ver) = migration.version() {
@@ -1016,6 +1353,11 @@ async fn run_batch_migrate(
                                             ver.raw,
                                             e
                                         );
+                                        report.warnings.push(Warning::new(
+                                            WarningCode::ReversalFailed,
+                                            &migration.script,
+                                            format!("reversal SQL not stored: {}", e),
+                                        ));
                                     }
                                 }
                                 for warning in &result.warnings {
@@ -1024,6 +1366,11 @@ async fn run_batch_migrate(
                                         migration.script,
                                         warning
                                     );
+                                    report.warnings.push(Warning::new(
+                                        WarningCode::Reversal,
+                                        &migration.script,
+                                        warning.clone(),
+                                    ));
                                 }
                             }
                             Err(e) => {
@@ -1032,6 +1379,11 @@ async fn run_batch_migrate(
                                     migration.script,
                                     e
                                 );
+                                report.warnings.push(Warning::new(
+                                    WarningCode::ReversalFailed,
+                                    &migration.script,
+                                    format!("reversal not generated: {}", e),
+                                ));
                             }
                         }
                     }
@@ -1063,122 +1415,1662 @@ async fn run_batch_migrate(
     .await?;
     report.hooks_executed += count;
     report.hooks_time_ms += ms;
+    let (count, ms) = hooks::run_command_hooks(
+        &config.hooks,
+        &HookType::AfterMigrate,
+        &hook_env,
+        &report.details,
+    )
+    .await?;
+    report.hooks_executed += count;
+    report.hooks_time_ms += ms;
+
+    if config.migrations.post_migrate_analyze {
+        report.maintenance = post_migrate_maintenance(
+            client,
+            config,
+            &setup,
+            &report.details,
+            &mut report.warnings,
+        )
+        .await;
+    }
 
     Ok(report)
 }
 
-/// Apply a single migration within a transaction.
-#[allow(clippy::too_many_arguments)]
-async fn apply_migration(
+/// Split the pending versioned migrations into waves for
+/// `parallel_migrations`: one migration per wave unless parallel apply is
+/// configured and nothing rules it out.
+fn plan_waves<'a>(
+    config: &WaypointConfig,
+    setup: &MigrateSetup<'_>,
+    versioned: &[&ResolvedMigration],
+    pending: &[&'a ResolvedMigration],
+) -> Result<Vec<Vec<&'a ResolvedMigration>>> {
+    let requested = config.migrations.parallel_migrations;
+    let serial = pending.iter().map(|m| vec![*m]).collect();
+    if requested <= 1 {
+        return Ok(serial);
+    }
+    let per_migration_hooks = setup.all_hooks.iter().any(|h| {
+        matches!(
+            h.hook_type,
+            HookType::BeforeEachMigrate | HookType::AfterEachMigrate
+        )
+    });
+    let blocker = if !config.migrations.dependency_ordering {
+        Some("dependency_ordering is off")
+    } else if config.migrations.statement_progress {
+        Some("statement_progress is on")
+    } else if config.reversals.enabled {
+        Some("reversals are enabled")
+    } else if per_migration_hooks {
+        Some("per-migration hooks are configured")
+    } else if config.connection_string().is_err() {
+        Some("no connection settings for extra connections")
+    } else {
+        None
+    };
+    if let Some(reason) = blocker {
+        log::info!(
+            "Applying migrations serially; parallel_migrations={}, reason={}",
+            requested,
+            reason
+        );
+        return Ok(serial);
+    }
+    crate::parallel::plan_waves(versioned, pending, requested, |m| {
+        m.directives.ensure.is_empty() && !m.directives.no_transaction
+    })
+}
+
+/// Checks that passed before a versioned migration is applied.
+struct PreparedMigration {
+    /// Reference of the backup taken for it, if any.
+    backup_ref: Option<String>,
+}
+
+/// Run the safety check, require guards and backup for a versioned
+/// migration. `None` when a require guard skips it.
+async fn prepare_versioned(
     client: &Client,
     config: &WaypointConfig,
     migration: &ResolvedMigration,
-    schema: &str,
-    table: &str,
-    installed_by: &str,
-    db_user: &str,
-    db_name: &str,
-    hold_transaction: bool,
-) -> Result<i32> {
-    log::info!(
-        "Applying migration; migration={}, schema={}",
-        migration.script,
-        schema
-    );
+    force_override: bool,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<PreparedMigration>> {
+    let schema = &config.migrations.schema;
+    let version = migration.version().unwrap();
 
-    let placeholders = build_placeholders(
+    let safety_report = if config.safety.enabled {
+        let safety_report = crate::safety::analyze_migration(
+            client,
+            schema,
+            &migration.sql,
+            &migration.script,
+            &config.safety,
+        )
+        .await?;
+        safety_analyzed(&migration.script, &safety_report);
+        if safety_report.overall_verdict == crate::safety::SafetyVerdict::Danger
+            && config.safety.block_on_danger
+            && !migration.directives.safety_override
+            && !force_override
+        {
+            return Err(WaypointError::MigrationBlocked {
+                script: migration.script.clone(),
+                reason: safety_report.suggestions.join("; "),
+            });
+        }
+        Some(safety_report)
+    } else {
+        None
+    };
+
+    match evaluate_require_guards(client, schema, migration, config, warnings).await? {
+        GuardAction::Continue => {}
+        GuardAction::Skip => return Ok(None),
+        GuardAction::Error(e) => return Err(e),
+    }
+
+    let backup_ref = if config.backup.command.is_some()
+        && crate::backup::needs_backup(&migration.sql, safety_report.as_ref())
+    {
+        crate::backup::run_backup(
+            &config.backup,
+            &migration.script,
+            Some(&version.raw),
+            schema,
+        )
+        .await?
+    } else {
+        None
+    };
+
+    Ok(Some(PreparedMigration { backup_ref }))
+}
+
+/// Apply one versioned migration on `client`, with its before/after hooks.
+async fn migrate_versioned(
+    client: &Client,
+    config: &WaypointConfig,
+    setup: &MigrateSetup<'_>,
+    migration: &ResolvedMigration,
+    force_override: bool,
+    report: &mut MigrateReport,
+) -> Result<()> {
+    let schema = &config.migrations.schema;
+
+    if config.preflight.enabled && report.migrations_applied > 0 {
+        crate::preflight::wait_for_replicas(&config.preflight, DialectKind::Postgres).await?;
+    }
+
+    let each_placeholders = build_placeholders(
         &config.placeholders,
         schema,
-        db_user,
-        db_name,
+        &setup.db_user,
+        &setup.db_name,
         &migration.script,
     );
+    let (count, ms) = hooks::run_hooks(
+        client,
+        &setup.all_hooks,
+        &HookType::BeforeEachMigrate,
+        &each_placeholders,
+    )
+    .await?;
+    report.hooks_executed += count;
+    report.hooks_time_ms += ms;
 
-    let sql = replace_placeholders(&migration.sql, &placeholders)?;
+    let Some(prepared) = prepare_versioned(
+        client,
+        config,
+        migration,
+        force_override,
+        &mut report.warnings,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
 
-    let version_str = migration.version().map(|v| v.raw.as_str());
-    let type_str = migration.migration_type().to_string();
+    let (exec_time, rows_affected) = apply_versioned(
+        client,
+        config,
+        setup,
+        migration,
+        &prepared,
+        &mut report.warnings,
+    )
+    .await?;
 
-    let start = std::time::Instant::now();
-    client.batch_execute("BEGIN").await?;
+    let (count, ms) = hooks::run_hooks(
+        client,
+        &setup.all_hooks,
+        &HookType::AfterEachMigrate,
+        &each_placeholders,
+    )
+    .await?;
+    report.hooks_executed += count;
+    report.hooks_time_ms += ms;
 
-    match client.batch_execute(&sql).await {
-        Ok(()) => {
-            let exec_time = start.elapsed().as_millis() as i32;
-            match history::insert_applied_migration(
-                client,
-                schema,
-                table,
-                version_str,
-                &migration.description,
-                &type_str,
-                &migration.script,
-                Some(migration.checksum),
-                installed_by,
-                exec_time,
-                true,
-            )
-            .await
-            {
-                Ok(()) => {
-                    if !hold_transaction {
-                        client.batch_execute("COMMIT").await?;
-                    }
-                    Ok(exec_time)
-                }
-                Err(e) => {
-                    if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
-                        log::error!("Failed to rollback transaction: {}", rollback_err);
-                    }
-                    Err(e)
-                }
-            }
-        }
-        Err(e) => {
-            if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+    record_applied(report, config, migration, exec_time, rows_affected);
+    Ok(())
+}
+
+/// Apply a prepared versioned migration on `client`: ensure guards, backup
+/// reference and reversal included.
+async fn apply_versioned(
+    client: &Client,
+    config: &WaypointConfig,
+    setup: &MigrateSetup<'_>,
+    migration: &ResolvedMigration,
+    prepared: &PreparedMigration,
+    warnings: &mut Vec<Warning>,
+) -> Result<(i32, u64)> {
+    let schema = &config.migrations.schema;
+    let table = &config.migrations.table;
+
+    let before_snapshot = if config.reversals.enabled && migration.is_versioned() {
+        Some(crate::reversal::capture_before(client, schema).await?)
+    } else {
+        None
+    };
+
+    let has_ensure_guards = !migration.directives.ensure.is_empty();
+    let applied = telemetry::traced(
+        telemetry::migration_span(migration),
+        apply_migration(
+            client,
+            config,
+            migration,
+            schema,
+            table,
+            &setup.installed_by,
+            &setup.db_user,
+            &setup.db_name,
+            has_ensure_guards,
+        ),
+    )
+    .await;
+    if applied.is_err() {
+        // Keep the reference on the failure row, where a restore starts.
+        store_backup_ref(client, config, migration, prepared, warnings).await;
+    }
+    let (exec_time, rows_affected) = applied?;
+
+    if has_ensure_guards {
+        if let Err(guard_err) = evaluate_ensure_guards(client, schema, migration).await {
+            if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                log::error!(
+                    "Failed to rollback after ensure guard failure: {}",
+                    rollback_err
+                );
+            }
+            return Err(guard_err);
+        }
+        client.batch_execute("COMMIT").await?;
+    }
+
+    store_backup_ref(client, config, migration, prepared, warnings).await;
+    if let Some(ref before) = before_snapshot {
+        if let Some(ver) = migration.version() {
+            match crate::reversal::generate_reversal(
+                client,
+                schema,
+                before,
+                config.reversals.warn_data_loss,
+            )
+            .await
+            {
+                Ok(result) => {
+                    if let Some(ref reversal_sql) = result.reversal_sql {
+                        if let Err(e) = crate::reversal::store_reversal(
+                            client,
+                            schema,
+                            table,
+                            &ver.raw,
+                            reversal_sql,
+                        )
+                        .await
+                        {
+                            log::warn!(
+                                "Failed to store reversal SQL; version={}, error={}",
+                                ver.raw,
+                                e
+                            );
+                            warnings.push(Warning::new(
+                                WarningCode::ReversalFailed,
+                                &migration.script,
+                                format!("reversal SQL not stored: {}", e),
+                            ));
+                        }
+                    }
+                    for warning in &result.warnings {
+                        log::warn!("Reversal warning for {}: {}", migration.script, warning);
+                        warnings.push(Warning::new(
+                            WarningCode::Reversal,
+                            &migration.script,
+                            warning.clone(),
+                        ));
+                    }
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to generate reversal; script={}, error={}",
+                        migration.script,
+                        e
+                    );
+                    warnings.push(Warning::new(
+                        WarningCode::ReversalFailed,
+                        &migration.script,
+                        format!("reversal not generated: {}", e),
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok((exec_time, rows_affected))
+}
+
+/// Insert the success row for `migration` into the history table. With
+/// `checksum_algorithm = "sha256"` its SHA-256 is recorded next to the CRC32,
+/// and unless `[audit]` is disabled the audit metadata is recorded too, all in
+/// the caller's transaction.
+async fn insert_success_row(
+    client: &Client,
+    config: &WaypointConfig,
+    migration: &ResolvedMigration,
+    schema: &str,
+    table: &str,
+    installed_by: &str,
+    exec_time: i32,
+) -> Result<()> {
+    reset_directive_settings(client, migration).await?;
+    history::insert_applied_migration(
+        client,
+        schema,
+        table,
+        migration.version().map(|v| v.raw.as_str()),
+        &migration.description,
+        &migration.migration_type().to_string(),
+        &migration.script,
+        Some(migration.checksum),
+        installed_by,
+        exec_time,
+        true,
+    )
+    .await?;
+    if config.migrations.checksum_algorithm == ChecksumAlgorithm::Sha256 {
+        history::update_checksum_sha256(
+            client,
+            schema,
+            table,
+            &migration.script,
+            &migration.checksum_sha256(),
+        )
+        .await?;
+    }
+    let audit = AuditMetadata::collect(&config.audit);
+    if !audit.is_empty() {
+        crate::audit::store_audit(client, schema, table, &migration.script, &audit).await?;
+    }
+    Ok(())
+}
+
+/// Undo `-- waypoint:set` before writing history rows, so that settings such
+/// as `role` apply to the migration's SQL but not to waypoint's bookkeeping.
+/// `SET LOCAL ... TO DEFAULT` ends with the transaction, leaving session
+/// settings such as the connect-time `statement_timeout` alone. A
+/// no-transaction migration's settings were set for the session and are put
+/// back by [`restore_session_settings`] instead.
+async fn reset_directive_settings(client: &Client, migration: &ResolvedMigration) -> Result<()> {
+    if migration.directives.settings.is_empty() || migration.directives.no_transaction {
+        return Ok(());
+    }
+    let reset: Vec<String> = migration
+        .directives
+        .settings
+        .iter()
+        .map(|(name, _)| format!("SET LOCAL {} TO DEFAULT", name))
+        .collect();
+    client.batch_execute(&reset.join("; ")).await?;
+    Ok(())
+}
+
+/// Session values of `settings`' names, to put back with
+/// [`restore_session_settings`] after setting them for the session.
+async fn session_values(
+    client: &Client,
+    settings: &[(String, String)],
+) -> Result<Vec<(String, String)>> {
+    let mut saved = Vec::with_capacity(settings.len());
+    for (name, _) in settings {
+        let row = client
+            .query_one("SELECT current_setting($1)", &[name])
+            .await?;
+        saved.push((name.clone(), row.get(0)));
+    }
+    Ok(saved)
+}
+
+/// Put back session settings saved by [`session_values`].
+async fn restore_session_settings(client: &Client, saved: &[(String, String)]) -> Result<()> {
+    if saved.is_empty() {
+        return Ok(());
+    }
+    let set: Vec<String> = saved
+        .iter()
+        .map(|(name, value)| set_statement("SET", name, value))
+        .collect();
+    client.batch_execute(&set.join("; ")).await?;
+    Ok(())
+}
+
+/// Record the backup reference taken for `migration` (best effort).
+async fn store_backup_ref(
+    client: &Client,
+    config: &WaypointConfig,
+    migration: &ResolvedMigration,
+    prepared: &PreparedMigration,
+    warnings: &mut Vec<Warning>,
+) {
+    let Some(ref reference) = prepared.backup_ref else {
+        return;
+    };
+    if let Err(e) = crate::backup::store_backup_ref(
+        client,
+        &config.migrations.schema,
+        &config.migrations.table,
+        &migration.script,
+        reference,
+    )
+    .await
+    {
+        log::warn!(
+            "Failed to record backup reference; migration={}, error={}",
+            migration.script,
+            e
+        );
+        warnings.push(Warning::new(
+            WarningCode::BackupRefNotRecorded,
+            &migration.script,
+            format!("backup reference {} not recorded: {}", reference, e),
+        ));
+    }
+}
+
+/// Count an applied versioned migration in the report.
+fn record_applied(
+    report: &mut MigrateReport,
+    config: &WaypointConfig,
+    migration: &ResolvedMigration,
+    exec_time: i32,
+    rows_affected: u64,
+) {
+    report.migrations_applied += 1;
+    report.total_time_ms += exec_time;
+    report.push_detail(
+        MigrateDetail {
+            version: migration.version().map(|v| v.raw.clone()),
+            description: migration.description.clone(),
+            script: migration.script.clone(),
+            execution_time_ms: exec_time,
+            rows_affected,
+            schema: None,
+        },
+        config.migrations.warn_rows_affected,
+    );
+}
+
+/// How a migration applied on its own connection in a parallel wave ended.
+enum WaveOutcome {
+    /// Applied and recorded: `(execution_time_ms, rows_affected)`.
+    Applied(i32, u64),
+    /// Rolled back on a lock conflict or serialization failure with another
+    /// member of the wave; to be retried serially.
+    Conflict(String),
+}
+
+/// Apply the members of a parallel wave concurrently, each on its own
+/// connection. Members that hit a conflict are applied serially afterwards.
+async fn migrate_wave(
+    client: &Client,
+    config: &WaypointConfig,
+    setup: &MigrateSetup<'_>,
+    wave: &[&ResolvedMigration],
+    force_override: bool,
+    report: &mut MigrateReport,
+) -> Result<()> {
+    let schema = &config.migrations.schema;
+
+    if config.preflight.enabled && report.migrations_applied > 0 {
+        crate::preflight::wait_for_replicas(&config.preflight, DialectKind::Postgres).await?;
+    }
+
+    let mut prepared = Vec::new();
+    for migration in wave {
+        if let Some(p) = prepare_versioned(
+            client,
+            config,
+            migration,
+            force_override,
+            &mut report.warnings,
+        )
+        .await?
+        {
+            prepared.push((*migration, p));
+        }
+    }
+
+    log::info!(
+        "Applying migrations in parallel; migrations={}, schema={}",
+        prepared
+            .iter()
+            .map(|(m, _)| m.script.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        schema
+    );
+
+    let conn_string = config.connection_string()?;
+    let mut tasks = tokio::task::JoinSet::new();
+    for (i, (migration, _)) in prepared.iter().enumerate() {
+        let placeholders = build_placeholders(
+            &config.placeholders,
+            schema,
+            &setup.db_user,
+            &setup.db_name,
+            &migration.script,
+        );
+        let sql = replace_placeholders(&migration.sql, &placeholders)?;
+        let config = config.clone();
+        let migration = (*migration).clone();
+        let conn_string = conn_string.clone();
+        let installed_by = setup.installed_by.clone();
+        let observer = crate::events::current();
+        tasks.spawn(crate::events::inherit(observer, async move {
+            let outcome =
+                apply_concurrently(&config, &conn_string, &migration, &sql, &installed_by).await;
+            (i, outcome)
+        }));
+    }
+
+    let mut outcomes: Vec<Option<Result<WaveOutcome>>> = prepared.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        let (i, outcome) = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        outcomes[i] = Some(outcome);
+    }
+
+    let mut failure = None;
+    let mut conflicts = Vec::new();
+    for ((migration, p), outcome) in prepared.iter().zip(outcomes) {
+        match outcome.expect("every task reports") {
+            Ok(WaveOutcome::Applied(exec_time, rows_affected)) => {
+                store_backup_ref(client, config, migration, p, &mut report.warnings).await;
+                record_applied(report, config, migration, exec_time, rows_affected);
+            }
+            Ok(WaveOutcome::Conflict(reason)) => {
+                log::warn!(
+                    "Parallel migration conflicted, retrying serially; migration={}, reason={}",
+                    migration.script,
+                    reason
+                );
+                conflicts.push((*migration, p));
+            }
+            Err(e) => {
+                store_backup_ref(client, config, migration, p, &mut report.warnings).await;
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    for (migration, p) in conflicts {
+        let (exec_time, rows_affected) =
+            apply_versioned(client, config, setup, migration, p, &mut report.warnings).await?;
+        record_applied(report, config, migration, exec_time, rows_affected);
+    }
+    Ok(())
+}
+
+/// Apply one member of a parallel wave on a fresh connection.
+///
+/// The history insert takes a `SHARE ROW EXCLUSIVE` lock on the history
+/// table first, so concurrent members commit their rows one at a time and
+/// get distinct `installed_rank`s.
+async fn apply_concurrently(
+    config: &WaypointConfig,
+    conn_string: &str,
+    migration: &ResolvedMigration,
+    sql: &str,
+    installed_by: &str,
+) -> Result<WaveOutcome> {
+    let schema = &config.migrations.schema;
+    let table = &config.migrations.table;
+    let client = db::connect_with_full_config(
+        conn_string,
+        &config.database.ssl_mode,
+        config.database.connect_retries,
+        config.database.connect_timeout_secs,
+        config.database.statement_timeout_secs,
+        config.database.keepalive_secs,
+    )
+    .await?;
+
+    log::info!(
+        "Applying migration; migration={}, schema={}",
+        migration.script,
+        schema
+    );
+    let copies = load_copies(&migration.script, sql, &config.migrations.locations)?;
+    migration_started(migration);
+
+    let start = std::time::Instant::now();
+    client
+        .batch_execute(&begin_statement(config, migration))
+        .await?;
+    let applied: Result<(i32, u64)> =
+        telemetry::traced(telemetry::migration_span(migration), async {
+            let rows_affected = match config.migrations.code_migrations.find(&migration.script) {
+                Some(code) => code.up(&client).await.map(|()| 0)?,
+                None => execute_with_copies(&client, &migration.script, sql, &copies).await?,
+            };
+            // Lock and write history as the connecting user, not a
+            // `waypoint:set role` the migration switched to.
+            reset_directive_settings(&client, migration).await?;
+            // Timed before the history lock, so waiting for other wave
+            // members doesn't count as this migration's execution time.
+            let exec_time = start.elapsed().as_millis() as i32;
+            let lock_start = std::time::Instant::now();
+            client
+                .batch_execute(&format!(
+                    "LOCK TABLE {}.{} IN SHARE ROW EXCLUSIVE MODE",
+                    db::quote_ident(schema),
+                    db::quote_ident(table)
+                ))
+                .await?;
+            log::debug!(
+                "Acquired history lock; script={}, wait_ms={}",
+                migration.script,
+                lock_start.elapsed().as_millis()
+            );
+            insert_success_row(
+                &client,
+                config,
+                migration,
+                schema,
+                table,
+                installed_by,
+                exec_time,
+            )
+            .await?;
+            client.batch_execute("COMMIT").await?;
+            Ok((exec_time, rows_affected))
+        })
+        .await;
+
+    match applied {
+        Ok((exec_time, rows_affected)) => Ok(WaveOutcome::Applied(exec_time, rows_affected)),
+        Err(e) => {
+            if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                log::error!("Failed to rollback transaction: {}", rollback_err);
+            }
+            match e {
+                WaypointError::DatabaseError(ref db_err) if is_wave_conflict(db_err) => {
+                    Ok(WaveOutcome::Conflict(crate::error::format_db_error(db_err)))
+                }
+                WaypointError::DatabaseError(db_err) => {
+                    record_failed_migration(&client, migration, schema, table, installed_by).await;
+                    let reason = crate::error::format_db_error(&db_err);
+                    log::error!(
+                        "Migration failed; script={}, reason={}",
+                        migration.script,
+                        reason
+                    );
+                    Err(WaypointError::MigrationFailed {
+                        script: migration.script.clone(),
+                        reason,
+                    })
+                }
+                e => Err(e),
+            }
+        }
+    }
+}
+
+/// Whether an error from a parallel wave member means it collided with
+/// another member: a serialization failure, deadlock or lock timeout.
+fn is_wave_conflict(e: &tokio_postgres::Error) -> bool {
+    is_serialization_failure(e) || is_lock_timeout(e)
+}
+
+/// Apply a single migration within a transaction.
+#[allow(clippy::too_many_arguments)]
+async fn apply_migration(
+    client: &Client,
+    config: &WaypointConfig,
+    migration: &ResolvedMigration,
+    schema: &str,
+    table: &str,
+    installed_by: &str,
+    db_user: &str,
+    db_name: &str,
+    hold_transaction: bool,
+) -> Result<(i32, u64)> {
+    log::info!(
+        "Applying migration; migration={}, schema={}",
+        migration.script,
+        schema
+    );
+    migration_started(migration);
+
+    if let Some(code) = config.migrations.code_migrations.find(&migration.script) {
+        return apply_code_migration(
+            client,
+            config,
+            code,
+            migration,
+            schema,
+            table,
+            installed_by,
+            hold_transaction,
+        )
+        .await;
+    }
+
+    let placeholders = build_placeholders(
+        &config.placeholders,
+        schema,
+        db_user,
+        db_name,
+        &migration.script,
+    );
+
+    let sql = replace_placeholders(&migration.sql, &placeholders)?;
+    let copies = load_copies(&migration.script, &sql, &config.migrations.locations)?;
+
+    if config.migrations.statement_progress || migration.directives.no_transaction {
+        // Without a transaction there is no SET LOCAL, so the lock timeout
+        // and `-- waypoint:set` settings are set for the session and their
+        // previous values put back before the history row is written.
+        let session_settings = if migration.directives.no_transaction {
+            transaction_settings(config, migration)
+        } else {
+            Vec::new()
+        };
+        let saved = session_values(client, &session_settings).await?;
+        if !session_settings.is_empty() {
+            let set: Vec<String> = session_settings
+                .iter()
+                .map(|(name, value)| set_statement("SET", name, value))
+                .collect();
+            client.batch_execute(&set.join("; ")).await?;
+        }
+        let result = apply_statements(
+            client,
+            config,
+            migration,
+            schema,
+            table,
+            installed_by,
+            &sql,
+            &copies,
+            hold_transaction,
+            &saved,
+        )
+        .await;
+        if let Err(e) = restore_session_settings(client, &saved).await {
+            log::warn!("Failed to restore session settings; error={}", e);
+        }
+        return result;
+    }
+
+    let begin = begin_statement(config, migration);
+    let mut attempt = 0;
+    let mut lock_attempt = 0;
+    loop {
+        // Timed per attempt, so a retried migration reports its last run.
+        let start = std::time::Instant::now();
+        client.batch_execute(&begin).await?;
+
+        let e = match execute_with_copies(client, &migration.script, &sql, &copies).await {
+            Ok(rows_affected) => {
+                let exec_time = start.elapsed().as_millis() as i32;
+                return match insert_success_row(
+                    client,
+                    config,
+                    migration,
+                    schema,
+                    table,
+                    installed_by,
+                    exec_time,
+                )
+                .await
+                {
+                    Ok(()) => {
+                        if !hold_transaction {
+                            client.batch_execute("COMMIT").await?;
+                        }
+                        Ok((exec_time, rows_affected))
+                    }
+                    Err(e) => {
+                        if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                            log::error!("Failed to rollback transaction: {}", rollback_err);
+                        }
+                        Err(e)
+                    }
+                };
+            }
+            Err(e) => e,
+        };
+
+        if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+            log::error!("Failed to rollback transaction: {}", rollback_err);
+        }
+
+        let db_error = match &e {
+            WaypointError::DatabaseError(db_error) => Some(db_error),
+            _ => None,
+        };
+        if attempt < migration.directives.retries && db_error.is_some_and(is_serialization_failure)
+        {
+            attempt += 1;
+            wait_for_serialization_retry(&migration.script, attempt, migration.directives.retries)
+                .await;
+            continue;
+        }
+        if lock_attempt < config.safety.ddl_retry && db_error.is_some_and(is_lock_timeout) {
+            lock_attempt += 1;
+            wait_for_lock_retry(config, migration, lock_attempt).await;
+            continue;
+        }
+
+        record_failed_migration(client, migration, schema, table, installed_by).await;
+
+        let reason = error_reason(&e);
+        log::error!(
+            "Migration failed; script={}, reason={}",
+            migration.script,
+            reason
+        );
+        return Err(WaypointError::MigrationFailed {
+            script: migration.script.clone(),
+            reason,
+        });
+    }
+}
+
+/// Apply a [`CodeMigration`] in a transaction of its own (or the held one),
+/// recording its history row before committing.
+#[allow(clippy::too_many_arguments)]
+async fn apply_code_migration(
+    client: &Client,
+    config: &WaypointConfig,
+    code: &dyn CodeMigration,
+    migration: &ResolvedMigration,
+    schema: &str,
+    table: &str,
+    installed_by: &str,
+    hold_transaction: bool,
+) -> Result<(i32, u64)> {
+    let mut attempt = 0;
+    let start = loop {
+        let start = std::time::Instant::now();
+        client
+            .batch_execute(&begin_statement(config, migration))
+            .await?;
+        let e = match code.up(client).await {
+            Ok(()) => break start,
+            Err(e) => e,
+        };
+        if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+            log::error!("Failed to rollback transaction: {}", rollback_err);
+        }
+        if attempt < migration.directives.retries && is_retryable(&e) {
+            attempt += 1;
+            wait_for_serialization_retry(&migration.script, attempt, migration.directives.retries)
+                .await;
+            continue;
+        }
+        record_failed_migration(client, migration, schema, table, installed_by).await;
+        let reason = match e {
+            WaypointError::DatabaseError(db_err) => crate::error::format_db_error(&db_err),
+            e => e.to_string(),
+        };
+        log::error!(
+            "Migration failed; script={}, reason={}",
+            migration.script,
+            reason
+        );
+        return Err(WaypointError::MigrationFailed {
+            script: migration.script.clone(),
+            reason,
+        });
+    };
+
+    let exec_time = start.elapsed().as_millis() as i32;
+    match insert_success_row(
+        client,
+        config,
+        migration,
+        schema,
+        table,
+        installed_by,
+        exec_time,
+    )
+    .await
+    {
+        Ok(()) => {
+            if !hold_transaction {
+                client.batch_execute("COMMIT").await?;
+            }
+            Ok((exec_time, 0))
+        }
+        Err(e) => {
+            if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
                 log::error!("Failed to rollback transaction: {}", rollback_err);
             }
+            Err(e)
+        }
+    }
+}
 
-            if let Err(record_err) = history::insert_applied_migration(
-                client,
-                schema,
+/// `BEGIN`, with `ISOLATION LEVEL` from the migration's directive or, failing
+/// that, `[migrations] isolation_level`, followed by `SET LOCAL` for each of
+/// [`transaction_settings`].
+fn begin_statement(config: &WaypointConfig, migration: &ResolvedMigration) -> String {
+    let mut begin = match migration
+        .directives
+        .isolation
+        .or(config.migrations.isolation_level)
+    {
+        Some(level) => format!("BEGIN ISOLATION LEVEL {}", level.as_sql()),
+        None => "BEGIN".to_string(),
+    };
+    if migration.directives.low_priority {
+        begin.push_str("; SET LOCAL statement_timeout = 0");
+    }
+    for (name, value) in transaction_settings(config, migration) {
+        begin.push_str("; ");
+        begin.push_str(&set_statement("SET LOCAL", &name, &value));
+    }
+    begin
+}
+
+/// Settings a migration runs with: `[safety] lock_timeout_ms` as
+/// `lock_timeout`, then the migration's `-- waypoint:set` directives, which
+/// win over it (and over `low-priority`) by coming later.
+fn transaction_settings(
+    config: &WaypointConfig,
+    migration: &ResolvedMigration,
+) -> Vec<(String, String)> {
+    let mut settings = Vec::new();
+    if config.safety.lock_timeout_ms > 0 {
+        settings.push((
+            "lock_timeout".to_string(),
+            format!("{}ms", config.safety.lock_timeout_ms),
+        ));
+    }
+    settings.extend(migration.directives.settings.iter().cloned());
+    settings
+}
+
+/// `SET name = 'value'` (or `SET LOCAL ...`). `name` was validated when the
+/// directive was parsed; the value is quoted as a literal.
+fn set_statement(set: &str, name: &str, value: &str) -> String {
+    format!("{} {} = '{}'", set, name, value.replace('\'', "''"))
+}
+
+/// Whether an error is a lock timeout (`55P03`, `lock_not_available`).
+fn is_lock_timeout(e: &tokio_postgres::Error) -> bool {
+    e.as_db_error()
+        .is_some_and(|db| matches!(db.code().code(), "55P03"))
+}
+
+/// Log and sleep before lock-timeout retry number `attempt`.
+async fn wait_for_lock_retry(config: &WaypointConfig, migration: &ResolvedMigration, attempt: u32) {
+    let delay = config.safety.ddl_retry_delay(attempt);
+    log::warn!(
+        "Lock timeout, retrying; migration={}, attempt={}, max_retries={}, delay_ms={}",
+        migration.script,
+        attempt,
+        config.safety.ddl_retry,
+        delay.as_millis()
+    );
+    tokio::time::sleep(delay).await;
+}
+
+/// Whether an error is a serialization failure (`40001`) or deadlock
+/// (`40P01`), i.e. safe to retry from the start of the transaction.
+fn is_serialization_failure(e: &tokio_postgres::Error) -> bool {
+    e.as_db_error()
+        .is_some_and(|db| matches!(db.code().code(), "40001" | "40P01"))
+}
+
+/// [`is_serialization_failure`] for a waypoint error.
+fn is_retryable(e: &WaypointError) -> bool {
+    matches!(e, WaypointError::DatabaseError(db_err) if is_serialization_failure(db_err))
+}
+
+/// Log and back off before `-- waypoint:retries` attempt number `attempt`.
+async fn wait_for_serialization_retry(script: &str, attempt: u32, max_retries: u32) {
+    log::warn!(
+        "Serialization failure, retrying migration; migration={}, attempt={}, max_retries={}",
+        script,
+        attempt,
+        max_retries
+    );
+    tokio::time::sleep(std::time::Duration::from_millis(100 << attempt.min(6))).await;
+}
+
+/// Apply a migration one statement at a time.
+///
+/// Used when `statement_progress` is enabled or the migration carries
+/// `-- waypoint:no-transaction`. Without that directive the statements still
+/// share one transaction; with it each statement commits on its own, which is
+/// what makes `--resume` possible (and what lets several `CONCURRENTLY`
+/// statements live in one file).
+#[allow(clippy::too_many_arguments)]
+async fn apply_statements(
+    client: &Client,
+    config: &WaypointConfig,
+    migration: &ResolvedMigration,
+    schema: &str,
+    table: &str,
+    installed_by: &str,
+    sql: &str,
+    copies: &[CopyLoad],
+    hold_transaction: bool,
+    saved_settings: &[(String, String)],
+) -> Result<(i32, u64)> {
+    let transactional = !migration.directives.no_transaction;
+    // History rows are written without the migration's session settings.
+    let restore = || async {
+        if let Err(e) = restore_session_settings(client, saved_settings).await {
+            log::warn!("Failed to restore session settings; error={}", e);
+        }
+    };
+    let track = config.migrations.statement_progress;
+    let statements = crate::sql_parser::split_statements(sql);
+    let total = statements.len();
+
+    let mut skip = 0;
+    if track {
+        if config.migrations.resume && !transactional {
+            let records =
+                crate::progress::get_statement_records(client, schema, table, &migration.script)
+                    .await?;
+            skip = crate::progress::resume_point(&migration.script, migration.checksum, &records)?;
+            if skip > 0 {
+                log::info!(
+                    "Resuming migration; migration={}, completed_statements={}, total_statements={}",
+                    migration.script,
+                    skip,
+                    total
+                );
+            }
+        }
+        crate::progress::clear_statements(
+            client,
+            schema,
+            table,
+            &migration.script,
+            skip as i32 + 1,
+        )
+        .await?;
+    }
+
+    let mut start;
+    let mut lock_attempt = 0;
+    let mut attempt = 0;
+    let mut total_rows;
+    // A lock timeout or (with `-- waypoint:retries`) serialization failure
+    // restarts the whole transaction; without one, only the failed statement
+    // is retried on a lock timeout, unless it is a CONCURRENTLY statement,
+    // whose failure can leave an invalid index behind.
+    'attempt: loop {
+        start = std::time::Instant::now();
+        if transactional {
+            client
+                .batch_execute(&begin_statement(config, migration))
+                .await?;
+        }
+
+        total_rows = 0u64;
+        for (i, stmt) in statements.iter().enumerate().skip(skip) {
+            let index = i as i32 + 1;
+            let stmt_start = std::time::Instant::now();
+            let span = telemetry::statement_span(&migration.script, index, total);
+            let result = telemetry::traced(span.clone(), async {
+                loop {
+                    match execute_counting(client, stmt).await {
+                        Err(e)
+                            if !transactional
+                                && lock_attempt < config.safety.ddl_retry
+                                && is_lock_timeout(&e)
+                                && !stmt.to_uppercase().contains("CONCURRENTLY") =>
+                        {
+                            lock_attempt += 1;
+                            wait_for_lock_retry(config, migration, lock_attempt).await;
+                        }
+                        result => break result,
+                    }
+                }
+            })
+            .await;
+            match result {
+                Ok(rows) => {
+                    span.record("rows", rows);
+                    total_rows += rows;
+                    crate::events::emit(|| crate::events::MigrateEventKind::StatementExecuted {
+                        script: migration.script.clone(),
+                        statement: i + 1,
+                        total,
+                        rows_affected: rows,
+                        duration_ms: stmt_start.elapsed().as_millis() as i32,
+                    });
+                    if track {
+                        crate::progress::record_statement(
+                            client,
+                            schema,
+                            table,
+                            &migration.script,
+                            migration.checksum,
+                            index,
+                            stmt_start.elapsed().as_millis() as i32,
+                            Some(rows as i64),
+                            true,
+                        )
+                        .await?;
+                    }
+                }
+                Err(e) => {
+                    if transactional {
+                        if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                            log::error!("Failed to rollback transaction: {}", rollback_err);
+                        }
+                        if attempt < migration.directives.retries && is_serialization_failure(&e) {
+                            attempt += 1;
+                            wait_for_serialization_retry(
+                                &migration.script,
+                                attempt,
+                                migration.directives.retries,
+                            )
+                            .await;
+                            continue 'attempt;
+                        }
+                        if lock_attempt < config.safety.ddl_retry && is_lock_timeout(&e) {
+                            lock_attempt += 1;
+                            wait_for_lock_retry(config, migration, lock_attempt).await;
+                            continue 'attempt;
+                        }
+                    }
+                    if track {
+                        if let Err(record_err) = crate::progress::record_statement(
+                            client,
+                            schema,
+                            table,
+                            &migration.script,
+                            migration.checksum,
+                            index,
+                            stmt_start.elapsed().as_millis() as i32,
+                            None,
+                            false,
+                        )
+                        .await
+                        {
+                            log::warn!(
+                            "Failed to record statement failure; script={}, statement={}, error={}",
+                            migration.script,
+                            index,
+                            record_err
+                        );
+                        }
+                    }
+                    restore().await;
+                    record_failed_migration(client, migration, schema, table, installed_by).await;
+
+                    let reason = format!(
+                        "statement {} of {}: {}",
+                        index,
+                        total,
+                        crate::error::format_db_error(&e)
+                    );
+                    log::error!(
+                        "Migration failed; script={}, reason={}",
+                        migration.script,
+                        reason
+                    );
+                    return Err(WaypointError::MigrationFailed {
+                        script: migration.script.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+        for copy in copies {
+            let copied = match copy.open(&migration.script) {
+                Ok(file) => db::copy_in(client, &copy.statement, file).await,
+                Err(e) => Err(e),
+            };
+            match copied {
+                Ok(rows) => total_rows += rows,
+                Err(e) => {
+                    if transactional {
+                        if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                            log::error!("Failed to rollback transaction: {}", rollback_err);
+                        }
+                    }
+                    restore().await;
+                    record_failed_migration(client, migration, schema, table, installed_by).await;
+
+                    let reason = format!("copy into {}: {}", copy.table, error_reason(&e));
+                    log::error!(
+                        "Migration failed; script={}, reason={}",
+                        migration.script,
+                        reason
+                    );
+                    return Err(WaypointError::MigrationFailed {
+                        script: migration.script.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+        break;
+    }
+
+    let exec_time = start.elapsed().as_millis() as i32;
+    restore().await;
+    let insert = insert_success_row(
+        client,
+        config,
+        migration,
+        schema,
+        table,
+        installed_by,
+        exec_time,
+    )
+    .await;
+    match insert {
+        Ok(()) => {
+            if transactional && !hold_transaction {
+                client.batch_execute("COMMIT").await?;
+            }
+            Ok((exec_time, total_rows))
+        }
+        Err(e) => {
+            if transactional {
+                if let Err(rollback_err) = client.batch_execute("ROLLBACK").await {
+                    log::error!("Failed to rollback transaction: {}", rollback_err);
+                }
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Run a migration's SQL, then its `-- waypoint:copy` loads, and sum the
+/// row counts of both.
+async fn execute_with_copies(
+    client: &Client,
+    script: &str,
+    sql: &str,
+    copies: &[CopyLoad],
+) -> Result<u64> {
+    let mut rows = execute_counting(client, sql).await?;
+    for copy in copies {
+        rows += db::copy_in(client, &copy.statement, copy.open(script)?).await?;
+    }
+    Ok(rows)
+}
+
+/// Failure reason for a migration error, with PostgreSQL's detail and hint.
+fn error_reason(e: &WaypointError) -> String {
+    match e {
+        WaypointError::DatabaseError(db_error) => crate::error::format_db_error(db_error),
+        other => other.to_string(),
+    }
+}
+
+/// Execute SQL over the simple query protocol and sum the row counts from
+/// the command tags of its data-changing statements: INSERT, UPDATE, DELETE,
+/// MERGE and COPY FROM, including behind a `WITH`. Counts from SELECT, FETCH,
+/// MOVE, COPY TO and the like are ignored. Tags are matched to statements in
+/// order; rows a statement returns are dropped as they stream in rather than
+/// buffered.
+async fn execute_counting(
+    client: &Client,
+    sql: &str,
+) -> std::result::Result<u64, tokio_postgres::Error> {
+    use futures_util::{pin_mut, TryStreamExt};
+
+    // Comment-only fragments produce no command tag.
+    let counted: Vec<bool> = crate::sql_parser::split_statements(sql)
+        .into_iter()
+        .filter_map(top_level_words)
+        .map(|words| changes_rows(&words))
+        .collect();
+    let stream = client.simple_query_raw(sql).await?;
+    pin_mut!(stream);
+    let mut rows = 0;
+    let mut index = 0;
+    while let Some(message) = stream.try_next().await? {
+        if let tokio_postgres::SimpleQueryMessage::CommandComplete(n) = message {
+            if counted.get(index).copied().unwrap_or(false) {
+                rows += n;
+            }
+            index += 1;
+        }
+    }
+    Ok(rows)
+}
+
+/// Whether a statement with these top-level words reports changed rows in
+/// its command tag.
+fn changes_rows(words: &[String]) -> bool {
+    const DML: [&str; 4] = ["INSERT", "UPDATE", "DELETE", "MERGE"];
+    match words.first().map(String::as_str) {
+        Some(w) if DML.contains(&w) => true,
+        // The main statement follows the CTE list.
+        Some("WITH") => words[1..]
+            .iter()
+            .find(|w| {
+                DML.contains(&w.as_str()) || matches!(w.as_str(), "SELECT" | "VALUES" | "TABLE")
+            })
+            .is_some_and(|w| DML.contains(&w.as_str())),
+        Some("COPY") => words[1..]
+            .iter()
+            .find(|w| matches!(w.as_str(), "FROM" | "TO"))
+            .is_some_and(|w| w == "FROM"),
+        _ => false,
+    }
+}
+
+/// Upper-cased words of `stmt` outside parentheses, string literals, quoted
+/// identifiers and comments, or `None` when it holds nothing but comments.
+fn top_level_words(stmt: &str) -> Option<Vec<String>> {
+    let bytes = stmt.as_bytes();
+    let mut words = Vec::new();
+    let mut has_code = false;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'-' && bytes.get(i + 1) == Some(&b'-') {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if b == b'/' && bytes.get(i + 1) == Some(&b'*') {
+            i = stmt[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + 2 + end + 2);
+            continue;
+        }
+        if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        has_code = true;
+        match b {
+            b'\'' | b'"' => {
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == b && bytes.get(i + 1) == Some(&b) {
+                        i += 2;
+                    } else if bytes[i] == b {
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+                i += 1;
+            }
+            b'(' => {
+                depth += 1;
+                i += 1;
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            _ if b.is_ascii_alphabetic() || b == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                if depth == 0 {
+                    words.push(stmt[start..i].to_ascii_uppercase());
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    has_code.then_some(words)
+}
+
+/// Record a failed attempt in the history table (best effort).
+async fn record_failed_migration(
+    client: &Client,
+    migration: &ResolvedMigration,
+    schema: &str,
+    table: &str,
+    installed_by: &str,
+) {
+    // Called after ROLLBACK, or with the session settings already restored.
+    if let Err(record_err) = history::insert_applied_migration(
+        client,
+        schema,
+        table,
+        migration.version().map(|v| v.raw.as_str()),
+        &migration.description,
+        &migration.migration_type().to_string(),
+        &migration.script,
+        Some(migration.checksum),
+        installed_by,
+        0,
+        false,
+    )
+    .await
+    {
+        log::warn!(
+            "Failed to record migration failure in history table; script={}, error={}",
+            migration.script,
+            record_err
+        );
+    }
+}
+
+/// Tables that need fresh statistics after the given scripts ran, each with
+/// the rows its migration affected: those rewritten, and those backfilled or
+/// bulk-deleted by a migration that affected at least `min_rows` rows.
+/// Deduplicated, in first-seen order.
+fn maintenance_targets(
+    scripts: &[(&str, u64)],
+    default_schema: &str,
+    min_rows: u64,
+) -> Vec<(String, String)> {
+    let mut targets: Vec<(String, String)> = Vec::new();
+    for &(sql, rows_affected) in scripts {
+        for stmt in crate::sql_parser::split_statements(sql) {
+            let Some((stmt_schema, table, cost)) = crate::wal::classify_statement(stmt, None)
+            else {
+                continue;
+            };
+            let bulk = match cost {
+                crate::wal::WalCost::Rewrite => true,
+                crate::wal::WalCost::IndexBuild => false,
+                crate::wal::WalCost::Backfill | crate::wal::WalCost::Delete => {
+                    rows_affected >= min_rows
+                }
+            };
+            if !bulk {
+                continue;
+            }
+            let key = (
+                stmt_schema.unwrap_or_else(|| default_schema.to_string()),
                 table,
-                version_str,
-                &migration.description,
-                &type_str,
-                &migration.script,
-                Some(migration.checksum),
-                installed_by,
-                0,
-                false,
+            );
+            if !targets.contains(&key) {
+                targets.push(key);
+            }
+        }
+    }
+    targets
+}
+
+/// Run `ANALYZE` (or `VACUUM (ANALYZE)`) on tables the applied migrations
+/// rewrote or bulk-modified, skipping tables below `post_migrate_min_table_mb`.
+///
+/// Best effort: a failure is logged, recorded in `warnings`, and the table
+/// left out of the report, since the migrations themselves have already been
+/// committed.
+async fn post_migrate_maintenance(
+    client: &Client,
+    config: &WaypointConfig,
+    setup: &MigrateSetup<'_>,
+    details: &[MigrateDetail],
+    warnings: &mut Vec<Warning>,
+) -> Vec<TableMaintenance> {
+    let applied: Vec<(&ResolvedMigration, u64)> = details
+        .iter()
+        .filter_map(|d| {
+            let m = setup.resolved.iter().find(|m| m.script == d.script)?;
+            Some((m, d.rows_affected))
+        })
+        .collect();
+    // Classify the SQL as it ran, placeholders replaced.
+    let sql = match crate::wal::substituted_scripts(
+        config,
+        &setup.db_user,
+        &setup.db_name,
+        applied.iter().map(|(m, _)| *m),
+    ) {
+        Ok(sql) => sql,
+        Err(e) => {
+            log::warn!("Post-migrate maintenance skipped; error={}", e);
+            warnings.push(Warning::new(
+                WarningCode::MaintenanceFailed,
+                "post-migrate maintenance",
+                format!("maintenance skipped: {}", e),
+            ));
+            return Vec::new();
+        }
+    };
+    let scripts: Vec<(&str, u64)> = sql
+        .iter()
+        .zip(&applied)
+        .map(|(sql, (_, rows))| (sql.as_str(), *rows))
+        .collect();
+    let min_bytes = config.migrations.post_migrate_min_table_mb * 1024 * 1024;
+    let vacuum = config.migrations.post_migrate_vacuum;
+
+    let mut done = Vec::new();
+    for (schema, table) in maintenance_targets(
+        &scripts,
+        &config.migrations.schema,
+        config.migrations.post_migrate_min_rows,
+    ) {
+        let name = format!("{}.{}", schema, table);
+        let qualified = format!("{}.{}", db::quote_ident(&schema), db::quote_ident(&table));
+        let size: i64 = match client
+            .query_opt(
+                "SELECT pg_table_size(c.oid) FROM pg_class c
+                 JOIN pg_namespace n ON n.oid = c.relnamespace
+                 WHERE n.nspname = $1 AND c.relname = $2",
+                &[&schema, &table],
             )
             .await
-            {
+        {
+            Ok(Some(row)) => row.get(0),
+            // Dropped later in the same run.
+            Ok(None) => continue,
+            Err(e) => {
                 log::warn!(
-                    "Failed to record migration failure in history table; script={}, error={}",
-                    migration.script,
-                    record_err
+                    "Post-migrate maintenance skipped; table={}, error={}",
+                    qualified,
+                    e
                 );
+                warnings.push(Warning::new(
+                    WarningCode::MaintenanceFailed,
+                    &name,
+                    format!("maintenance skipped: {}", e),
+                ));
+                continue;
             }
+        };
+        if size < min_bytes {
+            done.push(TableMaintenance {
+                table: name,
+                action: MaintenanceAction::SkippedSmall,
+                table_size_bytes: size,
+                execution_time_ms: 0,
+            });
+            continue;
+        }
 
-            let reason = crate::error::format_db_error(&e);
-            log::error!(
-                "Migration failed; script={}, reason={}",
-                migration.script,
-                reason
+        let (action, sql) = if vacuum {
+            (
+                MaintenanceAction::VacuumAnalyze,
+                format!("VACUUM (ANALYZE) {}", qualified),
+            )
+        } else {
+            (MaintenanceAction::Analyze, format!("ANALYZE {}", qualified))
+        };
+        let start = std::time::Instant::now();
+        if let Err(e) = client.batch_execute(&sql).await {
+            log::warn!(
+                "Post-migrate maintenance failed; table={}, error={}",
+                qualified,
+                e
             );
-            Err(WaypointError::MigrationFailed {
-                script: migration.script.clone(),
-                reason,
-            })
+            warnings.push(Warning::new(
+                WarningCode::MaintenanceFailed,
+                &name,
+                format!("{} failed: {}", sql, e),
+            ));
+            continue;
         }
+        let ms = start.elapsed().as_millis() as i32;
+        log::info!(
+            "Post-migrate maintenance complete; table={}, action={:?}, time_ms={}",
+            name,
+            action,
+            ms
+        );
+        done.push(TableMaintenance {
+            table: name,
+            action,
+            table_size_bytes: size,
+            execution_time_ms: ms,
+        });
     }
+    done
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_changes_rows() {
+        let changes = |sql: &str| changes_rows(&top_level_words(sql).unwrap());
+        assert!(changes("INSERT INTO t VALUES (1)"));
+        assert!(changes("-- backfill\nupdate t SET x = 1"));
+        assert!(changes("DELETE FROM t RETURNING id"));
+        assert!(changes(
+            "MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN DELETE"
+        ));
+        assert!(changes(
+            "WITH moved AS (SELECT id FROM a) INSERT INTO b SELECT id FROM moved"
+        ));
+        assert!(changes("COPY t FROM '/tmp/t.csv'"));
+        assert!(!changes("SELECT count(*) FROM t"));
+        // The tag is SELECT's, counting returned rows, not deleted ones.
+        assert!(!changes(
+            "WITH x AS (DELETE FROM t RETURNING 1) SELECT * FROM x"
+        ));
+        assert!(!changes("FETCH 10 FROM cur"));
+        assert!(!changes("MOVE 10 IN cur"));
+        assert!(!changes("COPY (SELECT * FROM t) TO STDOUT"));
+        assert!(!changes("CREATE TABLE \"insert\" (id INT)"));
+        assert!(!changes("SELECT 'UPDATE t'"));
+        assert_eq!(top_level_words("-- only a comment"), None);
+        assert_eq!(top_level_words("/* only a comment */"), None);
+    }
+
+    #[test]
+    fn test_maintenance_targets() {
+        let scripts = [
+            ("CREATE INDEX idx_a ON a (x); UPDATE a SET x = 1;", 5000),
+            (
+                "ALTER TABLE app.b ALTER COLUMN y TYPE bigint; DELETE FROM a WHERE x = 2;",
+                0,
+            ),
+        ];
+        assert_eq!(
+            maintenance_targets(&scripts, "public", 1000),
+            vec![
+                ("public".to_string(), "a".to_string()),
+                ("app".to_string(), "b".to_string()),
+            ]
+        );
+
+        // Rewrites always count; DML only above the row threshold.
+        assert_eq!(
+            maintenance_targets(&scripts, "public", 10_000),
+            vec![("app".to_string(), "b".to_string())]
+        );
+        assert!(maintenance_targets(&[("UPDATE a SET x = 1", 0)], "public", 1).is_empty());
+    }
+
     #[test]
     fn test_detect_concurrent_index() {
         let sql = "CREATE INDEX CONCURRENTLY idx_users_email ON users (email);";
@@ -1,13 +1,19 @@
 //! PostgreSQL safety analysis: lock-level mapping, table size lookup,
 //! verdict generation. Shared types and dispatcher live in [`crate::safety`].
 
+use std::sync::LazyLock;
+
+use regex_lite::Regex;
 use tokio_postgres::Client;
 
+use crate::dialect::{DialectKind, PgVersion};
 use crate::error::{Result, WaypointError};
 use crate::safety::{
-    affected_table, classify_row_count, compute_verdict, is_data_loss, LockLevel, SafetyConfig,
-    SafetyReport, SafetyVerdict, StatementAnalysis, TableSize,
+    affected_table, classify_row_count, compute_verdict, is_data_loss, LockLevel,
+    PrivilegeRequirement, PrivilegedStatement, SafetyConfig, SafetyReport, SafetyVerdict,
+    StatementAnalysis, TableSize, ESTIMATED_ROWS_PER_SEC,
 };
+use crate::schema::{DistributedTableDef, DistributionKind};
 use crate::sql_parser::DdlOperation;
 
 /// Determine the PostgreSQL lock level required by a DDL operation.
@@ -67,11 +73,26 @@ pub async fn classify_table_size(
     Ok((size, estimated_rows))
 }
 
-/// Generate actionable suggestions for a DDL operation based on table size.
-fn generate_suggestions(op: &DdlOperation, size: TableSize) -> Vec<String> {
+/// Generate actionable suggestions for a DDL operation based on table size
+/// and, when known, the server version.
+fn generate_suggestions(
+    op: &DdlOperation,
+    size: TableSize,
+    version: Option<PgVersion>,
+) -> Vec<String> {
     let mut suggestions = Vec::new();
 
     match op {
+        DdlOperation::AlterTableAddColumn {
+            has_default: true, ..
+        } if (size == TableSize::Large || size == TableSize::Huge)
+            && version.is_some_and(|v| !v.has_fast_column_default()) =>
+        {
+            suggestions.push(format!(
+                "PostgreSQL {} rewrites the table for ADD COLUMN ... DEFAULT: add the column without a default, then SET DEFAULT and backfill",
+                version.unwrap()
+            ));
+        }
         DdlOperation::CreateIndex {
             is_concurrent: false,
             ..
@@ -102,6 +123,251 @@ fn generate_suggestions(op: &DdlOperation, size: TableSize) -> Vec<String> {
     suggestions
 }
 
+/// Warnings for DDL that TimescaleDB or Citus rejects or restricts on the
+/// table the operation targets. Empty when the table is a plain table.
+fn distributed_table_warnings(
+    op: &DdlOperation,
+    distributed: &[DistributedTableDef],
+) -> Vec<String> {
+    let Some(table) = affected_table(op) else {
+        return Vec::new();
+    };
+    let bare = table.rsplit('.').next().unwrap_or(&table).trim_matches('"');
+    let Some(def) = distributed.iter().find(|d| d.name == bare) else {
+        return Vec::new();
+    };
+    let column = def.column.as_deref().unwrap_or("");
+    let mut warnings = Vec::new();
+
+    match (def.kind, op) {
+        (
+            DistributionKind::Hypertable,
+            DdlOperation::CreateIndex {
+                is_concurrent,
+                is_unique,
+                ..
+            },
+        ) => {
+            if *is_concurrent {
+                warnings.push(format!(
+                    "CREATE INDEX CONCURRENTLY is not supported on hypertable '{}'; use CREATE INDEX ... WITH (timescaledb.transaction_per_chunk)",
+                    bare
+                ));
+            }
+            if *is_unique {
+                warnings.push(format!(
+                    "Unique indexes on hypertable '{}' must include the time column '{}'",
+                    bare, column
+                ));
+            }
+        }
+        (
+            DistributionKind::Hypertable,
+            DdlOperation::AddConstraint {
+                constraint_type, ..
+            },
+        ) if constraint_type == "UNIQUE" || constraint_type == "PRIMARY KEY" => {
+            warnings.push(format!(
+                "{} constraints on hypertable '{}' must include the time column '{}'",
+                constraint_type, bare, column
+            ));
+        }
+        (DistributionKind::Hypertable, DdlOperation::AlterTableAlterColumn { column: c, .. })
+        | (DistributionKind::Hypertable, DdlOperation::AlterTableDropColumn { column: c, .. })
+            if c.trim_matches('"') == column =>
+        {
+            warnings.push(format!(
+                "Altering or dropping time column '{}' of hypertable '{}' is not supported",
+                column, bare
+            ));
+        }
+        (DistributionKind::Distributed, DdlOperation::AlterTableAlterColumn { column: c, .. })
+        | (DistributionKind::Distributed, DdlOperation::AlterTableDropColumn { column: c, .. })
+            if c.trim_matches('"') == column =>
+        {
+            warnings.push(format!(
+                "Altering or dropping distribution column '{}' of Citus table '{}' is not supported",
+                column, bare
+            ));
+        }
+        (
+            DistributionKind::Distributed,
+            DdlOperation::CreateIndex {
+                is_unique: true, ..
+            },
+        ) => {
+            warnings.push(format!(
+                "Unique indexes on Citus table '{}' must include the distribution column '{}'",
+                bare, column
+            ));
+        }
+        (
+            DistributionKind::Distributed,
+            DdlOperation::AddConstraint {
+                constraint_type, ..
+            },
+        ) => {
+            if constraint_type == "UNIQUE" || constraint_type == "PRIMARY KEY" {
+                warnings.push(format!(
+                    "{} constraints on Citus table '{}' must include the distribution column '{}'",
+                    constraint_type, bare, column
+                ));
+            } else if constraint_type == "FOREIGN KEY" {
+                warnings.push(format!(
+                    "Foreign keys from Citus table '{}' must reference a reference table or a co-located table on '{}'",
+                    bare, column
+                ));
+            }
+        }
+        (
+            DistributionKind::Reference,
+            DdlOperation::AddConstraint {
+                constraint_type, ..
+            },
+        ) if constraint_type == "FOREIGN KEY" => {
+            warnings.push(format!(
+                "Foreign keys from reference table '{}' may only reference other reference tables",
+                bare
+            ));
+        }
+        _ => {}
+    }
+
+    warnings
+}
+
+static RENAME_ENUM_VALUE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)^ALTER\s+TYPE\s+((?:"?\w+"?\.)?"?\w+"?)\s+RENAME\s+VALUE\b"#).unwrap()
+});
+
+static PG_ENUM_WRITE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)^(?:DELETE\s+FROM|UPDATE)\s+(?:pg_catalog\.)?pg_enum\b").unwrap()
+});
+
+/// Flag in-place enum label changes: `ALTER TYPE ... RENAME VALUE` breaks
+/// running code that still writes the old label, and editing `pg_enum`
+/// directly can leave rows holding a label that no longer exists.
+fn enum_change_analysis(stmt: &str) -> Option<StatementAnalysis> {
+    let stmt = crate::wal::strip_leading_comments(stmt).trim();
+    let (type_name, data_loss) = if let Some(caps) = RENAME_ENUM_VALUE_RE.captures(stmt) {
+        (caps[1].replace('"', ""), false)
+    } else if PG_ENUM_WRITE_RE.is_match(stmt) {
+        ("<type>".to_string(), true)
+    } else {
+        return None;
+    };
+    Some(StatementAnalysis {
+        statement_preview: stmt.chars().take(120).collect(),
+        lock_level: LockLevel::None,
+        affected_table: None,
+        table_size: None,
+        estimated_rows: None,
+        verdict: SafetyVerdict::Danger,
+        suggestions: vec![format!(
+            "Changing enum labels in place breaks code that still uses the old label; generate a safe new-type/backfill/swap sequence with `waypoint assist enum --type {} --rename old=new` (or --remove label)",
+            type_name
+        )],
+        data_loss,
+    })
+}
+
+/// Row estimate already looked up for `table` by an earlier statement.
+fn estimated_rows_for(statements: &[StatementAnalysis], table: &str) -> Option<i64> {
+    let bare = |t: &str| {
+        t.rsplit('.')
+            .next()
+            .unwrap_or(t)
+            .trim_matches('"')
+            .to_lowercase()
+    };
+    let table = bare(table);
+    statements
+        .iter()
+        .filter(|s| s.affected_table.as_deref().map(bare).as_deref() == Some(table.as_str()))
+        .find_map(|s| s.estimated_rows)
+}
+
+/// Whether the connected role is a member of a predefined role such as
+/// `pg_read_server_files`. False when the role doesn't exist (PG < 11).
+async fn has_role(client: &Client, role: &str) -> bool {
+    client
+        .query_one("SELECT pg_has_role(current_user, $1, 'MEMBER')", &[&role])
+        .await
+        .map(|row| row.get::<_, bool>(0))
+        .unwrap_or(false)
+}
+
+/// Whether `CREATE EXTENSION name` can run without superuser: the extension
+/// is already installed (so `IF NOT EXISTS` is a no-op) or marked trusted.
+/// Trusted extensions only exist on PG 13+; older servers have no `trusted`
+/// column, so only installed extensions are allowed there.
+async fn extension_allowed(client: &Client, version: Option<PgVersion>, name: &str) -> bool {
+    let installed = client
+        .query_one(
+            "SELECT EXISTS(SELECT 1 FROM pg_extension WHERE extname = $1)",
+            &[&name],
+        )
+        .await
+        .map(|row| row.get::<_, bool>(0))
+        .unwrap_or(false);
+    if installed || !version.is_some_and(|v| v.has_trusted_extensions()) {
+        return installed;
+    }
+    client
+        .query_one(
+            "SELECT EXISTS(SELECT 1 FROM pg_available_extension_versions \
+             WHERE name = $1 AND trusted)",
+            &[&name],
+        )
+        .await
+        .map(|row| row.get::<_, bool>(0))
+        .unwrap_or(false)
+}
+
+/// Return the statements the connected role lacks the privileges to run.
+pub async fn unmet_privileges(
+    client: &Client,
+    statements: &[PrivilegedStatement],
+) -> Result<Vec<PrivilegedStatement>> {
+    if statements.is_empty() {
+        return Ok(Vec::new());
+    }
+    let row = client
+        .query_one(
+            "SELECT rolsuper, rolcreaterole FROM pg_roles WHERE rolname = current_user",
+            &[],
+        )
+        .await?;
+    let (superuser, createrole): (bool, bool) = (row.get(0), row.get(1));
+    if superuser {
+        return Ok(Vec::new());
+    }
+    let version = crate::db::get_server_version(client).await.ok();
+
+    let mut unmet = Vec::new();
+    for stmt in statements {
+        let allowed = match stmt.requirement {
+            PrivilegeRequirement::Superuser | PrivilegeRequirement::Admin => false,
+            PrivilegeRequirement::CreateRole => createrole,
+            PrivilegeRequirement::Extension => match stmt.extension {
+                Some(ref name) => extension_allowed(client, version, name).await,
+                None => false,
+            },
+            PrivilegeRequirement::ReadServerFiles => has_role(client, "pg_read_server_files").await,
+            PrivilegeRequirement::WriteServerFiles => {
+                has_role(client, "pg_write_server_files").await
+            }
+            PrivilegeRequirement::ServerProgram => {
+                has_role(client, "pg_execute_server_program").await
+            }
+        };
+        if !allowed {
+            unmet.push(stmt.clone());
+        }
+    }
+    Ok(unmet)
+}
+
 /// Analyze a migration script for safety concerns (PostgreSQL).
 ///
 /// Parses the SQL into individual DDL operations, queries the database
@@ -114,17 +380,42 @@ pub async fn analyze_migration(
     script: &str,
     config: &SafetyConfig,
 ) -> Result<SafetyReport> {
+    if crate::migration::is_code_script(script) {
+        return Ok(SafetyReport::code_migration(script));
+    }
     let ops = crate::sql_parser::extract_ddl_operations(sql);
+    let version = crate::db::get_server_version(client).await.ok();
+    // CockroachDB runs schema changes online without blocking table locks and
+    // has no pg_stat_user_tables row estimates, so only data loss is judged.
+    let crdb = crate::db::is_cockroach(client).await;
+    let distributed = if crdb {
+        Vec::new()
+    } else {
+        crate::schema::introspect_distributed_tables(client, schema)
+            .await
+            .unwrap_or_default()
+    };
     let mut statements = Vec::new();
     let mut all_suggestions = Vec::new();
     let mut worst_verdict = SafetyVerdict::Safe;
 
     for op in &ops {
-        let lock = lock_level_for_ddl(op);
+        // Reported by `enum_change_analysis` below, with the full statement.
+        if matches!(op, DdlOperation::Other { statement_preview } if enum_change_analysis(statement_preview).is_some())
+        {
+            continue;
+        }
+        let lock = if crdb {
+            LockLevel::None
+        } else {
+            lock_level_for_ddl(op)
+        };
         let table = affected_table(op);
         let data_loss = is_data_loss(op);
 
-        let (table_size, estimated_rows) = if let Some(ref t) = table {
+        let (table_size, estimated_rows) = if crdb {
+            (None, None)
+        } else if let Some(ref t) = table {
             match classify_table_size(
                 client,
                 schema,
@@ -142,9 +433,14 @@ pub async fn analyze_migration(
         };
 
         let size_for_verdict = table_size.unwrap_or(TableSize::Small);
-        let verdict = compute_verdict(lock, size_for_verdict, data_loss);
+        let mut verdict = compute_verdict(lock, size_for_verdict, data_loss);
 
-        let suggestions = generate_suggestions(op, size_for_verdict);
+        let mut suggestions = generate_suggestions(op, size_for_verdict, version);
+        let warnings = distributed_table_warnings(op, &distributed);
+        if !warnings.is_empty() && verdict == SafetyVerdict::Safe {
+            verdict = SafetyVerdict::Caution;
+        }
+        suggestions.extend(warnings);
         all_suggestions.extend(suggestions.clone());
 
         if verdict == SafetyVerdict::Danger
@@ -167,6 +463,47 @@ pub async fn analyze_migration(
         });
     }
 
+    let mut default_changes = Vec::new();
+    for stmt in crate::sql_parser::split_statements(sql) {
+        if let Some(analysis) = enum_change_analysis(stmt) {
+            all_suggestions.extend(analysis.suggestions.clone());
+            worst_verdict = SafetyVerdict::Danger;
+            statements.push(analysis);
+        }
+        if crdb {
+            continue;
+        }
+        if let Some(mut change) = crate::safety::analyze_default_change(stmt, version) {
+            if change.rewrites_table {
+                change.estimated_rewrite_ms = estimated_rows_for(&statements, &change.table)
+                    .map(|rows| rows * 1000 / ESTIMATED_ROWS_PER_SEC);
+                all_suggestions.extend(change.alternative.clone());
+            }
+            default_changes.push(change);
+        }
+        if let Some(unique) = crate::commands::assist::parse_unique_constraint(stmt) {
+            if estimated_rows_for(&statements, &unique.table)
+                .is_some_and(|rows| rows >= config.large_table_threshold)
+            {
+                all_suggestions.push(format!(
+                    "Adding UNIQUE constraint {} builds its index under an ACCESS EXCLUSIVE lock; build it with CREATE UNIQUE INDEX CONCURRENTLY and attach it with ADD CONSTRAINT ... USING INDEX (`waypoint assist unique` generates these steps)",
+                    unique.name
+                ));
+            }
+        }
+        if let Some(fk) = crate::commands::assist::parse_foreign_key(stmt) {
+            if !fk.not_valid
+                && estimated_rows_for(&statements, &fk.table)
+                    .is_some_and(|rows| rows >= config.large_table_threshold)
+            {
+                all_suggestions.push(format!(
+                    "Adding FOREIGN KEY {} checks every existing row while blocking writes; add it NOT VALID and VALIDATE CONSTRAINT in a later migration (`waypoint assist fk` generates these steps)",
+                    fk.name
+                ));
+            }
+        }
+    }
+
     all_suggestions.sort();
     all_suggestions.dedup();
 
@@ -175,6 +512,10 @@ pub async fn analyze_migration(
         overall_verdict: worst_verdict,
         statements,
         suggestions: all_suggestions,
+        server_version: version.map(|v| v.to_string()),
+        privileged: crate::safety::privileged_statements(sql, DialectKind::Postgres),
+        default_changes,
+        unanalyzable: false,
     })
 }
 
@@ -346,7 +687,7 @@ mod tests {
             is_concurrent: false,
             is_unique: false,
         };
-        let suggestions = generate_suggestions(&op, TableSize::Large);
+        let suggestions = generate_suggestions(&op, TableSize::Large, None);
         assert_eq!(suggestions.len(), 1);
         assert!(suggestions[0].contains("CONCURRENTLY"));
     }
@@ -359,7 +700,7 @@ mod tests {
             is_concurrent: false,
             is_unique: false,
         };
-        let suggestions = generate_suggestions(&op, TableSize::Huge);
+        let suggestions = generate_suggestions(&op, TableSize::Huge, None);
         assert_eq!(suggestions.len(), 1);
         assert!(suggestions[0].contains("CONCURRENTLY"));
     }
@@ -372,7 +713,7 @@ mod tests {
             is_concurrent: false,
             is_unique: false,
         };
-        let suggestions = generate_suggestions(&op, TableSize::Small);
+        let suggestions = generate_suggestions(&op, TableSize::Small, None);
         assert!(suggestions.is_empty());
     }
 
@@ -384,7 +725,7 @@ mod tests {
             is_concurrent: true,
             is_unique: false,
         };
-        let suggestions = generate_suggestions(&op, TableSize::Large);
+        let suggestions = generate_suggestions(&op, TableSize::Large, None);
         assert!(suggestions.is_empty());
     }
 
@@ -397,11 +738,29 @@ mod tests {
             has_default: true,
             is_not_null: true,
         };
-        let suggestions = generate_suggestions(&op, TableSize::Large);
+        let suggestions = generate_suggestions(&op, TableSize::Large, None);
         assert_eq!(suggestions.len(), 1);
         assert!(suggestions[0].contains("nullable column"));
     }
 
+    #[test]
+    fn test_suggestion_add_default_rewrites_before_pg11() {
+        let op = DdlOperation::AlterTableAddColumn {
+            table: "users".into(),
+            column: "active".into(),
+            data_type: "boolean".into(),
+            has_default: true,
+            is_not_null: false,
+        };
+        let v10 = Some(PgVersion::from_version_num(100023));
+        let suggestions = generate_suggestions(&op, TableSize::Large, v10);
+        assert_eq!(suggestions.len(), 1);
+        assert!(suggestions[0].contains("PostgreSQL 10.23 rewrites the table"));
+
+        let v11 = Some(PgVersion::from_version_num(110000));
+        assert!(generate_suggestions(&op, TableSize::Large, v11).is_empty());
+    }
+
     #[test]
     fn test_suggestion_add_nullable_column_large_no_suggestion() {
         let op = DdlOperation::AlterTableAddColumn {
@@ -411,7 +770,7 @@ mod tests {
             has_default: false,
             is_not_null: false,
         };
-        let suggestions = generate_suggestions(&op, TableSize::Large);
+        let suggestions = generate_suggestions(&op, TableSize::Large, None);
         assert!(suggestions.is_empty());
     }
 
@@ -421,7 +780,7 @@ mod tests {
             table: "users".into(),
             column: "name".into(),
         };
-        let suggestions = generate_suggestions(&op, TableSize::Huge);
+        let suggestions = generate_suggestions(&op, TableSize::Huge, None);
         assert_eq!(suggestions.len(), 1);
         assert!(suggestions[0].contains("backfill"));
     }
@@ -432,7 +791,7 @@ mod tests {
             table: "users".into(),
             column: "name".into(),
         };
-        let suggestions = generate_suggestions(&op, TableSize::Small);
+        let suggestions = generate_suggestions(&op, TableSize::Small, None);
         assert!(suggestions.is_empty());
     }
 
@@ -441,7 +800,7 @@ mod tests {
         let op = DdlOperation::DropTable {
             table: "users".into(),
         };
-        let suggestions = generate_suggestions(&op, TableSize::Small);
+        let suggestions = generate_suggestions(&op, TableSize::Small, None);
         assert_eq!(suggestions.len(), 1);
         assert!(suggestions[0].contains("soft-delete"));
     }
@@ -452,7 +811,7 @@ mod tests {
             table: "users".into(),
             column: "email".into(),
         };
-        let suggestions = generate_suggestions(&op, TableSize::Medium);
+        let suggestions = generate_suggestions(&op, TableSize::Medium, None);
         assert_eq!(suggestions.len(), 1);
         assert!(suggestions[0].contains("soft-delete"));
     }
@@ -462,8 +821,77 @@ mod tests {
         let op = DdlOperation::TruncateTable {
             table: "logs".into(),
         };
-        let suggestions = generate_suggestions(&op, TableSize::Huge);
+        let suggestions = generate_suggestions(&op, TableSize::Huge, None);
         assert_eq!(suggestions.len(), 1);
         assert!(suggestions[0].contains("DELETE with batching"));
     }
+
+    fn hypertable() -> Vec<DistributedTableDef> {
+        vec![DistributedTableDef {
+            name: "metrics".to_string(),
+            kind: DistributionKind::Hypertable,
+            column: Some("ts".to_string()),
+        }]
+    }
+
+    #[test]
+    fn test_distributed_concurrent_index_on_hypertable() {
+        let op = DdlOperation::CreateIndex {
+            name: "idx".to_string(),
+            table: "metrics".to_string(),
+            is_concurrent: true,
+            is_unique: false,
+        };
+        let warnings = distributed_table_warnings(&op, &hypertable());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("transaction_per_chunk"));
+    }
+
+    #[test]
+    fn test_distributed_alter_distribution_column() {
+        let citus = vec![DistributedTableDef {
+            name: "orders".to_string(),
+            kind: DistributionKind::Distributed,
+            column: Some("tenant_id".to_string()),
+        }];
+        let op = DdlOperation::AlterTableAlterColumn {
+            table: "public.orders".to_string(),
+            column: "tenant_id".to_string(),
+        };
+        let warnings = distributed_table_warnings(&op, &citus);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("distribution column 'tenant_id'"));
+
+        let other = DdlOperation::AlterTableAlterColumn {
+            table: "orders".to_string(),
+            column: "status".to_string(),
+        };
+        assert!(distributed_table_warnings(&other, &citus).is_empty());
+    }
+
+    #[test]
+    fn test_distributed_plain_table_no_warnings() {
+        let op = DdlOperation::AddConstraint {
+            table: "users".to_string(),
+            constraint_type: "PRIMARY KEY".to_string(),
+        };
+        assert!(distributed_table_warnings(&op, &hypertable()).is_empty());
+    }
+
+    // ── Enum change rule ──────────────────────────────────────────────
+
+    #[test]
+    fn test_enum_rename_value_is_danger() {
+        let a =
+            enum_change_analysis("ALTER TYPE public.status RENAME VALUE 'todo' TO 'open'").unwrap();
+        assert_eq!(a.verdict, SafetyVerdict::Danger);
+        assert!(a.suggestions[0].contains("waypoint assist enum --type public.status"));
+        assert!(!a.data_loss);
+
+        let a = enum_change_analysis("DELETE FROM pg_enum WHERE enumlabel = 'archived'").unwrap();
+        assert!(a.data_loss);
+
+        assert!(enum_change_analysis("ALTER TYPE status ADD VALUE 'blocked'").is_none());
+        assert!(enum_change_analysis("ALTER TYPE status RENAME TO state").is_none());
+    }
 }
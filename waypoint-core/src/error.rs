@@ -34,6 +34,14 @@ pub fn format_db_error(e: &tokio_postgres::Error) -> String {
     msg
 }
 
+/// Render a guard's failure message for `GuardFailed`'s Display.
+fn guard_message_suffix(message: &Option<String>) -> String {
+    message
+        .as_deref()
+        .map(|m| format!(" — {}", m))
+        .unwrap_or_default()
+}
+
 /// All error types that Waypoint operations can produce.
 #[derive(Error, Debug)]
 pub enum WaypointError {
@@ -97,8 +105,8 @@ pub enum WaypointError {
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
-    /// A migration version is lower than the highest applied version and out-of-order is disabled.
-    #[error("Out-of-order migration not allowed: version {version} is below the highest applied version {highest}. Enable out_of_order to allow this.")]
+    /// A migration version is lower than the highest applied version and the out-of-order policy doesn't allow it.
+    #[error("Out-of-order migration not allowed: version {version} is below the highest applied version {highest}. Enable out_of_order, or set out_of_order_policy = \"patch-only\" to allow hotfixes within an applied major version.")]
     OutOfOrder { version: String, highest: String },
 
     /// A `${key}` placeholder in migration SQL has no corresponding value defined.
@@ -122,8 +130,21 @@ pub enum WaypointError {
     UndoFailed { script: String, reason: String },
 
     /// No undo migration file was found for the requested version.
-    #[error("No undo migration found for version {version}. Expected U{version}__*.sql file.")]
-    UndoMissing { version: String },
+    /// `backup_ref` is the backup taken before the version was applied, if
+    /// one was recorded.
+    #[error(
+        "No undo migration found for version {version}. Expected U{version}__*.sql file.{}",
+        .backup_ref.as_ref().map(|r| format!(" A backup was taken before it was applied: {}", r)).unwrap_or_default()
+    )]
+    UndoMissing {
+        version: String,
+        backup_ref: Option<String>,
+    },
+
+    /// A single version can't be undone on its own because later applied
+    /// migrations depend on it.
+    #[error("Cannot undo version {version} on its own: {reason}")]
+    UndoUnsafe { version: String, reason: String },
 
     /// Lint analysis found one or more errors in migration SQL.
     #[error("Lint found {error_count} error(s): {details}")]
@@ -156,6 +177,10 @@ pub enum WaypointError {
     #[error("Migration V{version} depends on V{dependency}, which does not exist")]
     MissingDependency { version: String, dependency: String },
 
+    /// A migration file is over `max_migration_bytes` or `max_statements`.
+    #[error("Migration {script} is too large: {reason}")]
+    MigrationTooLarge { script: String, reason: String },
+
     /// A migration directive comment is malformed or contains invalid values.
     #[error("Invalid directive in {script}: {reason}")]
     InvalidDirective { script: String, reason: String },
@@ -185,11 +210,13 @@ pub enum WaypointError {
     PreflightFailed { checks: String },
 
     /// A guard precondition or postcondition check failed.
-    #[error("Guard {kind} failed for {script}: {expression}")]
+    #[error("Guard {kind} failed for {script}: {expression}{}", guard_message_suffix(.message))]
     GuardFailed {
         kind: String,
         script: String,
         expression: String,
+        /// Message from the directive's `:: "..."` suffix, if any.
+        message: Option<String>,
     },
 
     /// A migration was blocked by a DANGER safety verdict.
@@ -211,6 +238,50 @@ pub enum WaypointError {
     /// The database connection was lost during an operation.
     #[error("Connection lost during {operation}: {detail}")]
     ConnectionLost { operation: String, detail: String },
+
+    /// A configured `[prerequisites]` schema or extension could not be created.
+    #[error("Failed to create prerequisite {object}: {reason}")]
+    PrerequisiteFailed { object: String, reason: String },
+
+    /// The `[backup]` command run before a destructive migration failed.
+    #[error("Backup before {script} failed: {reason}")]
+    BackupFailed { script: String, reason: String },
+
+    /// The database or migration files changed between `plan()` and `apply()`.
+    #[error("Migration plan is out of date: {reason}")]
+    PlanDiverged { reason: String },
+
+    /// The `[policy]` config does not allow this command.
+    #[error("Command '{command}' is denied by [policy]: {reason}")]
+    PolicyDenied { command: String, reason: String },
+
+    /// Connected to a read-only standby (or otherwise read-only server).
+    #[error("Cannot run '{command}' on a read-only server: {reason}")]
+    ReadOnlyStandby { command: String, reason: String },
+
+    /// An execution receipt failed `verify-receipt`.
+    #[error("Receipt {run_id} did not verify: {reason}")]
+    ReceiptInvalid { run_id: String, reason: String },
+
+    /// `info --pending` found migrations that are not applied yet.
+    #[error("{count} pending migration(s): {scripts}")]
+    PendingMigrations { count: usize, scripts: String },
+
+    /// `waypoint check` found data-quality checks that did not hold.
+    #[error("{count} check(s) failed: {checks}")]
+    ChecksFailed { count: usize, checks: String },
+
+    /// `sla-check` found migrations pending for longer than allowed.
+    #[error("{count} pending migration(s) older than {max_age}: {scripts}")]
+    PendingAgeExceeded {
+        count: usize,
+        max_age: String,
+        scripts: String,
+    },
+
+    /// `fingerprint --expect` found a different schema.
+    #[error("Schema fingerprint is {actual}, expected {expected}")]
+    FingerprintMismatch { expected: String, actual: String },
 }
 
 /// Convenience type alias for `Result<T, WaypointError>`.
@@ -0,0 +1,197 @@
+//! Structured progress events emitted while `migrate` runs.
+//!
+//! [`Waypoint::migrate_with_observer`](crate::Waypoint::migrate_with_observer)
+//! installs an observer for the duration of one run, and the engines report
+//! each step through [`emit`]: migrations starting and finishing, statements
+//! executed, hooks run, guards evaluated and safety verdicts. The observer is
+//! task-local, so engine code doesn't thread it through every call, and
+//! events are only built when one is installed. `waypoint migrate
+//! --json-stream` prints them as NDJSON.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::safety::SafetyVerdict;
+use crate::warning::Warning;
+
+/// Callback receiving migrate events.
+pub type Observer = Arc<dyn Fn(&MigrateEvent) + Send + Sync>;
+
+tokio::task_local! {
+    static OBSERVER: Observer;
+}
+
+/// One step of a migrate run.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrateEvent {
+    /// When the event was emitted.
+    pub timestamp: DateTime<Utc>,
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: MigrateEventKind,
+}
+
+/// What a [`MigrateEvent`] reports. Serialized with an `event` tag such as
+/// `"migration_started"`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MigrateEventKind {
+    /// The run began.
+    MigrateStarted {
+        /// Schema holding the history table.
+        schema: String,
+    },
+    /// A migration is about to be applied.
+    MigrationStarted {
+        /// Migration filename.
+        script: String,
+        /// Version, or `None` for a repeatable migration.
+        version: Option<String>,
+        /// Description from the filename.
+        description: String,
+    },
+    /// A statement of a migration finished. Only emitted when statements run
+    /// one at a time (`statement_progress` or `-- waypoint:no-transaction`);
+    /// otherwise the script runs as a single batch.
+    StatementExecuted {
+        /// Migration filename.
+        script: String,
+        /// 1-based statement number.
+        statement: usize,
+        /// Number of statements in the migration.
+        total: usize,
+        /// Rows reported as affected.
+        rows_affected: u64,
+        /// Statement execution time in milliseconds.
+        duration_ms: i32,
+    },
+    /// A migration was applied and recorded.
+    MigrationCompleted {
+        /// Migration filename.
+        script: String,
+        /// Version, or `None` for a repeatable migration.
+        version: Option<String>,
+        /// Execution time in milliseconds.
+        execution_time_ms: i32,
+        /// Rows reported as affected.
+        rows_affected: u64,
+    },
+    /// A lifecycle hook ran.
+    HookExecuted {
+        /// Hook phase, e.g. `beforeEachMigrate`.
+        phase: String,
+        /// Hook filename.
+        script: String,
+        /// Execution time in milliseconds.
+        duration_ms: i32,
+    },
+    /// A `require` or `ensure` guard was evaluated.
+    GuardEvaluated {
+        /// Migration filename.
+        script: String,
+        /// `require` or `ensure`.
+        kind: String,
+        /// Guard expression as written.
+        expression: String,
+        /// Whether it held.
+        passed: bool,
+    },
+    /// Safety analysis finished for a migration.
+    SafetyVerdict {
+        /// Migration filename.
+        script: String,
+        /// Overall verdict.
+        verdict: SafetyVerdict,
+        /// Suggestions for making the migration safer.
+        suggestions: Vec<String>,
+    },
+    /// The run succeeded.
+    MigrateFinished {
+        /// Number of migrations applied.
+        migrations_applied: usize,
+        /// Total migration execution time in milliseconds.
+        total_time_ms: i32,
+        /// Number of hooks executed.
+        hooks_executed: usize,
+        /// Non-fatal problems raised during the run.
+        warnings: Vec<Warning>,
+    },
+    /// The run failed.
+    MigrateFailed {
+        /// Error message.
+        error: String,
+    },
+}
+
+/// Run `fut` with `observer` receiving the events it emits.
+pub async fn observe<F: Future>(observer: Observer, fut: F) -> F::Output {
+    OBSERVER.scope(observer, fut).await
+}
+
+/// The observer installed for the current task, for handing to spawned tasks.
+#[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+pub(crate) fn current() -> Option<Observer> {
+    OBSERVER.try_with(Arc::clone).ok()
+}
+
+/// Run `fut` under `observer` when there is one.
+#[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+pub(crate) async fn inherit<F: Future>(observer: Option<Observer>, fut: F) -> F::Output {
+    match observer {
+        Some(observer) => observe(observer, fut).await,
+        None => fut.await,
+    }
+}
+
+/// Report an event to the installed observer, if any. `kind` is only called
+/// when someone is listening.
+pub(crate) fn emit(kind: impl FnOnce() -> MigrateEventKind) {
+    let _ = OBSERVER.try_with(|observer| {
+        observer(&MigrateEvent {
+            timestamp: Utc::now(),
+            kind: kind(),
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_events_reach_observer_only_inside_scope() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let observer: Observer = Arc::new(move |e: &MigrateEvent| {
+            sink.lock().unwrap().push(serde_json::to_value(e).unwrap());
+        });
+
+        emit(|| unreachable!("no observer installed"));
+        observe(observer, async {
+            emit(|| MigrateEventKind::MigrateStarted {
+                schema: "public".to_string(),
+            });
+            // Spawned tasks see the observer when it is handed over.
+            let inherited = current();
+            tokio::spawn(inherit(inherited, async {
+                emit(|| MigrateEventKind::MigrateFailed {
+                    error: "boom".to_string(),
+                });
+            }))
+            .await
+            .unwrap();
+        })
+        .await;
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0]["event"], "migrate_started");
+        assert_eq!(seen[0]["schema"], "public");
+        assert!(seen[0]["timestamp"].is_string());
+        assert_eq!(seen[1]["event"], "migrate_failed");
+    }
+}
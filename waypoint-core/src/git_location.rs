@@ -0,0 +1,210 @@
+//! Migration locations in remote git repositories.
+//!
+//! A location of the form `git+<url>#<ref>:<path>` (for example
+//! `git+https://github.com/acme/billing.git#main:db/migrations`) names a
+//! directory inside a git repository rather than on disk. [`GitRemoteProvider`]
+//! makes a shallow fetch of `<ref>` into a temporary directory and
+//! [`fetch`] substitutes the checked-out `<path>`, so file-only commands (`lint`, `changelog`,
+//! `safety`, ...) can run across many repositories without local clones.
+//!
+//! `<ref>` may be a branch, tag or commit; without it the remote's default
+//! branch is used. `<path>` defaults to the repository root. Fetching shells
+//! out to the `git` binary, so credentials come from the usual git
+//! configuration (credential helpers, SSH agent).
+
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Result, WaypointError};
+use crate::location::{LocationProvider, LocationProviders};
+
+pub use crate::location::FetchedLocations;
+
+const PREFIX: &str = "git+";
+
+/// A parsed `git+<url>#<ref>:<path>` location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitLocation {
+    /// Repository URL passed to `git fetch` (without the `git+` prefix).
+    pub url: String,
+    /// Branch, tag or commit to fetch; `None` for the remote's `HEAD`.
+    pub git_ref: Option<String>,
+    /// Directory within the repository holding the migrations.
+    pub path: PathBuf,
+}
+
+impl GitLocation {
+    /// Parse `location` if it is a git location; `Ok(None)` for a plain path.
+    pub fn parse(location: &Path) -> Result<Option<Self>> {
+        let Some(rest) = location.to_str().and_then(|s| s.strip_prefix(PREFIX)) else {
+            return Ok(None);
+        };
+        let (url, fragment) = match rest.split_once('#') {
+            Some((url, fragment)) => (url, fragment),
+            None => (rest, ""),
+        };
+        let (git_ref, path) = match fragment.split_once(':') {
+            Some((git_ref, path)) => (git_ref, path),
+            None => (fragment, ""),
+        };
+        if url.is_empty() {
+            return Err(WaypointError::ConfigError(format!(
+                "Git location '{}' has no repository URL",
+                location.display()
+            )));
+        }
+        let path = PathBuf::from(path.trim_matches('/'));
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(WaypointError::ConfigError(format!(
+                "Git location '{}' must use a relative path inside the repository",
+                location.display()
+            )));
+        }
+        Ok(Some(GitLocation {
+            url: url.to_string(),
+            git_ref: (!git_ref.is_empty()).then(|| git_ref.to_string()),
+            path,
+        }))
+    }
+}
+
+/// Fetches `git+<url>#<ref>:<path>` locations; one of the default
+/// [`LocationProviders`](crate::location::LocationProviders).
+#[derive(Debug, Clone, Copy)]
+pub struct GitRemoteProvider;
+
+impl LocationProvider for GitRemoteProvider {
+    fn handles(&self, location: &str) -> bool {
+        location.starts_with(PREFIX)
+    }
+
+    fn fetch(&self, location: &str, dir: &Path) -> Result<PathBuf> {
+        match GitLocation::parse(Path::new(location))? {
+            Some(git) => checkout(&git, dir),
+            None => Err(WaypointError::ConfigError(format!(
+                "'{}' is not a git location",
+                location
+            ))),
+        }
+    }
+}
+
+/// Fetch each remote location in `locations` (git or otherwise) into a
+/// temporary directory, with the default providers; see
+/// [`crate::location::fetch`].
+pub fn fetch(locations: &[PathBuf]) -> Result<FetchedLocations> {
+    crate::location::fetch(locations, &LocationProviders::default())
+}
+
+/// Shallow-fetch `git.git_ref` into `dir` and return the migrations path.
+fn checkout(git: &GitLocation, dir: &Path) -> Result<PathBuf> {
+    let git_ref = git.git_ref.as_deref().unwrap_or("HEAD");
+    log::info!(
+        "Fetching git location; url={}, ref={}, path={}",
+        git.url,
+        git_ref,
+        git.path.display()
+    );
+    run_git(dir, &["init", "--quiet"])?;
+    run_git(
+        dir,
+        &["fetch", "--quiet", "--depth", "1", "--", &git.url, git_ref],
+    )?;
+    run_git(dir, &["checkout", "--quiet", "FETCH_HEAD"])?;
+
+    let location = dir.join(&git.path);
+    if !location.is_dir() {
+        return Err(WaypointError::GitError(format!(
+            "'{}' is not a directory in {} at {}",
+            git.path.display(),
+            git.url,
+            git_ref
+        )));
+    }
+    Ok(location)
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| WaypointError::GitError(format!("Failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(WaypointError::GitError(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Option<GitLocation> {
+        GitLocation::parse(Path::new(s)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_git_locations() {
+        assert_eq!(parse("db/migrations"), None);
+        assert_eq!(
+            parse("git+https://host/repo.git#v1.2:db/migrations/"),
+            Some(GitLocation {
+                url: "https://host/repo.git".into(),
+                git_ref: Some("v1.2".into()),
+                path: PathBuf::from("db/migrations"),
+            })
+        );
+        let bare = parse("git+ssh://git@host/repo.git").unwrap();
+        assert_eq!(bare.git_ref, None);
+        assert_eq!(bare.path, PathBuf::new());
+        assert_eq!(
+            parse("git+file:///r#main").unwrap().git_ref.as_deref(),
+            Some("main")
+        );
+        assert!(GitLocation::parse(Path::new("git+https://host/r.git#main:../etc")).is_err());
+        assert!(GitLocation::parse(Path::new("git+#main:db")).is_err());
+    }
+
+    #[test]
+    fn test_fetch_checks_out_ref_and_cleans_up() {
+        let repo = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(repo.path())
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "--quiet", "--initial-branch=main"]);
+        std::fs::create_dir_all(repo.path().join("db")).unwrap();
+        std::fs::write(repo.path().join("db/V1__Init.sql"), "SELECT 1;").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+
+        let url = format!("git+file://{}#main:db", repo.path().display());
+        let fetched = fetch(&[PathBuf::from(&url), PathBuf::from("local")]).unwrap();
+        let checkout = fetched.locations[0].clone();
+        assert!(checkout.join("V1__Init.sql").is_file());
+        assert_eq!(fetched.locations[1], PathBuf::from("local"));
+        drop(fetched);
+        assert!(!checkout.exists());
+
+        let missing = format!("git+file://{}#main:nope", repo.path().display());
+        assert!(matches!(
+            fetch(&[PathBuf::from(missing)]),
+            Err(WaypointError::GitError(_))
+        ));
+    }
+}
@@ -10,6 +10,14 @@
 //! Expressions support boolean operators (`AND`, `OR`, `NOT`), comparison
 //! operators (`<`, `>`, `<=`, `>=`), and built-in assertion functions that
 //! query the database schema.
+//!
+//! The `remote_*` builtins run the same checks against another database,
+//! named in `[guards.targets]` and installed for the duration of a command by
+//! [`with_targets`], so a rollout can wait on another environment (e.g.
+//! `remote_table_exists("prod", "users")` in staging).
+
+use std::collections::HashMap;
+use std::future::Future;
 
 use crate::db::DbClient;
 use crate::dialect::DialectKind;
@@ -18,6 +26,10 @@ use crate::error::{Result, WaypointError};
 /// Maximum nesting depth for guard expression parsing.
 const MAX_PARSE_DEPTH: usize = 50;
 
+tokio::task_local! {
+    static TARGETS: HashMap<String, String>;
+}
+
 // ---------------------------------------------------------------------------
 // Configuration
 // ---------------------------------------------------------------------------
@@ -48,12 +60,14 @@ impl std::str::FromStr for OnRequireFail {
 }
 
 /// Configuration for guard (pre/post condition) evaluation.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct GuardsConfig {
     /// Whether guard conditions are evaluated before/after migrations.
     pub enabled: bool,
     /// What to do when a precondition (`-- waypoint:require`) fails.
     pub on_require_fail: OnRequireFail,
+    /// Named connection strings the `remote_*` builtins connect to.
+    pub targets: HashMap<String, String>,
 }
 
 impl Default for GuardsConfig {
@@ -61,10 +75,24 @@ impl Default for GuardsConfig {
         Self {
             enabled: true,
             on_require_fail: OnRequireFail::default(),
+            targets: HashMap::new(),
         }
     }
 }
 
+impl std::fmt::Debug for GuardsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Target connection strings carry credentials; show only the names.
+        let mut targets: Vec<&str> = self.targets.keys().map(String::as_str).collect();
+        targets.sort_unstable();
+        f.debug_struct("GuardsConfig")
+            .field("enabled", &self.enabled)
+            .field("on_require_fail", &self.on_require_fail)
+            .field("targets", &targets)
+            .finish()
+    }
+}
+
 // ---------------------------------------------------------------------------
 // AST
 // ---------------------------------------------------------------------------
@@ -126,8 +154,66 @@ pub enum GuardExpr {
     BoolLiteral(bool),
 }
 
+impl std::fmt::Display for GuardExpr {
+    /// Render back to guard syntax, parenthesizing only where precedence needs it.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Binding strength: OR < AND < NOT < comparison < atoms.
+        fn precedence(expr: &GuardExpr) -> u8 {
+            match expr {
+                GuardExpr::Or(..) => 1,
+                GuardExpr::And(..) => 2,
+                GuardExpr::Not(_) => 3,
+                GuardExpr::Comparison { .. } => 4,
+                _ => 5,
+            }
+        }
+        fn operand(f: &mut std::fmt::Formatter<'_>, expr: &GuardExpr, min: u8) -> std::fmt::Result {
+            if precedence(expr) < min {
+                write!(f, "({expr})")
+            } else {
+                write!(f, "{expr}")
+            }
+        }
+        match self {
+            GuardExpr::FunctionCall { name, args } => {
+                write!(f, "{name}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+            GuardExpr::And(left, right) => {
+                operand(f, left, 2)?;
+                write!(f, " AND ")?;
+                operand(f, right, 3)
+            }
+            GuardExpr::Or(left, right) => {
+                operand(f, left, 1)?;
+                write!(f, " OR ")?;
+                operand(f, right, 2)
+            }
+            GuardExpr::Not(inner) => {
+                write!(f, "NOT ")?;
+                operand(f, inner, 3)
+            }
+            GuardExpr::Comparison { left, op, right } => {
+                operand(f, left, 5)?;
+                write!(f, " {op} ")?;
+                operand(f, right, 5)
+            }
+            GuardExpr::StringLiteral(s) => write!(f, "\"{s}\""),
+            GuardExpr::NumberLiteral(n) => write!(f, "{n}"),
+            GuardExpr::BoolLiteral(b) => write!(f, "{b}"),
+        }
+    }
+}
+
 /// The runtime value produced by evaluating a guard expression.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(untagged)]
 pub enum GuardValue {
     /// A boolean value.
     Bool(bool),
@@ -551,6 +637,39 @@ pub fn parse(input: &str) -> Result<GuardExpr> {
     Ok(expr)
 }
 
+/// Check a parsed expression without a database: every function must be a
+/// built-in called with the right number of string arguments.
+///
+/// # Errors
+///
+/// Returns `WaypointError::ConfigError` describing the first problem found.
+pub fn check(expr: &GuardExpr) -> Result<()> {
+    match expr {
+        GuardExpr::FunctionCall { name, args } => {
+            let args = extract_string_args(args)?;
+            let (name, args) = match split_remote(name, &args)? {
+                Some((_, local, rest)) => (local, rest),
+                None => (name.as_str(), &args[..]),
+            };
+            #[cfg(feature = "postgres")]
+            builtin_sql(name, args, "public")?;
+            #[cfg(not(feature = "postgres"))]
+            builtin_sql_mysql(name, args, "public")?;
+            Ok(())
+        }
+        GuardExpr::And(left, right)
+        | GuardExpr::Or(left, right)
+        | GuardExpr::Comparison { left, right, .. } => {
+            check(left)?;
+            check(right)
+        }
+        GuardExpr::Not(inner) => check(inner),
+        GuardExpr::StringLiteral(_) | GuardExpr::NumberLiteral(_) | GuardExpr::BoolLiteral(_) => {
+            Ok(())
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Built-in function SQL generation
 // ---------------------------------------------------------------------------
@@ -959,6 +1078,9 @@ fn eval_expr<'a>(
 
             GuardExpr::FunctionCall { name, args } => {
                 let string_args = extract_string_args(args)?;
+                if let Some((target, local, rest)) = split_remote(name, &string_args)? {
+                    return exec_remote(schema, target, local, rest).await;
+                }
                 let (sql, param_values, is_boolean) = builtin_sql(name, &string_args, schema)?;
                 let params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = param_values
                     .iter()
@@ -977,6 +1099,7 @@ fn eval_expr<'a>(
                                 .collect::<Vec<_>>()
                                 .join(", ")
                         ),
+                        message: None,
                     }
                 })?;
 
@@ -996,13 +1119,61 @@ fn eval_expr<'a>(
 // Dialect-aware evaluator
 // ---------------------------------------------------------------------------
 
+/// One node of an evaluated guard expression, in evaluation order.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GuardStep {
+    /// Nesting depth below the root expression (root is 0).
+    pub depth: usize,
+    /// The sub-expression, rendered back to guard syntax.
+    pub expression: String,
+    /// The value it produced, or `None` when `AND`/`OR` short-circuited past it.
+    pub value: Option<GuardValue>,
+}
+
+/// Result of [`trace_db`]: the overall verdict plus every sub-expression value.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GuardEvaluation {
+    /// The expression as parsed, rendered back to guard syntax.
+    pub expression: String,
+    /// Schema the built-in functions were evaluated against.
+    pub schema: String,
+    /// Final boolean result.
+    pub result: bool,
+    /// Sub-expression values in pre-order (parents before children).
+    pub steps: Vec<GuardStep>,
+}
+
 /// Evaluate a guard expression against a [`DbClient`] (dialect-aware entry).
 ///
 /// Dispatches to the PostgreSQL or MySQL implementation based on the connection
 /// kind. Recursion shape mirrors the legacy [`evaluate`] function; only the
 /// leaf `FunctionCall` arm differs per engine.
 pub async fn evaluate_db(client: &DbClient, schema: &str, expr: &GuardExpr) -> Result<bool> {
-    let value = eval_expr_db(client, schema, expr).await?;
+    let value = eval_expr_db(client, schema, expr, 0, &mut Vec::new()).await?;
+    expect_bool(value)
+}
+
+/// Evaluate a guard expression and record the value of every sub-expression.
+///
+/// Backs `waypoint guard eval`, so a failing `require`/`ensure` can be debugged
+/// without editing migrations. Evaluation semantics match [`evaluate_db`],
+/// including `AND`/`OR` short-circuiting; skipped operands appear with no value.
+pub async fn trace_db(
+    client: &DbClient,
+    schema: &str,
+    expr: &GuardExpr,
+) -> Result<GuardEvaluation> {
+    let mut steps = Vec::new();
+    let value = eval_expr_db(client, schema, expr, 0, &mut steps).await?;
+    Ok(GuardEvaluation {
+        expression: expr.to_string(),
+        schema: schema.to_string(),
+        result: expect_bool(value)?,
+        steps,
+    })
+}
+
+fn expect_bool(value: GuardValue) -> Result<bool> {
     match value {
         GuardValue::Bool(b) => Ok(b),
         other => Err(WaypointError::ConfigError(format!(
@@ -1011,68 +1182,102 @@ pub async fn evaluate_db(client: &DbClient, schema: &str, expr: &GuardExpr) -> R
     }
 }
 
+/// Record a short-circuited operand so traces show why it has no value.
+fn skip_step(steps: &mut Vec<GuardStep>, expr: &GuardExpr, depth: usize) {
+    steps.push(GuardStep {
+        depth,
+        expression: expr.to_string(),
+        value: None,
+    });
+}
+
 fn eval_expr_db<'a>(
     client: &'a DbClient,
     schema: &'a str,
     expr: &'a GuardExpr,
+    depth: usize,
+    steps: &'a mut Vec<GuardStep>,
 ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<GuardValue>> + Send + 'a>> {
     Box::pin(async move {
-        match expr {
-            GuardExpr::BoolLiteral(b) => Ok(GuardValue::Bool(*b)),
-            GuardExpr::NumberLiteral(n) => Ok(GuardValue::Number(*n)),
-            GuardExpr::StringLiteral(s) => Ok(GuardValue::Str(s.clone())),
+        // Reserve this node's slot first so the trace stays in pre-order.
+        let slot = steps.len();
+        steps.push(GuardStep {
+            depth,
+            expression: expr.to_string(),
+            value: None,
+        });
+        let value = match expr {
+            GuardExpr::BoolLiteral(b) => GuardValue::Bool(*b),
+            GuardExpr::NumberLiteral(n) => GuardValue::Number(*n),
+            GuardExpr::StringLiteral(s) => GuardValue::Str(s.clone()),
 
             GuardExpr::Not(inner) => {
-                let val = eval_expr_db(client, schema, inner).await?;
+                let val = eval_expr_db(client, schema, inner, depth + 1, steps).await?;
                 match val {
-                    GuardValue::Bool(b) => Ok(GuardValue::Bool(!b)),
-                    other => Err(WaypointError::ConfigError(format!(
-                        "Guard expression: NOT requires boolean, got {other}"
-                    ))),
+                    GuardValue::Bool(b) => GuardValue::Bool(!b),
+                    other => {
+                        return Err(WaypointError::ConfigError(format!(
+                            "Guard expression: NOT requires boolean, got {other}"
+                        )))
+                    }
                 }
             }
 
             GuardExpr::And(left, right) => {
-                let lval = eval_expr_db(client, schema, left).await?;
+                let lval = eval_expr_db(client, schema, left, depth + 1, steps).await?;
                 match lval {
-                    GuardValue::Bool(false) => Ok(GuardValue::Bool(false)),
+                    GuardValue::Bool(false) => {
+                        skip_step(steps, right, depth + 1);
+                        GuardValue::Bool(false)
+                    }
                     GuardValue::Bool(true) => {
-                        let rval = eval_expr_db(client, schema, right).await?;
+                        let rval = eval_expr_db(client, schema, right, depth + 1, steps).await?;
                         match rval {
-                            GuardValue::Bool(b) => Ok(GuardValue::Bool(b)),
-                            other => Err(WaypointError::ConfigError(format!(
-                                "Guard expression: AND requires boolean operands, got {other}"
-                            ))),
+                            GuardValue::Bool(b) => GuardValue::Bool(b),
+                            other => {
+                                return Err(WaypointError::ConfigError(format!(
+                                    "Guard expression: AND requires boolean operands, got {other}"
+                                )))
+                            }
                         }
                     }
-                    other => Err(WaypointError::ConfigError(format!(
-                        "Guard expression: AND requires boolean operands, got {other}"
-                    ))),
+                    other => {
+                        return Err(WaypointError::ConfigError(format!(
+                            "Guard expression: AND requires boolean operands, got {other}"
+                        )))
+                    }
                 }
             }
 
             GuardExpr::Or(left, right) => {
-                let lval = eval_expr_db(client, schema, left).await?;
+                let lval = eval_expr_db(client, schema, left, depth + 1, steps).await?;
                 match lval {
-                    GuardValue::Bool(true) => Ok(GuardValue::Bool(true)),
+                    GuardValue::Bool(true) => {
+                        skip_step(steps, right, depth + 1);
+                        GuardValue::Bool(true)
+                    }
                     GuardValue::Bool(false) => {
-                        let rval = eval_expr_db(client, schema, right).await?;
+                        let rval = eval_expr_db(client, schema, right, depth + 1, steps).await?;
                         match rval {
-                            GuardValue::Bool(b) => Ok(GuardValue::Bool(b)),
-                            other => Err(WaypointError::ConfigError(format!(
-                                "Guard expression: OR requires boolean operands, got {other}"
-                            ))),
+                            GuardValue::Bool(b) => GuardValue::Bool(b),
+                            other => {
+                                return Err(WaypointError::ConfigError(format!(
+                                    "Guard expression: OR requires boolean operands, got {other}"
+                                )))
+                            }
                         }
                     }
-                    other => Err(WaypointError::ConfigError(format!(
-                        "Guard expression: OR requires boolean operands, got {other}"
-                    ))),
+                    other => {
+                        return Err(WaypointError::ConfigError(format!(
+                            "Guard expression: OR requires boolean operands, got {other}"
+                        )))
+                    }
                 }
             }
 
             GuardExpr::Comparison { left, op, right } => {
-                let lval = eval_expr_db(client, schema, left).await?;
-                let rval = eval_expr_db(client, schema, right).await?;
+                let lval = eval_expr_db(client, schema, left, depth + 1, steps).await?;
+                let rval = eval_expr_db(client, schema, right, depth + 1, steps).await?;
                 match (&lval, &rval) {
                     (GuardValue::Number(a), GuardValue::Number(b)) => {
                         let result = match op {
@@ -1081,19 +1286,26 @@ fn eval_expr_db<'a>(
                             ComparisonOp::Le => a <= b,
                             ComparisonOp::Ge => a >= b,
                         };
-                        Ok(GuardValue::Bool(result))
+                        GuardValue::Bool(result)
+                    }
+                    _ => {
+                        return Err(WaypointError::ConfigError(format!(
+                            "Guard expression: comparison requires numeric operands, got {lval} {op} {rval}"
+                        )))
                     }
-                    _ => Err(WaypointError::ConfigError(format!(
-                        "Guard expression: comparison requires numeric operands, got {lval} {op} {rval}"
-                    ))),
                 }
             }
 
             GuardExpr::FunctionCall { name, args } => {
                 let string_args = extract_string_args(args)?;
-                exec_builtin(client, schema, name, &string_args).await
+                match split_remote(name, &string_args)? {
+                    Some((target, local, rest)) => exec_remote(schema, target, local, rest).await?,
+                    None => exec_builtin(client, schema, name, &string_args).await?,
+                }
             }
-        }
+        };
+        steps[slot].value = Some(value.clone());
+        Ok(value)
     })
 }
 
@@ -1167,6 +1379,116 @@ async fn exec_builtin(
     }
 }
 
+/// Run `fut` with `targets` (`[guards.targets]`) available to the
+/// `remote_*` builtins. Target names are case-insensitive.
+pub async fn with_targets<F: Future>(targets: HashMap<String, String>, fut: F) -> F::Output {
+    let targets = targets
+        .into_iter()
+        .map(|(name, url)| (name.to_lowercase(), url))
+        .collect();
+    TARGETS.scope(targets, fut).await
+}
+
+/// Split a `remote_*` call into its target and the builtin (with its
+/// arguments) it runs there; `None` for every other function.
+fn split_remote<'a>(
+    name: &str,
+    args: &'a [String],
+) -> Result<Option<(&'a str, &'static str, &'a [String])>> {
+    let local = match name {
+        "remote_table_exists" => "table_exists",
+        "remote_sql" => "sql",
+        _ => return Ok(None),
+    };
+    require_args(name, args, 2)?;
+    Ok(Some((&args[0], local, &args[1..])))
+}
+
+/// Connection string of the target installed by [`with_targets`].
+fn target_url(target: &str) -> Result<String> {
+    TARGETS
+        .try_with(|targets| targets.get(&target.to_lowercase()).cloned())
+        .ok()
+        .flatten()
+        .ok_or_else(|| {
+            WaypointError::ConfigError(format!(
+                "Guard expression: unknown target '{target}' (define it under [guards.targets])"
+            ))
+        })
+}
+
+/// Evaluate builtin `local` on the database named `target`, in the same
+/// schema as local guards. The connection lasts for this one call.
+///
+/// The target is usually another production database reached with full
+/// credentials, so the builtin runs inside a read-only transaction that is
+/// always rolled back: a `remote_sql` query can read but never write.
+async fn exec_remote(
+    schema: &str,
+    target: &str,
+    local: &str,
+    args: &[String],
+) -> Result<GuardValue> {
+    let url = target_url(target)?;
+    let remote = match DialectKind::from_url(&url).unwrap_or(DialectKind::Postgres) {
+        #[cfg(feature = "postgres")]
+        DialectKind::Postgres => crate::db::connect(&url).await.map(DbClient::with_postgres),
+        #[cfg(not(feature = "postgres"))]
+        DialectKind::Postgres => Err(WaypointError::ConfigError(
+            "PostgreSQL support is not compiled in".into(),
+        )),
+        #[cfg(feature = "mysql")]
+        DialectKind::Mysql => single_connection_pool(&url).map(DbClient::with_mysql),
+        #[cfg(not(feature = "mysql"))]
+        DialectKind::Mysql => Err(WaypointError::ConfigError(
+            "MySQL support is not compiled in".into(),
+        )),
+    }
+    .map_err(|e| guard_failed(local, args, &format!("target '{target}': {e}")))?;
+    let begin = match remote.dialect_kind() {
+        DialectKind::Postgres => "BEGIN READ ONLY",
+        DialectKind::Mysql => "START TRANSACTION READ ONLY",
+    };
+    let value = match remote.execute_raw(begin).await {
+        Ok(_) => {
+            let value = match remote.resolve_schema(schema).await {
+                Ok(schema) => exec_builtin(&remote, &schema, local, args).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = remote.execute_raw("ROLLBACK").await {
+                log::warn!("Failed to roll back guard transaction; target={target}, error={e}");
+            }
+            value
+        }
+        Err(e) => Err(guard_failed(
+            local,
+            args,
+            &format!("target '{target}': could not start a read-only transaction: {e}"),
+        )),
+    };
+    #[cfg(feature = "mysql")]
+    #[allow(irrefutable_let_patterns)] // without the postgres feature
+    if let DbClient::Mysql(pool) = remote {
+        let _ = pool.disconnect().await;
+    }
+    value
+}
+
+/// A pool that hands out one connection, never reset between queries, so
+/// the read-only transaction [`exec_remote`] opens covers every query.
+#[cfg(feature = "mysql")]
+fn single_connection_pool(url: &str) -> Result<mysql_async::Pool> {
+    let opts = mysql_async::Opts::from_url(&crate::db::mysql_url(url))
+        .map_err(|e| WaypointError::ConfigError(format!("Invalid MySQL connection URL: {}", e)))?;
+    let constraints = mysql_async::PoolConstraints::new(1, 1).expect("1..=1 is a valid range");
+    let pool_opts = mysql_async::PoolOpts::default()
+        .with_constraints(constraints)
+        .with_reset_connection(false);
+    Ok(mysql_async::Pool::new(
+        mysql_async::OptsBuilder::from_opts(opts).pool_opts(pool_opts),
+    ))
+}
+
 fn guard_failed(name: &str, args: &[String], reason: &str) -> WaypointError {
     WaypointError::GuardFailed {
         kind: "evaluation".to_string(),
@@ -1178,6 +1500,7 @@ fn guard_failed(name: &str, args: &[String], reason: &str) -> WaypointError {
                 .collect::<Vec<_>>()
                 .join(", ")
         ),
+        message: None,
     }
 }
 
@@ -1619,6 +1942,19 @@ mod tests {
         assert!(err.contains("unknown function"), "got: {err}");
     }
 
+    #[test]
+    fn test_check_static() {
+        assert!(
+            check(&parse("table_exists(\"a\") AND NOT column_exists(\"a\", \"b\")").unwrap())
+                .is_ok()
+        );
+        assert!(check(&parse("row_count(\"a\") < 10").unwrap()).is_ok());
+        let err = check(&parse("tabel_exists(\"a\") OR true").unwrap()).unwrap_err();
+        assert!(err.to_string().contains("unknown function 'tabel_exists'"));
+        assert!(check(&parse("column_exists(\"a\")").unwrap()).is_err());
+        assert!(check(&parse("table_exists(1)").unwrap()).is_err());
+    }
+
     #[test]
     fn test_builtin_sql_wrong_arg_count() {
         let result = builtin_sql("table_exists", &[], "public");
@@ -1627,6 +1963,34 @@ mod tests {
         assert!(err.contains("expects 1 argument"), "got: {err}");
     }
 
+    #[tokio::test]
+    async fn test_remote_builtins() {
+        check(&parse(r#"remote_table_exists("prod", "users")"#).unwrap()).unwrap();
+        check(&parse(r#"remote_sql("prod", "SELECT true")"#).unwrap()).unwrap();
+        let err = check(&parse(r#"remote_table_exists("users")"#).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("expects 2 argument"), "got: {err}");
+
+        let args = ["prod".to_string(), "users".to_string()];
+        assert_eq!(
+            split_remote("remote_table_exists", &args).unwrap(),
+            Some(("prod", "table_exists", &args[1..]))
+        );
+        assert_eq!(split_remote("table_exists", &args[1..]).unwrap(), None);
+
+        assert!(target_url("prod").is_err());
+        let targets = HashMap::from([("prod".to_string(), "postgres://prod/app".to_string())]);
+        let config = GuardsConfig {
+            targets: targets.clone(),
+            ..Default::default()
+        };
+        assert!(!format!("{config:?}").contains("postgres://"));
+        let url = with_targets(targets, async { target_url("prod") }).await;
+        assert_eq!(url.unwrap(), "postgres://prod/app");
+        let targets = HashMap::from([("Prod".to_string(), "postgres://prod/app".to_string())]);
+        let url = with_targets(targets, async { target_url("PROD") }).await;
+        assert_eq!(url.unwrap(), "postgres://prod/app");
+    }
+
     #[test]
     fn test_parse_depth_limit() {
         // Build a deeply nested expression: NOT NOT NOT ... NOT true
@@ -1763,4 +2127,40 @@ mod tests {
             other => panic!("Expected FunctionCall, got {other:?}"),
         }
     }
+
+    #[test]
+    fn test_display_round_trips_through_parse() {
+        for input in [
+            "table_exists(\"users\") AND row_count(\"users\") < 1000",
+            "NOT (table_exists(\"a\") OR column_exists(\"a\", \"b\"))",
+            "(true OR false) AND NOT false",
+            "true OR (false AND true)",
+            "a(\"x\") AND (b(\"y\") AND c(\"z\"))",
+        ] {
+            let expr = parse(input).unwrap();
+            let rendered = expr.to_string();
+            assert_eq!(parse(&rendered).unwrap(), expr, "{input} -> {rendered}");
+        }
+        assert_eq!(
+            parse("((table_exists(\"users\")))").unwrap().to_string(),
+            "table_exists(\"users\")"
+        );
+        assert_eq!(
+            parse("a() OR b() AND c()").unwrap().to_string(),
+            "a() OR b() AND c()"
+        );
+    }
+
+    #[test]
+    fn test_guard_value_serializes_untagged() {
+        let values = vec![
+            GuardValue::Bool(true),
+            GuardValue::Number(42),
+            GuardValue::Str("users".into()),
+        ];
+        assert_eq!(
+            serde_json::to_string(&values).unwrap(),
+            r#"[true,42,"users"]"#
+        );
+    }
 }
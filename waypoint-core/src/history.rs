@@ -10,12 +10,210 @@
 //! [`crate::engines::postgres::history`] for back-compat — code that
 //! previously called `crate::history::create_history_table(&Client, …)`
 //! keeps working unchanged.
+//!
+//! The layout of the history table is versioned. Each schema records the
+//! layout version of its history tables in [`HISTORY_META_TABLE`], and the
+//! first write-side contact with an older table applies the missing
+//! [`HISTORY_UPGRADES`] through [`ensure_schema_version_db`]
+//! (transactionally on PostgreSQL). Reads don't upgrade: columns an older
+//! table lacks come back as `None`, so read-only commands keep working
+//! against tables a newer release hasn't touched yet.
 
 use chrono::{DateTime, Utc};
+use serde::Serialize;
 
 use crate::db::DbClient;
+use crate::dialect::{DatabaseDialect, DialectKind};
 use crate::error::{Result, WaypointError};
 
+/// Table recording the history-table layout version, one row per history
+/// table, created next to it in the same schema.
+pub const HISTORY_META_TABLE: &str = "waypoint_meta";
+
+/// History-table layout version this build of waypoint writes.
+pub const HISTORY_SCHEMA_VERSION: i32 = 5;
+
+/// A column added to the history table after the original
+/// Flyway-compatible layout (version 1).
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryColumn {
+    /// Layout version that introduced the column.
+    pub version: i32,
+    /// Column name.
+    pub name: &'static str,
+    /// Column type on PostgreSQL.
+    pub pg_type: &'static str,
+    /// Column type on MySQL.
+    pub mysql_type: &'static str,
+}
+
+/// Columns added to the history table, in layout-version order.
+pub const HISTORY_UPGRADES: &[HistoryColumn] = &[
+    HistoryColumn {
+        version: 2,
+        name: "reversal_sql",
+        pg_type: "TEXT",
+        mysql_type: "LONGTEXT",
+    },
+    HistoryColumn {
+        version: 3,
+        name: "backup_ref",
+        pg_type: "TEXT",
+        mysql_type: "TEXT",
+    },
+    HistoryColumn {
+        version: 4,
+        name: "checksum_sha256",
+        pg_type: "VARCHAR(64)",
+        mysql_type: "VARCHAR(64)",
+    },
+    HistoryColumn {
+        version: 5,
+        name: "git_commit",
+        pg_type: "VARCHAR(64)",
+        mysql_type: "VARCHAR(64)",
+    },
+    HistoryColumn {
+        version: 5,
+        name: "ci_build_url",
+        pg_type: "TEXT",
+        mysql_type: "TEXT",
+    },
+    HistoryColumn {
+        version: 5,
+        name: "hostname",
+        pg_type: "VARCHAR(255)",
+        mysql_type: "VARCHAR(255)",
+    },
+    HistoryColumn {
+        version: 5,
+        name: "waypoint_version",
+        pg_type: "VARCHAR(32)",
+        mysql_type: "VARCHAR(32)",
+    },
+];
+
+/// What bringing a history table up to [`HISTORY_SCHEMA_VERSION`] takes,
+/// and whether it was done.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryUpgrade {
+    /// The history table, as `schema.table`.
+    pub table: String,
+    /// Layout version recorded in `waypoint_meta`, or `None` if the table
+    /// predates version tracking.
+    pub from_version: Option<i32>,
+    /// Layout version after the upgrade.
+    pub to_version: i32,
+    /// Columns the upgrade adds.
+    pub columns_added: Vec<String>,
+    /// Statements that perform the upgrade, in order. Empty when current.
+    pub statements: Vec<String>,
+    /// Whether the statements were executed (false for `--dry-run`).
+    pub applied: bool,
+}
+
+impl HistoryUpgrade {
+    /// Whether the table is already at (or past) this build's layout.
+    pub fn is_current(&self) -> bool {
+        self.statements.is_empty()
+    }
+}
+
+/// Work out the statements that bring a history table to
+/// [`HISTORY_SCHEMA_VERSION`], given the version recorded in `waypoint_meta`
+/// and the columns the table has now.
+///
+/// Columns are added by presence rather than by recorded version, so tables
+/// upgraded by hand or by older releases (which added columns without
+/// recording a version) converge on the same layout.
+pub fn build_upgrade_plan(
+    dialect: &dyn DatabaseDialect,
+    schema: &str,
+    table: &str,
+    from_version: Option<i32>,
+    columns: &[String],
+) -> HistoryUpgrade {
+    let fq = dialect.qualified_table(schema, table);
+    let meta = dialect.qualified_table(schema, HISTORY_META_TABLE);
+    let mysql = dialect.kind() == DialectKind::Mysql;
+
+    let missing: Vec<&HistoryColumn> = HISTORY_UPGRADES
+        .iter()
+        .filter(|c| !columns.iter().any(|have| have.eq_ignore_ascii_case(c.name)))
+        .collect();
+    let mut statements: Vec<String> = missing
+        .iter()
+        .map(|c| {
+            let ty = if mysql { c.mysql_type } else { c.pg_type };
+            format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                fq,
+                dialect.quote_ident(c.name),
+                ty
+            )
+        })
+        .collect();
+
+    let behind = from_version.is_none_or(|v| v < HISTORY_SCHEMA_VERSION);
+    if behind || !statements.is_empty() {
+        let (timestamp, now) = if mysql {
+            ("TIMESTAMP", "CURRENT_TIMESTAMP")
+        } else {
+            ("TIMESTAMPTZ", "now()")
+        };
+        statements.insert(
+            0,
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (\
+                 history_table VARCHAR(255) PRIMARY KEY, \
+                 schema_version INTEGER NOT NULL, \
+                 upgraded_on {} NOT NULL DEFAULT {})",
+                meta, timestamp, now
+            ),
+        );
+        let version =
+            from_version.map_or(HISTORY_SCHEMA_VERSION, |v| v.max(HISTORY_SCHEMA_VERSION));
+        let literal = table.replace('\'', "''");
+        statements.push(if mysql {
+            format!(
+                "INSERT INTO {} (history_table, schema_version) VALUES ('{}', {}) \
+                 ON DUPLICATE KEY UPDATE schema_version = VALUES(schema_version), \
+                 upgraded_on = CURRENT_TIMESTAMP",
+                meta, literal, version
+            )
+        } else {
+            format!(
+                "INSERT INTO {} (history_table, schema_version) VALUES ('{}', {}) \
+                 ON CONFLICT (history_table) DO UPDATE SET \
+                 schema_version = EXCLUDED.schema_version, upgraded_on = now()",
+                meta, literal, version
+            )
+        });
+    }
+
+    HistoryUpgrade {
+        table: format!("{}.{}", schema, table),
+        from_version,
+        to_version: from_version.map_or(HISTORY_SCHEMA_VERSION, |v| v.max(HISTORY_SCHEMA_VERSION)),
+        columns_added: missing.iter().map(|c| c.name.to_string()).collect(),
+        statements,
+        applied: false,
+    }
+}
+
+/// Log that a history table was upgraded by a newer waypoint release. Older
+/// releases keep working against it, since upgrades only add nullable columns.
+pub(crate) fn warn_if_newer(upgrade: &HistoryUpgrade) {
+    if let Some(v) = upgrade.from_version.filter(|v| *v > HISTORY_SCHEMA_VERSION) {
+        log::warn!(
+            "History table layout is newer than this release; table={}, version={}, supported={}",
+            upgrade.table,
+            v,
+            HISTORY_SCHEMA_VERSION
+        );
+    }
+}
+
 /// A row from the schema history table.
 #[derive(Debug, Clone)]
 pub struct AppliedMigration {
@@ -41,6 +239,38 @@ pub struct AppliedMigration {
     pub success: bool,
     /// Auto-generated reverse SQL, if available.
     pub reversal_sql: Option<String>,
+    /// Reference printed by the `[backup]` command run before this migration.
+    pub backup_ref: Option<String>,
+    /// SHA-256 of the migration SQL as lowercase hex, recorded when
+    /// `checksum_algorithm = "sha256"`.
+    pub checksum_sha256: Option<String>,
+    /// Git commit, CI build, hostname and waypoint version recorded by the
+    /// migrate that applied this row.
+    pub audit: crate::audit::AuditMetadata,
+}
+
+#[cfg(test)]
+impl AppliedMigration {
+    /// A successful `SQL` row applied just now, for tests to vary with
+    /// struct update syntax.
+    pub(crate) fn for_test(rank: i32, version: Option<&str>, script: &str) -> Self {
+        AppliedMigration {
+            installed_rank: rank,
+            version: version.map(str::to_string),
+            description: String::new(),
+            migration_type: "SQL".to_string(),
+            script: script.to_string(),
+            checksum: Some(0),
+            installed_by: "test".to_string(),
+            installed_on: Utc::now(),
+            execution_time: 0,
+            success: true,
+            reversal_sql: None,
+            backup_ref: None,
+            checksum_sha256: None,
+            audit: Default::default(),
+        }
+    }
 }
 
 // ── Re-exports of the legacy PG-only entry points ────────────────────────────
@@ -51,9 +281,9 @@ pub struct AppliedMigration {
 
 #[cfg(feature = "postgres")]
 pub use crate::engines::postgres::history::{
-    create_history_table, delete_failed_migrations, get_applied_migrations, has_entries,
-    history_table_exists, insert_applied_migration, next_installed_rank, update_checksum,
-    update_repeatable_checksum,
+    create_history_table, delete_failed_migrations, ensure_schema_version, get_applied_migrations,
+    has_entries, history_schema_version, history_table_exists, insert_applied_migration,
+    next_installed_rank, update_checksum, update_checksum_sha256, update_repeatable_checksum,
 };
 
 // ── Dialect-aware dispatchers ────────────────────────────────────────────────
@@ -71,28 +301,99 @@ pub async fn create_history_table_db(client: &DbClient, schema: &str, table: &st
             return Err(e);
         }
     }
-    upgrade_history_table_db(client, schema, table).await?;
+    ensure_schema_version_db(client, schema, table).await?;
     Ok(())
 }
 
-/// Auto-upgrade the history table to add new columns if they don't exist.
-async fn upgrade_history_table_db(client: &DbClient, schema: &str, table: &str) -> Result<()> {
-    let dialect = client.dialect();
-    let fq = dialect.qualified_table(schema, table);
-    let sql = match client.dialect_kind() {
-        crate::dialect::DialectKind::Postgres => {
-            format!("ALTER TABLE {fq} ADD COLUMN IF NOT EXISTS reversal_sql TEXT")
+/// Bring an existing history table up to [`HISTORY_SCHEMA_VERSION`] on
+/// first contact, applying the missing [`HISTORY_UPGRADES`] in version
+/// order (dialect-aware). A table already at (or past) this layout is left
+/// alone. Missing columns that can't be added fail the call; failing only to
+/// record the version is logged.
+pub async fn ensure_schema_version_db(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+) -> Result<HistoryUpgrade> {
+    let mut upgrade = plan_history_upgrade_db(client, schema, table).await?;
+    let result = apply_history_upgrade_db(client, &mut upgrade).await;
+    check_first_contact(&upgrade, result)?;
+    Ok(upgrade)
+}
+
+/// Work out what bringing the history table up to [`HISTORY_SCHEMA_VERSION`]
+/// takes, without changing anything (dialect-aware).
+pub async fn plan_history_upgrade_db(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+) -> Result<HistoryUpgrade> {
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => {
+            crate::engines::postgres::history::plan_history_upgrade(c, schema, table).await
         }
-        crate::dialect::DialectKind::Mysql => {
-            // MySQL 8.0.29+ supports IF NOT EXISTS on ADD COLUMN; older
-            // patch versions error on duplicate column — caller log-ignores.
-            format!("ALTER TABLE {fq} ADD COLUMN IF NOT EXISTS reversal_sql LONGTEXT")
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            crate::engines::mysql::history::plan_history_upgrade(pool, schema, table).await
+        }
+    }
+}
+
+/// Run the statements of a planned upgrade and mark it applied
+/// (dialect-aware).
+pub async fn apply_history_upgrade_db(
+    client: &DbClient,
+    upgrade: &mut HistoryUpgrade,
+) -> Result<()> {
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => {
+            crate::engines::postgres::history::apply_history_upgrade(c, upgrade).await
+        }
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            crate::engines::mysql::history::apply_history_upgrade(pool, upgrade).await
+        }
+    }
+}
+
+/// Bring the history table up to [`HISTORY_SCHEMA_VERSION`] and record the
+/// version in `waypoint_meta` (dialect-aware).
+pub async fn upgrade_history_table_db(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+) -> Result<HistoryUpgrade> {
+    let mut upgrade = plan_history_upgrade_db(client, schema, table).await?;
+    apply_history_upgrade_db(client, &mut upgrade).await?;
+    Ok(upgrade)
+}
+
+/// Decide whether a failed first-contact upgrade should stop the command.
+///
+/// Missing columns are fatal, since later writes need them. Failing only to
+/// record the version (e.g. no `CREATE` privilege on a locked-down schema)
+/// is logged and ignored, so existing deployments keep working.
+pub(crate) fn check_first_contact(upgrade: &HistoryUpgrade, result: Result<()>) -> Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) if upgrade.columns_added.is_empty() => {
+            log::warn!(
+                "Could not record history table version; table={}, error={}",
+                upgrade.table,
+                e
+            );
+            Ok(())
+        }
+        Err(e) => {
+            log::error!(
+                "History table upgrade failed; table={}. Run `waypoint history upgrade --dry-run` to see the statements.",
+                upgrade.table
+            );
+            Err(e)
         }
-    };
-    if let Err(e) = client.execute_raw(&sql).await {
-        log::debug!("History table upgrade (reversal_sql): {}", e);
     }
-    Ok(())
 }
 
 /// Whether an error message indicates a benign "duplicate index/key name"
@@ -294,6 +595,41 @@ pub async fn update_repeatable_checksum_db(
     }
 }
 
+/// Set the SHA-256 checksum on every history row for a script
+/// (dialect-aware).
+pub async fn update_checksum_sha256_db(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+    script: &str,
+    checksum_sha256: &str,
+) -> Result<()> {
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => {
+            crate::engines::postgres::history::update_checksum_sha256(
+                c,
+                schema,
+                table,
+                script,
+                checksum_sha256,
+            )
+            .await
+        }
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            crate::engines::mysql::history::update_checksum_sha256(
+                pool,
+                schema,
+                table,
+                script,
+                checksum_sha256,
+            )
+            .await
+        }
+    }
+}
+
 // ── Engine-agnostic helpers ──────────────────────────────────────────────────
 
 /// Compute the set of versions that are currently effectively applied.
@@ -320,3 +656,83 @@ pub fn effective_applied_versions(
     }
     effective
 }
+
+#[cfg(all(test, feature = "postgres"))]
+mod tests {
+    use super::*;
+    use crate::dialect::postgres::PostgresDialect;
+
+    fn cols(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_upgrade_plan_adds_missing_columns_and_records_version() {
+        let plan = build_upgrade_plan(
+            &PostgresDialect,
+            "public",
+            "waypoint_schema_history",
+            None,
+            &cols(&[
+                "installed_rank",
+                "version",
+                "reversal_sql",
+                "git_commit",
+                "ci_build_url",
+                "hostname",
+                "waypoint_version",
+            ]),
+        );
+        assert_eq!(plan.from_version, None);
+        assert_eq!(plan.to_version, HISTORY_SCHEMA_VERSION);
+        assert_eq!(plan.columns_added, vec!["backup_ref", "checksum_sha256"]);
+        assert_eq!(plan.statements.len(), 4);
+        assert!(plan.statements[0]
+            .starts_with("CREATE TABLE IF NOT EXISTS \"public\".\"waypoint_meta\""));
+        assert_eq!(
+            plan.statements[1],
+            "ALTER TABLE \"public\".\"waypoint_schema_history\" ADD COLUMN \"backup_ref\" TEXT"
+        );
+        assert_eq!(
+            plan.statements[2],
+            "ALTER TABLE \"public\".\"waypoint_schema_history\" ADD COLUMN \"checksum_sha256\" VARCHAR(64)"
+        );
+        assert!(plan.statements[3].contains("VALUES ('waypoint_schema_history', 5)"));
+    }
+
+    #[test]
+    fn test_upgrade_plan_current_and_newer_tables_need_nothing() {
+        let all = cols(&[
+            "reversal_sql",
+            "backup_ref",
+            "checksum_sha256",
+            "git_commit",
+            "ci_build_url",
+            "hostname",
+            "waypoint_version",
+        ]);
+        let current = build_upgrade_plan(
+            &PostgresDialect,
+            "public",
+            "h",
+            Some(HISTORY_SCHEMA_VERSION),
+            &all,
+        );
+        assert!(current.is_current());
+
+        let newer = build_upgrade_plan(
+            &PostgresDialect,
+            "public",
+            "h",
+            Some(HISTORY_SCHEMA_VERSION + 1),
+            &all,
+        );
+        assert!(newer.is_current());
+        assert_eq!(newer.to_version, HISTORY_SCHEMA_VERSION + 1);
+
+        // Columns present but no version row yet: only record the version.
+        let untracked = build_upgrade_plan(&PostgresDialect, "public", "h", None, &all);
+        assert!(untracked.columns_added.is_empty());
+        assert_eq!(untracked.statements.len(), 2);
+    }
+}
@@ -1,18 +1,24 @@
-//! SQL callback hooks that run before/after migrations (Flyway-compatible).
+//! SQL callback hooks that run before/after migrations (Flyway-compatible),
+//! and shell command hooks (`[hooks] before_migrate_cmd` / `after_migrate_cmd`)
+//! for coordination that can't be expressed in SQL.
 
 use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
+use crate::commands::migrate::MigrateDetail;
 use crate::config::HooksConfig;
 #[cfg(feature = "postgres")]
 use crate::db;
 use crate::db::DbClient;
 use crate::error::{Result, WaypointError};
+use crate::migration::ResolvedMigration;
 use crate::placeholder::replace_placeholders;
+use crate::telemetry;
 
 /// The phase at which a hook runs.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -197,10 +203,16 @@ pub async fn run_hooks(
 
         let sql = replace_placeholders(&hook.sql, placeholders)?;
 
-        match db::execute_in_transaction(client, &sql).await {
+        let span = telemetry::hook_span(phase, &hook.script_name);
+        match telemetry::traced(span, db::execute_in_transaction(client, &sql)).await {
             Ok(exec_time) => {
                 total_ms += exec_time;
                 count += 1;
+                crate::events::emit(|| crate::events::MigrateEventKind::HookExecuted {
+                    phase: phase.to_string(),
+                    script: hook.script_name.clone(),
+                    duration_ms: exec_time,
+                });
             }
             Err(e) => {
                 let reason = match &e {
@@ -239,15 +251,24 @@ pub async fn run_hooks_db(
 
         let sql = replace_placeholders(&hook.sql, placeholders)?;
 
-        let exec_result = match client.dialect_kind() {
-            crate::dialect::DialectKind::Postgres => client.execute_in_transaction(&sql).await,
-            crate::dialect::DialectKind::Mysql => client.execute_raw(&sql).await,
-        };
+        let span = telemetry::hook_span(phase, &hook.script_name);
+        let exec_result = telemetry::traced(span, async {
+            match client.dialect_kind() {
+                crate::dialect::DialectKind::Postgres => client.execute_in_transaction(&sql).await,
+                crate::dialect::DialectKind::Mysql => client.execute_raw(&sql).await,
+            }
+        })
+        .await;
 
         match exec_result {
             Ok(exec_time) => {
                 total_ms += exec_time;
                 count += 1;
+                crate::events::emit(|| crate::events::MigrateEventKind::HookExecuted {
+                    phase: phase.to_string(),
+                    script: hook.script_name.clone(),
+                    duration_ms: exec_time,
+                });
             }
             Err(e) => {
                 // Match the legacy `run_hooks` error format: when the cause is
@@ -273,6 +294,118 @@ pub async fn run_hooks_db(
     Ok((count, total_ms))
 }
 
+/// What a hook command is told about the run, through its environment.
+#[derive(Debug, Clone, Default)]
+pub struct CommandHookEnv {
+    /// Database being migrated.
+    pub database: String,
+    /// Schema being migrated.
+    pub schema: String,
+    /// Versions (script names for repeatables) pending when the run started.
+    pub pending: Vec<String>,
+}
+
+impl CommandHookEnv {
+    /// Describe a run of `pending` against `database` / `schema`.
+    pub fn new<'a>(
+        database: &str,
+        schema: &str,
+        pending: impl IntoIterator<Item = &'a ResolvedMigration>,
+    ) -> Self {
+        Self {
+            database: database.to_string(),
+            schema: schema.to_string(),
+            pending: pending
+                .into_iter()
+                .map(|m| match m.version() {
+                    Some(v) => v.raw.clone(),
+                    None => m.script.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Environment variables for a command run at `phase`, after `applied`
+    /// (empty before the run).
+    fn vars(&self, phase: &HookType, applied: &[MigrateDetail]) -> Vec<(&'static str, String)> {
+        let applied: Vec<&str> = applied
+            .iter()
+            .map(|d| d.version.as_deref().unwrap_or(&d.script))
+            .collect();
+        vec![
+            ("WAYPOINT_HOOK", phase.to_string()),
+            ("WAYPOINT_DATABASE", self.database.clone()),
+            ("WAYPOINT_SCHEMA", self.schema.clone()),
+            ("WAYPOINT_PENDING_COUNT", self.pending.len().to_string()),
+            ("WAYPOINT_PENDING_VERSIONS", self.pending.join(",")),
+            ("WAYPOINT_APPLIED_COUNT", applied.len().to_string()),
+            ("WAYPOINT_APPLIED_VERSIONS", applied.join(",")),
+        ]
+    }
+}
+
+/// Run the `[hooks]` commands for `phase` (`before_migrate_cmd` for
+/// `BeforeMigrate`, `after_migrate_cmd` for `AfterMigrate`; the per-migration
+/// phases have none) via `sh -c`, in order. A command that exits non-zero or
+/// outlives `command_timeout` fails the run with `HookFailed`.
+///
+/// Returns `(hook_count, total_ms)`.
+pub async fn run_command_hooks(
+    config: &HooksConfig,
+    phase: &HookType,
+    env: &CommandHookEnv,
+    applied: &[MigrateDetail],
+) -> Result<(usize, i32)> {
+    let commands = match phase {
+        HookType::BeforeMigrate => &config.before_migrate_cmd,
+        HookType::AfterMigrate => &config.after_migrate_cmd,
+        HookType::BeforeEachMigrate | HookType::AfterEachMigrate => return Ok((0, 0)),
+    };
+    let timeout = Duration::from_secs(config.command_timeout_secs);
+    let mut total_ms = 0;
+    let mut count = 0;
+
+    for command in commands {
+        log::info!("Running {} hook command: {}", phase, command);
+
+        let vars = env.vars(phase, applied);
+        let cmd = command.clone();
+        let start = Instant::now();
+        let span = telemetry::hook_span(phase, command);
+        let outcome = telemetry::traced(span, async move {
+            tokio::task::spawn_blocking(move || crate::backup::run_command(&cmd, &vars, timeout))
+                .await
+                .unwrap_or_else(|e| Err(format!("hook task panicked: {}", e)))
+        })
+        .await;
+
+        match outcome {
+            Ok(stdout) => {
+                let exec_time = start.elapsed().as_millis() as i32;
+                for line in stdout.lines().filter(|l| !l.trim().is_empty()) {
+                    log::debug!("Hook command output; command={}, line={}", command, line);
+                }
+                total_ms += exec_time;
+                count += 1;
+                crate::events::emit(|| crate::events::MigrateEventKind::HookExecuted {
+                    phase: phase.to_string(),
+                    script: command.clone(),
+                    duration_ms: exec_time,
+                });
+            }
+            Err(reason) => {
+                return Err(WaypointError::HookFailed {
+                    phase: phase.to_string(),
+                    script: command.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    Ok((count, total_ms))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -356,6 +489,7 @@ mod tests {
             after_migrate: vec![],
             before_each_migrate: vec![],
             after_each_migrate: vec![],
+            ..Default::default()
         };
 
         let hooks = load_config_hooks(&config).unwrap();
@@ -373,8 +507,66 @@ mod tests {
             after_migrate: vec![],
             before_each_migrate: vec![],
             after_each_migrate: vec![],
+            ..Default::default()
         };
 
         assert!(load_config_hooks(&config).is_err());
     }
+
+    #[tokio::test]
+    async fn test_run_command_hooks_env_and_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("env.txt");
+        let config = HooksConfig {
+            before_migrate_cmd: vec![format!(
+                "echo \"$WAYPOINT_HOOK $WAYPOINT_DATABASE $WAYPOINT_PENDING_COUNT $WAYPOINT_PENDING_VERSIONS\" > {}",
+                out.display()
+            )],
+            after_migrate_cmd: vec![
+                format!(
+                    "echo \"$WAYPOINT_HOOK $WAYPOINT_APPLIED_VERSIONS\" >> {}",
+                    out.display()
+                ),
+                "echo nope >&2; exit 2".to_string(),
+            ],
+            ..Default::default()
+        };
+        let env = CommandHookEnv {
+            database: "app".into(),
+            schema: "public".into(),
+            pending: vec!["1".into(), "R__views.sql".into()],
+        };
+
+        let (count, _) = run_command_hooks(&config, &HookType::BeforeMigrate, &env, &[])
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        let (count, _) = run_command_hooks(&config, &HookType::BeforeEachMigrate, &env, &[])
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let applied = [MigrateDetail {
+            version: Some("1".into()),
+            description: "Init".into(),
+            script: "V1__Init.sql".into(),
+            execution_time_ms: 1,
+            rows_affected: 0,
+            schema: None,
+        }];
+        let err = run_command_hooks(&config, &HookType::AfterMigrate, &env, &applied)
+            .await
+            .unwrap_err();
+        match err {
+            WaypointError::HookFailed { phase, reason, .. } => {
+                assert_eq!(phase, "afterMigrate");
+                assert!(reason.contains("nope"), "{}", reason);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "beforeMigrate app 2 1,R__views.sql\nafterMigrate 1\n"
+        );
+    }
 }
@@ -0,0 +1,261 @@
+//! Time limits for catalog queries.
+//!
+//! Introspection, diff and advisor queries can run for a long time on
+//! catalogs with hundreds of thousands of objects. `[database]
+//! introspection_timeout` (or `--introspection-timeout`) bounds each of those
+//! queries, separately from `statement_timeout`, which governs migrations.
+//!
+//! The limit is task-local: the library façade installs it with [`scope`]
+//! around `drift`, `diff`, `advise` and `find`, so introspection code doesn't
+//! thread it through every call. On PostgreSQL, [`limited`] applies it as the
+//! session's `statement_timeout` for the duration of one introspection, so a
+//! query that runs too long is cancelled on the server rather than abandoned.
+//! A category whose query was cancelled comes back empty and is named in the
+//! result's `incomplete` list; reports show partial results instead of
+//! failing. MySQL introspection is not limited.
+//!
+//! Whether objects created by `CREATE EXTENSION` appear in snapshots is
+//! task-local in the same way: they are left out unless
+//! [`with_extension_objects`] says otherwise (`[database]
+//! include_extension_objects`).
+//!
+//! So is the [`ObjectFilter`] built from `[database] include_objects` /
+//! `exclude_objects` (`--include` / `--exclude`): glob patterns on object
+//! names that every snapshot taken inside [`with_object_filter`] is narrowed
+//! by, so drift, diff, advisor, snapshot and clean skip the same noisy
+//! objects.
+
+use std::future::Future;
+use std::sync::Mutex;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+use crate::error::{Result, WaypointError};
+
+tokio::task_local! {
+    static TIMEOUT_SECS: u32;
+    static INCLUDE_EXTENSION_OBJECTS: bool;
+    static OBJECT_FILTER: ObjectFilter;
+}
+
+/// Include/exclude glob patterns on object names. `*` matches any run of
+/// characters and `?` any single one; matching is case-sensitive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjectFilter {
+    /// If non-empty, only objects matching one of these are kept.
+    pub include: Vec<String>,
+    /// Objects matching any of these are dropped, even if included.
+    pub exclude: Vec<String>,
+}
+
+impl ObjectFilter {
+    /// Whether the filter keeps everything.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether an object called `name` passes the filter.
+    pub fn allows(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| glob_match(p, name)))
+            && !self.exclude.iter().any(|p| glob_match(p, name))
+    }
+}
+
+/// Match `name` against a `*` / `?` glob.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, tried)) => {
+                    p = after;
+                    n = tried + 1;
+                    star = Some((after, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Run `fut` with catalog queries limited to `secs` seconds each (0 means
+/// no limit).
+pub async fn scope<F: Future>(secs: u32, fut: F) -> F::Output {
+    TIMEOUT_SECS.scope(secs, fut).await
+}
+
+/// The limit installed by [`scope`], or 0 when there is none.
+pub fn timeout_secs() -> u32 {
+    TIMEOUT_SECS.try_with(|secs| *secs).unwrap_or(0)
+}
+
+/// Run `fut` with extension members included in snapshots (`include`) or
+/// left out.
+pub async fn with_extension_objects<F: Future>(include: bool, fut: F) -> F::Output {
+    INCLUDE_EXTENSION_OBJECTS.scope(include, fut).await
+}
+
+/// Whether snapshots include objects that belong to an extension; false
+/// outside [`with_extension_objects`].
+pub fn include_extension_objects() -> bool {
+    INCLUDE_EXTENSION_OBJECTS
+        .try_with(|include| *include)
+        .unwrap_or(false)
+}
+
+/// Run `fut` with snapshots narrowed by `filter`.
+pub async fn with_object_filter<F: Future>(filter: ObjectFilter, fut: F) -> F::Output {
+    OBJECT_FILTER.scope(filter, fut).await
+}
+
+/// The filter installed by [`with_object_filter`]; empty outside it.
+pub fn object_filter() -> ObjectFilter {
+    OBJECT_FILTER
+        .try_with(ObjectFilter::clone)
+        .unwrap_or_default()
+}
+
+/// Whether `e` is a query the server cancelled.
+pub fn is_timeout(e: &WaypointError) -> bool {
+    match e {
+        #[cfg(feature = "postgres")]
+        WaypointError::DatabaseError(pg) => {
+            pg.code() == Some(&tokio_postgres::error::SqlState::QUERY_CANCELED)
+        }
+        _ => false,
+    }
+}
+
+/// Run `fut` on `client` with the session's `statement_timeout` set to the
+/// current limit, restoring the previous value afterwards. Without a limit,
+/// just runs `fut`.
+#[cfg(feature = "postgres")]
+pub async fn limited<T>(client: &Client, fut: impl Future<Output = Result<T>>) -> Result<T> {
+    let secs = timeout_secs();
+    if secs == 0 {
+        return fut.await;
+    }
+    let previous: String = client
+        .query_one("SHOW statement_timeout", &[])
+        .await?
+        .get(0);
+    client
+        .batch_execute(&format!("SET statement_timeout = '{}s'", secs))
+        .await?;
+    let result = fut.await;
+    if let Err(e) = client
+        .batch_execute(&format!(
+            "SET statement_timeout = '{}'",
+            previous.replace('\'', "''")
+        ))
+        .await
+    {
+        log::debug!("Could not restore statement_timeout; error={}", e);
+    }
+    result
+}
+
+/// Treat a timed-out category as empty, adding `what` to `incomplete`.
+/// Other errors, and timeouts when no limit is installed, are returned.
+pub async fn tolerate<T>(
+    incomplete: &Mutex<Vec<String>>,
+    what: &str,
+    fut: impl Future<Output = Result<Vec<T>>>,
+) -> Result<Vec<T>> {
+    match fut.await {
+        Err(e) if timeout_secs() > 0 && is_timeout(&e) => {
+            log::warn!(
+                "Introspection query timed out, result is partial; objects={}, timeout_secs={}",
+                what,
+                timeout_secs()
+            );
+            incomplete
+                .lock()
+                .expect("incomplete lock")
+                .push(what.to_string());
+            Ok(Vec::new())
+        }
+        other => other,
+    }
+}
+
+/// Categories in `incomplete`, in a stable order.
+pub fn into_sorted(incomplete: Mutex<Vec<String>>) -> Vec<String> {
+    let mut list = incomplete.into_inner().expect("incomplete lock");
+    list.sort();
+    list.dedup();
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_installs_timeout() {
+        assert_eq!(timeout_secs(), 0);
+        let inside = scope(15, async { timeout_secs() }).await;
+        assert_eq!(inside, 15);
+        assert_eq!(timeout_secs(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tolerate_passes_through_without_timeout() {
+        let incomplete = Mutex::new(Vec::new());
+        let ok = tolerate(&incomplete, "tables", async { Ok(vec![1, 2]) })
+            .await
+            .unwrap();
+        assert_eq!(ok, vec![1, 2]);
+        let err = scope(5, async {
+            tolerate::<i32>(&incomplete, "views", async {
+                Err(WaypointError::ConfigError("boom".into()))
+            })
+            .await
+        })
+        .await;
+        assert!(err.is_err());
+        assert!(into_sorted(incomplete).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_object_filter() {
+        let filter = ObjectFilter {
+            include: vec!["app_*".into(), "users".into()],
+            exclude: vec!["*_tmp".into(), "app_?".into()],
+        };
+        assert!(filter.allows("app_orders"));
+        assert!(filter.allows("users"));
+        assert!(!filter.allows("audit_log"));
+        assert!(!filter.allows("app_orders_tmp"));
+        assert!(!filter.allows("app_x"));
+        assert!(filter.allows("app_xy"));
+
+        let exclude_only = ObjectFilter {
+            include: vec![],
+            exclude: vec!["tmp_*".into()],
+        };
+        assert!(exclude_only.allows("orders"));
+        assert!(!exclude_only.allows("tmp_"));
+        assert!(ObjectFilter::default().allows("anything"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+
+        assert!(object_filter().is_empty());
+        let inside = with_object_filter(exclude_only.clone(), async { object_filter() }).await;
+        assert_eq!(inside, exclude_only);
+    }
+}
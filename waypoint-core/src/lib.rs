@@ -11,7 +11,7 @@
 //! use waypoint_core::Waypoint;
 //!
 //! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
-//! let config = WaypointConfig::load(None, None)?;
+//! let config = WaypointConfig::load(None, &Default::default())?;
 //! let wp = Waypoint::new(config).await?;
 //! let report = wp.migrate(None).await?;
 //! println!("Applied {} migrations", report.migrations_applied);
@@ -24,45 +24,86 @@
 //! - [`config`] — Configuration loading (TOML, env vars, CLI overrides)
 //! - [`dialect`] — Engine-specific dialect (Postgres / MySQL) abstraction
 //! - [`migration`] — Migration file parsing and scanning
+//! - [`git_location`] — `git+<url>#<ref>:<path>` locations fetched to temp dirs
 //! - [`db`] — Database connections, TLS, advisory locks
 //! - [`history`] — Schema history table operations
+//! - [`audit`] — Git commit, CI build and hostname recorded per migration
 //! - [`commands`] — Individual command implementations
 //! - [`checksum`] — CRC32 checksums (Flyway-compatible)
 //! - [`placeholder`] — `${key}` placeholder replacement in SQL
 //! - [`hooks`] — SQL callback hooks (before/after migrate)
+//! - [`backup`] — External backup command before destructive migrations
+//! - [`policy`] — Per-command `[policy]` (allowed / require_override / never)
 //! - [`directive`] — `-- waypoint:*` comment directive parsing
 //! - [`guard`] — Guard expression parser and evaluator for pre/post conditions
 //! - [`sql_parser`] — Regex-based DDL extraction
 //! - [`safety`] — Migration safety analysis (lock levels, impact, verdicts)
 //! - [`schema`] — Schema introspection + diff
+//! - [`introspection`] — Per-query time limits for catalog queries
 //! - [`dependency`] — Migration dependency graph
+//! - [`provenance`] — Which migration defined each schema object
 //! - [`preflight`] — Pre-migration health checks
+//! - [`prerequisites`] — Auto-created schemas and extensions
+//! - [`progress`] — Per-statement migration progress and resume
+//! - [`receipt`] — Signed execution receipts for migrate runs
 //! - [`multi`] — Multi-database orchestration
+//! - [`notify`] — `NOTIFY` after migrate for fleet coordination
+//! - [`parallel`] — Wave planning for `parallel_migrations`
+//! - [`wal`] — WAL volume estimation and headroom check
+//! - [`warning`] — Structured warnings attached to reports
+//! - [`code_migration`] — Migrations implemented in Rust (PostgreSQL)
+//! - [`embedded`] — Migration files compiled into the binary
 //! - [`error`] — Error types
 
 pub mod advisor;
+pub mod audit;
+pub mod backup;
 pub mod checksum;
+#[cfg(feature = "postgres")]
+pub mod code_migration;
 pub mod commands;
 pub mod config;
 pub mod db;
 pub mod dependency;
 pub mod dialect;
 pub mod directive;
+pub mod drift_baseline;
+pub mod embedded;
 pub mod engines;
 pub mod error;
+pub mod events;
+pub mod git_location;
 pub mod guard;
 pub mod history;
 pub mod hooks;
+pub mod introspection;
+pub mod location;
 pub mod migration;
 pub mod multi;
+pub mod notify;
+pub mod parallel;
 pub mod placeholder;
+pub mod policy;
 pub mod preflight;
+pub mod prerequisites;
+pub mod progress;
+pub mod provenance;
+pub mod receipt;
+pub mod redact;
 pub mod reversal;
 pub mod safety;
 pub mod schema;
+pub mod schema_sql;
 pub mod sql_parser;
+mod telemetry;
+pub mod templates;
+pub mod wal;
+pub mod warning;
 
-use std::path::PathBuf;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
 
 use config::WaypointConfig;
 use db::DbClient;
@@ -71,26 +112,59 @@ use error::Result;
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
+// `embed_migrations!` expands to `::waypoint_core::...` paths.
+extern crate self as waypoint_core;
+
 pub use advisor::AdvisorReport;
+pub use commands::assist::{
+    DefaultAssistReport, EnumAssistReport, EnumChangeOptions, ForeignKeyAssistReport,
+    UniqueAssistReport,
+};
+pub use commands::audit_history::{FindingKind, FindingSeverity, HistoryAuditReport};
 pub use commands::changelog::ChangelogReport;
+pub use commands::check::{CheckReport, CheckResult, Expect};
 pub use commands::check_conflicts::ConflictReport;
+pub use commands::checksums::{ChecksumUpdateOptions, ChecksumUpdateReport};
 pub use commands::diff::DiffReport;
 pub use commands::drift::DriftReport;
 pub use commands::explain::ExplainReport;
-pub use commands::info::{MigrationInfo, MigrationState};
+pub use commands::export::{ExportFormat, ExportOptions, ExportReport};
+pub use commands::find::{FindQuery, FindReport, ObjectType};
+pub use commands::fingerprint::FingerprintReport;
+pub use commands::guard_check::GuardCheckReport;
+pub use commands::import_flyway::ImportFlywayReport;
+pub use commands::info::{InfoFilter, MigrationInfo, MigrationState};
+pub use commands::init::{InitOptions, InitReport};
 pub use commands::lint::LintReport;
-pub use commands::migrate::MigrateReport;
+pub use commands::listen::ListenEvent;
+pub use commands::metrics::MetricsSummary;
+pub use commands::migrate::{ConvergeReport, MigrateReport};
+pub use commands::new_migration::{MigrationTemplate, NewMigrationOptions, NewMigrationReport};
+pub use commands::pending_report::{PendingMigration, PendingSummary};
+pub use commands::plan::{MigrationPlan, PlannedMigration};
+pub use commands::rebase::{RebaseOptions, RebaseReport};
+pub use commands::reconcile::ReconcileReport;
 pub use commands::repair::RepairReport;
 pub use commands::safety::SafetyCommandReport;
+pub use commands::schedule::{RunScheduledReport, ScheduledRun};
+pub use commands::seed::{SeedReport, SeedStatus};
 pub use commands::simulate::SimulationReport;
-pub use commands::snapshot::{RestoreReport, SnapshotReport};
-pub use commands::undo::{UndoReport, UndoTarget};
+pub use commands::sla_check::{PendingAge, SlaCheckReport};
+pub use commands::snapshot::{RestoreReport, SnapshotDiffReport, SnapshotReport};
+pub use commands::squash::{SquashOptions, SquashReport};
+pub use commands::timeline::{HistoryTimeline, TimelineEventKind};
+pub use commands::undo::{UndoPlan, UndoReport, UndoStep, UndoTarget};
 pub use commands::validate::ValidateReport;
 pub use config::CliOverrides;
 pub use dialect::{DatabaseDialect, DialectKind};
+pub use events::{MigrateEvent, MigrateEventKind};
+pub use history::HistoryUpgrade;
 pub use multi::MultiWaypoint;
 pub use preflight::PreflightReport;
 pub use safety::SafetyReport;
+pub use schema::{Introspector, SchemaDiff, SchemaSnapshot};
+pub use warning::{Warning, WarningCode};
+pub use waypoint_macros::embed_migrations;
 
 /// Main entry point for the Waypoint library.
 ///
@@ -98,19 +172,25 @@ pub use safety::SafetyReport;
 /// run migration commands programmatically.
 pub struct Waypoint {
     pub config: WaypointConfig,
-    client: DbClient,
+    client: Arc<DbClient>,
+    /// Connection opened after `client` was lost during a read-only command;
+    /// every later command runs on it.
+    reconnected: std::sync::Mutex<Option<Arc<DbClient>>>,
 }
 
+/// Future returned by a read-only command run through [`Waypoint::read_only`].
+type ReadOnlyFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
 impl Waypoint {
     /// Create a new Waypoint instance, connecting to the database.
     ///
     /// Engine is auto-detected from the configured connection URL scheme
-    /// (`postgres://` / `postgresql://` → PostgreSQL, `mysql://` → MySQL).
+    /// (`postgres://` / `postgresql://` → PostgreSQL, `mysql://` / `mariadb://` → MySQL).
     /// If `connect_retries` is configured, retries with exponential backoff.
     pub async fn new(config: WaypointConfig) -> Result<Self> {
         let conn_string = config.connection_string()?;
         let client = connect_for_url(&conn_string, &config).await?;
-        Ok(Self { config, client })
+        Ok(Self::with_db_client(config, client))
     }
 
     /// Create a new Waypoint instance with an existing PostgreSQL client.
@@ -119,18 +199,126 @@ impl Waypoint {
     /// code or for MySQL connections, use [`Self::with_db_client`].
     #[cfg(feature = "postgres")]
     pub fn with_client(config: WaypointConfig, client: Client) -> Self {
+        Self::with_db_client(config, DbClient::with_postgres(client))
+    }
+
+    /// Create a new Waypoint instance on a connection checked out of a
+    /// PostgreSQL pool, for services that already manage one. The connection
+    /// is held until the `Waypoint` is dropped.
+    #[cfg(feature = "postgres")]
+    pub async fn with_pool(config: WaypointConfig, pool: &dyn db::PostgresPool) -> Result<Self> {
+        let client = DbClient::from_pool(pool).await?;
+        Ok(Self::with_db_client(config, client))
+    }
+
+    /// Create a new Waypoint instance with an already-constructed [`DbClient`].
+    pub fn with_db_client(config: WaypointConfig, client: DbClient) -> Self {
         Self {
             config,
-            client: DbClient::with_postgres(client),
+            client: Arc::new(client),
+            reconnected: std::sync::Mutex::new(None),
         }
     }
 
-    /// Create a new Waypoint instance with an already-constructed [`DbClient`].
-    pub fn with_db_client(config: WaypointConfig, client: DbClient) -> Self {
-        Self { config, client }
+    /// Enforce `[policy]` for a library call; `require_override` commands
+    /// run only with `config.policy.overridden` set.
+    fn check_policy(&self, command: &str) -> Result<()> {
+        self.config.policy.check(command)
+    }
+
+    /// Refuse `command` on a read-only standby before it touches anything;
+    /// see [`DbClient::check_standby`]. Returns whether a read-only command
+    /// is running on a standby.
+    async fn check_standby(&self, command: &str) -> Result<bool> {
+        self.current()
+            .check_standby(command, &self.config.migrations)
+            .await
+    }
+
+    /// Run an idempotent read-only command. If the connection is lost while
+    /// it runs, reconnect with the configured URL, take the migration locks
+    /// the old session held again, and run it once more; if that isn't
+    /// possible, the error is [`error::WaypointError::ConnectionLost`] with
+    /// the cause. Connection settings such as `statement_timeout` are
+    /// re-applied by the new connection; commands set `search_path`
+    /// themselves.
+    async fn read_only<T, F>(&self, command: &str, run: F) -> Result<T>
+    where
+        F: for<'c> Fn(&'c DbClient, &'c WaypointConfig) -> ReadOnlyFuture<'c, T>,
+    {
+        let client = self.current();
+        let lost = match run(&client, &self.config).await {
+            Ok(value) => return Ok(value),
+            Err(e) => client.connection_error(command, e),
+        };
+        let error::WaypointError::ConnectionLost { detail, .. } = &lost else {
+            return Err(lost);
+        };
+        log::warn!(
+            "Connection lost, reconnecting; command={}, cause={}",
+            command,
+            detail
+        );
+        let fresh = match self.reconnect(&client).await {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                log::warn!("Reconnect failed; command={}, error={}", command, e);
+                return Err(lost);
+            }
+        };
+        run(&fresh, &self.config)
+            .await
+            .map_err(|e| fresh.connection_error(command, e))
+    }
+
+    /// Run `fut` with `[database] introspection_timeout` limiting each
+    /// catalog query, `include_extension_objects` deciding whether
+    /// extension members are introspected and `include_objects` /
+    /// `exclude_objects` narrowing snapshots; see [`introspection`].
+    async fn introspecting<F: Future>(&self, fut: F) -> F::Output {
+        introspection::scope(
+            self.config.database.introspection_timeout_secs,
+            introspection::with_extension_objects(
+                self.config.database.include_extension_objects,
+                introspection::with_object_filter(self.config.database.object_filter(), fut),
+            ),
+        )
+        .await
+    }
+
+    /// Run `fut` with `[guards] targets` available to the `remote_*` guard
+    /// builtins; see [`guard::with_targets`].
+    async fn guarding<F: Future>(&self, fut: F) -> F::Output {
+        guard::with_targets(self.config.guards.targets.clone(), fut).await
+    }
+
+    /// The connection commands run on: the replacement opened by the last
+    /// reconnect, if any, otherwise the original.
+    fn current(&self) -> Arc<DbClient> {
+        let replaced = self.reconnected.lock().expect("reconnect lock").clone();
+        replaced.unwrap_or_else(|| Arc::clone(&self.client))
+    }
+
+    /// Open a replacement for the lost connection `old`, take its migration
+    /// locks again and make it the connection every later command runs on.
+    /// Only read-only commands reconnect and retry; one that writes fails
+    /// with [`error::WaypointError::ConnectionLost`] instead.
+    async fn reconnect(&self, old: &DbClient) -> Result<Arc<DbClient>> {
+        let conn_string = self.config.connection_string()?;
+        let fresh = connect_for_url(&conn_string, &self.config).await?;
+        for lock in old.held_locks() {
+            fresh.acquire_lock(&lock).await?;
+        }
+        let fresh = Arc::new(fresh);
+        *self.reconnected.lock().expect("reconnect lock") = Some(Arc::clone(&fresh));
+        log::info!("Reconnected to the database");
+        Ok(fresh)
     }
 
     /// Get a reference to the underlying database client.
+    ///
+    /// This is the connection the `Waypoint` was created with. After a
+    /// read-only command reconnects, commands run on the replacement instead.
     pub fn client(&self) -> &DbClient {
         &self.client
     }
@@ -153,95 +341,549 @@ impl Waypoint {
     /// Apply pending migrations with the additional `force` flag for
     /// overriding DANGER safety verdicts (PostgreSQL only; MySQL safety
     /// analysis does not currently gate migrations).
+    ///
+    /// With `[migrations] schemas`, each schema is migrated in turn, in the
+    /// listed order, and the run stops at the first schema that fails.
     pub async fn migrate_with_options(
         &self,
         target_version: Option<&str>,
         force: bool,
     ) -> Result<MigrateReport> {
-        match self.client.dialect_kind() {
-            #[cfg(feature = "postgres")]
-            DialectKind::Postgres => {
-                commands::migrate::execute_with_options(
-                    self.client.as_postgres()?,
-                    &self.config,
-                    target_version,
-                    force,
-                )
-                .await
-            }
-            #[cfg(not(feature = "postgres"))]
-            DialectKind::Postgres => Err(error::WaypointError::ConfigError(
-                "PostgreSQL support is not compiled in (enable the `postgres` feature)".into(),
-            )),
-            #[cfg(feature = "mysql")]
-            DialectKind::Mysql => {
-                commands::migrate::execute_mysql_with_options(
-                    &self.client,
-                    &self.config,
-                    target_version,
-                    force,
-                )
-                .await
-            }
-            #[cfg(not(feature = "mysql"))]
-            DialectKind::Mysql => Err(error::WaypointError::ConfigError(
-                "MySQL support is not compiled in (enable the `mysql` feature)".into(),
-            )),
+        self.check_policy("migrate")?;
+        self.check_standby("migrate").await?;
+        if force && self.config.mode.strict {
+            return Err(error::WaypointError::ConfigError(
+                "--force is not allowed in strict mode".to_string(),
+            ));
+        }
+        // Boxed: the engine futures are deep enough to overflow the
+        // compiler's layout depth limit when nested in the schema loop.
+        if self.config.migrations.schemas.is_empty() {
+            return Box::pin(self.migrate_schema(&self.config, target_version, force)).await;
+        }
+
+        let mut report = MigrateReport::default();
+        for schema in self.config.target_schemas() {
+            let config = self.config.for_schema(&schema);
+            let schema_report =
+                Box::pin(self.migrate_schema(&config, target_version, force)).await?;
+            report.merge_schema(&schema, schema_report);
         }
+        Ok(report)
     }
 
-    /// Show migration status information.
+    /// Migrate the single schema `config` points at, then issue its receipt.
+    async fn migrate_schema(
+        &self,
+        config: &WaypointConfig,
+        target_version: Option<&str>,
+        force: bool,
+    ) -> Result<MigrateReport> {
+        let started_at = chrono::Utc::now();
+        let client = self.current();
+        let mut report = self
+            .guarding(async {
+                match client.dialect_kind() {
+                    #[cfg(feature = "postgres")]
+                    DialectKind::Postgres => {
+                        commands::migrate::execute_with_options(
+                            client.as_postgres()?,
+                            config,
+                            target_version,
+                            force,
+                        )
+                        .await
+                    }
+                    #[cfg(not(feature = "postgres"))]
+                    DialectKind::Postgres => Err(error::WaypointError::ConfigError(
+                        "PostgreSQL support is not compiled in (enable the `postgres` feature)"
+                            .into(),
+                    )),
+                    #[cfg(feature = "mysql")]
+                    DialectKind::Mysql => {
+                        commands::migrate::execute_mysql_with_options(
+                            &client,
+                            config,
+                            target_version,
+                            force,
+                        )
+                        .await
+                    }
+                    #[cfg(not(feature = "mysql"))]
+                    DialectKind::Mysql => Err(error::WaypointError::ConfigError(
+                        "MySQL support is not compiled in (enable the `mysql` feature)".into(),
+                    )),
+                }
+            })
+            .await
+            .map_err(|e| client.connection_error("migrate", e))?;
+        receipt::issue(&client, config, &mut report, started_at).await;
+        drift_baseline::record(&client, config, &mut report).await;
+        commands::check::after_migrate(&client, config, &mut report).await;
+        Ok(report)
+    }
+
+    /// Apply pending migrations like [`migrate_with_options`](Self::migrate_with_options),
+    /// calling `observer` with a [`MigrateEvent`] as each step happens:
+    /// migrations started and completed, statements executed, hooks run,
+    /// guards evaluated, safety verdicts, and a final `migrate_finished` or
+    /// `migrate_failed`.
+    pub async fn migrate_with_observer(
+        &self,
+        target_version: Option<&str>,
+        force: bool,
+        observer: impl Fn(&MigrateEvent) + Send + Sync + 'static,
+    ) -> Result<MigrateReport> {
+        events::observe(Arc::new(observer), async {
+            events::emit(|| MigrateEventKind::MigrateStarted {
+                schema: self.config.migrations.schema.clone(),
+            });
+            let result = self.migrate_with_options(target_version, force).await;
+            match &result {
+                Ok(report) => events::emit(|| MigrateEventKind::MigrateFinished {
+                    migrations_applied: report.migrations_applied,
+                    total_time_ms: report.total_time_ms,
+                    hooks_executed: report.hooks_executed,
+                    warnings: report.warnings.clone(),
+                }),
+                Err(e) => events::emit(|| MigrateEventKind::MigrateFailed {
+                    error: e.to_string(),
+                }),
+            }
+            result
+        })
+        .await
+    }
+
+    /// Show migration status information. With `[migrations] schemas`,
+    /// rows for each schema follow one another and carry their `schema`.
     pub async fn info(&self) -> Result<Vec<MigrationInfo>> {
-        commands::info::execute_db(&self.client, &self.config).await
+        self.check_policy("info")?;
+        self.read_only("info", |client, config| {
+            Box::pin(async move {
+                client.check_standby("info", &config.migrations).await?;
+                if config.migrations.schemas.is_empty() {
+                    return commands::info::execute_db(client, config).await;
+                }
+
+                let mut infos = Vec::new();
+                for schema in config.target_schemas() {
+                    let schema_config = config.for_schema(&schema);
+                    let schema_infos = commands::info::execute_db(client, &schema_config).await?;
+                    infos.extend(schema_infos.into_iter().map(|info| MigrationInfo {
+                        schema: Some(schema.clone()),
+                        ..info
+                    }));
+                }
+                Ok(infos)
+            })
+        })
+        .await
+    }
+
+    /// [`info`](Self::info), keeping only the rows `filter` selects.
+    pub async fn info_filtered(&self, filter: &InfoFilter) -> Result<Vec<MigrationInfo>> {
+        Ok(filter.apply(self.info().await?))
+    }
+
+    /// Reconstruct how each version reached its current state from the
+    /// history rows: applies, failures, undos, baselines and checksum
+    /// updates in order, plus the undo stack (`info --history`).
+    pub async fn info_history(&self) -> Result<HistoryTimeline> {
+        self.check_policy("info")?;
+        self.read_only("info", |client, config| {
+            Box::pin(async move {
+                client.check_standby("info", &config.migrations).await?;
+                commands::timeline::execute_db(client, config).await
+            })
+        })
+        .await
     }
 
     /// Validate applied migrations against local files.
     pub async fn validate(&self) -> Result<ValidateReport> {
-        commands::validate::execute_db(&self.client, &self.config).await
+        self.check_policy("validate")?;
+        self.read_only("validate", |client, config| {
+            Box::pin(async move {
+                client.check_standby("validate", &config.migrations).await?;
+                commands::validate::execute_db(client, config).await
+            })
+        })
+        .await
+    }
+
+    /// Cross-check every history row against the migration files.
+    pub async fn audit_history(&self) -> Result<HistoryAuditReport> {
+        self.check_policy("audit")?;
+        self.read_only("audit", |client, config| {
+            Box::pin(commands::audit_history::execute_db(client, config))
+        })
+        .await
     }
 
     /// Repair the schema history table.
     pub async fn repair(&self) -> Result<RepairReport> {
-        commands::repair::execute_db(&self.client, &self.config).await
+        self.check_policy("repair")?;
+        self.check_standby("repair").await?;
+        commands::repair::execute_db(&self.current(), &self.config).await
+    }
+
+    /// Record current file checksums for the given versions, with an audit
+    /// note (PostgreSQL only). Requires `options.force` when
+    /// `migrations.environment` is `production`.
+    pub async fn update_checksums(
+        &self,
+        options: &ChecksumUpdateOptions,
+    ) -> Result<ChecksumUpdateReport> {
+        self.check_policy("checksums")?;
+        self.check_standby("checksums").await?;
+        commands::checksums::update_db(&self.current(), &self.config, options).await
+    }
+
+    /// Bring the history table to the current layout, recording the version
+    /// in `waypoint_meta`. With `dry_run`, only report the statements.
+    pub async fn upgrade_history(&self, dry_run: bool) -> Result<HistoryUpgrade> {
+        self.check_policy("history")?;
+        self.check_standby("history").await?;
+        commands::history_upgrade::execute_db(&self.current(), &self.config, dry_run).await
+    }
+
+    /// Record pending migrations whose effects are already in the database
+    /// (applied outside waypoint) as `RECONCILED`. With `dry_run`, only report.
+    pub async fn reconcile(&self, dry_run: bool) -> Result<ReconcileReport> {
+        self.check_policy("reconcile")?;
+        if !dry_run {
+            self.check_standby("reconcile").await?;
+        }
+        self.guarding(commands::reconcile::execute_db(
+            &self.current(),
+            &self.config,
+            dry_run,
+        ))
+        .await
+    }
+
+    /// Apply seed files (`S__*.sql`) that are new or changed, recording them
+    /// in the seed history table (PostgreSQL only). With `dry_run`, only
+    /// report; `reapply` runs unchanged seeds too.
+    pub async fn seed(&self, dry_run: bool, reapply: bool) -> Result<SeedReport> {
+        self.check_policy("seed")?;
+        if !dry_run {
+            self.check_standby("seed").await?;
+        }
+        self.guarding(commands::seed::execute_db(
+            &self.current(),
+            &self.config,
+            dry_run,
+            reapply,
+        ))
+        .await
+    }
+
+    /// Copy a Flyway history table (`source_table`, in the configured
+    /// schema) into an empty waypoint history table. Stops before writing if
+    /// a local file's checksum differs from Flyway's, unless `force`. With
+    /// `dry_run`, only report.
+    pub async fn import_flyway(
+        &self,
+        source_table: &str,
+        force: bool,
+        dry_run: bool,
+    ) -> Result<ImportFlywayReport> {
+        self.check_policy("import-flyway")?;
+        if !dry_run {
+            self.check_standby("import-flyway").await?;
+        }
+        commands::import_flyway::execute_db(
+            &self.current(),
+            &self.config,
+            source_table,
+            force,
+            dry_run,
+        )
+        .await
+    }
+
+    /// Listen for migrate notifications (`[notify]`) on a dedicated
+    /// connection, calling `on_event` for each (PostgreSQL only). Stops after
+    /// `count` notifications or `timeout_secs`; returns how many arrived.
+    pub async fn listen(
+        &self,
+        channel: Option<&str>,
+        count: Option<usize>,
+        timeout_secs: Option<u64>,
+        on_event: impl FnMut(&ListenEvent),
+    ) -> Result<usize> {
+        self.check_policy("listen")?;
+        commands::listen::execute_db(
+            &self.current(),
+            &self.config,
+            channel,
+            count,
+            timeout_secs,
+            on_event,
+        )
+        .await
     }
 
     /// Baseline an existing database.
     pub async fn baseline(&self, version: Option<&str>, description: Option<&str>) -> Result<()> {
-        commands::baseline::execute_db(&self.client, &self.config, version, description).await
+        self.check_policy("baseline")?;
+        self.check_standby("baseline").await?;
+        commands::baseline::execute_db(&self.current(), &self.config, version, description).await
     }
 
     /// Undo applied migrations.
     pub async fn undo(&self, target: UndoTarget) -> Result<UndoReport> {
-        commands::undo::execute_db(&self.client, &self.config, target).await
+        self.check_policy("undo")?;
+        self.check_standby("undo").await?;
+        commands::undo::execute_db(&self.current(), &self.config, target).await
+    }
+
+    /// Show which versions [`undo`](Self::undo) would undo and the SQL it
+    /// would run, without changing the database.
+    pub async fn undo_plan(&self, target: UndoTarget) -> Result<UndoPlan> {
+        self.check_policy("undo")?;
+        self.read_only("undo", |client, config| {
+            Box::pin(commands::undo::plan_db(client, config, target.clone()))
+        })
+        .await
     }
 
     /// Drop all objects in managed schemas.
     pub async fn clean(&self, allow_clean: bool) -> Result<Vec<String>> {
-        commands::clean::execute_db(&self.client, &self.config, allow_clean).await
+        self.check_policy("clean")?;
+        self.check_standby("clean").await?;
+        commands::clean::execute_db(&self.current(), &self.config, allow_clean).await
+    }
+
+    /// Scaffold a new migration file, numbered after both the migration
+    /// files and the versions recorded in the history table.
+    pub async fn new_migration(&self, options: &NewMigrationOptions) -> Result<NewMigrationReport> {
+        self.check_policy("new")?;
+        let applied = self.applied_versions().await?;
+        commands::new_migration::execute(&self.config, options, &applied)
+    }
+
+    /// Versions recorded in the history table (none if it doesn't exist yet).
+    async fn applied_versions(&self) -> Result<Vec<String>> {
+        let schema = self
+            .current()
+            .resolve_schema(&self.config.migrations.schema)
+            .await?;
+        let table = &self.config.migrations.table;
+        if !history::history_table_exists_db(&self.current(), &schema, table).await? {
+            return Ok(Vec::new());
+        }
+        Ok(
+            history::get_applied_migrations_db(&self.current(), &schema, table)
+                .await?
+                .into_iter()
+                .filter_map(|m| m.version)
+                .collect(),
+        )
     }
 
     /// Run lint on migration files (no DB required).
-    pub fn lint(locations: &[PathBuf], disabled_rules: &[String]) -> Result<LintReport> {
-        commands::lint::execute(locations, disabled_rules)
+    pub fn lint(
+        locations: &[PathBuf],
+        limits: &migration::ScanLimits,
+        lint_config: &config::LintConfig,
+    ) -> Result<LintReport> {
+        commands::lint::execute(locations, limits, lint_config)
     }
 
     /// Generate changelog from migration files (no DB required).
     pub fn changelog(
         locations: &[PathBuf],
+        limits: &migration::ScanLimits,
         from: Option<&str>,
         to: Option<&str>,
     ) -> Result<ChangelogReport> {
-        commands::changelog::execute(locations, from, to)
+        commands::changelog::execute(locations, limits, from, to)
+    }
+
+    /// Compare two JSON reports (`info`, `advise`, `safety` or `drift`) from
+    /// different runs (no DB required).
+    pub fn report_diff(
+        before: &std::path::Path,
+        after: &std::path::Path,
+    ) -> Result<commands::report_diff::ReportDiff> {
+        commands::report_diff::execute(before, after)
+    }
+
+    /// Write a stored snapshot as a schema-only SQL dump (no DB required).
+    pub fn snapshot_export(
+        snapshot_config: &commands::snapshot::SnapshotConfig,
+        snapshot_id: &str,
+        schema: &str,
+    ) -> Result<String> {
+        commands::snapshot::execute_export(snapshot_config, snapshot_id, schema)
+    }
+
+    /// Store a schema-only SQL dump as a snapshot (no DB required).
+    pub fn snapshot_import(
+        snapshot_config: &commands::snapshot::SnapshotConfig,
+        path: &Path,
+        snapshot_id: Option<&str>,
+        schema: &str,
+    ) -> Result<SnapshotReport> {
+        commands::snapshot::execute_import(snapshot_config, path, snapshot_id, schema)
+    }
+
+    /// Compare two stored snapshots (no DB required).
+    pub fn snapshot_diff(
+        snapshot_config: &commands::snapshot::SnapshotConfig,
+        from: &str,
+        to: &str,
+    ) -> Result<SnapshotDiffReport> {
+        commands::snapshot::execute_diff(snapshot_config, from, to)
     }
 
     /// Compare database schema against a target.
     pub async fn diff(&self, target: commands::diff::DiffTarget) -> Result<DiffReport> {
-        commands::diff::execute_db(&self.client, &self.config, target).await
+        self.check_policy("diff")?;
+        self.introspecting(commands::diff::execute_db(
+            &self.current(),
+            &self.config,
+            target,
+        ))
+        .await
+    }
+
+    /// Search this database, `target_urls` and stored `snapshots` for objects
+    /// matching `query`.
+    pub async fn find(
+        &self,
+        query: &FindQuery,
+        target_urls: &[String],
+        snapshots: &[String],
+    ) -> Result<FindReport> {
+        self.check_policy("find")?;
+        self.introspecting(commands::find::execute_db(
+            &self.current(),
+            &self.config,
+            query,
+            target_urls,
+            snapshots,
+        ))
+        .await
     }
 
     /// Detect schema drift.
     pub async fn drift(&self) -> Result<DriftReport> {
-        commands::drift::execute_db(&self.client, &self.config).await
+        self.check_policy("drift")?;
+        self.introspecting(self.read_only("drift", |client, config| {
+            Box::pin(async move {
+                if client.check_standby("drift", &config.migrations).await? {
+                    return commands::drift::execute_on_standby(client, config).await;
+                }
+                commands::drift::execute_db(client, config).await
+            })
+        }))
+        .await
+    }
+
+    /// Detect schema drift against the baseline stored by the last migrate
+    /// (`[drift] store_baseline`), without replaying migrations.
+    pub async fn drift_stored(&self) -> Result<DriftReport> {
+        self.check_policy("drift")?;
+        self.introspecting(self.read_only("drift", |client, config| {
+            Box::pin(commands::drift::execute_stored(client, config))
+        }))
+        .await
+    }
+
+    /// Detect drift against a schema-only SQL dump instead of the migrations
+    /// (`drift --against`, PostgreSQL only).
+    pub async fn drift_against(&self, path: &Path) -> Result<DriftReport> {
+        self.check_policy("drift")?;
+        let path = path.to_path_buf();
+        self.introspecting(self.read_only("drift", |client, config| {
+            let path = path.clone();
+            Box::pin(
+                async move { commands::drift::execute_against_file(client, config, &path).await },
+            )
+        }))
+        .await
+    }
+
+    /// Write a drift report's corrective migration as the next versioned
+    /// migration, `V{next}__Absorb_drift.sql` (`drift --generate-fix`).
+    ///
+    /// This database already has the drift, so the migration is recorded as
+    /// applied here, the way `baseline` records its version; other databases
+    /// apply it on their next migrate.
+    pub async fn drift_fix_migration(
+        &self,
+        report: &DriftReport,
+        allow_destructive: bool,
+        dry_run: bool,
+    ) -> Result<NewMigrationReport> {
+        let applied = self.applied_versions().await?;
+        let fix = commands::drift::write_fix_migration(
+            report,
+            &self.config,
+            &applied,
+            allow_destructive,
+            dry_run,
+        )?;
+        if !dry_run {
+            if let (Some(version), Some(file)) = (&fix.version, fix.files.first()) {
+                self.record_absorbed(version, file).await?;
+            }
+        }
+        Ok(fix)
+    }
+
+    /// Record the `drift --generate-fix` migration as applied on this
+    /// database, with the checksums `migrate` would store for the file.
+    async fn record_absorbed(
+        &self,
+        version: &str,
+        file: &commands::new_migration::NewMigrationFile,
+    ) -> Result<()> {
+        let schema = self
+            .current()
+            .resolve_schema(&self.config.migrations.schema)
+            .await?;
+        let table = &self.config.migrations.table;
+        let script = Path::new(&file.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file.path.clone());
+        let installed_by = self
+            .config
+            .migrations
+            .installed_by
+            .as_deref()
+            .unwrap_or("waypoint");
+        history::create_history_table_db(&self.current(), &schema, table).await?;
+        history::insert_applied_migration_db(
+            &self.current(),
+            &schema,
+            table,
+            Some(version),
+            "Absorb drift",
+            &migration::MigrationType::Versioned.to_string(),
+            &script,
+            Some(checksum::calculate_checksum(&file.contents)),
+            installed_by,
+            0,
+            true,
+        )
+        .await?;
+        if self.config.migrations.checksum_algorithm == checksum::ChecksumAlgorithm::Sha256 {
+            history::update_checksum_sha256_db(
+                &self.current(),
+                &schema,
+                table,
+                &script,
+                &checksum::calculate_sha256(&file.contents),
+            )
+            .await?;
+        }
+        Ok(())
     }
 
     /// Take a schema snapshot.
@@ -249,7 +891,13 @@ impl Waypoint {
         &self,
         snapshot_config: &commands::snapshot::SnapshotConfig,
     ) -> Result<SnapshotReport> {
-        commands::snapshot::execute_snapshot_db(&self.client, &self.config, snapshot_config).await
+        self.check_policy("snapshot")?;
+        self.introspecting(commands::snapshot::execute_snapshot_db(
+            &self.current(),
+            &self.config,
+            snapshot_config,
+        ))
+        .await
     }
 
     /// Restore from a schema snapshot.
@@ -258,8 +906,10 @@ impl Waypoint {
         snapshot_config: &commands::snapshot::SnapshotConfig,
         snapshot_id: &str,
     ) -> Result<RestoreReport> {
+        self.check_policy("restore")?;
+        self.check_standby("restore").await?;
         commands::snapshot::execute_restore_db(
-            &self.client,
+            &self.current(),
             &self.config,
             snapshot_config,
             snapshot_id,
@@ -269,12 +919,58 @@ impl Waypoint {
 
     /// Run enhanced dry-run with EXPLAIN.
     pub async fn explain(&self) -> Result<ExplainReport> {
-        commands::explain::execute_db(&self.client, &self.config).await
+        commands::explain::execute_db(&self.current(), &self.config).await
+    }
+
+    /// Parse and evaluate a guard expression against the database, returning
+    /// the value of every sub-expression.
+    pub async fn eval_guard(&self, expression: &str) -> Result<guard::GuardEvaluation> {
+        self.check_policy("guard")?;
+        let expr = guard::parse(expression)?;
+        self.guarding(guard::trace_db(
+            &self.current(),
+            &self.config.migrations.schema,
+            &expr,
+        ))
+        .await
+    }
+
+    /// Evaluate the guards of every pending migration without applying
+    /// anything.
+    pub async fn check_guards(&self) -> Result<GuardCheckReport> {
+        self.check_policy("guard")?;
+        self.guarding(self.read_only("guard", |client, config| {
+            Box::pin(commands::guard_check::execute_db(client, config))
+        }))
+        .await
+    }
+
+    /// Run the data-quality checks in `[checks] locations`, or only those
+    /// named in `names`.
+    pub async fn check(&self, names: &[String]) -> Result<CheckReport> {
+        self.check_policy("check")?;
+        let names = names.to_vec();
+        self.read_only("check", |client, config| {
+            let names = names.clone();
+            Box::pin(async move { commands::check::execute_db(client, config, &names).await })
+        })
+        .await
     }
 
     /// Run pre-flight health checks.
     pub async fn preflight(&self) -> Result<PreflightReport> {
-        preflight::run_preflight_db(&self.client, &self.config.preflight).await
+        self.check_policy("preflight")?;
+        self.read_only("preflight", |client, config| {
+            Box::pin(async move {
+                let mut report = preflight::run_preflight_db(client, &config.preflight).await?;
+                if let Some(check) = wal::preflight_check_db(client, config).await? {
+                    report.passed &= check.status != preflight::CheckStatus::Fail;
+                    report.checks.push(check);
+                }
+                Ok(report)
+            })
+        })
+        .await
     }
 
     /// Check for branch conflicts (no DB required).
@@ -282,19 +978,231 @@ impl Waypoint {
         commands::check_conflicts::execute(locations, base_branch)
     }
 
+    /// Renumber the current branch's migrations that collide with or precede
+    /// those on `options.base` (no DB required).
+    pub fn rebase(config: &WaypointConfig, options: &RebaseOptions) -> Result<RebaseReport> {
+        commands::rebase::execute(config, options)
+    }
+
     /// Analyze pending migrations for safety (lock analysis, impact estimation).
     pub async fn safety(&self) -> Result<SafetyCommandReport> {
-        commands::safety::execute_db(&self.client, &self.config).await
+        self.check_policy("safety")?;
+        commands::safety::execute_db(&self.current(), &self.config).await
     }
 
     /// Run schema advisor to suggest improvements.
     pub async fn advise(&self) -> Result<AdvisorReport> {
-        commands::advisor::execute_db(&self.client, &self.config).await
+        self.check_policy("advise")?;
+        self.introspecting(self.read_only("advise", |client, config| {
+            Box::pin(commands::advisor::execute_db(client, config))
+        }))
+        .await
     }
 
     /// Simulate pending migrations in a throwaway schema.
     pub async fn simulate(&self) -> Result<SimulationReport> {
-        commands::simulate::execute_db(&self.client, &self.config).await
+        self.check_policy("simulate")?;
+        self.check_standby("simulate").await?;
+        commands::simulate::execute_db(&self.current(), &self.config).await
+    }
+
+    /// Export a table's rows as an INSERT or COPY script, anonymizing
+    /// configured and requested columns (PostgreSQL only).
+    pub async fn export(&self, options: &ExportOptions) -> Result<ExportReport> {
+        self.check_policy("export")?;
+        commands::export::execute_db(&self.current(), &self.config, options).await
+    }
+
+    /// Export a table's rows like [`export`](Self::export), writing the
+    /// script to `out` as rows arrive; the report's `script` is left empty.
+    pub async fn export_to(
+        &self,
+        options: &ExportOptions,
+        out: &mut (dyn std::io::Write + Send),
+    ) -> Result<ExportReport> {
+        self.check_policy("export")?;
+        commands::export::execute_db_to(&self.current(), &self.config, options, out).await
+    }
+
+    /// Schedule a migrate run for a maintenance window (PostgreSQL only).
+    pub async fn schedule(
+        &self,
+        at: &str,
+        target_version: Option<&str>,
+        window_minutes: u32,
+    ) -> Result<ScheduledRun> {
+        self.check_policy("schedule")?;
+        self.check_standby("schedule").await?;
+        commands::schedule::schedule_db(
+            &self.current(),
+            &self.config,
+            at,
+            target_version,
+            window_minutes,
+        )
+        .await
+    }
+
+    /// List scheduled migrate runs, newest first (PostgreSQL only).
+    pub async fn list_schedules(&self) -> Result<Vec<ScheduledRun>> {
+        self.check_policy("schedule")?;
+        commands::schedule::list_db(&self.current(), &self.config).await
+    }
+
+    /// Cancel a pending scheduled run (PostgreSQL only).
+    pub async fn cancel_schedule(&self, id: i64) -> Result<ScheduledRun> {
+        self.check_policy("schedule")?;
+        self.check_standby("schedule").await?;
+        commands::schedule::cancel_db(&self.current(), &self.config, id).await
+    }
+
+    /// Run the earliest due scheduled migrate run, if any (PostgreSQL only).
+    pub async fn run_scheduled(&self) -> Result<RunScheduledReport> {
+        self.check_policy("run-scheduled")?;
+        self.check_standby("run-scheduled").await?;
+        commands::schedule::run_scheduled_db(&self.current(), &self.config).await
+    }
+
+    /// Check an execution receipt's signature, that this is the database it
+    /// was issued against, and that the history table still records what it
+    /// says was applied.
+    pub async fn verify_receipt(
+        &self,
+        receipt: &receipt::ExecutionReceipt,
+    ) -> Result<receipt::ReceiptVerification> {
+        self.check_policy("verify-receipt")?;
+        receipt::verify(&self.current(), &self.config, receipt).await
+    }
+
+    /// Load a receipt stored by `[receipts] history = true`.
+    pub async fn load_receipt(&self, run_id: &str) -> Result<receipt::ExecutionReceipt> {
+        receipt::load(&self.current(), &self.config, run_id).await
+    }
+
+    /// Record what `migrate` up to `target_version` would apply right now:
+    /// pending scripts, checksums, safety verdicts and duration estimates.
+    ///
+    /// The plan serializes to JSON; pass it back to [`Waypoint::apply`] later.
+    pub async fn plan(&self, target_version: Option<&str>) -> Result<MigrationPlan> {
+        commands::plan::execute_db(&self.current(), &self.config, target_version).await
+    }
+
+    /// Apply a plan from [`Waypoint::plan`].
+    ///
+    /// Re-plans first and fails with `PlanDiverged` (applying nothing) if the
+    /// applied version, the pending scripts, or their checksums changed. The
+    /// migrate checks the plan again once it holds the migrate lock, against
+    /// the files it then applies.
+    pub async fn apply(&self, plan: &MigrationPlan) -> Result<MigrateReport> {
+        commands::plan::check_db(&self.current(), &self.config, plan).await?;
+        commands::plan::with_approved(plan.clone(), self.migrate(plan.target_version.as_deref()))
+            .await
+    }
+
+    /// Summarize migration cadence, durations and failures from the history table.
+    ///
+    /// `since` limits the window (e.g. the last 30 days); `top` is the number
+    /// of longest migrations to list.
+    pub async fn metrics_summary(
+        &self,
+        since: Option<chrono::Duration>,
+        top: usize,
+    ) -> Result<MetricsSummary> {
+        self.check_policy("metrics")?;
+        self.read_only("metrics", move |client, config| {
+            Box::pin(commands::metrics::execute_db(client, config, since, top))
+        })
+        .await
+    }
+
+    /// Summarize pending migrations with their safety verdicts and age.
+    ///
+    /// With `notify`, the summary is also sent as a `pending_report`
+    /// notification on `channel` (default: `[notify] channel`; PostgreSQL
+    /// only).
+    pub async fn pending_report(
+        &self,
+        notify: bool,
+        channel: Option<&str>,
+    ) -> Result<PendingSummary> {
+        self.check_policy("pending-report")?;
+        let mut summary = self
+            .read_only("pending-report", |client, config| {
+                Box::pin(commands::pending_report::execute_db(client, config))
+            })
+            .await?;
+        if notify {
+            commands::pending_report::send_db(&self.current(), &self.config, &mut summary, channel)
+                .await?;
+        }
+        Ok(summary)
+    }
+
+    /// Age the pending migrations against `max_pending_age` (`14d`, `36h`,
+    /// `2w`); [`SlaCheckReport::check`] turns breaches into an error.
+    pub async fn sla_check(&self, max_pending_age: &str) -> Result<SlaCheckReport> {
+        self.check_policy("sla-check")?;
+        let max_pending_age = max_pending_age.to_string();
+        self.read_only("sla-check", |client, config| {
+            let max_pending_age = max_pending_age.clone();
+            Box::pin(async move {
+                commands::sla_check::execute_db(client, config, &max_pending_age).await
+            })
+        })
+        .await
+    }
+
+    /// Fingerprint the live schema, as `[drift] publish_fingerprint` does
+    /// after migrate; [`FingerprintReport::check`] compares it with the
+    /// fingerprint a service expects. Not narrowed by `include_objects` /
+    /// `exclude_objects`, so it matches the one migrate reports.
+    pub async fn fingerprint(&self) -> Result<FingerprintReport> {
+        self.check_policy("fingerprint")?;
+        self.read_only("fingerprint", |client, config| {
+            Box::pin(async move { commands::fingerprint::execute_db(client, config).await })
+        })
+        .await
+    }
+
+    /// Generate the migrations that rename or remove enum labels safely:
+    /// new type, batched backfill, then swap (PostgreSQL only).
+    pub async fn assist_enum(&self, options: &EnumChangeOptions) -> Result<EnumAssistReport> {
+        self.check_policy("assist")?;
+        commands::assist::enum_change_db(&self.current(), &self.config, options).await
+    }
+
+    /// Split a table-rewriting `ADD COLUMN ... DEFAULT` statement into an
+    /// add, a batched backfill and a NOT NULL step, for the connected
+    /// server's version.
+    pub async fn assist_default(
+        &self,
+        statement: &str,
+        batch_size: i64,
+    ) -> Result<DefaultAssistReport> {
+        self.check_policy("assist")?;
+        commands::assist::default_backfill_db(&self.current(), statement, batch_size).await
+    }
+
+    /// Split `ADD CONSTRAINT ... UNIQUE (...)` into a concurrent unique index
+    /// build and an `ADD CONSTRAINT ... USING INDEX` (PostgreSQL only).
+    pub async fn assist_unique(&self, statement: &str) -> Result<UniqueAssistReport> {
+        self.check_policy("assist")?;
+        commands::assist::unique_constraint_db(&self.current(), statement)
+    }
+
+    /// Split `ADD CONSTRAINT ... FOREIGN KEY` into an `ADD ... NOT VALID` and
+    /// a low-priority `VALIDATE CONSTRAINT` migration (PostgreSQL only).
+    pub async fn assist_fk(&self, statement: &str) -> Result<ForeignKeyAssistReport> {
+        self.check_policy("assist")?;
+        commands::assist::foreign_key_db(&self.current(), statement)
+    }
+
+    /// Fold old migrations into a single generated baseline and archive the
+    /// originals (PostgreSQL only).
+    pub async fn squash(&self, options: &SquashOptions) -> Result<SquashReport> {
+        self.check_policy("squash")?;
+        self.check_standby("squash").await?;
+        commands::squash::execute_db(&self.current(), &self.config, options).await
     }
 }
 
@@ -303,32 +1211,28 @@ async fn connect_for_url(
     conn_string: &str,
     #[cfg_attr(not(feature = "postgres"), allow(unused_variables))] config: &WaypointConfig,
 ) -> Result<DbClient> {
-    let kind = DialectKind::from_url(conn_string).unwrap_or(DialectKind::Postgres);
+    let kind = DialectKind::from_url(conn_string)
+        .or(config.database.dialect.kind())
+        .unwrap_or(DialectKind::Postgres);
     match kind {
         #[cfg(feature = "postgres")]
         DialectKind::Postgres => {
-            let client = db::connect_with_full_config(
-                conn_string,
-                &config.database.ssl_mode,
-                config.database.connect_retries,
-                config.database.connect_timeout_secs,
-                config.database.statement_timeout_secs,
-                config.database.keepalive_secs,
-            )
-            .await?;
-            Ok(DbClient::with_postgres(client))
+            let client = db::connect_supervised(conn_string, &config.database).await?;
+            if config.database.dialect == config::DialectSetting::Cockroach
+                && !db::is_cockroach(client.as_postgres()?).await
+            {
+                return Err(error::WaypointError::ConfigError(
+                    "dialect = \"cockroach\" is set but the server is not CockroachDB".into(),
+                ));
+            }
+            Ok(client)
         }
         #[cfg(not(feature = "postgres"))]
         DialectKind::Postgres => Err(error::WaypointError::ConfigError(
             "PostgreSQL support is not compiled in (enable the `postgres` feature)".into(),
         )),
         #[cfg(feature = "mysql")]
-        DialectKind::Mysql => {
-            let pool = mysql_async::Pool::from_url(conn_string).map_err(|e| {
-                error::WaypointError::ConfigError(format!("Invalid MySQL connection URL: {}", e))
-            })?;
-            Ok(DbClient::with_mysql(pool))
-        }
+        DialectKind::Mysql => Ok(DbClient::with_mysql(db::mysql_pool(conn_string)?)),
         #[cfg(not(feature = "mysql"))]
         DialectKind::Mysql => Err(error::WaypointError::ConfigError(
             "MySQL support is not compiled in (enable the `mysql` feature)".into(),
@@ -0,0 +1,551 @@
+//! Migration locations that are not local directories.
+//!
+//! A [`LocationProvider`] recognizes a location string and fetches it into a
+//! temporary directory, which is then scanned like any other location. The
+//! built-in providers shell out to the usual tools, so credentials come from
+//! their normal configuration:
+//!
+//! | Location | Fetched with |
+//! |---|---|
+//! | `git+<url>#<ref>:<path>` | `git fetch` (see [`crate::git_location`]) |
+//! | `git:<ref>#<path>` | `git archive` from the repository in the working directory |
+//! | `https://host/migrations.tar.gz#<path>` | `curl` and `tar` (`.tar.gz`, `.tgz`, `.tar`); plain `http://` needs a `sha256=` pin |
+//! | `s3://bucket/prefix` | `aws s3 cp --recursive` (top-level objects only) |
+//!
+//! Library users can [`register`](LocationProviders::register) their own on
+//! `config.migrations.location_providers`. [`fetch`] swaps every remote
+//! location for its checkout; the CLI does this once per run so hooks and
+//! `-- waypoint:copy` files are found too. Checkouts are cached on the
+//! [`LocationProviders`] (and its clones), so resolving a config's
+//! migrations again doesn't fetch again.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Result, WaypointError};
+use crate::git_location::GitRemoteProvider;
+
+/// Fetches one kind of remote migration location.
+pub trait LocationProvider: Send + Sync {
+    /// Whether `location` is one this provider fetches.
+    fn handles(&self, location: &str) -> bool;
+
+    /// Fetch `location` into the empty directory `dir` and return the
+    /// directory holding its migrations (`dir` or one below it).
+    fn fetch(&self, location: &str, dir: &Path) -> Result<PathBuf>;
+}
+
+/// The providers consulted for each location, most recently registered
+/// first. The default holds the built-in git, HTTP archive and S3 providers.
+///
+/// Clones share the checkouts already fetched, which are deleted once the
+/// last clone and every [`FetchedLocations`] using them have dropped.
+#[derive(Clone)]
+pub struct LocationProviders {
+    providers: Vec<Arc<dyn LocationProvider>>,
+    checkouts: Arc<Mutex<HashMap<String, Arc<Checkout>>>>,
+}
+
+impl Default for LocationProviders {
+    fn default() -> Self {
+        Self {
+            providers: vec![
+                Arc::new(GitRemoteProvider),
+                Arc::new(GitRefProvider::default()),
+                Arc::new(HttpArchiveProvider),
+                Arc::new(S3Provider),
+            ],
+            checkouts: Default::default(),
+        }
+    }
+}
+
+impl fmt::Debug for LocationProviders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocationProviders")
+            .field("count", &self.providers.len())
+            .finish()
+    }
+}
+
+impl LocationProviders {
+    /// Add a provider, taking precedence over those already registered.
+    pub fn register(&mut self, provider: impl LocationProvider + 'static) {
+        self.providers.insert(0, Arc::new(provider));
+        // A checkout may have come from a provider the new one overrides.
+        self.checkouts = Default::default();
+    }
+
+    /// The provider that fetches `location`, or `None` for a local path.
+    pub fn find(&self, location: &Path) -> Option<&dyn LocationProvider> {
+        let location = location.to_str()?;
+        self.providers
+            .iter()
+            .map(Arc::as_ref)
+            .find(|p| p.handles(location))
+    }
+}
+
+/// A remote location fetched into a temporary directory, deleted on drop.
+#[derive(Debug)]
+struct Checkout {
+    dir: PathBuf,
+    /// The directory holding the migrations (`dir` or one below it).
+    migrations: PathBuf,
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            log::debug!(
+                "Could not remove location checkout; dir={}, error={}",
+                self.dir.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Locations with every remote location replaced by a local checkout. The
+/// checkouts stay on disk while this (or the [`LocationProviders`] that
+/// fetched them) is alive, so keep it for as long as the locations are in
+/// use.
+#[derive(Debug)]
+pub struct FetchedLocations {
+    /// The locations to scan, in the original order.
+    pub locations: Vec<PathBuf>,
+    checkouts: Vec<Arc<Checkout>>,
+}
+
+/// Fetch each location in `locations` that one of `providers` handles into
+/// a temporary directory, or reuse the checkout `providers` already holds
+/// for it. Local paths are passed through unchanged and nothing is fetched
+/// when there are no remote locations.
+pub fn fetch(locations: &[PathBuf], providers: &LocationProviders) -> Result<FetchedLocations> {
+    let mut fetched = FetchedLocations {
+        locations: Vec::with_capacity(locations.len()),
+        checkouts: Vec::new(),
+    };
+    for location in locations {
+        let Some(provider) = providers.find(location) else {
+            fetched.locations.push(location.clone());
+            continue;
+        };
+        let location = location.to_string_lossy().into_owned();
+        let mut cache = providers.checkouts.lock().expect("location cache lock");
+        let checkout = match cache.get(&location) {
+            Some(checkout) => checkout.clone(),
+            None => {
+                let dir = std::env::temp_dir().join(format!(
+                    "waypoint-location-{}-{:016x}",
+                    std::process::id(),
+                    fastrand::u64(..)
+                ));
+                std::fs::create_dir_all(&dir)?;
+                let mut checkout = Checkout {
+                    migrations: dir.clone(),
+                    dir,
+                };
+                // Dropping `checkout` on error removes the directory.
+                checkout.migrations = provider.fetch(&location, &checkout.dir)?;
+                let checkout = Arc::new(checkout);
+                cache.insert(location, checkout.clone());
+                checkout
+            }
+        };
+        fetched.locations.push(checkout.migrations.clone());
+        fetched.checkouts.push(checkout);
+    }
+    Ok(fetched)
+}
+
+/// `git:<ref>#<path>`: a directory at a ref of a local repository, e.g.
+/// `git:refs/tags/v1.2#db/migrations`. `<path>` defaults to the root.
+#[derive(Debug, Clone)]
+pub struct GitRefProvider {
+    /// Repository to read from; the working directory by default.
+    pub repo: PathBuf,
+}
+
+impl Default for GitRefProvider {
+    fn default() -> Self {
+        Self {
+            repo: PathBuf::from("."),
+        }
+    }
+}
+
+impl LocationProvider for GitRefProvider {
+    fn handles(&self, location: &str) -> bool {
+        location.starts_with("git:")
+    }
+
+    fn fetch(&self, location: &str, dir: &Path) -> Result<PathBuf> {
+        let rest = location.trim_start_matches("git:");
+        let (git_ref, path) = rest.split_once('#').unwrap_or((rest, ""));
+        if git_ref.is_empty() {
+            return Err(WaypointError::ConfigError(format!(
+                "Git location '{}' has no ref",
+                location
+            )));
+        }
+        // git would read a leading `-` as an option.
+        if git_ref.starts_with('-') {
+            return Err(WaypointError::ConfigError(format!(
+                "Git location '{}' has an invalid ref",
+                location
+            )));
+        }
+        let path = relative_path(location, path)?;
+        log::info!(
+            "Reading git location; ref={}, path={}",
+            git_ref,
+            path.display()
+        );
+
+        let mut archive = Command::new("git");
+        archive.arg("-C").arg(&self.repo).args([
+            "archive",
+            "--format=tar",
+            "--end-of-options",
+            git_ref,
+        ]);
+        if !path.as_os_str().is_empty() {
+            archive.arg(&path);
+        }
+        extract(archive, "-x", dir)
+            .map_err(|e| WaypointError::GitError(format!("{location}: {e}")))?;
+        checkout_dir(location, dir, &path)
+    }
+}
+
+/// `https://` URL of a `.tar.gz`, `.tgz` or `.tar` archive, with an optional
+/// `#<path>` to the migrations inside it. A `sha256=<hex>` fragment part
+/// (`#db&sha256=...`) pins the archive's digest; plain `http://` URLs, which
+/// anyone on the path could rewrite, are only fetched with one.
+#[derive(Debug, Clone, Copy)]
+pub struct HttpArchiveProvider;
+
+impl LocationProvider for HttpArchiveProvider {
+    fn handles(&self, location: &str) -> bool {
+        location.starts_with("https://") || location.starts_with("http://")
+    }
+
+    fn fetch(&self, location: &str, dir: &Path) -> Result<PathBuf> {
+        let (url, fragment) = location.split_once('#').unwrap_or((location, ""));
+        let mut path = "";
+        let mut pin = None;
+        for part in fragment.split('&') {
+            match part.strip_prefix("sha256=") {
+                Some(hex) => pin = Some(hex.to_ascii_lowercase()),
+                None if !part.is_empty() => path = part,
+                None => {}
+            }
+        }
+        let path = relative_path(location, path)?;
+        if url.starts_with("http://") && pin.is_none() {
+            return Err(WaypointError::ConfigError(format!(
+                "HTTP location '{}' must use https:// or pin the archive with #sha256=<hex>",
+                location
+            )));
+        }
+        let file = url.split('?').next().unwrap_or(url);
+        let flags = if file.ends_with(".tar.gz") || file.ends_with(".tgz") {
+            "-xz"
+        } else if file.ends_with(".tar") {
+            "-x"
+        } else {
+            return Err(WaypointError::ConfigError(format!(
+                "HTTP location '{}' must be a .tar.gz, .tgz or .tar archive",
+                location
+            )));
+        };
+        log::info!("Downloading migration archive; url={}", url);
+
+        // Downloaded beside the checkout so the digest can be checked before
+        // anything is extracted.
+        let archive = dir.with_extension("archive");
+        let result = download(url, &archive, pin.is_none())
+            .and_then(|()| verify_sha256(&archive, pin.as_deref()))
+            .and_then(|()| {
+                let mut tar = Command::new("tar");
+                tar.arg(flags).arg("-f").arg(&archive).arg("-C").arg(dir);
+                run(tar)
+            });
+        let _ = std::fs::remove_file(&archive);
+        result.map_err(|e| location_error(location, e))?;
+        checkout_dir(location, dir, &path)
+    }
+}
+
+/// Download `url` to `file` with curl. With `https_only`, redirects may not
+/// leave https.
+fn download(url: &str, file: &Path, https_only: bool) -> std::result::Result<(), String> {
+    let mut curl = Command::new("curl");
+    curl.args(["-fsSL", "--netrc-optional"]);
+    if https_only {
+        curl.args(["--proto-redir", "=https"]);
+    }
+    curl.arg("-o").arg(file).arg(url);
+    run(curl)
+}
+
+/// Check `file` against a pinned lowercase hex SHA-256 digest, if any.
+fn verify_sha256(file: &Path, pin: Option<&str>) -> std::result::Result<(), String> {
+    let Some(pin) = pin else {
+        return Ok(());
+    };
+    let mut reader = std::fs::File::open(file).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let actual = format!("{:x}", hasher.finalize());
+    if actual != pin {
+        return Err(format!(
+            "archive SHA-256 is {actual}, but the location pins {pin}"
+        ));
+    }
+    Ok(())
+}
+
+/// Run `command`, with its stderr as the error when it fails.
+fn run(mut command: Command) -> std::result::Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run {program}: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// `s3://bucket/prefix`: the objects directly under the prefix.
+#[derive(Debug, Clone, Copy)]
+pub struct S3Provider;
+
+impl LocationProvider for S3Provider {
+    fn handles(&self, location: &str) -> bool {
+        location.starts_with("s3://")
+    }
+
+    fn fetch(&self, location: &str, dir: &Path) -> Result<PathBuf> {
+        let prefix = format!("{}/", location.trim_end_matches('/'));
+        log::info!("Downloading S3 location; prefix={}", prefix);
+        let output = Command::new("aws")
+            .args(["s3", "cp", "--recursive", "--quiet", "--exclude", "*/*"])
+            .arg(&prefix)
+            .arg(dir)
+            .output()
+            .map_err(|e| location_error(location, format!("failed to run aws: {e}")))?;
+        if !output.status.success() {
+            return Err(location_error(
+                location,
+                format!(
+                    "aws s3 cp failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        Ok(dir.to_path_buf())
+    }
+}
+
+fn location_error(location: &str, reason: impl fmt::Display) -> WaypointError {
+    WaypointError::IoError(std::io::Error::other(format!(
+        "Failed to fetch migration location '{}': {}",
+        location, reason
+    )))
+}
+
+/// `path` from a location fragment, which must stay inside the checkout.
+fn relative_path(location: &str, path: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(path.trim_matches('/'));
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(WaypointError::ConfigError(format!(
+            "Location '{}' must use a relative path inside the checkout",
+            location
+        )));
+    }
+    Ok(path)
+}
+
+fn checkout_dir(location: &str, dir: &Path, path: &Path) -> Result<PathBuf> {
+    let checkout = dir.join(path);
+    if !checkout.is_dir() {
+        return Err(WaypointError::ConfigError(format!(
+            "'{}' is not a directory in location '{}'",
+            path.display(),
+            location
+        )));
+    }
+    Ok(checkout)
+}
+
+/// Run `source | tar <flags> -C dir`, with the stderr of whichever side
+/// failed as the error.
+fn extract(mut source: Command, flags: &str, dir: &Path) -> std::result::Result<(), String> {
+    let program = source.get_program().to_string_lossy().into_owned();
+    let mut source = source
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run {program}: {e}"))?;
+    let stdout = source.stdout.take().expect("piped stdout");
+    let tar = Command::new("tar")
+        .arg(flags)
+        .arg("-C")
+        .arg(dir)
+        .stdin(stdout)
+        .stderr(Stdio::piped())
+        .output();
+    let source = source
+        .wait_with_output()
+        .map_err(|e| format!("{program} failed: {e}"))?;
+    if !source.status.success() {
+        return Err(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&source.stderr).trim()
+        ));
+    }
+    let tar = tar.map_err(|e| format!("failed to run tar: {e}"))?;
+    if !tar.status.success() {
+        return Err(format!(
+            "tar failed: {}",
+            String::from_utf8_lossy(&tar.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(dir: &Path, program: &str, args: &[&str]) {
+        let status = Command::new(program)
+            .current_dir(dir)
+            .args(args)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{program} {args:?}");
+    }
+
+    #[test]
+    fn test_git_ref_and_archive_locations() {
+        let repo = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let mut all = vec!["-c", "user.name=t", "-c", "user.email=t@example.com"];
+            all.extend_from_slice(args);
+            run(repo.path(), "git", &all);
+        };
+        git(&["init", "--quiet", "--initial-branch=main"]);
+        std::fs::create_dir_all(repo.path().join("db")).unwrap();
+        std::fs::write(repo.path().join("db/V1__Init.sql"), "SELECT 1;").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        git(&["tag", "v1"]);
+        std::fs::write(repo.path().join("db/V2__Later.sql"), "SELECT 2;").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "later"]);
+
+        let mut providers = LocationProviders::default();
+        providers.register(GitRefProvider {
+            repo: repo.path().to_path_buf(),
+        });
+        let fetched = fetch(
+            &[PathBuf::from("git:refs/tags/v1#db"), PathBuf::from("local")],
+            &providers,
+        )
+        .unwrap();
+        let checkout = fetched.locations[0].clone();
+        assert!(checkout.join("V1__Init.sql").is_file());
+        assert!(!checkout.join("V2__Later.sql").exists());
+        assert_eq!(fetched.locations[1], PathBuf::from("local"));
+        // Fetching again reuses the checkout until the providers drop.
+        let again = fetch(&[PathBuf::from("git:refs/tags/v1#db")], &providers).unwrap();
+        assert_eq!(again.locations[0], checkout);
+        drop(fetched);
+        drop(again);
+        assert!(checkout.exists());
+        assert!(fetch(&[PathBuf::from("git:v1#nope")], &providers).is_err());
+        assert!(fetch(&[PathBuf::from("git:--output=/tmp/x#db")], &providers).is_err());
+        drop(providers);
+        assert!(!checkout.exists());
+
+        // Archives go through the same extraction; curl reads file:// URLs.
+        run(repo.path(), "tar", &["-czf", "m.tar.gz", "db"]);
+        let dir = tempfile::tempdir().unwrap();
+        let archive = std::fs::read(repo.path().join("m.tar.gz")).unwrap();
+        let digest = format!("{:x}", Sha256::digest(&archive));
+        let url = format!("file://{}/m.tar.gz#db", repo.path().display());
+        let checkout = HttpArchiveProvider.fetch(&url, dir.path()).unwrap();
+        assert!(checkout.join("V2__Later.sql").is_file());
+        let pinned = format!("{url}&sha256={digest}");
+        let dir = tempfile::tempdir().unwrap();
+        assert!(HttpArchiveProvider.fetch(&pinned, dir.path()).is_ok());
+        let wrong = format!("{url}&sha256={}", "0".repeat(64));
+        let dir = tempfile::tempdir().unwrap();
+        let err = HttpArchiveProvider.fetch(&wrong, dir.path()).unwrap_err();
+        assert!(err.to_string().contains("SHA-256"), "{err}");
+        assert!(!dir.path().join("db").exists());
+        assert!(HttpArchiveProvider
+            .fetch("https://host/migrations.zip", dir.path())
+            .is_err());
+        assert!(HttpArchiveProvider
+            .fetch("https://host/m.tgz#../etc", dir.path())
+            .is_err());
+        assert!(matches!(
+            HttpArchiveProvider.fetch("http://host/m.tgz#db", dir.path()),
+            Err(WaypointError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_registered_provider_feeds_resolve() {
+        struct Fixed;
+        impl LocationProvider for Fixed {
+            fn handles(&self, location: &str) -> bool {
+                location.starts_with("fixed:")
+            }
+            fn fetch(&self, _location: &str, dir: &Path) -> Result<PathBuf> {
+                std::fs::write(dir.join("V1__Remote.sql"), "SELECT 1;")?;
+                Ok(dir.to_path_buf())
+            }
+        }
+
+        let mut settings = crate::config::MigrationSettings {
+            locations: vec![PathBuf::from("fixed:anything")],
+            ..Default::default()
+        };
+        assert!(settings.resolve().unwrap().is_empty());
+        settings.location_providers.register(Fixed);
+        let resolved = settings.resolve().unwrap();
+        assert_eq!(resolved[0].script, "V1__Remote.sql");
+        assert!(LocationProviders::default()
+            .find(Path::new("s3://bucket/migrations"))
+            .is_some());
+        assert!(LocationProviders::default()
+            .find(Path::new("db/migrations"))
+            .is_none());
+    }
+}
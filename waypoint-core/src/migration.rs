@@ -1,6 +1,7 @@
 //! Migration file parsing, scanning, and types.
 //!
-//! Supports versioned (`V{version}__{desc}.sql`) and repeatable (`R__{desc}.sql`) migrations.
+//! Supports versioned (`V{version}__{desc}.sql`) and repeatable (`R__{desc}.sql`,
+//! or `R{order}__{desc}.sql` to fix their application order) migrations.
 
 use std::cmp::Ordering;
 use std::fmt;
@@ -15,20 +16,115 @@ use crate::hooks;
 
 static VERSIONED_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^V([\d._]+)__(.+)$").unwrap());
 static UNDO_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^U([\d._]+)__(.+)$").unwrap());
-static REPEATABLE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^R__(.+)$").unwrap());
+static REPEATABLE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^R(\d*)__(.+)$").unwrap());
+
+/// How version strings are parsed and ordered (`[migrations] version_scheme`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionScheme {
+    /// Dotted segments compared as numbers, missing trailing segments
+    /// counting as 0: `1.9` < `1.10`, `1.2` = `1.2.0`, `06` = `6` (default).
+    #[default]
+    Numeric,
+    /// Like `numeric`, but at most `major.minor.patch` and no zero-padded
+    /// segments, so `1.02` and `1.2.3.4` are rejected.
+    Semver,
+    /// Versions compared as text (`_` read as `.`), for fixed-width schemes
+    /// such as zero-padded timestamps: `0010` and `10` are different versions.
+    Lexicographic,
+}
+
+impl VersionScheme {
+    /// Order two parsed versions under this scheme.
+    pub fn compare(self, a: &MigrationVersion, b: &MigrationVersion) -> Ordering {
+        match self {
+            VersionScheme::Numeric | VersionScheme::Semver => {
+                let max_len = a.segments.len().max(b.segments.len());
+                for i in 0..max_len {
+                    let x = a.segments.get(i).copied().unwrap_or(0);
+                    let y = b.segments.get(i).copied().unwrap_or(0);
+                    match x.cmp(&y) {
+                        Ordering::Equal => continue,
+                        ord => return ord,
+                    }
+                }
+                Ordering::Equal
+            }
+            VersionScheme::Lexicographic => {
+                let text = |v: &MigrationVersion| {
+                    v.raw
+                        .bytes()
+                        .map(|b| if b == b'_' { b'.' } else { b })
+                        .collect::<Vec<u8>>()
+                };
+                text(a).cmp(&text(b))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for VersionScheme {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "numeric" | "numeric-dotted" => Ok(VersionScheme::Numeric),
+            "semver" => Ok(VersionScheme::Semver),
+            "lexicographic" => Ok(VersionScheme::Lexicographic),
+            _ => Err(WaypointError::ConfigError(format!(
+                "Invalid version_scheme '{}'. Use 'numeric', 'semver', or 'lexicographic'.",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for VersionScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionScheme::Numeric => write!(f, "numeric"),
+            VersionScheme::Semver => write!(f, "semver"),
+            VersionScheme::Lexicographic => write!(f, "lexicographic"),
+        }
+    }
+}
 
 /// A parsed migration version, supporting dotted numeric segments (e.g., "1.2.3").
-#[derive(Debug, Clone, Eq, PartialEq)]
+///
+/// Equality and ordering follow the [`VersionScheme`] the version was parsed
+/// under, so `1_2`, `1.2` and `1.2.0` are the same version under `numeric`.
+/// Only versions parsed under the same scheme may be compared: parse every
+/// version a command compares with
+/// [`MigrationSettings::parse_version`](crate::config::MigrationSettings::parse_version),
+/// or re-parse it with [`in_scheme`](Self::in_scheme).
+#[derive(Debug, Clone)]
 pub struct MigrationVersion {
     /// Parsed numeric segments of the version (e.g., `[1, 2, 3]` for `"1.2.3"`).
     pub segments: Vec<u64>,
     /// Original version string as it appeared in the filename.
     pub raw: String,
+    /// Scheme the version was parsed under.
+    pub scheme: VersionScheme,
 }
 
 impl MigrationVersion {
-    /// Parse a version string like `"1.2.3"` or `"1_2"` into segments.
+    /// Parse a version string like `"1.2.3"` or `"1_2"` into segments,
+    /// under the default (`numeric`) [`VersionScheme`]. Use
+    /// [`MigrationSettings::parse_version`](crate::config::MigrationSettings::parse_version)
+    /// for versions that should follow `[migrations] version_scheme`.
     pub fn parse(raw: &str) -> Result<Self> {
+        Self::parse_with(raw, VersionScheme::default())
+    }
+
+    /// This version re-parsed under `scheme`, failing if `scheme` rejects it.
+    pub fn in_scheme(&self, scheme: VersionScheme) -> Result<Self> {
+        if scheme == self.scheme {
+            return Ok(self.clone());
+        }
+        Self::parse_with(&self.raw, scheme)
+    }
+
+    /// Parse a version string under `scheme`.
+    pub fn parse_with(raw: &str, scheme: VersionScheme) -> Result<Self> {
         if raw.is_empty() {
             return Err(WaypointError::MigrationParseError(
                 "Version string is empty".to_string(),
@@ -46,28 +142,51 @@ impl MigrationVersion {
             ))
         })?;
 
+        if scheme == VersionScheme::Semver {
+            if segments.len() > 3 {
+                return Err(WaypointError::MigrationParseError(format!(
+                    "Version '{}' has more than major.minor.patch (version_scheme = \"semver\")",
+                    raw
+                )));
+            }
+            if raw
+                .split(['.', '_'])
+                .any(|s| s.len() > 1 && s.starts_with('0'))
+            {
+                return Err(WaypointError::MigrationParseError(format!(
+                    "Version '{}' has a zero-padded segment (version_scheme = \"semver\")",
+                    raw
+                )));
+            }
+        }
+
         Ok(MigrationVersion {
             segments,
             raw: raw.to_string(),
+            scheme,
         })
     }
 }
 
 impl Ord for MigrationVersion {
     fn cmp(&self, other: &Self) -> Ordering {
-        let max_len = self.segments.len().max(other.segments.len());
-        for i in 0..max_len {
-            let a = self.segments.get(i).copied().unwrap_or(0);
-            let b = other.segments.get(i).copied().unwrap_or(0);
-            match a.cmp(&b) {
-                Ordering::Equal => continue,
-                ord => return ord,
-            }
-        }
-        Ordering::Equal
+        debug_assert_eq!(
+            self.scheme, other.scheme,
+            "versions {} and {} were parsed under different schemes",
+            self.raw, other.raw
+        );
+        self.scheme.compare(self, other)
     }
 }
 
+impl PartialEq for MigrationVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MigrationVersion {}
+
 impl PartialOrd for MigrationVersion {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -80,12 +199,146 @@ impl fmt::Display for MigrationVersion {
     }
 }
 
+/// Which versions below the highest applied one may still be applied
+/// (`[migrations] out_of_order_policy`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutOfOrderPolicy {
+    /// Never (default).
+    #[default]
+    Never,
+    /// Always (same as `out_of_order = true`).
+    Always,
+    /// Only when an applied version shares the first segment, so a hotfix
+    /// `4.1` may follow `5` once `4` is applied, but `3` may not.
+    PatchOnly,
+}
+
+impl OutOfOrderPolicy {
+    /// Whether `version` may be applied below the highest applied version,
+    /// given the versions applied so far.
+    pub fn allows<'a>(
+        &self,
+        version: &MigrationVersion,
+        mut applied: impl Iterator<Item = &'a MigrationVersion>,
+    ) -> bool {
+        match self {
+            OutOfOrderPolicy::Never => false,
+            OutOfOrderPolicy::Always => true,
+            OutOfOrderPolicy::PatchOnly => {
+                let major = version.segments.first();
+                applied.any(|a| a.segments.first() == major)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for OutOfOrderPolicy {
+    type Err = WaypointError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "never" => Ok(OutOfOrderPolicy::Never),
+            "always" => Ok(OutOfOrderPolicy::Always),
+            "patch-only" => Ok(OutOfOrderPolicy::PatchOnly),
+            _ => Err(WaypointError::ConfigError(format!(
+                "Invalid out_of_order_policy '{}'. Use 'never', 'always', or 'patch-only'.",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for OutOfOrderPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutOfOrderPolicy::Never => write!(f, "never"),
+            OutOfOrderPolicy::Always => write!(f, "always"),
+            OutOfOrderPolicy::PatchOnly => write!(f, "patch-only"),
+        }
+    }
+}
+
+/// Which pending versions a run may apply (`[migrations] cherry_pick` /
+/// `skip_versions`, or `migrate --versions` / `--skip-versions`). Versions
+/// compare under the configured scheme, so under `numeric` `4` also selects
+/// `V4.0__...`.
+#[derive(Debug, Clone, Default)]
+pub struct VersionFilter {
+    only: Vec<MigrationVersion>,
+    skip: Vec<MigrationVersion>,
+    scheme: VersionScheme,
+}
+
+impl VersionFilter {
+    /// Parse the cherry-picked and skipped version lists under `scheme`.
+    pub fn new(
+        cherry_pick: &[String],
+        skip_versions: &[String],
+        scheme: VersionScheme,
+    ) -> Result<Self> {
+        let parse = |raw: &String| {
+            MigrationVersion::parse_with(raw.trim(), scheme).map_err(|_| {
+                WaypointError::ConfigError(format!("Invalid version '{}' in version filter", raw))
+            })
+        };
+        Ok(Self {
+            only: cherry_pick.iter().map(parse).collect::<Result<_>>()?,
+            skip: skip_versions.iter().map(parse).collect::<Result<_>>()?,
+            scheme,
+        })
+    }
+
+    /// Whether any versions are cherry-picked or skipped.
+    pub fn is_active(&self) -> bool {
+        !self.only.is_empty() || !self.skip.is_empty()
+    }
+
+    /// Whether `version` may be applied in this run.
+    pub fn selects(&self, version: &MigrationVersion) -> bool {
+        let same = |v: &MigrationVersion| v.cmp(version) == Ordering::Equal;
+        (self.only.is_empty() || self.only.iter().any(same)) && !self.skip.iter().any(same)
+    }
+
+    /// Fail when a cherry-picked or skipped version has no migration file,
+    /// so a typo can't silently apply (or leave out) the wrong migration.
+    pub fn check_known(&self, versioned: &[&ResolvedMigration]) -> Result<()> {
+        for v in self.only.iter().chain(&self.skip) {
+            if !versioned
+                .iter()
+                .any(|m| m.version().is_some_and(|mv| mv.cmp(v) == Ordering::Equal))
+            {
+                return Err(WaypointError::ConfigError(format!(
+                    "Version filter names {}, but no versioned migration has that version",
+                    v
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail when `dependency_ordering` pulled in a dependency the filter
+    /// leaves out, naming the versions that need it.
+    pub fn check_dependencies(&self, added: &[String]) -> Result<()> {
+        if let Some(dep) = added
+            .iter()
+            .filter_map(|raw| MigrationVersion::parse_with(raw, self.scheme).ok())
+            .find(|v| !self.selects(v))
+        {
+            return Err(WaypointError::ConfigError(format!(
+                "A selected migration depends on version {}, which the version filter leaves out",
+                dep
+            )));
+        }
+        Ok(())
+    }
+}
+
 /// The type of a migration (for display/serialization).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MigrationType {
     /// V{version}__{description}.sql
     Versioned,
-    /// R__{description}.sql
+    /// R__{description}.sql or R{order}__{description}.sql
     Repeatable,
     /// U{version}__{description}.sql
     Undo,
@@ -130,6 +383,9 @@ pub struct ResolvedMigration {
     pub sql: String,
     /// Parsed directives from SQL comments (e.g., `@depends`, `@environment`).
     pub directives: MigrationDirectives,
+    /// SHA-256 over the SQL and then the `-- waypoint:copy` files, set when
+    /// scanning a location on disk; `checksum` covers the files too.
+    pub copy_checksum_sha256: Option<String>,
 }
 
 impl ResolvedMigration {
@@ -141,6 +397,16 @@ impl ResolvedMigration {
         }
     }
 
+    /// SHA-256 checksum of the migration SQL content (and its
+    /// `-- waypoint:copy` files, see `copy_checksum_sha256`), as lowercase
+    /// hex (see [`crate::checksum::calculate_sha256`]).
+    pub fn checksum_sha256(&self) -> String {
+        match &self.copy_checksum_sha256 {
+            Some(sha256) => sha256.clone(),
+            None => crate::checksum::calculate_sha256(&self.sql),
+        }
+    }
+
     /// Get the migration type for display/serialization.
     pub fn migration_type(&self) -> MigrationType {
         match &self.kind {
@@ -159,6 +425,31 @@ impl ResolvedMigration {
     pub fn is_undo(&self) -> bool {
         matches!(&self.kind, MigrationKind::Undo(_))
     }
+
+    /// Whether this is a Rust code migration, whose `sql` only stands in for
+    /// the checksum and can't be replayed or analyzed.
+    pub fn is_code(&self) -> bool {
+        is_code_script(&self.script)
+    }
+
+    /// Application order of a repeatable migration: the `-- waypoint:order`
+    /// directive, else the `R{order}__` filename prefix. `None` for versioned
+    /// and undo migrations, and for repeatables without either.
+    pub fn repeatable_order(&self) -> Option<u32> {
+        if !matches!(&self.kind, MigrationKind::Repeatable) {
+            return None;
+        }
+        self.directives.order.or_else(|| {
+            let stem = self.script.strip_suffix(".sql")?;
+            REPEATABLE_RE.captures(stem)?.get(1)?.as_str().parse().ok()
+        })
+    }
+}
+
+/// Whether `script` names a Rust code migration (`V{version}__{description}.rs`,
+/// see [`crate::code_migration`]); every scanned or embedded file is `.sql`.
+pub fn is_code_script(script: &str) -> bool {
+    script.ends_with(".rs")
 }
 
 /// Parse a migration filename into its components.
@@ -166,6 +457,7 @@ impl ResolvedMigration {
 /// Expected patterns:
 ///   V{version}__{description}.sql  — versioned migration
 ///   R__{description}.sql           — repeatable migration
+///   R{order}__{description}.sql    — repeatable migration with an order prefix
 pub fn parse_migration_filename(filename: &str) -> Result<(MigrationKind, String)> {
     // Strip .sql extension
     let stem = filename.strip_suffix(".sql").ok_or_else(|| {
@@ -186,7 +478,7 @@ pub fn parse_migration_filename(filename: &str) -> Result<(MigrationKind, String
         let version = MigrationVersion::parse(version_str)?;
         Ok((MigrationKind::Undo(version), description))
     } else if let Some(caps) = REPEATABLE_RE.captures(stem) {
-        let description = caps.get(1).unwrap().as_str().replace('_', " ");
+        let description = caps.get(2).unwrap().as_str().replace('_', " ");
         Ok((MigrationKind::Repeatable, description))
     } else {
         Err(WaypointError::MigrationParseError(format!(
@@ -196,81 +488,375 @@ pub fn parse_migration_filename(filename: &str) -> Result<(MigrationKind, String
     }
 }
 
+/// Per-file limits checked while scanning, so that a file that was never
+/// meant to be a migration (say, a `pg_dump` committed by mistake) fails
+/// with a clear error before it is read into memory or sent to the server.
+/// A limit of 0 disables that check, and both are 0 by default: an already
+/// applied migration that is over a limit would otherwise stop every
+/// command. Also carries the [`VersionScheme`] the scanned files' versions
+/// are parsed under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScanLimits {
+    /// Largest allowed migration file, in bytes.
+    pub max_bytes: u64,
+    /// Most statements allowed in one migration file.
+    pub max_statements: usize,
+    /// Scheme versions are parsed under (`[migrations] version_scheme`).
+    pub version_scheme: VersionScheme,
+}
+
+/// A `-- waypoint:copy` load ready to run: the `COPY` statement and the CSV
+/// file it streams.
+#[derive(Debug, Clone)]
+pub struct CopyLoad {
+    /// Target table as written in the directive.
+    pub table: String,
+    /// `COPY ... FROM STDIN` statement.
+    pub statement: String,
+    /// The CSV file, opened and streamed when the load runs.
+    pub path: std::path::PathBuf,
+}
+
+/// Resolve the CSV files named by `script`'s `-- waypoint:copy` directives,
+/// relative to the location on disk holding `script`, and check that each
+/// can be opened. Pass `sql` with placeholders already replaced.
+///
+/// A malformed directive, a missing file, a script that is not in any local
+/// location (embedded and code migrations) and a `-- waypoint:copy` line
+/// below the header comments are errors, so a load is never skipped or run
+/// from the wrong place silently. Loads run after all of the file's SQL,
+/// which a directive placed between statements would misrepresent.
+pub fn load_copies(
+    script: &str,
+    sql: &str,
+    locations: &[std::path::PathBuf],
+) -> Result<Vec<CopyLoad>> {
+    reject_misplaced_copies(script, sql)?;
+    let lines: Vec<_> = directive::directive_lines(sql)
+        .into_iter()
+        .filter(|d| d.name == "copy")
+        .collect();
+    if lines.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dir = locations
+        .iter()
+        .find(|location| location.join(script).is_file())
+        .ok_or_else(|| {
+            WaypointError::ConfigError(format!(
+                "{}: -- waypoint:copy needs the migration in a location on disk; \
+                 it is not in any of the configured locations",
+                script
+            ))
+        })?;
+    lines
+        .iter()
+        .map(|d| {
+            let copy = directive::CopyDirective::parse(&d.value)?;
+            let path = dir.join(&copy.file);
+            open_copy_file(script, &path)?;
+            Ok(CopyLoad {
+                statement: crate::db::copy_statement(&copy),
+                table: copy.table,
+                path,
+            })
+        })
+        .collect()
+}
+
+impl CopyLoad {
+    /// Open the CSV file for streaming; `script` names the migration in errors.
+    pub fn open(&self, script: &str) -> Result<std::fs::File> {
+        open_copy_file(script, &self.path)
+    }
+}
+
+/// Open a `-- waypoint:copy` file, naming the script and path on failure.
+pub(crate) fn open_copy_file(script: &str, path: &std::path::Path) -> Result<std::fs::File> {
+    std::fs::File::open(path).map_err(|e| {
+        WaypointError::IoError(std::io::Error::new(
+            e.kind(),
+            format!(
+                "{}: failed to read copy file '{}': {}",
+                script,
+                path.display(),
+                e
+            ),
+        ))
+    })
+}
+
+/// Directives are only read from the header comments, so a
+/// `-- waypoint:copy` line after the first statement would never run.
+fn reject_misplaced_copies(script: &str, sql: &str) -> Result<()> {
+    let mut in_header = true;
+    for (idx, line) in sql.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let Some(comment) = trimmed.strip_prefix("--") else {
+            in_header = false;
+            continue;
+        };
+        if !in_header && comment.trim_start().starts_with("waypoint:copy") {
+            return Err(WaypointError::ConfigError(format!(
+                "{}: line {}: -- waypoint:copy must be in the header comments at the top \
+                 of the file; loads run after all of the file's SQL",
+                script,
+                idx + 1
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fold the `-- waypoint:copy` files of `migration`, scanned from `dir`, into
+/// its checksums, so editing a CSV reads as a changed migration. Files named
+/// with a placeholder are only known when the migration runs and are left
+/// out.
+fn checksum_copy_files(migration: &mut ResolvedMigration, dir: &std::path::Path) -> Result<()> {
+    use std::io::Read;
+
+    let files: Vec<_> = migration
+        .directives
+        .copy
+        .iter()
+        .filter(|copy| !copy.file.contains("${"))
+        .map(|copy| dir.join(&copy.file))
+        .collect();
+    if files.is_empty() {
+        return Ok(());
+    }
+    let mut hasher = crate::checksum::ContentHasher::new(&migration.sql);
+    let mut buf = vec![0u8; 64 * 1024];
+    for path in &files {
+        let mut file = open_copy_file(&migration.script, path)?;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+    }
+    let (checksum, sha256) = hasher.finish();
+    migration.checksum = checksum;
+    migration.copy_checksum_sha256 = Some(sha256);
+    Ok(())
+}
+
 /// Scan migration locations for SQL files and parse them into ResolvedMigrations.
+///
+/// Applies the default [`ScanLimits`]; see [`scan_migrations_with_limits`].
 pub fn scan_migrations(locations: &[std::path::PathBuf]) -> Result<Vec<ResolvedMigration>> {
+    scan_migrations_with_limits(locations, &ScanLimits::default())
+}
+
+/// Scan migration locations, failing with `MigrationTooLarge` on the first
+/// file over one of `limits`. Remote locations are fetched first with fresh
+/// default [`LocationProviders`](crate::location::LocationProviders), so on
+/// every call; `MigrationSettings::resolve` reuses its providers' checkouts.
+pub fn scan_migrations_with_limits(
+    locations: &[std::path::PathBuf],
+    limits: &ScanLimits,
+) -> Result<Vec<ResolvedMigration>> {
+    let fetched = crate::location::fetch(locations, &Default::default())?;
     let mut migrations = Vec::new();
 
-    for location in locations {
+    for location in &fetched.locations {
         if !location.exists() {
             log::warn!("Migration location does not exist: {}", location.display());
             continue;
         }
+        for mut migration in resolve_dir(&DiskDir(location), limits)? {
+            checksum_copy_files(&mut migration, location)?;
+            migrations.push(migration);
+        }
+    }
+
+    sort_migrations(&mut migrations)?;
+    Ok(migrations)
+}
+
+/// A flat directory of migration files, read by [`scan_migration_dir`]:
+/// a location on disk ([`DiskDir`]), files embedded in the binary
+/// ([`EmbeddedMigrations`](crate::embedded::EmbeddedMigrations)) or any
+/// other store.
+pub trait MigrationDir {
+    /// Names of the files in the directory, in any order.
+    fn file_names(&self) -> Result<Vec<String>>;
 
-        let entries = std::fs::read_dir(location).map_err(|e| {
+    /// Size of `name` in bytes, checked against the limits before it is read.
+    fn file_size(&self, name: &str) -> Result<u64>;
+
+    /// Content of `name`.
+    fn read_file(&self, name: &str) -> Result<String>;
+}
+
+/// A migration location on disk. Subdirectories are not descended into.
+#[derive(Debug, Clone, Copy)]
+pub struct DiskDir<'a>(pub &'a std::path::Path);
+
+impl MigrationDir for DiskDir<'_> {
+    fn file_names(&self) -> Result<Vec<String>> {
+        let entries = std::fs::read_dir(self.0).map_err(|e| {
             WaypointError::IoError(std::io::Error::new(
                 e.kind(),
                 format!(
                     "Failed to read migration directory '{}': {}",
-                    location.display(),
+                    self.0.display(),
                     e
                 ),
             ))
         })?;
-
+        let mut names = Vec::new();
         for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
-
+            let path = entry?.path();
             if !path.is_file() {
                 continue;
             }
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
 
-            let filename = match path.file_name().and_then(|n| n.to_str()) {
-                Some(name) => name.to_string(),
-                None => continue,
-            };
+    fn file_size(&self, name: &str) -> Result<u64> {
+        Ok(std::fs::metadata(self.0.join(name))?.len())
+    }
 
-            // Skip non-SQL files
-            if !filename.ends_with(".sql") {
-                continue;
-            }
+    fn read_file(&self, name: &str) -> Result<String> {
+        Ok(std::fs::read_to_string(self.0.join(name))?)
+    }
+}
 
-            // Skip hook callback files
-            if hooks::is_hook_file(&filename) {
-                continue;
-            }
+/// Resolve the migrations in `dir`, sorted, failing with `MigrationTooLarge`
+/// on the first file over one of `limits`. Files that are not migrations are
+/// skipped, as in a scanned location.
+pub fn scan_migration_dir(
+    dir: &dyn MigrationDir,
+    limits: &ScanLimits,
+) -> Result<Vec<ResolvedMigration>> {
+    let mut migrations = resolve_dir(dir, limits)?;
+    sort_migrations(&mut migrations)?;
+    Ok(migrations)
+}
 
-            // Skip files that don't start with V, U, or R
-            if !filename.starts_with('V')
-                && !filename.starts_with('U')
-                && !filename.starts_with('R')
-            {
-                continue;
-            }
+/// The migrations in `dir`, unsorted.
+pub(crate) fn resolve_dir(
+    dir: &dyn MigrationDir,
+    limits: &ScanLimits,
+) -> Result<Vec<ResolvedMigration>> {
+    let mut migrations = Vec::new();
+    for filename in dir.file_names()? {
+        let Some((kind, description)) = parse_candidate(&filename) else {
+            continue;
+        };
+        check_size(&filename, dir.file_size(&filename)?, limits)?;
+        let sql = dir.read_file(&filename)?;
+        migrations.push(resolve_sql(kind, description, filename, sql, limits)?);
+    }
+    Ok(migrations)
+}
 
-            let (kind, description) = match parse_migration_filename(&filename) {
-                Ok(result) => result,
-                Err(e) => {
-                    log::warn!("Skipping malformed migration file '{}': {}", filename, e);
-                    continue;
-                }
-            };
-            let sql = std::fs::read_to_string(&path)?;
-            let checksum = calculate_checksum(&sql);
-            let directives = directive::parse_directives(&sql);
-
-            migrations.push(ResolvedMigration {
-                kind,
-                description,
+/// Kind and description of a file that should be resolved as a migration,
+/// or `None` for anything else in a location: non-SQL files, hook scripts,
+/// and malformed names (which are logged).
+pub(crate) fn parse_candidate(filename: &str) -> Option<(MigrationKind, String)> {
+    // Skip non-SQL files
+    if !filename.ends_with(".sql") {
+        return None;
+    }
+
+    // Skip hook callback files
+    if hooks::is_hook_file(filename) {
+        return None;
+    }
+
+    // Skip files that don't start with V, U, or R
+    if !filename.starts_with('V') && !filename.starts_with('U') && !filename.starts_with('R') {
+        return None;
+    }
+
+    match parse_migration_filename(filename) {
+        Ok(result) => Some(result),
+        Err(e) => {
+            log::warn!("Skipping malformed migration file '{}': {}", filename, e);
+            None
+        }
+    }
+}
+
+/// Fail with `MigrationTooLarge` when a file of `size` bytes is over
+/// `limits.max_bytes`, before it is read.
+pub(crate) fn check_size(filename: &str, size: u64, limits: &ScanLimits) -> Result<()> {
+    if limits.max_bytes > 0 && size > limits.max_bytes {
+        return Err(WaypointError::MigrationTooLarge {
+            script: filename.to_string(),
+            reason: format!(
+                "{} bytes, over max_migration_bytes = {}",
+                size, limits.max_bytes
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Build the [`ResolvedMigration`] for a migration's content, checking
+/// `limits.max_statements` and parsing its version under
+/// `limits.version_scheme`.
+pub(crate) fn resolve_sql(
+    kind: MigrationKind,
+    description: String,
+    filename: String,
+    sql: String,
+    limits: &ScanLimits,
+) -> Result<ResolvedMigration> {
+    if limits.max_statements > 0 {
+        let statements = crate::sql_parser::split_statements(&sql).len();
+        if statements > limits.max_statements {
+            return Err(WaypointError::MigrationTooLarge {
                 script: filename,
-                checksum,
-                sql,
-                directives,
+                reason: format!(
+                    "{} statements, over max_statements = {}",
+                    statements, limits.max_statements
+                ),
             });
         }
     }
+    let kind = match kind {
+        MigrationKind::Versioned(v) => {
+            MigrationKind::Versioned(v.in_scheme(limits.version_scheme)?)
+        }
+        MigrationKind::Undo(v) => MigrationKind::Undo(v.in_scheme(limits.version_scheme)?),
+        MigrationKind::Repeatable => MigrationKind::Repeatable,
+    };
+    let checksum = calculate_checksum(&sql);
+    let directives = directive::parse_directives(&sql).map_err(|e| match e {
+        WaypointError::ConfigError(msg) => {
+            WaypointError::ConfigError(format!("{}: {}", filename, msg))
+        }
+        e => e,
+    })?;
+
+    Ok(ResolvedMigration {
+        kind,
+        description,
+        script: filename,
+        checksum,
+        sql,
+        directives,
+        copy_checksum_sha256: None,
+    })
+}
 
-    // Sort: versioned by version, then undo by version, then repeatable by description
+/// Sort migrations into apply order and reject duplicate versions.
+///
+/// Versioned by version, then undo by version, then repeatable by order
+/// (repeatables without one last), then description.
+pub(crate) fn sort_migrations(migrations: &mut [ResolvedMigration]) -> Result<()> {
     migrations.sort_by(|a, b| {
         // Order groups: Versioned first, then Undo, then Repeatable
         fn group_order(kind: &MigrationKind) -> u8 {
@@ -289,7 +875,13 @@ pub fn scan_migrations(locations: &[std::path::PathBuf]) -> Result<Vec<ResolvedM
             (MigrationKind::Versioned(va), MigrationKind::Versioned(vb)) => va.cmp(vb),
             (MigrationKind::Undo(va), MigrationKind::Undo(vb)) => va.cmp(vb),
             (MigrationKind::Repeatable, MigrationKind::Repeatable) => {
-                a.description.cmp(&b.description)
+                match (a.repeatable_order(), b.repeatable_order()) {
+                    (Some(oa), Some(ob)) => oa.cmp(&ob),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+                .then_with(|| a.description.cmp(&b.description))
             }
             _ => Ordering::Equal,
         }
@@ -297,7 +889,7 @@ pub fn scan_migrations(locations: &[std::path::PathBuf]) -> Result<Vec<ResolvedM
 
     // Detect duplicate versions
     let mut seen_versions: std::collections::HashSet<String> = std::collections::HashSet::new();
-    for m in &migrations {
+    for m in migrations.iter() {
         if let Some(v) = m.version() {
             let prefix = if m.is_versioned() { "V" } else { "U" };
             let key = format!("{}{}", prefix, v.raw);
@@ -310,13 +902,62 @@ pub fn scan_migrations(locations: &[std::path::PathBuf]) -> Result<Vec<ResolvedM
         }
     }
 
-    Ok(migrations)
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_scan_limits() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("V1__Small.sql"), "SELECT 1;").unwrap();
+        std::fs::write(
+            dir.path().join("V2__Dump.sql"),
+            "CREATE TABLE a (id int);\n".repeat(20),
+        )
+        .unwrap();
+        let locations = [dir.path().to_path_buf()];
+
+        assert_eq!(scan_migrations(&locations).unwrap().len(), 2);
+
+        let limits = ScanLimits {
+            max_bytes: 100,
+            max_statements: 0,
+            ..ScanLimits::default()
+        };
+        match scan_migrations_with_limits(&locations, &limits).unwrap_err() {
+            WaypointError::MigrationTooLarge { script, reason } => {
+                assert_eq!(script, "V2__Dump.sql");
+                assert!(reason.contains("max_migration_bytes = 100"), "{reason}");
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+
+        let limits = ScanLimits {
+            max_bytes: 0,
+            max_statements: 10,
+            ..ScanLimits::default()
+        };
+        let err = scan_migrations_with_limits(&locations, &limits).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("20 statements, over max_statements = 10"));
+
+        let unlimited = ScanLimits {
+            max_bytes: 0,
+            max_statements: 0,
+            ..ScanLimits::default()
+        };
+        assert_eq!(
+            scan_migrations_with_limits(&locations, &unlimited)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
     #[test]
     fn test_version_parsing() {
         let v = MigrationVersion::parse("1").unwrap();
@@ -350,6 +991,101 @@ mod tests {
         assert!(MigrationVersion::parse("abc").is_err());
     }
 
+    #[test]
+    fn test_version_schemes() {
+        let v = |s: &str, scheme| MigrationVersion::parse_with(s, scheme).unwrap();
+        let cmp =
+            |scheme: VersionScheme, a: &str, b: &str| scheme.compare(&v(a, scheme), &v(b, scheme));
+
+        use VersionScheme::*;
+        assert_eq!(cmp(Numeric, "2024.06.12.1", "2024.6.12.2"), Ordering::Less);
+        assert_eq!(cmp(Numeric, "0010", "10"), Ordering::Equal);
+        assert_eq!(cmp(Lexicographic, "0010", "10"), Ordering::Less);
+        assert_eq!(cmp(Lexicographic, "1_10", "1.9"), Ordering::Less);
+        assert_eq!(cmp(Lexicographic, "1_2", "1.2"), Ordering::Equal);
+        assert_eq!(cmp(Semver, "1.9", "1.10"), Ordering::Less);
+
+        assert!(MigrationVersion::parse_with("1.02", Semver).is_err());
+        assert!(MigrationVersion::parse_with("1.2.3.4", Semver).is_err());
+        assert!(MigrationVersion::parse_with("1.0.10", Semver).is_ok());
+
+        assert_eq!("numeric_dotted".parse::<VersionScheme>().unwrap(), Numeric);
+        assert_eq!(
+            "Lexicographic".parse::<VersionScheme>().unwrap(),
+            Lexicographic
+        );
+        assert!("natural".parse::<VersionScheme>().is_err());
+    }
+
+    #[test]
+    fn test_version_eq_agrees_with_ord() {
+        use VersionScheme::*;
+        let lex = |s: &str| MigrationVersion::parse_with(s, Lexicographic).unwrap();
+        assert_eq!(lex("1_2"), lex("1.2"));
+        assert_eq!(lex("1_2").cmp(&lex("1.2")), Ordering::Equal);
+        assert_ne!(lex("0010"), lex("10"));
+
+        let num = |s: &str| MigrationVersion::parse_with(s, Numeric).unwrap();
+        assert_eq!(num("1.2"), num("1.2.0"));
+        assert!(num("1.9") < num("1.10"));
+        assert!(lex("1.10") < lex("1.9"));
+        assert_eq!(num("1.10").in_scheme(Lexicographic).unwrap(), lex("1.10"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "different schemes")]
+    fn test_version_cmp_rejects_mixed_schemes() {
+        let lex = MigrationVersion::parse_with("2", VersionScheme::Lexicographic).unwrap();
+        let _ = lex.cmp(&MigrationVersion::parse("9").unwrap());
+    }
+
+    #[test]
+    fn test_out_of_order_policy() {
+        let v = |s: &str| MigrationVersion::parse(s).unwrap();
+        let applied = [v("3"), v("4"), v("5")];
+
+        let patch_only: OutOfOrderPolicy = "patch-only".parse().unwrap();
+        assert!(patch_only.allows(&v("4.1"), applied.iter()));
+        assert!(!patch_only.allows(&v("2.1"), applied.iter()));
+        assert!(OutOfOrderPolicy::Always.allows(&v("2"), applied.iter()));
+        assert!(!OutOfOrderPolicy::Never.allows(&v("4.1"), applied.iter()));
+        assert!("sometimes".parse::<OutOfOrderPolicy>().is_err());
+        assert_eq!(
+            "patch_only".parse::<OutOfOrderPolicy>().unwrap(),
+            patch_only
+        );
+    }
+
+    #[test]
+    fn test_version_filter() {
+        let v = |s: &str| MigrationVersion::parse(s).unwrap();
+        let strings = |l: &[&str]| l.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let all = VersionFilter::default();
+        assert!(!all.is_active());
+        assert!(all.selects(&v("6")));
+
+        let filter = VersionFilter::new(
+            &strings(&["4", "7", "9"]),
+            &strings(&["7"]),
+            VersionScheme::Numeric,
+        )
+        .unwrap();
+        assert!(filter.is_active());
+        assert!(filter.selects(&v("4")));
+        assert!(filter.selects(&v("4.0")));
+        assert!(!filter.selects(&v("5")));
+        assert!(!filter.selects(&v("7")));
+        assert!(filter.check_dependencies(&strings(&["9"])).is_ok());
+        assert!(filter.check_dependencies(&strings(&["5"])).is_err());
+
+        let skip = VersionFilter::new(&[], &strings(&["6"]), VersionScheme::Numeric).unwrap();
+        assert!(skip.selects(&v("5")) && !skip.selects(&v("6")));
+
+        assert!(VersionFilter::new(&strings(&["x"]), &[], VersionScheme::Numeric).is_err());
+    }
+
     #[test]
     fn test_parse_versioned_filename() {
         let (kind, desc) = parse_migration_filename("V1__Create_users.sql").unwrap();
@@ -377,6 +1113,45 @@ mod tests {
         assert_eq!(desc, "Create user view");
     }
 
+    #[test]
+    fn test_parse_ordered_repeatable_filename() {
+        let (kind, desc) = parse_migration_filename("R10__Create_user_view.sql").unwrap();
+        assert!(matches!(kind, MigrationKind::Repeatable));
+        assert_eq!(desc, "Create user view");
+    }
+
+    #[test]
+    fn test_scan_orders_repeatables() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for (name, sql) in [
+            ("R__Alpha.sql", "SELECT 1;"),
+            ("R20__Base_views.sql", "SELECT 1;"),
+            ("R3__Zeta.sql", "SELECT 1;"),
+            ("R__Reports.sql", "-- waypoint:order 5\nSELECT 1;"),
+            ("R1__Grants.sql", "-- waypoint:order 30\nSELECT 1;"),
+            ("V1__Init.sql", "SELECT 1;"),
+        ] {
+            std::fs::write(dir.path().join(name), sql).unwrap();
+        }
+
+        let migrations = scan_migrations(&[dir.path().to_path_buf()]).unwrap();
+        let scripts: Vec<(&str, Option<u32>)> = migrations
+            .iter()
+            .map(|m| (m.script.as_str(), m.repeatable_order()))
+            .collect();
+        assert_eq!(
+            scripts,
+            vec![
+                ("V1__Init.sql", None),
+                ("R3__Zeta.sql", Some(3)),
+                ("R__Reports.sql", Some(5)),
+                ("R20__Base_views.sql", Some(20)),
+                ("R1__Grants.sql", Some(30)),
+                ("R__Alpha.sql", None),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_invalid_filename() {
         assert!(parse_migration_filename("random.sql").is_err());
@@ -420,10 +1195,45 @@ mod tests {
             checksum: 0,
             sql: String::new(),
             directives: MigrationDirectives::default(),
+            copy_checksum_sha256: None,
         };
         assert!(m.is_undo());
         assert!(!m.is_versioned());
         assert_eq!(m.migration_type(), MigrationType::Undo);
         assert_eq!(m.migration_type().to_string(), "UNDO_SQL");
     }
+
+    #[test]
+    fn test_copy_files_checksummed_and_placement_checked() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let script = "V1__Countries.sql";
+        let sql = "-- waypoint:copy table=countries file=countries.csv\n\
+                   CREATE TABLE countries (code text);\n";
+        std::fs::write(dir.path().join(script), sql).unwrap();
+        std::fs::write(dir.path().join("countries.csv"), "code\nNL\n").unwrap();
+        let locations = vec![dir.path().to_path_buf()];
+
+        let before = scan_migrations(&locations).unwrap().remove(0);
+        assert_ne!(before.checksum, crate::checksum::calculate_checksum(sql));
+        assert_ne!(
+            before.checksum_sha256(),
+            crate::checksum::calculate_sha256(sql)
+        );
+        std::fs::write(dir.path().join("countries.csv"), "code\nBE\n").unwrap();
+        let after = scan_migrations(&locations).unwrap().remove(0);
+        assert_ne!(before.checksum, after.checksum);
+        assert_ne!(before.checksum_sha256(), after.checksum_sha256());
+
+        let copies = load_copies(script, sql, &locations).unwrap();
+        assert_eq!(copies[0].path, dir.path().join("countries.csv"));
+
+        // Not on disk in any location: no fallback to the working directory.
+        assert!(load_copies("V9__Embedded.sql", sql, &locations).is_err());
+
+        // A copy line below the first statement would never run.
+        let misplaced = "CREATE TABLE countries (code text);\n\
+                         -- waypoint:copy table=countries file=countries.csv\n";
+        let err = load_copies(script, misplaced, &locations).unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
 }
@@ -6,6 +6,7 @@
 //! database from the URL scheme.
 
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
 
 use serde::Serialize;
 
@@ -13,6 +14,9 @@ use crate::config::{DatabaseConfig, HooksConfig, MigrationSettings, WaypointConf
 use crate::db::DbClient;
 use crate::dialect::DialectKind;
 use crate::error::{Result, WaypointError};
+use crate::guard::{self, GuardsConfig};
+use crate::notify::{LifecycleEvent, Notifier};
+use crate::warning::Warning;
 
 /// Configuration for a single named database within a multi-db setup.
 #[derive(Debug, Clone)]
@@ -29,22 +33,35 @@ pub struct NamedDatabaseConfig {
     pub placeholders: HashMap<String, String>,
     /// Names of other databases that must be migrated before this one.
     pub depends_on: Vec<String>,
+    /// Labels for selecting databases, e.g. `multi exec --tag eu`.
+    pub tags: Vec<String>,
+    /// Guard settings, shared by every database (`[guards]`).
+    pub guards: GuardsConfig,
 }
 
 impl NamedDatabaseConfig {
-    /// Convert to a standalone WaypointConfig for running commands.
-    pub fn to_waypoint_config(&self) -> WaypointConfig {
-        WaypointConfig {
+    /// This database's settings on top of `base`, keeping the rest of `base`
+    /// (safety, strict mode, policy, ...), with strict mode applied to the
+    /// result.
+    pub fn merge_into(&self, base: &WaypointConfig) -> Result<WaypointConfig> {
+        let mut config = WaypointConfig {
             database: self.database.clone(),
             migrations: self.migrations.clone(),
             hooks: self.hooks.clone(),
             placeholders: self.placeholders.clone(),
-            ..WaypointConfig::default()
-        }
+            guards: self.guards.clone(),
+            multi_database: None,
+            ..base.clone()
+        };
+        config.apply_strict()?;
+        Ok(config)
     }
 }
 
 /// Multi-database orchestration entry point.
+///
+/// Each function takes the loaded config as `base`; every database runs with
+/// its own settings merged onto it ([`NamedDatabaseConfig::merge_into`]).
 pub struct MultiWaypoint {
     /// List of all database configurations to orchestrate.
     pub databases: Vec<NamedDatabaseConfig>,
@@ -59,6 +76,187 @@ pub struct DatabaseResult {
     pub success: bool,
     /// Human-readable summary of the operation result.
     pub message: String,
+    /// Scripts applied on this database, in order.
+    pub applied: Vec<String>,
+    /// Wall-clock time spent on this database, including connecting.
+    pub duration_ms: u64,
+}
+
+impl DatabaseResult {
+    /// Result of migrating one database, from the outcome of the run.
+    fn migrated(
+        name: String,
+        outcome: Result<crate::commands::migrate::MigrateReport>,
+        started: std::time::Instant,
+    ) -> Self {
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match outcome {
+            Ok(report) => DatabaseResult {
+                name,
+                success: true,
+                message: format!(
+                    "Applied {} migration(s) ({}ms)",
+                    report.migrations_applied, report.total_time_ms
+                ),
+                applied: report
+                    .details
+                    .iter()
+                    .map(|d| match &d.schema {
+                        Some(schema) => format!("{}/{}", schema, d.script),
+                        None => d.script.clone(),
+                    })
+                    .collect(),
+                duration_ms,
+            },
+            Err(e) => DatabaseResult {
+                name,
+                success: false,
+                message: format!("{}", e),
+                applied: Vec::new(),
+                duration_ms,
+            },
+        }
+    }
+
+    /// A database that was not run at all.
+    fn not_run(name: String, message: impl Into<String>) -> Self {
+        DatabaseResult {
+            name,
+            success: false,
+            message: message.into(),
+            applied: Vec::new(),
+            duration_ms: 0,
+        }
+    }
+}
+
+/// Options for [`MultiWaypoint::apply_single`].
+#[derive(Debug, Clone, Default)]
+pub struct ApplySingleOptions {
+    /// Version of the migration to apply.
+    pub version: String,
+    /// Only databases with at least one of these tags; empty means all.
+    pub tags: Vec<String>,
+    /// Report where the migration is pending without applying it.
+    pub dry_run: bool,
+    /// Start no further databases after one fails.
+    pub fail_fast: bool,
+    /// Override DANGER safety verdicts (PostgreSQL).
+    pub force: bool,
+}
+
+/// Options for [`MultiWaypoint::for_each_tenant`].
+#[derive(Debug, Clone)]
+pub struct TenantOptions {
+    /// Query run on the control database. Its first column is a tenant's
+    /// connection URL; an optional second column names the tenant in the
+    /// report (otherwise the URL without its password is used).
+    pub query: String,
+    /// Maximum number of tenants migrated at the same time.
+    pub concurrency: usize,
+    /// Migrate up to this version only.
+    pub target_version: Option<String>,
+    /// Override DANGER safety verdicts (PostgreSQL).
+    pub force: bool,
+    /// Start no further tenants after one fails.
+    pub fail_fast: bool,
+    /// Report what each tenant would apply without applying it.
+    pub dry_run: bool,
+}
+
+/// Default for [`TenantOptions::concurrency`].
+pub const DEFAULT_TENANT_CONCURRENCY: usize = 4;
+
+/// A health check polled while canary databases bake, on top of
+/// [`CanaryOptions::health_sql`]. Callers implement it for checks waypoint
+/// can't run itself, such as the CLI's `--health-url`.
+#[async_trait::async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// How the check is named in the report, e.g. the URL it fetches.
+    fn describe(&self) -> String;
+    /// Run the check once; `Err` says why it failed.
+    async fn probe(&self) -> std::result::Result<(), String>;
+}
+
+/// Options for [`MultiWaypoint::migrate_canary`].
+pub struct CanaryOptions {
+    /// Databases migrated first.
+    pub databases: Vec<String>,
+    /// How long the canaries must stay healthy before the rest migrate.
+    pub bake_time: Duration,
+    /// Pause between health check rounds while baking.
+    pub poll_interval: Duration,
+    /// Query run on every canary each round. The check fails when the query
+    /// errors, returns no rows, or its first column is false, 0 or NULL.
+    pub health_sql: Option<String>,
+    /// Further checks run once per round.
+    pub probes: Vec<Box<dyn HealthProbe>>,
+}
+
+/// How a canary rollout ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanaryOutcome {
+    /// The canaries stayed healthy and the rest were migrated.
+    Promoted,
+    /// A canary failed to migrate; the rest were left alone.
+    CanaryFailed,
+    /// A health check failed while baking; the rest were left alone.
+    Unhealthy,
+}
+
+/// One health check result recorded while baking.
+#[derive(Debug, Serialize)]
+pub struct HealthCheckResult {
+    /// Seconds into the bake when the check ran.
+    pub elapsed_secs: u64,
+    /// Canary the check ran on; `None` for probes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    /// `health_sql`, or the probe's description.
+    pub check: String,
+    /// Whether the check passed.
+    pub passed: bool,
+    /// Why the check failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HealthCheckResult {
+    fn new(
+        elapsed_secs: u64,
+        database: Option<String>,
+        check: String,
+        outcome: std::result::Result<(), String>,
+    ) -> Self {
+        HealthCheckResult {
+            elapsed_secs,
+            database,
+            check,
+            passed: outcome.is_ok(),
+            error: outcome.err(),
+        }
+    }
+}
+
+/// The canary phase of a [`MultiWaypoint::migrate_canary`] run.
+#[derive(Debug, Serialize)]
+pub struct CanaryReport {
+    /// Canary databases, in the order they were migrated.
+    pub databases: Vec<String>,
+    /// Configured bake time.
+    pub bake_time_secs: u64,
+    /// Time actually spent baking; shorter when a check failed.
+    pub baked_secs: u64,
+    /// Every health check result, in the order they ran.
+    pub checks: Vec<HealthCheckResult>,
+    /// How the rollout ended.
+    pub outcome: CanaryOutcome,
+}
+
+/// Parse a duration such as `90s`, `30m` or `2h`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    crate::config::parse_duration_units("duration", s, &[('s', 1), ('m', 60), ('h', 3600)])
 }
 
 /// Aggregate result from a multi-db operation.
@@ -68,6 +266,12 @@ pub struct MultiResult {
     pub results: Vec<DatabaseResult>,
     /// Whether every database operation succeeded.
     pub all_succeeded: bool,
+    /// The canary phase, for [`MultiWaypoint::migrate_canary`] runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryReport>,
+    /// Problems that didn't fail the run, such as undelivered webhooks.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
 }
 
 impl MultiWaypoint {
@@ -139,6 +343,7 @@ impl MultiWaypoint {
     /// database is auto-detected from the URL scheme — mixed PG/MySQL configs
     /// are fully supported here.
     pub async fn connect(
+        base: &WaypointConfig,
         databases: &[NamedDatabaseConfig],
         filter: Option<&str>,
     ) -> Result<HashMap<String, DbClient>> {
@@ -151,44 +356,72 @@ impl MultiWaypoint {
                 }
             }
 
-            let config = db.to_waypoint_config();
+            let config = db.merge_into(base)?;
             let conn_string = config.connection_string()?;
             let client = connect_one(&conn_string, &config).await?;
             clients.insert(db.name.clone(), client);
         }
 
-        if let Some(name_filter) = filter {
-            if !clients.contains_key(name_filter) {
-                let available = databases
-                    .iter()
-                    .map(|d| d.name.clone())
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                return Err(WaypointError::DatabaseNotFound {
-                    name: name_filter.to_string(),
-                    available,
-                });
+        check_filter_matched(databases, filter, &clients)?;
+        Ok(clients)
+    }
+
+    /// Like [`Self::connect`], but databases named in `pools` check a
+    /// connection out of their pool instead of connecting from their URL.
+    #[cfg(feature = "postgres")]
+    pub async fn connect_with_pools(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        filter: Option<&str>,
+        pools: &HashMap<String, &dyn crate::db::PostgresPool>,
+    ) -> Result<HashMap<String, DbClient>> {
+        let mut clients = HashMap::new();
+
+        for db in databases {
+            if filter.is_some_and(|name_filter| db.name != name_filter) {
+                continue;
             }
+
+            let client = match pools.get(&db.name) {
+                Some(pool) => DbClient::from_pool(*pool).await?,
+                None => {
+                    let config = db.merge_into(base)?;
+                    let conn_string = config.connection_string()?;
+                    connect_one(&conn_string, &config).await?
+                }
+            };
+            clients.insert(db.name.clone(), client);
         }
 
+        check_filter_matched(databases, filter, &clients)?;
         Ok(clients)
     }
 
     /// Run migrate on all databases in dependency order.
     pub async fn migrate(
+        base: &WaypointConfig,
         databases: &[NamedDatabaseConfig],
         clients: &HashMap<String, DbClient>,
         order: &[String],
         target_version: Option<&str>,
         fail_fast: bool,
     ) -> Result<MultiResult> {
-        Self::migrate_with_options(databases, clients, order, target_version, fail_fast, false)
-            .await
+        Self::migrate_with_options(
+            base,
+            databases,
+            clients,
+            order,
+            target_version,
+            fail_fast,
+            false,
+        )
+        .await
     }
 
     /// Run migrate on all databases in dependency order with the `force`
     /// flag for overriding DANGER safety verdicts on PostgreSQL.
     pub async fn migrate_with_options(
+        base: &WaypointConfig,
         databases: &[NamedDatabaseConfig],
         clients: &HashMap<String, DbClient>,
         order: &[String],
@@ -204,37 +437,22 @@ impl MultiWaypoint {
 
             match (db, client) {
                 (Some(db), Some(client)) => {
-                    let config = db.to_waypoint_config();
-                    let outcome = dispatch_migrate(client, &config, target_version, force).await;
-                    match outcome {
-                        Ok(report) => {
-                            results.push(DatabaseResult {
-                                name: name.clone(),
-                                success: true,
-                                message: format!(
-                                    "Applied {} migration(s) ({}ms)",
-                                    report.migrations_applied, report.total_time_ms
-                                ),
-                            });
-                        }
-                        Err(e) => {
-                            results.push(DatabaseResult {
-                                name: name.clone(),
-                                success: false,
-                                message: format!("{}", e),
-                            });
-                            if fail_fast {
-                                break;
-                            }
-                        }
+                    let config = db.merge_into(base)?;
+                    let started = std::time::Instant::now();
+                    let outcome =
+                        dispatch_migrate_schemas(client, &config, target_version, force).await;
+                    let result = DatabaseResult::migrated(name.clone(), outcome, started);
+                    let failed = !result.success;
+                    results.push(result);
+                    if failed && fail_fast {
+                        break;
                     }
                 }
                 _ => {
-                    results.push(DatabaseResult {
-                        name: name.clone(),
-                        success: false,
-                        message: "Database not connected".to_string(),
-                    });
+                    results.push(DatabaseResult::not_run(
+                        name.clone(),
+                        "Database not connected",
+                    ));
                     if fail_fast {
                         break;
                     }
@@ -246,11 +464,434 @@ impl MultiWaypoint {
         Ok(MultiResult {
             results,
             all_succeeded,
+            canary: None,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Apply only the migration with `options.version` on every database
+    /// (with one of `options.tags`, if any) in dependency order, leaving
+    /// anything else pending, repeatable migrations included: for an
+    /// already-reviewed emergency fix where a full migrate across the fleet
+    /// is too risky. Each database runs `migrate` with `cherry_pick` set to
+    /// the version, so out-of-order and dependency checks apply as usual,
+    /// and a database with no such migration fails. Where it is already
+    /// applied, nothing runs. A database whose `depends_on` failed is
+    /// skipped. With `dry_run`, each database only reports whether the
+    /// migration is pending there.
+    pub async fn apply_single(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        clients: &HashMap<String, DbClient>,
+        order: &[String],
+        options: &ApplySingleOptions,
+    ) -> Result<MultiResult> {
+        let version = options.version.as_str();
+        let databases: Vec<NamedDatabaseConfig> = databases
+            .iter()
+            .filter(|db| {
+                options.tags.is_empty() || db.tags.iter().any(|t| options.tags.contains(t))
+            })
+            .cloned()
+            .map(|mut db| {
+                db.migrations.cherry_pick = vec![version.to_string()];
+                db.migrations.skip_versions.clear();
+                db.migrations.skip_repeatables = true;
+                db
+            })
+            .collect();
+        let order: Vec<&String> = order
+            .iter()
+            .filter(|name| databases.iter().any(|d| &d.name == *name))
+            .collect();
+        if order.is_empty() {
+            return Err(WaypointError::ConfigError(format!(
+                "No database in [[databases]] is tagged {}",
+                options.tags.join(" or ")
+            )));
+        }
+
+        let mut results: Vec<DatabaseResult> = Vec::new();
+        for name in order {
+            let db = databases
+                .iter()
+                .find(|d| &d.name == name)
+                .expect("filtered above");
+            let failed_dep = db
+                .depends_on
+                .iter()
+                .find(|dep| results.iter().any(|r| &r.name == *dep && !r.success));
+            let result = match (failed_dep, clients.get(name)) {
+                (Some(dep), _) => DatabaseResult::not_run(
+                    name.clone(),
+                    format!("Skipped: depends on '{}', which did not migrate", dep),
+                ),
+                (None, None) => DatabaseResult::not_run(name.clone(), "Database not connected"),
+                (None, Some(client)) if options.dry_run => {
+                    plan_single(client, &db.merge_into(base)?, name, version).await
+                }
+                (None, Some(client)) => {
+                    let config = db.merge_into(base)?;
+                    let started = std::time::Instant::now();
+                    let outcome =
+                        dispatch_migrate_schemas(client, &config, None, options.force).await;
+                    let mut result = DatabaseResult::migrated(name.clone(), outcome, started);
+                    if result.success && result.applied.is_empty() {
+                        result.message =
+                            format!("Version {} not pending; nothing applied", version);
+                    }
+                    result
+                }
+            };
+            let failed = !result.success;
+            results.push(result);
+            if failed && options.fail_fast {
+                break;
+            }
+        }
+
+        let all_succeeded = results.iter().all(|r| r.success);
+        Ok(MultiResult {
+            results,
+            all_succeeded,
+            canary: None,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Run migrate on all databases, starting each as soon as the databases
+    /// it depends on have succeeded and running up to `max_parallel` at a
+    /// time. A failed database only holds back its dependents, which are
+    /// reported as skipped; independent databases carry on (with
+    /// `fail_fast`, no further databases start). Results follow `order`.
+    ///
+    /// Each database's connection is moved into its own task, so `clients`
+    /// is taken by value. With `max_parallel` of 1 this is
+    /// [`Self::migrate_with_options`].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn migrate_parallel(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        mut clients: HashMap<String, DbClient>,
+        order: &[String],
+        target_version: Option<&str>,
+        fail_fast: bool,
+        force: bool,
+        max_parallel: usize,
+    ) -> Result<MultiResult> {
+        if max_parallel <= 1 {
+            return Self::migrate_with_options(
+                base,
+                databases,
+                &clients,
+                order,
+                target_version,
+                fail_fast,
+                force,
+            )
+            .await;
+        }
+        log::info!(
+            "Migrating databases in parallel; databases={}, max_parallel={}",
+            order.len(),
+            max_parallel
+        );
+
+        let mut pending: Vec<&String> = order.iter().collect();
+        let mut results: HashMap<String, DatabaseResult> = HashMap::new();
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut stopped = false;
+        loop {
+            let mut i = 0;
+            while !stopped && i < pending.len() && tasks.len() < max_parallel {
+                let name = pending[i];
+                let Some(db) = databases.iter().find(|d| &d.name == name) else {
+                    results.insert(
+                        name.clone(),
+                        DatabaseResult::not_run(name.clone(), "Database not connected"),
+                    );
+                    pending.remove(i);
+                    stopped |= fail_fast;
+                    continue;
+                };
+                // Dependencies outside `order` (e.g. filtered out) don't hold it back.
+                let deps: Vec<&String> =
+                    db.depends_on.iter().filter(|d| order.contains(d)).collect();
+                if let Some(failed) = deps
+                    .iter()
+                    .find(|d| results.get(d.as_str()).is_some_and(|r| !r.success))
+                {
+                    results.insert(
+                        name.clone(),
+                        DatabaseResult::not_run(
+                            name.clone(),
+                            format!("Skipped: depends on '{}', which did not migrate", failed),
+                        ),
+                    );
+                    pending.remove(i);
+                    continue;
+                }
+                if !deps.iter().all(|d| results.contains_key(d.as_str())) {
+                    i += 1;
+                    continue;
+                }
+                pending.remove(i);
+                let Some(client) = clients.remove(name) else {
+                    results.insert(
+                        name.clone(),
+                        DatabaseResult::not_run(name.clone(), "Database not connected"),
+                    );
+                    stopped |= fail_fast;
+                    continue;
+                };
+                let config = db.merge_into(base)?;
+                let name = name.clone();
+                let target_version = target_version.map(str::to_string);
+                let observer = crate::events::current();
+                tasks.spawn(crate::events::inherit(observer, async move {
+                    let started = std::time::Instant::now();
+                    let outcome = dispatch_migrate_schemas(
+                        &client,
+                        &config,
+                        target_version.as_deref(),
+                        force,
+                    )
+                    .await;
+                    if let Err(e) = &outcome {
+                        log::error!("Database migration failed; database={}, error={}", name, e);
+                    }
+                    DatabaseResult::migrated(name, outcome, started)
+                }));
+            }
+
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            let result = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            stopped |= fail_fast && !result.success;
+            results.insert(result.name.clone(), result);
+        }
+
+        let results: Vec<DatabaseResult> = order
+            .iter()
+            .map(|name| {
+                results.remove(name).unwrap_or_else(|| {
+                    DatabaseResult::not_run(
+                        name.clone(),
+                        "Skipped after an earlier database failed",
+                    )
+                })
+            })
+            .collect();
+        let all_succeeded = results.iter().all(|r| r.success);
+        Ok(MultiResult {
+            results,
+            all_succeeded,
+            canary: None,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Canary rollout: migrate `canary.databases` first, one at a time and
+    /// stopping at the first failure, then run the health checks every
+    /// `poll_interval` until `bake_time` has passed. Only if every canary
+    /// migrated and every check passed are the rest of `order` migrated, as
+    /// [`Self::migrate_parallel`] does; otherwise they are reported as
+    /// skipped. The canary phase is recorded in [`MultiResult::canary`].
+    ///
+    /// A canary may only depend on other canaries.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn migrate_canary(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        mut clients: HashMap<String, DbClient>,
+        order: &[String],
+        canary: &CanaryOptions,
+        target_version: Option<&str>,
+        fail_fast: bool,
+        force: bool,
+        max_parallel: usize,
+    ) -> Result<MultiResult> {
+        if canary.poll_interval.is_zero() {
+            return Err(WaypointError::ConfigError(
+                "The health check interval must be greater than zero".to_string(),
+            ));
+        }
+        for name in &canary.databases {
+            let Some(db) = databases.iter().find(|d| &d.name == name) else {
+                return Err(WaypointError::DatabaseNotFound {
+                    name: name.clone(),
+                    available: databases
+                        .iter()
+                        .map(|d| d.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                });
+            };
+            if let Some(dep) = db.depends_on.iter().find(|d| !canary.databases.contains(d)) {
+                return Err(WaypointError::ConfigError(format!(
+                    "Canary '{}' depends on '{}', which is not a canary; add it to the canaries",
+                    name, dep
+                )));
+            }
+        }
+        let (canary_order, rest_order): (Vec<String>, Vec<String>) = order
+            .iter()
+            .cloned()
+            .partition(|name| canary.databases.contains(name));
+        log::info!(
+            "Migrating canary databases; canaries={}, bake_time_secs={}",
+            canary_order.join(","),
+            canary.bake_time.as_secs()
+        );
+
+        let first = Self::migrate_with_options(
+            base,
+            databases,
+            &clients,
+            &canary_order,
+            target_version,
+            true,
+            force,
+        )
+        .await?;
+        let mut report = CanaryReport {
+            databases: canary_order.clone(),
+            bake_time_secs: canary.bake_time.as_secs(),
+            baked_secs: 0,
+            checks: Vec::new(),
+            outcome: CanaryOutcome::Promoted,
+        };
+        let mut results: HashMap<String, DatabaseResult> = first
+            .results
+            .into_iter()
+            .map(|r| (r.name.clone(), r))
+            .collect();
+
+        let skipped = if !first.all_succeeded {
+            report.outcome = CanaryOutcome::CanaryFailed;
+            Some("Skipped: a canary failed to migrate")
+        } else if !bake(&canary_order, &clients, canary, &mut report).await {
+            report.outcome = CanaryOutcome::Unhealthy;
+            Some("Skipped: a canary health check failed")
+        } else {
+            None
+        };
+        if skipped.is_none() {
+            log::info!(
+                "Canaries healthy, migrating the rest; databases={}",
+                rest_order.len()
+            );
+            clients.retain(|name, _| rest_order.contains(name));
+            let rest = Self::migrate_parallel(
+                base,
+                databases,
+                clients,
+                &rest_order,
+                target_version,
+                fail_fast,
+                force,
+                max_parallel,
+            )
+            .await?;
+            results.extend(rest.results.into_iter().map(|r| (r.name.clone(), r)));
+        }
+
+        let results: Vec<DatabaseResult> = order
+            .iter()
+            .map(|name| {
+                results.remove(name).unwrap_or_else(|| {
+                    DatabaseResult::not_run(
+                        name.clone(),
+                        skipped.unwrap_or("Skipped after an earlier database failed"),
+                    )
+                })
+            })
+            .collect();
+        let all_succeeded = results.iter().all(|r| r.success);
+        Ok(MultiResult {
+            results,
+            all_succeeded,
+            canary: Some(report),
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Discover tenant databases with `options.query` on `control`, then
+    /// apply `config`'s migrations to each, at most `options.concurrency`
+    /// at a time. Each tenant gets `config` with its own URL, so `[policy]`
+    /// and the version filter apply as for a single migrate; results are
+    /// reported per tenant, in the order the query returned them.
+    pub async fn for_each_tenant(
+        config: &WaypointConfig,
+        control: &DbClient,
+        options: &TenantOptions,
+    ) -> Result<MultiResult> {
+        config.policy.check("migrate")?;
+        let tenants = discover_tenants(control, &options.query).await?;
+        log::info!(
+            "Migrating tenant databases; tenants={}, concurrency={}",
+            tenants.len(),
+            options.concurrency
+        );
+
+        let semaphore =
+            std::sync::Arc::new(tokio::sync::Semaphore::new(options.concurrency.max(1)));
+        let failed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut tasks = tokio::task::JoinSet::new();
+        for (i, (url, name)) in tenants.iter().enumerate() {
+            let permit = semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("semaphore is never closed");
+            if options.fail_fast && failed.load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
+            let mut tenant_config = config.clone();
+            tenant_config.database.url = Some(url.clone());
+            tenant_config.multi_database = None;
+            let name = name.clone();
+            let options = options.clone();
+            let failed = failed.clone();
+            let observer = crate::events::current();
+            tasks.spawn(crate::events::inherit(observer, async move {
+                let result = migrate_tenant(&tenant_config, name, &options).await;
+                if !result.success {
+                    failed.store(true, std::sync::atomic::Ordering::SeqCst);
+                }
+                drop(permit);
+                (i, result)
+            }));
+        }
+
+        let mut results: Vec<Option<DatabaseResult>> = tenants.iter().map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            let (i, result) = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+            results[i] = Some(result);
+        }
+        let results: Vec<DatabaseResult> = results
+            .into_iter()
+            .zip(tenants)
+            .map(|(result, (_, name))| {
+                result.unwrap_or_else(|| {
+                    DatabaseResult::not_run(name, "Skipped after an earlier tenant failed")
+                })
+            })
+            .collect();
+
+        let all_succeeded = results.iter().all(|r| r.success);
+        Ok(MultiResult {
+            results,
+            all_succeeded,
+            canary: None,
+            warnings: Vec::new(),
         })
     }
 
     /// Run info on all databases in dependency order.
     pub async fn info(
+        base: &WaypointConfig,
         databases: &[NamedDatabaseConfig],
         clients: &HashMap<String, DbClient>,
         order: &[String],
@@ -262,14 +903,441 @@ impl MultiWaypoint {
             let client = clients.get(name);
 
             if let (Some(db), Some(client)) = (db, client) {
-                let config = db.to_waypoint_config();
-                let info = crate::commands::info::execute_db(client, &config).await?;
+                let config = db.merge_into(base)?;
+                client.check_standby("info", &config.migrations).await?;
+                let mut info = Vec::new();
+                for schema in config.target_schemas() {
+                    let schema_config = config.for_schema(&schema);
+                    let rows = crate::commands::info::execute_db(client, &schema_config).await?;
+                    let tag = (!config.migrations.schemas.is_empty()).then_some(schema);
+                    info.extend(
+                        rows.into_iter()
+                            .map(|row| crate::commands::info::MigrationInfo {
+                                schema: tag.clone(),
+                                ..row
+                            }),
+                    );
+                }
                 all_info.insert(name.clone(), info);
             }
         }
 
         Ok(all_info)
     }
+
+    /// `migrate --converge --dry-run` across all databases: what each would
+    /// apply up to `target_version`, as `<database>/<script>` in dependency
+    /// order. Nothing is applied.
+    pub async fn converge_check(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        clients: &HashMap<String, DbClient>,
+        order: &[String],
+        target_version: Option<&str>,
+    ) -> Result<crate::commands::migrate::ConvergeReport> {
+        let all_info = Self::info(base, databases, clients, order).await?;
+        let mut migrations = Vec::new();
+        for name in order {
+            let (Some(db), Some(infos)) = (
+                databases.iter().find(|d| &d.name == name),
+                all_info.get(name),
+            ) else {
+                continue;
+            };
+            let pending = crate::commands::migrate::ConvergeReport::pending(
+                infos,
+                target_version,
+                &db.migrations,
+            )?;
+            migrations.extend(
+                pending
+                    .migrations
+                    .into_iter()
+                    .map(|script| format!("{}/{}", name, script)),
+            );
+        }
+        Ok(crate::commands::migrate::ConvergeReport {
+            changed: !migrations.is_empty(),
+            migrations,
+            check_mode: true,
+        })
+    }
+
+    /// Search every database, in dependency order, for objects matching
+    /// `query`. Each source is labelled with the database name.
+    pub async fn find(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        clients: &HashMap<String, DbClient>,
+        order: &[String],
+        query: &crate::commands::find::FindQuery,
+    ) -> Result<crate::commands::find::FindReport> {
+        let mut report = crate::commands::find::FindReport::new(query);
+
+        for name in order {
+            let db = databases.iter().find(|d| &d.name == name);
+            let client = clients.get(name);
+
+            if let (Some(db), Some(client)) = (db, client) {
+                let config = db.merge_into(base)?;
+                let schema_name = client.resolve_schema(&config.migrations.schema).await?;
+                report.push(
+                    crate::commands::find::search_db(client, &schema_name, name, query).await?,
+                );
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Summarize migration metrics for every database in dependency order.
+    pub async fn metrics(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        clients: &HashMap<String, DbClient>,
+        order: &[String],
+        since: Option<chrono::Duration>,
+        top: usize,
+    ) -> Result<Vec<crate::commands::metrics::MetricsSummary>> {
+        let mut summaries = Vec::new();
+
+        for name in order {
+            let db = databases.iter().find(|d| &d.name == name);
+            let client = clients.get(name);
+
+            if let (Some(db), Some(client)) = (db, client) {
+                let config = db.merge_into(base)?;
+                let mut summary =
+                    crate::commands::metrics::execute_db(client, &config, since, top).await?;
+                summary.database = Some(name.clone());
+                summaries.push(summary);
+            }
+        }
+
+        Ok(summaries)
+    }
+
+    /// Summarize pending migrations on every database in dependency order,
+    /// sending each database's summary on its own `[notify]` channel when
+    /// `notify` is set. A database that can't be summarized fails the run.
+    pub async fn pending_report(
+        base: &WaypointConfig,
+        databases: &[NamedDatabaseConfig],
+        clients: &HashMap<String, DbClient>,
+        order: &[String],
+        notify: bool,
+        channel: Option<&str>,
+    ) -> Result<Vec<crate::commands::pending_report::PendingSummary>> {
+        let mut summaries = Vec::new();
+
+        for name in order {
+            let db = databases.iter().find(|d| &d.name == name);
+            let client = clients.get(name);
+
+            if let (Some(db), Some(client)) = (db, client) {
+                let config = db.merge_into(base)?;
+                let mut summary =
+                    crate::commands::pending_report::execute_db(client, &config).await?;
+                summary.database = Some(name.clone());
+                if notify {
+                    crate::commands::pending_report::send_db(
+                        client,
+                        &config,
+                        &mut summary,
+                        channel,
+                    )
+                    .await?;
+                }
+                summaries.push(summary);
+            }
+        }
+
+        Ok(summaries)
+    }
+
+    /// Send a `migrate_start` webhook event for each database in `order`.
+    pub async fn notify_start(
+        notifier: &Notifier,
+        databases: &[NamedDatabaseConfig],
+        order: &[String],
+    ) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        for db in order
+            .iter()
+            .filter_map(|name| databases.iter().find(|d| &d.name == name))
+        {
+            let event = LifecycleEvent::migrate_start(&db.name, &db.migrations.schema);
+            warnings.extend(notifier.send(&event).await);
+        }
+        warnings
+    }
+
+    /// Send a `migrate_success` or `migrate_failure` webhook event for each
+    /// database in `result`, with its [`DatabaseResult`] as the report.
+    pub async fn notify_result(
+        notifier: &Notifier,
+        databases: &[NamedDatabaseConfig],
+        result: &MultiResult,
+    ) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        for r in &result.results {
+            let schema = databases
+                .iter()
+                .find(|d| d.name == r.name)
+                .map_or("", |d| d.migrations.schema.as_str());
+            let event = LifecycleEvent::database_result(r, schema);
+            warnings.extend(notifier.send(&event).await);
+        }
+        warnings
+    }
+}
+
+/// Run the canary health checks every `poll_interval` until `bake_time` has
+/// passed, recording each result in `report`. Returns whether every check
+/// passed; the first failing round ends the bake.
+async fn bake(
+    canaries: &[String],
+    clients: &HashMap<String, DbClient>,
+    canary: &CanaryOptions,
+    report: &mut CanaryReport,
+) -> bool {
+    let started = Instant::now();
+    loop {
+        let elapsed = started.elapsed();
+        let elapsed_secs = elapsed.as_secs();
+        let mut healthy = true;
+        if let Some(sql) = &canary.health_sql {
+            for name in canaries {
+                let outcome = match clients.get(name) {
+                    Some(client) => health_query(client, sql).await,
+                    None => Err("Database not connected".to_string()),
+                };
+                healthy &= outcome.is_ok();
+                report.checks.push(HealthCheckResult::new(
+                    elapsed_secs,
+                    Some(name.clone()),
+                    "health_sql".to_string(),
+                    outcome,
+                ));
+            }
+        }
+        for probe in &canary.probes {
+            let outcome = probe.probe().await;
+            healthy &= outcome.is_ok();
+            report.checks.push(HealthCheckResult::new(
+                elapsed_secs,
+                None,
+                probe.describe(),
+                outcome,
+            ));
+        }
+        report.baked_secs = elapsed_secs;
+        if !healthy {
+            log::warn!(
+                "Canary health check failed, not migrating the rest; elapsed_secs={}",
+                elapsed_secs
+            );
+            return false;
+        }
+        if elapsed >= canary.bake_time {
+            return true;
+        }
+        tokio::time::sleep(canary.poll_interval.min(canary.bake_time - elapsed)).await;
+    }
+}
+
+/// Run a canary health query; see [`CanaryOptions::health_sql`].
+async fn health_query(client: &DbClient, sql: &str) -> std::result::Result<(), String> {
+    // Outer `None`: no rows. Inner `None`: NULL.
+    let first: Option<Option<String>> = match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => c
+            .simple_query(sql)
+            .await
+            .map_err(|e| e.to_string())?
+            .iter()
+            .find_map(|message| match message {
+                tokio_postgres::SimpleQueryMessage::Row(row) => {
+                    Some(row.try_get(0).ok().flatten().map(str::to_string))
+                }
+                _ => None,
+            }),
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let mut conn = pool.get_conn().await.map_err(|e| e.to_string())?;
+            let row: Option<mysql_async::Row> =
+                conn.query_first(sql).await.map_err(|e| e.to_string())?;
+            row.map(|row| {
+                row.get_opt::<Option<String>, usize>(0)
+                    .and_then(|v| v.ok())
+                    .flatten()
+            })
+        }
+    };
+    match first {
+        None => Err("Health query returned no rows".to_string()),
+        Some(None) => Err("Health query returned NULL".to_string()),
+        Some(Some(value)) if matches!(value.trim(), "f" | "false" | "FALSE" | "0") => {
+            Err(format!("Health query returned {}", value))
+        }
+        Some(Some(_)) => Ok(()),
+    }
+}
+
+/// Run the tenant query on the control database, returning each tenant's
+/// URL and report name.
+async fn discover_tenants(control: &DbClient, query: &str) -> Result<Vec<(String, String)>> {
+    let rows: Vec<(String, Option<String>)> = match control {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => c
+            .query(query, &[])
+            .await?
+            .iter()
+            .map(|row| {
+                let url: Option<String> = row.try_get(0)?;
+                let name: Option<String> = if row.len() > 1 { row.try_get(1)? } else { None };
+                Ok((url.unwrap_or_default(), name))
+            })
+            .collect::<std::result::Result<_, tokio_postgres::Error>>()?,
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let mut conn = pool.get_conn().await?;
+            let rows: Vec<mysql_async::Row> = conn.query(query).await?;
+            rows.iter()
+                .map(|row| {
+                    let url: Option<String> = row.get(0);
+                    let name: Option<String> = if row.len() > 1 { row.get(1) } else { None };
+                    (url.unwrap_or_default(), name)
+                })
+                .collect()
+        }
+    };
+
+    let mut tenants = Vec::new();
+    for (url, name) in rows {
+        if url.is_empty() {
+            return Err(WaypointError::ConfigError(
+                "Tenant query returned a row without a connection URL".to_string(),
+            ));
+        }
+        let name = name.unwrap_or_else(|| crate::commands::find::display_url(&url));
+        tenants.push((url, name));
+    }
+    Ok(tenants)
+}
+
+/// Connect to one tenant and migrate it, reporting any failure in the result.
+async fn migrate_tenant(
+    config: &WaypointConfig,
+    name: String,
+    options: &TenantOptions,
+) -> DatabaseResult {
+    let started = std::time::Instant::now();
+    if options.dry_run {
+        return plan_tenant(config, name, options.target_version.as_deref()).await;
+    }
+    let outcome = async {
+        let conn_string = config.connection_string()?;
+        let client = connect_one(&conn_string, config).await?;
+        dispatch_migrate_schemas(
+            &client,
+            config,
+            options.target_version.as_deref(),
+            options.force,
+        )
+        .await
+    }
+    .await;
+    if let Err(e) = &outcome {
+        log::error!("Tenant migration failed; tenant={}, error={}", name, e);
+    }
+    DatabaseResult::migrated(name, outcome, started)
+}
+
+/// What a tenant would apply: its pending migrations up to `target`.
+async fn plan_tenant(
+    config: &WaypointConfig,
+    name: String,
+    target: Option<&str>,
+) -> DatabaseResult {
+    let started = Instant::now();
+    let outcome = async {
+        let client = connect_one(&config.connection_string()?, config).await?;
+        let target = target
+            .map(|t| config.migrations.parse_version(t))
+            .transpose()?;
+        let mut pending = Vec::new();
+        for schema in config.target_schemas() {
+            let schema_config = config.for_schema(&schema);
+            for row in crate::commands::info::execute_db(&client, &schema_config).await? {
+                let beyond_target = match (&target, &row.version) {
+                    (Some(target), Some(v)) => config
+                        .migrations
+                        .parse_version(v)
+                        .is_ok_and(|v| &v > target),
+                    _ => false,
+                };
+                if !row.state.is_pending() || beyond_target {
+                    continue;
+                }
+                pending.push(if config.migrations.schemas.is_empty() {
+                    row.script
+                } else {
+                    format!("{}/{}", schema, row.script)
+                });
+            }
+        }
+        Ok::<_, WaypointError>(pending)
+    }
+    .await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match outcome {
+        Ok(pending) => DatabaseResult {
+            name,
+            success: true,
+            message: if pending.is_empty() {
+                "Up to date; nothing would be applied".to_string()
+            } else {
+                format!("Would apply {}", pending.join(", "))
+            },
+            applied: Vec::new(),
+            duration_ms,
+        },
+        Err(e) => {
+            log::error!("Tenant plan failed; tenant={}, error={}", name, e);
+            DatabaseResult {
+                name,
+                success: false,
+                message: e.to_string(),
+                applied: Vec::new(),
+                duration_ms,
+            }
+        }
+    }
+}
+
+/// Error when `filter` names a database that isn't configured.
+fn check_filter_matched(
+    databases: &[NamedDatabaseConfig],
+    filter: Option<&str>,
+    clients: &HashMap<String, DbClient>,
+) -> Result<()> {
+    if let Some(name_filter) = filter {
+        if !clients.contains_key(name_filter) {
+            let available = databases
+                .iter()
+                .map(|d| d.name.clone())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(WaypointError::DatabaseNotFound {
+                name: name_filter.to_string(),
+                available,
+            });
+        }
+    }
+    Ok(())
 }
 
 /// Connect to one named database, auto-detecting the engine from the URL.
@@ -280,28 +1348,13 @@ async fn connect_one(
     let kind = DialectKind::from_url(conn_string).unwrap_or(DialectKind::Postgres);
     match kind {
         #[cfg(feature = "postgres")]
-        DialectKind::Postgres => {
-            let client = crate::db::connect_with_full_config(
-                conn_string,
-                &config.database.ssl_mode,
-                config.database.connect_retries,
-                config.database.connect_timeout_secs,
-                config.database.statement_timeout_secs,
-                config.database.keepalive_secs,
-            )
-            .await?;
-            Ok(DbClient::with_postgres(client))
-        }
+        DialectKind::Postgres => crate::db::connect_supervised(conn_string, &config.database).await,
         #[cfg(not(feature = "postgres"))]
         DialectKind::Postgres => Err(WaypointError::ConfigError(
             "PostgreSQL support is not compiled in (enable the `postgres` feature)".into(),
         )),
         #[cfg(feature = "mysql")]
-        DialectKind::Mysql => {
-            let pool = mysql_async::Pool::from_url(conn_string)
-                .map_err(|e| WaypointError::ConfigError(format!("Invalid MySQL URL: {}", e)))?;
-            Ok(DbClient::with_mysql(pool))
-        }
+        DialectKind::Mysql => Ok(DbClient::with_mysql(crate::db::mysql_pool(conn_string)?)),
         #[cfg(not(feature = "mysql"))]
         DialectKind::Mysql => Err(WaypointError::ConfigError(
             "MySQL support is not compiled in (enable the `mysql` feature)".into(),
@@ -309,6 +1362,92 @@ async fn connect_one(
     }
 }
 
+/// The `multi exec --dry-run` result for one database: whether the
+/// migration with `version` is pending in each of its schemas.
+async fn plan_single(
+    client: &DbClient,
+    config: &WaypointConfig,
+    name: &str,
+    version: &str,
+) -> DatabaseResult {
+    let started = Instant::now();
+    let outcome = async {
+        let wanted = config.migrations.parse_version(version)?;
+        let mut found = false;
+        let mut pending = Vec::new();
+        for schema in config.target_schemas() {
+            let schema_config = config.for_schema(&schema);
+            for row in crate::commands::info::execute_db(client, &schema_config).await? {
+                let matches = row
+                    .version
+                    .as_deref()
+                    .and_then(|v| config.migrations.parse_version(v).ok())
+                    .is_some_and(|v| v == wanted);
+                if !matches {
+                    continue;
+                }
+                found = true;
+                if row.state.is_pending() {
+                    pending.push(if config.migrations.schemas.is_empty() {
+                        row.script
+                    } else {
+                        format!("{}/{}", schema, row.script)
+                    });
+                }
+            }
+        }
+        if !found {
+            return Err(WaypointError::ConfigError(format!(
+                "No migration with version {}",
+                version
+            )));
+        }
+        Ok(pending)
+    }
+    .await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match outcome {
+        Ok(pending) => DatabaseResult {
+            name: name.to_string(),
+            success: true,
+            message: if pending.is_empty() {
+                format!("Version {} not pending; nothing would be applied", version)
+            } else {
+                format!("Would apply {}", pending.join(", "))
+            },
+            applied: Vec::new(),
+            duration_ms,
+        },
+        Err(e) => DatabaseResult {
+            name: name.to_string(),
+            success: false,
+            message: e.to_string(),
+            applied: Vec::new(),
+            duration_ms,
+        },
+    }
+}
+
+/// Migrate each of the database's `[migrations] schemas` in turn (or just
+/// `schema`), stopping at the first failure.
+async fn dispatch_migrate_schemas(
+    client: &DbClient,
+    config: &WaypointConfig,
+    target_version: Option<&str>,
+    force: bool,
+) -> Result<crate::commands::migrate::MigrateReport> {
+    if config.migrations.schemas.is_empty() {
+        return dispatch_migrate(client, config, target_version, force).await;
+    }
+    let mut report = crate::commands::migrate::MigrateReport::default();
+    for schema in config.target_schemas() {
+        let schema_config = config.for_schema(&schema);
+        let schema_report = dispatch_migrate(client, &schema_config, target_version, force).await?;
+        report.merge_schema(&schema, schema_report);
+    }
+    Ok(report)
+}
+
 /// Dispatch migrate to the appropriate engine-specific implementation.
 async fn dispatch_migrate(
     client: &DbClient,
@@ -316,34 +1455,45 @@ async fn dispatch_migrate(
     target_version: Option<&str>,
     force: bool,
 ) -> Result<crate::commands::migrate::MigrateReport> {
-    match client.dialect_kind() {
-        #[cfg(feature = "postgres")]
-        DialectKind::Postgres => {
-            crate::commands::migrate::execute_with_options(
-                client.as_postgres()?,
-                config,
-                target_version,
-                force,
-            )
-            .await
-        }
-        #[cfg(not(feature = "postgres"))]
-        DialectKind::Postgres => Err(WaypointError::ConfigError(
-            "PostgreSQL support is not compiled in (enable the `postgres` feature)".into(),
-        )),
-        #[cfg(feature = "mysql")]
-        DialectKind::Mysql => {
-            crate::commands::migrate::execute_mysql_with_options(
-                client,
-                config,
-                target_version,
-                force,
-            )
-            .await
+    client.check_standby("migrate", &config.migrations).await?;
+    let started_at = chrono::Utc::now();
+    let migrate = async {
+        match client.dialect_kind() {
+            #[cfg(feature = "postgres")]
+            DialectKind::Postgres => {
+                crate::commands::migrate::execute_with_options(
+                    client.as_postgres()?,
+                    config,
+                    target_version,
+                    force,
+                )
+                .await
+            }
+            #[cfg(not(feature = "postgres"))]
+            DialectKind::Postgres => Err(WaypointError::ConfigError(
+                "PostgreSQL support is not compiled in (enable the `postgres` feature)".into(),
+            )),
+            #[cfg(feature = "mysql")]
+            DialectKind::Mysql => {
+                crate::commands::migrate::execute_mysql_with_options(
+                    client,
+                    config,
+                    target_version,
+                    force,
+                )
+                .await
+            }
+            #[cfg(not(feature = "mysql"))]
+            DialectKind::Mysql => Err(WaypointError::ConfigError(
+                "MySQL support is not compiled in (enable the `mysql` feature)".into(),
+            )),
         }
-        #[cfg(not(feature = "mysql"))]
-        DialectKind::Mysql => Err(WaypointError::ConfigError(
-            "MySQL support is not compiled in (enable the `mysql` feature)".into(),
-        )),
-    }
+    };
+    // Tasks spawned per database don't inherit task-locals, so the remote
+    // guard targets are installed here rather than by the caller.
+    let mut report = guard::with_targets(config.guards.targets.clone(), migrate).await?;
+    crate::receipt::issue(client, config, &mut report, started_at).await;
+    crate::drift_baseline::record(client, config, &mut report).await;
+    crate::commands::check::after_migrate(client, config, &mut report).await;
+    Ok(report)
 }
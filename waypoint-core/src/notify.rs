@@ -0,0 +1,718 @@
+//! Notifications about migration runs: PostgreSQL `NOTIFY` for fleet
+//! coordination, and HTTP webhooks for people.
+//!
+//! With `[notify] enabled = true`, a PostgreSQL `migrate` that applied at
+//! least one migration sends `NOTIFY <channel>, '<payload>'` once the run has
+//! finished. Application instances that `LISTEN` on the channel can reload
+//! prepared statements or drop caches. The payload is a
+//! [`MigrationNotification`] serialized as JSON; `waypoint listen` prints
+//! notifications as they arrive.
+//!
+//! `[notifications]` webhooks receive a [`LifecycleEvent`] when a migrate
+//! starts, succeeds or fails, and when drift is detected, as JSON or as a
+//! Slack message. The HTTP call itself is a [`WebhookTransport`] supplied by
+//! the caller (the CLI uses ureq); [`Notifier`] handles event filtering,
+//! payload formatting, timeouts and retries.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+use crate::commands::drift::DriftReport;
+use crate::commands::migrate::MigrateReport;
+use crate::warning::{Warning, WarningCode};
+
+/// Channel used when `[notify] channel` isn't set.
+pub const DEFAULT_CHANNEL: &str = "waypoint_migrations";
+
+/// PostgreSQL rejects `NOTIFY` payloads of 8000 bytes or more.
+pub(crate) const MAX_PAYLOAD_BYTES: usize = 7999;
+
+/// Configuration for the `[notify]` section.
+#[derive(Debug, Clone)]
+pub struct NotifyConfig {
+    /// Send a notification after a migrate that applied something.
+    pub enabled: bool,
+    /// Channel to notify on.
+    pub channel: String,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channel: DEFAULT_CHANNEL.to_string(),
+        }
+    }
+}
+
+/// A migration listed in a [`MigrationNotification`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotifiedMigration {
+    /// Version, or `None` for a repeatable migration.
+    pub version: Option<String>,
+    /// Description from the filename.
+    pub description: String,
+    /// Migration filename.
+    pub script: String,
+}
+
+/// Payload of the notification sent after a migrate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationNotification {
+    /// Always `"migrate"`.
+    pub event: String,
+    /// Database the migrations were applied to.
+    pub database: String,
+    /// Schema holding the history table.
+    pub schema: String,
+    /// Number of migrations applied in the run.
+    pub migrations_applied: usize,
+    /// The applied migrations, in the order they ran.
+    pub migrations: Vec<NotifiedMigration>,
+    /// Whether `migrations` was cut short to fit PostgreSQL's payload limit.
+    pub truncated: bool,
+    /// When the notification was sent.
+    pub sent_at: DateTime<Utc>,
+}
+
+impl MigrationNotification {
+    /// Describe a finished migrate run.
+    pub fn from_report(report: &MigrateReport, database: &str, schema: &str) -> Self {
+        Self {
+            event: "migrate".to_string(),
+            database: database.to_string(),
+            schema: schema.to_string(),
+            migrations_applied: report.migrations_applied,
+            migrations: report
+                .details
+                .iter()
+                .map(|d| NotifiedMigration {
+                    version: d.version.clone(),
+                    description: d.description.clone(),
+                    script: d.script.clone(),
+                })
+                .collect(),
+            truncated: false,
+            sent_at: Utc::now(),
+        }
+    }
+
+    /// JSON payload, dropping migrations from the end (and setting
+    /// `truncated`) until it fits in a `NOTIFY`.
+    pub fn to_payload(&self) -> String {
+        let mut notification = self.clone();
+        loop {
+            let payload = serde_json::to_string(&notification).unwrap_or_default();
+            if payload.len() <= MAX_PAYLOAD_BYTES || notification.migrations.is_empty() {
+                return payload;
+            }
+            notification.migrations.pop();
+            notification.truncated = true;
+        }
+    }
+}
+
+/// Notify `[notify] channel` about a migrate run. Failures are logged and
+/// added to the report's warnings; the migrations are already committed.
+#[cfg(feature = "postgres")]
+pub async fn send(
+    client: &Client,
+    config: &crate::config::WaypointConfig,
+    report: &mut MigrateReport,
+) {
+    let channel = &config.notify.channel;
+    let result = async {
+        let database: String = client
+            .query_one("SELECT current_database()", &[])
+            .await?
+            .get(0);
+        let payload =
+            MigrationNotification::from_report(report, &database, &config.migrations.schema)
+                .to_payload();
+        client
+            .execute("SELECT pg_notify($1, $2)", &[channel, &payload])
+            .await?;
+        Ok::<_, tokio_postgres::Error>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => log::info!(
+            "Sent migrate notification; channel={}, migrations_applied={}",
+            channel,
+            report.migrations_applied
+        ),
+        Err(e) => {
+            let reason = crate::error::format_db_error(&e);
+            log::warn!(
+                "Failed to send migrate notification; channel={}, error={}",
+                channel,
+                reason
+            );
+            report.warnings.push(crate::warning::Warning::new(
+                crate::warning::WarningCode::NotifyFailed,
+                channel,
+                format!("notification not sent: {}", reason),
+            ));
+        }
+    }
+}
+
+// ── Webhooks ──────────────────────────────────────────────────────────────────
+
+/// A point in a run that webhooks can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleKind {
+    /// `migrate` is about to apply pending migrations.
+    MigrateStart,
+    /// `migrate` finished.
+    MigrateSuccess,
+    /// `migrate` failed.
+    MigrateFailure,
+    /// `drift` found differences from the migrations.
+    DriftDetected,
+}
+
+impl LifecycleKind {
+    /// Every event, the default subscription.
+    pub const ALL: [LifecycleKind; 4] = [
+        LifecycleKind::MigrateStart,
+        LifecycleKind::MigrateSuccess,
+        LifecycleKind::MigrateFailure,
+        LifecycleKind::DriftDetected,
+    ];
+}
+
+impl std::fmt::Display for LifecycleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            LifecycleKind::MigrateStart => "migrate_start",
+            LifecycleKind::MigrateSuccess => "migrate_success",
+            LifecycleKind::MigrateFailure => "migrate_failure",
+            LifecycleKind::DriftDetected => "drift_detected",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::str::FromStr for LifecycleKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        LifecycleKind::ALL
+            .into_iter()
+            .find(|k| k.to_string() == s.trim().to_lowercase().replace('-', "_"))
+            .ok_or_else(|| {
+                format!(
+                    "unknown notification event: '{}' (expected migrate_start, migrate_success, \
+                     migrate_failure or drift_detected)",
+                    s
+                )
+            })
+    }
+}
+
+/// Body a webhook is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WebhookFormat {
+    /// The [`LifecycleEvent`] as JSON.
+    #[default]
+    Json,
+    /// A Slack incoming-webhook message: `{"text": ...}` in mrkdwn.
+    Slack,
+}
+
+impl std::str::FromStr for WebhookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "slack" => Ok(Self::Slack),
+            other => Err(format!("unknown webhook format: '{other}'")),
+        }
+    }
+}
+
+/// One `[[notifications.webhooks]]` entry.
+#[derive(Clone)]
+pub struct WebhookConfig {
+    /// URL the event is POSTed to.
+    pub url: String,
+    /// Payload format.
+    pub format: WebhookFormat,
+    /// Events sent to this webhook.
+    pub events: Vec<LifecycleKind>,
+    /// Extra request headers, e.g. `Authorization`.
+    pub headers: HashMap<String, String>,
+}
+
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headers: HashMap<&str, &str> = self
+            .headers
+            .keys()
+            .map(|k| (k.as_str(), "[REDACTED]"))
+            .collect();
+        f.debug_struct("WebhookConfig")
+            .field("url", &redact_url(&self.url))
+            .field("format", &self.format)
+            .field("events", &self.events)
+            .field("headers", &headers)
+            .finish()
+    }
+}
+
+/// Configuration for the `[notifications]` section.
+#[derive(Debug, Clone)]
+pub struct NotificationsConfig {
+    /// Webhooks to call.
+    pub webhooks: Vec<WebhookConfig>,
+    /// Per-request timeout in seconds.
+    pub timeout_secs: u64,
+    /// Further attempts after a failed request.
+    pub retries: u32,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            timeout_secs: 10,
+            retries: 2,
+        }
+    }
+}
+
+/// What happened, as sent to webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct LifecycleEvent {
+    /// Which event this is.
+    pub event: LifecycleKind,
+    /// Database the run was against.
+    pub database: String,
+    /// Schema holding the history table.
+    pub schema: String,
+    /// One-line description, e.g. "Applied 2 migration(s): V7, V8".
+    pub summary: String,
+    /// Why the run failed, for `migrate_failure`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The command's JSON report, when there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<serde_json::Value>,
+    /// When the event happened.
+    pub sent_at: DateTime<Utc>,
+}
+
+impl LifecycleEvent {
+    fn new(kind: LifecycleKind, database: &str, schema: &str, summary: String) -> Self {
+        Self {
+            event: kind,
+            database: database.to_string(),
+            schema: schema.to_string(),
+            summary,
+            error: None,
+            report: None,
+            sent_at: Utc::now(),
+        }
+    }
+
+    /// A migrate is starting.
+    pub fn migrate_start(database: &str, schema: &str) -> Self {
+        Self::new(
+            LifecycleKind::MigrateStart,
+            database,
+            schema,
+            "Migrate started".to_string(),
+        )
+    }
+
+    /// A migrate finished with `report`.
+    pub fn migrate_success(database: &str, schema: &str, report: &MigrateReport) -> Self {
+        let scripts: Vec<&str> = report.details.iter().map(|d| d.script.as_str()).collect();
+        let summary = if scripts.is_empty() {
+            "Schema is up to date; nothing applied".to_string()
+        } else {
+            format!(
+                "Applied {} migration(s) in {}ms: {}",
+                report.migrations_applied,
+                report.total_time_ms,
+                scripts.join(", ")
+            )
+        };
+        let mut event = Self::new(LifecycleKind::MigrateSuccess, database, schema, summary);
+        event.report = serde_json::to_value(report).ok();
+        event
+    }
+
+    /// A migrate failed with `error`.
+    pub fn migrate_failure(database: &str, schema: &str, error: &dyn std::fmt::Display) -> Self {
+        let mut event = Self::new(
+            LifecycleKind::MigrateFailure,
+            database,
+            schema,
+            "Migrate failed".to_string(),
+        );
+        event.error = Some(error.to_string());
+        event
+    }
+
+    /// How one database of a multi-database migrate ended.
+    pub fn database_result(result: &crate::multi::DatabaseResult, schema: &str) -> Self {
+        let kind = if result.success {
+            LifecycleKind::MigrateSuccess
+        } else {
+            LifecycleKind::MigrateFailure
+        };
+        let mut event = Self::new(kind, &result.name, schema, result.message.clone());
+        if !result.success {
+            event.error = Some(result.message.clone());
+        }
+        event.report = serde_json::to_value(result).ok();
+        event
+    }
+
+    /// `drift` found differences, listed in `report`.
+    pub fn drift_detected(database: &str, report: &DriftReport) -> Self {
+        let mut event = Self::new(
+            LifecycleKind::DriftDetected,
+            database,
+            &report.schema,
+            format!("{} drift finding(s)", report.drifts.len()),
+        );
+        event.report = serde_json::to_value(report).ok();
+        event
+    }
+
+    /// Slack mrkdwn text for the event.
+    pub fn slack_text(&self) -> String {
+        let (icon, headline) = match self.event {
+            LifecycleKind::MigrateStart => (":hourglass_flowing_sand:", "Migrate started"),
+            LifecycleKind::MigrateSuccess => (":white_check_mark:", "Migrate succeeded"),
+            LifecycleKind::MigrateFailure => (":x:", "Migrate failed"),
+            LifecycleKind::DriftDetected => (":warning:", "Schema drift detected"),
+        };
+        let mut text = format!(
+            "{} *{}* on `{}` (schema `{}`)",
+            icon, headline, self.database, self.schema
+        );
+        match (&self.event, &self.error) {
+            (_, Some(error)) => text.push_str(&format!("\n```{}```", error)),
+            (LifecycleKind::MigrateStart, None) => {}
+            (_, None) => text.push_str(&format!("\n{}", self.summary)),
+        }
+        text
+    }
+
+    /// Request body for a webhook in `format`.
+    pub fn to_body(&self, format: WebhookFormat) -> String {
+        match format {
+            WebhookFormat::Json => serde_json::to_string(self).unwrap_or_default(),
+            WebhookFormat::Slack => serde_json::json!({ "text": self.slack_text() }).to_string(),
+        }
+    }
+}
+
+/// Sends one webhook request. Implemented by the caller, which owns the HTTP
+/// client; `Err` says why the request failed (non-2xx responses included).
+#[async_trait::async_trait]
+pub trait WebhookTransport: Send + Sync {
+    /// POST `body` (JSON) to `url` with `headers`, giving up after `timeout`.
+    async fn post(
+        &self,
+        url: &str,
+        headers: &HashMap<String, String>,
+        body: &str,
+        timeout: Duration,
+    ) -> std::result::Result<(), String>;
+}
+
+/// First pause before retrying a failed webhook; doubled for each retry.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest pause between webhook retries.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Delivers [`LifecycleEvent`]s to the configured webhooks.
+#[derive(Clone)]
+pub struct Notifier {
+    config: NotificationsConfig,
+    transport: Arc<dyn WebhookTransport>,
+}
+
+impl Notifier {
+    /// A notifier for `config`, or `None` when no webhooks are configured.
+    pub fn new(config: &NotificationsConfig, transport: Arc<dyn WebhookTransport>) -> Option<Self> {
+        if config.webhooks.is_empty() {
+            return None;
+        }
+        Some(Self {
+            config: config.clone(),
+            transport,
+        })
+    }
+
+    /// Send `event` to every webhook subscribed to it, retrying failed
+    /// requests. Delivery never fails the run: each webhook that could not
+    /// be reached is logged and returned as a `webhook_failed` warning.
+    pub async fn send(&self, event: &LifecycleEvent) -> Vec<Warning> {
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let mut warnings = Vec::new();
+        for webhook in &self.config.webhooks {
+            if !webhook.events.contains(&event.event) {
+                continue;
+            }
+            let body = event.to_body(webhook.format);
+            let mut attempt = 0;
+            loop {
+                match self
+                    .transport
+                    .post(&webhook.url, &webhook.headers, &body, timeout)
+                    .await
+                {
+                    Ok(()) => {
+                        log::info!(
+                            "Sent webhook; event={}, url={}",
+                            event.event,
+                            redact_url(&webhook.url)
+                        );
+                        break;
+                    }
+                    Err(e) if attempt < self.config.retries => {
+                        log::debug!(
+                            "Webhook attempt failed, retrying; url={}, attempt={}, error={}",
+                            redact_url(&webhook.url),
+                            attempt + 1,
+                            e
+                        );
+                        let backoff = RETRY_BACKOFF
+                            .saturating_mul(2u32.saturating_pow(attempt))
+                            .min(MAX_RETRY_BACKOFF);
+                        tokio::time::sleep(backoff).await;
+                        attempt += 1;
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to send webhook; event={}, url={}, error={}",
+                            event.event,
+                            redact_url(&webhook.url),
+                            e
+                        );
+                        warnings.push(Warning::new(
+                            WarningCode::WebhookFailed,
+                            redact_url(&webhook.url),
+                            format!("{} not delivered: {}", event.event, e),
+                        ));
+                        break;
+                    }
+                }
+            }
+        }
+        warnings
+    }
+}
+
+/// Scheme and host of a webhook URL, for logs: Slack and similar services
+/// put the secret in the path.
+fn redact_url(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split(['/', '?']).next().unwrap_or(rest);
+            let host = host.rsplit('@').next().unwrap_or(host);
+            format!("{}://{}", scheme, host)
+        }
+        None => "<webhook>".to_string(),
+    }
+}
+
+/// Name of the database `config` points at, for event payloads: the path of
+/// a URL or its `dbname=` setting, falling back to `default`.
+pub fn database_label(config: &crate::config::WaypointConfig) -> String {
+    let url = config.database.url.as_deref().unwrap_or_default();
+    let name = if let Some(rest) = url.split("dbname=").nth(1) {
+        rest.split_whitespace().next().unwrap_or_default()
+    } else {
+        url.split_once("://")
+            .and_then(|(_, rest)| rest.split_once('/'))
+            .map(|(_, path)| path.split('?').next().unwrap_or_default())
+            .unwrap_or_default()
+    };
+    if name.is_empty() {
+        "default".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::migrate::MigrateDetail;
+
+    fn report(count: usize) -> MigrateReport {
+        MigrateReport {
+            migrations_applied: count,
+            total_time_ms: 0,
+            details: (1..=count)
+                .map(|i| MigrateDetail {
+                    version: Some(i.to_string()),
+                    description: "x".repeat(100),
+                    script: format!("V{}__{}.sql", i, "x".repeat(100)),
+                    execution_time_ms: 0,
+                    rows_affected: 0,
+                    schema: None,
+                })
+                .collect(),
+            hooks_executed: 0,
+            hooks_time_ms: 0,
+            maintenance: Vec::new(),
+            dependencies_added: Vec::new(),
+            warnings: Vec::new(),
+            checks: None,
+            schema_fingerprint: None,
+            manifest: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_payload_round_trips_and_fits_notify_limit() {
+        let small = MigrationNotification::from_report(&report(2), "app", "public");
+        let parsed: MigrationNotification = serde_json::from_str(&small.to_payload()).unwrap();
+        assert_eq!(parsed, small);
+
+        let large = MigrationNotification::from_report(&report(200), "app", "public");
+        let parsed: MigrationNotification = serde_json::from_str(&large.to_payload()).unwrap();
+        assert!(large.to_payload().len() <= MAX_PAYLOAD_BYTES);
+        assert!(parsed.truncated);
+        assert_eq!(parsed.migrations_applied, 200);
+        assert!(parsed.migrations.len() < 200);
+    }
+
+    /// Fails the first `failures` requests, recording every body.
+    struct FlakyTransport {
+        failures: u32,
+        calls: std::sync::Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl WebhookTransport for FlakyTransport {
+        async fn post(
+            &self,
+            url: &str,
+            _headers: &HashMap<String, String>,
+            body: &str,
+            _timeout: Duration,
+        ) -> std::result::Result<(), String> {
+            let mut calls = self.calls.lock().unwrap();
+            calls.push((url.to_string(), body.to_string()));
+            if calls.len() as u32 <= self.failures {
+                Err("HTTP 503".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn webhook(url: &str, format: WebhookFormat, events: &[LifecycleKind]) -> WebhookConfig {
+        WebhookConfig {
+            url: url.to_string(),
+            format,
+            events: events.to_vec(),
+            headers: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notifier_filters_events_and_retries() {
+        let config = NotificationsConfig {
+            webhooks: vec![
+                webhook(
+                    "https://hooks.slack.com/services/T0/B0/secret",
+                    WebhookFormat::Slack,
+                    &[LifecycleKind::MigrateFailure],
+                ),
+                webhook(
+                    "https://ops.example.com/hook",
+                    WebhookFormat::Json,
+                    &LifecycleKind::ALL,
+                ),
+            ],
+            timeout_secs: 5,
+            retries: 1,
+        };
+        let transport = Arc::new(FlakyTransport {
+            failures: 1,
+            calls: Default::default(),
+        });
+        let notifier = Notifier::new(&config, transport.clone()).unwrap();
+
+        // Only the JSON webhook takes successes; its first attempt fails and
+        // the retry goes through.
+        let warnings = notifier
+            .send(&LifecycleEvent::migrate_success(
+                "app",
+                "public",
+                &report(2),
+            ))
+            .await;
+        assert!(warnings.is_empty());
+        let calls = transport.calls.lock().unwrap().clone();
+        assert_eq!(calls.len(), 2);
+        assert!(calls
+            .iter()
+            .all(|(url, _)| url == "https://ops.example.com/hook"));
+        let body: serde_json::Value = serde_json::from_str(&calls[1].1).unwrap();
+        assert_eq!(body["event"], "migrate_success");
+        assert_eq!(body["report"]["migrations_applied"], 2);
+
+        // A failure goes to both; the Slack one gets mrkdwn text.
+        transport.calls.lock().unwrap().clear();
+        let warnings = notifier
+            .send(&LifecycleEvent::migrate_failure("app", "public", &"boom"))
+            .await;
+        assert!(warnings.is_empty());
+        let calls = transport.calls.lock().unwrap().clone();
+        let slack: serde_json::Value = serde_json::from_str(&calls[0].1).unwrap();
+        assert_eq!(
+            slack["text"],
+            ":x: *Migrate failed* on `app` (schema `public`)\n```boom```"
+        );
+
+        // Out of retries: the run carries on with a warning that hides the
+        // URL's secret path.
+        let failing = Notifier::new(
+            &NotificationsConfig {
+                retries: 0,
+                ..config
+            },
+            Arc::new(FlakyTransport {
+                failures: u32::MAX,
+                calls: Default::default(),
+            }),
+        )
+        .unwrap();
+        let warnings = failing
+            .send(&LifecycleEvent::migrate_failure("app", "public", &"boom"))
+            .await;
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].context, "https://hooks.slack.com");
+    }
+
+    #[test]
+    fn test_lifecycle_kind_parse() {
+        assert_eq!(
+            "drift-detected".parse::<LifecycleKind>(),
+            Ok(LifecycleKind::DriftDetected)
+        );
+        assert!("deploy".parse::<LifecycleKind>().is_err());
+    }
+}
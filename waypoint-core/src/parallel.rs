@@ -0,0 +1,193 @@
+//! Wave planning for `parallel_migrations`.
+//!
+//! Splits an ordered pending set into waves whose members can be applied at
+//! the same time: no member depends on another (per the dependency graph used
+//! for `dependency_ordering`), and their DDL touches disjoint sets of objects.
+//! Migrations whose objects can't be determined — anything with a statement
+//! [`crate::sql_parser`] doesn't recognise — always run in a wave of their
+//! own, as do those the caller marks ineligible.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+use regex_lite::Regex;
+
+use crate::dependency::DependencyGraph;
+use crate::error::Result;
+use crate::migration::ResolvedMigration;
+use crate::provenance::normalize;
+use crate::sql_parser::{extract_ddl_operations, DdlOperation};
+
+static REFERENCES_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bREFERENCES\s+(?:\w+\.)?(\w+)").unwrap());
+
+/// Names of the objects `sql` creates, changes or references, or `None` when
+/// a statement isn't recognised DDL and the set can't be trusted.
+pub fn touched_objects(sql: &str) -> Option<BTreeSet<String>> {
+    let mut objects = BTreeSet::new();
+    for op in extract_ddl_operations(sql) {
+        match op {
+            DdlOperation::CreateTable { table, .. }
+            | DdlOperation::DropTable { table }
+            | DdlOperation::AlterTableAddColumn { table, .. }
+            | DdlOperation::AlterTableDropColumn { table, .. }
+            | DdlOperation::AlterTableAlterColumn { table, .. }
+            | DdlOperation::AddConstraint { table, .. }
+            | DdlOperation::DropConstraint { table, .. }
+            | DdlOperation::TruncateTable { table } => {
+                objects.insert(normalize(&table));
+            }
+            DdlOperation::CreateIndex { name, table, .. } => {
+                objects.insert(normalize(&name));
+                objects.insert(normalize(&table));
+            }
+            DdlOperation::DropIndex { name }
+            | DdlOperation::CreateView { name, .. }
+            | DdlOperation::DropView { name }
+            | DdlOperation::CreateFunction { name }
+            | DdlOperation::DropFunction { name }
+            | DdlOperation::CreateEnum { name } => {
+                objects.insert(normalize(&name));
+            }
+            DdlOperation::Other { .. } => return None,
+        }
+    }
+    objects.extend(
+        REFERENCES_RE
+            .captures_iter(sql)
+            .map(|caps| normalize(&caps[1])),
+    );
+    if objects.is_empty() {
+        None
+    } else {
+        Some(objects)
+    }
+}
+
+/// Group `pending` (already in dependency order) into waves of at most
+/// `max_parallel` migrations. `versioned` is every versioned migration, for
+/// the dependency graph; `eligible` can force a migration to run alone.
+pub fn plan_waves<'a>(
+    versioned: &[&ResolvedMigration],
+    pending: &[&'a ResolvedMigration],
+    max_parallel: usize,
+    eligible: impl Fn(&ResolvedMigration) -> bool,
+) -> Result<Vec<Vec<&'a ResolvedMigration>>> {
+    let graph = DependencyGraph::build(versioned, true)?;
+
+    // Level = one more than the deepest pending dependency.
+    let mut levels: HashMap<&str, usize> = HashMap::new();
+    let mut by_level: Vec<Vec<&'a ResolvedMigration>> = Vec::new();
+    for m in pending {
+        let version = m.version().expect("versioned").raw.as_str();
+        let level = graph
+            .dependencies(version)
+            .iter()
+            .filter_map(|d| levels.get(d.as_str()))
+            .map(|l| l + 1)
+            .max()
+            .unwrap_or(0);
+        levels.insert(version, level);
+        if by_level.len() <= level {
+            by_level.resize_with(level + 1, Vec::new);
+        }
+        by_level[level].push(*m);
+    }
+
+    let mut waves = Vec::new();
+    for members in by_level {
+        // Waves of this level with the objects they touch; `None` once a
+        // migration that must run alone has it.
+        let mut level_waves: Vec<(Vec<&'a ResolvedMigration>, Option<BTreeSet<String>>)> =
+            Vec::new();
+        for m in members {
+            let objects = if max_parallel > 1 && eligible(m) {
+                touched_objects(&m.sql)
+            } else {
+                None
+            };
+            let Some(objects) = objects else {
+                level_waves.push((vec![m], None));
+                continue;
+            };
+            let fits = level_waves.iter_mut().find(|(wave, used)| {
+                wave.len() < max_parallel && used.as_ref().is_some_and(|u| u.is_disjoint(&objects))
+            });
+            match fits {
+                Some((wave, Some(used))) => {
+                    wave.push(m);
+                    used.extend(objects);
+                }
+                _ => level_waves.push((vec![m], Some(objects))),
+            }
+        }
+        waves.extend(level_waves.into_iter().map(|(wave, _)| wave));
+    }
+    Ok(waves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directive::MigrationDirectives;
+    use crate::migration::{MigrationKind, MigrationVersion};
+
+    fn migration(version: &str, depends: &[&str], sql: &str) -> ResolvedMigration {
+        ResolvedMigration {
+            kind: MigrationKind::Versioned(MigrationVersion::parse(version).unwrap()),
+            description: format!("V{}", version),
+            script: format!("V{}__test.sql", version),
+            checksum: 0,
+            sql: sql.to_string(),
+            directives: MigrationDirectives {
+                depends: depends.iter().map(|d| d.to_string()).collect(),
+                ..Default::default()
+            },
+            copy_checksum_sha256: None,
+        }
+    }
+
+    fn versions(waves: &[Vec<&ResolvedMigration>]) -> Vec<Vec<String>> {
+        waves
+            .iter()
+            .map(|w| w.iter().map(|m| m.version().unwrap().raw.clone()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_touched_objects() {
+        let objects = touched_objects(
+            "CREATE TABLE orders (id INT, user_id INT REFERENCES public.users (id));\n\
+             CREATE INDEX orders_user_idx ON orders (user_id);",
+        )
+        .unwrap();
+        assert_eq!(
+            objects.into_iter().collect::<Vec<_>>(),
+            vec!["orders", "orders_user_idx", "users"]
+        );
+        assert!(touched_objects("UPDATE orders SET user_id = 1;").is_none());
+    }
+
+    #[test]
+    fn test_plan_waves_groups_independent_disjoint_migrations() {
+        let m1 = migration("1", &[], "CREATE TABLE a (id INT);");
+        let m2 = migration("2", &["1"], "CREATE TABLE b (id INT);");
+        let m3 = migration("3", &["1"], "CREATE TABLE c (id INT);");
+        let m4 = migration("4", &["1"], "ALTER TABLE b ADD COLUMN x INT;");
+        let m5 = migration("5", &["1"], "UPDATE a SET id = 1;");
+        let m6 = migration("6", &[], "CREATE TABLE d (id INT);");
+        let all: Vec<&ResolvedMigration> = vec![&m1, &m2, &m3, &m4, &m5, &m6];
+
+        // V2 and V3 are independent and disjoint. V4 touches V2's table, so it
+        // gets its own wave. V5 isn't recognised DDL, and V6 implicitly follows V5.
+        let waves = plan_waves(&all, &all, 4, |_| true).unwrap();
+        assert_eq!(
+            versions(&waves),
+            vec![vec!["1"], vec!["2", "3"], vec!["4"], vec!["5"], vec!["6"],]
+        );
+
+        // Serial when parallelism is off or nothing is eligible.
+        assert_eq!(plan_waves(&all, &all, 1, |_| true).unwrap().len(), 6);
+        assert_eq!(plan_waves(&all, &all, 4, |_| false).unwrap().len(), 6);
+    }
+}
@@ -1,6 +1,12 @@
 //! Placeholder replacement in SQL (`${key}` syntax).
+//!
+//! Values are layered, later sources winning: `[placeholders]` in the TOML
+//! file, `WAYPOINT_PLACEHOLDER_*` env vars, `--placeholders-file`, then
+//! `--placeholder key=value`. The built-in `waypoint:*` placeholders are
+//! added last and can't be overridden.
 
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::LazyLock;
 
 use regex_lite::Regex;
@@ -155,6 +161,86 @@ fn find_dollar_quoted_regions(sql: &str) -> Vec<(usize, usize)> {
     regions
 }
 
+/// Parse a `--placeholder key=value` argument.
+pub fn parse_placeholder_arg(arg: &str) -> Result<(String, String)> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(WaypointError::ConfigError(format!(
+            "Invalid placeholder '{}'; expected key=value",
+            arg
+        ))),
+    }
+}
+
+/// Read placeholders from a file: a flat JSON object for `.json`, otherwise
+/// `.env` style `KEY=value` lines (`#` comments, optional `export ` prefix
+/// and surrounding quotes).
+pub fn load_placeholders_file(path: &Path) -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        WaypointError::ConfigError(format!(
+            "Cannot read placeholders file '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        parse_json_placeholders(&content)
+    } else {
+        parse_env_placeholders(&content)
+    }
+    .map_err(|e| {
+        WaypointError::ConfigError(format!(
+            "Invalid placeholders file '{}': {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+fn parse_json_placeholders(content: &str) -> std::result::Result<HashMap<String, String>, String> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let serde_json::Value::Object(map) = value else {
+        return Err("expected a JSON object".to_string());
+    };
+    map.into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(s) => Ok((key, s)),
+            serde_json::Value::Number(n) => Ok((key, n.to_string())),
+            serde_json::Value::Bool(b) => Ok((key, b.to_string())),
+            _ => Err(format!(
+                "value of '{}' must be a string, number or boolean",
+                key
+            )),
+        })
+        .collect()
+}
+
+fn parse_env_placeholders(content: &str) -> std::result::Result<HashMap<String, String>, String> {
+    let mut map = HashMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected KEY=value", i + 1));
+        };
+        let value = value.trim();
+        let unquoted = ['"', '\'']
+            .iter()
+            .find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+            .unwrap_or(value);
+        map.insert(key.trim().to_string(), unquoted.to_string());
+    }
+    Ok(map)
+}
+
 /// Build the full placeholder map including built-in waypoint placeholders.
 pub fn build_placeholders(
     user_placeholders: &HashMap<String, String>,
@@ -181,6 +267,44 @@ pub fn build_placeholders(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_placeholder_arg() {
+        assert_eq!(
+            parse_placeholder_arg("env=prod=eu").unwrap(),
+            ("env".to_string(), "prod=eu".to_string())
+        );
+        assert_eq!(parse_placeholder_arg("empty=").unwrap().1, "");
+        assert!(parse_placeholder_arg("novalue").is_err());
+        assert!(parse_placeholder_arg("=x").is_err());
+    }
+
+    #[test]
+    fn test_load_placeholders_file_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("vars.json");
+        std::fs::write(&json, r#"{"env": "staging", "replicas": 3, "audit": true}"#).unwrap();
+        let map = load_placeholders_file(&json).unwrap();
+        assert_eq!(map["env"], "staging");
+        assert_eq!(map["replicas"], "3");
+        assert_eq!(map["audit"], "true");
+
+        let env = dir.path().join("vars.env");
+        std::fs::write(
+            &env,
+            "# CI values\nexport ENV=\"prod\"\nOWNER='app role'\n\nTAG=v1=2\n",
+        )
+        .unwrap();
+        let map = load_placeholders_file(&env).unwrap();
+        assert_eq!(map["ENV"], "prod");
+        assert_eq!(map["OWNER"], "app role");
+        assert_eq!(map["TAG"], "v1=2");
+
+        std::fs::write(&json, r#"{"nested": {"a": 1}}"#).unwrap();
+        assert!(load_placeholders_file(&json).is_err());
+        std::fs::write(&env, "not a pair\n").unwrap();
+        assert!(load_placeholders_file(&env).is_err());
+    }
+
     #[test]
     fn test_replace_placeholders() {
         let mut placeholders = HashMap::new();
@@ -0,0 +1,181 @@
+//! Per-command policy from the `[policy]` config section.
+//!
+//! Maps command names to what is allowed, so a config shipped to production
+//! hosts can forbid `clean` outright or make `undo` require an explicit
+//! override, no matter which flags an operator passes:
+//!
+//! ```toml
+//! [policy]
+//! clean = "never"
+//! undo = "require_override"
+//! migrate = "allowed"
+//! ```
+//!
+//! The CLI checks the policy before dispatching any command, with
+//! [`PolicyConfig::overridden`] set from `--override-policy` (not `--force`,
+//! which overrides safety checks). The library checks it at the top of each
+//! `Waypoint` method with the same field.
+
+use std::collections::HashMap;
+
+use crate::error::{Result, WaypointError};
+
+/// Command names accepted as `[policy]` keys (the CLI subcommand names).
+pub const COMMANDS: &[&str] = &[
+    "migrate",
+    "multi",
+    "info",
+    "validate",
+    "audit",
+    "repair",
+    "reconcile",
+    "import-flyway",
+    "listen",
+    "find",
+    "checksums",
+    "history",
+    "baseline",
+    "undo",
+    "clean",
+    "lint",
+    "init",
+    "new",
+    "changelog",
+    "diff",
+    "drift",
+    "snapshot",
+    "restore",
+    "preflight",
+    "guard",
+    "check-conflicts",
+    "safety",
+    "advise",
+    "simulate",
+    "export",
+    "schedule",
+    "run-scheduled",
+    "verify-receipt",
+    "metrics",
+    "pending-report",
+    "sla-check",
+    "fingerprint",
+    "check",
+    "assist",
+    "report",
+    "squash",
+    "rebase",
+    "seed",
+    "serve",
+    "version",
+    "self-update",
+];
+
+/// What a `[policy]` entry allows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommandPolicy {
+    /// Run normally (default for commands not listed).
+    #[default]
+    Allowed,
+    /// Run only when the policy is overridden (`--override-policy`).
+    RequireOverride,
+    /// Never run.
+    Never,
+}
+
+impl std::str::FromStr for CommandPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "allowed" | "allow" => Ok(Self::Allowed),
+            // `require_force` is the name from before `--override-policy`.
+            "require_override" | "require_force" => Ok(Self::RequireOverride),
+            "never" | "deny" => Ok(Self::Never),
+            other => Err(format!("unknown policy value: '{other}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for CommandPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandPolicy::Allowed => write!(f, "allowed"),
+            CommandPolicy::RequireOverride => write!(f, "require_override"),
+            CommandPolicy::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Configuration for the `[policy]` section.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyConfig {
+    /// Policy per command name; commands not listed are allowed.
+    pub commands: HashMap<String, CommandPolicy>,
+    /// Whether the caller asked to run `require_override` commands; set by
+    /// the CLI's `--override-policy`.
+    pub overridden: bool,
+}
+
+impl PolicyConfig {
+    /// Policy for `command` (`Allowed` when not listed).
+    pub fn get(&self, command: &str) -> CommandPolicy {
+        self.commands.get(command).copied().unwrap_or_default()
+    }
+
+    /// Return `PolicyDenied` if `command` may not run.
+    pub fn check(&self, command: &str) -> Result<()> {
+        let reason = match self.get(command) {
+            CommandPolicy::Allowed => return Ok(()),
+            CommandPolicy::RequireOverride if self.overridden => return Ok(()),
+            CommandPolicy::RequireOverride => "requires --override-policy",
+            CommandPolicy::Never => "never allowed",
+        };
+        Err(WaypointError::PolicyDenied {
+            command: command.to_string(),
+            reason: reason.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(entries: &[(&str, CommandPolicy)]) -> PolicyConfig {
+        PolicyConfig {
+            commands: entries.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+            overridden: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_command_policy() {
+        assert_eq!("allowed".parse(), Ok(CommandPolicy::Allowed));
+        assert_eq!(
+            "require_override".parse(),
+            Ok(CommandPolicy::RequireOverride)
+        );
+        assert_eq!("require_force".parse(), Ok(CommandPolicy::RequireOverride));
+        assert_eq!("require-force".parse(), Ok(CommandPolicy::RequireOverride));
+        assert_eq!("NEVER".parse(), Ok(CommandPolicy::Never));
+        assert!("sometimes".parse::<CommandPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let mut p = policy(&[
+            ("clean", CommandPolicy::Never),
+            ("undo", CommandPolicy::RequireOverride),
+            ("migrate", CommandPolicy::Allowed),
+        ]);
+
+        assert!(p.check("migrate").is_ok());
+        assert!(p.check("info").is_ok());
+        let err = p.check("undo").unwrap_err();
+        assert!(err.to_string().contains("requires --override-policy"));
+
+        p.overridden = true;
+        assert!(p.check("undo").is_ok());
+        assert!(p.check("clean").is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! Schema and extension prerequisites applied before the first migration.
+//!
+//! Replaces the hand-written `V0__bootstrap.sql` that most projects keep
+//! around just to run `CREATE SCHEMA` / `CREATE EXTENSION`. Everything here
+//! is idempotent: objects that already exist are left alone, and only
+//! missing ones are created.
+
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+#[cfg(feature = "postgres")]
+use crate::db::quote_ident;
+use crate::db::DbClient;
+use crate::error::{Result, WaypointError};
+
+/// Configuration for the `[prerequisites]` section.
+#[derive(Debug, Clone, Default)]
+pub struct PrerequisitesConfig {
+    /// Schemas to create if missing (databases on MySQL).
+    pub schemas: Vec<String>,
+    /// Extensions to create if missing (PostgreSQL only).
+    pub extensions: Vec<String>,
+}
+
+impl PrerequisitesConfig {
+    /// Whether any prerequisites are configured.
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty() && self.extensions.is_empty()
+    }
+}
+
+/// Outcome for a single prerequisite object.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub enum PrerequisiteStatus {
+    /// The object already existed; nothing was done.
+    Exists,
+    /// The object was created by this run.
+    Created,
+    /// The object kind does not apply to this engine and was skipped.
+    Skipped,
+}
+
+/// A single schema or extension checked by [`ensure_prerequisites_db`].
+#[derive(Debug, Clone, Serialize)]
+pub struct PrerequisiteResult {
+    /// Object kind: `"schema"` or `"extension"`.
+    pub kind: String,
+    /// Object name as configured.
+    pub name: String,
+    /// What happened to the object.
+    pub status: PrerequisiteStatus,
+}
+
+/// Report of all prerequisites checked in one run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PrerequisitesReport {
+    /// Per-object results in configuration order (schemas first).
+    pub results: Vec<PrerequisiteResult>,
+}
+
+impl PrerequisitesReport {
+    /// Number of objects created by this run.
+    pub fn created_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.status == PrerequisiteStatus::Created)
+            .count()
+    }
+}
+
+/// Ensure all configured prerequisites exist (dialect-aware entry).
+pub async fn ensure_prerequisites_db(
+    client: &DbClient,
+    config: &PrerequisitesConfig,
+) -> Result<PrerequisitesReport> {
+    if config.is_empty() {
+        return Ok(PrerequisitesReport::default());
+    }
+    match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => ensure_prerequisites(c, config).await,
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(_) => ensure_prerequisites_mysql(client, config).await,
+    }
+}
+
+/// Ensure all configured prerequisites exist (PostgreSQL legacy entry).
+#[cfg(feature = "postgres")]
+pub async fn ensure_prerequisites(
+    client: &Client,
+    config: &PrerequisitesConfig,
+) -> Result<PrerequisitesReport> {
+    let mut report = PrerequisitesReport::default();
+
+    for schema in &config.schemas {
+        let row = client
+            .query_one(
+                "SELECT EXISTS (SELECT 1 FROM pg_namespace WHERE nspname = $1)",
+                &[schema],
+            )
+            .await?;
+        let status = if row.get::<_, bool>(0) {
+            PrerequisiteStatus::Exists
+        } else {
+            let sql = format!("CREATE SCHEMA IF NOT EXISTS {}", quote_ident(schema));
+            client
+                .batch_execute(&sql)
+                .await
+                .map_err(|e| prerequisite_error_pg("schema", schema, &e))?;
+            log::info!("Created prerequisite schema; schema={}", schema);
+            PrerequisiteStatus::Created
+        };
+        report.results.push(PrerequisiteResult {
+            kind: "schema".to_string(),
+            name: schema.clone(),
+            status,
+        });
+    }
+
+    for extension in &config.extensions {
+        let row = client
+            .query_one(
+                "SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = $1)",
+                &[extension],
+            )
+            .await?;
+        let status = if row.get::<_, bool>(0) {
+            PrerequisiteStatus::Exists
+        } else {
+            let sql = format!("CREATE EXTENSION IF NOT EXISTS {}", quote_ident(extension));
+            client
+                .batch_execute(&sql)
+                .await
+                .map_err(|e| prerequisite_error_pg("extension", extension, &e))?;
+            log::info!("Created prerequisite extension; extension={}", extension);
+            PrerequisiteStatus::Created
+        };
+        report.results.push(PrerequisiteResult {
+            kind: "extension".to_string(),
+            name: extension.clone(),
+            status,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Map a PostgreSQL error from a `CREATE SCHEMA` / `CREATE EXTENSION` into a
+/// [`WaypointError::PrerequisiteFailed`] with a privilege-aware reason.
+#[cfg(feature = "postgres")]
+fn prerequisite_error_pg(kind: &str, name: &str, e: &tokio_postgres::Error) -> WaypointError {
+    let code = e.as_db_error().map(|d| d.code().code().to_string());
+    let reason = match (kind, code.as_deref()) {
+        ("schema", Some("42501")) => format!(
+            "permission denied — the connecting role needs CREATE on the database ({})",
+            crate::error::format_db_error(e)
+        ),
+        ("extension", Some("42501")) => format!(
+            "permission denied — creating this extension requires superuser or a trusted extension ({})",
+            crate::error::format_db_error(e)
+        ),
+        ("extension", Some("58P01")) | ("extension", Some("0A000")) => format!(
+            "extension is not available on this server ({})",
+            crate::error::format_db_error(e)
+        ),
+        _ => crate::error::format_db_error(e),
+    };
+    WaypointError::PrerequisiteFailed {
+        object: format!("{} '{}'", kind, name),
+        reason,
+    }
+}
+
+/// MySQL: schemas map to databases; extensions do not exist and are skipped.
+#[cfg(feature = "mysql")]
+async fn ensure_prerequisites_mysql(
+    client: &DbClient,
+    config: &PrerequisitesConfig,
+) -> Result<PrerequisitesReport> {
+    use mysql_async::prelude::*;
+
+    let dialect = client.dialect();
+    let pool = client.as_mysql()?;
+    let mut conn = pool.get_conn().await?;
+    let mut report = PrerequisitesReport::default();
+
+    for schema in &config.schemas {
+        let exists: Option<i64> = conn
+            .exec_first(
+                "SELECT COUNT(*) FROM information_schema.SCHEMATA WHERE SCHEMA_NAME = ?",
+                (schema.as_str(),),
+            )
+            .await?;
+        let status = if exists.unwrap_or(0) > 0 {
+            PrerequisiteStatus::Exists
+        } else {
+            let sql = format!(
+                "CREATE DATABASE IF NOT EXISTS {}",
+                dialect.quote_ident(schema)
+            );
+            conn.query_drop(&sql).await.map_err(|e| {
+                let reason = match &e {
+                    mysql_async::Error::Server(se) if se.code == 1044 || se.code == 1142 => {
+                        format!(
+                            "permission denied — the connecting account needs CREATE privilege ({})",
+                            se.message
+                        )
+                    }
+                    other => other.to_string(),
+                };
+                WaypointError::PrerequisiteFailed {
+                    object: format!("schema '{}'", schema),
+                    reason,
+                }
+            })?;
+            log::info!("Created prerequisite database; database={}", schema);
+            PrerequisiteStatus::Created
+        };
+        report.results.push(PrerequisiteResult {
+            kind: "schema".to_string(),
+            name: schema.clone(),
+            status,
+        });
+    }
+
+    for extension in &config.extensions {
+        log::warn!(
+            "Skipping prerequisite extension on MySQL (extensions are PostgreSQL-only); extension={}",
+            extension
+        );
+        report.results.push(PrerequisiteResult {
+            kind: "extension".to_string(),
+            name: extension.clone(),
+            status: PrerequisiteStatus::Skipped,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_empty() {
+        assert!(PrerequisitesConfig::default().is_empty());
+    }
+
+    #[test]
+    fn test_not_empty_with_extension() {
+        let config = PrerequisitesConfig {
+            schemas: vec![],
+            extensions: vec!["pgcrypto".to_string()],
+        };
+        assert!(!config.is_empty());
+    }
+
+    #[test]
+    fn test_created_count() {
+        let report = PrerequisitesReport {
+            results: vec![
+                PrerequisiteResult {
+                    kind: "schema".to_string(),
+                    name: "app".to_string(),
+                    status: PrerequisiteStatus::Created,
+                },
+                PrerequisiteResult {
+                    kind: "extension".to_string(),
+                    name: "pgcrypto".to_string(),
+                    status: PrerequisiteStatus::Exists,
+                },
+            ],
+        };
+        assert_eq!(report.created_count(), 1);
+    }
+}
@@ -0,0 +1,304 @@
+//! Per-statement progress for multi-statement migrations.
+//!
+//! When `[migrations] statement_progress` is enabled, each statement of a
+//! migration is executed and recorded individually in a sidecar table
+//! (`<history_table>_statements`) next to the schema history table. A failure
+//! then reports exactly which statement failed, and for migrations that run
+//! without a transaction (`-- waypoint:no-transaction`, or any MySQL migration)
+//! `migrate --resume` continues from the failed statement instead of re-running
+//! the statements that already took effect.
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+#[cfg(feature = "postgres")]
+use crate::db::quote_ident;
+#[cfg(feature = "mysql")]
+use crate::db::DbClient;
+use crate::error::{Result, WaypointError};
+
+/// Name of the sidecar progress table for a given history table.
+pub fn progress_table_name(history_table: &str) -> String {
+    format!("{}_statements", history_table)
+}
+
+/// A recorded statement outcome, as stored in the progress table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementRecord {
+    /// 1-based position of the statement within the migration.
+    pub statement_index: i32,
+    /// Checksum of the migration file when the statement ran.
+    pub checksum: Option<i32>,
+    /// Whether the statement succeeded.
+    pub success: bool,
+}
+
+/// Number of statements that can be skipped on resume: the run of successful
+/// statements starting at index 1, stopping at the first gap or failure.
+///
+/// Fails if any record was written for a different checksum, because the
+/// statement indices no longer line up with the file on disk.
+pub fn resume_point(script: &str, checksum: i32, records: &[StatementRecord]) -> Result<usize> {
+    if records.iter().any(|r| r.checksum != Some(checksum)) {
+        return Err(WaypointError::MigrationFailed {
+            script: script.to_string(),
+            reason: "cannot resume: the migration file changed since the failed attempt \
+                     (run without --resume to start over)"
+                .to_string(),
+        });
+    }
+    let mut done = 0;
+    for r in records {
+        if r.success && r.statement_index == done as i32 + 1 {
+            done += 1;
+        } else {
+            break;
+        }
+    }
+    Ok(done)
+}
+
+// ── PostgreSQL ──────────────────────────────────────────────────────────────
+
+/// Create the progress table if it does not exist (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn create_progress_table(client: &Client, schema: &str, table: &str) -> Result<()> {
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {}.{} (
+            script VARCHAR(1000) NOT NULL,
+            checksum INTEGER,
+            statement_index INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            rows_affected BIGINT,
+            success BOOLEAN NOT NULL,
+            executed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (script, statement_index)
+        )",
+        quote_ident(schema),
+        quote_ident(&progress_table_name(table))
+    );
+    client.batch_execute(&sql).await?;
+    Ok(())
+}
+
+/// Load the recorded statements for a script, ordered by index (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn get_statement_records(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    script: &str,
+) -> Result<Vec<StatementRecord>> {
+    let sql = format!(
+        "SELECT statement_index, checksum, success FROM {}.{} WHERE script = $1 ORDER BY statement_index",
+        quote_ident(schema),
+        quote_ident(&progress_table_name(table))
+    );
+    let rows = client.query(&sql, &[&script]).await?;
+    Ok(rows
+        .iter()
+        .map(|r| StatementRecord {
+            statement_index: r.get(0),
+            checksum: r.get(1),
+            success: r.get(2),
+        })
+        .collect())
+}
+
+/// Delete records for a script from `from_index` onwards (PostgreSQL).
+#[cfg(feature = "postgres")]
+pub async fn clear_statements(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    script: &str,
+    from_index: i32,
+) -> Result<()> {
+    let sql = format!(
+        "DELETE FROM {}.{} WHERE script = $1 AND statement_index >= $2",
+        quote_ident(schema),
+        quote_ident(&progress_table_name(table))
+    );
+    client.execute(&sql, &[&script, &from_index]).await?;
+    Ok(())
+}
+
+/// Record the outcome of one statement (PostgreSQL).
+#[cfg(feature = "postgres")]
+#[allow(clippy::too_many_arguments)]
+pub async fn record_statement(
+    client: &Client,
+    schema: &str,
+    table: &str,
+    script: &str,
+    checksum: i32,
+    statement_index: i32,
+    duration_ms: i32,
+    rows_affected: Option<i64>,
+    success: bool,
+) -> Result<()> {
+    let sql = format!(
+        "INSERT INTO {}.{} (script, checksum, statement_index, duration_ms, rows_affected, success)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+        quote_ident(schema),
+        quote_ident(&progress_table_name(table))
+    );
+    client
+        .execute(
+            &sql,
+            &[
+                &script,
+                &checksum,
+                &statement_index,
+                &duration_ms,
+                &rows_affected,
+                &success,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+// ── MySQL ───────────────────────────────────────────────────────────────────
+
+/// Create the progress table if it does not exist (MySQL).
+#[cfg(feature = "mysql")]
+pub async fn create_progress_table_mysql(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+) -> Result<()> {
+    let dialect = client.dialect();
+    // VARCHAR(255) keeps the primary key within InnoDB's index length limit.
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            script VARCHAR(255) NOT NULL,
+            checksum INT,
+            statement_index INT NOT NULL,
+            duration_ms INT NOT NULL,
+            rows_affected BIGINT,
+            success BOOLEAN NOT NULL,
+            executed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (script, statement_index)
+        )",
+        dialect.qualified_table(schema, &progress_table_name(table))
+    );
+    client.execute_raw(&sql).await?;
+    Ok(())
+}
+
+/// Load the recorded statements for a script, ordered by index (MySQL).
+#[cfg(feature = "mysql")]
+pub async fn get_statement_records_mysql(
+    client: &DbClient,
+    schema: &str,
+    table: &str,
+    script: &str,
+) -> Result<Vec<StatementRecord>> {
+    use mysql_async::prelude::*;
+    let sql = format!(
+        "SELECT statement_index, checksum, success FROM {} WHERE script = ? ORDER BY statement_index",
+        client
+            .dialect()
+            .qualified_table(schema, &progress_table_name(table))
+    );
+    let mut conn = client.as_mysql()?.get_conn().await?;
+    let rows: Vec<(i32, Option<i32>, bool)> = conn.exec(&sql, (script,)).await?;
+    Ok(rows
+        .into_iter()
+        .map(|(statement_index, checksum, success)| StatementRecord {
+            statement_index,
+            checksum,
+            success,
+        })
+        .collect())
+}
+
+/// Delete records for a script from `from_index` onwards (MySQL).
+#[cfg(feature = "mysql")]
+pub async fn clear_statements_mysql(
+    conn: &mut mysql_async::Conn,
+    qualified_table: &str,
+    script: &str,
+    from_index: i32,
+) -> Result<()> {
+    use mysql_async::prelude::*;
+    let sql = format!(
+        "DELETE FROM {} WHERE script = ? AND statement_index >= ?",
+        qualified_table
+    );
+    conn.exec_drop(&sql, (script, from_index)).await?;
+    Ok(())
+}
+
+/// Record the outcome of one statement (MySQL).
+#[cfg(feature = "mysql")]
+#[allow(clippy::too_many_arguments)]
+pub async fn record_statement_mysql(
+    conn: &mut mysql_async::Conn,
+    qualified_table: &str,
+    script: &str,
+    checksum: i32,
+    statement_index: i32,
+    duration_ms: i32,
+    rows_affected: Option<i64>,
+    success: bool,
+) -> Result<()> {
+    use mysql_async::prelude::*;
+    let sql = format!(
+        "INSERT INTO {} (script, checksum, statement_index, duration_ms, rows_affected, success)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        qualified_table
+    );
+    conn.exec_drop(
+        &sql,
+        (
+            script,
+            checksum,
+            statement_index,
+            duration_ms,
+            rows_affected,
+            success,
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(statement_index: i32, success: bool) -> StatementRecord {
+        StatementRecord {
+            statement_index,
+            checksum: Some(42),
+            success,
+        }
+    }
+
+    #[test]
+    fn test_progress_table_name() {
+        assert_eq!(
+            progress_table_name("waypoint_schema_history"),
+            "waypoint_schema_history_statements"
+        );
+    }
+
+    #[test]
+    fn test_resume_point_stops_at_failure() {
+        let records = vec![rec(1, true), rec(2, true), rec(3, false)];
+        assert_eq!(resume_point("V1__x.sql", 42, &records).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_resume_point_empty() {
+        assert_eq!(resume_point("V1__x.sql", 42, &[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_resume_point_rejects_changed_checksum() {
+        let records = vec![rec(1, true)];
+        assert!(resume_point("V1__x.sql", 7, &records).is_err());
+    }
+}
@@ -0,0 +1,189 @@
+//! DDL provenance: which migration defined each schema object.
+//!
+//! Built from the DDL operations [`crate::sql_parser`] extracts from each
+//! versioned migration. Used to tell whether a migration can be undone on
+//! its own: a later migration that alters, drops, indexes or mentions an
+//! object the earlier one defined depends on it, whether or not it says so
+//! with `-- waypoint:depends`.
+//!
+//! The parser is regex-based, so the index errs on the side of reporting a
+//! use: any later migration that names a defined table, view, function or
+//! type as a whole word counts.
+
+use std::collections::BTreeMap;
+
+use regex_lite::Regex;
+
+use crate::migration::ResolvedMigration;
+use crate::sql_parser::{extract_ddl_operations, DdlOperation};
+
+/// A schema object defined by a migration.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchemaObject {
+    /// A table.
+    Table(String),
+    /// A column added to a table: `(table, column)`.
+    Column(String, String),
+    /// An index.
+    Index(String),
+    /// A view or materialized view.
+    View(String),
+    /// A function or procedure.
+    Function(String),
+    /// A type, such as an enum.
+    Type(String),
+}
+
+impl std::fmt::Display for SchemaObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaObject::Table(n) => write!(f, "table {}", n),
+            SchemaObject::Column(t, c) => write!(f, "column {}.{}", t, c),
+            SchemaObject::Index(n) => write!(f, "index {}", n),
+            SchemaObject::View(n) => write!(f, "view {}", n),
+            SchemaObject::Function(n) => write!(f, "function {}", n),
+            SchemaObject::Type(n) => write!(f, "type {}", n),
+        }
+    }
+}
+
+/// Index of the objects each versioned migration defined.
+#[derive(Debug, Default)]
+pub struct ProvenanceIndex {
+    /// Object -> version of the latest migration that defined it.
+    defined_by: BTreeMap<SchemaObject, String>,
+}
+
+impl ProvenanceIndex {
+    /// Build the index from versioned migrations; later versions win when
+    /// an object is defined more than once (dropped and re-created).
+    pub fn build(migrations: &[&ResolvedMigration]) -> Self {
+        let mut versioned: Vec<&ResolvedMigration> = migrations
+            .iter()
+            .filter(|m| m.is_versioned())
+            .copied()
+            .collect();
+        versioned.sort_by(|a, b| a.version().cmp(&b.version()));
+
+        let mut defined_by = BTreeMap::new();
+        for m in versioned {
+            let version = &m.version().expect("versioned").raw;
+            for object in defined_objects(&m.sql) {
+                defined_by.insert(object, version.clone());
+            }
+        }
+        ProvenanceIndex { defined_by }
+    }
+
+    /// Objects whose latest definition is in `version`.
+    pub fn defined_by(&self, version: &str) -> Vec<&SchemaObject> {
+        self.defined_by
+            .iter()
+            .filter(|(_, v)| v.as_str() == version)
+            .map(|(o, _)| o)
+            .collect()
+    }
+
+    /// Objects defined by `version` that `migration` uses.
+    pub fn uses_of(&self, version: &str, migration: &ResolvedMigration) -> Vec<&SchemaObject> {
+        self.defined_by(version)
+            .into_iter()
+            .filter(|o| uses(&migration.sql, o))
+            .collect()
+    }
+}
+
+/// Objects a migration's DDL creates.
+fn defined_objects(sql: &str) -> Vec<SchemaObject> {
+    extract_ddl_operations(sql)
+        .into_iter()
+        .filter_map(|op| match op {
+            DdlOperation::CreateTable { table, .. } => Some(SchemaObject::Table(normalize(&table))),
+            DdlOperation::AlterTableAddColumn { table, column, .. } => {
+                Some(SchemaObject::Column(normalize(&table), normalize(&column)))
+            }
+            DdlOperation::CreateIndex { name, .. } => Some(SchemaObject::Index(normalize(&name))),
+            DdlOperation::CreateView { name, .. } => Some(SchemaObject::View(normalize(&name))),
+            DdlOperation::CreateFunction { name } => Some(SchemaObject::Function(normalize(&name))),
+            DdlOperation::CreateEnum { name } => Some(SchemaObject::Type(normalize(&name))),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether `sql` uses `object`: names it as a whole word, or for a column,
+/// names both the table and the column.
+fn uses(sql: &str, object: &SchemaObject) -> bool {
+    match object {
+        SchemaObject::Table(n)
+        | SchemaObject::Index(n)
+        | SchemaObject::View(n)
+        | SchemaObject::Function(n)
+        | SchemaObject::Type(n) => mentions(sql, n),
+        SchemaObject::Column(t, c) => mentions(sql, t) && mentions(sql, c),
+    }
+}
+
+fn mentions(sql: &str, name: &str) -> bool {
+    Regex::new(&format!(r"(?i)\b{}\b", regex_lite::escape(name)))
+        .map(|re| re.is_match(sql))
+        .unwrap_or(false)
+}
+
+/// Unquoted, unqualified, lowercased object name.
+pub(crate) fn normalize(name: &str) -> String {
+    let unquoted: String = name.chars().filter(|c| !matches!(c, '"' | '`')).collect();
+    unquoted
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directive::MigrationDirectives;
+    use crate::migration::{MigrationKind, MigrationVersion};
+
+    fn migration(version: &str, sql: &str) -> ResolvedMigration {
+        ResolvedMigration {
+            kind: MigrationKind::Versioned(MigrationVersion::parse(version).unwrap()),
+            description: format!("V{}", version),
+            script: format!("V{}__test.sql", version),
+            checksum: 0,
+            sql: sql.to_string(),
+            directives: MigrationDirectives::default(),
+            copy_checksum_sha256: None,
+        }
+    }
+
+    #[test]
+    fn test_provenance_tracks_definitions_and_uses() {
+        let v1 = migration("1", "CREATE TABLE users (id INT);");
+        let v2 = migration(
+            "2",
+            "CREATE TABLE public.orders (id INT);\nALTER TABLE users ADD COLUMN email TEXT;",
+        );
+        let v3 = migration(
+            "3",
+            "CREATE INDEX orders_id_idx ON orders (id);\nCREATE TABLE audit (id INT);",
+        );
+        let v4 = migration("4", "UPDATE users SET name = 'x';");
+        let index = ProvenanceIndex::build(&[&v1, &v2, &v3, &v4]);
+
+        assert_eq!(
+            index.defined_by("2"),
+            vec![
+                &SchemaObject::Table("orders".into()),
+                &SchemaObject::Column("users".into(), "email".into()),
+            ]
+        );
+        assert_eq!(
+            index.uses_of("2", &v3),
+            vec![&SchemaObject::Table("orders".into())]
+        );
+        // Mentions users but not email: not a use of the column.
+        assert!(index.uses_of("2", &v4).is_empty());
+    }
+}
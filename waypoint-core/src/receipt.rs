@@ -0,0 +1,668 @@
+//! Signed execution receipts for migrate runs.
+//!
+//! With `[receipts] enabled = true`, a migrate that applied at least one
+//! migration produces an [`ExecutionReceipt`]: which migrations ran, their
+//! checksums, a hash of the whole migration set on disk (the run manifest),
+//! a fingerprint of the target database and the run's timestamps. The
+//! receipt is signed with HMAC-SHA256 under `[receipts] key` (normally
+//! `WAYPOINT_RECEIPT_KEY`) and written to `[receipts] dir` and/or the
+//! `<history_table>_receipts` sidecar table. `waypoint verify-receipt`
+//! checks the signature and, optionally, that the history table still
+//! records what the receipt says was applied.
+//!
+//! HMAC is symmetric: whoever holds the key to verify receipts can also
+//! forge them. Receipts prove that nobody without the key altered them;
+//! they are not proof of who issued them. Keep the key with the deploy
+//! pipeline and the auditors who verify, and rotate it (`key_id`) when
+//! either changes.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::commands::migrate::MigrateReport;
+use crate::config::WaypointConfig;
+use crate::db::DbClient;
+use crate::error::{Result, WaypointError};
+use crate::migration::ResolvedMigration;
+use crate::warning::{Warning, WarningCode};
+
+/// Current layout of [`ExecutionReceipt`].
+pub const RECEIPT_VERSION: u32 = 2;
+
+/// Prefix of [`ExecutionReceipt::signature`], naming the algorithm.
+const SIGNATURE_PREFIX: &str = "hmac-sha256:";
+
+/// Configuration for the `[receipts]` section.
+#[derive(Clone)]
+pub struct ReceiptConfig {
+    /// Issue a receipt after each migrate that applied migrations.
+    pub enabled: bool,
+    /// Directory receipts are written to as `<run_id>.json`. `None` skips
+    /// the file.
+    pub dir: Option<PathBuf>,
+    /// Also store receipts in the `<history_table>_receipts` table.
+    pub history: bool,
+    /// HMAC key receipts are signed and verified with.
+    pub key: Option<String>,
+    /// Label recorded in receipts to say which key signed them, for rotation.
+    pub key_id: Option<String>,
+}
+
+impl Default for ReceiptConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: Some(PathBuf::from("receipts")),
+            history: false,
+            key: None,
+            key_id: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for ReceiptConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReceiptConfig")
+            .field("enabled", &self.enabled)
+            .field("dir", &self.dir)
+            .field("history", &self.history)
+            .field("key", &self.key.as_ref().map(|_| "[REDACTED]"))
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+impl ReceiptConfig {
+    fn signing_key(&self) -> Result<&str> {
+        self.key
+            .as_deref()
+            .filter(|k| !k.is_empty())
+            .ok_or_else(|| {
+                WaypointError::ConfigError(
+                    "Receipts need a signing key; set [receipts] key or WAYPOINT_RECEIPT_KEY"
+                        .to_string(),
+                )
+            })
+    }
+}
+
+/// The database a receipt was issued against.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatabaseFingerprint {
+    /// `postgres` or `mysql`.
+    pub dialect: String,
+    /// Database name.
+    pub database: String,
+    /// Server version string, if it could be read.
+    pub server_version: Option<String>,
+    /// SHA-256 of the dialect, the server's identity (PostgreSQL
+    /// `system_identifier`, MySQL `server_uuid`, when readable) and the
+    /// database name.
+    pub fingerprint: String,
+}
+
+/// A migration applied in the run a receipt covers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptEntry {
+    /// Version, or `None` for a repeatable migration.
+    pub version: Option<String>,
+    /// Migration filename.
+    pub script: String,
+    /// CRC32 checksum of the file that was applied, as the history table
+    /// records it.
+    pub checksum: Option<i32>,
+    /// SHA-256 of the file that was applied (absent in version 1 receipts).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_sha256: Option<String>,
+    /// Execution time in milliseconds.
+    pub execution_time_ms: i32,
+}
+
+/// Tamper-evident record of one migrate run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionReceipt {
+    /// Receipt layout version ([`RECEIPT_VERSION`]).
+    pub receipt_version: u32,
+    /// Unique id of the run, also the receipt's filename.
+    pub run_id: String,
+    /// When the run started.
+    pub started_at: DateTime<Utc>,
+    /// When the run finished.
+    pub finished_at: DateTime<Utc>,
+    /// Target database.
+    pub database: DatabaseFingerprint,
+    /// Schema holding the history table.
+    pub schema: String,
+    /// History table name.
+    pub history_table: String,
+    /// Who applied the migrations.
+    pub installed_by: String,
+    /// SHA-256 over every migration file's name and SHA-256 as the run
+    /// resolved them (CRC32 checksums in version 1 receipts).
+    pub manifest_hash: String,
+    /// Migrations applied, in the order they ran.
+    pub applied: Vec<ReceiptEntry>,
+    /// `[receipts] key_id` of the signing key.
+    pub key_id: Option<String>,
+    /// `hmac-sha256:<hex>` over the receipt with this field empty.
+    pub signature: String,
+}
+
+impl ExecutionReceipt {
+    /// Bytes covered by the signature: the receipt as JSON with an empty
+    /// `signature`.
+    fn signed_bytes(&self) -> Vec<u8> {
+        let unsigned = Self {
+            signature: String::new(),
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).unwrap_or_default()
+    }
+
+    /// Sign the receipt with `key`.
+    pub fn sign(&mut self, key: &str) {
+        self.signature = format!(
+            "{}{}",
+            SIGNATURE_PREFIX,
+            hex(&hmac(key, &self.signed_bytes()))
+        );
+    }
+
+    /// Whether the signature matches the receipt's contents under `key`.
+    pub fn verify_signature(&self, key: &str) -> bool {
+        let Some(expected) = self
+            .signature
+            .strip_prefix(SIGNATURE_PREFIX)
+            .and_then(unhex)
+        else {
+            return false;
+        };
+        let mut mac = <Hmac<Sha256>>::new_from_slice(key.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(&self.signed_bytes());
+        mac.verify_slice(&expected).is_ok()
+    }
+
+    /// Read a receipt from a JSON file.
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            WaypointError::ConfigError(format!("Invalid receipt '{}': {}", path.display(), e))
+        })
+    }
+}
+
+/// Result of `verify-receipt`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiptVerification {
+    /// Run the receipt covers.
+    pub run_id: String,
+    /// `key_id` recorded in the receipt.
+    pub key_id: Option<String>,
+    /// Whether the signature matched.
+    pub signature_valid: bool,
+    /// Whether the connected database has the receipt's fingerprint, when
+    /// checked against a database.
+    pub database_matches: Option<bool>,
+    /// Receipt entries the history table no longer records as applied with
+    /// the same checksum, when checked against a database.
+    pub history_mismatches: Vec<String>,
+    /// Whether every check passed.
+    pub valid: bool,
+}
+
+impl ReceiptVerification {
+    fn finish(mut self) -> Self {
+        self.valid = self.signature_valid
+            && self.database_matches != Some(false)
+            && self.history_mismatches.is_empty();
+        self
+    }
+}
+
+/// A migration file as a run resolved it, before anything was applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Migration filename.
+    pub script: String,
+    /// CRC32 checksum.
+    pub checksum: i32,
+    /// SHA-256 of the file.
+    pub checksum_sha256: String,
+}
+
+/// The run manifest: every migration the run resolved.
+pub fn manifest(resolved: &[ResolvedMigration]) -> Vec<ManifestEntry> {
+    resolved
+        .iter()
+        .map(|m| ManifestEntry {
+            script: m.script.clone(),
+            checksum: m.checksum,
+            checksum_sha256: m.checksum_sha256(),
+        })
+        .collect()
+}
+
+/// SHA-256 over every migration's filename and SHA-256, sorted by filename.
+pub fn manifest_hash(manifest: &[ManifestEntry]) -> String {
+    let mut lines: Vec<String> = manifest
+        .iter()
+        .map(|m| format!("{}:{}", m.script, m.checksum_sha256))
+        .collect();
+    lines.sort();
+    hex(&Sha256::digest(lines.join("\n").as_bytes()))
+}
+
+/// Issue a receipt for a finished run, if `[receipts]` is enabled and the
+/// run applied something. Failures are logged and added to the report's
+/// warnings; the migrations are already committed.
+pub async fn issue(
+    client: &DbClient,
+    config: &WaypointConfig,
+    report: &mut MigrateReport,
+    started_at: DateTime<Utc>,
+) {
+    if !config.receipts.enabled || report.migrations_applied == 0 {
+        return;
+    }
+    let result = async {
+        let receipt = build(client, config, report, started_at).await?;
+        store(client, config, &receipt).await?;
+        Ok::<_, WaypointError>(receipt)
+    }
+    .await;
+
+    match result {
+        Ok(receipt) => log::info!(
+            "Issued execution receipt; run_id={}, migrations_applied={}",
+            receipt.run_id,
+            receipt.applied.len()
+        ),
+        Err(e) => {
+            log::error!("Failed to issue execution receipt: {}", e);
+            report.warnings.push(Warning::new(
+                WarningCode::ReceiptFailed,
+                "receipt",
+                format!("execution receipt was not issued: {}", e),
+            ));
+        }
+    }
+}
+
+async fn build(
+    client: &DbClient,
+    config: &WaypointConfig,
+    report: &MigrateReport,
+    started_at: DateTime<Utc>,
+) -> Result<ExecutionReceipt> {
+    let key = config.receipts.signing_key()?;
+    // The files as the run saw them; they may have changed on disk since.
+    if report.manifest.is_empty() {
+        return Err(WaypointError::ConfigError(
+            "The migrate run recorded no manifest to sign".to_string(),
+        ));
+    }
+    let applied = report
+        .details
+        .iter()
+        .map(|d| {
+            let file = report.manifest.iter().find(|m| m.script == d.script);
+            ReceiptEntry {
+                version: d.version.clone(),
+                script: d.script.clone(),
+                checksum: file.map(|m| m.checksum),
+                checksum_sha256: file.map(|m| m.checksum_sha256.clone()),
+                execution_time_ms: d.execution_time_ms,
+            }
+        })
+        .collect();
+    let installed_by = match &config.migrations.installed_by {
+        Some(by) => by.clone(),
+        None => client.current_user().await?,
+    };
+    let mut receipt = ExecutionReceipt {
+        receipt_version: RECEIPT_VERSION,
+        run_id: format!(
+            "{}-{:08x}",
+            started_at.format("%Y%m%dT%H%M%SZ"),
+            fastrand::u32(..)
+        ),
+        started_at,
+        finished_at: Utc::now(),
+        database: fingerprint(client).await?,
+        schema: client.resolve_schema(&config.migrations.schema).await?,
+        history_table: config.migrations.table.clone(),
+        installed_by,
+        manifest_hash: manifest_hash(&report.manifest),
+        applied,
+        key_id: config.receipts.key_id.clone(),
+        signature: String::new(),
+    };
+    receipt.sign(key);
+    Ok(receipt)
+}
+
+async fn store(
+    client: &DbClient,
+    config: &WaypointConfig,
+    receipt: &ExecutionReceipt,
+) -> Result<()> {
+    let json = serde_json::to_string_pretty(receipt)
+        .map_err(|e| WaypointError::IoError(std::io::Error::other(e)))?;
+    if let Some(dir) = &config.receipts.dir {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(format!("{}.json", receipt.run_id)), &json)?;
+    }
+    if config.receipts.history {
+        let schema = &receipt.schema;
+        let table = receipts_table_name(&config.migrations.table);
+        let qualified = client.dialect().qualified_table(schema, &table);
+        match client {
+            #[cfg(feature = "postgres")]
+            DbClient::Postgres(c) => {
+                c.batch_execute(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} (
+                        run_id VARCHAR(64) PRIMARY KEY,
+                        issued_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                        receipt TEXT NOT NULL
+                    )",
+                    qualified
+                ))
+                .await?;
+                c.execute(
+                    &format!(
+                        "INSERT INTO {} (run_id, receipt) VALUES ($1, $2)",
+                        qualified
+                    ),
+                    &[&receipt.run_id, &json],
+                )
+                .await?;
+            }
+            #[cfg(feature = "mysql")]
+            DbClient::Mysql(pool) => {
+                use mysql_async::prelude::*;
+                client
+                    .execute_raw(&format!(
+                        "CREATE TABLE IF NOT EXISTS {} (
+                            run_id VARCHAR(64) PRIMARY KEY,
+                            issued_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                            receipt LONGTEXT NOT NULL
+                        )",
+                        qualified
+                    ))
+                    .await?;
+                let mut conn = pool.get_conn().await?;
+                conn.exec_drop(
+                    format!("INSERT INTO {} (run_id, receipt) VALUES (?, ?)", qualified),
+                    (&receipt.run_id, &json),
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Name of the sidecar receipts table for a given history table.
+pub fn receipts_table_name(history_table: &str) -> String {
+    format!("{}_receipts", history_table)
+}
+
+/// Load a receipt stored in the `<history_table>_receipts` table.
+pub async fn load(
+    client: &DbClient,
+    config: &WaypointConfig,
+    run_id: &str,
+) -> Result<ExecutionReceipt> {
+    let schema = client.resolve_schema(&config.migrations.schema).await?;
+    let qualified = client
+        .dialect()
+        .qualified_table(&schema, &receipts_table_name(&config.migrations.table));
+    let json: Option<String> = match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => c
+            .query_opt(
+                &format!("SELECT receipt FROM {} WHERE run_id = $1", qualified),
+                &[&run_id],
+            )
+            .await?
+            .map(|row| row.get(0)),
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let mut conn = pool.get_conn().await?;
+            conn.exec_first(
+                format!("SELECT receipt FROM {} WHERE run_id = ?", qualified),
+                (run_id,),
+            )
+            .await?
+        }
+    };
+    let json = json.ok_or_else(|| {
+        WaypointError::ConfigError(format!("No receipt with run_id '{}'", run_id))
+    })?;
+    serde_json::from_str(&json)
+        .map_err(|e| WaypointError::ConfigError(format!("Invalid stored receipt: {}", e)))
+}
+
+/// Check a receipt's signature without a database.
+pub fn verify_offline(
+    config: &WaypointConfig,
+    receipt: &ExecutionReceipt,
+) -> Result<ReceiptVerification> {
+    let key = config.receipts.signing_key()?;
+    Ok(ReceiptVerification {
+        run_id: receipt.run_id.clone(),
+        key_id: receipt.key_id.clone(),
+        signature_valid: receipt.verify_signature(key),
+        database_matches: None,
+        history_mismatches: Vec::new(),
+        valid: false,
+    }
+    .finish())
+}
+
+/// Check a receipt's signature, that `client` is the database it was issued
+/// against, and that the history table still records each applied
+/// migration with the receipt's checksum.
+pub async fn verify(
+    client: &DbClient,
+    config: &WaypointConfig,
+    receipt: &ExecutionReceipt,
+) -> Result<ReceiptVerification> {
+    let mut verification = verify_offline(config, receipt)?;
+    verification.database_matches =
+        Some(fingerprint(client).await?.fingerprint == receipt.database.fingerprint);
+
+    let applied =
+        crate::history::get_applied_migrations_db(client, &receipt.schema, &receipt.history_table)
+            .await?;
+    for entry in &receipt.applied {
+        let recorded = applied.iter().any(|a| {
+            a.success
+                && a.script == entry.script
+                && a.version == entry.version
+                && a.checksum == entry.checksum
+                && match (&a.checksum_sha256, &entry.checksum_sha256) {
+                    (Some(recorded), Some(issued)) => recorded == issued,
+                    _ => true,
+                }
+        });
+        if !recorded {
+            verification.history_mismatches.push(entry.script.clone());
+        }
+    }
+    Ok(verification.finish())
+}
+
+/// Identify the connected database.
+async fn fingerprint(client: &DbClient) -> Result<DatabaseFingerprint> {
+    let kind = client.dialect_kind().name();
+    let database = client.current_database().await?;
+    let identity = server_identity(client).await?;
+    Ok(DatabaseFingerprint {
+        dialect: kind.to_string(),
+        fingerprint: hex(&Sha256::digest(
+            format!("{}\n{}\n{}", kind, identity, database).as_bytes(),
+        )),
+        database,
+        server_version: client.server_version().await.ok(),
+    })
+}
+
+/// PostgreSQL `system_identifier` or MySQL `server_uuid`, which survive
+/// restarts but differ between clusters. An error when the role can't read
+/// it: a fingerprint without it would match any database of the same name.
+async fn server_identity(client: &DbClient) -> Result<String> {
+    let identity: Option<String> = match client {
+        #[cfg(feature = "postgres")]
+        DbClient::Postgres(c) => c
+            .query_one(
+                "SELECT system_identifier::text FROM pg_control_system()",
+                &[],
+            )
+            .await
+            .map_err(|e| identity_error("pg_control_system()", e))?
+            .try_get(0)
+            .map_err(|e| identity_error("pg_control_system()", e))?,
+        #[cfg(feature = "mysql")]
+        DbClient::Mysql(pool) => {
+            use mysql_async::prelude::*;
+            let mut conn = pool.get_conn().await?;
+            conn.query_first("SELECT @@server_uuid")
+                .await
+                .map_err(|e| identity_error("@@server_uuid", e))?
+        }
+    };
+    identity.ok_or_else(|| identity_error("the server identity", "no value"))
+}
+
+fn identity_error(source: &str, e: impl std::fmt::Display) -> WaypointError {
+    WaypointError::ConfigError(format!(
+        "Cannot fingerprint the database: reading {} failed ({}); \
+         grant the role access to it",
+        source, e
+    ))
+}
+
+fn hmac(key: &str, data: &[u8]) -> Vec<u8> {
+    let mut mac =
+        <Hmac<Sha256>>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::scan_migrations;
+
+    fn receipt() -> ExecutionReceipt {
+        let at = DateTime::parse_from_rfc3339("2026-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        ExecutionReceipt {
+            receipt_version: RECEIPT_VERSION,
+            run_id: "20260102T030405Z-0000abcd".to_string(),
+            started_at: at,
+            finished_at: at,
+            database: DatabaseFingerprint {
+                dialect: "postgres".to_string(),
+                database: "app".to_string(),
+                server_version: Some("16.2".to_string()),
+                fingerprint: "f".repeat(64),
+            },
+            schema: "public".to_string(),
+            history_table: "waypoint_schema_history".to_string(),
+            installed_by: "deploy".to_string(),
+            manifest_hash: "0".repeat(64),
+            applied: vec![ReceiptEntry {
+                version: Some("1".to_string()),
+                script: "V1__init.sql".to_string(),
+                checksum: Some(42),
+                checksum_sha256: Some("a".repeat(64)),
+                execution_time_ms: 5,
+            }],
+            key_id: Some("2026-q1".to_string()),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_signature_detects_tampering() {
+        let mut r = receipt();
+        r.sign("secret");
+        assert!(r.signature.starts_with("hmac-sha256:"));
+        assert!(r.verify_signature("secret"));
+        assert!(!r.verify_signature("other"));
+
+        // Survives a JSON round-trip.
+        let parsed: ExecutionReceipt =
+            serde_json::from_str(&serde_json::to_string_pretty(&r).unwrap()).unwrap();
+        assert!(parsed.verify_signature("secret"));
+
+        let mut tampered = parsed.clone();
+        tampered.applied[0].checksum = Some(43);
+        assert!(!tampered.verify_signature("secret"));
+
+        let mut garbled = parsed;
+        garbled.signature = "hmac-sha256:zz".to_string();
+        assert!(!garbled.verify_signature("secret"));
+    }
+
+    #[test]
+    fn test_manifest_hash_ignores_scan_order() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("V1__a.sql"), "SELECT 1;").unwrap();
+        std::fs::write(dir.path().join("V2__b.sql"), "SELECT 2;").unwrap();
+        let mut files = manifest(&scan_migrations(&[dir.path().to_path_buf()]).unwrap());
+        let hash = manifest_hash(&files);
+        assert_eq!(hash.len(), 64);
+        files.reverse();
+        assert_eq!(manifest_hash(&files), hash);
+
+        std::fs::write(dir.path().join("V2__b.sql"), "SELECT 3;").unwrap();
+        let changed = manifest(&scan_migrations(&[dir.path().to_path_buf()]).unwrap());
+        assert_ne!(manifest_hash(&changed), hash);
+    }
+
+    #[test]
+    fn test_version_1_receipts_still_verify() {
+        let mut r = receipt();
+        r.receipt_version = 1;
+        r.applied[0].checksum_sha256 = None;
+        r.sign("secret");
+        let json = serde_json::to_string(&r).unwrap();
+        assert!(!json.contains("checksum_sha256"));
+        let parsed: ExecutionReceipt = serde_json::from_str(&json).unwrap();
+        assert!(parsed.verify_signature("secret"));
+    }
+
+    #[test]
+    fn test_verify_offline_requires_key() {
+        let mut config = WaypointConfig::default();
+        let mut r = receipt();
+        r.sign("secret");
+        assert!(verify_offline(&config, &r).is_err());
+        config.receipts.key = Some("secret".to_string());
+        assert!(verify_offline(&config, &r).unwrap().valid);
+    }
+}
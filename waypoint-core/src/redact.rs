@@ -0,0 +1,352 @@
+//! Redaction of identifiers and SQL in JSON and log output.
+//!
+//! Some organisations treat table and column names as sensitive once logs
+//! leave the host. The `[output]` section lists what to hide:
+//!
+//! ```toml
+//! [output]
+//! redact = ["table_names", "sql"]
+//! redact_mode = "hash"            # hash | mask
+//!
+//! [output.commands]
+//! drift = ["table_names", "column_names", "sql"]
+//! ```
+//!
+//! A [`Redactor`] rewrites the values of sensitive fields in a serialized
+//! report, and the `key=value` fields of log lines, leaving counts, versions,
+//! verdicts and timings alone. Hashing keeps equal names equal across runs,
+//! so output can still be correlated; masking hides them entirely.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use hmac::{Hmac, Mac};
+use regex_lite::Regex;
+use serde_json::Value;
+use sha2::Sha256;
+
+/// A class of sensitive value in `[output] redact`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactField {
+    /// Schema, table, view, index, constraint and other object names.
+    TableNames,
+    /// Column names.
+    ColumnNames,
+    /// SQL text: statements, definitions, generated DDL and guard expressions.
+    Sql,
+    /// Migration filenames and descriptions.
+    Scripts,
+    /// Free-text messages (warnings, errors, suggestions), which may quote
+    /// any of the above.
+    Messages,
+}
+
+impl std::str::FromStr for RedactField {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "table_names" | "tables" => Ok(Self::TableNames),
+            "column_names" | "columns" => Ok(Self::ColumnNames),
+            "sql" => Ok(Self::Sql),
+            "scripts" => Ok(Self::Scripts),
+            "messages" => Ok(Self::Messages),
+            other => Err(format!("unknown redact field: '{other}'")),
+        }
+    }
+}
+
+/// How redacted values are replaced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedactMode {
+    /// A short stable HMAC-SHA256 (`#1a2b3c4d5e6f7a8b`), so equal values stay equal.
+    #[default]
+    Hash,
+    /// A fixed `***`.
+    Mask,
+}
+
+impl std::str::FromStr for RedactMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hash" => Ok(Self::Hash),
+            "mask" => Ok(Self::Mask),
+            other => Err(format!("unknown redact mode: '{other}'")),
+        }
+    }
+}
+
+/// Configuration for the `[output]` section.
+#[derive(Debug, Clone, Default)]
+pub struct OutputConfig {
+    /// Fields redacted for every command.
+    pub redact: Vec<RedactField>,
+    /// Per-command overrides of `redact`, keyed by command name.
+    pub commands: HashMap<String, Vec<RedactField>>,
+    /// How redacted values are replaced.
+    pub redact_mode: RedactMode,
+    /// HMAC key for hashes, so short names can't be recovered by guessing.
+    pub hash_salt: Option<String>,
+}
+
+impl OutputConfig {
+    /// Redactor for `command`: its `[output.commands]` entry if any,
+    /// otherwise the top-level `redact` list.
+    pub fn redactor(&self, command: &str) -> Redactor {
+        Redactor {
+            fields: self.commands.get(command).unwrap_or(&self.redact).clone(),
+            mode: self.redact_mode,
+            salt: self.hash_salt.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Rewrites sensitive values in reports and log lines.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    fields: Vec<RedactField>,
+    mode: RedactMode,
+    salt: String,
+}
+
+/// Bytes of the HMAC kept in a hashed value: 64 bits, so distinct names
+/// don't collide in practice.
+const HASH_BYTES: usize = 8;
+
+/// Matches the start of a `key=value` field in a log line.
+static LOG_FIELD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|, |; )([a-z_]+)=").unwrap());
+
+/// Matches `SchemaDiff` variant tags such as `TableDropped`, whose value is
+/// an object name.
+static DIFF_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Z][A-Za-z]*(Added|Dropped)$").unwrap());
+
+impl Redactor {
+    /// Whether anything is redacted.
+    pub fn is_active(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    /// Whether `field` is redacted.
+    pub fn redacts(&self, field: RedactField) -> bool {
+        self.fields.contains(&field)
+    }
+
+    /// Replacement for a sensitive value.
+    pub fn redact_str(&self, value: &str) -> String {
+        match self.mode {
+            RedactMode::Mask => "***".to_string(),
+            RedactMode::Hash => {
+                let mut mac = <Hmac<Sha256>>::new_from_slice(self.salt.as_bytes())
+                    .expect("HMAC accepts keys of any length");
+                mac.update(value.as_bytes());
+                let digest = mac.finalize().into_bytes();
+                let hex: String = digest[..HASH_BYTES]
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                format!("#{}", hex)
+            }
+        }
+    }
+
+    /// Redact sensitive fields of a serialized report in place.
+    pub fn redact_value(&self, value: &mut Value) {
+        if self.is_active() {
+            self.walk(value, false);
+        }
+    }
+
+    /// Redact the sensitive `key=value` fields of a log line. A line with
+    /// no such fields is free text, redacted whole when `messages` is on.
+    pub fn redact_log(&self, line: &str) -> String {
+        if !self.is_active() {
+            return line.to_string();
+        }
+        let fields: Vec<_> = LOG_FIELD.captures_iter(line).collect();
+        if fields.is_empty() {
+            return if self.redacts(RedactField::Messages) {
+                self.redact_str(line)
+            } else {
+                line.to_string()
+            };
+        }
+        let mut out = String::with_capacity(line.len());
+        let mut pos = 0;
+        for (i, caps) in fields.iter().enumerate() {
+            let value_start = caps.get(0).unwrap().end();
+            let value_end = fields
+                .get(i + 1)
+                .map_or(line.len(), |next| next.get(0).unwrap().start());
+            out.push_str(&line[pos..value_start]);
+            let value = &line[value_start..value_end];
+            if self.key_redacted(&caps[1], false) && !value.is_empty() {
+                out.push_str(&self.redact_str(value));
+            } else {
+                out.push_str(value);
+            }
+            pos = value_end;
+        }
+        out.push_str(&line[pos..]);
+        out
+    }
+
+    fn walk(&self, value: &mut Value, in_columns: bool) {
+        match value {
+            Value::Object(map) => {
+                for (key, v) in map.iter_mut() {
+                    let columns = in_columns || key == "columns" || key == "column";
+                    let redact = self.key_redacted(key, in_columns)
+                        || (DIFF_TAG.is_match(key) && self.redacts(RedactField::TableNames));
+                    match v {
+                        Value::String(s) if redact => *s = self.redact_str(s),
+                        Value::Array(items) if redact => {
+                            for item in items.iter_mut() {
+                                match item {
+                                    Value::String(s) => *s = self.redact_str(s),
+                                    other => self.walk(other, columns),
+                                }
+                            }
+                        }
+                        other => self.walk(other, columns),
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items.iter_mut() {
+                    self.walk(item, in_columns);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether values under `key` are redacted. Inside a column definition,
+    /// `name` is a column name rather than an object name.
+    fn key_redacted(&self, key: &str, in_columns: bool) -> bool {
+        let classes: &[RedactField] = match key {
+            "name" if in_columns => &[RedactField::ColumnNames],
+            "table" | "table_name" | "tables" | "name" | "schema" | "schemas" | "temp_schema"
+            | "object" | "view" | "index" | "constraint" | "trigger" | "sequence"
+            | "references" | "extension" | "objects" | "lock" | "lock_table" => {
+                &[RedactField::TableNames]
+            }
+            "column" | "columns" | "column_name" | "columns_added" => &[RedactField::ColumnNames],
+            // `TableRenamed` names tables; `ViewAltered` carries definitions.
+            "from" | "to" => &[RedactField::TableNames, RedactField::Sql],
+            "sql" | "generated_sql" | "definition" | "statement" | "statements"
+            | "statement_preview" | "reversal_sql" | "fix_sql" | "expression" | "where_clause"
+            | "ddl" | "query" => &[RedactField::Sql],
+            "script" | "script_name" | "description" | "files" => &[RedactField::Scripts],
+            "message" | "reason" | "detail" | "error" | "suggestion" | "suggestions"
+            | "explanation" | "warnings" => &[RedactField::Messages],
+            _ => &[],
+        };
+        classes.iter().any(|c| self.redacts(*c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redactor(fields: &[RedactField], mode: RedactMode) -> Redactor {
+        OutputConfig {
+            redact: fields.to_vec(),
+            redact_mode: mode,
+            ..Default::default()
+        }
+        .redactor("migrate")
+    }
+
+    #[test]
+    fn test_redact_value_keeps_counts_and_verdicts() {
+        let r = redactor(
+            &[RedactField::TableNames, RedactField::Sql],
+            RedactMode::Mask,
+        );
+        let mut report = json!({
+            "migrations_applied": 2,
+            "verdict": "Safe",
+            "script": "V2__users.sql",
+            "diffs": [
+                {"TableDropped": "users"},
+                {"ColumnAdded": {"table": "orders", "column": {"name": "email"}}}
+            ],
+            "generated_sql": "ALTER TABLE orders ADD COLUMN email text;"
+        });
+        r.redact_value(&mut report);
+        assert_eq!(report["migrations_applied"], 2);
+        assert_eq!(report["verdict"], "Safe");
+        assert_eq!(report["script"], "V2__users.sql");
+        assert_eq!(report["diffs"][0]["TableDropped"], "***");
+        assert_eq!(report["diffs"][1]["ColumnAdded"]["table"], "***");
+        // Column names are a separate class.
+        assert_eq!(report["diffs"][1]["ColumnAdded"]["column"]["name"], "email");
+        assert_eq!(report["generated_sql"], "***");
+    }
+
+    #[test]
+    fn test_hash_is_stable_and_salted() {
+        let r = redactor(&[RedactField::TableNames], RedactMode::Hash);
+        assert_eq!(r.redact_str("users"), r.redact_str("users"));
+        assert_ne!(r.redact_str("users"), r.redact_str("orders"));
+
+        let salted = OutputConfig {
+            redact: vec![RedactField::TableNames],
+            hash_salt: Some("pepper".into()),
+            ..Default::default()
+        }
+        .redactor("info");
+        assert_ne!(salted.redact_str("users"), r.redact_str("users"));
+    }
+
+    #[test]
+    fn test_hash_is_hmac_sha256_prefix() {
+        let salted = OutputConfig {
+            redact: vec![RedactField::TableNames],
+            hash_salt: Some("key".into()),
+            ..Default::default()
+        }
+        .redactor("info");
+        // HMAC-SHA256("key", "The quick brown fox jumps over the lazy dog")
+        // is f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8.
+        assert_eq!(
+            salted.redact_str("The quick brown fox jumps over the lazy dog"),
+            "#f7bc83f430538424"
+        );
+    }
+
+    #[test]
+    fn test_redact_log_fields() {
+        let r = redactor(&[RedactField::Scripts], RedactMode::Mask);
+        assert_eq!(
+            r.redact_log("Migration applied; version=2, script=V2__users.sql, time_ms=4"),
+            "Migration applied; version=2, script=***, time_ms=4"
+        );
+        assert_eq!(
+            Redactor::default().redact_log("Applying migration; script=V1__a.sql"),
+            "Applying migration; script=V1__a.sql"
+        );
+        // Free text is only touched when messages are redacted.
+        let free = "Reversal warning for V1__a.sql: DROP TABLE users";
+        assert_eq!(r.redact_log(free), free);
+        let r = redactor(&[RedactField::Messages], RedactMode::Mask);
+        assert_eq!(r.redact_log(free), "***");
+    }
+
+    #[test]
+    fn test_per_command_override() {
+        let config = OutputConfig {
+            redact: vec![RedactField::Sql],
+            commands: HashMap::from([("info".to_string(), vec![])]),
+            ..Default::default()
+        };
+        assert!(config.redactor("diff").is_active());
+        assert!(!config.redactor("info").is_active());
+        assert!("nope".parse::<RedactField>().is_err());
+    }
+}
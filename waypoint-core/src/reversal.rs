@@ -108,7 +108,8 @@ pub async fn generate_reversal(
     }
 
     // Generate DDL from the reverse diff
-    let mut sql = schema::generate_ddl(&reverse_diffs);
+    let version = crate::db::get_server_version(client).await.ok();
+    let mut sql = schema::generate_ddl_for_version(&reverse_diffs, version);
 
     // Prepend data-loss warnings as SQL comments
     if has_data_loss && warn_data_loss {
@@ -219,7 +220,9 @@ pub async fn generate_reversal_db(
 
     // Emit DDL in the dialect of the connection.
     let mut sql = match client.dialect_kind() {
-        DialectKind::Postgres => schema::generate_ddl(&reverse_diffs),
+        DialectKind::Postgres => {
+            schema::generate_ddl_for_version(&reverse_diffs, client.pg_version().await)
+        }
         DialectKind::Mysql => schema::generate_ddl_mysql(&reverse_diffs),
     };
 
@@ -341,6 +344,8 @@ mod tests {
             constraints: vec![],
             triggers: vec![],
             extensions: vec![],
+            distributed_tables: vec![],
+            incomplete: vec![],
         }
     }
 
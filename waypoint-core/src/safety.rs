@@ -5,11 +5,20 @@
 //! ([`analyze_migration_db`]), and a handful of shared helpers used by both
 //! engine paths. The actual per-engine analysers live in
 //! [`crate::engines::postgres::safety`] and [`crate::engines::mysql::safety`].
+//!
+//! [`privileged_statements`] is a purely static check for statements that
+//! need more than ordinary owner rights (superuser, CREATEROLE, server file
+//! access, ...), so they can be reported before anyone tries to run them.
+//! [`analyze_default_change`] decides whether an `ADD COLUMN ... DEFAULT` or
+//! `SET DEFAULT` rewrites the table on a given PostgreSQL version.
+
+use std::sync::LazyLock;
 
-use serde::Serialize;
+use regex_lite::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::db::DbClient;
-use crate::dialect::DialectKind;
+use crate::dialect::{DialectKind, PgVersion};
 use crate::error::Result;
 use crate::sql_parser::DdlOperation;
 
@@ -27,6 +36,11 @@ pub use crate::engines::postgres::safety::{
 
 // ── Shared types ────────────────────────────────────────────────────────────
 
+/// Rows per second assumed when estimating how long a statement that scans
+/// or rewrites a table takes. Deliberately conservative; estimates are for
+/// ordering work, not SLAs.
+pub const ESTIMATED_ROWS_PER_SEC: i64 = 200_000;
+
 /// PostgreSQL lock levels, ordered from least to most restrictive.
 ///
 /// The ordering matches PostgreSQL's internal lock hierarchy so that
@@ -94,7 +108,7 @@ impl std::fmt::Display for TableSize {
 }
 
 /// Overall safety verdict for a migration statement or script.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum SafetyVerdict {
     /// No significant risk detected.
     Safe,
@@ -146,6 +160,108 @@ pub struct SafetyReport {
     pub statements: Vec<StatementAnalysis>,
     /// Aggregated suggestions across all statements.
     pub suggestions: Vec<String>,
+    /// Server version the analysis was run against, if it could be detected.
+    pub server_version: Option<String>,
+    /// Statements that need elevated privileges to run.
+    pub privileged: Vec<PrivilegedStatement>,
+    /// `ADD COLUMN ... DEFAULT` / `SET DEFAULT` statements and whether they
+    /// rewrite the table (PostgreSQL).
+    pub default_changes: Vec<DefaultChange>,
+    /// Whether the migration is Rust code that couldn't be analyzed; its
+    /// verdict is then `Caution` with no statements.
+    pub unanalyzable: bool,
+}
+
+impl SafetyReport {
+    /// Report for a Rust code migration: nothing to analyze, so review it by hand.
+    pub fn code_migration(script: &str) -> Self {
+        SafetyReport {
+            script: script.to_string(),
+            overall_verdict: SafetyVerdict::Caution,
+            statements: Vec::new(),
+            suggestions: vec![
+                "Rust code migration: its statements can't be analyzed; review `up()` by hand"
+                    .to_string(),
+            ],
+            server_version: None,
+            privileged: Vec::new(),
+            default_changes: Vec::new(),
+            unanalyzable: true,
+        }
+    }
+}
+
+/// Kind of column default change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultPattern {
+    /// `ALTER TABLE ... ADD COLUMN ... DEFAULT ...`
+    AddColumnDefault,
+    /// `ALTER TABLE ... ALTER COLUMN ... SET DEFAULT ...`
+    SetDefault,
+}
+
+/// Analysis of a statement that adds or changes a column default.
+#[derive(Debug, Clone, Serialize)]
+pub struct DefaultChange {
+    /// A short preview of the statement.
+    pub statement_preview: String,
+    /// Table as written in the statement.
+    pub table: String,
+    /// Column as written in the statement.
+    pub column: String,
+    /// Column type (`ADD COLUMN` only).
+    pub data_type: Option<String>,
+    /// The default expression.
+    pub default_expr: String,
+    pub pattern: DefaultPattern,
+    /// Whether the default is volatile (evaluated per row), e.g. `random()`
+    /// or a `serial` column.
+    pub volatile: bool,
+    /// Whether the column is declared `NOT NULL`.
+    pub not_null: bool,
+    /// Whether the statement rewrites the whole table on this server.
+    pub rewrites_table: bool,
+    /// Why it does or doesn't rewrite.
+    pub reason: String,
+    /// Rough rewrite duration from the table's row estimate.
+    pub estimated_rewrite_ms: Option<i64>,
+    /// Safer alternative, when the statement rewrites the table.
+    pub alternative: Option<String>,
+}
+
+/// The privilege a [`PrivilegedStatement`] needs beyond owning the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivilegeRequirement {
+    /// Superuser (PostgreSQL).
+    Superuser,
+    /// The CREATEROLE attribute (PostgreSQL role DDL).
+    CreateRole,
+    /// Superuser, unless the extension is trusted or already installed.
+    Extension,
+    /// Superuser or `pg_read_server_files` (server-side `COPY ... FROM 'file'`).
+    ReadServerFiles,
+    /// Superuser or `pg_write_server_files` (server-side `COPY ... TO 'file'`).
+    WriteServerFiles,
+    /// Superuser or `pg_execute_server_program` (`COPY ... PROGRAM`).
+    ServerProgram,
+    /// A MySQL administrative privilege (CREATE USER, SUPER, FILE, ...).
+    Admin,
+}
+
+/// A statement that needs elevated privileges to run.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivilegedStatement {
+    /// A short preview of the statement.
+    pub statement_preview: String,
+    /// What the statement needs.
+    pub requirement: PrivilegeRequirement,
+    /// Human-readable explanation, e.g. "ALTER SYSTEM requires superuser".
+    pub reason: String,
+    /// Extension name for `CREATE EXTENSION` statements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
 }
 
 /// Configuration for safety analysis.
@@ -166,6 +282,22 @@ pub struct SafetyConfig {
     /// at the cost of touching the table — typically during a CI safety check
     /// rather than at production-migrate time.
     pub refresh_stats_mysql: bool,
+    /// PostgreSQL only: refuse to start `migrate` when a pending migration
+    /// contains a statement the connected role lacks the privileges for,
+    /// instead of failing halfway through the run.
+    pub block_privileged: bool,
+    /// PostgreSQL only: `lock_timeout` in milliseconds for each migration, so
+    /// DDL waiting on a busy table gives up instead of queueing (and making
+    /// every later query on that table queue behind it). 0 keeps the server's
+    /// setting.
+    pub lock_timeout_ms: u64,
+    /// PostgreSQL only: how many times `migrate` retries a migration (or, with
+    /// `-- waypoint:no-transaction`, a statement) that failed on a lock
+    /// timeout.
+    pub ddl_retry: u32,
+    /// Delay before the first lock-timeout retry, in milliseconds; doubled for
+    /// each further attempt, up to a minute.
+    pub ddl_retry_backoff_ms: u64,
 }
 
 impl Default for SafetyConfig {
@@ -176,10 +308,27 @@ impl Default for SafetyConfig {
             large_table_threshold: 1_000_000,
             huge_table_threshold: 100_000_000,
             refresh_stats_mysql: false,
+            block_privileged: false,
+            lock_timeout_ms: 0,
+            ddl_retry: 0,
+            ddl_retry_backoff_ms: 1_000,
         }
     }
 }
 
+impl SafetyConfig {
+    /// Delay before lock-timeout retry number `attempt` (starting at 1).
+    pub fn ddl_retry_delay(&self, attempt: u32) -> std::time::Duration {
+        const MAX_BACKOFF_MS: u64 = 60_000;
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        std::time::Duration::from_millis(
+            self.ddl_retry_backoff_ms
+                .saturating_mul(factor)
+                .min(MAX_BACKOFF_MS),
+        )
+    }
+}
+
 // ── Dispatcher ──────────────────────────────────────────────────────────────
 
 /// Analyse a migration's SQL for safety verdicts (dialect-aware entry).
@@ -190,6 +339,9 @@ pub async fn analyze_migration_db(
     script: &str,
     config: &SafetyConfig,
 ) -> Result<SafetyReport> {
+    if crate::migration::is_code_script(script) {
+        return Ok(SafetyReport::code_migration(script));
+    }
     match client.dialect_kind() {
         #[cfg(feature = "postgres")]
         DialectKind::Postgres => {
@@ -283,6 +435,292 @@ pub(crate) fn affected_table(op: &DdlOperation) -> Option<String> {
     }
 }
 
+// ── Column default analysis ─────────────────────────────────────────────────
+
+static ADD_COLUMN_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)^ALTER\s+TABLE\s+(?:ONLY\s+)?(?:IF\s+EXISTS\s+)?((?:"?\w+"?\.)?"?\w+"?)\s+ADD\s+(?:COLUMN\s+)?(?:IF\s+NOT\s+EXISTS\s+)?("?\w+"?)\s+(.+)$"#).unwrap()
+});
+
+static SET_DEFAULT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)^ALTER\s+TABLE\s+(?:ONLY\s+)?(?:IF\s+EXISTS\s+)?((?:"?\w+"?\.)?"?\w+"?)\s+ALTER\s+(?:COLUMN\s+)?("?\w+"?)\s+SET\s+DEFAULT\s+(.+)$"#).unwrap()
+});
+
+static DEFAULT_KEYWORD_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bDEFAULT\s+").unwrap());
+
+/// Column constraint keywords that end a type or default expression.
+static CONSTRAINT_KEYWORD_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s+(?:NOT\s+NULL|NULL|CONSTRAINT|CHECK|REFERENCES|PRIMARY\s+KEY|UNIQUE|GENERATED|COLLATE|DEFAULT)\b").unwrap()
+});
+
+static NOT_NULL_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\bNOT\s+NULL\b").unwrap());
+
+static SERIAL_TYPE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^(?:small|big)?serial\d?\b").unwrap());
+
+/// Text up to the first column-constraint keyword.
+fn until_constraint(s: &str) -> &str {
+    let end = CONSTRAINT_KEYWORD_RE.find(s).map_or(s.len(), |m| m.start());
+    s[..end].trim().trim_end_matches(';').trim()
+}
+
+/// Analyze an `ADD COLUMN ... DEFAULT` or `SET DEFAULT` statement for
+/// PostgreSQL `version` (`None` = unknown, assumed 11+).
+///
+/// Since PostgreSQL 11 a non-volatile `ADD COLUMN` default is stored in the
+/// catalog and existing rows are not touched; volatile defaults (and every
+/// default before 11) rewrite the table under an ACCESS EXCLUSIVE lock.
+/// `SET DEFAULT` never rewrites, but only applies to rows inserted later.
+pub fn analyze_default_change(stmt: &str, version: Option<PgVersion>) -> Option<DefaultChange> {
+    let stmt = crate::wal::strip_leading_comments(stmt)
+        .trim()
+        .trim_end_matches(';')
+        .trim();
+    let preview: String = stmt.chars().take(120).collect();
+
+    if let Some(caps) = SET_DEFAULT_RE.captures(stmt) {
+        return Some(DefaultChange {
+            statement_preview: preview,
+            table: caps[1].to_string(),
+            column: caps[2].to_string(),
+            data_type: None,
+            default_expr: caps[3].trim().to_string(),
+            pattern: DefaultPattern::SetDefault,
+            volatile: crate::wal::VOLATILE_DEFAULT_RE.is_match(&caps[3]),
+            not_null: false,
+            rewrites_table: false,
+            reason: "SET DEFAULT only changes the catalog; existing rows keep their values"
+                .to_string(),
+            estimated_rewrite_ms: None,
+            alternative: None,
+        });
+    }
+
+    let caps = ADD_COLUMN_RE.captures(stmt)?;
+    let rest = &caps[3];
+    let data_type = until_constraint(rest).to_string();
+    let serial = SERIAL_TYPE_RE.is_match(&data_type);
+    let default_expr = DEFAULT_KEYWORD_RE
+        .find(rest)
+        .map(|m| until_constraint(&rest[m.end()..]).to_string());
+    let default_expr = match (default_expr, serial) {
+        (Some(expr), _) => expr,
+        (None, true) => "nextval(...)".to_string(),
+        (None, false) => return None,
+    };
+    let volatile = serial || crate::wal::VOLATILE_DEFAULT_RE.is_match(&default_expr);
+    let fast_default = version.is_none_or(|v| v.has_fast_column_default());
+    let not_null = NOT_NULL_RE.is_match(rest);
+    let rewrites_table = volatile || !fast_default;
+
+    let reason = if volatile {
+        format!(
+            "{} is evaluated per row, so every existing row is written",
+            if serial {
+                "A serial column's nextval()"
+            } else {
+                "The volatile default"
+            }
+        )
+    } else if !fast_default {
+        format!(
+            "PostgreSQL {} rewrites the table for ADD COLUMN ... DEFAULT (fixed in 11)",
+            version.map_or("< 11".to_string(), |v| v.to_string())
+        )
+    } else {
+        "Constant default is stored in the catalog (PostgreSQL 11+); no rewrite".to_string()
+    };
+    let alternative = rewrites_table.then(|| {
+        format!(
+            "Add the column without a default, SET DEFAULT for new rows, backfill existing rows in batches{}; `waypoint assist default` generates these steps",
+            if not_null { ", then add NOT NULL via a validated CHECK" } else { "" }
+        )
+    });
+
+    Some(DefaultChange {
+        statement_preview: preview,
+        table: caps[1].to_string(),
+        column: caps[2].to_string(),
+        data_type: Some(data_type),
+        default_expr,
+        pattern: DefaultPattern::AddColumnDefault,
+        volatile,
+        not_null,
+        rewrites_table,
+        reason,
+        estimated_rewrite_ms: None,
+        alternative,
+    })
+}
+
+// ── Privilege detection ─────────────────────────────────────────────────────
+
+static PG_CREATE_EXTENSION_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)^CREATE\s+EXTENSION\s+(?:IF\s+NOT\s+EXISTS\s+)?"?([\w-]+)"?"#).unwrap()
+});
+
+static PG_ROLE_DDL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)^(CREATE|ALTER|DROP)\s+(ROLE|USER|GROUP)\s+(\w+)").unwrap());
+
+static PG_ROLE_SUPER_ATTR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(SUPERUSER|REPLICATION|BYPASSRLS)\b").unwrap());
+
+static PG_COPY_PROGRAM_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)^COPY\b.*\s(FROM|TO)\s+PROGRAM\b").unwrap());
+
+static PG_COPY_FILE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)^COPY\b.*\s(FROM|TO)\s+'").unwrap());
+
+static PG_SUPERUSER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?is)^(ALTER\s+SYSTEM\b|CREATE\s+(?:OR\s+REPLACE\s+)?(?:TRUSTED\s+)?(?:PROCEDURAL\s+)?LANGUAGE\b|CREATE\s+EVENT\s+TRIGGER\b|CREATE\s+FOREIGN\s+DATA\s+WRAPPER\b|LOAD\s+'|SET\s+(?:SESSION\s+)?session_replication_role\b)",
+    )
+    .unwrap()
+});
+
+static MYSQL_ADMIN_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?is)^((?:CREATE|ALTER|DROP|RENAME)\s+USER|(?:CREATE|DROP)\s+ROLE|GRANT|REVOKE|SET\s+(?:GLOBAL|PERSIST|PERSIST_ONLY|@@GLOBAL)|(?:INSTALL|UNINSTALL)\s+(?:PLUGIN|COMPONENT))\b",
+    )
+    .unwrap()
+});
+
+static MYSQL_LOAD_INFILE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)^LOAD\s+DATA\s+(?:LOW_PRIORITY\s+|CONCURRENT\s+)?INFILE\b").unwrap()
+});
+
+/// Upper-cased, whitespace-collapsed keyword prefix of a regex match, for messages.
+fn keywords(m: &str) -> String {
+    m.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches('\'')
+        .trim()
+        .to_uppercase()
+}
+
+/// Classify one statement, or `None` if it needs no elevated privileges.
+pub fn classify_privileged(stmt: &str, dialect: DialectKind) -> Option<PrivilegedStatement> {
+    let stmt = crate::wal::strip_leading_comments(stmt).trim();
+    let hit = |requirement, reason: String, extension: Option<String>| {
+        Some(PrivilegedStatement {
+            statement_preview: stmt.chars().take(120).collect(),
+            requirement,
+            reason,
+            extension,
+        })
+    };
+
+    match dialect {
+        DialectKind::Postgres => {
+            if let Some(caps) = PG_CREATE_EXTENSION_RE.captures(stmt) {
+                let name = caps[1].to_string();
+                return hit(
+                    PrivilegeRequirement::Extension,
+                    format!(
+                        "CREATE EXTENSION {} requires superuser unless the extension is trusted",
+                        name
+                    ),
+                    Some(name),
+                );
+            }
+            if let Some(caps) = PG_ROLE_DDL_RE.captures(stmt) {
+                // CREATE USER MAPPING is foreign-server DDL, not role DDL.
+                if caps[3].eq_ignore_ascii_case("MAPPING") {
+                    return None;
+                }
+                let what = format!("{} {}", &caps[1], &caps[2]).to_uppercase();
+                if caps[1].eq_ignore_ascii_case("DROP") || !PG_ROLE_SUPER_ATTR_RE.is_match(stmt) {
+                    return hit(
+                        PrivilegeRequirement::CreateRole,
+                        format!("{} requires the CREATEROLE attribute", what),
+                        None,
+                    );
+                }
+                return hit(
+                    PrivilegeRequirement::Superuser,
+                    format!(
+                        "{} granting SUPERUSER, REPLICATION or BYPASSRLS requires superuser",
+                        what
+                    ),
+                    None,
+                );
+            }
+            if let Some(caps) = PG_COPY_PROGRAM_RE.captures(stmt) {
+                return hit(
+                    PrivilegeRequirement::ServerProgram,
+                    format!(
+                        "COPY {} PROGRAM requires superuser or pg_execute_server_program",
+                        caps[1].to_uppercase()
+                    ),
+                    None,
+                );
+            }
+            if let Some(caps) = PG_COPY_FILE_RE.captures(stmt) {
+                return if caps[1].eq_ignore_ascii_case("FROM") {
+                    hit(
+                        PrivilegeRequirement::ReadServerFiles,
+                        "COPY FROM a server file requires superuser or pg_read_server_files"
+                            .to_string(),
+                        None,
+                    )
+                } else {
+                    hit(
+                        PrivilegeRequirement::WriteServerFiles,
+                        "COPY TO a server file requires superuser or pg_write_server_files"
+                            .to_string(),
+                        None,
+                    )
+                };
+            }
+            if let Some(caps) = PG_SUPERUSER_RE.captures(stmt) {
+                return hit(
+                    PrivilegeRequirement::Superuser,
+                    format!("{} requires superuser", keywords(&caps[1])),
+                    None,
+                );
+            }
+            None
+        }
+        DialectKind::Mysql => {
+            if let Some(caps) = MYSQL_ADMIN_RE.captures(stmt) {
+                return hit(
+                    PrivilegeRequirement::Admin,
+                    format!(
+                        "{} requires an administrative privilege (CREATE USER, GRANT OPTION, SYSTEM_VARIABLES_ADMIN or SUPER)",
+                        keywords(&caps[1])
+                    ),
+                    None,
+                );
+            }
+            if MYSQL_LOAD_INFILE_RE.is_match(stmt) {
+                return hit(
+                    PrivilegeRequirement::Admin,
+                    "LOAD DATA INFILE reads a server file and requires the FILE privilege"
+                        .to_string(),
+                    None,
+                );
+            }
+            None
+        }
+    }
+}
+
+/// Statically find the statements in `sql` that need elevated privileges.
+pub fn privileged_statements(sql: &str, dialect: DialectKind) -> Vec<PrivilegedStatement> {
+    let statements: Vec<String> = match dialect {
+        DialectKind::Postgres => crate::sql_parser::split_statements(sql)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        DialectKind::Mysql => crate::sql_parser::split_mysql_statements(sql),
+    };
+    statements
+        .iter()
+        .filter_map(|s| classify_privileged(s, dialect))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,6 +739,19 @@ mod tests {
         assert!(LockLevel::ExclusiveLock < LockLevel::AccessExclusiveLock);
     }
 
+    #[test]
+    fn test_ddl_retry_delay_doubles_up_to_a_minute() {
+        let config = SafetyConfig {
+            ddl_retry_backoff_ms: 500,
+            ..Default::default()
+        };
+        let delays: Vec<u128> = (1..=4)
+            .map(|a| config.ddl_retry_delay(a).as_millis())
+            .collect();
+        assert_eq!(delays, vec![500, 1_000, 2_000, 4_000]);
+        assert_eq!(config.ddl_retry_delay(40).as_millis(), 60_000);
+    }
+
     // ── Verdict computation ───────────────────────────────────────────
 
     #[test]
@@ -598,7 +1049,141 @@ mod tests {
         let config = SafetyConfig::default();
         assert!(config.enabled);
         assert!(!config.block_on_danger);
+        assert!(!config.block_privileged);
         assert_eq!(config.large_table_threshold, 1_000_000);
         assert_eq!(config.huge_table_threshold, 100_000_000);
     }
+
+    // ── Privilege detection ───────────────────────────────────────────
+
+    fn requirement(sql: &str, dialect: DialectKind) -> Option<PrivilegeRequirement> {
+        classify_privileged(sql, dialect).map(|p| p.requirement)
+    }
+
+    #[test]
+    fn test_privileged_postgres() {
+        use PrivilegeRequirement::*;
+        let pg = |sql| requirement(sql, DialectKind::Postgres);
+
+        let ext = classify_privileged(
+            "-- needs postgis\nCREATE EXTENSION IF NOT EXISTS \"postgis\"",
+            DialectKind::Postgres,
+        )
+        .unwrap();
+        assert_eq!(ext.requirement, Extension);
+        assert_eq!(ext.extension.as_deref(), Some("postgis"));
+
+        assert_eq!(pg("ALTER SYSTEM SET work_mem = '64MB'"), Some(Superuser));
+        assert_eq!(pg("create role app_reader nologin"), Some(CreateRole));
+        assert_eq!(pg("DROP USER old_app"), Some(CreateRole));
+        assert_eq!(pg("ALTER ROLE etl WITH REPLICATION"), Some(Superuser));
+        assert_eq!(pg("COPY t FROM PROGRAM 'curl x'"), Some(ServerProgram));
+        assert_eq!(pg("COPY t FROM '/tmp/t.csv' CSV"), Some(ReadServerFiles));
+        assert_eq!(pg("COPY (SELECT 1) TO '/tmp/out'"), Some(WriteServerFiles));
+        assert_eq!(pg("LOAD 'auto_explain'"), Some(Superuser));
+        assert_eq!(
+            pg("CREATE EVENT TRIGGER t ON ddl_command_end EXECUTE FUNCTION f()"),
+            Some(Superuser)
+        );
+        assert_eq!(
+            pg("SET session_replication_role = replica"),
+            Some(Superuser)
+        );
+
+        assert_eq!(pg("COPY t FROM STDIN"), None);
+        assert_eq!(pg("CREATE USER MAPPING FOR app SERVER remote"), None);
+        assert_eq!(pg("CREATE TABLE roles (id int)"), None);
+        assert_eq!(pg("GRANT SELECT ON t TO app"), None);
+    }
+
+    #[test]
+    fn test_privileged_mysql() {
+        let my = |sql| requirement(sql, DialectKind::Mysql);
+        assert_eq!(
+            my("CREATE USER 'app'@'%'"),
+            Some(PrivilegeRequirement::Admin)
+        );
+        assert_eq!(
+            my("GRANT SELECT ON db.* TO 'app'@'%'"),
+            Some(PrivilegeRequirement::Admin)
+        );
+        assert_eq!(
+            my("SET GLOBAL max_connections = 500"),
+            Some(PrivilegeRequirement::Admin)
+        );
+        assert_eq!(
+            my("LOAD DATA INFILE '/tmp/x' INTO TABLE t"),
+            Some(PrivilegeRequirement::Admin)
+        );
+        assert_eq!(my("LOAD DATA LOCAL INFILE 'x' INTO TABLE t"), None);
+        assert_eq!(my("SET NAMES utf8mb4"), None);
+        assert_eq!(my("CREATE TABLE t (id INT)"), None);
+    }
+
+    #[test]
+    fn test_privileged_statements_splits_script() {
+        let sql = "CREATE TABLE t (id int);\nCREATE EXTENSION pgcrypto;\nALTER SYSTEM SET x = 1;";
+        let found = privileged_statements(sql, DialectKind::Postgres);
+        assert_eq!(found.len(), 2);
+        assert!(found[0].reason.contains("pgcrypto"));
+        assert_eq!(found[1].reason, "ALTER SYSTEM requires superuser");
+    }
+
+    // ── Column default analysis ───────────────────────────────────────
+
+    #[test]
+    fn test_default_constant_on_pg11_is_metadata_only() {
+        let v11 = Some(PgVersion::from_version_num(110000));
+        let dc = analyze_default_change(
+            "ALTER TABLE orders ADD COLUMN status text NOT NULL DEFAULT 'new';",
+            v11,
+        )
+        .unwrap();
+        assert_eq!(dc.pattern, DefaultPattern::AddColumnDefault);
+        assert_eq!(dc.table, "orders");
+        assert_eq!(dc.column, "status");
+        assert_eq!(dc.data_type.as_deref(), Some("text"));
+        assert_eq!(dc.default_expr, "'new'");
+        assert!(dc.not_null);
+        assert!(!dc.volatile);
+        assert!(!dc.rewrites_table);
+        assert!(dc.alternative.is_none());
+    }
+
+    #[test]
+    fn test_default_rewrites() {
+        let v10 = Some(PgVersion::from_version_num(100023));
+        let v16 = Some(PgVersion::from_version_num(160002));
+
+        let old = analyze_default_change("ALTER TABLE t ADD COLUMN c int DEFAULT 0", v10).unwrap();
+        assert!(old.rewrites_table);
+        assert!(old.reason.contains("fixed in 11"));
+
+        let volatile = analyze_default_change(
+            "ALTER TABLE t ADD COLUMN token uuid DEFAULT gen_random_uuid() NOT NULL",
+            v16,
+        )
+        .unwrap();
+        assert!(volatile.volatile && volatile.rewrites_table && volatile.not_null);
+        assert_eq!(volatile.default_expr, "gen_random_uuid()");
+        assert!(volatile
+            .alternative
+            .unwrap()
+            .contains("waypoint assist default"));
+
+        let serial = analyze_default_change("ALTER TABLE t ADD COLUMN n bigserial", v16).unwrap();
+        assert!(serial.rewrites_table);
+    }
+
+    #[test]
+    fn test_default_set_default_and_non_matches() {
+        let dc =
+            analyze_default_change("ALTER TABLE t ALTER COLUMN c SET DEFAULT now()", None).unwrap();
+        assert_eq!(dc.pattern, DefaultPattern::SetDefault);
+        assert!(!dc.rewrites_table);
+        assert!(dc.reason.contains("existing rows"));
+
+        assert!(analyze_default_change("ALTER TABLE t ADD COLUMN c int", None).is_none());
+        assert!(analyze_default_change("ALTER TABLE t DROP COLUMN c", None).is_none());
+    }
 }
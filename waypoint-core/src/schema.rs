@@ -8,26 +8,32 @@
 //! [`generate_ddl`] for PostgreSQL and [`generate_ddl_mysql`] for MySQL
 //! (the latter omits CASCADE and filters dependent constraint/index diffs
 //! when their parent table is being dropped, since MySQL has no CASCADE).
+//!
+//! Outside a [`Waypoint`](crate::Waypoint), [`Introspector`] snapshots any
+//! schema over a bare connection and [`generate_sql`] picks the DDL flavour
+//! for a dialect. Snapshots and diffs round-trip through serde, so they can
+//! be stored as JSON and compared later.
 
 use std::collections::{HashMap, HashSet};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "postgres")]
 use tokio_postgres::Client;
 
 use crate::db::{quote_ident, DbClient};
-use crate::dialect::DialectKind;
+use crate::dialect::{DialectKind, PgVersion};
 use crate::error::Result;
 #[cfg(any(not(feature = "postgres"), not(feature = "mysql")))]
 use crate::error::WaypointError;
+use crate::introspection;
 
 /// Complete snapshot of a database schema.
 ///
 /// Populated by [`introspect`] on PostgreSQL and [`introspect_mysql`] on
 /// MySQL. Concepts that don't apply to MySQL (sequences, PG-style enums,
-/// extensions) come back as empty vectors when produced by `introspect_mysql`.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+/// extensions, distributed tables) come back as empty vectors when produced by `introspect_mysql`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SchemaSnapshot {
     /// All base tables in the schema.
     pub tables: Vec<TableDef>,
@@ -47,10 +53,70 @@ pub struct SchemaSnapshot {
     pub triggers: Vec<TriggerDef>,
     /// Names of installed extensions (excluding plpgsql).
     pub extensions: Vec<String>,
+    /// TimescaleDB hypertables and Citus distributed/reference tables.
+    #[serde(default)]
+    pub distributed_tables: Vec<DistributedTableDef>,
+    /// Categories (by field name, e.g. `"indexes"`) whose catalog query hit
+    /// the introspection timeout; they are empty here and skipped by [`diff`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub incomplete: Vec<String>,
+}
+
+impl SchemaSnapshot {
+    /// Number of objects across all categories, columns included.
+    pub fn object_count(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|t| 1 + t.columns.len())
+            .sum::<usize>()
+            + self.views.len()
+            + self.indexes.len()
+            + self.sequences.len()
+            + self.functions.len()
+            + self.enums.len()
+            + self.constraints.len()
+            + self.triggers.len()
+            + self.extensions.len()
+            + self.distributed_tables.len()
+    }
+}
+
+/// How an extension spreads a table's storage.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionKind {
+    /// TimescaleDB hypertable, chunked by a time dimension.
+    Hypertable,
+    /// Citus table sharded across workers by a distribution column.
+    Distributed,
+    /// Citus reference table, replicated to every node.
+    Reference,
+}
+
+impl std::fmt::Display for DistributionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DistributionKind::Hypertable => write!(f, "hypertable"),
+            DistributionKind::Distributed => write!(f, "distributed table"),
+            DistributionKind::Reference => write!(f, "reference table"),
+        }
+    }
+}
+
+/// A table managed by TimescaleDB or Citus.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DistributedTableDef {
+    /// Name of the table.
+    pub name: String,
+    /// Which extension manages it, and how.
+    pub kind: DistributionKind,
+    /// Time dimension column (hypertables) or distribution column (Citus).
+    /// `None` for reference tables.
+    pub column: Option<String>,
 }
 
 /// Definition of a database table.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TableDef {
     /// Schema the table belongs to.
     pub schema: String,
@@ -61,7 +127,7 @@ pub struct TableDef {
 }
 
 /// Definition of a table column.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct ColumnDef {
     /// Name of the column.
     pub name: String,
@@ -76,7 +142,7 @@ pub struct ColumnDef {
 }
 
 /// Definition of a database view.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ViewDef {
     /// Schema the view belongs to.
     pub schema: String,
@@ -89,7 +155,7 @@ pub struct ViewDef {
 }
 
 /// Definition of a database index.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct IndexDef {
     /// Schema the index belongs to.
     pub schema: String,
@@ -104,7 +170,7 @@ pub struct IndexDef {
 }
 
 /// Definition of a database sequence.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SequenceDef {
     /// Schema the sequence belongs to.
     pub schema: String,
@@ -115,7 +181,7 @@ pub struct SequenceDef {
 }
 
 /// Definition of a database function or procedure.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FunctionDef {
     /// Schema the function belongs to.
     pub schema: String,
@@ -132,7 +198,7 @@ pub struct FunctionDef {
 }
 
 /// Definition of a PostgreSQL enum type.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EnumDef {
     /// Schema the enum belongs to.
     pub schema: String,
@@ -143,7 +209,7 @@ pub struct EnumDef {
 }
 
 /// Definition of a table constraint.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConstraintDef {
     /// Schema the constraint belongs to.
     pub schema: String,
@@ -158,7 +224,7 @@ pub struct ConstraintDef {
 }
 
 /// Definition of a database trigger.
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TriggerDef {
     /// Schema the trigger belongs to.
     pub schema: String,
@@ -171,12 +237,14 @@ pub struct TriggerDef {
 }
 
 /// Differences between two schema snapshots.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SchemaDiff {
     /// A table was added in the target schema.
     TableAdded(TableDef),
     /// A table was dropped from the target schema.
     TableDropped(String),
+    /// A table was renamed. Never produced by [`diff`]; see [`detect_renames`].
+    TableRenamed { from: String, to: String },
     /// A column was added to an existing table.
     ColumnAdded { table: String, column: ColumnDef },
     /// A column was dropped from an existing table.
@@ -239,6 +307,7 @@ impl std::fmt::Display for SchemaDiff {
         match self {
             SchemaDiff::TableAdded(t) => write!(f, "+ TABLE {}", t.name),
             SchemaDiff::TableDropped(n) => write!(f, "- TABLE {}", n),
+            SchemaDiff::TableRenamed { from, to } => write!(f, "~ TABLE {} -> {}", from, to),
             SchemaDiff::ColumnAdded { table, column } => {
                 write!(
                     f,
@@ -300,23 +369,142 @@ pub async fn introspect_db(client: &DbClient, schema: &str) -> Result<SchemaSnap
     }
 }
 
+/// Standalone entry point for introspecting schemas over a connection.
+///
+/// ```no_run
+/// # async fn run() -> waypoint_core::error::Result<()> {
+/// use waypoint_core::db::{self, DbClient};
+/// use waypoint_core::schema::{self, Introspector};
+///
+/// let client = DbClient::with_postgres(db::connect("postgres://localhost/app").await?);
+/// let introspector = Introspector::new(&client);
+/// let before = introspector.snapshot("public").await?;
+/// let after = introspector.snapshot("staging").await?;
+/// let diffs = schema::diff(&before, &after);
+/// println!("{}", introspector.generate_sql(&diffs).await);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Introspector<'a> {
+    client: &'a DbClient,
+}
+
+impl<'a> Introspector<'a> {
+    /// Introspect through `client`.
+    pub fn new(client: &'a DbClient) -> Self {
+        Self { client }
+    }
+
+    /// Snapshot `schema`. On MySQL, `"public"` means the connection's
+    /// current database.
+    pub async fn snapshot(&self, schema: &str) -> Result<SchemaSnapshot> {
+        let schema = self.client.resolve_schema(schema).await?;
+        introspect_db(self.client, &schema).await
+    }
+
+    /// DDL turning the "before" side of `diffs` into the "after" side, for
+    /// this connection's dialect and server version.
+    pub async fn generate_sql(&self, diffs: &[SchemaDiff]) -> String {
+        match self.client.dialect_kind() {
+            DialectKind::Postgres => {
+                generate_ddl_for_version(diffs, self.client.pg_version().await)
+            }
+            DialectKind::Mysql => generate_ddl_mysql(diffs),
+        }
+    }
+}
+
+/// Generate DDL from schema diffs for `dialect`: [`generate_ddl`] for
+/// PostgreSQL, [`generate_ddl_mysql`] for MySQL.
+pub fn generate_sql(diffs: &[SchemaDiff], dialect: DialectKind) -> String {
+    match dialect {
+        DialectKind::Postgres => generate_ddl(diffs),
+        DialectKind::Mysql => generate_ddl_mysql(diffs),
+    }
+}
+
 /// Introspect the current state of a PostgreSQL schema.
 #[cfg(feature = "postgres")]
 pub async fn introspect(client: &Client, schema: &str) -> Result<SchemaSnapshot> {
-    let (tables, views, indexes, sequences, functions, enums, constraints, triggers, extensions) =
-        tokio::try_join!(
-            introspect_tables(client, schema),
-            introspect_views(client, schema),
-            introspect_indexes(client, schema),
-            introspect_sequences(client, schema),
-            introspect_functions(client, schema),
-            introspect_enums(client, schema),
-            introspect_constraints(client, schema),
-            introspect_triggers(client, schema),
-            introspect_extensions(client),
-        )?;
-
-    Ok(SchemaSnapshot {
+    let started = std::time::Instant::now();
+    let crdb = crate::db::is_cockroach(client).await;
+    let incomplete = std::sync::Mutex::new(Vec::new());
+    // Boxed: the joined catalog queries make a large future, and introspection
+    // runs deep inside migrate (auto-reversals).
+    let (
+        (tables, views, indexes, sequences, functions, enums, constraints, triggers, extensions),
+        distributed_tables,
+        members,
+    ) = introspection::limited(
+        client,
+        Box::pin(async {
+            let lists = tokio::try_join!(
+                introspection::tolerate(
+                    &incomplete,
+                    "tables",
+                    introspect_tables(client, schema, crdb)
+                ),
+                introspection::tolerate(&incomplete, "views", introspect_views(client, schema)),
+                introspection::tolerate(&incomplete, "indexes", introspect_indexes(client, schema)),
+                introspection::tolerate(
+                    &incomplete,
+                    "sequences",
+                    introspect_sequences(client, schema)
+                ),
+                crdb_tolerant(
+                    crdb,
+                    "functions",
+                    introspection::tolerate(
+                        &incomplete,
+                        "functions",
+                        introspect_functions(client, schema)
+                    ),
+                ),
+                introspection::tolerate(&incomplete, "enums", introspect_enums(client, schema)),
+                introspection::tolerate(
+                    &incomplete,
+                    "constraints",
+                    introspect_constraints(client, schema)
+                ),
+                crdb_tolerant(
+                    crdb,
+                    "triggers",
+                    introspection::tolerate(
+                        &incomplete,
+                        "triggers",
+                        introspect_triggers(client, schema)
+                    ),
+                ),
+                crdb_tolerant(
+                    crdb,
+                    "extensions",
+                    introspection::tolerate(
+                        &incomplete,
+                        "extensions",
+                        introspect_extensions(client)
+                    ),
+                ),
+            )?;
+            let distributed_tables = introspection::tolerate(
+                &incomplete,
+                "distributed_tables",
+                introspect_distributed_tables(client, schema),
+            )
+            .await?;
+            let members = if introspection::include_extension_objects() {
+                Vec::new()
+            } else {
+                crdb_tolerant(crdb, "extension members", async {
+                    introspect_extension_members(client, schema).await
+                })
+                .await?
+            };
+            Ok((lists, distributed_tables, members))
+        }),
+    )
+    .await?;
+
+    let mut snapshot = SchemaSnapshot {
         tables,
         views,
         indexes,
@@ -326,22 +514,286 @@ pub async fn introspect(client: &Client, schema: &str) -> Result<SchemaSnapshot>
         constraints,
         triggers,
         extensions,
-    })
+        distributed_tables,
+        incomplete: introspection::into_sorted(incomplete),
+    };
+    filter_extension_managed(&mut snapshot);
+    filter_extension_members(&mut snapshot, &members);
+    filter_objects(&mut snapshot, &introspection::object_filter());
+    log_introspected(schema, &snapshot, started);
+    Ok(snapshot)
 }
 
+fn log_introspected(schema: &str, snapshot: &SchemaSnapshot, started: std::time::Instant) {
+    log::debug!(
+        "Introspected schema; schema={}, tables={}, objects={}, elapsed_ms={}",
+        schema,
+        snapshot.tables.len(),
+        snapshot.object_count(),
+        started.elapsed().as_millis()
+    );
+}
+
+/// Find TimescaleDB hypertables and Citus distributed/reference tables.
+///
+/// Returns an empty list when neither extension is installed. Catalog lookup
+/// failures (e.g. TimescaleDB 1.x, which lacks `timescaledb_information.dimensions`)
+/// are logged and treated as "none".
 #[cfg(feature = "postgres")]
-async fn introspect_tables(client: &Client, schema: &str) -> Result<Vec<TableDef>> {
+pub async fn introspect_distributed_tables(
+    client: &Client,
+    schema: &str,
+) -> Result<Vec<DistributedTableDef>> {
     let rows = client
         .query(
-            "SELECT t.table_name, c.column_name, c.data_type, c.is_nullable, c.column_default, c.ordinal_position
-             FROM information_schema.tables t
-             LEFT JOIN information_schema.columns c
-               ON t.table_schema = c.table_schema AND t.table_name = c.table_name
-             WHERE t.table_schema = $1 AND t.table_type = 'BASE TABLE'
-             ORDER BY t.table_name, c.ordinal_position",
+            "SELECT extname::text FROM pg_extension WHERE extname IN ('timescaledb', 'citus')",
+            &[],
+        )
+        .await?;
+    let installed: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+    let mut tables = Vec::new();
+
+    if installed.iter().any(|e| e == "timescaledb") {
+        match client
+            .query(
+                "SELECT hypertable_name::text, column_name::text
+                 FROM timescaledb_information.dimensions
+                 WHERE hypertable_schema = $1 AND dimension_number = 1
+                 ORDER BY hypertable_name",
+                &[&schema],
+            )
+            .await
+        {
+            Ok(rows) => tables.extend(rows.iter().map(|r| DistributedTableDef {
+                name: r.get(0),
+                kind: DistributionKind::Hypertable,
+                column: r.get(1),
+            })),
+            Err(e) => log::debug!("Could not list hypertables; error={}", e),
+        }
+    }
+
+    if installed.iter().any(|e| e == "citus") {
+        // partmethod 'n' with repmodel 's' is a Citus local table — not distributed.
+        match client
+            .query(
+                "SELECT c.relname::text, p.partmethod::text,
+                        CASE WHEN p.partkey IS NULL THEN NULL
+                             ELSE column_to_column_name(p.logicalrelid, p.partkey)::text END
+                 FROM pg_dist_partition p
+                 JOIN pg_class c ON c.oid = p.logicalrelid
+                 JOIN pg_namespace n ON n.oid = c.relnamespace
+                 WHERE n.nspname = $1 AND NOT (p.partmethod = 'n' AND p.repmodel = 's')
+                 ORDER BY c.relname",
+                &[&schema],
+            )
+            .await
+        {
+            Ok(rows) => tables.extend(rows.iter().map(|r| {
+                let method: String = r.get(1);
+                DistributedTableDef {
+                    name: r.get(0),
+                    kind: if method == "n" {
+                        DistributionKind::Reference
+                    } else {
+                        DistributionKind::Distributed
+                    },
+                    column: r.get(2),
+                }
+            })),
+            Err(e) => log::debug!("Could not list Citus tables; error={}", e),
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Drop objects that TimescaleDB or Citus create on their own, so they don't
+/// show up as drift or as diffs against a schema built from migrations.
+///
+/// - Citus shard placements that live in the same schema (`<table>_<shardid>`,
+///   common on single-node clusters) and their indexes, constraints, triggers.
+/// - Citus `truncate_trigger*` triggers on distributed tables.
+/// - TimescaleDB `ts_insert_blocker` / `ts_cagg_invalidation_trigger` triggers.
+pub fn filter_extension_managed(snapshot: &mut SchemaSnapshot) {
+    if snapshot.distributed_tables.is_empty() {
+        return;
+    }
+    let citus_tables: Vec<&str> = snapshot
+        .distributed_tables
+        .iter()
+        .filter(|d| d.kind != DistributionKind::Hypertable)
+        .map(|d| d.name.as_str())
+        .collect();
+    let is_shard = |table: &str| {
+        citus_tables.iter().any(|base| {
+            table
+                .strip_prefix(base)
+                .and_then(|rest| rest.strip_prefix('_'))
+                .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+        })
+    };
+
+    snapshot.tables.retain(|t| !is_shard(&t.name));
+    snapshot.indexes.retain(|i| !is_shard(&i.table_name));
+    snapshot.constraints.retain(|c| !is_shard(&c.table_name));
+    snapshot.triggers.retain(|t| {
+        !is_shard(&t.table_name)
+            && !t.name.starts_with("truncate_trigger")
+            && t.name != "ts_insert_blocker"
+            && t.name != "ts_cagg_invalidation_trigger"
+    });
+}
+
+/// An object in the schema that belongs to an extension: `pg_depend` records
+/// it with `deptype = 'e'` because `CREATE EXTENSION` created it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionMember {
+    /// Catalog the object lives in.
+    pub kind: ExtensionMemberKind,
+    /// Object name.
+    pub name: String,
+}
+
+/// Catalog of an [`ExtensionMember`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionMemberKind {
+    /// Table, view, index or sequence (`pg_class`).
+    Relation,
+    /// Function or procedure (`pg_proc`).
+    Function,
+    /// Type (`pg_type`).
+    Type,
+}
+
+/// List the objects in `schema` that belong to an extension.
+#[cfg(feature = "postgres")]
+async fn introspect_extension_members(
+    client: &Client,
+    schema: &str,
+) -> Result<Vec<ExtensionMember>> {
+    let rows = client
+        .query(
+            "SELECT 'r', c.relname::text FROM pg_depend d
+             JOIN pg_class c ON d.classid = 'pg_class'::regclass AND d.objid = c.oid
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE d.deptype = 'e' AND n.nspname = $1
+             UNION ALL
+             SELECT 'f', p.proname::text FROM pg_depend d
+             JOIN pg_proc p ON d.classid = 'pg_proc'::regclass AND d.objid = p.oid
+             JOIN pg_namespace n ON n.oid = p.pronamespace
+             WHERE d.deptype = 'e' AND n.nspname = $1
+             UNION ALL
+             SELECT 't', t.typname::text FROM pg_depend d
+             JOIN pg_type t ON d.classid = 'pg_type'::regclass AND d.objid = t.oid
+             JOIN pg_namespace n ON n.oid = t.typnamespace
+             WHERE d.deptype = 'e' AND n.nspname = $1",
             &[&schema],
         )
         .await?;
+    Ok(rows
+        .iter()
+        .map(|r| {
+            let kind: &str = r.get(0);
+            ExtensionMember {
+                kind: match kind {
+                    "f" => ExtensionMemberKind::Function,
+                    "t" => ExtensionMemberKind::Type,
+                    _ => ExtensionMemberKind::Relation,
+                },
+                name: r.get(1),
+            }
+        })
+        .collect())
+}
+
+/// Drop extension members from a snapshot, along with the indexes,
+/// constraints and triggers on member tables. They are managed by the
+/// extension, so they would otherwise show up as drift against a schema
+/// built from migrations.
+pub fn filter_extension_members(snapshot: &mut SchemaSnapshot, members: &[ExtensionMember]) {
+    if members.is_empty() {
+        return;
+    }
+    let is = |kind: ExtensionMemberKind, name: &str| {
+        members.iter().any(|m| m.kind == kind && m.name == name)
+    };
+    let relation = |name: &str| is(ExtensionMemberKind::Relation, name);
+
+    snapshot.tables.retain(|t| !relation(&t.name));
+    snapshot.views.retain(|v| !relation(&v.name));
+    snapshot
+        .indexes
+        .retain(|i| !relation(&i.name) && !relation(&i.table_name));
+    snapshot.sequences.retain(|s| !relation(&s.name));
+    snapshot
+        .functions
+        .retain(|f| !is(ExtensionMemberKind::Function, &f.name));
+    snapshot
+        .enums
+        .retain(|e| !is(ExtensionMemberKind::Type, &e.name));
+    snapshot.constraints.retain(|c| !relation(&c.table_name));
+    snapshot.triggers.retain(|t| !relation(&t.table_name));
+}
+
+/// Drop objects whose names `filter` rejects (`[database] include_objects`
+/// / `exclude_objects`). Indexes, constraints and triggers go with their
+/// table, whatever their own name.
+pub fn filter_objects(snapshot: &mut SchemaSnapshot, filter: &introspection::ObjectFilter) {
+    if filter.is_empty() {
+        return;
+    }
+    snapshot.tables.retain(|t| filter.allows(&t.name));
+    snapshot.views.retain(|v| filter.allows(&v.name));
+    snapshot.indexes.retain(|i| filter.allows(&i.table_name));
+    snapshot.sequences.retain(|s| filter.allows(&s.name));
+    snapshot.functions.retain(|f| filter.allows(&f.name));
+    snapshot.enums.retain(|e| filter.allows(&e.name));
+    snapshot
+        .constraints
+        .retain(|c| filter.allows(&c.table_name));
+    snapshot.triggers.retain(|t| filter.allows(&t.table_name));
+    snapshot
+        .distributed_tables
+        .retain(|d| filter.allows(&d.name));
+}
+
+/// CockroachDB's catalogs for some object kinds are partial or missing
+/// depending on version; treat a failed lookup there as "none" rather than
+/// failing the whole snapshot.
+#[cfg(feature = "postgres")]
+async fn crdb_tolerant<T>(
+    crdb: bool,
+    what: &str,
+    fut: impl std::future::Future<Output = Result<Vec<T>>>,
+) -> Result<Vec<T>> {
+    match fut.await {
+        Err(e) if crdb => {
+            log::debug!(
+                "Skipping introspection on CockroachDB; objects={}, error={}",
+                what,
+                e
+            );
+            Ok(Vec::new())
+        }
+        other => other,
+    }
+}
+
+#[cfg(feature = "postgres")]
+async fn introspect_tables(client: &Client, schema: &str, crdb: bool) -> Result<Vec<TableDef>> {
+    // CockroachDB adds a hidden `rowid` column to tables without a primary key.
+    let hidden_filter = if crdb { " AND c.is_hidden = 'NO'" } else { "" };
+    let sql = format!(
+        "SELECT t.table_name, c.column_name, c.data_type, c.is_nullable, c.column_default, c.ordinal_position
+         FROM information_schema.tables t
+         LEFT JOIN information_schema.columns c
+           ON t.table_schema = c.table_schema AND t.table_name = c.table_name{}
+         WHERE t.table_schema = $1 AND t.table_type = 'BASE TABLE'
+         ORDER BY t.table_name, c.ordinal_position",
+        hidden_filter
+    );
+    let rows = client.query(&sql, &[&schema]).await?;
 
     let mut tables: Vec<TableDef> = Vec::new();
     let mut current_table: Option<String> = None;
@@ -600,228 +1052,238 @@ async fn introspect_extensions(client: &Client) -> Result<Vec<String>> {
     Ok(rows.iter().map(|r| r.get(0)).collect())
 }
 
-/// Compare two schema snapshots and return the differences.
+/// Categories either snapshot lists as `incomplete`, sorted.
+pub fn incomplete_categories(a: &SchemaSnapshot, b: &SchemaSnapshot) -> Vec<String> {
+    let mut categories: Vec<String> = a.incomplete.iter().chain(&b.incomplete).cloned().collect();
+    categories.sort();
+    categories.dedup();
+    categories
+}
+
+/// Compare two schema snapshots and return the differences. Categories
+/// either snapshot lists as `incomplete` are skipped.
 pub fn diff(before: &SchemaSnapshot, after: &SchemaSnapshot) -> Vec<SchemaDiff> {
     let mut diffs = Vec::new();
+    diff_each(before, after, |d| diffs.push(d));
+    diffs
+}
 
-    // Build lookup maps for O(1) access
-
-    // Tables - keyed by name, value is reference to TableDef
-    let before_tables: HashMap<&str, &TableDef> =
-        before.tables.iter().map(|t| (t.name.as_str(), t)).collect();
-    let after_tables: HashMap<&str, &TableDef> =
-        after.tables.iter().map(|t| (t.name.as_str(), t)).collect();
-
-    // Views - keyed by name, value is reference to ViewDef
-    let before_views: HashMap<&str, &ViewDef> =
-        before.views.iter().map(|v| (v.name.as_str(), v)).collect();
-    let after_views: HashMap<&str, &ViewDef> =
-        after.views.iter().map(|v| (v.name.as_str(), v)).collect();
-
-    // Indexes - existence check only, keyed by name
-    let before_indexes: HashSet<&str> = before.indexes.iter().map(|i| i.name.as_str()).collect();
-    let after_indexes: HashSet<&str> = after.indexes.iter().map(|i| i.name.as_str()).collect();
-
-    // Sequences - existence check only, keyed by name
-    let before_sequences: HashSet<&str> =
-        before.sequences.iter().map(|s| s.name.as_str()).collect();
-    let after_sequences: HashSet<&str> = after.sequences.iter().map(|s| s.name.as_str()).collect();
-
-    // Functions - keyed by name, value is reference to FunctionDef
-    let before_functions: HashMap<&str, &FunctionDef> = before
-        .functions
-        .iter()
-        .map(|f| (f.name.as_str(), f))
-        .collect();
-    let after_functions: HashMap<&str, &FunctionDef> = after
-        .functions
-        .iter()
-        .map(|f| (f.name.as_str(), f))
-        .collect();
-
-    // Enums - existence check only, keyed by name
-    let before_enums: HashSet<&str> = before.enums.iter().map(|e| e.name.as_str()).collect();
-    let after_enums: HashSet<&str> = after.enums.iter().map(|e| e.name.as_str()).collect();
-
-    // Constraints - compound key (table_name, name)
-    let before_constraints: HashSet<(&str, &str)> = before
-        .constraints
-        .iter()
-        .map(|c| (c.table_name.as_str(), c.name.as_str()))
-        .collect();
-    let after_constraints: HashSet<(&str, &str)> = after
-        .constraints
-        .iter()
-        .map(|c| (c.table_name.as_str(), c.name.as_str()))
-        .collect();
-
-    // Triggers - compound key (table_name, name)
-    let before_triggers: HashSet<(&str, &str)> = before
-        .triggers
-        .iter()
-        .map(|t| (t.table_name.as_str(), t.name.as_str()))
-        .collect();
-    let after_triggers: HashSet<(&str, &str)> = after
-        .triggers
-        .iter()
-        .map(|t| (t.table_name.as_str(), t.name.as_str()))
-        .collect();
-
-    // Extensions - existence check only
-    let before_extensions: HashSet<&str> = before.extensions.iter().map(|e| e.as_str()).collect();
-    let after_extensions: HashSet<&str> = after.extensions.iter().map(|e| e.as_str()).collect();
-
-    // Tables: check dropped/altered then added
-    for bt in &before.tables {
-        if let Some(at) = after_tables.get(bt.name.as_str()) {
-            diff_columns(&mut diffs, &bt.name, &bt.columns, &at.columns);
-        } else {
-            diffs.push(SchemaDiff::TableDropped(bt.name.clone()));
+/// Like [`diff`], but hand each difference to `emit` as it is found instead
+/// of collecting them, for callers that count or print the diffs of very
+/// large schemas.
+///
+/// Each category is matched through a hash index keyed by object name (by
+/// table and name for constraints and triggers), so the comparison is linear
+/// in the size of the snapshots, and only objects that differ are cloned.
+/// Keys leave out the schema: snapshots of two different schemas — drift's
+/// scratch schema against the live one, or `public` against `staging` —
+/// compare object by object. Timing is logged at debug level (`--verbose`).
+pub fn diff_each(
+    before: &SchemaSnapshot,
+    after: &SchemaSnapshot,
+    mut emit: impl FnMut(SchemaDiff),
+) {
+    let started = std::time::Instant::now();
+    let skipped = incomplete_categories(before, after);
+    let compare = |category: &str| !skipped.iter().any(|c| c == category);
+    let mut found = 0usize;
+    let mut emit = |d: SchemaDiff| {
+        found += 1;
+        emit(d);
+    };
+
+    if compare("tables") {
+        let before_tables = index_by(&before.tables, |t| t.name.as_str());
+        let after_tables = index_by(&after.tables, |t| t.name.as_str());
+        for bt in &before.tables {
+            match after_tables.get(bt.name.as_str()) {
+                Some(at) => diff_columns(&mut emit, &bt.name, &bt.columns, &at.columns),
+                None => emit(SchemaDiff::TableDropped(bt.name.clone())),
+            }
         }
-    }
-    for at in &after.tables {
-        if !before_tables.contains_key(at.name.as_str()) {
-            diffs.push(SchemaDiff::TableAdded(at.clone()));
+        for at in &after.tables {
+            if !before_tables.contains_key(at.name.as_str()) {
+                emit(SchemaDiff::TableAdded(at.clone()));
+            }
         }
     }
 
-    // Views: check dropped/altered then added
-    for bv in &before.views {
-        if let Some(av) = after_views.get(bv.name.as_str()) {
-            if bv.definition != av.definition {
-                diffs.push(SchemaDiff::ViewAltered {
+    if compare("views") {
+        let before_views = index_by(&before.views, |v| v.name.as_str());
+        let after_views = index_by(&after.views, |v| v.name.as_str());
+        for bv in &before.views {
+            match after_views.get(bv.name.as_str()) {
+                Some(av) if bv.definition != av.definition => emit(SchemaDiff::ViewAltered {
                     name: bv.name.clone(),
                     from: bv.definition.clone(),
                     to: av.definition.clone(),
-                });
+                }),
+                Some(_) => {}
+                None => emit(SchemaDiff::ViewDropped(bv.name.clone())),
             }
-        } else {
-            diffs.push(SchemaDiff::ViewDropped(bv.name.clone()));
         }
-    }
-    for av in &after.views {
-        if !before_views.contains_key(av.name.as_str()) {
-            diffs.push(SchemaDiff::ViewAdded(av.clone()));
+        for av in &after.views {
+            if !before_views.contains_key(av.name.as_str()) {
+                emit(SchemaDiff::ViewAdded(av.clone()));
+            }
         }
     }
 
-    // Indexes: check dropped then added
-    for bi in &before.indexes {
-        if !after_indexes.contains(bi.name.as_str()) {
-            diffs.push(SchemaDiff::IndexDropped {
-                name: bi.name.clone(),
-                table_name: bi.table_name.clone(),
-            });
+    if compare("indexes") {
+        let before_indexes = index_by(&before.indexes, |i| i.name.as_str());
+        let after_indexes = index_by(&after.indexes, |i| i.name.as_str());
+        for bi in &before.indexes {
+            if !after_indexes.contains_key(bi.name.as_str()) {
+                emit(SchemaDiff::IndexDropped {
+                    name: bi.name.clone(),
+                    table_name: bi.table_name.clone(),
+                });
+            }
         }
-    }
-    for ai in &after.indexes {
-        if !before_indexes.contains(ai.name.as_str()) {
-            diffs.push(SchemaDiff::IndexAdded(ai.clone()));
+        for ai in &after.indexes {
+            if !before_indexes.contains_key(ai.name.as_str()) {
+                emit(SchemaDiff::IndexAdded(ai.clone()));
+            }
         }
     }
 
-    // Sequences: check dropped then added
-    for bs in &before.sequences {
-        if !after_sequences.contains(bs.name.as_str()) {
-            diffs.push(SchemaDiff::SequenceDropped(bs.name.clone()));
+    if compare("sequences") {
+        let before_sequences = index_by(&before.sequences, |s| s.name.as_str());
+        let after_sequences = index_by(&after.sequences, |s| s.name.as_str());
+        for bs in &before.sequences {
+            if !after_sequences.contains_key(bs.name.as_str()) {
+                emit(SchemaDiff::SequenceDropped(bs.name.clone()));
+            }
         }
-    }
-    for a_s in &after.sequences {
-        if !before_sequences.contains(a_s.name.as_str()) {
-            diffs.push(SchemaDiff::SequenceAdded(a_s.clone()));
+        for a_s in &after.sequences {
+            if !before_sequences.contains_key(a_s.name.as_str()) {
+                emit(SchemaDiff::SequenceAdded(a_s.clone()));
+            }
         }
     }
 
-    // Functions: check dropped/altered then added
-    for bf in &before.functions {
-        if let Some(af) = after_functions.get(bf.name.as_str()) {
-            if bf.definition != af.definition {
-                diffs.push(SchemaDiff::FunctionAltered {
+    if compare("functions") {
+        let before_functions = index_by(&before.functions, |f| f.name.as_str());
+        let after_functions = index_by(&after.functions, |f| f.name.as_str());
+        for bf in &before.functions {
+            match after_functions.get(bf.name.as_str()) {
+                Some(af) if bf.definition != af.definition => emit(SchemaDiff::FunctionAltered {
                     name: bf.name.clone(),
-                });
+                }),
+                Some(_) => {}
+                None => emit(SchemaDiff::FunctionDropped(bf.name.clone())),
             }
-        } else {
-            diffs.push(SchemaDiff::FunctionDropped(bf.name.clone()));
         }
-    }
-    for af in &after.functions {
-        if !before_functions.contains_key(af.name.as_str()) {
-            diffs.push(SchemaDiff::FunctionAdded(af.clone()));
+        for af in &after.functions {
+            if !before_functions.contains_key(af.name.as_str()) {
+                emit(SchemaDiff::FunctionAdded(af.clone()));
+            }
         }
     }
 
-    // Enums: check dropped then added
-    for be in &before.enums {
-        if !after_enums.contains(be.name.as_str()) {
-            diffs.push(SchemaDiff::EnumDropped(be.name.clone()));
+    if compare("enums") {
+        let before_enums = index_by(&before.enums, |e| e.name.as_str());
+        let after_enums = index_by(&after.enums, |e| e.name.as_str());
+        for be in &before.enums {
+            if !after_enums.contains_key(be.name.as_str()) {
+                emit(SchemaDiff::EnumDropped(be.name.clone()));
+            }
         }
-    }
-    for ae in &after.enums {
-        if !before_enums.contains(ae.name.as_str()) {
-            diffs.push(SchemaDiff::EnumAdded(ae.clone()));
+        for ae in &after.enums {
+            if !before_enums.contains_key(ae.name.as_str()) {
+                emit(SchemaDiff::EnumAdded(ae.clone()));
+            }
         }
     }
 
-    // Constraints: check dropped then added
-    for bc in &before.constraints {
-        if !after_constraints.contains(&(bc.table_name.as_str(), bc.name.as_str())) {
-            diffs.push(SchemaDiff::ConstraintDropped {
-                table: bc.table_name.clone(),
-                name: bc.name.clone(),
-            });
+    if compare("constraints") {
+        fn key(c: &ConstraintDef) -> (&str, &str) {
+            (c.table_name.as_str(), c.name.as_str())
         }
-    }
-    for ac in &after.constraints {
-        if !before_constraints.contains(&(ac.table_name.as_str(), ac.name.as_str())) {
-            diffs.push(SchemaDiff::ConstraintAdded(ac.clone()));
+        let before_constraints = index_by(&before.constraints, key);
+        let after_constraints = index_by(&after.constraints, key);
+        for bc in &before.constraints {
+            if !after_constraints.contains_key(&key(bc)) {
+                emit(SchemaDiff::ConstraintDropped {
+                    table: bc.table_name.clone(),
+                    name: bc.name.clone(),
+                });
+            }
+        }
+        for ac in &after.constraints {
+            if !before_constraints.contains_key(&key(ac)) {
+                emit(SchemaDiff::ConstraintAdded(ac.clone()));
+            }
         }
     }
 
-    // Triggers: check dropped then added
-    for bt in &before.triggers {
-        if !after_triggers.contains(&(bt.table_name.as_str(), bt.name.as_str())) {
-            diffs.push(SchemaDiff::TriggerDropped {
-                table: bt.table_name.clone(),
-                name: bt.name.clone(),
-            });
+    if compare("triggers") {
+        fn key(t: &TriggerDef) -> (&str, &str) {
+            (t.table_name.as_str(), t.name.as_str())
         }
-    }
-    for at in &after.triggers {
-        if !before_triggers.contains(&(at.table_name.as_str(), at.name.as_str())) {
-            diffs.push(SchemaDiff::TriggerAdded(at.clone()));
+        let before_triggers = index_by(&before.triggers, key);
+        let after_triggers = index_by(&after.triggers, key);
+        for bt in &before.triggers {
+            if !after_triggers.contains_key(&key(bt)) {
+                emit(SchemaDiff::TriggerDropped {
+                    table: bt.table_name.clone(),
+                    name: bt.name.clone(),
+                });
+            }
+        }
+        for at in &after.triggers {
+            if !before_triggers.contains_key(&key(at)) {
+                emit(SchemaDiff::TriggerAdded(at.clone()));
+            }
         }
     }
 
-    // Extensions: check dropped then added
-    for ext in &before.extensions {
-        if !after_extensions.contains(ext.as_str()) {
-            diffs.push(SchemaDiff::ExtensionDropped(ext.clone()));
+    if compare("extensions") {
+        let before_extensions: HashSet<&str> =
+            before.extensions.iter().map(String::as_str).collect();
+        let after_extensions: HashSet<&str> = after.extensions.iter().map(String::as_str).collect();
+        for ext in &before.extensions {
+            if !after_extensions.contains(ext.as_str()) {
+                emit(SchemaDiff::ExtensionDropped(ext.clone()));
+            }
         }
-    }
-    for ext in &after.extensions {
-        if !before_extensions.contains(ext.as_str()) {
-            diffs.push(SchemaDiff::ExtensionAdded(ext.clone()));
+        for ext in &after.extensions {
+            if !before_extensions.contains(ext.as_str()) {
+                emit(SchemaDiff::ExtensionAdded(ext.clone()));
+            }
         }
     }
 
-    diffs
+    log::debug!(
+        "Compared schemas; before_objects={}, after_objects={}, diffs={}, elapsed_ms={}",
+        before.object_count(),
+        after.object_count(),
+        found,
+        started.elapsed().as_millis()
+    );
+}
+
+/// Hash index over `items` by `key`, borrowing both.
+fn index_by<'a, T, K: std::hash::Hash + Eq>(
+    items: &'a [T],
+    key: impl Fn(&'a T) -> K,
+) -> HashMap<K, &'a T> {
+    items.iter().map(|item| (key(item), item)).collect()
 }
 
 fn diff_columns(
-    diffs: &mut Vec<SchemaDiff>,
+    emit: &mut impl FnMut(SchemaDiff),
     table: &str,
     before: &[ColumnDef],
     after: &[ColumnDef],
 ) {
-    let before_cols: HashMap<&str, &ColumnDef> =
-        before.iter().map(|c| (c.name.as_str(), c)).collect();
-    let after_cols: HashMap<&str, &ColumnDef> =
-        after.iter().map(|c| (c.name.as_str(), c)).collect();
+    let before_cols = index_by(before, |c| c.name.as_str());
+    let after_cols = index_by(after, |c| c.name.as_str());
 
     for bc in before {
         if let Some(ac) = after_cols.get(bc.name.as_str()) {
             if bc != *ac {
-                diffs.push(SchemaDiff::ColumnAltered {
+                emit(SchemaDiff::ColumnAltered {
                     table: table.to_string(),
                     column: bc.name.clone(),
                     from: bc.clone(),
@@ -829,7 +1291,7 @@ fn diff_columns(
                 });
             }
         } else {
-            diffs.push(SchemaDiff::ColumnDropped {
+            emit(SchemaDiff::ColumnDropped {
                 table: table.to_string(),
                 column: bc.name.clone(),
             });
@@ -837,7 +1299,7 @@ fn diff_columns(
     }
     for ac in after {
         if !before_cols.contains_key(ac.name.as_str()) {
-            diffs.push(SchemaDiff::ColumnAdded {
+            emit(SchemaDiff::ColumnAdded {
                 table: table.to_string(),
                 column: ac.clone(),
             });
@@ -845,8 +1307,175 @@ fn diff_columns(
     }
 }
 
+/// Collapse a dropped table and an added table with identical columns into a
+/// single [`SchemaDiff::TableRenamed`].
+///
+/// `before` must be the snapshot the diffs were computed from. Columns match
+/// when name, type, nullability, default and position all agree. A pair is
+/// only collapsed when the match is unambiguous: the dropped table matches
+/// exactly one added table and vice versa. The rename replaces the
+/// `TableAdded` entry in place; the `TableDropped` entry is removed.
+///
+/// The rename carries the table's indexes, constraints and triggers along,
+/// so diffs on them are rewritten to match: a constraint or trigger dropped
+/// from the old table and re-added unchanged on the new one drops out, and
+/// any other drop is pointed at the new table name, which is what the
+/// object is attached to once the rename has run. Index diffs for the
+/// indexes backing those constraints are left to the constraint diffs.
+pub fn detect_renames(before: &SchemaSnapshot, diffs: Vec<SchemaDiff>) -> Vec<SchemaDiff> {
+    let before_tables = index_by(&before.tables, |t| t.name.as_str());
+    // Dropped and added tables grouped by their column list; a group with
+    // exactly one of each is a rename.
+    let mut by_columns: HashMap<&[ColumnDef], (Vec<&str>, Vec<&str>)> = HashMap::new();
+    for d in &diffs {
+        match d {
+            SchemaDiff::TableDropped(name) => {
+                if let Some(t) = before_tables.get(name.as_str()) {
+                    if !t.columns.is_empty() {
+                        by_columns.entry(&t.columns).or_default().0.push(&t.name);
+                    }
+                }
+            }
+            SchemaDiff::TableAdded(t) if !t.columns.is_empty() => {
+                by_columns.entry(&t.columns).or_default().1.push(&t.name);
+            }
+            _ => {}
+        }
+    }
+    let renames: HashMap<String, String> = by_columns
+        .into_values()
+        .filter_map(
+            |(dropped, added)| match (dropped.as_slice(), added.as_slice()) {
+                ([from], [to]) => Some((to.to_string(), from.to_string())),
+                _ => None,
+            },
+        )
+        .collect();
+    if renames.is_empty() {
+        return diffs;
+    }
+
+    let renamed_to: HashMap<String, String> = renames
+        .iter()
+        .map(|(to, from)| (from.clone(), to.clone()))
+        .collect();
+    let before_constraints = index_by(&before.constraints, |c| {
+        (c.table_name.as_str(), c.name.as_str())
+    });
+    let before_triggers = index_by(&before.triggers, |t| {
+        (t.table_name.as_str(), t.name.as_str())
+    });
+    // Constraints and triggers that survive the rename unchanged, keyed by
+    // the new table and the object name.
+    let mut carried: HashSet<(String, String)> = HashSet::new();
+    for d in &diffs {
+        let (table, name, unchanged) = match d {
+            SchemaDiff::ConstraintAdded(c) => (
+                &c.table_name,
+                &c.name,
+                renames.get(&c.table_name).is_some_and(|from| {
+                    before_constraints
+                        .get(&(from.as_str(), c.name.as_str()))
+                        .is_some_and(|b| {
+                            b.constraint_type == c.constraint_type && b.definition == c.definition
+                        })
+                }),
+            ),
+            SchemaDiff::TriggerAdded(t) => (
+                &t.table_name,
+                &t.name,
+                renames.get(&t.table_name).is_some_and(|from| {
+                    before_triggers
+                        .get(&(from.as_str(), t.name.as_str()))
+                        .is_some_and(|b| b.definition == t.definition)
+                }),
+            ),
+            _ => continue,
+        };
+        if unchanged {
+            carried.insert((table.clone(), name.clone()));
+        }
+    }
+    let is_carried =
+        |table: &str, name: &str| carried.contains(&(table.to_string(), name.to_string()));
+    // Constraints replaced on a renamed table; dropping or adding the
+    // constraint drops or builds its index.
+    let constraint_indexes: HashSet<String> = diffs
+        .iter()
+        .filter_map(|d| match d {
+            SchemaDiff::ConstraintDropped { table, name } if renamed_to.contains_key(table) => {
+                Some(name.clone())
+            }
+            SchemaDiff::ConstraintAdded(c) if renames.contains_key(&c.table_name) => {
+                Some(c.name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+
+    diffs
+        .into_iter()
+        .filter_map(|d| match d {
+            SchemaDiff::TableDropped(name) if renamed_to.contains_key(&name) => None,
+            SchemaDiff::ConstraintAdded(c) if is_carried(&c.table_name, &c.name) => None,
+            SchemaDiff::TriggerAdded(t) if is_carried(&t.table_name, &t.name) => None,
+            SchemaDiff::ConstraintDropped { table, name } => match renamed_to.get(&table) {
+                Some(to) if is_carried(to, &name) => None,
+                Some(to) => Some(SchemaDiff::ConstraintDropped {
+                    table: to.clone(),
+                    name,
+                }),
+                None => Some(SchemaDiff::ConstraintDropped { table, name }),
+            },
+            SchemaDiff::TriggerDropped { table, name } => match renamed_to.get(&table) {
+                Some(to) if is_carried(to, &name) => None,
+                Some(to) => Some(SchemaDiff::TriggerDropped {
+                    table: to.clone(),
+                    name,
+                }),
+                None => Some(SchemaDiff::TriggerDropped { table, name }),
+            },
+            SchemaDiff::IndexDropped { name, table_name }
+                if renamed_to.contains_key(&table_name) && constraint_indexes.contains(&name) =>
+            {
+                None
+            }
+            SchemaDiff::IndexAdded(i)
+                if renames.contains_key(&i.table_name) && constraint_indexes.contains(&i.name) =>
+            {
+                None
+            }
+            SchemaDiff::IndexDropped { name, table_name } => Some(SchemaDiff::IndexDropped {
+                table_name: renamed_to.get(&table_name).cloned().unwrap_or(table_name),
+                name,
+            }),
+            SchemaDiff::TableAdded(t) => match renames.get(&t.name) {
+                Some(from) => Some(SchemaDiff::TableRenamed {
+                    from: from.clone(),
+                    to: t.name,
+                }),
+                None => Some(SchemaDiff::TableAdded(t)),
+            },
+            other => Some(other),
+        })
+        .collect()
+}
+
 /// Generate DDL statements from schema diffs.
+///
+/// Assumes a current PostgreSQL server; see [`generate_ddl_for_version`] to
+/// target an older one.
 pub fn generate_ddl(diffs: &[SchemaDiff]) -> String {
+    generate_ddl_for_version(diffs, None)
+}
+
+/// Generate DDL statements from schema diffs for a specific PostgreSQL version.
+///
+/// With `None` the output is identical to [`generate_ddl`]. On servers older
+/// than 11, `ADD COLUMN ... DEFAULT` rewrites the whole table, so it is split
+/// into add, set default, backfill, and (if needed) set not null.
+pub fn generate_ddl_for_version(diffs: &[SchemaDiff], version: Option<PgVersion>) -> String {
+    let split_defaults = version.is_some_and(|v| !v.has_fast_column_default());
     let mut statements = Vec::new();
 
     for d in diffs {
@@ -878,6 +1507,44 @@ pub fn generate_ddl(diffs: &[SchemaDiff]) -> String {
                     quote_ident(name)
                 ));
             }
+            SchemaDiff::TableRenamed { from, to } => {
+                statements.push(format!(
+                    "ALTER TABLE IF EXISTS {} RENAME TO {};",
+                    quote_ident(from),
+                    quote_ident(to)
+                ));
+            }
+            SchemaDiff::ColumnAdded { table, column }
+                if split_defaults && column.default.is_some() =>
+            {
+                let default = column.default.as_deref().unwrap_or_default();
+                statements.push(format!(
+                    "ALTER TABLE {} ADD COLUMN {} {};",
+                    quote_ident(table),
+                    quote_ident(&column.name),
+                    column.data_type
+                ));
+                statements.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};",
+                    quote_ident(table),
+                    quote_ident(&column.name),
+                    default
+                ));
+                statements.push(format!(
+                    "UPDATE {} SET {} = {} WHERE {} IS NULL;",
+                    quote_ident(table),
+                    quote_ident(&column.name),
+                    default,
+                    quote_ident(&column.name)
+                ));
+                if !column.is_nullable {
+                    statements.push(format!(
+                        "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;",
+                        quote_ident(table),
+                        quote_ident(&column.name)
+                    ));
+                }
+            }
             SchemaDiff::ColumnAdded { table, column } => {
                 let mut stmt = format!(
                     "ALTER TABLE {} ADD COLUMN {} {}",
@@ -1222,6 +1889,9 @@ pub fn generate_ddl_mysql(diffs: &[SchemaDiff]) -> String {
             SchemaDiff::TableDropped(name) => {
                 statements.push(format!("DROP TABLE IF EXISTS {};", q(name)));
             }
+            SchemaDiff::TableRenamed { from, to } => {
+                statements.push(format!("RENAME TABLE {} TO {};", q(from), q(to)));
+            }
             SchemaDiff::ColumnAdded { table, column } => {
                 let mut stmt = format!(
                     "ALTER TABLE {} ADD COLUMN {} {}",
@@ -1359,6 +2029,7 @@ pub fn generate_ddl_mysql(diffs: &[SchemaDiff]) -> String {
 #[cfg(feature = "mysql")]
 pub async fn introspect_mysql(client: &DbClient, schema: &str) -> Result<SchemaSnapshot> {
     use mysql_async::prelude::*;
+    let started = std::time::Instant::now();
     let pool = client.as_mysql()?;
     let mut conn = pool.get_conn().await?;
 
@@ -1524,7 +2195,7 @@ pub async fn introspect_mysql(client: &DbClient, schema: &str) -> Result<SchemaS
         })
         .collect();
 
-    Ok(SchemaSnapshot {
+    let mut snapshot = SchemaSnapshot {
         tables,
         views,
         indexes,
@@ -1534,7 +2205,12 @@ pub async fn introspect_mysql(client: &DbClient, schema: &str) -> Result<SchemaS
         constraints,
         triggers,
         extensions: Vec::new(),
-    })
+        distributed_tables: Vec::new(),
+        incomplete: Vec::new(),
+    };
+    filter_objects(&mut snapshot, &introspection::object_filter());
+    log_introspected(schema, &snapshot, started);
+    Ok(snapshot)
 }
 
 #[cfg(test)]
@@ -1603,3 +2279,452 @@ mod tests_generate_ddl_mysql {
         assert!(sql.contains("utf8mb4"));
     }
 }
+
+#[cfg(test)]
+mod tests_generate_ddl_versions {
+    use super::*;
+
+    fn add_col_with_default() -> Vec<SchemaDiff> {
+        vec![SchemaDiff::ColumnAdded {
+            table: "users".into(),
+            column: ColumnDef {
+                name: "active".into(),
+                data_type: "boolean".into(),
+                is_nullable: false,
+                default: Some("true".into()),
+                ordinal_position: 2,
+            },
+        }]
+    }
+
+    #[test]
+    fn add_column_default_inline_on_modern_pg() {
+        let sql = generate_ddl_for_version(
+            &add_col_with_default(),
+            Some(PgVersion::from_version_num(110000)),
+        );
+        assert_eq!(sql, generate_ddl(&add_col_with_default()));
+        assert!(sql.contains("ADD COLUMN \"active\" boolean NOT NULL DEFAULT true;"));
+    }
+
+    #[test]
+    fn add_column_default_split_before_pg11() {
+        let sql = generate_ddl_for_version(
+            &add_col_with_default(),
+            Some(PgVersion::from_version_num(100023)),
+        );
+        assert!(sql.contains("ADD COLUMN \"active\" boolean;"));
+        assert!(sql.contains("SET DEFAULT true;"));
+        assert!(sql.contains("UPDATE \"users\" SET \"active\" = true WHERE \"active\" IS NULL;"));
+        assert!(sql.contains("SET NOT NULL;"));
+    }
+}
+
+#[cfg(test)]
+mod tests_extension_managed {
+    use super::*;
+
+    fn table(name: &str) -> TableDef {
+        TableDef {
+            schema: "public".into(),
+            name: name.into(),
+            columns: vec![],
+        }
+    }
+
+    fn trigger(table_name: &str, name: &str) -> TriggerDef {
+        TriggerDef {
+            schema: "public".into(),
+            table_name: table_name.into(),
+            name: name.into(),
+            definition: String::new(),
+        }
+    }
+
+    fn snapshot(distributed_tables: Vec<DistributedTableDef>) -> SchemaSnapshot {
+        SchemaSnapshot {
+            tables: vec![table("orders"), table("orders_102008"), table("orders_v2")],
+            views: vec![],
+            indexes: vec![],
+            sequences: vec![],
+            functions: vec![],
+            enums: vec![],
+            constraints: vec![],
+            triggers: vec![
+                trigger("orders", "truncate_trigger"),
+                trigger("metrics", "ts_insert_blocker"),
+                trigger("orders", "audit_orders"),
+            ],
+            extensions: vec![],
+            distributed_tables,
+            incomplete: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn drops_citus_shards_and_extension_triggers() {
+        let mut s = snapshot(vec![
+            DistributedTableDef {
+                name: "orders".into(),
+                kind: DistributionKind::Distributed,
+                column: Some("tenant_id".into()),
+            },
+            DistributedTableDef {
+                name: "metrics".into(),
+                kind: DistributionKind::Hypertable,
+                column: Some("ts".into()),
+            },
+        ]);
+        filter_extension_managed(&mut s);
+        let tables: Vec<&str> = s.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tables, vec!["orders", "orders_v2"]);
+        let triggers: Vec<&str> = s.triggers.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(triggers, vec!["audit_orders"]);
+    }
+
+    #[test]
+    fn leaves_plain_schemas_untouched() {
+        let mut s = snapshot(vec![]);
+        filter_extension_managed(&mut s);
+        assert_eq!(s.tables.len(), 3);
+        assert_eq!(s.triggers.len(), 3);
+    }
+
+    #[test]
+    fn drops_extension_members_and_their_triggers() {
+        let mut s = snapshot(vec![]);
+        s.functions.push(FunctionDef {
+            schema: "public".into(),
+            name: "citextin".into(),
+            arguments: "cstring".into(),
+            return_type: "citext".into(),
+            language: "c".into(),
+            definition: String::new(),
+        });
+        filter_extension_members(
+            &mut s,
+            &[
+                ExtensionMember {
+                    kind: ExtensionMemberKind::Relation,
+                    name: "orders".into(),
+                },
+                ExtensionMember {
+                    kind: ExtensionMemberKind::Function,
+                    name: "citextin".into(),
+                },
+            ],
+        );
+        let tables: Vec<&str> = s.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tables, vec!["orders_102008", "orders_v2"]);
+        let triggers: Vec<&str> = s.triggers.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(triggers, vec!["ts_insert_blocker"]);
+        assert!(s.functions.is_empty());
+    }
+
+    #[test]
+    fn filters_objects_by_name_glob() {
+        let mut s = snapshot(vec![]);
+        filter_objects(
+            &mut s,
+            &introspection::ObjectFilter {
+                include: vec!["orders*".into()],
+                exclude: vec!["*_1?????".into()],
+            },
+        );
+        let tables: Vec<&str> = s.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tables, vec!["orders", "orders_v2"]);
+        let triggers: Vec<&str> = s.triggers.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(triggers, vec!["truncate_trigger", "audit_orders"]);
+    }
+}
+
+#[cfg(test)]
+mod tests_detect_renames {
+    use super::*;
+
+    fn table(name: &str, cols: &[&str]) -> TableDef {
+        TableDef {
+            schema: "public".into(),
+            name: name.into(),
+            columns: cols
+                .iter()
+                .enumerate()
+                .map(|(i, c)| ColumnDef {
+                    name: (*c).into(),
+                    data_type: "integer".into(),
+                    is_nullable: true,
+                    default: None,
+                    ordinal_position: i as i32 + 1,
+                })
+                .collect(),
+        }
+    }
+
+    fn snapshot(tables: Vec<TableDef>) -> SchemaSnapshot {
+        SchemaSnapshot {
+            tables,
+            views: vec![],
+            indexes: vec![],
+            sequences: vec![],
+            functions: vec![],
+            enums: vec![],
+            constraints: vec![],
+            triggers: vec![],
+            extensions: vec![],
+            distributed_tables: vec![],
+            incomplete: vec![],
+        }
+    }
+
+    #[test]
+    fn identical_columns_become_rename() {
+        let before = snapshot(vec![table("orders", &["id", "total"])]);
+        let after = snapshot(vec![table("purchases", &["id", "total"])]);
+        let diffs = detect_renames(&before, diff(&before, &after));
+        assert_eq!(diffs.len(), 1);
+        assert!(matches!(
+            &diffs[0],
+            SchemaDiff::TableRenamed { from, to } if from == "orders" && to == "purchases"
+        ));
+        assert_eq!(
+            generate_ddl(&diffs).trim(),
+            "ALTER TABLE IF EXISTS \"orders\" RENAME TO \"purchases\";"
+        );
+    }
+
+    #[test]
+    fn rename_carries_keys_and_indexes() {
+        let index = |name: &str, table: &str, cols: &str| IndexDef {
+            schema: "public".into(),
+            name: name.into(),
+            table_name: table.into(),
+            definition: format!(
+                "CREATE UNIQUE INDEX {} ON public.{} USING btree ({})",
+                name, table, cols
+            ),
+            is_unique: true,
+        };
+        let constraint = |name: &str, table: &str, kind: &str, def: &str| ConstraintDef {
+            schema: "public".into(),
+            table_name: table.into(),
+            name: name.into(),
+            constraint_type: kind.into(),
+            definition: def.into(),
+        };
+        let mut before = snapshot(vec![table("orders", &["id", "total"])]);
+        before.indexes = vec![
+            index("orders_pkey", "orders", "id"),
+            index("orders_total_idx", "orders", "total"),
+            index("orders_id_total_idx", "orders", "id, total"),
+        ];
+        before.constraints = vec![
+            constraint("orders_pkey", "orders", "PRIMARY KEY", "PRIMARY KEY (id)"),
+            constraint("positive_total", "orders", "CHECK", "CHECK ((total > 0))"),
+        ];
+        let mut after = snapshot(vec![table("purchases", &["id", "total"])]);
+        after.indexes = vec![
+            index("purchases_pkey", "purchases", "id"),
+            index("orders_total_idx", "purchases", "total"),
+        ];
+        after.constraints = vec![
+            constraint(
+                "purchases_pkey",
+                "purchases",
+                "PRIMARY KEY",
+                "PRIMARY KEY (id)",
+            ),
+            constraint(
+                "positive_total",
+                "purchases",
+                "CHECK",
+                "CHECK ((total > 0))",
+            ),
+        ];
+
+        let diffs = detect_renames(&before, diff(&before, &after));
+        assert_eq!(
+            generate_ddl(&diffs),
+            [
+                "ALTER TABLE IF EXISTS \"orders\" RENAME TO \"purchases\";",
+                "DROP INDEX IF EXISTS \"orders_id_total_idx\";",
+                "ALTER TABLE \"purchases\" DROP CONSTRAINT IF EXISTS \"orders_pkey\";",
+                "ALTER TABLE \"purchases\" ADD CONSTRAINT \"purchases_pkey\" PRIMARY KEY (id);",
+            ]
+            .join("\n\n")
+        );
+        assert!(matches!(
+            &diffs[1],
+            SchemaDiff::IndexDropped { table_name, .. } if table_name == "purchases"
+        ));
+    }
+
+    #[test]
+    fn different_columns_stay_drop_and_create() {
+        let before = snapshot(vec![table("orders", &["id", "total"])]);
+        let after = snapshot(vec![table("purchases", &["id", "amount"])]);
+        let diffs = detect_renames(&before, diff(&before, &after));
+        assert!(diffs
+            .iter()
+            .all(|d| !matches!(d, SchemaDiff::TableRenamed { .. })));
+        assert_eq!(diffs.len(), 2);
+    }
+
+    #[test]
+    fn ambiguous_matches_are_left_alone() {
+        let before = snapshot(vec![table("a", &["id"]), table("b", &["id"])]);
+        let after = snapshot(vec![table("c", &["id"]), table("d", &["id"])]);
+        let diffs = detect_renames(&before, diff(&before, &after));
+        assert!(diffs
+            .iter()
+            .all(|d| !matches!(d, SchemaDiff::TableRenamed { .. })));
+    }
+
+    /// `n` tables, and the same with the first tenth renamed and a column
+    /// widened in the next tenth.
+    fn large_schemas(n: usize) -> (SchemaSnapshot, SchemaSnapshot) {
+        let table_with = |name: String, i: usize| table(&name, &[&format!("id_{}", i), "payload"]);
+        let before = snapshot((0..n).map(|i| table_with(format!("t{}", i), i)).collect());
+        let after = snapshot(
+            (0..n)
+                .map(|i| {
+                    let name = if i < n / 10 {
+                        format!("renamed{}", i)
+                    } else {
+                        format!("t{}", i)
+                    };
+                    let mut t = table_with(name, i);
+                    if (n / 10..n / 5).contains(&i) {
+                        t.columns[1].data_type = "bigint".into();
+                    }
+                    t
+                })
+                .collect(),
+        );
+        (before, after)
+    }
+
+    #[test]
+    fn ten_thousand_tables_diff_in_linear_time() {
+        let (before, after) = large_schemas(10_000);
+        let mut streamed = 0;
+        diff_each(&before, &after, |_| streamed += 1);
+        let diffs = detect_renames(&before, diff(&before, &after));
+
+        assert_eq!(streamed, 3_000);
+        let renamed = diffs
+            .iter()
+            .filter(|d| matches!(d, SchemaDiff::TableRenamed { .. }))
+            .count();
+        let altered = diffs
+            .iter()
+            .filter(|d| matches!(d, SchemaDiff::ColumnAltered { .. }))
+            .count();
+        assert_eq!((renamed, altered, diffs.len()), (1_000, 1_000, 2_000));
+
+        // Eight times the tables should take about eight times as long (a
+        // bit more as the maps outgrow the cache); a quadratic pass takes
+        // sixty-four. Best of three, against a ratio, so neither a slow
+        // runner nor one stalled run fails it.
+        let best_of_three = |n: usize| {
+            let (before, after) = large_schemas(n);
+            (0..3)
+                .map(|_| {
+                    let started = std::time::Instant::now();
+                    std::hint::black_box(detect_renames(&before, diff(&before, &after)));
+                    started.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let small = best_of_three(1_250);
+        let large = best_of_three(10_000);
+        let ratio = large.as_secs_f64() / small.as_secs_f64();
+        assert!(
+            ratio < 24.0,
+            "1.25k tables {:?}, 10k tables {:?}",
+            small,
+            large
+        );
+    }
+
+    #[test]
+    fn incomplete_categories_are_skipped_without_copying() {
+        let before = snapshot(vec![table("orders", &["id"])]);
+        let mut after = snapshot(vec![]);
+        after.incomplete = vec!["tables".into()];
+        assert!(diff(&before, &after).is_empty());
+        after.incomplete.clear();
+        assert_eq!(diff(&before, &after).len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod tests_standalone_api {
+    use super::*;
+
+    fn snapshot(columns: &[&str]) -> SchemaSnapshot {
+        SchemaSnapshot {
+            tables: vec![TableDef {
+                schema: "public".into(),
+                name: "users".into(),
+                columns: columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| ColumnDef {
+                        name: c.to_string(),
+                        data_type: "text".into(),
+                        is_nullable: true,
+                        default: None,
+                        ordinal_position: i as i32 + 1,
+                    })
+                    .collect(),
+            }],
+            views: vec![],
+            indexes: vec![],
+            sequences: vec![],
+            functions: vec![],
+            enums: vec![],
+            constraints: vec![],
+            triggers: vec![],
+            extensions: vec!["pgcrypto".into()],
+            distributed_tables: vec![DistributedTableDef {
+                name: "users".into(),
+                kind: DistributionKind::Reference,
+                column: None,
+            }],
+            incomplete: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn snapshot_and_diffs_round_trip_through_json() {
+        let before = snapshot(&["id"]);
+        let json = serde_json::to_string(&before).unwrap();
+        let restored: SchemaSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, before);
+
+        let diffs = diff(&restored, &snapshot(&["id", "email"]));
+        let json = serde_json::to_string(&diffs).unwrap();
+        let restored: Vec<SchemaDiff> = serde_json::from_str(&json).unwrap();
+        assert_eq!(generate_ddl(&restored), generate_ddl(&diffs));
+    }
+
+    #[test]
+    fn snapshot_without_distributed_tables_deserializes() {
+        // Snapshots written before distributed tables were tracked.
+        let mut json = serde_json::to_value(snapshot(&["id"])).unwrap();
+        json.as_object_mut().unwrap().remove("distributed_tables");
+        let restored: SchemaSnapshot = serde_json::from_value(json).unwrap();
+        assert!(restored.distributed_tables.is_empty());
+        assert_eq!(restored.tables, snapshot(&["id"]).tables);
+    }
+
+    #[test]
+    fn generate_sql_follows_dialect() {
+        let diffs = diff(&snapshot(&["id"]), &snapshot(&["id", "email"]));
+        let pg = generate_sql(&diffs, DialectKind::Postgres);
+        let mysql = generate_sql(&diffs, DialectKind::Mysql);
+        assert!(pg.contains("\"email\""), "{}", pg);
+        assert!(mysql.contains("`email`"), "{}", mysql);
+    }
+}
@@ -0,0 +1,1627 @@
+//! Schema snapshots as plain SQL (PostgreSQL).
+//!
+//! [`to_sql`] writes a [`SchemaSnapshot`] as a schema-only dump in the layout
+//! `pg_dump --schema-only` uses: `SET` preamble, a `-- Name: ...; Type: ...`
+//! header per object, and objects ordered so the script loads with `psql`
+//! (extensions, types, functions, sequences, tables, views in dependency
+//! order, then constraints, indexes, and foreign keys last).
+//!
+//! [`from_sql`] goes the other way: it reads such a dump, whether written by
+//! `pg_dump` or by [`to_sql`], and builds the snapshot introspection would
+//! return for it, so a schema file checked into git can stand in for a
+//! database in `diff` and `drift`. Column types are normalized to the
+//! `information_schema` names introspection reports (`varchar(255)` becomes
+//! `character varying`, `serial` becomes `integer` with a `nextval` default
+//! and an owned sequence), and unnamed constraints get PostgreSQL's default
+//! names. Statements it doesn't model (`SET`, `COMMENT`, `GRANT`, `OWNER TO`,
+//! ...) are skipped.
+//!
+//! Snapshots only record what `information_schema` reports, so an export
+//! loses column length modifiers, and array and user-defined column types are
+//! written as `text[]` and `text` with a `/* ARRAY */` or `/* USER-DEFINED */`
+//! marker that [`from_sql`] reads back. Triggers are written as comments, as
+//! in [`schema::to_ddl`](crate::schema::to_ddl), because the snapshot lacks
+//! their timing and events.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use regex_lite::Regex;
+
+use crate::db::quote_ident;
+use crate::error::{Result, WaypointError};
+use crate::schema::{
+    ColumnDef, ConstraintDef, DistributedTableDef, DistributionKind, EnumDef, FunctionDef,
+    IndexDef, SchemaSnapshot, SequenceDef, TableDef, TriggerDef, ViewDef,
+};
+use crate::sql_parser::split_statements;
+
+/// Column types `information_schema.columns.data_type` reports by name.
+const BUILTIN_TYPES: &[&str] = &[
+    "text",
+    "date",
+    "bytea",
+    "uuid",
+    "json",
+    "jsonb",
+    "xml",
+    "inet",
+    "cidr",
+    "macaddr",
+    "macaddr8",
+    "money",
+    "tsvector",
+    "tsquery",
+    "point",
+    "line",
+    "lseg",
+    "box",
+    "path",
+    "polygon",
+    "circle",
+    "oid",
+    "regclass",
+    "name",
+    "pg_lsn",
+    "int4range",
+    "int8range",
+    "numrange",
+    "tsrange",
+    "tstzrange",
+    "daterange",
+    "bit",
+];
+
+// ---------------------------------------------------------------------------
+// Export
+// ---------------------------------------------------------------------------
+
+/// Render `snapshot` as a schema-only dump of `schema`.
+pub fn to_sql(snapshot: &SchemaSnapshot, schema: &str) -> String {
+    let mut out =
+        String::from("--\n-- PostgreSQL database dump (waypoint snapshot export)\n--\n\n");
+    for set in [
+        "SET statement_timeout = 0;",
+        "SET lock_timeout = 0;",
+        "SET client_encoding = 'UTF8';",
+        "SET standard_conforming_strings = on;",
+        "SET check_function_bodies = false;",
+        "SET client_min_messages = warning;",
+    ] {
+        out.push_str(set);
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "SET search_path = {}, pg_catalog;\n",
+        quote_ident(schema)
+    ));
+
+    let mut object = |name: &str, kind: &str, sql: String| {
+        let owner = if kind == "EXTENSION" { "-" } else { schema };
+        out.push_str(&format!(
+            "\n--\n-- Name: {}; Type: {}; Schema: {}\n--\n\n{}\n",
+            name, kind, owner, sql
+        ));
+    };
+
+    for ext in &snapshot.extensions {
+        object(
+            ext,
+            "EXTENSION",
+            format!("CREATE EXTENSION IF NOT EXISTS {};", quote_ident(ext)),
+        );
+    }
+    for e in &snapshot.enums {
+        let values: Vec<String> = e
+            .values
+            .iter()
+            .map(|v| format!("    '{}'", v.replace('\'', "''")))
+            .collect();
+        object(
+            &e.name,
+            "TYPE",
+            format!(
+                "CREATE TYPE {} AS ENUM (\n{}\n);",
+                quote_ident(&e.name),
+                values.join(",\n")
+            ),
+        );
+    }
+    for f in &snapshot.functions {
+        object(
+            &format!("{}({})", f.name, f.arguments),
+            "FUNCTION",
+            format!("{};", f.definition.trim_end().trim_end_matches(';')),
+        );
+    }
+    for s in &snapshot.sequences {
+        object(
+            &s.name,
+            "SEQUENCE",
+            format!(
+                "CREATE SEQUENCE {} AS {};",
+                quote_ident(&s.name),
+                s.data_type
+            ),
+        );
+    }
+    for t in &snapshot.tables {
+        let mut columns: Vec<&ColumnDef> = t.columns.iter().collect();
+        columns.sort_by_key(|c| c.ordinal_position);
+        let columns: Vec<String> = columns
+            .iter()
+            .map(|c| {
+                let data_type = match c.data_type.as_str() {
+                    "ARRAY" => "text[] /* ARRAY */",
+                    "USER-DEFINED" => "text /* USER-DEFINED */",
+                    other => other,
+                };
+                let mut col = format!("    {} {}", quote_ident(&c.name), data_type);
+                if let Some(ref default) = c.default {
+                    col.push_str(&format!(" DEFAULT {}", default));
+                }
+                if !c.is_nullable {
+                    col.push_str(" NOT NULL");
+                }
+                col
+            })
+            .collect();
+        object(
+            &t.name,
+            "TABLE",
+            format!(
+                "CREATE TABLE {} (\n{}\n);",
+                quote_ident(&t.name),
+                columns.join(",\n")
+            ),
+        );
+    }
+    for v in ordered_views(&snapshot.views) {
+        let kind = if v.is_materialized {
+            "MATERIALIZED VIEW"
+        } else {
+            "VIEW"
+        };
+        let body = v.definition.trim_end().trim_end_matches(';');
+        let body = if body.starts_with(char::is_whitespace) {
+            body.to_string()
+        } else {
+            format!(" {}", body)
+        };
+        object(
+            &v.name,
+            kind,
+            format!("CREATE {} {} AS\n{};", kind, quote_ident(&v.name), body),
+        );
+    }
+
+    let (foreign_keys, constraints): (Vec<&ConstraintDef>, Vec<&ConstraintDef>) = snapshot
+        .constraints
+        .iter()
+        .partition(|c| c.constraint_type == "FOREIGN KEY");
+    let add_constraint = |c: &ConstraintDef| {
+        format!(
+            "ALTER TABLE ONLY {}\n    ADD CONSTRAINT {} {};",
+            quote_ident(&c.table_name),
+            quote_ident(&c.name),
+            c.definition
+        )
+    };
+    for c in &constraints {
+        object(
+            &format!("{} {}", c.table_name, c.name),
+            "CONSTRAINT",
+            add_constraint(c),
+        );
+    }
+    // Primary key and unique constraints create their own index.
+    let constraint_indexes: HashSet<(&str, &str)> = constraints
+        .iter()
+        .filter(|c| matches!(c.constraint_type.as_str(), "PRIMARY KEY" | "UNIQUE"))
+        .map(|c| (c.table_name.as_str(), c.name.as_str()))
+        .collect();
+    for idx in &snapshot.indexes {
+        if constraint_indexes.contains(&(idx.table_name.as_str(), idx.name.as_str())) {
+            continue;
+        }
+        object(
+            &idx.name,
+            "INDEX",
+            format!("{};", idx.definition.trim_end_matches(';')),
+        );
+    }
+    for t in &snapshot.triggers {
+        object(
+            &format!("{} {}", t.table_name, t.name),
+            "TRIGGER",
+            format!(
+                "-- Trigger {} on {}: {}",
+                t.name, t.table_name, t.definition
+            ),
+        );
+    }
+    for c in &foreign_keys {
+        object(
+            &format!("{} {}", c.table_name, c.name),
+            "FK CONSTRAINT",
+            add_constraint(c),
+        );
+    }
+    for d in &snapshot.distributed_tables {
+        let call = match (d.kind, &d.column) {
+            (DistributionKind::Hypertable, Some(col)) => {
+                format!("create_hypertable({}, {})", literal(&d.name), literal(col))
+            }
+            (DistributionKind::Distributed, Some(col)) => format!(
+                "create_distributed_table({}, {})",
+                literal(&d.name),
+                literal(col)
+            ),
+            _ => format!("create_reference_table({})", literal(&d.name)),
+        };
+        object(&d.name, "TABLE DISTRIBUTION", format!("SELECT {};", call));
+    }
+
+    out.push_str("\n--\n-- PostgreSQL database dump complete\n--\n");
+    out
+}
+
+fn literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Views ordered so each comes after the views its definition mentions.
+/// Views caught in a cycle keep their original order at the end.
+fn ordered_views(views: &[ViewDef]) -> Vec<&ViewDef> {
+    let words = |def: &str| -> HashSet<String> {
+        def.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let mut pending: Vec<(&ViewDef, HashSet<String>)> = views
+        .iter()
+        .map(|v| {
+            let mut deps = words(&v.definition);
+            deps.retain(|w| *w != v.name && views.iter().any(|o| o.name == *w));
+            (v, deps)
+        })
+        .collect();
+    let mut ordered = Vec::with_capacity(views.len());
+    while let Some(i) = pending.iter().position(|(_, deps)| deps.is_empty()) {
+        let (view, _) = pending.remove(i);
+        for (_, deps) in &mut pending {
+            deps.remove(&view.name);
+        }
+        ordered.push(view);
+    }
+    ordered.extend(pending.into_iter().map(|(v, _)| v));
+    ordered
+}
+
+// ---------------------------------------------------------------------------
+// Import
+// ---------------------------------------------------------------------------
+
+/// Read a schema dump from `path` into a snapshot of `schema`.
+pub fn read_file(path: &Path, schema: &str) -> Result<SchemaSnapshot> {
+    from_sql(&std::fs::read_to_string(path)?, schema)
+}
+
+/// Build the snapshot of `schema` that `sql`, a schema-only dump, describes.
+/// Objects qualified with another schema are left out. Fails when no object
+/// of `schema` is recognised, which means the file is not a dump of it (or
+/// only holds statements this parser doesn't read), rather than reading as
+/// an empty schema.
+pub fn from_sql(sql: &str, schema: &str) -> Result<SchemaSnapshot> {
+    let mut parser = DumpParser {
+        schema: schema.to_string(),
+        snapshot: SchemaSnapshot {
+            tables: Vec::new(),
+            views: Vec::new(),
+            indexes: Vec::new(),
+            sequences: Vec::new(),
+            functions: Vec::new(),
+            enums: Vec::new(),
+            constraints: Vec::new(),
+            triggers: Vec::new(),
+            extensions: Vec::new(),
+            distributed_tables: Vec::new(),
+            incomplete: Vec::new(),
+        },
+    };
+    for stmt in split_statements(sql) {
+        let stmt = parser.leading_comments(stmt);
+        if !stmt.is_empty() {
+            parser.statement(stmt);
+        }
+    }
+    let s = &parser.snapshot;
+    if s.tables.is_empty()
+        && s.views.is_empty()
+        && s.indexes.is_empty()
+        && s.sequences.is_empty()
+        && s.functions.is_empty()
+        && s.enums.is_empty()
+        && s.constraints.is_empty()
+        && s.triggers.is_empty()
+        && s.extensions.is_empty()
+        && s.distributed_tables.is_empty()
+    {
+        return Err(WaypointError::SnapshotError {
+            reason: format!(
+                "No objects of schema '{}' recognised in the schema file",
+                schema
+            ),
+        });
+    }
+    Ok(parser.snapshot)
+}
+
+/// The first `max` characters of `s`, for log previews.
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
+struct DumpParser {
+    schema: String,
+    snapshot: SchemaSnapshot,
+}
+
+impl DumpParser {
+    /// Strip the comment lines in front of a statement, picking up the
+    /// trigger comments [`to_sql`] writes.
+    fn leading_comments<'a>(&mut self, mut stmt: &'a str) -> &'a str {
+        static TRIGGER: OnceLock<Regex> = OnceLock::new();
+        let trigger = TRIGGER
+            .get_or_init(|| Regex::new(r"^-- Trigger (\S+) on (\S+): (.+)$").expect("valid regex"));
+        while stmt.starts_with("--") {
+            let (line, rest) = stmt.split_once('\n').unwrap_or((stmt, ""));
+            if let Some(caps) = trigger.captures(line.trim_end()) {
+                self.snapshot.triggers.push(TriggerDef {
+                    schema: self.schema.clone(),
+                    table_name: caps[2].to_string(),
+                    name: caps[1].to_string(),
+                    definition: caps[3].to_string(),
+                });
+            }
+            stmt = rest.trim_start();
+        }
+        stmt
+    }
+
+    fn statement(&mut self, stmt: &str) {
+        let mut c = Cursor::new(stmt);
+        if c.eat(&["create"]) {
+            c.eat(&["or", "replace"]);
+            if c.eat(&["unlogged"]) || c.peek(&["table"]) {
+                if c.eat(&["table"]) {
+                    self.create_table(&mut c);
+                }
+            } else if c.eat(&["unique", "index"]) {
+                self.create_index(&mut c, stmt, true);
+            } else if c.eat(&["index"]) {
+                self.create_index(&mut c, stmt, false);
+            } else if c.eat(&["sequence"]) {
+                self.create_sequence(&mut c);
+            } else if c.eat(&["type"]) {
+                self.create_enum(&mut c);
+            } else if c.eat(&["extension"]) {
+                c.eat(&["if", "not", "exists"]);
+                if let Some(name) = c.ident() {
+                    if name != "plpgsql" && !self.snapshot.extensions.contains(&name) {
+                        self.snapshot.extensions.push(name);
+                    }
+                }
+            } else if c.eat(&["materialized", "view"]) {
+                self.create_view(&mut c, true);
+            } else if c.eat(&["recursive", "view"]) || c.eat(&["view"]) {
+                self.create_view(&mut c, false);
+            } else if c.eat(&["function"]) || c.eat(&["procedure"]) {
+                self.create_function(&mut c, stmt);
+            } else if c.eat(&["constraint", "trigger"]) || c.eat(&["trigger"]) {
+                self.create_trigger(&mut c);
+            }
+        } else if c.eat(&["alter", "table"]) {
+            self.alter_table(&mut c);
+        } else if c.eat(&["select"]) {
+            self.distribution(&mut c);
+        } else {
+            log::debug!(
+                "Skipping statement in schema file; statement={}",
+                truncate(stmt, 60)
+            );
+        }
+    }
+
+    /// A possibly schema-qualified name, `None` if it belongs to another schema.
+    fn local_name(&self, c: &mut Cursor) -> Option<String> {
+        let (schema, name) = c.qualified()?;
+        match schema {
+            Some(s) if s != self.schema => None,
+            _ => Some(name),
+        }
+    }
+
+    fn create_table(&mut self, c: &mut Cursor) {
+        c.eat(&["if", "not", "exists"]);
+        let Some(table) = self.local_name(c) else {
+            return;
+        };
+        let Some(body) = c.group() else {
+            // `PARTITION OF` and `OF type` tables have no column list.
+            return;
+        };
+        let mut columns = Vec::new();
+        let mut primary_key = Vec::new();
+        for element in split_top_level(inner(body)) {
+            let mut e = Cursor::new(element);
+            if TABLE_CONSTRAINT_START.iter().any(|kw| e.peek(&[kw])) {
+                primary_key.extend(self.table_constraint(&table, &mut e, element));
+            } else {
+                primary_key.extend(self.column(&table, &mut columns, &mut e, element));
+            }
+        }
+        for col in &mut columns {
+            if primary_key.contains(&col.name) {
+                col.is_nullable = false;
+            }
+        }
+        self.snapshot.tables.push(TableDef {
+            schema: self.schema.clone(),
+            name: table,
+            columns,
+        });
+    }
+
+    /// Parse a column definition into `columns`. Returns the column's name
+    /// if it is declared `PRIMARY KEY`.
+    fn column(
+        &mut self,
+        table: &str,
+        columns: &mut Vec<ColumnDef>,
+        c: &mut Cursor,
+        raw: &str,
+    ) -> Option<String> {
+        static MARKER: OnceLock<Regex> = OnceLock::new();
+        let marker = MARKER
+            .get_or_init(|| Regex::new(r"/\*\s*(ARRAY|USER-DEFINED)\s*\*/").expect("valid regex"));
+        let name = c.ident()?;
+        let mut type_words = Vec::new();
+        let mut array = false;
+        let mut qualified = false;
+        while let Some(tok) = c.current() {
+            match tok.kind {
+                TokKind::Word if COLUMN_CONSTRAINT_START.contains(&tok.lower().as_str()) => break,
+                TokKind::Word if tok.lower() == "array" => array = true,
+                TokKind::Word | TokKind::Quoted => type_words.push(tok.ident()),
+                TokKind::Group if tok.text.starts_with('[') => array = true,
+                TokKind::Punct if tok.text == "." => qualified = true,
+                _ => {}
+            }
+            c.pos += 1;
+        }
+        let type_name = type_words.join(" ");
+        let serial = match type_name.as_str() {
+            "serial" | "serial4" => Some("integer"),
+            "bigserial" | "serial8" => Some("bigint"),
+            "smallserial" | "serial2" => Some("smallint"),
+            _ => None,
+        };
+        let data_type = match marker.captures(raw) {
+            Some(caps) => caps[1].to_string(),
+            None => normalize_type(&type_words, qualified, array),
+        };
+
+        let mut col = ColumnDef {
+            name: name.clone(),
+            data_type,
+            is_nullable: true,
+            default: None,
+            ordinal_position: columns.len() as i32 + 1,
+        };
+        if let Some(seq_type) = serial {
+            let seq = format!("{}_{}_seq", table, name);
+            col.default = Some(format!("nextval('{}'::regclass)", seq));
+            col.is_nullable = false;
+            self.add_sequence(&seq, seq_type);
+        }
+
+        let mut primary_key = None;
+        let mut constraint_name = None;
+        while let Some(tok) = c.current() {
+            let start = tok.start;
+            if c.eat(&["constraint"]) {
+                constraint_name = c.ident();
+            } else if c.eat(&["not", "null"]) {
+                col.is_nullable = false;
+            } else if c.eat(&["null"]) {
+                col.is_nullable = true;
+            } else if c.eat(&["default"]) {
+                let from = c.offset();
+                c.skip_until(COLUMN_CONSTRAINT_START);
+                col.default = Some(self.default_expr(c.src[from..c.offset()].trim()));
+            } else if c.eat(&["primary", "key"]) {
+                col.is_nullable = false;
+                primary_key = Some(name.clone());
+                let cname = constraint_name
+                    .take()
+                    .unwrap_or_else(|| format!("{}_pkey", table));
+                self.add_key_constraint(table, cname, "PRIMARY KEY", std::slice::from_ref(&name));
+            } else if c.eat(&["unique"]) {
+                let cname = constraint_name
+                    .take()
+                    .unwrap_or_else(|| format!("{}_{}_key", table, name));
+                self.add_key_constraint(table, cname, "UNIQUE", std::slice::from_ref(&name));
+            } else if c.eat(&["references"]) {
+                let cname = constraint_name
+                    .take()
+                    .unwrap_or_else(|| format!("{}_{}_fkey", table, name));
+                c.skip_references();
+                let definition =
+                    format!("FOREIGN KEY ({}) {}", name, c.src[start..c.offset()].trim());
+                self.add_constraint(table, cname, "FOREIGN KEY", definition);
+            } else if c.eat(&["check"]) {
+                let group = c.group().unwrap_or("()");
+                c.eat(&["no", "inherit"]);
+                let cname = constraint_name
+                    .take()
+                    .unwrap_or_else(|| format!("{}_{}_check", table, name));
+                self.add_constraint(table, cname, "CHECK", format!("CHECK ({})", group));
+            } else if c.eat(&["generated"]) {
+                c.skip_until(&["as"]);
+                c.eat(&["as"]);
+                if c.eat(&["identity"]) {
+                    col.is_nullable = false;
+                    let data_type = col.data_type.clone();
+                    self.add_sequence(&format!("{}_{}_seq", table, name), &data_type);
+                }
+                c.group();
+                c.eat(&["stored"]);
+            } else {
+                // COLLATE and anything else this parser doesn't model.
+                c.pos += 1;
+            }
+        }
+        columns.push(col);
+        primary_key
+    }
+
+    /// Parse a table constraint (`CONSTRAINT name ...`, `PRIMARY KEY (...)`).
+    /// Returns the primary key's columns, if it is one.
+    fn table_constraint(&mut self, table: &str, c: &mut Cursor, raw: &str) -> Vec<String> {
+        let name = if c.eat(&["constraint"]) {
+            c.ident()
+        } else {
+            None
+        };
+        let body = raw[c.offset()..].trim();
+        if c.eat(&["primary", "key"]) {
+            let cols = c.group().map(column_list).unwrap_or_default();
+            let name = name.unwrap_or_else(|| format!("{}_pkey", table));
+            self.add_key_constraint(table, name, "PRIMARY KEY", &cols);
+            return cols;
+        }
+        if c.eat(&["unique"]) {
+            c.eat(&["nulls", "not", "distinct"]);
+            let cols = c.group().map(column_list).unwrap_or_default();
+            let name = name.unwrap_or_else(|| format!("{}_{}_key", table, cols.join("_")));
+            self.add_key_constraint(table, name, "UNIQUE", &cols);
+        } else if c.eat(&["foreign", "key"]) {
+            let cols = c.group().map(column_list).unwrap_or_default();
+            let name = name.unwrap_or_else(|| format!("{}_{}_fkey", table, cols.join("_")));
+            self.add_constraint(table, name, "FOREIGN KEY", body.to_string());
+        } else if c.eat(&["check"]) {
+            let name = name.unwrap_or_else(|| format!("{}_check", table));
+            self.add_constraint(table, name, "CHECK", body.to_string());
+        } else if c.eat(&["exclude"]) {
+            // Exclusion constraints aren't in `information_schema`; their
+            // index is.
+            if let Some(name) = name {
+                let definition = format!(
+                    "CREATE INDEX {} ON {}.{} {}",
+                    name,
+                    self.schema,
+                    table,
+                    body["exclude".len()..].trim()
+                );
+                self.add_index(table, &name, definition, false);
+            }
+        }
+        Vec::new()
+    }
+
+    fn alter_table(&mut self, c: &mut Cursor) {
+        c.eat(&["if", "exists"]);
+        c.eat(&["only"]);
+        let Some(table) = self.local_name(c) else {
+            return;
+        };
+        let rest = &c.src[c.offset()..];
+        for action in split_top_level(rest) {
+            let mut a = Cursor::new(action);
+            if a.eat(&["add"]) {
+                if TABLE_CONSTRAINT_START.iter().any(|kw| a.peek(&[kw])) {
+                    let raw = &action[a.offset()..];
+                    let mut constraint = Cursor::new(raw);
+                    let primary_key = self.table_constraint(&table, &mut constraint, raw);
+                    self.set_not_null(&table, &primary_key);
+                } else {
+                    a.eat(&["column"]);
+                    a.eat(&["if", "not", "exists"]);
+                    let raw = &action[a.offset()..];
+                    let Some(idx) = self.snapshot.tables.iter().position(|t| t.name == table)
+                    else {
+                        continue;
+                    };
+                    let mut columns = std::mem::take(&mut self.snapshot.tables[idx].columns);
+                    let primary_key = self.column(&table, &mut columns, &mut Cursor::new(raw), raw);
+                    self.snapshot.tables[idx].columns = columns;
+                    self.set_not_null(&table, &primary_key.into_iter().collect::<Vec<_>>());
+                }
+            } else if a.eat(&["alter"]) {
+                a.eat(&["column"]);
+                let Some(column) = a.ident() else {
+                    continue;
+                };
+                if a.eat(&["set", "default"]) {
+                    let expr = self.default_expr(action[a.offset()..].trim());
+                    self.with_column(&table, &column, |col| col.default = Some(expr));
+                } else if a.eat(&["drop", "default"]) {
+                    self.with_column(&table, &column, |col| col.default = None);
+                } else if a.eat(&["set", "not", "null"]) {
+                    self.set_not_null(&table, std::slice::from_ref(&column));
+                } else if a.eat(&["drop", "not", "null"]) {
+                    self.with_column(&table, &column, |col| col.is_nullable = true);
+                } else if a.eat(&["add", "generated"]) {
+                    let options = action[a.offset()..].to_string();
+                    static SEQUENCE_NAME: OnceLock<Regex> = OnceLock::new();
+                    let seq = SEQUENCE_NAME
+                        .get_or_init(|| {
+                            Regex::new(r"(?i)SEQUENCE\s+NAME\s+([\w.]+)").expect("valid regex")
+                        })
+                        .captures(&options)
+                        .map(|caps| {
+                            let name = &caps[1];
+                            name.rsplit('.').next().unwrap_or(name).to_string()
+                        })
+                        .unwrap_or_else(|| format!("{}_{}_seq", table, column));
+                    let mut data_type = String::from("integer");
+                    self.with_column(&table, &column, |col| {
+                        col.is_nullable = false;
+                        data_type = col.data_type.clone();
+                    });
+                    self.add_sequence(&seq, &data_type);
+                }
+            }
+        }
+    }
+
+    fn create_index(&mut self, c: &mut Cursor, stmt: &str, unique: bool) {
+        c.eat(&["concurrently"]);
+        c.eat(&["if", "not", "exists"]);
+        let Some(name) = c.ident() else {
+            return;
+        };
+        if !c.eat(&["on"]) {
+            return;
+        }
+        c.eat(&["only"]);
+        let Some(table) = self.local_name(c) else {
+            return;
+        };
+        self.add_index(&table, &name, stmt.to_string(), unique);
+    }
+
+    fn create_sequence(&mut self, c: &mut Cursor) {
+        c.eat(&["if", "not", "exists"]);
+        let Some(name) = self.local_name(c) else {
+            return;
+        };
+        let data_type = if c.eat(&["as"]) {
+            let words: Vec<String> = c.current().map(|t| t.ident()).into_iter().collect();
+            normalize_type(&words, false, false)
+        } else {
+            "bigint".to_string()
+        };
+        self.add_sequence(&name, &data_type);
+    }
+
+    fn create_enum(&mut self, c: &mut Cursor) {
+        let Some(name) = self.local_name(c) else {
+            return;
+        };
+        if !c.eat(&["as", "enum"]) {
+            return;
+        }
+        let values = c
+            .group()
+            .map(|g| {
+                split_top_level(inner(g))
+                    .into_iter()
+                    .map(|v| {
+                        let v = v.trim();
+                        v.strip_prefix('\'')
+                            .and_then(|v| v.strip_suffix('\''))
+                            .unwrap_or(v)
+                            .replace("''", "'")
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.snapshot.enums.push(EnumDef {
+            schema: self.schema.clone(),
+            name,
+            values,
+        });
+    }
+
+    fn create_view(&mut self, c: &mut Cursor, is_materialized: bool) {
+        c.eat(&["if", "not", "exists"]);
+        let Some(name) = self.local_name(c) else {
+            return;
+        };
+        if !c.skip_until(&["as"]) {
+            return;
+        }
+        let as_end = c.current().map(|t| t.end).unwrap_or(c.src.len());
+        let body = &c.src[as_end..];
+        let body = body.strip_prefix('\n').unwrap_or(body).trim_end();
+        // pg_dump writes materialized views `WITH NO DATA`.
+        static WITH_DATA: OnceLock<Regex> = OnceLock::new();
+        let body = WITH_DATA
+            .get_or_init(|| Regex::new(r"(?i)\s+WITH\s+(NO\s+)?DATA$").expect("valid regex"))
+            .replace(body, "");
+        let body = if body.starts_with(char::is_whitespace) {
+            body.into_owned()
+        } else {
+            format!(" {}", body)
+        };
+        self.snapshot.views.push(ViewDef {
+            schema: self.schema.clone(),
+            name,
+            definition: format!("{};", body),
+            is_materialized,
+        });
+    }
+
+    fn create_function(&mut self, c: &mut Cursor, stmt: &str) {
+        let Some(name) = self.local_name(c) else {
+            return;
+        };
+        let arguments = c
+            .group()
+            .map(|g| inner(g).trim().to_string())
+            .unwrap_or_default();
+        let mut return_type = String::new();
+        if c.eat(&["returns"]) {
+            let from = c.offset();
+            c.skip_until(&[
+                "language",
+                "as",
+                "immutable",
+                "stable",
+                "volatile",
+                "strict",
+                "security",
+                "parallel",
+                "cost",
+                "rows",
+                "set",
+                "window",
+                "begin",
+                "return",
+            ]);
+            return_type = c.src[from..c.offset()].trim().to_string();
+        }
+        let language = if c.skip_until(&["language"]) {
+            c.eat(&["language"]);
+            c.ident().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        self.snapshot.functions.push(FunctionDef {
+            schema: self.schema.clone(),
+            name,
+            arguments,
+            return_type,
+            language,
+            definition: format!("{}\n", stmt),
+        });
+    }
+
+    fn create_trigger(&mut self, c: &mut Cursor) {
+        let Some(name) = c.ident() else {
+            return;
+        };
+        let mut events = Vec::new();
+        while let Some(tok) = c.current() {
+            if c.eat(&["on"]) {
+                break;
+            }
+            let word = tok.lower();
+            if matches!(word.as_str(), "insert" | "update" | "delete") {
+                events.push(word);
+            }
+            c.pos += 1;
+        }
+        let Some(table) = self.local_name(c) else {
+            return;
+        };
+        if !c.skip_until(&["execute"]) {
+            return;
+        }
+        let definition = c.src[c.offset()..].trim().to_string();
+        // `information_schema.triggers` has a row per event.
+        for _ in 0..events.len().max(1) {
+            self.snapshot.triggers.push(TriggerDef {
+                schema: self.schema.clone(),
+                table_name: table.clone(),
+                name: name.clone(),
+                definition: definition.clone(),
+            });
+        }
+    }
+
+    /// `SELECT create_hypertable(...)` and the Citus distribution calls.
+    fn distribution(&mut self, c: &mut Cursor) {
+        let Some((_, function)) = c.qualified() else {
+            return;
+        };
+        let kind = match function.as_str() {
+            "create_hypertable" => DistributionKind::Hypertable,
+            "create_distributed_table" => DistributionKind::Distributed,
+            "create_reference_table" => DistributionKind::Reference,
+            _ => return,
+        };
+        let args: Vec<String> = c
+            .group()
+            .map(|g| {
+                split_top_level(inner(g))
+                    .into_iter()
+                    .map(|a| {
+                        let a = a.trim();
+                        let a = a.split("::").next().unwrap_or(a);
+                        a.trim_matches('\'').to_string()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let Some(table) = args.first() else {
+            return;
+        };
+        let table = match table.split_once('.') {
+            Some((schema, _)) if schema != self.schema => return,
+            Some((_, name)) => name.to_string(),
+            None => table.clone(),
+        };
+        self.snapshot.distributed_tables.push(DistributedTableDef {
+            name: table,
+            kind,
+            column: match kind {
+                DistributionKind::Reference => None,
+                _ => args.get(1).cloned(),
+            },
+        });
+    }
+
+    /// Introspection shows defaults without this schema's qualifier.
+    fn default_expr(&self, expr: &str) -> String {
+        expr.replace(&format!("nextval('{}.", self.schema), "nextval('")
+    }
+
+    fn add_sequence(&mut self, name: &str, data_type: &str) {
+        if let Some(existing) = self.snapshot.sequences.iter_mut().find(|s| s.name == name) {
+            existing.data_type = data_type.to_string();
+            return;
+        }
+        self.snapshot.sequences.push(SequenceDef {
+            schema: self.schema.clone(),
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+        });
+    }
+
+    fn add_constraint(&mut self, table: &str, name: String, kind: &str, definition: String) {
+        self.snapshot.constraints.push(ConstraintDef {
+            schema: self.schema.clone(),
+            table_name: table.to_string(),
+            name,
+            constraint_type: kind.to_string(),
+            definition,
+        });
+    }
+
+    /// A primary key or unique constraint and the index backing it.
+    fn add_key_constraint(&mut self, table: &str, name: String, kind: &str, columns: &[String]) {
+        let cols = columns.join(", ");
+        self.add_index(
+            table,
+            &name,
+            format!(
+                "CREATE UNIQUE INDEX {} ON {}.{} USING btree ({})",
+                name, self.schema, table, cols
+            ),
+            true,
+        );
+        self.add_constraint(table, name, kind, format!("{} ({})", kind, cols));
+    }
+
+    fn add_index(&mut self, table: &str, name: &str, definition: String, is_unique: bool) {
+        self.snapshot.indexes.push(IndexDef {
+            schema: self.schema.clone(),
+            name: name.to_string(),
+            table_name: table.to_string(),
+            definition,
+            is_unique,
+        });
+    }
+
+    fn with_column(&mut self, table: &str, column: &str, f: impl FnOnce(&mut ColumnDef)) {
+        if let Some(col) = self
+            .snapshot
+            .tables
+            .iter_mut()
+            .filter(|t| t.name == table)
+            .flat_map(|t| t.columns.iter_mut())
+            .find(|c| c.name == column)
+        {
+            f(col);
+        }
+    }
+
+    fn set_not_null(&mut self, table: &str, columns: &[String]) {
+        for column in columns {
+            self.with_column(table, column, |col| col.is_nullable = false);
+        }
+    }
+}
+
+/// Keywords that start a table constraint inside `CREATE TABLE (...)` or
+/// after `ALTER TABLE ... ADD`.
+const TABLE_CONSTRAINT_START: &[&str] = &[
+    "constraint",
+    "primary",
+    "unique",
+    "check",
+    "foreign",
+    "exclude",
+    "like",
+];
+
+/// Keywords that end a column's type or default expression.
+const COLUMN_CONSTRAINT_START: &[&str] = &[
+    "constraint",
+    "not",
+    "null",
+    "default",
+    "primary",
+    "unique",
+    "references",
+    "check",
+    "collate",
+    "generated",
+];
+
+/// The `information_schema.columns.data_type` name of a declared type.
+fn normalize_type(words: &[String], qualified: bool, array: bool) -> String {
+    if array {
+        return "ARRAY".to_string();
+    }
+    // `pg_catalog.int4` and friends are still built-in.
+    let words: Vec<&str> = match words {
+        [schema, rest @ ..] if qualified && schema == "pg_catalog" => {
+            rest.iter().map(String::as_str).collect()
+        }
+        _ if qualified => return "USER-DEFINED".to_string(),
+        _ => words.iter().map(String::as_str).collect(),
+    };
+    let name = words.join(" ");
+    let normalized = match name.as_str() {
+        "int" | "integer" | "int4" | "serial" | "serial4" => "integer",
+        "bigint" | "int8" | "bigserial" | "serial8" => "bigint",
+        "smallint" | "int2" | "smallserial" | "serial2" => "smallint",
+        "varchar" | "character varying" => "character varying",
+        "char" | "character" | "bpchar" => "character",
+        "bool" | "boolean" => "boolean",
+        "float" | "float8" | "double precision" => "double precision",
+        "float4" | "real" => "real",
+        "decimal" | "numeric" => "numeric",
+        "timestamp" | "timestamp without time zone" => "timestamp without time zone",
+        "timestamptz" | "timestamp with time zone" => "timestamp with time zone",
+        "time" | "time without time zone" => "time without time zone",
+        "timetz" | "time with time zone" => "time with time zone",
+        "varbit" | "bit varying" => "bit varying",
+        n if n.starts_with("interval") => "interval",
+        n if BUILTIN_TYPES.contains(&n) => n,
+        _ => "USER-DEFINED",
+    };
+    normalized.to_string()
+}
+
+/// Identifiers in a parenthesized column list.
+fn column_list(group: &str) -> Vec<String> {
+    split_top_level(inner(group))
+        .into_iter()
+        .filter_map(|col| Cursor::new(col).ident())
+        .collect()
+}
+
+/// The text between a group's outer brackets.
+fn inner(group: &str) -> &str {
+    &group[1..group.len().saturating_sub(1).max(1)]
+}
+
+/// Split on commas outside parentheses, quotes and comments.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for tok in tokenize(s) {
+        if tok.kind == TokKind::Punct && tok.text == "," {
+            parts.push(s[start..tok.start].trim());
+            start = tok.end;
+        }
+    }
+    let last = s[start..].trim();
+    if !last.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+// ---------------------------------------------------------------------------
+// Tokenizer
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokKind {
+    /// Unquoted identifier, keyword or number.
+    Word,
+    /// `"quoted identifier"`.
+    Quoted,
+    /// String literal, including dollar-quoted bodies.
+    Str,
+    /// Balanced `(...)` or `[...]`, brackets included.
+    Group,
+    /// Any other character (`,`, `.`, `::`, ...).
+    Punct,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: TokKind,
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+impl Token<'_> {
+    fn lower(&self) -> String {
+        self.text.to_lowercase()
+    }
+
+    /// The identifier this token names: unquoted ones fold to lower case.
+    fn ident(&self) -> String {
+        match self.kind {
+            TokKind::Quoted => self.text[1..self.text.len() - 1].replace("\"\"", "\""),
+            _ => self.lower(),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Vec<Token<'_>> {
+    let bytes = s.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = s[i + 2..].find("*/").map_or(bytes.len(), |p| i + 2 + p + 2);
+                continue;
+            }
+            b'\'' => {
+                i = skip_quoted(bytes, i, b'\'');
+                TokKind::Str
+            }
+            b'"' => {
+                i = skip_quoted(bytes, i, b'"');
+                TokKind::Quoted
+            }
+            b'$' => {
+                let tag_end = s[i + 1..]
+                    .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                    .map(|p| i + 1 + p);
+                match tag_end {
+                    Some(end) if bytes[end] == b'$' => {
+                        let tag = &s[i..=end];
+                        i = s[end + 1..]
+                            .find(tag)
+                            .map_or(bytes.len(), |p| end + 1 + p + tag.len());
+                        TokKind::Str
+                    }
+                    _ => {
+                        i += 1;
+                        TokKind::Punct
+                    }
+                }
+            }
+            b'(' | b'[' => {
+                i = skip_group(s, i);
+                TokKind::Group
+            }
+            b':' if bytes.get(i + 1) == Some(&b':') => {
+                i += 2;
+                TokKind::Punct
+            }
+            b if b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80 => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric()
+                        || bytes[i] == b'_'
+                        || bytes[i] == b'$'
+                        || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+                TokKind::Word
+            }
+            _ => {
+                i += s[i..].chars().next().map_or(1, char::len_utf8);
+                TokKind::Punct
+            }
+        };
+        tokens.push(Token {
+            kind,
+            text: &s[start..i],
+            start,
+            end: i,
+        });
+    }
+    tokens
+}
+
+/// End of the quoted run starting at `start` (doubled quotes escape).
+fn skip_quoted(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// End of the balanced group opening at `start`.
+fn skip_group(s: &str, start: usize) -> usize {
+    let mut depth = 0usize;
+    let inner_tokens = tokenize_flat(s, start);
+    for (i, b) in inner_tokens {
+        match b {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// Bracket positions from `start`, skipping quoted text and comments.
+fn tokenize_flat(s: &str, start: usize) -> impl Iterator<Item = (usize, u8)> + '_ {
+    let bytes = s.as_bytes();
+    let mut i = start;
+    std::iter::from_fn(move || {
+        while i < bytes.len() {
+            let at = i;
+            match bytes[i] {
+                b'\'' | b'"' => i = skip_quoted(bytes, i, bytes[i]),
+                b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                    i = s[i..].find('\n').map_or(bytes.len(), |p| i + p);
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    i = s[i + 2..].find("*/").map_or(bytes.len(), |p| i + 2 + p + 2);
+                }
+                b @ (b'(' | b')' | b'[' | b']') => {
+                    i += 1;
+                    return Some((at, b));
+                }
+                _ => i += 1,
+            }
+        }
+        None
+    })
+}
+
+/// Position in a tokenized statement.
+struct Cursor<'a> {
+    src: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(src: &'a str) -> Self {
+        Cursor {
+            src,
+            tokens: tokenize(src),
+            pos: 0,
+        }
+    }
+
+    fn current(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    /// Byte offset of the current token (end of input when exhausted).
+    fn offset(&self) -> usize {
+        self.current().map_or(self.src.len(), |t| t.start)
+    }
+
+    /// Whether the next tokens are the keywords `kws`.
+    fn peek(&self, kws: &[&str]) -> bool {
+        kws.iter().enumerate().all(|(i, kw)| {
+            self.tokens
+                .get(self.pos + i)
+                .is_some_and(|t| t.kind == TokKind::Word && t.text.eq_ignore_ascii_case(kw))
+        })
+    }
+
+    /// Consume the keywords `kws` if they come next.
+    fn eat(&mut self, kws: &[&str]) -> bool {
+        let matched = self.peek(kws);
+        if matched {
+            self.pos += kws.len();
+        }
+        matched
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        let tok = self.current()?;
+        if !matches!(tok.kind, TokKind::Word | TokKind::Quoted) {
+            return None;
+        }
+        self.pos += 1;
+        Some(tok.ident())
+    }
+
+    /// `name` or `schema.name`.
+    fn qualified(&mut self) -> Option<(Option<String>, String)> {
+        let first = self.ident()?;
+        if self.current().is_some_and(|t| t.text == ".") {
+            self.pos += 1;
+            let second = self.ident()?;
+            return Some((Some(first), second));
+        }
+        Some((None, first))
+    }
+
+    fn group(&mut self) -> Option<&'a str> {
+        let tok = self.current()?;
+        if tok.kind != TokKind::Group {
+            return None;
+        }
+        self.pos += 1;
+        Some(tok.text)
+    }
+
+    /// Advance to the next of the keywords `kws`, returning whether one was
+    /// found.
+    fn skip_until(&mut self, kws: &[&str]) -> bool {
+        while let Some(tok) = self.current() {
+            if tok.kind == TokKind::Word && kws.contains(&tok.lower().as_str()) {
+                return true;
+            }
+            self.pos += 1;
+        }
+        false
+    }
+
+    /// Skip the rest of a `REFERENCES table (cols) ...` clause.
+    fn skip_references(&mut self) {
+        self.qualified();
+        self.group();
+        loop {
+            if self.eat(&["match"]) || self.eat(&["initially"]) {
+                self.pos += 1;
+            } else if self.eat(&["on"]) {
+                self.pos += 1; // DELETE / UPDATE
+                if !(self.eat(&["set", "null"])
+                    || self.eat(&["set", "default"])
+                    || self.eat(&["no", "action"]))
+                {
+                    self.pos += 1;
+                }
+                self.group();
+            } else if !(self.eat(&["deferrable"]) || self.eat(&["not", "deferrable"])) {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema;
+    use std::collections::BTreeSet;
+
+    const PG_DUMP: &str = r#"--
+-- PostgreSQL database dump
+--
+
+SET statement_timeout = 0;
+SELECT pg_catalog.set_config('search_path', '', false);
+
+CREATE EXTENSION IF NOT EXISTS pgcrypto WITH SCHEMA public;
+
+CREATE TYPE public.mood AS ENUM (
+    'happy',
+    'it''s fine'
+);
+
+ALTER TYPE public.mood OWNER TO app;
+
+CREATE FUNCTION public.touch() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $$
+BEGIN
+  NEW.updated_at := now();
+  RETURN NEW;
+END;
+$$;
+
+SET default_tablespace = '';
+
+CREATE TABLE public.users (
+    id integer NOT NULL,
+    email character varying(255) NOT NULL,
+    tags text[],
+    mood public.mood DEFAULT 'happy'::public.mood,
+    balance numeric(10,2) DEFAULT 0,
+    updated_at timestamp with time zone DEFAULT now()
+);
+
+CREATE SEQUENCE public.users_id_seq
+    AS integer
+    START WITH 1
+    INCREMENT BY 1
+    CACHE 1;
+
+ALTER SEQUENCE public.users_id_seq OWNED BY public.users.id;
+
+CREATE TABLE public.orders (
+    id bigint NOT NULL,
+    user_id integer
+);
+
+ALTER TABLE public.orders ALTER COLUMN id ADD GENERATED ALWAYS AS IDENTITY (
+    SEQUENCE NAME public.orders_id_seq
+    START WITH 1
+);
+
+CREATE TABLE audit.log (id integer);
+
+CREATE VIEW public.active_users AS
+ SELECT users.id,
+    users.email
+   FROM public.users
+  WHERE (users.mood = 'happy'::public.mood);
+
+ALTER TABLE ONLY public.users ALTER COLUMN id SET DEFAULT nextval('public.users_id_seq'::regclass);
+
+ALTER TABLE ONLY public.users
+    ADD CONSTRAINT users_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.users
+    ADD CONSTRAINT users_email_key UNIQUE (email);
+
+CREATE INDEX users_updated_idx ON public.users USING btree (updated_at);
+
+CREATE TRIGGER users_touch BEFORE INSERT OR UPDATE ON public.users FOR EACH ROW EXECUTE FUNCTION public.touch();
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_user_id_fkey FOREIGN KEY (user_id) REFERENCES public.users(id) ON DELETE SET NULL;
+
+GRANT SELECT ON TABLE public.users TO reporting;
+"#;
+
+    fn column<'a>(snapshot: &'a SchemaSnapshot, table: &str, name: &str) -> &'a ColumnDef {
+        snapshot
+            .tables
+            .iter()
+            .find(|t| t.name == table)
+            .and_then(|t| t.columns.iter().find(|c| c.name == name))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_from_sql_reads_pg_dump() {
+        let s = from_sql(PG_DUMP, "public").unwrap();
+
+        assert_eq!(s.extensions, vec!["pgcrypto"]);
+        assert_eq!(s.enums[0].values, vec!["happy", "it's fine"]);
+        let tables: Vec<&str> = s.tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tables, vec!["users", "orders"], "other schemas are skipped");
+
+        let id = column(&s, "users", "id");
+        assert_eq!(id.data_type, "integer");
+        assert!(!id.is_nullable);
+        assert_eq!(
+            id.default.as_deref(),
+            Some("nextval('users_id_seq'::regclass)")
+        );
+        assert_eq!(column(&s, "users", "email").data_type, "character varying");
+        assert_eq!(column(&s, "users", "tags").data_type, "ARRAY");
+        assert_eq!(column(&s, "users", "mood").data_type, "USER-DEFINED");
+        assert_eq!(column(&s, "users", "balance").data_type, "numeric");
+        let updated = column(&s, "users", "updated_at");
+        assert_eq!(updated.data_type, "timestamp with time zone");
+        assert_eq!(updated.default.as_deref(), Some("now()"));
+        assert_eq!(updated.ordinal_position, 6);
+
+        let sequences: Vec<(&str, &str)> = s
+            .sequences
+            .iter()
+            .map(|q| (q.name.as_str(), q.data_type.as_str()))
+            .collect();
+        assert_eq!(
+            sequences,
+            vec![("users_id_seq", "integer"), ("orders_id_seq", "bigint")]
+        );
+
+        let constraints: Vec<(&str, &str)> = s
+            .constraints
+            .iter()
+            .map(|c| (c.name.as_str(), c.constraint_type.as_str()))
+            .collect();
+        assert_eq!(
+            constraints,
+            vec![
+                ("users_pkey", "PRIMARY KEY"),
+                ("users_email_key", "UNIQUE"),
+                ("orders_user_id_fkey", "FOREIGN KEY"),
+            ]
+        );
+        let indexes: Vec<&str> = s.indexes.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(
+            indexes,
+            vec!["users_pkey", "users_email_key", "users_updated_idx"]
+        );
+
+        assert_eq!(s.views[0].name, "active_users");
+        assert!(s.views[0].definition.starts_with(" SELECT users.id,\n"));
+        assert!(s.views[0].definition.ends_with("::public.mood);"));
+        assert_eq!(s.functions[0].name, "touch");
+        assert_eq!(s.functions[0].return_type, "trigger");
+        assert_eq!(s.functions[0].language, "plpgsql");
+        assert_eq!(s.triggers.len(), 2, "one row per event");
+        assert_eq!(s.triggers[0].definition, "EXECUTE FUNCTION public.touch()");
+    }
+
+    #[test]
+    fn test_from_sql_rejects_unrecognised_input() {
+        assert!(from_sql("", "public").is_err());
+        assert!(from_sql("SELECT 1; GRANT USAGE ON SCHEMA public TO app;", "public").is_err());
+        // Objects of other schemas only.
+        assert!(from_sql("CREATE TABLE other.t (id int);", "public").is_err());
+        // A skipped statement with multibyte text around the preview cut.
+        let s = from_sql(
+            &format!(
+                "COMMENT ON TABLE t IS '{}';\nCREATE TABLE t (id int);",
+                "é".repeat(40)
+            ),
+            "public",
+        )
+        .unwrap();
+        assert_eq!(s.tables[0].name, "t");
+        assert_eq!(truncate("ééé", 2), "éé");
+        assert_eq!(truncate("ab", 60), "ab");
+    }
+
+    #[test]
+    fn test_from_sql_inline_constraints_and_serials() {
+        let s = from_sql(
+            "CREATE TABLE IF NOT EXISTS \"Accounts\" (
+                id bigserial PRIMARY KEY,
+                code varchar(8) UNIQUE NOT NULL,
+                parent_id bigint REFERENCES \"Accounts\" (id) ON DELETE SET NULL,
+                qty int CHECK (qty > 0) DEFAULT 1,
+                UNIQUE (code, parent_id)
+             );
+             ALTER TABLE \"Accounts\" ADD COLUMN note text;",
+            "public",
+        )
+        .unwrap();
+        let t = &s.tables[0];
+        assert_eq!(t.name, "Accounts");
+        let names: Vec<&str> = t.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "code", "parent_id", "qty", "note"]);
+        assert_eq!(t.columns[0].data_type, "bigint");
+        assert_eq!(
+            t.columns[0].default.as_deref(),
+            Some("nextval('Accounts_id_seq'::regclass)")
+        );
+        assert!(!t.columns[1].is_nullable);
+        assert!(t.columns[2].is_nullable);
+        assert_eq!(t.columns[3].default.as_deref(), Some("1"));
+        assert_eq!(t.columns[4].ordinal_position, 5);
+
+        let constraints: Vec<&str> = s.constraints.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            constraints,
+            vec![
+                "Accounts_pkey",
+                "Accounts_code_key",
+                "Accounts_parent_id_fkey",
+                "Accounts_qty_check",
+                "Accounts_code_parent_id_key",
+            ]
+        );
+        assert_eq!(s.sequences[0].name, "Accounts_id_seq");
+    }
+
+    #[test]
+    fn test_to_sql_round_trips_and_orders_dependencies() {
+        let mut expected = from_sql(PG_DUMP, "public").unwrap();
+        expected.views.insert(
+            0,
+            ViewDef {
+                schema: "public".into(),
+                name: "happy_emails".into(),
+                definition: " SELECT active_users.email\n   FROM active_users;".into(),
+                is_materialized: true,
+            },
+        );
+        expected.distributed_tables.push(DistributedTableDef {
+            name: "orders".into(),
+            kind: DistributionKind::Distributed,
+            column: Some("user_id".into()),
+        });
+
+        let sql = to_sql(&expected, "public");
+        let pos = |needle: &str| sql.find(needle).unwrap_or_else(|| panic!("{}", needle));
+        assert!(pos("CREATE TYPE \"mood\"") < pos("CREATE TABLE \"users\""));
+        assert!(pos("CREATE SEQUENCE \"users_id_seq\"") < pos("CREATE TABLE \"users\""));
+        assert!(pos("CREATE VIEW \"active_users\"") < pos("MATERIALIZED VIEW \"happy_emails\""));
+        assert!(
+            pos("ADD CONSTRAINT \"users_pkey\"") < pos("ADD CONSTRAINT \"orders_user_id_fkey\"")
+        );
+        assert!(sql.contains("-- Name: users; Type: TABLE; Schema: public\n"));
+        assert!(sql.contains("\"mood\" text /* USER-DEFINED */ DEFAULT 'happy'::public.mood,"));
+        assert!(!sql.contains("CREATE UNIQUE INDEX users_pkey"));
+        assert!(sql.contains("SELECT create_distributed_table('orders', 'user_id');"));
+
+        let actual = from_sql(&sql, "public").unwrap();
+        assert!(schema::diff(&expected, &actual).is_empty());
+        assert_eq!(expected.tables, actual.tables);
+        assert_eq!(expected.enums, actual.enums);
+        assert_eq!(expected.triggers, actual.triggers);
+        assert_eq!(expected.distributed_tables, actual.distributed_tables);
+        let names = |s: &SchemaSnapshot| -> BTreeSet<String> {
+            s.indexes.iter().map(|i| i.name.clone()).collect()
+        };
+        assert_eq!(names(&expected), names(&actual));
+    }
+}
@@ -0,0 +1,83 @@
+//! `tracing` spans around the command pipeline.
+//!
+//! `migrate`, `undo` and `simulate` open a span for the run, with child spans
+//! per migration, hook, guard and, when statements run one at a time
+//! (`statement_progress`, `-- waypoint:no-transaction`), per statement.
+//! A failure is recorded as an `ERROR` event inside the failing span, as
+//! `#[instrument(err)]` does, which OpenTelemetry exporters turn into the
+//! span's error status and an exception event. The library never installs a
+//! subscriber, so without one the spans cost next to nothing; the CLI's
+//! `otel` feature exports them over OTLP.
+
+use std::fmt::Display;
+use std::future::Future;
+
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+use crate::migration::ResolvedMigration;
+
+/// Span for applying one migration.
+pub(crate) fn migration_span(migration: &ResolvedMigration) -> Span {
+    tracing::info_span!(
+        "migration",
+        script = %migration.script,
+        version = migration.version().map(|v| v.raw.as_str()),
+        rows = Empty,
+    )
+}
+
+/// Span for reverting one version.
+pub(crate) fn undo_span(version: &str, script: &str) -> Span {
+    tracing::info_span!("undo_migration", version = version, script = script)
+}
+
+/// Span for one hook script.
+pub(crate) fn hook_span(phase: &dyn Display, script: &str) -> Span {
+    tracing::info_span!("hook", phase = %phase, script = script)
+}
+
+/// Span for one statement of a migration run statement by statement.
+pub(crate) fn statement_span(script: &str, index: i32, total: usize) -> Span {
+    tracing::info_span!(
+        "statement",
+        script = script,
+        index = index,
+        total = total,
+        rows = Empty,
+    )
+}
+
+/// Run `fut` inside `span`, recording an error on the span if it fails.
+pub(crate) async fn traced<T, E: Display>(
+    span: Span,
+    fut: impl Future<Output = std::result::Result<T, E>>,
+) -> std::result::Result<T, E> {
+    let result = fut.instrument(span.clone()).await;
+    if let Err(e) = &result {
+        span.in_scope(|| tracing::error!(error = %e));
+    }
+    result
+}
+
+/// Evaluate a `require` / `ensure` guard in its own span, recording whether
+/// it passed.
+pub(crate) async fn guard<E: Display>(
+    kind: &str,
+    script: &str,
+    expression: &str,
+    fut: impl Future<Output = std::result::Result<bool, E>>,
+) -> std::result::Result<bool, E> {
+    let span = tracing::info_span!(
+        "guard",
+        kind = kind,
+        script = script,
+        expression = expression,
+        passed = Empty,
+    );
+    let result = traced(span.clone(), fut).await;
+    if let Ok(passed) = &result {
+        span.record("passed", passed);
+    }
+    result
+}
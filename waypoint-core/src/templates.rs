@@ -0,0 +1,222 @@
+//! Vetted migration templates for `waypoint new --template`.
+//!
+//! Each template is a PostgreSQL migration that already follows the safe
+//! pattern for its change (`lock_timeout` via `-- waypoint:set`, `NOT VALID`
+//! constraints, `CONCURRENTLY` outside a transaction, batched backfills),
+//! embedded from `waypoint-core/templates/new/`. Parameters are written
+//! `{{name}}` in the files and filled from [`TemplateParam`] values; the CLI
+//! prompts for any not given with `--set`.
+
+use std::collections::BTreeMap;
+
+/// A value a template needs filled in.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateParam {
+    /// Name used as `{{name}}` in the template and as `--set name=...`.
+    pub name: &'static str,
+    /// Question asked when prompting for the value.
+    pub prompt: &'static str,
+    /// Used when no value is given.
+    pub default: Option<&'static str>,
+}
+
+/// A built-in migration template.
+#[derive(Debug, Clone, Copy)]
+pub struct Template {
+    /// Name given to `--template`.
+    pub name: &'static str,
+    /// One-line description.
+    pub summary: &'static str,
+    /// Values to fill in, in prompt order.
+    pub params: &'static [TemplateParam],
+    migration: &'static str,
+    undo: Option<&'static str>,
+}
+
+const fn param(name: &'static str, prompt: &'static str) -> TemplateParam {
+    TemplateParam {
+        name,
+        prompt,
+        default: None,
+    }
+}
+
+const TABLE: TemplateParam = param("table", "Table (optionally schema-qualified)");
+
+/// The built-in templates.
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "add-column",
+        summary: "Add a nullable column under a lock timeout, with the NOT VALID path to NOT NULL",
+        params: &[
+            TABLE,
+            param("column", "Column name"),
+            TemplateParam {
+                name: "type",
+                prompt: "Column type",
+                default: Some("text"),
+            },
+        ],
+        migration: include_str!("../templates/new/add-column.sql"),
+        undo: Some(include_str!("../templates/new/add-column.undo.sql")),
+    },
+    Template {
+        name: "create-table",
+        summary: "Create a table with an identity key and timestamps",
+        params: &[TABLE],
+        migration: include_str!("../templates/new/create-table.sql"),
+        undo: Some(include_str!("../templates/new/create-table.undo.sql")),
+    },
+    Template {
+        name: "add-index-concurrently",
+        summary: "Build an index without blocking writes, safe to re-run after a failed build",
+        params: &[
+            TABLE,
+            param("columns", "Indexed columns, comma-separated"),
+            param("index", "Index name"),
+        ],
+        migration: include_str!("../templates/new/add-index-concurrently.sql"),
+        undo: Some(include_str!(
+            "../templates/new/add-index-concurrently.undo.sql"
+        )),
+    },
+    Template {
+        name: "backfill",
+        summary: "Fill a column in committed batches, resuming where a failed run stopped",
+        params: &[
+            TABLE,
+            param("column", "Column to fill"),
+            param("expression", "Value to set it to (SQL expression)"),
+            TemplateParam {
+                name: "batch_size",
+                prompt: "Rows per batch",
+                default: Some("5000"),
+            },
+        ],
+        migration: include_str!("../templates/new/backfill.sql"),
+        undo: None,
+    },
+];
+
+/// Look up a built-in template by name.
+pub fn find(name: &str) -> Option<&'static Template> {
+    TEMPLATES.iter().find(|t| t.name == name)
+}
+
+/// A template rendered with its values.
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    /// The migration body.
+    pub migration: String,
+    /// The undo body, if the template has one.
+    pub undo: Option<String>,
+    /// Parameters with no value or default, left as `{{name}}`.
+    pub unfilled: Vec<String>,
+}
+
+impl Template {
+    /// Fill in `title` and the template's parameters from `values`, falling
+    /// back to each parameter's default.
+    pub fn render(&self, title: &str, values: &BTreeMap<String, String>) -> Rendered {
+        let mut unfilled = Vec::new();
+        let mut fill = |text: &str| {
+            let mut out = text.replace("{{title}}", title);
+            for p in self.params {
+                let value = values.get(p.name).map(String::as_str).or(p.default);
+                match value {
+                    Some(v) => out = out.replace(&format!("{{{{{}}}}}", p.name), v),
+                    None => {
+                        if !unfilled.iter().any(|u| u == p.name) {
+                            unfilled.push(p.name.to_string());
+                        }
+                    }
+                }
+            }
+            out
+        };
+        let migration = fill(self.migration);
+        let undo = self.undo.map(&mut fill);
+        Rendered {
+            migration,
+            undo,
+            unfilled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_declare_every_parameter() {
+        for t in TEMPLATES {
+            let values: BTreeMap<String, String> = t
+                .params
+                .iter()
+                .map(|p| (p.name.to_string(), "x".to_string()))
+                .collect();
+            let rendered = t.render("Title", &values);
+            assert!(rendered.unfilled.is_empty());
+            for body in std::iter::once(&rendered.migration).chain(rendered.undo.as_ref()) {
+                assert!(
+                    !body.contains("{{"),
+                    "{} leaves a parameter unfilled",
+                    t.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_defaults_and_unfilled() {
+        let t = find("backfill").unwrap();
+        let values = BTreeMap::from([("table".to_string(), "users".to_string())]);
+        let rendered = t.render("Backfill emails", &values);
+        assert!(rendered.migration.starts_with("-- Backfill emails\n"));
+        assert!(rendered.migration.contains("LIMIT 5000"));
+        assert!(rendered.migration.contains("SELECT ctid FROM users"));
+        assert_eq!(rendered.unfilled, vec!["column", "expression"]);
+        assert!(rendered.migration.contains("{{expression}}"));
+        assert!(rendered.undo.is_none());
+    }
+
+    #[test]
+    fn test_templates_lint_clean() {
+        use crate::commands::lint;
+        use crate::config::LintConfig;
+        use crate::migration::ScanLimits;
+
+        let dir = tempfile::tempdir().unwrap();
+        for (i, t) in TEMPLATES.iter().enumerate() {
+            let values: BTreeMap<String, String> = t
+                .params
+                .iter()
+                .map(|p| (p.name.to_string(), format!("{}_value", p.name)))
+                .collect();
+            let rendered = t.render(t.summary, &values);
+            let version = i + 1;
+            std::fs::write(
+                dir.path()
+                    .join(format!("V{}__{}.sql", version, t.name.replace('-', "_"))),
+                &rendered.migration,
+            )
+            .unwrap();
+            if let Some(undo) = &rendered.undo {
+                std::fs::write(dir.path().join(format!("U{}__undo.sql", version)), undo).unwrap();
+            }
+        }
+        let report = lint::execute(
+            &[dir.path().to_path_buf()],
+            &ScanLimits::default(),
+            &LintConfig::default(),
+        )
+        .unwrap();
+        let found: Vec<_> = report
+            .issues
+            .iter()
+            .map(|i| format!("{} {}", i.rule_id, i.script))
+            .collect();
+        assert!(found.is_empty(), "{:?}", found);
+    }
+}
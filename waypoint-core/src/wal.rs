@@ -0,0 +1,600 @@
+//! WAL volume estimation for pending migrations (PostgreSQL).
+//!
+//! Table rewrites, index builds, and large backfills can generate WAL on the
+//! order of the table's size. On a server with little disk headroom, or with
+//! an inactive replication slot pinning WAL, that is enough to fill the disk.
+//! This module estimates WAL per statement from table-size heuristics and
+//! provides a pre-flight check comparing the total against the configured
+//! budget, `max_wal_size`, and replication-slot retention. `migrate` runs it
+//! before applying anything, and `waypoint preflight` reports it for the
+//! pending migrations. Estimates use the SQL as it will run, placeholders
+//! replaced.
+//!
+//! The heuristics are deliberately coarse:
+//!
+//! | Statement | Estimated WAL |
+//! |---|---|
+//! | `ALTER COLUMN ... TYPE`, `VACUUM FULL`, `CLUSTER`, `SET LOGGED`, volatile or pre-11 `ADD COLUMN ... DEFAULT` | heap + indexes |
+//! | `UPDATE` | heap + indexes |
+//! | `CREATE INDEX`, `ADD PRIMARY KEY` / `UNIQUE`, `REINDEX` | ¼ of heap |
+//! | `DELETE` | ¼ of heap |
+//! | anything else | 0 |
+
+use std::sync::LazyLock;
+
+use regex_lite::Regex;
+use serde::Serialize;
+
+#[cfg(feature = "postgres")]
+use std::collections::HashMap;
+#[cfg(feature = "postgres")]
+use tokio_postgres::Client;
+
+#[cfg(feature = "postgres")]
+use crate::config::WaypointConfig;
+use crate::dialect::PgVersion;
+#[cfg(feature = "postgres")]
+use crate::error::{Result, WaypointError};
+#[cfg(feature = "postgres")]
+use crate::migration::ResolvedMigration;
+#[cfg(feature = "postgres")]
+use crate::preflight::{CheckStatus, PreflightCheck, PreflightConfig};
+
+/// How a statement generates WAL relative to the size of its table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalCost {
+    /// Rewrites the heap and rebuilds every index.
+    Rewrite,
+    /// Builds one index over the table.
+    IndexBuild,
+    /// Writes a new version of (potentially) every row.
+    Backfill,
+    /// Marks (potentially) every row deleted.
+    Delete,
+}
+
+impl WalCost {
+    /// Estimated WAL bytes given the table's heap and total index size.
+    pub fn estimate_bytes(&self, heap_bytes: i64, index_bytes: i64) -> i64 {
+        match self {
+            WalCost::Rewrite | WalCost::Backfill => heap_bytes + index_bytes,
+            WalCost::IndexBuild | WalCost::Delete => heap_bytes / 4,
+        }
+    }
+}
+
+/// Estimated WAL for one statement.
+#[derive(Debug, Clone, Serialize)]
+pub struct WalStatementEstimate {
+    /// First 120 characters of the statement.
+    pub statement_preview: String,
+    /// Table the statement writes to.
+    pub table: String,
+    /// Which heuristic applied.
+    pub cost: WalCost,
+    /// Estimated WAL in bytes.
+    pub estimated_bytes: i64,
+}
+
+/// Estimated WAL for a set of migrations.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WalEstimate {
+    /// Statements expected to generate significant WAL.
+    pub statements: Vec<WalStatementEstimate>,
+    /// Sum of all statement estimates in bytes.
+    pub total_bytes: i64,
+}
+
+/// An identifier, plain or double-quoted (`""` escapes a quote).
+const IDENT: &str = r#"(?:"(?:[^"]|"")+"|\w+)"#;
+
+/// An optionally schema-qualified table: captures schema and table.
+const TABLE: &str = r#"(?:("(?:[^"]|"")+"|\w+)\.)?("(?:[^"]|"")+"|\w+)"#;
+
+/// Build a statement regex, expanding `{IDENT}` and `{TABLE}`.
+fn statement_re(pattern: &str) -> Regex {
+    Regex::new(&pattern.replace("{IDENT}", IDENT).replace("{TABLE}", TABLE)).unwrap()
+}
+
+static ALTER_TYPE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    statement_re(
+        r"(?is)^ALTER\s+TABLE\s+(?:ONLY\s+)?(?:IF\s+EXISTS\s+)?{TABLE}\s.*\bALTER\s+(?:COLUMN\s+)?{IDENT}\s+(?:SET\s+DATA\s+)?TYPE\b",
+    )
+});
+
+static SET_LOGGED_RE: LazyLock<Regex> = LazyLock::new(|| {
+    statement_re(r"(?is)^ALTER\s+TABLE\s+(?:IF\s+EXISTS\s+)?{TABLE}\s+SET\s+LOGGED\b")
+});
+
+static ADD_COLUMN_DEFAULT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    statement_re(
+        r"(?is)^ALTER\s+TABLE\s+(?:ONLY\s+)?(?:IF\s+EXISTS\s+)?{TABLE}\s+ADD\s+(?:COLUMN\s+)?.*\bDEFAULT\s+(.*)$",
+    )
+});
+
+pub(crate) static VOLATILE_DEFAULT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(random|gen_random_uuid|uuid_generate_v[14]|clock_timestamp|nextval|timeofday)\s*\(").unwrap()
+});
+
+static ADD_INDEXED_CONSTRAINT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    statement_re(
+        r"(?is)^ALTER\s+TABLE\s+(?:ONLY\s+)?(?:IF\s+EXISTS\s+)?{TABLE}\s+ADD\s+(?:CONSTRAINT\s+{IDENT}\s+)?(?:PRIMARY\s+KEY|UNIQUE)\s*\(",
+    )
+});
+
+static CREATE_INDEX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    statement_re(
+        r"(?is)^CREATE\s+(?:UNIQUE\s+)?INDEX\s+(?:CONCURRENTLY\s+)?(?:IF\s+NOT\s+EXISTS\s+)?(?:{IDENT}\s+)?ON\s+(?:ONLY\s+)?{TABLE}",
+    )
+});
+
+static REINDEX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    statement_re(r"(?is)^REINDEX\s+(?:\([^)]*\)\s+)?TABLE\s+(?:CONCURRENTLY\s+)?{TABLE}")
+});
+
+static VACUUM_FULL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    statement_re(r"(?is)^VACUUM\s+(?:\([^)]*\bFULL\b[^)]*\)|FULL\b)\s*(?:\w+\s+)*?{TABLE}\s*$")
+});
+
+static CLUSTER_RE: LazyLock<Regex> =
+    LazyLock::new(|| statement_re(r"(?is)^CLUSTER\s+(?:VERBOSE\s+)?{TABLE}"));
+
+static UPDATE_RE: LazyLock<Regex> =
+    LazyLock::new(|| statement_re(r"(?is)^UPDATE\s+(?:ONLY\s+)?{TABLE}\s+SET\b"));
+
+static DELETE_RE: LazyLock<Regex> =
+    LazyLock::new(|| statement_re(r"(?is)^DELETE\s+FROM\s+(?:ONLY\s+)?{TABLE}"));
+
+/// Classify a single SQL statement's WAL cost.
+///
+/// Returns `(schema, table, cost)` for statements expected to write WAL in
+/// proportion to a table's size, or `None` for everything else. Names are
+/// returned as PostgreSQL resolves them: quoted ones verbatim, unquoted ones
+/// folded to lower case. `version` decides whether `ADD COLUMN ... DEFAULT`
+/// rewrites (it does before PG 11, and always for volatile defaults).
+pub fn classify_statement(
+    stmt: &str,
+    version: Option<PgVersion>,
+) -> Option<(Option<String>, String, WalCost)> {
+    let stmt = strip_leading_comments(stmt);
+    let hit = |caps: regex_lite::Captures<'_>, cost| {
+        Some((
+            caps.get(1).map(|m| resolve_ident(m.as_str())),
+            resolve_ident(caps.get(2)?.as_str()),
+            cost,
+        ))
+    };
+
+    if let Some(caps) = ALTER_TYPE_RE.captures(stmt) {
+        return hit(caps, WalCost::Rewrite);
+    }
+    if let Some(caps) = SET_LOGGED_RE.captures(stmt) {
+        return hit(caps, WalCost::Rewrite);
+    }
+    if let Some(caps) = ADD_INDEXED_CONSTRAINT_RE.captures(stmt) {
+        return hit(caps, WalCost::IndexBuild);
+    }
+    if let Some(caps) = ADD_COLUMN_DEFAULT_RE.captures(stmt) {
+        let volatile = caps
+            .get(3)
+            .is_some_and(|d| VOLATILE_DEFAULT_RE.is_match(d.as_str()));
+        let fast_default = version.is_none_or(|v| v.has_fast_column_default());
+        if volatile || !fast_default {
+            return hit(caps, WalCost::Rewrite);
+        }
+        return None;
+    }
+    if let Some(caps) = CREATE_INDEX_RE.captures(stmt) {
+        return hit(caps, WalCost::IndexBuild);
+    }
+    if let Some(caps) = REINDEX_RE.captures(stmt) {
+        return hit(caps, WalCost::IndexBuild);
+    }
+    if let Some(caps) = VACUUM_FULL_RE.captures(stmt) {
+        return hit(caps, WalCost::Rewrite);
+    }
+    if let Some(caps) = CLUSTER_RE.captures(stmt) {
+        return hit(caps, WalCost::Rewrite);
+    }
+    if let Some(caps) = UPDATE_RE.captures(stmt) {
+        return hit(caps, WalCost::Backfill);
+    }
+    if let Some(caps) = DELETE_RE.captures(stmt) {
+        return hit(caps, WalCost::Delete);
+    }
+    None
+}
+
+/// The name an identifier refers to: a quoted one unquoted, an unquoted one
+/// folded to lower case.
+fn resolve_ident(ident: &str) -> String {
+    match ident
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => ident.to_lowercase(),
+    }
+}
+
+/// Skip `--` comment lines and blank lines before the statement body.
+pub(crate) fn strip_leading_comments(stmt: &str) -> &str {
+    let mut rest = stmt.trim_start();
+    while let Some(after) = rest.strip_prefix("--") {
+        rest = after.split_once('\n').map_or("", |(_, r)| r).trim_start();
+    }
+    rest
+}
+
+/// Estimate WAL for a list of migration scripts (PostgreSQL).
+///
+/// Table and index sizes come from `pg_table_size` / `pg_indexes_size`.
+/// Tables that don't exist yet (e.g. created earlier in the same run) count
+/// as empty.
+#[cfg(feature = "postgres")]
+pub async fn estimate_wal(client: &Client, schema: &str, scripts: &[&str]) -> Result<WalEstimate> {
+    let version = crate::db::get_server_version(client).await.ok();
+    let mut sizes: HashMap<(String, String), (i64, i64)> = HashMap::new();
+    let mut estimate = WalEstimate::default();
+
+    for sql in scripts {
+        for stmt in crate::sql_parser::split_statements(sql) {
+            let Some((stmt_schema, table, cost)) = classify_statement(stmt, version) else {
+                continue;
+            };
+            let key = (stmt_schema.unwrap_or_else(|| schema.to_string()), table);
+            let (heap, indexes) = match sizes.get(&key) {
+                Some(s) => *s,
+                None => {
+                    let s = relation_sizes(client, &key.0, &key.1).await?;
+                    sizes.insert(key.clone(), s);
+                    s
+                }
+            };
+            let bytes = cost.estimate_bytes(heap, indexes);
+            if bytes == 0 {
+                continue;
+            }
+            estimate.total_bytes += bytes;
+            estimate.statements.push(WalStatementEstimate {
+                statement_preview: strip_leading_comments(stmt).chars().take(120).collect(),
+                table: key.1,
+                cost,
+                estimated_bytes: bytes,
+            });
+        }
+    }
+
+    Ok(estimate)
+}
+
+/// Heap and total index size of a table in bytes; `(0, 0)` if it doesn't exist.
+#[cfg(feature = "postgres")]
+async fn relation_sizes(client: &Client, schema: &str, table: &str) -> Result<(i64, i64)> {
+    let row = client
+        .query_opt(
+            "SELECT pg_table_size(c.oid), pg_indexes_size(c.oid)
+             FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = $1 AND c.relname = $2 AND c.relkind IN ('r', 'p', 'm')",
+            &[&schema, &table],
+        )
+        .await?;
+    Ok(row.map_or((0, 0), |r| (r.get(0), r.get(1))))
+}
+
+/// Compare an estimate against WAL headroom (PostgreSQL).
+///
+/// - With `wal_budget_mb` set, current `pg_wal` usage plus the estimate must
+///   fit within the budget: over budget fails, over 80% warns.
+/// - Without a budget, an estimate above `max_wal_size` warns (the migration
+///   will force extra checkpoints and WAL can grow past the soft limit).
+/// - Inactive replication slots warn in both cases, because they pin every
+///   byte of WAL the migration generates until they are consumed or dropped.
+#[cfg(feature = "postgres")]
+pub async fn check_wal_headroom(
+    client: &Client,
+    config: &PreflightConfig,
+    estimate: &WalEstimate,
+) -> PreflightCheck {
+    const MB: i64 = 1024 * 1024;
+    let version = crate::db::get_server_version(client).await.ok();
+    let modern = version.is_none_or(|v| v.has_wal_functions());
+    let estimated_mb = estimate.total_bytes / MB;
+
+    let max_wal_size: Option<i64> = client
+        .query_one("SELECT pg_size_bytes(current_setting('max_wal_size'))", &[])
+        .await
+        .ok()
+        .map(|r| r.get(0));
+    // pg_ls_waldir() needs superuser or pg_monitor; treat failure as unknown.
+    let wal_dir_bytes: Option<i64> = if modern {
+        client
+            .query_one(
+                "SELECT COALESCE(sum(size), 0)::bigint FROM pg_ls_waldir()",
+                &[],
+            )
+            .await
+            .ok()
+            .map(|r| r.get(0))
+    } else {
+        None
+    };
+    let inactive_slots: Vec<(String, i64)> = if modern {
+        client
+            .query(
+                "SELECT slot_name::text, pg_wal_lsn_diff(pg_current_wal_lsn(), restart_lsn)::bigint
+                 FROM pg_replication_slots
+                 WHERE NOT active AND restart_lsn IS NOT NULL
+                 ORDER BY slot_name",
+                &[],
+            )
+            .await
+            .map(|rows| rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+
+    let mut status = CheckStatus::Pass;
+    let mut detail = format!("Estimated WAL {}MB", estimated_mb);
+
+    if config.wal_budget_mb > 0 {
+        match wal_dir_bytes {
+            Some(bytes) => {
+                let used_mb = bytes / MB;
+                let projected_mb = used_mb + estimated_mb;
+                detail = format!(
+                    "{} + {}MB in pg_wal = {}MB of {}MB budget",
+                    detail, used_mb, projected_mb, config.wal_budget_mb
+                );
+                if projected_mb > config.wal_budget_mb {
+                    status = CheckStatus::Fail;
+                } else if projected_mb * 5 > config.wal_budget_mb * 4 {
+                    status = CheckStatus::Warn;
+                }
+            }
+            // Without pg_wal usage only the estimate itself can be judged.
+            None => {
+                detail = format!(
+                    "{} of {}MB budget; pg_wal usage unknown (pg_ls_waldir() needs superuser or pg_monitor)",
+                    detail, config.wal_budget_mb
+                );
+                status = if estimated_mb > config.wal_budget_mb {
+                    CheckStatus::Fail
+                } else {
+                    CheckStatus::Warn
+                };
+            }
+        }
+    } else if let Some(max) = max_wal_size {
+        detail = format!("{} (max_wal_size: {}MB)", detail, max / MB);
+        if estimate.total_bytes > max {
+            status = CheckStatus::Warn;
+        }
+    }
+
+    if !inactive_slots.is_empty() && estimate.total_bytes > 0 {
+        let slots: Vec<String> = inactive_slots
+            .iter()
+            .map(|(name, retained)| format!("{} ({}MB retained)", name, retained / MB))
+            .collect();
+        detail = format!(
+            "{}; inactive slots will retain it: {}",
+            detail,
+            slots.join(", ")
+        );
+        if status == CheckStatus::Pass {
+            status = CheckStatus::Warn;
+        }
+    }
+
+    PreflightCheck {
+        name: "WAL Headroom".to_string(),
+        status,
+        detail,
+    }
+}
+
+/// The SQL of `migrations` with placeholders replaced, as `migrate` runs it.
+#[cfg(feature = "postgres")]
+pub(crate) fn substituted_scripts<'m>(
+    config: &WaypointConfig,
+    db_user: &str,
+    db_name: &str,
+    migrations: impl IntoIterator<Item = &'m ResolvedMigration>,
+) -> Result<Vec<String>> {
+    migrations
+        .into_iter()
+        .map(|m| {
+            let placeholders = crate::placeholder::build_placeholders(
+                &config.placeholders,
+                &config.migrations.schema,
+                db_user,
+                db_name,
+                &m.script,
+            );
+            crate::placeholder::replace_placeholders(&m.sql, &placeholders)
+        })
+        .collect()
+}
+
+/// The WAL headroom check for the pending migrations, for `waypoint
+/// preflight` (PostgreSQL). `None` on CockroachDB, which does not expose
+/// WAL, and when nothing is pending.
+#[cfg(feature = "postgres")]
+pub async fn preflight_check(
+    client: &Client,
+    config: &WaypointConfig,
+) -> Result<Option<PreflightCheck>> {
+    if crate::db::is_cockroach(client).await {
+        return Ok(None);
+    }
+    let pending: Vec<String> = crate::commands::info::execute(client, config)
+        .await?
+        .into_iter()
+        .filter(|row| row.state.is_pending())
+        .map(|row| row.script)
+        .collect();
+    if pending.is_empty() {
+        return Ok(None);
+    }
+    let resolved = config.migrations.resolve()?;
+    let db_user = crate::db::get_current_user(client)
+        .await
+        .unwrap_or_else(|_| "unknown".to_string());
+    let db_name = crate::db::get_current_database(client)
+        .await
+        .unwrap_or_else(|_| "unknown".to_string());
+    let scripts = substituted_scripts(
+        config,
+        &db_user,
+        &db_name,
+        resolved.iter().filter(|m| pending.contains(&m.script)),
+    )?;
+    let scripts: Vec<&str> = scripts.iter().map(String::as_str).collect();
+    let estimate = estimate_wal(client, &config.migrations.schema, &scripts).await?;
+    Ok(Some(
+        check_wal_headroom(client, &config.preflight, &estimate).await,
+    ))
+}
+
+/// [`preflight_check`] (dialect-aware); `None` on MySQL.
+#[cfg_attr(not(feature = "postgres"), allow(unused_variables))]
+pub async fn preflight_check_db(
+    client: &crate::db::DbClient,
+    config: &crate::config::WaypointConfig,
+) -> crate::error::Result<Option<crate::preflight::PreflightCheck>> {
+    match client {
+        #[cfg(feature = "postgres")]
+        crate::db::DbClient::Postgres(c) => preflight_check(c, config).await,
+        #[allow(unreachable_patterns)]
+        _ => Ok(None),
+    }
+}
+
+/// Estimate WAL for pending migrations and fail if it would exceed headroom
+/// (PostgreSQL).
+///
+/// Called by `migrate` before anything is applied when pre-flight checks are
+/// enabled. A warning is logged and the run continues; only a `wal_budget_mb`
+/// overrun returns [`WaypointError::PreflightFailed`]. Skipped on CockroachDB,
+/// which does not expose WAL.
+#[cfg(feature = "postgres")]
+pub async fn guard_wal(
+    client: &Client,
+    config: &PreflightConfig,
+    schema: &str,
+    scripts: &[&str],
+) -> Result<()> {
+    if scripts.is_empty() || crate::db::is_cockroach(client).await {
+        return Ok(());
+    }
+    let estimate = estimate_wal(client, schema, scripts).await?;
+    if estimate.total_bytes == 0 {
+        return Ok(());
+    }
+    for s in &estimate.statements {
+        log::debug!(
+            "WAL estimate; table={}, cost={:?}, bytes={}",
+            s.table,
+            s.cost,
+            s.estimated_bytes
+        );
+    }
+    let check = check_wal_headroom(client, config, &estimate).await;
+    match check.status {
+        CheckStatus::Pass => {
+            log::info!("WAL headroom ok; {}", check.detail);
+            Ok(())
+        }
+        CheckStatus::Warn => {
+            log::warn!("WAL headroom; {}", check.detail);
+            Ok(())
+        }
+        CheckStatus::Fail => Err(WaypointError::PreflightFailed {
+            checks: format!("{}: {}", check.name, check.detail),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(stmt: &str) -> Option<(String, WalCost)> {
+        classify_statement(stmt, Some(PgVersion::from_version_num(160000)))
+            .map(|(_, table, cost)| (table, cost))
+    }
+
+    #[test]
+    fn test_classify_rewrites() {
+        assert_eq!(
+            classify("ALTER TABLE orders ALTER COLUMN total TYPE numeric(12,2)"),
+            Some(("orders".to_string(), WalCost::Rewrite))
+        );
+        assert_eq!(
+            classify("VACUUM FULL public.events"),
+            Some(("events".to_string(), WalCost::Rewrite))
+        );
+        assert_eq!(
+            classify("ALTER TABLE users ADD COLUMN token uuid DEFAULT gen_random_uuid()"),
+            Some(("users".to_string(), WalCost::Rewrite))
+        );
+    }
+
+    #[test]
+    fn test_classify_fast_default_depends_on_version() {
+        let stmt = "ALTER TABLE users ADD COLUMN active boolean DEFAULT true";
+        assert_eq!(classify(stmt), None);
+        let v10 = Some(PgVersion::from_version_num(100000));
+        assert_eq!(
+            classify_statement(stmt, v10).map(|(_, _, c)| c),
+            Some(WalCost::Rewrite)
+        );
+    }
+
+    #[test]
+    fn test_classify_index_and_dml() {
+        assert_eq!(
+            classify("-- backfill\nUPDATE users SET active = true WHERE active IS NULL"),
+            Some(("users".to_string(), WalCost::Backfill))
+        );
+        assert_eq!(
+            classify("CREATE INDEX CONCURRENTLY idx_users_email ON users (email)"),
+            Some(("users".to_string(), WalCost::IndexBuild))
+        );
+        assert_eq!(
+            classify("ALTER TABLE users ADD CONSTRAINT users_pk PRIMARY KEY (id)"),
+            Some(("users".to_string(), WalCost::IndexBuild))
+        );
+        assert_eq!(
+            classify("DELETE FROM audit_log WHERE created_at < now() - interval '1 year'"),
+            Some(("audit_log".to_string(), WalCost::Delete))
+        );
+        assert_eq!(classify("CREATE TABLE t (id int)"), None);
+    }
+
+    #[test]
+    fn test_classify_resolves_quoted_and_unquoted_names() {
+        let v16 = Some(PgVersion::from_version_num(160000));
+        assert_eq!(
+            classify_statement(r#"UPDATE "Sales"."Order ""Lines"" " SET qty = 0"#, v16),
+            Some((
+                Some("Sales".to_string()),
+                "Order \"Lines\" ".to_string(),
+                WalCost::Backfill
+            ))
+        );
+        assert_eq!(
+            classify("CREATE INDEX \"Idx\" ON Public.Users (email)"),
+            Some(("users".to_string(), WalCost::IndexBuild))
+        );
+    }
+
+    #[test]
+    fn test_estimate_bytes() {
+        assert_eq!(WalCost::Rewrite.estimate_bytes(1000, 400), 1400);
+        assert_eq!(WalCost::IndexBuild.estimate_bytes(1000, 400), 250);
+    }
+}
@@ -0,0 +1,107 @@
+//! Structured warnings attached to command reports.
+//!
+//! Problems that don't fail a run (a guard in `warn` mode, a reversal that
+//! couldn't be generated, an unexpectedly large backfill) are logged as they
+//! happen and also collected here, so JSON consumers and the CLI summary
+//! can show them after the run.
+
+use serde::Serialize;
+
+/// What kind of warning this is. Serialized as a stable snake_case code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    /// A `-- waypoint:require` guard failed under `on_require_fail = "warn"`.
+    GuardRequireFailed,
+    /// Reversal generation flagged the migration (no reversal, possible data loss).
+    Reversal,
+    /// Reversal SQL could not be captured, generated or stored.
+    ReversalFailed,
+    /// A migration affected more rows than `warn_rows_affected`.
+    RowsAffected,
+    /// The backup reference could not be recorded in the history table.
+    BackupRefNotRecorded,
+    /// Post-migrate `ANALYZE` / `VACUUM` was skipped or failed for a table.
+    MaintenanceFailed,
+    /// A migration was undone with auto-generated reversal SQL.
+    AutoReversal,
+    /// The `[notify]` notification after migrate could not be sent.
+    NotifyFailed,
+    /// A `[notifications]` webhook could not be reached.
+    WebhookFailed,
+    /// The `[receipts]` execution receipt could not be issued.
+    ReceiptFailed,
+    /// The `[drift]` baseline could not be recorded after migrate.
+    DriftBaselineFailed,
+    /// A `[checks]` data-quality check failed (or could not run) after migrate.
+    CheckFailed,
+}
+
+impl std::fmt::Display for WarningCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            WarningCode::GuardRequireFailed => "guard_require_failed",
+            WarningCode::Reversal => "reversal",
+            WarningCode::ReversalFailed => "reversal_failed",
+            WarningCode::RowsAffected => "rows_affected",
+            WarningCode::BackupRefNotRecorded => "backup_ref_not_recorded",
+            WarningCode::MaintenanceFailed => "maintenance_failed",
+            WarningCode::AutoReversal => "auto_reversal",
+            WarningCode::NotifyFailed => "notify_failed",
+            WarningCode::WebhookFailed => "webhook_failed",
+            WarningCode::ReceiptFailed => "receipt_failed",
+            WarningCode::DriftBaselineFailed => "drift_baseline_failed",
+            WarningCode::CheckFailed => "check_failed",
+        };
+        f.write_str(code)
+    }
+}
+
+/// A non-fatal problem raised during a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    /// Machine-readable kind of warning.
+    pub code: WarningCode,
+    /// Human-readable description.
+    pub message: String,
+    /// What the warning is about: a migration script, version, or table.
+    pub context: String,
+}
+
+impl Warning {
+    /// Create a warning about `context` (a script, version, or table).
+    pub fn new(code: WarningCode, context: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            context: context.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.code, self.context, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_serializes_code_as_snake_case() {
+        let w = Warning::new(
+            WarningCode::RowsAffected,
+            "V2__Backfill.sql",
+            "affected 5000 rows (threshold 1000)",
+        );
+        let json = serde_json::to_value(&w).unwrap();
+        assert_eq!(json["code"], "rows_affected");
+        assert_eq!(json["context"], "V2__Backfill.sql");
+        assert_eq!(
+            w.to_string(),
+            "[rows_affected] V2__Backfill.sql: affected 5000 rows (threshold 1000)"
+        );
+    }
+}
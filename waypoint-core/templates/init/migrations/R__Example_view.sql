@@ -0,0 +1,5 @@
+-- Repeatable migration: re-applied whenever its checksum changes.
+CREATE OR REPLACE VIEW example_recent AS
+SELECT id, name, created_at
+FROM example
+WHERE created_at > now() - interval '7 days';
@@ -0,0 +1,2 @@
+-- Undo migration: `waypoint undo` runs this to reverse V1.
+DROP TABLE IF EXISTS example;
@@ -0,0 +1,6 @@
+-- Versioned migration: applied once, in version order.
+CREATE TABLE IF NOT EXISTS example (
+    id         BIGSERIAL PRIMARY KEY,
+    name       TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
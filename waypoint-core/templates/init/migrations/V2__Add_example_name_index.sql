@@ -0,0 +1,3 @@
+-- waypoint:no-transaction
+-- CONCURRENTLY can't run inside a transaction, so this migration opts out.
+CREATE INDEX CONCURRENTLY IF NOT EXISTS example_name_idx ON example (name);
@@ -0,0 +1,16 @@
+-- {{title}}
+--
+-- waypoint:set lock_timeout=5s
+--
+-- Nullable and without a volatile default, adding the column only touches the
+-- catalog. The ALTER still needs an ACCESS EXCLUSIVE lock for a moment;
+-- lock_timeout makes it give up instead of queueing behind a long
+-- transaction while every other query on the table queues behind it.
+-- Re-run (or set [safety] ddl_retry) if it times out.
+
+ALTER TABLE {{table}} ADD COLUMN IF NOT EXISTS {{column}} {{type}};
+
+-- To make the column NOT NULL later without a full scan under that lock,
+-- backfill it (waypoint new --template backfill), add a
+-- CHECK ({{column}} IS NOT NULL) constraint NOT VALID in a new migration, and
+-- VALIDATE CONSTRAINT it in a -- waypoint:low-priority migration of its own.
@@ -0,0 +1,7 @@
+-- Undo {{title}}
+--
+-- Undo runs in a transaction; lock_timeout keeps the DROP from queueing
+-- behind a long transaction while holding up every query on the table.
+
+SET LOCAL lock_timeout = '5s';
+ALTER TABLE {{table}} DROP COLUMN IF EXISTS {{column}};
@@ -0,0 +1,12 @@
+-- {{title}}
+--
+-- waypoint:no-transaction
+-- waypoint:set lock_timeout=5s
+--
+-- CONCURRENTLY builds the index without blocking writes, and cannot run in a
+-- transaction. A build that fails or is cancelled leaves an INVALID index
+-- behind, which IF NOT EXISTS would then keep; dropping it first makes a
+-- re-run after a failure rebuild it.
+
+DROP INDEX CONCURRENTLY IF EXISTS {{index}};
+CREATE INDEX CONCURRENTLY IF NOT EXISTS {{index}} ON {{table}} ({{columns}});
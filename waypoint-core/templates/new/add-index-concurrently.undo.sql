@@ -0,0 +1,8 @@
+-- Undo {{title}}
+--
+-- Undo runs in a transaction, where DROP INDEX CONCURRENTLY is not allowed.
+-- A plain DROP INDEX is quick but locks {{table}}; lock_timeout makes it
+-- give up instead of queueing behind a long transaction.
+
+SET LOCAL lock_timeout = '5s';
+DROP INDEX IF EXISTS {{index}};
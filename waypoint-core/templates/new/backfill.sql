@@ -0,0 +1,29 @@
+-- {{title}}
+--
+-- waypoint:no-transaction
+-- waypoint:set lock_timeout=5s
+--
+-- Updates {{table}} {{batch_size}} rows at a time, committing after each
+-- batch, so row locks are held briefly and no single transaction rewrites the
+-- table (COMMIT inside DO needs PostgreSQL 11+ and no surrounding
+-- transaction). Rows already done are skipped, so a re-run after a failure
+-- carries on where it stopped. If {{expression}} can be NULL, the loop never
+-- ends: narrow the WHERE clause to match.
+
+DO $$
+DECLARE
+    n integer;
+BEGIN
+    LOOP
+        UPDATE {{table}} SET {{column}} = {{expression}}
+        WHERE ctid IN (
+            SELECT ctid FROM {{table}}
+            WHERE {{column}} IS NULL
+            LIMIT {{batch_size}}
+        );
+        GET DIAGNOSTICS n = ROW_COUNT;
+        EXIT WHEN n = 0;
+        COMMIT;
+    END LOOP;
+END
+$$;
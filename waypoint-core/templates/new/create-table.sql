@@ -0,0 +1,15 @@
+-- {{title}}
+--
+-- waypoint:set lock_timeout=5s
+--
+-- IF NOT EXISTS keeps a re-run harmless. A foreign key locks the table it
+-- references as well; lock_timeout makes the migration give up instead of
+-- queueing behind a long transaction on that table.
+
+CREATE TABLE IF NOT EXISTS {{table}} (
+    id          BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Index the foreign key columns you add; PostgreSQL does not do it for you.
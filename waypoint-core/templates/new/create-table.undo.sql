@@ -0,0 +1,3 @@
+-- Undo {{title}}
+
+DROP TABLE IF EXISTS {{table}};
@@ -12,14 +12,18 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use waypoint_core::commands::diff::DiffTarget;
 use waypoint_core::commands::info::MigrationState;
 use waypoint_core::commands::snapshot::SnapshotConfig;
 use waypoint_core::commands::undo::UndoTarget;
 use waypoint_core::config::{DatabaseConfig, HooksConfig, MigrationSettings, WaypointConfig};
-use waypoint_core::db::{self, quote_ident};
+use waypoint_core::db::{self, quote_ident, LockTarget};
 use waypoint_core::dependency::DependencyGraph;
+use waypoint_core::dialect::DialectKind;
+use waypoint_core::error::WaypointError;
 use waypoint_core::history;
 use waypoint_core::migration::{scan_migrations, MigrationVersion};
+use waypoint_core::preflight::{self, PreflightConfig};
 use waypoint_core::safety::SafetyVerdict;
 use waypoint_core::Waypoint;
 
@@ -253,6 +257,109 @@ async fn test_info_shows_correct_states() {
     teardown_schema(&conn, &schema).await;
 }
 
+#[tokio::test]
+async fn test_migrate_relative_targets() {
+    let (client, schema) = setup_schema("relative_target").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__First.sql",
+            &format!("CREATE TABLE {}.rel_a (id SERIAL);", schema),
+        ),
+        (
+            "V2__Second.sql",
+            &format!("CREATE TABLE {}.rel_b (id SERIAL);", schema),
+        ),
+        (
+            "V3__Third.sql",
+            &format!("CREATE TABLE {}.rel_c (id SERIAL);", schema),
+        ),
+        (
+            "V4__Fourth.sql",
+            &format!("CREATE TABLE {}.rel_d (id SERIAL);", schema),
+        ),
+    ]);
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    let applied = |report: &waypoint_core::MigrateReport| -> Vec<String> {
+        report
+            .details
+            .iter()
+            .filter_map(|d| d.version.clone())
+            .collect()
+    };
+
+    let report = wp.migrate(Some("+1")).await.expect("migrate +1 failed");
+    assert_eq!(applied(&report), ["1"]);
+
+    let plan = wp.plan(Some("latest-1")).await.expect("plan failed");
+    assert_eq!(plan.migrations.len(), 2);
+
+    let report = wp
+        .migrate(Some("latest-1"))
+        .await
+        .expect("migrate latest-1 failed");
+    assert_eq!(applied(&report), ["2", "3"]);
+
+    let report = wp
+        .migrate(Some("latest-1"))
+        .await
+        .expect("repeat latest-1 failed");
+    assert_eq!(report.migrations_applied, 0);
+
+    let report = wp
+        .migrate(Some("latest"))
+        .await
+        .expect("migrate latest failed");
+    assert_eq!(applied(&report), ["4"]);
+
+    let err = wp.migrate(Some("+0")).await.unwrap_err();
+    assert!(matches!(err, WaypointError::ConfigError(_)));
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_migrate_relative_target_skips_other_environments() {
+    let (client, schema) = setup_schema("relative_target_env").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__First.sql",
+            &format!("CREATE TABLE {}.rel_env_a (id SERIAL);", schema),
+        ),
+        (
+            "V2__Dev_only.sql",
+            &format!(
+                "-- waypoint:env dev\nCREATE TABLE {}.rel_env_dev (id SERIAL);",
+                schema
+            ),
+        ),
+        (
+            "V3__Third.sql",
+            &format!("CREATE TABLE {}.rel_env_c (id SERIAL);", schema),
+        ),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.environment = Some("prod".to_string());
+    let wp = Waypoint::with_client(config, client);
+
+    let report = wp.migrate(Some("+1")).await.expect("migrate +1 failed");
+    assert_eq!(report.details[0].version.as_deref(), Some("1"));
+
+    // V2 never runs in prod, so the next +1 is V3.
+    let report = wp.migrate(Some("+1")).await.expect("second +1 failed");
+    assert_eq!(report.migrations_applied, 1);
+    assert_eq!(report.details[0].version.as_deref(), Some("3"));
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
 #[tokio::test]
 async fn test_validate_detects_checksum_mismatch() {
     let (client, schema) = setup_schema("validate").await;
@@ -386,6 +493,81 @@ async fn test_baseline_inserts_baseline_row() {
     teardown_schema(&conn, &schema).await;
 }
 
+#[tokio::test]
+async fn test_history_table_upgrade_from_untracked_layout() {
+    let (client, schema) = setup_schema("hist_upgrade").await;
+    // A history table from before version tracking, without backup_ref or checksum_sha256.
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {s}.waypoint_schema_history (
+                installed_rank INTEGER PRIMARY KEY, version VARCHAR(50),
+                description VARCHAR(200) NOT NULL, type VARCHAR(20) NOT NULL,
+                script VARCHAR(1000) NOT NULL, checksum INTEGER,
+                installed_by VARCHAR(100) NOT NULL,
+                installed_on TIMESTAMPTZ NOT NULL DEFAULT now(),
+                execution_time INTEGER NOT NULL, success BOOLEAN NOT NULL,
+                reversal_sql TEXT
+            )",
+            s = quote_ident(&schema)
+        ))
+        .await
+        .unwrap();
+
+    client
+        .batch_execute(&format!(
+            "INSERT INTO {s}.waypoint_schema_history VALUES \
+             (1, '1', 'Old', 'SQL', 'V1__Old.sql', 1, 'me', now(), 5, TRUE, NULL)",
+            s = quote_ident(&schema)
+        ))
+        .await
+        .unwrap();
+
+    let migrations = create_temp_migrations(&[]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    // Reads work against the old layout without upgrading it.
+    let infos = wp.info().await.expect("info on old layout failed");
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].audit, None);
+
+    let plan = wp.upgrade_history(true).await.expect("dry run failed");
+    assert_eq!(plan.from_version, None);
+    assert_eq!(
+        plan.columns_added,
+        vec![
+            "backup_ref",
+            "checksum_sha256",
+            "git_commit",
+            "ci_build_url",
+            "hostname",
+            "waypoint_version"
+        ]
+    );
+    assert!(!plan.applied);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    assert_eq!(
+        history::history_schema_version(&conn, &schema, "waypoint_schema_history")
+            .await
+            .unwrap(),
+        None,
+        "dry run must not change anything"
+    );
+
+    let done = wp.upgrade_history(false).await.expect("upgrade failed");
+    assert!(done.applied);
+    assert_eq!(
+        history::history_schema_version(&conn, &schema, "waypoint_schema_history")
+            .await
+            .unwrap(),
+        Some(history::HISTORY_SCHEMA_VERSION)
+    );
+    assert!(wp.upgrade_history(true).await.unwrap().is_current());
+
+    teardown_schema(&conn, &schema).await;
+}
+
 #[tokio::test]
 async fn test_baseline_prevents_old_migrations() {
     let (client, schema) = setup_schema("baseline_skip").await;
@@ -465,6 +647,74 @@ async fn test_clean_drops_everything() {
     teardown_schema(&client3, &schema).await;
 }
 
+/// Number of relations in `schema` with one of `names`.
+async fn count_relations(conn: &tokio_postgres::Client, schema: &str, names: &[&str]) -> i64 {
+    let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+    conn.query_one(
+        "SELECT COUNT(*) FROM pg_class c JOIN pg_namespace n ON c.relnamespace = n.oid \
+         WHERE n.nspname = $1 AND c.relname = ANY($2)",
+        &[&schema, &names],
+    )
+    .await
+    .unwrap()
+    .get(0)
+}
+
+#[tokio::test]
+async fn test_clean_filter_keeps_excluded_dependents() {
+    let (client, schema) = setup_schema("clean_filt").await;
+
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_objects.sql",
+        &format!(
+            "CREATE TABLE {s}.app_orders (id SERIAL PRIMARY KEY);\n\
+             CREATE TABLE {s}.app_items (order_id INT REFERENCES {s}.app_orders(id));\n\
+             CREATE VIEW {s}.keep_report AS SELECT id FROM {s}.app_orders;",
+            s = schema
+        ),
+    )]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(None).await.expect("migrate failed");
+
+    // The excluded view depends on an included table: refuse, drop nothing.
+    config.database.exclude_objects = vec!["keep_*".to_string()];
+    let wp = Waypoint::with_client(config.clone(), db::connect(&get_test_url()).await.unwrap());
+    let err = wp.clean(true).await.expect_err("clean should refuse");
+    assert!(err.to_string().contains("keep_report"), "{}", err);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    assert_eq!(
+        count_relations(&conn, &schema, &["app_orders", "app_items", "keep_report"]).await,
+        3
+    );
+
+    // Without the view in the way, the included objects go and the rest stay.
+    config.database.include_objects = vec!["app_*".to_string()];
+    conn.batch_execute(&format!("DROP VIEW {}.keep_report", schema))
+        .await
+        .unwrap();
+    conn.batch_execute(&format!(
+        "CREATE VIEW {s}.keep_count AS SELECT 1 AS one",
+        s = schema
+    ))
+    .await
+    .unwrap();
+    let wp = Waypoint::with_client(config, db::connect(&get_test_url()).await.unwrap());
+    wp.clean(true).await.expect("filtered clean failed");
+    assert_eq!(
+        count_relations(&conn, &schema, &["app_orders", "app_items"]).await,
+        0
+    );
+    assert_eq!(
+        count_relations(&conn, &schema, &["keep_count", "waypoint_schema_history"]).await,
+        2
+    );
+
+    teardown_schema(&conn, &schema).await;
+}
+
 #[tokio::test]
 async fn test_clean_disabled_by_default() {
     let (client, schema) = setup_schema("clean_dis").await;
@@ -592,772 +842,4253 @@ async fn test_target_version_limits_migration() {
     teardown_schema(&conn, &schema).await;
 }
 
-// ─── New Tests ───
-
 #[tokio::test]
-async fn test_undo_manual_u_file() {
-    let (client, schema) = setup_schema("undo_manual").await;
+async fn test_cherry_pick_and_skip_versions() {
+    let (client, schema) = setup_schema("cherry").await;
 
     let migrations = create_temp_migrations(&[
         (
-            "V1__Create_undo_tbl.sql",
-            &format!("CREATE TABLE {}.undo_tbl (id SERIAL PRIMARY KEY);", schema),
+            "V1__One.sql",
+            &format!("CREATE TABLE {}.c1 (id SERIAL);", schema),
         ),
         (
-            "V2__Add_col.sql",
-            &format!("ALTER TABLE {}.undo_tbl ADD COLUMN name TEXT;", schema),
+            "V2__Two.sql",
+            &format!("CREATE TABLE {}.c2 (id SERIAL);", schema),
         ),
         (
-            "U2__Add_col.sql",
-            &format!("ALTER TABLE {}.undo_tbl DROP COLUMN name;", schema),
+            "V3__Three.sql",
+            &format!("CREATE TABLE {}.c3 (id SERIAL);", schema),
         ),
     ]);
 
-    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.skip_versions = vec!["2".to_string()];
     let wp = Waypoint::with_client(config.clone(), client);
 
-    // Apply both versioned migrations
     let report = wp.migrate(None).await.expect("migrate failed");
-    assert_eq!(report.migrations_applied, 2);
+    let applied: Vec<_> = report
+        .details
+        .iter()
+        .filter_map(|d| d.version.as_deref())
+        .collect();
+    assert_eq!(applied, vec!["1", "3"]);
 
-    // Undo the last migration (V2)
+    let infos = wp.info().await.expect("info failed");
+    let v2 = infos
+        .iter()
+        .find(|i| i.version.as_deref() == Some("2"))
+        .unwrap();
+    assert_eq!(v2.state, MigrationState::Ignored);
+
+    // A version with no migration file is rejected rather than ignored.
+    config.migrations.skip_versions.clear();
+    config.migrations.cherry_pick = vec!["9".to_string()];
     let client2 = db::connect(&get_test_url()).await.unwrap();
     let wp2 = Waypoint::with_client(config.clone(), client2);
-    let undo_report = wp2.undo(UndoTarget::Last).await.expect("undo failed");
-    assert_eq!(undo_report.migrations_undone, 1);
-    assert_eq!(undo_report.details[0].version, "2");
-    assert!(!undo_report.details[0].auto_reversal);
+    assert!(wp2.migrate(None).await.is_err());
 
-    // Verify only V1 is effectively applied via info
+    // Cherry-picking the skipped version later still goes through the
+    // out-of-order check.
+    config.migrations.cherry_pick = vec!["2".to_string()];
     let client3 = db::connect(&get_test_url()).await.unwrap();
-    let wp3 = Waypoint::with_client(config, client3);
-    let infos = wp3.info().await.expect("info failed");
-    // V1 should be applied, V2 should be pending (since it was undone)
-    let applied: Vec<_> = infos
-        .iter()
-        .filter(|i| i.state == MigrationState::Applied)
-        .collect();
-    assert_eq!(applied.len(), 1);
-    assert_eq!(applied[0].version.as_deref(), Some("1"));
+    let wp3 = Waypoint::with_client(config.clone(), client3);
+    assert!(wp3.migrate(None).await.is_err());
+
+    config.migrations.out_of_order = true;
+    let client4 = db::connect(&get_test_url()).await.unwrap();
+    let wp4 = Waypoint::with_client(config, client4);
+    let report = wp4.migrate(None).await.expect("cherry-pick failed");
+    assert_eq!(report.migrations_applied, 1);
+    assert_eq!(report.details[0].version.as_deref(), Some("2"));
 
-    // Verify the column was actually dropped
     let conn = db::connect(&get_test_url()).await.unwrap();
-    let rows = conn
-        .query(
-            "SELECT column_name FROM information_schema.columns WHERE table_schema = $1 AND table_name = 'undo_tbl' ORDER BY ordinal_position",
-            &[&schema],
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_import_flyway_history() {
+    let (client, schema) = setup_schema("flyway").await;
+
+    let v1 = format!("CREATE TABLE {}.f1 (id SERIAL);", schema);
+    let migrations = create_temp_migrations(&[("V1__One.sql", &v1)]);
+    let checksum = waypoint_core::checksum::calculate_checksum(&v1);
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {s}.flyway_schema_history (
+                installed_rank INT PRIMARY KEY, version VARCHAR(50),
+                description VARCHAR(200) NOT NULL, type VARCHAR(20) NOT NULL,
+                script VARCHAR(1000) NOT NULL, checksum INTEGER,
+                installed_by VARCHAR(100) NOT NULL,
+                installed_on TIMESTAMP NOT NULL DEFAULT now(),
+                execution_time INTEGER NOT NULL, success BOOLEAN NOT NULL);
+             INSERT INTO {s}.flyway_schema_history VALUES
+                (1, '0', '<< Flyway Schema Creation >>', 'SCHEMA', '\"{s}\"', NULL, 'flyway', now(), 0, true),
+                (2, '1', 'One', 'SQL', 'V1__One.sql', {c}, 'flyway', now(), 7, true);
+             {v1}",
+            s = schema,
+            c = checksum + 1,
+            v1 = v1
+        ))
+        .await
+        .unwrap();
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    // A changed file blocks the import.
+    let report = wp
+        .import_flyway("flyway_schema_history", false, false)
+        .await
+        .unwrap();
+    assert!(report.blocked);
+    assert_eq!(report.mismatches, 1);
+    assert_eq!(wp.info().await.unwrap()[0].state, MigrationState::Pending);
+
+    wp.client()
+        .as_postgres()
+        .unwrap()
+        .execute(
+            &format!(
+                "UPDATE {}.flyway_schema_history SET checksum = $1 WHERE version = '1'",
+                schema
+            ),
+            &[&checksum],
         )
         .await
         .unwrap();
-    let columns: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
-    assert!(columns.contains(&"id".to_string()));
-    assert!(!columns.contains(&"name".to_string()));
+    let report = wp
+        .import_flyway("flyway_schema_history", false, false)
+        .await
+        .unwrap();
+    assert!(!report.blocked);
+    assert_eq!(report.imported, 1);
+
+    let infos = wp.info().await.unwrap();
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].state, MigrationState::Applied);
+    assert_eq!(wp.migrate(None).await.unwrap().migrations_applied, 0);
 
+    // Only an empty history table is filled.
+    assert!(wp
+        .import_flyway("flyway_schema_history", false, false)
+        .await
+        .is_err());
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
     teardown_schema(&conn, &schema).await;
 }
 
 #[tokio::test]
-async fn test_undo_with_count() {
-    let (client, schema) = setup_schema("undo_count").await;
+async fn test_execution_receipt_issue_and_verify() {
+    let (client, schema) = setup_schema("receipt").await;
 
     let migrations = create_temp_migrations(&[
         (
-            "V1__First.sql",
-            &format!("CREATE TABLE {}.uc_t1 (id SERIAL PRIMARY KEY);", schema),
-        ),
-        (
-            "V2__Second.sql",
-            &format!("CREATE TABLE {}.uc_t2 (id SERIAL PRIMARY KEY);", schema),
+            "V1__One.sql",
+            &format!("CREATE TABLE {}.r1 (id SERIAL);", schema),
         ),
         (
-            "V3__Third.sql",
-            &format!("CREATE TABLE {}.uc_t3 (id SERIAL PRIMARY KEY);", schema),
+            "V2__Two.sql",
+            &format!("CREATE TABLE {}.r2 (id SERIAL);", schema),
         ),
-        ("U3__Third.sql", &format!("DROP TABLE {}.uc_t3;", schema)),
-        ("U2__Second.sql", &format!("DROP TABLE {}.uc_t2;", schema)),
     ]);
+    let receipts = tempfile::tempdir().unwrap();
 
-    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.receipts.enabled = true;
+    config.receipts.dir = Some(receipts.path().to_path_buf());
+    config.receipts.history = true;
+    config.receipts.key = Some("test-key".to_string());
     let wp = Waypoint::with_client(config.clone(), client);
 
     let report = wp.migrate(None).await.expect("migrate failed");
-    assert_eq!(report.migrations_applied, 3);
-
-    // Undo the last 2 migrations
-    let client2 = db::connect(&get_test_url()).await.unwrap();
-    let wp2 = Waypoint::with_client(config.clone(), client2);
-    let undo_report = wp2
-        .undo(UndoTarget::Count(2))
-        .await
-        .expect("undo count=2 failed");
-    assert_eq!(undo_report.migrations_undone, 2);
+    assert!(
+        !report
+            .warnings
+            .iter()
+            .any(|w| w.code == waypoint_core::warning::WarningCode::ReceiptFailed),
+        "{:?}",
+        report.warnings
+    );
 
-    // Verify only V1 remains effectively applied
-    let client3 = db::connect(&get_test_url()).await.unwrap();
-    let applied = history::get_applied_migrations(&client3, &schema, "waypoint_schema_history")
+    let file = std::fs::read_dir(receipts.path())
+        .unwrap()
+        .next()
+        .expect("no receipt written")
+        .unwrap()
+        .path();
+    let receipt = waypoint_core::receipt::ExecutionReceipt::read_file(&file).unwrap();
+    assert_eq!(receipt.applied.len(), 2);
+    assert_eq!(wp.load_receipt(&receipt.run_id).await.unwrap(), receipt);
+
+    let verification = wp.verify_receipt(&receipt).await.unwrap();
+    assert!(verification.valid, "{:?}", verification);
+    assert_eq!(verification.database_matches, Some(true));
+
+    // Rewriting history is caught even though the receipt itself is intact.
+    wp.client()
+        .as_postgres()
+        .unwrap()
+        .execute(
+            &format!(
+                "UPDATE {}.waypoint_schema_history SET checksum = 1 WHERE version = '2'",
+                schema
+            ),
+            &[],
+        )
         .await
         .unwrap();
-    let effective = history::effective_applied_versions(&applied);
-    assert!(effective.contains("1"));
-    assert!(!effective.contains("2"));
-    assert!(!effective.contains("3"));
+    let verification = wp.verify_receipt(&receipt).await.unwrap();
+    assert!(!verification.valid);
+    assert_eq!(verification.history_mismatches, vec!["V2__Two.sql"]);
 
-    teardown_schema(&client3, &schema).await;
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
 }
 
 #[tokio::test]
-async fn test_undo_to_target_version() {
-    let (client, schema) = setup_schema("undo_target").await;
+async fn test_sha256_checksum_transition() {
+    use waypoint_core::checksum::ChecksumAlgorithm;
 
+    let (client, schema) = setup_schema("sha256").await;
     let migrations = create_temp_migrations(&[
         (
-            "V1__First.sql",
-            &format!("CREATE TABLE {}.ut_t1 (id SERIAL PRIMARY KEY);", schema),
+            "V1__One.sql",
+            &format!("CREATE TABLE {}.s1 (id INT);", schema),
         ),
         (
-            "V2__Second.sql",
-            &format!("CREATE TABLE {}.ut_t2 (id SERIAL PRIMARY KEY);", schema),
+            "V2__Two.sql",
+            &format!("CREATE TABLE {}.s2 (id INT);", schema),
         ),
-        (
-            "V3__Third.sql",
-            &format!("CREATE TABLE {}.ut_t3 (id SERIAL PRIMARY KEY);", schema),
-        ),
-        ("U3__Third.sql", &format!("DROP TABLE {}.ut_t3;", schema)),
-        ("U2__Second.sql", &format!("DROP TABLE {}.ut_t2;", schema)),
     ]);
+    let sha_of = |name: &str| {
+        waypoint_core::checksum::calculate_sha256(
+            &std::fs::read_to_string(migrations.path().join(name)).unwrap(),
+        )
+    };
 
-    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    // V1 is applied under CRC32, V2 after switching to SHA-256.
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
     let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(Some("1")).await.unwrap();
 
-    let report = wp.migrate(None).await.expect("migrate failed");
-    assert_eq!(report.migrations_applied, 3);
+    config.migrations.checksum_algorithm = ChecksumAlgorithm::Sha256;
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let wp = Waypoint::with_client(config.clone(), conn);
+    wp.migrate(None).await.unwrap();
 
-    // Undo to version "1" — V2 and V3 should be undone
-    let client2 = db::connect(&get_test_url()).await.unwrap();
-    let wp2 = Waypoint::with_client(config.clone(), client2);
-    let target = MigrationVersion::parse("1").unwrap();
-    let undo_report = wp2
-        .undo(UndoTarget::Version(target))
-        .await
-        .expect("undo to target failed");
-    assert_eq!(undo_report.migrations_undone, 2);
+    let applied = history::get_applied_migrations(
+        wp.client().as_postgres().unwrap(),
+        &schema,
+        "waypoint_schema_history",
+    )
+    .await
+    .unwrap();
+    assert_eq!(applied[0].checksum_sha256, None);
+    assert_eq!(applied[1].checksum_sha256, Some(sha_of("V2__Two.sql")));
+    assert!(applied.iter().all(|a| a.checksum.is_some()));
 
-    // Verify effective state
-    let client3 = db::connect(&get_test_url()).await.unwrap();
-    let applied = history::get_applied_migrations(&client3, &schema, "waypoint_schema_history")
+    // Strict SHA-256 rejects the CRC32-only row; transition accepts it.
+    let err = wp.validate().await.unwrap_err().to_string();
+    assert!(
+        err.contains("No SHA-256 checksum recorded for version 1"),
+        "{err}"
+    );
+
+    config.migrations.checksum_transition = true;
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let wp = Waypoint::with_client(config.clone(), conn);
+    assert!(wp.validate().await.unwrap().valid);
+
+    // Repair records the missing SHA-256, after which strict mode passes.
+    let report = wp.repair().await.unwrap();
+    assert_eq!(report.checksums_updated, 1);
+    assert_eq!(
+        report.details,
+        vec!["Recorded SHA-256 checksum for 'V1__One.sql'"]
+    );
+
+    config.migrations.checksum_transition = false;
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let wp = Waypoint::with_client(config.clone(), conn);
+    assert!(wp.validate().await.unwrap().valid);
+
+    // A recorded SHA-256 is authoritative even when the CRC32 still matches.
+    wp.client()
+        .as_postgres()
+        .unwrap()
+        .execute(
+            &format!(
+                "UPDATE {}.waypoint_schema_history SET checksum_sha256 = repeat('0', 64) \
+                 WHERE version = '2'",
+                schema
+            ),
+            &[],
+        )
         .await
         .unwrap();
-    let effective = history::effective_applied_versions(&applied);
-    assert!(effective.contains("1"), "V1 should remain applied");
-    assert!(!effective.contains("2"), "V2 should be undone");
-    assert!(!effective.contains("3"), "V3 should be undone");
+    let err = wp.validate().await.unwrap_err().to_string();
+    assert!(err.contains("Checksum mismatch for version 2"), "{err}");
 
-    teardown_schema(&client3, &schema).await;
+    teardown_schema(&db::connect(&get_test_url()).await.unwrap(), &schema).await;
 }
 
 #[tokio::test]
-async fn test_batch_transaction_mode() {
-    let (client, schema) = setup_schema("batch").await;
+async fn test_eval_guard_traces_sub_expressions() {
+    let (client, schema) = setup_schema("guard_eval").await;
+    client
+        .batch_execute(&format!("CREATE TABLE {schema}.users (id INT)"))
+        .await
+        .unwrap();
 
-    let migrations = create_temp_migrations(&[
-        (
-            "V1__Batch_one.sql",
-            &format!("CREATE TABLE {}.batch_t1 (id SERIAL PRIMARY KEY);", schema),
-        ),
-        (
-            "V2__Batch_two.sql",
-            &format!("CREATE TABLE {}.batch_t2 (id SERIAL PRIMARY KEY);", schema),
-        ),
-    ]);
+    let config = test_config(&schema, "/nonexistent");
+    let wp = Waypoint::with_client(config, client);
 
-    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
-    config.migrations.batch_transaction = true;
+    let evaluation = wp
+        .eval_guard(r#"table_exists("users") AND row_count("users") < 1000"#)
+        .await
+        .unwrap();
+    assert!(evaluation.result);
+    // row_count reads table statistics, so only its type is stable here.
+    assert!(matches!(
+        evaluation.steps[3].value,
+        Some(waypoint_core::guard::GuardValue::Number(_))
+    ));
+    let values: Vec<(usize, &str, Option<String>)> = evaluation
+        .steps
+        .iter()
+        .map(|s| {
+            (
+                s.depth,
+                s.expression.as_str(),
+                s.value.as_ref().map(|v| v.to_string()),
+            )
+        })
+        .filter(|(_, expr, _)| *expr != r#"row_count("users")"#)
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            (
+                0,
+                r#"table_exists("users") AND row_count("users") < 1000"#,
+                Some("true".into())
+            ),
+            (1, r#"table_exists("users")"#, Some("true".into())),
+            (1, r#"row_count("users") < 1000"#, Some("true".into())),
+            (2, "1000", Some("1000".into())),
+        ]
+    );
 
-    let wp = Waypoint::with_client(config.clone(), client);
-    let report = wp.migrate(None).await.expect("batch migrate failed");
-    assert_eq!(report.migrations_applied, 2);
+    // Short-circuited operands are listed without a value.
+    let evaluation = wp
+        .eval_guard(r#"table_exists("missing") AND row_count("missing") > 0"#)
+        .await
+        .unwrap();
+    assert!(!evaluation.result);
+    assert_eq!(evaluation.steps.len(), 3);
+    assert_eq!(evaluation.steps[2].value, None);
+
+    assert!(wp.eval_guard("row_count(\"users\")").await.is_err());
 
-    // Verify both tables exist
     let conn = db::connect(&get_test_url()).await.unwrap();
-    for tbl in &["batch_t1", "batch_t2"] {
-        let exists = conn
-            .query_one(
-                "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = $2)",
-                &[&schema, tbl],
-            )
-            .await
-            .unwrap();
-        assert!(exists.get::<_, bool>(0), "Table {} should exist", tbl);
-    }
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_remote_guard_is_read_only() {
+    let (client, schema) = setup_schema("guard_remote").await;
+    client
+        .batch_execute(&format!("CREATE TABLE {schema}.users (id INT)"))
+        .await
+        .unwrap();
 
+    let mut config = test_config(&schema, "/nonexistent");
+    config
+        .guards
+        .targets
+        .insert("prod".to_string(), get_test_url());
+    let wp = Waypoint::with_client(config, client);
+
+    let evaluation = wp
+        .eval_guard(r#"remote_table_exists("prod", "users")"#)
+        .await
+        .unwrap();
+    assert!(evaluation.result);
+
+    // The write is refused by the read-only transaction.
+    assert!(wp
+        .eval_guard(&format!(
+            r#"remote_sql("prod", "WITH ins AS (INSERT INTO {schema}.users VALUES (1) RETURNING 1) SELECT true FROM ins")"#
+        ))
+        .await
+        .is_err());
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let rows: i64 = conn
+        .query_one(&format!("SELECT count(*) FROM {schema}.users"), &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(rows, 0);
     teardown_schema(&conn, &schema).await;
 }
 
+// ─── New Tests ───
+
 #[tokio::test]
-async fn test_batch_transaction_rollback_on_failure() {
-    let (client, schema) = setup_schema("batch_fail").await;
+async fn test_undo_manual_u_file() {
+    let (client, schema) = setup_schema("undo_manual").await;
 
     let migrations = create_temp_migrations(&[
         (
-            "V1__Good.sql",
-            &format!("CREATE TABLE {}.batch_ok (id SERIAL PRIMARY KEY);", schema),
+            "V1__Create_undo_tbl.sql",
+            &format!("CREATE TABLE {}.undo_tbl (id SERIAL PRIMARY KEY);", schema),
         ),
         (
-            "V2__Bad.sql",
-            // Invalid SQL — this is not a valid SQL command
-            "THIS IS NOT VALID SQL;",
+            "V2__Add_col.sql",
+            &format!("ALTER TABLE {}.undo_tbl ADD COLUMN name TEXT;", schema),
+        ),
+        (
+            "U2__Add_col.sql",
+            &format!("ALTER TABLE {}.undo_tbl DROP COLUMN name;", schema),
         ),
     ]);
 
-    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
-    config.migrations.batch_transaction = true;
-
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
     let wp = Waypoint::with_client(config.clone(), client);
-    let result = wp.migrate(None).await;
-    assert!(result.is_err(), "batch migrate should fail on bad SQL");
 
-    // Verify V1 table does NOT exist — the whole batch should have been rolled back
-    let conn = db::connect(&get_test_url()).await.unwrap();
-    let exists = conn
-        .query_one(
-            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'batch_ok')",
-            &[&schema],
-        )
-        .await
-        .unwrap();
-    assert!(
-        !exists.get::<_, bool>(0),
-        "Table batch_ok should NOT exist after batch rollback"
-    );
+    // Apply both versioned migrations
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 2);
 
-    // Verify no history rows were committed (the batch rolled everything back)
-    // The history table might not even exist; that's fine.
-    let history_exists = conn
-        .query_one(
-            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'waypoint_schema_history')",
+    // Undo the last migration (V2)
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let wp2 = Waypoint::with_client(config.clone(), client2);
+    let undo_report = wp2.undo(UndoTarget::Last).await.expect("undo failed");
+    assert_eq!(undo_report.migrations_undone, 1);
+    assert_eq!(undo_report.details[0].version, "2");
+    assert!(!undo_report.details[0].auto_reversal);
+
+    // Verify only V1 is effectively applied via info
+    let client3 = db::connect(&get_test_url()).await.unwrap();
+    let wp3 = Waypoint::with_client(config, client3);
+    let infos = wp3.info().await.expect("info failed");
+    // V1 should be applied, V2 should be pending (since it was undone)
+    let applied: Vec<_> = infos
+        .iter()
+        .filter(|i| i.state == MigrationState::Applied)
+        .collect();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(applied[0].version.as_deref(), Some("1"));
+
+    // Verify the column was actually dropped
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let rows = conn
+        .query(
+            "SELECT column_name FROM information_schema.columns WHERE table_schema = $1 AND table_name = 'undo_tbl' ORDER BY ordinal_position",
             &[&schema],
         )
         .await
         .unwrap();
-    if history_exists.get::<_, bool>(0) {
-        let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
-            .await
-            .unwrap();
-        let effective = history::effective_applied_versions(&applied);
-        assert!(
-            effective.is_empty(),
-            "No migrations should be effectively applied after batch rollback"
-        );
-    }
+    let columns: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+    assert!(columns.contains(&"id".to_string()));
+    assert!(!columns.contains(&"name".to_string()));
 
     teardown_schema(&conn, &schema).await;
 }
 
 #[tokio::test]
-async fn test_environment_scoping() {
-    let (client, schema) = setup_schema("env_scope").await;
+async fn test_undo_plan_previews_without_changes() {
+    let (client, schema) = setup_schema("undo_plan").await;
 
     let migrations = create_temp_migrations(&[
         (
-            "V1__Prod_only.sql",
-            &format!(
-                "-- waypoint:env prod\nCREATE TABLE {}.env_prod (id SERIAL PRIMARY KEY);",
-                schema
-            ),
+            "V1__Create_plan_tbl.sql",
+            &format!("CREATE TABLE {}.plan_tbl (id SERIAL PRIMARY KEY);", schema),
         ),
         (
-            "V2__All_envs.sql",
-            &format!("CREATE TABLE {}.env_all (id SERIAL PRIMARY KEY);", schema),
+            "V2__Add_col.sql",
+            &format!("ALTER TABLE {}.plan_tbl ADD COLUMN name TEXT;", schema),
+        ),
+        (
+            "U2__Add_col.sql",
+            "ALTER TABLE ${waypoint:schema}.plan_tbl DROP COLUMN name;",
         ),
     ]);
 
-    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
-    config.migrations.environment = Some("dev".to_string());
-
-    let wp = Waypoint::with_client(config.clone(), client);
-    let report = wp.migrate(None).await.expect("migrate failed");
-
-    // Only V2 should have been applied (V1 is prod-only, we're running in dev)
-    assert_eq!(report.migrations_applied, 1);
-    assert_eq!(report.details[0].version.as_deref(), Some("2"));
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+    wp.migrate(None).await.expect("migrate failed");
 
-    // Verify that only env_all table exists, not env_prod
-    let conn = db::connect(&get_test_url()).await.unwrap();
-    let exists_prod = conn
-        .query_one(
-            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'env_prod')",
-            &[&schema],
-        )
+    let plan = wp
+        .undo_plan(UndoTarget::Count(2))
         .await
-        .unwrap();
-    assert!(
-        !exists_prod.get::<_, bool>(0),
-        "env_prod table should NOT exist in dev environment"
+        .expect("undo plan failed");
+    assert_eq!(plan.steps.len(), 2);
+    assert_eq!(plan.steps[0].version, "2");
+    assert!(!plan.steps[0].auto_reversal);
+    assert_eq!(plan.steps[0].script, "U2__Add_col.sql");
+    assert_eq!(
+        plan.steps[0].sql,
+        format!("ALTER TABLE {}.plan_tbl DROP COLUMN name;", schema)
     );
+    assert_eq!(plan.steps[1].version, "1");
+    assert!(plan.steps[1].auto_reversal);
+    assert!(plan.steps[1].sql.contains("DROP TABLE"));
+    assert_eq!(plan.warnings.len(), 1);
+
+    // Nothing was undone.
+    let infos = wp.info().await.expect("info failed");
+    let applied = infos
+        .iter()
+        .filter(|i| i.state == MigrationState::Applied)
+        .count();
+    assert_eq!(applied, 2);
 
-    let exists_all = conn
-        .query_one(
-            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'env_all')",
-            &[&schema],
-        )
-        .await
-        .unwrap();
-    assert!(exists_all.get::<_, bool>(0), "env_all table should exist");
-
+    let conn = db::connect(&get_test_url()).await.unwrap();
     teardown_schema(&conn, &schema).await;
 }
 
 #[tokio::test]
-async fn test_placeholders() {
-    let (client, schema) = setup_schema("placeholders").await;
+async fn test_migrate_records_audit_metadata() {
+    let (client, schema) = setup_schema("audit_meta").await;
 
     let migrations = create_temp_migrations(&[(
-        "V1__Create_with_placeholder.sql",
-        &format!(
-            "CREATE TABLE {}.{} (id SERIAL PRIMARY KEY, name TEXT);",
-            schema, "${mytable}"
-        ),
+        "V1__Create_audit_tbl.sql",
+        &format!("CREATE TABLE {}.audit_tbl (id SERIAL PRIMARY KEY);", schema),
     )]);
 
     let mut config = test_config(&schema, migrations.path().to_str().unwrap());
-    config
-        .placeholders
-        .insert("mytable".to_string(), "placeholder_tbl".to_string());
-
+    config.audit.git_commit = Some("abc123".to_string());
+    config.audit.hostname = Some("deploy-1".to_string());
     let wp = Waypoint::with_client(config, client);
-    let report = wp
-        .migrate(None)
-        .await
-        .expect("migrate with placeholders failed");
-    assert_eq!(report.migrations_applied, 1);
+    wp.migrate(None).await.expect("migrate failed");
 
-    // Verify the table was created with the replaced name
+    let infos = wp.info().await.expect("info failed");
+    let audit = infos[0].audit.as_ref().expect("audit recorded");
+    assert_eq!(audit.git_commit.as_deref(), Some("abc123"));
+    assert_eq!(audit.hostname.as_deref(), Some("deploy-1"));
+    assert_eq!(
+        audit.waypoint_version.as_deref(),
+        Some(env!("CARGO_PKG_VERSION"))
+    );
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_undo_with_count() {
+    let (client, schema) = setup_schema("undo_count").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__First.sql",
+            &format!("CREATE TABLE {}.uc_t1 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V2__Second.sql",
+            &format!("CREATE TABLE {}.uc_t2 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V3__Third.sql",
+            &format!("CREATE TABLE {}.uc_t3 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        ("U3__Third.sql", &format!("DROP TABLE {}.uc_t3;", schema)),
+        ("U2__Second.sql", &format!("DROP TABLE {}.uc_t2;", schema)),
+    ]);
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 3);
+
+    // Undo the last 2 migrations
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let wp2 = Waypoint::with_client(config.clone(), client2);
+    let undo_report = wp2
+        .undo(UndoTarget::Count(2))
+        .await
+        .expect("undo count=2 failed");
+    assert_eq!(undo_report.migrations_undone, 2);
+
+    // Verify only V1 remains effectively applied
+    let client3 = db::connect(&get_test_url()).await.unwrap();
+    let applied = history::get_applied_migrations(&client3, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    let effective = history::effective_applied_versions(&applied);
+    assert!(effective.contains("1"));
+    assert!(!effective.contains("2"));
+    assert!(!effective.contains("3"));
+
+    teardown_schema(&client3, &schema).await;
+}
+
+#[tokio::test]
+async fn test_undo_to_target_version() {
+    let (client, schema) = setup_schema("undo_target").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__First.sql",
+            &format!("CREATE TABLE {}.ut_t1 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V2__Second.sql",
+            &format!("CREATE TABLE {}.ut_t2 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V3__Third.sql",
+            &format!("CREATE TABLE {}.ut_t3 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        ("U3__Third.sql", &format!("DROP TABLE {}.ut_t3;", schema)),
+        ("U2__Second.sql", &format!("DROP TABLE {}.ut_t2;", schema)),
+    ]);
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 3);
+
+    // Undo to version "1" — V2 and V3 should be undone
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let wp2 = Waypoint::with_client(config.clone(), client2);
+    let target = MigrationVersion::parse("1").unwrap();
+    let undo_report = wp2
+        .undo(UndoTarget::Version(target))
+        .await
+        .expect("undo to target failed");
+    assert_eq!(undo_report.migrations_undone, 2);
+
+    // Verify effective state
+    let client3 = db::connect(&get_test_url()).await.unwrap();
+    let applied = history::get_applied_migrations(&client3, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    let effective = history::effective_applied_versions(&applied);
+    assert!(effective.contains("1"), "V1 should remain applied");
+    assert!(!effective.contains("2"), "V2 should be undone");
+    assert!(!effective.contains("3"), "V3 should be undone");
+
+    teardown_schema(&client3, &schema).await;
+}
+
+#[tokio::test]
+async fn test_undo_exact_version() {
+    let (client, schema) = setup_schema("undo_exact").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__First.sql",
+            &format!("CREATE TABLE {}.ux_t1 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V2__Second.sql",
+            &format!("CREATE TABLE {}.ux_t2 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V3__Third.sql",
+            &format!("INSERT INTO {}.ux_t1 DEFAULT VALUES;", schema),
+        ),
+        ("U1__First.sql", &format!("DROP TABLE {}.ux_t1;", schema)),
+        ("U2__Second.sql", &format!("DROP TABLE {}.ux_t2;", schema)),
+    ]);
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 3);
+
+    // V3 inserts into the table V1 created, so V1 can't be undone alone.
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let wp2 = Waypoint::with_client(config.clone(), client2);
+    let err = wp2
+        .undo(UndoTarget::Exact(MigrationVersion::parse("1").unwrap()))
+        .await
+        .expect_err("undo of V1 should be refused");
+    match err {
+        WaypointError::UndoUnsafe { version, reason } => {
+            assert_eq!(version, "1");
+            assert!(reason.contains("V3 uses table ux_t1"), "{}", reason);
+        }
+        other => panic!("expected UndoUnsafe, got {:?}", other),
+    }
+
+    // Nothing later uses V2's table: it is undone and V3 stays applied.
+    let undo_report = wp2
+        .undo(UndoTarget::Exact(MigrationVersion::parse("2").unwrap()))
+        .await
+        .expect("undo of V2 failed");
+    assert_eq!(undo_report.migrations_undone, 1);
+    assert_eq!(undo_report.details[0].version, "2");
+
+    let client3 = db::connect(&get_test_url()).await.unwrap();
+    let applied = history::get_applied_migrations(&client3, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    let effective = history::effective_applied_versions(&applied);
+    assert!(effective.contains("1"));
+    assert!(!effective.contains("2"), "V2 should be undone");
+    assert!(effective.contains("3"), "V3 should remain applied");
+
+    teardown_schema(&client3, &schema).await;
+}
+
+#[tokio::test]
+async fn test_reconcile_records_migrations_applied_outside_waypoint() {
+    use waypoint_core::commands::reconcile::ReconcileStatus;
+
+    let (client, schema) = setup_schema("reconcile").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_rc_t1.sql",
+            &format!(
+                "CREATE TABLE {0}.rc_t1 (id SERIAL PRIMARY KEY);\n\
+                 CREATE INDEX rc_t1_id_idx ON {0}.rc_t1 (id);",
+                schema
+            ),
+        ),
+        (
+            "V2__Create_rc_t2.sql",
+            &format!("CREATE TABLE {}.rc_t2 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V3__Seed.sql",
+            &format!("INSERT INTO {}.rc_t1 DEFAULT VALUES;", schema),
+        ),
+    ]);
+
+    // V1 was run by hand, outside waypoint.
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {0}.rc_t1 (id SERIAL PRIMARY KEY);\n\
+             CREATE INDEX rc_t1_id_idx ON {0}.rc_t1 (id);",
+            schema
+        ))
+        .await
+        .unwrap();
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+
+    let preview = wp.reconcile(true).await.expect("dry-run reconcile failed");
+    assert_eq!(preview.reconciled, 1);
+    let statuses: Vec<ReconcileStatus> = preview.migrations.iter().map(|m| m.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ReconcileStatus::Reconciled,
+            ReconcileStatus::NotApplied,
+            ReconcileStatus::Unverifiable,
+        ]
+    );
+
+    let report = wp.reconcile(false).await.expect("reconcile failed");
+    assert_eq!(report.reconciled, 1);
+
+    // Migrate now applies only V2 and V3.
+    let migrate = wp
+        .migrate(None)
+        .await
+        .expect("migrate after reconcile failed");
+    assert_eq!(migrate.migrations_applied, 2);
+
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let applied = history::get_applied_migrations(&client2, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    let v1 = applied
+        .iter()
+        .find(|a| a.version.as_deref() == Some("1"))
+        .unwrap();
+    assert_eq!(v1.migration_type, "RECONCILED");
+    assert!(v1.success);
+
+    teardown_schema(&client2, &schema).await;
+}
+
+#[tokio::test]
+async fn test_batch_transaction_mode() {
+    let (client, schema) = setup_schema("batch").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Batch_one.sql",
+            &format!("CREATE TABLE {}.batch_t1 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V2__Batch_two.sql",
+            &format!("CREATE TABLE {}.batch_t2 (id SERIAL PRIMARY KEY);", schema),
+        ),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.batch_transaction = true;
+
+    let wp = Waypoint::with_client(config.clone(), client);
+    let report = wp.migrate(None).await.expect("batch migrate failed");
+    assert_eq!(report.migrations_applied, 2);
+
+    // Verify both tables exist
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    for tbl in &["batch_t1", "batch_t2"] {
+        let exists = conn
+            .query_one(
+                "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = $2)",
+                &[&schema, tbl],
+            )
+            .await
+            .unwrap();
+        assert!(exists.get::<_, bool>(0), "Table {} should exist", tbl);
+    }
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_batch_transaction_rollback_on_failure() {
+    let (client, schema) = setup_schema("batch_fail").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Good.sql",
+            &format!("CREATE TABLE {}.batch_ok (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V2__Bad.sql",
+            // Invalid SQL — this is not a valid SQL command
+            "THIS IS NOT VALID SQL;",
+        ),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.batch_transaction = true;
+
+    let wp = Waypoint::with_client(config.clone(), client);
+    let result = wp.migrate(None).await;
+    assert!(result.is_err(), "batch migrate should fail on bad SQL");
+
+    // Verify V1 table does NOT exist — the whole batch should have been rolled back
     let conn = db::connect(&get_test_url()).await.unwrap();
     let exists = conn
         .query_one(
-            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'placeholder_tbl')",
+            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'batch_ok')",
             &[&schema],
         )
         .await
         .unwrap();
     assert!(
-        exists.get::<_, bool>(0),
-        "placeholder_tbl should exist after placeholder substitution"
+        !exists.get::<_, bool>(0),
+        "Table batch_ok should NOT exist after batch rollback"
+    );
+
+    // Verify no history rows were committed (the batch rolled everything back)
+    // The history table might not even exist; that's fine.
+    let history_exists = conn
+        .query_one(
+            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'waypoint_schema_history')",
+            &[&schema],
+        )
+        .await
+        .unwrap();
+    if history_exists.get::<_, bool>(0) {
+        let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
+            .await
+            .unwrap();
+        let effective = history::effective_applied_versions(&applied);
+        assert!(
+            effective.is_empty(),
+            "No migrations should be effectively applied after batch rollback"
+        );
+    }
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_environment_scoping() {
+    let (client, schema) = setup_schema("env_scope").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Prod_only.sql",
+            &format!(
+                "-- waypoint:env prod\nCREATE TABLE {}.env_prod (id SERIAL PRIMARY KEY);",
+                schema
+            ),
+        ),
+        (
+            "V2__All_envs.sql",
+            &format!("CREATE TABLE {}.env_all (id SERIAL PRIMARY KEY);", schema),
+        ),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.environment = Some("dev".to_string());
+
+    let wp = Waypoint::with_client(config.clone(), client);
+    let report = wp.migrate(None).await.expect("migrate failed");
+
+    // Only V2 should have been applied (V1 is prod-only, we're running in dev)
+    assert_eq!(report.migrations_applied, 1);
+    assert_eq!(report.details[0].version.as_deref(), Some("2"));
+
+    // Verify that only env_all table exists, not env_prod
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let exists_prod = conn
+        .query_one(
+            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'env_prod')",
+            &[&schema],
+        )
+        .await
+        .unwrap();
+    assert!(
+        !exists_prod.get::<_, bool>(0),
+        "env_prod table should NOT exist in dev environment"
+    );
+
+    let exists_all = conn
+        .query_one(
+            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'env_all')",
+            &[&schema],
+        )
+        .await
+        .unwrap();
+    assert!(exists_all.get::<_, bool>(0), "env_all table should exist");
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_placeholders() {
+    let (client, schema) = setup_schema("placeholders").await;
+
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_with_placeholder.sql",
+        &format!(
+            "CREATE TABLE {}.{} (id SERIAL PRIMARY KEY, name TEXT);",
+            schema, "${mytable}"
+        ),
+    )]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config
+        .placeholders
+        .insert("mytable".to_string(), "placeholder_tbl".to_string());
+
+    let wp = Waypoint::with_client(config, client);
+    let report = wp
+        .migrate(None)
+        .await
+        .expect("migrate with placeholders failed");
+    assert_eq!(report.migrations_applied, 1);
+
+    // Verify the table was created with the replaced name
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let exists = conn
+        .query_one(
+            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'placeholder_tbl')",
+            &[&schema],
+        )
+        .await
+        .unwrap();
+    assert!(
+        exists.get::<_, bool>(0),
+        "placeholder_tbl should exist after placeholder substitution"
+    );
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_hooks_before_migrate() {
+    let (client, schema) = setup_schema("hooks").await;
+
+    let dir = std::env::temp_dir().join(format!(
+        "waypoint_test_hooks_{}",
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // Create a beforeMigrate hook that creates a log table
+    std::fs::write(
+        dir.join("beforeMigrate.sql"),
+        format!(
+            "CREATE TABLE IF NOT EXISTS {}.hook_log (created_at TIMESTAMP DEFAULT now());",
+            schema
+        ),
+    )
+    .unwrap();
+
+    // Create a regular migration
+    std::fs::write(
+        dir.join("V1__Main.sql"),
+        format!(
+            "CREATE TABLE {}.hooks_main (id SERIAL PRIMARY KEY);",
+            schema
+        ),
+    )
+    .unwrap();
+
+    let config = test_config(&schema, dir.to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    let report = wp.migrate(None).await.expect("migrate with hooks failed");
+    assert_eq!(report.migrations_applied, 1);
+    assert!(report.hooks_executed > 0, "hooks should have been executed");
+
+    // Verify the hook ran by checking the log table exists
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let exists = conn
+        .query_one(
+            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'hook_log')",
+            &[&schema],
+        )
+        .await
+        .unwrap();
+    assert!(
+        exists.get::<_, bool>(0),
+        "hook_log table should exist (created by beforeMigrate hook)"
+    );
+
+    teardown_schema(&conn, &schema).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_dependency_ordering() {
+    // Test the DependencyGraph topological sort API directly with
+    // migration files that have depends directives.
+    let migrations = create_temp_migrations(&[
+        ("V1__Base.sql", "-- No deps\nSELECT 1;"),
+        ("V2__Depends_on_V1.sql", "-- waypoint:depends 1\nSELECT 1;"),
+        (
+            "V3__Also_depends_on_V1.sql",
+            "-- waypoint:depends 1\nSELECT 1;",
+        ),
+    ]);
+
+    let resolved = scan_migrations(&[migrations.path().to_path_buf()]).expect("scan failed");
+    let refs: Vec<&_> = resolved.iter().filter(|m| m.is_versioned()).collect();
+
+    // Build dependency graph without implicit chaining
+    let graph = DependencyGraph::build(&refs, false).expect("graph build failed");
+    let order = graph.topological_sort().expect("topo sort failed");
+
+    // V1 must come before V2 and V3
+    let pos_v1 = order.iter().position(|v| v == "1").unwrap();
+    let pos_v2 = order.iter().position(|v| v == "2").unwrap();
+    let pos_v3 = order.iter().position(|v| v == "3").unwrap();
+    assert!(
+        pos_v1 < pos_v2,
+        "V1 (pos {}) should be before V2 (pos {})",
+        pos_v1,
+        pos_v2
+    );
+    assert!(
+        pos_v1 < pos_v3,
+        "V1 (pos {}) should be before V3 (pos {})",
+        pos_v1,
+        pos_v3
+    );
+}
+
+#[tokio::test]
+async fn test_snapshot_and_drift() {
+    let (client, schema) = setup_schema("snap_drift").await;
+
+    let dir = std::env::temp_dir().join(format!(
+        "waypoint_test_snap_{}",
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // Use unqualified table names so drift detection can replay in temp schema
+    // (drift sets search_path to a temp schema, so schema-qualified names break it).
+    // Use INTEGER instead of SERIAL to avoid sequence-default drift between schemas.
+    std::fs::write(
+        dir.join("V1__Create_snap_tbl.sql"),
+        "CREATE TABLE snap_tbl (id INTEGER NOT NULL, name TEXT);",
+    )
+    .unwrap();
+
+    // Set search_path to our test schema so unqualified names resolve correctly
+    client
+        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+        .await
+        .unwrap();
+
+    let config = test_config(&schema, dir.to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(None).await.expect("migrate V1 failed");
+
+    // Take a snapshot
+    let snap_dir = std::env::temp_dir().join(format!(
+        "waypoint_test_snapshots_{}",
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let snap_config = SnapshotConfig {
+        directory: snap_dir.clone(),
+        auto_snapshot_on_migrate: false,
+        max_snapshots: 10,
+        strip_definer_mysql: true,
+    };
+
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    client2
+        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+        .await
+        .unwrap();
+    let wp2 = Waypoint::with_client(config.clone(), client2);
+    let snap_report = wp2.snapshot(&snap_config).await.expect("snapshot failed");
+    assert!(snap_report.objects_captured > 0);
+
+    // Now add V2 to alter the table (unqualified)
+    std::fs::write(
+        dir.join("V2__Add_email.sql"),
+        "ALTER TABLE snap_tbl ADD COLUMN email TEXT;",
+    )
+    .unwrap();
+
+    let client3 = db::connect(&get_test_url()).await.unwrap();
+    client3
+        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+        .await
+        .unwrap();
+    let wp3 = Waypoint::with_client(config.clone(), client3);
+    wp3.migrate(None).await.expect("migrate V2 failed");
+
+    // The stored schemas can be compared offline. Move the first snapshot
+    // aside so a second one taken within the same second gets its own ID.
+    for ext in ["sql", "json"] {
+        std::fs::rename(
+            snap_dir.join(format!("{}.{}", snap_report.snapshot_id, ext)),
+            snap_dir.join(format!("before_v2.{}", ext)),
+        )
+        .unwrap();
+    }
+    let after = wp3.snapshot(&snap_config).await.expect("snapshot failed");
+    let snap_diff = Waypoint::snapshot_diff(&snap_config, "before_v2", &after.snapshot_id)
+        .expect("snapshot diff failed");
+    assert_eq!(
+        snap_diff
+            .diffs
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>(),
+        vec!["+ COLUMN snap_tbl.email (text)".to_string()]
+    );
+    assert!(snap_diff
+        .generated_sql
+        .contains("ADD COLUMN \"email\" text"));
+
+    // Run drift detection — should detect no drift since migrations match DB
+    let client4 = db::connect(&get_test_url()).await.unwrap();
+    let wp4 = Waypoint::with_client(config.clone(), client4);
+    let drift_report = wp4.drift().await.expect("drift detection failed");
+    assert!(
+        !drift_report.has_drift,
+        "No drift should be detected when DB matches migrations"
+    );
+
+    // Now introduce manual drift by adding a column outside migrations
+    let client5 = db::connect(&get_test_url()).await.unwrap();
+    client5
+        .batch_execute(&format!(
+            "ALTER TABLE {}.snap_tbl ADD COLUMN extra_col TEXT;",
+            schema
+        ))
+        .await
+        .expect("manual ALTER failed");
+
+    // Drift detection should now find something
+    let wp5 = Waypoint::with_client(config, client5);
+    let drift_report2 = wp5.drift().await.expect("drift detection failed");
+    assert!(
+        drift_report2.has_drift,
+        "Drift should be detected after manual schema change"
+    );
+
+    // Cleanup
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_dir_all(&snap_dir);
+}
+
+#[tokio::test]
+async fn test_safety_analysis_drop_table() {
+    let (client, schema) = setup_schema("safety").await;
+
+    // Create a table first so DROP TABLE can be analyzed
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {}.safety_tbl (id SERIAL PRIMARY KEY);",
+            schema
+        ))
+        .await
+        .expect("create table failed");
+
+    let migrations = create_temp_migrations(&[(
+        "V1__Drop_table.sql",
+        &format!("DROP TABLE {}.safety_tbl;", schema),
+    )]);
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    let safety_report = wp.safety().await.expect("safety analysis failed");
+
+    // DROP TABLE should produce at minimum a Caution verdict (AccessExclusiveLock on small table)
+    assert!(
+        safety_report.overall_verdict >= SafetyVerdict::Caution,
+        "DROP TABLE should be at least Caution, got {:?}",
+        safety_report.overall_verdict
+    );
+
+    // Verify data_loss is detected in one of the statements
+    let has_data_loss = safety_report
+        .reports
+        .iter()
+        .flat_map(|r| &r.statements)
+        .any(|s| s.data_loss);
+    assert!(has_data_loss, "DROP TABLE should be flagged as data loss");
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_advisor_detects_table_without_pk() {
+    let (client, schema) = setup_schema("advisor").await;
+
+    // Create a table WITHOUT a primary key
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {}.no_pk_tbl (name TEXT, value INTEGER);",
+            schema
+        ))
+        .await
+        .expect("create table failed");
+
+    let migrations = create_temp_migrations(&[]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    let advisor_report = wp.advise().await.expect("advise failed");
+
+    // Should detect A004: table without primary key
+    let a004 = advisor_report
+        .advisories
+        .iter()
+        .find(|a| a.rule_id == "A004");
+    assert!(
+        a004.is_some(),
+        "A004 (table without PK) should be detected. Found rules: {:?}",
+        advisor_report
+            .advisories
+            .iter()
+            .map(|a| &a.rule_id)
+            .collect::<Vec<_>>()
+    );
+    let advisory = a004.unwrap();
+    assert_eq!(advisory.object, "no_pk_tbl");
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_advisory_lock_prevents_concurrent_access() {
+    let (client, schema) = setup_schema("lock").await;
+
+    let settings = MigrationSettings::default();
+    let lock = LockTarget::new(&settings, &schema);
+
+    // Acquire the advisory lock on the first connection
+    db::acquire_advisory_lock(&client, &lock)
+        .await
+        .expect("first lock acquire failed");
+
+    // Try to acquire the same lock on a second connection with a short timeout
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let result = db::acquire_advisory_lock_with_timeout(&client2, &lock, 2).await;
+    assert!(
+        result.is_err(),
+        "Second lock acquire should fail (timeout) while first holds it"
+    );
+
+    // A run against another schema is not blocked
+    let other = LockTarget::new(&settings, &format!("{}_other", schema));
+    db::acquire_advisory_lock_with_timeout(&client2, &other, 2)
+        .await
+        .expect("lock for another schema should not be blocked");
+    db::release_advisory_lock(&client2, &other)
+        .await
+        .expect("release failed");
+
+    // Release the first lock
+    db::release_advisory_lock(&client, &lock)
+        .await
+        .expect("release failed");
+
+    // Now the second client should be able to acquire it
+    let result2 = db::acquire_advisory_lock_with_timeout(&client2, &lock, 5).await;
+    assert!(
+        result2.is_ok(),
+        "Second lock acquire should succeed after release"
+    );
+
+    // Clean up
+    db::release_advisory_lock(&client2, &lock)
+        .await
+        .expect("cleanup release failed");
+
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_global_lock_blocks_other_schemas() {
+    let (client, schema) = setup_schema("global_lock").await;
+
+    let settings = MigrationSettings {
+        global_lock: true,
+        ..MigrationSettings::default()
+    };
+    let global = LockTarget::new(&settings, &schema);
+    db::acquire_advisory_lock(&client, &global)
+        .await
+        .expect("global lock acquire failed");
+
+    // Any other run waits for a global holder, whatever its schema
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let other = LockTarget::new(&MigrationSettings::default(), &format!("{}_other", schema));
+    let result = db::acquire_advisory_lock_with_timeout(&client2, &other, 1).await;
+
+    db::release_advisory_lock(&client, &global)
+        .await
+        .expect("release failed");
+
+    assert!(
+        result.is_err(),
+        "Lock for another schema should wait while a global lock is held"
+    );
+
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_dotted_version_numbers() {
+    let (client, schema) = setup_schema("dotted").await;
+
+    let migrations = create_temp_migrations(&[
+        (
+            "V1.0__First.sql",
+            &format!("CREATE TABLE {}.dot_t1 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V1.1__Second.sql",
+            &format!("CREATE TABLE {}.dot_t2 (id SERIAL PRIMARY KEY);", schema),
+        ),
+        (
+            "V2.0__Third.sql",
+            &format!("CREATE TABLE {}.dot_t3 (id SERIAL PRIMARY KEY);", schema),
+        ),
+    ]);
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 3);
+
+    // Verify ordering: 1.0, 1.1, 2.0
+    assert_eq!(report.details[0].version.as_deref(), Some("1.0"));
+    assert_eq!(report.details[1].version.as_deref(), Some("1.1"));
+    assert_eq!(report.details[2].version.as_deref(), Some("2.0"));
+
+    // Verify all three tables exist
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    for tbl in &["dot_t1", "dot_t2", "dot_t3"] {
+        let exists = conn
+            .query_one(
+                "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = $2)",
+                &[&schema, tbl],
+            )
+            .await
+            .unwrap();
+        assert!(exists.get::<_, bool>(0), "Table {} should exist", tbl);
+    }
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_validate_on_migrate_detects_modification() {
+    let (client, schema) = setup_schema("val_mig").await;
+
+    let dir = std::env::temp_dir().join(format!(
+        "waypoint_test_val_mig_{}",
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(
+        dir.join("V1__Create_val_mig.sql"),
+        format!("CREATE TABLE {}.val_mig_tbl (id SERIAL);", schema),
+    )
+    .unwrap();
+
+    // First run: migrate with validate_on_migrate disabled
+    let config = test_config(&schema, dir.to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+    wp.migrate(None).await.expect("first migrate failed");
+
+    // Modify the migration file after first run
+    std::fs::write(
+        dir.join("V1__Create_val_mig.sql"),
+        format!(
+            "CREATE TABLE {}.val_mig_tbl (id SERIAL, extra TEXT);",
+            schema
+        ),
+    )
+    .unwrap();
+
+    // Add a V2 so there's a pending migration to trigger the migrate path
+    std::fs::write(
+        dir.join("V2__Another.sql"),
+        format!("CREATE TABLE {}.val_mig_tbl2 (id SERIAL);", schema),
+    )
+    .unwrap();
+
+    // Second run with validate_on_migrate=true — should fail
+    let mut config2 = test_config(&schema, dir.to_str().unwrap());
+    config2.migrations.validate_on_migrate = true;
+
+    let client2 = db::connect(&get_test_url()).await.unwrap();
+    let wp2 = Waypoint::with_client(config2, client2);
+    let result = wp2.migrate(None).await;
+    assert!(
+        result.is_err(),
+        "migrate should fail when validate_on_migrate detects checksum change"
+    );
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_migrate_creates_prerequisite_schema() {
+    let (client, schema) = setup_schema("prereq").await;
+    let app_schema = format!("{}_app", schema);
+
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_in_app.sql",
+        &format!(
+            "CREATE TABLE {}.widgets (id SERIAL PRIMARY KEY);",
+            app_schema
+        ),
+    )]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.prerequisites.schemas = vec![app_schema.clone()];
+    let wp = Waypoint::with_client(config, client);
+
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 1);
+
+    // Second run is idempotent — schema already exists
+    wp.migrate(None).await.expect("second migrate failed");
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let row = conn
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM pg_namespace WHERE nspname = $1)",
+            &[&app_schema],
+        )
+        .await
+        .unwrap();
+    assert!(row.get::<_, bool>(0));
+
+    teardown_schema(&conn, &app_schema).await;
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_preflight_replica_not_replicating() {
+    // The primary itself is not in recovery, so it reports as not replicating
+    // and the gate lets migrations through.
+    let config = PreflightConfig {
+        replica_urls: vec![get_test_url()],
+        replica_lag_gate: true,
+        ..Default::default()
+    };
+    let checks = preflight::check_replica_lag(&config, DialectKind::Postgres).await;
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].status, preflight::CheckStatus::Warn);
+    assert_eq!(checks[0].detail, "Server is not replicating");
+    preflight::wait_for_replicas(&config, DialectKind::Postgres)
+        .await
+        .expect("gate should not block on a non-replica");
+}
+
+#[tokio::test]
+async fn test_statement_progress_resume() {
+    let (client, schema) = setup_schema("resume").await;
+
+    // Statement 2 fails until `ext` exists; statement 1 is not idempotent, so
+    // a plain retry would fail on "already exists".
+    let migrations = create_temp_migrations(&[(
+        "V1__Non_transactional.sql",
+        &format!(
+            "-- waypoint:no-transaction\n\
+             CREATE TABLE {s}.items (id INT);\n\
+             INSERT INTO {s}.ext VALUES (1);\n\
+             INSERT INTO {s}.items VALUES (1);",
+            s = schema
+        ),
+    )]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.statement_progress = true;
+    let wp = Waypoint::with_client(config.clone(), client);
+
+    let err = wp.migrate(None).await.unwrap_err().to_string();
+    assert!(
+        err.contains("statement 2 of 3"),
+        "unexpected error: {}",
+        err
+    );
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let rows = conn
+        .query(
+            &format!(
+                "SELECT statement_index, success FROM {}.waypoint_schema_history_statements ORDER BY statement_index",
+                quote_ident(&schema)
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    let recorded: Vec<(i32, bool)> = rows.iter().map(|r| (r.get(0), r.get(1))).collect();
+    assert_eq!(recorded, vec![(1, true), (2, false)]);
+
+    conn.batch_execute(&format!(
+        "CREATE TABLE {}.ext (id INT)",
+        quote_ident(&schema)
+    ))
+    .await
+    .unwrap();
+
+    config.migrations.resume = true;
+    let client = db::connect(&get_test_url()).await.unwrap();
+    let wp = Waypoint::with_client(config, client);
+    let report = wp.migrate(None).await.expect("resume failed");
+    assert_eq!(report.migrations_applied, 1);
+
+    let row = conn
+        .query_one(
+            &format!("SELECT count(*) FROM {}.items", quote_ident(&schema)),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i64>(0), 1);
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_migrate_reports_rows_affected() {
+    let (client, schema) = setup_schema("rows").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            &format!("CREATE TABLE {}.items (id INT);", schema),
+        ),
+        (
+            "V2__Backfill.sql",
+            &format!(
+                "INSERT INTO {s}.items SELECT generate_series(1, 5);\n\
+                 UPDATE {s}.items SET id = id + 1 WHERE id > 3;",
+                s = schema
+            ),
+        ),
+        // SELECT row counts are not rows affected.
+        (
+            "V3__Read.sql",
+            &format!(
+                "SELECT * FROM {s}.items;\n-- trailing comment;\n\
+                 DELETE FROM {s}.items WHERE id = 1;",
+                s = schema
+            ),
+        ),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.warn_rows_affected = Some(3);
+    let wp = Waypoint::with_client(config, client);
+
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.details[0].rows_affected, 0);
+    assert_eq!(report.details[1].rows_affected, 7);
+    assert_eq!(report.details[2].rows_affected, 1);
+    let rows_warnings: Vec<_> = report
+        .warnings
+        .iter()
+        .filter(|w| w.code == waypoint_core::WarningCode::RowsAffected)
+        .collect();
+    assert_eq!(rows_warnings.len(), 1);
+    assert_eq!(rows_warnings[0].context, "V2__Backfill.sql");
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_wal_estimate_and_budget_guard() {
+    let (conn, schema) = setup_schema("wal_guard").await;
+    conn.batch_execute(&format!(
+        "CREATE TABLE {schema}.events (id int PRIMARY KEY, payload text);
+         INSERT INTO {schema}.events SELECT g, repeat('x', 100) FROM generate_series(1, 1000) g;"
+    ))
+    .await
+    .unwrap();
+
+    let scripts = ["UPDATE events SET payload = 'y'; CREATE TABLE other (id int);"];
+    let estimate = waypoint_core::wal::estimate_wal(&conn, &schema, &scripts)
+        .await
+        .unwrap();
+    assert_eq!(estimate.statements.len(), 1);
+    assert_eq!(estimate.statements[0].table, "events");
+    assert!(estimate.total_bytes > 0);
+
+    // pg_wal alone is larger than a 1MB budget, so the guard must block.
+    let config = PreflightConfig {
+        wal_budget_mb: 1,
+        ..Default::default()
+    };
+    let err = waypoint_core::wal::guard_wal(&conn, &config, &schema, &scripts)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("WAL Headroom"));
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_post_migrate_analyze() {
+    let (client, schema) = setup_schema("analyze").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            &format!(
+                "CREATE TABLE {s}.items (id INT, flag BOOLEAN);\n\
+                 INSERT INTO {s}.items SELECT generate_series(1, 100);",
+                s = schema
+            ),
+        ),
+        // Classified after placeholder substitution.
+        (
+            "V2__Backfill.sql",
+            "UPDATE ${schema}.items SET flag = true;",
+        ),
+        (
+            "V3__Noop.sql",
+            &format!(
+                "CREATE TABLE {s}.other (id INT);\nDELETE FROM {s}.other;",
+                s = schema
+            ),
+        ),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.post_migrate_analyze = true;
+    config.migrations.post_migrate_min_table_mb = 0;
+    // V2 touches 100 rows; V3's DELETE touches none and is left alone.
+    config.migrations.post_migrate_min_rows = 50;
+    config
+        .placeholders
+        .insert("schema".to_string(), schema.clone());
+    let wp = Waypoint::with_client(config, client);
+
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.maintenance.len(), 1);
+    assert_eq!(report.maintenance[0].table, format!("{}.items", schema));
+    assert_eq!(
+        report.maintenance[0].action,
+        waypoint_core::commands::migrate::MaintenanceAction::Analyze
+    );
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let analyzed: bool = conn
+        .query_one(
+            "SELECT last_analyze IS NOT NULL FROM pg_stat_user_tables \
+             WHERE schemaname = $1 AND relname = 'items'",
+            &[&schema],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert!(analyzed);
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_isolation_directive() {
+    let (client, schema) = setup_schema("isolation").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            &format!("CREATE TABLE {}.levels (script TEXT, level TEXT);", schema),
+        ),
+        (
+            "V2__Serializable.sql",
+            &format!(
+                "-- waypoint:isolation serializable\n\
+                 -- waypoint:retries 2\n\
+                 INSERT INTO {}.levels SELECT 'V2', current_setting('transaction_isolation');",
+                schema
+            ),
+        ),
+        (
+            "V3__Default.sql",
+            &format!(
+                "INSERT INTO {}.levels SELECT 'V3', current_setting('transaction_isolation');",
+                schema
+            ),
+        ),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.isolation_level =
+        Some(waypoint_core::directive::IsolationLevel::RepeatableRead);
+    let wp = Waypoint::with_client(config, client);
+    wp.migrate(None).await.expect("migrate failed");
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let rows = conn
+        .query(
+            &format!(
+                "SELECT script, level FROM {}.levels ORDER BY script",
+                schema
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    let levels: Vec<(String, String)> = rows.iter().map(|r| (r.get(0), r.get(1))).collect();
+    assert_eq!(
+        levels,
+        vec![
+            ("V2".to_string(), "serializable".to_string()),
+            ("V3".to_string(), "repeatable read".to_string()),
+        ]
+    );
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_lock_timeout_and_ddl_retry() {
+    let (client, schema) = setup_schema("lock_retry").await;
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    conn.batch_execute(&format!("CREATE TABLE {}.busy (id INT)", schema))
+        .await
+        .unwrap();
+    let migrations = create_temp_migrations(&[(
+        "V1__Alter_busy.sql",
+        &format!(
+            "ALTER TABLE {s}.busy ADD COLUMN note TEXT;\n\
+             CREATE TABLE {s}.seen AS SELECT current_setting('lock_timeout') AS lock_timeout;",
+            s = schema
+        ),
+    )]);
+    let lock = format!("BEGIN; LOCK TABLE {}.busy IN ACCESS EXCLUSIVE MODE", schema);
+
+    // The lock is released while the migration is backing off.
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.safety.lock_timeout_ms = 100;
+    config.safety.ddl_retry = 5;
+    config.safety.ddl_retry_backoff_ms = 100;
+    let locker = db::connect(&get_test_url()).await.unwrap();
+    locker.batch_execute(&lock).await.unwrap();
+    let release = tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+        locker.batch_execute("COMMIT").await.unwrap();
+        locker
+    });
+    let wp = Waypoint::with_client(config.clone(), client);
+    let report = wp
+        .migrate(None)
+        .await
+        .expect("migrate should retry past the lock");
+    assert_eq!(report.migrations_applied, 1);
+    let locker = release.await.unwrap();
+    let seen: String = conn
+        .query_one(&format!("SELECT lock_timeout FROM {}.seen", schema), &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(seen, "100ms");
+
+    // Without retries the migration gives up on the lock.
+    std::fs::write(
+        migrations.path().join("V2__Alter_again.sql"),
+        format!("ALTER TABLE {}.busy ADD COLUMN extra TEXT;", schema),
+    )
+    .unwrap();
+    config.safety.ddl_retry = 0;
+    locker.batch_execute(&lock).await.unwrap();
+    let wp = Waypoint::with_client(config, db::connect(&get_test_url()).await.unwrap());
+    let err = wp.migrate(None).await.unwrap_err();
+    locker.batch_execute("COMMIT").await.unwrap();
+    match err {
+        WaypointError::MigrationFailed { script, reason } => {
+            assert_eq!(script, "V2__Alter_again.sql");
+            assert!(reason.contains("lock timeout"), "got: {reason}");
+        }
+        other => panic!("expected MigrationFailed, got {other:?}"),
+    }
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_set_directive() {
+    let (client, schema) = setup_schema("set_directive").await;
+    let role = format!("{}_migrator", schema);
+    client
+        .batch_execute(&format!(
+            "CREATE ROLE {r} NOLOGIN; GRANT USAGE, CREATE ON SCHEMA {s} TO {r}",
+            r = role,
+            s = schema
+        ))
+        .await
+        .unwrap();
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__As_role.sql",
+            &format!(
+                "-- waypoint:set statement_timeout=45s, role={r}\n\
+                 CREATE TABLE {s}.owned AS SELECT current_setting('statement_timeout') AS st, current_user::text AS who;",
+                r = role,
+                s = schema
+            ),
+        ),
+        (
+            "V2__No_tx.sql",
+            &format!(
+                "-- waypoint:no-transaction\n\
+                 -- waypoint:set work_mem='7MB'\n\
+                 CREATE TABLE {}.nt AS SELECT current_setting('work_mem') AS wm;",
+                schema
+            ),
+        ),
+        (
+            "V3__After.sql",
+            &format!(
+                "CREATE TABLE {}.after AS SELECT current_setting('work_mem') AS wm, \
+                 current_setting('statement_timeout') AS st, current_user::text AS who;",
+                schema
+            ),
+        ),
+    ]);
+
+    // Session settings made at connect time (like `statement_timeout`).
+    client
+        .batch_execute("SET statement_timeout = '13s'; SET work_mem = '3MB'")
+        .await
+        .unwrap();
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 3);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let row = conn
+        .query_one(
+            &format!(
+                "SELECT o.st, o.who, t.tableowner::text FROM {s}.owned o, pg_tables t \
+                 WHERE t.schemaname = '{s}' AND t.tablename = 'owned'",
+                s = schema
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "45s");
+    assert_eq!(row.get::<_, String>(1), role);
+    assert_eq!(row.get::<_, String>(2), role);
+
+    let wm: String = conn
+        .query_one(&format!("SELECT wm FROM {}.nt", schema), &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(wm, "7MB");
+
+    // Nothing leaks into the next migration.
+    let row = conn
+        .query_one(&format!("SELECT wm, st, who FROM {}.after", schema), &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "3MB");
+    assert_eq!(row.get::<_, String>(1), "13s");
+    assert_ne!(row.get::<_, String>(2), role);
+
+    teardown_schema(&conn, &schema).await;
+    conn.batch_execute(&format!("DROP ROLE IF EXISTS {}", role))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_schedule_and_run_scheduled() {
+    let (client, schema) = setup_schema("schedule").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create.sql",
+        &format!("CREATE TABLE {}.jobs (id INT);", schema),
+    )]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    // Listing and cancelling do not create the control table.
+    assert!(wp.list_schedules().await.unwrap().is_empty());
+    assert!(wp.cancel_schedule(1).await.is_err());
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let table_exists = || async {
+        conn.query_one(
+            &format!(
+                "SELECT to_regclass('{}.waypoint_schema_history_schedule') IS NOT NULL",
+                schema
+            ),
+            &[],
+        )
+        .await
+        .unwrap()
+        .get::<_, bool>(0)
+    };
+    assert!(!table_exists().await);
+
+    let stale = wp.schedule("2000-01-01T00:00Z", None, 1).await.unwrap();
+    assert_eq!(stale.status, "pending");
+    let due = wp.schedule("now", None, 60).await.unwrap();
+
+    let report = wp.run_scheduled().await.expect("run-scheduled failed");
+    assert_eq!(report.expired, 1);
+    let run = report.run.expect("a run should be due");
+    assert_eq!(run.id, due.id);
+    assert_eq!(run.status, "succeeded");
+    assert_eq!(report.migrate.unwrap().migrations_applied, 1);
+
+    let report = wp.run_scheduled().await.unwrap();
+    assert!(report.run.is_none());
+
+    let statuses: Vec<(i64, String)> = wp
+        .list_schedules()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.id, r.status))
+        .collect();
+    assert!(statuses.contains(&(stale.id, "expired".to_string())));
+    assert!(statuses.contains(&(due.id, "succeeded".to_string())));
+    assert!(wp.cancel_schedule(due.id).await.is_err());
+
+    // A claim left behind by a crashed runner is released once it expires.
+    let crashed = wp.schedule("now", None, 60).await.unwrap();
+    conn.execute(
+        &format!(
+            "UPDATE {}.waypoint_schema_history_schedule
+             SET status = 'running', claim_expires_at = now() - INTERVAL '1 minute'
+             WHERE id = $1",
+            schema
+        ),
+        &[&crashed.id],
+    )
+    .await
+    .unwrap();
+    let report = wp.run_scheduled().await.unwrap();
+    assert_eq!(report.reclaimed, 1);
+    assert_eq!(report.run.expect("the released run is due").id, crashed.id);
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_backup_before_destructive_migration() {
+    let (client, schema) = setup_schema("backup").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            &format!(
+                "CREATE TABLE {s}.keep (id INT);\nCREATE TABLE {s}.scratch (id INT);",
+                s = schema
+            ),
+        ),
+        ("V2__Drop.sql", &format!("DROP TABLE {}.scratch;", schema)),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.backup.command = Some("echo taking backup; echo \"snap-$WAYPOINT_VERSION\"".into());
+    config.reversals.enabled = false;
+    let wp = Waypoint::with_client(config, client);
+    wp.migrate(None).await.expect("migrate failed");
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    let backup_ref = |v: &str| {
+        applied
+            .iter()
+            .find(|a| a.version.as_deref() == Some(v))
+            .and_then(|a| a.backup_ref.clone())
+    };
+    assert_eq!(backup_ref("1"), None);
+    assert_eq!(backup_ref("2").as_deref(), Some("snap-2"));
+
+    // A failed run keeps its backup reference on the failure row.
+    std::fs::write(
+        migrations.path().join("V3__Drop_keep.sql"),
+        format!("DROP TABLE {}.keep;\nSELECT 1 / 0;", schema),
+    )
+    .unwrap();
+    assert!(wp.migrate(None).await.is_err());
+    let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    let failed = applied
+        .iter()
+        .find(|a| a.version.as_deref() == Some("3"))
+        .expect("failure row");
+    assert!(!failed.success);
+    assert_eq!(failed.backup_ref.as_deref(), Some("snap-3"));
+
+    // Undo without a U file points at the backup.
+    std::fs::remove_file(migrations.path().join("V3__Drop_keep.sql")).unwrap();
+    match wp.undo(UndoTarget::Count(1)).await.unwrap_err() {
+        waypoint_core::error::WaypointError::UndoMissing {
+            version,
+            backup_ref,
+        } => {
+            assert_eq!(version, "2");
+            assert_eq!(backup_ref.as_deref(), Some("snap-2"));
+        }
+        other => panic!("expected UndoMissing, got {}", other),
+    }
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_backup_failure_aborts_migration() {
+    let (client, schema) = setup_schema("backup_fail").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            &format!("CREATE TABLE {}.scratch (id INT);", schema),
+        ),
+        ("V2__Drop.sql", &format!("DROP TABLE {}.scratch;", schema)),
+    ]);
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.backup.command = Some("echo no space left >&2; exit 1".into());
+    let wp = Waypoint::with_client(config, client);
+    let err = wp.migrate(None).await.unwrap_err();
+    assert!(
+        matches!(err, waypoint_core::error::WaypointError::BackupFailed { ref script, .. } if script == "V2__Drop.sql")
+    );
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let exists: bool = conn
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[&format!("{}.scratch", schema)],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert!(exists, "table must survive when the backup fails");
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_export_with_anonymization() {
+    let (client, schema) = setup_schema("export").await;
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {s}.users (id INT PRIMARY KEY, email TEXT, name TEXT, ssn TEXT, bio TEXT);\n\
+             INSERT INTO {s}.users VALUES \
+             (1, 'alice@corp.com', 'Alice Real', '123-45-6789', 'likes O''Reilly books'), \
+             (2, 'bob@corp.com', 'Bob Real', NULL, NULL);",
+            s = schema
+        ))
+        .await
+        .unwrap();
+
+    let migrations = create_temp_migrations(&[]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.export.anonymize.insert(
+        "ssn".into(),
+        waypoint_core::commands::export::AnonymizeStrategy::Redact,
+    );
+
+    // Without a seed, hashing strategies refuse to run; redact needs none.
+    let unseeded =
+        Waypoint::with_client(config.clone(), db::connect(&get_test_url()).await.unwrap());
+    let err = unseeded
+        .export(&waypoint_core::ExportOptions {
+            table: "users".into(),
+            anonymize: vec!["email".into()],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("seed"), "{}", err);
+    let redacted = unseeded
+        .export(&waypoint_core::ExportOptions {
+            table: "users".into(),
+            ..Default::default()
+        })
+        .await
+        .expect("redact-only export needs no seed");
+    assert!(!redacted.script.contains("6789"));
+
+    config.export.seed = "test".into();
+    let wp = Waypoint::with_client(config, client);
+
+    let report = wp
+        .export(&waypoint_core::ExportOptions {
+            table: "users".into(),
+            anonymize: vec!["email".into(), "name".into()],
+            ..Default::default()
+        })
+        .await
+        .expect("export failed");
+    assert_eq!(report.rows, 2);
+    assert_eq!(report.anonymized.len(), 3);
+    assert!(!report.script.contains("corp.com"));
+    assert!(!report.script.contains("Real"));
+    assert!(!report.script.contains("6789"));
+    assert!(report.script.contains("O''Reilly"));
+
+    // The script reloads cleanly into the emptied table, with NULLs preserved.
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    conn.batch_execute(&format!("TRUNCATE {}.users", schema))
+        .await
+        .unwrap();
+    conn.batch_execute(&report.script).await.unwrap();
+    let row = conn
+        .query_one(
+            &format!(
+                "SELECT count(*), count(ssn), bool_and(email LIKE '%@example.com') FROM {}.users",
+                schema
+            ),
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i64>(0), 2);
+    assert_eq!(row.get::<_, i64>(1), 1);
+    assert!(row.get::<_, bool>(2));
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_drift_detects_manual_rename() {
+    let (client, schema) = setup_schema("drift_rename").await;
+    // Unqualified names so drift can replay the migrations in its temp schema.
+    let migrations = create_temp_migrations(&[(
+        "V1__Create.sql",
+        "CREATE TABLE orders (id INTEGER NOT NULL, total NUMERIC);\n\
+         CREATE TABLE notes (id INTEGER NOT NULL, body TEXT);\n\
+         CREATE INDEX notes_id_idx ON notes (id);",
+    )]);
+    client
+        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+        .await
+        .unwrap();
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(None).await.expect("migrate failed");
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    conn.batch_execute(&format!(
+        "ALTER TABLE {s}.orders RENAME TO purchases;\n\
+         ALTER TABLE {s}.notes DROP COLUMN body;\n\
+         DROP INDEX {s}.notes_id_idx;",
+        s = schema
+    ))
+    .await
+    .unwrap();
+
+    let wp = Waypoint::with_client(config, db::connect(&get_test_url()).await.unwrap());
+    let report = wp.drift().await.expect("drift failed");
+    assert!(report.has_drift);
+    assert!(report
+        .drifts
+        .iter()
+        .any(|d| d.object == "TABLE orders -> purchases"));
+    assert!(!report.drifts.iter().any(|d| d.object == "TABLE orders"));
+
+    let safe = report.corrective_sql(false);
+    assert!(safe.contains("ALTER TABLE IF EXISTS \"orders\" RENAME TO \"purchases\";"));
+    assert!(safe.contains("-- ALTER TABLE \"notes\" DROP COLUMN"));
+    assert!(!safe.contains("DROP TABLE"));
+    assert!(report.has_destructive());
+    let full = report.corrective_sql(true);
+    assert!(full
+        .lines()
+        .any(|l| l.starts_with("ALTER TABLE \"notes\" DROP COLUMN")));
+
+    // The revert script undoes the drift, pointing index definitions at the
+    // live schema rather than the replay schema.
+    let revert = report.revert_sql(false);
+    assert!(revert.contains("ALTER TABLE IF EXISTS \"purchases\" RENAME TO \"orders\";"));
+    assert!(revert.contains("ALTER TABLE \"notes\" ADD COLUMN \"body\" text"));
+    assert!(revert.contains(&format!("ON {}.notes", schema)));
+    assert!(!revert.contains("waypoint_drift_check"));
+    assert!(!report.revert_has_destructive());
+
+    // The generated fix is recorded as applied here, where the drift already
+    // is, so the next migrate has nothing to run.
+    let fix = wp
+        .drift_fix_migration(&report, false, false)
+        .await
+        .expect("generate fix failed");
+    assert_eq!(fix.version.as_deref(), Some("2"));
+    let migrated = wp.migrate(None).await.expect("migrate after fix failed");
+    assert_eq!(migrated.migrations_applied, 0);
+    let info = wp.info().await.unwrap();
+    assert!(info
+        .iter()
+        .any(|m| m.version.as_deref() == Some("2") && m.state == MigrationState::Applied));
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_diff_against_migration_files() {
+    let (client, schema) = setup_schema("diff_migrations").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            "CREATE TABLE accounts (id INTEGER PRIMARY KEY, name TEXT);",
+        ),
+        (
+            "V2__Add_email.sql",
+            "ALTER TABLE accounts ADD COLUMN email TEXT;\n\
+             CREATE INDEX accounts_email_idx ON accounts (email);",
+        ),
+        (
+            "R__Active.sql",
+            "CREATE OR REPLACE VIEW active_accounts AS SELECT id, name FROM accounts;",
+        ),
+    ]);
+    client
+        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+        .await
+        .unwrap();
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(Some("1")).await.expect("migrate failed");
+
+    // V2 is pending: the files describe more than the database holds.
+    let report = wp.diff(DiffTarget::Migrations).await.expect("diff failed");
+    assert!(report.has_changes);
+    assert!(report.generated_sql.contains("\"email\""));
+    assert!(report.generated_sql.contains("accounts_email_idx"));
+    assert!(!report.generated_sql.contains("waypoint_diff_"));
+    assert!(!report.generated_sql.contains(&config.migrations.table));
+
+    // Fully migrated, nothing differs; the view replayed from the repeatable
+    // compares equal to the live one.
+    wp.migrate(None).await.expect("migrate failed");
+    let report = wp.diff(DiffTarget::Migrations).await.expect("diff failed");
+    assert!(!report.has_changes, "{:?}", report.diffs);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_drift_against_stored_baseline() {
+    let (client, schema) = setup_schema("drift_stored").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create.sql",
+        &format!(
+            "CREATE TABLE {}.accounts (id INTEGER PRIMARY KEY, name TEXT);",
+            schema
+        ),
+    )]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(None).await.expect("migrate failed");
+    // Nothing stored unless [drift] store_baseline is set.
+    assert!(wp.drift_stored().await.is_err());
+
+    config.drift.store_baseline = true;
+    let wp = Waypoint::with_client(config.clone(), db::connect(&get_test_url()).await.unwrap());
+    // Nothing pending, but no baseline yet: migrate records one.
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    let report = wp.drift_stored().await.expect("drift failed");
+    assert!(!report.has_drift, "{:?}", report.drifts);
+    let baseline = report.baseline.expect("baseline comparison");
+    assert_eq!(baseline.expected, baseline.actual);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    conn.batch_execute(&format!(
+        "ALTER TABLE {}.accounts ADD COLUMN email TEXT",
+        schema
+    ))
+    .await
+    .unwrap();
+
+    // A migrate that applies nothing keeps the baseline, so the drift stays visible.
+    wp.migrate(None).await.expect("migrate failed");
+    let report = wp.drift_stored().await.expect("drift failed");
+    assert!(report.has_drift);
+    assert!(report
+        .drifts
+        .iter()
+        .any(|d| d.object == "COLUMN accounts.email"));
+    let baseline = report.baseline.expect("baseline comparison");
+    assert_ne!(baseline.expected, baseline.actual);
+
+    teardown_schema(&conn, &schema).await;
+}
+
+/// The test URL pointed at database `name`, as a URL or a key=value string.
+fn url_for_database(url: &str, name: &str) -> String {
+    if url.contains("://") {
+        let (base, query) = url.split_once('?').unwrap_or((url, ""));
+        let (server, _) = base.rsplit_once('/').unwrap_or((base, ""));
+        let query = if query.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", query)
+        };
+        return format!("{}/{}{}", server, name, query);
+    }
+    let mut parts: Vec<String> = url
+        .split_whitespace()
+        .filter(|part| !part.starts_with("dbname="))
+        .map(str::to_string)
+        .collect();
+    parts.push(format!("dbname={}", name));
+    parts.join(" ")
+}
+
+#[tokio::test]
+async fn test_for_each_tenant() {
+    use waypoint_core::db::DbClient;
+    use waypoint_core::multi::{MultiWaypoint, TenantOptions};
+
+    let (client, schema) = setup_schema("tenants").await;
+    let url = get_test_url();
+    let tenant_dbs = ["waypoint_tenant_a", "waypoint_tenant_b"];
+    for name in tenant_dbs {
+        // One statement per call: DROP/CREATE DATABASE can't share a transaction.
+        client
+            .batch_execute(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name))
+            .await
+            .unwrap();
+        client
+            .batch_execute(&format!("CREATE DATABASE {}", name))
+            .await
+            .unwrap();
+    }
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {s}.tenants (db_url TEXT, name TEXT);\n\
+             INSERT INTO {s}.tenants VALUES\n\
+             ('{a}', 'a'),\n\
+             ('{b}', 'b'),\n\
+             ('{missing}', 'missing')",
+            s = schema,
+            a = url_for_database(&url, "waypoint_tenant_a").replace('\'', "''"),
+            b = url_for_database(&url, "waypoint_tenant_b").replace('\'', "''"),
+            missing = url_for_database(&url, "waypoint_tenant_missing").replace('\'', "''"),
+        ))
+        .await
+        .unwrap();
+
+    let migrations = create_temp_migrations(&[(
+        "V1__Widgets.sql",
+        "CREATE TABLE public.widgets (id INTEGER PRIMARY KEY);",
+    )]);
+    let mut config = test_config("public", migrations.path().to_str().unwrap());
+    let control = DbClient::with_postgres(client);
+    let mut options = TenantOptions {
+        query: format!("SELECT db_url, name FROM {}.tenants ORDER BY name", schema),
+        concurrency: 2,
+        target_version: None,
+        force: false,
+        fail_fast: false,
+        dry_run: true,
+    };
+
+    // A NULL URL is an error, not a panic.
+    let null_query = TenantOptions {
+        query: "SELECT NULL::text, 'x'".to_string(),
+        ..options.clone()
+    };
+    assert!(
+        MultiWaypoint::for_each_tenant(&config, &control, &null_query)
+            .await
+            .is_err()
+    );
+
+    let result = MultiWaypoint::for_each_tenant(&config, &control, &options)
+        .await
+        .expect("tenant discovery failed");
+    assert!(result.results[0].success, "{}", result.results[0].message);
+    assert_eq!(result.results[0].message, "Would apply V1__Widgets.sql");
+    assert!(result.results[0].applied.is_empty());
+
+    // The version filter and [policy] apply to every tenant.
+    options.dry_run = false;
+    config.migrations.skip_versions = vec!["1".to_string()];
+    let result = MultiWaypoint::for_each_tenant(&config, &control, &options)
+        .await
+        .expect("tenant discovery failed");
+    assert!(result.results[0].success, "{}", result.results[0].message);
+    assert!(result.results[0].applied.is_empty());
+    config.migrations.skip_versions.clear();
+    config.policy.commands.insert(
+        "migrate".to_string(),
+        waypoint_core::policy::CommandPolicy::Never,
+    );
+    assert!(matches!(
+        MultiWaypoint::for_each_tenant(&config, &control, &options).await,
+        Err(waypoint_core::error::WaypointError::PolicyDenied { .. })
+    ));
+    config.policy.commands.clear();
+
+    let result = MultiWaypoint::for_each_tenant(&config, &control, &options)
+        .await
+        .expect("tenant discovery failed");
+
+    let names: Vec<&str> = result.results.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "missing"]);
+    assert!(result.results[0].success, "{}", result.results[0].message);
+    assert!(result.results[1].success, "{}", result.results[1].message);
+    assert_eq!(result.results[0].applied, vec!["V1__Widgets.sql"]);
+    assert!(!result.results[2].success);
+    assert!(!result.all_succeeded);
+
+    for name in tenant_dbs {
+        let tenant = db::connect(&url_for_database(&url, name)).await.unwrap();
+        let row = tenant
+            .query_one("SELECT to_regclass('public.widgets') IS NOT NULL", &[])
+            .await
+            .unwrap();
+        assert!(row.get::<_, bool>(0));
+    }
+
+    let conn = db::connect(&url).await.unwrap();
+    for name in tenant_dbs {
+        conn.batch_execute(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name))
+            .await
+            .unwrap();
+    }
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_read_only_server_fails_early() {
+    let (client, schema) = setup_schema("standby").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create.sql",
+        "CREATE TABLE items (id INTEGER PRIMARY KEY);",
+    )]);
+    client
+        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+        .await
+        .unwrap();
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    Waypoint::with_client(config.clone(), client)
+        .migrate(None)
+        .await
+        .expect("migrate failed");
+
+    // A session that is read-only by default stands in for a hot standby.
+    let read_only = || async {
+        let c = db::connect(&get_test_url()).await.unwrap();
+        c.batch_execute("SET default_transaction_read_only = on")
+            .await
+            .unwrap();
+        c
+    };
+
+    let wp = Waypoint::with_client(config.clone(), read_only().await);
+    for err in [
+        wp.migrate(None).await.unwrap_err(),
+        wp.repair().await.unwrap_err(),
+    ] {
+        assert!(matches!(
+            err,
+            waypoint_core::error::WaypointError::ReadOnlyStandby { .. }
+        ));
+    }
+    assert!(matches!(
+        wp.info().await.unwrap_err(),
+        waypoint_core::error::WaypointError::ReadOnlyStandby { ref command, .. } if command == "info"
+    ));
+
+    config.migrations.allow_standby = true;
+    let wp = Waypoint::with_client(config.clone(), read_only().await);
+    assert_eq!(wp.info().await.expect("info failed").len(), 1);
+    assert!(wp.validate().await.expect("validate failed").valid);
+    assert!(wp.migrate(None).await.is_err());
+    assert!(matches!(
+        wp.drift().await.unwrap_err(),
+        waypoint_core::error::WaypointError::ConfigError(_)
+    ));
+
+    config.migrations.standby_scratch_url = Some(get_test_url());
+    let wp = Waypoint::with_client(config, read_only().await);
+    let report = wp.drift().await.expect("drift failed");
+    assert!(!report.has_drift, "unexpected drift: {:?}", report.drifts);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_strict_mode_rejects_force() {
+    let (client, schema) = setup_schema("strict").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create.sql",
+        &format!("CREATE TABLE {}.t (id INT);", schema),
+    )]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.mode.strict = true;
+    config.apply_strict().unwrap();
+    let wp = Waypoint::with_client(config, client);
+
+    let err = wp.migrate_with_options(None, true).await.unwrap_err();
+    assert!(err.to_string().contains("strict mode"));
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
+        .await
+        .unwrap_or_default();
+    assert!(applied.is_empty());
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_metrics_summary() {
+    let (client, schema) = setup_schema("metrics").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            &format!("CREATE TABLE {}.t (id INT);", schema),
+        ),
+        (
+            "V2__Add.sql",
+            &format!("ALTER TABLE {}.t ADD COLUMN name TEXT;", schema),
+        ),
+    ]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    let empty = wp.metrics_summary(None, 5).await.unwrap();
+    assert_eq!(empty.applied, 0);
+
+    wp.migrate(None).await.unwrap();
+    let summary = wp
+        .metrics_summary(Some(chrono::Duration::days(30)), 1)
+        .await
+        .unwrap();
+    assert_eq!(summary.applied, 2);
+    assert_eq!(summary.failed, 0);
+    assert_eq!(summary.undone, 0);
+    assert_eq!(summary.longest.len(), 1);
+    assert!(summary.last_run.is_some());
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_plan_apply_rejects_diverged_plan() {
+    let (client, schema) = setup_schema("plan").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create.sql",
+            &format!("CREATE TABLE {}.t (id INT);", schema),
+        ),
+        (
+            "V2__Add.sql",
+            &format!("ALTER TABLE {}.t ADD COLUMN name TEXT;", schema),
+        ),
+    ]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+
+    let plan = wp.plan(None).await.unwrap();
+    assert_eq!(plan.current_version, None);
+    let scripts: Vec<_> = plan.migrations.iter().map(|m| m.script.as_str()).collect();
+    assert_eq!(scripts, ["V1__Create.sql", "V2__Add.sql"]);
+
+    // A plan stored as JSON survives the round trip.
+    let json = serde_json::to_string(&plan).unwrap();
+    let stored: waypoint_core::MigrationPlan = serde_json::from_str(&json).unwrap();
+
+    // Editing a planned file after review makes the plan stale.
+    std::fs::write(
+        migrations.path().join("V2__Add.sql"),
+        format!("ALTER TABLE {}.t ADD COLUMN email TEXT;", schema),
+    )
+    .unwrap();
+    let err = wp.apply(&stored).await.unwrap_err();
+    assert!(matches!(
+        err,
+        waypoint_core::error::WaypointError::PlanDiverged { .. }
+    ));
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
+        .await
+        .unwrap_or_default();
+    assert!(applied.is_empty());
+
+    // A fresh plan applies exactly what it lists.
+    let plan = wp.plan(None).await.unwrap();
+    let report = wp.apply(&plan).await.unwrap();
+    assert_eq!(report.migrations_applied, 2);
+
+    let after = wp.plan(None).await.unwrap();
+    assert!(after.is_empty());
+    assert_eq!(after.current_version.as_deref(), Some("2"));
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_policy_blocks_library_clean() {
+    let (client, schema) = setup_schema("policy").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create.sql",
+        &format!("CREATE TABLE {}.t (id INT);", schema),
+    )]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.clean_enabled = true;
+    config.policy.commands.insert(
+        "clean".to_string(),
+        waypoint_core::policy::CommandPolicy::Never,
+    );
+    config.policy.commands.insert(
+        "migrate".to_string(),
+        waypoint_core::policy::CommandPolicy::RequireOverride,
+    );
+    let mut wp = Waypoint::with_client(config, client);
+
+    let err = wp.migrate(None).await.unwrap_err();
+    assert!(matches!(
+        err,
+        waypoint_core::error::WaypointError::PolicyDenied { .. }
+    ));
+    wp.config.policy.overridden = true;
+    wp.migrate(None).await.unwrap();
+
+    let err = wp.clean(true).await.unwrap_err();
+    assert!(matches!(
+        err,
+        waypoint_core::error::WaypointError::PolicyDenied { .. }
+    ));
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let exists: bool = conn
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_schema = $1 AND table_name = 't')",
+            &[&schema],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert!(exists);
+    teardown_schema(&conn, &schema).await;
+}
+
+/// Minimal pool for the pooled-connection tests: connects on each checkout
+/// and counts checkouts.
+struct CountingPool {
+    checkouts: AtomicU64,
+}
+
+#[async_trait::async_trait]
+impl db::PostgresPool for CountingPool {
+    async fn get(&self) -> waypoint_core::error::Result<Box<dyn db::PooledClient>> {
+        self.checkouts.fetch_add(1, Ordering::SeqCst);
+        Ok(Box::new(db::connect(&get_test_url()).await?))
+    }
+}
+
+#[tokio::test]
+async fn test_migrate_through_pool() {
+    use waypoint_core::multi::{MultiWaypoint, NamedDatabaseConfig};
+
+    let (client, schema) = setup_schema("pool").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_pool_t.sql",
+        &format!("CREATE TABLE {}.pool_t (id SERIAL PRIMARY KEY);", schema),
+    )]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let pool = CountingPool {
+        checkouts: AtomicU64::new(0),
+    };
+
+    let wp = Waypoint::with_pool(config.clone(), &pool)
+        .await
+        .expect("checkout failed");
+    let report = wp.migrate(None).await.expect("migrate through pool failed");
+    assert_eq!(report.migrations_applied, 1);
+    drop(wp);
+
+    // Multi-db runs check the named database's connection out of its pool.
+    let databases = vec![NamedDatabaseConfig {
+        name: "main".to_string(),
+        database: config.database.clone(),
+        migrations: config.migrations.clone(),
+        hooks: config.hooks.clone(),
+        placeholders: HashMap::new(),
+        depends_on: Vec::new(),
+        tags: Vec::new(),
+        guards: Default::default(),
+    }];
+    let mut pools: HashMap<String, &dyn db::PostgresPool> = HashMap::new();
+    pools.insert("main".to_string(), &pool);
+    let clients =
+        MultiWaypoint::connect_with_pools(&WaypointConfig::default(), &databases, None, &pools)
+            .await
+            .expect("multi connect failed");
+    let result = MultiWaypoint::migrate(
+        &WaypointConfig::default(),
+        &databases,
+        &clients,
+        &["main".to_string()],
+        None,
+        true,
+    )
+    .await
+    .expect("multi migrate failed");
+    assert!(result.all_succeeded);
+    assert_eq!(pool.checkouts.load(Ordering::SeqCst), 2);
+
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_seed_applies_once_and_tracks_outside_history() {
+    use waypoint_core::SeedStatus;
+
+    let (client, schema) = setup_schema("seed").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_countries.sql",
+        &format!(
+            "CREATE TABLE {}.countries (code TEXT PRIMARY KEY, name TEXT);",
+            schema
+        ),
+    )]);
+    let seeds = create_temp_migrations(&[
+        (
+            "S1__Countries.sql",
+            "INSERT INTO ${waypoint:schema}.countries VALUES ('nl', 'Netherlands');",
+        ),
+        (
+            "S2__Test_countries.sql",
+            "-- waypoint:env dev\nINSERT INTO ${waypoint:schema}.countries VALUES ('xx', 'Test');",
+        ),
+        (
+            "S3__Guarded.sql",
+            &format!(
+                "-- waypoint:require sql(\"SELECT NOT EXISTS (SELECT 1 FROM {}.countries)\")\n\
+                 INSERT INTO ${{waypoint:schema}}.countries VALUES ('yy', 'Never');",
+                schema
+            ),
+        ),
+    ]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.seeds.locations = vec![seeds.path().to_path_buf()];
+    config.migrations.environment = Some("prod".to_string());
+    let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(None).await.expect("migrate failed");
+
+    let report = wp.seed(true, false).await.expect("seed dry run failed");
+    assert_eq!(report.applied, 2);
+    let statuses: Vec<SeedStatus> = report.seeds.iter().map(|s| s.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            SeedStatus::Applied,
+            SeedStatus::SkippedEnvironment,
+            SeedStatus::Applied
+        ]
+    );
+
+    let report = wp.seed(false, false).await.expect("seed failed");
+    assert_eq!(report.applied, 1);
+    // The guard sees the row S1 just inserted.
+    assert_eq!(report.seeds[2].status, SeedStatus::SkippedGuard);
+
+    // Unchanged seeds don't run again; --reapply forces them.
+    let report = wp.seed(false, false).await.expect("seed failed");
+    assert_eq!(report.applied, 0);
+    assert_eq!(report.seeds[0].status, SeedStatus::Unchanged);
+    std::fs::write(
+        seeds.path().join("S1__Countries.sql"),
+        "INSERT INTO ${waypoint:schema}.countries VALUES ('be', 'Belgium');",
+    )
+    .unwrap();
+    let report = wp.seed(false, false).await.expect("seed failed");
+    assert_eq!(report.seeds[0].status, SeedStatus::Reapplied);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let count: i64 = conn
+        .query_one(&format!("SELECT count(*) FROM {}.countries", schema), &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(count, 2);
+    let runs: i32 = conn
+        .query_one(
+            &format!(
+                "SELECT runs FROM {}.waypoint_schema_history_seeds WHERE script = 'S1__Countries.sql'",
+                schema
+            ),
+            &[],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(runs, 2);
+    // Seeds stay out of the schema history.
+    let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    assert_eq!(applied.len(), 1);
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_canary_rollout_promotes_or_stops() {
+    use waypoint_core::multi::{CanaryOptions, CanaryOutcome, MultiWaypoint, NamedDatabaseConfig};
+
+    let (client, canary_schema) = setup_schema("canary_first").await;
+    let (_, rest_schema) = setup_schema("canary_rest").await;
+    let canary_migrations = create_temp_migrations(&[(
+        "V1__Create_canary_t.sql",
+        &format!("CREATE TABLE {}.canary_t (id INT);", canary_schema),
+    )]);
+    let rest_migrations = create_temp_migrations(&[(
+        "V1__Create_rest_t.sql",
+        &format!("CREATE TABLE {}.rest_t (id INT);", rest_schema),
+    )]);
+    let named = |name: &str, schema: &str, dir: &TempDir| {
+        let config = test_config(schema, dir.path().to_str().unwrap());
+        NamedDatabaseConfig {
+            name: name.to_string(),
+            database: config.database,
+            migrations: config.migrations,
+            hooks: config.hooks,
+            placeholders: HashMap::new(),
+            depends_on: Vec::new(),
+            tags: Vec::new(),
+            guards: Default::default(),
+        }
+    };
+    let databases = vec![
+        named("rest", &rest_schema, &rest_migrations),
+        named("canary", &canary_schema, &canary_migrations),
+    ];
+    let order = vec!["rest".to_string(), "canary".to_string()];
+    let canary = |health_sql: String| CanaryOptions {
+        databases: vec!["canary".to_string()],
+        bake_time: std::time::Duration::ZERO,
+        poll_interval: std::time::Duration::from_secs(1),
+        health_sql: Some(health_sql),
+        probes: Vec::new(),
+    };
+
+    // An unhealthy canary keeps the rest of the fleet untouched.
+    let clients = MultiWaypoint::connect(&WaypointConfig::default(), &databases, None)
+        .await
+        .unwrap();
+    let result = MultiWaypoint::migrate_canary(
+        &WaypointConfig::default(),
+        &databases,
+        clients,
+        &order,
+        &canary("SELECT 1 WHERE false".to_string()),
+        None,
+        false,
+        false,
+        1,
+    )
+    .await
+    .expect("canary rollout failed");
+    assert!(!result.all_succeeded);
+    let report = result.canary.as_ref().expect("canary report");
+    assert_eq!(report.outcome, CanaryOutcome::Unhealthy);
+    assert!(!report.checks[0].passed);
+    assert!(result.results[1].success);
+    assert!(!result.results[0].success);
+    assert!(result.results[0].message.contains("health check"));
+
+    // A healthy canary is promoted.
+    let clients = MultiWaypoint::connect(&WaypointConfig::default(), &databases, None)
+        .await
+        .unwrap();
+    let result = MultiWaypoint::migrate_canary(
+        &WaypointConfig::default(),
+        &databases,
+        clients,
+        &order,
+        &canary(format!(
+            "SELECT count(*) = 0 FROM {}.canary_t",
+            canary_schema
+        )),
+        None,
+        false,
+        false,
+        1,
+    )
+    .await
+    .expect("canary rollout failed");
+    assert!(result.all_succeeded, "{:?}", result.results);
+    let report = result.canary.as_ref().expect("canary report");
+    assert_eq!(report.outcome, CanaryOutcome::Promoted);
+    assert_eq!(report.databases, vec!["canary".to_string()]);
+    assert_eq!(result.results[0].applied, vec!["V1__Create_rest_t.sql"]);
+
+    teardown_schema(&client, &canary_schema).await;
+    teardown_schema(&client, &rest_schema).await;
+}
+
+#[tokio::test]
+async fn test_parallel_migrations_apply_independent_waves() {
+    let (client, schema) = setup_schema("parallel").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_a.sql",
+            &format!("CREATE TABLE {}.par_a (id INT PRIMARY KEY);", schema),
+        ),
+        (
+            "V2__Create_b.sql",
+            &format!(
+                "-- waypoint:depends 1\nCREATE TABLE {}.par_b (id INT PRIMARY KEY);",
+                schema
+            ),
+        ),
+        (
+            "V3__Create_c.sql",
+            &format!(
+                "-- waypoint:depends 1\nCREATE TABLE {}.par_c (id INT PRIMARY KEY);",
+                schema
+            ),
+        ),
+        (
+            "V4__Create_d.sql",
+            &format!(
+                "-- waypoint:depends 2,3\nCREATE TABLE {s}.par_d (b_id INT REFERENCES {s}.par_b (id));",
+                s = schema
+            ),
+        ),
+    ]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.dependency_ordering = true;
+    config.migrations.parallel_migrations = 2;
+    // Reversal snapshots cover the whole schema, so they force serial apply.
+    config.reversals.enabled = false;
+
+    let wp = Waypoint::new(config).await.unwrap();
+    let report = wp.migrate(None).await.expect("parallel migrate failed");
+    assert_eq!(report.migrations_applied, 4);
+
+    // V2 and V3 ran side by side but still got distinct ranks.
+    let applied = history::get_applied_migrations(&client, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    let mut ranks: Vec<i32> = applied.iter().map(|a| a.installed_rank).collect();
+    ranks.sort();
+    assert_eq!(ranks, vec![1, 2, 3, 4]);
+    assert!(applied.iter().all(|a| a.success));
+
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_parallel_wave_member_with_set_role() {
+    let (client, schema) = setup_schema("parallel_role").await;
+    // The role may create tables but has no rights on the history table.
+    let role = format!("{}_migrator", schema);
+    client
+        .batch_execute(&format!(
+            "CREATE ROLE {r} NOLOGIN; GRANT USAGE, CREATE ON SCHEMA {s} TO {r}",
+            r = role,
+            s = schema
+        ))
+        .await
+        .unwrap();
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_a.sql",
+            &format!("CREATE TABLE {}.par_a (id INT PRIMARY KEY);", schema),
+        ),
+        (
+            "V2__As_role.sql",
+            &format!(
+                "-- waypoint:depends 1\n\
+                 -- waypoint:set role={r}\n\
+                 CREATE TABLE {s}.par_b (id INT PRIMARY KEY);",
+                r = role,
+                s = schema
+            ),
+        ),
+        (
+            "V3__Create_c.sql",
+            &format!(
+                "-- waypoint:depends 1\nCREATE TABLE {}.par_c (id INT PRIMARY KEY);",
+                schema
+            ),
+        ),
+    ]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.dependency_ordering = true;
+    config.migrations.parallel_migrations = 2;
+    config.reversals.enabled = false;
+
+    let wp = Waypoint::new(config).await.unwrap();
+    let report = wp.migrate(None).await.expect("parallel migrate failed");
+    assert_eq!(report.migrations_applied, 3);
+
+    let owner: String = client
+        .query_one(
+            "SELECT tableowner::text FROM pg_tables WHERE schemaname = $1 AND tablename = 'par_b'",
+            &[&schema],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(owner, role);
+    let applied = history::get_applied_migrations(&client, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    assert!(applied.iter().all(|a| a.success));
+
+    teardown_schema(&client, &schema).await;
+    client
+        .batch_execute(&format!("DROP ROLE IF EXISTS {}", role))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_migrate_sends_notify_payload() {
+    use waypoint_core::notify::MigrationNotification;
+
+    let (client, schema) = setup_schema("notify").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_notify_t.sql",
+        &format!("CREATE TABLE {}.notify_t (id INT);", schema),
+    )]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.notify.enabled = true;
+    config.notify.channel = schema.clone();
+
+    let (listener, mut notifications) = db::connect_listener(&get_test_url(), &config.database)
+        .await
+        .unwrap();
+    listener
+        .batch_execute(&format!("LISTEN {}", db::quote_ident(&schema)))
+        .await
+        .unwrap();
+
+    let wp = Waypoint::new(config).await.unwrap();
+    let report = wp.migrate(None).await.unwrap();
+    assert!(!report
+        .warnings
+        .iter()
+        .any(|w| w.code == waypoint_core::WarningCode::NotifyFailed));
+
+    let n = tokio::time::timeout(std::time::Duration::from_secs(10), notifications.recv())
+        .await
+        .expect("no notification")
+        .unwrap();
+    let payload: MigrationNotification = serde_json::from_str(n.payload()).unwrap();
+    assert_eq!(payload.event, "migrate");
+    assert_eq!(payload.schema, schema);
+    assert_eq!(payload.migrations_applied, 1);
+    assert_eq!(payload.migrations[0].script, "V1__Create_notify_t.sql");
+
+    // Nothing pending: no second notification.
+    wp.migrate(None).await.unwrap();
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(300), notifications.recv())
+            .await
+            .is_err()
+    );
+
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_copy_directive_loads_csv_in_migration_transaction() {
+    let (client, schema) = setup_schema("copy").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_countries.sql",
+            &format!(
+                "-- waypoint:copy table=${{waypoint:schema}}.countries file=countries.csv\n\
+                 CREATE TABLE {}.countries (code TEXT PRIMARY KEY, name TEXT);",
+                schema
+            ),
+        ),
+        (
+            "V2__Load_more.sql",
+            &format!(
+                "-- waypoint:copy table=${{waypoint:schema}}.countries file=more.csv columns=code\n\
+                 CREATE TABLE {}.marker (id INT);",
+                schema
+            ),
+        ),
+    ]);
+    std::fs::write(
+        migrations.path().join("countries.csv"),
+        "code,name\nnl,Netherlands\nbe,\"Belgium, Kingdom of\"\n",
+    )
+    .unwrap();
+    // The duplicate key fails the COPY, which rolls back V2 as a whole.
+    std::fs::write(migrations.path().join("more.csv"), "code\nde\nnl\n").unwrap();
+
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config, client);
+    let err = wp.migrate(None).await.unwrap_err();
+    assert!(err.to_string().contains("V2__Load_more.sql"), "{}", err);
+
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let names: Vec<String> = conn
+        .query(
+            &format!("SELECT name FROM {}.countries ORDER BY code", schema),
+            &[],
+        )
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+    assert_eq!(names, vec!["Belgium, Kingdom of", "Netherlands"]);
+    let marker: bool = conn
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[&format!("{}.marker", schema)],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert!(!marker);
+    let applied = history::get_applied_migrations(&conn, &schema, "waypoint_schema_history")
+        .await
+        .unwrap();
+    assert_eq!(applied.iter().filter(|a| a.success).count(), 1);
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_pending_report_summarizes_and_notifies() {
+    use waypoint_core::commands::pending_report::PendingNotification;
+    use waypoint_core::safety::SafetyVerdict;
+
+    let (client, schema) = setup_schema("pending_report").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_pr_t.sql",
+            &format!("CREATE TABLE {}.pr_t (id INT, name TEXT);", schema),
+        ),
+        (
+            "V2__Drop_pr_name.sql",
+            &format!("ALTER TABLE {}.pr_t DROP COLUMN name;", schema),
+        ),
+    ]);
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.notify.channel = schema.clone();
+
+    let (listener, mut notifications) = db::connect_listener(&get_test_url(), &config.database)
+        .await
+        .unwrap();
+    listener
+        .batch_execute(&format!("LISTEN {}", db::quote_ident(&schema)))
+        .await
+        .unwrap();
+
+    let wp = Waypoint::new(config).await.unwrap();
+    wp.migrate(Some("1")).await.unwrap();
+
+    // Without notify, nothing is sent.
+    let summary = wp.pending_report(false, None).await.unwrap();
+    assert!(!summary.notified);
+    assert_eq!(summary.pending.len(), 1);
+    assert_eq!(summary.pending[0].script, "V2__Drop_pr_name.sql");
+    assert_eq!(summary.worst_verdict, SafetyVerdict::Caution);
+    assert_eq!(summary.pending[0].age_days, Some(0));
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_millis(300), notifications.recv())
+            .await
+            .is_err()
+    );
+
+    let summary = wp.pending_report(true, None).await.unwrap();
+    assert!(summary.notified);
+    let n = tokio::time::timeout(std::time::Duration::from_secs(10), notifications.recv())
+        .await
+        .expect("no notification")
+        .unwrap();
+    let payload: PendingNotification = serde_json::from_str(n.payload()).unwrap();
+    assert_eq!(payload.event, "pending_report");
+    assert_eq!(payload.schema, schema);
+    assert_eq!(payload.pending_count, 1);
+    assert!(payload.text.contains("`V2__Drop_pr_name.sql` CAUTION"));
+
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_find_matches_database_and_snapshot() {
+    use waypoint_core::{FindQuery, ObjectType};
+
+    let (client, schema) = setup_schema("find").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_find_tables.sql",
+        &format!(
+            "CREATE TABLE {s}.users_old (id INT PRIMARY KEY, email TEXT);\n\
+             CREATE INDEX users_old_email ON {s}.users_old (email);\n\
+             CREATE TABLE {s}.orders (id INT);",
+            s = schema
+        ),
+    )]);
+    let snapshot_dir = tempfile::TempDir::new().unwrap();
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.snapshots.directory = snapshot_dir.path().to_path_buf();
+
+    let wp = Waypoint::new(config.clone()).await.unwrap();
+    wp.migrate(None).await.unwrap();
+    let snapshot = wp.snapshot(&config.snapshots).await.unwrap();
+
+    let query = FindQuery::new("USERS_%", vec![ObjectType::Table, ObjectType::Index]).unwrap();
+    let report = wp
+        .find(&query, &[], std::slice::from_ref(&snapshot.snapshot_id))
+        .await
+        .unwrap();
+
+    assert_eq!(report.sources.len(), 2);
+    for source in &report.sources {
+        let names: Vec<&str> = source.objects.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["users_old", "users_old_email", "users_old_pkey"],
+            "source {}",
+            source.source
+        );
+    }
+    assert_eq!(report.sources[0].source, "local");
+    assert_eq!(report.sources[1].source, snapshot.snapshot_id);
+    assert_eq!(report.total_matches, 6);
+
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_migrate_multiple_schemas() {
+    let (client, tenant_a) = setup_schema("tenant_a").await;
+    let (_, tenant_b) = setup_schema("tenant_b").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_users.sql",
+            "CREATE TABLE ${waypoint:schema}.users (id INT PRIMARY KEY);",
+        ),
+        (
+            "R__Users_view.sql",
+            "CREATE OR REPLACE VIEW ${waypoint:schema}.users_v AS SELECT id FROM ${waypoint:schema}.users;",
+        ),
+    ]);
+    let mut config = test_config(&tenant_a, migrations.path().to_str().unwrap());
+    config.migrations.schemas = vec![tenant_a.clone(), tenant_b.clone()];
+    let wp = Waypoint::new(config).await.unwrap();
+
+    let report = wp.migrate(None).await.unwrap();
+    assert_eq!(report.migrations_applied, 4);
+    assert_eq!(
+        waypoint_core::ConvergeReport::from_report(&report).migrations,
+        vec![
+            format!("{tenant_a}/V1__Create_users.sql"),
+            format!("{tenant_a}/R__Users_view.sql"),
+            format!("{tenant_b}/V1__Create_users.sql"),
+            format!("{tenant_b}/R__Users_view.sql"),
+        ]
+    );
+
+    // Each schema has its own objects and history rows.
+    for schema in [&tenant_a, &tenant_b] {
+        let rows: i64 = client
+            .query_one(
+                &format!(
+                    "SELECT count(*) FROM {}.waypoint_schema_history WHERE success",
+                    quote_ident(schema)
+                ),
+                &[],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(rows, 2);
+        client
+            .batch_execute(&format!("SELECT * FROM {}.users_v", quote_ident(schema)))
+            .await
+            .unwrap();
+    }
+
+    std::fs::write(
+        migrations.path().join("V2__Add_email.sql"),
+        "ALTER TABLE ${waypoint:schema}.users ADD COLUMN email TEXT;",
+    )
+    .unwrap();
+    let infos = wp.info().await.unwrap();
+    let rows: Vec<(Option<&str>, &str, MigrationState)> = infos
+        .iter()
+        .map(|i| (i.schema.as_deref(), i.script.as_str(), i.state.clone()))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (
+                Some(tenant_a.as_str()),
+                "V1__Create_users.sql",
+                MigrationState::Applied
+            ),
+            (
+                Some(tenant_a.as_str()),
+                "V2__Add_email.sql",
+                MigrationState::Pending
+            ),
+            (
+                Some(tenant_a.as_str()),
+                "R__Users_view.sql",
+                MigrationState::Applied
+            ),
+            (
+                Some(tenant_b.as_str()),
+                "V1__Create_users.sql",
+                MigrationState::Applied
+            ),
+            (
+                Some(tenant_b.as_str()),
+                "V2__Add_email.sql",
+                MigrationState::Pending
+            ),
+            (
+                Some(tenant_b.as_str()),
+                "R__Users_view.sql",
+                MigrationState::Applied
+            ),
+        ]
+    );
+    let pending =
+        waypoint_core::ConvergeReport::pending(&infos, None, &wp.config.migrations).unwrap();
+    assert_eq!(
+        pending.migrations,
+        vec![
+            format!("{tenant_a}/V2__Add_email.sql"),
+            format!("{tenant_b}/V2__Add_email.sql"),
+        ]
     );
 
-    teardown_schema(&conn, &schema).await;
+    teardown_schema(&client, &tenant_a).await;
+    teardown_schema(&client, &tenant_b).await;
 }
 
 #[tokio::test]
-async fn test_hooks_before_migrate() {
-    let (client, schema) = setup_schema("hooks").await;
-
-    let dir = std::env::temp_dir().join(format!(
-        "waypoint_test_hooks_{}",
-        COUNTER.fetch_add(1, Ordering::SeqCst)
-    ));
-    std::fs::create_dir_all(&dir).unwrap();
+async fn test_check_guards_and_failure_messages() {
+    let (client, schema) = setup_schema("guard_check").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_users.sql",
+        &format!("CREATE TABLE {}.users (id INT PRIMARY KEY);", schema),
+    )]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::new(config).await.unwrap();
+    wp.migrate(None).await.unwrap();
 
-    // Create a beforeMigrate hook that creates a log table
     std::fs::write(
-        dir.join("beforeMigrate.sql"),
+        migrations.path().join("V2__Add_email.sql"),
         format!(
-            "CREATE TABLE IF NOT EXISTS {}.hook_log (created_at TIMESTAMP DEFAULT now());",
+            "-- waypoint:require table_exists(\"users\") :: \"users table must exist\"\n\
+             -- waypoint:ensure column_exists(\"users\", \"email\")\n\
+             ALTER TABLE {}.users ADD COLUMN email TEXT;",
             schema
         ),
     )
     .unwrap();
-
-    // Create a regular migration
     std::fs::write(
-        dir.join("V1__Main.sql"),
+        migrations.path().join("V3__Orders_fk.sql"),
         format!(
-            "CREATE TABLE {}.hooks_main (id SERIAL PRIMARY KEY);",
-            schema
+            "-- waypoint:require table_exists(\"orders\") :: \"orders table must exist before adding FK\"\n\
+             ALTER TABLE {}.orders ADD FOREIGN KEY (user_id) REFERENCES {}.users (id);",
+            schema, schema
         ),
     )
     .unwrap();
 
-    let config = test_config(&schema, dir.to_str().unwrap());
-    let wp = Waypoint::with_client(config, client);
-
-    let report = wp.migrate(None).await.expect("migrate with hooks failed");
-    assert_eq!(report.migrations_applied, 1);
-    assert!(report.hooks_executed > 0, "hooks should have been executed");
-
-    // Verify the hook ran by checking the log table exists
-    let conn = db::connect(&get_test_url()).await.unwrap();
-    let exists = conn
-        .query_one(
-            "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = 'hook_log')",
-            &[&schema],
-        )
-        .await
-        .unwrap();
-    assert!(
-        exists.get::<_, bool>(0),
-        "hook_log table should exist (created by beforeMigrate hook)"
+    // Nothing is applied by the check.
+    let report = wp.check_guards().await.unwrap();
+    assert_eq!(report.migrations_checked, 2);
+    let outcomes: Vec<(&str, &str, Option<bool>)> = report
+        .results
+        .iter()
+        .map(|r| (r.script.as_str(), r.kind.as_str(), r.passed))
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            ("V2__Add_email.sql", "require", Some(true)),
+            ("V2__Add_email.sql", "ensure", Some(false)),
+            ("V3__Orders_fk.sql", "require", Some(false)),
+        ]
+    );
+    assert_eq!(report.failed, 1);
+    assert_eq!(
+        report.results[2].message.as_deref(),
+        Some("orders table must exist before adding FK")
+    );
+    let infos = wp.info().await.unwrap();
+    assert_eq!(
+        infos
+            .iter()
+            .filter(|i| i.state == MigrationState::Applied)
+            .count(),
+        1
     );
 
-    teardown_schema(&conn, &schema).await;
-    let _ = std::fs::remove_dir_all(&dir);
+    let err = wp.migrate(None).await.unwrap_err();
+    match &err {
+        WaypointError::GuardFailed {
+            kind,
+            script,
+            message,
+            ..
+        } => {
+            assert_eq!(kind, "require");
+            assert_eq!(script, "V3__Orders_fk.sql");
+            assert_eq!(
+                message.as_deref(),
+                Some("orders table must exist before adding FK")
+            );
+        }
+        other => panic!("expected GuardFailed, got {other}"),
+    }
+    assert!(err
+        .to_string()
+        .ends_with(" — orders table must exist before adding FK"));
+
+    teardown_schema(&client, &schema).await;
 }
 
 #[tokio::test]
-async fn test_dependency_ordering() {
-    // Test the DependencyGraph topological sort API directly with
-    // migration files that have depends directives.
+async fn test_migrate_with_observer_emits_events() {
+    use std::sync::{Arc, Mutex};
+    use waypoint_core::MigrateEventKind;
+
+    let (client, schema) = setup_schema("events").await;
     let migrations = create_temp_migrations(&[
-        ("V1__Base.sql", "-- No deps\nSELECT 1;"),
-        ("V2__Depends_on_V1.sql", "-- waypoint:depends 1\nSELECT 1;"),
         (
-            "V3__Also_depends_on_V1.sql",
-            "-- waypoint:depends 1\nSELECT 1;",
+            "V1__Create_events_t.sql",
+            &format!(
+                "-- waypoint:require NOT table_exists(\"events_t\")\nCREATE TABLE {}.events_t (id INT);",
+                schema
+            ),
+        ),
+        (
+            "V2__Fill_events_t.sql",
+            &format!(
+                "-- waypoint:no-transaction\nINSERT INTO {s}.events_t VALUES (1), (2);\nDELETE FROM {s}.events_t WHERE id = 1;",
+                s = schema
+            ),
         ),
     ]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::new(config).await.unwrap();
 
-    let resolved = scan_migrations(&[migrations.path().to_path_buf()]).expect("scan failed");
-    let refs: Vec<&_> = resolved.iter().filter(|m| m.is_versioned()).collect();
-
-    // Build dependency graph without implicit chaining
-    let graph = DependencyGraph::build(&refs, false).expect("graph build failed");
-    let order = graph.topological_sort().expect("topo sort failed");
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let report = wp
+        .migrate_with_observer(None, false, move |e| {
+            sink.lock().unwrap().push(e.kind.clone());
+        })
+        .await
+        .unwrap();
+    assert_eq!(report.migrations_applied, 2);
 
-    // V1 must come before V2 and V3
-    let pos_v1 = order.iter().position(|v| v == "1").unwrap();
-    let pos_v2 = order.iter().position(|v| v == "2").unwrap();
-    let pos_v3 = order.iter().position(|v| v == "3").unwrap();
-    assert!(
-        pos_v1 < pos_v2,
-        "V1 (pos {}) should be before V2 (pos {})",
-        pos_v1,
-        pos_v2
-    );
-    assert!(
-        pos_v1 < pos_v3,
-        "V1 (pos {}) should be before V3 (pos {})",
-        pos_v1,
-        pos_v3
+    let events = std::mem::take(&mut *events.lock().unwrap());
+    let names: Vec<String> = events
+        .iter()
+        .map(|k| {
+            serde_json::to_value(k).unwrap()["event"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .filter(|n| n != "safety_verdict")
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "migrate_started",
+            "guard_evaluated",
+            "migration_started",
+            "migration_completed",
+            "migration_started",
+            "statement_executed",
+            "statement_executed",
+            "migration_completed",
+            "migrate_finished",
+        ]
     );
+    assert!(events.iter().any(|k| matches!(
+        k,
+        MigrateEventKind::StatementExecuted {
+            statement: 2,
+            total: 2,
+            rows_affected: 1,
+            ..
+        }
+    )));
+
+    teardown_schema(&client, &schema).await;
 }
 
 #[tokio::test]
-async fn test_snapshot_and_drift() {
-    let (client, schema) = setup_schema("snap_drift").await;
+async fn test_read_only_command_reconnects_after_connection_loss() {
+    let (client, schema) = setup_schema("reconnect").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_reconnect_t.sql",
+        &format!("CREATE TABLE {}.reconnect_t (id INT);", schema),
+    )]);
+    let config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::new(config).await.unwrap();
+    wp.migrate(None).await.unwrap();
 
-    let dir = std::env::temp_dir().join(format!(
-        "waypoint_test_snap_{}",
-        COUNTER.fetch_add(1, Ordering::SeqCst)
-    ));
-    std::fs::create_dir_all(&dir).unwrap();
+    let pid: i32 = wp
+        .postgres_client()
+        .unwrap()
+        .query_one("SELECT pg_backend_pid()", &[])
+        .await
+        .unwrap()
+        .get(0);
+    client
+        .execute("SELECT pg_terminate_backend($1)", &[&pid])
+        .await
+        .unwrap();
+    for _ in 0..50 {
+        if wp.client().connection_lost().is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(wp.client().connection_lost().is_some());
 
-    // Use unqualified table names so drift detection can replay in temp schema
-    // (drift sets search_path to a temp schema, so schema-qualified names break it).
-    // Use INTEGER instead of SERIAL to avoid sequence-default drift between schemas.
+    // info runs again on a fresh connection.
+    let infos = wp.info().await.expect("info should reconnect");
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].state, MigrationState::Applied);
+
+    // Writes are not retried: the lost connection is reported with its cause.
+    let err = wp
+        .client()
+        .connection_error("migrate", wp.client().check_connection().await.unwrap_err());
+    assert!(matches!(err, WaypointError::ConnectionLost { .. }), "{err}");
+
+    // Later writes run on the replacement connection.
     std::fs::write(
-        dir.join("V1__Create_snap_tbl.sql"),
-        "CREATE TABLE snap_tbl (id INTEGER NOT NULL, name TEXT);",
+        migrations.path().join("V2__Create_reconnect_u.sql"),
+        format!("CREATE TABLE {}.reconnect_u (id INT);", schema),
     )
     .unwrap();
+    let report = wp.migrate(None).await.expect("migrate after reconnect");
+    assert_eq!(report.migrations_applied, 1);
 
-    // Set search_path to our test schema so unqualified names resolve correctly
-    client
-        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+    teardown_schema(&client, &schema).await;
+}
+
+#[tokio::test]
+async fn test_multi_converge_check_applies_nothing() {
+    use waypoint_core::multi::{MultiWaypoint, NamedDatabaseConfig};
+
+    let (client, schema_a) = setup_schema("mconv_a").await;
+    let (_, schema_b) = setup_schema("mconv_b").await;
+    let first = create_temp_migrations(&[(
+        "V1__Create_a.sql",
+        &format!("CREATE TABLE {}.mconv_a (id INT);", schema_a),
+    )]);
+    let second = create_temp_migrations(&[(
+        "V1__Create_b.sql",
+        &format!("CREATE TABLE {}.mconv_b (id INT);", schema_b),
+    )]);
+    let named = |name: &str, schema: &str, dir: &TempDir, depends_on: &[&str]| {
+        let config = test_config(schema, dir.path().to_str().unwrap());
+        NamedDatabaseConfig {
+            name: name.to_string(),
+            database: config.database,
+            migrations: config.migrations,
+            hooks: config.hooks,
+            placeholders: HashMap::new(),
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            tags: Vec::new(),
+            guards: Default::default(),
+        }
+    };
+    let databases = vec![
+        named("b", &schema_b, &second, &["a"]),
+        named("a", &schema_a, &first, &[]),
+    ];
+    let order = MultiWaypoint::execution_order(&databases).unwrap();
+    let clients = MultiWaypoint::connect(&WaypointConfig::default(), &databases, None)
         .await
         .unwrap();
 
-    let config = test_config(&schema, dir.to_str().unwrap());
-    let wp = Waypoint::with_client(config.clone(), client);
-    wp.migrate(None).await.expect("migrate V1 failed");
+    let report = MultiWaypoint::converge_check(
+        &WaypointConfig::default(),
+        &databases,
+        &clients,
+        &order,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(report.changed && report.check_mode);
+    assert_eq!(
+        report.migrations,
+        ["a/V1__Create_a.sql", "b/V1__Create_b.sql"]
+    );
 
-    // Take a snapshot
-    let snap_dir = std::env::temp_dir().join(format!(
-        "waypoint_test_snapshots_{}",
-        COUNTER.fetch_add(1, Ordering::SeqCst)
-    ));
-    let snap_config = SnapshotConfig {
-        directory: snap_dir.clone(),
-        auto_snapshot_on_migrate: false,
-        max_snapshots: 10,
-        strip_definer_mysql: true,
-    };
+    for schema in [&schema_a, &schema_b] {
+        let tables: i64 = client
+            .query_one(
+                "SELECT count(*) FROM information_schema.tables WHERE table_schema = $1",
+                &[schema],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(tables, 0, "nothing may be created in {}", schema);
+        teardown_schema(&client, schema).await;
+    }
+}
 
-    let client2 = db::connect(&get_test_url()).await.unwrap();
-    client2
-        .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
+#[tokio::test]
+async fn test_multi_migrate_parallel_isolates_failures() {
+    use waypoint_core::multi::{MultiWaypoint, NamedDatabaseConfig};
+
+    let (client, schema_a) = setup_schema("mpar_a").await;
+    let (_, schema_b) = setup_schema("mpar_b").await;
+    let (_, schema_c) = setup_schema("mpar_c").await;
+    let broken = create_temp_migrations(&[("V1__Broken.sql", "CREATE TABLE;")]);
+    let dependent = create_temp_migrations(&[(
+        "V1__Create_b.sql",
+        &format!("CREATE TABLE {}.mpar_b (id INT);", schema_b),
+    )]);
+    let independent = create_temp_migrations(&[(
+        "V1__Create_c.sql",
+        &format!("CREATE TABLE {}.mpar_c (id INT);", schema_c),
+    )]);
+
+    let named = |name: &str, schema: &str, dir: &TempDir, depends_on: &[&str]| {
+        let config = test_config(schema, dir.path().to_str().unwrap());
+        NamedDatabaseConfig {
+            name: name.to_string(),
+            database: config.database,
+            migrations: config.migrations,
+            hooks: config.hooks,
+            placeholders: HashMap::new(),
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            tags: Vec::new(),
+            guards: Default::default(),
+        }
+    };
+    let databases = vec![
+        named("a", &schema_a, &broken, &[]),
+        named("b", &schema_b, &dependent, &["a"]),
+        named("c", &schema_c, &independent, &[]),
+    ];
+    let order = MultiWaypoint::execution_order(&databases).unwrap();
+    let clients = MultiWaypoint::connect(&WaypointConfig::default(), &databases, None)
         .await
         .unwrap();
-    let wp2 = Waypoint::with_client(config.clone(), client2);
-    let snap_report = wp2.snapshot(&snap_config).await.expect("snapshot failed");
-    assert!(snap_report.objects_captured > 0);
 
-    // Now add V2 to alter the table (unqualified)
-    std::fs::write(
-        dir.join("V2__Add_email.sql"),
-        "ALTER TABLE snap_tbl ADD COLUMN email TEXT;",
+    let result = MultiWaypoint::migrate_parallel(
+        &WaypointConfig::default(),
+        &databases,
+        clients,
+        &order,
+        None,
+        false,
+        false,
+        3,
     )
+    .await
+    .unwrap();
+    assert!(!result.all_succeeded);
+    let by_name: HashMap<&str, _> = result
+        .results
+        .iter()
+        .map(|r| (r.name.as_str(), r))
+        .collect();
+    assert!(!by_name["a"].success);
+    assert!(!by_name["b"].success);
+    assert!(by_name["b"].message.contains("depends on 'a'"));
+    assert_eq!(by_name["b"].duration_ms, 0);
+    assert!(by_name["c"].success);
+    assert_eq!(by_name["c"].applied, vec!["V1__Create_c.sql"]);
+    let names: Vec<&str> = result.results.iter().map(|r| r.name.as_str()).collect();
+    let expected: Vec<&str> = order.iter().map(String::as_str).collect();
+    assert_eq!(names, expected);
+
+    for schema in [&schema_a, &schema_b, &schema_c] {
+        teardown_schema(&client, schema).await;
+    }
+}
+
+#[tokio::test]
+async fn test_introspection_timeout_reports_partial_snapshot() {
+    use waypoint_core::{introspection, schema};
+
+    let (client, schema_name) = setup_schema("introspect_to").await;
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE {s}.it_t (id INT); CREATE VIEW {s}.it_v AS SELECT id FROM {s}.it_t;",
+            s = schema_name
+        ))
+        .await
+        .unwrap();
+    let full = schema::introspect(&client, &schema_name).await.unwrap();
+    assert!(full.incomplete.is_empty());
+
+    // Reading a view's definition waits for this lock, so the views query
+    // runs into the timeout.
+    let mut locker = db::connect(&get_test_url()).await.unwrap();
+    let tx = locker.transaction().await.unwrap();
+    tx.batch_execute(&format!(
+        "LOCK TABLE {}.it_v IN ACCESS EXCLUSIVE MODE",
+        schema_name
+    ))
+    .await
     .unwrap();
 
-    let client3 = db::connect(&get_test_url()).await.unwrap();
-    client3
+    let partial = introspection::scope(1, schema::introspect(&client, &schema_name))
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+
+    assert_eq!(partial.incomplete, vec!["views"]);
+    assert!(partial.views.is_empty());
+    assert!(partial.tables.iter().any(|t| t.name == "it_t"));
+    // The view isn't reported as dropped, and the session timeout is back.
+    assert!(schema::diff(&full, &partial).is_empty());
+    let timeout: String = client
+        .query_one("SHOW statement_timeout", &[])
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(timeout, "0");
+
+    teardown_schema(&client, &schema_name).await;
+}
+
+#[tokio::test]
+async fn test_extension_members_excluded_from_drift_and_clean() {
+    let (client, schema) = setup_schema("ext_members").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_items.sql",
+        "CREATE TABLE items (id INT PRIMARY KEY);",
+    )]);
+    client
         .batch_execute(&format!("SET search_path TO {}", quote_ident(&schema)))
         .await
         .unwrap();
-    let wp3 = Waypoint::with_client(config.clone(), client3);
-    wp3.migrate(None).await.expect("migrate V2 failed");
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    let wp = Waypoint::with_client(config.clone(), client);
+    wp.migrate(None).await.expect("migrate failed");
 
-    // Run drift detection — should detect no drift since migrations match DB
-    let client4 = db::connect(&get_test_url()).await.unwrap();
-    let wp4 = Waypoint::with_client(config.clone(), client4);
-    let drift_report = wp4.drift().await.expect("drift detection failed");
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    conn.batch_execute(&format!("CREATE EXTENSION seg SCHEMA {}", schema))
+        .await
+        .unwrap();
+
+    let report = wp.drift().await.expect("drift failed");
+    assert!(!report.has_drift, "{:?}", report.drifts);
+
+    config.database.include_extension_objects = true;
+    let included =
+        Waypoint::with_client(config.clone(), db::connect(&get_test_url()).await.unwrap());
+    let report = included.drift().await.expect("drift failed");
+    assert!(report.has_drift);
+
+    let dropped = wp.clean(true).await.expect("clean failed");
+    assert!(dropped.contains(&format!("Table: {}.items", schema)));
     assert!(
-        !drift_report.has_drift,
-        "No drift should be detected when DB matches migrations"
+        dropped.iter().all(|d| d.starts_with("Table: ")),
+        "{:?}",
+        dropped
     );
-
-    // Now introduce manual drift by adding a column outside migrations
-    let client5 = db::connect(&get_test_url()).await.unwrap();
-    client5
-        .batch_execute(&format!(
-            "ALTER TABLE {}.snap_tbl ADD COLUMN extra_col TEXT;",
-            schema
-        ))
+    let installed: bool = conn
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'seg')",
+            &[],
+        )
         .await
-        .expect("manual ALTER failed");
+        .unwrap()
+        .get(0);
+    assert!(installed);
 
-    // Drift detection should now find something
-    let wp5 = Waypoint::with_client(config, client5);
-    let drift_report2 = wp5.drift().await.expect("drift detection failed");
-    assert!(
-        drift_report2.has_drift,
-        "Drift should be detected after manual schema change"
+    let dropped = included.clean(true).await.expect("clean failed");
+    assert_eq!(dropped, vec!["Extension: seg".to_string()]);
+
+    teardown_schema(&conn, &schema).await;
+}
+
+#[tokio::test]
+async fn test_checks_report_failures_and_run_after_migrate() {
+    let (client, schema) = setup_schema("checks").await;
+    let migrations = create_temp_migrations(&[(
+        "V1__Create_orders.sql",
+        &format!(
+            "CREATE TABLE {s}.orders (id INT PRIMARY KEY, total INT);\n\
+             INSERT INTO {s}.orders VALUES (1, 10), (2, -5);",
+            s = schema
+        ),
+    )]);
+    let checks = tempfile::tempdir().unwrap();
+    std::fs::write(
+        checks.path().join("orders.sql"),
+        "-- waypoint:check orders_exist expect=true\n\
+         SELECT count(*) > 0 FROM ${waypoint:schema}.orders;\n\
+         -- waypoint:check no_negative_totals\n\
+         SELECT id, total FROM ${waypoint:schema}.orders WHERE total < 0;\n",
+    )
+    .unwrap();
+    std::fs::write(
+        checks.path().join("broken.sql"),
+        "SELECT * FROM ${waypoint:schema}.missing_table;",
+    )
+    .unwrap();
+
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.checks.locations = vec![checks.path().to_path_buf()];
+    config.checks.after_migrate = true;
+    let wp = Waypoint::with_client(config, client);
+    let report = wp.migrate(None).await.expect("migrate failed");
+    assert_eq!(report.migrations_applied, 1);
+    let checks_report = report.checks.expect("checks ran after migrate");
+    assert_eq!(
+        checks_report.failed_names(),
+        vec!["broken", "no_negative_totals"]
+    );
+    assert_eq!(
+        report
+            .warnings
+            .iter()
+            .filter(|w| w.code == waypoint_core::warning::WarningCode::CheckFailed)
+            .count(),
+        2
     );
 
-    // Cleanup
+    let report = wp
+        .check(&["no_negative_totals".to_string(), "orders_exist".to_string()])
+        .await
+        .expect("check failed to run");
+    assert_eq!(report.passed, 1);
+    assert_eq!(report.failed, 1);
+    let failing = &report.checks[1];
+    assert_eq!(failing.rows, 1);
+    assert_eq!(failing.sample, vec!["id=2, total=-5".to_string()]);
+    assert!(wp.check(&["nope".to_string()]).await.is_err());
+
+    // Checks that cannot run at all fail the report instead of being skipped.
+    std::fs::write(
+        checks.path().join("typo.sql"),
+        "-- waypoint:check typo expect=yes\nSELECT 1;",
+    )
+    .unwrap();
+    let report = wp.migrate(None).await.expect("migrate failed");
+    let checks_report = report.checks.expect("checks reported");
+    assert_eq!(checks_report.failed, 1);
+    assert!(checks_report.checks[0]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("expect"));
+
     let conn = db::connect(&get_test_url()).await.unwrap();
     teardown_schema(&conn, &schema).await;
-    let _ = std::fs::remove_dir_all(&dir);
-    let _ = std::fs::remove_dir_all(&snap_dir);
 }
 
 #[tokio::test]
-async fn test_safety_analysis_drop_table() {
-    let (client, schema) = setup_schema("safety").await;
-
-    // Create a table first so DROP TABLE can be analyzed
-    client
-        .batch_execute(&format!(
-            "CREATE TABLE {}.safety_tbl (id SERIAL PRIMARY KEY);",
-            schema
-        ))
-        .await
-        .expect("create table failed");
-
+async fn test_assist_enum_migrations_apply_and_keep_indexes() {
+    let (client, schema) = setup_schema("assist_enum").await;
     let migrations = create_temp_migrations(&[(
-        "V1__Drop_table.sql",
-        &format!("DROP TABLE {}.safety_tbl;", schema),
+        "V1__Create_tasks.sql",
+        &format!(
+            "CREATE TYPE {s}.status AS ENUM ('todo', 'done', 'archived');\n\
+             CREATE TABLE {s}.tasks (id INT PRIMARY KEY, \
+             status {s}.status NOT NULL DEFAULT 'todo');\n\
+             CREATE INDEX tasks_status_idx ON {s}.tasks (status);\n\
+             INSERT INTO {s}.tasks VALUES (1, 'todo'), (2, 'done'), (3, 'archived');",
+            s = schema
+        ),
     )]);
-
     let config = test_config(&schema, migrations.path().to_str().unwrap());
     let wp = Waypoint::with_client(config, client);
+    wp.migrate(None).await.expect("migrate failed");
 
-    let safety_report = wp.safety().await.expect("safety analysis failed");
-
-    // DROP TABLE should produce at minimum a Caution verdict (AccessExclusiveLock on small table)
-    assert!(
-        safety_report.overall_verdict >= SafetyVerdict::Caution,
-        "DROP TABLE should be at least Caution, got {:?}",
-        safety_report.overall_verdict
-    );
+    let report = wp
+        .assist_enum(&waypoint_core::EnumChangeOptions {
+            type_name: "status".to_string(),
+            remove: vec!["archived".to_string()],
+            rename: vec![("todo".to_string(), "open".to_string())],
+            replace_with: Some("done".to_string()),
+            batch_size: 1,
+        })
+        .await
+        .expect("assist enum failed");
+    assert_eq!(report.migrations.len(), 3);
+    for (i, m) in report.migrations.iter().enumerate() {
+        std::fs::write(
+            migrations
+                .path()
+                .join(format!("V{}__{}.sql", i + 2, m.description)),
+            &m.sql,
+        )
+        .unwrap();
+    }
+    let applied = wp.migrate(None).await.expect("enum migrations failed");
+    assert_eq!(applied.migrations_applied, 3);
 
-    // Verify data_loss is detected in one of the statements
-    let has_data_loss = safety_report
-        .reports
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let rows = conn
+        .query(
+            &format!("SELECT status::text FROM {}.tasks ORDER BY id", schema),
+            &[],
+        )
+        .await
+        .unwrap();
+    let statuses: Vec<String> = rows.iter().map(|r| r.get(0)).collect();
+    assert_eq!(statuses, vec!["open", "done", "done"]);
+    let labels: Vec<String> = conn
+        .query(
+            &format!("SELECT unnest(enum_range(NULL::{}.status))::text", schema),
+            &[],
+        )
+        .await
+        .unwrap()
         .iter()
-        .flat_map(|r| &r.statements)
-        .any(|s| s.data_loss);
-    assert!(has_data_loss, "DROP TABLE should be flagged as data loss");
+        .map(|r| r.get(0))
+        .collect();
+    assert_eq!(labels, vec!["open", "done"]);
+    // The column was converted in place: index, NOT NULL and default survive.
+    let index: i64 = conn
+        .query_one(
+            "SELECT count(*) FROM pg_indexes WHERE schemaname = $1 AND indexname = 'tasks_status_idx'",
+            &[&schema],
+        )
+        .await
+        .unwrap()
+        .get(0);
+    assert_eq!(index, 1);
+    conn.execute(
+        &format!("INSERT INTO {}.tasks (id) VALUES (4)", schema),
+        &[],
+    )
+    .await
+    .unwrap();
+    assert!(conn
+        .execute(
+            &format!("INSERT INTO {}.tasks VALUES (5, NULL)", schema),
+            &[]
+        )
+        .await
+        .is_err());
 
-    let conn = db::connect(&get_test_url()).await.unwrap();
     teardown_schema(&conn, &schema).await;
 }
 
 #[tokio::test]
-async fn test_advisor_detects_table_without_pk() {
-    let (client, schema) = setup_schema("advisor").await;
-
-    // Create a table WITHOUT a primary key
-    client
-        .batch_execute(&format!(
-            "CREATE TABLE {}.no_pk_tbl (name TEXT, value INTEGER);",
-            schema
-        ))
-        .await
-        .expect("create table failed");
-
-    let migrations = create_temp_migrations(&[]);
+async fn test_info_history_timeline_replays_undo_and_reapply() {
+    let (client, schema) = setup_schema("timeline").await;
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_tl.sql",
+            &format!("CREATE TABLE {}.tl (id INT);", schema),
+        ),
+        (
+            "V2__Add_col.sql",
+            &format!("ALTER TABLE {}.tl ADD COLUMN name TEXT;", schema),
+        ),
+        (
+            "U2__Add_col.sql",
+            &format!("ALTER TABLE {}.tl DROP COLUMN name;", schema),
+        ),
+    ]);
     let config = test_config(&schema, migrations.path().to_str().unwrap());
     let wp = Waypoint::with_client(config, client);
+    wp.migrate(None).await.expect("migrate failed");
+    wp.undo(UndoTarget::Last).await.expect("undo failed");
+    assert_eq!(wp.info_history().await.unwrap().undo_stack, vec!["1"]);
+    wp.migrate(None).await.expect("re-migrate failed");
 
-    let advisor_report = wp.advise().await.expect("advise failed");
-
-    // Should detect A004: table without primary key
-    let a004 = advisor_report
-        .advisories
+    let timeline = wp.info_history().await.expect("info --history failed");
+    let versions: Vec<Option<&str>> = timeline
+        .versions
         .iter()
-        .find(|a| a.rule_id == "A004");
-    assert!(
-        a004.is_some(),
-        "A004 (table without PK) should be detected. Found rules: {:?}",
-        advisor_report
-            .advisories
-            .iter()
-            .map(|a| &a.rule_id)
-            .collect::<Vec<_>>()
-    );
-    let advisory = a004.unwrap();
-    assert_eq!(advisory.object, "no_pk_tbl");
+        .map(|v| v.version.as_deref())
+        .collect();
+    assert_eq!(versions, vec![Some("1"), Some("2")]);
+    let kinds: Vec<waypoint_core::TimelineEventKind> =
+        timeline.versions[1].events.iter().map(|e| e.kind).collect();
+    use waypoint_core::TimelineEventKind::*;
+    assert_eq!(kinds, vec![Applied, Undone, Applied]);
+    assert_eq!(timeline.versions[1].events[1].script, "U2__Add_col.sql");
+    assert_eq!(timeline.versions[1].current, Applied);
+    assert_eq!(timeline.undo_stack, vec!["2", "1"]);
 
     let conn = db::connect(&get_test_url()).await.unwrap();
     teardown_schema(&conn, &schema).await;
 }
 
-#[tokio::test]
-async fn test_advisory_lock_prevents_concurrent_access() {
-    let (client, schema) = setup_schema("lock").await;
+struct SeedThings {
+    schema: String,
+    hash: &'static str,
+}
 
-    let table = "waypoint_schema_history";
+#[async_trait::async_trait]
+impl waypoint_core::code_migration::CodeMigration for SeedThings {
+    fn version(&self) -> &str {
+        "2"
+    }
+    fn description(&self) -> &str {
+        "Seed things"
+    }
+    fn stable_hash(&self) -> &str {
+        self.hash
+    }
+    async fn up(&self, client: &tokio_postgres::Client) -> waypoint_core::error::Result<()> {
+        client
+            .execute(
+                &format!("INSERT INTO {}.things (name) VALUES ($1)", self.schema),
+                &[&"seeded"],
+            )
+            .await?;
+        Ok(())
+    }
+}
 
-    // Acquire the advisory lock on the first connection
-    db::acquire_advisory_lock(&client, table)
-        .await
-        .expect("first lock acquire failed");
+#[tokio::test]
+async fn test_code_migration_applied_in_version_order() {
+    let (client, schema) = setup_schema("code_mig").await;
 
-    // Try to acquire the same lock on a second connection with a short timeout
-    let client2 = db::connect(&get_test_url()).await.unwrap();
-    let result = db::acquire_advisory_lock_with_timeout(&client2, table, 2).await;
-    assert!(
-        result.is_err(),
-        "Second lock acquire should fail (timeout) while first holds it"
-    );
+    let migrations = create_temp_migrations(&[
+        (
+            "V1__Create_things.sql",
+            &format!(
+                "CREATE TABLE {}.things (id SERIAL PRIMARY KEY, name TEXT);",
+                schema
+            ),
+        ),
+        (
+            "V3__Add_value.sql",
+            &format!("ALTER TABLE {}.things ADD COLUMN value INTEGER;", schema),
+        ),
+    ]);
 
-    // Release the first lock
-    db::release_advisory_lock(&client, table)
-        .await
-        .expect("release failed");
+    let mut config = test_config(&schema, migrations.path().to_str().unwrap());
+    config.migrations.code_migrations.register(SeedThings {
+        schema: schema.clone(),
+        hash: "rev-1",
+    });
+    let wp = Waypoint::with_client(config.clone(), client);
 
-    // Now the second client should be able to acquire it
-    let result2 = db::acquire_advisory_lock_with_timeout(&client2, table, 5).await;
-    assert!(
-        result2.is_ok(),
-        "Second lock acquire should succeed after release"
+    let report = wp.migrate(None).await.expect("migrate failed");
+    let scripts: Vec<_> = report.details.iter().map(|d| d.script.as_str()).collect();
+    assert_eq!(
+        scripts,
+        [
+            "V1__Create_things.sql",
+            "V2__Seed_things.rs",
+            "V3__Add_value.sql"
+        ]
     );
+    assert!(wp.validate().await.unwrap().valid);
 
-    // Clean up
-    db::release_advisory_lock(&client2, table)
+    let conn = db::connect(&get_test_url()).await.unwrap();
+    let seeded: i64 = conn
+        .query_one(&format!("SELECT count(*) FROM {}.things", schema), &[])
         .await
-        .expect("cleanup release failed");
+        .unwrap()
+        .get(0);
+    assert_eq!(seeded, 1);
+
+    // A new stable hash means the applied migration changed.
+    config.migrations.code_migrations = Default::default();
+    config.migrations.code_migrations.register(SeedThings {
+        schema: schema.clone(),
+        hash: "rev-2",
+    });
+    let wp = Waypoint::with_client(config, db::connect(&get_test_url()).await.unwrap());
+    assert!(wp.validate().await.is_err());
 
-    teardown_schema(&client, &schema).await;
+    teardown_schema(&conn, &schema).await;
 }
 
 #[tokio::test]
-async fn test_dotted_version_numbers() {
-    let (client, schema) = setup_schema("dotted").await;
+async fn test_multi_apply_single_version() {
+    use waypoint_core::multi::{ApplySingleOptions, MultiWaypoint, NamedDatabaseConfig};
 
+    let (client, schema) = setup_schema("apply_single").await;
     let migrations = create_temp_migrations(&[
         (
-            "V1.0__First.sql",
-            &format!("CREATE TABLE {}.dot_t1 (id SERIAL PRIMARY KEY);", schema),
+            "V1__Create_a.sql",
+            &format!("CREATE TABLE {}.a (id INT);", schema),
         ),
         (
-            "V1.1__Second.sql",
-            &format!("CREATE TABLE {}.dot_t2 (id SERIAL PRIMARY KEY);", schema),
+            "V2__Create_b.sql",
+            &format!("CREATE TABLE {}.b (id INT);", schema),
         ),
         (
-            "V2.0__Third.sql",
-            &format!("CREATE TABLE {}.dot_t3 (id SERIAL PRIMARY KEY);", schema),
+            "V3__Create_c.sql",
+            &format!(
+                "-- waypoint:require remote_sql(\"Prod\", \"SELECT true\")\n\
+                 CREATE TABLE {}.c (id INT);",
+                schema
+            ),
+        ),
+        (
+            "R__View.sql",
+            &format!("CREATE OR REPLACE VIEW {}.v AS SELECT 1 AS x;", schema),
         ),
     ]);
-
     let config = test_config(&schema, migrations.path().to_str().unwrap());
-    let wp = Waypoint::with_client(config, client);
-
-    let report = wp.migrate(None).await.expect("migrate failed");
-    assert_eq!(report.migrations_applied, 3);
-
-    // Verify ordering: 1.0, 1.1, 2.0
-    assert_eq!(report.details[0].version.as_deref(), Some("1.0"));
-    assert_eq!(report.details[1].version.as_deref(), Some("1.1"));
-    assert_eq!(report.details[2].version.as_deref(), Some("2.0"));
-
-    // Verify all three tables exist
-    let conn = db::connect(&get_test_url()).await.unwrap();
-    for tbl in &["dot_t1", "dot_t2", "dot_t3"] {
-        let exists = conn
-            .query_one(
-                "SELECT EXISTS (SELECT FROM information_schema.tables WHERE table_schema = $1 AND table_name = $2)",
-                &[&schema, tbl],
-            )
-            .await
-            .unwrap();
-        assert!(exists.get::<_, bool>(0), "Table {} should exist", tbl);
-    }
-
-    teardown_schema(&conn, &schema).await;
-}
+    // Remote guards reach their targets in multi-database runs too.
+    let mut guards = waypoint_core::guard::GuardsConfig::default();
+    guards.targets.insert("prod".to_string(), get_test_url());
+    let databases = vec![NamedDatabaseConfig {
+        name: "main".to_string(),
+        database: config.database.clone(),
+        migrations: config.migrations.clone(),
+        hooks: config.hooks.clone(),
+        placeholders: HashMap::new(),
+        depends_on: Vec::new(),
+        tags: vec!["eu".to_string()],
+        guards,
+    }];
+    let order = MultiWaypoint::execution_order(&databases).unwrap();
+    let clients = MultiWaypoint::connect(&WaypointConfig::default(), &databases, None)
+        .await
+        .unwrap();
 
-#[tokio::test]
-async fn test_validate_on_migrate_detects_modification() {
-    let (client, schema) = setup_schema("val_mig").await;
+    let options = |version: &str| ApplySingleOptions {
+        version: version.to_string(),
+        fail_fast: true,
+        ..Default::default()
+    };
 
-    let dir = std::env::temp_dir().join(format!(
-        "waypoint_test_val_mig_{}",
-        COUNTER.fetch_add(1, Ordering::SeqCst)
-    ));
-    std::fs::create_dir_all(&dir).unwrap();
+    let untagged = ApplySingleOptions {
+        tags: vec!["us".to_string()],
+        ..options("3")
+    };
+    assert!(MultiWaypoint::apply_single(
+        &WaypointConfig::default(),
+        &databases,
+        &clients,
+        &order,
+        &untagged
+    )
+    .await
+    .is_err());
 
-    std::fs::write(
-        dir.join("V1__Create_val_mig.sql"),
-        format!("CREATE TABLE {}.val_mig_tbl (id SERIAL);", schema),
+    let plan = ApplySingleOptions {
+        dry_run: true,
+        tags: vec!["eu".to_string()],
+        ..options("3")
+    };
+    let planned = MultiWaypoint::apply_single(
+        &WaypointConfig::default(),
+        &databases,
+        &clients,
+        &order,
+        &plan,
+    )
+    .await
+    .unwrap();
+    assert!(planned.all_succeeded, "{:?}", planned.results);
+    assert_eq!(planned.results[0].message, "Would apply V3__Create_c.sql");
+    assert!(planned.results[0].applied.is_empty());
+
+    let result = MultiWaypoint::apply_single(
+        &WaypointConfig::default(),
+        &databases,
+        &clients,
+        &order,
+        &options("3"),
     )
+    .await
     .unwrap();
+    assert!(result.all_succeeded, "{:?}", result.results);
+    assert_eq!(result.results[0].applied, ["V3__Create_c.sql"]);
 
-    // First run: migrate with validate_on_migrate disabled
-    let config = test_config(&schema, dir.to_str().unwrap());
-    let wp = Waypoint::with_client(config, client);
-    wp.migrate(None).await.expect("first migrate failed");
+    // Only V3 ran; V1, V2 and the repeatable are still pending.
+    let wp = Waypoint::with_client(config, db::connect(&get_test_url()).await.unwrap());
+    let pending: Vec<_> = wp
+        .info()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|i| i.state.is_pending())
+        .map(|i| i.script)
+        .collect();
+    assert_eq!(
+        pending,
+        ["V1__Create_a.sql", "V2__Create_b.sql", "R__View.sql"]
+    );
 
-    // Modify the migration file after first run
-    std::fs::write(
-        dir.join("V1__Create_val_mig.sql"),
-        format!(
-            "CREATE TABLE {}.val_mig_tbl (id SERIAL, extra TEXT);",
-            schema
-        ),
+    let again = MultiWaypoint::apply_single(
+        &WaypointConfig::default(),
+        &databases,
+        &clients,
+        &order,
+        &options("3"),
     )
+    .await
     .unwrap();
-
-    // Add a V2 so there's a pending migration to trigger the migrate path
-    std::fs::write(
-        dir.join("V2__Another.sql"),
-        format!("CREATE TABLE {}.val_mig_tbl2 (id SERIAL);", schema),
+    assert!(again.all_succeeded);
+    assert!(again.results[0].applied.is_empty());
+
+    let missing = MultiWaypoint::apply_single(
+        &WaypointConfig::default(),
+        &databases,
+        &clients,
+        &order,
+        &options("9"),
     )
+    .await
     .unwrap();
+    assert!(!missing.all_succeeded);
 
-    // Second run with validate_on_migrate=true — should fail
-    let mut config2 = test_config(&schema, dir.to_str().unwrap());
-    config2.migrations.validate_on_migrate = true;
-
-    let client2 = db::connect(&get_test_url()).await.unwrap();
-    let wp2 = Waypoint::with_client(config2, client2);
-    let result = wp2.migrate(None).await;
-    assert!(
-        result.is_err(),
-        "migrate should fail when validate_on_migrate detects checksum change"
-    );
-
-    let conn = db::connect(&get_test_url()).await.unwrap();
-    teardown_schema(&conn, &schema).await;
-    let _ = std::fs::remove_dir_all(&dir);
+    teardown_schema(&client, &schema).await;
 }
@@ -0,0 +1,121 @@
+//! Procedural macros re-exported by `waypoint-core`.
+//!
+//! Use them through `waypoint_core::embedded`; the generated code refers to
+//! `::waypoint_core` and does not compile without it.
+
+use std::path::Path;
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// Embed migration files from a directory of the calling crate at compile
+/// time, as a `waypoint_core::embedded::EmbeddedMigrations`.
+///
+/// `embed_migrations!("db/migrations")` embeds every `.sql` file directly in
+/// the directory; `embed_migrations!("db/migrations", ["V1__a.sql", ...])`
+/// embeds just the named files. See `waypoint_core::embedded` for details.
+#[proc_macro]
+pub fn embed_migrations(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(tokens) => tokens,
+        Err(message) => format!("compile_error!({:?})", message)
+            .parse()
+            .expect("compile_error! invocation"),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let (dir, listed) = match tokens.as_slice() {
+        [TokenTree::Literal(dir)] => (string_literal(&dir.to_string())?, None),
+        [TokenTree::Literal(dir), TokenTree::Punct(comma), TokenTree::Group(files)]
+            if comma.as_char() == ',' && files.delimiter() == Delimiter::Bracket =>
+        {
+            (
+                string_literal(&dir.to_string())?,
+                Some(listed_files(files.stream())?),
+            )
+        }
+        _ => {
+            return Err(
+                "expected embed_migrations!(\"dir\") or embed_migrations!(\"dir\", [\"file.sql\", ...])"
+                    .to_string(),
+            )
+        }
+    };
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| "CARGO_MANIFEST_DIR is not set".to_string())?;
+    let dir = Path::new(&manifest_dir).join(dir);
+    let files = match listed {
+        Some(files) => files,
+        None => sql_files(&dir)?,
+    };
+
+    let entries: Vec<String> = files
+        .iter()
+        .map(|name| {
+            let path = dir.join(name);
+            format!(
+                "({:?}, include_str!({:?}))",
+                name,
+                path.to_string_lossy().as_ref()
+            )
+        })
+        .collect();
+    format!(
+        "::waypoint_core::embedded::EmbeddedMigrations::from_files([{}])",
+        entries.join(", ")
+    )
+    .parse()
+    .map_err(|e| format!("embed_migrations!: {}", e))
+}
+
+/// The value of a plain `"..."` string literal.
+fn string_literal(repr: &str) -> Result<String, String> {
+    match repr.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(value) if !value.contains('\\') => Ok(value.to_string()),
+        _ => Err(format!(
+            "embed_migrations!: expected a plain string literal, got {}",
+            repr
+        )),
+    }
+}
+
+fn listed_files(stream: TokenStream) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    for token in stream {
+        match token {
+            TokenTree::Literal(lit) => files.push(string_literal(&lit.to_string())?),
+            TokenTree::Punct(p) if p.as_char() == ',' => {}
+            other => {
+                return Err(format!(
+                    "embed_migrations!: expected a file name, got {}",
+                    other
+                ))
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Names of the `.sql` files directly in `dir`, sorted. Subdirectories are
+/// not descended into, as with a scanned location.
+fn sql_files(dir: &Path) -> Result<Vec<String>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("embed_migrations!: cannot read '{}': {}", dir.display(), e))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("embed_migrations!: cannot read '{}': {}", dir.display(), e))?
+            .path();
+        if !path.is_file() {
+            continue;
+        }
+        match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) if name.ends_with(".sql") => files.push(name.to_string()),
+            _ => {}
+        }
+    }
+    files.sort();
+    Ok(files)
+}